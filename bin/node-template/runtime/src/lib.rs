@@ -185,6 +185,7 @@ impl indices::Trait for Runtime {
 
 parameter_types! {
 	pub const MinimumPeriod: u64 = SLOT_DURATION / 2;
+	pub const MaxDrift: u64 = 30 * 1000;
 }
 
 impl timestamp::Trait for Runtime {
@@ -192,6 +193,7 @@ impl timestamp::Trait for Runtime {
 	type Moment = u64;
 	type OnTimestampSet = Aura;
 	type MinimumPeriod = MinimumPeriod;
+	type MaxDrift = MaxDrift;
 }
 
 parameter_types! {
@@ -229,6 +231,7 @@ impl transaction_payment::Trait for Runtime {
 	type TransactionBaseFee = TransactionBaseFee;
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = ConvertInto;
+	type OperationalWeightToFee = ConvertInto;
 	type FeeMultiplierUpdate = ();
 }
 
@@ -332,6 +335,15 @@ impl_runtime_apis! {
 		fn random_seed() -> <Block as BlockT>::Hash {
 			RandomnessCollectiveFlip::random_seed()
 		}
+
+		fn block_weight() -> sp_block_builder::BlockWeight {
+			sp_block_builder::BlockWeight {
+				hooks: System::hooks_weight().into(),
+				consumed: System::all_extrinsics_weight().into(),
+				normal_limit: System::weight_limit(frame_support::weights::DispatchClass::Normal).into(),
+				maximum: System::weight_limit(frame_support::weights::DispatchClass::Operational).into(),
+			}
+		}
 	}
 
 	impl sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block> for Runtime {
@@ -88,6 +88,7 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 	let force_authoring = config.force_authoring;
 	let name = config.name.clone();
 	let disable_grandpa = config.disable_grandpa;
+	let grandpa_lightweight_observer = config.grandpa_lightweight_observer;
 
 	// sentry nodes announce themselves as authorities to the network
 	// and should run the same protocols authorities do, but it should
@@ -110,6 +111,7 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 		let proposer = sc_basic_authority::ProposerFactory {
 			client: service.client(),
 			transaction_pool: service.transaction_pool(),
+			weight_tracing: None,
 		};
 
 		let client = service.client();
@@ -134,7 +136,7 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 
 		// the AURA authoring task is considered essential, i.e. if it
 		// fails we take down the service with it.
-		service.spawn_essential_task(aura);
+		service.spawn_consensus_task(aura);
 	}
 
 	// if the node isn't actively participating in consensus then it doesn't
@@ -151,8 +153,11 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 		justification_period: 512,
 		name: Some(name),
 		observer_enabled: true,
+		lightweight_observer: grandpa_lightweight_observer,
 		keystore,
 		is_authority,
+		clock: None,
+		threshold_signer: None,
 	};
 
 	match (is_authority, disable_grandpa) {
@@ -172,7 +177,6 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 				config: grandpa_config,
 				link: grandpa_link,
 				network: service.network(),
-				inherent_data_providers: inherent_data_providers.clone(),
 				on_exit: service.on_exit(),
 				telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
 				voting_rule: grandpa::VotingRulesBuilder::default().build(),
@@ -181,14 +185,10 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 
 			// the GRANDPA voter task is considered infallible, i.e.
 			// if it fails we take down the service with it.
-			service.spawn_essential_task(grandpa::run_grandpa_voter(voter_config)?.compat().map(drop));
+			service.spawn_consensus_task(grandpa::run_grandpa_voter(voter_config)?.compat().map(drop));
 		},
 		(_, true) => {
-			grandpa::setup_disabled_grandpa(
-				service.client(),
-				&inherent_data_providers,
-				service.network(),
-			)?;
+			grandpa::setup_disabled_grandpa(service.network())?;
 		},
 	}
 
@@ -0,0 +1,437 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `benchmark` subcommand and of the hardware check run at startup.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use rand::Rng;
+use sc_cli::error;
+use serde::{Serialize, Deserialize};
+use sp_core::{Pair, hashing::blake2_256, sr25519};
+
+use crate::cli::{BenchmarkCmd, MachineCmd};
+
+/// Results below this fraction of the reference still pass, to allow for measurement noise.
+const TOLERANCE: f64 = 0.9;
+
+/// Size of the buffers hashed, copied and written by the benchmarks.
+const BUFFER_SIZE: usize = 64 * 1024 * 1024;
+
+/// Size of the blocks written by the random write benchmark.
+const BLOCK_SIZE: usize = 4 * 1024;
+
+/// Name of the scratch file of the disk benchmarks.
+const SCRATCH_FILE: &str = ".benchmark-scratch";
+
+/// Duration of each benchmark of the check run at startup.
+const STARTUP_DURATION: Duration = Duration::from_millis(200);
+
+/// Name of the file caching the results of the check run at startup.
+pub const CHECK_CACHE_FILE: &str = "hardware-check.json";
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// A property of the machine measured by the benchmarks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Metric {
+	/// BLAKE2-256 hashing throughput.
+	Blake2256,
+	/// SR25519 signature verifications.
+	Sr25519Verify,
+	/// Memory copy bandwidth.
+	MemCopy,
+	/// Sequential disk write throughput, synced to disk.
+	DiskSeqWrite,
+	/// Random 4 KiB disk writes, each synced to disk.
+	DiskRndWrite,
+}
+
+impl Metric {
+	/// The metrics measured on the CPU and the memory.
+	const MACHINE: &'static [Metric] = &[Metric::Blake2256, Metric::Sr25519Verify, Metric::MemCopy];
+
+	/// The metrics measured on the disk.
+	const DISK: &'static [Metric] = &[Metric::DiskSeqWrite, Metric::DiskRndWrite];
+
+	fn name(&self) -> &'static str {
+		match self {
+			Metric::Blake2256 => "BLAKE2-256",
+			Metric::Sr25519Verify => "SR25519 verify",
+			Metric::MemCopy => "Memory copy",
+			Metric::DiskSeqWrite => "Disk sequential write",
+			Metric::DiskRndWrite => "Disk random write",
+		}
+	}
+
+	fn unit(&self) -> &'static str {
+		match self {
+			Metric::Blake2256 | Metric::MemCopy | Metric::DiskSeqWrite => "MiB/s",
+			Metric::Sr25519Verify => "verify/s",
+			Metric::DiskRndWrite => "IOPS",
+		}
+	}
+
+	/// The result of the reference validator machine, a single core of a current server CPU
+	/// with NVMe storage.
+	fn reference(&self) -> f64 {
+		match self {
+			Metric::Blake2256 => 1_000.0,
+			Metric::Sr25519Verify => 15_000.0,
+			Metric::MemCopy => 14_000.0,
+			Metric::DiskSeqWrite => 450.0,
+			Metric::DiskRndWrite => 1_000.0,
+		}
+	}
+
+	/// Run the benchmark of the metric for about `duration`. The disk benchmarks write to a
+	/// scratch file in `dir`.
+	fn measure(&self, duration: Duration, dir: &Path) -> error::Result<f64> {
+		let result = match self {
+			Metric::Blake2256 => blake2_256_throughput(duration),
+			Metric::Sr25519Verify => sr25519_verify_rate(duration),
+			Metric::MemCopy => memory_copy_bandwidth(duration),
+			Metric::DiskSeqWrite => disk_sequential_write(duration, dir)?,
+			Metric::DiskRndWrite => disk_random_write(duration, dir)?,
+		};
+		Ok(result)
+	}
+}
+
+/// The measured result of a metric.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+	/// The metric measured.
+	pub metric: Metric,
+	/// The measured result, in the unit of the metric.
+	pub result: f64,
+}
+
+impl Score {
+	/// The result relative to the reference machine.
+	fn relative(&self) -> f64 {
+		self.result / self.metric.reference()
+	}
+
+	/// Whether the result meets the requirements.
+	fn passed(&self) -> bool {
+		self.relative() >= TOLERANCE
+	}
+
+	fn to_json(&self) -> serde_json::Value {
+		serde_json::json!({
+			"metric": self.metric.name(),
+			"unit": self.metric.unit(),
+			"result": self.result,
+			"reference": self.metric.reference(),
+			"passed": self.passed(),
+		})
+	}
+}
+
+impl BenchmarkCmd {
+	/// Run the benchmark command.
+	pub fn run(&self) -> error::Result<()> {
+		match self {
+			BenchmarkCmd::Machine(cmd) => cmd.run(),
+		}
+	}
+}
+
+impl MachineCmd {
+	/// Benchmark the machine and print the results next to the reference values.
+	pub fn run(&self) -> error::Result<()> {
+		let duration = Duration::from_millis(self.duration);
+		let dir = self.base_path.clone().unwrap_or_else(std::env::temp_dir);
+		let metrics = Metric::MACHINE.iter()
+			.chain(if self.skip_disk { &[][..] } else { Metric::DISK });
+
+		let mut scores = Vec::new();
+		for metric in metrics {
+			if !self.json {
+				eprintln!("Benchmarking {}...", metric.name());
+			}
+			scores.push(Score { metric: *metric, result: metric.measure(duration, &dir)? });
+		}
+
+		if self.json {
+			let json = scores.iter().map(Score::to_json).collect::<Vec<_>>();
+			println!(
+				"{}",
+				serde_json::to_string_pretty(&json).expect("JSON values are always serializable; qed"),
+			);
+		} else {
+			print(&scores);
+		}
+
+		let failed = scores.iter().filter(|score| !score.passed()).count();
+		if self.enforce && failed > 0 {
+			return Err(error::Error::Other(format!(
+				"{} of {} benchmarks are below the validator requirements.",
+				failed,
+				scores.len(),
+			)));
+		}
+		Ok(())
+	}
+}
+
+fn print(scores: &[Score]) {
+	println!("{:<24} {:>12} {:>12} {:<10} {:>7}", "Metric", "Result", "Reference", "Unit", "Score");
+	for score in scores {
+		println!(
+			"{:<24} {:>12.2} {:>12.2} {:<10} {:>6.0}%  {}",
+			score.metric.name(),
+			score.result,
+			score.metric.reference(),
+			score.metric.unit(),
+			score.relative() * 100.0,
+			if score.passed() { "ok" } else { "BELOW REQUIREMENTS" },
+		);
+	}
+}
+
+/// Run a short benchmark of the machine and warn about every metric below the validator
+/// requirements. The disk is benchmarked in `database_dir` if given.
+///
+/// The results are stored in `cache` if given, and later checks reuse them instead of running
+/// the benchmarks again. Deleting the file makes the next check benchmark the machine again.
+pub fn check_hardware(database_dir: Option<&Path>, cache: Option<&Path>) {
+	let metrics = Metric::MACHINE.iter()
+		.chain(if database_dir.is_some() { Metric::DISK } else { &[][..] })
+		.cloned()
+		.collect::<Vec<_>>();
+
+	if let Some(cache) = cache {
+		if let Some(scores) = cached_scores(cache, &metrics) {
+			info!(
+				"Using the hardware benchmark results cached in {}, delete it to benchmark again.",
+				cache.display(),
+			);
+			report(&scores);
+			return
+		}
+	}
+
+	let dir = database_dir.map(Path::to_path_buf).unwrap_or_else(PathBuf::new);
+	let mut scores = Vec::new();
+	for metric in metrics {
+		match metric.measure(STARTUP_DURATION, &dir) {
+			Ok(result) => scores.push(Score { metric, result }),
+			Err(e) => warn!("Unable to benchmark {}: {}", metric.name(), e),
+		}
+	}
+	report(&scores);
+
+	if let Some(cache) = cache {
+		if let Err(e) = write_scores(cache, &scores) {
+			warn!("Unable to cache the hardware benchmark results in {}: {}", cache.display(), e);
+		}
+	}
+}
+
+fn write_scores(cache: &Path, scores: &[Score]) -> std::io::Result<()> {
+	if let Some(dir) = cache.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	fs::write(cache, serde_json::to_vec(scores).expect("scores are always serializable; qed"))
+}
+
+/// The scores cached in `cache`, if it holds a score of each of `metrics`.
+fn cached_scores(cache: &Path, metrics: &[Metric]) -> Option<Vec<Score>> {
+	let scores: Vec<Score> = serde_json::from_slice(&fs::read(cache).ok()?).ok()?;
+	metrics.iter()
+		.map(|metric| scores.iter().find(|score| score.metric == *metric).cloned())
+		.collect()
+}
+
+/// Warn about every score below the validator requirements.
+fn report(scores: &[Score]) {
+	let mut failed = 0;
+	for score in scores.iter().filter(|score| !score.passed()) {
+		failed += 1;
+		warn!(
+			"{} of {:.2} {} is below the {:.2} {} of the reference validator machine.",
+			score.metric.name(),
+			score.result,
+			score.metric.unit(),
+			score.metric.reference(),
+			score.metric.unit(),
+		);
+	}
+
+	if failed > 0 {
+		warn!(
+			"The hardware does not meet the validator requirements, the node may miss slots \
+			and fall behind. Run `benchmark machine` for details."
+		);
+	} else {
+		info!("The hardware meets the validator requirements.");
+	}
+}
+
+/// Repeat `run` for at least `duration`, returning the number of runs per second.
+fn runs_per_second(duration: Duration, mut run: impl FnMut()) -> f64 {
+	let start = Instant::now();
+	let mut runs = 0u64;
+	while runs == 0 || start.elapsed() < duration {
+		run();
+		runs += 1;
+	}
+	runs as f64 / start.elapsed().as_secs_f64()
+}
+
+fn blake2_256_throughput(duration: Duration) -> f64 {
+	const CHUNK: usize = 1024 * 1024;
+	let mut data = vec![0u8; CHUNK];
+	let rate = runs_per_second(duration, || {
+		// Feed the hash back, so the hashing can't be optimized away.
+		let hash = blake2_256(&data);
+		data[..32].copy_from_slice(&hash);
+	});
+	rate * CHUNK as f64 / MIB
+}
+
+fn sr25519_verify_rate(duration: Duration) -> f64 {
+	let (pair, _) = sr25519::Pair::generate();
+	let public = pair.public();
+	let message = blake2_256(b"benchmark");
+	let signature = pair.sign(&message);
+	runs_per_second(duration, || {
+		assert!(sr25519::Pair::verify(&signature, &message, &public), "signed with the pair above; qed");
+	})
+}
+
+fn memory_copy_bandwidth(duration: Duration) -> f64 {
+	let source = vec![1u8; BUFFER_SIZE];
+	let mut dest = vec![0u8; BUFFER_SIZE];
+	let rate = runs_per_second(duration, || dest.copy_from_slice(&source));
+	assert_eq!(dest[BUFFER_SIZE - 1], 1);
+	rate * BUFFER_SIZE as f64 / MIB
+}
+
+/// A file in the benchmarked directory, removed on drop.
+struct ScratchFile {
+	path: PathBuf,
+	file: File,
+}
+
+impl ScratchFile {
+	fn create(dir: &Path) -> error::Result<Self> {
+		fs::create_dir_all(dir)?;
+		let path = dir.join(SCRATCH_FILE);
+		let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+		Ok(ScratchFile { path, file })
+	}
+}
+
+impl Drop for ScratchFile {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+fn disk_sequential_write(duration: Duration, dir: &Path) -> error::Result<f64> {
+	const CHUNK: usize = 1024 * 1024;
+	let mut scratch = ScratchFile::create(dir)?;
+	let data = vec![1u8; CHUNK];
+
+	let start = Instant::now();
+	let mut written = 0usize;
+	while written == 0 || start.elapsed() < duration {
+		if written % BUFFER_SIZE == 0 {
+			scratch.file.seek(SeekFrom::Start(0))?;
+		}
+		scratch.file.write_all(&data)?;
+		written += CHUNK;
+		if written % BUFFER_SIZE == 0 {
+			scratch.file.sync_data()?;
+		}
+	}
+	scratch.file.sync_data()?;
+	Ok(written as f64 / MIB / start.elapsed().as_secs_f64())
+}
+
+fn disk_random_write(duration: Duration, dir: &Path) -> error::Result<f64> {
+	let mut scratch = ScratchFile::create(dir)?;
+	scratch.file.set_len(BUFFER_SIZE as u64)?;
+	scratch.file.sync_all()?;
+
+	let mut rng = rand::thread_rng();
+	let mut block = vec![0u8; BLOCK_SIZE];
+	let blocks = (BUFFER_SIZE / BLOCK_SIZE) as u64;
+
+	let start = Instant::now();
+	let mut writes = 0u64;
+	while writes == 0 || start.elapsed() < duration {
+		rng.fill(&mut block[..]);
+		scratch.file.seek(SeekFrom::Start(rng.gen_range(0, blocks) * BLOCK_SIZE as u64))?;
+		scratch.file.write_all(&block)?;
+		scratch.file.sync_data()?;
+		writes += 1;
+	}
+	Ok(writes as f64 / start.elapsed().as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn scores_pass_within_tolerance() {
+		let score = |result| Score { metric: Metric::Blake2256, result };
+		assert!(score(1_000.0).passed());
+		assert!(score(900.0).passed());
+		assert!(!score(899.0).passed());
+		assert_eq!(score(500.0).relative(), 0.5);
+	}
+
+	#[test]
+	fn check_results_are_cached() {
+		let dir = tempfile::tempdir().unwrap();
+		let cache = dir.path().join("chains").join(CHECK_CACHE_FILE);
+		assert_eq!(cached_scores(&cache, Metric::MACHINE), None);
+
+		check_hardware(None, Some(&cache));
+		let scores = cached_scores(&cache, Metric::MACHINE).unwrap();
+		assert_eq!(
+			scores.iter().map(|score| score.metric).collect::<Vec<_>>(),
+			Metric::MACHINE.to_vec(),
+		);
+
+		// The cached scores are reused, and don't cover the disk.
+		check_hardware(None, Some(&cache));
+		assert_eq!(cached_scores(&cache, Metric::MACHINE), Some(scores));
+		assert_eq!(cached_scores(&cache, Metric::DISK), None);
+
+		fs::write(&cache, b"garbage").unwrap();
+		assert_eq!(cached_scores(&cache, Metric::MACHINE), None);
+	}
+
+	#[test]
+	fn benchmarks_measure_and_clean_up() {
+		let dir = tempfile::tempdir().unwrap();
+		for metric in Metric::MACHINE.iter().chain(Metric::DISK) {
+			let result = metric.measure(Duration::from_millis(1), dir.path()).unwrap();
+			assert!(result > 0.0, "{} measured {}", metric.name(), result);
+		}
+		assert!(!dir.path().join(SCRATCH_FILE).exists());
+	}
+}
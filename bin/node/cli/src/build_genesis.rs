@@ -0,0 +1,128 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `build-genesis` subcommand.
+
+use std::fs;
+
+use codec::{Decode, Encode};
+use frame_support::BasicExternalities;
+use sc_cli::error;
+use serde_json::Value;
+use sp_core::storage::Storage;
+
+use crate::cli::BuildGenesisCmd;
+
+/// Call the `GenesisBuilder` runtime api of the native runtime on the given storage.
+fn call_genesis_builder<R: Decode>(
+	storage: &mut Storage,
+	method: &str,
+	data: &[u8],
+) -> error::Result<R> {
+	let method = format!("GenesisBuilder_{}", method);
+	let result = BasicExternalities::execute_with_storage(storage, || {
+		node_runtime::api::dispatch(&method, data)
+	}).ok_or_else(|| error::Error::Other(format!("Runtime does not implement {}", method)))?;
+	R::decode(&mut &result[..])
+		.map_err(|e| error::Error::Other(format!("Invalid result of {}: {}", method, e.what())))
+}
+
+impl BuildGenesisCmd {
+	/// Build the genesis config from the runtime defaults and the patch, and either print it or
+	/// write a raw chain spec with the resulting genesis state to `--out`.
+	pub fn run<F>(&self, spec_factory: F) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Option<crate::chain_spec::ChainSpec>, String>,
+	{
+		let default: Vec<u8> = call_genesis_builder(&mut Storage::default(), "create_default_config", &[])?;
+		let mut config: Value = serde_json::from_slice(&default)
+			.map_err(|e| error::Error::Other(format!("Invalid default genesis config: {}", e)))?;
+
+		// The runtime can't know its own code, so it is filled in before applying the patch.
+		if let Some(system) = config.get_mut("frameSystem") {
+			system["code"] = Value::String(format!("0x{}", hex::encode(node_runtime::WASM_BINARY)));
+		}
+
+		if let Some(path) = &self.patch {
+			let patch = serde_json::from_slice(&fs::read(path)?)
+				.map_err(|e| error::Error::Input(format!("Invalid genesis patch: {}", e)))?;
+			sp_genesis_builder::merge_patch(&mut config, patch);
+		}
+
+		if self.print_config {
+			println!(
+				"{}",
+				serde_json::to_string_pretty(&config).expect("JSON values are always serializable; qed"),
+			);
+			return Ok(())
+		}
+
+		let out = self.out.as_ref()
+			.ok_or_else(|| error::Error::Input("Either `--out` or `--print-config` is required".into()))?;
+
+		let mut storage = Storage::default();
+		let json = serde_json::to_vec(&config).expect("JSON values are always serializable; qed");
+		call_genesis_builder::<sp_genesis_builder::Result>(&mut storage, "build_config", &json.encode())?
+			.map_err(|e| error::Error::Other(String::from_utf8_lossy(&e).into_owned()))?;
+
+		let mut spec = sc_cli::load_spec(&self.shared_params, spec_factory)?;
+		spec.set_storage(storage);
+		fs::write(out, spec.to_json(true)?)?;
+		println!("Raw chain spec written to {}", out.display());
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::BuildStorage;
+
+	#[test]
+	fn genesis_built_by_runtime_matches_hand_assembled_one() {
+		let spec = crate::chain_spec::development_config();
+		let expected = spec.build_storage().unwrap();
+		let config: Value = serde_json::from_str(&spec.to_json(false).unwrap()).unwrap();
+
+		let mut storage = Storage::default();
+		let json = serde_json::to_vec(&config["genesis"]["runtime"]).unwrap();
+		call_genesis_builder::<sp_genesis_builder::Result>(&mut storage, "build_config", &json.encode())
+			.unwrap()
+			.unwrap();
+
+		assert_eq!(storage.top, expected.top);
+	}
+
+	#[test]
+	fn default_config_of_runtime_builds_with_a_patch() {
+		let default: Vec<u8> = call_genesis_builder(&mut Storage::default(), "create_default_config", &[])
+			.unwrap();
+		let mut config: Value = serde_json::from_slice(&default).unwrap();
+		sp_genesis_builder::merge_patch(&mut config, serde_json::json!({
+			"palletSudo": { "key": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY" },
+			"palletStaking": { "validatorCount": 7, "unknownField": null },
+		}));
+
+		let mut storage = Storage::default();
+		let json = serde_json::to_vec(&config).unwrap();
+		call_genesis_builder::<sp_genesis_builder::Result>(&mut storage, "build_config", &json.encode())
+			.unwrap()
+			.unwrap();
+
+		let key = [sp_core::twox_128(b"Staking"), sp_core::twox_128(b"ValidatorCount")].concat();
+		assert_eq!(storage.top.get(&key).map(|value| u32::decode(&mut &value[..]).unwrap()), Some(7));
+	}
+}
@@ -22,11 +22,11 @@ use serde::{Serialize, Deserialize};
 use node_runtime::{
 	AuthorityDiscoveryConfig, BabeConfig, BalancesConfig, ContractsConfig, CouncilConfig, DemocracyConfig,
 	GrandpaConfig, ImOnlineConfig, IndicesConfig, SessionConfig, SessionKeys, StakerStatus, StakingConfig, SudoConfig,
-	SystemConfig, TechnicalCommitteeConfig, WASM_BINARY,
+	SystemConfig, TechnicalCommitteeConfig, FeeDistributionConfig, Distribution, BlockTimeConfig, WASM_BINARY,
 };
 use node_runtime::Block;
-use node_runtime::constants::currency::*;
-use sc_service;
+use node_runtime::constants::{address::SS58_PREFIX, currency::*, time::MILLISECS_PER_BLOCK};
+use sc_service::{self, Properties};
 use hex_literal::hex;
 use sc_telemetry::TelemetryEndpoints;
 use grandpa_primitives::{AuthorityId as GrandpaId};
@@ -36,6 +36,7 @@ use sp_authority_discovery::AuthorityId as AuthorityDiscoveryId;
 use sp_runtime::{Perbill, traits::{Verify, IdentifyAccount}};
 
 pub use node_primitives::{AccountId, Balance, Signature};
+use node_primitives::{BlockNumber, Hash};
 pub use node_runtime::GenesisConfig;
 
 type AccountPublic = <Signature as Verify>::Signer;
@@ -53,6 +54,12 @@ pub struct Extensions {
 	pub fork_blocks: sc_client::ForkBlocks<Block>,
 	/// Known bad block hashes.
 	pub bad_blocks: sc_client::BadBlocks<Block>,
+	/// Block light clients are pinned to.
+	pub light_checkpoint: sc_client::LightCheckpoint<Block>,
+	/// Expected length of the epochs in slots.
+	pub epoch_length: Option<u64>,
+	/// Trusted finalized checkpoint to sync from.
+	pub checkpoint: Option<grandpa::TrustedCheckpoint<Hash, BlockNumber>>,
 }
 
 /// Specialized `ChainSpec`.
@@ -151,6 +158,13 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 	)
 }
 
+/// The properties of the chain specs of the node, with the SS58 address format of its accounts.
+fn properties() -> Properties {
+	let mut properties = Properties::new();
+	properties.insert("ss58Format".into(), SS58_PREFIX.into());
+	properties
+}
+
 /// Staging testnet config.
 pub fn staging_testnet_config() -> ChainSpec {
 	let boot_nodes = vec![];
@@ -161,7 +175,7 @@ pub fn staging_testnet_config() -> ChainSpec {
 		boot_nodes,
 		Some(TelemetryEndpoints::new(vec![(STAGING_TELEMETRY_URL.to_string(), 0)])),
 		None,
-		None,
+		Some(properties()),
 		Default::default(),
 	)
 }
@@ -295,6 +309,19 @@ pub fn testnet_genesis(
 		}),
 		pallet_membership_Instance1: Some(Default::default()),
 		pallet_treasury: Some(Default::default()),
+		pallet_fee_distribution: Some(FeeDistributionConfig {
+			fee_distribution: Distribution {
+				author: Perbill::from_percent(20),
+				treasury: Perbill::from_percent(80),
+			},
+			tip_distribution: Distribution {
+				author: Perbill::one(),
+				treasury: Perbill::zero(),
+			},
+		}),
+		block_time: Some(BlockTimeConfig {
+			block_time_ms: MILLISECS_PER_BLOCK,
+		}),
 	}
 }
 
@@ -318,7 +345,7 @@ pub fn development_config() -> ChainSpec {
 		vec![],
 		None,
 		None,
-		None,
+		Some(properties()),
 		Default::default(),
 	)
 }
@@ -344,7 +371,38 @@ pub fn local_testnet_config() -> ChainSpec {
 		vec![],
 		None,
 		None,
+		Some(properties()),
+		Default::default(),
+	)
+}
+
+/// Seed of the validator with the given index in a generated testnet.
+///
+/// The first validators use the well known development accounts.
+pub fn testnet_validator_seed(index: usize) -> String {
+	const NAMED: [&str; 6] = ["Alice", "Bob", "Charlie", "Dave", "Eve", "Ferdie"];
+	match NAMED.get(index) {
+		Some(name) => name.to_string(),
+		None => format!("Validator{}", index),
+	}
+}
+
+/// Local testnet config with the given number of validators.
+pub fn multi_validator_testnet_config(validators: usize, boot_nodes: Vec<String>) -> ChainSpec {
+	let seeds = (0..validators).map(testnet_validator_seed).collect::<Vec<_>>();
+	ChainSpec::from_genesis(
+		"Local Testnet",
+		"local_testnet",
+		move || testnet_genesis(
+			seeds.iter().map(|seed| get_authority_keys_from_seed(seed)).collect(),
+			get_account_id_from_seed::<sr25519::Public>("Alice"),
+			None,
+			false,
+		),
+		boot_nodes,
+		None,
 		None,
+		Some(properties()),
 		Default::default(),
 	)
 }
@@ -354,6 +412,7 @@ pub(crate) mod tests {
 	use super::*;
 	use crate::service::{new_full, new_light};
 	use sc_service_test;
+	use sp_core::crypto::Ss58AddressFormat;
 
 	fn local_testnet_genesis_instant_single() -> GenesisConfig {
 		testnet_genesis(
@@ -394,6 +453,85 @@ pub(crate) mod tests {
 		)
 	}
 
+	#[test]
+	fn light_clients_are_built_with_the_extensions_of_the_chain_spec() {
+		use node_runtime::constants::time::EPOCH_DURATION_MS;
+		use sc_service::{ServiceBuilder, config::{Configuration, KeystoreConfig, DatabaseConfig}};
+		use sp_consensus::{BlockImport, BlockCheckParams, ImportResult};
+
+		let fork_block = (1, Hash::repeat_byte(1));
+		let bad_block = Hash::repeat_byte(2);
+		let light_checkpoint = (2, Hash::repeat_byte(3));
+		let epoch_length = EPOCH_DURATION_MS / MILLISECS_PER_BLOCK;
+		let spec = ChainSpec::from_genesis(
+			"Integration Test",
+			"test",
+			local_testnet_genesis_instant_single,
+			vec![],
+			None,
+			None,
+			None,
+			Extensions {
+				fork_blocks: Some(vec![fork_block]),
+				bad_blocks: Some(vec![bad_block].into_iter().collect()),
+				light_checkpoint: Some(light_checkpoint),
+				epoch_length: Some(epoch_length),
+				checkpoint: None,
+			},
+		);
+		// The extensions are read back from the JSON of the spec, as the node does.
+		let spec = ChainSpec::from_json_bytes(spec.to_json(false).unwrap().into_bytes()).unwrap();
+
+		let base_path = tempfile::tempdir().unwrap();
+		let mut config = Configuration::<(), _, _>::default_with_spec_and_base_path(
+			spec,
+			Some(base_path.path().to_path_buf()),
+		);
+		config.keystore = KeystoreConfig::InMemory;
+		config.database = DatabaseConfig::Path { path: base_path.path().join("db"), cache_size: None };
+		let builder = ServiceBuilder::new_light::<
+			Block, node_runtime::RuntimeApi, node_executor::Executor,
+		>(config).unwrap();
+		let mut client = &**builder.client();
+
+		let genesis_hash = client.chain_info().genesis_hash;
+		let other = Hash::repeat_byte(4);
+		let mut status = |number: BlockNumber, hash, parent_hash| client.check_block(BlockCheckParams {
+			hash,
+			number,
+			parent_hash,
+			allow_missing_state: false,
+			import_existing: false,
+		}).unwrap();
+
+		assert_ne!(status(1, fork_block.1, genesis_hash), ImportResult::KnownBad);
+		assert_eq!(status(1, other, genesis_hash), ImportResult::KnownBad);
+		assert_eq!(status(2, other, fork_block.1), ImportResult::KnownBad);
+		assert_eq!(status(3, bad_block, other), ImportResult::KnownBad);
+		assert_eq!(status(4, other, bad_block), ImportResult::KnownBad);
+
+		let babe_config = sc_consensus_babe::Config::get_or_compute(client).unwrap();
+		assert_eq!(babe_config.epoch_length, epoch_length);
+		let babe_config = babe_config.ensure_epoch_length(Some(epoch_length)).unwrap();
+		assert!(babe_config.ensure_epoch_length(Some(epoch_length + 1)).is_err());
+	}
+
+	#[test]
+	fn chain_specs_use_the_ss58_prefix_of_the_runtime() {
+		let specs = vec![
+			development_config(),
+			local_testnet_config(),
+			staging_testnet_config(),
+			flaming_fir_config().unwrap(),
+		];
+		for spec in specs {
+			// Read back from the JSON of the spec, as the node does.
+			let spec = ChainSpec::from_json_bytes(spec.to_json(false).unwrap().into_bytes()).unwrap();
+			let format = Ss58AddressFormat::deserialize(&spec.properties()["ss58Format"]).unwrap();
+			assert_eq!(u8::from(format), SS58_PREFIX);
+		}
+	}
+
 	#[test]
 	#[ignore]
 	fn test_connectivity() {
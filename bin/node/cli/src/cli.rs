@@ -17,14 +17,15 @@
 pub use sc_cli::VersionInfo;
 use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
 use sc_cli::{IntoExit, NoCustom, SharedParams, ImportParams, error};
-use sc_service::{AbstractService, Roles as ServiceRoles, Configuration};
+use sc_service::{AbstractService, Roles as ServiceRoles, Configuration, config::DatabaseConfig};
 use log::info;
-use structopt::StructOpt;
+use structopt::{StructOpt, clap::arg_enum};
 use sc_cli::{display_role, parse_and_prepare, GetSharedParams, ParseAndPrepare};
-use crate::{service, ChainSpec, load_spec};
+use crate::{benchmark, service, ChainSpec, load_spec};
 use crate::factory_impl::FactoryState;
-use node_transaction_factory::RuntimeAdapter;
-use futures::{channel::oneshot, future::{select, Either}};
+use node_transaction_factory::{AccountPool, RuntimeAdapter};
+use futures::{channel::oneshot, future::{join, select, Either, FutureExt}};
+use std::path::PathBuf;
 
 /// Custom subcommands.
 #[derive(Clone, Debug, StructOpt)]
@@ -36,21 +37,124 @@ pub enum CustomSubcommands {
 		Only supported for development or local testnet."
 	)]
 	Factory(FactoryCmd),
+
+	/// Decode SCALE encoded data using the runtime metadata.
+	#[structopt(name = "decode")]
+	Decode(DecodeCmd),
+
+	/// Create a raw chain spec from the state of a live chain.
+	#[structopt(
+		name = "fork-off",
+		about = "Creates a raw chain spec from the state of a live chain, \
+		to rehearse upgrades and migrations on a realistic fork."
+	)]
+	ForkOff(ForkOffCmd),
+
+	/// Launch a local testnet.
+	#[structopt(
+		name = "testnet",
+		about = "Launches a local testnet of validator nodes with a generated chain spec, \
+		one child process per validator."
+	)]
+	Testnet(TestnetCmd),
+
+	/// Emergency tooling for GRANDPA.
+	#[structopt(name = "grandpa")]
+	Grandpa(GrandpaCmd),
+
+	/// Manage the key of the node's network identity.
+	#[structopt(name = "key")]
+	Key(KeyCmd),
+
+	/// Report the storage usage per module and storage item.
+	#[structopt(
+		name = "storage-report",
+		about = "Reports the number and size of the storage keys of each module and storage \
+		item at a block of the local database."
+	)]
+	StorageReport(StorageReportCmd),
+
+	/// Show the storage changes between two blocks.
+	#[structopt(
+		name = "state-diff",
+		about = "Shows the storage items created, modified and deleted between two blocks, \
+		decoded using the runtime metadata where possible."
+	)]
+	StateDiff(StateDiffCmd),
+
+	/// Check two runtime metadata versions for changes breaking encoded data.
+	#[structopt(
+		name = "check-metadata-compat",
+		about = "Compares two runtime metadata blobs and reports changes to call, event and \
+		error indices and argument layouts that break transactions encoded for the old runtime."
+	)]
+	CheckMetadataCompat(CheckMetadataCompatCmd),
+
+	/// Export the runtime interface as a JSON schema for client code generators.
+	#[structopt(
+		name = "export-types",
+		about = "Exports the calls, events, errors, constants, storage items and types of the \
+		runtime with their documentation as a JSON schema, for client code generators."
+	)]
+	ExportTypes(ExportTypesCmd),
+
+	/// Build the genesis state with the runtime from a JSON patch of its default config.
+	#[structopt(
+		name = "build-genesis",
+		about = "Builds the genesis state by executing the genesis logic of the runtime on its \
+		default config with a JSON merge patch applied, and writes it as a raw chain spec."
+	)]
+	BuildGenesis(BuildGenesisCmd),
+
+	/// Benchmark the hardware of the machine.
+	#[structopt(name = "benchmark")]
+	Benchmark(BenchmarkCmd),
+
+	/// Run the nodes of several chains in this process.
+	#[structopt(
+		name = "run-chains",
+		about = "Runs a node of each chain listed in a JSON file, sharing the threads of the \
+		process and a single Grafana data source, whose metrics are prefixed with the names of \
+		the chains."
+	)]
+	RunChains(RunChainsCmd),
+
+	/// Import justifications exported by another node.
+	#[structopt(
+		name = "import-justifications",
+		about = "Submits the justifications listed in a JSON file to a running node, finalizing \
+		the blocks whose justification the node missed, e.g. during a netsplit."
+	)]
+	ImportJustifications(ImportJustificationsCmd),
 }
 
 impl GetSharedParams for CustomSubcommands {
 	fn shared_params(&self) -> Option<&SharedParams> {
 		match self {
 			CustomSubcommands::Factory(cmd) => Some(&cmd.shared_params),
+			CustomSubcommands::Decode(_) => None,
+			CustomSubcommands::ForkOff(cmd) => Some(&cmd.shared_params),
+			CustomSubcommands::Testnet(_) => None,
+			CustomSubcommands::Grandpa(_) => None,
+			CustomSubcommands::Key(_) => None,
+			CustomSubcommands::StorageReport(cmd) => Some(&cmd.shared_params),
+			CustomSubcommands::StateDiff(_) => None,
+			CustomSubcommands::CheckMetadataCompat(_) => None,
+			CustomSubcommands::ExportTypes(_) => None,
+			CustomSubcommands::BuildGenesis(cmd) => Some(&cmd.shared_params),
+			CustomSubcommands::Benchmark(_) => None,
+			CustomSubcommands::RunChains(_) => None,
+			CustomSubcommands::ImportJustifications(_) => None,
 		}
 	}
 }
 
 /// The `factory` command used to generate transactions.
-/// Please note: this command currently only works on an empty database!
+/// Please note: unless submitting over RPC, this command only works on a database whose blocks
+/// were all manufactured by the factory!
 #[derive(Debug, StructOpt, Clone)]
 pub struct FactoryCmd {
-	/// How often to repeat. This option only has an effect in mode `MasterToNToM`.
+	/// How often to repeat. This option only has an effect in modes `MasterToNToM` and `Pool`.
 	#[structopt(long="rounds", default_value = "1")]
 	pub rounds: u64,
 
@@ -70,7 +174,14 @@ pub struct FactoryCmd {
 	///               B -> E, C -> F, D -> G, ...{n}
 	///               ... x `rounds`
 	///
-	/// These three modes control manufacturing.
+	/// Pool: Fund `pool-size` randomly created accounts from the master
+	///       account, then manufacture `num` transactions per round for
+	///       `rounds` rounds from senders picked in the pool, each to
+	///       another randomly created account.{n}
+	///       A -> P1, A -> P2, ... x `pool-size`{n}
+	///       P1 -> B, P2 -> C, ... x `num` x `rounds`
+	///
+	/// These four modes control manufacturing.
 	#[structopt(long="mode", default_value = "MasterToN")]
 	pub mode: node_transaction_factory::Mode,
 
@@ -79,6 +190,67 @@ pub struct FactoryCmd {
 	#[structopt(long="num", default_value = "8")]
 	pub num: u64,
 
+	/// Number of accounts sending the transactions in mode `Pool`.
+	#[structopt(long="pool-size", default_value = "16")]
+	pub pool_size: u64,
+
+	/// How the senders are picked in the pool in mode `Pool`: `RoundRobin` or `Random`. Either
+	/// way, every account of the pool sends once before any of them sends again.
+	#[structopt(long="sender-selection", default_value = "RoundRobin")]
+	pub sender_selection: node_transaction_factory::SenderSelection,
+
+	/// Signature scheme of the accounts created by the factory: `Sr25519`, `Ed25519` or `Ecdsa`.
+	/// The master account keeps signing with sr25519, so the scheme applies to the transactions
+	/// of the `MasterToNToM` rounds after the first and of the `Pool` mode once funded. It is
+	/// rejected in the other modes, whose transactions are all sent by the master account.
+	#[structopt(long="signature-scheme", default_value = "Sr25519")]
+	pub signature_scheme: node_transaction_factory::SignatureScheme,
+
+	/// Number of transactions per block manufactured on the local database. The transactions of
+	/// a block are signed in parallel. Those not fitting in the block are included in the next.
+	#[structopt(long="transactions-per-block", default_value = "1")]
+	pub transactions_per_block: usize,
+
+	/// The calls to manufacture transactions of: a built-in scenario, `transfer` or
+	/// `transfer_keep_alive`, or the path of a JSON scenario file. The transactions cycle
+	/// through the calls of the scenario, the mode picking their senders, destinations and
+	/// amounts.
+	#[structopt(long="scenario", default_value = "transfer")]
+	pub scenario: String,
+
+	/// File to write the metrics of the blocks manufactured on the local database to: their
+	/// construction and import times, weights, transactions, extrinsics, storage writes and reads
+	/// and writes of the database. Written as CSV if the file name ends with `.csv`, JSON
+	/// otherwise. A summary is logged at the end of the run either way.
+	#[structopt(long="report-file", parse(from_os_str))]
+	pub report_file: Option<PathBuf>,
+
+	/// Import: import the transactions in blocks manufactured on the local database.{n}
+	/// Rpc: submit the transactions with `author_submitExtrinsic` to the nodes at `rpc-url`,
+	///      to load a live network and its transaction pools. `MasterToNToM` is not supported,
+	///      its later rounds would spend funds not yet received. In mode `Pool`, the transactions
+	///      of the pool are submitted once its funding is included in the best block.
+	#[structopt(
+		long="submit",
+		possible_values = &FactorySubmission::variants(),
+		case_insensitive = true,
+		default_value = "Import"
+	)]
+	pub submit: FactorySubmission,
+
+	/// RPC endpoint to submit the transactions to, may be repeated to submit to several nodes
+	/// in turn.
+	#[structopt(long="rpc-url", number_of_values = 1, default_value = "http://localhost:9933")]
+	pub rpc_urls: Vec<String>,
+
+	/// Number of transactions submitted per second over RPC, 0 for no limit.
+	#[structopt(long="rate", default_value = "0")]
+	pub rate: u64,
+
+	/// Maximum number of RPC submissions awaiting the answer of the nodes.
+	#[structopt(long="concurrency", default_value = "16")]
+	pub concurrency: usize,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -88,6 +260,433 @@ pub struct FactoryCmd {
 	pub import_params: ImportParams,
 }
 
+arg_enum! {
+	/// How the factory submits the transactions.
+	#[allow(missing_docs)]
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum FactorySubmission {
+		Import,
+		Rpc,
+	}
+}
+
+/// The `decode` command used to decode storage, events, extrinsics and other values.
+///
+/// The metadata is read from `--metadata` or fetched from the node at `--url`.
+#[derive(Debug, StructOpt, Clone)]
+pub struct DecodeCmd {
+	/// File containing the SCALE encoded runtime metadata, either binary or hex encoded.
+	#[structopt(long="metadata", parse(from_os_str))]
+	pub metadata: Option<PathBuf>,
+
+	/// RPC endpoint of the node to fetch the metadata from.
+	#[structopt(long="url", default_value = "http://localhost:9933")]
+	pub url: String,
+
+	/// Hash of the block to fetch the metadata at. Defaults to the best block.
+	#[structopt(long="at")]
+	pub at: Option<String>,
+
+	/// JSON file with type definitions in addition to the built in ones.
+	#[structopt(long="types", parse(from_os_str))]
+	pub types: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(subcommand)]
+	pub target: DecodeTarget,
+}
+
+/// What to decode. All data is hex encoded.
+#[derive(Debug, StructOpt, Clone)]
+pub enum DecodeTarget {
+	/// Decode a value of the given type, e.g. `Vec<AccountId>`.
+	#[structopt(name = "type")]
+	Type {
+		/// The type name, as used in the metadata.
+		ty: String,
+		/// The encoded value.
+		data: String,
+	},
+	/// Decode a storage key and, if given, its value.
+	#[structopt(name = "storage")]
+	Storage {
+		/// The full storage key.
+		key: String,
+		/// The encoded value; the default of the storage entry if not given.
+		value: Option<String>,
+	},
+	/// Decode the content of the `System::Events` storage item.
+	#[structopt(name = "events")]
+	Events {
+		/// The encoded events.
+		data: String,
+	},
+	/// Decode an extrinsic.
+	#[structopt(name = "extrinsic")]
+	Extrinsic {
+		/// The encoded extrinsic.
+		data: String,
+	},
+	/// Look up the error reported by `DispatchError::Module`.
+	#[structopt(name = "error")]
+	Error {
+		/// The index of the module.
+		module: u8,
+		/// The index of the error within the module.
+		error: u8,
+	},
+}
+
+/// The `fork-off` command used to create a chain spec from the state of a live chain.
+///
+/// The client part of the spec (name, boot nodes, ...) is taken from `--chain`, its genesis
+/// is replaced by the forked state. Child storage is not carried over.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ForkOffCmd {
+	/// RPC endpoint of the node to download the state from.
+	#[structopt(long="url", default_value = "http://localhost:9933")]
+	pub url: String,
+
+	/// Hash of the block to fork off at. Defaults to the last finalized block.
+	#[structopt(long="at")]
+	pub at: Option<String>,
+
+	/// Read the state from a JSON file instead of downloading it. The file either maps hex
+	/// encoded keys to hex encoded values or is a raw chain spec.
+	#[structopt(long="state", parse(from_os_str))]
+	pub state: Option<PathBuf>,
+
+	/// Replace the sudo key with the given account, as SS58 address or hex encoded public key.
+	#[structopt(long="sudo")]
+	pub sudo: Option<String>,
+
+	/// Replace the validator set, i.e. the state of session, consensus and staking modules,
+	/// with the one of the `--chain`.
+	#[structopt(long="reset-validators")]
+	pub reset_validators: bool,
+
+	/// Remove all storage items with the given hex encoded key prefix.
+	#[structopt(long="remove-prefix", number_of_values = 1)]
+	pub remove_prefix: Vec<String>,
+
+	/// Set a storage item, given as hex encoded `KEY=VALUE`. Applied after `--remove-prefix`.
+	#[structopt(long="set", number_of_values = 1)]
+	pub set: Vec<String>,
+
+	/// File to write the raw chain spec to.
+	#[structopt(long="out", parse(from_os_str))]
+	pub out: PathBuf,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `testnet` command used to launch a local testnet.
+///
+/// Validator keys, node keys and ports are derived from the index of the node, so repeated runs
+/// produce the same network.
+#[derive(Debug, StructOpt, Clone)]
+pub struct TestnetCmd {
+	/// Number of validator nodes.
+	#[structopt(long="validators", default_value = "2")]
+	pub validators: usize,
+
+	/// Directory for the chain spec and the node databases. Defaults to a temporary directory
+	/// which is removed on exit.
+	#[structopt(long="base-path", parse(from_os_str))]
+	pub base_path: Option<PathBuf>,
+
+	/// libp2p port of the first node, the following nodes use the next ports.
+	#[structopt(long="port", default_value = "30333")]
+	pub port: u16,
+
+	/// HTTP RPC port of the first node, the following nodes use the next ports.
+	#[structopt(long="rpc-port", default_value = "9933")]
+	pub rpc_port: u16,
+
+	/// WebSockets RPC port of the first node, the following nodes use the next ports.
+	#[structopt(long="ws-port", default_value = "9944")]
+	pub ws_port: u16,
+
+	/// Additional arguments passed to every node, e.g. `-- --log sync=trace`.
+	#[structopt(last = true)]
+	pub node_args: Vec<String>,
+}
+
+/// The `storage-report` command used to report the storage usage at a block.
+#[derive(Debug, StructOpt, Clone)]
+pub struct StorageReportCmd {
+	/// Hash of the block to report the storage usage at. Defaults to the best block.
+	#[structopt(long="at")]
+	pub at: Option<String>,
+
+	/// Print the report as JSON.
+	#[structopt(long="json")]
+	pub json: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub import_params: ImportParams,
+}
+
+/// The `state-diff` command used to compare the storage of two blocks.
+///
+/// The diff is computed by the node at `--url`. Keys and values are decoded with the metadata
+/// of the block they belong to.
+#[derive(Debug, StructOpt, Clone)]
+pub struct StateDiffCmd {
+	/// Hash of the block to compare from.
+	pub from: String,
+
+	/// Hash of the block to compare to.
+	pub to: String,
+
+	/// RPC endpoint of the node to compute the diff.
+	#[structopt(long="url", default_value = "http://localhost:9933")]
+	pub url: String,
+
+	/// Only compare the keys starting with the given hex encoded prefix.
+	#[structopt(long="prefix")]
+	pub prefix: Option<String>,
+
+	/// JSON file with type definitions in addition to the built in ones.
+	#[structopt(long="types", parse(from_os_str))]
+	pub types: Option<PathBuf>,
+
+	/// Print the diff as JSON.
+	#[structopt(long="json")]
+	pub json: bool,
+}
+
+/// The `check-metadata-compat` command used to compare two runtime metadata versions.
+///
+/// Exits with an error if any breaking change is found, so it can guard runtime upgrades in CI.
+#[derive(Debug, StructOpt, Clone)]
+pub struct CheckMetadataCompatCmd {
+	/// File containing the SCALE encoded metadata of the old runtime, either binary or hex encoded.
+	#[structopt(parse(from_os_str))]
+	pub old: PathBuf,
+
+	/// File containing the SCALE encoded metadata of the new runtime, either binary or hex encoded.
+	#[structopt(parse(from_os_str))]
+	pub new: PathBuf,
+
+	/// JSON file with type definitions in addition to the built in ones, used for both runtimes.
+	#[structopt(long="types", parse(from_os_str))]
+	pub types: Option<PathBuf>,
+
+	/// Print the breaking changes as JSON.
+	#[structopt(long="json")]
+	pub json: bool,
+}
+
+/// The `export-types` command used to export the runtime interface as a JSON schema.
+///
+/// The metadata is read from `--metadata` or fetched from the node at `--url`.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportTypesCmd {
+	/// File containing the SCALE encoded runtime metadata, either binary or hex encoded.
+	#[structopt(long="metadata", parse(from_os_str))]
+	pub metadata: Option<PathBuf>,
+
+	/// RPC endpoint of the node to fetch the metadata from.
+	#[structopt(long="url", default_value = "http://localhost:9933")]
+	pub url: String,
+
+	/// Hash of the block to fetch the metadata at. Defaults to the best block.
+	#[structopt(long="at")]
+	pub at: Option<String>,
+
+	/// JSON file with type definitions in addition to the built in ones.
+	#[structopt(long="types", parse(from_os_str))]
+	pub types: Option<PathBuf>,
+
+	/// File to write the schema to. Printed if not given.
+	#[structopt(long="out", parse(from_os_str))]
+	pub out: Option<PathBuf>,
+}
+
+/// The `build-genesis` command used to build the genesis state with the runtime.
+///
+/// The client part of the spec (name, boot nodes, ...) is taken from `--chain`.
+#[derive(Debug, StructOpt, Clone)]
+pub struct BuildGenesisCmd {
+	/// JSON merge patch (RFC 7386) applied to the default genesis config of the runtime.
+	#[structopt(long="patch", parse(from_os_str))]
+	pub patch: Option<PathBuf>,
+
+	/// Print the patched genesis config instead of building the genesis state.
+	#[structopt(long="print-config")]
+	pub print_config: bool,
+
+	/// File to write the raw chain spec to.
+	#[structopt(long="out", parse(from_os_str), required_unless = "print-config")]
+	pub out: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// Emergency tooling for GRANDPA.
+#[derive(Debug, StructOpt, Clone)]
+pub enum GrandpaCmd {
+	/// Craft or verify the call recovering from a finality stall.
+	///
+	/// Once dispatched (e.g. through sudo or governance), the call schedules a forced change
+	/// to the authority set of the next session. The change is enacted without finality, so
+	/// a stall caused by more than 1/3 of the authorities being gone can be recovered from.
+	#[structopt(name = "note-stalled")]
+	NoteStalled(NoteStalledCmd),
+
+	/// Experimental: split a GRANDPA key into shares for threshold signing.
+	///
+	/// Prints one share per host, to be inserted into its keystore with the `grth` key type
+	/// under the public key of the split key, e.g. with the `author_insertKey` RPC. The hosts
+	/// are then started with `--grandpa-threshold-peer` for each of the other hosts.
+	#[structopt(name = "split-key")]
+	SplitKey(SplitKeyCmd),
+}
+
+/// Management of the key of the node's network identity.
+#[derive(Debug, StructOpt, Clone)]
+pub enum KeyCmd {
+	/// Generate an Ed25519 node key and print its `PeerId`.
+	///
+	/// The key is written to a new file, readable by the current user only, to be passed to the
+	/// node with `--node-key-file`.
+	#[structopt(name = "generate-node-key")]
+	GenerateNodeKey(NodeKeyFileCmd),
+
+	/// Print the `PeerId` of the node key stored in a file.
+	#[structopt(name = "inspect-node-key")]
+	InspectNodeKey(NodeKeyFileCmd),
+}
+
+/// The `key generate-node-key` and `key inspect-node-key` commands.
+#[derive(Debug, StructOpt, Clone)]
+pub struct NodeKeyFileCmd {
+	/// File the secret key is stored in.
+	#[structopt(long="file", parse(from_os_str))]
+	pub file: PathBuf,
+}
+
+/// The `grandpa note-stalled` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct NoteStalledCmd {
+	/// Number of blocks after the start of the next session to enact the forced change at.
+	/// Should be high enough for the signaling block to not be reorged.
+	#[structopt(long="delay", default_value = "1000")]
+	pub delay: node_primitives::BlockNumber,
+
+	/// Number of the best block finalized by the stalled authority set. The new voters
+	/// start from it.
+	#[structopt(long="best-finalized", required_unless = "verify")]
+	pub best_finalized: Option<node_primitives::BlockNumber>,
+
+	/// Wrap the call in `Sudo::sudo`.
+	#[structopt(long="sudo")]
+	pub sudo: bool,
+
+	/// Verify the given hex encoded call instead of crafting one.
+	#[structopt(long="verify", conflicts_with_all = &["best-finalized", "sudo"])]
+	pub verify: Option<String>,
+}
+
+/// The `grandpa split-key` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct SplitKeyCmd {
+	/// Secret URI of the GRANDPA key.
+	#[structopt(long="suri")]
+	pub suri: String,
+
+	/// Number of shares needed to sign, at least half the shares plus one so that a compromised
+	/// host can't sign conflicting votes with any other host.
+	#[structopt(long="threshold")]
+	pub threshold: u16,
+
+	/// Number of shares.
+	#[structopt(long="shares")]
+	pub shares: u16,
+}
+
+/// The `import-justifications` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ImportJustificationsCmd {
+	/// JSON file with an array of `{ "hash": "0x...", "justification": "0x..." }` objects, in
+	/// increasing block order. The justifications are e.g. the `justification` fields returned
+	/// by `chain_getBlock` on a node which has them.
+	#[structopt(parse(from_os_str))]
+	pub file: PathBuf,
+
+	/// RPC endpoint of the node to import the justifications into.
+	#[structopt(long="url", default_value = "http://localhost:9933")]
+	pub url: String,
+}
+
+/// Hardware benchmarks.
+#[derive(Debug, StructOpt, Clone)]
+pub enum BenchmarkCmd {
+	/// Measure the CPU, memory and disk performance and compare it to the reference machine
+	/// of the validator requirements.
+	///
+	/// Measures BLAKE2-256 hashing throughput, SR25519 signature verifications, memory copy
+	/// bandwidth and synced sequential and random disk writes.
+	#[structopt(name = "machine")]
+	Machine(MachineCmd),
+}
+
+/// The `benchmark machine` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct MachineCmd {
+	/// Duration of each benchmark in milliseconds.
+	#[structopt(long="duration", default_value = "2000")]
+	pub duration: u64,
+
+	/// Directory to run the disk benchmarks in, should be on the disk of the database.
+	/// Defaults to the temporary directory.
+	#[structopt(long="base-path", parse(from_os_str))]
+	pub base_path: Option<PathBuf>,
+
+	/// Skip the disk benchmarks.
+	#[structopt(long="skip-disk")]
+	pub skip_disk: bool,
+
+	/// Exit with an error if any result is below the requirements.
+	#[structopt(long="enforce")]
+	pub enforce: bool,
+
+	/// Print the results as JSON.
+	#[structopt(long="json")]
+	pub json: bool,
+}
+
+/// The `run-chains` command used to run the nodes of several chains in one process.
+///
+/// The file lists the chains with the arguments of the `run` command of their node, e.g.
+/// `{ "chains": [{ "name": "alpha", "args": ["--chain", "alpha.json", "--base-path", "/srv/alpha",
+/// "--port", "30334", "--rpc-port", "9934", "--ws-port", "9945"] }] }`. The nodes need distinct
+/// base paths and ports.
+#[derive(Debug, StructOpt, Clone)]
+pub struct RunChainsCmd {
+	/// JSON file listing the chains.
+	#[structopt(parse(from_os_str))]
+	pub chains: PathBuf,
+
+	/// Port of the Grafana data source serving the metrics of all chains.
+	#[structopt(long="grafana-port", value_name = "PORT", default_value = "9955")]
+	pub grafana_port: u16,
+
+	/// Listen to all Grafana data source interfaces, rather than only the local one.
+	#[structopt(long="grafana-external")]
+	pub grafana_external: bool,
+}
+
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: sc_cli::VersionInfo) -> error::Result<()> where
 	I: IntoIterator<Item = T>,
@@ -105,6 +704,14 @@ pub fn run<I, T, E>(args: I, exit: E, version: sc_cli::VersionInfo) -> error::Re
 			info!("Chain specification: {}", config.chain_spec.name());
 			info!("Node name: {}", config.name);
 			info!("Roles: {}", display_role(&config));
+			if config.roles == ServiceRoles::AUTHORITY {
+				let database_dir = match &config.database {
+					DatabaseConfig::Path { path, .. } => Some(path.as_path()),
+					_ => None,
+				};
+				let cache = config.in_chain_config_dir(benchmark::CHECK_CACHE_FILE);
+				benchmark::check_hardware(database_dir, cache.as_ref().map(PathBuf::as_path));
+			}
 			let runtime = RuntimeBuilder::new()
 				.thread_name("main-tokio-")
 				.threaded_scheduler()
@@ -133,6 +740,8 @@ pub fn run<I, T, E>(args: I, exit: E, version: sc_cli::VersionInfo) -> error::Re
 		ParseAndPrepare::PurgeChain(cmd) => cmd.run(load_spec),
 		ParseAndPrepare::RevertChain(cmd) => cmd.run_with_builder(|config: Config<_, _>|
 			Ok(new_full_start!(config).0), load_spec),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::Factory(cli_args))
+			if cli_args.submit == FactorySubmission::Rpc => cli_args.submit_rpc(),
 		ParseAndPrepare::CustomCommand(CustomSubcommands::Factory(cli_args)) => {
 			let mut config: Config<_, _> = sc_cli::create_config_with_db_path(
 				load_spec,
@@ -151,11 +760,18 @@ pub fn run<I, T, E>(args: I, exit: E, version: sc_cli::VersionInfo) -> error::Re
 				cli_args.mode.clone(),
 				cli_args.num,
 				cli_args.rounds,
+				AccountPool::new(cli_args.pool_size, cli_args.sender_selection),
+				cli_args.signature_scheme,
 			);
 
+			let scenario = node_transaction_factory::Scenario::load(&cli_args.scenario)?;
+
 			let service_builder = new_full_start!(config).0;
 			node_transaction_factory::factory::<FactoryState<_>, _, _, _, _, _>(
 				factory_state,
+				scenario,
+				cli_args.transactions_per_block,
+				cli_args.report_file.as_ref().map(PathBuf::as_path),
 				service_builder.client(),
 				service_builder.select_chain()
 					.expect("The select_chain is always initialized by new_full_start!; QED")
@@ -163,6 +779,30 @@ pub fn run<I, T, E>(args: I, exit: E, version: sc_cli::VersionInfo) -> error::Re
 
 			Ok(())
 		}
+		ParseAndPrepare::CustomCommand(CustomSubcommands::Decode(cli_args)) => cli_args.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::ForkOff(cli_args)) => cli_args.run(load_spec),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::Testnet(cli_args)) => cli_args.run(exit),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::Grandpa(cli_args)) => cli_args.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::Key(cli_args)) => cli_args.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::StorageReport(cli_args)) => {
+			let mut config: Config<_, _> = sc_cli::create_config_with_db_path(
+				load_spec,
+				&cli_args.shared_params,
+				&version,
+			)?;
+
+			sc_cli::fill_import_params(&mut config, &cli_args.import_params, ServiceRoles::FULL)?;
+
+			let service_builder = new_full_start!(config).0;
+			cli_args.run(&*service_builder.client())
+		},
+		ParseAndPrepare::CustomCommand(CustomSubcommands::StateDiff(cli_args)) => cli_args.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::CheckMetadataCompat(cli_args)) => cli_args.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::ExportTypes(cli_args)) => cli_args.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::BuildGenesis(cli_args)) => cli_args.run(load_spec),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::Benchmark(cli_args)) => cli_args.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::RunChains(cli_args)) => cli_args.run(exit, &version),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::ImportJustifications(cli_args)) => cli_args.run(),
 	}
 }
 
@@ -177,7 +817,7 @@ where
 {
 	let (exit_send, exit) = oneshot::channel();
 
-	let informant = sc_cli::informant::build(&service);
+	let informant = join(sc_cli::informant::build(&service), sc_cli::systemd::build(&service)).map(drop);
 
 	let handle = runtime.spawn(select(exit, informant));
 
@@ -188,6 +828,7 @@ where
 	let exit = e.into_exit();
 	let service_res = runtime.block_on(select(service, exit));
 
+	sc_cli::systemd::notify_stopping();
 	let _ = exit_send.send(());
 
 	runtime.block_on(handle);
@@ -0,0 +1,88 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `decode` subcommand.
+
+use std::{fs, path::Path};
+
+use node_primitives::Hash;
+use sc_cli::error;
+use sc_rpc::state::StateClient;
+use substrate_frame_decode::{Decoder, TypeRegistry};
+
+use crate::cli::{DecodeCmd, DecodeTarget};
+use crate::rpc::{self, parse_hex};
+
+impl DecodeCmd {
+	/// Run the decode command, printing the decoded data as JSON.
+	pub fn run(&self) -> error::Result<()> {
+		let metadata = match &self.metadata {
+			Some(path) => read_metadata(path)?,
+			None => {
+				let at = match &self.at {
+					Some(at) => Some(rpc::parse_hash(at)?),
+					None => None,
+				};
+				rpc::request(&self.url, move |client: StateClient<Hash>| client.metadata(at))?.0
+			},
+		};
+
+		let mut registry = TypeRegistry::with_defaults();
+		if let Some(path) = &self.types {
+			registry.extend_from_json(&fs::read_to_string(path)?).map_err(decode_error)?;
+		}
+		let decoder = Decoder::from_bytes(&metadata, registry).map_err(decode_error)?;
+
+		let decoded = match &self.target {
+			DecodeTarget::Type { ty, data } => decoder.decode(ty, &parse_hex(data)?),
+			DecodeTarget::Storage { key, value } => {
+				let value = match value {
+					Some(value) => Some(parse_hex(value)?),
+					None => None,
+				};
+				decoder.decode_storage(&parse_hex(key)?, value.as_ref().map(|v| &v[..]))
+			},
+			DecodeTarget::Events { data } => decoder.decode_events(&parse_hex(data)?),
+			DecodeTarget::Extrinsic { data } => decoder.decode_extrinsic(&parse_hex(data)?),
+			DecodeTarget::Error { module, error } => decoder.module_error(*module, *error)
+				.map(|e| serde_json::json!({
+					"module": e.module,
+					"error": e.error,
+					"documentation": e.documentation,
+				})),
+		}.map_err(decode_error)?;
+
+		println!(
+			"{}",
+			serde_json::to_string_pretty(&decoded).expect("JSON values are always serializable; qed"),
+		);
+		Ok(())
+	}
+}
+
+/// Read SCALE encoded metadata from a file, either binary or hex encoded.
+pub(crate) fn read_metadata(path: &Path) -> error::Result<Vec<u8>> {
+	let metadata = fs::read(path)?;
+	// The metadata is accepted as returned by the RPC, too.
+	match std::str::from_utf8(&metadata) {
+		Ok(text) if text.trim().starts_with("0x") => parse_hex(text),
+		_ => Ok(metadata),
+	}
+}
+
+pub(crate) fn decode_error(e: substrate_frame_decode::Error) -> error::Error {
+	error::Error::Other(format!("{}", e))
+}
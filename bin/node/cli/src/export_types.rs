@@ -0,0 +1,58 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `export-types` subcommand.
+
+use std::fs;
+
+use node_primitives::Hash;
+use sc_cli::error;
+use sc_rpc::state::StateClient;
+use substrate_frame_decode::{export_schema, Decoder, TypeRegistry};
+
+use crate::cli::ExportTypesCmd;
+use crate::decode::{decode_error, read_metadata};
+use crate::rpc;
+
+impl ExportTypesCmd {
+	/// Export the schema of the runtime, writing it to `--out` or printing it.
+	pub fn run(&self) -> error::Result<()> {
+		let metadata = match &self.metadata {
+			Some(path) => read_metadata(path)?,
+			None => {
+				let at = match &self.at {
+					Some(at) => Some(rpc::parse_hash(at)?),
+					None => None,
+				};
+				rpc::request(&self.url, move |client: StateClient<Hash>| client.metadata(at))?.0
+			},
+		};
+
+		let mut registry = TypeRegistry::with_defaults();
+		if let Some(path) = &self.types {
+			registry.extend_from_json(&fs::read_to_string(path)?).map_err(decode_error)?;
+		}
+		let decoder = Decoder::from_bytes(&metadata, registry).map_err(decode_error)?;
+
+		let schema = serde_json::to_string_pretty(&export_schema(&decoder).map_err(decode_error)?)
+			.expect("JSON values are always serializable; qed");
+		match &self.out {
+			Some(path) => fs::write(path, schema)?,
+			None => println!("{}", schema),
+		}
+		Ok(())
+	}
+}
@@ -18,30 +18,93 @@
 //! using the cli to manufacture transactions and distribute them
 //! to accounts.
 
+use std::collections::HashMap;
+
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 
-use codec::{Encode, Decode};
+use codec::Decode;
+use serde_json::{json, Value};
 use sp_keyring::sr25519::Keyring;
+use frame_support::storage::StorageValue;
 use node_runtime::{
-	Call, CheckedExtrinsic, UncheckedExtrinsic, SignedExtra, BalancesCall, ExistentialDeposit,
-	MinimumPeriod
+	Runtime, block_time::{self, BlockTimeMs}, constants::time::MILLISECS_PER_BLOCK,
 };
-use node_primitives::Signature;
-use sp_core::{sr25519, crypto::Pair};
+use node_primitives::{AccountId, Hash, Index, Moment, Signature};
+use sp_core::{ecdsa, ed25519, hexdisplay::HexDisplay, sr25519, crypto::Pair};
 use sp_runtime::{
-	generic::Era, traits::{Block as BlockT, Header as HeaderT, SignedExtension, Verify, IdentifyAccount}
+	MultiSignature,
+	traits::{Block as BlockT, Header as HeaderT, Verify, IdentifyAccount, SignPayload},
+};
+use node_transaction_factory::{
+	AccountPool, Calls, ChainState, EncodedCall, RuntimeAdapter, SignatureScheme,
 };
-use node_transaction_factory::RuntimeAdapter;
 use node_transaction_factory::modes::Mode;
+use substrate_frame_decode::mortal_era;
 use sp_inherents::InherentData;
 use sp_timestamp;
-use sp_finality_tracker;
 
 type AccountPublic = <Signature as Verify>::Signer;
 
+/// The key of an account, of one of the signature schemes of the runtime.
+#[derive(Clone)]
+pub enum Secret {
+	Sr25519(sr25519::Pair),
+	Ed25519(ed25519::Pair),
+	Ecdsa(ecdsa::Pair),
+}
+
+impl Secret {
+	fn from_seed(scheme: SignatureScheme, seed: &[u8; 32]) -> Self {
+		match scheme {
+			SignatureScheme::Sr25519 => Secret::Sr25519(sr25519::Pair::from_seed(seed)),
+			SignatureScheme::Ed25519 => Secret::Ed25519(ed25519::Pair::from_seed(seed)),
+			SignatureScheme::Ecdsa => Secret::Ecdsa(ecdsa::Pair::from_seed(seed)),
+		}
+	}
+
+	fn signer(&self) -> AccountPublic {
+		match self {
+			Secret::Sr25519(pair) => pair.signer(),
+			Secret::Ed25519(pair) => pair.signer(),
+			Secret::Ecdsa(pair) => SignPayload::<Signature>::signer(pair),
+		}
+	}
+
+	/// Sign `message`, returning the signature as a value of the `Signature` type of the registry.
+	fn sign(&self, message: &[u8]) -> Value {
+		let signature: Signature = match self {
+			Secret::Sr25519(pair) => pair.sign_payload(message),
+			Secret::Ed25519(pair) => pair.sign_payload(message),
+			Secret::Ecdsa(pair) => SignPayload::<Signature>::sign_payload(pair, message),
+		};
+		match signature {
+			MultiSignature::Sr25519(signature) => json!({ "Sr25519": hex(signature.as_ref()) }),
+			MultiSignature::Ed25519(signature) => json!({ "Ed25519": hex(signature.as_ref()) }),
+			MultiSignature::Ecdsa(signature) => json!({ "Ecdsa": hex(signature.as_ref()) }),
+		}
+	}
+}
+
+/// A transaction to sign: its sender, call, signed extensions and their additional data, and the
+/// key of the sender. The signed extensions are values of the `ExtrinsicExtra` and
+/// `ExtrinsicAdditionalSigned` types of the registry.
+pub struct Payload {
+	sender: AccountId,
+	call: EncodedCall,
+	extra: Value,
+	additional_signed: Value,
+	key: Secret,
+}
+
 pub struct FactoryState<N> {
 	block_no: N,
+	/// The index of the next transaction of the senders.
+	nonces: HashMap<AccountId, Index>,
+	/// The phase of the transactions mortal from a block, cached for the last block.
+	phase: Option<(Hash, u64)>,
+	pool: AccountPool,
+	scheme: SignatureScheme,
 
 	mode: Mode,
 	start_number: u32,
@@ -53,27 +116,14 @@ pub struct FactoryState<N> {
 
 type Number = <<node_primitives::Block as BlockT>::Header as HeaderT>::Number;
 
-impl<Number> FactoryState<Number> {
-	fn build_extra(index: node_primitives::Index, phase: u64) -> node_runtime::SignedExtra {
-		(
-			frame_system::CheckVersion::new(),
-			frame_system::CheckGenesis::new(),
-			frame_system::CheckEra::from(Era::mortal(256, phase)),
-			frame_system::CheckNonce::from(index),
-			frame_system::CheckWeight::new(),
-			pallet_transaction_payment::ChargeTransactionPayment::from(0),
-			Default::default(),
-		)
-	}
-}
-
 impl RuntimeAdapter for FactoryState<Number> {
 	type AccountId = node_primitives::AccountId;
 	type Balance = node_primitives::Balance;
 	type Block = node_primitives::Block;
 	type Phase = sp_runtime::generic::Phase;
-	type Secret = sr25519::Pair;
+	type Secret = Secret;
 	type Index = node_primitives::Index;
+	type Payload = Payload;
 
 	type Number = Number;
 
@@ -81,6 +131,8 @@ impl RuntimeAdapter for FactoryState<Number> {
 		mode: Mode,
 		num: u64,
 		rounds: u64,
+		pool: AccountPool,
+		scheme: SignatureScheme,
 	) -> FactoryState<Self::Number> {
 		FactoryState {
 			mode,
@@ -89,6 +141,10 @@ impl RuntimeAdapter for FactoryState<Number> {
 			rounds: rounds as u32,
 			block_in_round: 0,
 			block_no: 0,
+			nonces: HashMap::new(),
+			phase: None,
+			pool,
+			scheme,
 			start_number: 0,
 		}
 	}
@@ -121,6 +177,14 @@ impl RuntimeAdapter for FactoryState<Number> {
 		&self.mode
 	}
 
+	fn pool(&mut self) -> &mut AccountPool {
+		&mut self.pool
+	}
+
+	fn signature_scheme(&self) -> SignatureScheme {
+		self.scheme
+	}
+
 	fn set_block_no(&mut self, val: Self::Number) {
 		self.block_no = val;
 	}
@@ -133,42 +197,62 @@ impl RuntimeAdapter for FactoryState<Number> {
 		self.round = val;
 	}
 
-	fn transfer_extrinsic(
-		&self,
+	fn signing_payload(
+		&mut self,
+		chain: &dyn ChainState<Self>,
 		sender: &Self::AccountId,
 		key: &Self::Secret,
-		destination: &Self::AccountId,
-		amount: &Self::Balance,
+		call: EncodedCall,
 		version: u32,
 		genesis_hash: &<Self::Block as BlockT>::Hash,
 		prior_block_hash: &<Self::Block as BlockT>::Hash,
-	) -> <Self::Block as BlockT>::Extrinsic {
-		let index = self.extract_index(&sender, prior_block_hash);
-		let phase = self.extract_phase(*prior_block_hash);
-		sign::<Self>(CheckedExtrinsic {
-			signed: Some((sender.clone(), Self::build_extra(index, phase))),
-			function: Call::Balances(
-				BalancesCall::transfer(
-					pallet_indices::address::Address::Id(destination.clone().into()),
-					(*amount).into()
-				)
-			)
-		}, key, (version, genesis_hash.clone(), prior_block_hash.clone(), (), (), (), ()))
-	}
-
-	fn inherent_extrinsics(&self) -> InherentData {
-		let timestamp = (self.block_no as u64 + 1) * MinimumPeriod::get();
+	) -> Result<Self::Payload, String> {
+		let index = self.extract_index(chain, &sender, prior_block_hash)?;
+		let phase = self.extract_phase(chain, *prior_block_hash)?;
+		Ok(Payload {
+			sender: sender.clone(),
+			call,
+			extra: json!({
+				"era": mortal_era(node_runtime::era_period(), phase),
+				"nonce": index,
+				"tip": 0,
+				"asset": null,
+			}),
+			additional_signed: json!({
+				"spec_version": version,
+				"genesis_hash": hex(genesis_hash.as_ref()),
+				"block_hash": hex(prior_block_hash.as_ref()),
+			}),
+			key: key.clone(),
+		})
+	}
+
+	fn sign(calls: &Calls, payload: Self::Payload) -> Result<<Self::Block as BlockT>::Extrinsic, String> {
+		let Payload { sender, call, extra, additional_signed, key } = payload;
+		let address = json!({ "Id": hex(sender.as_ref()) });
+		let extrinsic = calls.extrinsic(call)
+			.sign(&address, &extra, &additional_signed, |message| key.sign(message))
+			.map_err(|e| format!("Unable to sign the transaction of {}: {}", sender, e))?;
+		Decode::decode(&mut &extrinsic[..])
+			.map_err(|e| format!("Unable to decode the transaction of {}: {}", sender, e.what()))
+	}
+
+	fn inherent_extrinsics(
+		&self,
+		chain: &dyn ChainState<Self>,
+		parent: &<Self::Block as BlockT>::Hash,
+	) -> Result<InherentData, String> {
+		// the blocks are built as fast as possible, each one the minimum period after its parent.
+		let now_key = pallet_timestamp::Now::<Runtime>::hashed_key();
+		let now: Moment = read_storage(chain, &now_key, parent)?.unwrap_or_default();
+		let block_time = read_storage(chain, &BlockTimeMs::hashed_key(), parent)?
+			.unwrap_or(MILLISECS_PER_BLOCK);
+		let timestamp = now + block_time::minimum_period(block_time);
 
 		let mut inherent = InherentData::new();
 		inherent.put_data(sp_timestamp::INHERENT_IDENTIFIER, &timestamp)
 			.expect("Failed putting timestamp inherent");
-		inherent.put_data(sp_finality_tracker::INHERENT_IDENTIFIER, &self.block_no)
-			.expect("Failed putting finalized number inherent");
-		inherent
-	}
-
-	fn minimum_balance() -> Self::Balance {
-		ExistentialDeposit::get()
+		Ok(inherent)
 	}
 
 	fn master_account_id() -> Self::AccountId {
@@ -176,52 +260,60 @@ impl RuntimeAdapter for FactoryState<Number> {
 	}
 
 	fn master_account_secret() -> Self::Secret {
-		Keyring::Alice.pair()
+		Secret::Sr25519(Keyring::Alice.pair())
 	}
 
 	/// Generates a random `AccountId` from `seed`.
-	fn gen_random_account_id(seed: &Self::Number) -> Self::AccountId {
-		let pair: sr25519::Pair = sr25519::Pair::from_seed(&gen_seed_bytes(*seed));
-		AccountPublic::from(pair.public()).into_account()
+	fn gen_random_account_id(&self, seed: &Self::Number) -> Self::AccountId {
+		self.gen_random_account_secret(seed).signer().into_account()
 	}
 
 	/// Generates a random `Secret` from `seed`.
-	fn gen_random_account_secret(seed: &Self::Number) -> Self::Secret {
-		let pair: sr25519::Pair = sr25519::Pair::from_seed(&gen_seed_bytes(*seed));
-		pair
+	fn gen_random_account_secret(&self, seed: &Self::Number) -> Self::Secret {
+		Secret::from_seed(self.scheme, &gen_seed_bytes(*seed))
 	}
 
 	fn extract_index(
-		&self,
-		_account_id: &Self::AccountId,
-		_block_hash: &<Self::Block as BlockT>::Hash,
-	) -> Self::Index {
-		// TODO get correct index for account via api. See #2587.
-		// This currently prevents the factory from being used
-		// without a preceding purge of the database.
-		if self.mode == Mode::MasterToN || self.mode == Mode::MasterTo1 {
-			self.block_no() as Self::Index
-		} else {
-			match self.round() {
-				0 =>
-					// if round is 0 all transactions will be done with master as a sender
-					self.block_no() as Self::Index,
-				_ =>
-					// if round is e.g. 1 every sender account will be new and not yet have
-					// any transactions done
-					0
-			}
-		}
+		&mut self,
+		chain: &dyn ChainState<Self>,
+		account_id: &Self::AccountId,
+		block_hash: &<Self::Block as BlockT>::Hash,
+	) -> Result<Self::Index, String> {
+		let index = match self.nonces.get(account_id) {
+			Some(index) => *index,
+			None => chain.account_nonce(account_id, block_hash)?,
+		};
+		self.nonces.insert(account_id.clone(), index + 1);
+		Ok(index)
 	}
 
 	fn extract_phase(
-		&self,
-		_block_hash: <Self::Block as BlockT>::Hash
-	) -> Self::Phase {
-		// TODO get correct phase via api. See #2587.
-		// This currently prevents the factory from being used
-		// without a preceding purge of the database.
-		self.block_no() as Self::Phase
+		&mut self,
+		chain: &dyn ChainState<Self>,
+		block_hash: <Self::Block as BlockT>::Hash
+	) -> Result<Self::Phase, String> {
+		match self.phase {
+			Some((hash, phase)) if hash == block_hash => Ok(phase),
+			_ => {
+				let phase = chain.block_number(&block_hash)?;
+				self.phase = Some((block_hash, phase));
+				Ok(phase)
+			},
+		}
+	}
+}
+
+/// Read and decode the storage entry `key` of the block `at`.
+fn read_storage<T: Decode>(
+	chain: &dyn ChainState<FactoryState<Number>>,
+	key: &[u8],
+	at: &Hash,
+) -> Result<Option<T>, String> {
+	match chain.storage(key, at)? {
+		Some(value) => T::decode(&mut &value[..]).map(Some).map_err(|e| {
+			format!("Unable to decode the storage entry 0x{}: {:?}", HexDisplay::from(&key), e)
+		}),
+		None => Ok(None),
 	}
 }
 
@@ -235,34 +327,113 @@ fn gen_seed_bytes(seed: u32) -> [u8; 32] {
 	seed_bytes
 }
 
-/// Creates an `UncheckedExtrinsic` containing the appropriate signature for
-/// a `CheckedExtrinsics`.
-fn sign<RA: RuntimeAdapter>(
-	xt: CheckedExtrinsic,
-	key: &sr25519::Pair,
-	additional_signed: <SignedExtra as SignedExtension>::AdditionalSigned,
-) -> <RA::Block as BlockT>::Extrinsic {
-	let s = match xt.signed {
-		Some((signed, extra)) => {
-			let payload = (xt.function, extra.clone(), additional_signed);
-			let signature = payload.using_encoded(|b| {
-				if b.len() > 256 {
-					key.sign(&sp_io::hashing::blake2_256(b))
-				} else {
-					key.sign(b)
-				}
-			}).into();
-			UncheckedExtrinsic {
-				signature: Some((pallet_indices::address::Address::Id(signed), signature, extra)),
-				function: payload.0,
-			}
+/// `data` in hex, as the registry expects byte arrays.
+fn hex(data: &[u8]) -> String {
+	format!("0x{}", HexDisplay::from(&data))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use node_primitives::Balance;
+	use node_runtime::{Address, BalancesCall, Call, ExistentialDeposit, SignedPayload, UncheckedExtrinsic};
+	use node_transaction_factory::{Scenario, SenderSelection, Variables};
+
+	/// Chain state of a single block, with the given storage.
+	struct Storage(HashMap<Vec<u8>, Vec<u8>>);
+
+	impl ChainState<FactoryState<Number>> for Storage {
+		fn account_nonce(&self, _: &AccountId, _: &Hash) -> Result<Index, String> {
+			Ok(0)
+		}
+
+		fn block_number(&self, _: &Hash) -> Result<u64, String> {
+			Ok(1)
+		}
+
+		fn storage(&self, key: &[u8], _: &Hash) -> Result<Option<Vec<u8>>, String> {
+			Ok(self.0.get(key).cloned())
 		}
-		None => UncheckedExtrinsic {
-			signature: None,
-			function: xt.function,
-		},
-	};
-
-	let e = Encode::encode(&s);
-	Decode::decode(&mut &e[..]).expect("Failed to decode signed unchecked extrinsic")
+	}
+
+	fn factory_state(scheme: SignatureScheme) -> FactoryState<Number> {
+		FactoryState::new(
+			Mode::MasterToN,
+			1,
+			1,
+			AccountPool::new(0, SenderSelection::RoundRobin),
+			scheme,
+		)
+	}
+
+	fn timestamp(chain: &Storage) -> Moment {
+		let state = factory_state(SignatureScheme::Sr25519);
+		state.inherent_extrinsics(chain, &Default::default()).unwrap()
+			.get_data(&sp_timestamp::INHERENT_IDENTIFIER).unwrap().unwrap()
+	}
+
+	#[test]
+	fn timestamp_is_the_minimum_period_after_the_parent() {
+		let now: Moment = 1_600_000_000_000;
+		let mut storage = HashMap::new();
+		storage.insert(pallet_timestamp::Now::<Runtime>::hashed_key().to_vec(), now.encode());
+		assert_eq!(timestamp(&Storage(storage.clone())), now + MILLISECS_PER_BLOCK / 2);
+
+		// the block time of the chain is read from its state.
+		storage.insert(BlockTimeMs::hashed_key().to_vec(), 6000u64.encode());
+		assert_eq!(timestamp(&Storage(storage)), now + 3000);
+	}
+
+	#[test]
+	fn minimum_balance_is_read_from_the_metadata() {
+		let calls = Calls::new(Scenario::preset("transfer").unwrap(), &Runtime::metadata().encode()).unwrap();
+		assert_eq!(calls.minimum_balance::<Balance>().unwrap(), ExistentialDeposit::get());
+	}
+
+	#[test]
+	fn transactions_are_signed_as_extrinsics_of_the_runtime() {
+		let calls = Calls::new(Scenario::preset("transfer").unwrap(), &Runtime::metadata().encode()).unwrap();
+		let chain = Storage(HashMap::new());
+		let genesis_hash = Hash::repeat_byte(1);
+		let block_hash = Hash::repeat_byte(2);
+		let destination = Keyring::Bob.to_account_id();
+
+		for scheme in vec![SignatureScheme::Sr25519, SignatureScheme::Ed25519, SignatureScheme::Ecdsa] {
+			let mut state = factory_state(scheme);
+			let sender = state.gen_random_account_id(&1);
+			let variables = Variables {
+				sender: hex(sender.as_ref()),
+				destination: hex(destination.as_ref()),
+				amount: "1000".into(),
+				block_number: 0,
+			};
+			let call = calls.encode(0, &variables).unwrap();
+			let key = state.gen_random_account_secret(&1);
+			let payload = state.signing_payload(
+				&chain,
+				&sender,
+				&key,
+				call,
+				236,
+				&genesis_hash,
+				&block_hash,
+			).unwrap();
+
+			let extrinsic = FactoryState::sign(&calls, payload).unwrap();
+			let extrinsic = UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).unwrap();
+			let (address, signature, extra) = extrinsic.signature.unwrap();
+			assert_eq!(address, Address::Id(sender.clone()));
+			assert_eq!(
+				extrinsic.function,
+				Call::Balances(BalancesCall::transfer(Address::Id(destination.clone()), 1000)),
+			);
+			let payload = SignedPayload::from_raw(
+				extrinsic.function,
+				extra,
+				(236, genesis_hash, block_hash, (), (), (), ()),
+			);
+			assert!(payload.using_encoded(|payload| signature.verify(payload, &sender)));
+		}
+	}
 }
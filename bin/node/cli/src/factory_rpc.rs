@@ -0,0 +1,286 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Submission of the transactions of the factory to live nodes over RPC.
+
+use std::{sync::mpsc as std_mpsc, thread, time::{Duration, Instant}};
+
+use codec::{Decode, Encode};
+use hyper::rt;
+use jsonrpc_core::futures::{future, sync::mpsc, Future, Sink, Stream};
+use jsonrpc_core_client::transports::http;
+use log::{info, warn};
+use node_primitives::{AccountId, Block, BlockNumber, Hash, Header, Index};
+use node_transaction_factory::{AccountPool, Calls, ChainState, Mode, RuntimeAdapter, Scenario};
+use sc_cli::error;
+use sc_rpc::{author::AuthorClient, chain::ChainClient, state::StateClient};
+use sp_core::{Bytes, storage::StorageKey};
+use sp_rpc::{list::ListOrValue, number::NumberOrHex};
+use sp_runtime::{generic::SignedBlock, traits::Header as HeaderT};
+use substrate_frame_rpc_system::SystemClient;
+
+use crate::cli::FactoryCmd;
+use crate::factory_impl::FactoryState;
+use crate::rpc;
+
+/// Interval at which the block the transactions are mortal from is moved to the best block, so
+/// that long runs don't sign transactions whose era is over.
+const BIRTH_REFRESH: Duration = Duration::from_secs(30);
+
+/// Interval at which the best block is checked for the funding of the pool.
+const FUNDING_POLL: Duration = Duration::from_secs(1);
+
+/// How long to wait for the funding of the pool to be included before giving up.
+const FUNDING_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Maximum number of transactions signed at once, in parallel. With a rate, at most a second's
+/// worth of transactions are signed at once, so that they aren't signed long before they're
+/// submitted.
+const SIGNING_BATCH: u64 = 1024;
+
+type Client = ChainClient<BlockNumber, Hash, Header, SignedBlock<Block>>;
+
+/// The state of the chain of the node at the given url, read over RPC.
+struct RpcChainState<'a>(&'a str);
+
+impl<'a> ChainState<FactoryState<BlockNumber>> for RpcChainState<'a> {
+	/// The next index of `account_id` known to the node, including its transactions in the pool
+	/// of the node.
+	fn account_nonce(&self, account_id: &AccountId, _at: &Hash) -> Result<Index, String> {
+		let account_id = account_id.clone();
+		rpc::request(self.0, move |client: SystemClient<Hash, AccountId, Index>| client.nonce(account_id))
+			.map_err(|e| e.to_string())
+	}
+
+	fn block_number(&self, at: &Hash) -> Result<u64, String> {
+		let at = *at;
+		match rpc::request(self.0, move |client: Client| client.header(Some(at))) {
+			Ok(Some(header)) => Ok(*header.number() as u64),
+			Ok(None) => Err(format!("Unknown block {}", at)),
+			Err(e) => Err(e.to_string()),
+		}
+	}
+
+	fn storage(&self, key: &[u8], at: &Hash) -> Result<Option<Vec<u8>>, String> {
+		let (key, at) = (StorageKey(key.to_vec()), *at);
+		rpc::request(self.0, move |client: StateClient<Hash>| client.storage(key, Some(at)))
+			.map(|value| value.map(|value| value.0))
+			.map_err(|e| e.to_string())
+	}
+}
+
+impl FactoryCmd {
+	/// Sign the transactions of the factory and submit them to the nodes at `rpc_urls`, in turn.
+	pub fn submit_rpc(&self) -> error::Result<()> {
+		if self.mode == Mode::MasterToNToM {
+			return Err(error::Error::Input(
+				"The MasterToNToM mode can't be submitted over RPC".into()
+			));
+		}
+		if self.concurrency == 0 {
+			return Err(error::Error::Input("The concurrency must be at least 1".into()));
+		}
+
+		let mut factory_state = FactoryState::new(
+			self.mode.clone(),
+			self.num,
+			self.rounds,
+			AccountPool::new(self.pool_size, self.sender_selection),
+			self.signature_scheme,
+		);
+		node_transaction_factory::check_rounds(&factory_state)?;
+		node_transaction_factory::check_signature_scheme(&self.mode, self.signature_scheme)?;
+
+		let url = &self.rpc_urls[0];
+		let genesis_hash = match rpc::request(url, |client: Client| {
+			client.block_hash(Some(ListOrValue::Value(NumberOrHex::Number(0))))
+		})? {
+			ListOrValue::Value(Some(hash)) => hash,
+			_ => return Err(error::Error::Other("The node has no genesis block".into())),
+		};
+		let version = rpc::request(url, |client: StateClient<Hash>| client.runtime_version(None))?
+			.spec_version;
+		let metadata = rpc::request(url, |client: StateClient<Hash>| client.metadata(None))?;
+		let calls = Calls::new(Scenario::load(&self.scenario)?, &metadata)?;
+		let chain = RpcChainState(url);
+		let mut birth_hash = best_block(url)?;
+		let mut birth_refreshed = Instant::now();
+
+		// in mode `Pool`, the accounts of the pool only send once their funding is included.
+		let master = FactoryState::<BlockNumber>::master_account_id();
+		let mut funding = match self.mode {
+			Mode::Pool if self.pool_size > 0 => {
+				let nonce = chain.account_nonce(&master, &birth_hash)?;
+				Some((self.pool_size, nonce + self.pool_size as Index))
+			},
+			_ => None,
+		};
+
+		let (extrinsics, submitted) = mpsc::channel(self.concurrency);
+		let (urls, concurrency) = (self.rpc_urls.clone(), self.concurrency);
+		let submitter = thread::spawn(move || submit(urls, submitted, concurrency));
+
+		let started = Instant::now();
+		let mut extrinsics = extrinsics.wait();
+		let batch = match self.rate {
+			0 => SIGNING_BATCH,
+			rate => rate.min(SIGNING_BATCH),
+		};
+		let mut count = 0;
+		let mut signed = Vec::new().into_iter();
+		loop {
+			let extrinsic = match signed.next() {
+				Some(extrinsic) => extrinsic,
+				None => {
+					// in mode `Pool`, the batch ends with the funding of the pool.
+					let batch = match funding {
+						Some((transactions, _)) => batch.min(transactions - count),
+						None => batch,
+					};
+					signed = node_transaction_factory::next_extrinsics(
+						&mut factory_state,
+						&chain,
+						&calls,
+						version,
+						genesis_hash,
+						birth_hash,
+						batch as usize,
+					)?.into_iter();
+					match signed.next() {
+						Some(extrinsic) => extrinsic,
+						None => break,
+					}
+				},
+			};
+			if self.rate > 0 {
+				let due = started + Duration::from_micros(count * 1_000_000 / self.rate);
+				let now = Instant::now();
+				if due > now {
+					thread::sleep(due - now);
+				}
+			}
+			// the submitter stopped, its error is reported below.
+			if extrinsics.send(extrinsic.encode().into()).is_err() {
+				break;
+			}
+			count += 1;
+
+			if let Some((transactions, nonce)) = funding {
+				if count == transactions {
+					wait_for_nonce(url, &master, nonce)?;
+					birth_hash = best_block(url)?;
+					birth_refreshed = Instant::now();
+					funding = None;
+				}
+			}
+
+			if birth_refreshed.elapsed() > BIRTH_REFRESH {
+				birth_hash = best_block(url)?;
+				birth_refreshed = Instant::now();
+			}
+		}
+		drop(extrinsics);
+
+		let (accepted, rejected) = submitter.join()
+			.map_err(|_| error::Error::Other("The submission of the transactions panicked".into()))?
+			.map_err(|e| error::Error::Other(format!("Unable to submit the transactions: {}", e)))?;
+		let elapsed = started.elapsed();
+		info!(
+			"Submitted {} transactions in {:.1}s, {:.1} per second. {} accepted, {} rejected.",
+			accepted + rejected,
+			elapsed.as_secs_f64(),
+			(accepted + rejected) as f64 / elapsed.as_secs_f64(),
+			accepted,
+			rejected,
+		);
+		Ok(())
+	}
+}
+
+/// The hash of the best block of the node at `url`.
+fn best_block(url: &str) -> error::Result<Hash> {
+	let header = rpc::request(url, |client: Client| client.header(None))?
+		.ok_or_else(|| error::Error::Other("The node has no best block".into()))?;
+	Ok(header.hash())
+}
+
+/// Wait until the best block of the node at `url` includes the transactions of `account_id` up
+/// to the index `nonce`.
+fn wait_for_nonce(url: &str, account_id: &AccountId, nonce: Index) -> error::Result<()> {
+	let started = Instant::now();
+	loop {
+		let best = best_block(url)?;
+		let data = account_id.encode();
+		let included = rpc::request(url, move |client: StateClient<Hash>| {
+			client.call("AccountNonceApi_account_nonce".into(), data.into(), Some(best))
+		})?;
+		let included = Index::decode(&mut &included[..])
+			.map_err(|e| error::Error::Other(format!("Invalid account nonce: {:?}", e)))?;
+		if included >= nonce {
+			info!("The funding of the pool is included in block {}.", best);
+			return Ok(());
+		}
+		if started.elapsed() > FUNDING_TIMEOUT {
+			return Err(error::Error::Other(format!(
+				"The funding of the pool was not included after {}s, {} transactions left",
+				FUNDING_TIMEOUT.as_secs(),
+				nonce - included,
+			)));
+		}
+		thread::sleep(FUNDING_POLL);
+	}
+}
+
+/// Submit the `extrinsics` to the nodes at `urls` in turn, with at most `concurrency` submissions
+/// awaiting an answer. Returns the number of accepted and rejected extrinsics.
+fn submit(
+	urls: Vec<String>,
+	extrinsics: mpsc::Receiver<Bytes>,
+	concurrency: usize,
+) -> Result<(u64, u64), String> {
+	let (tx, rx) = std_mpsc::channel();
+
+	rt::run(
+		future::join_all(urls.iter().map(|url| http::connect(url)).collect::<Vec<_>>())
+			.map_err(|e| format!("{:?}", e))
+			.and_then(move |clients: Vec<AuthorClient<Hash, Hash>>| {
+				let mut next = 0;
+				extrinsics
+					.map_err(|()| "The transactions channel failed".to_string())
+					.map(move |extrinsic| {
+						let client = &clients[next % clients.len()];
+						next += 1;
+						client.submit_extrinsic(extrinsic).then(Ok::<_, String>)
+					})
+					.buffer_unordered(concurrency)
+					.fold((0, 0), |(accepted, rejected), result| {
+						Ok::<_, String>(match result {
+							Ok(_) => (accepted + 1, rejected),
+							Err(e) => {
+								warn!("Transaction rejected: {:?}", e);
+								(accepted, rejected + 1)
+							},
+						})
+					})
+			})
+			.then(move |result| {
+				let _ = tx.send(result);
+				Ok(())
+			})
+	);
+
+	rx.recv().map_err(|_| "The submission did not complete".to_string())?
+}
@@ -0,0 +1,202 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `fork-off` subcommand.
+
+use std::{collections::HashMap, fs};
+
+use jsonrpc_core::futures::{stream, Future, Stream};
+use jsonrpc_core_client::RpcError;
+use node_primitives::{AccountId, Block, BlockNumber, Hash, Header};
+use sc_cli::error;
+use sc_rpc::{chain::ChainClient, state::StateClient};
+use sp_core::{
+	crypto::Ss58Codec, hashing::twox_128, storage::Storage,
+};
+use sp_runtime::{BuildStorage, generic::SignedBlock};
+use codec::Encode;
+
+use crate::cli::ForkOffCmd;
+use crate::rpc::{self, parse_hex};
+
+/// Number of storage keys downloaded per request.
+const KEYS_PAGE_SIZE: u32 = 1000;
+/// Maximum number of storage values requested at once.
+const MAX_CONCURRENT_REQUESTS: usize = 16;
+
+/// Storage prefixes of the modules holding the validator set.
+const VALIDATOR_MODULES: &[&str] = &[
+	"Session", "Babe", "GrandpaFinality", "Staking", "ImOnline", "AuthorityDiscovery",
+];
+
+/// The storage key of a storage value.
+fn storage_value_key(module: &str, item: &str) -> Vec<u8> {
+	let mut key = twox_128(module.as_bytes()).to_vec();
+	key.extend_from_slice(&twox_128(item.as_bytes()));
+	key
+}
+
+/// Parse an account given either as SS58 address or as hex encoded public key.
+fn parse_account(account: &str) -> error::Result<AccountId> {
+	if let Ok(account) = AccountId::from_ss58check(account) {
+		return Ok(account)
+	}
+	let raw = parse_hex(account)?;
+	if raw.len() != 32 {
+		return Err(error::Error::Input(format!("Invalid account: {}", account)))
+	}
+	let mut id = [0u8; 32];
+	id.copy_from_slice(&raw);
+	Ok(id.into())
+}
+
+/// Read a state dump, either a JSON object from hex keys to hex values or a raw chain spec.
+fn read_state(path: &std::path::Path) -> error::Result<HashMap<Vec<u8>, Vec<u8>>> {
+	let json: serde_json::Value = serde_json::from_slice(&fs::read(path)?)
+		.map_err(|e| error::Error::Input(format!("Invalid state file: {}", e)))?;
+	let top = json.pointer("/genesis/raw/top").unwrap_or(&json);
+	let top = top.as_object()
+		.ok_or_else(|| error::Error::Input("Expected a JSON object of storage keys".into()))?;
+
+	top.iter()
+		.map(|(key, value)| {
+			let value = value.as_str()
+				.ok_or_else(|| error::Error::Input(format!("Invalid value for key {}", key)))?;
+			Ok((parse_hex(key)?, parse_hex(value)?))
+		})
+		.collect()
+}
+
+/// Download the whole state at the given block.
+fn download_state(url: &str, at: Option<Hash>) -> error::Result<HashMap<Vec<u8>, Vec<u8>>> {
+	let at = match at {
+		Some(at) => at,
+		None => rpc::request(url, |client: ChainClient<BlockNumber, Hash, Header, SignedBlock<Block>>| {
+			client.finalized_head()
+		})?,
+	};
+	println!("Downloading state at {}", at);
+
+	let mut state = HashMap::new();
+	let mut start_key = None;
+	loop {
+		let keys = rpc::request(url, move |client: StateClient<Hash>| {
+			client.storage_keys_paged(None, KEYS_PAGE_SIZE, start_key, Some(at))
+		})?;
+		// A page may be shorter than requested, only an empty one ends the state.
+		let last_key = match keys.last() {
+			Some(key) => key.clone(),
+			None => break,
+		};
+
+		let values = rpc::request(url, move |client: StateClient<Hash>| {
+			stream::iter_ok::<_, RpcError>(keys)
+				.map(move |key| client.storage(key.clone(), Some(at)).map(|value| (key, value)))
+				.buffered(MAX_CONCURRENT_REQUESTS)
+				.collect()
+		})?;
+		state.extend(values.into_iter().filter_map(|(key, value)| value.map(|value| (key.0, value.0))));
+		println!("Downloaded {} storage items", state.len());
+
+		start_key = Some(last_key);
+	}
+
+	Ok(state)
+}
+
+impl ForkOffCmd {
+	/// Run the fork-off command, writing the raw chain spec to `--out`.
+	pub fn run<F>(&self, spec_factory: F) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Option<crate::chain_spec::ChainSpec>, String>,
+	{
+		let mut spec = sc_cli::load_spec(&self.shared_params, spec_factory)?;
+
+		let mut state = match &self.state {
+			Some(path) => read_state(path)?,
+			None => {
+				let at = match &self.at {
+					Some(at) => Some(rpc::parse_hash(at)?),
+					None => None,
+				};
+				download_state(&self.url, at)?
+			},
+		};
+
+		if self.reset_validators {
+			let prefixes = VALIDATOR_MODULES.iter()
+				.map(|module| twox_128(module.as_bytes()).to_vec())
+				.chain(std::iter::once(grandpa_primitives::GRANDPA_AUTHORITIES_KEY.to_vec()))
+				.collect::<Vec<_>>();
+			state.retain(|key, _| !prefixes.iter().any(|prefix| key.starts_with(prefix)));
+
+			let base = spec.build_storage()?;
+			state.extend(
+				base.top.into_iter()
+					.filter(|(key, _)| prefixes.iter().any(|prefix| key.starts_with(prefix)))
+			);
+		}
+
+		for prefix in &self.remove_prefix {
+			let prefix = parse_hex(prefix)?;
+			state.retain(|key, _| !key.starts_with(&prefix));
+		}
+
+		for set in &self.set {
+			let mut parts = set.splitn(2, '=');
+			let key = parse_hex(parts.next().unwrap_or_default())?;
+			let value = parts.next()
+				.ok_or_else(|| error::Error::Input(format!("Expected `KEY=VALUE`, got `{}`", set)))?;
+			state.insert(key, parse_hex(value)?);
+		}
+
+		if let Some(sudo) = &self.sudo {
+			state.insert(storage_value_key("Sudo", "Key"), parse_account(sudo)?.encode());
+		}
+
+		spec.set_storage(Storage {
+			top: state.into_iter().collect(),
+			children: Default::default(),
+		});
+		let json = spec.to_json(true)?;
+		fs::write(&self.out, json)?;
+		println!("Raw chain spec written to {}", self.out.display());
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_accounts() {
+		let alice = sp_keyring::AccountKeyring::Alice.to_account_id();
+		assert_eq!(parse_account(&alice.to_ss58check()).unwrap(), alice);
+		assert_eq!(parse_account(&format!("0x{}", hex::encode(&alice))).unwrap(), alice);
+		assert!(parse_account("0x1234").is_err());
+	}
+
+	#[test]
+	fn reads_state_from_raw_chain_spec() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("spec.json");
+		fs::write(&path, r#"{ "genesis": { "raw": { "top": { "0x01": "0x0203" }, "children": {} } } }"#).unwrap();
+
+		let state = read_state(&path).unwrap();
+		assert_eq!(state.into_iter().collect::<Vec<_>>(), vec![(vec![1], vec![2, 3])]);
+	}
+}
@@ -0,0 +1,130 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `grandpa` subcommand.
+
+use codec::{Decode, Encode};
+use grandpa_primitives::AuthorityPair;
+use node_primitives::BlockNumber;
+use node_runtime::{Call, GrandpaCall, SudoCall};
+use sc_cli::error;
+use sp_core::Pair;
+
+use crate::cli::{GrandpaCmd, NoteStalledCmd, SplitKeyCmd};
+use crate::rpc::parse_hex;
+
+/// Delays shorter than this are rejected when verifying, the signaling block could be reorged.
+const MIN_DELAY: BlockNumber = 100;
+
+impl GrandpaCmd {
+	/// Run the grandpa command.
+	pub fn run(&self) -> error::Result<()> {
+		match self {
+			GrandpaCmd::NoteStalled(cmd) => cmd.run(),
+			GrandpaCmd::SplitKey(cmd) => cmd.run(),
+		}
+	}
+}
+
+impl NoteStalledCmd {
+	/// Print the hex encoded call, or the parameters of the verified call.
+	pub fn run(&self) -> error::Result<()> {
+		match &self.verify {
+			Some(call) => {
+				let (delay, best_finalized, sudo) = verify(&parse_hex(call)?)?;
+				println!(
+					"Forced change enacted {} blocks after the next session start, \
+					new voters start from block #{}{}.",
+					delay,
+					best_finalized,
+					if sudo { ", dispatched through sudo" } else { "" },
+				);
+			},
+			None => {
+				let best_finalized = self.best_finalized
+					.expect("`best-finalized` is required unless `verify` is given; qed");
+				let call = craft(self.delay, best_finalized, self.sudo);
+				println!("0x{}", hex::encode(call.encode()));
+			},
+		}
+		Ok(())
+	}
+}
+
+impl SplitKeyCmd {
+	/// Print the public key and the hex encoded shares of the key.
+	pub fn run(&self) -> error::Result<()> {
+		let pair = AuthorityPair::from_string(&self.suri, None)
+			.map_err(|_| error::Error::Input("Invalid secret URI".into()))?;
+		let shares = grandpa::threshold::split_key(&pair, self.threshold, self.shares)
+			.map_err(error::Error::Input)?;
+
+		println!("Public key: 0x{}", hex::encode(pair.public()));
+		for share in shares {
+			println!("Share {}: {}", share.index, share.to_hex());
+		}
+		Ok(())
+	}
+}
+
+/// The call noting the stall, wrapped in `Sudo::sudo` if requested.
+fn craft(delay: BlockNumber, best_finalized: BlockNumber, sudo: bool) -> Call {
+	let call = Call::Grandpa(GrandpaCall::note_stalled(delay, best_finalized));
+	if sudo {
+		Call::Sudo(SudoCall::sudo(Box::new(call)))
+	} else {
+		call
+	}
+}
+
+/// Check that `call` notes a stall, returning the delay, the best finalized block and whether
+/// the call is wrapped in `Sudo::sudo`.
+fn verify(mut call: &[u8]) -> error::Result<(BlockNumber, BlockNumber, bool)> {
+	let call = Call::decode(&mut call)
+		.map_err(|e| error::Error::Input(format!("Invalid call: {}", e.what())))?;
+	let (call, sudo) = match call {
+		Call::Sudo(SudoCall::sudo(call)) => (*call, true),
+		call => (call, false),
+	};
+
+	match call {
+		Call::Grandpa(GrandpaCall::note_stalled(delay, best_finalized)) => {
+			if delay < MIN_DELAY {
+				return Err(error::Error::Input(format!(
+					"Delay of {} blocks is too short, the forced change should be delayed by at \
+					least {} blocks",
+					delay,
+					MIN_DELAY,
+				)));
+			}
+			Ok((delay, best_finalized, sudo))
+		},
+		call => Err(error::Error::Input(format!("Not a `Grandpa::note_stalled` call: {:?}", call))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn verifies_crafted_calls() {
+		assert_eq!(verify(&craft(1000, 42, false).encode()).unwrap(), (1000, 42, false));
+		assert_eq!(verify(&craft(1000, 42, true).encode()).unwrap(), (1000, 42, true));
+		assert!(verify(&craft(10, 42, false).encode()).is_err());
+		assert!(verify(&Call::Grandpa(GrandpaCall::report_misbehavior(vec![])).encode()).is_err());
+	}
+}
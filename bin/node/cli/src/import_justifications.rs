@@ -0,0 +1,120 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `import-justifications` subcommand.
+
+use std::fs;
+
+use node_primitives::{BlockNumber, Hash};
+use sc_cli::error;
+use sc_finality_grandpa_rpc::GrandpaClient;
+use serde::Deserialize;
+
+use crate::cli::ImportJustificationsCmd;
+use crate::rpc::{self, parse_hash, parse_hex};
+
+/// A justification listed in the file.
+#[derive(Deserialize)]
+struct Entry {
+	hash: String,
+	justification: String,
+}
+
+impl ImportJustificationsCmd {
+	/// Submit the justifications of the file in order, stopping at the first one rejected since
+	/// the following ones may be signed by an authority set it enacts.
+	pub fn run(&self) -> error::Result<()> {
+		let entries: Vec<Entry> = serde_json::from_slice(&fs::read(&self.file)?)
+			.map_err(|e| error::Error::Input(format!("Invalid justifications file: {}", e)))?;
+
+		let total = entries.len();
+		for (imported, entry) in entries.into_iter().enumerate() {
+			let hash = parse_hash(&entry.hash)?;
+			let justification = parse_hex(&entry.justification)?;
+
+			rpc::request(&self.url, move |client: GrandpaClient<Hash, BlockNumber>|
+				client.submit_justification(hash, justification.into())
+			).map_err(|e| error::Error::Other(format!(
+				"Justification of block {} not imported, {} of {} imported: {}",
+				hash,
+				imported,
+				total,
+				e,
+			)))?;
+			println!("Imported the justification of block {}", hash);
+		}
+
+		println!("{} justifications imported", total);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{io::Write, sync::{Arc, Mutex}};
+	use jsonrpc_core::{Error, ErrorCode, IoHandler, Params, Value};
+	use sp_core::Bytes;
+
+	#[test]
+	fn stops_at_the_first_rejected_justification() {
+		let submitted = Arc::new(Mutex::new(Vec::new()));
+		let mut io = IoHandler::new();
+		let recorded = submitted.clone();
+		io.add_method("grandpa_submitJustification", move |params: Params| {
+			let (hash, justification): (Hash, Bytes) = params.parse()?;
+			recorded.lock().unwrap().push(hash);
+			if justification.0 == vec![2] {
+				Err(Error { code: ErrorCode::ServerError(3), message: "Invalid".into(), data: None })
+			} else {
+				Ok(Value::Null)
+			}
+		});
+		let server = jsonrpc_http_server::ServerBuilder::new(io)
+			.start_http(&"127.0.0.1:0".parse().unwrap())
+			.unwrap();
+
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		file.write_all(br#"[
+			{ "hash": "0x0101010101010101010101010101010101010101010101010101010101010101", "justification": "0x01" },
+			{ "hash": "0x0202020202020202020202020202020202020202020202020202020202020202", "justification": "0x02" },
+			{ "hash": "0x0303030303030303030303030303030303030303030303030303030303030303", "justification": "0x03" }
+		]"#).unwrap();
+
+		let cmd = ImportJustificationsCmd {
+			file: file.path().into(),
+			url: format!("http://{}", server.address()),
+		};
+		assert!(cmd.run().is_err());
+		assert_eq!(*submitted.lock().unwrap(), vec![Hash::repeat_byte(1), Hash::repeat_byte(2)]);
+		server.close();
+	}
+
+	#[test]
+	fn rejects_invalid_file() {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		file.write_all(br#"[{ "hash": "0x01" }]"#).unwrap();
+
+		let cmd = ImportJustificationsCmd {
+			file: file.path().into(),
+			url: "http://127.0.0.1:1".into(),
+		};
+		match cmd.run() {
+			Err(error::Error::Input(_)) => {},
+			res => panic!("Unexpected result {:?}", res),
+		}
+	}
+}
@@ -0,0 +1,56 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `key` subcommand.
+
+use sc_cli::error;
+use sc_network::config::{NodeKeyConfig, Secret};
+
+use crate::cli::{KeyCmd, NodeKeyFileCmd};
+
+impl KeyCmd {
+	/// Run the key command.
+	pub fn run(&self) -> error::Result<()> {
+		match self {
+			KeyCmd::GenerateNodeKey(cmd) => cmd.generate(),
+			KeyCmd::InspectNodeKey(cmd) => cmd.inspect(),
+		}
+	}
+}
+
+impl NodeKeyFileCmd {
+	fn generate(&self) -> error::Result<()> {
+		if self.file.exists() {
+			return Err(error::Error::Input(format!("{} already exists", self.file.display())));
+		}
+		self.print_peer_id()
+	}
+
+	fn inspect(&self) -> error::Result<()> {
+		if !self.file.exists() {
+			return Err(error::Error::Input(format!("{} doesn't exist", self.file.display())));
+		}
+		self.print_peer_id()
+	}
+
+	/// Load the key the way the node does, generating it if the file doesn't exist, and print
+	/// its `PeerId`.
+	fn print_peer_id(&self) -> error::Result<()> {
+		let keypair = NodeKeyConfig::Ed25519(Secret::File(self.file.clone())).into_keypair()?;
+		println!("{}", keypair.public().into_peer_id());
+		Ok(())
+	}
+}
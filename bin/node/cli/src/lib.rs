@@ -16,7 +16,7 @@
 
 //! Substrate CLI library.
 //!
-//! This package has two Cargo features:
+//! This package has five Cargo features:
 //!
 //! - `cli` (default): exposes functions that parse command-line options, then start and run the
 //! node as a CLI application.
@@ -25,6 +25,14 @@
 //! that are meant to be passed through the `wasm-bindgen` utility and called from JavaScript.
 //! Despite its name the produced WASM can theoretically also be used from NodeJS, although this
 //! hasn't been tested.
+//!
+//! - `grpc`: enables the `--grpc-port` option, serving the finalized blocks over gRPC.
+//!
+//! - `graphql`: enables the `--graphql-port` option, serving a GraphQL endpoint querying the
+//! blocks, extrinsics, events and accounts of the chain.
+//!
+//! - `kafka` and `nats`: enable the `--publish-url` option for Kafka and NATS brokers,
+//! publishing the finalized blocks, the results of their extrinsics and their events.
 
 #![warn(missing_docs)]
 #![warn(unused_extern_crates)]
@@ -39,6 +47,36 @@ mod browser;
 mod cli;
 #[cfg(feature = "cli")]
 mod factory_impl;
+#[cfg(feature = "cli")]
+mod factory_rpc;
+#[cfg(feature = "cli")]
+mod benchmark;
+#[cfg(feature = "cli")]
+mod build_genesis;
+#[cfg(feature = "cli")]
+mod decode;
+#[cfg(feature = "cli")]
+mod export_types;
+#[cfg(feature = "cli")]
+mod fork_off;
+#[cfg(feature = "cli")]
+mod grandpa;
+#[cfg(feature = "cli")]
+mod import_justifications;
+#[cfg(feature = "cli")]
+mod key;
+#[cfg(feature = "cli")]
+mod metadata_compat;
+#[cfg(feature = "cli")]
+mod rpc;
+#[cfg(feature = "cli")]
+mod run_chains;
+#[cfg(feature = "cli")]
+mod state_diff;
+#[cfg(feature = "cli")]
+mod storage_report;
+#[cfg(feature = "cli")]
+mod testnet;
 
 #[cfg(feature = "browser")]
 pub use browser::*;
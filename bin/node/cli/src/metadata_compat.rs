@@ -0,0 +1,62 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `check-metadata-compat` subcommand.
+
+use std::fs;
+
+use sc_cli::error;
+use serde_json::json;
+use substrate_frame_decode::{check_compat, Decoder, TypeRegistry};
+
+use crate::cli::CheckMetadataCompatCmd;
+use crate::decode::{decode_error, read_metadata};
+
+impl CheckMetadataCompatCmd {
+	/// Compare the two metadata files, printing the breaking changes found.
+	pub fn run(&self) -> error::Result<()> {
+		let mut registry = TypeRegistry::with_defaults();
+		if let Some(path) = &self.types {
+			registry.extend_from_json(&fs::read_to_string(path)?).map_err(decode_error)?;
+		}
+		let old = Decoder::from_bytes(&read_metadata(&self.old)?, registry.clone())
+			.map_err(decode_error)?;
+		let new = Decoder::from_bytes(&read_metadata(&self.new)?, registry)
+			.map_err(decode_error)?;
+
+		let changes = check_compat(&old, &new).map_err(decode_error)?;
+
+		if self.json {
+			let changes = changes.iter()
+				.map(|change| json!({ "location": change.location, "description": change.description }))
+				.collect::<Vec<_>>();
+			println!(
+				"{}",
+				serde_json::to_string_pretty(&changes).expect("JSON values are always serializable; qed"),
+			);
+		} else {
+			for change in &changes {
+				println!("{}", change);
+			}
+		}
+
+		if changes.is_empty() {
+			Ok(())
+		} else {
+			Err(error::Error::Other(format!("{} breaking changes found", changes.len())))
+		}
+	}
+}
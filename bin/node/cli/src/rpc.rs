@@ -0,0 +1,64 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers for subcommands talking to a running node over RPC.
+
+use std::sync::mpsc;
+
+use hyper::rt;
+use jsonrpc_core::futures::Future;
+use jsonrpc_core_client::{transports::http, RpcChannel, RpcError};
+use node_primitives::Hash;
+use sc_cli::error;
+
+/// Parse hex encoded data, with or without `0x` prefix.
+pub fn parse_hex(data: &str) -> error::Result<Vec<u8>> {
+	let data = data.trim();
+	let data = if data.starts_with("0x") { &data[2..] } else { data };
+	hex::decode(data).map_err(|e| error::Error::Input(format!("Invalid hex data: {}", e)))
+}
+
+/// Parse a hex encoded block hash.
+pub fn parse_hash(hash: &str) -> error::Result<Hash> {
+	let hash = parse_hex(hash)?;
+	if hash.len() != Hash::len_bytes() {
+		return Err(error::Error::Input("Invalid block hash".into()))
+	}
+	Ok(Hash::from_slice(&hash))
+}
+
+/// Connect to the node at `url` and wait for the result of the request made by `f`.
+pub fn request<C, F, R, T>(url: &str, f: F) -> error::Result<T> where
+	C: From<RpcChannel> + Send + 'static,
+	F: FnOnce(C) -> R + Send + 'static,
+	R: Future<Item = T, Error = RpcError> + Send + 'static,
+	T: Send + 'static,
+{
+	let (tx, rx) = mpsc::channel();
+
+	rt::run(
+		http::connect(url)
+			.and_then(f)
+			.then(move |res| {
+				let _ = tx.send(res.map_err(|e| format!("{:?}", e)));
+				Ok(())
+			})
+	);
+
+	rx.recv()
+		.map_err(|_| error::Error::Other("RPC request did not complete".into()))?
+		.map_err(|e| error::Error::Other(format!("RPC request to {} failed: {}", url, e)))
+}
@@ -0,0 +1,125 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `run-chains` subcommand.
+
+use std::{collections::HashSet, fs, iter, net::SocketAddr};
+
+use futures::{channel::oneshot, future::{self, select, BoxFuture, Either, FutureExt}};
+use log::info;
+use sc_cli::{display_role, error, IntoExit, VersionInfo};
+use sc_service::{AbstractService, Configuration, Roles as ServiceRoles, multi::{self, Chains}};
+use serde::Deserialize;
+use tokio::runtime::Builder as RuntimeBuilder;
+
+use crate::{cli::RunChainsCmd, load_spec, service};
+
+/// The chains listed in the file given to `run-chains`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChainsFile {
+	chains: Vec<Chain>,
+}
+
+/// A chain run by the process.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Chain {
+	/// Name of the chain in the logs and metrics.
+	name: String,
+	/// Arguments of the `run` command for the node of the chain.
+	args: Vec<String>,
+}
+
+impl RunChainsCmd {
+	/// Run the node of each chain of the file until one of them fails or `exit` resolves.
+	pub fn run<E: IntoExit>(&self, exit: E, version: &VersionInfo) -> error::Result<()> {
+		let file: ChainsFile = serde_json::from_str(&fs::read_to_string(&self.chains)?)
+			.map_err(|e| error::Error::Input(format!("Invalid chains file {}: {}", self.chains.display(), e)))?;
+		if file.chains.is_empty() {
+			return Err(error::Error::Input(format!("No chain in {}", self.chains.display())));
+		}
+		let mut names = HashSet::new();
+		if let Some(chain) = file.chains.iter().find(|chain| !names.insert(&chain.name)) {
+			return Err(error::Error::Input(format!("Chain {} is listed twice", chain.name)));
+		}
+
+		let mut configs = file.chains.into_iter()
+			.map(|chain| {
+				let args = iter::once(version.executable_name.to_string()).chain(chain.args);
+				let config: Configuration<(), _, _> =
+					sc_cli::create_run_config_from_args(args, load_spec, "substrate-node", version)?;
+				Ok((chain.name, config))
+			})
+			.collect::<error::Result<Vec<_>>>()?;
+		multi::share_resources(&mut configs);
+
+		let mut runtime = RuntimeBuilder::new()
+			.thread_name("main-tokio-")
+			.threaded_scheduler()
+			.build()
+			.map_err(|e| format!("{:?}", e))?;
+
+		let mut nodes = Nodes::default();
+		for (name, config) in configs {
+			info!(
+				"Chain {}: {}, node {}, roles {}",
+				name,
+				config.chain_spec.name(),
+				config.name,
+				display_role(&config),
+			);
+			match config.roles {
+				ServiceRoles::LIGHT => nodes.push(name, service::new_light(config)?),
+				_ => nodes.push(name, service::new_full(config)?),
+			}
+		}
+
+		let interface = if self.grafana_external { [0, 0, 0, 0] } else { [127, 0, 0, 1] };
+		let metrics = multi::metrics_server(SocketAddr::from((interface, self.grafana_port)));
+		let (exit_send, exit_recv) = oneshot::channel();
+		let background = future::join(future::join_all(nodes.informants), metrics);
+		let handle = runtime.spawn(select(exit_recv, background.boxed()));
+
+		// Keep the telemetry of the nodes until they stopped.
+		let _telemetry = nodes.telemetry;
+		let result = runtime.block_on(select(nodes.chains, exit.into_exit()));
+
+		let _ = exit_send.send(());
+		let _ = runtime.block_on(handle);
+
+		match result {
+			Either::Left((result, _)) => result.map_err(error::Error::Service),
+			Either::Right(_) => Ok(()),
+		}
+	}
+}
+
+/// The services of the chains and their companion tasks.
+#[derive(Default)]
+struct Nodes {
+	chains: Chains,
+	informants: Vec<BoxFuture<'static, ()>>,
+	telemetry: Vec<sc_telemetry::Telemetry>,
+}
+
+impl Nodes {
+	fn push<T: AbstractService>(&mut self, name: String, service: T) {
+		self.informants.push(sc_cli::informant::build(&service).boxed());
+		self.telemetry.extend(service.telemetry());
+		self.chains.push(name, service);
+	}
+}
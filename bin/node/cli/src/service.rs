@@ -30,12 +30,15 @@ use sc_service::{
 	AbstractService, ServiceBuilder, config::Configuration, error::{Error as ServiceError},
 };
 use sp_inherents::InherentDataProviders;
+use sc_consensus_slots::{OffsetClock, clock::TimestampInherentDataProvider};
 use sc_network::construct_simple_protocol;
 
 use sc_service::{Service, NetworkStatus};
 use sc_client::{Client, LocalCallExecutor};
 use sc_client_db::Backend;
 use sp_runtime::traits::Block as BlockT;
+use sp_api::ProvideRuntimeApi;
+use pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi;
 use node_executor::NativeExecutor;
 use sc_network::NetworkService;
 use sc_offchain::OffchainWorkers;
@@ -45,6 +48,23 @@ construct_simple_protocol! {
 	pub struct NodeProtocol where Block = Block { }
 }
 
+/// Creates the registry of inherent data providers shared by block authoring and import.
+///
+/// Providers that need nothing from the client are registered here, including any chain-specific
+/// ones. BABE adds its own (slot, uncles) when it is set up. The same registry decodes the
+/// runtime's inherent check errors when a block fails to import.
+///
+/// The timestamp is read from `clock` if given, the system clock otherwise.
+pub fn inherent_data_providers(clock: Option<OffsetClock>) -> Result<InherentDataProviders, ServiceError> {
+	let providers = InherentDataProviders::new();
+	match clock {
+		Some(clock) => providers.register_provider(TimestampInherentDataProvider(clock)),
+		None => providers.register_provider(sp_timestamp::InherentDataProvider),
+	}.map_err(|e| ServiceError::Other(e.into_string()))?;
+
+	Ok(providers)
+}
+
 /// Starts a `ServiceBuilder` for a full service.
 ///
 /// Use this macro if you don't actually need the full service, but just the builder in order to
@@ -53,7 +73,20 @@ macro_rules! new_full_start {
 	($config:expr) => {{
 		type RpcExtension = jsonrpc_core::IoHandler<sc_rpc::Metadata>;
 		let mut import_setup = None;
-		let inherent_data_providers = sp_inherents::InherentDataProviders::new();
+		// development chains can travel in time and revert to snapshots: their slots and
+		// timestamps follow a clock moved forward by the `dev_*` RPC methods, and GRANDPA
+		// doesn't finalize the blocks after a snapshot.
+		let (dev_voting_rule, dev_clock) = if $config.chain_spec.id() == "dev" {
+			(Some(node_rpc::dev::SnapshotVotingRule::default()), Some(sc_consensus_slots::OffsetClock::default()))
+		} else {
+			(None, None)
+		};
+		let inherent_data_providers = $crate::service::inherent_data_providers(dev_clock.clone())?;
+		let dev_deps = match (dev_voting_rule.clone(), dev_clock.clone()) {
+			(Some(voting_rule), Some(clock)) => Some(node_rpc::DevDeps { voting_rule, clock }),
+			_ => None,
+		};
+		let mut rpc_justification_import = None;
 
 		let builder = sc_service::ServiceBuilder::new_full::<
 			node_primitives::Block, node_runtime::RuntimeApi, node_executor::Executor
@@ -68,22 +101,29 @@ macro_rules! new_full_start {
 				let maintainable_pool = sp_transaction_pool::MaintainableTransactionPool::new(pool, maintainer);
 				Ok(maintainable_pool)
 			})?
-			.with_import_queue(|_config, client, mut select_chain, _transaction_pool| {
+			.with_import_queue(|config, client, mut select_chain, _transaction_pool| {
 				let select_chain = select_chain.take()
 					.ok_or_else(|| sc_service::Error::SelectChainRequired)?;
-				let (grandpa_block_import, grandpa_link) = grandpa::block_import(
+				let checkpoint = config.chain_spec.extensions().checkpoint.clone();
+				let (grandpa_block_import, grandpa_link) = grandpa::block_import_with_checkpoint(
 					client.clone(),
 					&*client,
 					select_chain,
+					checkpoint.clone(),
 				)?;
 				let justification_import = grandpa_block_import.clone();
+				rpc_justification_import = Some(grandpa_block_import.clone());
 
-				let (block_import, babe_link) = sc_consensus_babe::block_import(
-					sc_consensus_babe::Config::get_or_compute(&*client)?,
+				let (block_import, mut babe_link) = sc_consensus_babe::block_import(
+					sc_consensus_babe::Config::get_or_compute(&*client)?
+						.ensure_epoch_length(config.chain_spec.extensions().epoch_length)?,
 					grandpa_block_import,
 					client.clone(),
 					client.clone(),
 				)?;
+				if let Some(checkpoint) = checkpoint {
+					babe_link = babe_link.trust_ancestors_of(checkpoint.number, checkpoint.hash);
+				}
 
 				let import_queue = sc_consensus_babe::import_queue(
 					babe_link.clone(),
@@ -97,12 +137,39 @@ macro_rules! new_full_start {
 
 				import_setup = Some((block_import, grandpa_link, babe_link));
 				Ok(import_queue)
-			})?
-			.with_rpc_extensions(|client, pool, _backend, fetcher, _remote_blockchain| -> Result<RpcExtension, _> {
-				Ok(node_rpc::create(client, pool, node_rpc::LightDeps::none(fetcher)))
 			})?;
 
-		(builder, import_setup, inherent_data_providers)
+		let babe_deps = import_setup.as_ref().map(|(_, _, babe_link)| node_rpc::BabeDeps {
+			babe_link: babe_link.clone(),
+			keystore: builder.keystore(),
+		});
+		let author_deps = node_rpc::AuthorDeps {
+			keystore: builder.keystore(),
+			submissions: builder.submission_queue(),
+		};
+		let grandpa_deps = match (import_setup.as_ref(), rpc_justification_import) {
+			(Some((_, grandpa_link, _)), Some(justification_import)) => Some(node_rpc::GrandpaDeps {
+				round_state: grandpa_link.round_state().clone(),
+				justification_import: Box::new(justification_import),
+			}),
+			_ => None,
+		};
+		let builder = builder
+			.with_rpc_extensions(|client, pool, backend, fetcher, _remote_blockchain| -> Result<RpcExtension, _> {
+				let light_deps = node_rpc::LightDeps::none(fetcher);
+				Ok(node_rpc::create(
+					client,
+					pool,
+					backend,
+					light_deps,
+					babe_deps,
+					grandpa_deps,
+					Some(author_deps),
+					dev_deps,
+				))
+			})?;
+
+		(builder, import_setup, inherent_data_providers, dev_voting_rule, dev_clock)
 	}}
 }
 
@@ -123,13 +190,19 @@ macro_rules! new_full {
 			force_authoring,
 			name,
 			disable_grandpa,
+			grandpa_lightweight_observer,
 			sentry_nodes,
+			slow_extrinsic_factor,
+			grandpa_threshold_peers,
 		) = (
 			$config.roles.is_authority(),
 			$config.force_authoring,
 			$config.name.clone(),
 			$config.disable_grandpa,
+			$config.grandpa_lightweight_observer,
 			$config.network.sentry_nodes.clone(),
+			$config.slow_extrinsic_factor,
+			$config.grandpa_threshold_peers.clone(),
 		);
 
 		// sentry nodes announce themselves as authorities to the network
@@ -137,7 +210,8 @@ macro_rules! new_full {
 		// never actively participate in any consensus process.
 		let participates_in_consensus = is_authority && !$config.sentry_mode;
 
-		let (builder, mut import_setup, inherent_data_providers) = new_full_start!($config);
+		let (builder, mut import_setup, inherent_data_providers, dev_voting_rule, dev_clock) =
+			new_full_start!($config);
 
 		let service = builder.with_network_protocol(|_| Ok(crate::service::NodeProtocol::new()))?
 			.with_finality_proof_provider(|client, backend|
@@ -154,6 +228,10 @@ macro_rules! new_full {
 			let proposer = sc_basic_authority::ProposerFactory {
 				client: service.client(),
 				transaction_pool: service.transaction_pool(),
+				weight_tracing: match slow_extrinsic_factor {
+					0 => None,
+					factor => Some(crate::service::weight_tracing(service.client(), factor)),
+				},
 			};
 
 			let client = service.client();
@@ -174,10 +252,11 @@ macro_rules! new_full {
 				force_authoring,
 				babe_link,
 				can_author_with,
+				clock: dev_clock.map(|clock| Arc::new(clock) as Arc<dyn sc_consensus_slots::Clock>),
 			};
 
 			let babe = sc_consensus_babe::start_babe(babe_config)?;
-			service.spawn_essential_task(babe);
+			service.spawn_consensus_task(babe);
 
 			let network = service.network();
 			let dht_event_stream = network.event_stream().filter_map(|e| async move { match e {
@@ -203,14 +282,41 @@ macro_rules! new_full {
 			None
 		};
 
+		// hosts holding a share of a split GRANDPA key answer the requests of the other hosts,
+		// and sign with them if they are voting.
+		let threshold_signer = if grandpa_threshold_peers.is_empty() {
+			None
+		} else {
+			let cosigner = Arc::new(grandpa::threshold::CoSigner::new(service.keystore(), service.client()));
+			let (requests_tx, requests_rx) =
+				futures::channel::mpsc::channel(grandpa::threshold::INBOUND_QUEUE_SIZE);
+			service.network().register_request_response_protocol(
+				grandpa::threshold::request_response_config(Some(requests_tx)),
+			);
+			service.spawn_task(cosigner.clone().answer_requests(grandpa_threshold_peers.clone(), requests_rx));
+
+			if participates_in_consensus {
+				Some(Arc::new(grandpa::threshold::ThresholdSigner::new(
+					cosigner,
+					grandpa_threshold_peers,
+					service.network(),
+				)))
+			} else {
+				None
+			}
+		};
+
 		let config = grandpa::Config {
 			// FIXME #1578 make this available through chainspec
 			gossip_duration: std::time::Duration::from_millis(333),
 			justification_period: 512,
 			name: Some(name),
 			observer_enabled: true,
+			lightweight_observer: grandpa_lightweight_observer,
 			keystore,
 			is_authority,
+			clock: None,
+			threshold_signer,
 		};
 
 		match (is_authority, disable_grandpa) {
@@ -225,29 +331,29 @@ macro_rules! new_full {
 				)?.compat().map(drop));
 			},
 			(true, false) => {
-				// start the full GRANDPA voter
+				// start the full GRANDPA voter, not finalizing the blocks after the snapshots
+				// of development chains
+				let mut voting_rules = grandpa::VotingRulesBuilder::default();
+				if let Some(dev_voting_rule) = dev_voting_rule {
+					voting_rules = voting_rules.add(dev_voting_rule);
+				}
 				let grandpa_config = grandpa::GrandpaParams {
 					config: config,
 					link: grandpa_link,
 					network: service.network(),
-					inherent_data_providers: inherent_data_providers.clone(),
 					on_exit: service.on_exit(),
 					telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
-					voting_rule: grandpa::VotingRulesBuilder::default().build(),
+					voting_rule: voting_rules.build(),
 					executor: service.spawn_task_handle(),
 				};
 				// the GRANDPA voter task is considered infallible, i.e.
 				// if it fails we take down the service with it.
-				service.spawn_essential_task(
+				service.spawn_consensus_task(
 					grandpa::run_grandpa_voter(grandpa_config)?.compat().map(drop)
 				);
 			},
 			(_, true) => {
-				grandpa::setup_disabled_grandpa(
-					service.client(),
-					&inherent_data_providers,
-					service.network(),
-				)?;
+				grandpa::setup_disabled_grandpa(service.network())?;
 			},
 		}
 
@@ -283,6 +389,33 @@ type ConcreteTransactionPool = sp_transaction_pool::MaintainableTransactionPool<
 	>
 >;
 
+/// Configuration of the slow extrinsic log of the block authoring.
+///
+/// The weight and class of the extrinsics are queried from the runtime. Weights of the node
+/// runtime are assumed to be nanoseconds of execution time, and extrinsics taking more than
+/// `factor` times that long are reported.
+pub fn weight_tracing<C>(client: Arc<C>, factor: u64) -> sc_basic_authority::WeightTracing<Block> where
+	C: ProvideRuntimeApi<Block> + Send + Sync + 'static,
+	C::Api: TransactionPaymentApi<Block, node_primitives::Balance, node_runtime::UncheckedExtrinsic>,
+{
+	use codec::Decode;
+	use frame_support::weights::DispatchClass;
+
+	sc_basic_authority::WeightTracing {
+		weight_of: Arc::new(move |at, extrinsic| {
+			let len = extrinsic.0.len() as u32;
+			let extrinsic = node_runtime::UncheckedExtrinsic::decode(&mut &extrinsic.0[..]).ok()?;
+			let info = client.runtime_api().query_info(at, extrinsic, len).ok()?;
+			Some(sc_basic_authority::ExtrinsicDispatch {
+				weight: info.weight as u64,
+				operational: info.class == DispatchClass::Operational,
+			})
+		}),
+		nanos_per_weight: 1,
+		factor,
+	}
+}
+
 /// A specialized configuration object for setting up the node..
 pub type NodeConfiguration<C> = Configuration<C, GenesisConfig, crate::chain_spec::Extensions>;
 
@@ -312,7 +445,7 @@ pub fn new_full<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 pub fn new_light<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 -> Result<impl AbstractService, ServiceError> {
 	type RpcExtension = jsonrpc_core::IoHandler<sc_rpc::Metadata>;
-	let inherent_data_providers = InherentDataProviders::new();
+	let inherent_data_providers = inherent_data_providers(None)?;
 
 	let service = ServiceBuilder::new_light::<Block, RuntimeApi, node_executor::Executor>(config)?
 		.with_select_chain(|_config, backend| {
@@ -327,7 +460,7 @@ pub fn new_light<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 			let maintainable_pool = sp_transaction_pool::MaintainableTransactionPool::new(pool, maintainer);
 			Ok(maintainable_pool)
 		})?
-		.with_import_queue_and_fprb(|_config, client, backend, fetcher, _select_chain, _tx_pool| {
+		.with_import_queue_and_fprb(|config, client, backend, fetcher, _select_chain, _tx_pool| {
 			let fetch_checker = fetcher
 				.map(|fetcher| fetcher.checker().clone())
 				.ok_or_else(|| "Trying to start light import queue without active fetch checker")?;
@@ -343,7 +476,8 @@ pub fn new_light<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 				finality_proof_import.create_finality_proof_request_builder();
 
 			let (babe_block_import, babe_link) = sc_consensus_babe::block_import(
-				sc_consensus_babe::Config::get_or_compute(&*client)?,
+				sc_consensus_babe::Config::get_or_compute(&*client)?
+					.ensure_epoch_length(config.chain_spec.extensions().epoch_length)?,
 				grandpa_block_import,
 				client.clone(),
 				client.clone(),
@@ -365,14 +499,14 @@ pub fn new_light<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 		.with_finality_proof_provider(|client, backend|
 			Ok(Arc::new(GrandpaFinalityProofProvider::new(backend, client)) as _)
 		)?
-		.with_rpc_extensions(|client, pool, _backend, fetcher, remote_blockchain| -> Result<RpcExtension, _> {
+		.with_rpc_extensions(|client, pool, backend, fetcher, remote_blockchain| -> Result<RpcExtension, _> {
 			let fetcher = fetcher
 				.ok_or_else(|| "Trying to start node RPC without active fetcher")?;
 			let remote_blockchain = remote_blockchain
 				.ok_or_else(|| "Trying to start node RPC without active remote blockchain")?;
 
 			let light_deps = node_rpc::LightDeps { remote_blockchain, fetcher };
-			Ok(node_rpc::create(client, pool, Some(light_deps)))
+			Ok(node_rpc::create(client, pool, backend, Some(light_deps), None, None, None, None))
 		})?
 		.build()?;
 
@@ -389,7 +523,7 @@ mod tests {
 	};
 	use node_primitives::{Block, DigestItem, Signature};
 	use node_runtime::{BalancesCall, Call, UncheckedExtrinsic, Address};
-	use node_runtime::constants::{currency::CENTS, time::SLOT_DURATION};
+	use node_runtime::constants::{currency::CENTS, time::MILLISECS_PER_BLOCK};
 	use codec::{Encode, Decode};
 	use sp_core::{crypto::Pair as CryptoPair, H256};
 	use sp_runtime::{
@@ -399,7 +533,6 @@ mod tests {
 		OpaqueExtrinsic,
 	};
 	use sp_timestamp;
-	use sp_finality_tracker;
 	use sp_keyring::AccountKeyring;
 	use sc_service::AbstractService;
 	use crate::service::{new_full, new_light};
@@ -505,13 +638,13 @@ mod tests {
 				let mut inherent_data = inherent_data_providers
 					.create_inherent_data()
 					.expect("Creates inherent data.");
-				inherent_data.replace_data(sp_finality_tracker::INHERENT_IDENTIFIER, &1u64);
 
 				let parent_id = BlockId::number(service.client().chain_info().best_number);
 				let parent_header = service.client().header(&parent_id).unwrap().unwrap();
 				let mut proposer_factory = sc_basic_authority::ProposerFactory {
 					client: service.client(),
 					transaction_pool: service.transaction_pool(),
+					weight_tracing: None,
 				};
 
 				let mut digest = Digest::<H256>::default();
@@ -519,7 +652,7 @@ mod tests {
 				// even though there's only one authority some slots might be empty,
 				// so we must keep trying the next slots until we can claim one.
 				let babe_pre_digest = loop {
-					inherent_data.replace_data(sp_timestamp::INHERENT_IDENTIFIER, &(slot_num * SLOT_DURATION));
+					inherent_data.replace_data(sp_timestamp::INHERENT_IDENTIFIER, &(slot_num * MILLISECS_PER_BLOCK));
 					if let Some(babe_pre_digest) = sc_consensus_babe::test_helpers::claim_slot(
 						slot_num,
 						&parent_header,
@@ -589,7 +722,7 @@ mod tests {
 				let check_era = frame_system::CheckEra::from(Era::Immortal);
 				let check_nonce = frame_system::CheckNonce::from(index);
 				let check_weight = frame_system::CheckWeight::new();
-				let payment = pallet_transaction_payment::ChargeTransactionPayment::from(0);
+				let payment = pallet_asset_tx_payment::ChargeAssetTxPayment::from(0, None);
 				let extra = (
 					check_version,
 					check_genesis,
@@ -0,0 +1,180 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `state-diff` subcommand.
+
+use std::fs;
+
+use node_primitives::Hash;
+use sc_cli::error;
+use sc_rpc::state::{StateClient, StorageDiff};
+use sp_core::storage::StorageKey;
+use serde_json::{json, Value};
+use substrate_frame_decode::{Decoder, TypeRegistry};
+
+use crate::cli::StateDiffCmd;
+use crate::rpc::{self, parse_hash, parse_hex};
+
+/// Number of storage items requested per page of the diff.
+const PAGE_SIZE: u32 = 1000;
+
+impl StateDiffCmd {
+	/// Fetch the diff from the node and print it, decoded where possible.
+	pub fn run(&self) -> error::Result<()> {
+		let from = parse_hash(&self.from)?;
+		let to = parse_hash(&self.to)?;
+		let prefix = match &self.prefix {
+			Some(prefix) => Some(StorageKey(parse_hex(prefix)?)),
+			None => None,
+		};
+
+		let mut diff = Vec::new();
+		let mut start_key = None;
+		loop {
+			let prefix = prefix.clone();
+			let page = rpc::request(&self.url, move |client: StateClient<Hash>|
+				client.state_diff(from, to, prefix, PAGE_SIZE, start_key)
+			)?;
+			diff.extend(page.diffs);
+			start_key = match page.next_key {
+				Some(next_key) => Some(next_key),
+				None => break,
+			};
+		}
+		let old_decoder = self.decoder(from)?;
+		let new_decoder = self.decoder(to)?;
+		let diff = diff.iter().map(|diff| describe(diff, &old_decoder, &new_decoder)).collect::<Vec<_>>();
+
+		if self.json {
+			println!(
+				"{}",
+				serde_json::to_string_pretty(&diff).expect("JSON values are always serializable; qed"),
+			);
+		} else {
+			for diff in &diff {
+				print(diff);
+			}
+			println!("{} storage items differ", diff.len());
+		}
+		Ok(())
+	}
+
+	/// The decoder for the runtime at the given block.
+	fn decoder(&self, at: Hash) -> error::Result<Decoder> {
+		let metadata = rpc::request(&self.url, move |client: StateClient<Hash>|
+			client.metadata(Some(at))
+		)?.0;
+		let mut registry = TypeRegistry::with_defaults();
+		if let Some(path) = &self.types {
+			registry.extend_from_json(&fs::read_to_string(path)?).map_err(decode_error)?;
+		}
+		Decoder::from_bytes(&metadata, registry).map_err(decode_error)
+	}
+}
+
+/// Describe a changed storage item as JSON, decoding the old and new value with the decoder of
+/// the respective runtime. Items that can't be decoded are kept hex encoded.
+fn describe(diff: &StorageDiff, old_decoder: &Decoder, new_decoder: &Decoder) -> Value {
+	let change = match (&diff.old, &diff.new) {
+		(None, _) => "created",
+		(_, None) => "deleted",
+		_ => "modified",
+	};
+	let hex = |data: &[u8]| Value::String(format!("0x{}", hex::encode(data)));
+
+	let mut result = json!({ "change": change, "key": hex(&diff.key.0) });
+	let values = [("old", &diff.old, old_decoder), ("new", &diff.new, new_decoder)];
+	for (name, value, decoder) in values.iter() {
+		let value = match value {
+			Some(value) => value,
+			None => continue,
+		};
+		match decoder.decode_storage(&diff.key.0, Some(&value.0)) {
+			Ok(decoded) => {
+				for field in &["module", "storage", "key"] {
+					if let Some(field_value) = decoded.get(field) {
+						result[*field] = field_value.clone();
+					}
+				}
+				result[*name] = decoded.get("value").cloned().unwrap_or(Value::Null);
+			},
+			Err(_) => result[*name] = hex(&value.0),
+		}
+	}
+	result
+}
+
+/// Print a described storage item as one line per value.
+fn print(diff: &Value) {
+	let sign = match diff["change"].as_str() {
+		Some("created") => "+",
+		Some("deleted") => "-",
+		_ => "~",
+	};
+	match (diff["module"].as_str(), diff["storage"].as_str()) {
+		(Some(module), Some(storage)) => println!("{} {}::{} {}", sign, module, storage, diff["key"]),
+		_ => println!("{} {}", sign, diff["key"].as_str().unwrap_or_default()),
+	}
+	if let Some(old) = diff.get("old") {
+		println!("    old: {}", old);
+	}
+	if let Some(new) = diff.get("new") {
+		println!("    new: {}", new);
+	}
+}
+
+fn decode_error(e: substrate_frame_decode::Error) -> error::Error {
+	error::Error::Other(format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use sp_core::{hashing::twox_128, storage::StorageData};
+
+	#[test]
+	fn describes_changes() {
+		// the constants of the metadata derived from the block time are read from storage.
+		let metadata = sp_io::TestExternalities::default()
+			.execute_with(|| node_runtime::Runtime::metadata().encode());
+		let decoder = Decoder::from_bytes(&metadata, TypeRegistry::with_defaults()).unwrap();
+
+		let mut number_key = twox_128(b"System").to_vec();
+		number_key.extend_from_slice(&twox_128(b"Number"));
+		let number = StorageDiff {
+			key: StorageKey(number_key),
+			old: Some(StorageData(1u32.encode())),
+			new: Some(StorageData(2u32.encode())),
+		};
+		let described = describe(&number, &decoder, &decoder);
+		assert_eq!(described["change"], "modified");
+		assert_eq!(described["module"], "System");
+		assert_eq!(described["storage"], "Number");
+		assert_eq!(described["old"], 1);
+		assert_eq!(described["new"], 2);
+
+		let unknown = StorageDiff {
+			key: StorageKey(b":unknown".to_vec()),
+			old: None,
+			new: Some(StorageData(vec![1, 2])),
+		};
+		assert_eq!(
+			describe(&unknown, &decoder, &decoder),
+			json!({ "change": "created", "key": "0x3a756e6b6e6f776e", "new": "0x0102" }),
+		);
+	}
+}
@@ -0,0 +1,244 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `storage-report` subcommand.
+
+use std::collections::BTreeMap;
+
+use codec::Decode;
+use node_primitives::Block;
+use sc_cli::error;
+use sc_client::Client;
+use sc_client_api::{backend::{Backend, StateBackend}, CallExecutor, ExecutionStrategy};
+use sp_runtime::generic::BlockId;
+use substrate_frame_decode::{Decoder, TypeRegistry};
+
+use crate::cli::StorageReportCmd;
+use crate::rpc::parse_hash;
+
+/// Group of the keys starting with `:child_storage:`, holding the roots of the child tries.
+const CHILD_STORAGE: &str = "(child storage)";
+/// Group of the other keys starting with `:`, e.g. `:code`.
+const WELL_KNOWN: &str = "(well known)";
+/// Group of the keys that don't belong to any storage item of the metadata.
+const UNKNOWN: &str = "(unknown)";
+
+/// Number and size of storage keys.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Usage {
+	keys: u64,
+	key_bytes: u64,
+	value_bytes: u64,
+}
+
+impl Usage {
+	fn add(&mut self, other: Usage) {
+		self.keys += other.keys;
+		self.key_bytes += other.key_bytes;
+		self.value_bytes += other.value_bytes;
+	}
+
+	fn total_bytes(&self) -> u64 {
+		self.key_bytes + self.value_bytes
+	}
+
+	fn to_json(&self) -> serde_json::Value {
+		serde_json::json!({
+			"keys": self.keys,
+			"keyBytes": self.key_bytes,
+			"valueBytes": self.value_bytes,
+		})
+	}
+}
+
+/// Storage usage per module and storage item.
+#[derive(Debug, Default)]
+struct StorageReport {
+	items: BTreeMap<String, BTreeMap<String, Usage>>,
+}
+
+impl StorageReport {
+	/// Account for a key and its value, using the metadata to find the storage item of the key.
+	fn add(&mut self, decoder: &Decoder, key: &[u8], value_len: usize) -> error::Result<()> {
+		let (module, item) = if key.starts_with(b":child_storage:") {
+			(CHILD_STORAGE.into(), String::from_utf8_lossy(key).into_owned())
+		} else if key.starts_with(b":") {
+			(WELL_KNOWN.into(), String::from_utf8_lossy(key).into_owned())
+		} else {
+			let item = decoder.storage_item(key).map_err(|e| error::Error::Other(format!("{}", e)))?;
+			match item {
+				Some((module, item)) => (module.into(), item.into()),
+				None => (UNKNOWN.into(), format!("0x{}", hex::encode(&key[..key.len().min(16)]))),
+			}
+		};
+
+		self.items.entry(module).or_default().entry(item).or_default().add(Usage {
+			keys: 1,
+			key_bytes: key.len() as u64,
+			value_bytes: value_len as u64,
+		});
+		Ok(())
+	}
+
+	/// The usage of each module, largest first.
+	fn modules(&self) -> Vec<(&str, Usage, Vec<(&str, Usage)>)> {
+		let mut modules = self.items.iter()
+			.map(|(module, items)| {
+				let mut total = Usage::default();
+				let mut items = items.iter()
+					.map(|(item, usage)| {
+						total.add(*usage);
+						(&item[..], *usage)
+					})
+					.collect::<Vec<_>>();
+				items.sort_by_key(|(_, usage)| std::cmp::Reverse(usage.total_bytes()));
+				(&module[..], total, items)
+			})
+			.collect::<Vec<_>>();
+		modules.sort_by_key(|(_, usage, _)| std::cmp::Reverse(usage.total_bytes()));
+		modules
+	}
+
+	fn total(&self) -> Usage {
+		let mut total = Usage::default();
+		for (_, usage, _) in self.modules() {
+			total.add(usage);
+		}
+		total
+	}
+
+	fn to_json(&self) -> serde_json::Value {
+		let modules = self.modules().into_iter()
+			.map(|(module, usage, items)| {
+				let mut json = usage.to_json();
+				json["module"] = module.into();
+				json["items"] = items.into_iter()
+					.map(|(item, usage)| {
+						let mut json = usage.to_json();
+						json["item"] = item.into();
+						json
+					})
+					.collect();
+				json
+			})
+			.collect::<Vec<_>>();
+
+		let mut json = self.total().to_json();
+		json["modules"] = modules.into();
+		json
+	}
+
+	fn print(&self) {
+		println!("{:<48} {:>10} {:>14} {:>14}", "Module / storage item", "Keys", "Key bytes", "Value bytes");
+		for (module, usage, items) in self.modules() {
+			println!("{:<48} {:>10} {:>14} {:>14}", module, usage.keys, usage.key_bytes, usage.value_bytes);
+			for (item, usage) in items {
+				println!("  {:<46} {:>10} {:>14} {:>14}", item, usage.keys, usage.key_bytes, usage.value_bytes);
+			}
+		}
+		let total = self.total();
+		println!("{:<48} {:>10} {:>14} {:>14}", "Total", total.keys, total.key_bytes, total.value_bytes);
+	}
+}
+
+impl StorageReportCmd {
+	/// Iterate the state at the requested block and print the storage usage.
+	pub fn run<B, E, RA>(&self, client: &Client<B, E, Block, RA>) -> error::Result<()> where
+		B: Backend<Block>,
+		B::State: StateBackend<sp_core::Blake2Hasher>,
+		E: CallExecutor<Block>,
+	{
+		let at = match &self.at {
+			Some(at) => BlockId::Hash(parse_hash(at)?),
+			None => BlockId::Hash(client.chain_info().best_hash),
+		};
+
+		let metadata = client.executor()
+			.call(&at, "Metadata_metadata", &[], ExecutionStrategy::NativeElseWasm, None)
+			.and_then(|metadata| Vec::<u8>::decode(&mut &metadata[..]).map_err(|e|
+				sc_client_api::blockchain::Error::CallResultDecode("failed to decode the metadata", e)
+			))?;
+		let decoder = Decoder::from_bytes(&metadata, TypeRegistry::with_defaults())
+			.map_err(|e| error::Error::Other(format!("{}", e)))?;
+
+		let state = client.state_at(&at)?;
+		let storage_error = |e| error::Error::Other(format!("Failed to read the state: {}", e));
+
+		let mut report = StorageReport::default();
+		let mut key = Vec::new();
+		while let Some(next) = state.next_storage_key(&key).map_err(storage_error)? {
+			let value_len = state.storage(&next).map_err(storage_error)?.map_or(0, |v| v.len());
+			report.add(&decoder, &next, value_len)?;
+			key = next;
+		}
+
+		let header = client.header(&at)?
+			.ok_or_else(|| error::Error::Input(format!("Unknown block: {:?}", at)))?;
+		if self.json {
+			let mut json = report.to_json();
+			json["block"] = format!("{:?}", header.hash()).into();
+			println!(
+				"{}",
+				serde_json::to_string_pretty(&json).expect("JSON values are always serializable; qed"),
+			);
+		} else {
+			println!("Storage usage at block #{} ({:?})", header.number, header.hash());
+			report.print();
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use sp_core::hashing::twox_128;
+
+	#[test]
+	fn aggregates_per_module_and_item() {
+		// the constants of the metadata derived from the block time are read from storage.
+		let metadata = sp_io::TestExternalities::default()
+			.execute_with(|| node_runtime::Runtime::metadata().encode());
+		let decoder = Decoder::from_bytes(&metadata, TypeRegistry::with_defaults()).unwrap();
+
+		let item_key = |module: &str, item: &str, suffix: &[u8]| {
+			let mut key = twox_128(module.as_bytes()).to_vec();
+			key.extend_from_slice(&twox_128(item.as_bytes()));
+			key.extend_from_slice(suffix);
+			key
+		};
+
+		let mut report = StorageReport::default();
+		report.add(&decoder, &item_key("System", "Number", &[]), 4).unwrap();
+		report.add(&decoder, &item_key("Balances", "FreeBalance", &[1; 48]), 16).unwrap();
+		report.add(&decoder, &item_key("Balances", "FreeBalance", &[2; 48]), 16).unwrap();
+		report.add(&decoder, b":code", 100).unwrap();
+		report.add(&decoder, &[0; 40], 1).unwrap();
+
+		let modules = report.modules();
+		assert_eq!(
+			modules.iter().map(|(module, usage, _)| (*module, usage.keys)).collect::<Vec<_>>(),
+			vec![("Balances", 2), ("(well known)", 1), ("(unknown)", 1), ("System", 1)],
+		);
+		assert_eq!(
+			modules[0].2,
+			vec![("FreeBalance", Usage { keys: 2, key_bytes: 160, value_bytes: 32 })],
+		);
+		assert_eq!(modules[1].2[0].0, ":code");
+		assert_eq!(report.total(), Usage { keys: 5, key_bytes: 237, value_bytes: 137 });
+	}
+}
@@ -0,0 +1,231 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `testnet` subcommand.
+
+use std::{
+	convert::TryFrom, fs, path::Path, process::{Child, Command}, sync::mpsc, thread,
+	time::{Duration, Instant},
+};
+
+use sc_cli::{error, IntoExit};
+use sc_network::{config::identity, PeerId};
+use sc_keystore::Store;
+use sp_core::hashing::blake2_256;
+
+use crate::chain_spec::{self, testnet_validator_seed};
+use crate::cli::TestnetCmd;
+
+/// Time the nodes are given to shut down before they are killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The node key of the validator with the given index.
+///
+/// Derived from the index, so the peer ids and therefore the boot nodes are stable across runs.
+fn node_key(index: usize) -> [u8; 32] {
+	blake2_256(format!("testnet-node-{}", index).as_bytes())
+}
+
+/// The peer id belonging to `node_key`.
+fn peer_id(node_key: [u8; 32]) -> PeerId {
+	let mut secret = node_key;
+	let secret = identity::ed25519::SecretKey::from_bytes(&mut secret)
+		.expect("Any 32 bytes are a valid ed25519 secret key; qed");
+	identity::Keypair::Ed25519(secret.into()).public().into_peer_id()
+}
+
+/// Insert the session keys of the validator with the given seed into the keystore at `path`.
+fn insert_session_keys(path: &Path, seed: &str) -> error::Result<()> {
+	let store = Store::open(path, None).map_err(|e| format!("Failed to open keystore: {}", e))?;
+	let store = store.read();
+	let suri = format!("//{}", seed);
+	let err = |e| format!("Failed to insert key: {}", e);
+
+	store.insert::<sp_consensus_babe::AuthorityPair>(&suri).map_err(err)?;
+	store.insert::<grandpa_primitives::AuthorityPair>(&suri).map_err(err)?;
+	store.insert::<pallet_im_online::sr25519::AuthorityPair>(&suri).map_err(err)?;
+	store.insert::<sp_authority_discovery::AuthorityPair>(&suri).map_err(err)?;
+	Ok(())
+}
+
+/// Whether the node is still running.
+fn is_running(node: &mut Child) -> bool {
+	match node.try_wait() {
+		Ok(status) => status.is_none(),
+		Err(_) => false,
+	}
+}
+
+/// Ask the node to shut down.
+#[cfg(unix)]
+fn terminate(node: &mut Child) {
+	// The node is still running, so its pid can't have been reused.
+	unsafe {
+		libc::kill(node.id() as libc::pid_t, libc::SIGTERM);
+	}
+}
+
+/// Ask the node to shut down. Without signals it can only be killed.
+#[cfg(not(unix))]
+fn terminate(node: &mut Child) {
+	let _ = node.kill();
+}
+
+/// Ask all nodes to shut down, kill those still running after `STOP_TIMEOUT` and reap them.
+fn stop(nodes: &mut Vec<Child>) {
+	for node in nodes.iter_mut() {
+		if is_running(node) {
+			terminate(node);
+		}
+	}
+
+	let deadline = Instant::now() + STOP_TIMEOUT;
+	while Instant::now() < deadline && nodes.iter_mut().any(is_running) {
+		thread::sleep(Duration::from_millis(100));
+	}
+
+	for mut node in nodes.drain(..) {
+		if is_running(&mut node) {
+			let _ = node.kill();
+		}
+		let _ = node.wait();
+	}
+}
+
+impl TestnetCmd {
+	/// Check that every node gets its own ports, the ones after the given first ports.
+	fn check_ports(&self) -> error::Result<()> {
+		let last = u16::try_from(self.validators - 1).ok();
+		for (name, first) in &[("--port", self.port), ("--rpc-port", self.rpc_port), ("--ws-port", self.ws_port)] {
+			if last.and_then(|last| first.checked_add(last)).is_none() {
+				return Err(error::Error::Input(format!(
+					"Not enough ports from {} {} for {} validators", name, first, self.validators,
+				)))
+			}
+		}
+		Ok(())
+	}
+
+	/// Run the testnet command, until the exit signal is received or a node stops.
+	pub fn run<E: IntoExit>(&self, exit: E) -> error::Result<()> {
+		if self.validators == 0 {
+			return Err(error::Error::Input("At least one validator is required".into()))
+		}
+		self.check_ports()?;
+
+		let (base_path, temporary) = match &self.base_path {
+			Some(path) => (path.clone(), false),
+			None => (std::env::temp_dir().join(format!("substrate-testnet-{}", std::process::id())), true),
+		};
+		fs::create_dir_all(&base_path)?;
+
+		let boot_nodes = (0..self.validators)
+			.map(|i| format!("/ip4/127.0.0.1/tcp/{}/p2p/{}", self.port + i as u16, peer_id(node_key(i))))
+			.collect();
+		let spec = chain_spec::multi_validator_testnet_config(self.validators, boot_nodes);
+		let spec_path = base_path.join("chain_spec.json");
+		fs::write(&spec_path, spec.to_json(true)?)?;
+
+		let executable = std::env::current_exe()?;
+		let mut nodes = Vec::with_capacity(self.validators);
+		for i in 0..self.validators {
+			let seed = testnet_validator_seed(i);
+			let node_path = base_path.join(format!("node{}", i));
+			let keystore_path = node_path.join("keystore");
+			insert_session_keys(&keystore_path, &seed)?;
+
+			let node = Command::new(&executable)
+				.arg("--chain").arg(&spec_path)
+				.arg("--base-path").arg(&node_path)
+				.arg("--keystore-path").arg(&keystore_path)
+				.arg("--name").arg(&seed)
+				.arg("--validator")
+				.arg("--port").arg((self.port + i as u16).to_string())
+				.arg("--rpc-port").arg((self.rpc_port + i as u16).to_string())
+				.arg("--ws-port").arg((self.ws_port + i as u16).to_string())
+				.arg("--node-key").arg(hex::encode(node_key(i)))
+				.args(&self.node_args)
+				.spawn();
+			match node {
+				Ok(node) => nodes.push(node),
+				Err(e) => {
+					stop(&mut nodes);
+					return Err(e.into())
+				},
+			}
+			println!(
+				"Started {} (rpc port {}, ws port {}, base path {})",
+				seed, self.rpc_port + i as u16, self.ws_port + i as u16, node_path.display(),
+			);
+		}
+
+		let (exit_send, exit_recv) = mpsc::channel();
+		let exit = exit.into_exit();
+		thread::spawn(move || {
+			futures::executor::block_on(exit);
+			let _ = exit_send.send(());
+		});
+
+		let result = loop {
+			match exit_recv.recv_timeout(Duration::from_millis(500)) {
+				Err(mpsc::RecvTimeoutError::Timeout) => {},
+				_ => break Ok(()),
+			}
+			let stopped = nodes.iter_mut().position(|node| node.try_wait().ok().flatten().is_some());
+			if let Some(index) = stopped {
+				break Err(error::Error::Other(format!("{} stopped unexpectedly", testnet_validator_seed(index))))
+			}
+		};
+
+		println!("Stopping {} nodes", nodes.len());
+		stop(&mut nodes);
+		if temporary {
+			let _ = fs::remove_dir_all(&base_path);
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn node_identities_are_deterministic_and_distinct() {
+		assert_eq!(peer_id(node_key(0)), peer_id(node_key(0)));
+		assert_ne!(peer_id(node_key(0)), peer_id(node_key(1)));
+	}
+
+	#[test]
+	fn ports_must_not_overflow() {
+		use structopt::StructOpt;
+
+		let cmd = |args: &[&str]| TestnetCmd::from_iter(std::iter::once("testnet").chain(args.iter().cloned()));
+		assert!(cmd(&["--validators", "3", "--port", "65533"]).check_ports().is_ok());
+		assert!(cmd(&["--validators", "3", "--port", "65534"]).check_ports().is_err());
+		assert!(cmd(&["--validators", "3", "--ws-port", "65534"]).check_ports().is_err());
+		assert!(cmd(&["--validators", "70000"]).check_ports().is_err());
+	}
+
+	#[test]
+	fn generated_spec_contains_all_validators() {
+		let spec = chain_spec::multi_validator_testnet_config(8, vec![]);
+		let genesis = sp_runtime::BuildStorage::build_storage(&spec).unwrap();
+		let validators = sp_io::TestExternalities::new(genesis)
+			.execute_with(|| node_runtime::Session::validators());
+		assert_eq!(validators.len(), 8);
+	}
+}
@@ -373,6 +373,16 @@ fn full_native_block_import_works() {
 				)),
 				topics: vec![],
 			},
+			EventRecord {
+				phase: Phase::Finalization,
+				event: Event::pallet_fee_distribution(pallet_fee_distribution::RawEvent::Distributed(
+					Default::default(),
+					496200000000,
+					1984800000000,
+					0,
+				)),
+				topics: vec![],
+			},
 		];
 		assert_eq!(System::events(), events);
 	});
@@ -452,6 +462,16 @@ fn full_native_block_import_works() {
 				)),
 				topics: vec![],
 			},
+			EventRecord {
+				phase: Phase::Finalization,
+				event: Event::pallet_fee_distribution(pallet_fee_distribution::RawEvent::Distributed(
+					Default::default(),
+					2 * 496197049848,
+					2 * 1984788199392,
+					0,
+				)),
+				topics: vec![],
+			},
 		];
 		assert_eq!(System::events(), events);
 	});
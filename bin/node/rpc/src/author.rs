@@ -0,0 +1,396 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC methods submitting transactions signed with the keys of the keystore of the node.
+//!
+//! Lets bots running next to a node, e.g. payout or oracle submitters, submit calls without
+//! tracking nonces, eras and signed extensions themselves.
+
+use std::{collections::HashMap, sync::Arc};
+
+use codec::{Decode, Encode};
+use futures::{compat::Compat, future::FutureExt};
+use jsonrpc_core::{Error, ErrorCode, futures::future::{result, Future}};
+use jsonrpc_derive::rpc;
+use node_primitives::{AccountId, Block, Index, Signature};
+use node_runtime::{Call, Indices, SignedExtra, SignedPayload, UncheckedExtrinsic};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sc_keystore::{KeyStorePtr, Store};
+use sc_rpc::author::SubmissionQueue;
+use sp_api::{Core, ProvideRuntimeApi};
+use sp_core::{Bytes, Pair, crypto::key_types, ecdsa, ed25519, sr25519};
+use sp_runtime::{
+	generic::{BlockId, Era}, traits::{IdentifyAccount, SignPayload, StaticLookup},
+};
+use sp_transaction_pool::{TransactionPool, TxHash};
+use substrate_frame_rpc_system::{AccountNonceApi, MortalityApi, adjust_nonce};
+
+pub use self::gen_client::Client as ManagedAuthorClient;
+
+/// Future that resolves to the result of a managed author RPC call.
+pub type FutureResult<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
+
+const RUNTIME_ERROR: i64 = 1;
+const POOL_ERROR: i64 = 2;
+const KEYSTORE_ERROR: i64 = 3;
+const BAD_CALL: i64 = 4;
+const POOL_OVERLOADED: i64 = 5;
+
+/// A transaction submitted with a managed nonce.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedSubmission<Hash> {
+	/// Hash of the transaction.
+	pub hash: Hash,
+	/// Nonce the transaction is signed with.
+	pub nonce: Index,
+}
+
+/// Author RPC methods signing with the keys of the keystore.
+#[rpc]
+pub trait ManagedAuthorApi<Hash> {
+	/// RPC metadata
+	type Metadata;
+
+	/// Sign `call`, a SCALE encoded call of the runtime, with the account key (of type `acco`)
+	/// of `account` in the keystore, of any signature scheme, and submit it to the transaction
+	/// pool.
+	///
+	/// The transaction is signed with the next nonce of the account, considering its
+	/// transactions in the pool and the ones previously submitted with this method, and is
+	/// mortal from the best block for the longest period accepted by the runtime. It waits for
+	/// validation in the queue of `author_submitExtrinsic`.
+	#[rpc(meta, name = "author_submitWithManagedNonce")]
+	fn submit_with_managed_nonce(
+		&self,
+		metadata: Self::Metadata,
+		call: Bytes,
+		account: AccountId,
+	) -> FutureResult<ManagedSubmission<Hash>>;
+}
+
+/// Implementation of the author RPC methods signing with the keys of the keystore.
+pub struct ManagedAuthor<C, P> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	keystore: KeyStorePtr,
+	/// Submissions waiting for validation.
+	submissions: SubmissionQueue,
+	/// The next nonce of the accounts whose transactions submitted with this method are being
+	/// validated, ahead of the pool.
+	nonces: Arc<Mutex<HashMap<AccountId, Index>>>,
+}
+
+impl<C, P> ManagedAuthor<C, P> {
+	/// Create new `ManagedAuthor` given client, transaction pool, keystore and the queue of the
+	/// submissions waiting for validation.
+	pub fn new(
+		client: Arc<C>,
+		pool: Arc<P>,
+		keystore: KeyStorePtr,
+		submissions: SubmissionQueue,
+	) -> Self {
+		ManagedAuthor { client, pool, keystore, submissions, nonces: Default::default() }
+	}
+}
+
+/// A nonce signed with, ahead of the pool until dropped, once the transaction is validated.
+struct Reservation {
+	nonces: Arc<Mutex<HashMap<AccountId, Index>>>,
+	account: AccountId,
+	nonce: Index,
+}
+
+impl Drop for Reservation {
+	fn drop(&mut self) {
+		// once validated, the transaction is accounted for by the pool, or its nonce is free again
+		// if it was rejected or given up on, unless later transactions were signed meanwhile.
+		let mut nonces = self.nonces.lock();
+		if nonces.get(&self.account) == Some(&(self.nonce + 1)) {
+			nonces.remove(&self.account);
+		}
+	}
+}
+
+/// An account key of the keystore.
+enum AccountKey {
+	Sr25519(sr25519::Pair),
+	Ed25519(ed25519::Pair),
+	Ecdsa(ecdsa::Pair),
+}
+
+impl AccountKey {
+	/// The account key of `keystore` signing for `account`, `None` if there is none.
+	fn find(keystore: &Store, account: &AccountId) -> sc_keystore::Result<Option<Self>> {
+		Ok(match find_key::<sr25519::Pair>(keystore, account)? {
+			Some(pair) => Some(AccountKey::Sr25519(pair)),
+			None => match find_key::<ed25519::Pair>(keystore, account)? {
+				Some(pair) => Some(AccountKey::Ed25519(pair)),
+				None => find_key::<ecdsa::Pair>(keystore, account)?.map(AccountKey::Ecdsa),
+			},
+		})
+	}
+
+	fn sign(&self, payload: &[u8]) -> Signature {
+		match self {
+			AccountKey::Sr25519(pair) => pair.sign_payload(payload),
+			AccountKey::Ed25519(pair) => pair.sign_payload(payload),
+			AccountKey::Ecdsa(pair) => SignPayload::<Signature>::sign_payload(pair, payload),
+		}
+	}
+}
+
+/// The account key of type `P` of `keystore` signing for `account`, `None` if there is none.
+fn find_key<P: Pair + SignPayload<Signature>>(
+	keystore: &Store,
+	account: &AccountId,
+) -> sc_keystore::Result<Option<P>> {
+	for public in keystore.public_keys_by_type::<P::Public>(key_types::ACCOUNT)? {
+		let pair = keystore.key_pair_by_type::<P>(&public, key_types::ACCOUNT)?;
+		if pair.signer().into_account() == *account {
+			return Ok(Some(pair))
+		}
+	}
+	Ok(None)
+}
+
+fn error(code: i64, message: &str, e: impl std::fmt::Debug) -> Error {
+	Error {
+		code: ErrorCode::ServerError(code),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, P> ManagedAuthorApi<TxHash<P>> for ManagedAuthor<C, P> where
+	C: ProvideRuntimeApi<Block>,
+	C: sc_client::blockchain::HeaderBackend<Block>,
+	C: Send + Sync + 'static,
+	C::Api: Core<Block>,
+	C::Api: AccountNonceApi<Block, AccountId, Index>,
+	C::Api: MortalityApi<Block>,
+	P: TransactionPool<Block = Block> + 'static,
+{
+	type Metadata = sc_rpc::Metadata;
+
+	fn submit_with_managed_nonce(
+		&self,
+		metadata: Self::Metadata,
+		call: Bytes,
+		account: AccountId,
+	) -> FutureResult<ManagedSubmission<TxHash<P>>> {
+		let create_extrinsic = || {
+			let key = AccountKey::find(&self.keystore.read(), &account)
+				.map_err(|e| error(KEYSTORE_ERROR, "Unable to read the keystore.", e))?
+				.ok_or_else(|| {
+					error(KEYSTORE_ERROR, "Account key not found in the keystore.", &account)
+				})?;
+			let call = Call::decode(&mut &call[..])
+				.map_err(|e| error(BAD_CALL, "Unable to decode the call.", e))?;
+
+			let info = self.client.info();
+			let at = BlockId::hash(info.best_hash);
+			let api = self.client.runtime_api();
+			let version = api.version(&at)
+				.map_err(|e| error(RUNTIME_ERROR, "Unable to query runtime version.", e))?;
+			let period = api.era_period(&at)
+				.map_err(|e| error(RUNTIME_ERROR, "Unable to query era period.", e))?;
+			let nonce = api.account_nonce(&at, account.clone())
+				.map_err(|e| error(RUNTIME_ERROR, "Unable to query nonce.", e))?;
+			let nonce = adjust_nonce(&*self.pool, account.clone(), nonce);
+			let reservation = {
+				let mut nonces = self.nonces.lock();
+				let nonce = nonces.get(&account).map_or(nonce, |next| nonce.max(*next));
+				nonces.insert(account.clone(), nonce + 1);
+				Reservation { nonces: self.nonces.clone(), account: account.clone(), nonce }
+			};
+			let nonce = reservation.nonce;
+
+			let extra: SignedExtra = (
+				frame_system::CheckVersion::new(),
+				frame_system::CheckGenesis::new(),
+				frame_system::CheckEra::from(Era::mortal(period, info.best_number.into())),
+				frame_system::CheckNonce::from(nonce),
+				frame_system::CheckWeight::new(),
+				pallet_asset_tx_payment::ChargeAssetTxPayment::from(0, None),
+				Default::default(),
+			);
+			let additional_signed = (
+				version.spec_version,
+				info.genesis_hash,
+				info.best_hash,
+				(),
+				(),
+				(),
+				(),
+			);
+			let payload = SignedPayload::from_raw(call, extra, additional_signed);
+			let signature = payload.using_encoded(|payload| key.sign(payload));
+			let (call, extra, _) = payload.deconstruct();
+			let xt = UncheckedExtrinsic::new_signed(
+				call,
+				Indices::unlookup(account.clone()),
+				signature,
+				extra,
+			);
+
+			let xt = Decode::decode(&mut &xt.encode()[..])
+				.expect("`UncheckedExtrinsic` encodes to a valid opaque extrinsic; qed");
+			Ok((at, xt, reservation))
+		};
+
+		let admission = match self.submissions.admit(metadata.client()) {
+			Ok(admission) => admission,
+			Err(e) => return Box::new(result(Err(error(
+				POOL_OVERLOADED,
+				"Too many transactions are waiting for validation, retry later.",
+				e,
+			)))),
+		};
+		let (at, xt, reservation) = match create_extrinsic() {
+			Ok(extrinsic) => extrinsic,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+		let pool = self.pool.clone();
+		Box::new(Compat::new(admission.then(move |permit| {
+			pool.submit_one(&at, xt).map(move |submitted| {
+				drop(permit);
+				let nonce = reservation.nonce;
+				drop(reservation);
+				submitted
+					.map(|hash| ManagedSubmission { hash, nonce })
+					.map_err(|e| error(POOL_ERROR, "Unable to submit transaction.", e))
+			})
+		})))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::futures::Future as _;
+	use node_runtime::{
+		BalancesCall, CheckedExtrinsic, MaximumBlockLength, VERSION, constants::currency::DOLLARS,
+	};
+	use node_testing::{
+		client::{Client, TestClientBuilder, TestClientBuilderExt},
+		keyring::{alice, bob, sign, signed_extra},
+	};
+	use sc_transaction_pool::{BasicPool, FullChainApi};
+
+	type Pool = BasicPool<FullChainApi<Client, Block>, Block>;
+
+	fn author(submissions: SubmissionQueue) -> ManagedAuthor<Client, Pool> {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let pool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		let keystore = sc_keystore::Store::new_in_memory();
+		keystore.write()
+			.insert_ephemeral_from_seed_by_type::<sr25519::Pair>("//Alice", key_types::ACCOUNT)
+			.unwrap();
+		keystore.write()
+			.insert_ephemeral_from_seed_by_type::<sr25519::Pair>("//Bob", key_types::BABE)
+			.unwrap();
+		ManagedAuthor::new(client, pool, keystore, submissions)
+	}
+
+	fn transfer() -> Call {
+		Call::Balances(BalancesCall::transfer(bob().into(), 10 * DOLLARS))
+	}
+
+	fn submit(
+		author: &ManagedAuthor<Client, Pool>,
+		call: Call,
+		account: AccountId,
+	) -> FutureResult<ManagedSubmission<TxHash<Pool>>> {
+		author.submit_with_managed_nonce(Default::default(), Bytes(call.encode()), account)
+	}
+
+	#[test]
+	fn signs_after_the_transactions_of_the_pool() {
+		let author = author(Default::default());
+		let xt = sign(CheckedExtrinsic {
+			signed: Some((alice(), signed_extra(0, 0))),
+			function: transfer(),
+		}, VERSION.spec_version, author.client.info().genesis_hash.into());
+		let xt = Decode::decode(&mut &xt.encode()[..]).unwrap();
+		futures::executor::block_on(author.pool.submit_one(&BlockId::number(0), xt)).unwrap();
+
+		assert_eq!(submit(&author, transfer(), alice()).wait().unwrap().nonce, 1);
+	}
+
+	#[test]
+	fn concurrent_submissions_are_signed_with_consecutive_nonces() {
+		let author = author(Default::default());
+
+		let first = submit(&author, transfer(), alice());
+		let second = submit(&author, transfer(), alice());
+
+		assert_eq!(second.wait().unwrap().nonce, 1);
+		assert_eq!(first.wait().unwrap().nonce, 0);
+		assert!(author.nonces.lock().is_empty());
+		assert_eq!(submit(&author, transfer(), alice()).wait().unwrap().nonce, 2);
+	}
+
+	#[test]
+	fn the_nonce_of_a_rejected_transaction_is_released() {
+		let author = author(Default::default());
+
+		// Longer than the blocks.
+		let remark = vec![0; MaximumBlockLength::get() as usize];
+		let error = submit(&author, Call::System(frame_system::Call::remark(remark)), alice())
+			.wait()
+			.unwrap_err();
+
+		assert_eq!(error.code, ErrorCode::ServerError(POOL_ERROR));
+		assert!(author.nonces.lock().is_empty());
+		assert_eq!(submit(&author, transfer(), alice()).wait().unwrap().nonce, 0);
+	}
+
+	#[test]
+	fn only_account_keys_sign() {
+		let author = author(Default::default());
+
+		let error = submit(&author, transfer(), bob()).wait().unwrap_err();
+
+		assert_eq!(error.code, ErrorCode::ServerError(KEYSTORE_ERROR));
+	}
+
+	#[test]
+	fn ecdsa_account_keys_sign() {
+		let author = author(Default::default());
+		let pair = author.keystore.write()
+			.insert_ephemeral_from_seed_by_type::<ecdsa::Pair>("//Charlie", key_types::ACCOUNT)
+			.unwrap();
+		let account = SignPayload::<Signature>::signer(&pair).into_account();
+
+		let error = submit(&author, transfer(), account).wait().unwrap_err();
+
+		// The signature is valid, but the account can't pay the fees.
+		assert_eq!(error.code, ErrorCode::ServerError(POOL_ERROR));
+		assert!(format!("{:?}", error.data).contains("Payment"));
+	}
+
+	#[test]
+	fn submissions_wait_in_the_queue_of_the_author_rpc() {
+		let author = author(SubmissionQueue::new(0, 0, 0));
+
+		let error = submit(&author, transfer(), alice()).wait().unwrap_err();
+
+		assert_eq!(error.code, ErrorCode::ServerError(POOL_OVERLOADED));
+		assert!(author.nonces.lock().is_empty());
+	}
+}
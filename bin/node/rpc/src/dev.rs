@@ -0,0 +1,571 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC methods only available on development chains.
+
+use std::{sync::Arc, time::Duration};
+
+use codec::{Decode, Encode};
+use futures::{compat::Compat, future::{ready, Either, TryFutureExt}};
+use jsonrpc_core::{Error, ErrorCode, Result, futures::future::{result, Future}};
+use jsonrpc_derive::rpc;
+use node_primitives::{AccountId, Balance, Block, BlockNumber, Hash, Header, Index};
+use node_runtime::{BalancesCall, Call, Indices, SignedExtra, SignedPayload, UncheckedExtrinsic};
+use sp_api::{Core, ProvideRuntimeApi};
+use sp_core::{Pair, twox_128};
+use sp_keyring::AccountKeyring;
+use sp_runtime::{generic::{BlockId, Era}, traits::{Header as _, StaticLookup}};
+use parking_lot::Mutex;
+use sc_client::blockchain::HeaderBackend;
+use sc_client_api::backend::{Backend, StateBackend};
+use sc_consensus_slots::{Clock, OffsetClock};
+use sc_finality_grandpa::VotingRule;
+use sp_transaction_pool::{InPoolTransaction, TransactionFor, TransactionPool, TxHash};
+use substrate_frame_rpc_system::{AccountNonceApi, adjust_nonce};
+
+pub use self::gen_client::Client as DevClient;
+
+/// Future that resolves to the result of a dev RPC call.
+pub type FutureResult<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
+
+const RUNTIME_ERROR: i64 = 1;
+const POOL_ERROR: i64 = 2;
+const SNAPSHOT_ERROR: i64 = 3;
+const CLOCK_ERROR: i64 = 4;
+
+/// Maximum time in milliseconds the clock can be moved forward by at once.
+pub const MAX_TIME_TRAVEL: u64 = 366 * 24 * 60 * 60 * 1000;
+
+/// Maximum number of snapshots kept at once.
+pub const MAX_SNAPSHOTS: usize = 256;
+
+/// Development chain RPC methods.
+#[rpc]
+pub trait DevApi<Hash, AccountId, Balance> {
+	/// Transfer `amount` to `account` from the `Alice` development account.
+	///
+	/// Returns the hash of the transfer submitted to the transaction pool.
+	#[rpc(name = "dev_faucet")]
+	fn faucet(&self, account: AccountId, amount: Balance) -> FutureResult<Hash>;
+
+	/// Take a snapshot of the best block and the transactions of the pool.
+	///
+	/// Returns the id of the snapshot, to be passed to `dev_revertToSnapshot`. Fails if
+	/// `MAX_SNAPSHOTS` snapshots are kept, until reverting to one of them discards some.
+	#[rpc(name = "dev_snapshot")]
+	fn snapshot(&self) -> Result<u32>;
+
+	/// Revert the chain to the block of the given snapshot and replace the transactions of
+	/// the pool with the ones of the snapshot.
+	///
+	/// Finalized blocks are never reverted. The GRANDPA voter of the node doesn't finalize the
+	/// blocks after the oldest snapshot, see `SnapshotVotingRule`. The snapshot and all snapshots
+	/// taken after it are discarded.
+	#[rpc(name = "dev_revertToSnapshot")]
+	fn revert_to_snapshot(&self, id: u32) -> FutureResult<()>;
+
+	/// Move the clock of the node forward to the given timestamp, in milliseconds since the unix
+	/// epoch.
+	///
+	/// The next block is authored right away, with the given timestamp or one just after it.
+	/// The clock can't be moved backwards, nor forward by more than `MAX_TIME_TRAVEL`.
+	#[rpc(name = "dev_setBlockTimestamp")]
+	fn set_block_timestamp(&self, timestamp: u64) -> Result<()>;
+
+	/// Move the clock of the node forward by the given number of milliseconds, at most
+	/// `MAX_TIME_TRAVEL`, skipping the slots in between.
+	///
+	/// Returns the new timestamp of the clock.
+	#[rpc(name = "dev_increaseTime")]
+	fn increase_time(&self, milliseconds: u64) -> Result<u64>;
+}
+
+/// State of the chain and the pool to revert to.
+struct Snapshot<P: TransactionPool> {
+	number: BlockNumber,
+	hash: Hash,
+	transactions: Vec<TransactionFor<P>>,
+}
+
+/// GRANDPA voting rule not voting for the blocks after the oldest snapshot, so that they are
+/// not finalized and can be reverted.
+#[derive(Clone, Default)]
+pub struct SnapshotVotingRule(Arc<Mutex<Option<BlockNumber>>>);
+
+impl<B: HeaderBackend<Block>> VotingRule<Block, B> for SnapshotVotingRule {
+	fn restrict_vote(
+		&self,
+		backend: &B,
+		base: &Header,
+		_best_target: &Header,
+		current_target: &Header,
+	) -> Option<(Hash, BlockNumber)> {
+		let snapshot_number = (*self.0.lock())?;
+		let target_number = std::cmp::max(snapshot_number, *base.number());
+		if target_number >= *current_target.number() {
+			return None;
+		}
+
+		let mut target = current_target.clone();
+		while *target.number() > target_number {
+			target = backend.header(BlockId::Hash(*target.parent_hash())).ok()??;
+		}
+		Some((target.hash(), target_number))
+	}
+}
+
+/// Implementation of the development chain RPC methods.
+pub struct Dev<C, P: TransactionPool, Be> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	backend: Arc<Be>,
+	snapshots: Mutex<Vec<Snapshot<P>>>,
+	voting_rule: SnapshotVotingRule,
+	clock: OffsetClock,
+}
+
+impl<C, P, Be> Dev<C, P, Be> where
+	C: HeaderBackend<Block>,
+	P: TransactionPool,
+	Be: Backend<Block>,
+{
+	/// Create new `Dev` given client, transaction pool, backend, the voting rule of the GRANDPA
+	/// voter and the clock of the slot worker.
+	///
+	/// The clock is moved forward to the timestamp of the best block if that is in the future,
+	/// for the chain to go on after a restart of a node that travelled in time.
+	pub fn new(
+		client: Arc<C>,
+		pool: Arc<P>,
+		backend: Arc<Be>,
+		voting_rule: SnapshotVotingRule,
+		clock: OffsetClock,
+	) -> Self {
+		let dev = Dev { client, pool, backend, snapshots: Mutex::new(Vec::new()), voting_rule, clock };
+		if let Some(timestamp) = dev.best_block_timestamp() {
+			let _ = dev.clock.advance_to(Duration::from_millis(timestamp));
+		}
+		dev
+	}
+
+	/// Timestamp of the best block, read from the storage of the timestamp module.
+	fn best_block_timestamp(&self) -> Option<u64> {
+		let key = [twox_128(b"Timestamp"), twox_128(b"Now")].concat();
+		let state = self.backend.state_at(BlockId::hash(self.client.info().best_hash)).ok()?;
+		let timestamp = state.storage(&key).ok()??;
+		Decode::decode(&mut &timestamp[..]).ok()
+	}
+}
+
+impl<C, P: TransactionPool, Be> Dev<C, P, Be> {
+	/// Move the clock forward by the given number of milliseconds.
+	fn travel(&self, milliseconds: u64) -> Result<()> {
+		if milliseconds > MAX_TIME_TRAVEL {
+			return Err(clock_error(format!(
+				"The clock can't be moved forward by more than {} milliseconds at once.",
+				MAX_TIME_TRAVEL,
+			)))
+		}
+		self.clock.advance(Duration::from_millis(milliseconds));
+		Ok(())
+	}
+
+	/// Stop finalizing the blocks after the oldest snapshot left.
+	fn hold_finality(&self, snapshots: &[Snapshot<P>]) {
+		*self.voting_rule.0.lock() = snapshots.first().map(|snapshot| snapshot.number);
+	}
+
+	/// All the transactions of the pool, ready and future.
+	fn pool_transactions(&self) -> impl Iterator<Item=Arc<P::InPoolTransaction>> {
+		self.pool.ready().chain(self.pool.futures())
+	}
+}
+
+fn snapshot_error(message: impl Into<String>) -> Error {
+	Error {
+		code: ErrorCode::ServerError(SNAPSHOT_ERROR),
+		message: message.into(),
+		data: None,
+	}
+}
+
+fn clock_error(message: impl Into<String>) -> Error {
+	Error {
+		code: ErrorCode::ServerError(CLOCK_ERROR),
+		message: message.into(),
+		data: None,
+	}
+}
+
+fn pool_error(message: &str, e: impl std::fmt::Debug) -> Error {
+	Error {
+		code: ErrorCode::ServerError(POOL_ERROR),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+fn runtime_error(message: &str, e: impl std::fmt::Debug) -> Error {
+	Error {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, P, Be> DevApi<TxHash<P>, AccountId, Balance> for Dev<C, P, Be> where
+	C: ProvideRuntimeApi<Block>,
+	C: sc_client::blockchain::HeaderBackend<Block>,
+	C: Send + Sync + 'static,
+	C::Api: Core<Block>,
+	C::Api: AccountNonceApi<Block, AccountId, Index>,
+	P: TransactionPool<Block = Block> + 'static,
+	Be: Backend<Block> + 'static,
+{
+	fn faucet(&self, account: AccountId, amount: Balance) -> FutureResult<TxHash<P>> {
+		let create_transfer = || {
+			let info = self.client.info();
+			let at = BlockId::hash(info.best_hash);
+			let api = self.client.runtime_api();
+			let version = api.version(&at)
+				.map_err(|e| runtime_error("Unable to query runtime version.", e))?;
+
+			let funder = AccountKeyring::Alice;
+			let nonce = api.account_nonce(&at, funder.to_account_id())
+				.map_err(|e| runtime_error("Unable to query nonce.", e))?;
+			let nonce = adjust_nonce(&*self.pool, funder.to_account_id(), nonce);
+
+			let call = Call::Balances(BalancesCall::transfer(Indices::unlookup(account), amount));
+			let extra: SignedExtra = (
+				frame_system::CheckVersion::new(),
+				frame_system::CheckGenesis::new(),
+				frame_system::CheckEra::from(Era::Immortal),
+				frame_system::CheckNonce::from(nonce),
+				frame_system::CheckWeight::new(),
+				pallet_asset_tx_payment::ChargeAssetTxPayment::from(0, None),
+				Default::default(),
+			);
+			let additional_signed = (
+				version.spec_version,
+				info.genesis_hash,
+				info.genesis_hash,
+				(),
+				(),
+				(),
+				(),
+			);
+			let payload = SignedPayload::from_raw(call, extra, additional_signed);
+			let signature = payload.using_encoded(|payload| funder.pair().sign(payload));
+			let (call, extra, _) = payload.deconstruct();
+			let xt = UncheckedExtrinsic::new_signed(
+				call,
+				Indices::unlookup(funder.to_account_id()),
+				signature.into(),
+				extra,
+			);
+
+			let xt = Decode::decode(&mut &xt.encode()[..])
+				.expect("`UncheckedExtrinsic` encodes to a valid opaque extrinsic; qed");
+			Ok((at, xt))
+		};
+
+		let (at, xt) = match create_transfer() {
+			Ok(transfer) => transfer,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+		Box::new(Compat::new(
+			self.pool.submit_one(&at, xt).map_err(|e| pool_error("Unable to submit transfer.", e))
+		))
+	}
+
+	fn snapshot(&self) -> Result<u32> {
+		let info = self.client.info();
+		let mut snapshots = self.snapshots.lock();
+		if snapshots.len() >= MAX_SNAPSHOTS {
+			return Err(snapshot_error(format!(
+				"Too many snapshots, at most {} are kept. Revert to one of them first.",
+				MAX_SNAPSHOTS,
+			)))
+		}
+		snapshots.push(Snapshot {
+			number: info.best_number,
+			hash: info.best_hash,
+			transactions: self.pool_transactions().map(|tx| tx.data().clone()).collect(),
+		});
+		self.hold_finality(&snapshots);
+		Ok(snapshots.len() as u32 - 1)
+	}
+
+	fn revert_to_snapshot(&self, id: u32) -> FutureResult<()> {
+		let revert = || {
+			let mut snapshots = self.snapshots.lock();
+			let snapshot = snapshots.get(id as usize)
+				.ok_or_else(|| snapshot_error(format!("Unknown snapshot {}.", id)))?;
+
+			let info = self.client.info();
+			let hash = self.client.hash(snapshot.number)
+				.map_err(|e| runtime_error("Unable to query block hash.", e))?;
+			if hash != Some(snapshot.hash) {
+				return Err(snapshot_error("The snapshot block is no longer part of the best chain."))
+			}
+			if info.finalized_number > snapshot.number {
+				return Err(snapshot_error("Blocks after the snapshot block are finalized."))
+			}
+
+			let to_revert = info.best_number - snapshot.number;
+			let reverted = self.backend.revert(to_revert, false)
+				.map_err(|e| runtime_error("Unable to revert blocks.", e))?;
+			if reverted != to_revert {
+				return Err(snapshot_error(format!(
+					"Only {} of {} blocks could be reverted, the others are finalized or their state is pruned.",
+					reverted,
+					to_revert,
+				)))
+			}
+
+			let pending = self.pool_transactions().map(|tx| tx.hash().clone()).collect::<Vec<_>>();
+			self.pool.remove(&pending);
+
+			let snapshot = snapshots.drain(id as usize..).next().expect("snapshot checked above; qed");
+			self.hold_finality(&snapshots);
+			Ok(snapshot)
+		};
+
+		let resubmit = match revert() {
+			Ok(Snapshot { hash, transactions: xts, .. }) if !xts.is_empty() => Either::Left(
+				self.pool.submit_at(&BlockId::hash(hash), xts)
+					.map_ok(|_| ())
+					.map_err(|e| pool_error("Unable to resubmit snapshot transactions.", e))
+			),
+			Ok(_) => Either::Right(ready(Ok(()))),
+			Err(e) => Either::Right(ready(Err(e))),
+		};
+		Box::new(Compat::new(resubmit))
+	}
+
+	fn set_block_timestamp(&self, timestamp: u64) -> Result<()> {
+		let now = self.clock.now().as_millis() as u64;
+		match timestamp.checked_sub(now) {
+			Some(milliseconds) => self.travel(milliseconds),
+			None => Err(clock_error(format!(
+				"The clock can't be moved backwards, its timestamp is already {}.",
+				now,
+			))),
+		}
+	}
+
+	fn increase_time(&self, milliseconds: u64) -> Result<u64> {
+		self.travel(milliseconds)?;
+		Ok(self.clock.now().as_millis() as u64)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use node_primitives::Timestamp;
+	use node_runtime::{CheckedExtrinsic, TimestampCall, VERSION, constants::{currency::DOLLARS, time::MILLISECS_PER_BLOCK}};
+	use node_testing::{
+		client::{Client, ClientBlockImportExt, ClientExt, TestClientBuilder, TestClientBuilderExt},
+		keyring::{bob, sign, signed_extra},
+	};
+	use sc_consensus_slots::clock::TimestampInherentDataProvider;
+	use sc_transaction_pool::{BasicPool, FullChainApi};
+	use sp_consensus::BlockOrigin;
+	use sp_timestamp::TimestampInherentData;
+	use sp_runtime::traits::Header as _;
+
+	type Pool = BasicPool<FullChainApi<Client, Block>, Block>;
+
+	fn dev() -> (Arc<Client>, Dev<Client, Pool, node_testing::client::Backend>) {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = Arc::new(builder.build());
+		let pool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		(client.clone(), Dev::new(client, pool, backend, Default::default(), Default::default()))
+	}
+
+	fn import_block(client: &mut Arc<Client>) -> Hash {
+		let timestamp: Timestamp = (client.info().best_number as u64 + 1) * MILLISECS_PER_BLOCK;
+		import_block_at(client, timestamp)
+	}
+
+	fn import_block_at(client: &mut Arc<Client>, timestamp: Timestamp) -> Hash {
+		let xt = UncheckedExtrinsic::new_unsigned(Call::Timestamp(TimestampCall::set(timestamp)));
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push(Decode::decode(&mut &xt.encode()[..]).unwrap()).unwrap();
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		client.import(BlockOrigin::Own, block).unwrap();
+		hash
+	}
+
+	fn future_transfer(genesis_hash: Hash) -> sp_runtime::OpaqueExtrinsic {
+		let xt = sign(CheckedExtrinsic {
+			signed: Some((bob(), signed_extra(5, 0))),
+			function: Call::Balances(BalancesCall::transfer(Indices::unlookup(bob()), DOLLARS)),
+		}, VERSION.spec_version, genesis_hash.into());
+		Decode::decode(&mut &xt.encode()[..]).unwrap()
+	}
+
+	#[test]
+	fn faucet_submits_a_transfer_signed_by_alice() {
+		let (_, dev) = dev();
+
+		let hash = dev.faucet(bob(), 10 * DOLLARS).wait().unwrap();
+
+		let ready = dev.pool.ready().collect::<Vec<_>>();
+		assert_eq!(ready.len(), 1);
+		assert_eq!(*ready[0].hash(), hash);
+		let xt = UncheckedExtrinsic::decode(&mut &ready[0].data().encode()[..]).unwrap();
+		assert_eq!(xt.function, Call::Balances(BalancesCall::transfer(Indices::unlookup(bob()), 10 * DOLLARS)));
+		let (signer, _, _) = xt.signature.unwrap();
+		assert_eq!(signer, Indices::unlookup(AccountKeyring::Alice.to_account_id()));
+	}
+
+	#[test]
+	fn reverts_the_chain_and_the_pool() {
+		let (mut client, dev) = dev();
+		let genesis_hash = client.genesis_hash();
+		dev.faucet(bob(), DOLLARS).wait().unwrap();
+		assert_eq!(dev.snapshot().unwrap(), 0);
+
+		import_block(&mut client);
+		dev.faucet(bob(), DOLLARS).wait().unwrap();
+		block_on(dev.pool.submit_one(&BlockId::hash(genesis_hash), future_transfer(genesis_hash))).unwrap();
+		assert_eq!((dev.pool.status().ready, dev.pool.status().future), (2, 1));
+		assert_eq!(dev.snapshot().unwrap(), 1);
+
+		dev.revert_to_snapshot(0).wait().unwrap();
+
+		assert_eq!(client.info().best_hash, genesis_hash);
+		assert_eq!((dev.pool.status().ready, dev.pool.status().future), (1, 0));
+		assert!(dev.revert_to_snapshot(1).wait().is_err());
+		assert_eq!(*dev.voting_rule.0.lock(), None);
+	}
+
+	#[test]
+	fn snapshots_are_capped() {
+		let (_, dev) = dev();
+		for id in 0..MAX_SNAPSHOTS {
+			assert_eq!(dev.snapshot().unwrap(), id as u32);
+		}
+
+		assert_eq!(dev.snapshot().unwrap_err().code, ErrorCode::ServerError(SNAPSHOT_ERROR));
+
+		// reverting discards the snapshot and the ones taken after it.
+		dev.revert_to_snapshot(1).wait().unwrap();
+		assert_eq!(dev.snapshot().unwrap(), 1);
+	}
+
+	#[test]
+	fn restores_the_future_transactions_of_the_snapshot() {
+		let (mut client, dev) = dev();
+		let genesis_hash = client.genesis_hash();
+		block_on(dev.pool.submit_one(&BlockId::hash(genesis_hash), future_transfer(genesis_hash))).unwrap();
+		dev.snapshot().unwrap();
+		import_block(&mut client);
+
+		dev.revert_to_snapshot(0).wait().unwrap();
+
+		assert_eq!((dev.pool.status().ready, dev.pool.status().future), (0, 1));
+	}
+
+	#[test]
+	fn does_not_revert_finalized_blocks() {
+		let (mut client, dev) = dev();
+		dev.snapshot().unwrap();
+		let hash = import_block(&mut client);
+		client.finalize_block(BlockId::hash(hash), None).unwrap();
+
+		let error = dev.revert_to_snapshot(0).wait().unwrap_err();
+
+		assert_eq!(error.code, ErrorCode::ServerError(SNAPSHOT_ERROR));
+		assert_eq!(client.info().best_hash, hash);
+		// The snapshot is kept.
+		assert_eq!(dev.revert_to_snapshot(0).wait().unwrap_err().message, error.message);
+	}
+
+	#[test]
+	fn does_not_vote_for_blocks_after_the_oldest_snapshot() {
+		let (mut client, dev) = dev();
+		let (first, second) = (import_block(&mut client), import_block(&mut client));
+		let header = |hash| client.header(&BlockId::hash(hash)).unwrap().unwrap();
+		let genesis = header(client.genesis_hash());
+		let rule = dev.voting_rule.clone();
+		let restrict = |target| VotingRule::<Block, Client>::restrict_vote(
+			&rule,
+			&*client,
+			&genesis,
+			&header(second),
+			&header(target),
+		);
+		assert_eq!(restrict(second), None);
+
+		dev.snapshot().unwrap();
+		assert_eq!(restrict(second), None);
+		*dev.voting_rule.0.lock() = Some(1);
+		assert_eq!(restrict(second), Some((first, 1)));
+		assert_eq!(restrict(first), None);
+	}
+
+	#[test]
+	fn moves_the_clock_forward() {
+		let (_, dev) = dev();
+		let hour = 60 * 60 * 1000;
+		let start = dev.clock.now().as_millis() as u64;
+
+		let now = dev.increase_time(hour).unwrap();
+		assert!(now >= start + hour);
+
+		dev.set_block_timestamp(now + 2 * hour).unwrap();
+		assert!(dev.clock.now().as_millis() as u64 >= now + 2 * hour);
+		assert!(dev.clock.offset() <= Duration::from_millis(3 * hour));
+
+		let error = dev.set_block_timestamp(now).unwrap_err();
+		assert_eq!(error.code, ErrorCode::ServerError(CLOCK_ERROR));
+		let error = dev.increase_time(MAX_TIME_TRAVEL + 1).unwrap_err();
+		assert_eq!(error.code, ErrorCode::ServerError(CLOCK_ERROR));
+		assert!(dev.clock.offset() <= Duration::from_millis(3 * hour));
+	}
+
+	#[test]
+	fn timestamps_of_authored_blocks_follow_the_clock() {
+		let (_, dev) = dev();
+		let providers = sp_inherents::InherentDataProviders::new();
+		providers.register_provider(TimestampInherentDataProvider(dev.clock.clone())).unwrap();
+		let timestamp = dev.clock.now().as_millis() as u64 + 24 * 60 * 60 * 1000;
+
+		dev.set_block_timestamp(timestamp).unwrap();
+
+		let data = providers.create_inherent_data().unwrap();
+		assert!(data.timestamp_inherent_data().unwrap() >= timestamp);
+	}
+
+	#[test]
+	fn catches_up_with_the_timestamp_of_the_best_block() {
+		let (mut client, dev) = dev();
+		let day = 24 * 60 * 60 * 1000;
+		let timestamp = dev.clock.now().as_millis() as u64 + day;
+		import_block_at(&mut client, timestamp);
+
+		let dev = Dev::new(client, dev.pool, dev.backend, Default::default(), Default::default());
+
+		assert!(dev.clock.now().as_millis() as u64 >= timestamp);
+		assert!(dev.clock.offset() <= Duration::from_millis(day));
+	}
+}
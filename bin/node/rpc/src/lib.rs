@@ -31,11 +31,14 @@
 
 use std::sync::Arc;
 
-use node_primitives::{Block, AccountId, Index, Balance};
-use node_runtime::UncheckedExtrinsic;
+use node_primitives::{Block, BlockNumber, AccountId, Index, Balance, Hash};
+use node_runtime::{Call, UncheckedExtrinsic};
 use sp_api::ProvideRuntimeApi;
 use sp_transaction_pool::TransactionPool;
 
+pub mod author;
+pub mod dev;
+
 /// Light client extra dependencies.
 pub struct LightDeps<F> {
 	/// Remote access to the blockchain (async).
@@ -54,37 +57,99 @@ impl<F> LightDeps<F> {
 	}
 }
 
+/// Extra dependencies for the RPC methods of development chains.
+pub struct DevDeps {
+	/// Voting rule of the GRANDPA voter, holding back the finality of the snapshot blocks.
+	pub voting_rule: dev::SnapshotVotingRule,
+	/// Clock of the slot worker, moved forward by the time travel methods.
+	pub clock: sc_consensus_slots::OffsetClock,
+}
+
+/// Extra dependencies for the BABE RPC methods of full clients.
+pub struct BabeDeps {
+	/// BABE state shared with the import queue.
+	pub babe_link: sc_consensus_babe::BabeLink<Block>,
+	/// The keystore holding the BABE keys of the node.
+	pub keystore: sc_keystore::KeyStorePtr,
+}
+
+/// Extra dependencies for the GRANDPA RPC methods of full clients.
+pub struct GrandpaDeps {
+	/// State of the current round of the voter or observer.
+	pub round_state: sc_finality_grandpa::SharedRoundState<Hash, BlockNumber>,
+	/// Import of the justifications submitted through RPC, the one of the import queue.
+	pub justification_import: sp_consensus::import_queue::BoxJustificationImport<Block>,
+}
+
+/// Extra dependencies for the author RPC methods of full clients.
+pub struct AuthorDeps {
+	/// The keystore holding the account keys transactions are signed with.
+	pub keystore: sc_keystore::KeyStorePtr,
+	/// Queue of the transactions waiting for validation, the one of the author RPC.
+	pub submissions: sc_rpc::author::SubmissionQueue,
+}
+
 /// Instantiate all RPC extensions.
 ///
-/// If you provide `LightDeps`, the system is configured for light client.
-pub fn create<C, P, M, F>(
+/// If you provide `LightDeps`, the system is configured for light client. The `babe_*` and
+/// `grandpa_*` methods are only added to full clients given `BabeDeps` and `GrandpaDeps`, and
+/// `author_submitWithManagedNonce` to full clients given `AuthorDeps`.
+/// The `dev_*` methods, which spend dev account funds, revert blocks and move the clock, are only
+/// added to full clients given `DevDeps`, of development chains.
+pub fn create<C, P, F, Be>(
 	client: Arc<C>,
 	pool: Arc<P>,
+	backend: Arc<Be>,
 	light_deps: Option<LightDeps<F>>,
-) -> jsonrpc_core::IoHandler<M> where
+	babe_deps: Option<BabeDeps>,
+	grandpa_deps: Option<GrandpaDeps>,
+	author_deps: Option<AuthorDeps>,
+	dev_deps: Option<DevDeps>,
+) -> jsonrpc_core::IoHandler<sc_rpc::Metadata> where
 	C: ProvideRuntimeApi<Block>,
 	C: sc_client::blockchain::HeaderBackend<Block>,
+	C: sc_client::blockchain::HeaderMetadata<Block, Error = sc_client::blockchain::Error>,
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
+	C::Api: substrate_frame_rpc_system::MortalityApi<Block>,
+	C::Api: substrate_frame_rpc_system::BlockBuilder<Block>,
+	C::Api: substrate_frame_rpc_system::CallSimulationApi<Block, AccountId, Call>,
+	C::Api: sp_api::Metadata<Block>,
+	C::Api: sp_api::Core<Block>,
+	C::Api: sp_api::ApiExt<Block, StateBackend = sc_client_api::backend::StateBackendFor<Be, Block>>,
 	C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance, UncheckedExtrinsic>,
+	C::Api: pallet_validator_performance_rpc::ValidatorPerformanceRuntimeApi<Block, AccountId>,
+	C::Api: pallet_staking_rpc::StakingSlashingRuntimeApi<Block, AccountId, Balance>,
+	C::Api: pallet_name_service_rpc::NameServiceRuntimeApi<Block, AccountId, BlockNumber>,
 	F: sc_client::light::fetcher::Fetcher<Block> + 'static,
-	P: TransactionPool + 'static,
-	M: jsonrpc_core::Metadata + Default,
+	Be: sc_client_api::backend::Backend<Block> + 'static,
+	Be::State: sc_client_api::backend::StateBackend<sp_runtime::traits::HasherFor<Block>>,
+	P: TransactionPool<Block = Block> + 'static,
 {
-	use substrate_frame_rpc_system::{FullSystem, LightSystem, SystemApi};
+	use substrate_frame_rpc_system::{FullSimulation, FullSystem, LightSystem, SimulationApi, SystemApi};
 	use pallet_contracts_rpc::{Contracts, ContractsApi};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
+	use pallet_validator_performance_rpc::{ValidatorPerformanceRpc, ValidatorPerformanceApi};
+	use pallet_staking_rpc::{StakingSlashing, StakingSlashingApi};
+	use pallet_name_service_rpc::{NameService, NameServiceApi};
+	use sc_consensus_babe_rpc::{BabeApi, BabeRpcHandler};
+	use sc_finality_grandpa_rpc::{GrandpaApi, GrandpaRpcHandler};
+	use author::{ManagedAuthor, ManagedAuthorApi};
+	use dev::{Dev, DevApi};
 
 	let mut io = jsonrpc_core::IoHandler::default();
 
 	if let Some(LightDeps { remote_blockchain, fetcher }) = light_deps {
 		io.extend_with(
-			SystemApi::<AccountId, Index>::to_delegate(LightSystem::new(client, remote_blockchain, fetcher, pool))
+			SystemApi::<_, AccountId, Index>::to_delegate(LightSystem::new(client, remote_blockchain, fetcher, pool))
 		);
 	} else {
 		io.extend_with(
-			SystemApi::to_delegate(FullSystem::new(client.clone(), pool))
+			SystemApi::to_delegate(FullSystem::new(client.clone(), pool.clone()))
+		);
+		io.extend_with(
+			SimulationApi::<_, AccountId>::to_delegate(FullSimulation::<_, _, _, Call>::new(client.clone(), backend.clone()))
 		);
 
 		// Making synchronous calls in light client freezes the browser currently,
@@ -94,8 +159,155 @@ pub fn create<C, P, M, F>(
 			ContractsApi::to_delegate(Contracts::new(client.clone()))
 		);
 		io.extend_with(
-			TransactionPaymentApi::to_delegate(TransactionPayment::new(client))
+			TransactionPaymentApi::to_delegate(TransactionPayment::new(client.clone()))
 		);
+		io.extend_with(
+			ValidatorPerformanceApi::to_delegate(ValidatorPerformanceRpc::new(client.clone()))
+		);
+		io.extend_with(
+			StakingSlashingApi::to_delegate(StakingSlashing::new(client.clone()))
+		);
+		io.extend_with(
+			NameServiceApi::to_delegate(NameService::new(client.clone()))
+		);
+		if let Some(BabeDeps { babe_link, keystore }) = babe_deps {
+			io.extend_with(
+				BabeApi::to_delegate(BabeRpcHandler::new(client.clone(), babe_link, keystore))
+			);
+		}
+		if let Some(GrandpaDeps { round_state, justification_import }) = grandpa_deps {
+			io.extend_with(
+				GrandpaApi::to_delegate(GrandpaRpcHandler::new(client.clone(), round_state, justification_import))
+			);
+		}
+		if let Some(AuthorDeps { keystore, submissions }) = author_deps {
+			io.extend_with(
+				ManagedAuthorApi::to_delegate(ManagedAuthor::new(client.clone(), pool.clone(), keystore, submissions))
+			);
+		}
+		if let Some(DevDeps { voting_rule, clock }) = dev_deps {
+			io.extend_with(
+				DevApi::to_delegate(Dev::new(client, pool, backend, voting_rule, clock))
+			);
+		}
 	}
 	io
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use node_runtime::{BalancesCall, constants::currency::DOLLARS};
+	use node_testing::{client::{TestClientBuilder, TestClientBuilderExt}, keyring::{alice, bob}};
+	use serde_json::Value;
+	use sp_core::Bytes;
+	use substrate_frame_rpc_system::{FullSimulation, SimulationApi, MAX_SIMULATED_CALLS};
+
+	fn simulate_calls(calls: &[Call]) -> Value {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = Arc::new(builder.build());
+		let mut io = jsonrpc_core::IoHandler::default();
+		io.extend_with(
+			SimulationApi::<_, AccountId>::to_delegate(FullSimulation::<_, _, _, Call>::new(client, backend))
+		);
+
+		let calls = calls.iter().map(|call| Bytes(call.encode())).collect::<Vec<_>>();
+		let request = serde_json::json!({
+			"jsonrpc": "2.0",
+			"method": "system_simulateCalls",
+			"params": [alice(), calls],
+			"id": 1,
+		});
+		let response = io.handle_request_sync(&request.to_string()).expect("Calls get a response");
+		serde_json::from_str(&response).unwrap()
+	}
+
+	fn transfer() -> Call {
+		Call::Balances(BalancesCall::transfer(bob().into(), 10 * DOLLARS))
+	}
+
+	#[test]
+	fn reports_the_changes_of_each_call_over_the_previous_one() {
+		let response = simulate_calls(&[transfer(), transfer()]);
+		let effects = response["result"].as_array().expect("Calls are simulated");
+		assert_eq!(effects.len(), 2);
+		assert_eq!(effects[0]["result"], "success");
+		assert_eq!(effects[1]["result"], "success");
+
+		let first = effects[0]["storageChanges"].as_array().unwrap();
+		let second = effects[1]["storageChanges"].as_array().unwrap();
+		assert!(!first.is_empty());
+		let chained = second.iter()
+			.filter_map(|change| {
+				let previous = first.iter().find(|previous| previous["key"] == change["key"])?;
+				Some((previous, change))
+			})
+			.inspect(|(previous, change)| assert_eq!(change["old"], previous["new"]))
+			.count();
+		// At least the balances of both accounts are changed by both transfers.
+		assert!(chained >= 2);
+	}
+
+	#[test]
+	fn dispatches_calls_signed_by_the_account() {
+		let response = simulate_calls(&[Call::System(frame_system::Call::set_heap_pages(1))]);
+
+		assert_eq!(response["result"][0]["result"]["dispatchError"]["error"], "BadOrigin");
+		assert_eq!(response["result"][0]["storageChanges"], serde_json::json!([]));
+	}
+
+	#[test]
+	fn rejects_too_many_calls() {
+		let calls = vec![transfer(); MAX_SIMULATED_CALLS + 1];
+
+		assert!(simulate_calls(&calls)["error"].is_object());
+		assert!(simulate_calls(&calls[..MAX_SIMULATED_CALLS])["result"].is_array());
+	}
+
+	#[test]
+	fn rejects_calls_not_fitting_in_a_block() {
+		let response = simulate_calls(&[transfer(), Call::System(frame_system::Call::fill_block())]);
+
+		assert_eq!(response["error"]["message"], "The calls don't fit in a block.");
+	}
+
+	#[test]
+	fn describes_the_era_of_a_transaction_signed_for_another_chain() {
+		use jsonrpc_core::futures::Future;
+		use node_runtime::{CheckedExtrinsic, VERSION};
+		use node_testing::keyring::{sign, signed_extra};
+		use sc_rpc::author::{Author, AuthorApi};
+		use sc_transaction_pool::{BasicPool, FullChainApi};
+
+		let runtime = tokio::runtime::Runtime::new().unwrap();
+		let client = Arc::new(TestClientBuilder::new().build());
+		let pool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		let author = Author::new(
+			client,
+			pool,
+			sc_rpc::Subscriptions::new(Arc::new(runtime.executor())),
+			sc_keystore::Store::new_in_memory(),
+		);
+		let xt = sign(CheckedExtrinsic {
+			signed: Some((alice(), signed_extra(0, 0))),
+			function: transfer(),
+		}, VERSION.spec_version, [1; 32]);
+
+		let error = AuthorApi::submit_extrinsic(&author, Default::default(), Bytes(xt.encode()))
+			.wait()
+			.unwrap_err();
+
+		let error = jsonrpc_core::Error::from(error);
+		assert_eq!(error.message, "Invalid Transaction");
+		let data = error.data.expect("The error is described");
+		assert_eq!(data["error"], "BadProof");
+		assert_eq!(data["era"], serde_json::json!({
+			"birthBlock": 0,
+			"currentBlock": 0,
+			"period": 256,
+			"phase": 0,
+		}));
+	}
+}
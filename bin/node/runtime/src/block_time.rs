@@ -0,0 +1,127 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The block time of the chain, set at genesis from the `blockTimeMs` of the chain spec.
+//!
+//! The slot duration, the minimum period of the timestamp module and the epoch length are
+//! derived from it, so that they can't get out of sync.
+
+use frame_support::{decl_module, decl_storage, StorageValue, traits::Get};
+use node_primitives::{BlockNumber, Moment};
+
+use crate::constants::time::{EPOCH_DURATION_MS, MILLISECS_PER_BLOCK};
+
+/// The shortest block time, to leave enough time to author and propagate blocks.
+pub const MIN_BLOCK_TIME: Moment = 1000;
+
+pub trait Trait: frame_system::Trait {}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as BlockTime {
+		/// Expected time between blocks, in milliseconds.
+		pub BlockTimeMs get(fn block_time_ms) config(): Moment = MILLISECS_PER_BLOCK;
+	}
+	add_extra_genesis {
+		build(|config| {
+			assert!(
+				config.block_time_ms >= MIN_BLOCK_TIME && config.block_time_ms % 2 == 0,
+				"The block time must be an even number of milliseconds, at least {}",
+				MIN_BLOCK_TIME,
+			);
+			assert!(
+				config.block_time_ms <= EPOCH_DURATION_MS,
+				"The block time must not exceed the epoch duration of {} milliseconds",
+				EPOCH_DURATION_MS,
+			);
+		})
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
+}
+
+/// The block time BABE targets. All the slots are assigned with secondary slots, so it is also
+/// the slot duration.
+pub struct ExpectedBlockTime;
+
+impl Get<Moment> for ExpectedBlockTime {
+	fn get() -> Moment {
+		BlockTimeMs::get()
+	}
+}
+
+/// The minimum period between blocks, half of the slot duration BABE derives from it.
+pub struct MinimumPeriod;
+
+impl Get<Moment> for MinimumPeriod {
+	fn get() -> Moment {
+		minimum_period(BlockTimeMs::get())
+	}
+}
+
+/// The minimum period between blocks of a chain with the given block time.
+pub fn minimum_period(block_time_ms: Moment) -> Moment {
+	block_time_ms / 2
+}
+
+/// The maximum drift of the timestamp of blocks from the local time.
+pub struct MaxTimestampDrift;
+
+impl Get<Moment> for MaxTimestampDrift {
+	fn get() -> Moment {
+		2 * BlockTimeMs::get()
+	}
+}
+
+/// The length of an epoch, in slots.
+pub struct EpochDuration;
+
+impl Get<u64> for EpochDuration {
+	fn get() -> u64 {
+		EPOCH_DURATION_MS / BlockTimeMs::get()
+	}
+}
+
+/// The length of a session, which lasts an epoch, in blocks.
+pub struct SessionDuration;
+
+impl Get<BlockNumber> for SessionDuration {
+	fn get() -> BlockNumber {
+		EpochDuration::get() as BlockNumber
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn durations_follow_the_block_time() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			assert_eq!(ExpectedBlockTime::get(), MILLISECS_PER_BLOCK);
+			assert_eq!(EpochDuration::get(), 200);
+
+			BlockTimeMs::put(6000);
+			assert_eq!(ExpectedBlockTime::get(), 6000);
+			assert_eq!(MinimumPeriod::get(), 3000);
+			assert_eq!(minimum_period(6000), 3000);
+			assert_eq!(MaxTimestampDrift::get(), 12000);
+			assert_eq!(EpochDuration::get(), 100);
+			assert_eq!(SessionDuration::get(), 100);
+		});
+	}
+}
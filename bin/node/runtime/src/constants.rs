@@ -25,41 +25,41 @@ pub mod currency {
 	pub const DOLLARS: Balance = 100 * CENTS;
 }
 
+/// Addresses.
+pub mod address {
+	/// SS58 prefix of the addresses of the accounts, the one of generic Substrate networks.
+	pub const SS58_PREFIX: u8 = 42;
+}
+
 /// Time.
 pub mod time {
 	use node_primitives::{Moment, BlockNumber};
 
 	/// Since BABE is probabilistic this is the average expected block time that
 	/// we are targetting. Blocks will be produced at a minimum duration defined
-	/// by `SLOT_DURATION`, but some slots will not be allocated to any
+	/// by the slot duration, but some slots will not be allocated to any
 	/// authority and hence no block will be produced. We expect to have this
 	/// block time on average following the defined slot duration and the value
 	/// of `c` configured for BABE (where `1 - c` represents the probability of
 	/// a slot being empty).
-	/// This value is only used indirectly to define the unit constants below
-	/// that are expressed in blocks. The rest of the code should use
-	/// `SLOT_DURATION` instead (like the timestamp module for calculating the
-	/// minimum period).
 	///
-	/// If using BABE with secondary slots (default) then all of the slots will
-	/// always be assigned, in which case `MILLISECS_PER_BLOCK` and
-	/// `SLOT_DURATION` should have the same value.
+	/// This is the default of the block time set at genesis by the chain spec, see the
+	/// `block_time` module. The slot duration, the minimum period and the epoch length are
+	/// derived from the block time of the chain, while the unit constants below that are
+	/// expressed in blocks assume this default.
+	///
+	/// BABE is used with secondary slots, so all of the slots are assigned and the
+	/// block time is the slot duration.
 	///
 	/// <https://research.web3.foundation/en/latest/polkadot/BABE/Babe/#6-practical-results>
 	pub const MILLISECS_PER_BLOCK: Moment = 3000;
 	pub const SECS_PER_BLOCK: Moment = MILLISECS_PER_BLOCK / 1000;
 
-	pub const SLOT_DURATION: Moment = MILLISECS_PER_BLOCK;
-
 	// 1 in 4 blocks (on average, not counting collisions) will be primary BABE blocks.
 	pub const PRIMARY_PROBABILITY: (u64, u64) = (1, 4);
 
-	pub const EPOCH_DURATION_IN_BLOCKS: BlockNumber = 10 * MINUTES;
-	pub const EPOCH_DURATION_IN_SLOTS: u64 = {
-		const SLOT_FILL_RATE: f64 = MILLISECS_PER_BLOCK as f64 / SLOT_DURATION as f64;
-
-		(EPOCH_DURATION_IN_BLOCKS as f64 * SLOT_FILL_RATE) as u64
-	};
+	/// Duration of an epoch, and of a session, whatever the block time.
+	pub const EPOCH_DURATION_MS: Moment = 10 * 60 * 1000;
 
 	// These time units are defined in number of blocks.
 	pub const MINUTES: BlockNumber = 60 / (SECS_PER_BLOCK as BlockNumber);
@@ -19,15 +19,8 @@
 use node_primitives::Balance;
 use sp_runtime::traits::{Convert, Saturating};
 use sp_runtime::{Fixed64, Perbill};
-use frame_support::{traits::{OnUnbalanced, Currency, Get}, weights::Weight};
-use crate::{Balances, System, Authorship, MaximumBlockWeight, NegativeImbalance};
-
-pub struct Author;
-impl OnUnbalanced<NegativeImbalance> for Author {
-	fn on_nonzero_unbalanced(amount: NegativeImbalance) {
-		Balances::resolve_creating(&Authorship::author(), amount);
-	}
-}
+use frame_support::{traits::{Currency, Get}, weights::Weight};
+use crate::{Balances, System, MaximumBlockWeight};
 
 /// Struct that handles the conversion of Balance -> `u64`. This is used for staking's election
 /// calculation.
@@ -24,19 +24,20 @@ use sp_std::prelude::*;
 use frame_support::{
 	construct_runtime, parameter_types, debug,
 	weights::Weight,
-	traits::{SplitTwoWays, Currency, Randomness},
+	traits::{Currency, Randomness, Get},
 };
 use sp_core::u32_trait::{_1, _2, _3, _4};
 use node_primitives::{AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment, Signature};
 use sp_api::impl_runtime_apis;
 use sp_runtime::{
-	Permill, Perbill, Percent, ApplyExtrinsicResult, impl_opaque_keys, generic, create_runtime_str
+	Permill, Perbill, Percent, ApplyExtrinsicResult, DispatchResult, impl_opaque_keys, generic,
+	create_runtime_str,
 };
 use sp_runtime::curve::PiecewiseLinear;
-use sp_runtime::transaction_validity::TransactionValidity;
+use sp_runtime::transaction_validity::{TransactionValidity, TransactionValidityError, InvalidTransaction};
 use sp_runtime::traits::{
 	self, BlakeTwo256, Block as BlockT, StaticLookup, SaturatedConversion,
-	OpaqueKeys,
+	OpaqueKeys, Dispatchable,
 };
 use sp_version::RuntimeVersion;
 #[cfg(any(feature = "std", test))]
@@ -46,8 +47,9 @@ use pallet_grandpa::AuthorityList as GrandpaAuthorityList;
 use pallet_grandpa::fg_primitives;
 use pallet_im_online::sr25519::{AuthorityId as ImOnlineId};
 use sp_authority_discovery::AuthorityId as AuthorityDiscoveryId;
-use pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
+use pallet_transaction_payment_rpc_runtime_api::{FeeParameters, RuntimeDispatchInfo};
 use pallet_contracts_rpc_runtime_api::ContractExecResult;
+use pallet_asset_conversion_rpc_runtime_api::AssetKind;
 use frame_system::offchain::TransactionSubmitter;
 use sp_inherents::{InherentData, CheckInherentsResult};
 
@@ -55,18 +57,25 @@ use sp_inherents::{InherentData, CheckInherentsResult};
 pub use sp_runtime::BuildStorage;
 pub use pallet_timestamp::Call as TimestampCall;
 pub use pallet_balances::Call as BalancesCall;
+pub use pallet_grandpa::Call as GrandpaCall;
+pub use pallet_sudo::Call as SudoCall;
 pub use pallet_contracts::Gas;
 pub use frame_support::StorageValue;
 pub use pallet_staking::StakerStatus;
+pub use pallet_fee_distribution::Distribution;
 
 /// Implementations of some helper traits passed into runtime modules as associated types.
 pub mod impls;
-use impls::{CurrencyToVoteHandler, Author, LinearWeightToFee, TargetedFeeAdjustment};
+use impls::{CurrencyToVoteHandler, LinearWeightToFee, TargetedFeeAdjustment};
 
 /// Constant values used within the runtime.
 pub mod constants;
 use constants::{time::*, currency::*};
 
+/// The block time of the chain and the durations derived from it.
+pub mod block_time;
+pub use block_time::{EpochDuration, ExpectedBlockTime, MaxTimestampDrift, MinimumPeriod, SessionDuration};
+
 // Make the WASM binary available.
 #[cfg(feature = "std")]
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
@@ -80,8 +89,8 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// and set impl_version to equal spec_version. If only runtime
 	// implementation changes and behavior does not, then leave spec_version as
 	// is and increment impl_version.
-	spec_version: 204,
-	impl_version: 204,
+	spec_version: 233,
+	impl_version: 233,
 	apis: RUNTIME_API_VERSIONS,
 };
 
@@ -94,15 +103,6 @@ pub fn native_version() -> NativeVersion {
 	}
 }
 
-type NegativeImbalance = <Balances as Currency<AccountId>>::NegativeImbalance;
-
-pub type DealWithFees = SplitTwoWays<
-	Balance,
-	NegativeImbalance,
-	_4, Treasury,   // 4 parts (80%) goes to the treasury.
-	_1, Author,     // 1 part (20%) goes to the block author.
->;
-
 parameter_types! {
 	pub const BlockHashCount: BlockNumber = 250;
 	pub const MaximumBlockWeight: Weight = 1_000_000_000;
@@ -147,10 +147,7 @@ impl pallet_utility::Trait for Runtime {
 	type MaxSignatories = MaxSignatories;
 }
 
-parameter_types! {
-	pub const EpochDuration: u64 = EPOCH_DURATION_IN_SLOTS;
-	pub const ExpectedBlockTime: Moment = MILLISECS_PER_BLOCK;
-}
+impl block_time::Trait for Runtime {}
 
 impl pallet_babe::Trait for Runtime {
 	type EpochDuration = EpochDuration;
@@ -177,7 +174,7 @@ impl pallet_balances::Trait for Runtime {
 	type OnReapAccount = (System, Recovery);
 	type OnNewAccount = Indices;
 	type Event = Event;
-	type DustRemoval = ();
+	type DustRemoval = Treasury;
 	type TransferPayment = ();
 	type ExistentialDeposit = ExistentialDeposit;
 	type TransferFee = TransferFee;
@@ -189,26 +186,26 @@ parameter_types! {
 	pub const TransactionByteFee: Balance = 10 * MILLICENTS;
 	// setting this to zero will disable the weight fee.
 	pub const WeightFeeCoefficient: Balance = 1_000;
+	pub const OperationalWeightFeeCoefficient: Balance = 1_000;
 	// for a sane configuration, this should always be less than `AvailableBlockRatio`.
 	pub const TargetBlockFullness: Perbill = Perbill::from_percent(25);
 }
 
 impl pallet_transaction_payment::Trait for Runtime {
 	type Currency = Balances;
-	type OnTransactionPayment = DealWithFees;
+	type OnTransactionPayment = FeeDistribution;
 	type TransactionBaseFee = TransactionBaseFee;
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = LinearWeightToFee<WeightFeeCoefficient>;
+	type OperationalWeightToFee = LinearWeightToFee<OperationalWeightFeeCoefficient>;
 	type FeeMultiplierUpdate = TargetedFeeAdjustment<TargetBlockFullness>;
 }
 
-parameter_types! {
-	pub const MinimumPeriod: Moment = SLOT_DURATION / 2;
-}
 impl pallet_timestamp::Trait for Runtime {
 	type Moment = Moment;
 	type OnTimestampSet = Babe;
 	type MinimumPeriod = MinimumPeriod;
+	type MaxDrift = MaxTimestampDrift;
 }
 
 parameter_types! {
@@ -268,6 +265,10 @@ parameter_types! {
 	pub const BondingDuration: pallet_staking::EraIndex = 24 * 28;
 	pub const SlashDeferDuration: pallet_staking::EraIndex = 24 * 7; // 1/4 the bonding duration.
 	pub const RewardCurve: &'static PiecewiseLinear<'static> = &REWARD_CURVE;
+	pub const EraValidatorsPerBlock: u32 = 16;
+	pub const ElectionVotersPerBlock: u32 = 256;
+	pub const ElectionRoundsPerBlock: u32 = 4;
+	pub const ElectionLookahead: BlockNumber = 50;
 }
 
 impl pallet_staking::Trait for Runtime {
@@ -285,6 +286,25 @@ impl pallet_staking::Trait for Runtime {
 	type SlashCancelOrigin = pallet_collective::EnsureProportionAtLeast<_3, _4, AccountId, CouncilCollective>;
 	type SessionInterface = Self;
 	type RewardCurve = RewardCurve;
+	type EraValidatorsPerBlock = EraValidatorsPerBlock;
+	type ElectionVotersPerBlock = ElectionVotersPerBlock;
+	type ElectionRoundsPerBlock = ElectionRoundsPerBlock;
+	type ElectionLookahead = ElectionLookahead;
+	type NextNewSession = Babe;
+}
+
+parameter_types! {
+	pub const FastUnstakeDeposit: Balance = 1 * DOLLARS;
+	pub const ErasToCheckPerBlock: u32 = 16;
+}
+
+impl pallet_fast_unstake::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type Staking = Staking;
+	type Deposit = FastUnstakeDeposit;
+	type Slashed = Treasury;
+	type ErasToCheckPerBlock = ErasToCheckPerBlock;
 }
 
 parameter_types! {
@@ -402,6 +422,28 @@ impl pallet_treasury::Trait for Runtime {
 	type TipReportDepositPerByte = TipReportDepositPerByte;
 }
 
+parameter_types! {
+	/// 80% of the fees go to the treasury and 20% to the block author, as before the fee
+	/// distribution module.
+	pub const DefaultFeeDistribution: Distribution = Distribution {
+		author: Perbill::from_percent(20),
+		treasury: Perbill::from_percent(80),
+	};
+	pub const DefaultTipDistribution: Distribution = Distribution {
+		author: Perbill::one(),
+		treasury: Perbill::zero(),
+	};
+}
+
+impl pallet_fee_distribution::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type Treasury = Treasury;
+	type DistributionOrigin = pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>;
+	type DefaultFeeDistribution = DefaultFeeDistribution;
+	type DefaultTipDistribution = DefaultTipDistribution;
+}
+
 parameter_types! {
 	pub const ContractTransferFee: Balance = 1 * CENTS;
 	pub const ContractCreationFee: Balance = 1 * CENTS;
@@ -451,10 +493,6 @@ impl pallet_sudo::Trait for Runtime {
 /// A runtime transaction submitter.
 pub type SubmitTransaction = TransactionSubmitter<ImOnlineId, Runtime, UncheckedExtrinsic>;
 
-parameter_types! {
-	pub const SessionDuration: BlockNumber = EPOCH_DURATION_IN_SLOTS as _;
-}
-
 impl pallet_im_online::Trait for Runtime {
 	type AuthorityId = ImOnlineId;
 	type Call = Call;
@@ -462,6 +500,7 @@ impl pallet_im_online::Trait for Runtime {
 	type SubmitTransaction = SubmitTransaction;
 	type ReportUnresponsiveness = Offences;
 	type SessionDuration = SessionDuration;
+	type OnHeartbeat = ValidatorPerformance;
 }
 
 impl pallet_offences::Trait for Runtime {
@@ -483,10 +522,84 @@ parameter_types! {
 
 impl pallet_finality_tracker::Trait for Runtime {
 	type OnFinalizationStalled = Grandpa;
+	type OnFinalHint = ValidatorPerformance;
 	type WindowSize = WindowSize;
 	type ReportLatency = ReportLatency;
 }
 
+parameter_types! {
+	pub const StorageEntryFee: Balance = 1 * CENTS;
+	pub const StorageByteFee: Balance = 10 * MILLICENTS;
+	// One raw IPFS block.
+	pub const MaxTransactionSize: u32 = 256 * 1024;
+	pub const MaxBlockTransactions: u32 = 16;
+	pub const StoragePeriod: BlockNumber = 7 * DAYS;
+}
+
+impl pallet_transaction_storage::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type SubmitTransaction = SubmitTransaction;
+	type Currency = Balances;
+	type FeeDestination = Treasury;
+	type EntryFee = StorageEntryFee;
+	type ByteFee = StorageByteFee;
+	type MaxTransactionSize = MaxTransactionSize;
+	type MaxBlockTransactions = MaxBlockTransactions;
+	type StoragePeriod = StoragePeriod;
+	type Randomness = RandomnessCollectiveFlip;
+}
+
+parameter_types! {
+	pub const NameCommitmentDeposit: Balance = 1 * DOLLARS;
+	pub const NameRegistrationFee: Balance = 5 * DOLLARS;
+	pub const NameRegistrationPeriod: BlockNumber = 365 * DAYS;
+	pub const MaxNamePeriods: u32 = 10;
+	pub const NameGracePeriod: BlockNumber = 28 * DAYS;
+	pub const MinCommitmentAge: BlockNumber = 1 * MINUTES;
+	pub const MaxCommitmentAge: BlockNumber = 1 * DAYS;
+	pub const MinNameLength: usize = 3;
+	pub const MaxNameLength: usize = 64;
+}
+
+impl pallet_name_service::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type FeeDestination = Treasury;
+	type CommitmentDeposit = NameCommitmentDeposit;
+	type RegistrationFee = NameRegistrationFee;
+	type RegistrationPeriod = NameRegistrationPeriod;
+	type MaxPeriods = MaxNamePeriods;
+	type GracePeriod = NameGracePeriod;
+	type MinCommitmentAge = MinCommitmentAge;
+	type MaxCommitmentAge = MaxCommitmentAge;
+	type MinLength = MinNameLength;
+	type MaxLength = MaxNameLength;
+}
+
+parameter_types! {
+	pub const MaxOracleFeeders: u32 = 32;
+	pub const MaxOracleFeedValues: u32 = 64;
+	pub const MinimumOracleFeeds: u32 = 3;
+	pub const OracleStalenessThreshold: BlockNumber = 10 * MINUTES;
+	pub const OracleFeedInterval: BlockNumber = 1 * MINUTES;
+}
+
+impl pallet_oracle::Trait for Runtime {
+	type Event = Event;
+	type AuthorityId = pallet_oracle::sr25519::AuthorityId;
+	type Call = Call;
+	type SubmitTransaction = SubmitTransaction;
+	type Value = u128;
+	type FeedSource = pallet_oracle::LocalStorageSource;
+	type FeederOrigin = pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>;
+	type MaxFeeders = MaxOracleFeeders;
+	type MaxFeedValues = MaxOracleFeedValues;
+	type MinimumFeeds = MinimumOracleFeeds;
+	type StalenessThreshold = OracleStalenessThreshold;
+	type FeedInterval = OracleFeedInterval;
+}
+
 parameter_types! {
 	pub const BasicDeposit: Balance = 10 * DOLLARS;       // 258 bytes on-chain
 	pub const FieldDeposit: Balance = 250 * CENTS;        // 66 bytes on-chain
@@ -506,6 +619,14 @@ impl pallet_identity::Trait for Runtime {
 	type ForceOrigin = pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>;
 }
 
+/// The longest era period of mortal transactions, limited by the number of block hashes kept.
+pub fn era_period() -> u64 {
+	BlockHashCount::get()
+		.checked_next_power_of_two()
+		.map(|c| c / 2)
+		.unwrap_or(2) as u64
+}
+
 impl frame_system::offchain::CreateTransaction<Runtime, UncheckedExtrinsic> for Runtime {
 	type Public = <Signature as traits::Verify>::Signer;
 	type Signature = Signature;
@@ -516,11 +637,7 @@ impl frame_system::offchain::CreateTransaction<Runtime, UncheckedExtrinsic> for
 		account: AccountId,
 		index: Index,
 	) -> Option<(Call, <UncheckedExtrinsic as traits::Extrinsic>::SignaturePayload)> {
-		// take the biggest period possible.
-		let period = BlockHashCount::get()
-			.checked_next_power_of_two()
-			.map(|c| c / 2)
-			.unwrap_or(2) as u64;
+		let period = era_period();
 		let current_block = System::block_number()
 			.saturated_into::<u64>()
 			// The `System::block_number` is initialized with `n+1`,
@@ -533,7 +650,7 @@ impl frame_system::offchain::CreateTransaction<Runtime, UncheckedExtrinsic> for
 			frame_system::CheckEra::<Runtime>::from(generic::Era::mortal(period, current_block)),
 			frame_system::CheckNonce::<Runtime>::from(index),
 			frame_system::CheckWeight::<Runtime>::new(),
-			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
+			pallet_asset_tx_payment::ChargeAssetTxPayment::<Runtime>::from(tip, None),
 			Default::default(),
 		);
 		let raw_payload = SignedPayload::new(call, extra).map_err(|e| {
@@ -590,6 +707,57 @@ impl pallet_society::Trait for Runtime {
 	type ChallengePeriod = ChallengePeriod;
 }
 
+impl pallet_upgrade::Trait for Runtime {
+	type Event = Event;
+	type AuthorizeOrigin = frame_system::EnsureRoot<AccountId>;
+}
+
+/// Identifier of an asset of the assets module.
+pub type AssetId = u32;
+
+impl pallet_assets::Trait for Runtime {
+	type Event = Event;
+	type Balance = Balance;
+	type AssetId = AssetId;
+}
+
+parameter_types! {
+	pub const SwapFee: Permill = Permill::from_parts(3_000);
+	pub const MinimumLiquidity: Balance = 1_000;
+}
+
+impl pallet_asset_conversion::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type SwapFee = SwapFee;
+	type MinimumLiquidity = MinimumLiquidity;
+}
+
+impl pallet_asset_tx_payment::Trait for Runtime {
+	type Event = Event;
+	type FeeAssetOrigin = pallet_collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>;
+	type OnAssetFeePayment = FeeDistribution;
+}
+
+impl pallet_meta_tx::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type Signature = Signature;
+	type Public = <Signature as traits::Verify>::Signer;
+	type Currency = Balances;
+}
+
+parameter_types! {
+	pub const MaxFinalityLag: BlockNumber = 10;
+	pub const PerformanceHistoryDepth: sp_staking::SessionIndex = 28 * 6;
+}
+
+impl pallet_validator_performance::Trait for Runtime {
+	type MaxFinalityLag = MaxFinalityLag;
+	type HistoryDepth = PerformanceHistoryDepth;
+	type FindAuthor = pallet_session::FindAccountFromAuthorIndex<Self, Babe>;
+}
+
 construct_runtime!(
 	pub enum Runtime where
 		Block = Block,
@@ -605,13 +773,14 @@ construct_runtime!(
 		Balances: pallet_balances,
 		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 		Staking: pallet_staking,
+		FastUnstake: pallet_fast_unstake::{Module, Call, Storage, Event<T>},
 		Session: pallet_session::{Module, Call, Storage, Event, Config<T>},
 		Democracy: pallet_democracy::{Module, Call, Storage, Config, Event<T>},
 		Council: pallet_collective::<Instance1>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
 		TechnicalCommittee: pallet_collective::<Instance2>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
 		Elections: pallet_elections_phragmen::{Module, Call, Storage, Event<T>},
 		TechnicalMembership: pallet_membership::<Instance1>::{Module, Call, Storage, Event<T>, Config<T>},
-		FinalityTracker: pallet_finality_tracker::{Module, Call, Inherent},
+		FinalityTracker: pallet_finality_tracker::{Module, Call},
 		Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event},
 		Treasury: pallet_treasury::{Module, Call, Storage, Config, Event<T>},
 		Contracts: pallet_contracts,
@@ -623,6 +792,17 @@ construct_runtime!(
 		Identity: pallet_identity::{Module, Call, Storage, Event<T>},
 		Society: pallet_society::{Module, Call, Storage, Event<T>},
 		Recovery: pallet_recovery::{Module, Call, Storage, Event<T>},
+		Upgrade: pallet_upgrade::{Module, Call, Storage, Event<T>},
+		Assets: pallet_assets::{Module, Call, Storage, Event<T>},
+		AssetConversion: pallet_asset_conversion::{Module, Call, Storage, Event<T>},
+		AssetTxPayment: pallet_asset_tx_payment::{Module, Call, Storage, Event<T>},
+		MetaTx: pallet_meta_tx::{Module, Call, Storage, Event<T>},
+		ValidatorPerformance: pallet_validator_performance::{Module, Storage},
+		TransactionStorage: pallet_transaction_storage::{Module, Call, Storage, Event<T>, ValidateUnsigned},
+		NameService: pallet_name_service::{Module, Call, Storage, Event<T>},
+		Oracle: pallet_oracle::{Module, Call, Storage, Event<T>, ValidateUnsigned},
+		FeeDistribution: pallet_fee_distribution::{Module, Call, Storage, Event<T>, Config},
+		BlockTime: block_time::{Module, Storage, Config},
 	}
 );
 
@@ -643,7 +823,7 @@ pub type SignedExtra = (
 	frame_system::CheckEra<Runtime>,
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_asset_tx_payment::ChargeAssetTxPayment<Runtime>,
 	pallet_contracts::CheckBlockGasLimit<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
@@ -652,8 +832,16 @@ pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signatu
 pub type SignedPayload = generic::SignedPayload<Call, SignedExtra>;
 /// Extrinsic type that has already been checked.
 pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Call, SignedExtra>;
-/// Executive: handles dispatch to the various modules.
-pub type Executive = frame_executive::Executive<Runtime, Block, frame_system::ChainContext<Runtime>, Runtime, AllModules>;
+/// Executive: handles dispatch to the various modules. The unused weight of each block is given
+/// to staking for its housekeeping, then to the fast unstake checks.
+pub type Executive = frame_executive::Executive<
+	Runtime,
+	Block,
+	frame_system::ChainContext<Runtime>,
+	Runtime,
+	AllModules,
+	(Staking, FastUnstake),
+>;
 
 impl_runtime_apis! {
 	impl sp_api::Core<Block> for Runtime {
@@ -696,6 +884,15 @@ impl_runtime_apis! {
 		fn random_seed() -> <Block as BlockT>::Hash {
 			RandomnessCollectiveFlip::random_seed()
 		}
+
+		fn block_weight() -> sp_block_builder::BlockWeight {
+			sp_block_builder::BlockWeight {
+				hooks: System::hooks_weight().into(),
+				consumed: System::all_extrinsics_weight().into(),
+				normal_limit: System::weight_limit(frame_support::weights::DispatchClass::Normal).into(),
+				maximum: System::weight_limit(frame_support::weights::DispatchClass::Operational).into(),
+			}
+		}
 	}
 
 	impl sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block> for Runtime {
@@ -746,6 +943,31 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl frame_system_rpc_runtime_api::MortalityApi<Block> for Runtime {
+		fn era_period() -> u64 {
+			era_period()
+		}
+	}
+
+	impl frame_system_rpc_runtime_api::CallSimulationApi<Block, AccountId, Call> for Runtime {
+		fn simulate_calls(
+			who: AccountId,
+			calls: Vec<Call>,
+		) -> Result<Vec<DispatchResult>, TransactionValidityError> {
+			use frame_support::weights::GetDispatchInfo;
+
+			let weight = calls.iter()
+				.fold(0 as Weight, |weight, call| weight.saturating_add(call.get_dispatch_info().weight));
+			if weight > MaximumBlockWeight::get() {
+				return Err(InvalidTransaction::ExhaustsResources.into());
+			}
+
+			let origin: Origin = frame_system::RawOrigin::Signed(who).into();
+			System::reset_events();
+			Ok(calls.into_iter().map(|call| call.dispatch(origin.clone())).collect())
+		}
+	}
+
 	impl pallet_contracts_rpc_runtime_api::ContractsApi<Block, AccountId, Balance> for Runtime {
 		fn call(
 			origin: AccountId,
@@ -794,6 +1016,90 @@ impl_runtime_apis! {
 		fn query_info(uxt: UncheckedExtrinsic, len: u32) -> RuntimeDispatchInfo<Balance> {
 			TransactionPayment::query_info(uxt, len)
 		}
+
+		fn fee_parameters() -> FeeParameters<Balance> {
+			TransactionPayment::fee_parameters()
+		}
+	}
+
+	impl pallet_asset_conversion_rpc_runtime_api::AssetConversionApi<Block, Balance, AssetId> for Runtime {
+		fn quote_exact_in(
+			from: AssetKind<AssetId>,
+			to: AssetKind<AssetId>,
+			amount_in: Balance,
+		) -> Option<Balance> {
+			AssetConversion::quote_exact_in(from, to, amount_in)
+		}
+
+		fn quote_exact_out(
+			from: AssetKind<AssetId>,
+			to: AssetKind<AssetId>,
+			amount_out: Balance,
+		) -> Option<Balance> {
+			AssetConversion::quote_exact_out(from, to, amount_out)
+		}
+
+		fn reserves(asset: AssetId) -> Option<(Balance, Balance)> {
+			AssetConversion::reserves(asset)
+		}
+	}
+
+	impl pallet_balances_rpc_runtime_api::BalancesApi<Block, AccountId, Balance> for Runtime {
+		fn transferable_balance(who: AccountId) -> Balance {
+			Balances::transferable_balance(&who)
+		}
+
+		fn existential_deposit() -> Balance {
+			ExistentialDeposit::get()
+		}
+	}
+
+	impl pallet_validator_performance_rpc_runtime_api::ValidatorPerformanceApi<Block, AccountId> for Runtime {
+		fn history(
+			validator: AccountId,
+		) -> Vec<(sp_staking::SessionIndex, pallet_validator_performance::ValidatorPerformance)> {
+			ValidatorPerformance::history(&validator)
+		}
+
+		fn score(validator: AccountId) -> Option<Perbill> {
+			ValidatorPerformance::score(&validator)
+		}
+
+		fn scores() -> Vec<(AccountId, Option<Perbill>)> {
+			ValidatorPerformance::scores()
+		}
+	}
+
+	impl pallet_staking_rpc_runtime_api::StakingSlashingApi<Block, AccountId, Balance> for Runtime {
+		fn pending_slashes() -> Vec<pallet_staking::PendingSlash<AccountId, Balance>> {
+			Staking::pending_slashes()
+		}
+
+		fn pending_slashes_of(who: AccountId) -> Vec<pallet_staking::PendingSlash<AccountId, Balance>> {
+			Staking::pending_slashes_of(&who)
+		}
+
+		fn unapplied_slash_of(who: AccountId) -> Balance {
+			Staking::unapplied_slash_of(&who)
+		}
+
+		fn slashing_spans(stash: AccountId) -> Vec<pallet_staking::SlashingSpanInfo<Balance>> {
+			Staking::slashing_spans(&stash)
+		}
+	}
+
+	impl pallet_name_service_rpc_runtime_api::NameServiceApi<Block, AccountId, BlockNumber> for Runtime {
+		fn resolve(name: Vec<u8>) -> Option<AccountId> {
+			NameService::resolve(&name)
+		}
+
+		fn reverse(who: AccountId) -> Option<Vec<u8>> {
+			NameService::reverse(&who)
+		}
+
+		fn expiry(name: Vec<u8>) -> Option<BlockNumber> {
+			NameService::expiry(&name)
+		}
 	}
 
 	impl sp_session::SessionKeys<Block> for Runtime {
@@ -801,6 +1107,96 @@ impl_runtime_apis! {
 			SessionKeys::generate(seed)
 		}
 	}
+
+	impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+		fn create_default_config() -> Vec<u8> {
+			genesis::default_config()
+		}
+
+		fn build_config(json: Vec<u8>) -> sp_genesis_builder::Result {
+			genesis::build_config(json)
+		}
+	}
+}
+
+/// Building the genesis state from JSON.
+///
+/// The genesis config types only implement serde with `std`, so the wasm runtime can't build
+/// the genesis state itself.
+mod genesis {
+	use super::*;
+
+	/// The JSON encoded default genesis config.
+	#[cfg(feature = "std")]
+	pub fn default_config() -> Vec<u8> {
+		serde_json::to_vec(&default_genesis_config())
+			.expect("The genesis config is always serializable; qed")
+	}
+
+	/// Build the genesis storage from the config and write it to the current storage.
+	#[cfg(feature = "std")]
+	pub fn build_config(json: Vec<u8>) -> sp_genesis_builder::Result {
+		use sp_runtime::BuildStorage;
+
+		let config: GenesisConfig = serde_json::from_slice(&json)
+			.map_err(|e| format!("Invalid genesis config: {}", e).into_bytes())?;
+		let storage = config.build_storage().map_err(String::into_bytes)?;
+
+		for (key, value) in storage.top {
+			frame_support::storage::unhashed::put_raw(&key, &value);
+		}
+		for (storage_key, child) in storage.children {
+			for (key, value) in child.data {
+				frame_support::storage::child::put_raw(
+					&storage_key,
+					child.child_info.as_ref(),
+					&key,
+					&value,
+				);
+			}
+		}
+		Ok(())
+	}
+
+	#[cfg(not(feature = "std"))]
+	pub fn default_config() -> Vec<u8> {
+		Vec::new()
+	}
+
+	#[cfg(not(feature = "std"))]
+	pub fn build_config(_json: Vec<u8>) -> sp_genesis_builder::Result {
+		Err(b"Building the genesis state requires the native runtime".to_vec())
+	}
+}
+
+/// The default genesis config, with every module config present.
+///
+/// The genesis config types only implement serde with `std`, so it is only available natively.
+#[cfg(feature = "std")]
+pub fn default_genesis_config() -> GenesisConfig {
+	GenesisConfig {
+		frame_system: Some(Default::default()),
+		pallet_balances: Some(Default::default()),
+		pallet_indices: Some(Default::default()),
+		pallet_session: Some(Default::default()),
+		pallet_staking: Some(Default::default()),
+		pallet_democracy: Some(Default::default()),
+		pallet_collective_Instance1: Some(Default::default()),
+		pallet_collective_Instance2: Some(Default::default()),
+		pallet_contracts: Some(Default::default()),
+		pallet_sudo: Some(Default::default()),
+		pallet_babe: Some(Default::default()),
+		pallet_im_online: Some(Default::default()),
+		pallet_authority_discovery: Some(Default::default()),
+		pallet_grandpa: Some(Default::default()),
+		pallet_membership_Instance1: Some(Default::default()),
+		pallet_treasury: Some(Default::default()),
+		pallet_fee_distribution: Some(pallet_fee_distribution::GenesisConfig {
+			fee_distribution: DefaultFeeDistribution::get(),
+			tip_distribution: DefaultTipDistribution::get(),
+		}),
+		block_time: Some(Default::default()),
+	}
 }
 
 #[cfg(test)]
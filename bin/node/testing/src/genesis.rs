@@ -20,9 +20,10 @@ use crate::keyring::*;
 use sp_keyring::{Ed25519Keyring, Sr25519Keyring};
 use node_runtime::{
 	GenesisConfig, BalancesConfig, SessionConfig, StakingConfig, SystemConfig,
-	GrandpaConfig, IndicesConfig, ContractsConfig, WASM_BINARY,
+	GrandpaConfig, IndicesConfig, ContractsConfig, FeeDistributionConfig, Distribution, BlockTimeConfig,
+	WASM_BINARY,
 };
-use node_runtime::constants::currency::*;
+use node_runtime::constants::{currency::*, time::MILLISECS_PER_BLOCK};
 use sp_core::ChangesTrieConfiguration;
 use sp_runtime::Perbill;
 
@@ -96,5 +97,18 @@ pub fn config(support_changes_trie: bool, code: Option<&[u8]>) -> GenesisConfig
 		pallet_membership_Instance1: Some(Default::default()),
 		pallet_sudo: Some(Default::default()),
 		pallet_treasury: Some(Default::default()),
+		pallet_fee_distribution: Some(FeeDistributionConfig {
+			fee_distribution: Distribution {
+				author: Perbill::from_percent(20),
+				treasury: Perbill::from_percent(80),
+			},
+			tip_distribution: Distribution {
+				author: Perbill::one(),
+				treasury: Perbill::zero(),
+			},
+		}),
+		block_time: Some(BlockTimeConfig {
+			block_time_ms: MILLISECS_PER_BLOCK,
+		}),
 	}
 }
@@ -0,0 +1,101 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Calls built from the runtime metadata.
+//!
+//! The factory doesn't know the `Call` enum of the runtime it manufactures transactions for.
+//! It encodes the calls of its scenario from the metadata of the runtime itself, so that it
+//! works with any runtime exposing the `Metadata` runtime API.
+
+use codec::{Decode, Encode, Output};
+use substrate_frame_decode::{Decoder, ExtrinsicBuilder, TypeRegistry};
+
+use crate::scenario::{substitute, Scenario, ScenarioCall, Variables};
+
+/// A call of the outer `Call` enum of the runtime, encoded.
+///
+/// Encodes to the bytes of the call, so it can take the place of the `Call` of the runtime in
+/// extrinsics and signature payloads.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodedCall(pub Vec<u8>);
+
+impl Encode for EncodedCall {
+	fn size_hint(&self) -> usize {
+		self.0.len()
+	}
+
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.write(&self.0)
+	}
+}
+
+/// The calls of a scenario, encoded with the metadata of the runtime.
+pub struct Calls {
+	scenario: Scenario,
+	decoder: Decoder,
+}
+
+impl Calls {
+	/// Encode the calls of `scenario` with the SCALE encoded runtime metadata.
+	pub fn new(scenario: Scenario, metadata: &[u8]) -> Result<Self, String> {
+		let decoder = Decoder::from_bytes(metadata, TypeRegistry::with_defaults())
+			.map_err(|e| e.to_string())?;
+		for call in scenario.calls() {
+			if decoder.call_index(&call.module, &call.call).map_err(|e| e.to_string())?.is_none() {
+				return Err(format!("The runtime has no call {}", call));
+			}
+		}
+		Ok(Calls { scenario, decoder })
+	}
+
+	/// The scenario of the calls.
+	pub fn scenario(&self) -> &Scenario {
+		&self.scenario
+	}
+
+	/// The existential deposit of the runtime, read from the constants of its `Balances` module.
+	pub fn minimum_balance<Balance: Decode>(&self) -> Result<Balance, String> {
+		let value = self.decoder.constant("Balances", "ExistentialDeposit")
+			.map_err(|e| e.to_string())?
+			.ok_or_else(|| "The runtime has no constant Balances.ExistentialDeposit".to_string())?;
+		Balance::decode(&mut &value[..])
+			.map_err(|e| format!("Unable to decode Balances.ExistentialDeposit: {}", e.what()))
+	}
+
+	/// Start building the extrinsic of `call`, to sign it with the address, signature and signed
+	/// extensions of the runtime as described by the type registry.
+	pub fn extrinsic(&self, call: EncodedCall) -> ExtrinsicBuilder {
+		ExtrinsicBuilder::from_call(&self.decoder, call.0)
+	}
+
+	/// Encode the call of the `n`-th transaction with the placeholders replaced by `variables`.
+	pub fn encode(&self, n: u64, variables: &Variables) -> Result<EncodedCall, String> {
+		self.encode_call(self.scenario.call(n), variables)
+	}
+
+	/// Encode a transfer of the `transfer` scenario, whatever the scenario of the calls, to fund
+	/// the accounts the transactions are sent from.
+	pub fn encode_funding(&self, variables: &Variables) -> Result<EncodedCall, String> {
+		let transfer = Scenario::preset("transfer").expect("transfer is a built-in scenario; qed");
+		self.encode_call(transfer.call(0), variables)
+	}
+
+	fn encode_call(&self, call: &ScenarioCall, variables: &Variables) -> Result<EncodedCall, String> {
+		self.decoder.encode_call(&call.module, &call.call, &substitute(&call.args, variables))
+			.map(EncodedCall)
+			.map_err(|e| format!("Unable to encode {}: {}", call, e))
+	}
+}
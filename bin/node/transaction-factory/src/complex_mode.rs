@@ -38,40 +38,23 @@
 ///   ...
 ///   ... x `rounds`
 
-use std::sync::Arc;
-
 use log::info;
-use sc_client::Client;
-use sp_block_builder::BlockBuilder;
-use sp_api::{ConstructRuntimeApi, ProvideRuntimeApi};
-use sp_runtime::generic::BlockId;
-use sp_runtime::traits::{Block as BlockT, One, Zero};
+use sp_runtime::traits::{Block as BlockT, One, UniqueSaturatedInto, Zero};
 
-use crate::{RuntimeAdapter, create_block};
+use crate::{Calls, ChainState, RuntimeAdapter};
 
-pub fn next<RA, Backend, Exec, Block, RtApi>(
+pub fn next<RA: RuntimeAdapter>(
 	factory_state: &mut RA,
-	client: &Arc<Client<Backend, Exec, Block, RtApi>>,
+	chain: &dyn ChainState<RA>,
+	calls: &Calls,
 	version: u32,
 	genesis_hash: <RA::Block as BlockT>::Hash,
 	prior_block_hash: <RA::Block as BlockT>::Hash,
-	prior_block_id: BlockId<Block>,
-) -> Option<Block>
-where
-	Block: BlockT,
-	Exec: sc_client::CallExecutor<Block, Backend = Backend> + Send + Sync + Clone,
-	Backend: sc_client_api::backend::Backend<Block> + Send,
-	Client<Backend, Exec, Block, RtApi>: ProvideRuntimeApi<Block>,
-	<Client<Backend, Exec, Block, RtApi> as ProvideRuntimeApi<Block>>::Api:
-		BlockBuilder<Block, Error = sp_blockchain::Error> +
-		sp_api::ApiExt<Block, StateBackend = Backend::State>,
-	RtApi: ConstructRuntimeApi<Block, Client<Backend, Exec, Block, RtApi>> + Send + Sync,
-	RA: RuntimeAdapter,
-{
+) -> Result<Option<RA::Payload>, String> {
 	let total = factory_state.start_number() + factory_state.num() * factory_state.rounds();
 
 	if factory_state.block_no() >= total || factory_state.round() >= factory_state.rounds() {
-		return None;
+		return Ok(None);
 	}
 
 	info!(
@@ -85,30 +68,35 @@ where
 	let from = from::<RA>(factory_state);
 
 	let seed = factory_state.start_number() + factory_state.block_no();
-	let to = RA::gen_random_account_id(&seed);
+	let to = factory_state.gen_random_account_id(&seed);
 
 	let rounds_left = factory_state.rounds() - factory_state.round();
-	let amount = RA::minimum_balance() * rounds_left.into();
+	let amount = calls.minimum_balance::<RA::Balance>()? * rounds_left.into();
 
-	let transfer = factory_state.transfer_extrinsic(
+	let n: u64 = factory_state.block_no().unique_saturated_into();
+	let call = crate::encode_call(
+		calls,
+		chain,
+		n,
 		&from.0,
-		&from.1,
 		&to,
 		&amount,
+		&prior_block_hash,
+	)?;
+	let payload = factory_state.signing_payload(
+		chain,
+		&from.0,
+		&from.1,
+		call,
 		version,
 		&genesis_hash,
 		&prior_block_hash,
-	);
-
-	let inherents = factory_state.inherent_extrinsics();
-	let inherents = client.runtime_api().inherent_extrinsics(&prior_block_id, inherents)
-		.expect("Failed to create inherent extrinsics");
+	)?;
 
-	let block = create_block::<RA, _, _, _, _>(&client, transfer, inherents);
 	info!(
-		"Created block {} with hash {}. Transferring {} from {} to {}.",
+		"Created transaction {}. {} of {} from {} to {}.",
 		factory_state.block_no() + RA::Number::one(),
-		prior_block_hash,
+		calls.scenario().call(n),
 		amount,
 		from.0,
 		to
@@ -125,7 +113,7 @@ where
 		factory_state.set_block_in_round(factory_state.block_in_round() + RA::Number::one());
 	}
 
-	Some(block)
+	Ok(Some(payload))
 }
 
 /// Return the account which received tokens at this point in the previous round.
@@ -151,7 +139,7 @@ where RA: RuntimeAdapter
 					factory_state.start_number() + block_no_in_prior_round
 				}
 			};
-			(RA::gen_random_account_id(&seed), RA::gen_random_account_secret(&seed))
+			(factory_state.gen_random_account_id(&seed), factory_state.gen_random_account_secret(&seed))
 		},
 	}
 }
@@ -17,44 +17,80 @@
 //! Simple transaction factory which distributes tokens from a master
 //! account to a specified number of newly created accounts.
 //!
-//! The factory currently only works on an empty database!
+//! What the transactions do is described by a [`Scenario`], transfers by default.
+//!
+//! In the `Pool` mode, the transactions are sent from an [`AccountPool`] funded by the master
+//! account, so that they come from many accounts rather than one.
+//!
+//! The accounts created by the factory sign with the [`SignatureScheme`] of the run, to compare
+//! the cost of verifying the signatures of the different schemes.
+//!
+//! The transactions are signed with the nonces of the accounts read from the chain, so the
+//! factory can run repeatedly against the same chain. Their payloads are built in order, the
+//! signing of a batch of them being spread over a thread pool.
+//!
+//! The [`Metrics`] of the blocks are summarized at the end of the run, and can be written to a
+//! report file to compare runs.
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::cmp::PartialOrd;
 use std::fmt::Display;
+use std::path::Path;
+use std::time::Instant;
 
 use log::info;
+use rayon::prelude::*;
 
 use sc_client::Client;
 use sp_block_builder::BlockBuilder;
-use sp_api::{ConstructRuntimeApi, ProvideRuntimeApi, ApiExt};
+use sp_api::{ConstructRuntimeApi, ProvideRuntimeApi, ApiExt, Metadata};
 use sp_consensus::{
 	BlockOrigin, BlockImportParams, InherentData, ForkChoiceStrategy,
 	SelectChain
 };
 use sp_consensus::block_import::BlockImport;
-use codec::{Decode, Encode};
+use codec::{Codec, Decode, Encode};
+use frame_system_rpc_runtime_api::AccountNonceApi;
+use sp_core::{hexdisplay::HexDisplay, storage::StorageKey};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{
-	Block as BlockT, Header as HeaderT, SimpleArithmetic, One, Zero,
+	Block as BlockT, Header as HeaderT, SimpleArithmetic, One, Zero, UniqueSaturatedInto,
 };
-pub use crate::modes::Mode;
+pub use crate::calls::{Calls, EncodedCall};
+pub use crate::metrics::{BlockMetrics, Metrics, Report};
+pub use crate::modes::{Mode, SenderSelection, SignatureScheme};
+pub use crate::pool::AccountPool;
+pub use crate::scenario::{Scenario, ScenarioCall, Variables};
 
+pub mod metrics;
 pub mod modes;
+pub mod scenario;
+mod calls;
 mod complex_mode;
+mod pool;
+mod pool_mode;
 mod simple_modes;
 
-pub trait RuntimeAdapter {
-	type AccountId: Display;
-	type Balance: Display + SimpleArithmetic + From<Self::Number>;
+/// What the factory needs to know about a runtime besides its metadata.
+///
+/// Calls are encoded from the scenario and the metadata of the runtime, and signed with the
+/// address, signature and signed extensions types of its registry. The adapter only provides the
+/// accounts, the values of the signed extensions and the signature scheme of the chain.
+pub trait RuntimeAdapter: Sized {
+	type AccountId: Display + Codec;
+	type Balance: Display + SimpleArithmetic + Codec + From<Self::Number>;
 	type Block: BlockT;
-	type Index: Copy;
+	type Index: Copy + Codec;
 	type Number: Display + PartialOrd + SimpleArithmetic + Zero + One;
 	type Phase: Copy;
+	/// The key of an account, of any of the signature schemes of the adapter.
 	type Secret;
+	/// Everything needed to sign a transaction, independently of the state of the factory.
+	type Payload: Send;
 
-	fn new(mode: Mode, rounds: u64, start_number: u64) -> Self;
+	/// The state of a run in `mode`, the accounts it creates signing with `scheme`.
+	fn new(mode: Mode, num: u64, rounds: u64, pool: AccountPool, scheme: SignatureScheme) -> Self;
 
 	fn block_no(&self) -> Self::Number;
 	fn block_in_round(&self) -> Self::Number;
@@ -63,37 +99,116 @@ pub trait RuntimeAdapter {
 	fn rounds(&self) -> Self::Number;
 	fn round(&self) -> Self::Number;
 	fn start_number(&self) -> Self::Number;
+	/// The accounts sending the transactions of the `Pool` mode.
+	fn pool(&mut self) -> &mut AccountPool;
+	/// The signature scheme of the accounts created by the run.
+	fn signature_scheme(&self) -> SignatureScheme;
 
 	fn set_block_in_round(&mut self, val: Self::Number);
 	fn set_block_no(&mut self, val: Self::Number);
 	fn set_round(&mut self, val: Self::Number);
 
-	fn transfer_extrinsic(
-		&self,
+	/// The payload of `call` signed by `sender`, building its signed extensions from the state
+	/// of `chain`. Payloads must be built in the order of the transactions, as their nonces are.
+	fn signing_payload(
+		&mut self,
+		chain: &dyn ChainState<Self>,
 		sender: &Self::AccountId,
 		key: &Self::Secret,
-		destination: &Self::AccountId,
-		amount: &Self::Balance,
+		call: EncodedCall,
 		version: u32,
 		genesis_hash: &<Self::Block as BlockT>::Hash,
 		prior_block_hash: &<Self::Block as BlockT>::Hash,
-	) -> <Self::Block as BlockT>::Extrinsic;
+	) -> Result<Self::Payload, String>;
 
-	fn inherent_extrinsics(&self) -> InherentData;
+	/// Sign `payload` as an extrinsic of the runtime described by `calls`, possibly on another
+	/// thread.
+	fn sign(calls: &Calls, payload: Self::Payload) -> Result<<Self::Block as BlockT>::Extrinsic, String>;
+
+	/// The inherent data of the block built on top of the block `parent`, read from `chain`.
+	fn inherent_extrinsics(
+		&self,
+		chain: &dyn ChainState<Self>,
+		parent: &<Self::Block as BlockT>::Hash,
+	) -> Result<InherentData, String>;
 
-	fn minimum_balance() -> Self::Balance;
 	fn master_account_id() -> Self::AccountId;
 	fn master_account_secret() -> Self::Secret;
-	fn extract_index(&self, account_id: &Self::AccountId, block_hash: &<Self::Block as BlockT>::Hash) -> Self::Index;
-	fn extract_phase(&self, block_hash: <Self::Block as BlockT>::Hash) -> Self::Phase;
-	fn gen_random_account_id(seed: &Self::Number) -> Self::AccountId;
-	fn gen_random_account_secret(seed: &Self::Number) -> Self::Secret;
+	/// The index of the next transaction of `account_id`, read from `chain` at `block_hash` the
+	/// first time and incremented by every transaction signed since.
+	fn extract_index(
+		&mut self,
+		chain: &dyn ChainState<Self>,
+		account_id: &Self::AccountId,
+		block_hash: &<Self::Block as BlockT>::Hash,
+	) -> Result<Self::Index, String>;
+	/// The phase of transactions mortal from `block_hash`.
+	fn extract_phase(
+		&mut self,
+		chain: &dyn ChainState<Self>,
+		block_hash: <Self::Block as BlockT>::Hash,
+	) -> Result<Self::Phase, String>;
+	/// The account created from `seed`, of the signature scheme of the run.
+	fn gen_random_account_id(&self, seed: &Self::Number) -> Self::AccountId;
+	/// The key of the account created from `seed`.
+	fn gen_random_account_secret(&self, seed: &Self::Number) -> Self::Secret;
+}
+
+/// The state of the chain the factory signs transactions for.
+pub trait ChainState<RA: RuntimeAdapter> {
+	/// The index of the next transaction of `account_id` at the block `at`.
+	fn account_nonce(
+		&self,
+		account_id: &RA::AccountId,
+		at: &<RA::Block as BlockT>::Hash,
+	) -> Result<RA::Index, String>;
+
+	/// The number of the block `at`.
+	fn block_number(&self, at: &<RA::Block as BlockT>::Hash) -> Result<u64, String>;
+
+	/// The value of the storage entry `key` at the block `at`.
+	fn storage(&self, key: &[u8], at: &<RA::Block as BlockT>::Hash) -> Result<Option<Vec<u8>>, String>;
+}
+
+impl<RA, Backend, Exec, Block, RtApi> ChainState<RA> for Client<Backend, Exec, Block, RtApi> where
+	Block: BlockT,
+	Exec: sc_client::CallExecutor<Block, Backend = Backend> + Send + Sync,
+	Backend: sc_client_api::backend::Backend<Block>,
+	Client<Backend, Exec, Block, RtApi>: ProvideRuntimeApi<Block>,
+	<Client<Backend, Exec, Block, RtApi> as ProvideRuntimeApi<Block>>::Api:
+		AccountNonceApi<Block, RA::AccountId, RA::Index, Error = sp_blockchain::Error>,
+	RA: RuntimeAdapter<Block = Block>,
+{
+	fn account_nonce(&self, account_id: &RA::AccountId, at: &Block::Hash) -> Result<RA::Index, String> {
+		self.runtime_api().account_nonce(&BlockId::hash(*at), account_id.clone())
+			.map_err(|e| format!("Unable to read the nonce of {}: {:?}", account_id, e))
+	}
+
+	fn block_number(&self, at: &Block::Hash) -> Result<u64, String> {
+		match self.header(&BlockId::hash(*at)) {
+			Ok(Some(header)) => Ok((*header.number()).unique_saturated_into()),
+			Ok(None) => Err(format!("Unknown block {}", at)),
+			Err(e) => Err(format!("Unable to read the header of block {}: {:?}", at, e)),
+		}
+	}
+
+	fn storage(&self, key: &[u8], at: &Block::Hash) -> Result<Option<Vec<u8>>, String> {
+		Client::storage(self, &BlockId::hash(*at), &StorageKey(key.to_vec()))
+			.map(|value| value.map(|value| value.0))
+			.map_err(|e| format!("Unable to read the storage of block {}: {:?}", at, e))
+	}
 }
 
-/// Manufactures transactions. The exact amount depends on
-/// `mode`, `num` and `rounds`.
+/// Manufactures transactions of the calls of `scenario`, `transactions_per_block` per block.
+/// The exact amount depends on `mode`, `num` and `rounds`.
+///
+/// The metrics of the blocks are logged at the end of the run, and written to `report_file` if
+/// any.
 pub fn factory<RA, Backend, Exec, Block, RtApi, Sc>(
 	mut factory_state: RA,
+	scenario: Scenario,
+	transactions_per_block: usize,
+	report_file: Option<&Path>,
 	client: &Arc<Client<Backend, Exec, Block, RtApi>>,
 	select_chain: &Sc,
 ) -> sc_cli::error::Result<()>
@@ -104,59 +219,224 @@ where
 	Client<Backend, Exec, Block, RtApi>: ProvideRuntimeApi<Block>,
 	<Client<Backend, Exec, Block, RtApi> as ProvideRuntimeApi<Block>>::Api:
 		BlockBuilder<Block, Error = sp_blockchain::Error> +
+		Metadata<Block, Error = sp_blockchain::Error> +
+		AccountNonceApi<Block, RA::AccountId, RA::Index, Error = sp_blockchain::Error> +
 		ApiExt<Block, StateBackend = Backend::State>,
 	RtApi: ConstructRuntimeApi<Block, Client<Backend, Exec, Block, RtApi>> + Send + Sync,
 	Sc: SelectChain<Block>,
 	RA: RuntimeAdapter<Block = Block>,
 	Block::Hash: From<sp_core::H256>,
 {
-	if *factory_state.mode() != Mode::MasterToNToM && factory_state.rounds() > RA::Number::one() {
-		let msg = "The factory can only be used with rounds set to 1 in this mode.".into();
+	check_rounds(&factory_state)?;
+	check_signature_scheme(factory_state.mode(), factory_state.signature_scheme())?;
+	if transactions_per_block == 0 {
+		let msg = "The factory needs at least one transaction per block.".into();
 		return Err(sc_cli::error::Error::Input(msg));
 	}
 
 	let best_header: Result<<Block as BlockT>::Header, sc_cli::error::Error> =
 		select_chain.best_chain().map_err(|e| format!("{:?}", e).into());
-	let mut best_hash = best_header?.hash();
+	let best_header = best_header?;
+	let mut best_hash = best_header.hash();
+	let mut best_number: u64 = (*best_header.number()).unique_saturated_into();
 	let mut best_block_id = BlockId::<Block>::hash(best_hash);
 	let version = client.runtime_version_at(&best_block_id)?.spec_version;
 	let genesis_hash = client.block_hash(Zero::zero())?
 		.expect("Genesis block always exists; qed").into();
+	let metadata = client.runtime_api().metadata(&best_block_id)?;
+	let calls = Calls::new(scenario, &metadata)?;
 
-	while let Some(block) = match factory_state.mode() {
-		Mode::MasterToNToM => complex_mode::next::<RA, _, _, _, _>(
+	let mut metrics = Metrics::start(client.usage_info().usage);
+	// The transactions which didn't fit in the last block, included first in the next one.
+	let mut pending = Vec::new();
+	loop {
+		let started = Instant::now();
+		let inherents = factory_state.inherent_extrinsics(&**client, &best_hash)?;
+		let extrinsics = next_extrinsics(
 			&mut factory_state,
-			&client,
+			&**client,
+			&calls,
 			version,
 			genesis_hash,
 			best_hash.into(),
-			best_block_id,
-		),
-		_ => simple_modes::next::<RA, _, _, _, _>(
-			&mut factory_state,
-			&client,
-			version,
-			genesis_hash,
-			best_hash.into(),
-			best_block_id,
-		),
-	} {
+			transactions_per_block - pending.len(),
+		)?;
+		if pending.is_empty() && extrinsics.is_empty() {
+			break;
+		}
+		pending.extend(extrinsics);
+
+		let inherents = client.runtime_api().inherent_extrinsics(&best_block_id, inherents)
+			.expect("Failed to create inherent extrinsics");
+		let to_include = pending.len();
+		let (block, weight, storage_writes) =
+			create_block::<RA, _, _, _, _>(&client, &mut pending, inherents);
+		if pending.len() == to_include {
+			let msg = "A transaction of the scenario exhausts the resources of an empty block.";
+			return Err(sc_cli::error::Error::Input(msg.into()));
+		}
+		if !pending.is_empty() {
+			info!("Block is full, {} transactions postponed to the next block.", pending.len());
+		}
+		let construction = started.elapsed();
+		info!("Created block {} with hash {}.", factory_state.block_no(), block.header().hash());
+
 		best_hash = block.header().hash();
+		best_number += 1;
 		best_block_id = BlockId::<Block>::hash(best_hash);
+		let extrinsics = block.extrinsics().len();
+		let started = Instant::now();
 		import_block(client.clone(), block);
+		let import = started.elapsed();
+		let database = metrics.measure_database(|| client.usage_info().usage);
+		metrics.record_block(BlockMetrics {
+			number: best_number,
+			hash: format!("{:?}", best_hash),
+			transactions: to_include - pending.len(),
+			extrinsics,
+			construction_ms: metrics::millis(construction),
+			import_ms: metrics::millis(import),
+			weight,
+			storage_writes,
+			database,
+		});
 
 		info!("Imported block at {}", factory_state.block_no());
 	}
 
+	let report = metrics.finish();
+	report.log_summary();
+	if let Some(path) = report_file {
+		report.write(path)
+			.map_err(|e| format!("Unable to write the report to {}: {}", path.display(), e))?;
+		info!("Wrote the report to {}.", path.display());
+	}
+
+	Ok(())
+}
+
+/// Check that `rounds` is only set in the modes repeating them.
+pub fn check_rounds<RA: RuntimeAdapter>(factory_state: &RA) -> sc_cli::error::Result<()> {
+	let repeats = match factory_state.mode() {
+		Mode::MasterToNToM | Mode::Pool => true,
+		Mode::MasterToN | Mode::MasterTo1 => false,
+	};
+	if !repeats && factory_state.rounds() > RA::Number::one() {
+		let msg = "The factory can only be used with rounds set to 1 in this mode.".into();
+		return Err(sc_cli::error::Error::Input(msg));
+	}
+	Ok(())
+}
+
+/// Check that a signature scheme other than the one of the master account is only set in the
+/// modes sending transactions from the accounts they create.
+pub fn check_signature_scheme(mode: &Mode, scheme: SignatureScheme) -> sc_cli::error::Result<()> {
+	let creates_senders = match mode {
+		Mode::MasterToNToM | Mode::Pool => true,
+		Mode::MasterToN | Mode::MasterTo1 => false,
+	};
+	if !creates_senders && scheme != SignatureScheme::Sr25519 {
+		let msg = "The signature scheme only applies to the modes MasterToNToM and Pool, \
+			all the transactions of this mode are signed by the sr25519 master account.".into();
+		return Err(sc_cli::error::Error::Input(msg));
+	}
 	Ok(())
 }
 
-/// Create a baked block from a transfer extrinsic and timestamp inherent.
+/// Sign the next `max` transactions of the mode of `factory_state` for the state of `chain`,
+/// mortal from `prior_block_hash`. Returns fewer once all the transactions are manufactured.
+///
+/// The payloads are built in order, then signed in parallel. The transactions are returned in
+/// the order of their payloads.
+pub fn next_extrinsics<RA: RuntimeAdapter>(
+	factory_state: &mut RA,
+	chain: &dyn ChainState<RA>,
+	calls: &Calls,
+	version: u32,
+	genesis_hash: <RA::Block as BlockT>::Hash,
+	prior_block_hash: <RA::Block as BlockT>::Hash,
+	max: usize,
+) -> Result<Vec<<RA::Block as BlockT>::Extrinsic>, String> {
+	let mut payloads = Vec::with_capacity(max);
+	while payloads.len() < max {
+		match next_payload(factory_state, chain, calls, version, genesis_hash, prior_block_hash)? {
+			Some(payload) => payloads.push(payload),
+			None => break,
+		}
+	}
+
+	payloads.into_par_iter().map(|payload| RA::sign(calls, payload)).collect()
+}
+
+/// The payload of the next transaction of the mode of `factory_state`, `None` once all the
+/// transactions are manufactured.
+fn next_payload<RA: RuntimeAdapter>(
+	factory_state: &mut RA,
+	chain: &dyn ChainState<RA>,
+	calls: &Calls,
+	version: u32,
+	genesis_hash: <RA::Block as BlockT>::Hash,
+	prior_block_hash: <RA::Block as BlockT>::Hash,
+) -> Result<Option<RA::Payload>, String> {
+	match factory_state.mode() {
+		Mode::MasterToNToM =>
+			complex_mode::next(factory_state, chain, calls, version, genesis_hash, prior_block_hash),
+		Mode::Pool =>
+			pool_mode::next(factory_state, chain, calls, version, genesis_hash, prior_block_hash),
+		_ => simple_modes::next(factory_state, chain, calls, version, genesis_hash, prior_block_hash),
+	}
+}
+
+/// Encode the call of the transaction number `n` of the scenario, from `sender` to
+/// `destination`.
+fn encode_call<RA: RuntimeAdapter>(
+	calls: &Calls,
+	chain: &dyn ChainState<RA>,
+	n: u64,
+	sender: &RA::AccountId,
+	destination: &RA::AccountId,
+	amount: &RA::Balance,
+	prior_block_hash: &<RA::Block as BlockT>::Hash,
+) -> Result<EncodedCall, String> {
+	let block_number = match calls.scenario().uses_block_number() {
+		true => chain.block_number(prior_block_hash)?,
+		false => 0,
+	};
+	calls.encode(n, &variables::<RA>(sender, destination, amount, block_number))
+}
+
+/// Encode the transfer of `amount` from `sender` funding `destination`, whatever the scenario.
+fn encode_funding<RA: RuntimeAdapter>(
+	calls: &Calls,
+	sender: &RA::AccountId,
+	destination: &RA::AccountId,
+	amount: &RA::Balance,
+) -> Result<EncodedCall, String> {
+	calls.encode_funding(&variables::<RA>(sender, destination, amount, 0))
+}
+
+fn variables<RA: RuntimeAdapter>(
+	sender: &RA::AccountId,
+	destination: &RA::AccountId,
+	amount: &RA::Balance,
+	block_number: u64,
+) -> Variables {
+	Variables {
+		sender: format!("0x{}", HexDisplay::from(&sender.encode())),
+		destination: format!("0x{}", HexDisplay::from(&destination.encode())),
+		amount: amount.to_string(),
+		block_number,
+	}
+}
+
+/// Create a baked block from the inherents and as many of `extrinsics` as fit in it, removing
+/// them from `extrinsics`. Returns it with the weight it consumes, if the runtime reports it, and
+/// the number of storage keys it writes.
 pub fn create_block<RA, Backend, Exec, Block, RtApi>(
 	client: &Arc<Client<Backend, Exec, Block, RtApi>>,
-	transfer: <RA::Block as BlockT>::Extrinsic,
+	extrinsics: &mut Vec<<RA::Block as BlockT>::Extrinsic>,
 	inherent_extrinsics: Vec<<Block as BlockT>::Extrinsic>,
-) -> Block
+) -> (Block, Option<u64>, usize)
 where
 	Block: BlockT,
 	Exec: sc_client::CallExecutor<Block, Backend = Backend> + Send + Sync + Clone,
@@ -169,16 +449,33 @@ where
 	RA: RuntimeAdapter,
 {
 	let mut block = client.new_block(Default::default()).expect("Failed to create new block");
-	block.push(
-		Decode::decode(&mut &transfer.encode()[..])
-			.expect("Failed to decode transfer extrinsic")
-	).expect("Failed to push transfer extrinsic into block");
-
 	for inherent in inherent_extrinsics {
-		block.push(inherent).expect("Failed ...");
+		block.push(inherent).expect("Failed to push inherent into block");
+	}
+
+	let mut included = 0;
+	for extrinsic in extrinsics.iter() {
+		let extrinsic = Decode::decode(&mut &extrinsic.encode()[..])
+			.expect("Failed to decode extrinsic");
+		match block.push(extrinsic) {
+			Ok(()) => included += 1,
+			Err(sp_blockchain::Error::ApplyExtrinsicFailed(
+				sp_blockchain::ApplyExtrinsicFailed::Validity(e)
+			)) if e.exhausted_resources() => break,
+			Err(e) => panic!("Failed to push extrinsic into block: {:?}", e),
+		}
 	}
+	extrinsics.drain(..included);
 
-	block.build().expect("Failed to bake block").block
+	let weight = block.block_weight()
+		.expect("Failed to read the weight of the block")
+		.map(|weight| weight.consumed);
+	let built = block.build().expect("Failed to bake block");
+	let changes = &built.storage_changes;
+	let storage_writes = changes.main_storage_changes.len() + changes.child_storage_changes.iter()
+		.map(|(_, changes)| changes.len())
+		.sum::<usize>();
+	(built.block, weight, storage_writes)
 }
 
 fn import_block<Backend, Exec, Block, RtApi>(
@@ -208,3 +505,21 @@ fn import_block<Backend, Exec, Block, RtApi>(
 	};
 	client.import_block(import, HashMap::new()).expect("Failed to import block");
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn signature_scheme_is_only_set_in_modes_creating_senders() {
+		for mode in vec![Mode::MasterToNToM, Mode::Pool] {
+			assert!(check_signature_scheme(&mode, SignatureScheme::Ed25519).is_ok());
+			assert!(check_signature_scheme(&mode, SignatureScheme::Ecdsa).is_ok());
+		}
+		for mode in vec![Mode::MasterToN, Mode::MasterTo1] {
+			assert!(check_signature_scheme(&mode, SignatureScheme::Sr25519).is_ok());
+			assert!(check_signature_scheme(&mode, SignatureScheme::Ed25519).is_err());
+			assert!(check_signature_scheme(&mode, SignatureScheme::Ecdsa).is_err());
+		}
+	}
+}
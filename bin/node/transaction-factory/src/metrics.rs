@@ -0,0 +1,344 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Metrics of the blocks manufactured by the factory.
+//!
+//! The metrics of every block are recorded as it is built and imported, and reported at the end
+//! of the run as a summary table, and optionally as a JSON or CSV file to compare runs, e.g.
+//! across runtime versions.
+//!
+//! The database only measures its reads and writes at most once a second, so the factory waits
+//! for the next measure after every block. The waits are left out of the duration of the run.
+
+use std::{fs, io, path::Path, thread, time::{Duration, Instant}};
+
+use log::info;
+use sc_client_api::client::UsageInfo;
+use serde::Serialize;
+
+/// The interval at which the database measures its reads and writes.
+const IO_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The metrics of a block.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockMetrics {
+	/// Number of the block.
+	pub number: u64,
+	/// Hash of the block, hex encoded.
+	pub hash: String,
+	/// Number of transactions of the scenario included in the block.
+	pub transactions: usize,
+	/// Number of extrinsics of the block, inherents included.
+	pub extrinsics: usize,
+	/// Time spent signing the transactions and building the block, in milliseconds.
+	pub construction_ms: f64,
+	/// Time spent importing the block, in milliseconds.
+	pub import_ms: f64,
+	/// Weight of the block, `None` if the runtime doesn't report it.
+	pub weight: Option<u64>,
+	/// Number of storage keys written by the block.
+	pub storage_writes: usize,
+	/// The reads and writes of the database while building and importing the block, `None` if
+	/// the backend doesn't measure them.
+	pub database: Option<DatabaseMetrics>,
+}
+
+/// The reads and writes of the database.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseMetrics {
+	/// Keys read from the database.
+	pub reads: u64,
+	/// Keys written to the database.
+	pub writes: u64,
+	/// Bytes read from the database.
+	pub bytes_read: u64,
+	/// Bytes written to the database.
+	pub bytes_written: u64,
+}
+
+impl DatabaseMetrics {
+	fn add(&mut self, other: &DatabaseMetrics) {
+		self.reads += other.reads;
+		self.writes += other.writes;
+		self.bytes_read += other.bytes_read;
+		self.bytes_written += other.bytes_written;
+	}
+}
+
+impl From<UsageInfo> for DatabaseMetrics {
+	fn from(usage: UsageInfo) -> Self {
+		DatabaseMetrics {
+			reads: usage.io.reads,
+			writes: usage.io.writes,
+			bytes_read: usage.io.bytes_read,
+			bytes_written: usage.io.bytes_written,
+		}
+	}
+}
+
+/// The metrics of a run of the factory.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+	/// Duration of the run, in milliseconds.
+	pub duration_ms: f64,
+	/// The metrics of the blocks, in order.
+	pub blocks: Vec<BlockMetrics>,
+	/// The reads and writes of the database over the run, `None` if the backend doesn't measure
+	/// them.
+	pub database: Option<DatabaseMetrics>,
+}
+
+/// Collects the metrics of a run of the factory.
+pub struct Metrics {
+	started: Instant,
+	blocks: Vec<BlockMetrics>,
+	measures_database: bool,
+	measured: Instant,
+	/// Time spent waiting for the measures of the database.
+	waited: Duration,
+}
+
+impl Metrics {
+	/// Start collecting metrics, `usage` being the usage info of the backend, to reset its
+	/// measures.
+	pub fn start(usage: Option<UsageInfo>) -> Self {
+		let now = Instant::now();
+		Metrics {
+			started: now,
+			blocks: Vec::new(),
+			measures_database: usage.is_some(),
+			measured: now,
+			waited: Duration::from_secs(0),
+		}
+	}
+
+	/// Wait for the next measure of the database and return the reads and writes since the
+	/// previous one, `usage` returning the usage info of the backend. `None` if the backend
+	/// doesn't measure them.
+	pub fn measure_database(
+		&mut self,
+		usage: impl FnOnce() -> Option<UsageInfo>,
+	) -> Option<DatabaseMetrics> {
+		if !self.measures_database {
+			return None;
+		}
+		let elapsed = self.measured.elapsed();
+		if elapsed <= IO_SAMPLE_INTERVAL {
+			let wait = IO_SAMPLE_INTERVAL - elapsed + Duration::from_millis(10);
+			thread::sleep(wait);
+			self.waited += wait;
+		}
+		self.measured = Instant::now();
+		usage().map(Into::into)
+	}
+
+	/// Record the metrics of a block.
+	pub fn record_block(&mut self, block: BlockMetrics) {
+		self.blocks.push(block);
+	}
+
+	/// End the run.
+	pub fn finish(self) -> Report {
+		let duration = self.started.elapsed() - self.waited;
+		let database = match self.measures_database {
+			true => Some(self.blocks.iter()
+				.filter_map(|block| block.database.as_ref())
+				.fold(DatabaseMetrics::default(), |mut total, block| {
+					total.add(block);
+					total
+				})),
+			false => None,
+		};
+
+		Report {
+			duration_ms: millis(duration),
+			blocks: self.blocks,
+			database,
+		}
+	}
+}
+
+/// A duration in milliseconds.
+pub fn millis(duration: Duration) -> f64 {
+	duration.as_secs_f64() * 1000.0
+}
+
+impl Report {
+	/// The number of transactions of the scenario included per second spent building and
+	/// importing the blocks, inherents excluded.
+	pub fn transactions_per_second(&self) -> f64 {
+		let transactions: usize = self.blocks.iter().map(|block| block.transactions).sum();
+		let busy_ms: f64 = self.blocks.iter().map(|block| block.construction_ms + block.import_ms).sum();
+		if busy_ms > 0.0 { transactions as f64 * 1000.0 / busy_ms } else { 0.0 }
+	}
+
+	/// Log the summary of the run.
+	pub fn log_summary(&self) {
+		info!(
+			"Manufactured {} blocks with {} transactions ({} extrinsics) in {:.1}s: {:.1} transactions per second.",
+			self.blocks.len(),
+			self.blocks.iter().map(|block| block.transactions).sum::<usize>(),
+			self.blocks.iter().map(|block| block.extrinsics).sum::<usize>(),
+			self.duration_ms / 1000.0,
+			self.transactions_per_second(),
+		);
+		if self.blocks.is_empty() {
+			return;
+		}
+
+		info!("{:<20} {:>12} {:>12} {:>12}", "", "average", "minimum", "maximum");
+		let row = |name: &str, values: Vec<f64>| {
+			if values.is_empty() {
+				return;
+			}
+			let average = values.iter().sum::<f64>() / values.len() as f64;
+			let minimum = values.iter().cloned().fold(f64::INFINITY, f64::min);
+			let maximum = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+			info!("{:<20} {:>12.1} {:>12.1} {:>12.1}", name, average, minimum, maximum);
+		};
+		row("transactions", self.blocks.iter().map(|block| block.transactions as f64).collect());
+		row("extrinsics", self.blocks.iter().map(|block| block.extrinsics as f64).collect());
+		row("construction (ms)", self.blocks.iter().map(|block| block.construction_ms).collect());
+		row("import (ms)", self.blocks.iter().map(|block| block.import_ms).collect());
+		row("weight", self.blocks.iter().filter_map(|block| block.weight.map(|w| w as f64)).collect());
+		row("storage writes", self.blocks.iter().map(|block| block.storage_writes as f64).collect());
+		let database = || self.blocks.iter().filter_map(|block| block.database.as_ref());
+		row("database reads", database().map(|database| database.reads as f64).collect());
+		row("database writes", database().map(|database| database.writes as f64).collect());
+
+		if let Some(database) = &self.database {
+			info!(
+				"Database: {} reads ({} bytes), {} writes ({} bytes).",
+				database.reads,
+				database.bytes_read,
+				database.writes,
+				database.bytes_written,
+			);
+		}
+	}
+
+	/// Write the report to `path`, as CSV if its extension is `csv`, JSON otherwise.
+	pub fn write(&self, path: &Path) -> io::Result<()> {
+		let content = match path.extension().map_or(false, |extension| extension == "csv") {
+			true => self.to_csv(),
+			false => self.to_json().map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
+		};
+		fs::write(path, content)
+	}
+
+	/// The report as CSV, a row per block. The metrics not measured are left empty.
+	pub fn to_csv(&self) -> String {
+		let mut content = "number,hash,transactions,extrinsics,construction_ms,import_ms,weight,\
+			storage_writes,database_reads,database_writes,database_bytes_read,database_bytes_written\n"
+			.to_string();
+		for block in &self.blocks {
+			let optional = |value: Option<u64>| value.map(|value| value.to_string()).unwrap_or_default();
+			let database = |metric: fn(&DatabaseMetrics) -> u64| optional(block.database.as_ref().map(metric));
+			content.push_str(&format!(
+				"{},{},{},{},{:.3},{:.3},{},{},{},{},{},{}\n",
+				block.number,
+				block.hash,
+				block.transactions,
+				block.extrinsics,
+				block.construction_ms,
+				block.import_ms,
+				optional(block.weight),
+				block.storage_writes,
+				database(|database| database.reads),
+				database(|database| database.writes),
+				database(|database| database.bytes_read),
+				database(|database| database.bytes_written),
+			));
+		}
+		content
+	}
+
+	/// The report as JSON.
+	pub fn to_json(&self) -> serde_json::Result<String> {
+		serde_json::to_string_pretty(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn report() -> Report {
+		let block = |number, database| BlockMetrics {
+			number,
+			hash: format!("0x0{}", number),
+			transactions: 1,
+			extrinsics: 2,
+			construction_ms: 1.5,
+			import_ms: 0.5,
+			weight: None,
+			storage_writes: 3,
+			database,
+		};
+		let database = DatabaseMetrics { reads: 4, writes: 5, bytes_read: 6, bytes_written: 7 };
+		Report {
+			duration_ms: 10.0,
+			blocks: vec![block(1, Some(database.clone())), block(2, None)],
+			database: Some(database),
+		}
+	}
+
+	#[test]
+	fn csv_has_a_row_per_block() {
+		assert_eq!(report().to_csv(), "\
+			number,hash,transactions,extrinsics,construction_ms,import_ms,weight,storage_writes,\
+			database_reads,database_writes,database_bytes_read,database_bytes_written\n\
+			1,0x01,1,2,1.500,0.500,,3,4,5,6,7\n\
+			2,0x02,1,2,1.500,0.500,,3,,,,\n\
+		");
+	}
+
+	#[test]
+	fn json_has_the_blocks_and_the_database_metrics() {
+		let json: serde_json::Value = serde_json::from_str(&report().to_json().unwrap()).unwrap();
+
+		assert_eq!(json["durationMs"], 10.0);
+		assert_eq!(json["blocks"][0]["transactions"], 1);
+		assert_eq!(json["blocks"][0]["extrinsics"], 2);
+		assert_eq!(json["blocks"][0]["database"]["bytesWritten"], 7);
+		assert_eq!(json["blocks"][1]["database"], serde_json::Value::Null);
+		assert_eq!(json["database"]["reads"], 4);
+	}
+
+	#[test]
+	fn inherents_are_not_counted_as_transactions() {
+		// 2 transactions in 4ms.
+		assert_eq!(report().transactions_per_second(), 500.0);
+	}
+
+	#[test]
+	fn the_run_totals_the_database_metrics_of_the_blocks() {
+		let mut metrics = Metrics::start(None);
+		metrics.measures_database = true;
+		let mut report = report();
+		for block in report.blocks.drain(..) {
+			metrics.record_block(block.clone());
+			metrics.record_block(block);
+		}
+
+		let report = metrics.finish();
+
+		assert_eq!(report.database, Some(DatabaseMetrics { reads: 8, writes: 10, bytes_read: 12, bytes_written: 14 }));
+	}
+}
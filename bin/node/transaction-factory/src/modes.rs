@@ -15,7 +15,7 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! The transaction factory can operate in different modes. See
-//! the `simple_mode` and `complex_mode` modules for details.
+//! the `simple_mode`, `complex_mode` and `pool_mode` modules for details.
 
 use std::str::FromStr;
 
@@ -24,7 +24,8 @@ use std::str::FromStr;
 pub enum Mode {
 	MasterToN,
 	MasterTo1,
-	MasterToNToM
+	MasterToNToM,
+	Pool,
 }
 
 impl FromStr for Mode {
@@ -34,7 +35,50 @@ impl FromStr for Mode {
 			"MasterToN" => Ok(Mode::MasterToN),
 			"MasterTo1" => Ok(Mode::MasterTo1),
 			"MasterToNToM" => Ok(Mode::MasterToNToM),
+			"Pool" => Ok(Mode::Pool),
 			_ => Err(format!("Invalid mode: {}", mode)),
 		}
 	}
 }
+
+/// How the senders of the `Pool` mode are picked in the pool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SenderSelection {
+	/// The accounts of the pool send in turn.
+	RoundRobin,
+	/// The accounts of the pool send in a random order, drawn again every time all of them sent.
+	Random,
+}
+
+impl FromStr for SenderSelection {
+	type Err = String;
+	fn from_str(selection: &str) -> Result<Self, Self::Err> {
+		match selection {
+			"RoundRobin" => Ok(SenderSelection::RoundRobin),
+			"Random" => Ok(SenderSelection::Random),
+			_ => Err(format!("Invalid sender selection: {}", selection)),
+		}
+	}
+}
+
+/// The signature scheme of the accounts created by the factory. The master account keeps its
+/// own scheme, so the scheme applies to the transactions sent from the created accounts: the
+/// `MasterToNToM` rounds after the first, and the `Pool` mode once its accounts are funded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureScheme {
+	Sr25519,
+	Ed25519,
+	Ecdsa,
+}
+
+impl FromStr for SignatureScheme {
+	type Err = String;
+	fn from_str(scheme: &str) -> Result<Self, Self::Err> {
+		match scheme {
+			"Sr25519" => Ok(SignatureScheme::Sr25519),
+			"Ed25519" => Ok(SignatureScheme::Ed25519),
+			"Ecdsa" => Ok(SignatureScheme::Ecdsa),
+			_ => Err(format!("Invalid signature scheme: {}", scheme)),
+		}
+	}
+}
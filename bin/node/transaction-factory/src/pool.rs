@@ -0,0 +1,122 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The pool of accounts sending the transactions of the `Pool` mode.
+//!
+//! Every account of the pool sends once before any of them sends again, so that the number of
+//! transactions of each account, and the funds it needs, are known in advance whatever the
+//! selection.
+
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::modes::SenderSelection;
+
+/// The accounts of the pool, identified by their index in the pool.
+pub struct AccountPool {
+	size: u64,
+	selection: SenderSelection,
+	/// The order of the senders of the current cycle through the pool, with its number.
+	order: Option<(u64, Vec<u64>)>,
+}
+
+impl AccountPool {
+	/// A pool of `size` accounts picked according to `selection`. The random orders are seeded
+	/// with the number of the cycle, so that runs are reproducible.
+	pub fn new(size: u64, selection: SenderSelection) -> Self {
+		AccountPool { size, selection, order: None }
+	}
+
+	/// The number of accounts of the pool.
+	pub fn size(&self) -> u64 {
+		self.size
+	}
+
+	/// The most transactions an account of the pool sends, out of `total`.
+	pub fn sends_per_account(&self, total: u64) -> u64 {
+		match self.size {
+			0 => 0,
+			size => (total + size - 1) / size,
+		}
+	}
+
+	/// The index in the pool of the sender of the `n`-th transaction sent from the pool.
+	pub fn sender(&mut self, n: u64) -> u64 {
+		let (cycle, position) = (n / self.size, n % self.size);
+		match self.selection {
+			SenderSelection::RoundRobin => position,
+			SenderSelection::Random => {
+				match self.order {
+					Some((current, _)) if current == cycle => {},
+					_ => {
+						let mut order = (0..self.size).collect::<Vec<_>>();
+						order.shuffle(&mut StdRng::seed_from_u64(cycle));
+						self.order = Some((cycle, order));
+					},
+				}
+				self.order.as_ref().expect("The order of the cycle is set above; qed").1[position as usize]
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn senders(pool: &mut AccountPool, cycle: u64) -> Vec<u64> {
+		let size = pool.size();
+		(cycle * size..(cycle + 1) * size).map(|n| pool.sender(n)).collect()
+	}
+
+	#[test]
+	fn round_robin_sends_in_the_order_of_the_pool() {
+		let mut pool = AccountPool::new(3, SenderSelection::RoundRobin);
+
+		assert_eq!((0..7).map(|n| pool.sender(n)).collect::<Vec<_>>(), vec![0, 1, 2, 0, 1, 2, 0]);
+	}
+
+	#[test]
+	fn random_order_covers_every_account_once_per_cycle() {
+		let mut pool = AccountPool::new(10, SenderSelection::Random);
+
+		let cycles = (0..5).map(|cycle| senders(&mut pool, cycle)).collect::<Vec<_>>();
+
+		for order in &cycles {
+			let mut sorted = order.clone();
+			sorted.sort();
+			assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+		}
+		assert!(cycles.iter().any(|order| *order != cycles[0]));
+	}
+
+	#[test]
+	fn random_order_is_reproducible() {
+		let mut pool = AccountPool::new(10, SenderSelection::Random);
+		let first = senders(&mut pool, 1);
+		let second = senders(&mut pool, 2);
+
+		// Back to an earlier cycle, and with a new pool.
+		assert_eq!(senders(&mut pool, 1), first);
+		assert_eq!(senders(&mut AccountPool::new(10, SenderSelection::Random), 2), second);
+	}
+
+	#[test]
+	fn sends_are_spread_over_the_accounts() {
+		assert_eq!(AccountPool::new(3, SenderSelection::RoundRobin).sends_per_account(7), 3);
+		assert_eq!(AccountPool::new(3, SenderSelection::RoundRobin).sends_per_account(6), 2);
+		assert_eq!(AccountPool::new(0, SenderSelection::RoundRobin).sends_per_account(6), 0);
+	}
+}
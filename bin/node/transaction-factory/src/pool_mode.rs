@@ -0,0 +1,129 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+/// This module implements the `Pool` mode:
+///
+/// Fund the accounts of the pool from the master account, then
+/// manufacture `num` transactions per round for `rounds` rounds from
+/// senders picked in the pool, each to a randomly created account.
+/// The senders are picked in turn or at random, every account of the
+/// pool sending once before any of them sends again.
+///
+///   A -> P1
+///   A -> P2
+///   ... x pool size
+///
+///   P2 -> B
+///   P1 -> C
+///   ... x `num` x `rounds`
+
+use log::info;
+use sp_runtime::traits::{Block as BlockT, One, UniqueSaturatedFrom, UniqueSaturatedInto};
+
+use crate::{Calls, ChainState, RuntimeAdapter};
+
+pub fn next<RA: RuntimeAdapter>(
+	factory_state: &mut RA,
+	chain: &dyn ChainState<RA>,
+	calls: &Calls,
+	version: u32,
+	genesis_hash: <RA::Block as BlockT>::Hash,
+	prior_block_hash: <RA::Block as BlockT>::Hash,
+) -> Result<Option<RA::Payload>, String> {
+	let pool_size = factory_state.pool().size();
+	if pool_size == 0 {
+		return Err("The pool must have at least one account".into());
+	}
+
+	let num: u64 = factory_state.num().unique_saturated_into();
+	let rounds: u64 = factory_state.rounds().unique_saturated_into();
+	let total = num.saturating_mul(rounds);
+	let n: u64 = factory_state.block_no().unique_saturated_into();
+	if n >= pool_size.saturating_add(total) {
+		return Ok(None);
+	}
+
+	let (from, to, amount, call) = if n < pool_size {
+		// setup: the accounts of the pool get twice the amount of the transactions they send,
+		// to pay their fees, on top of the minimum balance.
+		if n == 0 {
+			info!("Funding the {} accounts of the pool.", pool_size);
+		}
+		let sends = factory_state.pool().sends_per_account(total);
+		let amount = calls.minimum_balance::<RA::Balance>()?
+			* RA::Balance::unique_saturated_from(sends.saturating_mul(2).saturating_add(1));
+		let from = (RA::master_account_id(), RA::master_account_secret());
+		let to = factory_state.gen_random_account_id(&pool_seed::<RA>(factory_state, n));
+		let call = crate::encode_funding::<RA>(calls, &from.0, &to, &amount)?;
+		(from, to, amount, call)
+	} else {
+		let sent = n - pool_size;
+		if sent % num.max(1) == 0 {
+			info!("Round {} of {}: Creating {} transactions.", sent / num.max(1) + 1, rounds, num);
+		}
+		let sender = factory_state.pool().sender(sent);
+		let seed = pool_seed::<RA>(factory_state, sender);
+		let from = (
+			factory_state.gen_random_account_id(&seed),
+			factory_state.gen_random_account_secret(&seed),
+		);
+		let to = factory_state
+			.gen_random_account_id(&pool_seed::<RA>(factory_state, pool_size + sent));
+		let amount = calls.minimum_balance::<RA::Balance>()?;
+		let call = crate::encode_call(
+			calls,
+			chain,
+			sent,
+			&from.0,
+			&to,
+			&amount,
+			&prior_block_hash,
+		)?;
+		(from, to, amount, call)
+	};
+
+	let payload = factory_state.signing_payload(
+		chain,
+		&from.0,
+		&from.1,
+		call,
+		version,
+		&genesis_hash,
+		&prior_block_hash,
+	)?;
+
+	factory_state.set_block_no(factory_state.block_no() + RA::Number::one());
+
+	info!(
+		"Created transaction {}. {} of {} from {} to {}.",
+		factory_state.block_no(),
+		match n.checked_sub(pool_size) {
+			Some(sent) => calls.scenario().call(sent).to_string(),
+			None => "Funding".into(),
+		},
+		amount,
+		from.0,
+		to
+	);
+
+	Ok(Some(payload))
+}
+
+/// The seed of the `index`-th account created by the mode: the accounts of the pool first, then
+/// the destinations of their transactions.
+fn pool_seed<RA: RuntimeAdapter>(factory_state: &RA, index: u64) -> RA::Number {
+	factory_state.start_number() + RA::Number::unique_saturated_from(index)
+}
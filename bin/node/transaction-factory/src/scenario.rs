@@ -0,0 +1,238 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Scenarios describing the calls the factory manufactures transactions of.
+//!
+//! A scenario is a sequence of calls, the transactions of the factory cycle through it. The
+//! mode still picks the sender, the destination and the amount of every transaction, the
+//! scenario decides what the transaction does with them. Scenarios are written in JSON:
+//!
+//! ```json
+//! {
+//!   "calls": [
+//!     { "module": "Balances", "call": "transfer", "args": { "dest": { "Id": "{destination}" }, "value": "{amount}" } },
+//!     { "module": "System", "call": "remark", "args": { "_remark": "0x00" } }
+//!   ]
+//! }
+//! ```
+//!
+//! The arguments are given in the form the metadata driven decoder produces them. String values
+//! which are exactly one of the following placeholders are replaced for every transaction:
+//!
+//! - `{sender}`: the hex encoded account id of the sender,
+//! - `{destination}`: the hex encoded account id of the destination,
+//! - `{amount}`: the amount of the transaction,
+//! - `{block_number}`: the number of the block the transaction is signed on top of.
+
+use std::{fmt, fs};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// A call of a scenario.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScenarioCall {
+	/// Name of the module of the call, e.g. `Balances`.
+	pub module: String,
+	/// Name of the call within the module, e.g. `transfer`.
+	pub call: String,
+	/// The arguments of the call, either an object keyed by argument name or an array.
+	#[serde(default)]
+	pub args: Value,
+}
+
+impl fmt::Display for ScenarioCall {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.{}", self.module, self.call)
+	}
+}
+
+/// The values of the placeholders for one transaction.
+pub struct Variables {
+	/// The hex encoded account id of the sender.
+	pub sender: String,
+	/// The hex encoded account id of the destination.
+	pub destination: String,
+	/// The amount of the transaction, in decimal.
+	pub amount: String,
+	/// The number of the block the transaction is signed on top of.
+	pub block_number: u64,
+}
+
+/// A sequence of calls to manufacture transactions of.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Scenario {
+	calls: Vec<ScenarioCall>,
+}
+
+/// Names of the built-in scenarios.
+pub const PRESETS: &[&str] = &["transfer", "transfer_keep_alive"];
+
+impl Scenario {
+	/// The built-in scenario `name`, one of [`PRESETS`].
+	pub fn preset(name: &str) -> Option<Self> {
+		let call = match name {
+			"transfer" | "transfer_keep_alive" => ScenarioCall {
+				module: "Balances".into(),
+				call: name.into(),
+				args: json!({ "dest": { "Id": "{destination}" }, "value": "{amount}" }),
+			},
+			_ => return None,
+		};
+		Some(Scenario { calls: vec![call] })
+	}
+
+	/// Parse a scenario from its JSON definition.
+	pub fn from_json(json: &str) -> Result<Self, String> {
+		let scenario: Scenario = serde_json::from_str(json)
+			.map_err(|e| format!("Invalid scenario: {}", e))?;
+		if scenario.calls.is_empty() {
+			return Err("Invalid scenario: no calls".into());
+		}
+		Ok(scenario)
+	}
+
+	/// The built-in scenario `scenario`, or else the scenario defined in the file at that path.
+	pub fn load(scenario: &str) -> Result<Self, String> {
+		match Self::preset(scenario) {
+			Some(preset) => Ok(preset),
+			None => fs::read_to_string(scenario)
+				.map_err(|e| format!("Unable to read the scenario {}: {}", scenario, e))
+				.and_then(|json| Self::from_json(&json)),
+		}
+	}
+
+	/// The calls of the scenario.
+	pub fn calls(&self) -> &[ScenarioCall] {
+		&self.calls
+	}
+
+	/// The call of the `n`-th transaction.
+	pub fn call(&self, n: u64) -> &ScenarioCall {
+		&self.calls[(n % self.calls.len() as u64) as usize]
+	}
+
+	/// Whether any call refers to the block number, which then needs to be read from the chain.
+	pub fn uses_block_number(&self) -> bool {
+		self.calls.iter().any(|call| mentions(&call.args, "{block_number}"))
+	}
+}
+
+/// `args` with the placeholders replaced by `variables`.
+pub fn substitute(args: &Value, variables: &Variables) -> Value {
+	match args {
+		Value::String(s) => match s.as_str() {
+			"{sender}" => Value::String(variables.sender.clone()),
+			"{destination}" => Value::String(variables.destination.clone()),
+			"{amount}" => Value::String(variables.amount.clone()),
+			"{block_number}" => Value::from(variables.block_number),
+			_ => args.clone(),
+		},
+		Value::Array(items) => Value::Array(items.iter().map(|item| substitute(item, variables)).collect()),
+		Value::Object(entries) => Value::Object(
+			entries.iter().map(|(k, v)| (k.clone(), substitute(v, variables))).collect()
+		),
+		_ => args.clone(),
+	}
+}
+
+fn mentions(args: &Value, placeholder: &str) -> bool {
+	match args {
+		Value::String(s) => s == placeholder,
+		Value::Array(items) => items.iter().any(|item| mentions(item, placeholder)),
+		Value::Object(entries) => entries.values().any(|v| mentions(v, placeholder)),
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn variables() -> Variables {
+		Variables {
+			sender: "0x01".into(),
+			destination: "0x02".into(),
+			amount: "100".into(),
+			block_number: 7,
+		}
+	}
+
+	#[test]
+	fn placeholders_are_substituted_anywhere_in_the_arguments() {
+		let args = json!({
+			"dest": { "Id": "{destination}" },
+			"calls": [["{sender}", "{amount}"], { "at": "{block_number}" }],
+			"value": 1,
+		});
+
+		assert_eq!(substitute(&args, &variables()), json!({
+			"dest": { "Id": "0x02" },
+			"calls": [["0x01", "100"], { "at": 7 }],
+			"value": 1,
+		}));
+	}
+
+	#[test]
+	fn only_whole_placeholders_are_substituted() {
+		let args = json!(["{amount}0", " {sender}", "{unknown}", "amount"]);
+
+		assert_eq!(substitute(&args, &variables()), args);
+	}
+
+	#[test]
+	fn scenarios_are_parsed_from_json() {
+		let scenario = Scenario::from_json(r#"{
+			"calls": [
+				{ "module": "Balances", "call": "transfer", "args": { "value": "{amount}" } },
+				{ "module": "System", "call": "remark_with_event", "args": ["{block_number}"] },
+				{ "module": "Timestamp", "call": "set" }
+			]
+		}"#).unwrap();
+
+		assert_eq!(scenario.calls().len(), 3);
+		assert_eq!(scenario.call(0).to_string(), "Balances.transfer");
+		assert_eq!(scenario.call(2).args, Value::Null);
+		assert_eq!(scenario.call(4), scenario.call(1));
+		assert!(scenario.uses_block_number());
+		assert!(!Scenario::preset("transfer").unwrap().uses_block_number());
+	}
+
+	#[test]
+	fn scenarios_without_calls_are_invalid() {
+		assert_eq!(Scenario::from_json(r#"{ "calls": [] }"#), Err("Invalid scenario: no calls".into()));
+	}
+
+	#[test]
+	fn malformed_scenarios_are_invalid() {
+		for json in &[
+			"",
+			"{}",
+			r#"{ "calls": {} }"#,
+			r#"{ "calls": [{ "module": "Balances" }] }"#,
+			r#"{ "calls": [{ "module": "Balances", "call": 1 }] }"#,
+		] {
+			let error = Scenario::from_json(json).unwrap_err();
+			assert!(error.starts_with("Invalid scenario: "), "{}", error);
+		}
+	}
+
+	#[test]
+	fn unknown_presets_are_read_from_files() {
+		assert_eq!(Scenario::preset("transfer_all"), None);
+		assert!(Scenario::load("transfer_all").unwrap_err().starts_with("Unable to read the scenario"));
+	}
+}
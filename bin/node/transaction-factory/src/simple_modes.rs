@@ -33,38 +33,21 @@
 ///   A -> B
 ///   ... x `num`
 
-use std::sync::Arc;
-
 use log::info;
-use sc_client::Client;
-use sp_block_builder::BlockBuilder;
-use sp_api::{ConstructRuntimeApi, ProvideRuntimeApi};
-use sp_runtime::traits::{Block as BlockT, One};
-use sp_runtime::generic::BlockId;
+use sp_runtime::traits::{Block as BlockT, One, UniqueSaturatedInto};
 
-use crate::{Mode, RuntimeAdapter, create_block};
+use crate::{Calls, ChainState, Mode, RuntimeAdapter};
 
-pub fn next<RA, Backend, Exec, Block, RtApi>(
+pub fn next<RA: RuntimeAdapter>(
 	factory_state: &mut RA,
-	client: &Arc<Client<Backend, Exec, Block, RtApi>>,
+	chain: &dyn ChainState<RA>,
+	calls: &Calls,
 	version: u32,
 	genesis_hash: <RA::Block as BlockT>::Hash,
 	prior_block_hash: <RA::Block as BlockT>::Hash,
-	prior_block_id: BlockId<Block>,
-) -> Option<Block>
-where
-	Block: BlockT,
-	Exec: sc_client::CallExecutor<Block, Backend = Backend> + Send + Sync + Clone,
-	Backend: sc_client_api::backend::Backend<Block> + Send,
-	Client<Backend, Exec, Block, RtApi>: ProvideRuntimeApi<Block>,
-	<Client<Backend, Exec, Block, RtApi> as ProvideRuntimeApi<Block>>::Api:
-		BlockBuilder<Block, Error = sp_blockchain::Error> +
-		sp_api::ApiExt<Block, StateBackend = Backend::State>,
-	RtApi: ConstructRuntimeApi<Block, Client<Backend, Exec, Block, RtApi>> + Send + Sync,
-	RA: RuntimeAdapter,
-{
+) -> Result<Option<RA::Payload>, String> {
 	if factory_state.block_no() >= factory_state.num() {
-		return None;
+		return Ok(None);
 	}
 
 	let from = (RA::master_account_id(), RA::master_account_secret());
@@ -77,36 +60,40 @@ where
 		Mode::MasterToN => factory_state.start_number() + factory_state.block_no(),
 		_ => unreachable!("Mode not covered!"),
 	};
-	let to = RA::gen_random_account_id(&seed);
+	let to = factory_state.gen_random_account_id(&seed);
 
-	let amount = RA::minimum_balance();
+	let amount = calls.minimum_balance::<RA::Balance>()?;
 
-	let transfer = factory_state.transfer_extrinsic(
+	let n: u64 = factory_state.block_no().unique_saturated_into();
+	let call = crate::encode_call(
+		calls,
+		chain,
+		n,
 		&from.0,
-		&from.1,
 		&to,
 		&amount,
+		&prior_block_hash,
+	)?;
+	let payload = factory_state.signing_payload(
+		chain,
+		&from.0,
+		&from.1,
+		call,
 		version,
 		&genesis_hash,
 		&prior_block_hash,
-	);
-
-	let inherents = RA::inherent_extrinsics(&factory_state);
-	let inherents = client.runtime_api().inherent_extrinsics(&prior_block_id, inherents)
-		.expect("Failed to create inherent extrinsics");
-
-	let block = create_block::<RA, _, _, _, _>(&client, transfer, inherents);
+	)?;
 
 	factory_state.set_block_no(factory_state.block_no() + RA::Number::one());
 
 	info!(
-		"Created block {} with hash {}. Transferring {} from {} to {}.",
+		"Created transaction {}. {} of {} from {} to {}.",
 		factory_state.block_no(),
-		prior_block_hash,
+		calls.scenario().call(n),
 		amount,
 		from.0,
 		to
 	);
 
-	Some(block)
+	Ok(Some(payload))
 }
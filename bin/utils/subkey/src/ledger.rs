@@ -0,0 +1,262 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Keys held on a Ledger device.
+//!
+//! Speaks the APDU protocol of the Substrate Ledger apps over USB HID. Keys are derived on the
+//! device along the BIP44 path `m/44'/<slip44>'/<account>'/<change>'/<index>'`. Transactions
+//! are sent to the device unhashed, so that the app can display them for review before
+//! signing.
+
+use std::{fmt, time::Duration};
+use super::Error;
+
+/// Class byte of the chain's Ledger app.
+const CLA: u8 = 0x99;
+/// SLIP-0044 coin type of the chain, used as second level of the derivation path.
+const SLIP44: u32 = 434;
+
+const INS_GET_VERSION: u8 = 0x00;
+const INS_GET_ADDRESS: u8 = 0x01;
+const INS_SIGN: u8 = 0x02;
+
+/// `P1` of the first chunk of a payload to sign, which holds the derivation path.
+const SIGN_INIT: u8 = 0x00;
+/// `P1` of the following chunks but the last one.
+const SIGN_ADD: u8 = 0x01;
+/// `P1` of the last chunk.
+const SIGN_LAST: u8 = 0x02;
+/// Maximum size of the data of an APDU.
+const CHUNK_SIZE: usize = 250;
+
+const LEDGER_VENDOR_ID: u16 = 0x2c97;
+const HID_PACKET_SIZE: usize = 64;
+const HID_CHANNEL: u16 = 0x0101;
+const HID_TAG_APDU: u8 = 0x05;
+const HID_TIMEOUT: Duration = Duration::from_secs(120);
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// Signature scheme of a key on the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+	Ed25519 = 0,
+	Sr25519 = 1,
+}
+
+/// Position of a key in the device's derivation tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Path {
+	pub account: u32,
+	pub change: u32,
+	pub index: u32,
+}
+
+impl Path {
+	fn encode(&self) -> Vec<u8> {
+		[44, SLIP44, self.account, self.change, self.index].iter()
+			.flat_map(|level| (level | HARDENED).to_le_bytes().to_vec())
+			.collect()
+	}
+}
+
+impl fmt::Display for Path {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "m/44'/{}'/{}'/{}'/{}'", SLIP44, self.account, self.change, self.index)
+	}
+}
+
+/// A connected Ledger device running the chain's app.
+pub struct Ledger {
+	device: hidapi::HidDevice,
+}
+
+impl Ledger {
+	/// Connect to the first Ledger device found.
+	pub fn connect() -> Result<Self, Error> {
+		let api = hidapi::HidApi::new()
+			.map_err(|e| Error::Formatted(format!("Cannot access USB devices: {}", e)))?;
+		let info = api.device_list()
+			.find(|info| info.vendor_id() == LEDGER_VENDOR_ID)
+			.ok_or(Error::Static("No Ledger device found. Is it connected and unlocked?"))?;
+		let device = info.open_device(&api)
+			.map_err(|e| Error::Formatted(format!("Cannot open the Ledger device: {}", e)))?;
+
+		let ledger = Ledger { device };
+		let (major, minor, patch) = ledger.version()?;
+		eprintln!("Using Ledger app version {}.{}.{}", major, minor, patch);
+		Ok(ledger)
+	}
+
+	/// Version of the app running on the device.
+	pub fn version(&self) -> Result<(u8, u8, u8), Error> {
+		let response = self.exchange(INS_GET_VERSION, 0, 0, &[])?;
+		match response.as_slice() {
+			[_test_mode, major, minor, patch, ..] => Ok((*major, *minor, *patch)),
+			_ => Err(Error::Static("Invalid version response from the Ledger app")),
+		}
+	}
+
+	/// Public key at `path`, optionally displaying the address on the device for the user to
+	/// confirm it.
+	pub fn public_key(&self, path: Path, scheme: Scheme, confirm: bool) -> Result<Vec<u8>, Error> {
+		let response = self.exchange(INS_GET_ADDRESS, confirm as u8, scheme as u8, &path.encode())?;
+		if response.len() < 32 {
+			return Err(Error::Static("Invalid address response from the Ledger app"));
+		}
+		Ok(response[..32].to_vec())
+	}
+
+	/// Sign `payload` with the key at `path`, after the user reviewed it on the device.
+	pub fn sign(&self, path: Path, scheme: Scheme, payload: &[u8]) -> Result<Vec<u8>, Error> {
+		let mut response = self.exchange(INS_SIGN, SIGN_INIT, scheme as u8, &path.encode())?;
+
+		let chunks = payload.chunks(CHUNK_SIZE).collect::<Vec<_>>();
+		for (i, chunk) in chunks.iter().enumerate() {
+			let p1 = if i + 1 == chunks.len() { SIGN_LAST } else { SIGN_ADD };
+			response = self.exchange(INS_SIGN, p1, scheme as u8, chunk)?;
+		}
+
+		// Recent versions of the app prefix the signature with its scheme.
+		match response.len() {
+			64 => Ok(response),
+			65 => Ok(response[1..].to_vec()),
+			_ => Err(Error::Static("Invalid signature response from the Ledger app")),
+		}
+	}
+
+	/// Send an APDU and return the data of the response, if successful.
+	fn exchange(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Result<Vec<u8>, Error> {
+		let mut apdu = vec![CLA, ins, p1, p2, data.len() as u8];
+		apdu.extend_from_slice(data);
+
+		for packet in hid_packets(&apdu) {
+			// The first byte is the HID report number.
+			let mut report = vec![0u8];
+			report.extend_from_slice(&packet);
+			self.device.write(&report)
+				.map_err(|e| Error::Formatted(format!("Cannot write to the Ledger device: {}", e)))?;
+		}
+
+		let mut reader = HidReader::default();
+		let mut response = loop {
+			let mut packet = [0u8; HID_PACKET_SIZE];
+			let read = self.device.read_timeout(&mut packet, HID_TIMEOUT.as_millis() as i32)
+				.map_err(|e| Error::Formatted(format!("Cannot read from the Ledger device: {}", e)))?;
+			if read == 0 {
+				return Err(Error::Static("Timed out waiting for the Ledger device"));
+			}
+			if let Some(response) = reader.push(&packet[..read])? {
+				break response;
+			}
+		};
+
+		if response.len() < 2 {
+			return Err(Error::Static("Invalid response from the Ledger device"));
+		}
+		let status = response.split_off(response.len() - 2);
+		match u16::from_be_bytes([status[0], status[1]]) {
+			0x9000 => Ok(response),
+			0x6986 => Err(Error::Static("Rejected on the Ledger device")),
+			0x6e00 | 0x6e01 => Err(Error::Static("The chain's app isn't open on the Ledger device")),
+			status => Err(Error::Formatted(format!("The Ledger device returned error 0x{:04x}", status))),
+		}
+	}
+}
+
+/// Split an APDU into HID packets.
+fn hid_packets(apdu: &[u8]) -> Vec<[u8; HID_PACKET_SIZE]> {
+	let mut data = (apdu.len() as u16).to_be_bytes().to_vec();
+	data.extend_from_slice(apdu);
+
+	data.chunks(HID_PACKET_SIZE - 5).enumerate().map(|(seq, chunk)| {
+		let mut packet = [0u8; HID_PACKET_SIZE];
+		packet[..2].copy_from_slice(&HID_CHANNEL.to_be_bytes());
+		packet[2] = HID_TAG_APDU;
+		packet[3..5].copy_from_slice(&(seq as u16).to_be_bytes());
+		packet[5..5 + chunk.len()].copy_from_slice(chunk);
+		packet
+	}).collect()
+}
+
+/// Reassembles a response from HID packets.
+#[derive(Default)]
+struct HidReader {
+	seq: u16,
+	expected: usize,
+	data: Vec<u8>,
+}
+
+impl HidReader {
+	/// Add a packet, returning the response once complete.
+	fn push(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+		if packet.len() < 5
+			|| packet[..2] != HID_CHANNEL.to_be_bytes()
+			|| packet[2] != HID_TAG_APDU
+			|| packet[3..5] != self.seq.to_be_bytes()
+		{
+			return Err(Error::Static("Unexpected packet from the Ledger device"));
+		}
+
+		let mut payload = &packet[5..];
+		if self.seq == 0 {
+			if payload.len() < 2 {
+				return Err(Error::Static("Unexpected packet from the Ledger device"));
+			}
+			self.expected = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+			payload = &payload[2..];
+		}
+		self.seq += 1;
+
+		let missing = self.expected - self.data.len();
+		self.data.extend_from_slice(&payload[..std::cmp::min(missing, payload.len())]);
+		if self.data.len() == self.expected {
+			Ok(Some(std::mem::replace(&mut self.data, Vec::new())))
+		} else {
+			Ok(None)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn hid_packets_are_reassembled() {
+		let apdu = (0..200u8).collect::<Vec<_>>();
+		let packets = hid_packets(&apdu);
+		assert_eq!(packets.len(), 4);
+		assert_eq!(&packets[0][..7], &[0x01, 0x01, 0x05, 0x00, 0x00, 0x00, 200]);
+		assert_eq!(&packets[3][..5], &[0x01, 0x01, 0x05, 0x00, 0x03]);
+
+		let mut reader = HidReader::default();
+		let (last, first) = packets.split_last().unwrap();
+		for packet in first {
+			assert_eq!(reader.push(packet).unwrap(), None);
+		}
+		assert_eq!(reader.push(last).unwrap(), Some(apdu));
+	}
+
+	#[test]
+	fn path_is_hardened() {
+		let path = Path { account: 1, change: 0, index: 2 }.encode();
+		assert_eq!(path.len(), 20);
+		assert_eq!(&path[..4], &(44 | HARDENED).to_le_bytes());
+		assert_eq!(&path[8..12], &(1 | HARDENED).to_le_bytes());
+		assert_eq!(&path[16..], &(2 | HARDENED).to_le_bytes());
+	}
+}
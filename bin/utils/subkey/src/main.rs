@@ -26,7 +26,7 @@ use itertools::Itertools;
 use node_primitives::{Balance, Hash, Index, AccountId, Signature};
 use node_runtime::{BalancesCall, Call, Runtime, SignedPayload, UncheckedExtrinsic, VERSION};
 use sp_core::{
-	crypto::{set_default_ss58_version, Ss58AddressFormat, Ss58Codec},
+	crypto::{set_default_ss58_version, AccountId20, Ss58AddressFormat, Ss58Codec},
 	ed25519, sr25519, ecdsa, Pair, Public, H256, hexdisplay::HexDisplay,
 };
 use sp_runtime::{traits::{IdentifyAccount, Verify}, generic::Era};
@@ -34,12 +34,15 @@ use std::{
 	convert::{TryInto, TryFrom}, io::{stdin, Read}, str::FromStr, path::PathBuf, fs, fmt,
 };
 
+mod ledger;
 mod rpc;
 mod vanity;
 
 trait Crypto: Sized {
 	type Pair: Pair<Public = Self::Public>;
 	type Public: Public + Ss58Codec + AsRef<[u8]> + std::hash::Hash;
+	/// The scheme of the key on a Ledger device, for keys which can be held on one.
+	const LEDGER_SCHEME: Option<ledger::Scheme> = None;
 	fn pair_from_suri(suri: &str, password: Option<&str>) -> Self::Pair {
 		Self::Pair::from_string(suri, password).expect("Invalid phrase")
 	}
@@ -51,6 +54,15 @@ trait Crypto: Sized {
 	fn public_from_pair(pair: &Self::Pair) -> Self::Public {
 		pair.public()
 	}
+	/// The Ethereum address of the key, for keys which have one.
+	fn eth_address(_public: &Self::Public) -> Option<AccountId20> {
+		None
+	}
+	fn print_eth_address(public: &Self::Public) {
+		if let Some(address) = Self::eth_address(public) {
+			println!("  Ethereum address: {}", address);
+		}
+	}
 	fn print_from_uri(
 		uri: &str,
 		password: Option<&str>,
@@ -68,9 +80,10 @@ trait Crypto: Sized {
 				uri,
 				format_seed::<Self>(seed),
 				format_public_key::<Self>(public_key.clone()),
-				format_account_id::<Self>(public_key),
+				format_account_id::<Self>(public_key.clone()),
 				Self::ss58_from_pair(&pair)
 			);
+			Self::print_eth_address(&public_key);
 		} else if let Ok((pair, seed)) = Self::Pair::from_string_with_seed(uri, password) {
 			let public_key = Self::public_from_pair(&pair);
 			println!("Secret Key URI `{}` is account:\n  \
@@ -81,9 +94,10 @@ trait Crypto: Sized {
 				uri,
 				if let Some(seed) = seed { format_seed::<Self>(seed) } else { "n/a".into() },
 				format_public_key::<Self>(public_key.clone()),
-				format_account_id::<Self>(public_key),
+				format_account_id::<Self>(public_key.clone()),
 				Self::ss58_from_pair(&pair)
 			);
+			Self::print_eth_address(&public_key);
 		} else if let Ok((public_key, v)) =
 			<Self::Pair as Pair>::Public::from_string_with_version(uri)
 		{
@@ -99,6 +113,7 @@ trait Crypto: Sized {
 				format_account_id::<Self>(public_key.clone()),
 				public_key.to_ss58check_with_version(v)
 			);
+			Self::print_eth_address(&public_key);
 		} else {
 			println!("Invalid phrase/URI given");
 		}
@@ -110,6 +125,7 @@ struct Ed25519;
 impl Crypto for Ed25519 {
 	type Pair = ed25519::Pair;
 	type Public = ed25519::Public;
+	const LEDGER_SCHEME: Option<ledger::Scheme> = Some(ledger::Scheme::Ed25519);
 
 	fn pair_from_suri(suri: &str, password_override: Option<&str>) -> Self::Pair {
 		ed25519::Pair::from_legacy_string(suri, password_override)
@@ -121,6 +137,7 @@ struct Sr25519;
 impl Crypto for Sr25519 {
 	type Pair = sr25519::Pair;
 	type Public = sr25519::Public;
+	const LEDGER_SCHEME: Option<ledger::Scheme> = Some(ledger::Scheme::Sr25519);
 }
 
 struct Ecdsa;
@@ -128,6 +145,10 @@ struct Ecdsa;
 impl Crypto for Ecdsa {
 	type Pair = ecdsa::Pair;
 	type Public = ecdsa::Public;
+
+	fn eth_address(public: &Self::Public) -> Option<AccountId20> {
+		public.to_eth_address().ok().map(Into::into)
+	}
 }
 
 type SignatureOf<C> = <<C as Crypto>::Pair as Pair>::Signature;
@@ -163,8 +184,12 @@ fn get_usage() -> String {
 		-k, --secp256k1 'Use SECP256k1/ECDSA/BIP39 cryptography'
 		-s, --sr25519 'Use Schnorr/Ristretto x25519/BIP39 cryptography'
 		[network] -n, --network <network> 'Specify a network. One of {}. Default is {}'
+		[chain] --chain <chain> 'Use the network given by the `ss58Format` property of a chain spec file'
 		[password] -p, --password <password> 'The password for the key'
 		--password-interactive 'You will be prompted for the password for the key.'
+		[signer] --signer <signer> 'Where the key is held. One of suri (default) and ledger'
+		[ledger-account] --ledger-account <ledger-account> 'The account of the key on the Ledger device. Default is 0'
+		[ledger-index] --ledger-index <ledger-index> 'The address index of the key on the Ledger device. Default is 0'
 	", networks, default_network)
 }
 
@@ -187,6 +212,7 @@ fn get_app<'a, 'b>(usage: &'a str) -> App<'a, 'b> {
 						secret URI (with derivation paths and password), SS58 or public URI. \
 						If the value is a file, the file content is used as URI. \
 						If not given, you will be prompted for the URI.'
+					--confirm 'Display the address on the Ledger device for confirmation'
 				"),
 			SubCommand::with_name("sign")
 				.about("Sign a message, provided on STDIN, with a given (secret) key")
@@ -202,9 +228,9 @@ fn get_app<'a, 'b>(usage: &'a str) -> App<'a, 'b> {
 				.args_from_usage("
 					-c, --call <call> 'The call, hex-encoded.'
 					-n, --nonce <nonce> 'The nonce.'
-					-p, --password <password> 'The password for the key.'
+					[password] -p, --password <password> 'The password for the key.'
 					-h, --prior-block-hash <prior-block-hash> 'The prior block hash, hex-encoded.'
-					-s, --suri <suri> 'The secret key URI.'
+					[suri] -s, --suri <suri> 'The secret key URI. Not needed with `--signer ledger`.'
 				"),
 			SubCommand::with_name("transfer")
 				.about("Author and sign a Node pallet_balances::Transfer transaction with a given (secret) key")
@@ -280,6 +306,23 @@ fn get_uri(match_name: &str, matches: &ArgMatches) -> Result<String, Error> {
 	Ok(uri)
 }
 
+/// The network given by the `ss58Format` property of the chain spec file at `path`, if any.
+fn chain_spec_network(path: &str) -> Result<Option<Ss58AddressFormat>, Error> {
+	let spec: serde_json::Value = serde_json::from_slice(&fs::read(path)?)
+		.map_err(|e| Error::Formatted(format!("Invalid chain spec: {}", e)))?;
+	ss58_format(&spec)
+}
+
+/// The network given by the `ss58Format` property of a chain spec, if any.
+fn ss58_format(spec: &serde_json::Value) -> Result<Option<Ss58AddressFormat>, Error> {
+	match &spec["properties"]["ss58Format"] {
+		serde_json::Value::Null => Ok(None),
+		format => serde_json::from_value(format.clone()).map(Some).map_err(|e| {
+			Error::Formatted(format!("Invalid `ss58Format` property {}: {}", format, e))
+		}),
+	}
+}
+
 #[derive(derive_more::Display, derive_more::From)]
 enum Error {
 	Static(&'static str),
@@ -325,18 +368,35 @@ where
 		Some(Ok(v)) => Some(v),
 		None => None,
 	 };
+	let maybe_network = match (maybe_network, matches.value_of("chain")) {
+		(Some(network), _) => Some(network),
+		(None, Some(path)) => chain_spec_network(path)?,
+		(None, None) => None,
+	};
 
 	if let Some(network) = maybe_network {
 		set_default_ss58_version(network);
 	}
+
+	let ledger_path = match matches.value_of("signer").unwrap_or("suri") {
+		"suri" => None,
+		"ledger" => Some(ledger::Path {
+			account: read_optional_parameter(&matches, "ledger-account")?.unwrap_or(0),
+			change: 0,
+			index: read_optional_parameter(&matches, "ledger-index")?.unwrap_or(0),
+		}),
+		_ => return static_err("Invalid signer. One of `suri` and `ledger`."),
+	};
+
 	match matches.subcommand() {
 		("generate", Some(matches)) => {
 			let mnemonic = generate_mnemonic(matches)?;
 			C::print_from_uri(mnemonic.phrase(), password, maybe_network);
 		}
-		("inspect", Some(matches)) => {
-			C::print_from_uri(&get_uri("uri", &matches)?, password, maybe_network);
-		}
+		("inspect", Some(matches)) => match ledger_path {
+			Some(path) => print_from_ledger::<C>(path, matches.is_present("confirm"))?,
+			None => C::print_from_uri(&get_uri("uri", &matches)?, password, maybe_network),
+		},
 		("sign", Some(matches)) => {
 			let suri = get_uri("suri", &matches)?;
 			let should_decode = matches.is_present("hex");
@@ -375,12 +435,17 @@ where
 			let amount = read_required_parameter::<Balance>(matches, "amount")?;
 			let function = Call::Balances(BalancesCall::transfer(to.into(), amount));
 
-			let extrinsic = create_extrinsic::<C>(function, index, signer, genesis_hash);
+			let extrinsic = create_extrinsic::<C>(
+				function,
+				index,
+				signer.public(),
+				genesis_hash,
+				|payload| Ok(payload.using_encoded(|payload| signer.sign(payload))),
+			)?;
 
 			print_extrinsic(extrinsic);
 		}
 		("sign-transaction", Some(matches)) => {
-			let signer = read_pair::<C>(matches.value_of("suri"), password)?;
 			let index = read_required_parameter::<Index>(matches, "nonce")?;
 			let genesis_hash = read_genesis_hash(matches)?;
 
@@ -390,7 +455,30 @@ where
 				.and_then(|x| Decode::decode(&mut &x[..]).ok())
 				.unwrap();
 
-			let extrinsic = create_extrinsic::<C>(function, index, signer, genesis_hash);
+			let extrinsic = match ledger_path {
+				Some(path) => {
+					let scheme = ledger_scheme::<C>()?;
+					let device = ledger::Ledger::connect()?;
+					let public = PublicOf::<C>::from_slice(&device.public_key(path, scheme, false)?);
+					create_extrinsic::<C>(function, index, public, genesis_hash, |payload| {
+						eprintln!("Review the transaction on the Ledger device.");
+						// The device displays the payload before signing it, so it is sent unhashed.
+						signature_from_slice::<C>(&device.sign(path, scheme, &payload.encode())?)
+					})?
+				},
+				None => {
+					let suri = matches.value_of("suri")
+						.ok_or(Error::Static("`--suri` is required unless signing with `--signer ledger`"))?;
+					let signer = read_pair::<C>(Some(suri), password)?;
+					create_extrinsic::<C>(
+						function,
+						index,
+						signer.public(),
+						genesis_hash,
+						|payload| Ok(payload.using_encoded(|payload| signer.sign(payload))),
+					)?
+				},
+			};
 
 			print_extrinsic(extrinsic);
 		}
@@ -478,6 +566,14 @@ fn read_required_parameter<T: FromStr>(matches: &ArgMatches, name: &str) -> Resu
 	)
 }
 
+fn read_optional_parameter<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, Error> {
+	matches.value_of(name)
+		.map(|value| str::parse::<T>(value).map_err(|_|
+			Error::Formatted(format!("Invalid `{}' parameter; expecting an integer.", name))
+		))
+		.transpose()
+}
+
 fn read_genesis_hash(matches: &ArgMatches) -> Result<H256, Error> {
 	let genesis_hash: Hash = match matches.value_of("genesis").unwrap_or("alex") {
 		"elm" => hex!["10c08714a10c7da78f40a60f6f732cf0dba97acfb5e2035445b032386157d5c3"].into(),
@@ -500,8 +596,14 @@ where
 	let sig_data = matches
 		.value_of("sig")
 		.expect("signature parameter is required; thus it can't be None; qed");
+	signature_from_slice::<C>(&decode_hex(sig_data)?)
+}
+
+fn signature_from_slice<C: Crypto>(sig_data: &[u8]) -> Result<SignatureOf<C>, Error>
+where
+	SignatureOf<C>: SignatureT,
+{
 	let mut signature = <<C as Crypto>::Pair as Pair>::Signature::default();
-	let sig_data = decode_hex(sig_data)?;
 	if sig_data.len() != signature.as_ref().len() {
 		return Err(Error::Formatted(format!(
 			"signature has an invalid length. read {} bytes, expected {} bytes",
@@ -509,10 +611,36 @@ where
 			signature.as_ref().len(),
 		)));
 	}
-	signature.as_mut().copy_from_slice(&sig_data);
+	signature.as_mut().copy_from_slice(sig_data);
 	Ok(signature)
 }
 
+fn ledger_scheme<C: Crypto>() -> Result<ledger::Scheme, Error> {
+	C::LEDGER_SCHEME.ok_or(Error::Static("This cryptography isn't supported on Ledger devices."))
+}
+
+/// Print the public key and address of the key at `path` on a Ledger device.
+fn print_from_ledger<C: Crypto>(path: ledger::Path, confirm: bool) -> Result<(), Error> where
+	PublicOf<C>: PublicT,
+{
+	let scheme = ledger_scheme::<C>()?;
+	let device = ledger::Ledger::connect()?;
+	if confirm {
+		eprintln!("Confirm the address on the Ledger device.");
+	}
+	let public_key = PublicOf::<C>::from_slice(&device.public_key(path, scheme, confirm)?);
+	println!("Ledger key `{}` is account:\n  \
+		Public key (hex): {}\n  \
+		Account ID:       {}\n  \
+		SS58 Address:     {}",
+		path,
+		format_public_key::<C>(public_key.clone()),
+		format_account_id::<C>(public_key.clone()),
+		public_key.into_runtime().into_account().to_ss58check(),
+	);
+	Ok(())
+}
+
 fn read_public_key<C: Crypto>(matched_uri: Option<&str>) -> PublicOf<C>
 where
 	PublicOf<C>: PublicT,
@@ -577,12 +705,14 @@ fn format_account_id<C: Crypto>(public_key: PublicOf<C>) -> String where
 	format!("0x{}", HexDisplay::from(&public_key.into_runtime().into_account().as_ref()))
 }
 
+/// Create a signed extrinsic, using `sign` to sign its payload.
 fn create_extrinsic<C: Crypto>(
 	function: Call,
 	index: Index,
-	signer: C::Pair,
+	signer: PublicOf<C>,
 	genesis_hash: H256,
-) -> UncheckedExtrinsic where
+	sign: impl FnOnce(&SignedPayload) -> Result<SignatureOf<C>, Error>,
+) -> Result<UncheckedExtrinsic, Error> where
 	PublicOf<C>: PublicT,
 	SignatureOf<C>: SignatureT,
 {
@@ -593,7 +723,7 @@ fn create_extrinsic<C: Crypto>(
 			frame_system::CheckEra::<Runtime>::from(Era::Immortal),
 			frame_system::CheckNonce::<Runtime>::from(i),
 			frame_system::CheckWeight::<Runtime>::new(),
-			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(f),
+			pallet_asset_tx_payment::ChargeAssetTxPayment::<Runtime>::from(f, None),
 			Default::default(),
 		)
 	};
@@ -610,16 +740,16 @@ fn create_extrinsic<C: Crypto>(
 			(),
 		),
 	);
-	let signature = raw_payload.using_encoded(|payload| signer.sign(payload)).into_runtime();
-	let signer = signer.public().into_runtime();
+	let signature = sign(&raw_payload)?.into_runtime();
+	let signer = signer.into_runtime();
 	let (function, extra, _) = raw_payload.deconstruct();
 
-	UncheckedExtrinsic::new_signed(
+	Ok(UncheckedExtrinsic::new_signed(
 		function,
 		signer.into_account().into(),
 		signature,
 		extra,
-	)
+	))
 }
 
 fn print_extrinsic(extrinsic: UncheckedExtrinsic) {
@@ -679,6 +809,18 @@ mod tests {
 		test_generate_sign_verify::<Sr25519>();
 	}
 
+	#[test]
+	fn ss58_format_is_read_from_the_chain_spec() {
+		let format = |spec: serde_json::Value| ss58_format(&spec).ok().map(|format| format.map(u8::from));
+
+		assert_eq!(format(serde_json::json!({ "name": "Development" })), Some(None));
+		assert_eq!(format(serde_json::json!({ "properties": {} })), Some(None));
+		assert_eq!(format(serde_json::json!({ "properties": { "ss58Format": 2 } })), Some(Some(2)));
+		assert_eq!(format(serde_json::json!({ "properties": { "ss58Format": 200 } })), Some(Some(200)));
+		assert_eq!(format(serde_json::json!({ "properties": { "ss58Format": 256 } })), None);
+		assert_eq!(format(serde_json::json!({ "properties": { "ss58Format": "kusama" } })), None);
+	}
+
 	#[test]
 	fn should_work() {
 		let s = "0123456789012345678901234567890123456789012345678901234567890123";
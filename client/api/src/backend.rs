@@ -51,6 +51,9 @@ pub type StorageCollection = Vec<(Vec<u8>, Option<Vec<u8>>)>;
 /// In memory arrays of storage values for multiple child tries.
 pub type ChildStorageCollection = Vec<(Vec<u8>, StorageCollection)>;
 
+/// In memory array of offchain storage values, indexed by the runtime.
+pub type OffchainStorageCollection = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
 /// Import operation summary.
 ///
 /// Contains information about the block that just got imported,
@@ -149,6 +152,12 @@ pub trait BlockImportOperation<Block: BlockT> {
 		child_update: ChildStorageCollection,
 	) -> sp_blockchain::Result<()>;
 
+	/// Write the changes to the persistent offchain storage indexed by the runtime.
+	fn update_offchain_storage(
+		&mut self,
+		offchain_update: OffchainStorageCollection,
+	) -> sp_blockchain::Result<()>;
+
 	/// Inject changes trie data into the database.
 	fn update_changes_trie(
 		&mut self,
@@ -297,6 +306,16 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 	/// Returns state backend with post-state of given block.
 	fn state_at(&self, block: BlockId<Block>) -> sp_blockchain::Result<Self::State>;
 
+	/// Prevents the state of the given block from being pruned.
+	///
+	/// The state is kept until `unpin_block` was called as many times as the block was pinned.
+	fn pin_block(&self, _hash: &Block::Hash) -> sp_blockchain::Result<()> {
+		Ok(())
+	}
+
+	/// Releases a pin taken with `pin_block`.
+	fn unpin_block(&self, _hash: &Block::Hash) {}
+
 	/// Destroy state and save any useful data, such as cache.
 	fn destroy_state(&self, _state: Self::State) -> sp_blockchain::Result<()> {
 		Ok(())
@@ -47,6 +47,14 @@ pub type ForkBlocks<Block> = Option<Vec<(NumberFor<Block>, <Block as BlockT>::Ha
 /// This may be used as chain spec extension to filter out known, unwanted forks.
 pub type BadBlocks<Block> = Option<HashSet<<Block as BlockT>::Hash>>;
 
+/// Expected hash of a block at a given height, on light clients only.
+///
+/// This may be used as chain spec extension to pin light clients, which trust the
+/// headers they sync instead of executing the blocks, to a recent block of the chain.
+/// Like with the fork blocks, the light client will refuse to import a header with a
+/// different hash at the given height.
+pub type LightCheckpoint<Block> = Option<(NumberFor<Block>, <Block as BlockT>::Hash)>;
+
 /// Figure out the block type for a given type (for now, just a `Client`).
 pub trait BlockOf {
 	/// The type of the block.
@@ -24,15 +24,15 @@ use sc_client::Client as SubstrateClient;
 use codec::Decode;
 use sp_consensus::{evaluation, Proposal, RecordProof};
 use sp_inherents::InherentData;
-use log::{error, info, debug, trace};
+use log::{error, info, debug, trace, warn};
 use sp_core::ExecutionContext;
 use sp_runtime::{
-	traits::{Block as BlockT, Hash as HashT, Header as HeaderT, DigestFor, BlakeTwo256},
+	traits::{Block as BlockT, Hash as HashT, Header as HeaderT, DigestFor, DigestItemFor, BlakeTwo256},
 	generic::BlockId,
 };
 use sp_transaction_pool::{TransactionPool, InPoolTransaction};
-use sc_telemetry::{telemetry, CONSENSUS_INFO};
-use sc_block_builder::BlockBuilderApi;
+use sc_telemetry::{telemetry, CONSENSUS_INFO, CONSENSUS_WARN};
+use sc_block_builder::{BlockBuilderApi, BlockWeight};
 use sp_api::{ProvideRuntimeApi, ApiExt};
 use futures::prelude::*;
 
@@ -42,6 +42,72 @@ pub struct ProposerFactory<C, A> where A: TransactionPool {
 	pub client: Arc<C>,
 	/// The transaction pool.
 	pub transaction_pool: Arc<A>,
+	/// Configuration of the slow extrinsic log, disabled if `None`. The declared weights it
+	/// provides also let the proposer skip the transactions not fitting in the block any more.
+	pub weight_tracing: Option<WeightTracing<A::Block>>,
+}
+
+/// Returns the dispatch information the runtime at the given block declares for an extrinsic,
+/// `None` if unknown.
+pub type ExtrinsicWeight<Block> = Arc<
+	dyn Fn(&BlockId<Block>, &<Block as BlockT>::Extrinsic) -> Option<ExtrinsicDispatch> + Send + Sync
+>;
+
+/// The dispatch information declared by an extrinsic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtrinsicDispatch {
+	/// The weight of the extrinsic.
+	pub weight: u64,
+	/// Whether the extrinsic is operational, in which case it may use the whole block weight
+	/// rather than the part left to normal extrinsics.
+	pub operational: bool,
+}
+
+/// Configuration of the slow extrinsic log.
+///
+/// Every extrinsic pushed from the pool is timed. An extrinsic whose execution takes more than
+/// `factor` times what its declared weight allows is reported, as its weight is probably too low.
+pub struct WeightTracing<Block: BlockT> {
+	/// Returns the dispatch information of an extrinsic.
+	pub weight_of: ExtrinsicWeight<Block>,
+	/// Expected execution time of one unit of weight, in nanoseconds.
+	pub nanos_per_weight: u64,
+	/// How many times slower than its weight allows an extrinsic may be before being reported.
+	pub factor: u64,
+}
+
+impl<Block: BlockT> Clone for WeightTracing<Block> {
+	fn clone(&self) -> Self {
+		WeightTracing {
+			weight_of: self.weight_of.clone(),
+			nanos_per_weight: self.nanos_per_weight,
+			factor: self.factor,
+		}
+	}
+}
+
+impl<Block: BlockT> WeightTracing<Block> {
+	/// Returns true if executing an extrinsic of the given weight took too long.
+	fn is_slow(&self, weight: u64, elapsed: time::Duration) -> bool {
+		let allowed = weight.saturating_mul(self.nanos_per_weight).saturating_mul(self.factor);
+		elapsed.as_nanos() > allowed as u128
+	}
+}
+
+/// A request to build a block, made by a driver other than the slot workers of the node, e.g. a
+/// collator or a custom sealing loop.
+pub struct BuildBlockRequest<Block: BlockT> {
+	/// The hash of the block to build on.
+	pub parent: <Block as BlockT>::Hash,
+	/// The inherent data to create the inherent extrinsics from.
+	pub inherent_data: InherentData,
+	/// The digests to put in the header before execution, e.g. a consensus pre-digest.
+	pub inherent_digests: DigestFor<Block>,
+	/// The instant after which no more transactions are pushed. Unlike the `max_duration` given
+	/// to `Proposer::propose`, no time is set aside for finalizing the block.
+	pub deadline: time::Instant,
+	/// Whether to record a proof of the storage accessed while building the block.
+	pub record_proof: RecordProof,
 }
 
 impl<B, E, Block, RA, A> ProposerFactory<SubstrateClient<B, E, Block, RA>, A>
@@ -74,12 +140,48 @@ impl<B, E, Block, RA, A> ProposerFactory<SubstrateClient<B, E, Block, RA>, A>
 				parent_id: id,
 				parent_number: *parent_header.number(),
 				transaction_pool: self.transaction_pool.clone(),
+				weight_tracing: self.weight_tracing.clone(),
 				now,
 			}),
 		};
 
 		proposer
 	}
+
+	/// Build and seal a block as requested by an external driver.
+	///
+	/// The block is built on `request.parent` with the inherents and transactions of the pool,
+	/// then `seal` is given its header and may return a digest item, usually a seal, to push to
+	/// it. The returned proposal holds the sealed block, the storage proof if one was requested,
+	/// and the storage changes needed to import it.
+	///
+	/// This blocks until the block is built, so it should not be called from an async context.
+	pub fn build_block<S>(
+		&mut self,
+		request: BuildBlockRequest<Block>,
+		seal: S,
+	) -> Result<Proposal<Block, backend::TransactionFor<B, Block>>, sp_blockchain::Error> where
+		S: FnOnce(&<Block as BlockT>::Header) -> Result<Option<DigestItemFor<Block>>, sp_blockchain::Error>,
+	{
+		let parent_header = self.client.header(&BlockId::hash(request.parent))?
+			.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("{}", request.parent)))?;
+		let proposer = self.init_with_now(&parent_header, Box::new(time::Instant::now));
+
+		let mut proposal = proposer.inner.propose_with(
+			request.inherent_data,
+			request.inherent_digests,
+			request.deadline,
+			request.record_proof,
+		)?;
+
+		if let Some(item) = seal(proposal.block.header())? {
+			let (mut header, extrinsics) = proposal.block.deconstruct();
+			header.digest_mut().push(item);
+			proposal.block = Block::new(header, extrinsics);
+		}
+
+		Ok(proposal)
+	}
 }
 
 impl<B, E, Block, RA, A> sp_consensus::Environment<Block> for
@@ -119,6 +221,7 @@ struct ProposerInner<Block: BlockT, C, A: TransactionPool> {
 	parent_id: BlockId<Block>,
 	parent_number: <<Block as BlockT>::Header as HeaderT>::Number,
 	transaction_pool: Arc<A>,
+	weight_tracing: Option<WeightTracing<Block>>,
 	now: Box<dyn Fn() -> time::Instant + Send + Sync>,
 }
 
@@ -198,6 +301,26 @@ impl<Block, B, E, RA, A> ProposerInner<Block, SubstrateClient<B, E, Block, RA>,
 			block_builder.push(extrinsic)?;
 		}
 
+		// The hooks of the runtime are accounted before any transaction, they may leave little
+		// of the block to the transactions, e.g. at era boundaries.
+		let mut block_weight = block_builder.block_weight()?;
+		if let Some(block_weight) = &block_weight {
+			debug!(
+				"Hooks take {} of the block weight, {} is left for normal transactions.",
+				block_weight.hooks,
+				block_weight.remaining(),
+			);
+		}
+		let is_full = |block_weight: &Option<BlockWeight>| {
+			block_weight.map_or(false, |block_weight| block_weight.remaining_operational() == 0)
+		};
+		if is_full(&block_weight) {
+			warn!("No block weight left for transactions after the hooks, proceeding with proposing.");
+			telemetry!(CONSENSUS_WARN; "basic_authorship.no_weight_left";
+				"parent_hash" => ?self.parent_hash,
+			);
+		}
+
 		// proceed with transactions
 		let mut is_first = true;
 		let mut skipped = 0;
@@ -206,6 +329,9 @@ impl<Block, B, E, RA, A> ProposerInner<Block, SubstrateClient<B, E, Block, RA>,
 
 		debug!("Attempting to push transactions from the pool.");
 		for pending_tx in pending_iterator {
+			if is_full(&block_weight) {
+				break;
+			}
 			if (self.now)() > deadline {
 				debug!(
 					"Consensus deadline reached when pushing block transactions, \
@@ -216,10 +342,54 @@ impl<Block, B, E, RA, A> ProposerInner<Block, SubstrateClient<B, E, Block, RA>,
 
 			let pending_tx_data = pending_tx.data().clone();
 			let pending_tx_hash = pending_tx.hash().clone();
+			let dispatch = self.weight_tracing.as_ref()
+				.and_then(|tracing| (tracing.weight_of)(&self.parent_id, &pending_tx_data));
+			let weight = dispatch.map(|dispatch| dispatch.weight);
+			// Don't execute transactions declaring more weight than is left for their class.
+			if let (Some(block_weight), Some(dispatch)) = (&block_weight, dispatch) {
+				let remaining = if dispatch.operational {
+					block_weight.remaining_operational()
+				} else {
+					block_weight.remaining()
+				};
+				if dispatch.weight > remaining {
+					if skipped < MAX_SKIPPED_TRANSACTIONS {
+						skipped += 1;
+						debug!(
+							"[{:?}] Weight {} exceeds the {} left in the block, skipping it.",
+							pending_tx_hash, dispatch.weight, remaining,
+						);
+						continue;
+					} else {
+						debug!("Block is full, proceed with proposing.");
+						break;
+					}
+				}
+			}
+
 			trace!("[{:?}] Pushing to the block.", pending_tx_hash);
+			let started = time::Instant::now();
 			match sc_block_builder::BlockBuilder::push(&mut block_builder, pending_tx_data) {
 				Ok(()) => {
 					debug!("[{:?}] Pushed to the block.", pending_tx_hash);
+					if let (Some(block_weight), Some(weight)) = (block_weight.as_mut(), weight) {
+						block_weight.consumed = block_weight.consumed.saturating_add(weight);
+					}
+					if let (Some(tracing), Some(weight)) = (&self.weight_tracing, weight) {
+						let elapsed = started.elapsed();
+						trace!("[{:?}] Executed in {:?} with weight {}.", pending_tx_hash, elapsed, weight);
+						if tracing.is_slow(weight, elapsed) {
+							warn!(
+								"[{:?}] Extrinsic took {:?} to execute, which is more than its weight of {} allows",
+								pending_tx_hash, elapsed, weight,
+							);
+							telemetry!(CONSENSUS_WARN; "basic_authorship.slow_extrinsic";
+								"hash" => ?pending_tx_hash,
+								"elapsed_nanos" => elapsed.as_nanos() as u64,
+								"weight" => weight,
+							);
+						}
+					}
 				}
 				Err(sp_blockchain::Error::ApplyExtrinsicFailed(sp_blockchain::ApplyExtrinsicFailed::Validity(e)))
 						if e.exhausted_resources() => {
@@ -237,6 +407,17 @@ impl<Block, B, E, RA, A> ProposerInner<Block, SubstrateClient<B, E, Block, RA>,
 						break;
 					}
 				}
+				Err(sp_blockchain::Error::Execution(e)) => {
+					// The runtime failed to execute the transaction, e.g. because it panicked. The
+					// changes of the failed call are discarded, so we can go on building the block
+					// without it. Removing it from the pool bans it for a while.
+					warn!("[{:?}] Runtime failed to apply transaction, dropping it: {}", pending_tx_hash, e);
+					telemetry!(CONSENSUS_WARN; "basic_authorship.transaction_failed";
+						"hash" => ?pending_tx_hash,
+						"error" => ?e,
+					);
+					unqueue_invalid.push(pending_tx_hash);
+				}
 				Err(e) => {
 					debug!("[{:?}] Invalid transaction: {}", pending_tx_hash, e);
 					unqueue_invalid.push(pending_tx_hash);
@@ -284,9 +465,11 @@ mod tests {
 	use parking_lot::Mutex;
 	use sp_consensus::Proposer;
 	use substrate_test_runtime_client::{
-		runtime::{Extrinsic, Transfer}, AccountKeyring, DefaultTestClientBuilderExt,
+		runtime::{AccountId, Extrinsic, Transfer}, AccountKeyring, DefaultTestClientBuilderExt,
 		TestClientBuilderExt,
 	};
+	use codec::{Encode, KeyedVec};
+	use sp_core::hashing::blake2_256;
 	use sc_transaction_pool::{BasicPool, FullChainApi};
 	use sp_api::Core;
 	use backend::Backend;
@@ -314,6 +497,7 @@ mod tests {
 		let mut proposer_factory = ProposerFactory {
 			client: client.clone(),
 			transaction_pool: txpool.clone(),
+			weight_tracing: None,
 		};
 
 		let cell = Mutex::new(time::Instant::now());
@@ -356,6 +540,7 @@ mod tests {
 		let mut proposer_factory = ProposerFactory {
 			client: client.clone(),
 			transaction_pool: txpool.clone(),
+			weight_tracing: None,
 		};
 
 		let mut proposer = proposer_factory.init_with_now(
@@ -384,4 +569,155 @@ mod tests {
 			storage_changes.transaction_storage_root,
 		);
 	}
+
+	#[test]
+	fn should_drop_transactions_the_runtime_fails_to_execute() {
+		// Transfers to Charlie overflow his balance, which panics in the runtime.
+		let charlie: AccountId = AccountKeyring::Charlie.into();
+		let client = Arc::new(substrate_test_runtime_client::TestClientBuilder::new()
+			.add_extra_storage(
+				blake2_256(&charlie.to_keyed_vec(b"balance:")).to_vec(),
+				u64::max_value().encode(),
+			)
+			.build());
+		let txpool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		let transfer = |from: AccountKeyring, to: AccountId| Transfer {
+			amount: 1,
+			nonce: 0,
+			from: from.into(),
+			to,
+		}.into_signed_tx();
+
+		futures::executor::block_on(txpool.submit_at(
+			&BlockId::number(0),
+			vec![transfer(AccountKeyring::Alice, charlie), transfer(AccountKeyring::Bob, Default::default())],
+		)).unwrap();
+
+		let mut proposer_factory = ProposerFactory {
+			client: client.clone(),
+			transaction_pool: txpool.clone(),
+			weight_tracing: None,
+		};
+		let mut proposer = proposer_factory.init_with_now(
+			&client.header(&BlockId::number(0)).unwrap().unwrap(),
+			Box::new(time::Instant::now),
+		);
+
+		let block = futures::executor::block_on(
+			proposer.propose(Default::default(), Default::default(), time::Duration::from_secs(9), RecordProof::No)
+		).map(|r| r.block).unwrap();
+
+		// Alice's transaction is left out of the block and removed from the pool, Bob's is included.
+		let senders = block.extrinsics().iter().map(|extrinsic| match extrinsic {
+			Extrinsic::Transfer(transfer, _) => transfer.from.clone(),
+			_ => panic!("Only transfers are submitted"),
+		}).collect::<Vec<_>>();
+		assert_eq!(senders, vec![AccountKeyring::Bob.public()]);
+		assert_eq!(txpool.ready().count(), 1);
+	}
+
+	#[test]
+	fn external_driver_should_build_sealed_block() {
+		use sp_runtime::generic::DigestItem;
+
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let txpool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		let genesis_hash = client.info().best_hash;
+
+		futures::executor::block_on(
+			txpool.submit_at(&BlockId::number(0), vec![extrinsic(0)]),
+		).unwrap();
+
+		let mut proposer_factory = ProposerFactory {
+			client: client.clone(),
+			transaction_pool: txpool.clone(),
+			weight_tracing: None,
+		};
+
+		let request = |parent| BuildBlockRequest {
+			parent,
+			inherent_data: Default::default(),
+			inherent_digests: Default::default(),
+			deadline: time::Instant::now() + time::Duration::from_secs(9),
+			record_proof: RecordProof::Yes,
+		};
+
+		let mut pre_seal_hash = None;
+		let proposal = proposer_factory.build_block(request(genesis_hash), |header| {
+			pre_seal_hash = Some(header.hash());
+			Ok(Some(DigestItem::Seal(*b"test", vec![42])))
+		}).unwrap();
+
+		assert_eq!(proposal.block.extrinsics().len(), 1);
+		assert_eq!(*proposal.block.header().parent_hash(), genesis_hash);
+		assert_eq!(proposal.block.header().digest().logs().last(), Some(&DigestItem::Seal(*b"test", vec![42])));
+		assert_ne!(Some(proposal.block.header().hash()), pre_seal_hash);
+		assert!(proposal.proof.is_some());
+
+		assert!(proposer_factory.build_block(request(Default::default()), |_| Ok(None)).is_err());
+	}
+
+	#[test]
+	fn should_skip_transactions_exceeding_the_weight_left_for_their_class() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let txpool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		let transfer = |from: AccountKeyring| Transfer {
+			amount: Default::default(),
+			nonce: 0,
+			from: from.into(),
+			to: Default::default(),
+		}.into_signed_tx();
+
+		futures::executor::block_on(txpool.submit_at(
+			&BlockId::number(0),
+			vec![transfer(AccountKeyring::Alice), transfer(AccountKeyring::Bob), transfer(AccountKeyring::Charlie)],
+		)).unwrap();
+
+		// The test runtime leaves 3 * 1024 * 1024 of its 4 * 1024 * 1024 block weight to normal
+		// transactions.
+		let mut proposer_factory = ProposerFactory {
+			client: client.clone(),
+			transaction_pool: txpool.clone(),
+			weight_tracing: Some(WeightTracing {
+				weight_of: Arc::new(|_, extrinsic| match extrinsic {
+					Extrinsic::Transfer(transfer, _) if transfer.from == AccountKeyring::Charlie.public() =>
+						Some(ExtrinsicDispatch { weight: 1024 * 1024, operational: true }),
+					Extrinsic::Transfer(transfer, _) if transfer.from == AccountKeyring::Bob.public() =>
+						Some(ExtrinsicDispatch { weight: 1024 * 1024, operational: false }),
+					_ => Some(ExtrinsicDispatch { weight: 3 * 1024 * 1024 - 1, operational: false }),
+				}),
+				nanos_per_weight: 1,
+				factor: u64::max_value(),
+			}),
+		};
+		let mut proposer = proposer_factory.init_with_now(
+			&client.header(&BlockId::number(0)).unwrap().unwrap(),
+			Box::new(time::Instant::now),
+		);
+
+		let block = futures::executor::block_on(
+			proposer.propose(Default::default(), Default::default(), time::Duration::from_secs(9), RecordProof::No)
+		).map(|r| r.block).unwrap();
+
+		// Bob's normal transaction doesn't fit, Charlie's operational one does.
+		let senders = block.extrinsics().iter().map(|extrinsic| match extrinsic {
+			Extrinsic::Transfer(transfer, _) => transfer.from.clone(),
+			_ => panic!("Only transfers are submitted"),
+		}).collect::<Vec<_>>();
+		assert_eq!(senders, vec![AccountKeyring::Alice.public(), AccountKeyring::Charlie.public()]);
+		assert_eq!(txpool.ready().count(), 3);
+	}
+
+	#[test]
+	fn should_report_extrinsics_slower_than_their_weight() {
+		let tracing = WeightTracing::<substrate_test_runtime_client::runtime::Block> {
+			weight_of: Arc::new(|_, _| Some(ExtrinsicDispatch { weight: 1_000, operational: false })),
+			nanos_per_weight: 10,
+			factor: 2,
+		};
+
+		assert!(!tracing.is_slow(1_000, time::Duration::from_micros(20)));
+		assert!(tracing.is_slow(1_000, time::Duration::from_micros(21)));
+		assert!(!tracing.is_slow(u64::max_value(), time::Duration::from_secs(3600)));
+	}
 }
@@ -31,6 +31,7 @@
 //! let mut proposer_factory = ProposerFactory {
 //! 	client: client.clone(),
 //! 	transaction_pool: txpool.clone(),
+//! 	weight_tracing: None,
 //! };
 //!
 //! // From this factory, we create a `Proposer`.
@@ -58,4 +59,6 @@
 
 mod basic_authorship;
 
-pub use crate::basic_authorship::{ProposerFactory, Proposer};
+pub use crate::basic_authorship::{
+	ProposerFactory, Proposer, WeightTracing, ExtrinsicWeight, ExtrinsicDispatch, BuildBlockRequest,
+};
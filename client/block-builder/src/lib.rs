@@ -37,7 +37,7 @@ use sp_core::ExecutionContext;
 use sp_api::{Core, ApiExt, ApiErrorFor, ApiRef, ProvideRuntimeApi, StorageChanges, StorageProof};
 use sp_consensus::RecordProof;
 
-pub use sp_block_builder::BlockBuilder as BlockBuilderApi;
+pub use sp_block_builder::{BlockBuilder as BlockBuilderApi, BlockWeight};
 
 use sc_client_api::backend;
 
@@ -168,6 +168,19 @@ where
 		}
 	}
 
+	/// The weight of the block so far, `None` if the runtime doesn't report it.
+	pub fn block_weight(&self) -> Result<Option<BlockWeight>, ApiErrorFor<A, Block>> {
+		let block_id = &self.block_id;
+		if !self.api.has_api_with::<dyn BlockBuilderApi<Block, Error = ApiErrorFor<A, Block>>, _>(
+			block_id,
+			|version| version >= 5,
+		)? {
+			return Ok(None);
+		}
+
+		self.api.block_weight_with_context(block_id, ExecutionContext::BlockConstruction).map(Some)
+	}
+
 	/// Consume the builder to build a valid `Block` containing all pushed extrinsics.
 	///
 	/// Returns the build `Block`, the changes to the storage and an optional `StorageProof`
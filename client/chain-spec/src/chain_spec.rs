@@ -22,6 +22,7 @@ use std::fs::File;
 use std::path::PathBuf;
 use std::rc::Rc;
 use serde::{Serialize, Deserialize};
+use sp_core::{ed25519, hexdisplay::HexDisplay, Pair};
 use sp_core::storage::{StorageKey, StorageData, ChildInfo, Storage, StorageChild};
 use sp_runtime::BuildStorage;
 use serde_json as json;
@@ -33,6 +34,7 @@ enum GenesisSource<G> {
 	File(PathBuf),
 	Binary(Cow<'static, [u8]>),
 	Factory(Rc<dyn Fn() -> G>),
+	Storage(Storage),
 }
 
 impl<G> Clone for GenesisSource<G> {
@@ -41,6 +43,7 @@ impl<G> Clone for GenesisSource<G> {
 			GenesisSource::File(ref path) => GenesisSource::File(path.clone()),
 			GenesisSource::Binary(ref d) => GenesisSource::Binary(d.clone()),
 			GenesisSource::Factory(ref f) => GenesisSource::Factory(f.clone()),
+			GenesisSource::Storage(ref s) => GenesisSource::Storage(s.clone()),
 		}
 	}
 }
@@ -66,6 +69,7 @@ impl<G: RuntimeGenesis> GenesisSource<G> {
 				Ok(genesis.genesis)
 			},
 			GenesisSource::Factory(f) => Ok(Genesis::Runtime(f())),
+			GenesisSource::Storage(storage) => Ok(Genesis::Raw(RawGenesis::from(storage.clone()))),
 		}
 	}
 }
@@ -121,6 +125,31 @@ struct RawGenesis {
 	pub children: HashMap<StorageKey, ChildRawStorage>,
 }
 
+impl From<Storage> for RawGenesis {
+	fn from(storage: Storage) -> Self {
+		let top = storage.top.into_iter()
+			.map(|(k, v)| (StorageKey(k), StorageData(v)))
+			.collect();
+		let children = storage.children.into_iter()
+			.map(|(sk, child)| {
+				let info = child.child_info.as_ref();
+				let (info, ci_type) = info.info();
+				(
+					StorageKey(sk),
+					ChildRawStorage {
+						data: child.data.into_iter()
+							.map(|(k, v)| (StorageKey(k), StorageData(v)))
+							.collect(),
+						child_info: info.to_vec(),
+						child_type: ci_type,
+					},
+			)})
+			.collect();
+
+		RawGenesis { top, children }
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -137,8 +166,12 @@ struct ClientSpec<E> {
 	pub name: String,
 	pub id: String,
 	pub boot_nodes: Vec<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub boot_nodes_source: Option<BootNodesSource>,
 	pub telemetry_endpoints: Option<TelemetryEndpoints>,
 	pub protocol_id: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub fork_id: Option<String>,
 	pub properties: Option<Properties>,
 	#[serde(flatten)]
 	pub extensions: E,
@@ -148,6 +181,56 @@ struct ClientSpec<E> {
 	genesis: serde::de::IgnoredAny,
 }
 
+/// An HTTPS endpoint serving an up-to-date list of bootnodes, signed by the maintainers of the
+/// chain.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct BootNodesSource {
+	/// URL of the list. Must use the `https` scheme.
+	pub url: String,
+	/// Key the list must be signed with.
+	pub public_key: ed25519::Public,
+}
+
+/// A list of bootnodes served by a `BootNodesSource`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+struct SignedBootNodes {
+	boot_nodes: Vec<String>,
+	expires: u64,
+	signature: ed25519::Signature,
+}
+
+impl BootNodesSource {
+	/// Returns the message that is signed for the given list of bootnodes of the chain with the
+	/// given genesis hash, valid until the `expires` UNIX time in seconds: the hex encoded genesis
+	/// hash, the expiry time and the addresses, one per line.
+	pub fn signing_payload(genesis_hash: &[u8], expires: u64, boot_nodes: &[String]) -> Vec<u8> {
+		let mut lines = vec![format!("0x{}", HexDisplay::from(&genesis_hash)), expires.to_string()];
+		lines.extend(boot_nodes.iter().cloned());
+		lines.join("\n").into_bytes()
+	}
+
+	/// Parses a JSON `{ "bootNodes": [...], "expires": <seconds>, "signature": "<hex>" }`
+	/// document served by the endpoint and returns the bootnodes if the signature is valid for
+	/// the chain with the given genesis hash and the list hasn't expired at `now`, the UNIX time
+	/// in seconds.
+	pub fn verify(&self, response: &[u8], genesis_hash: &[u8], now: u64) -> Result<Vec<String>, String> {
+		let signed: SignedBootNodes = json::from_slice(response)
+			.map_err(|e| format!("Error parsing bootnodes list: {}", e))?;
+		let payload = Self::signing_payload(genesis_hash, signed.expires, &signed.boot_nodes);
+		if !ed25519::Pair::verify(&signed.signature, payload, &self.public_key) {
+			return Err("Bad signature on bootnodes list".into())
+		}
+		if signed.expires <= now {
+			return Err("The bootnodes list has expired".into())
+		}
+		Ok(signed.boot_nodes)
+	}
+}
+
 /// Arbitrary properties defined in chain spec as a JSON object
 pub type Properties = json::map::Map<String, json::Value>;
 
@@ -177,6 +260,11 @@ impl<G, E> ChainSpec<G, E> {
 		&self.client_spec.boot_nodes
 	}
 
+	/// Endpoint serving additional bootnodes, if any.
+	pub fn boot_nodes_source(&self) -> Option<&BootNodesSource> {
+		self.client_spec.boot_nodes_source.as_ref()
+	}
+
 	/// Spec name.
 	pub fn name(&self) -> &str {
 		&self.client_spec.name
@@ -197,6 +285,13 @@ impl<G, E> ChainSpec<G, E> {
 		self.client_spec.protocol_id.as_ref().map(String::as_str)
 	}
 
+	/// Optional identifier of a fork of the chain.
+	///
+	/// Nodes following different forks of a chain with the same genesis refuse to peer.
+	pub fn fork_id(&self) -> Option<&str> {
+		self.client_spec.fork_id.as_ref().map(String::as_str)
+	}
+
 	/// Additional loosly-typed properties of the chain.
 	///
 	/// Returns an empty JSON object if 'properties' not defined in config
@@ -209,11 +304,23 @@ impl<G, E> ChainSpec<G, E> {
 		self.client_spec.boot_nodes.push(addr.to_string())
 	}
 
+	/// Set the endpoint serving additional bootnodes.
+	pub fn set_boot_nodes_source(&mut self, source: Option<BootNodesSource>) {
+		self.client_spec.boot_nodes_source = source;
+	}
+
 	/// Returns a reference to defined chain spec extensions.
 	pub fn extensions(&self) -> &E {
 		&self.client_spec.extensions
 	}
 
+	/// Replace the genesis of the spec with the given raw storage.
+	///
+	/// This is useful to start a new chain from the state of an existing one.
+	pub fn set_storage(&mut self, storage: Storage) {
+		self.genesis = GenesisSource::Storage(storage);
+	}
+
 	/// Create hardcoded spec.
 	pub fn from_genesis<F: Fn() -> G + 'static>(
 		name: &str,
@@ -229,8 +336,10 @@ impl<G, E> ChainSpec<G, E> {
 			name: name.to_owned(),
 			id: id.to_owned(),
 			boot_nodes,
+			boot_nodes_source: None,
 			telemetry_endpoints,
 			protocol_id: protocol_id.map(str::to_owned),
+			fork_id: None,
 			properties,
 			extensions,
 			consensus_engine: (),
@@ -280,29 +389,7 @@ impl<G: RuntimeGenesis, E: serde::Serialize> ChainSpec<G, E> {
 
 		};
 		let genesis = match (raw, self.genesis.resolve()?) {
-			(true, Genesis::Runtime(g)) => {
-				let storage = g.build_storage()?;
-				let top = storage.top.into_iter()
-					.map(|(k, v)| (StorageKey(k), StorageData(v)))
-					.collect();
-				let children = storage.children.into_iter()
-					.map(|(sk, child)| {
-						let info = child.child_info.as_ref();
-						let (info, ci_type) = info.info();
-						(
-							StorageKey(sk),
-							ChildRawStorage {
-								data: child.data.into_iter()
-									.map(|(k, v)| (StorageKey(k), StorageData(v)))
-									.collect(),
-								child_info: info.to_vec(),
-								child_type: ci_type,
-							},
-					)})
-					.collect();
-
-				Genesis::Raw(RawGenesis { top, children })
-			},
+			(true, Genesis::Runtime(g)) => Genesis::Raw(RawGenesis::from(g.build_storage()?)),
 			(_, genesis) => genesis,
 		};
 		let container = Container {
@@ -363,4 +450,57 @@ mod tests {
 
 		assert_eq!(spec.extensions().my_property, "Test Extension");
 	}
+
+	#[test]
+	fn should_replace_genesis_with_raw_storage() {
+		let mut spec = TestSpec::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec.json").to_vec()
+		)).unwrap();
+
+		let mut storage = Storage::default();
+		storage.top.insert(b"key".to_vec(), b"value".to_vec());
+		spec.set_storage(storage.clone());
+
+		assert_eq!(spec.build_storage().unwrap().top, storage.top);
+		let json: json::Value = json::from_str(&spec.to_json(false).unwrap()).unwrap();
+		assert_eq!(json["genesis"]["raw"]["top"]["0x6b6579"], "0x76616c7565");
+	}
+
+	#[test]
+	fn should_verify_signed_boot_nodes() {
+		let pair = ed25519::Pair::from_seed(&[1; 32]);
+		let source = BootNodesSource {
+			url: "https://example.com/bootnodes.json".into(),
+			public_key: pair.public(),
+		};
+		let boot_nodes = vec![
+			"/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string(),
+		];
+		let genesis_hash = [2; 32];
+		let signature = pair.sign(&BootNodesSource::signing_payload(&genesis_hash, 1000, &boot_nodes));
+		let response = |boot_nodes: &[String], expires: u64| json::to_vec(&json::json!({
+			"bootNodes": boot_nodes,
+			"expires": expires,
+			"signature": signature,
+		})).unwrap();
+
+		assert_eq!(source.verify(&response(&boot_nodes, 1000), &genesis_hash, 999), Ok(boot_nodes.clone()));
+		assert!(source.verify(&response(&[], 1000), &genesis_hash, 999).is_err());
+		assert!(source.verify(b"[]", &genesis_hash, 999).is_err());
+		// The list can't be replayed once it has expired, or on another chain.
+		assert_eq!(
+			source.verify(&response(&boot_nodes, 1000), &genesis_hash, 1000),
+			Err("The bootnodes list has expired".into()),
+		);
+		assert!(source.verify(&response(&boot_nodes, 2000), &genesis_hash, 999).is_err());
+		assert!(source.verify(&response(&boot_nodes, 1000), &[3; 32], 999).is_err());
+
+		let mut spec = TestSpec::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec.json").to_vec()
+		)).unwrap();
+		assert_eq!(spec.boot_nodes_source(), None);
+		spec.set_boot_nodes_source(Some(source.clone()));
+		let spec = TestSpec::from_json_bytes(Cow::Owned(spec.to_json(false).unwrap().into_bytes())).unwrap();
+		assert_eq!(spec.boot_nodes_source(), Some(&source));
+	}
 }
@@ -111,7 +111,7 @@
 mod chain_spec;
 mod extension;
 
-pub use chain_spec::{ChainSpec, Properties, NoExtension};
+pub use chain_spec::{ChainSpec, Properties, NoExtension, BootNodesSource};
 pub use extension::{Group, Fork, Forks, Extension};
 pub use sc_chain_spec_derive::{ChainSpecExtension, ChainSpecGroup};
 
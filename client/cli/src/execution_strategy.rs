@@ -30,6 +30,8 @@ arg_enum! {
 		Both,
 		// Execute with the native build if possible; if it fails, then execute with WebAssembly.
 		NativeElseWasm,
+		// Execute with both builds and log any divergence in results or storage changes.
+		Canary,
 	}
 }
 
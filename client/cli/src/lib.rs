@@ -25,12 +25,13 @@ mod params;
 mod execution_strategy;
 pub mod error;
 pub mod informant;
+pub mod systemd;
 
 use sc_client_api::execution_extensions::ExecutionStrategies;
 use sc_service::{
 	config::{Configuration, DatabaseConfig, KeystoreConfig},
 	ServiceBuilderCommand,
-	RuntimeGenesis, ChainSpecExtension, PruningMode, ChainSpec,
+	RuntimeGenesis, ChainSpecExtension, PruningMode, ChainSpec, Properties,
 };
 use sc_network::{
 	self,
@@ -39,7 +40,7 @@ use sc_network::{
 		NetworkConfiguration, TransportConfig, NonReservedPeerMode, NodeKeyConfig, build_multiaddr
 	},
 };
-use sp_core::H256;
+use sp_core::{H256, crypto::{set_default_ss58_version, Ss58AddressFormat}};
 
 use std::{
 	io::{Write, Read, Seek, Cursor, stdin, stdout, ErrorKind}, iter, fmt::Debug, fs::{self, File},
@@ -81,6 +82,14 @@ const NODE_NAME_MAX_LENGTH: usize = 32;
 /// is specified in combination with `--node-key-type=ed25519`.
 const NODE_KEY_ED25519_FILE: &str = "secret_ed25519";
 
+/// Percentage of `--memory-budget` given to the database cache.
+const DATABASE_CACHE_SHARE: usize = 40;
+/// Percentage of `--memory-budget` given to the state cache.
+const STATE_CACHE_SHARE: usize = 25;
+/// Percentage of `--memory-budget` given to the ready queue of the transaction pool. The
+/// rest of the budget is left for network buffers, the runtime and other allocations.
+const TRANSACTION_POOL_SHARE: usize = 8;
+
 /// Executable version. Used to pass version information from the root crate.
 #[derive(Clone)]
 pub struct VersionInfo {
@@ -139,9 +148,28 @@ pub fn load_spec<F, G, E>(cli: &SharedParams, factory: F) -> error::Result<Chain
 		Some(spec) => spec,
 		None => ChainSpec::from_json_file(PathBuf::from(chain_key))?
 	};
+	set_ss58_version(&spec)?;
 	Ok(spec)
 }
 
+/// Use the SS58 address format given by the `ss58Format` property of the chain spec, if any,
+/// for displaying and parsing addresses, instead of the generic Substrate format.
+fn set_ss58_version<G, E>(spec: &ChainSpec<G, E>) -> error::Result<()> {
+	if let Some(format) = ss58_version(&spec.properties())? {
+		set_default_ss58_version(format);
+	}
+	Ok(())
+}
+
+/// The SS58 address format given by the `ss58Format` property, if any.
+fn ss58_version(properties: &Properties) -> error::Result<Option<Ss58AddressFormat>> {
+	properties.get("ss58Format")
+		.map(|format| serde_json::from_value(format.clone()).map_err(|e| {
+			error::Error::Input(format!("Invalid `ss58Format` property {}: {}", format, e))
+		}))
+		.transpose()
+}
+
 fn base_path(cli: &SharedParams, version: &VersionInfo) -> PathBuf {
 	cli.base_path.clone()
 		.unwrap_or_else(||
@@ -684,6 +712,11 @@ fn fill_transaction_pool_configuration<C, G, E>(
 	options.transaction_pool.future.count = params.pool_limit / factor;
 	options.transaction_pool.future.total_bytes = params.pool_kbytes * 1024 / factor;
 
+	if let Some(budget) = options.memory_budget {
+		options.transaction_pool.ready.total_bytes = budget / 100 * TRANSACTION_POOL_SHARE;
+		options.transaction_pool.future.total_bytes = budget / 100 * TRANSACTION_POOL_SHARE / factor;
+	}
+
 	Ok(())
 }
 
@@ -799,6 +832,15 @@ pub fn fill_import_params<C, G, E>(
 	}
 
 	config.state_cache_size = cli.state_cache_size;
+	if let Some(budget) = cli.memory_budget {
+		if let DatabaseConfig::Path { ref mut cache_size, .. } = config.database {
+			*cache_size = Some((budget / 100 * DATABASE_CACHE_SHARE / 1024 / 1024) as u32);
+		}
+		config.state_cache_size = budget / 100 * STATE_CACHE_SHARE;
+		config.memory_budget = Some(budget);
+	}
+	config.compress_blocks = cli.compress_blocks;
+	config.bad_blocks = cli.bad_blocks.clone();
 
 	// by default we disable pruning if the node is an authority (i.e.
 	// `ArchiveAll`), otherwise we keep state for the last 256 blocks. if the
@@ -825,7 +867,14 @@ pub fn fill_import_params<C, G, E>(
 	config.wasm_method = cli.wasm_method.into();
 
 	let exec = &cli.execution_strategies;
-	let exec_all_or = |strat: ExecutionStrategy| exec.execution.unwrap_or(strat).into();
+	let exec_all_or = |strat: ExecutionStrategy| {
+		match exec.execution.unwrap_or(strat).into() {
+			sc_client_api::ExecutionStrategy::Canary { .. } => sc_client_api::ExecutionStrategy::Canary {
+				fail_on_divergence: exec.canary_fail_on_divergence,
+			},
+			strat => strat,
+		}
+	};
 	config.execution_strategies = ExecutionStrategies {
 		syncing: exec_all_or(exec.execution_syncing),
 		importing: exec_all_or(exec.execution_import_block),
@@ -894,6 +943,17 @@ where
 
 	config.roles = role;
 	config.disable_grandpa = cli.no_grandpa;
+	config.slow_extrinsic_factor = cli.slow_extrinsic_factor;
+	config.grandpa_lightweight_observer = cli.grandpa_lightweight_observer;
+	config.grandpa_threshold_peers = cli.grandpa_threshold_peers.iter()
+		.map(|peer| peer.parse().map_err(|_| error::Error::Input(format!("Invalid peer id: {}", peer))))
+		.collect::<error::Result<_>>()?;
+	config.consensus_threads = sc_service::config::ConsensusThreads {
+		count: cli.consensus_threads,
+		cpus: cli.consensus_cpus,
+		priority: cli.consensus_priority.into(),
+		pool: None,
+	};
 
 	let client_id = config.client_id();
 	fill_network_configuration(
@@ -924,8 +984,35 @@ where
 	config.grafana_port = Some(
 		parse_address(&format!("{}:{}", grafana_interface, 9955), cli.grafana_port)?
 	);
+	if let Some(port) = cli.grpc_port {
+		let grpc_interface: &str = if cli.grpc_external { "0.0.0.0" } else { "127.0.0.1" };
+		config.grpc = Some(parse_address(&format!("{}:{}", grpc_interface, port), None)?);
+	}
+	if let Some(port) = cli.graphql_port {
+		let graphql_interface: &str = if cli.graphql_external { "0.0.0.0" } else { "127.0.0.1" };
+		config.graphql = Some(parse_address(&format!("{}:{}", graphql_interface, port), None)?);
+	}
+	if let Some(url) = cli.publish_url {
+		let topic_prefix = cli.publish_topic_prefix.unwrap_or_else(|| config.chain_spec.id().into());
+		config.publisher = Some(sc_service::config::PublisherConfig { url, topic_prefix });
+	}
 
 	config.rpc_ws_max_connections = cli.ws_max_connections;
+	config.rpc_ws_compression = cli.ws_compression;
+	config.rpc_http_unsafe = cli.unsafe_rpc_external;
+	config.rpc_ws_unsafe = cli.unsafe_ws_external;
+	config.rpc_allowed_methods = cli.rpc_allowed_methods;
+	config.rpc_denied_methods = cli.rpc_denied_methods;
+	config.rpc_auth = cli.rpc_auth;
+	config.rpc_origin_policy = cli.rpc_origin_policy;
+	if let (Some(cert), Some(key)) = (cli.rpc_cert, cli.rpc_key) {
+		config.rpc_tls = Some(sc_service::config::RpcTlsConfig {
+			cert,
+			key,
+			sni_dir: cli.rpc_sni_dir,
+			client_ca: cli.rpc_client_ca,
+		});
+	}
 	config.rpc_cors = cli.rpc_cors.unwrap_or_else(|| if is_dev {
 		log::warn!("Running in --dev mode, RPC CORS has been disabled.");
 		Cors::All
@@ -970,6 +1057,10 @@ fn interface_str(
 	if is_external || is_unsafe_external {
 		log::warn!("It isn't safe to expose RPC publicly without a proxy server that filters \
 		available set of RPC methods.");
+		if !is_unsafe_external {
+			log::info!("Unsafe RPC methods are disabled on public interfaces. Use \
+			`--unsafe-rpc-external` or `--unsafe-ws-external` to expose them.");
+		}
 
 		Ok("0.0.0.0")
 	} else {
@@ -977,6 +1068,25 @@ fn interface_str(
 	}
 }
 
+/// Creates the configuration of a node from the arguments of the `run` command, the first one
+/// being the name of the executable.
+///
+/// Allows building the configurations of several nodes run by the same process.
+pub fn create_run_config_from_args<C, G, E, S, I>(
+	args: I, spec_factory: S, impl_name: &'static str, version: &VersionInfo,
+) -> error::Result<Configuration<C, G, E>>
+where
+	C: Default,
+	G: RuntimeGenesis,
+	E: ChainSpecExtension,
+	S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+	I: IntoIterator,
+	<I as IntoIterator>::Item: Into<std::ffi::OsString> + Clone,
+{
+	let cli = RunCmd::from_iter_safe(args).map_err(|e| error::Error::Input(e.message))?;
+	create_run_node_config(cli, spec_factory, impl_name, version)
+}
+
 /// Creates a configuration including the database path.
 pub fn create_config_with_db_path<C, G, E, S>(
 	spec_factory: S, cli: &SharedParams, version: &VersionInfo,
@@ -1096,6 +1206,41 @@ mod tests {
 	use super::*;
 	use sc_network::config::identity::ed25519;
 
+	#[test]
+	fn ss58_version_is_read_from_the_properties() {
+		let version = |properties: serde_json::Value| {
+			ss58_version(properties.as_object().unwrap()).map(|format| format.map(u8::from))
+		};
+
+		assert_eq!(version(serde_json::json!({})).unwrap(), None);
+		assert_eq!(version(serde_json::json!({ "ss58Format": 2 })).unwrap(), Some(2));
+		assert_eq!(version(serde_json::json!({ "ss58Format": 200 })).unwrap(), Some(200));
+		assert!(version(serde_json::json!({ "ss58Format": 256 })).is_err());
+		assert!(version(serde_json::json!({ "ss58Format": "kusama" })).is_err());
+	}
+
+	#[test]
+	fn ss58_version_of_the_chain_spec_is_the_default() {
+		let mut properties = Properties::new();
+		properties.insert("ss58Format".into(), 2.into());
+		let spec = ChainSpec::from_genesis(
+			"test",
+			"test-id",
+			|| (),
+			Vec::new(),
+			None,
+			None,
+			Some(properties),
+			None,
+		);
+
+		set_ss58_version(&spec).unwrap();
+		let format = Ss58AddressFormat::default();
+		set_default_ss58_version(Ss58AddressFormat::SubstrateAccountDirect);
+
+		assert!(format == Ss58AddressFormat::KusamaAccountDirect);
+	}
+
 	#[test]
 	fn tests_node_name_good() {
 		assert!(is_node_name_valid("short name").is_ok());
@@ -1250,4 +1395,53 @@ mod tests {
 			assert_eq!(expected_path, node_config.keystore.path().unwrap().to_owned());
 		}
 	}
+
+	#[test]
+	fn memory_budget_is_divided() {
+		let chain_spec = ChainSpec::from_genesis(
+			"test",
+			"test-id",
+			|| (),
+			Vec::new(),
+			None,
+			None,
+			None,
+			None,
+		);
+
+		let version_info = VersionInfo {
+			name: "test",
+			version: "42",
+			commit: "234234",
+			executable_name: "test",
+			description: "cool test",
+			author: "universe",
+			support_url: "com",
+		};
+
+		let mut run_cmds = RunCmd::from_iter(&["test", "--memory-budget", "1GiB"]);
+		run_cmds.shared_params.base_path = Some(PathBuf::from("/test/path"));
+
+		let node_config = create_run_node_config::<(), _, _, _>(
+			run_cmds,
+			|_| Ok(Some(chain_spec.clone())),
+			"test",
+			&version_info,
+		).unwrap();
+
+		let budget = 1024 * 1024 * 1024;
+		assert_eq!(node_config.memory_budget, Some(budget));
+		assert_eq!(node_config.state_cache_size, budget / 100 * STATE_CACHE_SHARE);
+		assert_eq!(node_config.transaction_pool.ready.total_bytes, budget / 100 * TRANSACTION_POOL_SHARE);
+		match node_config.database {
+			DatabaseConfig::Path { cache_size, .. } => assert_eq!(cache_size, Some(409)),
+			DatabaseConfig::Custom(_) => panic!("Expected a database path"),
+		}
+	}
+
+	#[test]
+	fn invalid_memory_budget_is_rejected() {
+		assert!(RunCmd::from_iter_safe(&["test", "--memory-budget", "4TiB"]).is_err());
+		assert!(RunCmd::from_iter_safe(&["test", "--memory-budget", "GiB"]).is_err());
+	}
 }
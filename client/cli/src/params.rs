@@ -28,6 +28,8 @@ impl Into<sc_client_api::ExecutionStrategy> for ExecutionStrategy {
 			ExecutionStrategy::Wasm => sc_client_api::ExecutionStrategy::AlwaysWasm,
 			ExecutionStrategy::Both => sc_client_api::ExecutionStrategy::Both,
 			ExecutionStrategy::NativeElseWasm => sc_client_api::ExecutionStrategy::NativeElseWasm,
+			ExecutionStrategy::Canary =>
+				sc_client_api::ExecutionStrategy::Canary { fail_on_divergence: false },
 		}
 	}
 }
@@ -80,6 +82,25 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// Scheduling priority of the threads running consensus-critical tasks.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy)]
+	pub enum ConsensusPriority {
+		Normal,
+		High,
+	}
+}
+
+impl Into<sc_service::config::ThreadPriority> for ConsensusPriority {
+	fn into(self) -> sc_service::config::ThreadPriority {
+		match self {
+			ConsensusPriority::Normal => sc_service::config::ThreadPriority::Normal,
+			ConsensusPriority::High => sc_service::config::ThreadPriority::High,
+		}
+	}
+}
+
 /// Shared parameters used by all `CoreParams`.
 #[derive(Debug, StructOpt, Clone)]
 pub struct SharedParams {
@@ -140,6 +161,27 @@ pub struct ImportParams {
 	/// Specify the state cache size.
 	#[structopt(long = "state-cache-size", value_name = "Bytes", default_value = "67108864")]
 	pub state_cache_size: usize,
+
+	/// Total amount of memory the node should use, e.g. `4GiB`.
+	///
+	/// The budget is divided between the database cache, the state cache and the
+	/// transaction pool, overriding `--db-cache`, `--state-cache-size` and `--pool-kbytes`.
+	/// The rest is left for the network, the runtime and other allocations. The state
+	/// cache is shrunk while the node uses more memory than the budget.
+	#[structopt(long = "memory-budget", value_name = "SIZE", parse(try_from_str = parse_size))]
+	pub memory_budget: Option<usize>,
+
+	/// Compress block bodies and justifications in the database.
+	///
+	/// Blocks imported before are compressed in the background. Compressed blocks stay
+	/// readable when the option is dropped again.
+	#[structopt(long = "compress-blocks")]
+	pub compress_blocks: bool,
+
+	/// Hash of a block that must never be imported nor built upon, in addition to the
+	/// `badBlocks` of the chain spec. Can be passed multiple times.
+	#[structopt(long = "bad-block", value_name = "HASH")]
+	pub bad_blocks: Vec<String>,
 }
 
 /// Parameters used to create the network configuration.
@@ -276,7 +318,8 @@ pub struct NodeKeyParams {
 	///   The file must contain an unencoded 32 bytes Ed25519 secret key.
 	///
 	/// If the file does not exist, it is created with a newly generated secret key of
-	/// the chosen type.
+	/// the chosen type. On Unix, the node refuses to start if other users have access
+	/// to the file.
 	#[structopt(long = "node-key-file", value_name = "FILE")]
 	pub node_key_file: Option<PathBuf>,
 }
@@ -380,6 +423,11 @@ pub struct ExecutionStrategies {
 		]
 	)]
 	pub execution: Option<ExecutionStrategy>,
+
+	/// Fail the call instead of only logging when the `Canary` strategy sees native and wasm
+	/// execution diverge.
+	#[structopt(long = "canary-fail-on-divergence")]
+	pub canary_fail_on_divergence: bool,
 }
 
 /// The `run` command used to run a node.
@@ -415,6 +463,49 @@ pub struct RunCmd {
 	#[structopt(long = "no-grandpa")]
 	pub no_grandpa: bool,
 
+	/// Report the extrinsics executing this many times slower than their weight allows while
+	/// authoring blocks, as their weight is probably too low. `0` disables the report.
+	#[structopt(long = "slow-extrinsic-factor", value_name = "FACTOR", default_value = "2")]
+	pub slow_extrinsic_factor: u64,
+
+	/// Run the GRANDPA observer in lightweight mode when not validating: blocks are finalized
+	/// from the commits only, the votes of the rounds are neither checked nor relayed.
+	#[structopt(
+		long = "grandpa-lightweight-observer",
+		conflicts_with_all = &[ "validator", "no-grandpa" ]
+	)]
+	pub grandpa_lightweight_observer: bool,
+
+	/// Experimental: peer holding a share of a GRANDPA key split between several hosts. Can be
+	/// passed multiple times.
+	///
+	/// Votes are signed with the shares of this node and of the given peers, which are stored in
+	/// the keystores with the `grth` key type. Only these peers are answered requests for
+	/// signature shares. The peers should be reachable, e.g. as reserved nodes.
+	#[structopt(long = "grandpa-threshold-peer", value_name = "PEER_ID")]
+	pub grandpa_threshold_peers: Vec<String>,
+
+	/// Number of threads dedicated to block authoring and GRANDPA voting. With `0`, these tasks
+	/// share the threads of the RPC and network tasks.
+	#[structopt(long = "consensus-threads", value_name = "COUNT", default_value = "2")]
+	pub consensus_threads: usize,
+
+	/// Comma separated list of CPUs to pin the consensus threads and the block import thread to.
+	#[structopt(long = "consensus-cpus", value_name = "CPUS", use_delimiter = true)]
+	pub consensus_cpus: Vec<usize>,
+
+	/// Scheduling priority of the consensus threads and of the block import thread.
+	///
+	/// `High` requires the `CAP_SYS_NICE` capability on Linux.
+	#[structopt(
+		long = "consensus-priority",
+		value_name = "PRIORITY",
+		possible_values = &ConsensusPriority::variants(),
+		case_insensitive = true,
+		default_value = "Normal"
+	)]
+	pub consensus_priority: ConsensusPriority,
+
 	/// Experimental: Run in light client mode.
 	#[structopt(long = "light")]
 	pub light: bool,
@@ -423,13 +514,14 @@ pub struct RunCmd {
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use a RPC proxy
 	/// server to filter out dangerous methods. More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
-	/// Use `--unsafe-rpc-external` to suppress the warning if you understand the risks.
+	/// Unsafe methods (key insertion and rotation, removing extrinsics) are disabled, use
+	/// `--unsafe-rpc-external` to expose them if you understand the risks.
 	#[structopt(long = "rpc-external")]
 	pub rpc_external: bool,
 
 	/// Listen to all RPC interfaces.
 	///
-	/// Same as `--rpc-external`.
+	/// Same as `--rpc-external`, but the unsafe methods are exposed as well.
 	#[structopt(long = "unsafe-rpc-external")]
 	pub unsafe_rpc_external: bool,
 
@@ -437,16 +529,26 @@ pub struct RunCmd {
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use a RPC proxy
 	/// server to filter out dangerous methods. More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
-	/// Use `--unsafe-ws-external` to suppress the warning if you understand the risks.
+	/// Unsafe methods (key insertion and rotation, removing extrinsics) are disabled, use
+	/// `--unsafe-ws-external` to expose them if you understand the risks.
 	#[structopt(long = "ws-external")]
 	pub ws_external: bool,
 
 	/// Listen to all Websocket interfaces.
 	///
-	/// Same as `--ws-external`.
+	/// Same as `--ws-external`, but the unsafe methods are exposed as well.
 	#[structopt(long = "unsafe-ws-external")]
 	pub unsafe_ws_external: bool,
 
+	/// RPC method to expose even if it is unsafe and the RPC servers listen to all interfaces.
+	/// Can be passed multiple times.
+	#[structopt(long = "rpc-allow-method", value_name = "METHOD")]
+	pub rpc_allowed_methods: Vec<String>,
+
+	/// RPC method not to expose over HTTP and Websockets. Can be passed multiple times.
+	#[structopt(long = "rpc-deny-method", value_name = "METHOD")]
+	pub rpc_denied_methods: Vec<String>,
+
 	/// Listen to all Grafana data source interfaces.
 	///
 	/// Default is local.
@@ -465,6 +567,12 @@ pub struct RunCmd {
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Compress the WS RPC messages with `permessage-deflate` for the clients supporting it.
+	///
+	/// Cuts the bandwidth of storage subscriptions, at the cost of some CPU.
+	#[structopt(long = "ws-compression")]
+	pub ws_compression: bool,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -475,10 +583,93 @@ pub struct RunCmd {
 	#[structopt(long = "rpc-cors", value_name = "ORIGINS", parse(try_from_str = parse_cors))]
 	pub rpc_cors: Option<Cors>,
 
+	/// File with the API keys required to call the HTTP & WS RPC methods.
+	///
+	/// Each key is granted a list of methods and a request budget. HTTP requests and WS
+	/// handshakes present their key with an `Authorization: Bearer <KEY>` header. Clients without
+	/// key get the `public` access, with a budget per client address. The file is reloaded when
+	/// it changes.
+	#[structopt(long = "rpc-auth", value_name = "PATH", parse(from_os_str))]
+	pub rpc_auth: Option<PathBuf>,
+
+	/// File with the RPC methods callable from given browser origins.
+	///
+	/// Restricts the methods requests with these `Origin` headers can call over HTTP and WS, for
+	/// example to only give a public dApp read access. The origins must also be allowed by
+	/// `--rpc-cors`. Other origins can only call the methods of the `*` entry, if any.
+	#[structopt(long = "rpc-origin-policy", value_name = "PATH", parse(from_os_str))]
+	pub rpc_origin_policy: Option<PathBuf>,
+
+	/// PEM file with the TLS certificate chain of the HTTP RPC server.
+	///
+	/// The HTTP RPC is then served over TLS only, with HTTP/2 for the clients supporting it.
+	/// Requires `--rpc-key`. On Unix, the certificates are reloaded on `SIGHUP`.
+	#[structopt(long = "rpc-cert", value_name = "PATH", parse(from_os_str), requires = "rpc-key")]
+	pub rpc_cert: Option<PathBuf>,
+
+	/// PEM file with the private key of the certificate given with `--rpc-cert`.
+	#[structopt(long = "rpc-key", value_name = "PATH", parse(from_os_str), requires = "rpc-cert")]
+	pub rpc_key: Option<PathBuf>,
+
+	/// Directory with the TLS certificates of other domain names served by the HTTP RPC server.
+	///
+	/// The certificate is chosen with the name requested by the client (SNI): `<NAME>.crt` holds
+	/// the certificate chain of `NAME` and `<NAME>.key` its private key. Other names get the
+	/// certificate given with `--rpc-cert`.
+	#[structopt(long = "rpc-sni-dir", value_name = "PATH", parse(from_os_str), requires = "rpc-cert")]
+	pub rpc_sni_dir: Option<PathBuf>,
+
+	/// PEM file with the certificate authorities of the clients of the HTTP RPC server.
+	///
+	/// Clients must then present a certificate signed by one of them.
+	#[structopt(long = "rpc-client-ca", value_name = "PATH", parse(from_os_str), requires = "rpc-cert")]
+	pub rpc_client_ca: Option<PathBuf>,
+
 	/// Specify Grafana data source server TCP Port.
 	#[structopt(long = "grafana-port", value_name = "PORT")]
 	pub grafana_port: Option<u16>,
 
+	/// Serve the finalized blocks over gRPC on the given TCP port.
+	///
+	/// Each finalized block is streamed with its extrinsics, its decoded events and the storage
+	/// items it changed. Requires the node to be built with the `grpc` feature.
+	#[structopt(long = "grpc-port", value_name = "PORT")]
+	pub grpc_port: Option<u16>,
+
+	/// Listen to all gRPC interfaces.
+	///
+	/// Default is local.
+	#[structopt(long = "grpc-external")]
+	pub grpc_external: bool,
+
+	/// Serve a GraphQL endpoint on the given TCP port.
+	///
+	/// Queries the blocks, extrinsics and events of the chain, and the ones of an account, with
+	/// filters and pagination. Requires the node to be built with the `graphql` feature.
+	#[structopt(long = "graphql-port", value_name = "PORT")]
+	pub graphql_port: Option<u16>,
+
+	/// Listen to all GraphQL interfaces.
+	///
+	/// Default is local.
+	#[structopt(long = "graphql-external")]
+	pub graphql_external: bool,
+
+	/// Publish the finalized blocks, the results of their extrinsics and their events to a
+	/// message broker.
+	///
+	/// Either `kafka://` followed by the comma-separated list of the brokers, or the `nats://`
+	/// URL of a NATS server. Requires the node to be built with the `kafka` or `nats` feature.
+	#[structopt(long = "publish-url", value_name = "URL")]
+	pub publish_url: Option<String>,
+
+	/// Prefix of the topics the blocks are published to.
+	///
+	/// Messages go to the `<PREFIX>.blocks`, `<PREFIX>.extrinsics` and `<PREFIX>.events`
+	/// topics. Default is the id of the chain.
+	#[structopt(long = "publish-topic-prefix", value_name = "PREFIX")]
+	pub publish_topic_prefix: Option<String>,
+
 	/// The human-readable name for this node.
 	///
 	/// The node name will be reported to the telemetry server, if enabled.
@@ -661,6 +852,25 @@ fn parse_telemetry_endpoints(s: &str) -> Result<(String, u8), Box<dyn std::error
 	}
 }
 
+/// Parse a size in bytes, optionally suffixed with `KiB`, `MiB` or `GiB`.
+fn parse_size(s: &str) -> Result<usize, Box<dyn std::error::Error>> {
+	let s = s.trim();
+	let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+		Some(pos) => (&s[..pos], s[pos..].trim()),
+		None => (s, ""),
+	};
+	let multiplier = match unit {
+		"" | "B" => 1,
+		"KiB" | "K" => 1024,
+		"MiB" | "M" => 1024 * 1024,
+		"GiB" | "G" => 1024 * 1024 * 1024,
+		_ => return Err(format!("Unknown size unit: {}", unit).into()),
+	};
+	number.parse::<usize>()?
+		.checked_mul(multiplier)
+		.ok_or_else(|| "Size is too large".into())
+}
+
 /// CORS setting
 ///
 /// The type is introduced to overcome `Option<Option<T>>`
@@ -0,0 +1,166 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Systemd integration for services of `Type=notify`.
+//!
+//! Notifies systemd once the node is ready and when it stops, and sends watchdog keepalives
+//! while blocks are imported. All notifications are ignored if the node is not started by
+//! systemd, i.e. `NOTIFY_SOCKET` is not set.
+
+use futures::prelude::*;
+use log::{debug, warn};
+use sc_service::AbstractService;
+use std::{env, time::{Duration, Instant}};
+
+/// How long blocks can wait for import without progress before the node is considered hung.
+const IMPORT_STALL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Creates a `Future` notifying systemd that the service is ready and keeping its watchdog
+/// alive. Must be polled regularly.
+pub fn build<S: AbstractService>(service: &S) -> impl futures::Future<Output = ()> {
+	// RPC servers and the network are started by the time the service is built.
+	notify("READY=1");
+
+	let interval = match watchdog_interval() {
+		Some(interval) => interval,
+		None => return future::Either::Left(future::ready(())),
+	};
+	let client = service.client();
+	let mut health = ImportHealth::new(client.chain_info().best_number, Instant::now());
+
+	// the status is reported by the network, so a hung network stops the keepalives, too.
+	let watchdog = service
+		.network_status(interval)
+		.for_each(move |(status, _)| {
+			let best = client.chain_info().best_number;
+			if health.is_healthy(best, status.num_queued_blocks, Instant::now()) {
+				notify("WATCHDOG=1");
+			} else {
+				warn!(
+					"No block imported for {}s while {} blocks are queued, stopping the watchdog",
+					IMPORT_STALL_TIMEOUT.as_secs(),
+					status.num_queued_blocks,
+				);
+			}
+			future::ready(())
+		});
+	future::Either::Right(watchdog)
+}
+
+/// Notify systemd that the service is stopping.
+pub fn notify_stopping() {
+	notify("STOPPING=1");
+}
+
+/// The interval to send watchdog keepalives at, half the `WATCHDOG_USEC` timeout.
+fn watchdog_interval() -> Option<Duration> {
+	if let Ok(pid) = env::var("WATCHDOG_PID") {
+		if pid.parse::<u32>().ok() != Some(std::process::id()) {
+			return None
+		}
+	}
+	let timeout = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+	Some(Duration::from_micros(timeout / 2))
+}
+
+/// Tracks whether blocks are being imported.
+///
+/// The import is considered stalled if blocks are queued, but the best block didn't change
+/// for `IMPORT_STALL_TIMEOUT`. A node that is merely idle, e.g. because the chain stalled, is
+/// healthy.
+struct ImportHealth<N> {
+	best: N,
+	updated: Instant,
+}
+
+impl<N: PartialEq> ImportHealth<N> {
+	fn new(best: N, now: Instant) -> Self {
+		ImportHealth { best, updated: now }
+	}
+
+	fn is_healthy(&mut self, best: N, queued_blocks: u32, now: Instant) -> bool {
+		if best != self.best || queued_blocks == 0 {
+			self.best = best;
+			self.updated = now;
+		}
+		now.saturating_duration_since(self.updated) < IMPORT_STALL_TIMEOUT
+	}
+}
+
+/// Send a notification to systemd, if the node is supervised by it.
+fn notify(state: &str) {
+	let socket = match env::var_os("NOTIFY_SOCKET") {
+		Some(socket) => socket,
+		None => return,
+	};
+	if let Err(e) = notify_socket(&socket, state) {
+		debug!("Failed to notify systemd of {}: {}", state, e);
+	}
+}
+
+#[cfg(unix)]
+fn notify_socket(socket: &std::ffi::OsStr, state: &str) -> std::io::Result<()> {
+	use std::os::unix::{ffi::OsStrExt, net::UnixDatagram};
+
+	if socket.as_bytes().starts_with(b"@") {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::Other,
+			"abstract notification sockets are not supported",
+		))
+	}
+	UnixDatagram::unbound()?.send_to(state.as_bytes(), socket).map(drop)
+}
+
+#[cfg(not(unix))]
+fn notify_socket(_: &std::ffi::OsStr, _: &str) -> std::io::Result<()> {
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn import_stalls_only_with_queued_blocks() {
+		let start = Instant::now();
+		let later = start + IMPORT_STALL_TIMEOUT;
+		let mut health = ImportHealth::new(1, start);
+
+		// idle, nothing to import.
+		assert!(health.is_healthy(1, 0, later));
+		// blocks are queued, but the best block doesn't move.
+		assert!(health.is_healthy(1, 5, later + Duration::from_secs(1)));
+		assert!(!health.is_healthy(1, 5, later + IMPORT_STALL_TIMEOUT));
+		// the import made progress.
+		assert!(health.is_healthy(2, 5, later + IMPORT_STALL_TIMEOUT));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn notifications_are_sent_to_the_socket() {
+		use std::os::unix::net::UnixDatagram;
+
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("notify");
+		let listener = UnixDatagram::bind(&path).unwrap();
+
+		notify_socket(path.as_os_str(), "READY=1").unwrap();
+
+		let mut buf = [0; 16];
+		let len = listener.recv(&mut buf).unwrap();
+		assert_eq!(&buf[..len], b"READY=1");
+	}
+}
@@ -512,6 +512,8 @@ impl<C, P, T> AuraVerifier<C, P, T>
 		).map_err(Error::Client)?;
 
 		if !inherent_res.ok() {
+			// Collect the errors not handled here, so all of them are reported at once.
+			let mut undecoded = Vec::new();
 			inherent_res
 				.into_errors()
 				.try_for_each(|(i, e)| match TIError::try_from(&i, &e) {
@@ -535,10 +537,17 @@ impl<C, P, T> AuraVerifier<C, P, T>
 						Ok(())
 					},
 					Some(TIError::Other(e)) => Err(Error::Runtime(e.into())),
-					None => Err(Error::DataProvider(
-						self.inherent_data_providers.error_to_string(&i, &e)
-					)),
-				})
+					None => {
+						undecoded.push((i, e));
+						Ok(())
+					},
+				})?;
+
+			if undecoded.is_empty() {
+				Ok(())
+			} else {
+				Err(Error::DataProvider(self.inherent_data_providers.errors_to_string(undecoded)))
+			}
 		} else {
 			Ok(())
 		}
@@ -0,0 +1,140 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC api for BABE.
+//!
+//! Lets validators check which slots their keys will claim without waiting for them, e.g. to
+//! verify that the keys in the keystore are the session keys of the chain, or to pick a time for
+//! maintenance.
+
+#![warn(missing_docs)]
+
+use std::{collections::HashMap, sync::Arc};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
+use sc_consensus_babe::{AuthorityId, AuthorityPair, BabeLink, SlotNumber};
+use sc_consensus_slots::{Clock, SystemClock};
+use sc_keystore::KeyStorePtr;
+use sp_blockchain::{Error as ClientError, HeaderBackend, HeaderMetadata};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use self::gen_client::Client as BabeClient;
+
+/// The most slots previewed by a call, as every slot costs a VRF evaluation per local key.
+pub const MAX_PREVIEWED_SLOTS: u64 = 1200;
+
+/// BABE RPC methods.
+///
+/// The primary slots of a validator are only known to it until it claims them, so the preview is
+/// unsafe to expose publicly: it would tell an attacker when to target the validator.
+#[rpc]
+pub trait BabeApi {
+	/// The slots of the current epoch, from the current slot on, that the keys of the local
+	/// keystore can claim on top of the best block. At most `max_slots` slots are previewed,
+	/// and never more than `MAX_PREVIEWED_SLOTS`.
+	#[rpc(name = "babe_previewSlotClaims")]
+	fn preview_slot_claims(&self, max_slots: Option<u64>) -> Result<SlotClaimsPreview>;
+}
+
+/// The slots claimed by a key, in increasing order.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotClaims {
+	/// Slots claimed through the VRF.
+	pub primary: Vec<SlotNumber>,
+	/// Slots claimed as the secondary author, when no primary author claims them.
+	pub secondary: Vec<SlotNumber>,
+}
+
+/// The slot claims of the local keys over a range of slots of the current epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotClaimsPreview {
+	/// Index of the current epoch.
+	pub epoch_index: u64,
+	/// The current slot, the first one previewed.
+	pub current_slot: SlotNumber,
+	/// The first slot not previewed, at most the first slot of the next epoch.
+	pub end_slot: SlotNumber,
+	/// The claims of each local key which is an authority of the epoch, including those claiming
+	/// no slot.
+	pub claims: HashMap<AuthorityId, SlotClaims>,
+}
+
+/// Implements the [`BabeApi`] RPC trait.
+pub struct BabeRpcHandler<C, B: BlockT> {
+	client: Arc<C>,
+	babe_link: BabeLink<B>,
+	keystore: KeyStorePtr,
+}
+
+impl<C, B: BlockT> BabeRpcHandler<C, B> {
+	/// Create a new `BabeRpcHandler`, `babe_link` being the one of the import queue.
+	pub fn new(client: Arc<C>, babe_link: BabeLink<B>, keystore: KeyStorePtr) -> Self {
+		BabeRpcHandler { client, babe_link, keystore }
+	}
+}
+
+fn client_error(e: ClientError) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(1),
+		message: "Unable to preview slot claims.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, B> BabeApi for BabeRpcHandler<C, B> where
+	B: BlockT,
+	C: HeaderBackend<B> + HeaderMetadata<B, Error = ClientError> + Send + Sync + 'static,
+{
+	fn preview_slot_claims(&self, max_slots: Option<u64>) -> Result<SlotClaimsPreview> {
+		let best_hash = self.client.info().best_hash;
+		let best = self.client.header(BlockId::Hash(best_hash))
+			.map_err(client_error)?
+			.ok_or_else(|| client_error(ClientError::UnknownBlock(format!("{}", best_hash))))?;
+
+		let slot_duration = self.babe_link.config().slot_duration;
+		let current_slot = SystemClock.now().as_millis() as u64 / slot_duration;
+		let epoch = self.babe_link.epoch_for_child_of(&*self.client, &best, current_slot)
+			.map_err(client_error)?;
+
+		let keystore = self.keystore.read();
+		let mut claims: HashMap<_, _> = epoch.authorities.iter()
+			.filter(|(id, _)| keystore.key_pair::<AuthorityPair>(id).is_ok())
+			.map(|(id, _)| (id.clone(), SlotClaims::default()))
+			.collect();
+		drop(keystore);
+
+		let slots = max_slots.unwrap_or(MAX_PREVIEWED_SLOTS).min(MAX_PREVIEWED_SLOTS);
+		let end_slot = current_slot.saturating_add(slots).min(epoch.end_slot());
+		for claim in self.babe_link.claimable_slots(&epoch, current_slot, end_slot, &self.keystore) {
+			let slots = claims.entry(claim.authority).or_default();
+			if claim.primary {
+				slots.primary.push(claim.slot_number);
+			} else {
+				slots.secondary.push(claim.slot_number);
+			}
+		}
+
+		Ok(SlotClaimsPreview {
+			epoch_index: epoch.epoch_index,
+			current_slot,
+			end_slot,
+			claims,
+		})
+	}
+}
@@ -161,6 +161,29 @@ pub(super) fn claim_slot(
 		})
 }
 
+/// Claims the slots of `epoch` from `from_slot` on, the way the slot worker would claim them when
+/// they come. Returns the claimed slots along with the claiming key and whether the claim is a
+/// primary one.
+pub(super) fn claimable_slots(
+	from_slot: SlotNumber,
+	to_slot: SlotNumber,
+	epoch: &Epoch,
+	config: &BabeConfiguration,
+	keystore: &KeyStorePtr,
+) -> Vec<(SlotNumber, AuthorityId, bool)> {
+	(from_slot.max(epoch.start_slot)..to_slot.min(epoch.end_slot()))
+		.filter_map(|slot_number| {
+			claim_slot(slot_number, epoch, config, keystore).map(|(pre_digest, pair)| {
+				let primary = match pre_digest {
+					BabePreDigest::Primary { .. } => true,
+					BabePreDigest::Secondary { .. } => false,
+				};
+				(slot_number, pair.public(), primary)
+			})
+		})
+		.collect()
+}
+
 fn get_keypair(q: &AuthorityPair) -> &schnorrkel::Keypair {
 	use sp_core::crypto::IsWrappedBy;
 	sp_core::sr25519::Pair::from_ref(q).as_ref()
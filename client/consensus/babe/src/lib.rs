@@ -71,9 +71,9 @@ use sp_consensus::import_queue::{
 };
 use sp_runtime::{
 	generic::{BlockId, OpaqueDigestItemId}, Justification,
-	traits::{Block as BlockT, Header, DigestItemFor, Zero},
+	traits::{Block as BlockT, Header, DigestItemFor, NumberFor, Zero},
 };
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ProvideRuntimeApi, ApiRef};
 use sc_keystore::KeyStorePtr;
 use parking_lot::Mutex;
 use sp_core::Pair;
@@ -100,6 +100,7 @@ use futures::prelude::*;
 use log::{warn, debug, info, trace};
 use sc_consensus_slots::{
 	SlotWorker, SlotInfo, SlotCompatible, StorageChanges, CheckedHeader, check_equivocation,
+	Clock, SystemClock, clock::TimestampInherentDataProvider,
 };
 use epoch_changes::descendent_query;
 use sp_blockchain::{
@@ -211,7 +212,17 @@ impl Config {
 		C: AuxStore + ProvideRuntimeApi<B>, C::Api: BabeApi<B, Error = sp_blockchain::Error>,
 	{
 		trace!(target: "babe", "Getting slot duration");
-		match sc_consensus_slots::SlotDuration::get_or_compute(client, |a, b| a.configuration(b)).map(Self) {
+		let compute = |api: ApiRef<C::Api>, at: &BlockId<B>| {
+			if api.has_api_with::<dyn BabeApi<B, Error = sp_blockchain::Error>, _>(at, |v| v < 2)? {
+				// Run compatibility fallback for v1, which predates secondary slots.
+				#[allow(deprecated)]
+				api.configuration_before_version_2(at).map(Into::into)
+			} else {
+				api.configuration(at)
+			}
+		};
+
+		match sc_consensus_slots::SlotDuration::get_or_compute(client, compute).map(Self) {
 			Ok(s) => Ok(s),
 			Err(s) => {
 				warn!(target: "babe", "Failed to get slot duration");
@@ -220,6 +231,21 @@ impl Config {
 		}
 	}
 
+	/// Ensure that the epochs last `epoch_length` slots, if the chain spec expects a length.
+	///
+	/// The epoch length is set by the runtime, a chain spec that disagrees with it is
+	/// for another chain.
+	pub fn ensure_epoch_length(self, epoch_length: Option<u64>) -> ClientResult<Self> {
+		match epoch_length {
+			Some(epoch_length) if epoch_length != self.epoch_length => Err(ClientError::Msg(format!(
+				"Epochs last {} slots, the chain spec expects {}",
+				self.epoch_length,
+				epoch_length,
+			))),
+			_ => Ok(self),
+		}
+	}
+
 	/// Create the genesis epoch (epoch #0). This is defined to start at the slot of
 	/// the first block, so that has to be provided.
 	pub fn genesis_epoch(&self, slot_number: SlotNumber) -> Epoch {
@@ -274,6 +300,11 @@ pub struct BabeParams<B: BlockT, C, E, I, SO, SC, CAW> {
 
 	/// Checks if the current native implementation can author with a runtime at a given block.
 	pub can_author_with: CAW,
+
+	/// The clock driving the slots, the system clock if `None`. Unless the inherent data
+	/// providers already have one, the timestamp inherent is read from this clock too, so that
+	/// the epochs, and the sessions and eras of the runtime, follow it.
+	pub clock: Option<Arc<dyn Clock>>,
 }
 
 /// Start the babe worker. The returned future should be run in a tokio runtime.
@@ -288,6 +319,7 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 	force_authoring,
 	babe_link,
 	can_author_with,
+	clock,
 }: BabeParams<B, C, E, I, SO, SC, CAW>) -> Result<
 	impl futures::Future<Output=()>,
 	sp_consensus::Error,
@@ -317,6 +349,12 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 		config: config.clone(),
 	};
 
+	let clock = clock.unwrap_or_else(|| Arc::new(SystemClock));
+	if !inherent_data_providers.has_provider(&sp_timestamp::INHERENT_IDENTIFIER) {
+		inherent_data_providers
+			.register_provider(TimestampInherentDataProvider(clock.clone()))
+			.map_err(sp_consensus::Error::InherentData)?;
+	}
 	register_babe_inherent_data_provider(&inherent_data_providers, config.slot_duration())?;
 	sc_consensus_uncles::register_uncles_inherent_data_provider(
 		client.clone(),
@@ -325,7 +363,7 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 	)?;
 
 	babe_info!("Starting BABE Authorship worker");
-	Ok(sc_consensus_slots::start_slot_worker(
+	Ok(sc_consensus_slots::start_slot_worker_with_clock(
 		config.0,
 		select_chain,
 		worker,
@@ -333,6 +371,7 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 		inherent_data_providers,
 		babe_link.time_source,
 		can_author_with,
+		clock,
 	))
 }
 
@@ -426,6 +465,8 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 	) -> Vec<sp_runtime::DigestItem<B::Hash>> {
 		vec![
 			<DigestItemFor<B> as CompatibleDigestItem>::babe_pre_digest(claim.0.clone()),
+			// the best block finalized by an imported justification, for the finality tracker.
+			sp_finality_tracker::finality_hint_digest(self.client.info().finalized_number),
 		]
 	}
 
@@ -601,7 +642,76 @@ pub struct BabeLink<Block: BlockT> {
 	time_source: TimeSource,
 	epoch_changes: SharedEpochChanges<Block>,
 	config: Config,
+	trusted_checkpoint: Option<(NumberFor<Block>, Block::Hash)>,
+}
+
+impl<Block: BlockT> BabeLink<Block> {
+	/// Trust the ancestors of the given block (e.g. a trusted finalized
+	/// checkpoint), skipping verification of their seals and inherents on
+	/// import.
+	///
+	/// Ancestry can't be known before the checkpoint is imported, so all
+	/// headers up to its number are trusted until then and none but the
+	/// checkpoint itself afterwards.
+	pub fn trust_ancestors_of(mut self, number: NumberFor<Block>, hash: Block::Hash) -> Self {
+		self.trusted_checkpoint = Some((number, hash));
+		self
+	}
+
+	/// The BABE configuration.
+	pub fn config(&self) -> &Config {
+		&self.config
+	}
+
+	/// The epoch a block authored on top of `parent` at `slot_number` belongs to.
+	pub fn epoch_for_child_of<C>(
+		&self,
+		client: &C,
+		parent: &Block::Header,
+		slot_number: SlotNumber,
+	) -> ClientResult<Epoch> where
+		C: HeaderBackend<Block> + HeaderMetadata<Block, Error = ClientError>,
+	{
+		self.epoch_changes.lock().epoch_for_child_of(
+			descendent_query(client),
+			&parent.hash(),
+			parent.number().clone(),
+			slot_number,
+			|slot| self.config.genesis_epoch(slot),
+		)
+			.map_err(|e| ClientError::Backend(format!("Could not fetch epoch: {:?}", e)))?
+			.map(|e| e.into_inner())
+			.ok_or_else(|| ClientError::Backend(format!("No epoch found for a child of {}", parent.hash())))
+	}
+
+	/// The slots of `epoch` from `from_slot` up to, excluding, `to_slot` that the keys of
+	/// `keystore` can claim, as the authoring worker would claim them. Nothing is signed but the
+	/// VRF outputs, one per slot and key.
+	pub fn claimable_slots(
+		&self,
+		epoch: &Epoch,
+		from_slot: SlotNumber,
+		to_slot: SlotNumber,
+		keystore: &KeyStorePtr,
+	) -> Vec<SlotClaim> {
+		authorship::claimable_slots(from_slot, to_slot, epoch, &self.config, keystore)
+			.into_iter()
+			.map(|(slot_number, authority, primary)| SlotClaim { slot_number, authority, primary })
+			.collect()
+	}
 }
+
+/// A slot the keys of the local keystore can claim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotClaim {
+	/// The claimed slot.
+	pub slot_number: SlotNumber,
+	/// The key claiming the slot.
+	pub authority: AuthorityId,
+	/// Whether the claim is a primary, VRF based, one rather than a secondary one.
+	pub primary: bool,
+}
+
 /// A verifier for Babe blocks.
 pub struct BabeVerifier<B, E, Block: BlockT, RA, PRA> {
 	client: Arc<Client<B, E, Block, RA>>,
@@ -610,6 +720,7 @@ pub struct BabeVerifier<B, E, Block: BlockT, RA, PRA> {
 	config: Config,
 	epoch_changes: SharedEpochChanges<Block>,
 	time_source: TimeSource,
+	trusted_checkpoint: Option<(NumberFor<Block>, Block::Hash)>,
 }
 
 impl<B, E, Block: BlockT, RA, PRA> BabeVerifier<B, E, Block, RA, PRA> {
@@ -630,11 +741,7 @@ impl<B, E, Block: BlockT, RA, PRA> BabeVerifier<B, E, Block, RA, PRA> {
 		).map_err(Error::Client)?;
 
 		if !inherent_res.ok() {
-			inherent_res
-				.into_errors()
-				.try_for_each(|(i, e)| {
-					Err(Error::CheckInherents(self.inherent_data_providers.error_to_string(&i, &e)))
-				})
+			Err(Error::CheckInherents(self.inherent_data_providers.check_result_to_string(inherent_res)))
 		} else {
 			Ok(())
 		}
@@ -738,6 +845,15 @@ impl<B, E, Block, RA, PRA> Verifier<Block> for BabeVerifier<B, E, Block, RA, PRA
 				.ok_or_else(|| Error::<Block>::FetchEpoch(parent_hash))?
 		};
 
+		// headers up to a trusted checkpoint are only checked for consistency,
+		// unless the checkpoint is already imported: they are on another fork then.
+		let trusted = match self.trusted_checkpoint {
+			Some((number, checkpoint)) if *header.number() <= number => hash == checkpoint ||
+				self.client.status(BlockId::Hash(checkpoint))
+					.map_err(Error::<Block>::Client)? == sp_blockchain::BlockStatus::Unknown,
+			_ => false,
+		};
+
 		// We add one to the current slot to allow for some small drift.
 		// FIXME #1019 in the future, alter this queue to allow deferring of headers
 		let v_params = verification::VerificationParams {
@@ -746,6 +862,7 @@ impl<B, E, Block, RA, PRA> Verifier<Block> for BabeVerifier<B, E, Block, RA, PRA
 			slot_now: slot_now + 1,
 			epoch: epoch.as_ref(),
 			config: &self.config,
+			trusted,
 		};
 
 		match verification::check_header::<Block>(v_params)? {
@@ -758,14 +875,20 @@ impl<B, E, Block, RA, PRA> Verifier<Block> for BabeVerifier<B, E, Block, RA, PRA
 				let author = verified_info.author;
 
 				// the header is valid but let's check if there was something else already
-				// proposed at the same slot by the given author
-				if let Some(equivocation_proof) = check_equivocation(
-					&*self.api,
-					slot_now,
-					babe_pre_digest.slot_number(),
-					&header,
-					&author,
-				).map_err(|e| e.to_string())? {
+				// proposed at the same slot by the given author (irrelevant for trusted
+				// headers)
+				let equivocation_proof = if trusted {
+					None
+				} else {
+					check_equivocation(
+						&*self.api,
+						slot_now,
+						babe_pre_digest.slot_number(),
+						&header,
+						&author,
+					).map_err(|e| e.to_string())?
+				};
+				if let Some(equivocation_proof) = equivocation_proof {
 					info!(
 						"Slot author {:?} is equivocating at slot {} with headers {:?} and {:?}",
 						author,
@@ -782,11 +905,13 @@ impl<B, E, Block, RA, PRA> Verifier<Block> for BabeVerifier<B, E, Block, RA, PRA
 					inherent_data.babe_replace_inherent_data(slot_number);
 					let block = Block::new(pre_header.clone(), inner_body);
 
-					self.check_inherents(
-						block.clone(),
-						BlockId::Hash(parent_hash),
-						inherent_data,
-					)?;
+					if !trusted {
+						self.check_inherents(
+							block.clone(),
+							BlockId::Hash(parent_hash),
+							inherent_data,
+						)?;
+					}
 
 					let (_, inner_body) = block.deconstruct();
 					body = Some(inner_body);
@@ -1170,6 +1295,7 @@ pub fn block_import<B, E, Block: BlockT, I, RA, PRA>(
 		epoch_changes: epoch_changes.clone(),
 		time_source: Default::default(),
 		config: config.clone(),
+		trusted_checkpoint: None,
 	};
 
 	// NOTE: this isn't entirely necessary, but since we didn't use to prune the
@@ -1226,6 +1352,7 @@ pub fn import_queue<B, E, Block: BlockT, I, RA, PRA>(
 		config: babe_link.config,
 		epoch_changes: babe_link.epoch_changes,
 		time_source: babe_link.time_source,
+		trusted_checkpoint: babe_link.trusted_checkpoint,
 	};
 
 	Ok(BasicQueue::new(
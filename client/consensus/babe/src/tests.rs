@@ -311,6 +311,7 @@ impl TestNetFactory for BabeTestNet {
 				config: data.link.config.clone(),
 				epoch_changes: data.link.epoch_changes.clone(),
 				time_source: data.link.time_source.clone(),
+				trusted_checkpoint: data.link.trusted_checkpoint,
 			},
 			mutator: MUTATOR.with(|m| m.borrow().clone()),
 		}
@@ -420,6 +421,7 @@ fn run_one_test(
 			babe_link: data.link.clone(),
 			keystore,
 			can_author_with: sp_consensus::AlwaysCanAuthor,
+			clock: None,
 		}).expect("Starts babe").unit_error().compat());
 	}
 
@@ -541,6 +543,59 @@ fn can_author_block() {
 	}
 }
 
+#[test]
+fn previews_claimable_slots() {
+	let keystore_path = tempfile::tempdir().expect("Creates keystore path");
+	let keystore = sc_keystore::Store::open(keystore_path.path(), None).expect("Creates keystore");
+	let pair = keystore.write().insert_ephemeral_from_seed::<AuthorityPair>("//Alice")
+		.expect("Generates authority pair");
+
+	let epoch = Epoch {
+		start_slot: 100,
+		authorities: vec![(pair.public(), 1)],
+		randomness: [0; 32],
+		epoch_index: 1,
+		duration: 100,
+	};
+
+	let mut config = crate::BabeConfiguration {
+		slot_duration: 1000,
+		epoch_length: 100,
+		c: (3, 10),
+		genesis_authorities: Vec::new(),
+		randomness: [0; 32],
+		secondary_slots: true,
+	};
+
+	// the only authority claims every slot left in the epoch, as a secondary slot when the
+	// primary claim fails.
+	let claims = authorship::claimable_slots(150, u64::max_value(), &epoch, &config, &keystore);
+	assert_eq!(claims.iter().map(|c| c.0).collect::<Vec<_>>(), (150..200).collect::<Vec<_>>());
+	assert!(claims.iter().all(|c| c.1 == pair.public()));
+	for (slot, _, primary) in &claims {
+		let (pre_digest, _) = claim_slot(*slot, &epoch, &config, &keystore).unwrap();
+		assert_eq!(*primary, pre_digest.added_weight() == 1);
+	}
+
+	// the range is bounded.
+	let bounded = authorship::claimable_slots(150, 160, &epoch, &config, &keystore);
+	assert_eq!(bounded, claims[..10].to_vec());
+
+	config.secondary_slots = false;
+	let primary = authorship::claimable_slots(0, u64::max_value(), &epoch, &config, &keystore);
+	assert!(primary.iter().all(|c| c.2 && c.0 >= 100));
+	assert_eq!(
+		primary.iter().map(|c| c.0).filter(|s| *s >= 150).collect::<Vec<_>>(),
+		claims.iter().filter(|c| c.2).map(|c| c.0).collect::<Vec<_>>(),
+	);
+
+	// keys which are not authorities of the epoch claim nothing.
+	let other_path = tempfile::tempdir().expect("Creates keystore path");
+	let other = sc_keystore::Store::open(other_path.path(), None).expect("Creates keystore");
+	other.write().insert_ephemeral_from_seed::<AuthorityPair>("//Bob").expect("Generates authority pair");
+	assert!(authorship::claimable_slots(100, 200, &epoch, &config, &other).is_empty());
+}
+
 // Propose and import a new BABE block on top of the given parent.
 fn propose_and_import_block<Transaction>(
 	parent: &TestHeader,
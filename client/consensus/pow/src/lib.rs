@@ -222,6 +222,8 @@ impl<B: BlockT, C, S, Algorithm> PowVerifier<B, C, S, Algorithm> {
 		).map_err(Error::Client)?;
 
 		if !inherent_res.ok() {
+			// Collect the errors not handled here, so all of them are reported at once.
+			let mut undecoded = Vec::new();
 			inherent_res
 				.into_errors()
 				.try_for_each(|(i, e)| match TIError::try_from(&i, &e) {
@@ -233,10 +235,17 @@ impl<B: BlockT, C, S, Algorithm> PowVerifier<B, C, S, Algorithm> {
 						Ok(())
 					},
 					Some(TIError::Other(e)) => Err(Error::Runtime(e)),
-					None => Err(Error::CheckInherents(
-						self.inherent_data_providers.error_to_string(&i, &e)
-					)),
-				})
+					None => {
+						undecoded.push((i, e));
+						Ok(())
+					},
+				})?;
+
+			if undecoded.is_empty() {
+				Ok(())
+			} else {
+				Err(Error::CheckInherents(self.inherent_data_providers.errors_to_string(undecoded)))
+			}
 		} else {
 			Ok(())
 		}
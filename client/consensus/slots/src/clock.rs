@@ -0,0 +1,429 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Sources of time for the slot worker.
+//!
+//! By default slots follow the system clock. A [`VirtualClock`] replaces it with time that only
+//! advances when told so, which makes slot timing reproducible in tests: slot misses or long
+//! gaps between blocks can be simulated without waiting for them. An [`OffsetClock`] follows the
+//! system clock but can be moved forward, to let development chains travel in time.
+
+use std::{
+	collections::BTreeMap, pin::Pin, sync::Arc, task::{Context, Poll, Waker}, time::Duration,
+};
+
+use futures::prelude::*;
+use futures_timer::Delay;
+use parking_lot::Mutex;
+use sp_inherents::{InherentData, InherentIdentifier, ProvideInherentData};
+
+use crate::slots::duration_now;
+
+/// A source of time.
+pub trait Clock: Send + Sync {
+	/// Current duration since the unix epoch.
+	fn now(&self) -> Duration;
+
+	/// A future that resolves after the given duration passed.
+	fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+	fn now(&self) -> Duration {
+		(**self).now()
+	}
+
+	fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+		(**self).delay(duration)
+	}
+}
+
+/// The system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Duration {
+		duration_now()
+	}
+
+	fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+		Box::pin(Delay::new(duration))
+	}
+}
+
+#[derive(Default)]
+struct VirtualClockInner {
+	now: Duration,
+	next_timer: u64,
+	/// Pending timers by deadline, with the waker of the task waiting for them.
+	timers: BTreeMap<(Duration, u64), Option<Waker>>,
+}
+
+/// A clock that only advances when told so.
+///
+/// Timers fire in the order of their deadlines when the clock is advanced past them.
+#[derive(Clone, Default)]
+pub struct VirtualClock(Arc<Mutex<VirtualClockInner>>);
+
+impl VirtualClock {
+	/// Create a clock starting at the given duration since the unix epoch.
+	pub fn new(start: Duration) -> Self {
+		VirtualClock(Arc::new(Mutex::new(VirtualClockInner {
+			now: start,
+			..Default::default()
+		})))
+	}
+
+	/// Advance the clock by the given duration, waking up all timers that expire.
+	pub fn advance(&self, by: Duration) {
+		let now = self.0.lock().now + by;
+		self.set(now);
+	}
+
+	/// Advance the clock to the next pending timer, if any. Returns the new time.
+	pub fn advance_to_next_timer(&self) -> Option<Duration> {
+		let next = self.0.lock().timers.keys().next().map(|(deadline, _)| *deadline)?;
+		self.set(next);
+		Some(next)
+	}
+
+	/// Number of timers not yet expired.
+	pub fn pending_timers(&self) -> usize {
+		self.0.lock().timers.len()
+	}
+
+	fn set(&self, now: Duration) {
+		let wakers = {
+			let mut inner = self.0.lock();
+			if now > inner.now {
+				inner.now = now;
+			}
+			let now = inner.now;
+			inner.timers.iter_mut()
+				.take_while(|((deadline, _), _)| *deadline <= now)
+				.filter_map(|(_, waker)| waker.take())
+				.collect::<Vec<_>>()
+		};
+		// Wake outside of the lock, the woken tasks may poll right away.
+		wakers.into_iter().for_each(Waker::wake);
+	}
+}
+
+impl Clock for VirtualClock {
+	fn now(&self) -> Duration {
+		self.0.lock().now
+	}
+
+	fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+		let mut inner = self.0.lock();
+		let key = (inner.now + duration, inner.next_timer);
+		inner.next_timer += 1;
+		inner.timers.insert(key, None);
+		Box::pin(VirtualDelay { clock: self.clone(), key })
+	}
+}
+
+/// A timer of a `VirtualClock`.
+struct VirtualDelay {
+	clock: VirtualClock,
+	key: (Duration, u64),
+}
+
+impl Future for VirtualDelay {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+		let mut inner = self.clock.0.lock();
+		if inner.now >= self.key.0 {
+			inner.timers.remove(&self.key);
+			Poll::Ready(())
+		} else {
+			inner.timers.insert(self.key, Some(cx.waker().clone()));
+			Poll::Pending
+		}
+	}
+}
+
+impl Drop for VirtualDelay {
+	fn drop(&mut self) {
+		self.clock.0.lock().timers.remove(&self.key);
+	}
+}
+
+#[derive(Default)]
+struct OffsetClockInner {
+	offset: Duration,
+	next_timer: u64,
+	/// Wakers of the pending timers, woken early when the clock moves forward.
+	wakers: BTreeMap<u64, Waker>,
+}
+
+/// The system clock, moved forward by an offset that can only grow.
+///
+/// Timers fire as soon as the clock moves past their deadline, be it because time passed or
+/// because the clock was moved forward.
+#[derive(Clone, Default)]
+pub struct OffsetClock(Arc<Mutex<OffsetClockInner>>);
+
+impl OffsetClock {
+	/// Move the clock forward by the given duration.
+	pub fn advance(&self, by: Duration) {
+		let wakers = {
+			let mut inner = self.0.lock();
+			inner.offset += by;
+			std::mem::replace(&mut inner.wakers, BTreeMap::new())
+		};
+		// Wake outside of the lock, the woken tasks may poll right away.
+		wakers.into_iter().for_each(|(_, waker)| waker.wake());
+	}
+
+	/// Move the clock forward to the given duration since the unix epoch.
+	///
+	/// Returns the current time of the clock as an error if it is already past `now`.
+	pub fn advance_to(&self, now: Duration) -> Result<(), Duration> {
+		let current = self.now();
+		let by = now.checked_sub(current).ok_or(current)?;
+		self.advance(by);
+		Ok(())
+	}
+
+	/// How far the clock is ahead of the system clock.
+	pub fn offset(&self) -> Duration {
+		self.0.lock().offset
+	}
+
+	/// Number of timers not yet expired.
+	pub fn pending_timers(&self) -> usize {
+		self.0.lock().wakers.len()
+	}
+}
+
+impl Clock for OffsetClock {
+	fn now(&self) -> Duration {
+		duration_now() + self.offset()
+	}
+
+	fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+		let id = {
+			let mut inner = self.0.lock();
+			inner.next_timer += 1;
+			inner.next_timer
+		};
+		Box::pin(OffsetDelay {
+			clock: self.clone(),
+			deadline: self.now() + duration,
+			id,
+			timer: Delay::new(duration),
+		})
+	}
+}
+
+/// A timer of an `OffsetClock`.
+struct OffsetDelay {
+	clock: OffsetClock,
+	deadline: Duration,
+	id: u64,
+	/// Fires at the deadline if the clock isn't moved forward in the meantime.
+	timer: Delay,
+}
+
+impl Future for OffsetDelay {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+		loop {
+			let remaining = match self.deadline.checked_sub(self.clock.now()) {
+				Some(remaining) if remaining > Duration::from_millis(0) => remaining,
+				_ => {
+					self.clock.0.lock().wakers.remove(&self.id);
+					return Poll::Ready(())
+				},
+			};
+			self.clock.0.lock().wakers.insert(self.id, cx.waker().clone());
+			match self.timer.poll_unpin(cx) {
+				// The system clock may be slightly behind the timer, wait for what is left.
+				Poll::Ready(()) => self.timer = Delay::new(remaining),
+				Poll::Pending => return Poll::Pending,
+			}
+		}
+	}
+}
+
+impl Drop for OffsetDelay {
+	fn drop(&mut self) {
+		self.clock.0.lock().wakers.remove(&self.id);
+	}
+}
+
+/// Provides the timestamp inherent from a `Clock`, replacing `sp_timestamp::InherentDataProvider`.
+pub struct TimestampInherentDataProvider<C>(pub C);
+
+impl<C: Clock> ProvideInherentData for TimestampInherentDataProvider<C> {
+	fn inherent_identifier(&self) -> &'static InherentIdentifier {
+		&sp_timestamp::INHERENT_IDENTIFIER
+	}
+
+	fn provide_inherent_data(&self, inherent_data: &mut InherentData) -> Result<(), sp_inherents::Error> {
+		let timestamp: sp_timestamp::InherentType = self.0.now().as_millis() as u64;
+		inherent_data.put_data(sp_timestamp::INHERENT_IDENTIFIER, &timestamp)
+	}
+
+	fn error_to_string(&self, error: &[u8]) -> Option<String> {
+		sp_timestamp::InherentDataProvider.error_to_string(error)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{slots::Slots, SlotCompatible};
+	use futures::task::noop_waker;
+	use sp_inherents::InherentDataProviders;
+	use sp_timestamp::TimestampInherentData;
+
+	const SLOT_DURATION: u64 = 1000;
+
+	struct TestSlotCompatible;
+
+	impl SlotCompatible for TestSlotCompatible {
+		fn extract_timestamp_and_slot(
+			&self,
+			data: &InherentData,
+		) -> Result<(u64, u64, Duration), sp_consensus::Error> {
+			let timestamp = data.timestamp_inherent_data()
+				.map_err(sp_consensus::Error::InherentData)?;
+			Ok((timestamp, timestamp / SLOT_DURATION, Default::default()))
+		}
+	}
+
+	#[test]
+	fn virtual_delay_fires_when_clock_advances() {
+		let clock = VirtualClock::new(Duration::from_secs(10));
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+
+		let mut delay = clock.delay(Duration::from_millis(500));
+		assert_eq!(delay.as_mut().poll(&mut cx), Poll::Pending);
+
+		clock.advance(Duration::from_millis(499));
+		assert_eq!(delay.as_mut().poll(&mut cx), Poll::Pending);
+		assert_eq!(clock.pending_timers(), 1);
+
+		assert_eq!(clock.advance_to_next_timer(), Some(Duration::from_millis(10_500)));
+		assert_eq!(delay.as_mut().poll(&mut cx), Poll::Ready(()));
+		assert_eq!(clock.pending_timers(), 0);
+
+		drop(clock.delay(Duration::from_secs(1)));
+		assert_eq!(clock.pending_timers(), 0);
+	}
+
+	#[test]
+	fn offset_delay_fires_when_clock_moves_forward() {
+		let clock = OffsetClock::default();
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+
+		let mut delay = clock.delay(Duration::from_secs(3600));
+		assert_eq!(delay.as_mut().poll(&mut cx), Poll::Pending);
+		assert_eq!(clock.pending_timers(), 1);
+
+		clock.advance(Duration::from_secs(1800));
+		assert_eq!(delay.as_mut().poll(&mut cx), Poll::Pending);
+		assert_eq!(clock.offset(), Duration::from_secs(1800));
+
+		clock.advance(Duration::from_secs(1800));
+		assert_eq!(delay.as_mut().poll(&mut cx), Poll::Ready(()));
+		assert_eq!(clock.pending_timers(), 0);
+
+		let mut delay = clock.delay(Duration::from_secs(3600));
+		assert_eq!(delay.as_mut().poll(&mut cx), Poll::Pending);
+		drop(delay);
+		assert_eq!(clock.pending_timers(), 0);
+	}
+
+	#[test]
+	fn offset_clock_only_moves_forward() {
+		let clock = OffsetClock::default();
+		let target = clock.now() + Duration::from_secs(3600);
+
+		clock.advance_to(target).unwrap();
+		assert!(clock.now() >= target);
+		assert!(clock.offset() <= Duration::from_secs(3600));
+
+		let now = clock.advance_to(target - Duration::from_secs(1)).unwrap_err();
+		assert!(now >= target);
+		assert!(clock.offset() <= Duration::from_secs(3600));
+	}
+
+	#[test]
+	fn slots_follow_virtual_clock() {
+		let clock = VirtualClock::new(Duration::from_millis(100 * SLOT_DURATION));
+		let providers = InherentDataProviders::new();
+		providers.register_provider(TimestampInherentDataProvider(clock.clone())).unwrap();
+		let mut slots = Slots::new(SLOT_DURATION, providers, TestSlotCompatible, Arc::new(clock.clone()));
+
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+		let mut next_slot = || match slots.poll_next_unpin(&mut cx) {
+			Poll::Ready(Some(Ok(info))) => Some((info.last_number, info.number)),
+			Poll::Ready(_) => panic!("slots stream must not end or fail"),
+			Poll::Pending => None,
+		};
+
+		// Nothing happens until time moves forward.
+		assert_eq!(next_slot(), None);
+		assert_eq!(next_slot(), None);
+
+		clock.advance(Duration::from_millis(SLOT_DURATION));
+		assert_eq!(next_slot(), Some((0, 101)));
+		assert_eq!(next_slot(), None);
+
+		clock.advance(Duration::from_millis(SLOT_DURATION));
+		assert_eq!(next_slot(), Some((101, 102)));
+
+		// A long gap skips the slots in between.
+		clock.advance(Duration::from_millis(5 * SLOT_DURATION));
+		assert_eq!(next_slot(), Some((102, 107)));
+		assert_eq!(next_slot(), None);
+	}
+
+	#[test]
+	fn slot_ends_on_virtual_clock() {
+		let clock = VirtualClock::new(Duration::from_millis(100 * SLOT_DURATION));
+		let providers = InherentDataProviders::new();
+		providers.register_provider(TimestampInherentDataProvider(clock.clone())).unwrap();
+		let mut slots = Slots::new(SLOT_DURATION, providers, TestSlotCompatible, Arc::new(clock.clone()));
+
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+		assert!(slots.poll_next_unpin(&mut cx).is_pending());
+		clock.advance(Duration::from_millis(SLOT_DURATION));
+		let info = match slots.poll_next_unpin(&mut cx) {
+			Poll::Ready(Some(Ok(info))) => info,
+			_ => panic!("a slot starts once the clock advanced"),
+		};
+
+		// The end of the slot is measured on the virtual clock, not on the wall clock.
+		assert_eq!(info.ends_at, Duration::from_millis(101 * SLOT_DURATION + SLOT_DURATION - 1));
+		clock.advance(Duration::from_millis(SLOT_DURATION / 2));
+		assert_eq!(info.clock.now(), Duration::from_millis(101 * SLOT_DURATION + SLOT_DURATION / 2));
+		assert_eq!(
+			info.ends_at.checked_sub(info.clock.now()),
+			Some(Duration::from_millis(SLOT_DURATION / 2 - 1)),
+		);
+	}
+}
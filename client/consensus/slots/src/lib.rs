@@ -25,21 +25,22 @@
 
 mod slots;
 mod aux_schema;
+pub mod clock;
 
 pub use slots::{SignedDuration, SlotInfo};
+pub use clock::{Clock, OffsetClock, SystemClock, VirtualClock};
 use slots::Slots;
 pub use aux_schema::{check_equivocation, MAX_SLOT_CAPACITY, PRUNING_BOUND};
 
 use codec::{Decode, Encode};
 use sp_consensus::{BlockImport, Proposer, SyncOracle, SelectChain, CanAuthorWith, SlotData, RecordProof};
 use futures::{prelude::*, future::{self, Either}};
-use futures_timer::Delay;
 use sp_inherents::{InherentData, InherentDataProviders};
 use log::{debug, error, info, warn};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{Block as BlockT, Header, HasherFor, NumberFor};
 use sp_api::{ProvideRuntimeApi, ApiRef};
-use std::{fmt::Debug, ops::Deref, pin::Pin, sync::Arc, time::{Instant, Duration}};
+use std::{fmt::Debug, ops::Deref, pin::Pin, sync::Arc, time::Duration};
 use sc_telemetry::{telemetry, CONSENSUS_DEBUG, CONSENSUS_WARN, CONSENSUS_INFO};
 use parking_lot::Mutex;
 
@@ -137,12 +138,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 
 	/// Remaining duration of the slot.
 	fn slot_remaining_duration(&self, slot_info: &SlotInfo) -> Duration {
-		let now = Instant::now();
-		if now < slot_info.ends_at {
-			slot_info.ends_at.duration_since(now)
-		} else {
-			Duration::from_millis(0)
-		}
+		slot_info.ends_at.checked_sub(slot_info.clock.now()).unwrap_or_default()
 	}
 
 	/// Remaining duration for proposing. None means unlimited.
@@ -164,7 +160,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			(slot_info.timestamp, slot_info.number, slot_info.duration);
 
 		{
-			let slot_now = SignedDuration::default().slot_now(slot_duration);
+			let slot_now = slot_info.clock.now().as_millis() as u64 / slot_duration;
 			if slot_now > slot_number {
 				// if this is behind, return.
 				debug!(target: self.logging_target(),
@@ -234,6 +230,11 @@ pub trait SimpleSlotWorker<B: BlockT> {
 		let proposing_remaining_duration = self.proposing_remaining_duration(&chain_head, &slot_info);
 		let logs = self.pre_digest_data(slot_number, &claim);
 
+		let delay: Pin<Box<dyn Future<Output=()> + Send>> = match proposing_remaining_duration {
+			Some(r) => slot_info.clock.delay(r),
+			None => Box::pin(future::pending()),
+		};
+
 		// deadline our production to approx. the end of the slot
 		let proposing = awaiting_proposer.and_then(move |mut proposer| proposer.propose(
 			slot_info.inherent_data,
@@ -244,11 +245,6 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			RecordProof::No,
 		).map_err(|e| sp_consensus::Error::ClientImport(format!("{:?}", e))));
 
-		let delay: Box<dyn Future<Output=()> + Unpin + Send> = match proposing_remaining_duration {
-			Some(r) => Box::new(Delay::new(r)),
-			None => Box::new(future::pending()),
-		};
-
 		let proposal_work =
 			Box::new(futures::future::select(proposing, delay).map(move |v| match v {
 				futures::future::Either::Left((b, _)) => b.map(|b| (b, claim)),
@@ -328,6 +324,43 @@ pub trait SlotCompatible {
 /// Every time a new slot is triggered, `worker.on_slot` is called and the future it returns is
 /// polled until completion, unless we are major syncing.
 pub fn start_slot_worker<B, C, W, T, SO, SC, CAW>(
+	slot_duration: SlotDuration<T>,
+	client: C,
+	worker: W,
+	sync_oracle: SO,
+	inherent_data_providers: InherentDataProviders,
+	timestamp_extractor: SC,
+	can_author_with: CAW,
+) -> impl Future<Output = ()>
+where
+	B: BlockT,
+	C: SelectChain<B>,
+	W: SlotWorker<B>,
+	W::OnSlot: Unpin,
+	SO: SyncOracle + Send,
+	SC: SlotCompatible + Unpin,
+	T: SlotData + Clone,
+	CAW: CanAuthorWith<B> + Send,
+{
+	start_slot_worker_with_clock(
+		slot_duration,
+		client,
+		worker,
+		sync_oracle,
+		inherent_data_providers,
+		timestamp_extractor,
+		can_author_with,
+		Arc::new(SystemClock),
+	)
+}
+
+/// Start a new slot worker driven by the given clock.
+///
+/// Same as [`start_slot_worker`], but slots are scheduled according to `clock`. Passing a
+/// [`VirtualClock`] allows simulating slot timing deterministically. The inherent data providers
+/// should then read the time from the same clock, see
+/// [`clock::TimestampInherentDataProvider`].
+pub fn start_slot_worker_with_clock<B, C, W, T, SO, SC, CAW>(
 	slot_duration: SlotDuration<T>,
 	client: C,
 	mut worker: W,
@@ -335,6 +368,7 @@ pub fn start_slot_worker<B, C, W, T, SO, SC, CAW>(
 	inherent_data_providers: InherentDataProviders,
 	timestamp_extractor: SC,
 	can_author_with: CAW,
+	clock: Arc<dyn Clock>,
 ) -> impl Future<Output = ()>
 where
 	B: BlockT,
@@ -353,6 +387,7 @@ where
 		slot_duration.slot_duration(),
 		inherent_data_providers,
 		timestamp_extractor,
+		clock,
 	).inspect_err(|e| debug!(target: "slots", "Faulty timer: {:?}", e))
 		.try_for_each(move |slot_info| {
 			// only propose when we are not syncing.
@@ -18,13 +18,12 @@
 //!
 //! This is used instead of `futures_timer::Interval` because it was unreliable.
 
-use super::SlotCompatible;
+use super::{Clock, SlotCompatible};
 use sp_consensus::Error;
 use futures::{prelude::*, task::Context, task::Poll};
 use sp_inherents::{InherentData, InherentDataProviders};
 
-use std::{pin::Pin, time::{Duration, Instant}};
-use futures_timer::Delay;
+use std::{pin::Pin, sync::Arc, time::Duration};
 
 /// Returns current duration since unix epoch.
 pub fn duration_now() -> Duration {
@@ -75,21 +74,24 @@ pub struct SlotInfo {
 	pub last_number: u64,
 	/// Current timestamp.
 	pub timestamp: u64,
-	/// The instant at which the slot ends.
-	pub ends_at: Instant,
+	/// The time at which the slot ends, as a duration since the unix epoch on `clock`.
+	pub ends_at: Duration,
 	/// The inherent data.
 	pub inherent_data: InherentData,
 	/// Slot duration.
 	pub duration: u64,
+	/// The clock driving the slots.
+	pub clock: Arc<dyn Clock>,
 }
 
 /// A stream that returns every time there is a new slot.
 pub(crate) struct Slots<SC> {
 	last_slot: u64,
 	slot_duration: u64,
-	inner_delay: Option<Delay>,
+	inner_delay: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
 	inherent_data_providers: InherentDataProviders,
 	timestamp_extractor: SC,
+	clock: Arc<dyn Clock>,
 }
 
 impl<SC> Slots<SC> {
@@ -98,6 +100,7 @@ impl<SC> Slots<SC> {
 		slot_duration: u64,
 		inherent_data_providers: InherentDataProviders,
 		timestamp_extractor: SC,
+		clock: Arc<dyn Clock>,
 	) -> Self {
 		Slots {
 			last_slot: 0,
@@ -105,6 +108,7 @@ impl<SC> Slots<SC> {
 			inner_delay: None,
 			inherent_data_providers,
 			timestamp_extractor,
+			clock,
 		}
 	}
 }
@@ -118,14 +122,14 @@ impl<SC: SlotCompatible + Unpin> Stream for Slots<SC> {
 			self.inner_delay = match self.inner_delay.take() {
 				None => {
 					// schedule wait.
-					let wait_dur = time_until_next(duration_now(), slot_duration);
-					Some(Delay::new(wait_dur))
+					let wait_dur = time_until_next(self.clock.now(), slot_duration);
+					Some(self.clock.delay(wait_dur))
 				}
 				Some(d) => Some(d),
 			};
 
 			if let Some(ref mut inner_delay) = self.inner_delay {
-				match Future::poll(Pin::new(inner_delay), cx) {
+				match Future::poll(inner_delay.as_mut(), cx) {
 					Poll::Pending => return Poll::Pending,
 					Poll::Ready(()) => {}
 				}
@@ -145,8 +149,8 @@ impl<SC: SlotCompatible + Unpin> Stream for Slots<SC> {
 			// reschedule delay for next slot.
 			let ends_in = offset +
 				time_until_next(Duration::from_millis(timestamp), slot_duration);
-			let ends_at = Instant::now() + ends_in;
-			self.inner_delay = Some(Delay::new(ends_in));
+			let ends_at = self.clock.now() + ends_in;
+			self.inner_delay = Some(self.clock.delay(ends_in));
 
 			// never yield the same slot twice.
 			if slot_num > self.last_slot {
@@ -160,6 +164,7 @@ impl<SC: SlotCompatible + Unpin> Stream for Slots<SC> {
 					timestamp,
 					ends_at,
 					inherent_data,
+					clock: self.clock.clone(),
 				})))
 			}
 		}
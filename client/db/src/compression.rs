@@ -0,0 +1,298 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Transparent compression of block bodies and justifications.
+//!
+//! Values are compressed with zstd, using a dictionary trained on the bodies already in the
+//! database. Compressed values are recognized by the zstd frame magic number: a SCALE-encoded
+//! body or justification starting with these four bytes would have to hold an extrinsic of
+//! an invalid version, respectively come from a round beyond 4 billions. Values are read
+//! transparently whether they are compressed or not, so that compression can be enabled and
+//! disabled at any time.
+//!
+//! Once enabled, a background task trains the dictionary as soon as there are enough blocks,
+//! and then keeps compressing the values of all the blocks, including the ones of forks, that
+//! have been stored uncompressed. It holds the import lock while doing so, so that values
+//! written or removed by an import in the meantime are not overwritten.
+
+use std::{borrow::Cow, io::{self, Read, Write}, sync::{Arc, Weak}, thread, time::Duration};
+use codec::{Decode, Encode};
+use kvdb::{KeyValueDB, DBTransaction};
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use sp_blockchain::Result as ClientResult;
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::{Block as BlockT, NumberFor, UniqueSaturatedFrom, UniqueSaturatedInto};
+
+use crate::columns;
+use crate::utils::{self, db_err, meta_keys};
+
+/// Magic number starting every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// Compression level.
+const LEVEL: i32 = 3;
+/// Maximum size of the trained dictionary.
+const DICTIONARY_SIZE: usize = 112 * 1024;
+/// Number of bodies the dictionary is trained on.
+const TRAINING_SAMPLES: u64 = 1024;
+/// Minimum number of bodies to train the dictionary on.
+const MIN_TRAINING_SAMPLES: usize = 16;
+/// Number of block heights compressed per database transaction by the background task.
+const MIGRATION_BATCH: u64 = 256;
+/// Time the background task waits for new blocks once it has compressed all of them.
+const MIGRATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Compresses and decompresses values with the dictionary stored in the database.
+pub(crate) struct Compressor {
+	dictionary: RwLock<Option<Vec<u8>>>,
+}
+
+impl Compressor {
+	/// Load the dictionary, if any, from `db`.
+	pub fn new(db: &dyn KeyValueDB) -> ClientResult<Self> {
+		let dictionary = db.get(columns::META, meta_keys::COMPRESSION_DICTIONARY)
+			.map_err(db_err)?
+			.map(|dictionary| dictionary.to_vec());
+		Ok(Compressor { dictionary: RwLock::new(dictionary) })
+	}
+
+	/// Compress `raw`.
+	///
+	/// The value is returned as is when there is no dictionary yet or when compressing
+	/// doesn't make it smaller.
+	pub fn compress(&self, raw: Vec<u8>) -> Vec<u8> {
+		let dictionary = self.dictionary.read();
+		let dictionary = match dictionary.as_ref() {
+			Some(dictionary) => dictionary,
+			None => return raw,
+		};
+
+		let compressed = zstd::stream::write::Encoder::with_dictionary(Vec::new(), LEVEL, dictionary)
+			.and_then(|mut encoder| encoder.write_all(&raw).and_then(|_| encoder.finish()));
+		match compressed {
+			Ok(compressed) if compressed.len() < raw.len() => compressed,
+			Ok(_) => raw,
+			Err(err) => {
+				warn!(target: "db", "Failed to compress block data: {}", err);
+				raw
+			},
+		}
+	}
+
+	/// Decompress `stored` if it is compressed.
+	pub fn decompress<'a>(&self, stored: &'a [u8]) -> ClientResult<Cow<'a, [u8]>> {
+		if !is_compressed(stored) {
+			return Ok(Cow::Borrowed(stored));
+		}
+
+		let dictionary = self.dictionary.read();
+		let dictionary = dictionary.as_ref().ok_or_else(|| sp_blockchain::Error::Backend(
+			"Compressed block data found without a compression dictionary".into()
+		))?;
+		let mut raw = Vec::new();
+		zstd::stream::read::Decoder::with_dictionary(stored, dictionary)
+			.and_then(|mut decoder| decoder.read_to_end(&mut raw))
+			.map_err(|err| sp_blockchain::Error::Backend(
+				format!("Error decompressing block data: {}", err)
+			))?;
+		Ok(Cow::Owned(raw))
+	}
+
+	fn has_dictionary(&self) -> bool {
+		self.dictionary.read().is_some()
+	}
+}
+
+pub(crate) fn is_compressed(stored: &[u8]) -> bool {
+	stored.starts_with(&ZSTD_MAGIC)
+}
+
+/// Start compressing the bodies and justifications of the stored blocks in the background.
+///
+/// Progress is saved in the database, so that the task resumes where it stopped after a
+/// restart. The task stops once the backend holding `db` is dropped.
+pub(crate) fn spawn_migration<Block: BlockT>(
+	db: &Arc<dyn KeyValueDB>,
+	compressor: &Arc<Compressor>,
+	import_lock: &Arc<RwLock<()>>,
+) {
+	let db = Arc::downgrade(db);
+	let compressor = Arc::downgrade(compressor);
+	let import_lock = Arc::downgrade(import_lock);
+	let spawned = thread::Builder::new()
+		.name("db-compression".into())
+		.spawn(move || {
+			while let Some(result) = migrate_once::<Block>(&db, &compressor, &import_lock) {
+				if let Err(err) = result {
+					warn!(target: "db", "Failed to compress existing blocks: {}", err);
+				}
+				thread::sleep(MIGRATION_INTERVAL);
+			}
+		});
+	if let Err(err) = spawned {
+		warn!(target: "db", "Failed to start compressing existing blocks: {}", err);
+	}
+}
+
+/// Run `migrate` if the backend still exists.
+fn migrate_once<Block: BlockT>(
+	db: &Weak<dyn KeyValueDB>,
+	compressor: &Weak<Compressor>,
+	import_lock: &Weak<RwLock<()>>,
+) -> Option<ClientResult<()>> {
+	let db = db.upgrade()?;
+	let compressor = compressor.upgrade()?;
+	let import_lock = import_lock.upgrade()?;
+	Some(migrate::<Block>(&*db, &compressor, &import_lock))
+}
+
+/// Compress the values of the blocks up to the best block stored uncompressed, training the
+/// dictionary first if needed.
+pub(crate) fn migrate<Block: BlockT>(
+	db: &dyn KeyValueDB,
+	compressor: &Compressor,
+	import_lock: &RwLock<()>,
+) -> ClientResult<()> {
+	if !compressor.has_dictionary() {
+		let dictionary = match train::<Block>(db)? {
+			Some(dictionary) => dictionary,
+			None => {
+				debug!(target: "db", "Not enough blocks to train a compression dictionary yet");
+				return Ok(());
+			},
+		};
+
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::META, meta_keys::COMPRESSION_DICTIONARY, &dictionary);
+		db.write(transaction).map_err(db_err)?;
+		*compressor.dictionary.write() = Some(dictionary);
+		info!(target: "db", "Trained block compression dictionary");
+	}
+
+	let mut number = match db.get(columns::META, meta_keys::COMPRESSION_CURSOR).map_err(db_err)? {
+		Some(cursor) => u64::decode(&mut &cursor[..])
+			.map_err(|err| sp_blockchain::Error::Backend(format!("Invalid compression cursor: {}", err)))?,
+		None => 0,
+	};
+	let start = number;
+
+	loop {
+		// Imports may write and remove values of any height, so none may happen between
+		// reading and writing back a value.
+		let _lock = import_lock.read();
+		let best = utils::read_meta::<Block>(db, columns::META, columns::HEADER)?.best_number;
+		let best: u64 = best.unique_saturated_into();
+		if number > best {
+			break;
+		}
+
+		let mut transaction = DBTransaction::new();
+		let batch_end = std::cmp::min(number + MIGRATION_BATCH, best + 1);
+		while number < batch_end {
+			let prefix = utils::number_index_key(number)?;
+			for column in &[columns::BODY, columns::JUSTIFICATION] {
+				// Values are keyed by number and hash, so this covers the blocks of all forks.
+				for (key, stored) in db.iter_from_prefix(*column, &prefix) {
+					if !key.starts_with(&prefix) { break }
+					if is_compressed(&stored) {
+						continue;
+					}
+					let compressed = compressor.compress(stored.to_vec());
+					if compressed[..] != stored[..] {
+						transaction.put(*column, &key, &compressed);
+					}
+				}
+			}
+			number += 1;
+		}
+
+		transaction.put(columns::META, meta_keys::COMPRESSION_CURSOR, &number.encode());
+		db.write(transaction).map_err(db_err)?;
+	}
+
+	if number > start {
+		info!(target: "db", "Compressed blocks #{}..#{}", start, number);
+	}
+	Ok(())
+}
+
+/// Train a dictionary on bodies spread over the canonical chain.
+fn train<Block: BlockT>(db: &dyn KeyValueDB) -> ClientResult<Option<Vec<u8>>> {
+	let best = utils::read_meta::<Block>(db, columns::META, columns::HEADER)?.best_number;
+	let best: u64 = best.unique_saturated_into();
+	let step = std::cmp::max(1, best / TRAINING_SAMPLES);
+
+	let mut samples = Vec::new();
+	let mut number = 0;
+	while number <= best {
+		if let Some(lookup_key) = lookup_key::<Block>(db, number)? {
+			if let Some(body) = db.get(columns::BODY, &lookup_key).map_err(db_err)? {
+				if !is_compressed(&body) {
+					samples.push(body.to_vec());
+				}
+			}
+		}
+		number += step;
+	}
+
+	// zstd needs a fair amount of samples to train on, and fails on too little data.
+	if samples.len() < MIN_TRAINING_SAMPLES {
+		return Ok(None);
+	}
+	let total_size = samples.iter().map(|sample| sample.len()).sum::<usize>();
+	match zstd::dict::from_samples(&samples, std::cmp::min(DICTIONARY_SIZE, total_size / 10)) {
+		Ok(dictionary) => Ok(Some(dictionary)),
+		Err(err) => {
+			let err: io::Error = err;
+			debug!(target: "db", "Failed to train a compression dictionary: {}", err);
+			Ok(None)
+		},
+	}
+}
+
+fn lookup_key<Block: BlockT>(db: &dyn KeyValueDB, number: u64) -> ClientResult<Option<Vec<u8>>> {
+	utils::block_id_to_lookup_key::<Block>(
+		db,
+		columns::KEY_LOOKUP,
+		BlockId::Number(NumberFor::<Block>::unique_saturated_from(number)),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn values_are_read_back_transparently() {
+		let db = kvdb_memorydb::create(utils::NUM_COLUMNS);
+		let compressor = Compressor::new(&db).unwrap();
+		let value = b"block body block body block body block body".to_vec();
+
+		// without a dictionary values are stored as they are
+		assert_eq!(compressor.compress(value.clone()), value);
+
+		let samples = (0..256u32)
+			.map(|i| [&i.to_le_bytes()[..], &value[..]].concat())
+			.collect::<Vec<_>>();
+		*compressor.dictionary.write() = Some(zstd::dict::from_samples(&samples, 4096).unwrap());
+
+		let compressed = compressor.compress(value.clone());
+		assert!(is_compressed(&compressed));
+		assert!(compressed.len() < value.len());
+		assert_eq!(compressor.decompress(&compressed).unwrap().into_owned(), value);
+		assert_eq!(compressor.decompress(&value).unwrap().into_owned(), value);
+	}
+}
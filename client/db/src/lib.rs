@@ -31,7 +31,9 @@ pub mod offchain;
 
 mod children;
 mod cache;
+mod compression;
 mod storage_cache;
+mod memory_budget;
 mod utils;
 mod stats;
 
@@ -42,7 +44,7 @@ use std::collections::{HashMap, HashSet};
 
 use sc_client_api::{execution_extensions::ExecutionExtensions, ForkBlocks, UsageInfo, MemoryInfo, BadBlocks, IoInfo};
 use sc_client_api::backend::NewBlockState;
-use sc_client_api::backend::{StorageCollection, ChildStorageCollection};
+use sc_client_api::backend::{StorageCollection, ChildStorageCollection, OffchainStorageCollection};
 use sp_blockchain::{
 	Result as ClientResult, Error as ClientError,
 	well_known_cache_keys, HeaderBackend,
@@ -254,6 +256,11 @@ pub struct DatabaseSettings {
 	pub pruning: PruningMode,
 	/// Where to find the database.
 	pub source: DatabaseSettingsSrc,
+	/// Total memory budget of the node in bytes. The state cache is shrunk while the
+	/// process uses more than this.
+	pub memory_budget: Option<usize>,
+	/// Compress block bodies and justifications, including the ones already stored.
+	pub compress_blocks: bool,
 }
 
 /// Where to find the database..
@@ -348,14 +355,17 @@ pub struct BlockchainDb<Block: BlockT> {
 	meta: Arc<RwLock<Meta<NumberFor<Block>, Block::Hash>>>,
 	leaves: RwLock<LeafSet<Block::Hash, NumberFor<Block>>>,
 	header_metadata_cache: HeaderMetadataCache<Block>,
+	compressor: Arc<compression::Compressor>,
 }
 
 impl<Block: BlockT> BlockchainDb<Block> {
 	fn new(db: Arc<dyn KeyValueDB>) -> ClientResult<Self> {
 		let meta = read_meta::<Block>(&*db, columns::META, columns::HEADER)?;
 		let leaves = LeafSet::read_from_db(&*db, columns::META, meta_keys::LEAF_PREFIX)?;
+		let compressor = Arc::new(compression::Compressor::new(&*db)?);
 		Ok(BlockchainDb {
 			db,
+			compressor,
 			leaves: RwLock::new(leaves),
 			meta: Arc::new(RwLock::new(meta)),
 			header_metadata_cache: HeaderMetadataCache::default(),
@@ -434,7 +444,7 @@ impl<Block: BlockT> sc_client::blockchain::HeaderBackend<Block> for BlockchainDb
 impl<Block: BlockT> sc_client::blockchain::Backend<Block> for BlockchainDb<Block> {
 	fn body(&self, id: BlockId<Block>) -> ClientResult<Option<Vec<Block::Extrinsic>>> {
 		match read_db(&*self.db, columns::KEY_LOOKUP, columns::BODY, id)? {
-			Some(body) => match Decode::decode(&mut &body[..]) {
+			Some(body) => match Decode::decode(&mut &self.compressor.decompress(&body)?[..]) {
 				Ok(body) => Ok(Some(body)),
 				Err(err) => return Err(sp_blockchain::Error::Backend(
 					format!("Error decoding body: {}", err)
@@ -446,7 +456,9 @@ impl<Block: BlockT> sc_client::blockchain::Backend<Block> for BlockchainDb<Block
 
 	fn justification(&self, id: BlockId<Block>) -> ClientResult<Option<Justification>> {
 		match read_db(&*self.db, columns::KEY_LOOKUP, columns::JUSTIFICATION, id)? {
-			Some(justification) => match Decode::decode(&mut &justification[..]) {
+			Some(justification) => match Decode::decode(
+				&mut &self.compressor.decompress(&justification)?[..]
+			) {
 				Ok(justification) => Ok(Some(justification)),
 				Err(err) => return Err(sp_blockchain::Error::Backend(
 					format!("Error decoding justification: {}", err)
@@ -514,6 +526,7 @@ pub struct BlockImportOperation<Block: BlockT> {
 	changes_trie_cache_update: Option<ChangesTrieCacheAction<Block::Hash, NumberFor<Block>>>,
 	pending_block: Option<PendingBlock<Block>>,
 	aux_ops: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+	offchain_storage_updates: Vec<(Vec<u8>, Option<Vec<u8>>)>,
 	finalized_blocks: Vec<(BlockId<Block>, Option<Justification>)>,
 	set_head: Option<BlockId<Block>>,
 	commit_state: bool,
@@ -528,6 +541,16 @@ impl<Block: BlockT> BlockImportOperation<Block> {
 			}
 		}
 	}
+
+	fn apply_offchain(&mut self, transaction: &mut DBTransaction) {
+		for (key, maybe_val) in self.offchain_storage_updates.drain(..) {
+			let key: Vec<u8> = sp_offchain::STORAGE_PREFIX.iter().chain(&key).cloned().collect();
+			match maybe_val {
+				Some(val) => transaction.put_vec(columns::OFFCHAIN, &key, val),
+				None => transaction.delete(columns::OFFCHAIN, &key),
+			}
+		}
+	}
 }
 
 impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block> for BlockImportOperation<Block> {
@@ -609,6 +632,14 @@ impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block> for Bloc
 		Ok(())
 	}
 
+	fn update_offchain_storage(
+		&mut self,
+		offchain_update: OffchainStorageCollection,
+	) -> ClientResult<()> {
+		self.offchain_storage_updates = offchain_update;
+		Ok(())
+	}
+
 	fn update_storage(
 		&mut self,
 		update: StorageCollection,
@@ -895,7 +926,9 @@ pub struct Backend<Block: BlockT> {
 	blockchain: BlockchainDb<Block>,
 	canonicalization_delay: u64,
 	shared_cache: SharedCache<Block>,
-	import_lock: RwLock<()>,
+	memory_budget: Option<memory_budget::MemoryBudget>,
+	compress_blocks: bool,
+	import_lock: Arc<RwLock<()>>,
 	is_archive: bool,
 	io_stats: FrozenForDuration<(kvdb::IoStats, StateUsageInfo)>,
 	state_usage: StateUsageStats,
@@ -919,6 +952,8 @@ impl<Block: BlockT> Backend<Block> {
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(keep_blocks),
 			source: DatabaseSettingsSrc::Custom(db),
+			memory_budget: None,
+			compress_blocks: false,
 		};
 
 		Self::new(db_setting, canonicalization_delay).expect("failed to create test-db")
@@ -942,6 +977,10 @@ impl<Block: BlockT> Backend<Block> {
 			state_db,
 		};
 		let offchain_storage = offchain::LocalStorage::new(db.clone());
+		let import_lock = Arc::new(RwLock::new(()));
+		if config.compress_blocks {
+			compression::spawn_migration::<Block>(&db, &blockchain.compressor, &import_lock);
+		}
 		let changes_tries_storage = DbChangesTrieStorage {
 			db,
 			meta,
@@ -964,13 +1003,24 @@ impl<Block: BlockT> Backend<Block> {
 				config.state_cache_size,
 				config.state_cache_child_ratio.unwrap_or(DEFAULT_CHILD_RATIO),
 			),
-			import_lock: Default::default(),
+			memory_budget: config.memory_budget.map(memory_budget::MemoryBudget::new),
+			compress_blocks: config.compress_blocks,
+			import_lock,
 			is_archive: is_archive_pruning,
 			io_stats: FrozenForDuration::new(std::time::Duration::from_secs(1), (kvdb::IoStats::empty(), StateUsageInfo::empty())),
 			state_usage: StateUsageStats::new(),
 		})
 	}
 
+	/// Encode a body or justification for storage, compressing it if enabled.
+	fn encode_block_data(&self, encoded: Vec<u8>) -> Vec<u8> {
+		if self.compress_blocks {
+			self.blockchain.compressor.compress(encoded)
+		} else {
+			encoded
+		}
+	}
+
 	/// Returns in-memory blockchain that contains the same set of blocks as self.
 	#[cfg(feature = "test-helpers")]
 	pub fn as_in_memory(&self) -> InMemoryBackend<Block> {
@@ -1153,7 +1203,7 @@ impl<Block: BlockT> Backend<Block> {
 			transaction.put(
 				columns::JUSTIFICATION,
 				&utils::number_and_hash_to_lookup_key(number, hash)?,
-				&justification.encode(),
+				&self.encode_block_data(justification.encode()),
 			);
 		}
 		Ok((*hash, number, false, true))
@@ -1200,6 +1250,7 @@ impl<Block: BlockT> Backend<Block> {
 		let mut finalization_displaced_leaves = None;
 
 		operation.apply_aux(&mut transaction);
+		operation.apply_offchain(&mut transaction);
 
 		let mut meta_updates = Vec::with_capacity(operation.finalized_blocks.len());
 		let mut last_finalized_hash = self.blockchain.meta.read().finalized_hash;
@@ -1248,10 +1299,14 @@ impl<Block: BlockT> Backend<Block> {
 
 			transaction.put(columns::HEADER, &lookup_key, &pending_block.header.encode());
 			if let Some(body) = &pending_block.body {
-				transaction.put(columns::BODY, &lookup_key, &body.encode());
+				transaction.put(columns::BODY, &lookup_key, &self.encode_block_data(body.encode()));
 			}
 			if let Some(justification) = pending_block.justification {
-				transaction.put(columns::JUSTIFICATION, &lookup_key, &justification.encode());
+				transaction.put(
+					columns::JUSTIFICATION,
+					&lookup_key,
+					&self.encode_block_data(justification.encode()),
+				);
 			}
 
 			if number.is_zero() {
@@ -1387,6 +1442,10 @@ impl<Block: BlockT> Backend<Block> {
 			self.shared_cache.lock().sync(&enacted, &retracted);
 		}
 
+		if let Some(memory_budget) = &self.memory_budget {
+			memory_budget.check(&self.shared_cache);
+		}
+
 		for (hash, number, is_best, is_finalized) in meta_updates {
 			self.blockchain.update_meta(hash, number, is_best, is_finalized);
 		}
@@ -1490,6 +1549,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			changes_trie_updates: MemoryDB::default(),
 			changes_trie_cache_update: None,
 			aux_ops: Vec::new(),
+			offchain_storage_updates: Vec::new(),
 			finalized_blocks: Vec::new(),
 			set_head: None,
 			commit_state: false,
@@ -1734,6 +1794,16 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		}
 	}
 
+	fn pin_block(&self, hash: &Block::Hash) -> ClientResult<()> {
+		self.storage.state_db.pin(hash).map_err(|_|
+			sp_blockchain::Error::UnknownBlock(format!("State already discarded for {:?}", hash))
+		)
+	}
+
+	fn unpin_block(&self, hash: &Block::Hash) {
+		self.storage.state_db.unpin(hash)
+	}
+
 	fn destroy_state(&self, state: Self::State) -> ClientResult<()> {
 		if let Some(hash) = state.cache.parent_hash.clone() {
 			let is_best = self.blockchain.meta.read().best_hash == hash;
@@ -1743,7 +1813,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 	}
 
 	fn get_import_lock(&self) -> &RwLock<()> {
-		&self.import_lock
+		&*self.import_lock
 	}
 }
 
@@ -1870,6 +1940,8 @@ mod tests {
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(1),
 			source: DatabaseSettingsSrc::Custom(backing),
+			memory_budget: None,
+			compress_blocks: false,
 		}, 0).unwrap();
 		assert_eq!(backend.blockchain().info().best_number, 9);
 		for i in 0..10 {
@@ -2553,6 +2625,24 @@ mod tests {
 		assert!(backend.get_aux(b"test").unwrap().is_none());
 	}
 
+	#[test]
+	fn offchain_storage_updates_are_written_on_commit() {
+		use sp_core::offchain::OffchainStorage;
+
+		let backend = Backend::<Block>::new_test(0, 0);
+		let offchain_storage = backend.offchain_storage().unwrap();
+		let update = |key: &[u8], value: Option<&[u8]>| {
+			let mut op = backend.begin_operation().unwrap();
+			op.update_offchain_storage(vec![(key.to_vec(), value.map(|v| v.to_vec()))]).unwrap();
+			backend.commit_operation(op).unwrap();
+		};
+
+		update(b"test", Some(b"hello"));
+		assert_eq!(offchain_storage.get(sp_offchain::STORAGE_PREFIX, b"test"), Some(b"hello".to_vec()));
+		update(b"test", None);
+		assert!(offchain_storage.get(sp_offchain::STORAGE_PREFIX, b"test").is_none());
+	}
+
 	#[test]
 	fn test_finalize_block_with_justification() {
 		use sc_client::blockchain::{Backend as BlockChainBackend};
@@ -2601,4 +2691,56 @@ mod tests {
 			backend.commit_operation(op).unwrap_err();
 		}
 	}
+
+	#[test]
+	fn compression_covers_the_blocks_of_all_forks() {
+		let backend = Backend::<Block>::new_test(1000, 100);
+		let insert_block = |number: u64, parent_hash: H256, extrinsics_root: H256, leaf_state: NewBlockState| {
+			let header = Header {
+				number,
+				parent_hash,
+				state_root: BlakeTwo256::trie_root(Vec::new()),
+				digest: Default::default(),
+				extrinsics_root,
+			};
+			let hash = header.hash();
+			let body = (0..40).map(|i| (number * 40 + i).into()).collect();
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(parent_hash)).unwrap();
+			op.set_block_data(header, Some(body), None, leaf_state).unwrap();
+			backend.commit_operation(op).unwrap();
+			hash
+		};
+		let migrate = || compression::migrate::<Block>(
+			&*backend.blockchain.db,
+			&backend.blockchain.compressor,
+			&backend.import_lock,
+		).unwrap();
+		let stored_bodies = || backend.blockchain.db.iter(columns::BODY)
+			.map(|(_, body)| body.to_vec())
+			.collect::<Vec<_>>();
+
+		let mut hashes = vec![insert_block(0, Default::default(), Default::default(), NewBlockState::Best)];
+		for number in 1..8 {
+			let parent_hash = hashes[number as usize - 1];
+			hashes.push(insert_block(number, parent_hash, Default::default(), NewBlockState::Best));
+		}
+
+		// there aren't enough blocks to train a dictionary yet
+		migrate();
+		assert!(stored_bodies().iter().all(|body| !compression::is_compressed(body)));
+
+		for number in 8..64 {
+			let parent_hash = hashes[number as usize - 1];
+			hashes.push(insert_block(number, parent_hash, Default::default(), NewBlockState::Best));
+		}
+		let fork = insert_block(10, hashes[9], H256::from_low_u64_be(1), NewBlockState::Normal);
+		migrate();
+		assert_eq!(stored_bodies().len(), 65);
+		assert!(stored_bodies().iter().all(|body| compression::is_compressed(body)));
+		for (number, hash) in hashes.iter().enumerate().chain(Some((10, &fork))) {
+			let body = backend.blockchain().body(BlockId::Hash(*hash)).unwrap().unwrap();
+			assert_eq!(body, (0..40).map(|i| (number as u64 * 40 + i).into()).collect::<Vec<_>>());
+		}
+	}
 }
@@ -0,0 +1,130 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Memory budget enforcement.
+//!
+//! Periodically compares the resident memory of the process with the configured budget and
+//! shrinks the state cache while the process is over budget. The cache is grown back to its
+//! configured size once memory usage drops well below the budget.
+
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use log::{debug, warn};
+use sp_runtime::traits::Block as BlockT;
+use crate::storage_cache::SharedCache;
+
+/// How often memory usage is checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Smallest percentage of its configured size the state cache is shrunk to.
+const MIN_PERCENT: usize = 6;
+/// Memory usage, as a percentage of the budget, below which the cache is grown again.
+const LOW_WATERMARK: usize = 80;
+
+/// Keeps the state cache within the memory budget.
+pub(crate) struct MemoryBudget {
+	budget: usize,
+	state: Mutex<(Instant, usize)>,
+}
+
+impl MemoryBudget {
+	/// Create a new instance for the given budget in bytes.
+	pub fn new(budget: usize) -> Self {
+		MemoryBudget {
+			budget,
+			state: Mutex::new((Instant::now(), 100)),
+		}
+	}
+
+	/// Check memory usage and resize the cache if needed. Does nothing if the last check
+	/// was less than `CHECK_INTERVAL` ago.
+	pub fn check<Block: BlockT>(&self, cache: &SharedCache<Block>) {
+		let mut state = self.state.lock();
+		if state.0.elapsed() < CHECK_INTERVAL {
+			return;
+		}
+		state.0 = Instant::now();
+
+		let used = match resident_memory() {
+			Some(used) => used,
+			None => return,
+		};
+		let percent = next_percent(state.1, used, self.budget);
+		if percent == state.1 {
+			return;
+		}
+
+		if percent < state.1 {
+			warn!(
+				"Memory usage of {} MiB exceeds the budget of {} MiB, shrinking state cache to {}%",
+				used / 1024 / 1024,
+				self.budget / 1024 / 1024,
+				percent,
+			);
+		} else {
+			debug!(target: "db", "Memory usage back under budget, growing state cache to {}%", percent);
+		}
+		state.1 = percent;
+		cache.lock().set_size_percent(percent);
+	}
+}
+
+/// Compute the new cache size percentage given the current one and memory usage.
+fn next_percent(current: usize, used: usize, budget: usize) -> usize {
+	if used > budget {
+		(current / 2).max(MIN_PERCENT)
+	} else if used < budget / 100 * LOW_WATERMARK {
+		(current * 2).min(100)
+	} else {
+		current
+	}
+}
+
+/// Resident memory of the process in bytes.
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<usize> {
+	let status = std::fs::read_to_string("/proc/self/status").ok()?;
+	let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+	let kib = line["VmRSS:".len()..].trim().trim_end_matches("kB").trim().parse::<usize>().ok()?;
+	Some(kib * 1024)
+}
+
+/// Resident memory of the process in bytes.
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<usize> {
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shrinks_over_budget_and_grows_back() {
+		assert_eq!(next_percent(100, 110, 100), 50);
+		assert_eq!(next_percent(50, 110, 100), 25);
+		assert_eq!(next_percent(8, 110, 100), MIN_PERCENT);
+		assert_eq!(next_percent(MIN_PERCENT, 110, 100), MIN_PERCENT);
+		assert_eq!(next_percent(25, 90, 100), 25);
+		assert_eq!(next_percent(25, 50, 100), 50);
+		assert_eq!(next_percent(75, 50, 100), 100);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn reads_resident_memory() {
+		assert!(resident_memory().unwrap() > 0);
+	}
+}
@@ -47,6 +47,8 @@ pub struct Cache<B: BlockT> {
 	/// Information on the modifications in recently committed blocks; specifically which keys
 	/// changed in which block. Ordered by block number.
 	modifications: VecDeque<BlockChanges<B::Header>>,
+	/// The configured size limits of the storage and child storage caches.
+	configured_limits: (usize, usize),
 }
 
 struct LRUMap<K, V>(LinkedHashMap<K, V>, usize, usize);
@@ -103,7 +105,6 @@ impl<K: EstimateSize + Eq + StdHash, V: EstimateSize> LRUMap<K, V> {
 	fn add(&mut self, k: K, v: V) {
 		let lmap = &mut self.0;
 		let storage_used_size = &mut self.1;
-		let limit = self.2;
 		let klen = k.estimate_size();
 		*storage_used_size += v.estimate_size();
 		// TODO assert k v size fit into limit?? to avoid insert remove?
@@ -120,7 +121,18 @@ impl<K: EstimateSize + Eq + StdHash, V: EstimateSize> LRUMap<K, V> {
 			},
 		};
 
-		while *storage_used_size > limit {
+		self.evict();
+	}
+
+	fn set_limit(&mut self, limit: usize) {
+		self.2 = limit;
+		self.evict();
+	}
+
+	fn evict(&mut self) {
+		let lmap = &mut self.0;
+		let storage_used_size = &mut self.1;
+		while *storage_used_size > self.2 {
 			if let Some((k,v)) = lmap.pop_front() {
 				*storage_used_size -= k.estimate_size();
 				*storage_used_size -= v.estimate_size();
@@ -156,6 +168,13 @@ impl<B: BlockT> Cache<B> {
 			//  ignore small hashes storage and self.lru_hashes.used_size()
 	}
 
+	/// Limit the storage cache to the given percentage of its configured size, evicting
+	/// entries if needed.
+	pub fn set_size_percent(&mut self, percent: usize) {
+		self.lru_storage.set_limit(self.configured_limits.0 * percent / 100);
+		self.lru_child_storage.set_limit(self.configured_limits.1 * percent / 100);
+	}
+
 	/// Synchronize the shared cache with the best block state.
 	///
 	/// This function updates the shared cache by removing entries
@@ -227,17 +246,16 @@ pub fn new_shared_cache<B: BlockT>(
 	child_ratio: (usize, usize),
 ) -> SharedCache<B> {
 	let top = child_ratio.1.saturating_sub(child_ratio.0);
+	let storage_limit = shared_cache_size * top / child_ratio.1;
+	let child_storage_limit = shared_cache_size * child_ratio.0 / child_ratio.1;
 	Arc::new(
 		Mutex::new(
 			Cache {
-				lru_storage: LRUMap(
-					LinkedHashMap::new(), 0, shared_cache_size * top / child_ratio.1
-				),
+				lru_storage: LRUMap(LinkedHashMap::new(), 0, storage_limit),
 				lru_hashes: LRUMap(LinkedHashMap::new(), 0, FIX_LRU_HASH_SIZE),
-				lru_child_storage: LRUMap(
-					LinkedHashMap::new(), 0, shared_cache_size * child_ratio.0 / child_ratio.1
-				),
+				lru_child_storage: LRUMap(LinkedHashMap::new(), 0, child_storage_limit),
 				modifications: VecDeque::new(),
+				configured_limits: (storage_limit, child_storage_limit),
 			}
 		)
 	)
@@ -1041,6 +1059,40 @@ mod tests {
 		assert_eq!(shared.lock().used_storage_cache_size(), 34 /* bytes */);
 	}
 
+	#[test]
+	fn should_evict_items_when_shrinking() {
+		let root_parent = H256::random();
+		let shared = new_shared_cache::<Block>(36 * 3, (0, 1));
+		let h0 = H256::random();
+
+		let mut s = CachingState::new(
+			InMemoryBackend::<Blake2Hasher>::default(),
+			shared.clone(),
+			Some(root_parent),
+		);
+
+		let key1 = H256::random()[..].to_vec();
+		let key2 = H256::random()[..].to_vec();
+		s.cache.sync_cache(
+			&[],
+			&[],
+			vec![(key1.clone(), Some(vec![1, 2, 3, 4])), (key2.clone(), Some(vec![1, 2, 3, 4]))],
+			vec![],
+			Some(h0),
+			Some(0),
+			true,
+		);
+		assert_eq!(shared.lock().used_storage_cache_size(), 72 /* bytes */);
+
+		shared.lock().set_size_percent(50);
+		// only the most recently used entry fits in 54 bytes
+		assert_eq!(shared.lock().used_storage_cache_size(), 36 /* bytes */);
+		assert!(shared.lock().lru_storage.get(&key2).is_some());
+
+		shared.lock().set_size_percent(100);
+		assert_eq!(shared.lock().lru_storage.2, 36 * 3);
+	}
+
 	#[test]
 	fn fix_storage_mismatch_issue() {
 		let _ = ::env_logger::try_init();
@@ -56,6 +56,10 @@ pub mod meta_keys {
 	pub const LEAF_PREFIX: &[u8; 4] = b"leaf";
 	/// Children prefix list key.
 	pub const CHILDREN_PREFIX: &[u8; 8] = b"children";
+	/// Dictionary block bodies and justifications are compressed with.
+	pub const COMPRESSION_DICTIONARY: &[u8; 8] = b"zstddict";
+	/// Number of the first canonical block not yet compressed by the background task.
+	pub const COMPRESSION_CURSOR: &[u8; 10] = b"zstdcursor";
 }
 
 /// Database metadata.
@@ -20,7 +20,7 @@ use crate::{
 };
 use sp_version::{NativeVersion, RuntimeVersion};
 use codec::{Decode, Encode};
-use sp_core::{NativeOrEncoded, traits::{CodeExecutor, Externalities}};
+use sp_core::{NativeOrEncoded, traits::{CodeExecutor, Externalities, CallInWasm}};
 use log::trace;
 use std::{result, cell::RefCell, panic::{UnwindSafe, AssertUnwindSafe}};
 use sp_wasm_interface::{HostFunctions, Function};
@@ -54,7 +54,7 @@ pub fn with_native_environment<F, U>(ext: &mut dyn Externalities, f: F) -> Resul
 /// Delegate for dispatching a CodeExecutor call.
 ///
 /// By dispatching we mean that we execute a runtime function specified by it's name.
-pub trait NativeExecutionDispatch: Send + Sync {
+pub trait NativeExecutionDispatch: Send + Sync + 'static {
 	/// Host functions for custom runtime interfaces that should be callable from within the runtime
 	/// besides the default Substrate runtime interfaces.
 	type ExtendHostFunctions: HostFunctions;
@@ -181,6 +181,26 @@ impl<D: NativeExecutionDispatch> RuntimeInfo for NativeExecutor<D> {
 	}
 }
 
+impl<D: NativeExecutionDispatch> CallInWasm for NativeExecutor<D> {
+	fn call_in_wasm(
+		&self,
+		wasm_blob: &[u8],
+		method: &str,
+		call_data: &[u8],
+		ext: &mut dyn Externalities,
+	) -> std::result::Result<Vec<u8>, String> {
+		crate::wasm_runtime::create_wasm_runtime_with_code(
+			self.fallback_method,
+			self.default_heap_pages,
+			wasm_blob,
+			self.host_functions.clone(),
+			false,
+		)
+			.map_err(|e| format!("Failed to create wasm runtime: {:?}", e))
+			.and_then(|mut runtime| runtime.call(ext, method, call_data).map_err(|e| e.to_string()))
+	}
+}
+
 impl<D: NativeExecutionDispatch> CodeExecutor for NativeExecutor<D> {
 	type Error = Error;
 
@@ -0,0 +1,198 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC api for GRANDPA.
+
+#![warn(missing_docs)]
+
+use std::sync::Arc;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use parking_lot::Mutex;
+use sc_finality_grandpa::{RoundStateReport, SharedRoundState};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::import_queue::BoxJustificationImport;
+use sp_core::Bytes;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
+
+pub use self::gen_client::Client as GrandpaClient;
+
+/// GRANDPA RPC methods.
+#[rpc]
+pub trait GrandpaApi<Hash, Number> {
+	/// The state of the current round followed by the node, voting or observing: the weights of
+	/// the prevotes and precommits seen so far, the voters missing, the prevote ghost and
+	/// estimate of the votes seen, and the outcome of the last completed round.
+	#[rpc(name = "grandpa_roundState")]
+	fn round_state(&self) -> Result<RoundStateReport<Hash, Number>>;
+
+	/// Import the justification of the imported block `hash`, finalizing it.
+	///
+	/// The justification must be signed by the current authority set, e.g. the one of the block
+	/// enacting the next set change, exported by a node which received it.
+	#[rpc(name = "grandpa_submitJustification")]
+	fn submit_justification(&self, hash: Hash, justification: Bytes) -> Result<()>;
+}
+
+/// Implements the [`GrandpaApi`] RPC trait.
+pub struct GrandpaRpcHandler<C, Block: BlockT> {
+	client: Arc<C>,
+	round_state: SharedRoundState<Block::Hash, NumberFor<Block>>,
+	justification_import: Mutex<BoxJustificationImport<Block>>,
+}
+
+impl<C, Block: BlockT> GrandpaRpcHandler<C, Block> {
+	/// Create a new `GrandpaRpcHandler`, reporting the state of the voter or observer given the
+	/// same `round_state` and importing justifications with `justification_import`.
+	pub fn new(
+		client: Arc<C>,
+		round_state: SharedRoundState<Block::Hash, NumberFor<Block>>,
+		justification_import: BoxJustificationImport<Block>,
+	) -> Self {
+		GrandpaRpcHandler { client, round_state, justification_import: Mutex::new(justification_import) }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The node didn't follow any round yet.
+	NotVoting,
+	/// The block of the justification is not imported.
+	UnknownBlock,
+	/// The justification could not be imported.
+	InvalidJustification,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::NotVoting => 1,
+			Error::UnknownBlock => 2,
+			Error::InvalidJustification => 3,
+		}
+	}
+}
+
+impl<C, Block> GrandpaApi<Block::Hash, NumberFor<Block>> for GrandpaRpcHandler<C, Block> where
+	Block: BlockT,
+	C: HeaderBackend<Block> + Send + Sync + 'static,
+{
+	fn round_state(&self) -> Result<RoundStateReport<Block::Hash, NumberFor<Block>>> {
+		self.round_state.report().ok_or_else(|| RpcError {
+			code: ErrorCode::ServerError(Error::NotVoting.into()),
+			message: "The node didn't follow any GRANDPA round yet.".into(),
+			data: None,
+		})
+	}
+
+	fn submit_justification(&self, hash: Block::Hash, justification: Bytes) -> Result<()> {
+		let unknown_block = |data| RpcError {
+			code: ErrorCode::ServerError(Error::UnknownBlock.into()),
+			message: "The block of the justification is not imported.".into(),
+			data,
+		};
+		let header = self.client.header(BlockId::Hash(hash))
+			.map_err(|e| unknown_block(Some(format!("{:?}", e).into())))?
+			.ok_or_else(|| unknown_block(None))?;
+
+		self.justification_import.lock()
+			.import_justification(hash, *header.number(), justification.0)
+			.map_err(|e| RpcError {
+				code: ErrorCode::ServerError(Error::InvalidJustification.into()),
+				message: "Unable to import the justification.".into(),
+				data: Some(format!("{}", e).into()),
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_consensus::{Error as ConsensusError, JustificationImport};
+	use sp_runtime::Justification;
+	use substrate_test_runtime_client::runtime::{Block, Hash};
+
+	type Imported = Arc<Mutex<Vec<(Hash, u64, Justification)>>>;
+
+	/// Imports only the justification `b"valid"`, recording it.
+	struct TestJustificationImport(Imported);
+
+	impl JustificationImport<Block> for TestJustificationImport {
+		type Error = ConsensusError;
+
+		fn import_justification(
+			&mut self,
+			hash: Hash,
+			number: u64,
+			justification: Justification,
+		) -> std::result::Result<(), Self::Error> {
+			if justification != b"valid".to_vec() {
+				return Err(ConsensusError::ClientImport("Bad justification".into()))
+			}
+			self.0.lock().push((hash, number, justification));
+			Ok(())
+		}
+	}
+
+	fn setup() -> (GrandpaRpcHandler<substrate_test_runtime_client::TestClient, Block>, Hash, Imported) {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let genesis_hash = client.info().genesis_hash;
+		let imported = Imported::default();
+		let handler = GrandpaRpcHandler::new(
+			client,
+			SharedRoundState::empty(),
+			Box::new(TestJustificationImport(imported.clone())),
+		);
+		(handler, genesis_hash, imported)
+	}
+
+	fn code<T: std::fmt::Debug>(res: Result<T>) -> ErrorCode {
+		res.unwrap_err().code
+	}
+
+	#[test]
+	fn round_state_fails_until_voting() {
+		let (handler, _, _) = setup();
+		assert_eq!(code(handler.round_state()), ErrorCode::ServerError(1));
+	}
+
+	#[test]
+	fn imports_valid_justification() {
+		let (handler, genesis_hash, imported) = setup();
+		assert!(handler.submit_justification(genesis_hash, b"valid".to_vec().into()).is_ok());
+		assert_eq!(*imported.lock(), vec![(genesis_hash, 0, b"valid".to_vec())]);
+	}
+
+	#[test]
+	fn rejects_invalid_justification() {
+		let (handler, genesis_hash, imported) = setup();
+		assert_eq!(
+			code(handler.submit_justification(genesis_hash, b"invalid".to_vec().into())),
+			ErrorCode::ServerError(3),
+		);
+		assert!(imported.lock().is_empty());
+	}
+
+	#[test]
+	fn rejects_justification_of_unknown_block() {
+		let (handler, _, imported) = setup();
+		assert_eq!(
+			code(handler.submit_justification(Hash::repeat_byte(1), b"valid".to_vec().into())),
+			ErrorCode::ServerError(2),
+		);
+		assert!(imported.lock().is_empty());
+	}
+}
@@ -744,6 +744,10 @@ impl<Block: BlockT> Inner<Block> {
 	fn validate_round_message(&self, who: &PeerId, full: &VoteMessage<Block>)
 		-> Action<Block::Hash>
 	{
+		if !self.config.follows_rounds() {
+			return Action::Discard(cost::UNFOLLOWED_ROUND);
+		}
+
 		match self.consider_vote(full.round, full.set_id) {
 			Consider::RejectFuture => return Action::Discard(Misbehavior::FutureMessage.cost()),
 			Consider::RejectOutOfScope => return Action::Discard(Misbehavior::OutOfScopeMessage.cost()),
@@ -1064,6 +1068,11 @@ impl<Block: BlockT> Inner<Block> {
 	/// Transitions will be triggered on repropagation attempts by the
 	/// underlying gossip layer, which should happen every 30 seconds.
 	fn round_message_allowed<N>(&self, who: &PeerId, peer: &PeerInfo<N>) -> bool {
+		if !self.config.follows_rounds() {
+			// lightweight observers don't relay votes.
+			return false;
+		}
+
 		let round_duration = self.config.gossip_duration * ROUND_DURATION;
 		let round_elapsed = self.round_start.elapsed();
 
@@ -1512,9 +1521,12 @@ mod tests {
 			gossip_duration: Duration::from_millis(10),
 			justification_period: 256,
 			keystore: None,
+			clock: None,
+			threshold_signer: None,
 			name: None,
 			is_authority: true,
 			observer_enabled: true,
+			lightweight_observer: false,
 		}
 	}
 
@@ -1752,6 +1764,50 @@ mod tests {
 		assert_eq!(bad_sig, Action::Discard(cost::BAD_SIGNATURE));
 	}
 
+	#[test]
+	fn lightweight_observers_ignore_round_messages() {
+		let mut config = config();
+		config.is_authority = false;
+		config.lightweight_observer = true;
+
+		let (val, _) = GossipValidator::<Block>::new(
+			config,
+			voter_set_state(),
+		);
+		let auth = AuthorityId::from_slice(&[1u8; 32]);
+		let peer = PeerId::random();
+
+		val.note_set(SetId(0), vec![auth.clone()], |_, _| {});
+		val.inner.write().peers.new_peer(peer.clone(), Roles::AUTHORITY);
+		val.inner.write().round_start = Instant::now() - Duration::from_secs(3600);
+
+		let message = VoteMessage {
+			round: Round(1),
+			set_id: SetId(0),
+			message: SignedMessage::<Block> {
+				message: finality_grandpa::Message::Prevote(finality_grandpa::Prevote {
+					target_hash: Default::default(),
+					target_number: 10,
+				}),
+				signature: Default::default(),
+				id: auth,
+			}
+		};
+		assert_eq!(
+			val.inner.read().validate_round_message(&peer, &message),
+			Action::Discard(cost::UNFOLLOWED_ROUND),
+		);
+
+		// votes are relayed to nobody, even long after the round started.
+		let mut message_allowed = val.message_allowed();
+		assert!(!message_allowed(
+			&peer,
+			MessageIntent::Broadcast,
+			&crate::communication::round_topic::<Block>(1, 0),
+			&[],
+		));
+	}
+
 	#[test]
 	fn unsolicited_catch_up_messages_discarded() {
 		let (val, _) = GossipValidator::<Block>::new(
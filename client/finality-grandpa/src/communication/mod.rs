@@ -33,7 +33,7 @@ use futures::{prelude::*, future::Executor as _, sync::mpsc};
 use futures03::{compat::Compat, stream::StreamExt, future::FutureExt as _, future::TryFutureExt as _};
 use finality_grandpa::Message::{Prevote, Precommit, PrimaryPropose};
 use finality_grandpa::{voter, voter_set::VoterSet};
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use sc_network::{NetworkService, ReputationChange};
 use sc_network_gossip::{GossipEngine, Network as GossipNetwork};
 use parity_scale_codec::{Encode, Decode};
@@ -43,7 +43,7 @@ use sc_telemetry::{telemetry, CONSENSUS_DEBUG, CONSENSUS_INFO};
 
 use crate::{
 	CatchUp, Commit, CommunicationIn, CommunicationOut, CompactCommit, Error,
-	Message, SignedMessage,
+	LocalKey, Message, SignedMessage,
 };
 use crate::environment::HasVoted;
 use gossip::{
@@ -83,6 +83,9 @@ mod cost {
 	// cost of answering a catch up request
 	pub(super) const CATCH_UP_REPLY: Rep = Rep::new(-200, "Grandpa: Catch-up reply");
 	pub(super) const HONEST_OUT_OF_SCOPE_CATCH_UP: Rep = Rep::new(-200, "Grandpa: Out-of-scope catch-up");
+
+	// round messages aren't checked by lightweight observers, nor held against their sender.
+	pub(super) const UNFOLLOWED_ROUND: Rep = Rep::new(0, "Grandpa: Round message to lightweight observer");
 }
 
 // benefit scalars for reporting peers.
@@ -236,7 +239,7 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 		round: Round,
 		set_id: SetId,
 		voters: Arc<VoterSet<AuthorityId>>,
-		local_key: Option<AuthorityPair>,
+		local_key: Option<LocalKey>,
 		has_voted: HasVoted<B>,
 	) -> (
 		impl Stream<Item=SignedMessage<B>,Error=Error>,
@@ -248,10 +251,10 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 			&*voters,
 		);
 
-		let locals = local_key.and_then(|pair| {
-			let id = pair.public();
+		let locals = local_key.and_then(|key| {
+			let id = key.public();
 			if voters.contains_key(&id) {
-				Some((pair, id))
+				Some((key, id))
 			} else {
 				None
 			}
@@ -319,6 +322,7 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 			locals,
 			sender: tx,
 			has_voted,
+			pending: None,
 		};
 
 		let out_rx = out_rx.map_err(move |()| Error::Network(
@@ -570,14 +574,87 @@ pub(crate) fn check_message_sig<Block: BlockT>(
 /// be "stable", i.e. we should end up with the exact same signed message if we
 /// use the same raw message and key to sign. This is currently true for
 /// `ed25519` and `BLS` signatures (which we might use in the future), care must
-/// be taken when switching to different key types.
+/// be taken when switching to different key types. Threshold signatures aren't stable, but
+/// signing the same message again isn't an equivocation.
 struct OutgoingMessages<Block: BlockT> {
 	round: RoundNumber,
 	set_id: SetIdNumber,
-	locals: Option<(AuthorityPair, AuthorityId)>,
+	locals: Option<(LocalKey, AuthorityId)>,
 	sender: mpsc::UnboundedSender<SignedMessage<Block>>,
 	network: GossipEngine<Block>,
 	has_voted: HasVoted<Block>,
+	/// Message waiting for its threshold signature.
+	pending: Option<(Message<Block>, PendingSignature)>,
+}
+
+type PendingSignature = Compat<futures03::future::BoxFuture<'static, Result<AuthoritySignature, String>>>;
+
+impl<Block: BlockT> OutgoingMessages<Block> {
+	/// Drive the pending threshold signature, sending the message once signed.
+	fn poll_pending(&mut self) -> Async<()> {
+		let signature = match &mut self.pending {
+			None => return Async::Ready(()),
+			Some((_, signing)) => match signing.poll() {
+				Ok(Async::NotReady) => return Async::NotReady,
+				Ok(Async::Ready(signature)) => Some(signature),
+				Err(err) => {
+					warn!(
+						target: "afg",
+						"Failed to sign vote in round {} in set {} with the threshold key: {}",
+						self.round,
+						self.set_id,
+						err,
+					);
+					None
+				},
+			},
+		};
+
+		let (msg, _) = self.pending.take().expect("returned above if no message is pending; qed");
+		let local_id = self.locals.as_ref().map(|(_, id)| id.clone());
+		if let (Some(signature), Some(local_id)) = (signature, local_id) {
+			self.send_signed(msg, signature, local_id);
+		}
+		Async::Ready(())
+	}
+
+	fn send_signed(&mut self, msg: Message<Block>, signature: AuthoritySignature, local_id: AuthorityId) {
+		let target_hash = msg.target().0.clone();
+		let signed = SignedMessage::<Block> {
+			message: msg,
+			signature,
+			id: local_id,
+		};
+
+		let message = GossipMessage::Vote(VoteMessage::<Block> {
+			message: signed.clone(),
+			round: Round(self.round),
+			set_id: SetId(self.set_id),
+		});
+
+		debug!(
+			target: "afg",
+			"Announcing block {} to peers which we voted on in round {} in set {}",
+			target_hash,
+			self.round,
+			self.set_id,
+		);
+
+		telemetry!(
+			CONSENSUS_DEBUG; "afg.announcing_blocks_to_voted_peers";
+			"block" => ?target_hash, "round" => ?self.round, "set_id" => ?self.set_id,
+		);
+
+		// announce the block we voted on to our peers.
+		self.network.announce(target_hash, Vec::new());
+
+		// propagate the message to peers
+		let topic = round_topic::<Block>(self.round, self.set_id);
+		self.network.gossip_message(topic, message.encode(), false);
+
+		// forward the message to the inner sender.
+		let _ = self.sender.unbounded_send(signed);
+	}
 }
 
 impl<Block: BlockT> Sink for OutgoingMessages<Block>
@@ -586,6 +663,11 @@ impl<Block: BlockT> Sink for OutgoingMessages<Block>
 	type SinkError = Error;
 
 	fn start_send(&mut self, mut msg: Message<Block>) -> StartSend<Message<Block>, Error> {
+		// messages are sent in order, so wait for the one being signed
+		if let Async::NotReady = self.poll_pending() {
+			return Ok(AsyncSink::NotReady(msg));
+		}
+
 		// if we've voted on this round previously under the same key, send that vote instead
 		match &mut msg {
 			finality_grandpa::Message::PrimaryPropose(ref mut vote) =>
@@ -603,51 +685,27 @@ impl<Block: BlockT> Sink for OutgoingMessages<Block>
 		}
 
 		// when locals exist, sign messages on import
-		if let Some((ref pair, ref local_id)) = self.locals {
-			let encoded = localized_payload(self.round, self.set_id, &msg);
-			let signature = pair.sign(&encoded[..]);
-
-			let target_hash = msg.target().0.clone();
-			let signed = SignedMessage::<Block> {
-				message: msg,
-				signature,
-				id: local_id.clone(),
-			};
-
-			let message = GossipMessage::Vote(VoteMessage::<Block> {
-				message: signed.clone(),
-				round: Round(self.round),
-				set_id: SetId(self.set_id),
-			});
-
-			debug!(
-				target: "afg",
-				"Announcing block {} to peers which we voted on in round {} in set {}",
-				target_hash,
-				self.round,
-				self.set_id,
-			);
-
-			telemetry!(
-				CONSENSUS_DEBUG; "afg.announcing_blocks_to_voted_peers";
-				"block" => ?target_hash, "round" => ?self.round, "set_id" => ?self.set_id,
-			);
-
-			// announce the block we voted on to our peers.
-			self.network.announce(target_hash, Vec::new());
-
-			// propagate the message to peers
-			let topic = round_topic::<Block>(self.round, self.set_id);
-			self.network.gossip_message(topic, message.encode(), false);
-
-			// forward the message to the inner sender.
-			let _ = self.sender.unbounded_send(signed);
+		match &self.locals {
+			Some((LocalKey::Pair(pair), local_id)) => {
+				let encoded = localized_payload(self.round, self.set_id, &msg);
+				let signature = pair.sign(&encoded[..]);
+				let local_id = local_id.clone();
+				self.send_signed(msg, signature, local_id);
+			},
+			Some((LocalKey::Threshold(signer, _), local_id)) => {
+				let encoded = localized_payload(self.round, self.set_id, &msg);
+				let signing = signer.sign(local_id.clone(), encoded).compat();
+				self.pending = Some((msg, signing));
+				// poll once so that the task is notified when the signature is ready
+				let _ = self.poll_pending();
+			},
+			None => {},
 		}
 
 		Ok(AsyncSink::Ready)
 	}
 
-	fn poll_complete(&mut self) -> Poll<(), Error> { Ok(Async::Ready(())) }
+	fn poll_complete(&mut self) -> Poll<(), Error> { Ok(self.poll_pending()) }
 
 	fn close(&mut self) -> Poll<(), Error> {
 		// ignore errors since we allow this inner sender to be closed already.
@@ -123,9 +123,12 @@ fn config() -> crate::Config {
 		gossip_duration: std::time::Duration::from_millis(10),
 		justification_period: 256,
 		keystore: None,
+		clock: None,
+		threshold_signer: None,
 		name: None,
 		is_authority: true,
 		observer_enabled: true,
+		lightweight_observer: false,
 	}
 }
 
@@ -23,7 +23,6 @@ use log::{debug, warn, info};
 use parity_scale_codec::{Decode, Encode};
 use futures::prelude::*;
 use futures03::future::{FutureExt as _, TryFutureExt as _};
-use futures_timer::Delay;
 use parking_lot::RwLock;
 use sp_blockchain::{HeaderBackend, Error as ClientError};
 
@@ -59,6 +58,8 @@ use crate::authorities::{AuthoritySet, SharedAuthoritySet};
 use crate::communication::Network as NetworkT;
 use crate::consensus_changes::SharedConsensusChanges;
 use crate::justification::GrandpaJustification;
+use crate::observer::ObserverChain;
+use crate::round_state::SharedRoundState;
 use crate::until_imported::UntilVoteTargetImported;
 use crate::voting_rule::VotingRule;
 use sp_finality_grandpa::{AuthorityId, AuthoritySignature, SetId, RoundNumber};
@@ -388,6 +389,7 @@ pub(crate) struct Environment<B, E, Block: BlockT, N: NetworkT<Block>, RA, SC, V
 	pub(crate) set_id: SetId,
 	pub(crate) voter_set_state: SharedVoterSetState<Block>,
 	pub(crate) voting_rule: VR,
+	pub(crate) round_state: SharedRoundState<Block::Hash, NumberFor<Block>>,
 }
 
 impl<B, E, Block: BlockT, N: NetworkT<Block>, RA, SC, VR> Environment<B, E, Block, N, RA, SC, VR> {
@@ -584,10 +586,11 @@ where
 		&self,
 		round: RoundNumber,
 	) -> voter::RoundData<Self::Id, Self::Timer, Self::In, Self::Out> {
-		let prevote_timer = Delay::new(self.config.gossip_duration * 2);
-		let precommit_timer = Delay::new(self.config.gossip_duration * 4);
+		let clock = self.config.clock();
+		let prevote_timer = clock.delay(self.config.gossip_duration * 2);
+		let precommit_timer = clock.delay(self.config.gossip_duration * 4);
 
-		let local_key = crate::is_voter(&self.voters, &self.config.keystore);
+		let local_key = crate::is_voter(&self.voters, &self.config.keystore, &self.config.threshold_signer);
 
 		let has_voted = match self.voter_set_state.has_voted(round) {
 			HasVoted::Yes(id, vote) => {
@@ -608,15 +611,48 @@ where
 			has_voted,
 		);
 
+		// note the votes of the round, ours included, for the round state RPC.
+		let info = self.client.chain_info();
+		self.round_state.note_round(
+			self.set_id,
+			&self.voters,
+			round,
+			(info.finalized_hash, info.finalized_number),
+			true,
+		);
+		let set_id = self.set_id;
+		let outgoing = {
+			let round_state = self.round_state.clone();
+			let client = self.client.clone();
+			let local_id = local_key.as_ref().map(|key| key.public());
+			outgoing.with(move |message| {
+				if let Some(ref id) = local_id {
+					round_state.note_vote(&ObserverChain(&*client), set_id, round, id, &message);
+				}
+				Ok::<_, Error>(message)
+			})
+		};
+
 		// schedule incoming messages from the network to be held until
 		// corresponding blocks are imported.
-		let incoming = Box::new(UntilVoteTargetImported::new(
+		let incoming = UntilVoteTargetImported::new(
 			self.client.import_notification_stream(),
 			self.network.clone(),
 			self.client.clone(),
 			incoming,
 			"round",
-		).map_err(Into::into));
+		);
+		let incoming = {
+			let round_state = self.round_state.clone();
+			let client = self.client.clone();
+			Box::new(incoming.inspect(move |signed| round_state.note_vote(
+				&ObserverChain(&*client),
+				set_id,
+				round,
+				&signed.id,
+				&signed.message,
+			)).map_err(Into::into))
+		};
 
 		// schedule network message cleanup when sink drops.
 		let outgoing = Box::new(outgoing.sink_map_err(Into::into));
@@ -631,7 +667,7 @@ where
 	}
 
 	fn proposed(&self, round: RoundNumber, propose: PrimaryPropose<Block>) -> Result<(), Self::Error> {
-		let local_id = crate::is_voter(&self.voters, &self.config.keystore);
+		let local_id = crate::is_voter(&self.voters, &self.config.keystore, &self.config.threshold_signer);
 
 		let local_id = match local_id {
 			Some(id) => id.public(),
@@ -670,7 +706,7 @@ where
 	}
 
 	fn prevoted(&self, round: RoundNumber, prevote: Prevote<Block>) -> Result<(), Self::Error> {
-		let local_id = crate::is_voter(&self.voters, &self.config.keystore);
+		let local_id = crate::is_voter(&self.voters, &self.config.keystore, &self.config.threshold_signer);
 
 		let local_id = match local_id {
 			Some(id) => id.public(),
@@ -711,7 +747,7 @@ where
 	}
 
 	fn precommitted(&self, round: RoundNumber, precommit: Precommit<Block>) -> Result<(), Self::Error> {
-		let local_id = crate::is_voter(&self.voters, &self.config.keystore);
+		let local_id = crate::is_voter(&self.voters, &self.config.keystore, &self.config.threshold_signer);
 
 		let local_id = match local_id {
 			Some(id) => id.public(),
@@ -768,6 +804,8 @@ where
 		base: (Block::Hash, NumberFor<Block>),
 		historical_votes: &HistoricalVotes<Block>,
 	) -> Result<(), Self::Error> {
+		self.round_state.note_completed(self.set_id, round, &state);
+
 		debug!(
 			target: "afg", "Voter {} completed round {} in set {}. Estimate = {:?}, Finalized in round = {:?}",
 			self.config.name(),
@@ -898,7 +936,7 @@ where
 
 		//random between 0-1 seconds.
 		let delay: u64 = thread_rng().gen_range(0, 1000);
-		Box::new(Delay::new(Duration::from_millis(delay)).map(Ok).compat())
+		Box::new(self.config.clock().delay(Duration::from_millis(delay)).map(Ok).compat())
 	}
 
 	fn prevote_equivocation(
@@ -25,7 +25,7 @@ use sp_blockchain::{HeaderBackend, BlockStatus, well_known_cache_keys};
 use sc_client_api::{backend::{TransactionFor, Backend}, CallExecutor, utils::is_descendent_of};
 use sc_client::Client;
 use sp_consensus::{
-	BlockImport, Error as ConsensusError,
+	BlockImport, BlockOrigin, Error as ConsensusError,
 	BlockCheckParams, BlockImportParams, ImportResult, JustificationImport,
 	SelectChain,
 };
@@ -36,7 +36,7 @@ use sp_runtime::traits::{
 	Block as BlockT, DigestFor, Header as HeaderT, NumberFor, Zero,
 };
 
-use crate::{Error, CommandOrError, NewAuthoritySet, VoterCommand};
+use crate::{Error, CommandOrError, NewAuthoritySet, TrustedCheckpoint, VoterCommand};
 use crate::authorities::{AuthoritySet, SharedAuthoritySet, DelayKind, PendingChange};
 use crate::consensus_changes::SharedConsensusChanges;
 use crate::environment::finalize_block;
@@ -57,6 +57,7 @@ pub struct GrandpaBlockImport<B, E, Block: BlockT, RA, SC> {
 	authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 	send_voter_commands: mpsc::UnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 	consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
+	checkpoint: Option<TrustedCheckpoint<Block::Hash, NumberFor<Block>>>,
 }
 
 impl<B, E, Block: BlockT, RA, SC: Clone> Clone for
@@ -69,6 +70,7 @@ impl<B, E, Block: BlockT, RA, SC: Clone> Clone for
 			authority_set: self.authority_set.clone(),
 			send_voter_commands: self.send_voter_commands.clone(),
 			consensus_changes: self.consensus_changes.clone(),
+			checkpoint: self.checkpoint.clone(),
 		}
 	}
 }
@@ -235,6 +237,29 @@ where
 		})
 	}
 
+	// check whether a block that isn't in the chain yet is known not to be an
+	// ancestor of the trusted checkpoint. all the ancestors of the checkpoint
+	// are imported before it, so once the checkpoint is in the chain any other
+	// block at or below its height is on another fork.
+	fn is_off_checkpoint_chain(&self, hash: Block::Hash, number: NumberFor<Block>)
+		-> Result<bool, ConsensusError>
+	{
+		let checkpoint = match self.checkpoint {
+			Some(ref checkpoint) if number <= checkpoint.number && hash != checkpoint.hash =>
+				checkpoint,
+			_ => return Ok(false),
+		};
+		if number == checkpoint.number {
+			return Ok(true);
+		}
+
+		match self.inner.status(BlockId::Hash(checkpoint.hash)) {
+			Ok(BlockStatus::InChain) => Ok(true),
+			Ok(BlockStatus::Unknown) => Ok(false),
+			Err(e) => Err(ConsensusError::ClientImport(e.to_string()).into()),
+		}
+	}
+
 	fn make_authorities_changes<'a>(
 		&'a self,
 		block: &mut BlockImportParams<Block, TransactionFor<B, Block>>,
@@ -278,10 +303,14 @@ where
 		}
 
 		let number = block.header.number().clone();
-		let maybe_change = self.check_new_change(
-			&block.header,
-			hash,
-		);
+
+		// blocks up to a trusted checkpoint don't change the authority set,
+		// the checkpoint block itself replaces it with the trusted one.
+		let checkpoint = self.checkpoint.as_ref().filter(|checkpoint| number <= checkpoint.number);
+		let maybe_change = match checkpoint {
+			Some(_) => None,
+			None => self.check_new_change(&block.header, hash),
+		};
 
 		// returns a function for checking whether a block is a descendent of another
 		// consistent with querying client directly after importing the block.
@@ -312,7 +341,23 @@ where
 			).map_err(|e| ConsensusError::from(ConsensusError::ClientImport(e.to_string())))?;
 		}
 
-		let applied_changes = {
+		let applied_changes = if let Some(checkpoint) = checkpoint {
+			if checkpoint.hash == hash {
+				let mut new_set = AuthoritySet::genesis(checkpoint.authorities.clone());
+				new_set.set_id = checkpoint.set_id;
+				let old = ::std::mem::replace(guard.as_mut(), new_set);
+				guard.set_old(old);
+
+				AppliedChanges::Forced(NewAuthoritySet {
+					canon_number: number,
+					canon_hash: hash,
+					set_id: checkpoint.set_id,
+					authorities: checkpoint.authorities.clone(),
+				})
+			} else {
+				AppliedChanges::None
+			}
+		} else {
 			let forced_change_set = guard.as_mut().apply_forced_changes(hash, number, &is_descendent_of)
 				.map_err(|e| ConsensusError::ClientImport(e.to_string()))
 				.map_err(ConsensusError::from)?;
@@ -380,6 +425,36 @@ where
 	}
 }
 
+/// Check the finality hint of the author of a block, see `sp_finality_tracker`, against the
+/// `finalized` block of the client.
+///
+/// The hint must be below the block, and not above the finalized block unless the block comes with
+/// its own justification. Finality lags behind during the initial sync, when the hints are only
+/// checked against the block.
+pub(crate) fn check_finality_hint<Block: BlockT, Transaction>(
+	block: &BlockImportParams<Block, Transaction>,
+	finalized: NumberFor<Block>,
+) -> Result<(), ConsensusError> {
+	let pre_runtime_digests = block.header.digest().logs().iter().filter_map(|log| log.as_pre_runtime());
+	let hint: NumberFor<Block> = match sp_finality_tracker::find_finality_hint(pre_runtime_digests) {
+		Some(hint) => hint,
+		None => return Ok(()),
+	};
+
+	let number = *block.header.number();
+	if hint >= number {
+		return Err(ConsensusError::ClientImport(
+			format!("Finality hint {} of block {} is not below it", hint, number)
+		));
+	}
+	if hint > finalized && block.justification.is_none() && block.origin != BlockOrigin::NetworkInitialSync {
+		return Err(ConsensusError::ClientImport(
+			format!("Finality hint {} of block {} is above the finalized block {}", hint, number, finalized)
+		));
+	}
+	Ok(())
+}
+
 impl<B, E, Block: BlockT, RA, SC> BlockImport<Block>
 	for GrandpaBlockImport<B, E, Block, RA, SC> where
 		NumberFor<Block>: finality_grandpa::BlockNumberOps,
@@ -409,10 +484,25 @@ impl<B, E, Block: BlockT, RA, SC> BlockImport<Block>
 			Err(e) => return Err(ConsensusError::ClientImport(e.to_string()).into()),
 		}
 
+		if self.is_off_checkpoint_chain(hash, number)? {
+			return Ok(ImportResult::KnownBad);
+		}
+
+		check_finality_hint(&block, self.inner.chain_info().finalized_number)?;
+
 		let pending_changes = self.make_authorities_changes(&mut block, hash)?;
 
 		// we don't want to finalize on `inner.import_block`
 		let mut justification = block.justification.take();
+
+		// justifications up to a trusted checkpoint are not needed, the
+		// checkpoint block itself is imported as finalized.
+		if let Some(ref checkpoint) = self.checkpoint {
+			if number <= checkpoint.number {
+				justification = None;
+				block.finalized = hash == checkpoint.hash;
+			}
+		}
 		let enacts_consensus_change = !new_cache.is_empty();
 		let import_result = (&*self.inner).import_block(block, new_cache);
 
@@ -517,6 +607,14 @@ impl<B, E, Block: BlockT, RA, SC> BlockImport<Block>
 		&mut self,
 		block: BlockCheckParams<Block>,
 	) -> Result<ImportResult, Self::Error> {
+		match self.inner.status(BlockId::Hash(block.hash)) {
+			Ok(BlockStatus::InChain) => {},
+			Ok(BlockStatus::Unknown) => if self.is_off_checkpoint_chain(block.hash, block.number)? {
+				return Ok(ImportResult::KnownBad);
+			},
+			Err(e) => return Err(ConsensusError::ClientImport(e.to_string()).into()),
+		}
+
 		self.inner.check_block(block)
 	}
 }
@@ -528,6 +626,7 @@ impl<B, E, Block: BlockT, RA, SC> GrandpaBlockImport<B, E, Block, RA, SC> {
 		authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 		send_voter_commands: mpsc::UnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 		consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
+		checkpoint: Option<TrustedCheckpoint<Block::Hash, NumberFor<Block>>>,
 	) -> GrandpaBlockImport<B, E, Block, RA, SC> {
 		GrandpaBlockImport {
 			inner,
@@ -535,6 +634,7 @@ impl<B, E, Block: BlockT, RA, SC> GrandpaBlockImport<B, E, Block, RA, SC> {
 			authority_set,
 			send_voter_commands,
 			consensus_changes,
+			checkpoint,
 		}
 	}
 }
@@ -63,14 +63,13 @@ use parity_scale_codec::{Decode, Encode};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{NumberFor, Block as BlockT, DigestFor, Zero};
 use sc_keystore::KeyStorePtr;
-use sp_inherents::InherentDataProviders;
 use sp_consensus::SelectChain;
 use sp_core::Pair;
 use sc_telemetry::{telemetry, CONSENSUS_INFO, CONSENSUS_DEBUG, CONSENSUS_WARN};
+use sc_consensus_slots::{Clock, SystemClock};
+use serde::{Serialize, Deserialize};
 use serde_json;
 
-use sp_finality_tracker;
-
 use finality_grandpa::Error as GrandpaError;
 use finality_grandpa::{voter, BlockNumberOps, voter_set::VoterSet};
 
@@ -88,6 +87,8 @@ mod import;
 mod justification;
 mod light_import;
 mod observer;
+mod round_state;
+pub mod threshold;
 mod until_imported;
 mod voting_rule;
 
@@ -95,6 +96,9 @@ pub use finality_proof::FinalityProofProvider;
 pub use justification::GrandpaJustification;
 pub use light_import::light_block_import;
 pub use observer::run_grandpa_observer;
+pub use round_state::{
+	BlockTarget, CompletedRoundReport, RoundStateReport, SharedRoundState, VotesReport,
+};
 pub use voting_rule::{
 	BeforeBestBlock, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRulesBuilder
 };
@@ -105,6 +109,9 @@ use import::GrandpaBlockImport;
 use until_imported::UntilGlobalMessageBlocksImported;
 use communication::{NetworkBridge, Network as NetworkT};
 use sp_finality_grandpa::{AuthorityList, AuthorityPair, AuthoritySignature, SetId};
+use sp_finality_grandpa::{GrandpaApi, VersionedAuthorityList, GRANDPA_AUTHORITIES_KEY};
+use sp_api::RuntimeApiInfo;
+use sp_core::storage::StorageKey;
 
 // Re-export these two because it's just so damn convenient.
 pub use sp_finality_grandpa::{AuthorityId, ScheduledChange};
@@ -202,18 +209,39 @@ pub struct Config {
 	/// protocol (we will only issue catch-up requests to authorities when the
 	/// observer protocol is enabled).
 	pub observer_enabled: bool,
+	/// Whether the GRANDPA observer of this node, when not an authority, only follows commits
+	/// to finalize blocks and track the authority sets, without validating and relaying the
+	/// votes of the rounds.
+	pub lightweight_observer: bool,
 	/// Whether the node is running as an authority (i.e. running the full GRANDPA protocol).
 	pub is_authority: bool,
 	/// Some local identifier of the voter.
 	pub name: Option<String>,
 	/// The keystore that manages the keys of this node.
 	pub keystore: Option<sc_keystore::KeyStorePtr>,
+	/// The clock driving the round timers, the system clock if `None`. A
+	/// `sc_consensus_slots::VirtualClock` shared with the slot worker lets tests simulate
+	/// timeouts without waiting for them.
+	pub clock: Option<Arc<dyn Clock>>,
+	/// Experimental: signer of the votes with a key split between several hosts, used when
+	/// the keystore doesn't hold the key of a voter.
+	pub threshold_signer: Option<Arc<threshold::ThresholdSigner>>,
 }
 
 impl Config {
 	fn name(&self) -> &str {
 		self.name.as_ref().map(|s| s.as_str()).unwrap_or("<unknown>")
 	}
+
+	/// The clock driving the round timers.
+	fn clock(&self) -> Arc<dyn Clock> {
+		self.clock.clone().unwrap_or_else(|| Arc::new(SystemClock))
+	}
+
+	/// Whether the node follows the rounds, validating and relaying their votes.
+	fn follows_rounds(&self) -> bool {
+		self.is_authority || !self.lightweight_observer
+	}
 }
 
 /// Errors that can occur while voting in GRANDPA.
@@ -361,6 +389,14 @@ pub struct LinkHalf<B, E, Block: BlockT, RA, SC> {
 	select_chain: SC,
 	persistent_data: PersistentData<Block>,
 	voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
+	round_state: SharedRoundState<Block::Hash, NumberFor<Block>>,
+}
+
+impl<B, E, Block: BlockT, RA, SC> LinkHalf<B, E, Block, RA, SC> {
+	/// State of the current round of the voter or observer, once it runs.
+	pub fn round_state(&self) -> &SharedRoundState<Block::Hash, NumberFor<Block>> {
+		&self.round_state
+	}
 }
 
 /// Provider for the Grandpa authority set configured on the genesis block.
@@ -376,12 +412,29 @@ impl<B, E, Block: BlockT, RA> GenesisAuthoritySetProvider<Block> for Client<B, E
 		RA: Send + Sync,
 {
 	fn get(&self) -> Result<AuthorityList, ClientError> {
+		let genesis = BlockId::Number(Zero::zero());
+
+		// Runtimes that predate the Grandpa runtime API only expose the authorities in storage.
+		let has_api = self.runtime_version_at(&genesis)?
+			.has_api_with(&<dyn GrandpaApi<Block, Error = ()>>::ID, |_| true);
+		if !has_api {
+			return self.storage(&genesis, &StorageKey(GRANDPA_AUTHORITIES_KEY.to_vec()))?
+				.ok_or_else(|| ClientError::Msg("GRANDPA authorities missing from genesis state".into()))
+				.and_then(|data| {
+					VersionedAuthorityList::decode(&mut &data.0[..])
+						.map(Into::into)
+						.map_err(|err| ClientError::CallResultDecode(
+							"failed to decode GRANDPA authorities set from genesis state".into(), err
+						))
+				})
+		}
+
 		// This implementation uses the Grandpa runtime API instead of reading directly from the
 		// `GRANDPA_AUTHORITIES_KEY` as the data may have been migrated since the genesis block of
 		// the chain, whereas the runtime API is backwards compatible.
 		self.executor()
 			.call(
-				&BlockId::Number(Zero::zero()),
+				&genesis,
 				"GrandpaApi_grandpa_authorities",
 				&[],
 				ExecutionStrategy::NativeElseWasm,
@@ -396,6 +449,25 @@ impl<B, E, Block: BlockT, RA> GenesisAuthoritySetProvider<Block> for Client<B, E
 	}
 }
 
+/// A trusted finalized block with the authority set that is live after it.
+///
+/// This may be used as chain spec extension to let new nodes sync up to the
+/// checkpoint without verifying header seals, requesting justifications or
+/// tracking authority set changes. Once the checkpoint block is imported it is
+/// finalized and the given authority set takes over from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedCheckpoint<H, N> {
+	/// Hash of the checkpoint block.
+	pub hash: H,
+	/// Number of the checkpoint block.
+	pub number: N,
+	/// Id of the authority set that is live after the checkpoint.
+	pub set_id: SetId,
+	/// Authorities that are live after the checkpoint.
+	pub authorities: AuthorityList,
+}
+
 /// Make block importer and link half necessary to tie the background voter
 /// to it.
 pub fn block_import<B, E, Block: BlockT, RA, SC>(
@@ -412,6 +484,31 @@ where
 	RA: Send + Sync,
 	SC: SelectChain<Block>,
 	Client<B, E, Block, RA>: AuxStore,
+{
+	block_import_with_checkpoint(client, genesis_authorities_provider, select_chain, None)
+}
+
+/// Make block importer and link half necessary to tie the background voter
+/// to it, trusting the given finalized checkpoint (if any).
+///
+/// Blocks at or below the checkpoint height are rejected unless they are
+/// ancestors of the checkpoint: any other block at its height, and any block
+/// below it once the checkpoint is imported.
+pub fn block_import_with_checkpoint<B, E, Block: BlockT, RA, SC>(
+	client: Arc<Client<B, E, Block, RA>>,
+	genesis_authorities_provider: &dyn GenesisAuthoritySetProvider<Block>,
+	select_chain: SC,
+	checkpoint: Option<TrustedCheckpoint<Block::Hash, NumberFor<Block>>>,
+) -> Result<(
+		GrandpaBlockImport<B, E, Block, RA, SC>,
+		LinkHalf<B, E, Block, RA, SC>
+	), ClientError>
+where
+	B: Backend<Block> + 'static,
+	E: CallExecutor<Block> + 'static + Clone + Send + Sync,
+	RA: Send + Sync,
+	SC: SelectChain<Block>,
+	Client<B, E, Block, RA>: AuxStore,
 {
 	let chain_info = client.chain_info();
 	let genesis_hash = chain_info.genesis_hash;
@@ -438,12 +535,14 @@ where
 			persistent_data.authority_set.clone(),
 			voter_commands_tx,
 			persistent_data.consensus_changes.clone(),
+			checkpoint,
 		),
 		LinkHalf {
 			client,
 			select_chain,
 			persistent_data,
 			voter_commands_rx,
+			round_state: SharedRoundState::empty(),
 		},
 	))
 }
@@ -454,6 +553,7 @@ fn global_communication<Block: BlockT, B, E, N, RA>(
 	client: &Arc<Client<B, E, Block, RA>>,
 	network: &NetworkBridge<Block, N>,
 	keystore: &Option<KeyStorePtr>,
+	threshold_signer: &Option<Arc<threshold::ThresholdSigner>>,
 ) -> (
 	impl Stream<
 		Item = CommunicationInH<Block, Block::Hash>,
@@ -470,7 +570,7 @@ fn global_communication<Block: BlockT, B, E, N, RA>(
 	RA: Send + Sync,
 	NumberFor<Block>: BlockNumberOps,
 {
-	let is_voter = is_voter(voters, keystore).is_some();
+	let is_voter = is_voter(voters, keystore, threshold_signer).is_some();
 
 	// verification stream
 	let (global_in, global_out) = network.global_communication(
@@ -494,35 +594,6 @@ fn global_communication<Block: BlockT, B, E, N, RA>(
 	(global_in, global_out)
 }
 
-/// Register the finality tracker inherent data provider (which is used by
-/// GRANDPA), if not registered already.
-fn register_finality_tracker_inherent_data_provider<B, E, Block: BlockT, RA>(
-	client: Arc<Client<B, E, Block, RA>>,
-	inherent_data_providers: &InherentDataProviders,
-) -> Result<(), sp_consensus::Error> where
-	B: Backend<Block> + 'static,
-	E: CallExecutor<Block> + Send + Sync + 'static,
-	RA: Send + Sync + 'static,
-{
-	if !inherent_data_providers.has_provider(&sp_finality_tracker::INHERENT_IDENTIFIER) {
-		inherent_data_providers
-			.register_provider(sp_finality_tracker::InherentDataProvider::new(move || {
-				#[allow(deprecated)]
-				{
-					let info = client.chain_info();
-					telemetry!(CONSENSUS_INFO; "afg.finalized";
-						"finalized_number" => ?info.finalized_number,
-						"finalized_hash" => ?info.finalized_hash,
-					);
-					Ok(info.finalized_number)
-				}
-			}))
-			.map_err(|err| sp_consensus::Error::InherentData(err.into()))
-	} else {
-		Ok(())
-	}
-}
-
 /// Parameters used to run Grandpa.
 pub struct GrandpaParams<B, E, Block: BlockT, N, RA, SC, VR, X, Sp> {
 	/// Configuration for the GRANDPA service.
@@ -531,8 +602,6 @@ pub struct GrandpaParams<B, E, Block: BlockT, N, RA, SC, VR, X, Sp> {
 	pub link: LinkHalf<B, E, Block, RA, SC>,
 	/// The Network instance.
 	pub network: N,
-	/// The inherent data providers.
-	pub inherent_data_providers: InherentDataProviders,
 	/// Handle to a future that will resolve on exit.
 	pub on_exit: X,
 	/// If supplied, can be used to hook on telemetry connection established events.
@@ -565,7 +634,6 @@ pub fn run_grandpa_voter<B, E, Block: BlockT, N, RA, SC, VR, X, Sp>(
 		config,
 		link,
 		network,
-		inherent_data_providers,
 		on_exit,
 		telemetry_on_connect,
 		voting_rule,
@@ -577,6 +645,7 @@ pub fn run_grandpa_voter<B, E, Block: BlockT, N, RA, SC, VR, X, Sp>(
 		select_chain,
 		persistent_data,
 		voter_commands_rx,
+		round_state,
 	} = link;
 
 	let network = NetworkBridge::new(
@@ -587,8 +656,6 @@ pub fn run_grandpa_voter<B, E, Block: BlockT, N, RA, SC, VR, X, Sp>(
 		on_exit.clone(),
 	);
 
-	register_finality_tracker_inherent_data_provider(client.clone(), &inherent_data_providers)?;
-
 	let conf = config.clone();
 	let telemetry_task = if let Some(telemetry_on_connect) = telemetry_on_connect {
 		let authorities = persistent_data.authority_set.clone();
@@ -626,6 +693,7 @@ pub fn run_grandpa_voter<B, E, Block: BlockT, N, RA, SC, VR, X, Sp>(
 		voting_rule,
 		persistent_data,
 		voter_commands_rx,
+		round_state,
 	);
 
 	let voter_work = voter_work
@@ -672,6 +740,7 @@ where
 		voting_rule: VR,
 		persistent_data: PersistentData<Block>,
 		voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
+		round_state: SharedRoundState<Block::Hash, NumberFor<Block>>,
 	) -> Self {
 
 		let voters = persistent_data.authority_set.current_authorities();
@@ -686,6 +755,7 @@ where
 			authority_set: persistent_data.authority_set.clone(),
 			consensus_changes: persistent_data.consensus_changes.clone(),
 			voter_set_state: persistent_data.set_state.clone(),
+			round_state,
 		});
 
 		let mut work = VoterWork {
@@ -705,8 +775,12 @@ where
 	fn rebuild_voter(&mut self) {
 		debug!(target: "afg", "{}: Starting new voter with set ID {}", self.env.config.name(), self.env.set_id);
 
-		let authority_id = is_voter(&self.env.voters, &self.env.config.keystore)
-			.map(|ap| ap.public())
+		let authority_id = is_voter(
+			&self.env.voters,
+			&self.env.config.keystore,
+			&self.env.config.threshold_signer,
+		)
+			.map(|key| key.public())
 			.unwrap_or(Default::default());
 
 		telemetry!(CONSENSUS_DEBUG; "afg.starting_new_voter";
@@ -742,6 +816,7 @@ where
 					&self.env.client,
 					&self.env.network,
 					&self.env.config.keystore,
+					&self.env.config.threshold_signer,
 				);
 
 				let last_completed_round = completed_rounds.last();
@@ -804,6 +879,7 @@ where
 					consensus_changes: self.env.consensus_changes.clone(),
 					network: self.env.network.clone(),
 					voting_rule: self.env.voting_rule.clone(),
+					round_state: self.env.round_state.clone(),
 				});
 
 				self.rebuild_voter();
@@ -902,27 +978,15 @@ pub fn run_grandpa<B, E, Block: BlockT, N, RA, SC, VR, X, Sp>(
 	run_grandpa_voter(grandpa_params)
 }
 
-/// When GRANDPA is not initialized we still need to register the finality
-/// tracker inherent provider which might be expected by the runtime for block
-/// authoring. Additionally, we register a gossip message validator that
-/// discards all GRANDPA messages (otherwise, we end up banning nodes that send
+/// When GRANDPA is not initialized we still register a gossip message validator
+/// that discards all GRANDPA messages (otherwise, we end up banning nodes that send
 /// us a `Neighbor` message, since there is no registered gossip validator for
 /// the engine id defined in the message.)
-pub fn setup_disabled_grandpa<B, E, Block: BlockT, RA, N>(
-	client: Arc<Client<B, E, Block, RA>>,
-	inherent_data_providers: &InherentDataProviders,
+pub fn setup_disabled_grandpa<Block: BlockT, N>(
 	network: N,
 ) -> Result<(), sp_consensus::Error> where
-	B: Backend<Block> + 'static,
-	E: CallExecutor<Block> + Send + Sync + 'static,
-	RA: Send + Sync + 'static,
 	N: NetworkT<Block> + Send + Clone + 'static,
 {
-	register_finality_tracker_inherent_data_provider(
-		client,
-		inherent_data_providers,
-	)?;
-
 	// We register the GRANDPA protocol so that we don't consider it an anomaly
 	// to receive GRANDPA messages on the network. We don't process the
 	// messages.
@@ -931,18 +995,44 @@ pub fn setup_disabled_grandpa<B, E, Block: BlockT, RA, N>(
 	Ok(())
 }
 
+/// Key signing the votes of the local voter.
+#[derive(Clone)]
+pub(crate) enum LocalKey {
+	/// Key pair from the keystore.
+	Pair(AuthorityPair),
+	/// Key split between several hosts.
+	Threshold(Arc<threshold::ThresholdSigner>, AuthorityId),
+}
+
+impl LocalKey {
+	pub(crate) fn public(&self) -> AuthorityId {
+		match self {
+			LocalKey::Pair(pair) => pair.public(),
+			LocalKey::Threshold(_, id) => id.clone(),
+		}
+	}
+}
+
 /// Checks if this node is a voter in the given voter set.
 ///
-/// Returns the key pair of the node that is being used in the current voter set or `None`.
+/// Returns the key of the node that is being used in the current voter set or `None`.
 fn is_voter(
 	voters: &Arc<VoterSet<AuthorityId>>,
 	keystore: &Option<KeyStorePtr>,
-) -> Option<AuthorityPair> {
-	match keystore {
-		Some(keystore) => voters.voters().iter()
-			.find_map(|(p, _)| keystore.read().key_pair::<AuthorityPair>(&p).ok()),
-		None => None,
+	threshold_signer: &Option<Arc<threshold::ThresholdSigner>>,
+) -> Option<LocalKey> {
+	let pair = keystore.as_ref().and_then(|keystore| voters.voters().iter()
+		.find_map(|(p, _)| keystore.read().key_pair::<AuthorityPair>(&p).ok())
+	);
+	if let Some(pair) = pair {
+		return Some(LocalKey::Pair(pair));
 	}
+
+	let threshold_signer = threshold_signer.as_ref()?;
+	let group_keys = threshold_signer.group_keys();
+	voters.voters().iter()
+		.find(|(id, _)| group_keys.contains(id))
+		.map(|(id, _)| LocalKey::Threshold(threshold_signer.clone(), id.clone()))
 }
 
 /// Returns the authority id of this node, if available.
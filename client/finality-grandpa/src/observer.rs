@@ -31,14 +31,18 @@ use sp_runtime::traits::{NumberFor, Block as BlockT};
 
 use crate::{
 	global_communication, CommandOrError, CommunicationIn, Config, environment,
-	LinkHalf, Error, aux_schema::PersistentData, VoterCommand, VoterSetState,
+	LinkHalf, Error, aux_schema::PersistentData, SignedMessage, VoterCommand, VoterSetState,
 };
 use crate::authorities::SharedAuthoritySet;
 use crate::communication::{Network as NetworkT, NetworkBridge};
 use crate::consensus_changes::SharedConsensusChanges;
-use sp_finality_grandpa::AuthorityId;
+use crate::environment::HasVoted;
+use crate::round_state::SharedRoundState;
+use crate::until_imported::UntilVoteTargetImported;
+use sp_finality_grandpa::{AuthorityId, RoundNumber};
 
-struct ObserverChain<'a, Block: BlockT, B, E, RA>(&'a Client<B, E, Block, RA>);
+/// The chain of the blocks imported by the client, to check commits and tally votes against.
+pub(crate) struct ObserverChain<'a, Block: BlockT, B, E, RA>(pub(crate) &'a Client<B, E, Block, RA>);
 
 impl<'a, Block: BlockT, B, E, RA> finality_grandpa::Chain<Block::Hash, NumberFor<Block>>
 	for ObserverChain<'a, Block, B, E, RA> where
@@ -63,7 +67,7 @@ fn grandpa_observer<B, E, Block: BlockT, RA, S, F>(
 	voters: &Arc<VoterSet<AuthorityId>>,
 	last_finalized_number: NumberFor<Block>,
 	commits: S,
-	note_round: F,
+	note_commit: F,
 ) -> impl Future<Item=(), Error=CommandOrError<Block::Hash, NumberFor<Block>>> where
 	NumberFor<Block>: BlockNumberOps,
 	B: Backend<Block>,
@@ -73,7 +77,7 @@ fn grandpa_observer<B, E, Block: BlockT, RA, S, F>(
 		Item = CommunicationIn<Block>,
 		Error = CommandOrError<Block::Hash, NumberFor<Block>>,
 	>,
-	F: Fn(u64),
+	F: Fn(RoundNumber, (Block::Hash, NumberFor<Block>)),
 {
 	let authority_set = authority_set.clone();
 	let consensus_changes = consensus_changes.clone();
@@ -127,7 +131,7 @@ fn grandpa_observer<B, E, Block: BlockT, RA, S, F>(
 
 			// note that we've observed completion of this round through the commit,
 			// and that implies that the next round has started.
-			note_round(round + 1);
+			note_commit(round, (finalized_hash, finalized_number));
 
 			finality_grandpa::process_commit_validation_result(validation_result, callback);
 
@@ -150,6 +154,9 @@ fn grandpa_observer<B, E, Block: BlockT, RA, S, F>(
 /// listening for and validating GRANDPA commits instead of following the full
 /// protocol. Provide configuration and a link to a block import worker that has
 /// already been instantiated with `block_import`.
+///
+/// Unless `config.lightweight_observer` is set, the observer still validates and
+/// relays the votes of the rounds to help their propagation.
 pub fn run_grandpa_observer<B, E, Block: BlockT, N, RA, SC, Sp>(
 	config: Config,
 	link: LinkHalf<B, E, Block, RA, SC>,
@@ -171,8 +178,10 @@ pub fn run_grandpa_observer<B, E, Block: BlockT, N, RA, SC, Sp>(
 		select_chain: _,
 		persistent_data,
 		voter_commands_rx,
+		round_state,
 	} = link;
 
+	let follows_rounds = config.follows_rounds();
 	let network = NetworkBridge::new(
 		network,
 		config.clone(),
@@ -186,6 +195,8 @@ pub fn run_grandpa_observer<B, E, Block: BlockT, N, RA, SC, Sp>(
 		network,
 		persistent_data,
 		config.keystore.clone(),
+		follows_rounds,
+		round_state,
 		voter_commands_rx
 	);
 
@@ -208,6 +219,11 @@ struct ObserverWork<B: BlockT, N: NetworkT<B>, E, Backend, RA> {
 	network: NetworkBridge<B, N>,
 	persistent_data: PersistentData<B>,
 	keystore: Option<sc_keystore::KeyStorePtr>,
+	follows_rounds: bool,
+	round_state: SharedRoundState<B::Hash, NumberFor<B>>,
+	/// The votes of the current round relayed by the network, with the round number, followed
+	/// unless the observer is lightweight.
+	round_votes: Option<(RoundNumber, Box<dyn Stream<Item = SignedMessage<B>, Error = Error> + Send>)>,
 	voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
 }
 
@@ -226,6 +242,8 @@ where
 		network: NetworkBridge<B, N>,
 		persistent_data: PersistentData<B>,
 		keystore: Option<sc_keystore::KeyStorePtr>,
+		follows_rounds: bool,
+		round_state: SharedRoundState<B::Hash, NumberFor<B>>,
 		voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
 	) -> Self {
 
@@ -237,6 +255,9 @@ where
 			network,
 			persistent_data,
 			keystore,
+			follows_rounds,
+			round_state,
+			round_votes: None,
 			voter_commands_rx,
 		};
 		work.rebuild_observer();
@@ -257,23 +278,35 @@ where
 			&self.client,
 			&self.network,
 			&self.keystore,
+			&None,
 		);
 
 		let last_finalized_number = self.client.chain_info().finalized_number;
 
-		// NOTE: since we are not using `round_communication` we have to
-		// manually note the round with the gossip validator, otherwise we won't
-		// relay round messages. we want all full nodes to contribute to vote
-		// availability.
-		let note_round = {
+		// NOTE: the votes of a round are only followed once its round is noted in the round
+		// state, so we have to manually note the round with the gossip validator, otherwise we
+		// won't relay round messages. we want all full nodes to contribute to vote
+		// availability, unless they run as lightweight observers.
+		let note_commit = {
 			let network = self.network.clone();
 			let voters = voters.clone();
-
-			move |round| network.note_round(
-				crate::communication::Round(round),
-				crate::communication::SetId(set_id),
-				&*voters,
-			)
+			let follows_rounds = self.follows_rounds;
+			let round_state = self.round_state.clone();
+
+			move |round, finalized: (B::Hash, NumberFor<B>)| {
+				// the round is only known through its commit if its votes weren't followed.
+				round_state.note_round(set_id, &voters, round, finalized.clone(), false);
+				round_state.note_commit(set_id, round, finalized.clone());
+				round_state.note_round(set_id, &voters, round + 1, finalized, follows_rounds);
+
+				if follows_rounds {
+					network.note_round(
+						crate::communication::Round(round + 1),
+						crate::communication::SetId(set_id),
+						&*voters,
+					)
+				}
+			}
 		};
 
 		// create observer for the current set
@@ -284,10 +317,63 @@ where
 			&voters,
 			last_finalized_number,
 			global_in,
-			note_round,
+			note_commit,
 		);
 
 		self.observer = Box::new(observer);
+		self.round_votes = None;
+	}
+
+	/// Note the votes of the current round of the round state relayed by the network, following
+	/// the votes of every new round, unless the observer is lightweight.
+	fn poll_round_votes(&mut self) {
+		if !self.follows_rounds {
+			return;
+		}
+		let set_id = self.persistent_data.authority_set.set_id();
+		let current = match self.round_state.current_round() {
+			Some((current_set_id, round)) if current_set_id == set_id => round,
+			_ => return,
+		};
+
+		if self.round_votes.as_ref().map_or(true, |(round, _)| *round != current) {
+			let voters = Arc::new(self.persistent_data.authority_set.current_authorities());
+			let (incoming, _) = self.network.round_communication(
+				crate::communication::Round(current),
+				crate::communication::SetId(set_id),
+				voters,
+				None,
+				HasVoted::No,
+			);
+			// hold the votes until their targets are imported, to tally them.
+			let incoming = UntilVoteTargetImported::new(
+				self.client.import_notification_stream(),
+				self.network.clone(),
+				self.client.clone(),
+				incoming,
+				"round",
+			);
+			self.round_votes = Some((current, Box::new(incoming)));
+		}
+
+		let (round, votes) = self.round_votes.as_mut().expect("Set above if missing; qed");
+		loop {
+			match votes.poll() {
+				Ok(Async::Ready(Some(signed))) => self.round_state.note_vote(
+					&ObserverChain(&*self.client),
+					set_id,
+					*round,
+					&signed.id,
+					&signed.message,
+				),
+				Ok(Async::NotReady) => break,
+				Ok(Async::Ready(None)) | Err(_) => {
+					// follow the round no further, until the next one.
+					*votes = Box::new(futures::stream::empty());
+					break;
+				},
+			}
+		}
 	}
 
 	fn handle_voter_command(
@@ -375,6 +461,8 @@ where
 			}
 		}
 
+		self.poll_round_votes();
+
 		Ok(Async::NotReady)
 	}
 }
@@ -0,0 +1,430 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! State of the current round followed by the node.
+//!
+//! The voter notes the votes it sees in its current round, its own included, and the observer
+//! the votes it relays, so that the voters which don't vote can be found out when finality
+//! stalls. The votes are tallied as the voter does, to report what the round would finalize.
+
+use std::{collections::HashSet, fmt::Debug, hash::Hash, sync::Arc};
+
+use finality_grandpa::{
+	BlockNumberOps, Chain, Message,
+	round::{Round, RoundParams, State as RoundState},
+	voter_set::VoterSet,
+};
+use log::debug;
+use parking_lot::RwLock;
+use serde::Serialize;
+use sp_finality_grandpa::{AuthorityId, RoundNumber, SetId};
+
+/// A block targeted by votes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTarget<H, N> {
+	/// Hash of the block.
+	pub hash: H,
+	/// Number of the block.
+	pub number: N,
+}
+
+impl<H, N> From<(H, N)> for BlockTarget<H, N> {
+	fn from((hash, number): (H, N)) -> Self {
+		BlockTarget { hash, number }
+	}
+}
+
+/// The votes of one kind seen in a round.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VotesReport {
+	/// Total weight of the voters who voted.
+	pub current_weight: u64,
+	/// Voters who didn't vote yet, in the order of the voter set.
+	pub missing: Vec<AuthorityId>,
+}
+
+/// The outcome of a completed round.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedRoundReport<H, N> {
+	/// The round number.
+	pub round: RoundNumber,
+	/// The block with the supermajority of prevotes.
+	pub prevote_ghost: Option<BlockTarget<H, N>>,
+	/// The best block that could be finalized in the round.
+	pub estimate: Option<BlockTarget<H, N>>,
+	/// The block finalized in the round.
+	pub finalized: Option<BlockTarget<H, N>>,
+}
+
+/// The state of the current round followed by the node.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundStateReport<H, N> {
+	/// Id of the authority set.
+	pub set_id: SetId,
+	/// The round number.
+	pub round: RoundNumber,
+	/// Total weight of the voters.
+	pub total_weight: u64,
+	/// Weight of votes needed for a supermajority.
+	pub threshold_weight: u64,
+	/// Prevotes seen in the round, `None` if the node doesn't follow the votes of the rounds,
+	/// being a lightweight observer.
+	pub prevotes: Option<VotesReport>,
+	/// Precommits seen in the round, `None` if the node doesn't follow the votes of the rounds.
+	pub precommits: Option<VotesReport>,
+	/// The block with the supermajority of the prevotes seen, if any.
+	pub prevote_ghost: Option<BlockTarget<H, N>>,
+	/// The best block that could be finalized in the round given the votes seen.
+	pub estimate: Option<BlockTarget<H, N>>,
+	/// The last round completed in the set, if any.
+	pub last_completed: Option<CompletedRoundReport<H, N>>,
+}
+
+struct CurrentRound<H: Hash + Eq, N> {
+	set_id: SetId,
+	voters: Arc<VoterSet<AuthorityId>>,
+	round: RoundNumber,
+	/// The votes seen, `None` if the node doesn't follow them.
+	votes: Option<RoundVotes<H, N>>,
+	last_completed: Option<CompletedRoundReport<H, N>>,
+}
+
+struct RoundVotes<H: Hash + Eq, N> {
+	prevoted: HashSet<AuthorityId>,
+	precommitted: HashSet<AuthorityId>,
+	/// The votes whose targets are known, tallied. Signatures are checked by the network.
+	tally: Round<AuthorityId, H, N, ()>,
+	/// The state of `tally`.
+	state: RoundState<H, N>,
+}
+
+impl<H: Hash + Eq, N> CurrentRound<H, N> {
+	fn votes_report(&self, voted: &HashSet<AuthorityId>) -> VotesReport {
+		let mut current_weight = 0;
+		let mut missing = Vec::new();
+		for (id, weight) in self.voters.voters() {
+			if voted.contains(id) {
+				current_weight += weight;
+			} else {
+				missing.push(id.clone());
+			}
+		}
+
+		VotesReport { current_weight, missing }
+	}
+}
+
+/// State of the current round followed by the node, shared with the RPC.
+pub struct SharedRoundState<H: Hash + Eq, N>(Arc<RwLock<Option<CurrentRound<H, N>>>>);
+
+impl<H: Hash + Eq, N> Clone for SharedRoundState<H, N> {
+	fn clone(&self) -> Self {
+		SharedRoundState(self.0.clone())
+	}
+}
+
+impl<H: Hash + Eq + Clone, N: Clone> SharedRoundState<H, N> {
+	/// A state without any round, until the node follows one.
+	pub fn empty() -> Self {
+		SharedRoundState(Arc::new(RwLock::new(None)))
+	}
+
+	/// Report the state of the current round, `None` if the node didn't follow any.
+	pub fn report(&self) -> Option<RoundStateReport<H, N>> {
+		let current = self.0.read();
+		let current = current.as_ref()?;
+		let votes = current.votes.as_ref();
+
+		Some(RoundStateReport {
+			set_id: current.set_id,
+			round: current.round,
+			total_weight: current.voters.total_weight(),
+			threshold_weight: current.voters.threshold(),
+			prevotes: votes.map(|votes| current.votes_report(&votes.prevoted)),
+			precommits: votes.map(|votes| current.votes_report(&votes.precommitted)),
+			prevote_ghost: votes.and_then(|votes| votes.state.prevote_ghost.clone()).map(Into::into),
+			estimate: votes.and_then(|votes| votes.state.estimate.clone()).map(Into::into),
+			last_completed: current.last_completed.clone(),
+		})
+	}
+
+	/// The set id and number of the current round, if any.
+	pub(crate) fn current_round(&self) -> Option<(SetId, RoundNumber)> {
+		self.0.read().as_ref().map(|current| (current.set_id, current.round))
+	}
+}
+
+impl<H, N> SharedRoundState<H, N> where
+	H: Hash + Eq + Ord + Clone + Debug,
+	N: Copy + Debug + BlockNumberOps,
+{
+	/// Note that the node follows `round` of the set `set_id`, built on top of `base`, and its
+	/// votes if `follows_votes`. Earlier rounds are ignored.
+	pub(crate) fn note_round(
+		&self,
+		set_id: SetId,
+		voters: &Arc<VoterSet<AuthorityId>>,
+		round: RoundNumber,
+		base: (H, N),
+		follows_votes: bool,
+	) {
+		let mut current = self.0.write();
+		let last_completed = match current.take() {
+			Some(c) if c.set_id == set_id && c.round >= round => {
+				*current = Some(c);
+				return;
+			},
+			Some(c) if c.set_id == set_id => c.last_completed,
+			_ => None,
+		};
+
+		let votes = match follows_votes {
+			true => {
+				let tally = Round::new(RoundParams {
+					round_number: round,
+					voters: (**voters).clone(),
+					base,
+				});
+				let state = tally.state();
+				Some(RoundVotes { prevoted: HashSet::new(), precommitted: HashSet::new(), tally, state })
+			},
+			false => None,
+		};
+		*current = Some(CurrentRound {
+			set_id,
+			voters: voters.clone(),
+			round,
+			votes,
+			last_completed,
+		});
+	}
+
+	/// Note a vote of `id` in `round` of the set `set_id`, ignored if that's not the current round.
+	/// The vote is tallied if its target is known to `chain`.
+	pub(crate) fn note_vote<C: Chain<H, N>>(
+		&self,
+		chain: &C,
+		set_id: SetId,
+		round: RoundNumber,
+		id: &AuthorityId,
+		message: &Message<H, N>,
+	) {
+		let mut current = self.0.write();
+		let votes = match current.as_mut() {
+			Some(c) if c.set_id == set_id && c.round == round => match c.votes.as_mut() {
+				Some(votes) => votes,
+				None => return,
+			},
+			_ => return,
+		};
+
+		let tallied = match message {
+			Message::Prevote(prevote) => {
+				votes.prevoted.insert(id.clone());
+				votes.tally.import_prevote(chain, prevote.clone(), id.clone(), ()).map(drop)
+			},
+			Message::Precommit(precommit) => {
+				votes.precommitted.insert(id.clone());
+				votes.tally.import_precommit(chain, precommit.clone(), id.clone(), ()).map(drop)
+			},
+			Message::PrimaryPropose(_) => return,
+		};
+		match tallied {
+			Ok(()) => votes.state = votes.tally.state(),
+			Err(e) => debug!(target: "afg", "Unable to tally the vote of {:?} in round {}: {:?}", id, round, e),
+		}
+	}
+
+	/// Note that `round` of the set `set_id` completed with a commit finalizing `finalized`, as
+	/// seen by a node not voting. The prevote ghost and estimate are the ones of the votes seen.
+	pub(crate) fn note_commit(&self, set_id: SetId, round: RoundNumber, finalized: (H, N)) {
+		let state = {
+			let current = self.0.read();
+			let votes = current.as_ref()
+				.filter(|c| c.set_id == set_id && c.round == round)
+				.and_then(|c| c.votes.as_ref());
+			RoundState {
+				prevote_ghost: votes.and_then(|votes| votes.state.prevote_ghost.clone()),
+				estimate: votes.and_then(|votes| votes.state.estimate.clone())
+					.filter(|estimate| estimate.1 >= finalized.1)
+					.or_else(|| Some(finalized.clone())),
+				finalized: Some(finalized),
+				completable: true,
+			}
+		};
+		self.note_completed(set_id, round, &state);
+	}
+
+	/// Note that `round` of the set `set_id` completed in `state`.
+	pub(crate) fn note_completed(&self, set_id: SetId, round: RoundNumber, state: &RoundState<H, N>) {
+		let mut current = self.0.write();
+		let current = match current.as_mut() {
+			Some(c) if c.set_id == set_id => c,
+			_ => return,
+		};
+
+		if current.last_completed.as_ref().map_or(false, |c| c.round >= round) {
+			return;
+		}
+
+		current.last_completed = Some(CompletedRoundReport {
+			round,
+			prevote_ghost: state.prevote_ghost.clone().map(Into::into),
+			estimate: state.estimate.clone().map(Into::into),
+			finalized: state.finalized.clone().map(Into::into),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use finality_grandpa::{Error as GrandpaError, Precommit, Prevote};
+	use sp_core::crypto::Public;
+
+	/// A chain where the hash of a block is its number.
+	struct LinearChain;
+
+	impl Chain<u64, u64> for LinearChain {
+		fn ancestry(&self, base: u64, block: u64) -> Result<Vec<u64>, GrandpaError> {
+			if block <= base {
+				return Err(GrandpaError::NotDescendent);
+			}
+			Ok((base + 1..block).rev().collect())
+		}
+
+		fn best_chain_containing(&self, base: u64) -> Option<(u64, u64)> {
+			Some((base, base))
+		}
+	}
+
+	fn voters() -> Arc<VoterSet<AuthorityId>> {
+		Arc::new((1..=4u8).map(|i| (AuthorityId::from_slice(&[i; 32]), 1)).collect())
+	}
+
+	fn id(i: u8) -> AuthorityId {
+		AuthorityId::from_slice(&[i; 32])
+	}
+
+	fn prevote(state: &SharedRoundState<u64, u64>, round: RoundNumber, voter: u8, target: u64) {
+		state.note_vote(&LinearChain, 1, round, &id(voter), &Message::Prevote(Prevote::new(target, target)));
+	}
+
+	fn precommit(state: &SharedRoundState<u64, u64>, round: RoundNumber, voter: u8, target: u64) {
+		state.note_vote(&LinearChain, 1, round, &id(voter), &Message::Precommit(Precommit::new(target, target)));
+	}
+
+	#[test]
+	fn reports_missing_voters_of_the_current_round() {
+		let state = SharedRoundState::<u64, u64>::empty();
+		assert!(state.report().is_none());
+
+		let voters = voters();
+		state.note_round(1, &voters, 5, (0, 0), true);
+		prevote(&state, 5, 1, 10);
+		prevote(&state, 5, 2, 10);
+		precommit(&state, 5, 2, 10);
+		// votes of other rounds are ignored.
+		prevote(&state, 4, 3, 10);
+
+		let report = state.report().unwrap();
+		assert_eq!((report.set_id, report.round), (1, 5));
+		assert_eq!((report.total_weight, report.threshold_weight), (4, 3));
+		assert_eq!(report.prevotes, Some(VotesReport { current_weight: 2, missing: vec![id(3), id(4)] }));
+		assert_eq!(
+			report.precommits,
+			Some(VotesReport { current_weight: 1, missing: vec![id(1), id(3), id(4)] }),
+		);
+
+		state.note_completed(1, 5, &RoundState {
+			prevote_ghost: Some((10, 10)),
+			finalized: Some((10, 10)),
+			estimate: Some((10, 10)),
+			completable: true,
+		});
+		state.note_round(1, &voters, 6, (10, 10), true);
+		// late start of an earlier round doesn't reset the state.
+		state.note_round(1, &voters, 5, (0, 0), true);
+
+		let report = state.report().unwrap();
+		assert_eq!(report.round, 6);
+		assert_eq!(report.prevotes.unwrap().current_weight, 0);
+		assert_eq!(report.last_completed.unwrap().finalized, Some(BlockTarget { hash: 10, number: 10 }));
+
+		// a new set forgets the rounds of the previous one.
+		state.note_round(2, &voters, 1, (10, 10), true);
+		assert!(state.report().unwrap().last_completed.is_none());
+	}
+
+	#[test]
+	fn reports_the_prevote_ghost_and_estimate_of_the_votes_seen() {
+		let state = SharedRoundState::<u64, u64>::empty();
+		state.note_round(1, &voters(), 1, (0, 0), true);
+		prevote(&state, 1, 1, 12);
+		prevote(&state, 1, 2, 10);
+
+		let report = state.report().unwrap();
+		assert_eq!(report.prevote_ghost, None);
+
+		prevote(&state, 1, 3, 11);
+
+		let report = state.report().unwrap();
+		assert_eq!(report.prevote_ghost, Some(BlockTarget { hash: 10, number: 10 }));
+		assert_eq!(report.estimate, Some(BlockTarget { hash: 10, number: 10 }));
+	}
+
+	#[test]
+	fn commits_complete_the_rounds_followed_by_observers() {
+		let state = SharedRoundState::<u64, u64>::empty();
+		let voters = voters();
+		state.note_round(1, &voters, 1, (0, 0), true);
+		for voter in 1..=3 {
+			prevote(&state, 1, voter, 10);
+		}
+
+		state.note_commit(1, 1, (8, 8));
+		state.note_round(1, &voters, 2, (8, 8), true);
+
+		let completed = state.report().unwrap().last_completed.unwrap();
+		assert_eq!(completed.round, 1);
+		assert_eq!(completed.prevote_ghost, Some(BlockTarget { hash: 10, number: 10 }));
+		assert_eq!(completed.estimate, Some(BlockTarget { hash: 10, number: 10 }));
+		assert_eq!(completed.finalized, Some(BlockTarget { hash: 8, number: 8 }));
+	}
+
+	#[test]
+	fn lightweight_observers_report_rounds_without_votes() {
+		let state = SharedRoundState::<u64, u64>::empty();
+		let voters = voters();
+		state.note_round(1, &voters, 1, (0, 0), false);
+		prevote(&state, 1, 1, 10);
+		state.note_commit(1, 1, (10, 10));
+		state.note_round(1, &voters, 2, (10, 10), false);
+
+		let report = state.report().unwrap();
+		assert_eq!(report.round, 2);
+		assert_eq!((report.prevotes, report.precommits), (None, None));
+		assert_eq!(report.prevote_ghost, None);
+		let completed = report.last_completed.unwrap();
+		assert_eq!(completed.prevote_ghost, None);
+		assert_eq!(completed.estimate, Some(BlockTarget { hash: 10, number: 10 }));
+		assert_eq!(completed.finalized, Some(BlockTarget { hash: 10, number: 10 }));
+	}
+}
@@ -433,13 +433,15 @@ fn run_to_completion_with<F>(
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore: Some(keystore),
+				clock: None,
+				threshold_signer: None,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
+				lightweight_observer: false,
 			},
 			link: link,
 			network: net_service,
-			inherent_data_providers: InherentDataProviders::new(),
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
@@ -573,13 +575,15 @@ fn finalize_3_voters_1_full_observer() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore,
+				clock: None,
+				threshold_signer: None,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
+				lightweight_observer: false,
 			},
 			link: link,
 			network: net_service,
-			inherent_data_providers: InherentDataProviders::new(),
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
@@ -742,13 +746,15 @@ fn transition_3_voters_twice_1_full_observer() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore: Some(keystore),
+				clock: None,
+				threshold_signer: None,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
+				lightweight_observer: false,
 			},
 			link: link,
 			network: net_service,
-			inherent_data_providers: InherentDataProviders::new(),
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
@@ -1058,6 +1064,45 @@ fn allows_reimporting_change_blocks() {
 	);
 }
 
+#[test]
+fn checks_finality_hints_against_the_finalized_block() {
+	let block = |number, hint, origin| {
+		let mut header = <Block as BlockT>::Header::new(
+			number,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		);
+		header.digest_mut().push(sp_finality_tracker::finality_hint_digest(hint));
+		BlockImportParams::<Block, ()> {
+			origin,
+			header,
+			justification: None,
+			post_digests: Vec::new(),
+			body: None,
+			storage_changes: None,
+			finalized: false,
+			auxiliary: Vec::new(),
+			fork_choice: ForkChoiceStrategy::LongestChain,
+			allow_missing_state: false,
+			import_existing: false,
+		}
+	};
+
+	assert!(import::check_finality_hint(&block(5, 3, BlockOrigin::NetworkBroadcast), 3).is_ok());
+	assert!(import::check_finality_hint(&block(5, 4, BlockOrigin::NetworkBroadcast), 3).is_err());
+
+	// the hint is verified by the justification of the block.
+	let mut justified = block(5, 4, BlockOrigin::NetworkBroadcast);
+	justified.justification = Some(Vec::new());
+	assert!(import::check_finality_hint(&justified, 3).is_ok());
+
+	// finality is not known during the initial sync, but the hint must be below the block.
+	assert!(import::check_finality_hint(&block(5, 4, BlockOrigin::NetworkInitialSync), 3).is_ok());
+	assert!(import::check_finality_hint(&block(5, 5, BlockOrigin::NetworkInitialSync), 3).is_err());
+}
+
 #[test]
 fn test_bad_justification() {
 	let peers_a = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
@@ -1193,13 +1238,15 @@ fn voter_persists_its_votes() {
 								gossip_duration: TEST_GOSSIP_DURATION,
 								justification_period: 32,
 								keystore: Some(self.keystore.clone()),
+								clock: None,
+								threshold_signer: None,
 								name: Some(format!("peer#{}", 0)),
 								is_authority: true,
 								observer_enabled: true,
+								lightweight_observer: false,
 							},
 							link,
 							network: self.net.lock().peers[0].network_service().clone(),
-							inherent_data_providers: InherentDataProviders::new(),
 							on_exit: Exit,
 							telemetry_on_connect: None,
 							voting_rule: VotingRulesBuilder::default().build(),
@@ -1252,9 +1299,12 @@ fn voter_persists_its_votes() {
 			gossip_duration: TEST_GOSSIP_DURATION,
 			justification_period: 32,
 			keystore: Some(keystore),
+			clock: None,
+			threshold_signer: None,
 			name: Some(format!("peer#{}", 1)),
 			is_authority: true,
 			observer_enabled: true,
+			lightweight_observer: false,
 		};
 
 		let set_state = {
@@ -1279,7 +1329,7 @@ fn voter_persists_its_votes() {
 			communication::Round(1),
 			communication::SetId(0),
 			Arc::new(VoterSet::from_iter(voters)),
-			Some(peers[1].pair().into()),
+			Some(LocalKey::Pair(peers[1].pair().into())),
 			HasVoted::No,
 		);
 
@@ -1400,6 +1450,7 @@ fn finalize_3_voters_1_light_observer() {
 
 	let net = Arc::new(Mutex::new(net));
 	let link = net.lock().peer(3).data.lock().take().expect("link initialized on startup; qed");
+	let round_state = link.round_state().clone();
 
 	let finality_notifications = net.lock().peer(3).client().finality_notification_stream()
 		.map(|v| Ok::<_, ()>(v)).compat()
@@ -1413,9 +1464,12 @@ fn finalize_3_voters_1_light_observer() {
 					gossip_duration: TEST_GOSSIP_DURATION,
 					justification_period: 32,
 					keystore: None,
+					clock: None,
+					threshold_signer: None,
 					name: Some("observer".to_string()),
 					is_authority: false,
 					observer_enabled: true,
+					lightweight_observer: false,
 				},
 				link,
 				net.lock().peers[3].network_service().clone(),
@@ -1426,6 +1480,11 @@ fn finalize_3_voters_1_light_observer() {
 
 		Some(Box::new(finality_notifications.map(|_| ())))
 	});
+
+	// the observer reports the rounds it follows.
+	let report = round_state.report().expect("The observer follows the rounds of the commits");
+	assert!(report.prevotes.is_some());
+	assert!(report.last_completed.unwrap().finalized.is_some());
 }
 
 #[test]
@@ -1546,13 +1605,15 @@ fn voter_catches_up_to_latest_round_when_behind() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore,
+				clock: None,
+				threshold_signer: None,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
+				lightweight_observer: false,
 			},
 			link,
 			network: net.lock().peer(peer_id).network_service().clone(),
-			inherent_data_providers: InherentDataProviders::new(),
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
@@ -1667,9 +1728,12 @@ fn grandpa_environment_respects_voting_rules() {
 			gossip_duration: TEST_GOSSIP_DURATION,
 			justification_period: 32,
 			keystore: None,
+			clock: None,
+			threshold_signer: None,
 			name: None,
 			is_authority: true,
 			observer_enabled: true,
+			lightweight_observer: false,
 		};
 
 		let network = NetworkBridge::new(
@@ -1691,6 +1755,7 @@ fn grandpa_environment_respects_voting_rules() {
 			voters: Arc::new(authority_set.current_authorities()),
 			network,
 			voting_rule,
+			round_state: SharedRoundState::empty(),
 		}
 	};
 
@@ -1854,3 +1919,197 @@ fn imports_justification_for_regular_blocks_on_import() {
 		client.justification(&BlockId::Hash(block_hash)).unwrap().is_some(),
 	);
 }
+
+#[test]
+fn round_timers_follow_the_configured_clock() {
+	use finality_grandpa::voter::Environment as _;
+	use sc_consensus_slots::VirtualClock;
+
+	let threads_pool = futures03::executor::ThreadPool::new().unwrap();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+
+	let peers = &[Ed25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = GrandpaTestNet::new(TestApi::new(voters), 1);
+	let peer = net.peer(0);
+	let network_service = peer.network_service().clone();
+	let link = peer.data.lock().take().unwrap();
+	let PersistentData {
+		ref authority_set,
+		ref consensus_changes,
+		ref set_state,
+		..
+	} = link.persistent_data;
+
+	let clock = VirtualClock::new(Duration::from_secs(1000));
+	let config = Config {
+		gossip_duration: TEST_GOSSIP_DURATION,
+		justification_period: 32,
+		keystore: None,
+		clock: Some(Arc::new(clock.clone())),
+		threshold_signer: None,
+		name: None,
+		is_authority: true,
+		observer_enabled: true,
+		lightweight_observer: false,
+	};
+
+	let network = NetworkBridge::new(
+		network_service.clone(),
+		config.clone(),
+		set_state.clone(),
+		&threads_pool,
+		Exit,
+	);
+
+	let environment = Environment {
+		authority_set: authority_set.clone(),
+		config: config.clone(),
+		consensus_changes: consensus_changes.clone(),
+		client: link.client.clone(),
+		select_chain: link.select_chain.clone(),
+		set_id: authority_set.set_id(),
+		voter_set_state: set_state.clone(),
+		voters: Arc::new(authority_set.current_authorities()),
+		network,
+		voting_rule: Box::new(()) as Box<dyn VotingRule<Block, sc_network_test::TestClient>>,
+		round_state: SharedRoundState::empty(),
+	};
+
+	let round_data = environment.round_data(1);
+	let mut prevote_timer = round_data.prevote_timer;
+	let mut precommit_timer = round_data.precommit_timer;
+	let mut commit_timer = environment.round_commit_timer();
+
+	fn expired<T: Future<Item = ()>>(runtime: &mut current_thread::Runtime, timer: &mut T) -> bool {
+		runtime.block_on(futures::future::poll_fn(|| -> result::Result<_, ()> {
+			Ok(Async::Ready(match timer.poll() {
+				Ok(Async::Ready(())) => true,
+				_ => false,
+			}))
+		})).unwrap()
+	}
+
+	// Waiting on the wall clock doesn't fire the timers, only advancing the virtual clock does.
+	std::thread::sleep(TEST_GOSSIP_DURATION * 2);
+	assert!(!expired(&mut runtime, &mut prevote_timer));
+
+	clock.advance(TEST_GOSSIP_DURATION * 2);
+	assert!(expired(&mut runtime, &mut prevote_timer));
+	assert!(!expired(&mut runtime, &mut precommit_timer));
+
+	clock.advance(TEST_GOSSIP_DURATION * 2);
+	assert!(expired(&mut runtime, &mut precommit_timer));
+
+	// The commit timer waits at most one second.
+	clock.advance(Duration::from_secs(1));
+	assert!(expired(&mut runtime, &mut commit_timer));
+}
+
+#[test]
+fn trusted_checkpoint_is_finalized_and_enacts_its_authority_set() {
+	let peers_a = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+	let peers_b = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob];
+	let api = TestApi::new(make_ids(peers_a));
+	let mut net = GrandpaTestNet::new(api.clone(), 2);
+
+	let import_params = |block: Block| BlockImportParams {
+		origin: BlockOrigin::File,
+		header: block.header,
+		justification: None,
+		post_digests: Vec::new(),
+		body: Some(block.extrinsics),
+		storage_changes: None,
+		finalized: false,
+		auxiliary: Vec::new(),
+		fork_choice: ForkChoiceStrategy::LongestChain,
+		allow_missing_state: false,
+		import_existing: false,
+	};
+
+	// build the chain up to the checkpoint on another peer, scheduling a change
+	// before the checkpoint.
+	let author = net.peer(1).client().as_full().unwrap();
+	let mut block1 = author.new_block_at(&BlockId::Number(0), Default::default(), false)
+		.unwrap().build().unwrap().block;
+	add_scheduled_change(&mut block1, ScheduledChange {
+		next_authorities: make_ids(peers_a),
+		delay: 0,
+	});
+	(&*author).import_block(import_params(block1.clone()), HashMap::new()).unwrap();
+	let block2 = author.new_block_at(&BlockId::Hash(block1.hash()), Default::default(), false)
+		.unwrap().build().unwrap().block;
+	let fork1 = author.new_block_at(&BlockId::Number(0), Default::default(), false)
+		.unwrap().build().unwrap().block;
+
+	let (client, backend) = match net.peer(0).client().clone() {
+		PeersClient::Full(client, backend) => (client, backend),
+		_ => panic!("only full clients are used in test"),
+	};
+	let (mut block_import, link) = block_import_with_checkpoint(
+		client.clone(),
+		&api,
+		LongestChain::new(backend),
+		Some(TrustedCheckpoint {
+			hash: block2.hash(),
+			number: 2,
+			set_id: 5,
+			authorities: make_ids(peers_b),
+		}),
+	).unwrap();
+
+	// the change before the checkpoint doesn't require a justification.
+	assert_eq!(
+		block_import.import_block(import_params(block1.clone()), HashMap::new()).unwrap(),
+		ImportResult::Imported(ImportedAux { is_new_best: true, ..Default::default() }),
+	);
+
+	// any other block at the checkpoint height is bad.
+	assert_eq!(
+		block_import.check_block(sp_consensus::BlockCheckParams {
+			hash: H256::random(),
+			number: 2,
+			parent_hash: block1.hash(),
+			allow_missing_state: false,
+			import_existing: false,
+		}).unwrap(),
+		ImportResult::KnownBad,
+	);
+
+	// the checkpoint is finalized and its authority set takes over.
+	assert_eq!(
+		block_import.import_block(import_params(block2.clone()), HashMap::new()).unwrap(),
+		ImportResult::Imported(ImportedAux {
+			clear_justification_requests: true,
+			is_new_best: true,
+			..Default::default()
+		}),
+	);
+	assert_eq!(client.chain_info().finalized_hash, block2.hash());
+
+	// blocks below the checkpoint on another fork are bad once it is imported.
+	assert_eq!(
+		block_import.check_block(sp_consensus::BlockCheckParams {
+			hash: fork1.hash(),
+			number: 1,
+			parent_hash: fork1.header.parent_hash,
+			allow_missing_state: false,
+			import_existing: false,
+		}).unwrap(),
+		ImportResult::KnownBad,
+	);
+	assert_eq!(
+		block_import.import_block(import_params(fork1), HashMap::new()).unwrap(),
+		ImportResult::KnownBad,
+	);
+	// while its ancestors are still known.
+	assert_eq!(
+		block_import.import_block(import_params(block1), HashMap::new()).unwrap(),
+		ImportResult::AlreadyInChain,
+	);
+
+	let authority_set = link.persistent_data.authority_set.inner().read();
+	assert_eq!(authority_set.current(), (5, &make_ids(peers_b)[..]));
+	assert_eq!(authority_set.pending_changes().count(), 0);
+}
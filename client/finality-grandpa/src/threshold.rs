@@ -0,0 +1,648 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Experimental threshold signing of GRANDPA votes.
+//!
+//! The GRANDPA key of a voter is split between `n` hosts with Shamir's secret sharing, and any
+//! `t` of them sign a vote together following the two-round FROST protocol. The result is a
+//! plain ed25519 signature of the original key, so other voters don't know the difference.
+//!
+//! Each host keeps a record of the votes it helped signing and refuses to sign a different vote
+//! of the same kind in the same round. Votes in rounds older than the record are refused
+//! altogether. Keys are only split so that any two sets of `t` hosts share at least two of them,
+//! i.e. `2t - n >= 2`, so the signers of two conflicting votes would include an honest host that
+//! refuses: a single compromised host can't produce an equivocation.
+//!
+//! The shares are stored in the keystore of each host with the `SHARE_KEY_TYPE` key type, under
+//! the public key of the split key, and the hosts exchange commitments and signature shares over
+//! a request/response protocol. The voting host coordinates the signing, and holds a share of
+//! the key itself.
+
+use std::{collections::{BTreeMap, HashSet, VecDeque}, sync::Arc, time::Duration};
+
+use curve25519_dalek::{
+	constants::ED25519_BASEPOINT_POINT, edwards::{CompressedEdwardsY, EdwardsPoint}, scalar::Scalar,
+};
+use futures03::{channel::mpsc, future::{self, BoxFuture}, prelude::*, stream::FuturesUnordered};
+use log::{debug, trace};
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use rand::{rngs::OsRng, RngCore};
+use sc_client_api::backend::AuxStore;
+use sc_keystore::KeyStorePtr;
+use sc_network::{
+	specialization::NetworkSpecialization, ExHashT, IncomingRequest, NetworkService, PeerId,
+	RequestFailure, RequestResponseConfig,
+};
+use sha2::{Digest, Sha512};
+use sp_core::{crypto::KeyTypeId, ed25519, hashing::blake2_256, Pair};
+use sp_finality_grandpa::{AuthorityId, AuthorityPair, AuthoritySignature, RoundNumber, SetId};
+use sp_runtime::traits::Block as BlockT;
+
+use crate::Message;
+
+/// Key type of the key shares in the keystore.
+pub const SHARE_KEY_TYPE: KeyTypeId = KeyTypeId(*b"grth");
+
+/// Name of the threshold signing protocol.
+pub const PROTOCOL_NAME: &str = "/substrate/grandpa-threshold/1";
+
+/// Number of incoming requests waiting to be answered before new ones are refused.
+pub const INBOUND_QUEUE_SIZE: usize = 64;
+
+/// Number of signing sessions a host keeps nonces for.
+const MAX_PENDING_SESSIONS: usize = 64;
+
+/// Number of signed votes a host remembers, per key.
+const MAX_RECORDED_VOTES: usize = 1024;
+
+/// Prefix of the auxiliary storage key of the votes signed with a key.
+const SIGNED_VOTES_KEY: &[u8] = b"grandpa_threshold_votes";
+
+/// Domain separator of the binding factors.
+const BINDING_CONTEXT: &[u8] = b"substrate-grandpa-frost-binding";
+
+/// A share of a GRANDPA key.
+#[derive(Clone, Encode, Decode)]
+pub struct KeyShare {
+	/// Index of the share, from 1.
+	pub index: u16,
+	/// Number of shares needed to sign.
+	pub threshold: u16,
+	/// Public key of the split key.
+	pub group_key: AuthorityId,
+	secret: [u8; 32],
+}
+
+impl KeyShare {
+	/// Hex encoding of the share, as inserted into the keystore.
+	pub fn to_hex(&self) -> String {
+		format!("0x{}", hex::encode(self.encode()))
+	}
+
+	/// Decode a share encoded with `to_hex`.
+	pub fn from_hex(encoded: &str) -> Result<Self, String> {
+		let encoded = encoded.trim_start_matches("0x");
+		let bytes = hex::decode(encoded).map_err(|e| format!("Invalid key share: {}", e))?;
+		KeyShare::decode(&mut &bytes[..]).map_err(|e| format!("Invalid key share: {}", e.what()))
+	}
+}
+
+/// Split `pair` into `parties` shares, any `threshold` of which are needed to sign.
+///
+/// Any two sets of `threshold` shares must have two shares in common, so that a compromised host
+/// and any other host can't sign conflicting votes: the threshold must be at least half the
+/// shares plus one, e.g. 3 of 4 or 4 of 6.
+pub fn split_key(pair: &AuthorityPair, threshold: u16, parties: u16) -> Result<Vec<KeyShare>, String> {
+	if threshold > parties || 2 * threshold as u32 < parties as u32 + 2 {
+		return Err(format!(
+			"The threshold must be at least half the number of shares plus one and at most the \
+			number of shares, got {} of {}",
+			threshold,
+			parties,
+		));
+	}
+
+	let pair: &ed25519::Pair = pair.as_ref();
+	let expanded = Sha512::digest(&pair.seed()[..]);
+	let mut secret = [0u8; 32];
+	secret.copy_from_slice(&expanded[..32]);
+	secret[0] &= 248;
+	secret[31] &= 127;
+	secret[31] |= 64;
+
+	let coefficients = std::iter::once(Scalar::from_bytes_mod_order(secret))
+		.chain((1..threshold).map(|_| Scalar::random(&mut OsRng)))
+		.collect::<Vec<_>>();
+
+	Ok((1..=parties).map(|index| {
+		let x = Scalar::from(index as u64);
+		let share = coefficients.iter().rev().fold(Scalar::zero(), |acc, coefficient| acc * x + coefficient);
+		KeyShare {
+			index,
+			threshold,
+			group_key: pair.public().into(),
+			secret: share.to_bytes(),
+		}
+	}).collect())
+}
+
+/// Commitment of a host to the nonces of a signing session.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct Commitment {
+	index: u16,
+	hiding: [u8; 32],
+	binding: [u8; 32],
+}
+
+/// Request of the threshold signing protocol.
+#[derive(Encode, Decode)]
+enum Request {
+	/// First round: commit to fresh nonces for `session`.
+	Commit {
+		group_key: AuthorityId,
+		session: u64,
+	},
+	/// Second round: sign `payload` with the nonces of `session`.
+	Sign {
+		group_key: AuthorityId,
+		session: u64,
+		payload: Vec<u8>,
+		commitments: Vec<Commitment>,
+	},
+}
+
+/// Configuration of the threshold signing protocol.
+///
+/// Pass `Some` inbound queue in order to answer requests with `CoSigner::answer_requests`.
+pub fn request_response_config(
+	inbound_queue: Option<mpsc::Sender<IncomingRequest>>,
+) -> RequestResponseConfig {
+	RequestResponseConfig {
+		name: PROTOCOL_NAME.into(),
+		max_request_size: 64 * 1024,
+		max_response_size: 1024,
+		request_timeout: Duration::from_secs(2),
+		inbound_queue,
+	}
+}
+
+/// Something that sends requests of the threshold signing protocol.
+pub trait RequestSender: Send + Sync {
+	/// Send `request` to `peer` and return the response.
+	fn request(&self, peer: PeerId, request: Vec<u8>) -> BoxFuture<'static, Result<Vec<u8>, RequestFailure>>;
+}
+
+impl<B, S, H> RequestSender for NetworkService<B, S, H> where
+	B: BlockT + 'static,
+	S: NetworkSpecialization<B>,
+	H: ExHashT,
+{
+	fn request(&self, peer: PeerId, request: Vec<u8>) -> BoxFuture<'static, Result<Vec<u8>, RequestFailure>> {
+		NetworkService::request(self, peer, PROTOCOL_NAME, request).boxed()
+	}
+}
+
+/// A host taking part in threshold signing.
+trait Participant: Send + Sync {
+	/// Public keys of the keys this host holds a share of.
+	fn group_keys(&self) -> Vec<AuthorityId>;
+	/// The share this host holds of `group_key`.
+	fn share(&self, group_key: &AuthorityId) -> Result<KeyShare, String>;
+	/// Commit to fresh nonces for `session`.
+	fn commit(&self, group_key: &AuthorityId, session: u64) -> Result<Commitment, String>;
+	/// Sign `payload` with the nonces of `session`, returning the signature share.
+	fn sign(
+		&self,
+		group_key: &AuthorityId,
+		session: u64,
+		payload: &[u8],
+		commitments: &[Commitment],
+	) -> Result<Scalar, String>;
+}
+
+/// Votes signed with a key, as stored in the auxiliary storage.
+#[derive(Default, Encode, Decode)]
+struct SignedVotes {
+	/// Votes up to this set and round, included, are refused since their record was pruned.
+	floor: Option<(SetId, RoundNumber)>,
+	/// Hash of the vote signed for a set, a round and a kind of vote.
+	votes: BTreeMap<(SetId, RoundNumber, u8), [u8; 32]>,
+}
+
+/// The share of a host in threshold signing.
+///
+/// Holds the nonces of the pending signing sessions, and the record of the signed votes.
+pub struct CoSigner<Block, C> {
+	keystore: KeyStorePtr,
+	client: Arc<C>,
+	/// Nonces of the pending sessions, oldest first.
+	sessions: Mutex<VecDeque<((AuthorityId, u64), (Scalar, Scalar))>>,
+	/// Held while checking and recording a vote.
+	record_lock: Mutex<()>,
+	_phantom: std::marker::PhantomData<Block>,
+}
+
+impl<Block: BlockT, C: AuxStore + Send + Sync> CoSigner<Block, C> {
+	/// Create a host signing with the shares in `keystore` and recording the signed votes in
+	/// the auxiliary storage of `client`.
+	pub fn new(keystore: KeyStorePtr, client: Arc<C>) -> Self {
+		CoSigner {
+			keystore,
+			client,
+			sessions: Mutex::new(VecDeque::new()),
+			record_lock: Mutex::new(()),
+			_phantom: Default::default(),
+		}
+	}
+
+	/// Answer the requests received on `requests` from `peers` until the channel is closed.
+	pub async fn answer_requests(
+		self: Arc<Self>,
+		peers: Vec<PeerId>,
+		mut requests: mpsc::Receiver<IncomingRequest>,
+	) {
+		let peers = peers.into_iter().collect::<HashSet<_>>();
+		while let Some(request) = requests.next().await {
+			if !peers.contains(&request.peer) {
+				debug!(target: "afg", "Refusing threshold signing request from unknown peer {}", request.peer);
+				continue;
+			}
+
+			match self.answer_request(&request.payload) {
+				Ok(response) => {
+					let _ = request.pending_response.send(response);
+				},
+				Err(err) => debug!(
+					target: "afg",
+					"Refusing threshold signing request from {}: {}",
+					request.peer,
+					err,
+				),
+			}
+		}
+	}
+
+	fn answer_request(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+		match Request::decode(&mut &payload[..]).map_err(|e| e.what().to_string())? {
+			Request::Commit { group_key, session } =>
+				self.commit(&group_key, session).map(|commitment| commitment.encode()),
+			Request::Sign { group_key, session, payload, commitments } =>
+				self.sign(&group_key, session, &payload, &commitments).map(|share| share.to_bytes().encode()),
+		}
+	}
+
+	/// Record that `payload` is signed with `group_key`, unless it is a vote conflicting with
+	/// one signed before.
+	fn check_and_record(&self, group_key: &AuthorityId, payload: &[u8]) -> Result<(), String> {
+		let (message, round, set_id) = <(Message<Block>, RoundNumber, SetId)>::decode(&mut &payload[..])
+			.map_err(|_| "Payload isn't a vote".to_string())?;
+		let kind = match message {
+			finality_grandpa::Message::Prevote(_) => 0,
+			finality_grandpa::Message::Precommit(_) => 1,
+			finality_grandpa::Message::PrimaryPropose(_) => 2,
+		};
+		let hash = blake2_256(payload);
+
+		let _lock = self.record_lock.lock();
+		let key = (SIGNED_VOTES_KEY, group_key).encode();
+		let mut record = match self.client.get_aux(&key).map_err(|e| e.to_string())? {
+			Some(encoded) => SignedVotes::decode(&mut &encoded[..])
+				.map_err(|e| format!("Invalid record of signed votes: {}", e.what()))?,
+			None => SignedVotes::default(),
+		};
+
+		if record.floor.map_or(false, |floor| (set_id, round) <= floor) {
+			return Err(format!("Vote of round {} of set {} is too old", round, set_id));
+		}
+		match record.votes.get(&(set_id, round, kind)) {
+			Some(signed) if *signed == hash => return Ok(()),
+			Some(_) => return Err(format!(
+				"A different vote has already been signed in round {} of set {}",
+				round,
+				set_id,
+			)),
+			None => {},
+		}
+
+		record.votes.insert((set_id, round, kind), hash);
+		while record.votes.len() > MAX_RECORDED_VOTES {
+			let oldest = *record.votes.keys().next().expect("more votes than the maximum; qed");
+			record.votes.remove(&oldest);
+			record.floor = Some((oldest.0, oldest.1));
+		}
+
+		// The record must be persisted before the signature share leaves this host.
+		self.client.insert_aux(&[(&key[..], &record.encode()[..])], &[]).map_err(|e| e.to_string())
+	}
+}
+
+impl<Block: BlockT, C: AuxStore + Send + Sync> Participant for CoSigner<Block, C> {
+	fn group_keys(&self) -> Vec<AuthorityId> {
+		self.keystore.read()
+			.public_keys_by_type::<ed25519::Public>(SHARE_KEY_TYPE)
+			.map(|keys| keys.into_iter().map(Into::into).collect())
+			.unwrap_or_default()
+	}
+
+	fn share(&self, group_key: &AuthorityId) -> Result<KeyShare, String> {
+		let encoded = self.keystore.read()
+			.raw_secret(group_key.as_ref(), SHARE_KEY_TYPE)
+			.map_err(|e| format!("No share of {}: {}", group_key, e))?;
+		let share = KeyShare::from_hex(&encoded)?;
+		if &share.group_key != group_key {
+			return Err(format!("Share of {} stored as a share of {}", share.group_key, group_key));
+		}
+		Ok(share)
+	}
+
+	fn commit(&self, group_key: &AuthorityId, session: u64) -> Result<Commitment, String> {
+		let share = self.share(group_key)?;
+		let (hiding, binding) = (Scalar::random(&mut OsRng), Scalar::random(&mut OsRng));
+
+		let mut sessions = self.sessions.lock();
+		if sessions.iter().any(|(key, _)| key.0 == *group_key && key.1 == session) {
+			return Err(format!("Session {} already exists", session));
+		}
+		if sessions.len() == MAX_PENDING_SESSIONS {
+			sessions.pop_front();
+		}
+		sessions.push_back(((group_key.clone(), session), (hiding, binding)));
+
+		Ok(Commitment {
+			index: share.index,
+			hiding: (hiding * ED25519_BASEPOINT_POINT).compress().to_bytes(),
+			binding: (binding * ED25519_BASEPOINT_POINT).compress().to_bytes(),
+		})
+	}
+
+	fn sign(
+		&self,
+		group_key: &AuthorityId,
+		session: u64,
+		payload: &[u8],
+		commitments: &[Commitment],
+	) -> Result<Scalar, String> {
+		let share = self.share(group_key)?;
+
+		// Nonces are used at most once, whatever the outcome.
+		let (hiding, binding) = {
+			let mut sessions = self.sessions.lock();
+			let position = sessions.iter()
+				.position(|(key, _)| key.0 == *group_key && key.1 == session)
+				.ok_or_else(|| format!("Unknown session {}", session))?;
+			sessions.remove(position).expect("position was just found; qed").1
+		};
+
+		let indices = commitments.iter().map(|c| c.index).collect::<Vec<_>>();
+		if indices.len() < share.threshold as usize || indices.windows(2).any(|w| w[0] >= w[1]) {
+			return Err("Invalid set of commitments".into());
+		}
+		let own = commitments.iter().find(|c| c.index == share.index)
+			.ok_or_else(|| "Own commitment missing".to_string())?;
+		if own.hiding != (hiding * ED25519_BASEPOINT_POINT).compress().to_bytes()
+			|| own.binding != (binding * ED25519_BASEPOINT_POINT).compress().to_bytes()
+		{
+			return Err("Own commitment altered".into());
+		}
+
+		self.check_and_record(group_key, payload)?;
+
+		let group_commitment = group_commitment(payload, commitments)?;
+		let challenge = challenge(&group_commitment, group_key, payload);
+		let secret = Scalar::from_canonical_bytes(share.secret)
+			.ok_or_else(|| "Invalid key share".to_string())?;
+
+		Ok(hiding
+			+ binding * binding_factor(share.index, payload, commitments)
+			+ lagrange_coefficient(share.index, &indices) * secret * challenge)
+	}
+}
+
+/// Signs GRANDPA votes with keys split between several hosts.
+pub struct ThresholdSigner {
+	local: Arc<dyn Participant>,
+	peers: Vec<PeerId>,
+	network: Arc<dyn RequestSender>,
+}
+
+impl ThresholdSigner {
+	/// Create a signer using the shares of `local` and requesting the other shares from `peers`.
+	pub fn new<Block, C>(
+		local: Arc<CoSigner<Block, C>>,
+		peers: Vec<PeerId>,
+		network: Arc<dyn RequestSender>,
+	) -> Self where
+		Block: BlockT,
+		C: AuxStore + Send + Sync + 'static,
+	{
+		ThresholdSigner { local, peers, network }
+	}
+
+	/// Public keys of the keys the local host holds a share of.
+	pub(crate) fn group_keys(&self) -> Vec<AuthorityId> {
+		self.local.group_keys()
+	}
+
+	/// Sign `payload` with `group_key`.
+	pub(crate) fn sign(
+		&self,
+		group_key: AuthorityId,
+		payload: Vec<u8>,
+	) -> BoxFuture<'static, Result<AuthoritySignature, String>> {
+		let local = self.local.clone();
+		let peers = self.peers.clone();
+		let network = self.network.clone();
+		async move {
+			let share = local.share(&group_key)?;
+			let session = OsRng.next_u64();
+			let own_commitment = local.commit(&group_key, session)?;
+
+			// First round, with as many hosts as needed among the first to answer.
+			let needed = share.threshold as usize - 1;
+			let request = Request::Commit { group_key: group_key.clone(), session }.encode();
+			let mut pending = peers.into_iter()
+				.map(|peer| network.request(peer.clone(), request.clone()).map(move |response| (peer, response)))
+				.collect::<FuturesUnordered<_>>();
+			let mut cosigners = Vec::with_capacity(needed);
+			while cosigners.len() < needed {
+				let (peer, response) = match pending.next().await {
+					Some(response) => response,
+					None => return Err(format!(
+						"Only {} of the {} other shares needed are available",
+						cosigners.len(),
+						needed,
+					)),
+				};
+				let commitment = response.map_err(|e| e.to_string())
+					.and_then(|response| Commitment::decode(&mut &response[..]).map_err(|e| e.what().to_string()));
+				match commitment {
+					Ok(commitment) => cosigners.push((peer, commitment)),
+					Err(err) => trace!(target: "afg", "No commitment from {}: {}", peer, err),
+				}
+			}
+
+			let mut commitments = cosigners.iter()
+				.map(|(_, commitment)| commitment.clone())
+				.chain(std::iter::once(own_commitment))
+				.collect::<Vec<_>>();
+			commitments.sort_by_key(|commitment| commitment.index);
+
+			// Second round. The local share goes first, so that its record of signed votes is
+			// checked before involving the other hosts.
+			let mut signature_share = local.sign(&group_key, session, &payload, &commitments)?;
+			let request = Request::Sign {
+				group_key: group_key.clone(),
+				session,
+				payload: payload.clone(),
+				commitments: commitments.clone(),
+			}.encode();
+			let responses = future::join_all(
+				cosigners.iter().map(|(peer, _)| network.request(peer.clone(), request.clone()))
+			).await;
+			for ((peer, _), response) in cosigners.iter().zip(responses) {
+				let share = response.map_err(|e| e.to_string())
+					.and_then(|response| <[u8; 32]>::decode(&mut &response[..]).map_err(|e| e.what().to_string()))
+					.and_then(|bytes| Scalar::from_canonical_bytes(bytes).ok_or_else(|| "Invalid scalar".into()))
+					.map_err(|err| format!("No signature share from {}: {}", peer, err))?;
+				signature_share += share;
+			}
+
+			let signature = signature(group_commitment(&payload, &commitments)?, signature_share);
+			if !AuthorityPair::verify(&signature, &payload, &group_key) {
+				return Err("Invalid signature share received".into());
+			}
+			Ok(signature)
+		}.boxed()
+	}
+}
+
+fn signature(group_commitment: EdwardsPoint, signature_share: Scalar) -> AuthoritySignature {
+	let mut signature = [0u8; 64];
+	signature[..32].copy_from_slice(group_commitment.compress().as_bytes());
+	signature[32..].copy_from_slice(signature_share.as_bytes());
+	ed25519::Signature::from_raw(signature).into()
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+	let mut hasher = Sha512::new();
+	for part in parts {
+		hasher.input(part);
+	}
+	Scalar::from_hash(hasher)
+}
+
+/// Factor binding the nonces of host `index` to the payload and the other commitments.
+fn binding_factor(index: u16, payload: &[u8], commitments: &[Commitment]) -> Scalar {
+	hash_to_scalar(&[BINDING_CONTEXT, &index.encode(), &payload.encode(), &commitments.encode()])
+}
+
+/// The `R` part of the signature.
+fn group_commitment(payload: &[u8], commitments: &[Commitment]) -> Result<EdwardsPoint, String> {
+	let decompress = |bytes: &[u8; 32]| CompressedEdwardsY(*bytes)
+		.decompress()
+		.ok_or_else(|| "Invalid commitment".to_string());
+	commitments.iter().try_fold(EdwardsPoint::default(), |acc, commitment| {
+		let factor = binding_factor(commitment.index, payload, commitments);
+		Ok(acc + decompress(&commitment.hiding)? + decompress(&commitment.binding)? * factor)
+	})
+}
+
+/// The ed25519 challenge.
+fn challenge(group_commitment: &EdwardsPoint, group_key: &AuthorityId, payload: &[u8]) -> Scalar {
+	hash_to_scalar(&[group_commitment.compress().as_bytes(), group_key.as_ref(), payload])
+}
+
+/// Coefficient of share `index` when interpolating the secret from the shares `indices`.
+fn lagrange_coefficient(index: u16, indices: &[u16]) -> Scalar {
+	let x = Scalar::from(index as u64);
+	let (numerator, denominator) = indices.iter()
+		.filter(|other| **other != index)
+		.map(|other| Scalar::from(*other as u64))
+		.fold((Scalar::one(), Scalar::one()), |(num, den), other| (num * other, den * (other - x)));
+	numerator * denominator.invert()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{H256, traits::BareCryptoStore};
+	use substrate_test_runtime_client::{runtime::Block, TestClient};
+	use tempfile::TempDir;
+
+	type TestCoSigner = CoSigner<Block, TestClient>;
+
+	fn cosigners(threshold: u16, parties: u16) -> (AuthorityId, Vec<(TestCoSigner, TempDir)>) {
+		let pair = AuthorityPair::from_seed(&[7; 32]);
+		let shares = split_key(&pair, threshold, parties).unwrap();
+		let cosigners = shares.into_iter().map(|share| {
+			let dir = TempDir::new().unwrap();
+			let keystore = sc_keystore::Store::open(dir.path(), None).unwrap();
+			keystore.write()
+				.insert_unknown(SHARE_KEY_TYPE, &share.to_hex(), share.group_key.as_ref())
+				.unwrap();
+			let client = Arc::new(substrate_test_runtime_client::new());
+			(CoSigner::new(keystore, client), dir)
+		}).collect();
+		(pair.public(), cosigners)
+	}
+
+	fn vote(round: RoundNumber, target: u8) -> Vec<u8> {
+		let message: Message<Block> = finality_grandpa::Message::Prevote(finality_grandpa::Prevote {
+			target_hash: H256::repeat_byte(target),
+			target_number: 1,
+		});
+		(message, round, 0 as SetId).encode()
+	}
+
+	fn sign(
+		group_key: &AuthorityId,
+		cosigners: &[&(TestCoSigner, TempDir)],
+		payload: &[u8],
+	) -> Result<AuthoritySignature, String> {
+		let session = OsRng.next_u64();
+		let commitments = cosigners.iter()
+			.map(|(cosigner, _)| cosigner.commit(group_key, session))
+			.collect::<Result<Vec<_>, _>>()?;
+		let mut signature_share = Scalar::zero();
+		for (cosigner, _) in cosigners {
+			signature_share += cosigner.sign(group_key, session, payload, &commitments)?;
+		}
+		Ok(signature(group_commitment(payload, &commitments)?, signature_share))
+	}
+
+	#[test]
+	fn quorums_must_share_two_hosts() {
+		let pair = AuthorityPair::from_seed(&[7; 32]);
+		assert!(split_key(&pair, 2, 3).is_err());
+		assert!(split_key(&pair, 3, 5).is_err());
+		assert!(split_key(&pair, 5, 4).is_err());
+		assert_eq!(split_key(&pair, 2, 2).unwrap().len(), 2);
+		assert_eq!(split_key(&pair, 3, 4).unwrap().len(), 4);
+	}
+
+	#[test]
+	fn any_threshold_of_shares_signs() {
+		let (group_key, cosigners) = cosigners(3, 4);
+		let quorums = [(0, 1, 2), (0, 1, 3), (0, 2, 3), (1, 2, 3)];
+		for (round, (a, b, c)) in quorums.iter().enumerate() {
+			let payload = vote(round as RoundNumber, 1);
+			let quorum = [&cosigners[*a], &cosigners[*b], &cosigners[*c]];
+			let signature = sign(&group_key, &quorum, &payload).unwrap();
+			assert!(AuthorityPair::verify(&signature, &payload, &group_key));
+		}
+
+		// not enough shares
+		assert!(sign(&group_key, &[&cosigners[0], &cosigners[1]], &vote(10, 1)).is_err());
+	}
+
+	#[test]
+	fn conflicting_votes_are_refused() {
+		let (group_key, cosigners) = cosigners(3, 4);
+		let quorum = |a: usize, b: usize, c: usize| [&cosigners[a], &cosigners[b], &cosigners[c]];
+		assert!(sign(&group_key, &quorum(0, 1, 2), &vote(1, 1)).is_ok());
+		// signing the same vote again is fine
+		assert!(sign(&group_key, &quorum(1, 2, 3), &vote(1, 1)).is_ok());
+		// a different vote in the same round is refused by the hosts that signed the first one,
+		// any quorum including at least two of them.
+		assert!(sign(&group_key, &quorum(0, 1, 3), &vote(1, 2)).is_err());
+		assert!(sign(&group_key, &quorum(0, 2, 3), &vote(1, 2)).is_err());
+		assert!(sign(&group_key, &quorum(1, 2, 3), &vote(1, 2)).is_err());
+		// anything else goes
+		assert!(sign(&group_key, &quorum(1, 2, 3), &vote(2, 2)).is_ok());
+		assert!(sign(&group_key, &quorum(0, 1, 2), b"not a vote").is_err());
+	}
+}
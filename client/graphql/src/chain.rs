@@ -0,0 +1,175 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reading and decoding the blocks the queries are resolved from.
+
+use std::sync::Arc;
+use codec::{Decode, Encode};
+use log::warn;
+use parking_lot::Mutex;
+use serde_json::Value;
+use sc_client::Client;
+use sc_client_api::{backend, CallExecutor};
+use sp_api::{Metadata, ProvideRuntimeApi};
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::{hashing::twox_128, storage::StorageKey};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, SaturatedConversion}};
+use substrate_frame_decode::{Decoder, TypeRegistry};
+
+use crate::schema::{self, hex};
+
+/// The blocks of the chain, as seen by the resolvers.
+pub trait Chain: Send + Sync {
+	/// Number of the best block.
+	fn best_number(&self) -> u32;
+
+	/// The block of the best chain with the given number, `None` if there is none.
+	fn block_by_number(&self, number: u32) -> ClientResult<Option<schema::Block>>;
+
+	/// The block with the given hash, `None` if unknown.
+	fn block_by_hash(&self, hash: &[u8]) -> ClientResult<Option<schema::Block>>;
+}
+
+/// The blocks of a full client, with their extrinsics and events decoded using the metadata of
+/// their runtime.
+pub struct ClientChain<B, E, Block: BlockT, RA> {
+	client: Arc<Client<B, E, Block, RA>>,
+	/// Decoder for the runtime of the latest block read, with its spec version.
+	decoder: Mutex<Option<(u32, Arc<Decoder>)>>,
+}
+
+impl<B, E, Block: BlockT, RA> ClientChain<B, E, Block, RA> {
+	/// Read the blocks of `client`.
+	pub fn new(client: Arc<Client<B, E, Block, RA>>) -> Self {
+		ClientChain { client, decoder: Mutex::new(None) }
+	}
+}
+
+impl<B, E, Block, RA> ClientChain<B, E, Block, RA> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = ClientError>,
+{
+	fn block(&self, id: BlockId<Block>) -> ClientResult<Option<schema::Block>> {
+		let header = match self.client.header(&id)? {
+			Some(header) => header,
+			None => return Ok(None),
+		};
+		let hash = header.hash();
+		let number = (*header.number()).saturated_into::<u32>();
+		let id = BlockId::Hash(hash);
+		let extrinsics = self.client.body(&id)?.unwrap_or_default();
+		let raw_events = self.client.storage(&id, &events_key())?
+			.map(|events| events.0)
+			.unwrap_or_default();
+
+		let decoder = self.decoder(&id);
+		let extrinsics = extrinsics.iter().enumerate()
+			.map(|(index, extrinsic)| {
+				let raw = extrinsic.encode();
+				let decoded = decoder.as_ref().and_then(|decoder| decoder.decode_extrinsic(&raw).ok());
+				schema::Extrinsic::new(number, index as u32, &raw, decoded.as_ref())
+			})
+			.collect();
+		let events = decoder.as_ref()
+			.and_then(|decoder| decode_events(decoder, &raw_events))
+			.map(|records| records.iter().enumerate()
+				.filter_map(|(index, record)| schema::Event::new(number, index as u32, record))
+				.collect()
+			)
+			.unwrap_or_default();
+
+		Ok(Some(schema::Block {
+			number: number as i32,
+			hash: hex(hash.as_ref()),
+			parent_hash: hex(header.parent_hash().as_ref()),
+			extrinsics,
+			events,
+		}))
+	}
+
+	/// The decoder for the runtime of the block `id`, `None` if its metadata isn't available.
+	fn decoder(&self, id: &BlockId<Block>) -> Option<Arc<Decoder>> {
+		let spec_version = self.client.runtime_version_at(id).ok()?.spec_version;
+		let mut cached = self.decoder.lock();
+		if let Some((version, decoder)) = cached.as_ref() {
+			if *version == spec_version {
+				return Some(decoder.clone());
+			}
+		}
+
+		let metadata = self.client.runtime_api().metadata(id)
+			.map_err(|e| warn!(target: "graphql", "Failed to get the metadata of block {}: {}", id, e))
+			.ok()?;
+		let decoder = Decoder::from_bytes(&metadata, TypeRegistry::with_defaults())
+			.map_err(|e| warn!(target: "graphql", "Failed to decode the metadata of block {}: {}", id, e))
+			.ok()?;
+		let decoder = Arc::new(decoder);
+		*cached = Some((spec_version, decoder.clone()));
+		Some(decoder)
+	}
+}
+
+impl<B, E, Block, RA> Chain for ClientChain<B, E, Block, RA> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = ClientError>,
+{
+	fn best_number(&self) -> u32 {
+		self.client.chain_info().best_number.saturated_into()
+	}
+
+	fn block_by_number(&self, number: u32) -> ClientResult<Option<schema::Block>> {
+		self.block(BlockId::Number(number.saturated_into()))
+	}
+
+	fn block_by_hash(&self, hash: &[u8]) -> ClientResult<Option<schema::Block>> {
+		match Block::Hash::decode(&mut &hash[..]) {
+			Ok(hash) => self.block(BlockId::Hash(hash)),
+			Err(_) => Ok(None),
+		}
+	}
+}
+
+/// Storage key of the events of a block.
+fn events_key() -> StorageKey {
+	let mut key = twox_128(b"System").to_vec();
+	key.extend_from_slice(&twox_128(b"Events"));
+	StorageKey(key)
+}
+
+/// Decode the content of `System::Events`, `None` if it can't be decoded.
+fn decode_events(decoder: &Decoder, raw_events: &[u8]) -> Option<Vec<Value>> {
+	if raw_events.is_empty() {
+		return Some(Vec::new());
+	}
+	match decoder.decode_events(raw_events) {
+		Ok(Value::Array(records)) => Some(records),
+		Ok(_) => None,
+		Err(e) => {
+			warn!(target: "graphql", "Failed to decode events: {}", e);
+			None
+		},
+	}
+}
@@ -0,0 +1,264 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! GraphQL endpoint querying the chain.
+//!
+//! Front-ends query the blocks, the extrinsics and the events of the chain, and the ones of an
+//! account, with filters and pagination, instead of writing their own indexer. Extrinsics and
+//! events are decoded using the metadata of the runtime of their block. Queries are sent as
+//! JSON in the body of `POST` requests, following the GraphQL over HTTP conventions.
+
+#![warn(missing_docs)]
+
+mod chain;
+mod schema;
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+use hyper::{
+	body::HttpBody, header, service::{make_service_fn, service_fn}, Body, Method, Request, Response,
+	Server, StatusCode,
+};
+use juniper::http::GraphQLRequest;
+use log::info;
+use sc_client::Client;
+use sc_client_api::{backend, CallExecutor};
+use sp_api::{Metadata, ProvideRuntimeApi};
+use sp_runtime::traits::Block as BlockT;
+
+pub use chain::{Chain, ClientChain};
+pub use schema::{Context, Schema, schema, DEFAULT_PAGE_SIZE, MAX_BLOCK_RANGE, MAX_PAGE_SIZE};
+
+/// Maximum size in bytes of the body of a request.
+pub const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+/// Error starting the server.
+pub type Error = hyper::Error;
+
+/// Serve the GraphQL endpoint of `client` on `addr`.
+pub async fn run_server<B, E, Block, RA>(
+	addr: SocketAddr,
+	client: Arc<Client<B, E, Block, RA>>,
+) -> Result<(), Error> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = sp_blockchain::Error>,
+{
+	let endpoint = Arc::new(Endpoint {
+		schema: schema(),
+		context: Context::new(Arc::new(ClientChain::new(client))),
+	});
+	let make_service = make_service_fn(move |_| {
+		let endpoint = endpoint.clone();
+		async move {
+			Ok::<_, Infallible>(service_fn(move |request| endpoint.clone().respond(request)))
+		}
+	});
+
+	let server = Server::try_bind(&addr)?.serve(make_service);
+	info!(target: "graphql", "GraphQL server started at {}", addr);
+	server.await
+}
+
+/// The schema with the context its queries are resolved in.
+struct Endpoint {
+	schema: Schema,
+	context: Context,
+}
+
+impl Endpoint {
+	/// Respond to an HTTP request.
+	async fn respond(self: Arc<Self>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+		if request.method() != Method::POST {
+			return Ok(status(StatusCode::METHOD_NOT_ALLOWED))
+		}
+		let body = match read_body(request.into_body()).await {
+			Some(body) => body,
+			None => return Ok(status(StatusCode::PAYLOAD_TOO_LARGE)),
+		};
+		let query = match serde_json::from_slice::<GraphQLRequest>(&body) {
+			Ok(query) => query,
+			Err(_) => return Ok(status(StatusCode::BAD_REQUEST)),
+		};
+
+		// The resolvers read the database, keep them off the threads serving the requests.
+		let response = tokio::task::spawn_blocking(move || self.execute(&query)).await;
+		Ok(match response {
+			Ok((ok, json)) => Response::builder()
+				.status(if ok { StatusCode::OK } else { StatusCode::BAD_REQUEST })
+				.header(header::CONTENT_TYPE, "application/json")
+				.body(Body::from(json))
+				.expect("The status and the header are valid; qed"),
+			Err(_) => status(StatusCode::INTERNAL_SERVER_ERROR),
+		})
+	}
+
+	/// Execute a query, returning whether it succeeded and the JSON of its response.
+	fn execute(&self, query: &GraphQLRequest) -> (bool, String) {
+		let response = query.execute(&self.schema, &self.context);
+		let json = serde_json::to_string(&response)
+			.expect("GraphQL responses serialize to JSON; qed");
+		(response.is_ok(), json)
+	}
+}
+
+/// Read a body of at most `MAX_REQUEST_SIZE` bytes, `None` if it is larger or fails.
+async fn read_body(mut body: Body) -> Option<Vec<u8>> {
+	let mut read = Vec::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = chunk.ok()?;
+		if read.len() + chunk.len() > MAX_REQUEST_SIZE {
+			return None
+		}
+		read.extend_from_slice(&chunk);
+	}
+	Some(read)
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+	Response::builder()
+		.status(status)
+		.body(Body::empty())
+		.expect("The status is valid; qed")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::{json, Value};
+	use schema::{Block, Event, Extrinsic};
+
+	const ALICE: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+	/// A chain of blocks each holding a transfer from Alice, with its event.
+	struct TestChain(Vec<Block>);
+
+	impl TestChain {
+		fn new(blocks: u32) -> Self {
+			TestChain((0..blocks).map(|number| Block {
+				number: number as i32,
+				hash: format!("0x{:064x}", number),
+				parent_hash: format!("0x{:064x}", number.saturating_sub(1)),
+				extrinsics: vec![Extrinsic::new(number, 0, &[number as u8], Some(&json!({
+					"signature": { "address": { "Id": ALICE } },
+					"call": { "module": "Balances", "call": "transfer", "args": {} },
+				})))],
+				events: vec![Event::new(number, 0, &json!({
+					"phase": { "ApplyExtrinsic": 0 },
+					"event": { "module": "Balances", "event": "Transfer", "args": [ALICE, number] },
+				})).unwrap()],
+			}).collect())
+		}
+	}
+
+	impl Chain for TestChain {
+		fn best_number(&self) -> u32 {
+			self.0.len() as u32 - 1
+		}
+
+		fn block_by_number(&self, number: u32) -> sp_blockchain::Result<Option<Block>> {
+			Ok(self.0.get(number as usize).cloned())
+		}
+
+		fn block_by_hash(&self, hash: &[u8]) -> sp_blockchain::Result<Option<Block>> {
+			let number = self.0.iter().position(|block| block.hash == schema::hex(hash));
+			number.map_or(Ok(None), |number| self.block_by_number(number as u32))
+		}
+	}
+
+	fn query(chain: TestChain, query: &str) -> (bool, Value) {
+		let endpoint = Endpoint { schema: schema(), context: Context::new(Arc::new(chain)) };
+		let request = serde_json::from_value(json!({ "query": query })).unwrap();
+		let (ok, response) = endpoint.execute(&request);
+		(ok, serde_json::from_str(&response).unwrap())
+	}
+
+	#[test]
+	fn queries_blocks() {
+		let (ok, response) = query(TestChain::new(5), "{
+			blocks(from: 3, first: 10) { number }
+			block(hash: \"0x0000000000000000000000000000000000000000000000000000000000000001\") {
+				number
+				extrinsics { signer call }
+			}
+		}");
+
+		assert!(ok);
+		assert_eq!(response["data"]["blocks"], json!([{ "number": 3 }, { "number": 4 }]));
+		assert_eq!(response["data"]["block"], json!({
+			"number": 1,
+			"extrinsics": [{ "signer": ALICE, "call": "transfer" }],
+		}));
+	}
+
+	#[test]
+	fn pages_through_the_events_of_an_account() {
+		let page = |after: &str| query(TestChain::new(5), &format!("{{
+			account(id: \"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY\") {{
+				events(fromBlock: 1, first: 2{}) {{ nodes {{ blockNumber name }} endCursor hasNextPage }}
+			}}
+		}}", after)).1["data"]["account"]["events"].clone();
+
+		let first = page("");
+		assert_eq!(first["nodes"], json!([
+			{ "blockNumber": 1, "name": "Transfer" },
+			{ "blockNumber": 2, "name": "Transfer" },
+		]));
+		assert_eq!(first["hasNextPage"], true);
+
+		let second = page(&format!(", after: {}", first["endCursor"]));
+		assert_eq!(second["nodes"], json!([
+			{ "blockNumber": 3, "name": "Transfer" },
+			{ "blockNumber": 4, "name": "Transfer" },
+		]));
+		assert_eq!(second["hasNextPage"], false);
+	}
+
+	#[test]
+	fn filters_extrinsics() {
+		let (ok, response) = query(TestChain::new(3), "{
+			transfers: extrinsics(filter: { fromBlock: 0, module: \"Balances\", call: \"transfer\" }) {
+				nodes { blockNumber }
+			}
+			remarks: extrinsics(filter: { fromBlock: 0, call: \"remark\" }) { nodes { blockNumber } }
+		}");
+
+		assert!(ok);
+		assert_eq!(
+			response["data"]["transfers"]["nodes"],
+			json!([{ "blockNumber": 0 }, { "blockNumber": 1 }, { "blockNumber": 2 }]),
+		);
+		assert_eq!(response["data"]["remarks"]["nodes"], json!([]));
+	}
+
+	#[test]
+	fn rejects_unbounded_queries() {
+		let (_, response) = query(TestChain::new(3), &format!(
+			"{{ events(filter: {{ fromBlock: 0, toBlock: {} }}) {{ nodes {{ name }} }} }}",
+			MAX_BLOCK_RANGE,
+		));
+		assert!(response["errors"].is_array());
+
+		let (_, response) = query(TestChain::new(3), &format!(
+			"{{ blocks(from: 0, first: {}) {{ number }} }}",
+			MAX_PAGE_SIZE + 1,
+		));
+		assert!(response["errors"].is_array());
+	}
+}
@@ -0,0 +1,574 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The GraphQL schema and its resolvers.
+//!
+//! Extrinsics and events are found by scanning the blocks of a range, which is bounded by
+//! `MAX_BLOCK_RANGE`. Pages of results are bounded by `MAX_PAGE_SIZE` and continue after the
+//! `endCursor` of the previous page.
+
+use std::sync::Arc;
+use juniper::{EmptyMutation, FieldResult, RootNode};
+use serde_json::Value;
+use sp_core::{bytes, crypto::{AccountId32, Ss58Codec}};
+
+use crate::chain::Chain;
+
+/// Maximum number of blocks scanned by a query of extrinsics or events.
+pub const MAX_BLOCK_RANGE: u32 = 1000;
+
+/// Maximum number of results of a page.
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+/// Number of results of a page when not given.
+pub const DEFAULT_PAGE_SIZE: i32 = 20;
+
+/// Context of the resolvers: the chain queried.
+pub struct Context {
+	chain: Arc<dyn Chain>,
+}
+
+impl Context {
+	/// Resolve the queries from `chain`.
+	pub fn new(chain: Arc<dyn Chain>) -> Self {
+		Context { chain }
+	}
+}
+
+impl juniper::Context for Context {}
+
+/// The GraphQL schema, which has no mutations.
+pub type Schema = RootNode<'static, Query, EmptyMutation<Context>>;
+
+/// Create the schema.
+pub fn schema() -> Schema {
+	Schema::new(Query, EmptyMutation::new())
+}
+
+/// A block of the chain.
+#[derive(Clone, Debug, PartialEq, juniper::GraphQLObject)]
+pub struct Block {
+	/// Number of the block.
+	pub number: i32,
+	/// Hash of the block.
+	pub hash: String,
+	/// Hash of the parent of the block.
+	pub parent_hash: String,
+	/// The extrinsics of the block.
+	pub extrinsics: Vec<Extrinsic>,
+	/// The events of the block, empty if they can't be decoded.
+	pub events: Vec<Event>,
+}
+
+/// An extrinsic included in a block.
+#[derive(Clone, Debug, PartialEq, juniper::GraphQLObject)]
+pub struct Extrinsic {
+	/// Number of the block including the extrinsic.
+	pub block_number: i32,
+	/// Index of the extrinsic in its block.
+	pub index: i32,
+	/// Account which signed the extrinsic, `null` if unsigned, signed through an account index
+	/// or not decoded.
+	pub signer: Option<String>,
+	/// Module of the call, `null` if not decoded.
+	pub module: Option<String>,
+	/// Name of the call, `null` if not decoded.
+	pub call: Option<String>,
+	/// JSON of the arguments of the call, `null` if not decoded.
+	pub args: Option<String>,
+	/// SCALE encoding of the extrinsic.
+	pub raw: String,
+}
+
+impl Extrinsic {
+	/// The extrinsic `index` of block `block_number`, given its encoding and its decoded content.
+	pub fn new(block_number: u32, index: u32, raw: &[u8], decoded: Option<&Value>) -> Self {
+		let call = decoded.map(|decoded| &decoded["call"]);
+		Extrinsic {
+			block_number: block_number as i32,
+			index: index as i32,
+			signer: decoded
+				.and_then(|decoded| decoded["signature"]["address"]["Id"].as_str())
+				.map(str::to_lowercase),
+			module: call.and_then(|call| call["module"].as_str()).map(String::from),
+			call: call.and_then(|call| call["call"].as_str()).map(String::from),
+			args: call.map(|call| call["args"].to_string()),
+			raw: hex(raw),
+		}
+	}
+}
+
+/// An event emitted by a block.
+#[derive(Clone, Debug, PartialEq, juniper::GraphQLObject)]
+pub struct Event {
+	/// Number of the block emitting the event.
+	pub block_number: i32,
+	/// Index of the event in its block.
+	pub index: i32,
+	/// Index of the extrinsic emitting the event, `null` if emitted during the initialization
+	/// or the finalization of the block.
+	pub extrinsic_index: Option<i32>,
+	/// Module of the event.
+	pub module: String,
+	/// Name of the event.
+	pub name: String,
+	/// JSON of the arguments of the event.
+	pub args: String,
+	/// The 32 bytes values among the arguments, which include the accounts concerned by the
+	/// event.
+	pub accounts: Vec<String>,
+}
+
+impl Event {
+	/// The event `index` of block `block_number`, given its decoded record. `None` if the record
+	/// isn't one of an event.
+	pub fn new(block_number: u32, index: u32, record: &Value) -> Option<Self> {
+		let event = &record["event"];
+		let args = &event["args"];
+		let mut accounts = Vec::new();
+		collect_accounts(args, &mut accounts);
+		Some(Event {
+			block_number: block_number as i32,
+			index: index as i32,
+			extrinsic_index: record["phase"]["ApplyExtrinsic"].as_u64().map(|index| index as i32),
+			module: event["module"].as_str()?.into(),
+			name: event["event"].as_str()?.into(),
+			args: args.to_string(),
+			accounts,
+		})
+	}
+}
+
+/// Collect the 32 bytes values found in `value`, once each.
+fn collect_accounts(value: &Value, accounts: &mut Vec<String>) {
+	match value {
+		Value::String(s) if s.len() == 66 && s.starts_with("0x") => {
+			let account = s.to_lowercase();
+			if !accounts.contains(&account) {
+				accounts.push(account);
+			}
+		},
+		Value::Array(values) => values.iter().for_each(|value| collect_accounts(value, accounts)),
+		Value::Object(values) => values.values().for_each(|value| collect_accounts(value, accounts)),
+		_ => {},
+	}
+}
+
+/// Filter of the extrinsics.
+#[derive(Default, juniper::GraphQLInputObject)]
+pub struct ExtrinsicFilter {
+	/// Number of the first block scanned.
+	pub from_block: i32,
+	/// Number of the last block scanned, the best block or the last one of the longest range
+	/// allowed if not given.
+	pub to_block: Option<i32>,
+	/// Module of the calls.
+	pub module: Option<String>,
+	/// Name of the calls.
+	pub call: Option<String>,
+	/// Account which signed the extrinsics, SS58 or hex encoded.
+	pub signer: Option<String>,
+}
+
+impl ExtrinsicFilter {
+	fn matches(&self, extrinsic: &Extrinsic, signer: Option<&String>) -> bool {
+		(self.module.is_none() || self.module == extrinsic.module)
+			&& (self.call.is_none() || self.call == extrinsic.call)
+			&& (signer.is_none() || signer == extrinsic.signer.as_ref())
+	}
+}
+
+/// Filter of the events.
+#[derive(Default, juniper::GraphQLInputObject)]
+pub struct EventFilter {
+	/// Number of the first block scanned.
+	pub from_block: i32,
+	/// Number of the last block scanned, the best block or the last one of the longest range
+	/// allowed if not given.
+	pub to_block: Option<i32>,
+	/// Module of the events.
+	pub module: Option<String>,
+	/// Name of the events.
+	pub name: Option<String>,
+	/// Account concerned by the events, SS58 or hex encoded.
+	pub account: Option<String>,
+}
+
+impl EventFilter {
+	fn matches(&self, event: &Event, account: Option<&String>) -> bool {
+		self.module.as_ref().map_or(true, |module| *module == event.module)
+			&& self.name.as_ref().map_or(true, |name| *name == event.name)
+			&& account.map_or(true, |account| event.accounts.contains(account))
+	}
+}
+
+/// A page of extrinsics.
+#[derive(Debug, juniper::GraphQLObject)]
+pub struct ExtrinsicPage {
+	/// The extrinsics of the page.
+	pub nodes: Vec<Extrinsic>,
+	/// Cursor of the last extrinsic of the page, to pass as `after` for the next page.
+	pub end_cursor: Option<String>,
+	/// Whether more extrinsics match within the blocks scanned.
+	pub has_next_page: bool,
+}
+
+/// A page of events.
+#[derive(Debug, juniper::GraphQLObject)]
+pub struct EventPage {
+	/// The events of the page.
+	pub nodes: Vec<Event>,
+	/// Cursor of the last event of the page, to pass as `after` for the next page.
+	pub end_cursor: Option<String>,
+	/// Whether more events match within the blocks scanned.
+	pub has_next_page: bool,
+}
+
+/// An account of the chain.
+pub struct Account {
+	id: String,
+}
+
+#[juniper::object(Context = Context)]
+impl Account {
+	/// Hex encoded id of the account.
+	fn id(&self) -> String {
+		self.id.clone()
+	}
+
+	/// The extrinsics signed by the account.
+	fn extrinsics(
+		&self,
+		context: &Context,
+		from_block: i32,
+		to_block: Option<i32>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> FieldResult<ExtrinsicPage> {
+		let filter = ExtrinsicFilter { from_block, to_block, signer: Some(self.id.clone()), ..Default::default() };
+		extrinsics(context, &filter, first, after)
+	}
+
+	/// The events concerning the account.
+	fn events(
+		&self,
+		context: &Context,
+		from_block: i32,
+		to_block: Option<i32>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> FieldResult<EventPage> {
+		let filter = EventFilter { from_block, to_block, account: Some(self.id.clone()), ..Default::default() };
+		events(context, &filter, first, after)
+	}
+}
+
+/// The root of the queries.
+pub struct Query;
+
+#[juniper::object(Context = Context)]
+impl Query {
+	/// The block of the best chain with the given number, or the block with the given hash.
+	fn block(context: &Context, number: Option<i32>, hash: Option<String>) -> FieldResult<Option<Block>> {
+		match (number, hash) {
+			(Some(number), None) => Ok(context.chain.block_by_number(block_number(number)?)?),
+			(None, Some(hash)) => Ok(context.chain.block_by_hash(&parse_hex(&hash)?)?),
+			_ => Err("Exactly one of `number` and `hash` must be given.".into()),
+		}
+	}
+
+	/// The blocks of the best chain, starting with the block `from`.
+	fn blocks(context: &Context, from: i32, first: Option<i32>) -> FieldResult<Vec<Block>> {
+		let first = page_size(first)? as u32;
+		let from = block_number(from)?;
+		let mut blocks = Vec::new();
+		for number in from..from.saturating_add(first) {
+			match context.chain.block_by_number(number)? {
+				Some(block) => blocks.push(block),
+				None => break,
+			}
+		}
+		Ok(blocks)
+	}
+
+	/// The extrinsics matching the filter.
+	fn extrinsics(
+		context: &Context,
+		filter: ExtrinsicFilter,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> FieldResult<ExtrinsicPage> {
+		extrinsics(context, &filter, first, after)
+	}
+
+	/// The events matching the filter.
+	fn events(
+		context: &Context,
+		filter: EventFilter,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> FieldResult<EventPage> {
+		events(context, &filter, first, after)
+	}
+
+	/// The account with the given SS58 or hex encoded id.
+	fn account(id: String) -> FieldResult<Account> {
+		Ok(Account { id: account_id(&id)? })
+	}
+}
+
+fn extrinsics(
+	context: &Context,
+	filter: &ExtrinsicFilter,
+	first: Option<i32>,
+	after: Option<String>,
+) -> FieldResult<ExtrinsicPage> {
+	let signer = filter.signer.as_ref().map(|signer| account_id(signer)).transpose()?;
+	let (nodes, has_next_page) = scan(
+		context,
+		(filter.from_block, filter.to_block),
+		first,
+		after,
+		|block| block.extrinsics,
+		|extrinsic| (extrinsic.block_number, extrinsic.index),
+		|extrinsic| filter.matches(extrinsic, signer.as_ref()),
+	)?;
+	Ok(ExtrinsicPage {
+		end_cursor: nodes.last().map(|extrinsic| cursor((extrinsic.block_number, extrinsic.index))),
+		nodes,
+		has_next_page,
+	})
+}
+
+fn events(
+	context: &Context,
+	filter: &EventFilter,
+	first: Option<i32>,
+	after: Option<String>,
+) -> FieldResult<EventPage> {
+	let account = filter.account.as_ref().map(|account| account_id(account)).transpose()?;
+	let (nodes, has_next_page) = scan(
+		context,
+		(filter.from_block, filter.to_block),
+		first,
+		after,
+		|block| block.events,
+		|event| (event.block_number, event.index),
+		|event| filter.matches(event, account.as_ref()),
+	)?;
+	Ok(EventPage {
+		end_cursor: nodes.last().map(|event| cursor((event.block_number, event.index))),
+		nodes,
+		has_next_page,
+	})
+}
+
+/// Collect a page of the items of the blocks in `range` which match, after the `after` cursor.
+///
+/// Returns the items and whether more match within the range.
+fn scan<T>(
+	context: &Context,
+	range: (i32, Option<i32>),
+	first: Option<i32>,
+	after: Option<String>,
+	items: impl Fn(Block) -> Vec<T>,
+	position: impl Fn(&T) -> (i32, i32),
+	matches: impl Fn(&T) -> bool,
+) -> FieldResult<(Vec<T>, bool)> {
+	let first = page_size(first)? as usize;
+	let (from, to) = block_range(context.chain.best_number(), range)?;
+	let after = after.map(|after| parse_cursor(&after)).transpose()?;
+	// Blocks before the cursor have nothing left to return.
+	let from = after.map_or(from, |(number, _)| from.max(number as u32));
+
+	let mut nodes = Vec::new();
+	for number in from..=to {
+		let block = match context.chain.block_by_number(number)? {
+			Some(block) => block,
+			None => break,
+		};
+		for item in items(block) {
+			if after.map_or(false, |after| position(&item) <= after) || !matches(&item) {
+				continue
+			}
+			if nodes.len() == first {
+				return Ok((nodes, true))
+			}
+			nodes.push(item);
+		}
+	}
+	Ok((nodes, false))
+}
+
+/// The numbers of the first and last blocks of `range` to scan.
+fn block_range(best_number: u32, range: (i32, Option<i32>)) -> FieldResult<(u32, u32)> {
+	let from = block_number(range.0)?;
+	let to = match range.1 {
+		Some(to) => block_number(to)?,
+		None => best_number.min(from.saturating_add(MAX_BLOCK_RANGE - 1)),
+	};
+	if to >= from && to - from >= MAX_BLOCK_RANGE {
+		return Err(format!("At most {} blocks can be scanned at once.", MAX_BLOCK_RANGE).into())
+	}
+	Ok((from, to))
+}
+
+fn block_number(number: i32) -> FieldResult<u32> {
+	if number < 0 {
+		return Err("Block numbers can't be negative.".into())
+	}
+	Ok(number as u32)
+}
+
+fn page_size(first: Option<i32>) -> FieldResult<i32> {
+	match first.unwrap_or(DEFAULT_PAGE_SIZE) {
+		first if first > 0 && first <= MAX_PAGE_SIZE => Ok(first),
+		_ => Err(format!("Pages hold between 1 and {} results.", MAX_PAGE_SIZE).into()),
+	}
+}
+
+/// The cursor of the item at the given position, the number of its block and its index.
+fn cursor(position: (i32, i32)) -> String {
+	format!("{}:{}", position.0, position.1)
+}
+
+fn parse_cursor(cursor: &str) -> FieldResult<(i32, i32)> {
+	let mut parts = cursor.splitn(2, ':').map(str::parse::<i32>);
+	match (parts.next(), parts.next()) {
+		(Some(Ok(number)), Some(Ok(index))) => Ok((number, index)),
+		_ => Err(format!("Invalid cursor {}.", cursor).into()),
+	}
+}
+
+/// Hex encoding of the id of the account given SS58 or hex encoded.
+fn account_id(id: &str) -> FieldResult<String> {
+	if let Ok(account) = AccountId32::from_ss58check(id) {
+		return Ok(hex(account.as_ref()))
+	}
+	match parse_hex(id) {
+		Ok(ref account) if account.len() == 32 => Ok(hex(account)),
+		_ => Err(format!("Invalid account {}.", id).into()),
+	}
+}
+
+fn parse_hex(data: &str) -> FieldResult<Vec<u8>> {
+	bytes::from_hex(data).map_err(|e| format!("Invalid hex {}: {}", data, e).into())
+}
+
+/// Hex encoding of `data`, `0x` prefixed.
+pub fn hex(data: &[u8]) -> String {
+	bytes::to_hex(data, false)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	const ALICE: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+	const BOB: &str = "0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48";
+
+	#[test]
+	fn reads_decoded_extrinsics() {
+		let decoded = json!({
+			"signature": { "address": { "Id": ALICE }, "signature": {}, "extra": {} },
+			"call": { "module": "Balances", "call": "transfer", "args": { "dest": { "Id": BOB }, "value": 5 } },
+		});
+		let extrinsic = Extrinsic::new(3, 1, &[1, 2], Some(&decoded));
+		assert_eq!(extrinsic, Extrinsic {
+			block_number: 3,
+			index: 1,
+			signer: Some(ALICE.into()),
+			module: Some("Balances".into()),
+			call: Some("transfer".into()),
+			args: Some(json!({ "dest": { "Id": BOB }, "value": 5 }).to_string()),
+			raw: "0x0102".into(),
+		});
+
+		let unsigned = Extrinsic::new(3, 0, &[1, 2], Some(&json!({ "call": decoded["call"].clone() })));
+		assert_eq!(unsigned.signer, None);
+		assert_eq!(Extrinsic::new(3, 0, &[1, 2], None).module, None);
+	}
+
+	#[test]
+	fn reads_decoded_events() {
+		let record = json!({
+			"phase": { "ApplyExtrinsic": 1 },
+			"event": { "module": "Balances", "event": "Transfer", "args": [ALICE, BOB, 5, 1] },
+			"topics": [],
+		});
+		let event = Event::new(3, 2, &record).unwrap();
+		assert_eq!(event.extrinsic_index, Some(1));
+		assert_eq!((event.module.as_str(), event.name.as_str()), ("Balances", "Transfer"));
+		assert_eq!(event.accounts, vec![ALICE.to_string(), BOB.to_string()]);
+
+		let record = json!({
+			"phase": "Finalization",
+			"event": { "module": "System", "event": "CodeUpdated", "args": [] },
+			"topics": [],
+		});
+		assert_eq!(Event::new(3, 0, &record).unwrap().extrinsic_index, None);
+		assert_eq!(Event::new(3, 0, &json!({ "phase": "Finalization" })), None);
+	}
+
+	#[test]
+	fn filters_match_accounts() {
+		let transfer = Event::new(1, 0, &json!({
+			"phase": { "ApplyExtrinsic": 1 },
+			"event": { "module": "Balances", "event": "Transfer", "args": [ALICE, BOB, 5, 1] },
+		})).unwrap();
+		let bob = account_id(BOB).unwrap();
+		let filter = EventFilter { module: Some("Balances".into()), ..Default::default() };
+		assert!(filter.matches(&transfer, Some(&bob)));
+		assert!(!filter.matches(&transfer, Some(&hex(&[0; 32]))));
+		let filter = EventFilter { name: Some("Deposit".into()), ..Default::default() };
+		assert!(!filter.matches(&transfer, None));
+
+		let extrinsic = Extrinsic::new(1, 1, &[], Some(&json!({
+			"signature": { "address": { "Id": ALICE } },
+			"call": { "module": "Balances", "call": "transfer", "args": {} },
+		})));
+		let filter = ExtrinsicFilter { call: Some("transfer".into()), ..Default::default() };
+		assert!(filter.matches(&extrinsic, Some(&ALICE.to_string())));
+		assert!(!filter.matches(&extrinsic, Some(&bob)));
+	}
+
+	#[test]
+	fn parses_accounts() {
+		let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+		assert_eq!(account_id(alice).unwrap(), ALICE);
+		assert_eq!(account_id(&ALICE.to_uppercase().replace("0X", "0x")).unwrap(), ALICE);
+		assert!(account_id("0x0102").is_err());
+		assert!(account_id("alice").is_err());
+	}
+
+	#[test]
+	fn bounds_ranges_and_pages() {
+		assert_eq!(block_range(10, (5, None)).unwrap(), (5, 10));
+		assert_eq!(block_range(5000, (5, None)).unwrap(), (5, 5 + MAX_BLOCK_RANGE - 1));
+		assert_eq!(block_range(5000, (5, Some(7))).unwrap(), (5, 7));
+		assert!(block_range(5000, (5, Some(5 + MAX_BLOCK_RANGE as i32))).is_err());
+		assert!(block_range(5000, (-1, None)).is_err());
+
+		assert_eq!(page_size(None).unwrap(), DEFAULT_PAGE_SIZE);
+		assert_eq!(page_size(Some(MAX_PAGE_SIZE)).unwrap(), MAX_PAGE_SIZE);
+		assert!(page_size(Some(MAX_PAGE_SIZE + 1)).is_err());
+		assert!(page_size(Some(0)).is_err());
+
+		assert_eq!(parse_cursor(&cursor((12, 3))).unwrap(), (12, 3));
+		assert!(parse_cursor("12").is_err());
+	}
+}
@@ -0,0 +1,202 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Assembly of the finalized block messages.
+
+use std::sync::Arc;
+use codec::Encode;
+use log::warn;
+use parking_lot::Mutex;
+use serde_json::Value;
+use sc_client::Client;
+use sc_client_api::{backend, CallExecutor};
+use sp_api::{Metadata, ProvideRuntimeApi};
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::{hashing::twox_128, storage::StorageKey};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion}};
+use substrate_frame_decode::{Decoder, TypeRegistry};
+
+use crate::proto;
+use crate::state_diffs::{StateDiff, StateDiffs};
+
+/// Builds the messages of the finalized blocks.
+pub struct Blocks<B, E, Block: BlockT, RA> {
+	client: Arc<Client<B, E, Block, RA>>,
+	state_diffs: Arc<StateDiffs<Block::Hash>>,
+	/// Decoder for the runtime of the latest block streamed, with its spec version.
+	decoder: Mutex<Option<(u32, Arc<Decoder>)>>,
+}
+
+impl<B, E, Block, RA> Blocks<B, E, Block, RA> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = ClientError>,
+{
+	/// Create the builder of the messages, taking the storage changes from `state_diffs`.
+	pub fn new(client: Arc<Client<B, E, Block, RA>>, state_diffs: Arc<StateDiffs<Block::Hash>>) -> Self {
+		Blocks { client, state_diffs, decoder: Mutex::new(None) }
+	}
+
+	/// The client the blocks come from.
+	pub fn client(&self) -> &Arc<Client<B, E, Block, RA>> {
+		&self.client
+	}
+
+	/// Build the message of the finalized block `number`.
+	pub fn block(&self, number: NumberFor<Block>) -> ClientResult<proto::FinalizedBlock> {
+		let id = BlockId::Number(number);
+		let header = self.client.header(&id)?
+			.ok_or_else(|| ClientError::UnknownBlock(format!("{}", number)))?;
+		let hash = header.hash();
+		let id = BlockId::Hash(hash);
+		let extrinsics = self.client.body(&id)?.unwrap_or_default();
+		let raw_events = self.client.storage(&id, &events_key())?
+			.map(|events| events.0)
+			.unwrap_or_default();
+
+		let decoder = self.decoder(&id);
+		let events = decoder.as_ref().and_then(|decoder| decode_events(decoder, &raw_events));
+		let state_diff = self.state_diffs.get(&hash);
+
+		Ok(proto::FinalizedBlock {
+			number: number.saturated_into::<u64>(),
+			hash: hash.as_ref().to_vec(),
+			parent_hash: header.parent_hash().as_ref().to_vec(),
+			header: header.encode(),
+			extrinsics: extrinsics.iter().map(Encode::encode).collect(),
+			raw_events,
+			events_decoded: events.is_some(),
+			events: events.unwrap_or_default(),
+			state_diff_known: state_diff.is_some(),
+			state_diff: state_diff
+				.map(|diff| storage_changes(decoder.as_ref().map(|d| &**d), diff))
+				.unwrap_or_default(),
+		})
+	}
+
+	/// The decoder for the runtime of the block `id`, `None` if its metadata isn't available.
+	fn decoder(&self, id: &BlockId<Block>) -> Option<Arc<Decoder>> {
+		let spec_version = self.client.runtime_version_at(id).ok()?.spec_version;
+		let mut cached = self.decoder.lock();
+		if let Some((version, decoder)) = cached.as_ref() {
+			if *version == spec_version {
+				return Some(decoder.clone());
+			}
+		}
+
+		let metadata = self.client.runtime_api().metadata(id)
+			.map_err(|e| warn!(target: "grpc", "Failed to get the metadata of block {}: {}", id, e))
+			.ok()?;
+		let decoder = Decoder::from_bytes(&metadata, TypeRegistry::with_defaults())
+			.map_err(|e| warn!(target: "grpc", "Failed to decode the metadata of block {}: {}", id, e))
+			.ok()?;
+		let decoder = Arc::new(decoder);
+		*cached = Some((spec_version, decoder.clone()));
+		Some(decoder)
+	}
+}
+
+/// Storage key of the events of a block.
+fn events_key() -> StorageKey {
+	let mut key = twox_128(b"System").to_vec();
+	key.extend_from_slice(&twox_128(b"Events"));
+	StorageKey(key)
+}
+
+/// Decode the content of `System::Events`, `None` if it can't be decoded.
+fn decode_events(decoder: &Decoder, raw_events: &[u8]) -> Option<Vec<proto::Event>> {
+	if raw_events.is_empty() {
+		return Some(Vec::new());
+	}
+	match decoder.decode_events(raw_events) {
+		Ok(Value::Array(records)) => records.iter().map(event).collect(),
+		Ok(_) => None,
+		Err(e) => {
+			warn!(target: "grpc", "Failed to decode events: {}", e);
+			None
+		},
+	}
+}
+
+/// Convert a decoded event record.
+fn event(record: &Value) -> Option<proto::Event> {
+	let phase = match &record["phase"] {
+		Value::String(phase) if phase == "Finalization" => proto::event::Phase::Finalization(true),
+		phase => proto::event::Phase::ApplyExtrinsic(phase["ApplyExtrinsic"].as_u64()? as u32),
+	};
+	let event = &record["event"];
+	Some(proto::Event {
+		phase: Some(phase),
+		module: event["module"].as_str()?.into(),
+		name: event["event"].as_str()?.into(),
+		args_json: event["args"].to_string(),
+		topics: record["topics"].as_array()?.iter()
+			.map(|topic| topic.as_str().map(String::from))
+			.collect::<Option<_>>()?,
+	})
+}
+
+/// Convert the storage changes of a block, decoding them when possible.
+fn storage_changes(decoder: Option<&Decoder>, diff: StateDiff) -> Vec<proto::StorageChange> {
+	diff.into_iter().map(|(key, value)| {
+		let item = decoder.and_then(|decoder| decoder.storage_item(&key.0).ok().flatten());
+		let decoded = decoder.and_then(|decoder|
+			decoder.decode_storage(&key.0, value.as_ref().map(|v| &v.0[..])).ok()
+		);
+		proto::StorageChange {
+			module: item.map(|(module, _)| module.into()).unwrap_or_default(),
+			storage: item.map(|(_, storage)| storage.into()).unwrap_or_default(),
+			decoded_json: decoded.map(|decoded| decoded.to_string()).unwrap_or_default(),
+			deleted: value.is_none(),
+			value: value.map(|value| value.0).unwrap_or_default(),
+			key: key.0,
+		}
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn converts_decoded_events() {
+		let record = json!({
+			"phase": { "ApplyExtrinsic": 1 },
+			"event": { "module": "balances", "event": "Transfer", "args": ["0x01", "0x02", 5, 1] },
+			"topics": ["0x03"],
+		});
+		assert_eq!(event(&record), Some(proto::Event {
+			phase: Some(proto::event::Phase::ApplyExtrinsic(1)),
+			module: "balances".into(),
+			name: "Transfer".into(),
+			args_json: r#"["0x01","0x02",5,1]"#.into(),
+			topics: vec!["0x03".into()],
+		}));
+
+		let record = json!({
+			"phase": "Finalization",
+			"event": { "module": "system", "event": "CodeUpdated", "args": [] },
+			"topics": [],
+		});
+		assert_eq!(event(&record).unwrap().phase, Some(proto::event::Phase::Finalization(true)));
+		assert_eq!(event(&json!({ "phase": "Finalization" })), None);
+	}
+}
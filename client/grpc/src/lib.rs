@@ -0,0 +1,169 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! gRPC server streaming the finalized blocks of the chain.
+//!
+//! Pipelines built on gRPC can't consume the JSON-RPC subscriptions. The server streams each
+//! finalized block in order, with its extrinsics, its events decoded using the metadata of its
+//! runtime and the storage items it changed. The protobuf schema is in
+//! `proto/finalized_blocks.proto`.
+
+#![warn(missing_docs)]
+
+mod blocks;
+mod state_diffs;
+
+use std::{net::SocketAddr, sync::Arc};
+use futures::{future::{self, Either}, FutureExt, StreamExt};
+use log::warn;
+use sc_client::Client;
+use sc_client_api::{backend, BlockchainEvents, CallExecutor, FinalityNotifications};
+use sp_api::{Metadata, ProvideRuntimeApi};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor, One, SaturatedConversion};
+use tokio::sync::mpsc;
+use tonic::{transport::Server, Request, Response, Status};
+
+use blocks::Blocks;
+use proto::{
+	finalized_blocks_server::{FinalizedBlocks, FinalizedBlocksServer},
+	subscribe_request::Start,
+};
+use state_diffs::StateDiffs;
+
+/// Types generated from the protobuf schema.
+#[allow(missing_docs)]
+pub mod proto {
+	tonic::include_proto!("storm.finalized.v1");
+}
+
+/// Number of blocks buffered for a subscriber which doesn't keep up.
+const SUBSCRIBER_BUFFER: usize = 16;
+
+/// Error starting the server.
+pub type Error = tonic::transport::Error;
+
+/// Serve the finalized blocks of `client` over gRPC on `addr`.
+///
+/// The storage changes are only known for the blocks imported after the server is started.
+pub async fn run_server<B, E, Block, RA>(
+	addr: SocketAddr,
+	client: Arc<Client<B, E, Block, RA>>,
+) -> Result<(), Error> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = sp_blockchain::Error>,
+{
+	let state_diffs = Arc::new(StateDiffs::default());
+	let collect_state_diffs = match client.storage_changes_notification_stream(None, None) {
+		Ok(changes) => {
+			let state_diffs = state_diffs.clone();
+			Either::Left(changes.for_each(move |(hash, changes)| {
+				state_diffs.insert(hash, &changes);
+				future::ready(())
+			}))
+		},
+		Err(e) => {
+			warn!(target: "grpc", "Storage changes aren't available, state diffs won't be streamed: {}", e);
+			Either::Right(future::pending())
+		},
+	};
+
+	let service = Service { blocks: Arc::new(Blocks::new(client, state_diffs)) };
+	let server = Server::builder()
+		.add_service(FinalizedBlocksServer::new(service))
+		.serve(addr);
+
+	futures::pin_mut!(server);
+	match future::select(server, collect_state_diffs.boxed()).await {
+		Either::Left((result, _)) => result,
+		// The storage changes end with the client, keep serving the blocks known until then.
+		Either::Right(((), server)) => server.await,
+	}
+}
+
+/// Implementation of the `FinalizedBlocks` gRPC service.
+struct Service<B, E, Block: BlockT, RA> {
+	blocks: Arc<Blocks<B, E, Block, RA>>,
+}
+
+#[tonic::async_trait]
+impl<B, E, Block, RA> FinalizedBlocks for Service<B, E, Block, RA> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = sp_blockchain::Error>,
+{
+	type SubscribeStream = mpsc::Receiver<Result<proto::FinalizedBlock, Status>>;
+
+	async fn subscribe(
+		&self,
+		request: Request<proto::SubscribeRequest>,
+	) -> Result<Response<Self::SubscribeStream>, Status> {
+		// Subscribe before looking up the finalized block, not to miss any.
+		let notifications = self.blocks.client().finality_notification_stream();
+		let finalized = self.blocks.client().chain_info().finalized_number;
+		let next = match request.into_inner().start {
+			Some(Start::FromNumber(number)) => number.saturated_into(),
+			None => finalized + One::one(),
+		};
+
+		let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER);
+		tokio::spawn(stream_blocks(self.blocks.clone(), next, finalized, notifications, sender));
+		Ok(Response::new(receiver))
+	}
+}
+
+/// Send the finalized blocks to a subscriber, starting with `next`, until it goes away.
+async fn stream_blocks<B, E, Block, RA>(
+	blocks: Arc<Blocks<B, E, Block, RA>>,
+	mut next: NumberFor<Block>,
+	mut finalized: NumberFor<Block>,
+	mut notifications: FinalityNotifications<Block>,
+	mut sender: mpsc::Sender<Result<proto::FinalizedBlock, Status>>,
+) where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = sp_blockchain::Error>,
+{
+	loop {
+		// Finality notifications may skip blocks, which are streamed all the same.
+		while next <= finalized {
+			let block = blocks.block(next)
+				.map_err(|e| Status::internal(format!("Failed to read block {}: {}", next, e)));
+			let failed = block.is_err();
+			if sender.send(block).await.is_err() || failed {
+				return;
+			}
+			next += One::one();
+		}
+
+		finalized = match notifications.next().await {
+			Some(notification) => *notification.header.number(),
+			None => return,
+		};
+	}
+}
@@ -0,0 +1,124 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage changes of the recently imported blocks.
+//!
+//! The client notifies the storage changes of the blocks it imports, but doesn't keep them. They
+//! are kept here until their block is finalized and streamed, rather than computed by comparing
+//! the whole states of a block and its parent.
+
+use std::{collections::{HashMap, VecDeque}, hash::Hash};
+use parking_lot::Mutex;
+use sc_client_api::notifications::StorageChangeSet;
+use sp_core::storage::{StorageData, StorageKey};
+
+/// Maximum number of blocks whose storage changes are kept.
+const MAX_BLOCKS: usize = 4096;
+
+/// Storage items changed by a block, ordered by key. `None` values are deletions.
+pub type StateDiff = Vec<(StorageKey, Option<StorageData>)>;
+
+/// Storage changes of the most recently imported blocks.
+pub struct StateDiffs<H> {
+	inner: Mutex<Inner<H>>,
+}
+
+struct Inner<H> {
+	max_blocks: usize,
+	diffs: HashMap<H, StateDiff>,
+	/// Blocks with changes, from the oldest to the most recently imported.
+	order: VecDeque<H>,
+}
+
+impl<H: Hash + Eq + Clone> StateDiffs<H> {
+	/// Keep the changes of at most `max_blocks` blocks.
+	pub fn new(max_blocks: usize) -> Self {
+		StateDiffs {
+			inner: Mutex::new(Inner {
+				max_blocks,
+				diffs: HashMap::new(),
+				order: VecDeque::new(),
+			}),
+		}
+	}
+
+	/// Record the storage changes notified for the block `hash`.
+	pub fn insert(&self, hash: H, changes: &StorageChangeSet) {
+		let mut diff = changes.iter()
+			.filter(|(child, _, _)| child.is_none())
+			.map(|(_, key, value)| (key.clone(), value.cloned()))
+			.collect::<StateDiff>();
+		diff.sort_by(|a, b| a.0.cmp(&b.0));
+
+		let mut inner = self.inner.lock();
+		if inner.diffs.insert(hash.clone(), diff).is_none() {
+			inner.order.push_back(hash);
+		}
+		while inner.order.len() > inner.max_blocks {
+			if let Some(oldest) = inner.order.pop_front() {
+				inner.diffs.remove(&oldest);
+			}
+		}
+	}
+
+	/// The storage changes of the block `hash`, if known.
+	pub fn get(&self, hash: &H) -> Option<StateDiff> {
+		self.inner.lock().diffs.get(hash).cloned()
+	}
+}
+
+impl<H: Hash + Eq + Clone> Default for StateDiffs<H> {
+	fn default() -> Self {
+		StateDiffs::new(MAX_BLOCKS)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::{empty, Empty};
+	use futures::{executor::block_on_stream, StreamExt};
+	use sc_client_api::notifications::StorageNotifications;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper, H256 as Hash};
+
+	type Block = RawBlock<ExtrinsicWrapper<Hash>>;
+
+	#[test]
+	fn keeps_changes_of_recent_blocks() {
+		let mut notifications = StorageNotifications::<Block>::default();
+		let changes = notifications.listen(None, None);
+		for block in 1..=3u8 {
+			notifications.trigger(
+				&Hash::from_low_u64_be(block as u64),
+				vec![(vec![2], Some(vec![block])), (vec![1], None)].into_iter(),
+				empty::<(_, Empty<_>)>(),
+			);
+		}
+
+		let diffs = StateDiffs::new(2);
+		for (hash, changes) in block_on_stream(changes.take(3)) {
+			diffs.insert(hash, &changes);
+		}
+
+		let diff = |block| Some(vec![
+			(StorageKey(vec![1]), None),
+			(StorageKey(vec![2]), Some(StorageData(vec![block]))),
+		]);
+		assert_eq!(diffs.get(&Hash::from_low_u64_be(1)), None);
+		assert_eq!(diffs.get(&Hash::from_low_u64_be(2)), diff(2));
+		assert_eq!(diffs.get(&Hash::from_low_u64_be(3)), diff(3));
+	}
+}
@@ -221,6 +221,18 @@ impl Store {
 		self.key_pair_by_type::<Pair::Generic>(IsWrappedBy::from_ref(public), Pair::ID).map(Into::into)
 	}
 
+	/// Get the secret stored for the given public key and key type, as it was inserted.
+	///
+	/// This is meant for keys of schemes the keystore doesn't know, whose secret isn't a
+	/// secret URI.
+	pub fn raw_secret(&self, public: &[u8], key_type: KeyTypeId) -> Result<String> {
+		let path = self.key_file_path(public, key_type)
+			.ok_or_else(|| Error::Unavailable)?;
+		let file = File::open(path)?;
+
+		serde_json::from_reader(&file).map_err(Into::into)
+	}
+
 	/// Get public keys of all stored keys that match the given key type.
 	pub fn public_keys_by_type<TPublic: Public>(&self, key_type: KeyTypeId) -> Result<Vec<TPublic>> {
 		let mut public_keys: Vec<TPublic> = self.additional.keys()
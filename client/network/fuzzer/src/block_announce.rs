@@ -0,0 +1,48 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fuzzes the decoding of block announcements received from peers.
+//!
+//! # Running
+//! Running this fuzzer can be done with `cargo hfuzz run block_announce`. `honggfuzz` CLI options
+//! can be used by setting `HFUZZ_RUN_ARGS`, such as `-n 4` to use 4 threads.
+//!
+//! # Debugging a panic
+//! Once a panic is found, it can be debugged with
+//! `cargo hfuzz run-debug block_announce hfuzz_workspace/block_announce/*.fuzz`.
+
+use codec::Encode;
+use honggfuzz::fuzz;
+use sc_network::message::{decode_message, generic::Message};
+use substrate_test_runtime::Block;
+
+/// Index of `Message::BlockAnnounce`.
+const BLOCK_ANNOUNCE: u8 = 3;
+
+fn main() {
+	loop {
+		fuzz!(|data: &[u8]| {
+			let mut message = vec![BLOCK_ANNOUNCE];
+			message.extend_from_slice(data);
+
+			if let Ok(decoded) = decode_message::<Block>(&message) {
+				assert!(match decoded { Message::BlockAnnounce(_) => true, _ => false });
+				// Announcements are re-encoded when relayed to other peers.
+				assert!(decode_message::<Block>(&decoded.encode()).is_ok());
+			}
+		});
+	}
+}
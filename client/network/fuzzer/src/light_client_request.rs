@@ -0,0 +1,51 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fuzzes the decoding of requests sent by light clients.
+//!
+//! # Running
+//! Running this fuzzer can be done with `cargo hfuzz run light_client_request`. `honggfuzz` CLI
+//! options can be used by setting `HFUZZ_RUN_ARGS`, such as `-n 4` to use 4 threads.
+//!
+//! # Debugging a panic
+//! Once a panic is found, it can be debugged with
+//! `cargo hfuzz run-debug light_client_request hfuzz_workspace/light_client_request/*.fuzz`.
+
+use codec::Encode;
+use honggfuzz::fuzz;
+use sc_network::message::decode_message;
+use substrate_test_runtime::Block;
+
+/// Indices of the `Message` variants light clients send to full nodes: remote call, read,
+/// header, changes and child read requests, and finality proof requests.
+const REQUESTS: [u8; 6] = [6, 8, 10, 12, 14, 15];
+
+fn main() {
+	loop {
+		fuzz!(|data: &[u8]| {
+			if data.is_empty() {
+				return;
+			}
+
+			let mut message = vec![REQUESTS[data[0] as usize % REQUESTS.len()]];
+			message.extend_from_slice(&data[1..]);
+
+			if let Ok(decoded) = decode_message::<Block>(&message) {
+				assert_eq!(decode_message::<Block>(&decoded.encode()).ok(), Some(decoded));
+			}
+		});
+	}
+}
@@ -18,13 +18,16 @@ use crate::{
 	debug_info, discovery::DiscoveryBehaviour, discovery::DiscoveryOut, DiscoveryNetBehaviour,
 	Event, protocol::event::DhtEvent
 };
-use crate::{ExHashT, specialization::NetworkSpecialization};
+use crate::request_responses::{RequestResponsesBehaviour, RequestResponseConfig, RequestFailure};
+use crate::{ExHashT, specialization::NetworkSpecialization, state_sync};
 use crate::protocol::{CustomMessageOutcome, Protocol};
 use libp2p::NetworkBehaviour;
 use libp2p::core::{Multiaddr, PeerId, PublicKey};
 use libp2p::kad::record;
 use libp2p::swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess};
 use libp2p::core::{nodes::Substream, muxing::StreamMuxerBox};
+use codec::Encode;
+use futures::{prelude::*, channel::oneshot, future::BoxFuture, stream::FuturesUnordered};
 use log::{debug, warn};
 use sp_consensus::{BlockOrigin, import_queue::{IncomingBlock, Origin}};
 use sp_runtime::{traits::{Block as BlockT, NumberFor}, Justification};
@@ -42,10 +45,15 @@ pub struct Behaviour<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> {
 	debug_info: debug_info::DebugInfoBehaviour<Substream<StreamMuxerBox>>,
 	/// Discovers nodes of the network.
 	discovery: DiscoveryBehaviour<Substream<StreamMuxerBox>>,
+	/// Request/response protocols registered from outside of this crate.
+	request_responses: RequestResponsesBehaviour<Substream<StreamMuxerBox>>,
 
 	/// Queue of events to produce for the outside.
 	#[behaviour(ignore)]
 	events: Vec<BehaviourOut<B>>,
+	/// Responses to the state requests of the sync, to be given back to it.
+	#[behaviour(ignore)]
+	state_responses: FuturesUnordered<BoxFuture<'static, (PeerId, Result<Vec<u8>, RequestFailure>)>>,
 }
 
 /// Event generated by `Behaviour`.
@@ -75,7 +83,9 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Behaviour<B, S, H> {
 				enable_mdns,
 				allow_private_ipv4
 			).await,
+			request_responses: RequestResponsesBehaviour::new(),
 			events: Vec::new(),
+			state_responses: FuturesUnordered::new(),
 		}
 	}
 
@@ -117,6 +127,23 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Behaviour<B, S, H> {
 	pub fn put_value(&mut self, key: record::Key, value: Vec<u8>) {
 		self.discovery.put_value(key, value);
 	}
+
+	/// Registers a request/response protocol.
+	pub fn register_request_response_protocol(&mut self, config: RequestResponseConfig) {
+		self.request_responses.register_protocol(config);
+	}
+
+	/// Sends a request on a registered request/response protocol. The outcome is sent on
+	/// `pending_response`.
+	pub fn send_request(
+		&mut self,
+		target: &PeerId,
+		protocol: &str,
+		request: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	) {
+		self.request_responses.send_request(target, protocol, request, pending_response);
+	}
 }
 
 impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> NetworkBehaviourEventProcess<void::Void> for
@@ -155,6 +182,13 @@ Behaviour<B, S, H> {
 				let ev = Event::NotificationsReceived { remote, messages };
 				self.events.push(BehaviourOut::Event(ev));
 			},
+			CustomMessageOutcome::StateRequest { target, request } => {
+				let (tx, rx) = oneshot::channel();
+				self.request_responses.send_request(&target, state_sync::PROTOCOL_NAME, request.encode(), tx);
+				self.state_responses.push(async move {
+					(target, rx.await.unwrap_or(Err(RequestFailure::Obsolete)))
+				}.boxed());
+			},
 			CustomMessageOutcome::None => {}
 		}
 	}
@@ -211,7 +245,13 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> NetworkBehaviourEventPr
 }
 
 impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Behaviour<B, S, H> {
-	fn poll<TEv>(&mut self, _: &mut Context) -> Poll<NetworkBehaviourAction<TEv, BehaviourOut<B>>> {
+	fn poll<TEv>(&mut self, cx: &mut Context) -> Poll<NetworkBehaviourAction<TEv, BehaviourOut<B>>> {
+		while let Poll::Ready(Some((who, response))) = self.state_responses.poll_next_unpin(cx) {
+			self.substrate.on_state_response(who, response);
+			// The sync may have new requests to send.
+			cx.waker().wake_by_ref();
+		}
+
 		if !self.events.is_empty() {
 			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(self.events.remove(0)))
 		}
@@ -23,7 +23,7 @@ use sp_consensus::{BlockImport, BlockStatus, Error as ConsensusError};
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
 use sp_runtime::generic::{BlockId};
 use sp_runtime::Justification;
-use sp_core::storage::{StorageKey, ChildInfo};
+use sp_core::storage::{StorageKey, ChildInfo, Storage};
 
 /// Local client abstraction for the network.
 pub trait Client<Block: BlockT>: Send + Sync {
@@ -52,6 +52,26 @@ pub trait Client<Block: BlockT>: Send + Sync {
 	/// Get storage read execution proof.
 	fn read_proof(&self, block: &Block::Hash, keys: &[Vec<u8>]) -> Result<StorageProof, Error>;
 
+	/// Get the proof of the storage entries of a key range.
+	fn read_range_proof(
+		&self,
+		block: &Block::Hash,
+		start: &[u8],
+		end: Option<&[u8]>,
+		max_keys: usize,
+	) -> Result<StorageProof, Error>;
+
+	/// Get the proof of the storage entries of a key range of a child trie.
+	fn read_child_range_proof(
+		&self,
+		block: &Block::Hash,
+		storage_key: &[u8],
+		child_info: ChildInfo,
+		start: &[u8],
+		end: Option<&[u8]>,
+		max_keys: usize,
+	) -> Result<StorageProof, Error>;
+
 	/// Get child storage read execution proof.
 	fn read_child_proof(
 		&self,
@@ -61,6 +81,9 @@ pub trait Client<Block: BlockT>: Send + Sync {
 		keys: &[Vec<u8>],
 	) -> Result<StorageProof, Error>;
 
+	/// Import a block with its whole state, downloaded from other nodes.
+	fn import_state(&self, header: Block::Header, state: Storage) -> Result<(), Error>;
+
 	/// Get method execution proof.
 	fn execution_proof(&self, block: &Block::Hash, method: &str, data: &[u8]) -> Result<(Vec<u8>, StorageProof), Error>;
 
@@ -135,6 +158,36 @@ impl<B, E, Block, RA> Client<Block> for SubstrateClient<B, E, Block, RA> where
 		(self as &SubstrateClient<B, E, Block, RA>).read_proof(&BlockId::Hash(block.clone()), keys)
 	}
 
+	fn read_range_proof(
+		&self,
+		block: &Block::Hash,
+		start: &[u8],
+		end: Option<&[u8]>,
+		max_keys: usize,
+	) -> Result<StorageProof, Error> {
+		(self as &SubstrateClient<B, E, Block, RA>)
+			.read_range_proof(&BlockId::Hash(block.clone()), start, end, max_keys)
+	}
+
+	fn read_child_range_proof(
+		&self,
+		block: &Block::Hash,
+		storage_key: &[u8],
+		child_info: ChildInfo,
+		start: &[u8],
+		end: Option<&[u8]>,
+		max_keys: usize,
+	) -> Result<StorageProof, Error> {
+		(self as &SubstrateClient<B, E, Block, RA>).read_child_range_proof(
+			&BlockId::Hash(block.clone()),
+			storage_key,
+			child_info,
+			start,
+			end,
+			max_keys,
+		)
+	}
+
 	fn read_child_proof(
 		&self,
 		block: &Block::Hash,
@@ -146,6 +199,10 @@ impl<B, E, Block, RA> Client<Block> for SubstrateClient<B, E, Block, RA> where
 			.read_child_proof(&BlockId::Hash(block.clone()), storage_key, child_info, keys)
 	}
 
+	fn import_state(&self, header: Block::Header, state: Storage) -> Result<(), Error> {
+		(self as &SubstrateClient<B, E, Block, RA>).import_state(header, state)
+	}
+
 	fn execution_proof(
 		&self,
 		block: &Block::Hash,
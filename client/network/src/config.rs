@@ -71,6 +71,10 @@ pub struct Params<B: BlockT, S, H: ExHashT> {
 	/// Name of the protocol to use on the wire. Should be different for each chain.
 	pub protocol_id: ProtocolId,
 
+	/// Identifier of the fork of the chain, if any. Peers announcing a different fork identifier
+	/// in their handshake are refused.
+	pub fork_id: Option<String>,
+
 	/// Import queue to use.
 	///
 	/// The import queue is the component that verifies that blocks received from other nodes are
@@ -238,6 +242,8 @@ pub struct NetworkConfiguration {
 	/// Directory path to store general network configuration. None means nothing will be saved.
 	pub config_path: Option<String>,
 	/// Directory path to store network-specific configuration. None means nothing will be saved.
+	///
+	/// The peers worth reconnecting to after a restart are saved there.
 	pub net_config_path: Option<String>,
 	/// Multiaddresses to listen for incoming connections.
 	pub listen_addresses: Vec<Multiaddr>,
@@ -265,6 +271,10 @@ pub struct NetworkConfiguration {
 	pub transport: TransportConfig,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Maximum number of light client requests a peer may have waiting for an answer.
+	pub max_pending_light_requests: usize,
+	/// Maximum number of light client requests answered per peer and per second.
+	pub max_light_requests_per_second: u32,
 }
 
 impl Default for NetworkConfiguration {
@@ -289,6 +299,8 @@ impl Default for NetworkConfiguration {
 				wasm_external_transport: None,
 			},
 			max_parallel_downloads: 5,
+			max_pending_light_requests: 8,
+			max_light_requests_per_second: 10,
 		}
 	}
 }
@@ -441,6 +453,8 @@ impl NodeKeyConfig {
 /// Load a secret key from a file, if it exists, or generate a
 /// new secret key and write it to that file. In either case,
 /// the secret key is returned.
+///
+/// Existing files that other users have access to are refused.
 fn get_secret<P, F, G, E, W, K>(file: P, parse: F, generate: G, serialize: W) -> io::Result<K>
 where
 	P: AsRef<Path>,
@@ -449,6 +463,7 @@ where
 	E: Error + Send + Sync + 'static,
 	W: Fn(&K) -> Vec<u8>,
 {
+	check_secret_file_permissions(&file)?;
 	std::fs::read(&file)
 		.and_then(|mut sk_bytes|
 			parse(&mut sk_bytes)
@@ -468,12 +483,55 @@ where
 }
 
 /// Write secret bytes to a file.
+///
+/// The bytes are written to a temporary file which is renamed once complete, so that an
+/// interruption doesn't leave a truncated key behind, which would keep the node from starting.
 fn write_secret_file<P>(path: P, sk_bytes: &[u8]) -> io::Result<()>
 where
 	P: AsRef<Path>
 {
-	let mut file = open_secret_file(&path)?;
-	file.write_all(sk_bytes)
+	let mut tmp_path = path.as_ref().as_os_str().to_owned();
+	tmp_path.push(".tmp");
+	let tmp_path = PathBuf::from(tmp_path);
+
+	// Left over by an interrupted write.
+	let _ = fs::remove_file(&tmp_path);
+	let mut file = open_secret_file(&tmp_path)?;
+	file.write_all(sk_bytes)?;
+	file.sync_all()?;
+	fs::rename(&tmp_path, path)
+}
+
+/// Fails if the secret key file exists and other users have access to it.
+#[cfg(unix)]
+fn check_secret_file_permissions<P>(path: P) -> io::Result<()>
+where
+	P: AsRef<Path>
+{
+	use std::os::unix::fs::PermissionsExt;
+	let mode = match fs::metadata(&path) {
+		Ok(metadata) => metadata.permissions().mode(),
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e),
+	};
+	if mode & 0o077 != 0 {
+		return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!(
+			"Other users have access to the secret key file {} (mode {:o}), \
+			restrict it with `chmod 600`",
+			path.as_ref().display(),
+			mode & 0o777,
+		)));
+	}
+	Ok(())
+}
+
+/// Fails if the secret key file exists and other users have access to it.
+#[cfg(not(unix))]
+fn check_secret_file_permissions<P>(_path: P) -> io::Result<()>
+where
+	P: AsRef<Path>
+{
+	Ok(())
 }
 
 /// Opens a file containing a secret key in write mode.
@@ -529,6 +587,21 @@ mod tests {
 		assert!(file.is_file() && secret_bytes(&kp1) == secret_bytes(&kp2))
 	}
 
+	#[cfg(unix)]
+	#[test]
+	fn test_secret_file_accessible_by_others() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let tmp = tempdir_with_prefix("x");
+		let file = tmp.path().join("x").to_path_buf();
+		NodeKeyConfig::Ed25519(Secret::File(file.clone())).into_keypair().unwrap();
+		assert_eq!(fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o600);
+
+		fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+		let err = NodeKeyConfig::Ed25519(Secret::File(file)).into_keypair().unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+	}
+
 	#[test]
 	fn test_secret_input() {
 		let sk = ed25519::SecretKey::generate();
@@ -59,6 +59,8 @@ struct NodeInfo {
 	client_version: Option<String>,
 	/// Latest ping time with this node.
 	latest_ping: Option<Duration>,
+	/// Address of the local node as observed by the remote, or `None` if unknown.
+	observed_addr: Option<Multiaddr>,
 }
 
 impl<TSubstream> DebugInfoBehaviour<TSubstream> {
@@ -103,10 +105,11 @@ impl<TSubstream> DebugInfoBehaviour<TSubstream> {
 
 	/// Inserts an identify record in the cache. Has no effect if we don't have any entry for that
 	/// node, which shouldn't happen.
-	fn handle_identify_report(&mut self, peer_id: &PeerId, info: &IdentifyInfo) {
-		trace!(target: "sub-libp2p", "Identified {:?} => {:?}", peer_id, info);
+	fn handle_identify_report(&mut self, peer_id: &PeerId, info: &IdentifyInfo, observed_addr: &Multiaddr) {
+		trace!(target: "sub-libp2p", "Identified {:?} => {:?}, observed us as {}", peer_id, info, observed_addr);
 		if let Some(entry) = self.nodes_info.get_mut(peer_id) {
 			entry.client_version = Some(info.agent_version.clone());
+			entry.observed_addr = Some(observed_addr.clone());
 		} else {
 			error!(target: "sub-libp2p",
 				"Received pong from node we're not connected to {:?}", peer_id);
@@ -133,6 +136,12 @@ impl<'a> Node<'a> {
 	pub fn latest_ping(&self) -> Option<Duration> {
 		self.0.latest_ping
 	}
+
+	/// Returns the latest address this node reported to see us as. `None` if the node never
+	/// identified itself.
+	pub fn observed_addr(&self) -> Option<&'a Multiaddr> {
+		self.0.observed_addr.as_ref()
+	}
 }
 
 /// Event that can be emitted by the behaviour.
@@ -177,6 +186,7 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
 					endpoint,
 					client_version: None,
 					latest_ping: None,
+					observed_addr: None,
 				});
 			}
 			Entry::Occupied(e) => {
@@ -184,6 +194,7 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
 				if e.info_expire.as_ref().map(|exp| *exp < Instant::now()).unwrap_or(false) {
 					e.client_version = None;
 					e.latest_ping = None;
+					e.observed_addr = None;
 				}
 				e.info_expire = None;
 				e.endpoint = endpoint;
@@ -288,8 +299,8 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
 				Poll::Pending => break,
 				Poll::Ready(NetworkBehaviourAction::GenerateEvent(event)) => {
 					match event {
-						IdentifyEvent::Received { peer_id, info, .. } => {
-							self.handle_identify_report(&peer_id, &info);
+						IdentifyEvent::Received { peer_id, info, observed_addr } => {
+							self.handle_identify_report(&peer_id, &info, &observed_addr);
 							let event = DebugInfoEvent::Identified { peer_id, info };
 							return Poll::Ready(NetworkBehaviourAction::GenerateEvent(event));
 						}
@@ -0,0 +1,144 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Peers worth reconnecting to after a restart.
+//!
+//! After a restart, it takes a while for the discovery to find peers again, during which the
+//! node lags behind the chain. The peers we were connected to and the ones with a good reputation
+//! are regularly saved in the network configuration directory, along with their addresses, and
+//! are connected to as soon as the node starts again.
+
+use std::{fs, io, path::{Path, PathBuf}, time::Duration};
+use libp2p::{Multiaddr, PeerId};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// Name of the file the peers are saved in, within the network configuration directory.
+const KNOWN_PEERS_FILE: &str = "known_peers.json";
+/// Maximum number of peers saved.
+const MAX_KNOWN_PEERS: usize = 128;
+/// Interval at which the peers are saved.
+pub(crate) const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A peer saved with its reputation and addresses.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KnownPeer {
+	/// Base58 representation of the `PeerId` of the peer.
+	pub peer_id: String,
+	/// Reputation of the peer when it was saved.
+	pub reputation: i32,
+	/// Addresses the peer can be reached at.
+	pub addresses: Vec<Multiaddr>,
+}
+
+/// Path of the file the peers are saved in.
+fn file_path(net_config_path: &str) -> PathBuf {
+	Path::new(net_config_path).join(KNOWN_PEERS_FILE)
+}
+
+/// Load the peers saved in `net_config_path`.
+///
+/// Returns an empty list if none were saved or if they can't be read.
+pub(crate) fn load(net_config_path: &str) -> Vec<(PeerId, i32, Vec<Multiaddr>)> {
+	let path = file_path(net_config_path);
+	let peers: Vec<KnownPeer> = match fs::read(&path) {
+		Ok(content) => match serde_json::from_slice(&content) {
+			Ok(peers) => peers,
+			Err(err) => {
+				warn!(target: "sub-libp2p", "Ignoring invalid known peers file {}: {}", path.display(), err);
+				return Vec::new()
+			},
+		},
+		Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Vec::new(),
+		Err(err) => {
+			warn!(target: "sub-libp2p", "Failed to read known peers file {}: {}", path.display(), err);
+			return Vec::new()
+		},
+	};
+
+	peers.into_iter().filter_map(|peer| match peer.peer_id.parse::<PeerId>() {
+		Ok(peer_id) => Some((peer_id, peer.reputation, peer.addresses)),
+		Err(_) => {
+			warn!(target: "sub-libp2p", "Ignoring known peer with invalid id: {}", peer.peer_id);
+			None
+		},
+	}).collect()
+}
+
+/// Save the peers worth reconnecting to in `net_config_path`.
+///
+/// `peers` are the peers of the peerset, with their reputation, whether we are connected to them
+/// and their addresses. The connected peers and the ones with a positive reputation are saved,
+/// the best ones first, as long as we know an address for them.
+pub(crate) fn save<I>(net_config_path: &str, peers: I) -> io::Result<()>
+where
+	I: IntoIterator<Item = (PeerId, i32, bool, Vec<Multiaddr>)>,
+{
+	let mut peers = peers.into_iter()
+		.filter(|(_, reputation, connected, addresses)|
+			((*connected && *reputation >= 0) || *reputation > 0) && !addresses.is_empty()
+		)
+		.collect::<Vec<_>>();
+	peers.sort_by(|a, b| (b.2, b.1).cmp(&(a.2, a.1)));
+	let peers = peers.into_iter()
+		.take(MAX_KNOWN_PEERS)
+		.map(|(peer_id, reputation, _, addresses)| KnownPeer {
+			peer_id: peer_id.to_base58(),
+			reputation,
+			addresses,
+		})
+		.collect::<Vec<_>>();
+
+	let content = serde_json::to_vec_pretty(&peers)
+		.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+	// Write to a temporary file first, so that an interruption doesn't lose the peers saved
+	// previously.
+	let path = file_path(net_config_path);
+	let tmp_path = path.with_extension("json.tmp");
+	fs::write(&tmp_path, content)?;
+	fs::rename(&tmp_path, &path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn useful_peers_are_saved_and_loaded() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().to_str().unwrap();
+		assert!(load(path).is_empty());
+
+		let addr: Multiaddr = "/ip4/127.0.0.1/tcp/30333".parse().unwrap();
+		let connected = PeerId::random();
+		let good = PeerId::random();
+		let bad = PeerId::random();
+		let unreachable = PeerId::random();
+		save(path, vec![
+			(good.clone(), 100, false, vec![addr.clone()]),
+			(bad, -100, true, vec![addr.clone()]),
+			(connected.clone(), 0, true, vec![addr.clone()]),
+			(unreachable, 1000, false, Vec::new()),
+		]).unwrap();
+
+		assert_eq!(load(path), vec![
+			(connected, 0, vec![addr.clone()]),
+			(good, 100, vec![addr]),
+		]);
+	}
+}
@@ -105,6 +105,9 @@
 //! - We periodically open ephemeral substreams for Kademlia random walk queries. Each Kademlia
 //! query is done in a new separate substream. This uses the
 //! [standard libp2p Kademlia protocol](https://github.com/libp2p/specs/pull/108).
+//! - We open an ephemeral substream for each request of the request/response protocols registered
+//! with `NetworkService::register_request_response_protocol`. The request is sent, the remote
+//! answers with a single response, and the substream is closed.
 //! - We optionally keep a substream alive for all Substrate-based communications. The name of the
 //! protocol negotiated is based on the *protocol ID* passed as part of the network configuration.
 //! This protocol ID should be unique for each chain and prevents nodes from different chains from
@@ -172,23 +175,28 @@ mod behaviour;
 mod chain;
 mod debug_info;
 mod discovery;
+mod known_peers;
 mod on_demand_layer;
 mod protocol;
+mod request_responses;
 mod service;
 mod transport;
 mod utils;
 
 pub mod config;
 pub mod error;
+pub mod state_sync;
 
 pub use chain::{Client as ClientHandle, FinalityProofProvider};
 pub use service::{
 	NetworkService, NetworkWorker, TransactionPool, ExHashT, ReportHandle,
-	NetworkStateInfo,
+	NetworkStateInfo, NODE_IDENTITY_CONTEXT,
 };
 pub use protocol::{PeerInfo, Context, ProtocolConfig, message, specialization};
+pub use protocol::bootnodes::BootnodeState;
 pub use protocol::event::{Event, DhtEvent};
 pub use protocol::sync::SyncState;
+pub use request_responses::{IncomingRequest, RequestFailure, RequestResponseConfig};
 pub use libp2p::{Multiaddr, PeerId};
 #[doc(inline)]
 pub use libp2p::multiaddr;
@@ -241,6 +249,8 @@ pub struct NetworkState {
 	pub average_upload_per_sec: u64,
 	/// State of the peerset manager.
 	pub peerset: serde_json::Value,
+	/// What we know about each bootnode.
+	pub bootnodes: HashMap<String, BootnodeState>,
 }
 
 /// Part of the `NetworkState` struct. Unstable.
@@ -303,3 +313,42 @@ impl From<ConnectedPoint> for NetworkStatePeerEndpoint {
 		}
 	}
 }
+
+/// Returns what the node knows about how it can be reached by other nodes.
+///
+/// Meant for diagnosing nodes that nobody manages to dial.
+///
+/// **Warning**: This API is not stable.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NatStatus {
+	/// List of addresses the node is currently listening on.
+	pub listened_addresses: HashSet<Multiaddr>,
+	/// List of addresses the node knows it can be reached as, either because they are part of the
+	/// configuration or because they have been confirmed by remotes.
+	pub external_addresses: HashSet<Multiaddr>,
+	/// Addresses under which the nodes we are connected to see us, with the number of nodes
+	/// reporting each of them.
+	pub observed_addresses: Vec<NatStatusObservedAddress>,
+	/// Outcome of mapping the listened ports on the gateway.
+	pub port_mapping: PortMappingStatus,
+}
+
+/// Part of the `NatStatus` struct. Unstable.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NatStatusObservedAddress {
+	/// Address of the local node, as observed by remotes.
+	pub address: Multiaddr,
+	/// Number of connected nodes reporting this address.
+	pub reporters: usize,
+}
+
+/// Part of the `NatStatus` struct. Unstable.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PortMappingStatus {
+	/// The node doesn't try to map its ports through UPnP or NAT-PMP. Nodes behind a NAT are
+	/// only reachable if their port is forwarded manually.
+	NotSupported,
+}
@@ -29,7 +29,7 @@ use sp_consensus::{
 	block_validation::BlockAnnounceValidator,
 	import_queue::{BlockImportResult, BlockImportError, IncomingBlock, Origin}
 };
-use codec::{Decode, Encode};
+use codec::Encode;
 use sp_runtime::{generic::BlockId, ConsensusEngineId, Justification};
 use sp_runtime::traits::{
 	Block as BlockT, Header as HeaderT, NumberFor, One, Zero, CheckedSub
@@ -37,9 +37,12 @@ use sp_runtime::traits::{
 use sp_arithmetic::traits::SaturatedConversion;
 use message::{BlockAnnounce, BlockAttributes, Direction, FromBlock, Message, RequestId};
 use message::generic::{Message as GenericMessage, ConsensusMessage};
+use bootnodes::Bootnodes;
 use light_dispatch::{LightDispatch, LightDispatchNetwork, RequestData};
+use light_server::{LightServer, LightRequest};
 use specialization::NetworkSpecialization;
 use sync::{ChainSync, SyncState};
+use transactions::{PendingRequests, TX_ANNOUNCE_VERSION};
 use crate::service::{TransactionPool, ExHashT};
 use crate::config::{BoxFinalityProofRequestBuilder, Roles};
 use rustc_hex::ToHex;
@@ -51,30 +54,47 @@ use log::{log, Level, trace, debug, warn, error};
 use crate::chain::{Client, FinalityProofProvider};
 use sc_client_api::{FetchChecker, ChangesProof, StorageProof};
 use crate::error;
+use crate::request_responses::RequestFailure;
+use crate::state_sync::StateRequest;
 use util::LruHashSet;
 
 mod legacy_proto;
+mod transactions;
 mod util;
 
+pub mod bootnodes;
+
 pub mod message;
 pub mod event;
 pub mod light_dispatch;
+pub mod light_server;
 pub mod specialization;
 pub mod sync;
 
 const REQUEST_TIMEOUT_SEC: u64 = 40;
+/// Maximum number of light client requests answered per call to `poll`.
+const MAX_LIGHT_REQUESTS_PER_POLL: usize = 16;
 /// Interval at which we perform time based maintenance
 const TICK_TIMEOUT: time::Duration = time::Duration::from_millis(1100);
 /// Interval at which we propagate exstrinsics;
 const PROPAGATE_TIMEOUT: time::Duration = time::Duration::from_millis(2900);
+/// Interval at which we report the state of the bootnodes.
+const BOOTNODES_CHECK_INTERVAL: time::Duration = time::Duration::from_secs(120);
 
 /// Maximim number of known block hashes to keep for a peer.
 const MAX_KNOWN_BLOCKS: usize = 1024; // ~32kb per peer + LruHashSet overhead
 /// Maximim number of known extrinsic hashes to keep for a peer.
 const MAX_KNOWN_EXTRINSICS: usize = 4096; // ~128kb per peer + overhead
+/// Maximum number of hashes of extrinsics received from peers to keep, so that they aren't
+/// requested again when announced by other peers.
+const MAX_RECEIVED_EXTRINSICS: usize = 16384; // ~512kb + overhead
+/// Maximum number of announced extrinsics requested from peers and not received yet.
+const MAX_PENDING_EXTRINSIC_REQUESTS: usize = 8192;
+/// Maximum number of announced extrinsics requested from a peer and not received yet.
+const MAX_PENDING_EXTRINSIC_REQUESTS_PER_PEER: usize = 512;
 
 /// Current protocol version.
-pub(crate) const CURRENT_VERSION: u32 = 5;
+pub(crate) const CURRENT_VERSION: u32 = 7;
 /// Lowest version we support
 pub(crate) const MIN_VERSION: u32 = 3;
 
@@ -92,6 +112,8 @@ mod rep {
 	pub const CLOGGED_PEER: Rep = Rep::new(-(1 << 12), "Clogged message queue");
 	/// Reputation change when a peer doesn't respond in time to our messages.
 	pub const TIMEOUT: Rep = Rep::new(-(1 << 10), "Request timeout");
+	/// Reputation change when a peer doesn't send in time the extrinsics it announced.
+	pub const EXTRINSIC_REQUEST_TIMEOUT: Rep = Rep::new(-(1 << 8), "Extrinsic request timeout");
 	/// Reputation change when a peer sends us a status message while we already received one.
 	pub const UNEXPECTED_STATUS: Rep = Rep::new(-(1 << 20), "Unexpected status message");
 	/// Reputation change when we are a light client and a peer is behind us.
@@ -104,6 +126,8 @@ mod rep {
 	pub const RPC_FAILED: Rep = Rep::new(-(1 << 12), "Remote call failed");
 	/// We received a message that failed to decode.
 	pub const BAD_MESSAGE: Rep = Rep::new(-(1 << 12), "Bad message");
+	/// We received a block announcement whose associated data failed validation.
+	pub const BAD_BLOCK_ANNOUNCEMENT: Rep = Rep::new(-(1 << 12), "Bad block announcement");
 	/// We received an unexpected response.
 	pub const UNEXPECTED_RESPONSE: Rep = Rep::new_fatal("Unexpected response packet");
 	/// We received an unexpected extrinsic packet.
@@ -112,10 +136,14 @@ mod rep {
 	pub const UNEXPECTED_REQUEST: Rep = Rep::new_fatal("Unexpected block request packet");
 	/// Peer has different genesis.
 	pub const GENESIS_MISMATCH: Rep = Rep::new_fatal("Genesis mismatch");
+	/// Peer follows a different fork of the chain.
+	pub const FORK_ID_MISMATCH: Rep = Rep::new_fatal("Fork ID mismatch");
 	/// Peer is on unsupported protocol version.
 	pub const BAD_PROTOCOL: Rep = Rep::new_fatal("Unsupported protocol");
 	/// Peer role does not match (e.g. light peer connecting to another light peer).
 	pub const BAD_ROLE: Rep = Rep::new_fatal("Unsupported role");
+	/// Peer sent more light client requests than we are willing to queue.
+	pub const TOO_MANY_LIGHT_REQUESTS: Rep = Rep::new(-(1 << 10), "Too many light client requests");
 }
 
 // Lock must always be taken in order declared here.
@@ -124,9 +152,13 @@ pub struct Protocol<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> {
 	tick_timeout: Pin<Box<dyn Stream<Item = ()> + Send>>,
 	/// Interval at which we call `propagate_extrinsics`.
 	propagate_timeout: Pin<Box<dyn Stream<Item = ()> + Send>>,
+	/// Interval at which we report the state of the bootnodes.
+	bootnodes_check_timeout: Pin<Box<dyn Stream<Item = ()> + Send>>,
 	config: ProtocolConfig,
 	/// Handler for light client requests.
 	light_dispatch: LightDispatch<B>,
+	/// Queue of requests from light clients waiting to be answered.
+	light_server: LightServer<LightRequest<B>>,
 	genesis_hash: B::Hash,
 	sync: ChainSync<B>,
 	specialization: S,
@@ -134,11 +166,20 @@ pub struct Protocol<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> {
 	/// List of nodes for which we perform additional logging because they are important for the
 	/// user.
 	important_peers: HashSet<PeerId>,
+	/// Liveness of the bootnodes.
+	bootnodes: Bootnodes,
 	// Connected peers pending Status message.
 	handshaking_peers: HashMap<PeerId, HandshakingPeer>,
 	/// Used to report reputation changes.
 	peerset_handle: sc_peerset::PeersetHandle,
 	transaction_pool: Arc<dyn TransactionPool<H, B>>,
+	/// Extrinsics of the pool as of the last propagation, served to the peers they were announced
+	/// to.
+	announced_extrinsics: HashMap<H, B::Extrinsic>,
+	/// Hashes of the extrinsics recently received from peers.
+	received_extrinsics: LruHashSet<H>,
+	/// Announced extrinsics requested from peers.
+	extrinsic_requests: PendingRequests<H, PeerId>,
 	/// When asked for a proof of finality, we use this struct to build one.
 	finality_proof_provider: Option<Arc<dyn FinalityProofProvider<B>>>,
 	/// Handles opening the unique substream and sending and receiving raw messages.
@@ -171,6 +212,8 @@ struct Peer<B: BlockT, H: ExHashT> {
 	obsolete_requests: HashMap<message::RequestId, time::Instant>,
 	/// Holds a set of transactions known to this peer.
 	known_extrinsics: LruHashSet<H>,
+	/// Holds a set of transactions announced to this peer and not requested yet.
+	announced_extrinsics: LruHashSet<H>,
 	/// Holds a set of blocks known to this peer.
 	known_blocks: LruHashSet<B::Hash>,
 	/// Request counter,
@@ -380,6 +423,12 @@ pub struct ProtocolConfig {
 	pub roles: Roles,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Identifier of the fork of the chain we follow. Peers announcing a different one are refused.
+	pub fork_id: Option<String>,
+	/// Maximum number of light client requests a peer may have waiting for an answer.
+	pub max_pending_light_requests: usize,
+	/// Maximum number of light client requests answered per peer and per second.
+	pub max_light_requests_per_second: u32,
 }
 
 impl Default for ProtocolConfig {
@@ -387,6 +436,9 @@ impl Default for ProtocolConfig {
 		ProtocolConfig {
 			roles: Roles::FULL,
 			max_parallel_downloads: 5,
+			fork_id: None,
+			max_pending_light_requests: 8,
+			max_light_requests_per_second: 10,
 		}
 	}
 }
@@ -424,13 +476,20 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 			imp_p
 		};
 
+		let bootnodes = Bootnodes::new(peerset_config.bootnodes.iter().cloned());
 		let (peerset, peerset_handle) = sc_peerset::Peerset::from_config(peerset_config);
 		let versions = &((MIN_VERSION as u8)..=(CURRENT_VERSION as u8)).collect::<Vec<u8>>();
 		let behaviour = LegacyProto::new(protocol_id, versions, peerset);
 
+		let light_server = LightServer::new(
+			config.max_pending_light_requests,
+			config.max_light_requests_per_second,
+		);
+
 		let protocol = Protocol {
 			tick_timeout: Box::pin(interval(TICK_TIMEOUT)),
 			propagate_timeout: Box::pin(interval(PROPAGATE_TIMEOUT)),
+			bootnodes_check_timeout: Box::pin(interval(BOOTNODES_CHECK_INTERVAL)),
 			config,
 			context_data: ContextData {
 				peers: HashMap::new(),
@@ -438,12 +497,21 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 				chain,
 			},
 			light_dispatch: LightDispatch::new(checker),
+			light_server,
 			genesis_hash: info.genesis_hash,
 			sync,
 			specialization,
 			handshaking_peers: HashMap::new(),
 			important_peers,
+			bootnodes,
 			transaction_pool,
+			announced_extrinsics: HashMap::new(),
+			received_extrinsics: LruHashSet::new(NonZeroUsize::new(MAX_RECEIVED_EXTRINSICS)
+				.expect("Constant is nonzero")),
+			extrinsic_requests: PendingRequests::new(
+				MAX_PENDING_EXTRINSIC_REQUESTS,
+				MAX_PENDING_EXTRINSIC_REQUESTS_PER_PEER,
+			),
 			finality_proof_provider,
 			peerset_handle: peerset_handle.clone(),
 			behaviour,
@@ -473,11 +541,29 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		self.behaviour.is_enabled(peer_id)
 	}
 
+	/// Returns what we know about the bootnodes.
+	pub fn bootnodes(&self) -> &Bootnodes {
+		&self.bootnodes
+	}
+
+	/// Adds a bootnode and tries to connect to it. The networking must know an address for it.
+	pub fn add_bootnode(&mut self, peer_id: PeerId) {
+		if self.bootnodes.insert(peer_id.clone()) {
+			self.behaviour.add_discovered_nodes(std::iter::once(peer_id));
+		}
+	}
+
 	/// Returns the state of the peerset manager, for debugging purposes.
 	pub fn peerset_debug_info(&mut self) -> serde_json::Value {
 		self.behaviour.peerset_debug_info()
 	}
 
+	/// Returns the peers known to the peerset manager, with their reputation and whether we are
+	/// connected to them.
+	pub fn peerset_known_peers(&mut self) -> Vec<(PeerId, i32, bool)> {
+		self.behaviour.peerset_known_peers()
+	}
+
 	/// Returns the number of peers we're connected to.
 	pub fn num_connected_peers(&self) -> usize {
 		self.context_data.peers.values().count()
@@ -573,7 +659,7 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		data: BytesMut,
 	) -> CustomMessageOutcome<B> {
 
-		let message = match <Message<B> as Decode>::decode(&mut &data[..]) {
+		let message = match message::decode_message::<B>(&data[..]) {
 			Ok(message) => message,
 			Err(err) => {
 				debug!(target: "sync", "Couldn't decode packet sent by {}: {:?}: {}", who, data, err.what());
@@ -608,27 +694,32 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 			},
 			GenericMessage::Transactions(m) =>
 				self.on_extrinsics(who, m),
-			GenericMessage::RemoteCallRequest(request) => self.on_remote_call_request(who, request),
+			GenericMessage::TransactionAnnounce(hashes) =>
+				self.on_extrinsics_announce(who, hashes),
+			GenericMessage::TransactionRequest(hashes) =>
+				self.on_extrinsics_request(who, hashes),
+			GenericMessage::RemoteCallRequest(request) =>
+				self.on_light_request(who, LightRequest::Call(request)),
 			GenericMessage::RemoteCallResponse(response) =>
 				self.on_remote_call_response(who, response),
 			GenericMessage::RemoteReadRequest(request) =>
-				self.on_remote_read_request(who, request),
+				self.on_light_request(who, LightRequest::Read(request)),
 			GenericMessage::RemoteReadResponse(response) =>
 				self.on_remote_read_response(who, response),
 			GenericMessage::RemoteHeaderRequest(request) =>
-				self.on_remote_header_request(who, request),
+				self.on_light_request(who, LightRequest::Header(request)),
 			GenericMessage::RemoteHeaderResponse(response) =>
 				self.on_remote_header_response(who, response),
 			GenericMessage::RemoteChangesRequest(request) =>
-				self.on_remote_changes_request(who, request),
+				self.on_light_request(who, LightRequest::Changes(request)),
 			GenericMessage::RemoteChangesResponse(response) =>
 				self.on_remote_changes_response(who, response),
 			GenericMessage::FinalityProofRequest(request) =>
-				self.on_finality_proof_request(who, request),
+				self.on_light_request(who, LightRequest::FinalityProof(request)),
 			GenericMessage::FinalityProofResponse(response) =>
 				return self.on_finality_proof_response(who, response),
 			GenericMessage::RemoteReadChildRequest(request) =>
-				self.on_remote_read_child_request(who, request),
+				self.on_light_request(who, LightRequest::ReadChild(request)),
 			GenericMessage::Consensus(msg) =>
 				return if self.registered_notif_protocols.contains(&msg.engine_id) {
 					CustomMessageOutcome::NotificationsReceived {
@@ -722,6 +813,7 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 			let mut context = ProtocolContext::new(&mut self.context_data, &mut self.behaviour, &self.peerset_handle);
 			self.sync.peer_disconnected(peer.clone());
 			self.specialization.on_disconnect(&mut context, peer.clone());
+			self.light_server.on_disconnect(&peer);
 			self.light_dispatch.on_disconnect(LightDispatchIn {
 				behaviour: &mut self.behaviour,
 				peerset: self.peerset_handle.clone(),
@@ -883,12 +975,34 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 	/// > **Note**: This method normally doesn't have to be called except for testing purposes.
 	pub fn tick(&mut self) {
 		self.maintain_peers();
+		self.retry_extrinsic_requests();
 		self.light_dispatch.maintain_peers(LightDispatchIn {
 			behaviour: &mut self.behaviour,
 			peerset: self.peerset_handle.clone(),
 		});
 	}
 
+	/// Request the extrinsics whose requests timed out from other peers announcing them, and the
+	/// queued ones from the peers which can be asked again.
+	fn retry_extrinsic_requests(&mut self) {
+		let peers = &self.context_data.peers;
+		let expired = self.extrinsic_requests.expire(time::Instant::now(), |who| peers.contains_key(who));
+
+		for who in expired.timed_out {
+			trace!(target: "sync", "Announced extrinsics request to {} timed out", who);
+			self.peerset_handle.report_peer(who, rep::EXTRINSIC_REQUEST_TIMEOUT);
+		}
+
+		let mut to_request = HashMap::<PeerId, Vec<H>>::new();
+		for (who, hash) in expired.requests {
+			to_request.entry(who).or_default().push(hash);
+		}
+		for (who, hashes) in to_request {
+			trace!(target: "sync", "Requesting {} announced extrinsics again from {}", hashes.len(), who);
+			self.send_message(&who, GenericMessage::TransactionRequest(transactions::encode_hashes(&hashes)));
+		}
+	}
+
 	fn maintain_peers(&mut self) {
 		let tick = time::Instant::now();
 		let mut aborting = Vec::new();
@@ -951,10 +1065,24 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 					"Peer is on different chain (our genesis: {} theirs: {})",
 					self.genesis_hash, status.genesis_hash
 				);
+				self.bootnodes.on_wrong_chain(&who);
 				self.peerset_handle.report_peer(who.clone(), rep::GENESIS_MISMATCH);
 				self.behaviour.disconnect_peer(&who);
 				return CustomMessageOutcome::None;
 			}
+			if status.fork_id.as_ref().map(Vec::as_slice) != self.config.fork_id.as_ref().map(String::as_bytes) {
+				log!(
+					target: "sync",
+					if self.important_peers.contains(&who) { Level::Warn } else { Level::Trace },
+					"Peer is on different fork (our fork id: {:?} theirs: {:?})",
+					self.config.fork_id,
+					status.fork_id.as_ref().map(|id| String::from_utf8_lossy(id)),
+				);
+				self.bootnodes.on_wrong_chain(&who);
+				self.peerset_handle.report_peer(who.clone(), rep::FORK_ID_MISMATCH);
+				self.behaviour.disconnect_peer(&who);
+				return CustomMessageOutcome::None;
+			}
 			if status.version < MIN_VERSION && CURRENT_VERSION < status.min_supported_version {
 				log!(
 					target: "sync",
@@ -1013,12 +1141,15 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 				block_request: None,
 				known_extrinsics: LruHashSet::new(NonZeroUsize::new(MAX_KNOWN_EXTRINSICS)
 					.expect("Constant is nonzero")),
+				announced_extrinsics: LruHashSet::new(NonZeroUsize::new(MAX_KNOWN_EXTRINSICS)
+					.expect("Constant is nonzero")),
 				known_blocks: LruHashSet::new(NonZeroUsize::new(MAX_KNOWN_BLOCKS)
 					.expect("Constant is nonzero")),
 				next_request_id: 0,
 				obsolete_requests: HashMap::new(),
 			};
 			self.context_data.peers.insert(who.clone(), peer);
+			self.bootnodes.on_alive(&who);
 
 			debug!(target: "sync", "Connected {}", who);
 			status.version
@@ -1122,6 +1253,8 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		if let Some(ref mut peer) = self.context_data.peers.get_mut(&who) {
 			for t in extrinsics {
 				let hash = self.transaction_pool.hash_of(&t);
+				self.extrinsic_requests.remove(&hash);
+				self.received_extrinsics.insert(hash.clone());
 				peer.known_extrinsics.insert(hash);
 
 				self.transaction_pool.import(
@@ -1135,7 +1268,87 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		}
 	}
 
+	/// Called when a peer announces extrinsics it can provide.
+	fn on_extrinsics_announce(&mut self, who: PeerId, hashes: message::TransactionHashes) {
+		// announcing extrinsics to light node is considered a bad behavior
+		if !self.config.roles.is_full() {
+			trace!(target: "sync", "Peer {} is trying to announce extrinsics to the light node", who);
+			self.behaviour.disconnect_peer(&who);
+			self.peerset_handle.report_peer(who, rep::UNEXPECTED_EXTRINSICS);
+			return;
+		}
+
+		// Accept extrinsics only when fully synced
+		if self.sync.status().state != SyncState::Idle {
+			trace!(target: "sync", "{} Ignoring extrinsics announcement while syncing", who);
+			return;
+		}
+
+		let hashes = match transactions::decode_hashes::<H>(hashes, MAX_KNOWN_EXTRINSICS) {
+			Ok(hashes) => hashes,
+			Err(err) => {
+				debug!(target: "sync", "Invalid extrinsics announcement from {}: {}", who, err.what());
+				self.peerset_handle.report_peer(who, rep::BAD_MESSAGE);
+				return;
+			},
+		};
+
+		let peer = match self.context_data.peers.get_mut(&who) {
+			Some(peer) => peer,
+			None => return,
+		};
+		let now = time::Instant::now();
+		let mut to_request = Vec::new();
+		for hash in hashes {
+			peer.known_extrinsics.insert(hash.clone());
+			if !self.announced_extrinsics.contains_key(&hash)
+				&& !self.received_extrinsics.contains(&hash)
+				&& self.extrinsic_requests.on_announce(hash.clone(), who.clone(), now)
+			{
+				to_request.push(hash);
+			}
+		}
+
+		if !to_request.is_empty() {
+			trace!(target: "sync", "Requesting {} announced extrinsics from {}", to_request.len(), who);
+			self.send_message(&who, GenericMessage::TransactionRequest(transactions::encode_hashes(&to_request)));
+		}
+	}
+
+	/// Called when a peer requests extrinsics we announced.
+	fn on_extrinsics_request(&mut self, who: PeerId, hashes: message::TransactionHashes) {
+		let hashes = match transactions::decode_hashes::<H>(hashes, MAX_KNOWN_EXTRINSICS) {
+			Ok(hashes) => hashes,
+			Err(err) => {
+				debug!(target: "sync", "Invalid extrinsics request from {}: {}", who, err.what());
+				self.peerset_handle.report_peer(who, rep::BAD_MESSAGE);
+				return;
+			},
+		};
+
+		let peer = match self.context_data.peers.get_mut(&who) {
+			Some(peer) => peer,
+			None => return,
+		};
+
+		// Only extrinsics announced to the peer are sent, each at most once per announcement, which
+		// bounds the work a peer can cause by the rate we announce extrinsics at. Extrinsics which
+		// left the pool since they were announced are not sent.
+		let announced = &self.announced_extrinsics;
+		let extrinsics = hashes.iter()
+			.filter(|hash| peer.announced_extrinsics.remove(hash))
+			.filter_map(|hash| announced.get(hash).cloned())
+			.collect::<Vec<_>>();
+		if !extrinsics.is_empty() {
+			trace!(target: "sync", "Sending {} requested transactions to {}", extrinsics.len(), who);
+			self.send_message(&who, GenericMessage::Transactions(extrinsics));
+		}
+	}
+
 	/// Call when we must propagate ready extrinsics to peers.
+	///
+	/// Peers supporting it are only sent the hashes of the extrinsics, which they request if they
+	/// don't know them.
 	pub fn propagate_extrinsics(
 		&mut self,
 	) {
@@ -1161,22 +1374,32 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 				.unzip();
 
 			if !to_send.is_empty() {
+				let message = if peer.info.protocol_version >= TX_ANNOUNCE_VERSION {
+					trace!(target: "sync", "Announcing {} transactions to {}", hashes.len(), who);
+					for hash in &hashes {
+						peer.announced_extrinsics.insert(hash.clone());
+					}
+					GenericMessage::TransactionAnnounce(transactions::encode_hashes(&hashes))
+				} else {
+					trace!(target: "sync", "Sending {} transactions to {}", to_send.len(), who);
+					GenericMessage::Transactions(to_send)
+				};
 				for hash in hashes {
 					propagated_to
 						.entry(hash)
 						.or_insert_with(Vec::new)
 						.push(who.to_base58());
 				}
-				trace!(target: "sync", "Sending {} transactions to {}", to_send.len(), who);
 				send_message::<B> (
 					&mut self.behaviour,
 					&mut self.context_data.stats,
 					&who,
-					GenericMessage::Transactions(to_send)
+					message,
 				)
 			}
 		}
 
+		self.announced_extrinsics = extrinsics.into_iter().collect();
 		self.transaction_pool.on_broadcasted(propagated_to);
 	}
 
@@ -1253,6 +1476,7 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 			best_number: info.best_number,
 			best_hash: info.best_hash,
 			chain_status: self.specialization.status(),
+			fork_id: self.config.fork_id.clone().map(String::into_bytes),
 		};
 
 		self.send_message(&who, GenericMessage::Status(status))
@@ -1283,6 +1507,10 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 				// 2) parent block is already imported and not pruned.
 				return CustomMessageOutcome::None
 			}
+			sync::OnBlockAnnounce::InvalidData => {
+				self.peerset_handle.report_peer(who, rep::BAD_BLOCK_ANNOUNCEMENT);
+				return CustomMessageOutcome::None
+			}
 			sync::OnBlockAnnounce::ImportHeader => () // We proceed with the import.
 		}
 
@@ -1346,6 +1574,27 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		self.sync.on_block_finalized(&hash, *header.number())
 	}
 
+	/// Queues a request from a light client. It is answered from `poll`.
+	fn on_light_request(&mut self, who: PeerId, request: LightRequest<B>) {
+		if let Err(request) = self.light_server.push(who.clone(), request) {
+			debug!(target: "sync", "Refusing light client request from {}: too many pending: {:?}",
+				who, request);
+			self.peerset_handle.report_peer(who, rep::TOO_MANY_LIGHT_REQUESTS);
+		}
+	}
+
+	/// Answers a request from a light client.
+	fn answer_light_request(&mut self, who: PeerId, request: LightRequest<B>) {
+		match request {
+			LightRequest::Call(request) => self.on_remote_call_request(who, request),
+			LightRequest::Read(request) => self.on_remote_read_request(who, request),
+			LightRequest::ReadChild(request) => self.on_remote_read_child_request(who, request),
+			LightRequest::Header(request) => self.on_remote_header_request(who, request),
+			LightRequest::Changes(request) => self.on_remote_changes_request(who, request),
+			LightRequest::FinalityProof(request) => self.on_finality_proof_request(who, request),
+		}
+	}
+
 	fn on_remote_call_request(
 		&mut self,
 		who: PeerId,
@@ -1393,6 +1642,11 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		self.sync.request_justification(&hash, number)
 	}
 
+	/// Stop or resume downloading blocks, depending on whether the import queue can take more.
+	pub fn set_import_queue_saturated(&mut self, saturated: Option<bool>) {
+		self.sync.set_import_queue_saturated(saturated)
+	}
+
 	/// Request syncing for the given block from given set of peers.
 	/// Uses `protocol` to queue a new block download request and tries to dispatch all pending
 	/// requests.
@@ -1447,6 +1701,37 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		self.sync.request_finality_proof(&hash, number)
 	}
 
+	/// Download the state of the block with the given header and import the block with it,
+	/// instead of executing the blocks up to it.
+	pub fn sync_state(&mut self, header: B::Header) {
+		self.sync.sync_state(header)
+	}
+
+	/// Call this when the response to a `CustomMessageOutcome::StateRequest` has been received,
+	/// or the request failed.
+	pub fn on_state_response(&mut self, who: PeerId, response: Result<Vec<u8>, RequestFailure>) {
+		let response = match response {
+			Ok(response) => response,
+			Err(e) => {
+				debug!(target: "sync", "State request to {} failed: {}", who, e);
+				self.sync.on_state_request_failure(&who);
+				return
+			}
+		};
+		trace!(target: "sync", "State response from {}", who);
+		match self.sync.on_state_data(&who, &response) {
+			Ok(sync::OnStateData::Nothing) => {},
+			Ok(sync::OnStateData::Import(header, state)) => {
+				let result = self.context_data.chain.import_state(header.clone(), state);
+				self.sync.on_state_imported(&header, result);
+			},
+			Err(sync::BadPeer(id, repu)) => {
+				self.behaviour.disconnect_peer(&id);
+				self.peerset_handle.report_peer(id, repu)
+			},
+		}
+	}
+
 	pub fn finality_proof_import_result(
 		&mut self,
 		request_block: (B::Hash, NumberFor<B>),
@@ -1795,6 +2080,9 @@ pub enum CustomMessageOutcome<B: BlockT> {
 	NotificationsStreamClosed { remote: PeerId, protocols: Vec<ConsensusEngineId> },
 	/// Messages have been received on one or more notifications protocols.
 	NotificationsReceived { remote: PeerId, messages: Vec<(ConsensusEngineId, Bytes)> },
+	/// A request of the state download must be sent to `target` on `state_sync::PROTOCOL_NAME`.
+	/// Its response must be given to `Protocol::on_state_response`.
+	StateRequest { target: PeerId, request: StateRequest<B::Hash> },
 	None,
 }
 
@@ -1879,6 +2167,10 @@ Protocol<B, S, H> {
 			self.propagate_extrinsics();
 		}
 
+		while let Poll::Ready(Some(())) = self.bootnodes_check_timeout.poll_next_unpin(cx) {
+			self.bootnodes.report();
+		}
+
 		for (id, r) in self.sync.block_requests() {
 			send_request(
 				&mut self.behaviour,
@@ -1905,6 +2197,27 @@ Protocol<B, S, H> {
 				&id,
 				GenericMessage::FinalityProofRequest(r))
 		}
+		// State requests go through the request/response protocols of the `Behaviour`, one at a
+		// time, the next ones are picked up when polled again.
+		if let Some((target, request)) = self.sync.state_requests().next() {
+			cx.waker().wake_by_ref();
+			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(
+				CustomMessageOutcome::StateRequest { target, request }
+			))
+		}
+
+		// Requests of peers that are over their rate are picked up again at the next tick.
+		let mut answered = 0;
+		while let Some((who, request)) = self.light_server.next() {
+			self.answer_light_request(who, request);
+			answered += 1;
+			if answered == MAX_LIGHT_REQUESTS_PER_POLL {
+				if self.light_server.has_pending() {
+					cx.waker().wake_by_ref();
+				}
+				break
+			}
+		}
 
 		let event = match self.behaviour.poll(cx, params) {
 			Poll::Pending => return Poll::Pending,
@@ -1941,7 +2254,7 @@ Protocol<B, S, H> {
 			LegacyProtoOut::Clogged { peer_id, messages } => {
 				debug!(target: "sync", "{} clogging messages:", messages.len());
 				for msg in messages.into_iter().take(5) {
-					let message: Option<Message<B>> = Decode::decode(&mut &msg[..]).ok();
+					let message = message::decode_message::<B>(&msg[..]).ok();
 					debug!(target: "sync", "{:?}", message);
 					self.on_clogged_peer(peer_id.clone(), message);
 				}
@@ -1970,6 +2283,7 @@ Protocol<B, S, H> {
 	}
 
 	fn inject_dial_failure(&mut self, peer_id: &PeerId) {
+		self.bootnodes.on_unreachable(peer_id);
 		self.behaviour.inject_dial_failure(peer_id)
 	}
 
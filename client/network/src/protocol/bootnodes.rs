@@ -0,0 +1,188 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Tracks whether the bootnodes are reachable and on our chain.
+//!
+//! Dead bootnodes don't need to be avoided here: every failed dial lowers the reputation of the
+//! node in the peerset, and a genesis mismatch bans it, so the peerset naturally moves on to the
+//! other bootnodes. This module only remembers the outcome of each attempt so that it can be
+//! inspected and so that operators are warned when none of the bootnodes is usable.
+
+use libp2p::PeerId;
+use log::{debug, info, warn};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+/// What we know about a bootnode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BootnodeState {
+	/// We have neither completed a handshake with the node nor failed to reach it yet.
+	Unknown,
+	/// The node completed the handshake and is on our chain.
+	Alive,
+	/// The last attempt to reach the node failed.
+	Unreachable,
+	/// The node is on a chain with a different genesis. It is banned by the peerset.
+	WrongChain,
+}
+
+/// Number of bootnodes in each state.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BootnodesSummary {
+	/// Number of bootnodes in the `Unknown` state.
+	pub unknown: usize,
+	/// Number of bootnodes in the `Alive` state.
+	pub alive: usize,
+	/// Number of bootnodes in the `Unreachable` state.
+	pub unreachable: usize,
+	/// Number of bootnodes in the `WrongChain` state.
+	pub wrong_chain: usize,
+}
+
+/// Liveness of the bootnodes.
+#[derive(Debug, Default)]
+pub struct Bootnodes {
+	nodes: HashMap<PeerId, BootnodeState>,
+}
+
+impl Bootnodes {
+	/// Starts tracking the given bootnodes.
+	pub fn new(bootnodes: impl IntoIterator<Item = PeerId>) -> Self {
+		Bootnodes {
+			nodes: bootnodes.into_iter().map(|peer_id| (peer_id, BootnodeState::Unknown)).collect(),
+		}
+	}
+
+	/// Adds a bootnode. Returns false if it was already known.
+	pub fn insert(&mut self, peer_id: PeerId) -> bool {
+		if self.nodes.contains_key(&peer_id) {
+			return false
+		}
+		self.nodes.insert(peer_id, BootnodeState::Unknown);
+		true
+	}
+
+	/// Returns the state of all the bootnodes.
+	pub fn states(&self) -> impl Iterator<Item = (&PeerId, BootnodeState)> {
+		self.nodes.iter().map(|(peer_id, state)| (peer_id, *state))
+	}
+
+	/// Must be called when a node completed the handshake.
+	pub fn on_alive(&mut self, peer_id: &PeerId) {
+		self.set_state(peer_id, BootnodeState::Alive)
+	}
+
+	/// Must be called when we failed to reach a node.
+	pub fn on_unreachable(&mut self, peer_id: &PeerId) {
+		self.set_state(peer_id, BootnodeState::Unreachable)
+	}
+
+	/// Must be called when a node turned out to have a different genesis.
+	pub fn on_wrong_chain(&mut self, peer_id: &PeerId) {
+		self.set_state(peer_id, BootnodeState::WrongChain)
+	}
+
+	fn set_state(&mut self, peer_id: &PeerId, new_state: BootnodeState) {
+		let state = match self.nodes.get_mut(peer_id) {
+			Some(state) => state,
+			None => return,
+		};
+		// A node on another chain won't come back to ours.
+		if *state == new_state || *state == BootnodeState::WrongChain {
+			return
+		}
+
+		match new_state {
+			BootnodeState::WrongChain =>
+				warn!(target: "sub-libp2p", "Bootnode {} is on a different chain", peer_id),
+			BootnodeState::Unreachable if *state == BootnodeState::Alive =>
+				info!(target: "sub-libp2p", "Bootnode {} became unreachable", peer_id),
+			_ => debug!(target: "sub-libp2p", "Bootnode {} is now {:?}", peer_id, new_state),
+		}
+		*state = new_state;
+	}
+
+	/// Counts the bootnodes in each state.
+	pub fn summary(&self) -> BootnodesSummary {
+		let mut summary = BootnodesSummary::default();
+		for state in self.nodes.values() {
+			match state {
+				BootnodeState::Unknown => summary.unknown += 1,
+				BootnodeState::Alive => summary.alive += 1,
+				BootnodeState::Unreachable => summary.unreachable += 1,
+				BootnodeState::WrongChain => summary.wrong_chain += 1,
+			}
+		}
+		summary
+	}
+
+	/// Logs the state of the bootnodes. Warns if none of them can be used.
+	pub fn report(&self) {
+		if self.nodes.is_empty() {
+			return
+		}
+
+		let summary = self.summary();
+		if summary.alive == 0 && summary.unknown == 0 {
+			warn!(
+				target: "sub-libp2p",
+				"None of the {} bootnodes is usable ({} unreachable, {} on a different chain)",
+				self.nodes.len(), summary.unreachable, summary.wrong_chain,
+			);
+		} else {
+			debug!(target: "sub-libp2p", "Bootnodes: {:?}", summary);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tracks_bootnode_states() {
+		let (first, second, other) = (PeerId::random(), PeerId::random(), PeerId::random());
+		let mut bootnodes = Bootnodes::new(vec![first.clone(), second.clone()]);
+		assert_eq!(bootnodes.summary(), BootnodesSummary { unknown: 2, ..Default::default() });
+
+		bootnodes.on_unreachable(&first);
+		bootnodes.on_alive(&second);
+		bootnodes.on_alive(&other);
+		assert_eq!(bootnodes.nodes.get(&first).cloned(), Some(BootnodeState::Unreachable));
+		assert_eq!(bootnodes.nodes.get(&second).cloned(), Some(BootnodeState::Alive));
+		assert_eq!(bootnodes.nodes.get(&other).cloned(), None);
+
+		bootnodes.on_alive(&first);
+		bootnodes.on_wrong_chain(&second);
+		bootnodes.on_alive(&second);
+		assert_eq!(
+			bootnodes.summary(),
+			BootnodesSummary { alive: 1, wrong_chain: 1, ..Default::default() },
+		);
+	}
+
+	#[test]
+	fn insert_keeps_known_state() {
+		let peer_id = PeerId::random();
+		let mut bootnodes = Bootnodes::default();
+		assert!(bootnodes.insert(peer_id.clone()));
+		bootnodes.on_alive(&peer_id);
+		assert!(!bootnodes.insert(peer_id.clone()));
+		assert_eq!(bootnodes.nodes.get(&peer_id).cloned(), Some(BootnodeState::Alive));
+	}
+}
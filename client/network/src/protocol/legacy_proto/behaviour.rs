@@ -368,6 +368,12 @@ impl<TSubstream> LegacyProto<TSubstream> {
 		self.peerset.debug_info()
 	}
 
+	/// Returns the peers known to the peerset manager, with their reputation and whether we are
+	/// connected to them.
+	pub fn peerset_known_peers(&mut self) -> Vec<(PeerId, i32, bool)> {
+		self.peerset.known_peers()
+	}
+
 	/// Function that is called when the peerset wants us to connect to a node.
 	fn peerset_report_connect(&mut self, peer_id: PeerId) {
 		let mut occ_entry = match self.peers.entry(peer_id) {
@@ -82,6 +82,7 @@ fn build_nodes()
 			},
 			reserved_only: false,
 			reserved_nodes: Vec::new(),
+			known_peers: Vec::new(),
 		});
 
 		let behaviour = CustomProtoWithAddr {
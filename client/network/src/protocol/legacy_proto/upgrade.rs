@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::config::ProtocolId;
+use crate::{config::ProtocolId, protocol::message::MAX_MESSAGE_SIZE};
 use bytes::BytesMut;
 use futures::prelude::*;
 use futures_codec::Framed;
@@ -253,11 +253,7 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin,
 		socket: Negotiated<TSubstream>,
 		info: Self::Info,
 	) -> Self::Future {
-		let framed = {
-			let mut codec = UviBytes::default();
-			codec.set_max_len(16 * 1024 * 1024);		// 16 MiB hard limit for packets.
-			Framed::new(socket, codec)
-		};
+		let framed = Framed::new(socket, packet_codec());
 
 		future::ok(RegisteredProtocolSubstream {
 			is_closing: false,
@@ -283,7 +279,7 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin,
 		socket: Negotiated<TSubstream>,
 		info: Self::Info,
 	) -> Self::Future {
-		let framed = Framed::new(socket, UviBytes::default());
+		let framed = Framed::new(socket, packet_codec());
 
 		future::ok(RegisteredProtocolSubstream {
 			is_closing: false,
@@ -296,3 +292,10 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin,
 		})
 	}
 }
+
+/// Codec framing the packets of a substream, with a hard limit on their size.
+fn packet_codec() -> UviBytes<BytesMut> {
+	let mut codec = UviBytes::default();
+	codec.set_max_len(MAX_MESSAGE_SIZE);
+	codec
+}
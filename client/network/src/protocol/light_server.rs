@@ -0,0 +1,207 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Bounded serving of light client requests.
+//!
+//! Requests for proofs are not answered as soon as they arrive. Instead they are queued per peer
+//! and handed out in a round-robin fashion, so that a single peer can't monopolize the node. Each
+//! peer may only have a limited number of requests pending, and may only have a limited number
+//! of requests answered each second. Requests exceeding the first limit are refused.
+
+use libp2p::PeerId;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use super::message;
+
+/// A request that a light client can send us.
+#[derive(Debug)]
+pub enum LightRequest<B: BlockT> {
+	/// Remote call request.
+	Call(message::RemoteCallRequest<B::Hash>),
+	/// Remote storage read request.
+	Read(message::RemoteReadRequest<B::Hash>),
+	/// Remote child storage read request.
+	ReadChild(message::RemoteReadChildRequest<B::Hash>),
+	/// Remote header request.
+	Header(message::RemoteHeaderRequest<NumberFor<B>>),
+	/// Remote changes request.
+	Changes(message::RemoteChangesRequest<B::Hash>),
+	/// Finality proof request.
+	FinalityProof(message::FinalityProofRequest<B::Hash>),
+}
+
+/// Requests from a single peer.
+struct PeerQueue<R> {
+	requests: VecDeque<R>,
+	/// Number of requests the peer may still have answered before the next refill.
+	tokens: u32,
+	/// When `tokens` was last refilled.
+	last_refill: Instant,
+}
+
+/// Queues light client requests and hands them out within the configured limits.
+pub struct LightServer<R> {
+	/// Maximum number of requests a peer may have queued.
+	max_pending: usize,
+	/// Maximum number of requests answered per peer and per second.
+	max_per_second: u32,
+	queues: HashMap<PeerId, PeerQueue<R>>,
+	/// Peers with queued requests, in the order they are served.
+	order: VecDeque<PeerId>,
+}
+
+impl<R> LightServer<R> {
+	/// Creates a new server with the given per-peer limits.
+	pub fn new(max_pending: usize, max_per_second: u32) -> Self {
+		LightServer {
+			max_pending,
+			max_per_second,
+			queues: HashMap::new(),
+			order: VecDeque::new(),
+		}
+	}
+
+	/// Queues a request. Returns it back if the peer already has too many requests pending.
+	pub fn push(&mut self, who: PeerId, request: R) -> Result<(), R> {
+		let max_per_second = self.max_per_second;
+		let queue = self.queues.entry(who.clone()).or_insert_with(|| PeerQueue {
+			requests: VecDeque::new(),
+			tokens: max_per_second,
+			last_refill: Instant::now(),
+		});
+		if queue.requests.len() >= self.max_pending {
+			return Err(request)
+		}
+		if queue.requests.is_empty() {
+			self.order.push_back(who);
+		}
+		queue.requests.push_back(request);
+		Ok(())
+	}
+
+	/// Returns the next request to answer, if any peer with pending requests is within its rate.
+	pub fn next(&mut self) -> Option<(PeerId, R)> {
+		self.next_at(Instant::now())
+	}
+
+	fn next_at(&mut self, now: Instant) -> Option<(PeerId, R)> {
+		for _ in 0..self.order.len() {
+			let who = self.order.pop_front()?;
+			let queue = match self.queues.get_mut(&who) {
+				Some(queue) => queue,
+				None => continue,
+			};
+
+			if now.duration_since(queue.last_refill) >= Duration::from_secs(1) {
+				queue.tokens = self.max_per_second;
+				queue.last_refill = now;
+			}
+			if queue.tokens == 0 {
+				self.order.push_back(who);
+				continue
+			}
+
+			let request = match queue.requests.pop_front() {
+				Some(request) => request,
+				None => continue,
+			};
+			queue.tokens -= 1;
+			if !queue.requests.is_empty() {
+				self.order.push_back(who.clone());
+			}
+			return Some((who, request))
+		}
+		None
+	}
+
+	/// Returns true if requests are waiting to be answered.
+	pub fn has_pending(&self) -> bool {
+		!self.order.is_empty()
+	}
+
+	/// Forgets the requests of a peer that disconnected.
+	pub fn on_disconnect(&mut self, who: &PeerId) {
+		self.queues.remove(who);
+		self.order.retain(|p| p != who);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn refuses_requests_over_pending_limit() {
+		let mut server = LightServer::new(2, 10);
+		let peer = PeerId::random();
+		assert_eq!(server.push(peer.clone(), 1), Ok(()));
+		assert_eq!(server.push(peer.clone(), 2), Ok(()));
+		assert_eq!(server.push(peer.clone(), 3), Err(3));
+		assert_eq!(server.push(PeerId::random(), 4), Ok(()));
+
+		assert_eq!(server.next(), Some((peer.clone(), 1)));
+		assert_eq!(server.push(peer.clone(), 5), Ok(()));
+	}
+
+	#[test]
+	fn serves_peers_in_turn() {
+		let mut server = LightServer::new(10, 10);
+		let peer1 = PeerId::random();
+		let peer2 = PeerId::random();
+		server.push(peer1.clone(), 1).unwrap();
+		server.push(peer1.clone(), 2).unwrap();
+		server.push(peer1.clone(), 3).unwrap();
+		server.push(peer2.clone(), 4).unwrap();
+
+		assert_eq!(server.next(), Some((peer1.clone(), 1)));
+		assert_eq!(server.next(), Some((peer2.clone(), 4)));
+		assert_eq!(server.next(), Some((peer1.clone(), 2)));
+		assert_eq!(server.next(), Some((peer1.clone(), 3)));
+		assert_eq!(server.next(), None);
+		assert!(!server.has_pending());
+	}
+
+	#[test]
+	fn limits_requests_per_second() {
+		let mut server = LightServer::new(10, 2);
+		let peer = PeerId::random();
+		for i in 0..4 {
+			server.push(peer.clone(), i).unwrap();
+		}
+
+		let now = Instant::now();
+		assert_eq!(server.next_at(now), Some((peer.clone(), 0)));
+		assert_eq!(server.next_at(now), Some((peer.clone(), 1)));
+		assert_eq!(server.next_at(now), None);
+		assert!(server.has_pending());
+
+		let later = now + Duration::from_secs(1);
+		assert_eq!(server.next_at(later), Some((peer.clone(), 2)));
+		assert_eq!(server.next_at(later), Some((peer.clone(), 3)));
+		assert_eq!(server.next_at(later), None);
+	}
+
+	#[test]
+	fn forgets_disconnected_peers() {
+		let mut server = LightServer::new(10, 10);
+		let peer = PeerId::random();
+		server.push(peer.clone(), 1).unwrap();
+		server.on_disconnect(&peer);
+		assert!(!server.has_pending());
+		assert_eq!(server.next(), None);
+	}
+}
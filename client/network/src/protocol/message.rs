@@ -31,6 +31,19 @@ use sc_client_api::StorageProof;
 /// A unique ID of a request.
 pub type RequestId = u64;
 
+/// Maximum size in bytes of a message exchanged with a peer.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Decode a message received from a peer.
+///
+/// Messages larger than `MAX_MESSAGE_SIZE` are rejected without attempting to decode them.
+pub fn decode_message<B: BlockT>(data: &[u8]) -> Result<Message<B>, Error> {
+	if data.len() > MAX_MESSAGE_SIZE {
+		return Err("Message exceeds the maximum size".into());
+	}
+	Message::<B>::decode(&mut &data[..])
+}
+
 /// Type alias for using the message type using block type parameters.
 pub type Message<B> = generic::Message<
 	<B as BlockT>::Header,
@@ -68,6 +81,11 @@ pub type BlockResponse<B> = generic::BlockResponse<
 /// A set of transactions.
 pub type Transactions<E> = Vec<E>;
 
+/// The encoded hashes of a set of transactions.
+///
+/// The hashes are those of the transaction pool, whose type is not known to the messages.
+pub type TransactionHashes = Vec<Vec<u8>>;
+
 // Bits of block data and associated artifacts to request.
 bitflags! {
 	/// Node roles bitmask.
@@ -141,7 +159,7 @@ pub mod generic {
 	use sp_runtime::Justification;
 	use crate::config::Roles;
 	use super::{
-		RemoteReadResponse, Transactions, Direction,
+		RemoteReadResponse, Transactions, TransactionHashes, Direction,
 		RequestId, BlockAttributes, RemoteCallResponse, ConsensusEngineId,
 		BlockState, StorageProof,
 	};
@@ -219,6 +237,10 @@ pub mod generic {
 		FinalityProofResponse(FinalityProofResponse<Hash>),
 		/// Batch of consensus protocol messages.
 		ConsensusBatch(Vec<ConsensusMessage>),
+		/// Hashes of transactions the sender can provide.
+		TransactionAnnounce(TransactionHashes),
+		/// Request of announced transactions, answered with `Transactions`.
+		TransactionRequest(TransactionHashes),
 		/// Chain-specific message.
 		#[codec(index = "255")]
 		ChainSpecific(Vec<u8>),
@@ -246,13 +268,15 @@ pub mod generic {
 				Message::FinalityProofRequest(_) => "FinalityProofRequest",
 				Message::FinalityProofResponse(_) => "FinalityProofResponse",
 				Message::ConsensusBatch(_) => "ConsensusBatch",
+				Message::TransactionAnnounce(_) => "TransactionAnnounce",
+				Message::TransactionRequest(_) => "TransactionRequest",
 				Message::ChainSpecific(_) => "ChainSpecific",
 			}
 		}
 	}
 
 	/// Status sent on connection.
-	#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+	#[derive(Debug, PartialEq, Eq, Clone)]
 	pub struct Status<Hash, Number> {
 		/// Protocol version.
 		pub version: u32,
@@ -268,6 +292,44 @@ pub mod generic {
 		pub genesis_hash: Hash,
 		/// Chain-specific status.
 		pub chain_status: Vec<u8>,
+		/// Identifier of the fork of the chain the node follows, if any.
+		pub fork_id: Option<Vec<u8>>,
+	}
+
+	// Custom Encode/Decode impl to maintain backwards compatibility with v5.
+	// This assumes that the packet contains nothing but the status message.
+	impl<Hash: Encode, Number: Encode> Encode for Status<Hash, Number> {
+		fn encode_to<T: Output>(&self, dest: &mut T) {
+			self.version.encode_to(dest);
+			self.min_supported_version.encode_to(dest);
+			self.roles.encode_to(dest);
+			self.best_number.encode_to(dest);
+			self.best_hash.encode_to(dest);
+			self.genesis_hash.encode_to(dest);
+			self.chain_status.encode_to(dest);
+			if let Some(fork_id) = &self.fork_id {
+				fork_id.encode_to(dest);
+			}
+		}
+	}
+
+	impl<Hash: Decode, Number: Decode> Decode for Status<Hash, Number> {
+		fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+			Ok(Status {
+				version: Decode::decode(input)?,
+				min_supported_version: Decode::decode(input)?,
+				roles: Decode::decode(input)?,
+				best_number: Decode::decode(input)?,
+				best_hash: Decode::decode(input)?,
+				genesis_hash: Decode::decode(input)?,
+				chain_status: Decode::decode(input)?,
+				// v5 statuses end with the chain status.
+				fork_id: match input.remaining_len()? {
+					Some(0) => None,
+					_ => Some(Decode::decode(input)?),
+				},
+			})
+		}
 	}
 
 	/// Request block data from a peer.
@@ -455,3 +517,39 @@ pub mod generic {
 		pub proof: Option<Vec<u8>>,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::generic::Status;
+	use crate::config::Roles;
+
+	fn status(fork_id: Option<Vec<u8>>) -> Status<u64, u64> {
+		Status {
+			version: 6,
+			min_supported_version: 3,
+			roles: Roles::FULL,
+			best_number: 1,
+			best_hash: 2,
+			genesis_hash: 3,
+			chain_status: vec![4],
+			fork_id,
+		}
+	}
+
+	#[test]
+	fn status_fork_id_is_optional() {
+		for fork_id in vec![None, Some(b"fork".to_vec())] {
+			let encoded = status(fork_id.clone()).encode();
+			assert_eq!(Status::decode(&mut &encoded[..]), Ok(status(fork_id)));
+		}
+	}
+
+	#[test]
+	fn status_with_invalid_fork_id_is_rejected() {
+		let mut encoded = status(None).encode();
+		// a fork id announced longer than the data left.
+		encoded.push(8);
+		assert!(Status::<u64, u64>::decode(&mut &encoded[..]).is_err());
+	}
+}
@@ -26,6 +26,10 @@
 //! the network, or whenever a block has been successfully verified, call the appropriate method in
 //! order to update it.
 //!
+//! Instead of executing all the blocks up to a given one, its state can be downloaded from the
+//! peers that have it with `sync_state`. The state is requested in key ranges from several peers
+//! in parallel, see the `state_sync` module.
+//!
 
 use blocks::BlockCollection;
 use sp_blockchain::{Error as ClientError, Info as BlockchainInfo};
@@ -37,17 +41,19 @@ use crate::{
 	config::{Roles, BoxFinalityProofRequestBuilder},
 	message::{self, generic::FinalityProofRequest, BlockAnnounce, BlockAttributes, BlockRequest, BlockResponse,
 	FinalityProofResponse},
+	state_sync::{self, StateRequest, StateSync},
 };
 use either::Either;
 use extra_requests::ExtraRequests;
 use libp2p::PeerId;
 use log::{debug, trace, warn, info, error};
+use sp_core::storage::Storage;
 use sp_runtime::{
 	Justification,
 	generic::BlockId,
 	traits::{Block as BlockT, Header, NumberFor, Zero, One, CheckedSub, SaturatedConversion}
 };
-use std::{fmt, ops::Range, collections::{HashMap, HashSet, VecDeque}, sync::Arc};
+use std::{fmt, iter, ops::Range, collections::{HashMap, HashSet, VecDeque}, sync::Arc};
 
 mod blocks;
 mod extra_requests;
@@ -55,12 +61,15 @@ mod extra_requests;
 /// Maximum blocks to request in a single packet.
 const MAX_BLOCKS_TO_REQUEST: usize = 128;
 
-/// Maximum blocks to store in the import queue.
+/// Maximum blocks to store in an import queue that doesn't report being saturated.
 const MAX_IMPORTING_BLOCKS: usize = 2048;
 
 /// Maximum blocks to download ahead of any gap.
 const MAX_DOWNLOAD_AHEAD: u32 = 2048;
 
+/// Maximum size of the blocks downloaded ahead of any gap.
+const MAX_DOWNLOADED_BYTES: usize = 256 * 1024 * 1024;
+
 /// We use a heuristic that with a high likelihood, by the time
 /// `MAJOR_SYNC_BLOCKS` have been imported we'll be on the same
 /// chain as (or at least closer to) the peer so we want to delay
@@ -71,6 +80,9 @@ const MAJOR_SYNC_BLOCKS: u8 = 5;
 /// Number of recently announced blocks to track for each peer.
 const ANNOUNCE_HISTORY_SIZE: usize = 64;
 
+/// Number of key ranges the state to download is split into, to be downloaded in parallel.
+const STATE_SYNC_RANGES: usize = 16;
+
 mod rep {
 	use sc_peerset::ReputationChange as Rep;
 	/// Reputation change when a peer sent us a message that led to a
@@ -138,6 +150,10 @@ pub struct ChainSync<B: BlockT> {
 	block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	max_parallel_downloads: u32,
+	/// Download of the state of a block, with the header of the block.
+	state_sync: Option<(B::Header, StateSync<B>)>,
+	/// Whether the import queue asked for no more blocks for now, if it keeps track of it.
+	import_queue_saturated: Option<bool>,
 }
 
 /// All the data we have about a Peer that we are trying to sync with
@@ -192,7 +208,9 @@ pub enum PeerSyncState<B: BlockT> {
 	/// Downloading justification for given block hash.
 	DownloadingJustification(B::Hash),
 	/// Downloading finality proof for given block hash.
-	DownloadingFinalityProof(B::Hash)
+	DownloadingFinalityProof(B::Hash),
+	/// Downloading a range of the state of the block whose state is synced.
+	DownloadingState,
 }
 
 impl<B: BlockT> PeerSyncState<B> {
@@ -248,6 +266,16 @@ pub enum OnBlockData<B: BlockT> {
 	Request(PeerId, BlockRequest<B>)
 }
 
+/// Result of [`ChainSync::on_state_data`].
+#[derive(Debug)]
+pub enum OnStateData<B: BlockT> {
+	/// The state isn't complete yet.
+	Nothing,
+	/// The state is complete, and the block should be imported with it. Call
+	/// [`ChainSync::on_state_imported`] afterwards.
+	Import(B::Header, Storage),
+}
+
 /// Result of [`ChainSync::on_block_announce`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OnBlockAnnounce {
@@ -255,6 +283,9 @@ pub enum OnBlockAnnounce {
 	Nothing,
 	/// The announcement header should be imported.
 	ImportHeader,
+	/// The data attached to the announcement failed validation. The announcing peer
+	/// should be penalized.
+	InvalidData,
 }
 
 /// Result of [`ChainSync::on_block_justification`].
@@ -318,6 +349,8 @@ impl<B: BlockT> ChainSync<B> {
 			is_idle: false,
 			block_announce_validator,
 			max_parallel_downloads,
+			state_sync: None,
+			import_queue_saturated: None,
 		}
 	}
 
@@ -332,7 +365,9 @@ impl<B: BlockT> ChainSync<B> {
 	pub fn status(&self) -> Status<B> {
 		let best_seen = self.peers.values().max_by_key(|p| p.best_number).map(|p| p.best_number);
 		let sync_state =
-			if let Some(n) = best_seen {
+			if self.state_sync.is_some() {
+				SyncState::Downloading
+			} else if let Some(n) = best_seen {
 				// A chain is classified as downloading if the provided best block is
 				// more than `MAJOR_SYNC_BLOCKS` behind the best queued block.
 				if n > self.best_queued_number && n - self.best_queued_number > MAJOR_SYNC_BLOCKS.into() {
@@ -471,6 +506,17 @@ impl<B: BlockT> ChainSync<B> {
 		})
 	}
 
+	/// Stop or resume downloading blocks, depending on whether the import queue can take more.
+	///
+	/// `None` means that the import queue doesn't keep track of it, and that no more than
+	/// `MAX_IMPORTING_BLOCKS` blocks are handed over to it.
+	pub fn set_import_queue_saturated(&mut self, saturated: Option<bool>) {
+		if self.import_queue_saturated != saturated {
+			debug!(target: "sync", "Import queue saturated: {:?}", saturated);
+		}
+		self.import_queue_saturated = saturated;
+	}
+
 	/// Request syncing for the given block from given set of peers.
 	// The implementation is similar to on_block_announce with unknown parent hash.
 	pub fn set_sync_fork_request(&mut self, mut peers: Vec<PeerId>, hash: &B::Hash, number: NumberFor<B>) {
@@ -574,7 +620,14 @@ impl<B: BlockT> ChainSync<B> {
 		if self.is_idle {
 			return Either::Left(std::iter::empty())
 		}
-		if self.queue_blocks.len() > MAX_IMPORTING_BLOCKS {
+		if self.state_sync.is_some() {
+			// The blocks can't be imported until the state they build on is.
+			trace!(target: "sync", "Block requests on hold while downloading state.");
+			return Either::Left(std::iter::empty())
+		}
+		let saturated = self.import_queue_saturated
+			.unwrap_or_else(|| self.queue_blocks.len() > MAX_IMPORTING_BLOCKS);
+		if saturated {
 			trace!(target: "sync", "Too many blocks in the queue.");
 			return Either::Left(std::iter::empty())
 		}
@@ -639,6 +692,121 @@ impl<B: BlockT> ChainSync<B> {
 		Either::Right(iter)
 	}
 
+	/// Download the state of the block with the given header from the peers that have it, instead
+	/// of executing the blocks up to it.
+	///
+	/// The parent of the block must be known, but its state doesn't need to be. Block downloads
+	/// are on hold until the block has been imported with its state, after which they go on from
+	/// it. A previous state download is abandoned.
+	pub fn sync_state(&mut self, header: B::Header) {
+		let hash = header.hash();
+		if !self.role.is_full() {
+			debug!(target: "sync", "Light clients don't store the state of {}", hash);
+			return
+		}
+		if self.is_known(&hash) {
+			debug!(target: "sync", "Not downloading the state of known block {}", hash);
+			return
+		}
+
+		debug!(target: "sync", "Downloading the state of {} ({})", hash, header.number());
+		let sync = StateSync::new(
+			hash,
+			*header.state_root(),
+			STATE_SYNC_RANGES,
+			state_sync::DEFAULT_MAX_KEYS,
+		);
+		if self.state_sync.replace((header, sync)).is_some() {
+			for peer in self.peers.values_mut() {
+				if peer.state == PeerSyncState::DownloadingState {
+					peer.state = PeerSyncState::Available;
+				}
+			}
+		}
+		self.is_idle = false;
+	}
+
+	/// Get an iterator over the requests of the state download, if any.
+	///
+	/// The requests are sent on the `state_sync::PROTOCOL_NAME` protocol, their responses are
+	/// handed to `on_state_data` and their failures to `on_state_request_failure`.
+	pub fn state_requests(&mut self) -> impl Iterator<Item = (PeerId, StateRequest<B::Hash>)> + '_ {
+		let (number, sync) = match self.state_sync.as_mut() {
+			Some((header, sync)) => (*header.number(), sync),
+			None => return Either::Left(iter::empty()),
+		};
+		let iter = self.peers.iter_mut().filter_map(move |(id, peer)| {
+			if !peer.state.is_available() || peer.best_number < number {
+				return None
+			}
+			let request = sync.next_request(id)?;
+			trace!(target: "sync", "New state request for {}: {:?}", id, request);
+			peer.state = PeerSyncState::DownloadingState;
+			Some((id.clone(), request))
+		});
+		Either::Right(iter)
+	}
+
+	/// Handle the response of `who` to a state request.
+	///
+	/// Once the state is complete, the header of the block and its state are returned to be
+	/// imported.
+	pub fn on_state_data(&mut self, who: &PeerId, response: &[u8]) -> Result<OnStateData<B>, BadPeer> {
+		self.on_state_request_done(who);
+		let complete = match self.state_sync.as_mut() {
+			Some((_, sync)) if sync.is_requesting(who) => {
+				sync.on_response(who, response).map_err(|rep| BadPeer(who.clone(), rep))?;
+				sync.is_complete()
+			},
+			_ => return Ok(OnStateData::Nothing),
+		};
+		if !complete {
+			return Ok(OnStateData::Nothing)
+		}
+
+		let (header, sync) = self.state_sync.take()
+			.expect("`state_sync` is `Some` if the state is complete; qed");
+		info!("Downloaded the state of block {} ({} entries)", header.hash(), sync.num_entries());
+		Ok(OnStateData::Import(header, sync.into_state()))
+	}
+
+	/// Handle the failure of the state request sent to `who`, for example a timeout or a refusal.
+	/// The range is requested again from another peer.
+	pub fn on_state_request_failure(&mut self, who: &PeerId) {
+		self.on_state_request_done(who);
+		if let Some((_, sync)) = self.state_sync.as_mut() {
+			sync.on_request_failure(who);
+		}
+	}
+
+	/// Call when a block returned by `on_state_data` has been imported with its state, or failed
+	/// to be.
+	pub fn on_state_imported(&mut self, header: &B::Header, result: Result<(), ClientError>) {
+		let hash = header.hash();
+		let number = *header.number();
+		match result {
+			Ok(()) => {
+				info!("Imported the state of block {} ({})", hash, number);
+				if number > self.best_imported_number {
+					self.best_imported_number = number;
+				}
+				self.on_block_queued(&hash, number);
+			},
+			Err(e) => warn!(target: "sync", "Failed to import the state of {} ({}): {:?}", hash, number, e),
+		}
+		self.is_idle = false;
+	}
+
+	/// Make `who` available again after a state request.
+	fn on_state_request_done(&mut self, who: &PeerId) {
+		if let Some(peer) = self.peers.get_mut(who) {
+			if peer.state == PeerSyncState::DownloadingState {
+				peer.state = PeerSyncState::Available;
+			}
+		}
+		self.is_idle = false;
+	}
+
 	/// Handle a response from the remote to a block request that we made.
 	///
 	/// `request` must be the original request that triggered `response`.
@@ -748,7 +916,8 @@ impl<B: BlockT> ChainSync<B> {
 
 						| PeerSyncState::Available
 						| PeerSyncState::DownloadingJustification(..)
-						| PeerSyncState::DownloadingFinalityProof(..) => Vec::new()
+						| PeerSyncState::DownloadingFinalityProof(..)
+						| PeerSyncState::DownloadingState => Vec::new()
 					}
 				} else {
 					// When request.is_none() just acccept blocks
@@ -1062,6 +1231,21 @@ impl<B: BlockT> ChainSync<B> {
 			warn!(target: "sync", "Ignored genesis block (#0) announcement from {}: {}", who, hash);
 			return OnBlockAnnounce::Nothing
 		}
+
+		// Let external validator check the block announcement before trusting any of it.
+		let assoc_data = announce.data.as_ref().map_or(&[][..], |v| v.as_slice());
+		match self.block_announce_validator.validate(&header, assoc_data) {
+			Ok(Validation::Success) => (),
+			Ok(Validation::Failure) => {
+				debug!(target: "sync", "Block announcement validation of block {} from {} failed", hash, who);
+				return OnBlockAnnounce::InvalidData
+			}
+			Err(e) => {
+				error!(target: "sync", "Block announcement validation errored: {}", e);
+				return OnBlockAnnounce::Nothing
+			}
+		}
+
 		let parent_status = self.block_status(header.parent_hash()).ok().unwrap_or(BlockStatus::Unknown);
 		let known_parent = parent_status != BlockStatus::Unknown;
 		let ancient_parent = parent_status == BlockStatus::InChainPruned;
@@ -1105,20 +1289,6 @@ impl<B: BlockT> ChainSync<B> {
 			return OnBlockAnnounce::Nothing
 		}
 
-		// Let external validator check the block announcement.
-		let assoc_data = announce.data.as_ref().map_or(&[][..], |v| v.as_slice());
-		match self.block_announce_validator.validate(&header, assoc_data) {
-			Ok(Validation::Success) => (),
-			Ok(Validation::Failure) => {
-				debug!(target: "sync", "Block announcement validation of block {} from {} failed", hash, who);
-				return OnBlockAnnounce::Nothing
-			}
-			Err(e) => {
-				error!(target: "sync", "Block announcement validation errored: {}", e);
-				return OnBlockAnnounce::Nothing
-			}
-		}
-
 		if ancient_parent {
 			trace!(target: "sync", "Ignored ancient block announced from {}: {} {:?}", who, hash, header);
 			return OnBlockAnnounce::Nothing
@@ -1150,6 +1320,9 @@ impl<B: BlockT> ChainSync<B> {
 
 	/// Call when a peer has disconnected.
 	pub fn peer_disconnected(&mut self, who: PeerId) {
+		if let Some((_, sync)) = self.state_sync.as_mut() {
+			sync.on_request_failure(&who);
+		}
 		self.blocks.clear_peer_download(&who);
 		self.peers.remove(&who);
 		self.extra_justifications.peer_disconnected(&who);
@@ -1293,6 +1466,7 @@ fn peer_block_request<B: BlockT>(
 		peer.common_number,
 		max_parallel_downloads,
 		MAX_DOWNLOAD_AHEAD,
+		MAX_DOWNLOADED_BYTES,
 	) {
 		let request = message::generic::BlockRequest {
 			id: 0,
@@ -19,6 +19,7 @@ use std::cmp;
 use std::ops::Range;
 use std::collections::{HashMap, BTreeMap};
 use std::collections::hash_map::Entry;
+use codec::Encode;
 use log::trace;
 use libp2p::PeerId;
 use sp_runtime::traits::{Block as BlockT, NumberFor, One};
@@ -49,6 +50,14 @@ impl<B: BlockT> BlockRangeState<B> {
 			BlockRangeState::Complete(ref blocks) => (blocks.len() as u32).into(),
 		}
 	}
+
+	/// Encoded size of the downloaded blocks.
+	fn size(&self) -> usize {
+		match *self {
+			BlockRangeState::Downloading { .. } => 0,
+			BlockRangeState::Complete(ref blocks) => blocks.iter().map(|b| b.block.encoded_size()).sum(),
+		}
+	}
 }
 
 /// A collection of blocks being downloaded.
@@ -56,6 +65,8 @@ impl<B: BlockT> BlockRangeState<B> {
 pub struct BlockCollection<B: BlockT> {
 	/// Downloaded blocks.
 	blocks: BTreeMap<NumberFor<B>, BlockRangeState<B>>,
+	/// Encoded size of the downloaded blocks.
+	size: usize,
 	peer_requests: HashMap<PeerId, NumberFor<B>>,
 }
 
@@ -64,6 +75,7 @@ impl<B: BlockT> BlockCollection<B> {
 	pub fn new() -> Self {
 		BlockCollection {
 			blocks: BTreeMap::new(),
+			size: 0,
 			peer_requests: HashMap::new(),
 		}
 	}
@@ -71,6 +83,7 @@ impl<B: BlockT> BlockCollection<B> {
 	/// Clear everything.
 	pub fn clear(&mut self) {
 		self.blocks.clear();
+		self.size = 0;
 		self.peer_requests.clear();
 	}
 
@@ -91,11 +104,18 @@ impl<B: BlockT> BlockCollection<B> {
 			_ => (),
 		}
 
-		self.blocks.insert(start, BlockRangeState::Complete(blocks.into_iter()
-			.map(|b| BlockData { origin: Some(who.clone()), block: b }).collect()));
+		let range = BlockRangeState::Complete(blocks.into_iter()
+			.map(|b| BlockData { origin: Some(who.clone()), block: b }).collect());
+		self.size += range.size();
+		if let Some(replaced) = self.blocks.insert(start, range) {
+			self.size -= replaced.size();
+		}
 	}
 
 	/// Returns a set of block hashes that require a header download. The returned set is marked as being downloaded.
+	///
+	/// Once the downloaded blocks take more than `max_bytes`, only the blocks before the first
+	/// downloaded ones are requested.
 	pub fn needed_blocks(
 		&mut self,
 		who: PeerId,
@@ -104,6 +124,7 @@ impl<B: BlockT> BlockCollection<B> {
 		common: NumberFor<B>,
 		max_parallel: u32,
 		max_ahead: u32,
+		max_bytes: usize,
 	) -> Option<Range<NumberFor<B>>>
 	{
 		if peer_best <= common {
@@ -149,6 +170,11 @@ impl<B: BlockT> BlockCollection<B> {
 			return None;
 		}
 
+		if self.size > max_bytes && self.blocks.iter().next().map_or(false, |(n, _)| range.start > *n) {
+			trace!(target: "sync", "Too many downloaded blocks for peer {} ({})", who, range.start);
+			return None;
+		}
+
 		self.peer_requests.insert(who, range.start);
 		self.blocks.insert(range.start, BlockRangeState::Downloading {
 			len: range.end - range.start,
@@ -171,6 +197,7 @@ impl<B: BlockT> BlockCollection<B> {
 				match range_data {
 					&mut BlockRangeState::Complete(ref mut blocks) if *start <= prev => {
 							prev = *start + (blocks.len() as u32).into();
+							self.size -= blocks.iter().map(|b| b.block.encoded_size()).sum::<usize>();
 							let mut blocks = mem::replace(blocks, Vec::new());
 							drained.append(&mut blocks);
 							ranges.push(*start);
@@ -214,6 +241,7 @@ impl<B: BlockT> BlockCollection<B> {
 #[cfg(test)]
 mod test {
 	use super::{BlockCollection, BlockData, BlockRangeState};
+	use codec::Encode;
 	use crate::{message, PeerId};
 	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
 	use sp_core::H256;
@@ -255,18 +283,18 @@ mod test {
 		let peer2 = PeerId::random();
 
 		let blocks = generate_blocks(150);
-		assert_eq!(bc.needed_blocks(peer0.clone(), 40, 150, 0, 1, 200), Some(1 .. 41));
-		assert_eq!(bc.needed_blocks(peer1.clone(), 40, 150, 0, 1, 200), Some(41 .. 81));
-		assert_eq!(bc.needed_blocks(peer2.clone(), 40, 150, 0, 1, 200), Some(81 .. 121));
+		assert_eq!(bc.needed_blocks(peer0.clone(), 40, 150, 0, 1, 200, usize::max_value()), Some(1 .. 41));
+		assert_eq!(bc.needed_blocks(peer1.clone(), 40, 150, 0, 1, 200, usize::max_value()), Some(41 .. 81));
+		assert_eq!(bc.needed_blocks(peer2.clone(), 40, 150, 0, 1, 200, usize::max_value()), Some(81 .. 121));
 
 		bc.clear_peer_download(&peer1);
 		bc.insert(41, blocks[41..81].to_vec(), peer1.clone());
 		assert_eq!(bc.drain(1), vec![]);
-		assert_eq!(bc.needed_blocks(peer1.clone(), 40, 150, 0, 1, 200), Some(121 .. 151));
+		assert_eq!(bc.needed_blocks(peer1.clone(), 40, 150, 0, 1, 200, usize::max_value()), Some(121 .. 151));
 		bc.clear_peer_download(&peer0);
 		bc.insert(1, blocks[1..11].to_vec(), peer0.clone());
 
-		assert_eq!(bc.needed_blocks(peer0.clone(), 40, 150, 0, 1, 200), Some(11 .. 41));
+		assert_eq!(bc.needed_blocks(peer0.clone(), 40, 150, 0, 1, 200, usize::max_value()), Some(11 .. 41));
 		assert_eq!(bc.drain(1), blocks[1..11].iter()
 			.map(|b| BlockData { block: b.clone(), origin: Some(peer0.clone()) }).collect::<Vec<_>>());
 
@@ -280,7 +308,7 @@ mod test {
 			.map(|b| BlockData { block: b.clone(), origin: Some(peer1.clone()) }).collect::<Vec<_>>()[..]);
 
 		bc.clear_peer_download(&peer2);
-		assert_eq!(bc.needed_blocks(peer2.clone(), 40, 150, 80, 1, 200), Some(81 .. 121));
+		assert_eq!(bc.needed_blocks(peer2.clone(), 40, 150, 80, 1, 200, usize::max_value()), Some(81 .. 121));
 		bc.clear_peer_download(&peer2);
 		bc.insert(81, blocks[81..121].to_vec(), peer2.clone());
 		bc.clear_peer_download(&peer1);
@@ -305,8 +333,33 @@ mod test {
 		bc.blocks.insert(114305, BlockRangeState::Complete(blocks));
 
 		let peer0 = PeerId::random();
-		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 000, 1, 200), Some(1 .. 100));
-		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200), None); // too far ahead
-		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200000), Some(100 + 128 .. 100 + 128 + 128));
+		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 000, 1, 200, usize::max_value()), Some(1 .. 100));
+		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200, usize::max_value()), None); // too far ahead
+		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200000, usize::max_value()), Some(100 + 128 .. 100 + 128 + 128));
+	}
+
+	#[test]
+	fn downloaded_size_is_bounded() {
+		let mut bc = BlockCollection::new();
+		let peer0 = PeerId::random();
+		let peer1 = PeerId::random();
+		let blocks = generate_blocks(60);
+		let max_bytes = blocks[21..41].iter().map(|b| b.encoded_size()).sum();
+
+		assert_eq!(bc.needed_blocks(peer0.clone(), 20, 60, 0, 1, 200, max_bytes), Some(1 .. 21));
+		assert_eq!(bc.needed_blocks(peer1.clone(), 20, 60, 0, 1, 200, max_bytes), Some(21 .. 41));
+		bc.clear_peer_download(&peer1);
+		bc.insert(21, blocks[21..41].to_vec(), peer1.clone());
+		bc.insert(41, blocks[41..42].to_vec(), peer1.clone());
+
+		// only the gap before the downloaded blocks is requested until they are drained
+		assert_eq!(bc.needed_blocks(peer1.clone(), 20, 60, 0, 1, 200, max_bytes), None);
+		bc.clear_peer_download(&peer0);
+		assert_eq!(bc.needed_blocks(peer0.clone(), 20, 60, 0, 1, 200, max_bytes), Some(1 .. 21));
+		bc.clear_peer_download(&peer0);
+		bc.insert(1, blocks[1..21].to_vec(), peer0.clone());
+		assert_eq!(bc.drain(1).len(), 41);
+		assert_eq!(bc.size, 0);
+		assert_eq!(bc.needed_blocks(peer1.clone(), 20, 60, 41, 1, 200, max_bytes), Some(42 .. 61));
 	}
 }
@@ -0,0 +1,334 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Announcement of transactions by hash.
+//!
+//! Peers supporting `TX_ANNOUNCE_VERSION` of the protocol are sent the hashes of the transactions
+//! they don't know yet rather than the transactions themselves, and request the ones they don't
+//! know either. A transaction then crosses each connection about once instead of once per
+//! neighbour of the sender. Requests of a transaction to one peer at a time are tracked here,
+//! along with the other peers announcing it, which are asked in turn when a request times out.
+//! Each peer has a bounded number of pending requests, so that a peer announcing transactions it
+//! never sends can't starve the others: the transactions which can't be requested from their
+//! announcer are queued until another announcer, or the same one, can be asked.
+
+use std::{collections::{HashMap, VecDeque}, hash::Hash, time::{Duration, Instant}};
+use codec::{Decode, Encode};
+
+use super::message::TransactionHashes;
+
+/// First protocol version announcing transactions by hash.
+pub(crate) const TX_ANNOUNCE_VERSION: u32 = 7;
+
+/// Time after which a transaction is requested from another peer announcing it.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum number of other peers announcing a requested transaction kept to retry the request.
+const MAX_ALTERNATIVES: usize = 4;
+
+/// A request of an announced transaction.
+#[derive(Debug)]
+struct Request<P> {
+	/// The peer the transaction was requested from.
+	peer: P,
+	/// When the transaction was requested.
+	requested_at: Instant,
+	/// Other peers announcing the transaction, in the order of their announcements.
+	alternatives: Vec<P>,
+}
+
+/// Requests of announced transactions waiting for an answer.
+///
+/// Holds at most `limit` requests and `peer_limit` requests per peer. Transactions which can't be
+/// requested because of these limits are queued, at most `limit` of them, and requested when
+/// requests are answered or time out.
+#[derive(Debug)]
+pub(crate) struct PendingRequests<H: Hash + Eq, P: Hash + Eq> {
+	requests: HashMap<H, Request<P>>,
+	/// Number of pending requests of each peer.
+	peer_requests: HashMap<P, usize>,
+	/// Transactions not requested yet, with the peers announcing them.
+	queued: HashMap<H, Vec<P>>,
+	/// The queued transactions, in the order of their first announcement.
+	queue: VecDeque<H>,
+	limit: usize,
+	peer_limit: usize,
+}
+
+/// Outcome of the expiration of the requests which timed out.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Expired<H, P> {
+	/// Peers to request transactions from, with the transaction to request.
+	pub(crate) requests: Vec<(P, H)>,
+	/// Peers which didn't answer a request in time.
+	pub(crate) timed_out: Vec<P>,
+}
+
+impl<H: Hash + Eq + Clone, P: Hash + Eq + Clone> PendingRequests<H, P> {
+	/// Create a tracker holding at most `limit` requests, and `peer_limit` requests per peer.
+	pub(crate) fn new(limit: usize, peer_limit: usize) -> Self {
+		PendingRequests {
+			requests: HashMap::new(),
+			peer_requests: HashMap::new(),
+			queued: HashMap::new(),
+			queue: VecDeque::new(),
+			limit,
+			peer_limit,
+		}
+	}
+
+	/// Note that `peer` announced `hash` at `now`.
+	///
+	/// Returns `true` if the transaction should be requested from `peer`. Returns `false` if it
+	/// already is requested from another peer, `peer` then being asked if that request times
+	/// out, or if there are too many pending requests, the transaction then being queued.
+	pub(crate) fn on_announce(&mut self, hash: H, peer: P, now: Instant) -> bool {
+		if let Some(request) = self.requests.get_mut(&hash) {
+			if request.peer != peer
+				&& request.alternatives.len() < MAX_ALTERNATIVES
+				&& !request.alternatives.contains(&peer)
+			{
+				request.alternatives.push(peer);
+			}
+			return false;
+		}
+
+		if self.can_request(&peer) {
+			let mut alternatives = self.queued.remove(&hash).unwrap_or_default();
+			alternatives.retain(|announcer| *announcer != peer);
+			self.insert(hash, peer, now, alternatives);
+			return true;
+		}
+
+		match self.queued.get_mut(&hash) {
+			Some(announcers) => {
+				if announcers.len() <= MAX_ALTERNATIVES && !announcers.contains(&peer) {
+					announcers.push(peer);
+				}
+			},
+			None if self.queued.len() >= self.limit => {},
+			None => {
+				self.queued.insert(hash.clone(), vec![peer]);
+				self.queue.push_back(hash);
+			},
+		}
+		false
+	}
+
+	/// Note that the transaction was received.
+	pub(crate) fn remove(&mut self, hash: &H) {
+		if let Some(request) = self.requests.remove(hash) {
+			self.release(&request.peer);
+		}
+		self.queued.remove(hash);
+	}
+
+	/// Request the transactions whose request timed out at `now` from the next peer announcing
+	/// them for which `is_connected` holds, and the queued transactions from their first
+	/// announcer which can be asked. Forget the transactions without any such announcer.
+	pub(crate) fn expire(&mut self, now: Instant, is_connected: impl Fn(&P) -> bool) -> Expired<H, P> {
+		let mut timed_out = Vec::new();
+		let expired = self.requests.iter()
+			.filter(|(_, request)| now.duration_since(request.requested_at) >= REQUEST_TIMEOUT)
+			.map(|(hash, _)| hash.clone())
+			.collect::<Vec<_>>();
+		for hash in expired {
+			let request = self.requests.remove(&hash).expect("Expired requests are pending; qed");
+			self.release(&request.peer);
+			if !timed_out.contains(&request.peer) {
+				timed_out.push(request.peer);
+			}
+			// The transaction is now requested from the first announcer which can be asked.
+			let announcers = request.alternatives.into_iter()
+				.filter(|peer| is_connected(peer))
+				.collect::<Vec<_>>();
+			if !announcers.is_empty() {
+				self.queued.insert(hash.clone(), announcers);
+				self.queue.push_front(hash);
+			}
+		}
+
+		let mut requests = Vec::new();
+		for _ in 0..self.queue.len() {
+			let hash = match self.queue.pop_front() {
+				Some(hash) => hash,
+				None => break,
+			};
+			let mut announcers = match self.queued.remove(&hash) {
+				Some(announcers) => announcers,
+				None => continue,
+			};
+			announcers.retain(|peer| is_connected(peer));
+			match announcers.iter().position(|peer| self.can_request(peer)) {
+				Some(index) => {
+					let peer = announcers.remove(index);
+					requests.push((peer.clone(), hash.clone()));
+					self.insert(hash, peer, now, announcers);
+				},
+				None if announcers.is_empty() => {},
+				None => {
+					self.queued.insert(hash.clone(), announcers);
+					self.queue.push_back(hash);
+				},
+			}
+		}
+
+		Expired { requests, timed_out }
+	}
+
+	/// Whether a transaction can be requested from `peer`.
+	fn can_request(&self, peer: &P) -> bool {
+		self.requests.len() < self.limit
+			&& self.peer_requests.get(peer).map_or(true, |requests| *requests < self.peer_limit)
+	}
+
+	fn insert(&mut self, hash: H, peer: P, now: Instant, alternatives: Vec<P>) {
+		*self.peer_requests.entry(peer.clone()).or_default() += 1;
+		self.requests.insert(hash, Request { peer, requested_at: now, alternatives });
+	}
+
+	fn release(&mut self, peer: &P) {
+		if let Some(requests) = self.peer_requests.get_mut(peer) {
+			*requests -= 1;
+			if *requests == 0 {
+				self.peer_requests.remove(peer);
+			}
+		}
+	}
+}
+
+/// Encode the hashes of transactions for `TransactionAnnounce` and `TransactionRequest`.
+pub(crate) fn encode_hashes<'a, H: Encode + 'a>(hashes: impl IntoIterator<Item = &'a H>) -> TransactionHashes {
+	hashes.into_iter().map(Encode::encode).collect()
+}
+
+/// Decode the hashes of a `TransactionAnnounce` or a `TransactionRequest`, keeping at most
+/// `limit` of them.
+pub(crate) fn decode_hashes<H: Decode>(hashes: TransactionHashes, limit: usize) -> Result<Vec<H>, codec::Error> {
+	hashes.into_iter()
+		.take(limit)
+		.map(|hash| H::decode(&mut &hash[..]))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn expired(requests: Vec<(char, u8)>, timed_out: Vec<char>) -> Expired<u8, char> {
+		Expired { requests, timed_out }
+	}
+
+	#[test]
+	fn requests_transactions_from_one_peer_at_a_time() {
+		let start = Instant::now();
+		let mut requests = PendingRequests::new(2, 2);
+
+		assert!(requests.on_announce(1u8, 'a', start));
+		assert!(!requests.on_announce(1u8, 'b', start + Duration::from_secs(1)));
+		assert!(!requests.on_announce(1u8, 'b', start + Duration::from_secs(1)));
+		assert!(requests.on_announce(2u8, 'a', start));
+
+		// Too many pending requests.
+		assert!(!requests.on_announce(3u8, 'a', start));
+
+		requests.remove(&2);
+		assert!(requests.on_announce(3u8, 'a', start));
+
+		// Nothing timed out yet.
+		assert_eq!(requests.expire(start + Duration::from_secs(1), |_| true), expired(vec![], vec![]));
+
+		// The other announcer is asked once the request timed out, and requests without any
+		// are forgotten.
+		let now = start + REQUEST_TIMEOUT;
+		assert_eq!(requests.expire(now, |_| true), expired(vec![('b', 1u8)], vec!['a']));
+		assert_eq!(requests.requests.len(), 1);
+
+		// No announcer is left after the retry timed out.
+		assert_eq!(requests.expire(now + REQUEST_TIMEOUT, |_| true), expired(vec![], vec!['b']));
+		assert!(requests.requests.is_empty());
+		assert!(requests.peer_requests.is_empty());
+	}
+
+	#[test]
+	fn retries_with_connected_announcers_only() {
+		let start = Instant::now();
+		let mut requests = PendingRequests::new(1, 1);
+
+		assert!(requests.on_announce(1u8, 'a', start));
+		for peer in &['b', 'c', 'd', 'e', 'f'] {
+			assert!(!requests.on_announce(1u8, *peer, start));
+		}
+
+		// At most `MAX_ALTERNATIVES` announcers are kept.
+		assert_eq!(requests.requests[&1].alternatives, vec!['b', 'c', 'd', 'e']);
+
+		assert_eq!(
+			requests.expire(start + REQUEST_TIMEOUT, |peer| *peer == 'd'),
+			expired(vec![('d', 1u8)], vec!['a']),
+		);
+		assert!(requests.requests[&1].alternatives.is_empty());
+		assert_eq!(
+			requests.expire(start + REQUEST_TIMEOUT * 2, |peer| *peer == 'd'),
+			expired(vec![], vec!['d']),
+		);
+		assert!(requests.requests.is_empty());
+	}
+
+	#[test]
+	fn peers_not_answering_do_not_starve_the_others() {
+		let start = Instant::now();
+		let later = start + Duration::from_secs(3);
+		let mut requests = PendingRequests::new(4, 2);
+
+		// `a` has at most two pending requests, the transactions it announces next are queued.
+		assert!(requests.on_announce(1u8, 'a', start));
+		assert!(requests.on_announce(2u8, 'a', start));
+		for hash in 3..6 {
+			assert!(!requests.on_announce(hash, 'a', start));
+		}
+
+		// Another announcer of a queued transaction is asked for it.
+		assert!(requests.on_announce(6u8, 'b', later));
+		assert!(requests.on_announce(3u8, 'b', later));
+		assert_eq!(requests.requests[&3].alternatives, vec!['a']);
+
+		// Too many pending requests.
+		assert!(!requests.on_announce(7u8, 'b', later));
+
+		// The requests of `a` time out, the slots they free are used by the queued transactions.
+		assert_eq!(
+			requests.expire(start + REQUEST_TIMEOUT, |_| true),
+			expired(vec![('a', 4u8), ('a', 5u8)], vec!['a']),
+		);
+		assert!(!requests.queued.contains_key(&4));
+
+		requests.remove(&6);
+		assert_eq!(
+			requests.expire(start + REQUEST_TIMEOUT + Duration::from_secs(1), |_| true),
+			expired(vec![('b', 7u8)], vec![]),
+		);
+		assert!(requests.queued.is_empty());
+	}
+
+	#[test]
+	fn encodes_hashes() {
+		let hashes = encode_hashes(&[[1u8; 32], [2u8; 32]]);
+		assert_eq!(decode_hashes::<[u8; 32]>(hashes.clone(), 10).unwrap(), vec![[1u8; 32], [2u8; 32]]);
+		assert_eq!(decode_hashes::<[u8; 32]>(hashes, 1).unwrap(), vec![[1u8; 32]]);
+		assert!(decode_hashes::<[u8; 32]>(vec![vec![1, 2]], 10).is_err());
+	}
+}
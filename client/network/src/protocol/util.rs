@@ -46,6 +46,16 @@ impl<T: Hash + Eq> LruHashSet<T> {
 		}
 		false
 	}
+
+	/// Returns `true` if the set contains the element, without updating its LRU position.
+	pub(crate) fn contains(&self, e: &T) -> bool {
+		self.set.contains(e)
+	}
+
+	/// Removes the element from the set. Returns `true` if it was present.
+	pub(crate) fn remove(&mut self, e: &T) -> bool {
+		self.set.remove(e)
+	}
 }
 
 #[cfg(test)]
@@ -72,5 +82,7 @@ mod tests {
 		// We reached the limit. The next element forces the oldest one out.
 		assert!(set.insert(3));
 		assert_eq!(vec![&1, &3], set.set.iter().collect::<Vec<_>>());
+		assert!(set.contains(&1));
+		assert!(!set.contains(&2));
 	}
 }
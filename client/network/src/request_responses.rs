@@ -0,0 +1,443 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generic request/response protocols.
+//!
+//! Code outside of this crate can register protocols where the dialer opens a new substream, sends
+//! a single request on it, and the listener answers with a single response before the substream is
+//! closed. Requests and responses are length-prefixed opaque bytes.
+//!
+//! Protocols are registered at runtime and are shared with the handlers of all connections, so
+//! that a protocol registered after a connection has been opened is still accepted on it.
+
+use fnv::FnvHashMap;
+use futures::{prelude::*, channel::{mpsc, oneshot}, future::Either, stream::FuturesUnordered};
+use futures_timer::Delay;
+use libp2p::core::{ConnectedPoint, Multiaddr, PeerId};
+use libp2p::core::upgrade::{
+	self, InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated, NegotiationError, ReadOneError,
+	UpgradeError,
+};
+use libp2p::swarm::{
+	KeepAlive, NetworkBehaviour, NetworkBehaviourAction, PollParameters, ProtocolsHandler,
+	ProtocolsHandlerEvent, ProtocolsHandlerUpgrErr, SubstreamProtocol,
+};
+use log::{debug, warn};
+use parking_lot::RwLock;
+use std::{borrow::Cow, collections::{HashSet, VecDeque}, io, iter, marker::PhantomData, pin::Pin};
+use std::{sync::Arc, task::{Context, Poll}, time::Duration};
+
+/// Configuration of a request/response protocol.
+#[derive(Debug, Clone)]
+pub struct RequestResponseConfig {
+	/// Name of the protocol on the wire, e.g. `/myproto/snapshot/1`.
+	pub name: Cow<'static, str>,
+	/// Maximum size of a request in bytes. Larger incoming requests are rejected.
+	pub max_request_size: usize,
+	/// Maximum size of a response in bytes. Larger responses are rejected.
+	pub max_response_size: usize,
+	/// Time after which a request is abandoned if no response has been received, and after which
+	/// an incoming request that hasn't been answered is dropped.
+	pub request_timeout: Duration,
+	/// Channel on which incoming requests are delivered, or `None` if the local node only sends
+	/// requests on this protocol. Incoming requests are refused if the channel is full.
+	pub inbound_queue: Option<mpsc::Sender<IncomingRequest>>,
+}
+
+/// A request received from a remote.
+#[derive(Debug)]
+pub struct IncomingRequest {
+	/// Who sent the request.
+	pub peer: PeerId,
+	/// Content of the request.
+	pub payload: Vec<u8>,
+	/// Channel to send the response on. Dropping it refuses the request.
+	pub pending_response: oneshot::Sender<Vec<u8>>,
+}
+
+/// Error when sending a request.
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display)]
+pub enum RequestFailure {
+	/// We are not connected to the target.
+	#[display(fmt = "Not connected to the target")]
+	NotConnected,
+	/// The protocol hasn't been registered locally.
+	#[display(fmt = "Protocol not registered")]
+	UnknownProtocol,
+	/// The remote doesn't support the protocol.
+	#[display(fmt = "Protocol not supported by the remote")]
+	UnsupportedProtocol,
+	/// The remote closed the substream without answering.
+	#[display(fmt = "Request refused by the remote")]
+	Refused,
+	/// The response is larger than `max_response_size`.
+	#[display(fmt = "Response too large")]
+	ResponseTooLarge,
+	/// No response was received in time.
+	#[display(fmt = "Request timed out")]
+	Timeout,
+	/// Error on the substream.
+	#[display(fmt = "Network error: {}", _0)]
+	Network(String),
+	/// The network worker has shut down.
+	#[display(fmt = "Network worker has shut down")]
+	Obsolete,
+}
+
+impl std::error::Error for RequestFailure {}
+
+/// Protocols shared between the behaviour and the handlers of all connections.
+type SharedProtocols = Arc<RwLock<FnvHashMap<Cow<'static, str>, RequestResponseConfig>>>;
+
+/// Implementation of `NetworkBehaviour` that sends and answers requests on the registered
+/// protocols.
+pub struct RequestResponsesBehaviour<TSubstream> {
+	/// Registered protocols.
+	protocols: SharedProtocols,
+	/// Peers we are connected to.
+	connected: HashSet<PeerId>,
+	/// Actions to produce in `poll()`.
+	pending_actions: VecDeque<NetworkBehaviourAction<HandlerIn, void::Void>>,
+	/// Pin the `TSubstream` generic.
+	marker: PhantomData<TSubstream>,
+}
+
+impl<TSubstream> RequestResponsesBehaviour<TSubstream> {
+	/// Creates a behaviour without any protocol.
+	pub fn new() -> Self {
+		RequestResponsesBehaviour {
+			protocols: Default::default(),
+			connected: HashSet::new(),
+			pending_actions: VecDeque::new(),
+			marker: PhantomData,
+		}
+	}
+
+	/// Registers a protocol, replacing any existing protocol with the same name.
+	pub fn register_protocol(&mut self, config: RequestResponseConfig) {
+		if let Some(previous) = self.protocols.write().insert(config.name.clone(), config) {
+			warn!(target: "sub-libp2p", "Request/response protocol {} registered twice", previous.name);
+		}
+	}
+
+	/// Sends a request to `target`. The outcome is sent on `pending_response`.
+	pub fn send_request(
+		&mut self,
+		target: &PeerId,
+		protocol: &str,
+		payload: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	) {
+		let (name, max_response_size, timeout) = match self.protocols.read().get(protocol) {
+			Some(config) => (config.name.clone(), config.max_response_size, config.request_timeout),
+			None => {
+				let _ = pending_response.send(Err(RequestFailure::UnknownProtocol));
+				return
+			}
+		};
+
+		if !self.connected.contains(target) {
+			let _ = pending_response.send(Err(RequestFailure::NotConnected));
+			return
+		}
+
+		self.pending_actions.push_back(NetworkBehaviourAction::SendEvent {
+			peer_id: target.clone(),
+			event: HandlerIn {
+				upgrade: RequestUpgrade { name, payload, max_response_size },
+				timeout,
+				pending_response,
+			},
+		});
+	}
+}
+
+impl<TSubstream> NetworkBehaviour for RequestResponsesBehaviour<TSubstream>
+where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+	type ProtocolsHandler = RequestResponseHandler<TSubstream>;
+	type OutEvent = void::Void;
+
+	fn new_handler(&mut self) -> Self::ProtocolsHandler {
+		RequestResponseHandler::new(self.protocols.clone())
+	}
+
+	fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn inject_connected(&mut self, peer_id: PeerId, _: ConnectedPoint) {
+		self.connected.insert(peer_id);
+	}
+
+	fn inject_disconnected(&mut self, peer_id: &PeerId, _: ConnectedPoint) {
+		self.connected.remove(peer_id);
+	}
+
+	fn inject_node_event(&mut self, peer: PeerId, event: HandlerOut) {
+		let HandlerOut { protocol, payload, pending_response } = event;
+		let mut protocols = self.protocols.write();
+		let queue = match protocols.get_mut(&protocol).and_then(|config| config.inbound_queue.as_mut()) {
+			Some(queue) => queue,
+			None => return,
+		};
+
+		let request = IncomingRequest { peer: peer.clone(), payload, pending_response };
+		if queue.try_send(request).is_err() {
+			debug!(target: "sub-libp2p", "Refusing {} request from {}: queue is full", protocol, peer);
+		}
+	}
+
+	fn poll(
+		&mut self,
+		_: &mut Context,
+		_: &mut impl PollParameters,
+	) -> Poll<NetworkBehaviourAction<HandlerIn, Self::OutEvent>> {
+		match self.pending_actions.pop_front() {
+			Some(action) => Poll::Ready(action),
+			None => Poll::Pending,
+		}
+	}
+}
+
+/// Request sent from the behaviour to the handler of a connection.
+pub struct HandlerIn {
+	upgrade: RequestUpgrade,
+	timeout: Duration,
+	pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+}
+
+/// Request received by the handler of a connection, reported to the behaviour.
+pub struct HandlerOut {
+	protocol: Cow<'static, str>,
+	payload: Vec<u8>,
+	pending_response: oneshot::Sender<Vec<u8>>,
+}
+
+/// Implementation of `ProtocolsHandler` for request/response protocols. Each request uses a new
+/// substream. Failures never close the connection.
+pub struct RequestResponseHandler<TSubstream> {
+	/// Registered protocols.
+	protocols: SharedProtocols,
+	/// Requests waiting for a substream to be opened.
+	pending_requests: VecDeque<HandlerIn>,
+	/// Number of outbound substreams being opened or waiting for their response.
+	requests_in_progress: usize,
+	/// Incoming requests to report to the behaviour.
+	events_out: VecDeque<HandlerOut>,
+	/// Incoming requests waiting for their response to be sent.
+	responses: FuturesUnordered<Pin<Box<dyn Future<Output = ()> + Send>>>,
+	/// Pin the `TSubstream` generic.
+	marker: PhantomData<TSubstream>,
+}
+
+impl<TSubstream> RequestResponseHandler<TSubstream> {
+	fn new(protocols: SharedProtocols) -> Self {
+		RequestResponseHandler {
+			protocols,
+			pending_requests: VecDeque::new(),
+			requests_in_progress: 0,
+			events_out: VecDeque::new(),
+			responses: FuturesUnordered::new(),
+			marker: PhantomData,
+		}
+	}
+}
+
+impl<TSubstream> ProtocolsHandler for RequestResponseHandler<TSubstream>
+where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+	type InEvent = HandlerIn;
+	type OutEvent = HandlerOut;
+	type Error = void::Void;
+	type Substream = TSubstream;
+	type InboundProtocol = InboundProtocols;
+	type OutboundProtocol = RequestUpgrade;
+	type OutboundOpenInfo = oneshot::Sender<Result<Vec<u8>, RequestFailure>>;
+
+	fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+		let protocols = self.protocols.read();
+		let inbound = protocols.values()
+			.filter(|config| config.inbound_queue.is_some())
+			.map(|config| ProtocolInfo { name: config.name.clone(), max_size: config.max_request_size })
+			.collect();
+		let timeout = protocols.values()
+			.map(|config| config.request_timeout)
+			.max()
+			.unwrap_or_else(|| Duration::from_secs(10));
+		SubstreamProtocol::new(InboundProtocols(inbound)).with_timeout(timeout)
+	}
+
+	fn inject_fully_negotiated_inbound(
+		&mut self,
+		(protocol, payload, mut socket): <Self::InboundProtocol as InboundUpgrade<TSubstream>>::Output,
+	) {
+		let timeout = match self.protocols.read().get(&protocol) {
+			Some(config) => config.request_timeout,
+			None => return,
+		};
+
+		let (pending_response, response) = oneshot::channel();
+		self.responses.push(Box::pin(async move {
+			let response = match future::select(response, Delay::new(timeout)).await {
+				Either::Left((Ok(response), _)) => response,
+				// Refused or timed out. We drop the substream without answering.
+				_ => return,
+			};
+			if let Err(err) = upgrade::write_one(&mut socket, response).await {
+				debug!(target: "sub-libp2p", "Failed to send response: {}", err);
+			}
+		}));
+		self.events_out.push_back(HandlerOut { protocol, payload, pending_response });
+	}
+
+	fn inject_fully_negotiated_outbound(
+		&mut self,
+		response: Vec<u8>,
+		pending_response: Self::OutboundOpenInfo,
+	) {
+		self.requests_in_progress -= 1;
+		let _ = pending_response.send(Ok(response));
+	}
+
+	fn inject_event(&mut self, request: HandlerIn) {
+		self.pending_requests.push_back(request);
+	}
+
+	fn inject_dial_upgrade_error(
+		&mut self,
+		pending_response: Self::OutboundOpenInfo,
+		error: ProtocolsHandlerUpgrErr<ReadOneError>,
+	) {
+		self.requests_in_progress -= 1;
+		let error = match error {
+			ProtocolsHandlerUpgrErr::Timeout | ProtocolsHandlerUpgrErr::Timer => RequestFailure::Timeout,
+			ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Select(NegotiationError::Failed)) =>
+				RequestFailure::UnsupportedProtocol,
+			ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Select(NegotiationError::ProtocolError(err))) =>
+				RequestFailure::Network(err.to_string()),
+			ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Apply(ReadOneError::TooLarge { .. })) =>
+				RequestFailure::ResponseTooLarge,
+			ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Apply(ReadOneError::Io(err))) =>
+				if err.kind() == io::ErrorKind::UnexpectedEof {
+					RequestFailure::Refused
+				} else {
+					RequestFailure::Network(err.to_string())
+				},
+		};
+		let _ = pending_response.send(Err(error));
+	}
+
+	fn connection_keep_alive(&self) -> KeepAlive {
+		if self.pending_requests.is_empty() && self.requests_in_progress == 0 && self.responses.is_empty() {
+			KeepAlive::No
+		} else {
+			KeepAlive::Yes
+		}
+	}
+
+	fn poll(
+		&mut self,
+		cx: &mut Context,
+	) -> Poll<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, HandlerOut, void::Void>> {
+		while let Poll::Ready(Some(())) = self.responses.poll_next_unpin(cx) {}
+
+		if let Some(event) = self.events_out.pop_front() {
+			return Poll::Ready(ProtocolsHandlerEvent::Custom(event))
+		}
+
+		if let Some(request) = self.pending_requests.pop_front() {
+			self.requests_in_progress += 1;
+			return Poll::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+				protocol: SubstreamProtocol::new(request.upgrade).with_timeout(request.timeout),
+				info: request.pending_response,
+			})
+		}
+
+		Poll::Pending
+	}
+}
+
+/// Name of a protocol, along with the maximum size of the message read on it.
+#[derive(Debug, Clone)]
+pub struct ProtocolInfo {
+	name: Cow<'static, str>,
+	max_size: usize,
+}
+
+impl AsRef<[u8]> for ProtocolInfo {
+	fn as_ref(&self) -> &[u8] {
+		self.name.as_bytes()
+	}
+}
+
+/// Upgrade for inbound substreams, accepting all the protocols that accept requests. Reads the
+/// request.
+#[derive(Debug, Clone)]
+pub struct InboundProtocols(Vec<ProtocolInfo>);
+
+impl UpgradeInfo for InboundProtocols {
+	type Info = ProtocolInfo;
+	type InfoIter = Vec<ProtocolInfo>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		self.0.clone()
+	}
+}
+
+impl<TSubstream> InboundUpgrade<TSubstream> for InboundProtocols
+where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+	type Output = (Cow<'static, str>, Vec<u8>, Negotiated<TSubstream>);
+	type Error = ReadOneError;
+	type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
+
+	fn upgrade_inbound(self, mut socket: Negotiated<TSubstream>, info: ProtocolInfo) -> Self::Future {
+		Box::pin(async move {
+			let payload = upgrade::read_one(&mut socket, info.max_size).await?;
+			Ok((info.name, payload, socket))
+		})
+	}
+}
+
+/// Upgrade for outbound substreams. Sends the request and reads the response.
+#[derive(Debug)]
+pub struct RequestUpgrade {
+	name: Cow<'static, str>,
+	payload: Vec<u8>,
+	max_response_size: usize,
+}
+
+impl UpgradeInfo for RequestUpgrade {
+	type Info = ProtocolInfo;
+	type InfoIter = iter::Once<ProtocolInfo>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		iter::once(ProtocolInfo { name: self.name.clone(), max_size: self.max_response_size })
+	}
+}
+
+impl<TSubstream> OutboundUpgrade<TSubstream> for RequestUpgrade
+where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+	type Output = Vec<u8>;
+	type Error = ReadOneError;
+	type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
+
+	fn upgrade_outbound(self, mut socket: Negotiated<TSubstream>, info: ProtocolInfo) -> Self::Future {
+		let payload = self.payload;
+		Box::pin(async move {
+			upgrade::write_with_len_prefix(&mut socket, payload).await?;
+			upgrade::read_one(&mut socket, info.max_size).await
+		})
+	}
+}
@@ -25,16 +25,17 @@
 //! The methods of the [`NetworkService`] are implemented by sending a message over a channel,
 //! which is then processed by [`NetworkWorker::poll`].
 
-use std::{collections::{HashMap, HashSet}, fs, marker::PhantomData, io, path::Path};
+use std::{borrow::Cow, collections::{HashMap, HashSet}, fs, marker::PhantomData, io, path::Path};
 use std::sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}};
 use std::pin::Pin;
 use std::task::Poll;
 
 use sp_consensus::import_queue::{ImportQueue, Link};
 use sp_consensus::import_queue::{BlockImportResult, BlockImportError};
-use futures::{prelude::*, channel::mpsc};
+use futures::{prelude::*, channel::{mpsc, oneshot}};
 use log::{warn, error, info, trace};
-use libp2p::{PeerId, Multiaddr, kad::record};
+use libp2p::{PeerId, Multiaddr, multiaddr, kad::record};
+use libp2p::identity::{Keypair, PublicKey, error::SigningError};
 use libp2p::core::{transport::boxed::Boxed, muxing::StreamMuxerBox};
 use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
 use parking_lot::Mutex;
@@ -43,19 +44,31 @@ use sp_runtime::{traits::{Block as BlockT, NumberFor}, ConsensusEngineId};
 
 use crate::{behaviour::{Behaviour, BehaviourOut}, config::{parse_str_addr, parse_addr}};
 use crate::{NetworkState, NetworkStateNotConnectedPeer, NetworkStatePeer};
+use crate::{NatStatus, NatStatusObservedAddress, PortMappingStatus};
 use crate::{transport, config::NonReservedPeerMode, ReputationChange};
 use crate::config::{Params, TransportConfig};
 use crate::error::Error;
+use crate::known_peers;
 use crate::protocol::{self, Protocol, Context, PeerInfo};
 use crate::protocol::{event::Event, light_dispatch::{AlwaysBadChecker, RequestData}};
 use crate::protocol::specialization::NetworkSpecialization;
 use crate::protocol::sync::SyncState;
+use crate::request_responses::{RequestFailure, RequestResponseConfig};
+use crate::utils::interval;
+
+/// Prefix of the messages signed with the key of the local node.
+///
+/// Only challenges with this prefix are signed, so that the signatures can't be replayed in the
+/// handshakes of libp2p or any other protocol using the key.
+pub const NODE_IDENTITY_CONTEXT: &[u8] = b"substrate-node-identity:";
 
 /// Minimum Requirements for a Hash within Networking
-pub trait ExHashT: std::hash::Hash + Eq + std::fmt::Debug + Clone + Send + Sync + 'static {}
+///
+/// Hashes are encoded to announce transactions to peers.
+pub trait ExHashT: std::hash::Hash + Eq + std::fmt::Debug + Clone + Send + Sync + codec::Codec + 'static {}
 
 impl<T> ExHashT for T where
-	T: std::hash::Hash + Eq + std::fmt::Debug + Clone + Send + Sync + 'static
+	T: std::hash::Hash + Eq + std::fmt::Debug + Clone + Send + Sync + codec::Codec + 'static
 {}
 
 /// Transaction pool interface
@@ -109,6 +122,8 @@ pub struct NetworkService<B: BlockT + 'static, S: NetworkSpecialization<B>, H: E
 	is_major_syncing: Arc<AtomicBool>,
 	/// Local copy of the `PeerId` of the local node.
 	local_peer_id: PeerId,
+	/// Key of the local node, from which its `PeerId` derives.
+	local_identity: Keypair,
 	/// Bandwidth logging system. Can be queried to know the average bandwidth consumed.
 	bandwidth: Arc<transport::BandwidthSinks>,
 	/// Peerset manager (PSM); manages the reputation of nodes and indicates the network which
@@ -177,12 +192,23 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 			}
 		}
 
+		// Reconnect to the peers that were useful before the restart, without waiting for the
+		// discovery to find them again.
+		let mut known_peers = Vec::new();
+		if let Some(ref path) = params.network_config.net_config_path {
+			for (peer_id, reputation, addresses) in known_peers::load(path) {
+				known_addresses.extend(addresses.into_iter().map(|addr| (peer_id.clone(), addr)));
+				known_peers.push((peer_id, reputation));
+			}
+		}
+
 		let peerset_config = sc_peerset::PeersetConfig {
 			in_peers: params.network_config.in_peers,
 			out_peers: params.network_config.out_peers,
 			bootnodes,
 			reserved_only: params.network_config.non_reserved_mode == NonReservedPeerMode::Deny,
 			reserved_nodes,
+			known_peers,
 		};
 
 		// Private and public keys configuration.
@@ -197,6 +223,9 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 			protocol::ProtocolConfig {
 				roles: params.roles,
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
+				fork_id: params.fork_id,
+				max_pending_light_requests: params.network_config.max_pending_light_requests,
+				max_light_requests_per_second: params.network_config.max_light_requests_per_second,
 			},
 			params.chain,
 			params.on_demand.as_ref().map(|od| od.checker().clone())
@@ -237,7 +266,7 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 					TransportConfig::Normal { wasm_external_transport, .. } =>
 						(false, wasm_external_transport)
 				};
-				transport::build_transport(local_identity, config_mem, config_wasm)
+				transport::build_transport(local_identity.clone(), config_mem, config_wasm)
 			};
 			(Swarm::<B, S, H>::new(transport, behaviour, local_peer_id.clone()), bandwidth)
 		};
@@ -263,6 +292,7 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 			is_major_syncing: is_major_syncing.clone(),
 			peerset: peerset_handle,
 			local_peer_id,
+			local_identity,
 			to_worker: to_worker.clone(),
 			_marker: PhantomData,
 		});
@@ -277,6 +307,8 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 			from_worker,
 			light_client_rqs: params.on_demand.and_then(|od| od.extract_receiver()),
 			event_streams: Vec::new(),
+			net_config_path: params.network_config.net_config_path,
+			known_peers_save: Box::pin(interval(known_peers::SAVE_INTERVAL)),
 		})
 	}
 
@@ -346,6 +378,26 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 		self.network_service.user_protocol_mut().on_block_finalized(hash, &header);
 	}
 
+	/// Save the peers worth reconnecting to after a restart, if there is a network configuration
+	/// directory.
+	fn save_known_peers(&mut self) {
+		let path = match self.net_config_path {
+			Some(ref path) => path,
+			None => return,
+		};
+
+		let swarm = &mut self.network_service;
+		let peers = swarm.user_protocol_mut().peerset_known_peers().into_iter()
+			.map(|(peer_id, reputation, connected)| {
+				let addresses = NetworkBehaviour::addresses_of_peer(&mut **swarm, &peer_id);
+				(peer_id, reputation, connected, addresses)
+			})
+			.collect::<Vec<_>>();
+		if let Err(err) = known_peers::save(path, peers) {
+			warn!(target: "sub-libp2p", "Failed to save known peers in {}: {}", path, err);
+		}
+	}
+
 	/// Get network state.
 	///
 	/// **Note**: Use this only for debugging. This API is unstable. There are warnings literaly
@@ -404,9 +456,46 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 			connected_peers,
 			not_connected_peers,
 			peerset: swarm.user_protocol_mut().peerset_debug_info(),
+			bootnodes: swarm.user_protocol().bootnodes().states()
+				.map(|(peer_id, state)| (peer_id.to_base58(), state))
+				.collect(),
+		}
+	}
+
+	/// Get what the node knows about its reachability from other nodes.
+	///
+	/// **Note**: Use this only for debugging. This API is unstable.
+	pub fn nat_status(&mut self) -> NatStatus {
+		let swarm = &mut self.network_service;
+		let mut observed_addresses = HashMap::<Multiaddr, usize>::new();
+		for peer_id in swarm.user_protocol().open_peers() {
+			if let Some(address) = swarm.node(peer_id).and_then(|i| i.observed_addr()) {
+				*observed_addresses.entry(address.clone()).or_default() += 1;
+			}
+		}
+		let mut observed_addresses = observed_addresses.into_iter()
+			.map(|(address, reporters)| NatStatusObservedAddress { address, reporters })
+			.collect::<Vec<_>>();
+		observed_addresses.sort_by(|a, b| b.reporters.cmp(&a.reporters));
+
+		NatStatus {
+			listened_addresses: Swarm::<B, S, H>::listeners(&swarm).cloned().collect(),
+			external_addresses: Swarm::<B, S, H>::external_addresses(&swarm).cloned().collect(),
+			observed_addresses,
+			port_mapping: PortMappingStatus::NotSupported,
 		}
 	}
 
+	/// Returns the addresses the node is listening on, with the local `PeerId` appended so that
+	/// they can be handed out to other nodes as is.
+	pub fn local_listen_addresses(&self) -> Vec<Multiaddr> {
+		let swarm = &self.network_service;
+		let peer_id = Swarm::<B, S, H>::local_peer_id(swarm).clone();
+		Swarm::<B, S, H>::listeners(swarm)
+			.map(|addr| addr.clone().with(multiaddr::Protocol::P2p(peer_id.clone().into())))
+			.collect()
+	}
+
 	/// Get currently connected peers.
 	pub fn peers_debug_info(&mut self) -> Vec<(PeerId, PeerInfo<B>)> {
 		self.network_service.user_protocol_mut()
@@ -428,6 +517,16 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 }
 
 impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkService<B, S, H> {
+	/// Signs `challenge` prefixed with [`NODE_IDENTITY_CONTEXT`] with the key of the local node,
+	/// proving the possession of the key its `PeerId` derives from. Returns the public key
+	/// together with the signature.
+	pub fn sign_with_local_identity(&self, challenge: &[u8]) -> Result<(PublicKey, Vec<u8>), SigningError> {
+		let mut message = NODE_IDENTITY_CONTEXT.to_vec();
+		message.extend_from_slice(challenge);
+		let signature = self.local_identity.sign(&message)?;
+		Ok((self.local_identity.public(), signature))
+	}
+
 	/// Writes a message on an open notifications channel. Has no effect if the notifications
 	/// channel with this protocol name is closed.
 	///
@@ -477,6 +576,35 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkServic
 		});
 	}
 
+	/// Registers a request/response protocol.
+	///
+	/// Incoming requests on the protocol are delivered on the `inbound_queue` of the
+	/// configuration, and requests can be sent with `request`. Registering a protocol with the
+	/// same name as an existing one replaces it.
+	pub fn register_request_response_protocol(&self, config: RequestResponseConfig) {
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::RegisterRequestResponseProtocol(config));
+	}
+
+	/// Sends a request to `target` on a registered request/response protocol and returns the
+	/// response.
+	///
+	/// We must already be connected to `target`. Fails immediately otherwise.
+	pub fn request(
+		&self,
+		target: PeerId,
+		protocol: impl Into<Cow<'static, str>>,
+		request: Vec<u8>,
+	) -> impl Future<Output = Result<Vec<u8>, RequestFailure>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::Request {
+			target,
+			protocol: protocol.into(),
+			request,
+			pending_response: tx,
+		});
+		rx.map(|response| response.unwrap_or(Err(RequestFailure::Obsolete)))
+	}
+
 	/// You must call this when new transactons are imported by the transaction pool.
 	///
 	/// The latest transactions will be fetched from the `TransactionPool` that was passed at
@@ -516,6 +644,16 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkServic
 			.unbounded_send(ServiceToWorkerMsg::RequestJustification(hash.clone(), number));
 	}
 
+	/// Download the state of the block with the given header from the peers that have it and
+	/// import the block with it, instead of executing the blocks up to it.
+	///
+	/// The parent of the block must be known. The sync goes on from the block once imported.
+	pub fn sync_state(&self, header: B::Header) {
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::SyncState(header));
+	}
+
 	/// Execute a closure with the chain-specific network specialization.
 	pub fn with_spec<F>(&self, f: F)
 		where F: FnOnce(&mut S, &mut dyn Context<B>) + Send + 'static
@@ -576,6 +714,16 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkServic
 		Ok(())
 	}
 
+	/// Adds a bootnode, in addition to the ones passed in the configuration. The string should
+	/// encode the address and peer ID of the remote node.
+	pub fn add_bootnode(&self, bootnode: String) -> Result<(), String> {
+		let (peer_id, addr) = parse_str_addr(&bootnode).map_err(|e| format!("{:?}", e))?;
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::AddBootnode(peer_id, addr));
+		Ok(())
+	}
+
 	/// Configure an explicit fork sync request.
 	/// Note that this function should not be used for recent blocks.
 	/// Sync should be able to download all the recent forks normally.
@@ -668,11 +816,13 @@ impl<B, S, H> NetworkStateInfo for NetworkService<B, S, H>
 enum ServiceToWorkerMsg<B: BlockT, S: NetworkSpecialization<B>> {
 	PropagateExtrinsics,
 	RequestJustification(B::Hash, NumberFor<B>),
+	SyncState(B::Header),
 	AnnounceBlock(B::Hash, Vec<u8>),
 	ExecuteWithSpec(Box<dyn FnOnce(&mut S, &mut dyn Context<B>) + Send>),
 	GetValue(record::Key),
 	PutValue(record::Key, Vec<u8>),
 	AddKnownAddress(PeerId, Multiaddr),
+	AddBootnode(PeerId, Multiaddr),
 	SyncFork(Vec<PeerId>, B::Hash, NumberFor<B>),
 	EventStream(mpsc::UnboundedSender<Event>),
 	WriteNotification {
@@ -683,6 +833,13 @@ enum ServiceToWorkerMsg<B: BlockT, S: NetworkSpecialization<B>> {
 	RegisterNotifProtocol {
 		engine_id: ConsensusEngineId,
 	},
+	RegisterRequestResponseProtocol(RequestResponseConfig),
+	Request {
+		target: PeerId,
+		protocol: Cow<'static, str>,
+		request: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	},
 	DisconnectPeer(PeerId),
 }
 
@@ -709,6 +866,10 @@ pub struct NetworkWorker<B: BlockT + 'static, S: NetworkSpecialization<B>, H: Ex
 	light_client_rqs: Option<mpsc::UnboundedReceiver<RequestData<B>>>,
 	/// Senders for events that happen on the network.
 	event_streams: Vec<mpsc::UnboundedSender<Event>>,
+	/// Directory the peers worth reconnecting to after a restart are saved in.
+	net_config_path: Option<String>,
+	/// Interval at which we save the peers worth reconnecting to.
+	known_peers_save: Pin<Box<dyn Stream<Item = ()> + Send>>,
 }
 
 impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Future for NetworkWorker<B, S, H> {
@@ -721,6 +882,8 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Future for Ne
 		this.import_queue.poll_actions(cx, &mut NetworkLink {
 			protocol: &mut this.network_service,
 		});
+		let saturated = this.import_queue.is_saturated();
+		this.network_service.user_protocol_mut().set_import_queue_saturated(saturated);
 
 		// Check for new incoming light client requests.
 		if let Some(light_client_rqs) = this.light_client_rqs.as_mut() {
@@ -747,6 +910,8 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Future for Ne
 					this.network_service.user_protocol_mut().announce_block(hash, data),
 				ServiceToWorkerMsg::RequestJustification(hash, number) =>
 					this.network_service.user_protocol_mut().request_justification(&hash, number),
+				ServiceToWorkerMsg::SyncState(header) =>
+					this.network_service.user_protocol_mut().sync_state(header),
 				ServiceToWorkerMsg::PropagateExtrinsics =>
 					this.network_service.user_protocol_mut().propagate_extrinsics(),
 				ServiceToWorkerMsg::GetValue(key) =>
@@ -755,6 +920,10 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Future for Ne
 					this.network_service.put_value(key, value),
 				ServiceToWorkerMsg::AddKnownAddress(peer_id, addr) =>
 					this.network_service.add_known_address(peer_id, addr),
+				ServiceToWorkerMsg::AddBootnode(peer_id, addr) => {
+					this.network_service.add_known_address(peer_id.clone(), addr);
+					this.network_service.user_protocol_mut().add_bootnode(peer_id);
+				},
 				ServiceToWorkerMsg::SyncFork(peer_ids, hash, number) =>
 					this.network_service.user_protocol_mut().set_sync_fork_request(peer_ids, &hash, number),
 				ServiceToWorkerMsg::EventStream(sender) =>
@@ -767,6 +936,10 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Future for Ne
 						this.event_streams.retain(|sender| sender.unbounded_send(event.clone()).is_ok());
 					}
 				},
+				ServiceToWorkerMsg::RegisterRequestResponseProtocol(config) =>
+					this.network_service.register_request_response_protocol(config),
+				ServiceToWorkerMsg::Request { target, protocol, request, pending_response } =>
+					this.network_service.send_request(&target, &protocol, request, pending_response),
 				ServiceToWorkerMsg::DisconnectPeer(who) =>
 					this.network_service.user_protocol_mut().disconnect_peer(&who),
 			}
@@ -803,6 +976,10 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Future for Ne
 			};
 		}
 
+		while let Poll::Ready(Some(())) = this.known_peers_save.poll_next_unpin(cx) {
+			this.save_known_peers();
+		}
+
 		// Update the variables shared with the `NetworkService`.
 		this.num_connected.store(this.network_service.user_protocol_mut().num_connected_peers(), Ordering::Relaxed);
 		{
@@ -821,6 +998,12 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Future for Ne
 impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Unpin for NetworkWorker<B, S, H> {
 }
 
+impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Drop for NetworkWorker<B, S, H> {
+	fn drop(&mut self) {
+		self.save_known_peers();
+	}
+}
+
 /// The libp2p swarm, customized for our needs.
 type Swarm<B, S, H> = libp2p::swarm::Swarm<
 	Boxed<(PeerId, StreamMuxerBox), io::Error>,
@@ -0,0 +1,511 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Serving and checking of the state of a block over key ranges.
+//!
+//! Full nodes answer requests for the entries of a key range of the state of a block over a
+//! request/response protocol, with a proof of these entries. `StateSync` splits the key space into
+//! ranges that can be requested from different peers in parallel, and checks each response
+//! against the state root of the target block as soon as it arrives. The checked entries are kept,
+//! and the rest of the range, if any, is requested again. A range that fails is retried from a
+//! different peer.
+//!
+//! The roots of the child tries are entries of the top trie. Once one is downloaded, the entries of
+//! its child trie are requested in turn, with proofs checked against the same state root.
+//! `ChainSync` drives the download and imports the resulting state.
+
+use std::{cmp, collections::{HashMap, HashSet, VecDeque}, sync::Arc, time::Duration};
+use codec::{Encode, Decode};
+use futures::{prelude::*, channel::mpsc};
+use libp2p::PeerId;
+use log::{debug, trace};
+use sc_client_api::StorageProof;
+use sc_peerset::ReputationChange;
+use sp_core::storage::{ChildInfo, OwnedChildInfo, Storage, StorageChild, well_known_keys};
+use sp_runtime::traits::{Block as BlockT, HasherFor};
+
+use crate::chain::Client;
+use crate::request_responses::{IncomingRequest, RequestResponseConfig};
+
+/// Name of the state request protocol.
+pub const PROTOCOL_NAME: &str = "/substrate/state/1";
+
+/// Default number of entries requested in one go.
+pub const DEFAULT_MAX_KEYS: u32 = 1024;
+
+/// Maximum number of entries served in one go.
+pub const MAX_KEYS_PER_RESPONSE: usize = 4096;
+
+/// Number of incoming requests waiting to be answered before new ones are refused.
+pub const INBOUND_QUEUE_SIZE: usize = 16;
+
+mod rep {
+	use sc_peerset::ReputationChange as Rep;
+
+	/// Reputation change for a peer that sent a response we can't decode.
+	pub const BAD_MESSAGE: Rep = Rep::new(-(1 << 12), "Bad state response");
+
+	/// Reputation change for a peer whose proof doesn't match the state root.
+	pub const BAD_PROOF: Rep = Rep::new(-(1 << 16), "Invalid state proof");
+}
+
+/// Prefix of the storage keys of the child tries that can be downloaded.
+const DEFAULT_CHILD_PREFIX: &[u8] = b":child_storage:default:";
+
+/// Child trie whose entries are requested.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ChildTrie {
+	/// Key of the root of the child trie in the top trie.
+	pub storage_key: Vec<u8>,
+	/// Unique id of the child trie, the keyspace its nodes are stored in.
+	pub unique_id: Vec<u8>,
+}
+
+impl ChildTrie {
+	/// The child trie whose root is stored at `storage_key`, `None` if it isn't a default child
+	/// trie.
+	///
+	/// The unique id of a default child trie is the part of its storage key following
+	/// `:child_storage:default:`, as in the contracts module.
+	pub fn from_storage_key(storage_key: &[u8]) -> Option<Self> {
+		if !well_known_keys::is_child_trie_key_valid(storage_key) {
+			return None;
+		}
+		Some(ChildTrie {
+			storage_key: storage_key.to_vec(),
+			unique_id: storage_key[DEFAULT_CHILD_PREFIX.len()..].to_vec(),
+		})
+	}
+
+	fn child_info(&self) -> ChildInfo {
+		ChildInfo::new_default(&self.unique_id)
+	}
+}
+
+/// Request for the storage entries of a key range.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct StateRequest<Hash> {
+	/// Block whose state is requested.
+	pub block: Hash,
+	/// Child trie the entries belong to, `None` for the top trie.
+	pub child: Option<ChildTrie>,
+	/// First key of the range.
+	pub start: Vec<u8>,
+	/// Key the range stops before, or `None` for the end of the key space.
+	pub end: Option<Vec<u8>>,
+	/// Maximum number of entries to return.
+	pub max_keys: u32,
+}
+
+/// Configuration of the state request protocol.
+///
+/// Pass `Some` inbound queue in order to answer requests with `answer_requests`.
+pub fn request_response_config(
+	inbound_queue: Option<mpsc::Sender<IncomingRequest>>,
+) -> RequestResponseConfig {
+	RequestResponseConfig {
+		name: PROTOCOL_NAME.into(),
+		max_request_size: 4 * 1024,
+		max_response_size: 16 * 1024 * 1024,
+		request_timeout: Duration::from_secs(20),
+		inbound_queue,
+	}
+}
+
+/// Answer the state requests received on `requests` until the channel is closed.
+pub async fn answer_requests<B: BlockT>(
+	client: Arc<dyn Client<B>>,
+	mut requests: mpsc::Receiver<IncomingRequest>,
+) {
+	while let Some(request) = requests.next().await {
+		match answer_request(&*client, &request.payload) {
+			Ok(response) => {
+				let _ = request.pending_response.send(response);
+			},
+			Err(err) => debug!(
+				target: "sync",
+				"Refusing state request from {}: {}",
+				request.peer,
+				err,
+			),
+		}
+	}
+}
+
+fn answer_request<B: BlockT>(client: &dyn Client<B>, payload: &[u8]) -> Result<Vec<u8>, String> {
+	let request = StateRequest::<B::Hash>::decode(&mut &payload[..])
+		.map_err(|err| err.what().to_string())?;
+	// The requester checks the proof against the number of entries it asked for, so the
+	// request is refused rather than served partially.
+	let max_keys = request.max_keys as usize;
+	if max_keys > MAX_KEYS_PER_RESPONSE {
+		return Err(format!("Too many entries requested: {}", max_keys));
+	}
+	let end = request.end.as_ref().map(|end| &end[..]);
+	let proof = match request.child {
+		Some(child) => client.read_child_range_proof(
+			&request.block,
+			&child.storage_key,
+			child.child_info(),
+			&request.start,
+			end,
+			max_keys,
+		),
+		None => client.read_range_proof(&request.block, &request.start, end, max_keys),
+	}.map_err(|err| err.to_string())?;
+	Ok(proof.encode())
+}
+
+/// A range of keys that remains to be downloaded.
+#[derive(Debug)]
+struct Range {
+	child: Option<ChildTrie>,
+	start: Vec<u8>,
+	end: Option<Vec<u8>>,
+	/// Peers that failed to serve this range.
+	failed: HashSet<PeerId>,
+}
+
+/// State machine of a state download.
+///
+/// Hands out ranges to peers and accounts for their responses. Doesn't perform any I/O: the
+/// requests are to be sent over the protocol named `PROTOCOL_NAME`.
+pub struct StateSync<B: BlockT> {
+	block: B::Hash,
+	root: B::Hash,
+	max_keys: u32,
+	pending: VecDeque<Range>,
+	in_flight: HashMap<PeerId, Range>,
+	state: Storage,
+	num_entries: usize,
+}
+
+impl<B: BlockT> StateSync<B> {
+	/// Start downloading the state of `block`, whose state root is `root`.
+	///
+	/// The key space is split into `ranges` ranges of about equal width, that can be downloaded
+	/// in parallel. At most `max_keys` entries are requested at once, which is capped at
+	/// `MAX_KEYS_PER_RESPONSE`.
+	pub fn new(block: B::Hash, root: B::Hash, ranges: usize, max_keys: u32) -> Self {
+		let ranges = cmp::max(1, cmp::min(ranges, 256));
+		let mut pending = VecDeque::with_capacity(ranges);
+		let mut start = Vec::new();
+		for i in 1..=ranges {
+			let end = if i == ranges { None } else { Some(vec![(i * 256 / ranges) as u8]) };
+			let next_start = end.clone().unwrap_or_default();
+			pending.push_back(Range { child: None, start, end, failed: HashSet::new() });
+			start = next_start;
+		}
+
+		StateSync {
+			block,
+			root,
+			max_keys: cmp::max(1, cmp::min(max_keys, MAX_KEYS_PER_RESPONSE as u32)),
+			pending,
+			in_flight: HashMap::new(),
+			state: Storage::default(),
+			num_entries: 0,
+		}
+	}
+
+	/// Returns `true` once all ranges have been downloaded.
+	pub fn is_complete(&self) -> bool {
+		self.pending.is_empty() && self.in_flight.is_empty()
+	}
+
+	/// Returns `true` if a request is in flight for `peer`.
+	pub fn is_requesting(&self, peer: &PeerId) -> bool {
+		self.in_flight.contains_key(peer)
+	}
+
+	/// Number of entries downloaded so far, the ones of child tries included.
+	pub fn num_entries(&self) -> usize {
+		self.num_entries
+	}
+
+	/// The downloaded state.
+	///
+	/// The roots of the child tries aren't part of the top trie, as they follow from the
+	/// entries of the child tries.
+	pub fn into_state(self) -> Storage {
+		self.state
+	}
+
+	/// Build the next request to send to `peer`.
+	///
+	/// Returns `None` if a request is already in flight for `peer`, or if there is no range
+	/// left that `peer` hasn't failed to serve.
+	pub fn next_request(&mut self, peer: &PeerId) -> Option<StateRequest<B::Hash>> {
+		if self.in_flight.contains_key(peer) {
+			return None;
+		}
+
+		let index = self.pending.iter().position(|range| !range.failed.contains(peer))?;
+		let range = self.pending.remove(index)?;
+		let request = StateRequest {
+			block: self.block.clone(),
+			child: range.child.clone(),
+			start: range.start.clone(),
+			end: range.end.clone(),
+			max_keys: self.max_keys,
+		};
+		self.in_flight.insert(peer.clone(), range);
+		Some(request)
+	}
+
+	/// Handle the response of `peer` to its in-flight request.
+	///
+	/// On failure the range is put back to be retried from another peer, and the reputation
+	/// change to apply to `peer` is returned.
+	pub fn on_response(&mut self, peer: &PeerId, response: &[u8]) -> Result<(), ReputationChange> {
+		let mut range = match self.in_flight.remove(peer) {
+			Some(range) => range,
+			None => return Ok(()),
+		};
+
+		let child = range.child.clone();
+		let end = range.end.as_ref().map(|end| &end[..]);
+		let checked = match StorageProof::decode(&mut &response[..]) {
+			Ok(proof) => match child {
+				Some(ref child) => sp_state_machine::child_range_proof_check::<HasherFor<B>>(
+					self.root.clone(),
+					proof,
+					&child.storage_key,
+					child.child_info(),
+					&range.start,
+					end,
+					self.max_keys as usize,
+				),
+				None => sp_state_machine::range_proof_check::<HasherFor<B>>(
+					self.root.clone(),
+					proof,
+					&range.start,
+					end,
+					self.max_keys as usize,
+				),
+			}.map_err(|err| {
+				debug!(target: "sync", "Invalid state proof from {}: {}", peer, err);
+				rep::BAD_PROOF
+			}),
+			Err(err) => {
+				debug!(target: "sync", "Failed to decode state response from {}: {}", peer, err.what());
+				Err(rep::BAD_MESSAGE)
+			},
+		};
+
+		let (entries, complete) = match checked {
+			Ok(checked) => checked,
+			Err(rep) => {
+				range.failed.insert(peer.clone());
+				self.pending.push_back(range);
+				return Err(rep);
+			},
+		};
+
+		trace!(
+			target: "sync",
+			"Downloaded {} state entries from {} (complete: {})",
+			entries.len(),
+			peer,
+			complete,
+		);
+
+		if !complete {
+			// The range stops before the entry following the last one we got, which is
+			// `last ++ 0` at the earliest.
+			if let Some((last, _)) = entries.last() {
+				let mut start = last.clone();
+				start.push(0);
+				range.start = start;
+			}
+			// The remaining part of the range can be served by anyone again.
+			range.failed.clear();
+			self.pending.push_front(range);
+		}
+		self.num_entries += entries.len();
+
+		match child {
+			Some(child) => {
+				let storage_child = self.state.children.entry(child.storage_key.clone())
+					.or_insert_with(|| StorageChild {
+						data: Default::default(),
+						child_info: OwnedChildInfo::new_default(child.unique_id.clone()),
+					});
+				storage_child.data.extend(entries);
+			},
+			None => for (key, value) in entries {
+				// The root of a child trie follows from its entries, which are downloaded in turn.
+				// The roots of other child tries stay in the top trie, and fail the import.
+				match ChildTrie::from_storage_key(&key) {
+					Some(child) => self.pending.push_back(Range {
+						child: Some(child),
+						start: Vec::new(),
+						end: None,
+						failed: HashSet::new(),
+					}),
+					None => {
+						self.state.top.insert(key, value);
+					},
+				}
+			},
+		}
+		Ok(())
+	}
+
+	/// Handle the failure of the in-flight request of `peer`, for example a timeout or a
+	/// disconnection. The range is retried from another peer.
+	pub fn on_request_failure(&mut self, peer: &PeerId) {
+		if let Some(mut range) = self.in_flight.remove(peer) {
+			range.failed.insert(peer.clone());
+			self.pending.push_back(range);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{Blake2Hasher, storage::StorageMap};
+	use sp_state_machine::{Backend, InMemoryBackend, prove_child_range_read, prove_range_read};
+	use sp_test_primitives::Block;
+
+	const CHILD_STORAGE_KEY: &[u8] = b":child_storage:default:sub";
+
+	fn top() -> StorageMap {
+		(0..=255u8).map(|i| (vec![i, 1], vec![i])).collect()
+	}
+
+	fn child() -> StorageChild {
+		StorageChild {
+			data: (0..40u8).map(|i| (vec![i], vec![i])).collect(),
+			child_info: OwnedChildInfo::new_default(b"sub".to_vec()),
+		}
+	}
+
+	fn backend(with_child: bool) -> InMemoryBackend<Blake2Hasher> {
+		let mut storage = Storage { top: top(), children: Default::default() };
+		if with_child {
+			storage.children.insert(CHILD_STORAGE_KEY.to_vec(), child());
+		}
+		storage.into()
+	}
+
+	fn root(with_child: bool) -> <Block as BlockT>::Hash {
+		*backend(with_child).as_trie_backend().unwrap().root()
+	}
+
+	fn serve(with_child: bool, request: &StateRequest<<Block as BlockT>::Hash>) -> Vec<u8> {
+		let end = request.end.as_ref().map(|end| &end[..]);
+		match request.child {
+			Some(ref child) => prove_child_range_read(
+				backend(with_child),
+				&child.storage_key,
+				child.child_info(),
+				&request.start,
+				end,
+				request.max_keys as usize,
+			),
+			None => prove_range_read(backend(with_child), &request.start, end, request.max_keys as usize),
+		}.unwrap().encode()
+	}
+
+	#[test]
+	fn ranges_are_downloaded_in_parallel_and_retried() {
+		let mut sync = StateSync::<Block>::new(Default::default(), root(false), 4, 16);
+		let honest = PeerId::random();
+		let faulty = PeerId::random();
+
+		// both peers get a different range at the same time
+		let honest_request = sync.next_request(&honest).unwrap();
+		let faulty_request = sync.next_request(&faulty).unwrap();
+		assert_ne!(honest_request.start, faulty_request.start);
+		assert!(sync.next_request(&honest).is_none());
+
+		// an invalid proof puts the range back, and it is not handed to the same peer again
+		assert!(sync.on_response(&faulty, &StorageProof::empty().encode()).is_err());
+		sync.on_response(&honest, &serve(false, &honest_request)).unwrap();
+		assert_eq!(sync.num_entries(), 16);
+
+		let mut retried = false;
+		while !sync.is_complete() {
+			if let Some(request) = sync.next_request(&faulty) {
+				assert_ne!(request.start, faulty_request.start);
+				sync.on_response(&faulty, &serve(false, &request)).unwrap();
+			}
+			if let Some(request) = sync.next_request(&honest) {
+				retried |= request.start == faulty_request.start;
+				sync.on_response(&honest, &serve(false, &request)).unwrap();
+			}
+		}
+
+		assert!(retried);
+		let state = sync.into_state();
+		assert_eq!(state.top, top());
+		assert!(state.children.is_empty());
+	}
+
+	#[test]
+	fn child_tries_are_downloaded_once_their_root_is_known() {
+		let mut sync = StateSync::<Block>::new(Default::default(), root(true), 1, 32);
+		let peer = PeerId::random();
+
+		let mut child_requests = 0;
+		while !sync.is_complete() {
+			let request = sync.next_request(&peer).unwrap();
+			if let Some(ref child) = request.child {
+				assert_eq!(child.storage_key, CHILD_STORAGE_KEY);
+				assert_eq!(child.unique_id, b"sub");
+				child_requests += 1;
+			}
+			sync.on_response(&peer, &serve(true, &request)).unwrap();
+		}
+
+		// the child trie doesn't fit in one response
+		assert_eq!(child_requests, 2);
+		let state = sync.into_state();
+		assert_eq!(state.top, top());
+		assert_eq!(state.children.len(), 1);
+		assert_eq!(state.children[CHILD_STORAGE_KEY], child());
+	}
+
+	#[test]
+	fn child_entries_must_match_the_state_root() {
+		let mut sync = StateSync::<Block>::new(Default::default(), root(true), 1, 1024);
+		let peer = PeerId::random();
+
+		let request = sync.next_request(&peer).unwrap();
+		sync.on_response(&peer, &serve(true, &request)).unwrap();
+		let request = sync.next_request(&peer).unwrap();
+		assert!(request.child.is_some());
+
+		// entries of the child trie of another state are refused
+		let mut other_child = child();
+		other_child.data.insert(vec![0], vec![42]);
+		let mut other = Storage { top: top(), children: Default::default() };
+		other.children.insert(CHILD_STORAGE_KEY.to_vec(), other_child);
+		let other: InMemoryBackend<Blake2Hasher> = other.into();
+		let response = prove_child_range_read(
+			other,
+			CHILD_STORAGE_KEY,
+			ChildInfo::new_default(b"sub"),
+			&[],
+			None,
+			1024,
+		).unwrap().encode();
+		assert_eq!(sync.on_response(&peer, &response), Err(rep::BAD_PROOF));
+		assert!(!sync.is_complete());
+	}
+}
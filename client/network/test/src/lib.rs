@@ -19,7 +19,11 @@
 #[cfg(test)]
 mod block_import;
 #[cfg(test)]
+mod request_responses;
+#[cfg(test)]
 mod sync;
+#[cfg(test)]
+mod transactions;
 
 use std::{collections::HashMap, pin::Pin, sync::Arc, marker::PhantomData};
 
@@ -38,7 +42,7 @@ use sc_client_api::{
 use sc_block_builder::BlockBuilder;
 use sc_client::LongestChain;
 use sc_network::config::Roles;
-use sp_consensus::block_validation::DefaultBlockAnnounceValidator;
+use sp_consensus::block_validation::{BlockAnnounceValidator, DefaultBlockAnnounceValidator};
 use sp_consensus::import_queue::{
 	BasicQueue, BoxJustificationImport, Verifier, BoxFinalityProofImport,
 };
@@ -49,7 +53,7 @@ use futures::prelude::*;
 use futures03::{Future as _, FutureExt as _, TryFutureExt as _, StreamExt as _, TryStreamExt as _};
 use sc_network::{NetworkWorker, NetworkStateInfo, NetworkService, ReportHandle, config::ProtocolId};
 use sc_network::config::{NetworkConfiguration, TransportConfig, BoxFinalityProofRequestBuilder};
-use libp2p::PeerId;
+use libp2p::{Multiaddr, PeerId};
 use parking_lot::Mutex;
 use sp_core::H256;
 use sc_network::{Context, ProtocolConfig};
@@ -222,6 +226,7 @@ pub struct Peer<D, S: NetworkSpecialization<Block>> {
 	select_chain: Option<LongestChain<substrate_test_runtime_client::Backend, Block>>,
 	backend: Option<Arc<substrate_test_runtime_client::Backend>>,
 	network: NetworkWorker<Block, S, <Block as BlockT>::Hash>,
+	listen_addr: Multiaddr,
 	imported_blocks_stream: Box<dyn Stream<Item = BlockImportNotification<Block>, Error = ()> + Send>,
 	finality_notification_stream: Box<dyn Stream<Item = FinalityNotification<Block>, Error = ()> + Send>,
 }
@@ -232,6 +237,11 @@ impl<D, S: NetworkSpecialization<Block>> Peer<D, S> {
 		self.network.service().local_peer_id()
 	}
 
+	/// Get the address this peer is listening on.
+	pub fn listen_addr(&self) -> &Multiaddr {
+		&self.listen_addr
+	}
+
 	/// Returns true if we're major syncing.
 	pub fn is_major_syncing(&self) -> bool {
 		self.network.service().is_major_syncing()
@@ -578,6 +588,16 @@ pub trait TestNetFactory: Sized {
 		None
 	}
 
+	/// Get custom block announce validator. `DefaultBlockAnnounceValidator` is used if `None`.
+	fn make_block_announce_validator(&self) -> Option<Box<dyn BlockAnnounceValidator<Block> + Send>> {
+		None
+	}
+
+	/// Get the transaction pool of a full peer. `EmptyTransactionPool` is used by default.
+	fn make_transaction_pool(&self, _peer_data: &Self::PeerData) -> Arc<dyn TransactionPool<Hash, Block>> {
+		Arc::new(EmptyTransactionPool)
+	}
+
 	fn default_config() -> ProtocolConfig {
 		ProtocolConfig::default()
 	}
@@ -646,11 +666,13 @@ pub trait TestNetFactory: Sized {
 			),
 			finality_proof_request_builder,
 			on_demand: None,
-			transaction_pool: Arc::new(EmptyTransactionPool),
+			transaction_pool: self.make_transaction_pool(&data),
 			protocol_id: ProtocolId::from(&b"test-protocol-name"[..]),
+			fork_id: config.fork_id.clone(),
 			import_queue,
 			specialization: self::SpecializationFactory::create(),
-			block_announce_validator: Box::new(DefaultBlockAnnounceValidator::new(client.clone()))
+			block_announce_validator: self.make_block_announce_validator()
+				.unwrap_or_else(|| Box::new(DefaultBlockAnnounceValidator::new(client.clone()))),
 		}).unwrap();
 
 		self.mut_peers(|peers| {
@@ -673,6 +695,7 @@ pub trait TestNetFactory: Sized {
 				block_import,
 				verifier,
 				network,
+				listen_addr,
 			});
 		});
 	}
@@ -723,9 +746,11 @@ pub trait TestNetFactory: Sized {
 			on_demand: None,
 			transaction_pool: Arc::new(EmptyTransactionPool),
 			protocol_id: ProtocolId::from(&b"test-protocol-name"[..]),
+			fork_id: config.fork_id.clone(),
 			import_queue,
 			specialization: self::SpecializationFactory::create(),
-			block_announce_validator: Box::new(DefaultBlockAnnounceValidator::new(client.clone()))
+			block_announce_validator: self.make_block_announce_validator()
+				.unwrap_or_else(|| Box::new(DefaultBlockAnnounceValidator::new(client.clone()))),
 		}).unwrap();
 
 		self.mut_peers(|peers| {
@@ -748,6 +773,7 @@ pub trait TestNetFactory: Sized {
 				imported_blocks_stream,
 				finality_notification_stream,
 				network,
+				listen_addr,
 			});
 		});
 	}
@@ -0,0 +1,104 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Testing request/response protocols.
+
+use futures03::{channel::mpsc, task::Poll};
+use sc_network::{IncomingRequest, RequestFailure, RequestResponseConfig};
+use std::time::Duration;
+use tokio::runtime::current_thread;
+use super::*;
+
+const PROTOCOL: &str = "/test/echo/1";
+
+fn config(inbound_queue: Option<mpsc::Sender<IncomingRequest>>) -> RequestResponseConfig {
+	RequestResponseConfig {
+		name: PROTOCOL.into(),
+		max_request_size: 1024,
+		max_response_size: 16,
+		request_timeout: Duration::from_secs(10),
+		inbound_queue,
+	}
+}
+
+#[test]
+fn requests_are_answered() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let mut net = TestNet::new(2);
+
+	// peer 1 echoes requests, peer 0 only sends requests.
+	let (tx, mut requests) = mpsc::channel(8);
+	net.peer(0).network_service().register_request_response_protocol(config(None));
+	net.peer(1).network_service().register_request_response_protocol(config(Some(tx)));
+
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		if net.peer(0).num_peers() == 0 || net.peer(1).num_peers() == 0 {
+			return Ok(Async::NotReady)
+		}
+		Ok(Async::Ready(()))
+	})).unwrap();
+
+	let peer0 = net.peer(0).id();
+	let peer1 = net.peer(1).id();
+	let mut echo = net.peer(0).network_service()
+		.request(peer1.clone(), PROTOCOL, b"hello".to_vec()).boxed();
+	let mut too_large = net.peer(0).network_service()
+		.request(peer1.clone(), PROTOCOL, vec![0; 32]).boxed();
+	let mut unknown = net.peer(0).network_service()
+		.request(peer1, "/test/unknown/1", Vec::new()).boxed();
+	let mut unsupported = net.peer(1).network_service()
+		.request(peer0.clone(), PROTOCOL, Vec::new()).boxed();
+
+	let mut results = (None, None, None, None);
+	runtime.block_on(futures03::future::poll_fn(|cx| {
+		net.poll();
+		while let Poll::Ready(Some(request)) = requests.poll_next_unpin(cx) {
+			assert_eq!(request.peer, peer0);
+			let _ = request.pending_response.send(request.payload);
+		}
+		if results.0.is_none() {
+			if let Poll::Ready(result) = echo.poll_unpin(cx) {
+				results.0 = Some(result);
+			}
+		}
+		if results.1.is_none() {
+			if let Poll::Ready(result) = too_large.poll_unpin(cx) {
+				results.1 = Some(result);
+			}
+		}
+		if results.2.is_none() {
+			if let Poll::Ready(result) = unknown.poll_unpin(cx) {
+				results.2 = Some(result);
+			}
+		}
+		if results.3.is_none() {
+			if let Poll::Ready(result) = unsupported.poll_unpin(cx) {
+				results.3 = Some(result);
+			}
+		}
+		match results {
+			(Some(_), Some(_), Some(_), Some(_)) => Poll::Ready(Ok::<_, ()>(())),
+			_ => Poll::Pending,
+		}
+	}).compat()).unwrap();
+
+	assert_eq!(results.0, Some(Ok(b"hello".to_vec())));
+	assert_eq!(results.1, Some(Err(RequestFailure::ResponseTooLarge)));
+	assert_eq!(results.2, Some(Err(RequestFailure::UnknownProtocol)));
+	assert_eq!(results.3, Some(Err(RequestFailure::UnsupportedProtocol)));
+}
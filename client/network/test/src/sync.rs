@@ -14,9 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use sc_network::config::Roles;
+use sc_network::{config::Roles, state_sync, ClientHandle};
 use sp_consensus::BlockOrigin;
-use futures03::TryFutureExt as _;
+use sp_core::storage::{StorageKey, well_known_keys};
+use futures03::{task::Poll, TryFutureExt as _};
 use std::time::Duration;
 use tokio::runtime::current_thread;
 use super::*;
@@ -660,3 +661,162 @@ fn does_not_sync_announced_old_best_block() {
 	})).unwrap();
 	assert!(!net.peer(1).is_major_syncing());
 }
+
+/// Rejects block announcements that don't carry any data.
+struct RequireAnnounceData;
+
+impl BlockAnnounceValidator<Block> for RequireAnnounceData {
+	fn validate(
+		&mut self,
+		_header: &<Block as BlockT>::Header,
+		data: &[u8],
+	) -> Result<sp_consensus::block_validation::Validation, Box<dyn std::error::Error + Send>> {
+		Ok(if data.is_empty() {
+			sp_consensus::block_validation::Validation::Failure
+		} else {
+			sp_consensus::block_validation::Validation::Success
+		})
+	}
+}
+
+struct AnnounceDataTestNet(TestNet);
+
+impl TestNetFactory for AnnounceDataTestNet {
+	type Specialization = DummySpecialization;
+	type Verifier = PassThroughVerifier;
+	type PeerData = ();
+
+	fn from_config(config: &ProtocolConfig) -> Self {
+		AnnounceDataTestNet(TestNet::from_config(config))
+	}
+
+	fn make_verifier(&self, client: PeersClient, config: &ProtocolConfig, peer_data: &())
+		-> Self::Verifier
+	{
+		self.0.make_verifier(client, config, peer_data)
+	}
+
+	fn make_block_announce_validator(&self) -> Option<Box<dyn BlockAnnounceValidator<Block> + Send>> {
+		Some(Box::new(RequireAnnounceData))
+	}
+
+	fn peer(&mut self, i: usize) -> &mut Peer<(), Self::Specialization> {
+		self.0.peer(i)
+	}
+
+	fn peers(&self) -> &Vec<Peer<(), Self::Specialization>> {
+		self.0.peers()
+	}
+
+	fn mut_peers<F: FnOnce(&mut Vec<Peer<(), Self::Specialization>>)>(&mut self, closure: F) {
+		self.0.mut_peers(closure)
+	}
+}
+
+#[test]
+fn block_announce_data_is_validated() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let mut net = AnnounceDataTestNet::new(2);
+
+	// connect the peers before there is anything to sync.
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		if net.peer(0).num_peers() == 0 || net.peer(1).num_peers() == 0 {
+			return Ok(Async::NotReady)
+		}
+		Ok(Async::Ready(()))
+	})).unwrap();
+
+	// announcements without data are rejected.
+	let hash = net.peer(0).push_blocks(1, false);
+	let mut delay = futures_timer::Delay::new(Duration::from_secs(2)).compat();
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| {
+		net.poll();
+		delay.poll().map_err(|_| ())
+	})).unwrap();
+	assert_eq!(net.peer(1).client.info().best_number, 0);
+
+	net.peer(0).announce_block(hash, vec![42]);
+	net.block_until_sync(&mut runtime);
+	assert_eq!(net.peer(1).client.info().best_number, 1);
+}
+
+#[test]
+fn peers_on_different_forks_do_not_connect() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let mut net = TestNet::new(0);
+
+	let mut config = ProtocolConfig::default();
+	config.fork_id = Some("fork-a".into());
+	net.add_full_peer(&config);
+	net.add_full_peer(&config);
+	config.fork_id = Some("fork-b".into());
+	net.add_full_peer(&config);
+
+	// Poll for a few seconds and make sure only 0 and 1 connect together.
+	let mut delay = futures_timer::Delay::new(Duration::from_secs(5)).compat();
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| {
+		net.poll();
+		delay.poll().map_err(|_| ())
+	})).unwrap();
+	assert_eq!(net.peer(0).num_peers(), 1);
+	assert_eq!(net.peer(1).num_peers(), 1);
+	assert_eq!(net.peer(2).num_peers(), 0);
+}
+
+#[test]
+fn syncs_state_from_the_peers_that_serve_it() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let mut net = TestNet::new(3);
+
+	// peers 0 and 2 have the block, only peer 0 serves its state.
+	net.peer(0).push_blocks(1, true);
+	net.peer(2).push_blocks(1, true);
+	let header = net.peer(0).client().header(&BlockId::Number(1)).unwrap().unwrap();
+
+	let (tx, rx) = futures03::channel::mpsc::channel(state_sync::INBOUND_QUEUE_SIZE);
+	let server: Arc<dyn ClientHandle<Block>> = net.peer(0).client().as_full().unwrap();
+	let mut answers = state_sync::answer_requests(server, rx).boxed();
+	net.peer(0).network_service().register_request_response_protocol(
+		state_sync::request_response_config(Some(tx)),
+	);
+	net.peer(1).network_service().register_request_response_protocol(
+		state_sync::request_response_config(None),
+	);
+	net.peer(1).network_service().sync_state(header);
+
+	runtime.block_on(futures03::future::poll_fn(|cx| {
+		net.poll();
+		let _ = answers.poll_unpin(cx);
+		if net.peer(1).client().info().best_number == 1 {
+			Poll::Ready(Ok::<_, ()>(()))
+		} else {
+			Poll::Pending
+		}
+	}).compat()).unwrap();
+
+	let code = StorageKey(well_known_keys::CODE.to_vec());
+	let peer0 = net.peer(0).client().as_full().unwrap();
+	let peer1 = net.peer(1).client().as_full().unwrap();
+	assert!(peer1.storage(&BlockId::Number(1), &code).unwrap().is_some());
+	assert_eq!(
+		peer1.storage(&BlockId::Number(1), &code).unwrap(),
+		peer0.storage(&BlockId::Number(1), &code).unwrap(),
+	);
+
+	// blocks are synced on top of the imported state.
+	net.peer(0).push_blocks(5, true);
+	runtime.block_on(futures03::future::poll_fn(|cx| {
+		net.poll();
+		let _ = answers.poll_unpin(cx);
+		if net.peer(1).client().info().best_number == 6 {
+			Poll::Ready(Ok::<_, ()>(()))
+		} else {
+			Poll::Pending
+		}
+	}).compat()).unwrap();
+	assert!(net.peers()[0].blockchain_canon_equals(&net.peers()[1]));
+}
@@ -0,0 +1,126 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Testing the propagation of transactions.
+
+use sp_runtime::traits::{BlakeTwo256, Hash as _};
+use tokio::runtime::current_thread;
+use super::*;
+
+/// Transaction pool recording the transactions imported from the network.
+#[derive(Clone, Default)]
+struct TestPool(Arc<Mutex<Vec<Extrinsic>>>);
+
+impl TestPool {
+	fn imported(&self) -> Vec<Extrinsic> {
+		self.0.lock().clone()
+	}
+}
+
+impl TransactionPool<Hash, Block> for TestPool {
+	fn transactions(&self) -> Vec<(Hash, Extrinsic)> {
+		let mut transactions = self.0.lock().clone();
+		transactions.dedup();
+		transactions.into_iter().map(|transaction| (self.hash_of(&transaction), transaction)).collect()
+	}
+
+	fn hash_of(&self, transaction: &Extrinsic) -> Hash {
+		BlakeTwo256::hash_of(transaction)
+	}
+
+	fn import(
+		&self,
+		_report_handle: ReportHandle,
+		_who: PeerId,
+		_rep_change_good: sc_network::ReputationChange,
+		_rep_change_bad: sc_network::ReputationChange,
+		transaction: Extrinsic,
+	) {
+		self.0.lock().push(transaction);
+	}
+
+	fn on_broadcasted(&self, _: HashMap<Hash, Vec<String>>) {}
+}
+
+struct TransactionsTestNet(TestNet);
+
+impl TestNetFactory for TransactionsTestNet {
+	type Specialization = DummySpecialization;
+	type Verifier = PassThroughVerifier;
+	type PeerData = TestPool;
+
+	fn from_config(_config: &ProtocolConfig) -> Self {
+		TransactionsTestNet(TestNet { peers: Vec::new() })
+	}
+
+	fn make_verifier(&self, _client: PeersClient, _config: &ProtocolConfig, _peer_data: &TestPool)
+		-> Self::Verifier
+	{
+		PassThroughVerifier(false)
+	}
+
+	fn peer(&mut self, i: usize) -> &mut Peer<TestPool, Self::Specialization> {
+		&mut self.0.peers[i]
+	}
+
+	fn peers(&self) -> &Vec<Peer<TestPool, Self::Specialization>> {
+		&self.0.peers
+	}
+
+	fn mut_peers<F: FnOnce(&mut Vec<Peer<TestPool, Self::Specialization>>)>(&mut self, closure: F) {
+		closure(&mut self.0.peers);
+	}
+
+	fn make_transaction_pool(&self, peer_data: &TestPool) -> Arc<dyn TransactionPool<Hash, Block>> {
+		Arc::new(peer_data.clone())
+	}
+}
+
+#[test]
+fn announced_transactions_are_requested_once() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let mut net = TransactionsTestNet::new(3);
+	let transaction = Extrinsic::IncludeData(vec![1]);
+	net.peer(0).data.0.lock().push(transaction.clone());
+
+	// Wait until the peers are connected to each other.
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		for peer in 0..3 {
+			if net.peer(peer).num_peers() != 2 {
+				return Ok(Async::NotReady)
+			}
+		}
+		Ok(Async::Ready(()))
+	})).unwrap();
+
+	net.peer(0).network_service().trigger_repropagate();
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		for peer in 1..3 {
+			if net.peer(peer).data.imported().is_empty() {
+				return Ok(Async::NotReady)
+			}
+		}
+		Ok(Async::Ready(()))
+	})).unwrap();
+
+	// The transaction was only announced, and each peer requested it from the sender once.
+	for peer in 1..3 {
+		assert_eq!(net.peer(peer).data.imported(), vec![transaction.clone()]);
+	}
+}
@@ -166,6 +166,14 @@ pub struct PeersetConfig {
 	/// > **Note**: Keep in mind that the networking has to know an address for these nodes,
 	/// >			otherwise it will not be able to connect to them.
 	pub reserved_nodes: Vec<PeerId>,
+
+	/// Peers known from a previous run, with their reputation at the time.
+	///
+	/// They are connected to like bootnodes, the ones with the highest reputation first.
+	///
+	/// > **Note**: Keep in mind that the networking has to know an address for these nodes,
+	/// >			otherwise it will not be able to connect to them.
+	pub known_peers: Vec<(PeerId, i32)>,
 }
 
 /// Side of the peer set manager owned by the network. In other words, the "receiving" side.
@@ -216,6 +224,13 @@ impl Peerset {
 				debug!(target: "peerset", "Duplicate bootnode in config: {:?}", peer_id);
 			}
 		}
+		for (peer_id, reputation) in config.known_peers {
+			match peerset.data.peer(&peer_id) {
+				peersstate::Peer::Unknown(entry) => entry.discover().set_reputation(reputation),
+				peersstate::Peer::NotConnected(mut entry) => entry.set_reputation(reputation),
+				peersstate::Peer::Connected(_) => {},
+			}
+		}
 
 		peerset.alloc_slots();
 		(peerset, handle)
@@ -501,6 +516,20 @@ impl Peerset {
 		})
 	}
 
+	/// Returns the peers we know of, with their reputation and whether we are connected to them.
+	pub fn known_peers(&mut self) -> Vec<(PeerId, i32, bool)> {
+		self.update_time();
+
+		self.data.peers().cloned().collect::<Vec<_>>().into_iter().map(|peer_id| {
+			match self.data.peer(&peer_id) {
+				peersstate::Peer::Connected(entry) => (peer_id, entry.reputation(), true),
+				peersstate::Peer::NotConnected(entry) => (peer_id, entry.reputation(), false),
+				peersstate::Peer::Unknown(_) =>
+					unreachable!("We iterate over the known peers; QED")
+			}
+		}).collect()
+	}
+
 	/// Returns priority group by id.
 	pub fn get_priority_group(&self, group_id: &str) -> Option<HashSet<PeerId>> {
 		self.data.get_priority_group(group_id)
@@ -576,6 +605,7 @@ mod tests {
 			bootnodes: vec![bootnode],
 			reserved_only: true,
 			reserved_nodes: Vec::new(),
+			known_peers: Vec::new(),
 		};
 
 		let (peerset, handle) = Peerset::from_config(config);
@@ -604,6 +634,7 @@ mod tests {
 			bootnodes: vec![bootnode.clone()],
 			reserved_only: false,
 			reserved_nodes: Vec::new(),
+			known_peers: Vec::new(),
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
@@ -631,6 +662,7 @@ mod tests {
 			bootnodes: vec![bootnode.clone()],
 			reserved_only: false,
 			reserved_nodes: vec![],
+			known_peers: vec![],
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
@@ -644,6 +676,30 @@ mod tests {
 		]);
 	}
 
+	#[test]
+	fn test_peerset_known_peers() {
+		let bootnode = PeerId::random();
+		let known = PeerId::random();
+		let config = PeersetConfig {
+			in_peers: 0,
+			out_peers: 1,
+			bootnodes: vec![bootnode.clone()],
+			reserved_only: false,
+			reserved_nodes: vec![],
+			known_peers: vec![(known.clone(), 1000)],
+		};
+
+		let (mut peerset, _handle) = Peerset::from_config(config);
+		let mut known_peers = peerset.known_peers();
+		known_peers.sort_by_key(|(_, reputation, _)| *reputation);
+		assert_eq!(known_peers, vec![(bootnode, 0, false), (known.clone(), 1000, true)]);
+
+		// Peers from a previous run are connected to before the bootnodes.
+		assert_messages(peerset, vec![
+			Message::Connect(known),
+		]);
+	}
+
 	#[test]
 	fn test_peerset_banned() {
 		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
@@ -652,6 +708,7 @@ mod tests {
 			bootnodes: vec![],
 			reserved_only: false,
 			reserved_nodes: vec![],
+			known_peers: vec![],
 		});
 
 		// We ban a node by setting its reputation under the threshold.
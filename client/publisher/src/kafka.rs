@@ -0,0 +1,55 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Publishing to Kafka.
+
+use futures::{future::BoxFuture, FutureExt};
+use rdkafka::{config::ClientConfig, producer::{FutureProducer, FutureRecord}};
+
+use crate::{Error, Publisher};
+
+/// Publishes to a Kafka cluster.
+///
+/// Messages are only acknowledged once written to all the in-sync replicas of their partition,
+/// and the producer is idempotent so that its own retries don't duplicate them.
+pub struct KafkaPublisher {
+	producer: FutureProducer,
+}
+
+impl KafkaPublisher {
+	/// Create a producer for the comma-separated list of `brokers`.
+	pub fn new(brokers: &str) -> Result<Self, Error> {
+		let producer = ClientConfig::new()
+			.set("bootstrap.servers", brokers)
+			.set("acks", "all")
+			.set("enable.idempotence", "true")
+			.create()?;
+		Ok(KafkaPublisher { producer })
+	}
+}
+
+impl Publisher for KafkaPublisher {
+	fn publish(&mut self, topic: &str, key: &str, payload: Vec<u8>) -> BoxFuture<'static, Result<(), Error>> {
+		let record = FutureRecord::to(topic).key(key).payload(&payload);
+		self.producer.send(record, 0)
+			.map(|delivery| match delivery {
+				Ok(Ok(_)) => Ok(()),
+				Ok(Err((e, _))) => Err(Error::Kafka(e)),
+				Err(_) => Err(Error::NotAcknowledged("the producer was dropped".into())),
+			})
+			.boxed()
+	}
+}
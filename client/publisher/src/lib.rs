@@ -0,0 +1,273 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Publishing of the finalized blocks to a message broker.
+//!
+//! The headers of the finalized blocks, their extrinsics with their result and their events are
+//! published on the `<prefix>.blocks`, `<prefix>.extrinsics` and `<prefix>.events` topics of a
+//! Kafka cluster or NATS server, chosen with the scheme of the URL of the broker. The number of
+//! the last block whose messages were all acknowledged by the broker is stored in the auxiliary
+//! database, and publishing resumes after it when the node restarts: each message is delivered
+//! at least once.
+//!
+//! Kafka and NATS support are enabled with the `kafka` and `nats` features. Other brokers can be
+//! supported by implementing [`Publisher`].
+
+#![warn(missing_docs)]
+
+#[cfg(feature = "kafka")]
+mod kafka;
+mod messages;
+#[cfg(feature = "nats")]
+mod nats;
+
+pub use messages::{Message, Topic};
+
+use std::{sync::Arc, thread, time::Duration};
+use codec::{Decode, Encode};
+use futures::{future::{self, BoxFuture, Either}, FutureExt, StreamExt};
+use futures_timer::Delay;
+use log::{info, warn};
+use sc_client::Client;
+use sc_client_api::{backend, AuxStore, BlockchainEvents, CallExecutor};
+use sp_api::{Metadata, ProvideRuntimeApi};
+use sp_runtime::traits::{Block as BlockT, NumberFor, One, SaturatedConversion};
+
+use messages::Messages;
+
+/// Prefix of the auxiliary database key of the last block published, followed by the prefix of
+/// the topics.
+const OFFSET_KEY: &[u8] = b"publisher_offset:";
+/// Delay before publishing a block again after a failure.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Publisher error.
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum Error {
+	/// The scheme of the URL of the broker isn't supported.
+	#[display(fmt="Unsupported broker URL {}, expected kafka:// or nats://", _0)]
+	#[from(ignore)]
+	UnsupportedUrl(String),
+	/// The broker didn't acknowledge a message.
+	#[display(fmt="Message not acknowledged: {}", _0)]
+	#[from(ignore)]
+	NotAcknowledged(String),
+	/// Client error.
+	Client(sp_blockchain::Error),
+	/// Kafka error.
+	#[cfg(feature = "kafka")]
+	Kafka(rdkafka::error::KafkaError),
+	/// IO error.
+	Io(std::io::Error),
+}
+
+impl std::error::Error for Error {}
+
+/// Sends messages to a broker.
+pub trait Publisher: Send {
+	/// Publish `payload` on `topic`.
+	///
+	/// The returned future resolves once the broker acknowledged the message.
+	fn publish(&mut self, topic: &str, key: &str, payload: Vec<u8>) -> BoxFuture<'static, Result<(), Error>>;
+
+	/// Wait for the messages published so far to reach the broker.
+	fn flush(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+/// Connect to the broker at `url`.
+pub fn connect(url: &str) -> Result<Box<dyn Publisher>, Error> {
+	#[cfg(feature = "kafka")]
+	{
+		const KAFKA: &str = "kafka://";
+		if url.starts_with(KAFKA) {
+			return Ok(Box::new(kafka::KafkaPublisher::new(&url[KAFKA.len()..])?));
+		}
+	}
+	#[cfg(feature = "nats")]
+	{
+		if url.starts_with("nats://") {
+			return Ok(Box::new(nats::NatsPublisher::new(url)?));
+		}
+	}
+	Err(Error::UnsupportedUrl(url.into()))
+}
+
+/// Publish the finalized blocks of `client` to the broker at `url` from a thread of its own, on
+/// the topics starting with `topic_prefix`, until `exit` resolves.
+///
+/// Fails if the broker can't be reached.
+pub fn start<B, E, Block, RA, F>(
+	url: &str,
+	topic_prefix: String,
+	client: Arc<Client<B, E, Block, RA>>,
+	exit: F,
+) -> Result<(), Error> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = sp_blockchain::Error>,
+	F: future::Future<Output = ()> + Send + 'static,
+{
+	let publisher = connect(url)?;
+	info!(target: "publisher", "Publishing finalized blocks to {} on {}.*", url, topic_prefix);
+
+	// Reading the blocks and waiting for the broker would hold up the tasks of the node.
+	thread::Builder::new()
+		.name("block-publisher".into())
+		.spawn(move || futures::executor::block_on(run(publisher, topic_prefix, client, exit)))?;
+	Ok(())
+}
+
+/// Publish the finalized blocks, starting after the last one published, until `exit` resolves.
+async fn run<B, E, Block, RA, F>(
+	mut publisher: Box<dyn Publisher>,
+	topic_prefix: String,
+	client: Arc<Client<B, E, Block, RA>>,
+	exit: F,
+) where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = sp_blockchain::Error>,
+	F: future::Future<Output = ()>,
+{
+	futures::pin_mut!(exit);
+	let mut notifications = client.finality_notification_stream();
+	let messages = Messages::new(client.clone());
+
+	let mut next: NumberFor<Block> = match load_offset(&*client, &topic_prefix) {
+		Ok(Some(offset)) => (offset + 1).saturated_into(),
+		// Start with the latest finalized block.
+		Ok(None) => client.chain_info().finalized_number,
+		Err(e) => {
+			warn!(target: "publisher", "Not publishing blocks, failed to read the last block published: {}", e);
+			return;
+		},
+	};
+
+	loop {
+		while next <= client.chain_info().finalized_number {
+			if exit.as_mut().now_or_never().is_some() {
+				return;
+			}
+
+			match publish_block(&mut *publisher, &topic_prefix, &messages, next).await {
+				Ok(()) => {
+					if let Err(e) = store_offset(&*client, &topic_prefix, next.saturated_into()) {
+						warn!(target: "publisher", "Failed to store the last block published: {}", e);
+					}
+					next += One::one();
+				},
+				Err(e) => {
+					warn!(
+						target: "publisher",
+						"Failed to publish block {}, retrying in {:?}: {}", next, RETRY_DELAY, e,
+					);
+					if let Either::Right(_) = future::select(Delay::new(RETRY_DELAY), exit.as_mut()).await {
+						return;
+					}
+				},
+			}
+		}
+
+		match future::select(notifications.next(), exit.as_mut()).await {
+			Either::Left((Some(_), _)) => {},
+			_ => return,
+		}
+	}
+}
+
+/// Publish the messages of the block `number` and wait for all of them to be acknowledged.
+async fn publish_block<B, E, Block, RA>(
+	publisher: &mut dyn Publisher,
+	topic_prefix: &str,
+	messages: &Messages<B, E, Block, RA>,
+	number: NumberFor<Block>,
+) -> Result<(), Error> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = sp_blockchain::Error>,
+{
+	let acknowledgements = messages.block(number)?.into_iter()
+		.map(|message| publisher.publish(
+			&format!("{}.{}", topic_prefix, message.topic.name()),
+			&message.key,
+			message.payload.to_string().into_bytes(),
+		))
+		.collect::<Vec<_>>();
+	publisher.flush()?;
+	future::try_join_all(acknowledgements).await?;
+	Ok(())
+}
+
+fn offset_key(topic_prefix: &str) -> Vec<u8> {
+	let mut key = OFFSET_KEY.to_vec();
+	key.extend_from_slice(topic_prefix.as_bytes());
+	key
+}
+
+/// The number of the last block published on the topics starting with `topic_prefix`.
+fn load_offset<A: AuxStore>(aux: &A, topic_prefix: &str) -> sp_blockchain::Result<Option<u64>> {
+	match aux.get_aux(&offset_key(topic_prefix))? {
+		Some(offset) => u64::decode(&mut &offset[..])
+			.map(Some)
+			.map_err(|e| sp_blockchain::Error::Backend(format!("Invalid publisher offset: {}", e))),
+		None => Ok(None),
+	}
+}
+
+/// Record `number` as the last block published on the topics starting with `topic_prefix`.
+fn store_offset<A: AuxStore>(aux: &A, topic_prefix: &str, number: u64) -> sp_blockchain::Result<()> {
+	aux.insert_aux(&[(&offset_key(topic_prefix)[..], &number.encode()[..])], &[])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use substrate_test_runtime_client::{TestClientBuilder, TestClientBuilderExt};
+
+	#[test]
+	fn offsets_are_kept_per_topic_prefix() {
+		let client = TestClientBuilder::new().build();
+		assert_eq!(load_offset(&client, "storm").unwrap(), None);
+
+		store_offset(&client, "storm", 5).unwrap();
+		store_offset(&client, "storm", 6).unwrap();
+		store_offset(&client, "other", 1).unwrap();
+		assert_eq!(load_offset(&client, "storm").unwrap(), Some(6));
+		assert_eq!(load_offset(&client, "other").unwrap(), Some(1));
+	}
+
+	#[test]
+	fn unsupported_brokers_are_rejected() {
+		match connect("amqp://localhost") {
+			Err(Error::UnsupportedUrl(url)) => assert_eq!(url, "amqp://localhost"),
+			_ => panic!("amqp isn't supported"),
+		}
+	}
+}
@@ -0,0 +1,286 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The messages published for a finalized block.
+//!
+//! Each block gives one message on the `blocks` topic, one per extrinsic on the `extrinsics`
+//! topic and one per event on the `events` topic. Messages are JSON objects, decoded with the
+//! metadata of the runtime of the block where possible. Their key identifies them, so that
+//! consumers can drop the duplicates published after a failure.
+
+use std::{collections::HashMap, sync::Arc};
+use codec::Encode;
+use log::warn;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use sc_client::Client;
+use sc_client_api::{backend, CallExecutor};
+use sp_api::{Metadata, ProvideRuntimeApi};
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::{hashing::twox_128, hexdisplay::HexDisplay, storage::StorageKey};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion}};
+use substrate_frame_decode::{Decoder, TypeRegistry};
+
+/// Kind of message, each published on its own topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+	/// Finalized block headers.
+	Blocks,
+	/// Extrinsics with their result.
+	Extrinsics,
+	/// Events.
+	Events,
+}
+
+impl Topic {
+	/// Name of the topic, appended to the prefix of the topics.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Topic::Blocks => "blocks",
+			Topic::Extrinsics => "extrinsics",
+			Topic::Events => "events",
+		}
+	}
+}
+
+/// A message to publish.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+	/// Topic of the message.
+	pub topic: Topic,
+	/// Identifies the message among the ones of its topic.
+	pub key: String,
+	/// Content of the message.
+	pub payload: Value,
+}
+
+/// Builds the messages of the finalized blocks.
+pub struct Messages<B, E, Block: BlockT, RA> {
+	client: Arc<Client<B, E, Block, RA>>,
+	/// Decoder for the runtime of the latest block published, with its spec version.
+	decoder: Mutex<Option<(u32, Arc<Decoder>)>>,
+}
+
+impl<B, E, Block, RA> Messages<B, E, Block, RA> where
+	Block: BlockT + 'static,
+	B: backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api:
+		Metadata<Block, Error = ClientError>,
+{
+	/// Create the builder of the messages of the blocks of `client`.
+	pub fn new(client: Arc<Client<B, E, Block, RA>>) -> Self {
+		Messages { client, decoder: Mutex::new(None) }
+	}
+
+	/// Build the messages of the finalized block `number`.
+	pub fn block(&self, number: NumberFor<Block>) -> ClientResult<Vec<Message>> {
+		let header = self.client.header(&BlockId::Number(number))?
+			.ok_or_else(|| ClientError::UnknownBlock(format!("{}", number)))?;
+		let hash = header.hash();
+		let id = BlockId::Hash(hash);
+		let extrinsics = self.client.body(&id)?.unwrap_or_default();
+		let decoder = self.decoder(&id);
+
+		// The state of old blocks may be pruned, in which case their events are lost.
+		let events = match self.client.storage(&id, &events_key()) {
+			Ok(events) => events.and_then(|events| {
+				let decoder = decoder.as_ref()?;
+				match decoder.decode_events(&events.0) {
+					Ok(Value::Array(records)) => Some(records),
+					Ok(_) => None,
+					Err(e) => {
+						warn!(target: "publisher", "Failed to decode the events of block {}: {}", number, e);
+						None
+					},
+				}
+			}),
+			Err(e) => {
+				warn!(target: "publisher", "Failed to read the events of block {}: {}", number, e);
+				None
+			},
+		};
+
+		let block_hash = hex(hash.as_ref());
+		let mut messages = vec![Message {
+			topic: Topic::Blocks,
+			key: block_hash.clone(),
+			payload: json!({
+				"number": number.saturated_into::<u64>(),
+				"hash": block_hash,
+				"parentHash": hex(header.parent_hash().as_ref()),
+				"stateRoot": hex(header.state_root().as_ref()),
+				"extrinsicsRoot": hex(header.extrinsics_root().as_ref()),
+				"header": hex(&header.encode()),
+				"extrinsicsCount": extrinsics.len(),
+				"eventsCount": events.as_ref().map(|events| events.len()),
+			}),
+		}];
+
+		let results = events.as_ref()
+			.map(|events| extrinsic_results(events, decoder.as_ref().map(|d| &**d)))
+			.unwrap_or_default();
+		for (index, extrinsic) in extrinsics.iter().enumerate() {
+			let encoded = extrinsic.encode();
+			let decoded = decoder.as_ref().and_then(|decoder| decoder.decode_extrinsic(&encoded).ok());
+			messages.push(Message {
+				topic: Topic::Extrinsics,
+				key: format!("{}-{}", block_hash, index),
+				payload: json!({
+					"blockNumber": number.saturated_into::<u64>(),
+					"blockHash": block_hash,
+					"index": index,
+					"extrinsic": hex(&encoded),
+					"decoded": decoded,
+					"result": results.get(&(index as u64)),
+				}),
+			});
+		}
+
+		for (index, record) in events.iter().flatten().enumerate() {
+			messages.push(Message {
+				topic: Topic::Events,
+				key: format!("{}-{}", block_hash, index),
+				payload: json!({
+					"blockNumber": number.saturated_into::<u64>(),
+					"blockHash": block_hash,
+					"index": index,
+					"phase": record["phase"],
+					"module": record["event"]["module"],
+					"event": record["event"]["event"],
+					"args": record["event"]["args"],
+					"topics": record["topics"],
+				}),
+			});
+		}
+
+		Ok(messages)
+	}
+
+	/// The decoder for the runtime of the block `id`, `None` if its metadata isn't available.
+	fn decoder(&self, id: &BlockId<Block>) -> Option<Arc<Decoder>> {
+		let spec_version = self.client.runtime_version_at(id).ok()?.spec_version;
+		let mut cached = self.decoder.lock();
+		match cached.as_ref() {
+			Some((version, decoder)) if *version == spec_version => return Some(decoder.clone()),
+			_ => {},
+		}
+
+		let decoder = self.client.runtime_api().metadata(id)
+			.map_err(|e| format!("{}", e))
+			.and_then(|metadata| Decoder::from_bytes(&metadata, TypeRegistry::with_defaults())
+				.map_err(|e| format!("{}", e))
+			)
+			.map_err(|e| warn!(target: "publisher", "Can't decode the data of block {}: {}", id, e))
+			.ok()?;
+		let decoder = Arc::new(decoder);
+		*cached = Some((spec_version, decoder.clone()));
+		Some(decoder)
+	}
+}
+
+/// Storage key of the events of a block.
+fn events_key() -> StorageKey {
+	let mut key = twox_128(b"System").to_vec();
+	key.extend_from_slice(&twox_128(b"Events"));
+	StorageKey(key)
+}
+
+fn hex(data: &[u8]) -> String {
+	format!("0x{}", HexDisplay::from(&data))
+}
+
+/// The results of the extrinsics of a block by index, from the `ExtrinsicSuccess` and
+/// `ExtrinsicFailed` events among its decoded event records.
+///
+/// The errors of modules are resolved to their name with `decoder`.
+fn extrinsic_results(records: &[Value], decoder: Option<&Decoder>) -> HashMap<u64, Value> {
+	records.iter().filter_map(|record| {
+		let index = record["phase"]["ApplyExtrinsic"].as_u64()?;
+		let event = &record["event"];
+		if event["module"] != "System" {
+			return None;
+		}
+		let result = match event["event"].as_str()? {
+			"ExtrinsicSuccess" => json!({ "success": true, "dispatchInfo": event["args"][0] }),
+			"ExtrinsicFailed" => {
+				let error = &event["args"][0];
+				let module_error = match (error["Module"]["index"].as_u64(), error["Module"]["error"].as_u64()) {
+					(Some(module), Some(error)) => decoder
+						.and_then(|decoder| decoder.module_error(module as u8, error as u8).ok())
+						.map(|error| json!({
+							"module": error.module,
+							"error": error.error,
+							"documentation": error.documentation,
+						})),
+					_ => None,
+				};
+				json!({
+					"success": false,
+					"dispatchError": error,
+					"moduleError": module_error,
+					"dispatchInfo": event["args"][1],
+				})
+			},
+			_ => return None,
+		};
+		Some((index, result))
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn extrinsic_results_come_from_system_events() {
+		let info = json!({ "weight": 10, "class": "Normal", "pays_fee": true });
+		let records = vec![
+			json!({
+				"phase": { "ApplyExtrinsic": 0 },
+				"event": { "module": "System", "event": "ExtrinsicSuccess", "args": [info] },
+				"topics": [],
+			}),
+			json!({
+				"phase": { "ApplyExtrinsic": 1 },
+				"event": { "module": "Balances", "event": "Transfer", "args": [] },
+				"topics": [],
+			}),
+			json!({
+				"phase": { "ApplyExtrinsic": 1 },
+				"event": { "module": "System", "event": "ExtrinsicFailed", "args": ["BadOrigin", info] },
+				"topics": [],
+			}),
+			json!({
+				"phase": "Finalization",
+				"event": { "module": "System", "event": "ExtrinsicSuccess", "args": [info] },
+				"topics": [],
+			}),
+		];
+
+		let results = extrinsic_results(&records, None);
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[&0], json!({ "success": true, "dispatchInfo": info }));
+		assert_eq!(results[&1], json!({
+			"success": false,
+			"dispatchError": "BadOrigin",
+			"moduleError": null,
+			"dispatchInfo": info,
+		}));
+	}
+}
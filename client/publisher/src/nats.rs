@@ -0,0 +1,48 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Publishing to NATS.
+
+use futures::{future::{self, BoxFuture}, FutureExt};
+
+use crate::{Error, Publisher};
+
+/// Publishes to a NATS server.
+///
+/// NATS doesn't acknowledge the messages it receives: they are considered delivered once flushed
+/// to the server, and may still be lost if no subscriber is listening.
+pub struct NatsPublisher {
+	connection: nats_client::Connection,
+}
+
+impl NatsPublisher {
+	/// Connect to the server at `url`.
+	pub fn new(url: &str) -> Result<Self, Error> {
+		Ok(NatsPublisher { connection: nats_client::connect(url)? })
+	}
+}
+
+impl Publisher for NatsPublisher {
+	fn publish(&mut self, topic: &str, _key: &str, payload: Vec<u8>) -> BoxFuture<'static, Result<(), Error>> {
+		// Subjects carry no key, consumers identify the messages from their content.
+		future::ready(self.connection.publish(topic, payload).map_err(Error::from)).boxed()
+	}
+
+	fn flush(&mut self) -> Result<(), Error> {
+		self.connection.flush()?;
+		Ok(())
+	}
+}
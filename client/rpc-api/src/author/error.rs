@@ -18,6 +18,7 @@
 
 use crate::errors;
 use jsonrpc_core as rpc;
+use serde::Serialize;
 
 /// Author RPC Result type.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -35,6 +36,10 @@ pub enum Error {
 	/// Transaction pool error,
 	#[display(fmt="Transaction pool error: {}", _0)]
 	Pool(sp_transaction_pool::error::Error),
+	/// Transaction pool error, possibly caused by the era of the transaction.
+	#[display(fmt="Transaction pool error: {} ({})", _0, _1)]
+	#[from(ignore)]
+	InvalidEra(sp_transaction_pool::error::Error, EraInfo),
 	/// Verification error
 	#[display(fmt="Extrinsic verification error: {}", _0)]
 	#[from(ignore)]
@@ -54,6 +59,9 @@ pub enum Error {
 	/// Some random issue with the key store. Shouldn't happen.
 	#[display(fmt="The key store is unavailable")]
 	KeyStoreUnavailable,
+	/// Too many transactions are waiting for validation.
+	#[display(fmt="The transaction pool is overloaded")]
+	Overloaded,
 }
 
 impl std::error::Error for Error {
@@ -61,12 +69,31 @@ impl std::error::Error for Error {
 		match self {
 			Error::Client(ref err) => Some(&**err),
 			Error::Pool(ref err) => Some(err),
+			Error::InvalidEra(ref err, _) => Some(err),
 			Error::Verification(ref err) => Some(&**err),
 			_ => None,
 		}
 	}
 }
 
+/// The era of a rejected transaction, relative to the block it was checked at.
+#[derive(Debug, Clone, PartialEq, Serialize, derive_more::Display)]
+#[serde(rename_all = "camelCase")]
+#[display(
+	fmt="birth block {}, current block {}, era period {}",
+	birth_block, current_block, period,
+)]
+pub struct EraInfo {
+	/// The block the era starts at, whose hash the transaction is signed with.
+	pub birth_block: u64,
+	/// The block the transaction was checked at.
+	pub current_block: u64,
+	/// The period of the era.
+	pub period: u64,
+	/// The phase of the era.
+	pub phase: u64,
+}
+
 /// Base code for all authorship errors.
 const BASE_ERROR: i64 = 1000;
 /// Extrinsic has an invalid format.
@@ -90,6 +117,8 @@ const POOL_CYCLE_DETECTED: i64 = POOL_INVALID_TX + 5;
 const POOL_IMMEDIATELY_DROPPED: i64 = POOL_INVALID_TX + 6;
 /// The key type crypto is not known.
 const UNSUPPORTED_KEY_TYPE: i64 = POOL_INVALID_TX + 7;
+/// Too many transactions are waiting for validation.
+const POOL_OVERLOADED: i64 = POOL_INVALID_TX + 8;
 
 impl From<Error> for rpc::Error {
 	fn from(e: Error) -> Self {
@@ -106,6 +135,13 @@ impl From<Error> for rpc::Error {
 				message: format!("Verification Error: {}", e).into(),
 				data: Some(format!("{:?}", e).into()),
 			},
+			Error::InvalidEra(e, era) => {
+				let error = rpc::Error::from(Error::Pool(e));
+				rpc::Error {
+					data: Some(serde_json::json!({ "error": error.data, "era": era })),
+					..error
+				}
+			},
 			Error::Pool(PoolError::InvalidTransaction(e)) => rpc::Error {
 				code: rpc::ErrorCode::ServerError(POOL_INVALID_TX),
 				message: "Invalid Transaction".into(),
@@ -149,6 +185,11 @@ impl From<Error> for rpc::Error {
 					request to insert the key successfully.".into()
 				),
 			},
+			Error::Overloaded => rpc::Error {
+				code: rpc::ErrorCode::ServerError(POOL_OVERLOADED),
+				message: "Pool Overloaded".into(),
+				data: Some("Too many transactions are waiting for validation, retry later.".into()),
+			},
 			e => errors::internal(e),
 		}
 	}
@@ -34,8 +34,8 @@ pub trait AuthorApi<Hash, BlockHash> {
 	type Metadata;
 
 	/// Submit hex-encoded extrinsic for inclusion in block.
-	#[rpc(name = "author_submitExtrinsic")]
-	fn submit_extrinsic(&self, extrinsic: Bytes) -> FutureResult<Hash>;
+	#[rpc(meta, name = "author_submitExtrinsic")]
+	fn submit_extrinsic(&self, metadata: Self::Metadata, extrinsic: Bytes) -> FutureResult<Hash>;
 
 	/// Insert a key into the keystore.
 	#[rpc(name = "author_insertKey")]
@@ -97,4 +97,35 @@ pub trait ChainApi<Number, Hash, Header, SignedBlock> {
 		metadata: Option<Self::Metadata>,
 		id: SubscriptionId,
 	) -> RpcResult<bool>;
+
+	/// New head subscription that pins the state of every announced block.
+	///
+	/// A pinned state is not pruned until it is released with `chain_unpinBlock` or the
+	/// subscription ends. All subscriptions share a pin budget and each one may only pin a
+	/// limited number of blocks; the subscription ends when a block can not be pinned.
+	#[pubsub(
+		subscription = "chain_pinnedHead",
+		subscribe,
+		name = "chain_subscribePinnedHeads"
+	)]
+	fn subscribe_pinned_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Header>);
+
+	/// Unsubscribe from pinned head subscription, releasing all its pins.
+	#[pubsub(
+		subscription = "chain_pinnedHead",
+		unsubscribe,
+		name = "chain_unsubscribePinnedHeads"
+	)]
+	fn unsubscribe_pinned_heads(
+		&self,
+		metadata: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
+
+	/// Release the pin the given pinned head subscription holds on a block.
+	///
+	/// Only the connection the subscription was made on can release its pins. Returns `false`
+	/// if the subscription does not hold a pin on the block or was made on another connection.
+	#[rpc(meta, name = "chain_unpinBlock")]
+	fn unpin_block(&self, metadata: Self::Metadata, subscription: u64, hash: Hash) -> Result<bool>;
 }
@@ -41,6 +41,14 @@ pub enum Error {
 		/// Details of the error message.
 		details: String,
 	},
+	/// More keys were requested than can be served at once.
+	#[display(fmt = "{} keys were requested, at most {} are served at once.", count, max)]
+	TooManyKeys {
+		/// Number of keys requested.
+		count: usize,
+		/// Maximum number of keys served.
+		max: usize,
+	},
 }
 
 impl std::error::Error for Error {
@@ -63,6 +71,11 @@ impl From<Error> for rpc::Error {
 				message: format!("{}", e),
 				data: None,
 			},
+			Error::TooManyKeys { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 2),
+				message: format!("{}", e),
+				data: None,
+			},
 			e => errors::internal(e),
 		}
 	}
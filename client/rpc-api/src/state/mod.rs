@@ -25,10 +25,72 @@ use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use sp_core::Bytes;
 use sp_core::storage::{StorageKey, StorageData, StorageChangeSet};
 use sp_version::RuntimeVersion;
+use serde::{Serialize, Deserialize};
 use self::error::FutureResult;
 
 pub use self::gen_client::Client as StateClient;
 
+/// A range of blocks of the best chain executed by the same runtime.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeVersionRange<Hash> {
+	/// Hash of the first block of the range.
+	pub from_block: Hash,
+	/// Number of the first block of the range.
+	pub from_number: u64,
+	/// Version of the runtime.
+	pub version: RuntimeVersion,
+	/// Hash of the runtime metadata, if the runtime provides it.
+	pub metadata_hash: Option<Hash>,
+}
+
+/// A storage item that differs between the states of two blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiff {
+	/// The storage key.
+	pub key: StorageKey,
+	/// The value at the first block, `None` if the item was created.
+	pub old: Option<StorageData>,
+	/// The value at the second block, `None` if the item was deleted.
+	pub new: Option<StorageData>,
+}
+
+/// A page of the storage items that differ between the states of two blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiffPage {
+	/// The differing storage items, ordered by key.
+	pub diffs: Vec<StorageDiff>,
+	/// The last key compared, to pass as `start_key` for the next page. `None` once all the keys
+	/// are compared.
+	pub next_key: Option<StorageKey>,
+}
+
+/// Proof of the values of some storage items at a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadProof<Hash> {
+	/// Block hash used to generate the proof.
+	pub at: Hash,
+	/// Trie nodes proving the values against the state root of the block.
+	pub proof: Vec<Bytes>,
+}
+
+/// Result of a runtime call along with the proof of its execution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionProof<Hash> {
+	/// Block hash the call was executed at.
+	pub at: Hash,
+	/// Return value of the call.
+	pub result: Bytes,
+	/// Trie nodes read during the execution, to re-execute the call against the state root
+	/// of the block. The leaf holding the runtime code is left out, it is to be proven with
+	/// `state_getReadProof` of `:code`.
+	pub proof: Vec<Bytes>,
+}
+
 /// Substrate state API
 #[rpc]
 pub trait StateApi<Hash> {
@@ -39,10 +101,38 @@ pub trait StateApi<Hash> {
 	#[rpc(name = "state_call", alias("state_callAt"))]
 	fn call(&self, name: String, bytes: Bytes, hash: Option<Hash>) -> FutureResult<Bytes>;
 
+	/// Call a contract at a block's state and return the result with a proof of execution.
+	#[rpc(name = "state_getExecutionProof")]
+	fn execution_proof(
+		&self,
+		name: String,
+		bytes: Bytes,
+		hash: Option<Hash>,
+	) -> FutureResult<ExecutionProof<Hash>>;
+
+	/// Returns proof of storage entries at a specific block's state.
+	///
+	/// At most 1000 keys are proven at once.
+	#[rpc(name = "state_getReadProof")]
+	fn read_proof(&self, keys: Vec<StorageKey>, hash: Option<Hash>) -> FutureResult<ReadProof<Hash>>;
+
 	/// Returns the keys with prefix, leave empty to get all the keys
 	#[rpc(name = "state_getKeys")]
 	fn storage_keys(&self, prefix: StorageKey, hash: Option<Hash>) -> FutureResult<Vec<StorageKey>>;
 
+	/// Returns at most `count` keys with prefix, ordered and only after `start_key` if given.
+	///
+	/// The count is capped by the node, a page shorter than `count` doesn't mean that there are
+	/// no more keys.
+	#[rpc(name = "state_getKeysPaged", alias("state_getKeysPagedAt"))]
+	fn storage_keys_paged(
+		&self,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		hash: Option<Hash>,
+	) -> FutureResult<Vec<StorageKey>>;
+
 	/// Returns a storage entry at a specific block's state.
 	#[rpc(name = "state_getStorage", alias("state_getStorageAt"))]
 	fn storage(&self, key: StorageKey, hash: Option<Hash>) -> FutureResult<Option<StorageData>>;
@@ -100,13 +190,36 @@ pub trait StateApi<Hash> {
 	) -> FutureResult<Option<u64>>;
 
 	/// Returns the runtime metadata as an opaque blob.
-	#[rpc(name = "state_getMetadata")]
+	#[rpc(name = "state_getMetadata", alias("state_getMetadataAt"))]
 	fn metadata(&self, hash: Option<Hash>) -> FutureResult<Bytes>;
 
 	/// Get the runtime version.
-	#[rpc(name = "state_getRuntimeVersion", alias("chain_getRuntimeVersion"))]
+	#[rpc(name = "state_getRuntimeVersion", alias("chain_getRuntimeVersion", "state_getRuntimeVersionAt"))]
 	fn runtime_version(&self, hash: Option<Hash>) -> FutureResult<RuntimeVersion>;
 
+	/// Get the ranges of blocks of the best chain executed by the same runtime, starting at genesis.
+	///
+	/// Tools decoding historical blocks can use it to pick the metadata matching a block.
+	#[rpc(name = "state_getRuntimeVersionHistory")]
+	fn runtime_version_history(&self) -> FutureResult<Vec<RuntimeVersionRange<Hash>>>;
+
+	/// Get a page of the storage items that differ between the states of two blocks, ordered by
+	/// key.
+	///
+	/// Only keys starting with `prefix` are compared, if given, and only the keys after
+	/// `start_key`. A page has at most `count` items, and ends early when many keys are compared,
+	/// so that it may be empty although items differ after its `next_key`. Child tries are not
+	/// compared, changes to them show up as changes to their roots.
+	#[rpc(name = "state_diff")]
+	fn state_diff(
+		&self,
+		from: Hash,
+		to: Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<StorageDiffPage>;
+
 	/// Query historical storage entries (by key) starting from a block given as the second parameter.
 	///
 	/// NOTE This first returned result contains the initial state of storage for all keys.
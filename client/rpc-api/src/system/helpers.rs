@@ -19,6 +19,7 @@
 use std::fmt;
 use serde::{Serialize, Deserialize};
 use serde_json::{Value, map::Map};
+use sp_core::Bytes;
 
 /// Node properties
 pub type Properties = Map<String, Value>;
@@ -87,6 +88,18 @@ pub enum NodeRole {
 	UnknownRole(u8)
 }
 
+/// Identity of the node on the network, with a proof of possession of its key.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeIdentity {
+	/// `PeerId` of the node.
+	pub peer_id: String,
+	/// Public key the `PeerId` derives from, in the protobuf encoding of libp2p.
+	pub public_key: Bytes,
+	/// Signature with the key of the challenge, prefixed with `substrate-node-identity:`.
+	pub signature: Bytes,
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -25,7 +25,7 @@ use futures::{future::BoxFuture, compat::Compat};
 
 use self::error::Result as SystemResult;
 
-pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole, NodeIdentity};
 pub use self::gen_client::Client as SystemClient;
 
 /// Substrate system RPC API
@@ -66,6 +66,22 @@ pub trait SystemApi<Hash, Number> {
 	#[rpc(name = "system_networkState", returns = "jsonrpc_core::Value")]
 	fn system_network_state(&self) -> Receiver<jsonrpc_core::Value>;
 
+	/// Returns the multiaddresses that the local node is listening on, with its `PeerId`
+	/// appended.
+	///
+	/// `/ip4/127.0.0.1/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV`
+	/// is an example of a returned address.
+	#[rpc(name = "system_localListenAddresses", returns = "Vec<String>")]
+	fn system_local_listen_addresses(&self) -> Receiver<Vec<String>>;
+
+	/// Returns what the node knows about how other nodes can reach it: the addresses it listens
+	/// on, its confirmed external addresses, the addresses connected peers observe it as and
+	/// the outcome of port mapping.
+	///
+	/// **Warning**: This API is not stable.
+	#[rpc(name = "system_natStatus", returns = "jsonrpc_core::Value")]
+	fn system_nat_status(&self) -> Receiver<jsonrpc_core::Value>;
+
 	/// Adds a reserved peer. Returns the empty string or an error. The string
 	/// parameter should encode a `p2p` multiaddr.
 	///
@@ -84,4 +100,12 @@ pub trait SystemApi<Hash, Number> {
 	/// Returns the roles the node is running as.
 	#[rpc(name = "system_nodeRoles", returns = "Vec<NodeRole>")]
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>>;
+
+	/// Returns the `PeerId` of the node and its public key, with a signature of `challenge`
+	/// proving the possession of the key.
+	///
+	/// The signed message is `challenge` prefixed with the bytes of `substrate-node-identity:`,
+	/// so that the signature can't be reused in other protocols.
+	#[rpc(name = "system_nodeIdentity", returns = "NodeIdentity")]
+	fn system_node_identity(&self, challenge: Option<sp_core::Bytes>) -> Receiver<NodeIdentity>;
 }
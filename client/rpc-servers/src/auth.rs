@@ -0,0 +1,459 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! API key authentication for the RPC servers.
+//!
+//! The keys are read from a JSON file like:
+//!
+//! ```json
+//! {
+//!   "public": { "methods": ["system_health", "chain_*"], "requestsPerSecond": 5, "burst": 10 },
+//!   "keys": {
+//!     "explorer-secret": { "methods": ["*"], "requestsPerSecond": 100, "burst": 200 }
+//!   }
+//! }
+//! ```
+//!
+//! HTTP requests and WebSocket handshakes present their key with an `Authorization: Bearer <key>`
+//! header. Requests without a key get the `public` access and are rejected if there is none. The
+//! budget of a key is shared by all its clients, while each client address gets its own `public`
+//! budget. Method names ending with `*` allow all methods with the given prefix. The file is
+//! reloaded when it changes.
+
+use std::{
+	collections::HashMap, fs, io, net::IpAddr, path::{Path, PathBuf}, sync::{Arc, Mutex},
+	time::{Duration, Instant, SystemTime},
+};
+use jsonrpc_core::{
+	Error, ErrorCode, MetaIoHandler, Metadata, RemoteProcedure,
+	futures::future::{self, Either},
+};
+use linked_hash_map::LinkedHashMap;
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::is_method_allowed;
+
+/// How often the key file is checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+/// Maximum number of clients whose `public` budget is kept. Once reached, the least recently used
+/// client is forgotten if its budget refilled, else new clients share a budget.
+const MAX_CLIENTS: usize = 64 * 1024;
+
+/// Base code for all authentication errors.
+pub(crate) const BASE_ERROR: i64 = 5000;
+/// The request has no API key allowing the method.
+const UNAUTHORIZED: i64 = BASE_ERROR + 1;
+/// The budget of the API key is exhausted.
+const RATE_LIMITED: i64 = BASE_ERROR + 2;
+
+/// The access granted to an API key.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Access {
+	/// The methods that can be called. Names ending with `*` match by prefix.
+	pub methods: Vec<String>,
+	/// The number of requests per second the budget is refilled with.
+	pub requests_per_second: f64,
+	/// The maximum budget, i.e. the number of requests that can be made at once.
+	pub burst: f64,
+}
+
+impl Access {
+	fn allows(&self, method: &str) -> bool {
+		self.methods.iter().any(|allowed| is_method_allowed(allowed, method))
+	}
+}
+
+/// The content of an API key file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ApiKeys {
+	/// The access of requests without API key. `None` if they are rejected.
+	#[serde(default)]
+	pub public: Option<Access>,
+	/// The access granted to each API key.
+	#[serde(default)]
+	pub keys: HashMap<String, Access>,
+}
+
+impl ApiKeys {
+	/// Parse the API keys from JSON.
+	pub fn from_json(json: &str) -> io::Result<Self> {
+		serde_json::from_str(json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+
+	fn access(&self, key: Option<&str>) -> Option<&Access> {
+		match key {
+			Some(key) => self.keys.get(key),
+			None => self.public.as_ref(),
+		}
+	}
+}
+
+/// A token bucket limiting the request rate of an API key or client.
+#[derive(Debug)]
+struct Bucket {
+	tokens: f64,
+	updated: Instant,
+}
+
+impl Bucket {
+	fn new(access: &Access, now: Instant) -> Self {
+		Bucket { tokens: access.burst, updated: now }
+	}
+
+	/// Refill the bucket and take a token from it. Returns `false` if it is empty.
+	fn take(&mut self, access: &Access, now: Instant) -> bool {
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * access.requests_per_second).min(access.burst);
+		self.updated = now;
+		if self.tokens >= 1.0 {
+			self.tokens -= 1.0;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Whether the bucket refilled at `now`, and is the same as a new one.
+	fn is_full(&self, access: &Access, now: Instant) -> bool {
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+		self.tokens + elapsed * access.requests_per_second >= access.burst
+	}
+}
+
+struct State {
+	keys: ApiKeys,
+	/// The budgets of the API keys.
+	buckets: HashMap<String, Bucket>,
+	/// The `public` budgets of the clients, least recently used first.
+	clients: LinkedHashMap<IpAddr, Bucket>,
+	/// The `public` budget shared by the clients of unknown address, or not kept.
+	shared: Option<Bucket>,
+	modified: Option<SystemTime>,
+	checked: Instant,
+}
+
+/// API key authentication, shared by all requests to a server.
+#[derive(Clone)]
+pub struct Auth {
+	path: Option<PathBuf>,
+	state: Arc<Mutex<State>>,
+}
+
+impl Auth {
+	/// Authenticate with fixed API keys.
+	pub fn new(keys: ApiKeys) -> Self {
+		Self::with_state(None, keys, None)
+	}
+
+	/// Authenticate with the API keys of the given file, reloading it when it changes.
+	pub fn from_file(path: &Path) -> io::Result<Self> {
+		let (keys, modified) = read(path)?;
+		Ok(Self::with_state(Some(path.into()), keys, modified))
+	}
+
+	fn with_state(path: Option<PathBuf>, keys: ApiKeys, modified: Option<SystemTime>) -> Self {
+		let state = State {
+			keys,
+			buckets: HashMap::new(),
+			clients: LinkedHashMap::new(),
+			shared: None,
+			modified,
+			checked: Instant::now(),
+		};
+		Auth { path, state: Arc::new(Mutex::new(state)) }
+	}
+
+	/// Check whether a request from `client` with the given API key may call `method` at `now`.
+	pub fn check(
+		&self,
+		key: Option<&str>,
+		client: Option<IpAddr>,
+		method: &str,
+		now: Instant,
+	) -> Result<(), Error> {
+		let mut state = self.state.lock().expect("Auth state is never poisoned; qed");
+		self.reload(&mut state, now);
+
+		let State { keys, buckets, clients, shared, .. } = &mut *state;
+		let access = match keys.access(key) {
+			Some(access) if access.allows(method) => access,
+			_ => return Err(Error {
+				code: ErrorCode::ServerError(UNAUTHORIZED),
+				message: format!("Method {} is not allowed for this API key", method),
+				data: None,
+			}),
+		};
+		let bucket = match key {
+			Some(key) => buckets.entry(key.into()).or_insert_with(|| Bucket::new(access, now)),
+			None => match client.and_then(|client| client_bucket(clients, client, access, now)) {
+				Some(bucket) => bucket,
+				None => shared.get_or_insert_with(|| Bucket::new(access, now)),
+			},
+		};
+		if bucket.take(access, now) {
+			Ok(())
+		} else {
+			Err(Error {
+				code: ErrorCode::ServerError(RATE_LIMITED),
+				message: "Request budget is exhausted".into(),
+				data: None,
+			})
+		}
+	}
+
+	/// Reload the key file if it changed, keeping the budgets of the remaining keys.
+	fn reload(&self, state: &mut State, now: Instant) {
+		let path = match &self.path {
+			Some(path) => path,
+			None => return,
+		};
+		if now.saturating_duration_since(state.checked) < RELOAD_INTERVAL {
+			return
+		}
+		state.checked = now;
+
+		let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+		if modified == state.modified {
+			return
+		}
+		state.modified = modified;
+		match read(path) {
+			Ok((keys, _)) => {
+				info!("Reloaded RPC API keys from {}", path.display());
+				state.buckets.retain(|key, _| keys.keys.contains_key(key));
+				if keys.public.is_none() {
+					state.clients.clear();
+					state.shared = None;
+				}
+				state.keys = keys;
+			},
+			Err(e) => warn!("Failed to reload RPC API keys from {}, keeping the old ones: {}", path.display(), e),
+		}
+	}
+
+	/// Wrap the methods of `io`, so they can only be called with authorized API keys.
+	pub fn wrap<M: Metadata>(&self, io: &MetaIoHandler<M>) -> MetaIoHandler<AuthMetadata<M>> {
+		let mut handler = MetaIoHandler::default();
+		for (name, procedure) in io.iter() {
+			let (auth, method_name) = (self.clone(), name.clone());
+			match procedure.clone() {
+				RemoteProcedure::Method(method) => handler.add_method_with_meta(
+					name,
+					move |params, meta: AuthMetadata<M>| {
+						match auth.check(meta.api_key(), meta.client, &method_name, Instant::now()) {
+							Ok(()) => Either::A(method.call(params, meta.inner)),
+							Err(e) => Either::B(future::err(e)),
+						}
+					},
+				),
+				RemoteProcedure::Notification(notification) => handler.add_notification_with_meta(
+					name,
+					move |params, meta: AuthMetadata<M>| {
+						if auth.check(meta.api_key(), meta.client, &method_name, Instant::now()).is_ok() {
+							notification.execute(params, meta.inner)
+						}
+					},
+				),
+				RemoteProcedure::Alias(other) => handler.add_alias(name, &other),
+			}
+		}
+		handler
+	}
+}
+
+/// Metadata of a request to a server with authentication.
+#[derive(Debug, Clone, Default)]
+pub struct AuthMetadata<M> {
+	/// The metadata of the wrapped handler.
+	pub inner: M,
+	/// The API key presented by the request.
+	pub api_key: Option<String>,
+	/// The address of the client, `None` if unknown.
+	pub client: Option<IpAddr>,
+}
+
+impl<M> AuthMetadata<M> {
+	fn api_key(&self) -> Option<&str> {
+		self.api_key.as_ref().map(|key| &key[..])
+	}
+}
+
+impl<M: Metadata> Metadata for AuthMetadata<M> {}
+
+/// Extract the API key from the `Authorization` header of a HTTP request.
+pub fn api_key<T>(request: &http::hyper::Request<T>) -> Option<String> {
+	bearer(request.headers().get(http::hyper::header::AUTHORIZATION)?.to_str().ok()?)
+}
+
+/// Extract the API key from the value of an `Authorization` header.
+pub fn bearer(header: &str) -> Option<String> {
+	const BEARER: &str = "Bearer ";
+
+	if header.starts_with(BEARER) {
+		Some(header[BEARER.len()..].trim().into())
+	} else {
+		None
+	}
+}
+
+/// The `public` budget of `client`, kept from now on. Once `MAX_CLIENTS` are kept, the least
+/// recently used client is forgotten to make room, if its budget refilled. `None` if there is no
+/// room.
+fn client_bucket<'a>(
+	clients: &'a mut LinkedHashMap<IpAddr, Bucket>,
+	client: IpAddr,
+	public: &Access,
+	now: Instant,
+) -> Option<&'a mut Bucket> {
+	if clients.contains_key(&client) {
+		return clients.get_refresh(&client)
+	}
+	if clients.len() >= MAX_CLIENTS {
+		match clients.front() {
+			Some((_, bucket)) if bucket.is_full(public, now) => {
+				clients.pop_front();
+			},
+			_ => return None,
+		}
+	}
+	clients.insert(client, Bucket::new(public, now));
+	clients.get_mut(&client)
+}
+
+fn read(path: &Path) -> io::Result<(ApiKeys, Option<SystemTime>)> {
+	let modified = fs::metadata(path)?.modified().ok();
+	Ok((ApiKeys::from_json(&fs::read_to_string(path)?)?, modified))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn keys() -> ApiKeys {
+		ApiKeys::from_json(r#"{
+			"public": { "methods": ["system_health", "chain_*"], "requestsPerSecond": 1, "burst": 2 },
+			"keys": {
+				"explorer": { "methods": ["*"], "requestsPerSecond": 10, "burst": 10 }
+			}
+		}"#).unwrap()
+	}
+
+	#[test]
+	fn methods_are_allowed_per_key() {
+		let auth = Auth::new(keys());
+		let now = Instant::now();
+
+		assert!(auth.check(None, None, "chain_getBlock", now).is_ok());
+		assert_eq!(
+			auth.check(None, None, "state_getStorage", now).unwrap_err().code,
+			ErrorCode::ServerError(UNAUTHORIZED),
+		);
+		assert!(auth.check(Some("explorer"), None, "state_getStorage", now).is_ok());
+		assert!(auth.check(Some("unknown"), None, "system_health", now).is_err());
+	}
+
+	#[test]
+	fn budget_is_refilled_over_time() {
+		let auth = Auth::new(keys());
+		let now = Instant::now();
+
+		assert!(auth.check(None, None, "system_health", now).is_ok());
+		assert!(auth.check(None, None, "system_health", now).is_ok());
+		assert_eq!(
+			auth.check(None, None, "system_health", now).unwrap_err().code,
+			ErrorCode::ServerError(RATE_LIMITED),
+		);
+		// the budget of other keys is separate.
+		assert!(auth.check(Some("explorer"), None, "system_health", now).is_ok());
+
+		let later = now + Duration::from_secs(1);
+		assert!(auth.check(None, None, "system_health", later).is_ok());
+		assert!(auth.check(None, None, "system_health", later).is_err());
+	}
+
+	#[test]
+	fn public_budget_is_per_client() {
+		let auth = Auth::new(keys());
+		let now = Instant::now();
+		let (alice, bob) = (Some([10, 0, 0, 1].into()), Some([10, 0, 0, 2].into()));
+
+		assert!(auth.check(None, alice, "system_health", now).is_ok());
+		assert!(auth.check(None, alice, "system_health", now).is_ok());
+		assert!(auth.check(None, alice, "system_health", now).is_err());
+		// other clients don't share the exhausted budget.
+		assert!(auth.check(None, bob, "system_health", now).is_ok());
+		assert!(auth.check(None, None, "system_health", now).is_ok());
+	}
+
+	#[test]
+	fn least_recently_used_clients_are_forgotten_once_their_budget_refilled() {
+		let now = Instant::now();
+		let address = |n: u32| IpAddr::from(n.to_be_bytes());
+		let public = keys().public.unwrap();
+		let mut clients = LinkedHashMap::new();
+		for n in 0..MAX_CLIENTS as u32 {
+			clients.insert(address(n), Bucket { tokens: 0.0, updated: now });
+		}
+
+		// the first client was used last.
+		assert!(client_bucket(&mut clients, address(0), &public, now).is_some());
+		// no budget refilled yet.
+		let new = address(MAX_CLIENTS as u32);
+		assert!(client_bucket(&mut clients, new, &public, now).is_none());
+		assert!(!clients.contains_key(&new));
+
+		let later = now + Duration::from_secs(2);
+		assert!(client_bucket(&mut clients, new, &public, later).is_some());
+		assert_eq!(clients.len(), MAX_CLIENTS);
+		assert!(clients.contains_key(&address(0)));
+		assert!(!clients.contains_key(&address(1)));
+	}
+
+	#[test]
+	fn clients_share_a_budget_once_all_are_kept() {
+		let auth = Auth::new(keys());
+		let now = Instant::now();
+		{
+			let mut state = auth.state.lock().unwrap();
+			for n in 0..MAX_CLIENTS as u32 {
+				state.clients.insert(IpAddr::from(n.to_be_bytes()), Bucket { tokens: 0.0, updated: now });
+			}
+		}
+		let (alice, bob) = (Some([255, 0, 0, 1].into()), Some([255, 0, 0, 2].into()));
+
+		assert!(auth.check(None, alice, "system_health", now).is_ok());
+		assert!(auth.check(None, bob, "system_health", now).is_ok());
+		assert!(auth.check(None, alice, "system_health", now).is_err());
+	}
+
+	#[test]
+	fn wrapped_methods_check_the_api_key() {
+		let mut io = MetaIoHandler::<()>::default();
+		io.add_method("state_getStorage", |_| Ok(jsonrpc_core::Value::Bool(true)));
+		let handler = Auth::new(keys()).wrap(&io);
+		let request = r#"{"jsonrpc":"2.0","method":"state_getStorage","params":[],"id":1}"#;
+
+		let public = handler.handle_request_sync(request, AuthMetadata::default()).unwrap();
+		assert!(public.contains("5001"));
+
+		let meta = AuthMetadata { api_key: Some("explorer".into()), ..Default::default() };
+		let explorer = handler.handle_request_sync(request, meta).unwrap();
+		assert_eq!(explorer, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+	}
+}
@@ -0,0 +1,323 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! HTTP RPC server, optionally over TLS.
+//!
+//! The server of `jsonrpc-http-server` neither terminates TLS nor tells the address of the clients
+//! to the metadata, which the rate limits of the API keys need. This one serves the same endpoints:
+//! JSON-RPC requests are `POST`ed to any path and `GET /health` calls `system_health`. Over TLS, it
+//! negotiates HTTP/2 with ALPN, falling back to HTTP/1.1 for the clients which don't support it.
+//!
+//! When CORS are restricted, plain HTTP requests must have the listening address as `Host`, to
+//! prevent DNS rebinding. Hosts aren't filtered over TLS: a TLS client only talks to a server with
+//! a certificate for the host it asked for, which already defeats it.
+//!
+//! On Unix, the certificates are reloaded when the node receives `SIGHUP`.
+
+use std::{io, net::SocketAddr, sync::{Arc, Mutex}};
+use tokio::io::{AsyncRead, AsyncWrite};
+use http::hyper::{
+	self, Body, Method, Request, Response, StatusCode, header,
+	server::conn::Http, service::service_fn,
+};
+use jsonrpc_core::{
+	MetaIoHandler, Metadata,
+	futures::{future::{self, Either}, sync::oneshot, Future, Stream},
+};
+use log::{debug, warn};
+use pubsub::PubSubMetadata;
+
+use crate::{
+	Auth, AuthMetadata, ClientMetadata, MAX_PAYLOAD, OriginMetadata, OriginPolicy, RpcHandler, TlsConfig,
+	is_host_allowed, is_origin_allowed, origins, tls::TlsAcceptor,
+};
+
+type ResponseFuture = Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+type Handle = Arc<dyn Fn(Request<Body>, SocketAddr) -> ResponseFuture + Send + Sync>;
+
+/// HTTP RPC server. Stops when dropped.
+pub struct HttpServer {
+	local_addr: SocketAddr,
+	stop: Option<oneshot::Sender<()>>,
+	// The servers are kept in a `Sync` box, which the runtime isn't.
+	_runtime: Mutex<tokio::runtime::Runtime>,
+}
+
+impl HttpServer {
+	/// The address the server listens on.
+	pub fn address(&self) -> &SocketAddr {
+		&self.local_addr
+	}
+}
+
+impl Drop for HttpServer {
+	fn drop(&mut self) {
+		if let Some(stop) = self.stop.take() {
+			let _ = stop.send(());
+		}
+	}
+}
+
+/// Start a HTTP server listening on the given address.
+///
+/// If `auth` is given, the methods can only be called with the API keys allowing them. If
+/// `policy` is given, the methods callable from some origins are restricted.
+///
+/// **Note**: Only available if `not(target_os = "unknown")`.
+pub fn start_http<M: PubSubMetadata + ClientMetadata + Default>(
+	addr: &SocketAddr,
+	cors: Option<&Vec<String>>,
+	auth: Option<&Auth>,
+	policy: Option<&OriginPolicy>,
+	io: RpcHandler<M>,
+) -> io::Result<HttpServer> {
+	start(addr, cors, auth, policy, io, None)
+}
+
+/// Start a HTTP server over TLS listening on the given address.
+///
+/// If `auth` is given, the methods can only be called with the API keys allowing them. If
+/// `policy` is given, the methods callable from some origins are restricted.
+///
+/// **Note**: Only available if `not(target_os = "unknown")`.
+pub fn start_https<M: PubSubMetadata + ClientMetadata + Default>(
+	addr: &SocketAddr,
+	cors: Option<&Vec<String>>,
+	auth: Option<&Auth>,
+	policy: Option<&OriginPolicy>,
+	io: RpcHandler<M>,
+	tls: &TlsConfig,
+) -> io::Result<HttpServer> {
+	start(addr, cors, auth, policy, io, Some(TlsAcceptor::new(tls.clone())?))
+}
+
+fn start<M: PubSubMetadata + ClientMetadata + Default>(
+	addr: &SocketAddr,
+	cors: Option<&Vec<String>>,
+	auth: Option<&Auth>,
+	policy: Option<&OriginPolicy>,
+	io: RpcHandler<M>,
+	acceptor: Option<TlsAcceptor>,
+) -> io::Result<HttpServer> {
+	let listener = tokio::net::TcpListener::bind(addr)?;
+	let local_addr = listener.local_addr()?;
+	let cors = cors.cloned();
+	// Only the listening address can be the `Host` of plain HTTP requests with restricted CORS.
+	let listening = match (&acceptor, &cors) {
+		(None, Some(_)) => Some(local_addr),
+		_ => None,
+	};
+	let io = policy.cloned().unwrap_or_default().wrap(&io);
+	let handle: Handle = match auth {
+		Some(auth) => {
+			let handler = Arc::new(Handler {
+				io: auth.wrap(&io),
+				meta: Box::new(|request: &Request<Body>, peer: SocketAddr| AuthMetadata {
+					inner: OriginMetadata { inner: client(peer), origin: origins::origin(request) },
+					api_key: crate::auth::api_key(request),
+					client: Some(peer.ip()),
+				}),
+				cors,
+				listening,
+			});
+			Arc::new(move |request: Request<Body>, peer: SocketAddr| handler.clone().handle(request, peer))
+		},
+		None => {
+			let handler = Arc::new(Handler {
+				io,
+				meta: Box::new(|request: &Request<Body>, peer: SocketAddr| OriginMetadata {
+					inner: client(peer),
+					origin: origins::origin(request),
+				}),
+				cors,
+				listening,
+			});
+			Arc::new(move |request: Request<Body>, peer: SocketAddr| handler.clone().handle(request, peer))
+		},
+	};
+
+	let (stop, stopped) = oneshot::channel();
+	let reload = match &acceptor {
+		Some(acceptor) => Either::A(reload_on_hangup(acceptor.clone())),
+		None => Either::B(future::empty()),
+	};
+	let server = listener.incoming()
+		.then(|stream| Ok::<_, ()>(stream.map_err(|e| warn!("Failed to accept RPC connection: {}", e)).ok()))
+		.filter_map(|stream| stream)
+		.for_each(move |stream| {
+			let peer = match stream.peer_addr() {
+				Ok(peer) => peer,
+				Err(e) => {
+					debug!("RPC connection without peer address: {}", e);
+					return Ok(())
+				},
+			};
+			let handle = handle.clone();
+			let connection = match &acceptor {
+				Some(acceptor) => Either::A(acceptor.current().accept(stream)
+					.map_err(|e| debug!("RPC TLS handshake failed: {}", e))
+					.and_then(move |stream| serve(stream, peer, handle))
+				),
+				None => Either::B(serve(stream, peer, handle)),
+			};
+			tokio::spawn(connection);
+			Ok(())
+		});
+
+	let runtime = tokio::runtime::Builder::new()
+		.core_threads(4)
+		.name_prefix("rpc-http-")
+		.build()?;
+	let server = server.select(reload).map(drop).map_err(drop);
+	runtime.executor().spawn(server.select(stopped.map_err(drop)).map(drop).map_err(drop));
+	Ok(HttpServer { local_addr, stop: Some(stop), _runtime: Mutex::new(runtime) })
+}
+
+/// The metadata of a request from `peer`.
+fn client<M: ClientMetadata + Default>(peer: SocketAddr) -> M {
+	let mut meta = M::default();
+	meta.set_client(peer.ip());
+	meta
+}
+
+/// Serve the requests of a connection from `peer`.
+fn serve<S: AsyncRead + AsyncWrite + Send + 'static>(
+	stream: S,
+	peer: SocketAddr,
+	handle: Handle,
+) -> impl Future<Item = (), Error = ()> {
+	Http::new()
+		.serve_connection(stream, service_fn(move |request| handle(request, peer)))
+		.map_err(|e| debug!("RPC connection failed: {}", e))
+}
+
+/// Reload the certificates of `acceptor` whenever the node receives `SIGHUP`. Never resolves.
+#[cfg(unix)]
+fn reload_on_hangup(acceptor: TlsAcceptor) -> impl Future<Item = (), Error = ()> {
+	use tokio_signal::unix::{Signal, SIGHUP};
+
+	Signal::new(SIGHUP)
+		.flatten_stream()
+		.for_each(move |_| {
+			acceptor.reload();
+			Ok(())
+		})
+		.map_err(|e| warn!("Can't reload the RPC TLS certificates on SIGHUP: {}", e))
+		.then(|_| future::empty::<(), ()>())
+}
+
+#[cfg(not(unix))]
+fn reload_on_hangup(_: TlsAcceptor) -> impl Future<Item = (), Error = ()> {
+	future::empty()
+}
+
+/// Answers the requests of a server.
+struct Handler<T: Metadata> {
+	io: MetaIoHandler<T>,
+	meta: Box<dyn Fn(&Request<Body>, SocketAddr) -> T + Send + Sync>,
+	cors: Option<Vec<String>>,
+	/// The only address allowed as `Host`, if hosts are filtered.
+	listening: Option<SocketAddr>,
+}
+
+impl<T: Metadata> Handler<T> {
+	fn handle(self: Arc<Self>, request: Request<Body>, peer: SocketAddr) -> ResponseFuture {
+		if let Some(listening) = &self.listening {
+			let host = request.headers().get(header::HOST).and_then(|host| host.to_str().ok());
+			if !host.map_or(false, |host| is_host_allowed(listening, host)) {
+				return Box::new(future::ok(response(StatusCode::FORBIDDEN, "Host not allowed", None)));
+			}
+		}
+		let origin = request.headers().get(header::ORIGIN)
+			.and_then(|origin| origin.to_str().ok())
+			.map(String::from);
+		if let Some(origin) = &origin {
+			if !is_origin_allowed(self.cors.as_ref(), origin) {
+				return Box::new(future::ok(response(StatusCode::FORBIDDEN, "Origin not allowed", None)));
+			}
+		}
+
+		let meta = (self.meta)(&request, peer);
+		match (request.method(), request.uri().path()) {
+			(&Method::OPTIONS, _) => {
+				let mut preflight = response(StatusCode::OK, "", origin.as_ref());
+				let headers = preflight.headers_mut();
+				headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, header::HeaderValue::from_static("OPTIONS, POST"));
+				headers.insert(
+					header::ACCESS_CONTROL_ALLOW_HEADERS,
+					header::HeaderValue::from_static("Origin, Content-Type, Accept, Authorization"),
+				);
+				Box::new(future::ok(preflight))
+			},
+			(&Method::GET, "/health") => {
+				let request = r#"{"jsonrpc":"2.0","method":"system_health","params":[],"id":1}"#;
+				Box::new(self.io.handle_request(request, meta).then(move |result| {
+					let health = result.ok().and_then(|health| health)
+						.and_then(|health| serde_json::from_str::<serde_json::Value>(&health).ok());
+					Ok::<_, hyper::Error>(match health {
+						Some(ref health) if !health["result"].is_null() =>
+							response(StatusCode::OK, &health["result"].to_string(), origin.as_ref()),
+						_ => response(StatusCode::SERVICE_UNAVAILABLE, "", origin.as_ref()),
+					})
+				}))
+			},
+			(&Method::POST, _) => {
+				let body = request.into_body()
+					.map_err(|_| StatusCode::BAD_REQUEST)
+					.fold(Vec::new(), |mut body, chunk| {
+						if body.len() + chunk.len() > MAX_PAYLOAD {
+							return Err(StatusCode::PAYLOAD_TOO_LARGE);
+						}
+						body.extend_from_slice(&chunk);
+						Ok(body)
+					});
+				Box::new(body.then(move |body| {
+					let status = match body.map(String::from_utf8) {
+						Ok(Ok(body)) => Ok(body),
+						Ok(Err(_)) => Err(StatusCode::BAD_REQUEST),
+						Err(status) => Err(status),
+					};
+					let body = match status {
+						Ok(body) => body,
+						Err(status) => return Either::A(future::ok::<_, hyper::Error>(
+							response(status, "", origin.as_ref())
+						)),
+					};
+					Either::B(self.io.handle_request(&body, meta).then(move |result| Ok::<_, hyper::Error>(match result {
+						Ok(Some(result)) => response(StatusCode::OK, &result, origin.as_ref()),
+						// Notifications have no response.
+						Ok(None) => response(StatusCode::OK, "", origin.as_ref()),
+						Err(()) => response(StatusCode::INTERNAL_SERVER_ERROR, "", origin.as_ref()),
+					})))
+				}))
+			},
+			_ => Box::new(future::ok(response(StatusCode::METHOD_NOT_ALLOWED, "", origin.as_ref()))),
+		}
+	}
+}
+
+/// A JSON response, allowing `origin` to read it.
+fn response(status: StatusCode, body: &str, origin: Option<&String>) -> Response<Body> {
+	let mut response = Response::new(Body::from(body.to_owned()));
+	*response.status_mut() = status;
+	let headers = response.headers_mut();
+	headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json; charset=utf-8"));
+	if let Some(origin) = origin.and_then(|origin| header::HeaderValue::from_str(origin).ok()) {
+		headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+		headers.insert(header::VARY, header::HeaderValue::from_static("Origin"));
+	}
+	response
+}
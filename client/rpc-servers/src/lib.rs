@@ -18,31 +18,163 @@
 
 #![warn(missing_docs)]
 
-use std::io;
+#[cfg(not(target_os = "unknown"))]
+mod auth;
+#[cfg(not(target_os = "unknown"))]
+mod http_server;
+#[cfg(not(target_os = "unknown"))]
+mod origins;
+#[cfg(not(target_os = "unknown"))]
+mod tls;
+#[cfg(not(target_os = "unknown"))]
+mod ws_server;
+
+use std::path::PathBuf;
 use jsonrpc_core::IoHandlerExtension;
-use log::error;
 use pubsub::PubSubMetadata;
 
+#[cfg(not(target_os = "unknown"))]
+pub use self::auth::{Access, ApiKeys, Auth, AuthMetadata};
+#[cfg(not(target_os = "unknown"))]
+pub use self::http_server::{HttpServer, start_http, start_https};
+#[cfg(not(target_os = "unknown"))]
+pub use self::origins::{OriginMetadata, OriginPolicy};
+#[cfg(not(target_os = "unknown"))]
+pub use self::ws_server::{WsServer, start_compressed_ws, start_ws};
+
 /// Maximal payload accepted by RPC servers.
 const MAX_PAYLOAD: usize = 15 * 1024 * 1024;
 
 /// Default maximum number of connections for WS RPC servers.
 const WS_MAX_CONNECTIONS: usize = 100;
 
+/// Methods that are unsafe to expose on public interfaces, as they give access to the keystore
+/// or the transaction pool of the node, or let any caller run unbounded work on it.
+pub const UNSAFE_METHODS: &[&str] = &[
+	"author_insertKey",
+	"author_rotateKeys",
+	"author_removeExtrinsic",
+	"system_dryRun",
+	"state_diff",
+	"state_getRuntimeVersionHistory",
+	"state_getExecutionProof",
+	"state_getReadProof",
+	"system_simulateCalls",
+	"dev_faucet",
+	"dev_setBlockTimestamp",
+	"dev_increaseTime",
+	"dev_snapshot",
+	"dev_revertToSnapshot",
+	"chain_subscribePinnedHeads",
+	"chain_unpinBlock",
+	"system_nodeIdentity",
+	"babe_previewSlotClaims",
+	"grandpa_submitJustification",
+	"author_submitWithManagedNonce",
+];
+
+/// Metadata recording the address of the client a request comes from.
+pub trait ClientMetadata {
+	/// Record the address of the client the request comes from.
+	fn set_client(&mut self, client: std::net::IpAddr);
+}
+
 /// The RPC IoHandler containing all requested APIs.
 pub type RpcHandler<T> = pubsub::PubSubHandler<T>;
 
-pub use self::inner::*;
+/// Which methods of a RPC handler are exposed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RpcMethods {
+	/// Whether the methods in `UNSAFE_METHODS` are exposed.
+	pub unsafe_methods: bool,
+	/// Methods that are exposed even if they are unsafe.
+	pub allowed: Vec<String>,
+	/// Methods that are never exposed.
+	pub denied: Vec<String>,
+}
+
+impl RpcMethods {
+	/// Expose all methods.
+	pub fn all() -> Self {
+		RpcMethods { unsafe_methods: true, ..Default::default() }
+	}
+
+	/// Whether the given method is exposed.
+	pub fn is_exposed(&self, method: &str) -> bool {
+		if self.denied.iter().any(|denied| denied == method) {
+			return false
+		}
+		self.unsafe_methods
+			|| !UNSAFE_METHODS.contains(&method)
+			|| self.allowed.iter().any(|allowed| allowed == method)
+	}
+}
 
-/// Construct rpc `IoHandler`
+/// TLS configuration of the HTTP RPC server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+	/// PEM file with the certificate chain, starting with the certificate of the server.
+	pub cert: PathBuf,
+	/// PEM file with the PKCS#8 or RSA private key of the certificate.
+	pub key: PathBuf,
+	/// Directory with the certificates of other server names, chosen with the name requested by
+	/// the client: `<name>.crt` for the certificate chain and `<name>.key` for its private key.
+	pub sni_dir: Option<PathBuf>,
+	/// PEM file with the certificate authorities signing the certificates clients must present.
+	/// `None` if clients aren't authenticated.
+	pub client_ca: Option<PathBuf>,
+}
+
+/// Whether requests from `origin` are allowed by the `cors` list, `None` allowing all origins.
+#[cfg(not(target_os = "unknown"))]
+fn is_origin_allowed(cors: Option<&Vec<String>>, origin: &str) -> bool {
+	cors.map_or(true, |cors| cors.iter().any(|allowed| allowed == "*" || allowed == origin))
+}
+
+/// Whether `host` is the listening address `listening`, which is all servers allow as `Host` when
+/// CORS are restricted, to prevent DNS rebinding.
+#[cfg(not(target_os = "unknown"))]
+fn is_host_allowed(listening: &std::net::SocketAddr, host: &str) -> bool {
+	host == listening.to_string() || host == format!("localhost:{}", listening.port())
+}
+
+/// Whether `pattern` allows calling `method`. Patterns ending with `*` match by prefix.
+#[cfg(not(target_os = "unknown"))]
+fn is_method_allowed(pattern: &str, method: &str) -> bool {
+	if pattern.ends_with('*') {
+		method.starts_with(&pattern[..pattern.len() - 1])
+	} else {
+		pattern == method
+	}
+}
+
+/// Construct rpc `IoHandler`, exposing the `exposed` methods only.
 pub fn rpc_handler<M: PubSubMetadata>(
-	extension: impl IoHandlerExtension<M>
+	extension: impl IoHandlerExtension<M>,
+	exposed: &RpcMethods,
 ) -> RpcHandler<M> {
 	let mut io = pubsub::PubSubHandler::default();
 	extension.augment(&mut io);
 
+	// replace the methods that are not exposed with one returning an error.
+	let disabled = io.iter()
+		.map(|x| x.0.clone())
+		.filter(|method| !exposed.is_exposed(method))
+		.collect::<Vec<String>>();
+	for method in disabled {
+		let message = format!("Method {} is not available on this RPC server", method);
+		io.add_method(&method, move |_| Err(jsonrpc_core::Error {
+			code: jsonrpc_core::ErrorCode::MethodNotFound,
+			message: message.clone(),
+			data: None,
+		}));
+	}
+
 	// add an endpoint to list all available methods.
-	let mut methods = io.iter().map(|x| x.0.clone()).collect::<Vec<String>>();
+	let mut methods = io.iter()
+		.map(|x| x.0.clone())
+		.filter(|method| exposed.is_exposed(method))
+		.collect::<Vec<String>>();
 	io.add_method("rpc_methods", {
 		methods.sort();
 		let methods = serde_json::to_value(&methods)
@@ -56,80 +188,54 @@ pub fn rpc_handler<M: PubSubMetadata>(
 	io
 }
 
-#[cfg(not(target_os = "unknown"))]
-mod inner {
+#[cfg(test)]
+mod tests {
 	use super::*;
 
-	/// Type alias for http server
-	pub type HttpServer = http::Server;
-	/// Type alias for ws server
-	pub type WsServer = ws::Server;
-
-	/// Start HTTP server listening on given address.
-	///
-	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_http<M: pubsub::PubSubMetadata + Default>(
-		addr: &std::net::SocketAddr,
-		cors: Option<&Vec<String>>,
-		io: RpcHandler<M>,
-	) -> io::Result<http::Server> {
-		http::ServerBuilder::new(io)
-			.threads(4)
-			.health_api(("/health", "system_health"))
-			.allowed_hosts(hosts_filtering(cors.is_some()))
-			.rest_api(if cors.is_some() {
-				http::RestApi::Secure
-			} else {
-				http::RestApi::Unsecure
-			})
-			.cors(map_cors::<http::AccessControlAllowOrigin>(cors))
-			.max_request_body_size(MAX_PAYLOAD)
-			.start_http(addr)
+	#[test]
+	fn unsafe_methods_are_exposed_only_if_allowed() {
+		let safe = RpcMethods::default();
+		assert!(safe.is_exposed("system_health"));
+		assert!(!safe.is_exposed("author_insertKey"));
+
+		let allowed = RpcMethods { allowed: vec!["author_insertKey".into()], ..Default::default() };
+		assert!(allowed.is_exposed("author_insertKey"));
+		assert!(!allowed.is_exposed("author_rotateKeys"));
+
+		assert!(RpcMethods::all().is_exposed("author_rotateKeys"));
 	}
 
-	/// Start WS server listening on given address.
-	///
-	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_ws<M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
-		addr: &std::net::SocketAddr,
-		max_connections: Option<usize>,
-		cors: Option<&Vec<String>>,
-		io: RpcHandler<M>,
-	) -> io::Result<ws::Server> {
-		ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| context.sender().into())
-			.max_payload(MAX_PAYLOAD)
-			.max_connections(max_connections.unwrap_or(WS_MAX_CONNECTIONS))
-			.allowed_origins(map_cors(cors))
-			.allowed_hosts(hosts_filtering(cors.is_some()))
-			.start(addr)
-			.map_err(|err| match err {
-				ws::Error::Io(io) => io,
-				ws::Error::ConnectionClosed => io::ErrorKind::BrokenPipe.into(),
-				e => {
-					error!("{}", e);
-					io::ErrorKind::Other.into()
-				}
-			})
+	#[test]
+	fn state_proofs_are_unsafe() {
+		assert!(!RpcMethods::default().is_exposed("state_getReadProof"));
+		assert!(!RpcMethods::default().is_exposed("state_getExecutionProof"));
+		assert!(RpcMethods::default().is_exposed("state_getStorage"));
 	}
 
-	fn map_cors<T: for<'a> From<&'a str>>(
-		cors: Option<&Vec<String>>
-	) -> http::DomainsValidation<T> {
-		cors.map(|x| x.iter().map(AsRef::as_ref).map(Into::into).collect::<Vec<_>>()).into()
+	#[test]
+	fn origins_are_allowed_by_cors() {
+		let cors = vec!["https://polkadot.js.org".to_string(), "null".to_string()];
+		assert!(is_origin_allowed(Some(&cors), "https://polkadot.js.org"));
+		assert!(is_origin_allowed(Some(&cors), "null"));
+		assert!(!is_origin_allowed(Some(&cors), "https://evil.com"));
+		assert!(is_origin_allowed(Some(&vec!["*".into()]), "https://evil.com"));
+		assert!(is_origin_allowed(None, "https://evil.com"));
 	}
 
-	fn hosts_filtering(enable: bool) -> http::DomainsValidation<http::Host> {
-		if enable {
-			// NOTE The listening address is whitelisted by default.
-			// Setting an empty vector here enables the validation
-			// and allows only the listening address.
-			http::DomainsValidation::AllowOnly(vec![])
-		} else {
-			http::DomainsValidation::Disabled
-		}
+	#[test]
+	fn only_the_listening_address_is_allowed_as_host() {
+		let listening = "127.0.0.1:9933".parse().unwrap();
+		assert!(is_host_allowed(&listening, "127.0.0.1:9933"));
+		assert!(is_host_allowed(&listening, "localhost:9933"));
+		assert!(!is_host_allowed(&listening, "evil.com:9933"));
+		assert!(!is_host_allowed(&listening, "127.0.0.1:9944"));
 	}
-}
 
-#[cfg(target_os = "unknown")]
-mod inner {
+	#[test]
+	fn denied_methods_are_never_exposed() {
+		let methods = RpcMethods { denied: vec!["system_health".into()], ..RpcMethods::all() };
+
+		assert!(!methods.is_exposed("system_health"));
+		assert!(methods.is_exposed("author_insertKey"));
+	}
 }
@@ -0,0 +1,239 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Restriction of the RPC methods callable from given browser origins.
+//!
+//! CORS only tells browsers which origins may read the responses, and doesn't restrict the
+//! methods. The policy is read from a JSON file like:
+//!
+//! ```json
+//! {
+//!   "groups": { "readOnly": ["chain_*", "state_*", "system_health", "rpc_methods"] },
+//!   "origins": {
+//!     "https://dapp.example.com": ["@readOnly", "author_submitExtrinsic"],
+//!     "https://admin.example.com": ["*"],
+//!     "*": ["@readOnly"]
+//!   }
+//! }
+//! ```
+//!
+//! Requests with an `Origin` header listed in `origins` can only call the methods it lists. Names
+//! starting with `@` refer to a group of `groups`, and names ending with `*` allow all methods with
+//! the given prefix. Requests from other origins can only call the methods of the `*` entry, and
+//! none without it, as CORS may allow more origins than the policy lists. Requests without origin
+//! don't come from browsers and aren't restricted beyond the other settings of the server.
+
+use std::{collections::HashMap, fs, io, path::Path};
+use jsonrpc_core::{
+	Error, ErrorCode, MetaIoHandler, Metadata, RemoteProcedure,
+	futures::future::{self, Either},
+};
+use serde::Deserialize;
+
+use crate::{auth::BASE_ERROR, is_method_allowed};
+
+/// The origin of the request isn't allowed to call the method.
+const ORIGIN_NOT_ALLOWED: i64 = BASE_ERROR + 3;
+
+/// Entry of `origins` holding the methods callable from the origins not listed.
+const OTHER_ORIGINS: &str = "*";
+
+/// Methods callable from each browser origin.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct OriginPolicy {
+	/// Named lists of methods, referred to as `@<name>` in the lists of the origins.
+	#[serde(default)]
+	pub groups: HashMap<String, Vec<String>>,
+	/// The methods callable from each origin, and from the others under `*`.
+	#[serde(default)]
+	pub origins: HashMap<String, Vec<String>>,
+}
+
+impl OriginPolicy {
+	/// Parse the policy from JSON, checking that the groups it refers to are defined.
+	pub fn from_json(json: &str) -> io::Result<Self> {
+		let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+		let policy: Self = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+		for (origin, methods) in &policy.origins {
+			for group in methods.iter().filter(|method| method.starts_with('@')) {
+				if !policy.groups.contains_key(&group[1..]) {
+					return Err(invalid(format!("Unknown method group {} for origin {}", group, origin)));
+				}
+			}
+		}
+		Ok(policy)
+	}
+
+	/// Read the policy from a JSON file.
+	pub fn from_file(path: &Path) -> io::Result<Self> {
+		Self::from_json(&fs::read_to_string(path)?)
+	}
+
+	/// Whether a request from `origin` may call `method`.
+	pub fn allows(&self, origin: Option<&str>, method: &str) -> bool {
+		let origin = match origin {
+			Some(origin) => origin,
+			None => return true,
+		};
+		let allowed = match self.origins.get(origin).or_else(|| self.origins.get(OTHER_ORIGINS)) {
+			Some(allowed) => allowed,
+			None => return false,
+		};
+		allowed.iter().any(|allowed| if allowed.starts_with('@') {
+			self.groups.get(&allowed[1..])
+				.map_or(false, |group| group.iter().any(|pattern| is_method_allowed(pattern, method)))
+		} else {
+			is_method_allowed(allowed, method)
+		})
+	}
+
+	/// Wrap the methods of `io`, so they can only be called from the origins allowing them.
+	pub fn wrap<M: Metadata>(&self, io: &MetaIoHandler<M>) -> MetaIoHandler<OriginMetadata<M>> {
+		let mut handler = MetaIoHandler::default();
+		for (name, procedure) in io.iter() {
+			// Methods allowed from all origins need no check.
+			let restricted = !self.origins.contains_key(OTHER_ORIGINS)
+				|| self.origins.keys().any(|origin| !self.allows(Some(origin), name));
+			let (policy, method_name) = (self.clone(), name.clone());
+			match procedure.clone() {
+				RemoteProcedure::Method(method) if restricted => handler.add_method_with_meta(
+					name,
+					move |params, meta: OriginMetadata<M>| {
+						if policy.allows(meta.origin(), &method_name) {
+							Either::A(method.call(params, meta.inner))
+						} else {
+							Either::B(future::err(Error {
+								code: ErrorCode::ServerError(ORIGIN_NOT_ALLOWED),
+								message: format!("Method {} is not allowed from this origin", method_name),
+								data: None,
+							}))
+						}
+					},
+				),
+				RemoteProcedure::Method(method) => handler.add_method_with_meta(
+					name,
+					move |params, meta: OriginMetadata<M>| method.call(params, meta.inner),
+				),
+				RemoteProcedure::Notification(notification) => handler.add_notification_with_meta(
+					name,
+					move |params, meta: OriginMetadata<M>| {
+						if policy.allows(meta.origin(), &method_name) {
+							notification.execute(params, meta.inner)
+						}
+					},
+				),
+				RemoteProcedure::Alias(other) => handler.add_alias(name, &other),
+			}
+		}
+		handler
+	}
+}
+
+/// Metadata of a request to a server with an origin policy.
+#[derive(Debug, Clone, Default)]
+pub struct OriginMetadata<M> {
+	/// The metadata of the wrapped handler.
+	pub inner: M,
+	/// The `Origin` header of the request.
+	pub origin: Option<String>,
+}
+
+impl<M> OriginMetadata<M> {
+	fn origin(&self) -> Option<&str> {
+		self.origin.as_ref().map(|origin| &origin[..])
+	}
+}
+
+impl<M: Metadata> Metadata for OriginMetadata<M> {}
+
+/// Extract the `Origin` header of a HTTP request.
+pub fn origin<T>(request: &http::hyper::Request<T>) -> Option<String> {
+	request.headers().get(http::hyper::header::ORIGIN)?.to_str().ok().map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn policy() -> OriginPolicy {
+		OriginPolicy::from_json(r#"{
+			"groups": { "readOnly": ["chain_*", "state_getStorage"] },
+			"origins": {
+				"https://dapp.example.com": ["@readOnly", "author_submitExtrinsic"],
+				"https://admin.example.com": ["*"]
+			}
+		}"#).unwrap()
+	}
+
+	#[test]
+	fn methods_are_restricted_per_origin() {
+		let policy = policy();
+		let dapp = Some("https://dapp.example.com");
+
+		assert!(policy.allows(dapp, "chain_getBlock"));
+		assert!(policy.allows(dapp, "state_getStorage"));
+		assert!(policy.allows(dapp, "author_submitExtrinsic"));
+		assert!(!policy.allows(dapp, "author_insertKey"));
+		assert!(!policy.allows(dapp, "state_call"));
+
+		assert!(policy.allows(Some("https://admin.example.com"), "author_insertKey"));
+		assert!(policy.allows(None, "author_insertKey"));
+	}
+
+	#[test]
+	fn unlisted_origins_are_denied_by_default() {
+		let other = Some("https://other.example.com");
+		assert!(!policy().allows(other, "author_insertKey"));
+		assert!(!policy().allows(other, "chain_getBlock"));
+
+		let policy = OriginPolicy::from_json(r#"{
+			"groups": { "readOnly": ["chain_*"] },
+			"origins": {
+				"https://admin.example.com": ["*"],
+				"*": ["@readOnly"]
+			}
+		}"#).unwrap();
+		assert!(policy.allows(other, "chain_getBlock"));
+		assert!(!policy.allows(other, "author_insertKey"));
+		assert!(policy.allows(Some("https://admin.example.com"), "author_insertKey"));
+	}
+
+	#[test]
+	fn unknown_groups_are_rejected() {
+		let policy = OriginPolicy::from_json(r#"{ "origins": { "https://dapp.example.com": ["@unknown"] } }"#);
+		assert_eq!(policy.unwrap_err().kind(), io::ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn wrapped_methods_check_the_origin() {
+		let mut io = MetaIoHandler::<()>::default();
+		io.add_method("author_insertKey", |_| Ok(jsonrpc_core::Value::Bool(true)));
+		let handler = policy().wrap(&io);
+		let request = r#"{"jsonrpc":"2.0","method":"author_insertKey","params":[],"id":1}"#;
+
+		let meta = OriginMetadata { origin: Some("https://dapp.example.com".into()), ..Default::default() };
+		let dapp = handler.handle_request_sync(request, meta).unwrap();
+		assert!(dapp.contains("5003"));
+
+		let meta = OriginMetadata { origin: Some("https://other.example.com".into()), ..Default::default() };
+		let other = handler.handle_request_sync(request, meta).unwrap();
+		assert!(other.contains("5003"));
+
+		let local = handler.handle_request_sync(request, OriginMetadata::default()).unwrap();
+		assert_eq!(local, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+	}
+}
@@ -0,0 +1,182 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! TLS termination of the RPC servers.
+//!
+//! The certificates are chosen with the server name indicated by the client, so that a node can
+//! serve several domains, and the certificate given for the server is used for the other names.
+//! The files are read again on [`TlsAcceptor::reload`], which keeps the previous configuration if
+//! they are invalid.
+
+use std::{
+	collections::HashMap, fs::{self, File}, io::{self, BufReader}, path::Path, sync::{Arc, RwLock},
+};
+use log::{info, warn};
+use tokio_rustls::{
+	rustls::{
+		self, AllowAnyAuthenticatedClient, NoClientAuth, ResolvesServerCert, RootCertStore,
+		ServerConfig, SignatureScheme, sign::{self, CertifiedKey},
+	},
+	webpki::DNSNameRef,
+};
+
+use crate::TlsConfig;
+
+/// Extension of the certificate chains in the SNI directory.
+const CERT_EXTENSION: &str = "crt";
+/// Extension of the private keys in the SNI directory.
+const KEY_EXTENSION: &str = "key";
+
+/// Accepts TLS connections with the current configuration.
+#[derive(Clone)]
+pub struct TlsAcceptor {
+	config: TlsConfig,
+	current: Arc<RwLock<tokio_rustls::TlsAcceptor>>,
+}
+
+impl TlsAcceptor {
+	/// Read the certificates and keys of `config`.
+	pub fn new(config: TlsConfig) -> io::Result<Self> {
+		let current = tokio_rustls::TlsAcceptor::from(Arc::new(server_config(&config)?));
+		Ok(TlsAcceptor { config, current: Arc::new(RwLock::new(current)) })
+	}
+
+	/// Read the certificates and keys again, for the connections accepted from now on.
+	pub fn reload(&self) {
+		match server_config(&self.config) {
+			Ok(server) => {
+				*self.current.write().expect("TLS acceptor is never poisoned; qed") =
+					tokio_rustls::TlsAcceptor::from(Arc::new(server));
+				info!("Reloaded the RPC TLS certificates");
+			},
+			Err(e) => warn!("Failed to reload the RPC TLS certificates, keeping the old ones: {}", e),
+		}
+	}
+
+	/// The acceptor of the current configuration.
+	pub fn current(&self) -> tokio_rustls::TlsAcceptor {
+		self.current.read().expect("TLS acceptor is never poisoned; qed").clone()
+	}
+}
+
+fn invalid(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Build the rustls configuration of the server.
+fn server_config(config: &TlsConfig) -> io::Result<ServerConfig> {
+	let mut server = match &config.client_ca {
+		Some(path) => {
+			let mut roots = RootCertStore::empty();
+			let (valid, _) = roots.add_pem_file(&mut BufReader::new(File::open(path)?))
+				.map_err(|()| invalid(format!("Invalid CA file {}", path.display())))?;
+			if valid == 0 {
+				return Err(invalid(format!("No CA certificate in {}", path.display())));
+			}
+			ServerConfig::new(AllowAnyAuthenticatedClient::new(roots))
+		},
+		None => ServerConfig::new(NoClientAuth::new()),
+	};
+
+	let mut by_name = HashMap::new();
+	if let Some(dir) = &config.sni_dir {
+		for entry in fs::read_dir(dir)? {
+			let cert = entry?.path();
+			if cert.extension().map_or(true, |extension| extension != CERT_EXTENSION) {
+				continue;
+			}
+			let name = match cert.file_stem().and_then(|name| name.to_str()) {
+				Some(name) => name.to_ascii_lowercase(),
+				None => continue,
+			};
+			by_name.insert(name, certified_key(&cert, &cert.with_extension(KEY_EXTENSION))?);
+		}
+	}
+
+	server.cert_resolver = Arc::new(Certificates {
+		default: certified_key(&config.cert, &config.key)?,
+		by_name,
+	});
+	server.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+	Ok(server)
+}
+
+/// Read a certificate chain and its private key.
+fn certified_key(cert: &Path, key: &Path) -> io::Result<CertifiedKey> {
+	let certs = rustls::internal::pemfile::certs(&mut BufReader::new(File::open(cert)?))
+		.map_err(|()| invalid(format!("Invalid certificate file {}", cert.display())))?;
+	if certs.is_empty() {
+		return Err(invalid(format!("No certificate in {}", cert.display())));
+	}
+	let mut keys = rustls::internal::pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(key)?))
+		.map_err(|()| invalid(format!("Invalid key file {}", key.display())))?;
+	if keys.is_empty() {
+		keys = rustls::internal::pemfile::rsa_private_keys(&mut BufReader::new(File::open(key)?))
+			.map_err(|()| invalid(format!("Invalid key file {}", key.display())))?;
+	}
+	let private_key = keys.first()
+		.ok_or_else(|| invalid(format!("No private key in {}", key.display())))?;
+	let private_key = sign::any_supported_type(private_key)
+		.map_err(|()| invalid(format!("Unsupported private key in {}", key.display())))?;
+	Ok(CertifiedKey::new(certs, Arc::new(private_key)))
+}
+
+/// Certificates of the server, by server name.
+struct Certificates {
+	default: CertifiedKey,
+	by_name: HashMap<String, CertifiedKey>,
+}
+
+impl ResolvesServerCert for Certificates {
+	fn resolve(&self, server_name: Option<DNSNameRef>, _: &[SignatureScheme]) -> Option<CertifiedKey> {
+		let by_name = server_name.and_then(|name| {
+			let name: &str = name.into();
+			self.by_name.get(&name.to_ascii_lowercase())
+		});
+		Some(by_name.unwrap_or(&self.default).clone())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn missing_files_are_reported() {
+		let config = TlsConfig {
+			cert: "/nonexistent/rpc.crt".into(),
+			key: "/nonexistent/rpc.key".into(),
+			sni_dir: None,
+			client_ca: None,
+		};
+		assert_eq!(server_config(&config).unwrap_err().kind(), io::ErrorKind::NotFound);
+	}
+
+	#[test]
+	fn empty_files_are_invalid() {
+		let dir = tempfile::tempdir().unwrap();
+		let config = TlsConfig {
+			cert: dir.path().join("rpc.crt"),
+			key: dir.path().join("rpc.key"),
+			sni_dir: None,
+			client_ca: None,
+		};
+		fs::write(&config.cert, "").unwrap();
+		fs::write(&config.key, "").unwrap();
+
+		assert_eq!(server_config(&config).unwrap_err().kind(), io::ErrorKind::InvalidData);
+	}
+}
@@ -0,0 +1,240 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! WebSocket RPC server.
+//!
+//! `jsonrpc-ws-server` neither lets its sessions negotiate extensions nor tells the headers and
+//! address of the clients to the metadata, which the API keys and their rate limits need. This
+//! server can negotiate `permessage-deflate` with the clients which offer it. Storage
+//! subscriptions send the same hex-encoded keys and values over and over, which compress very well.
+
+use std::{io, net::SocketAddr, sync::{Arc, Mutex}, thread};
+use jsonrpc_core::futures::{sync::mpsc, Future, Stream};
+use log::{error, warn};
+use pubsub::PubSubMetadata;
+use ws_rs::{deflate::DeflateHandler, Handshake, Message, Request, Response};
+
+use crate::{
+	Auth, AuthMetadata, ClientMetadata, MAX_PAYLOAD, OriginMetadata, OriginPolicy, RpcHandler,
+	WS_MAX_CONNECTIONS, is_host_allowed, is_origin_allowed,
+};
+
+/// Number of subscription notifications buffered for a session.
+const NOTIFICATIONS_BUFFER: usize = 1024;
+
+type ResponseFuture = Box<dyn Future<Item = Option<String>, Error = ()> + Send>;
+
+/// WebSocket RPC server. Stops when dropped.
+pub struct WsServer {
+	local_addr: SocketAddr,
+	broadcaster: ws_rs::Sender,
+	// The servers are kept in a `Sync` box, which the runtime isn't.
+	_runtime: Mutex<tokio::runtime::Runtime>,
+}
+
+impl WsServer {
+	/// The address the server listens on.
+	pub fn address(&self) -> &SocketAddr {
+		&self.local_addr
+	}
+}
+
+impl Drop for WsServer {
+	fn drop(&mut self) {
+		let _ = self.broadcaster.shutdown();
+	}
+}
+
+/// Start a WS server listening on the given address.
+///
+/// If `auth` is given, the methods can only be called with the API keys allowing them. If
+/// `policy` is given, the methods callable from some origins are restricted.
+///
+/// **Note**: Only available if `not(target_os = "unknown")`.
+pub fn start_ws<M: PubSubMetadata + ClientMetadata + From<mpsc::Sender<String>>>(
+	addr: &SocketAddr,
+	max_connections: Option<usize>,
+	cors: Option<&Vec<String>>,
+	auth: Option<&Auth>,
+	policy: Option<&OriginPolicy>,
+	io: RpcHandler<M>,
+) -> io::Result<WsServer> {
+	start(addr, max_connections, cors, auth, policy, io, |session| session)
+}
+
+/// Start a WS server compressing its messages, listening on the given address.
+///
+/// If `auth` is given, the methods can only be called with the API keys allowing them. If
+/// `policy` is given, the methods callable from some origins are restricted.
+///
+/// **Note**: Only available if `not(target_os = "unknown")`.
+pub fn start_compressed_ws<M: PubSubMetadata + ClientMetadata + From<mpsc::Sender<String>>>(
+	addr: &SocketAddr,
+	max_connections: Option<usize>,
+	cors: Option<&Vec<String>>,
+	auth: Option<&Auth>,
+	policy: Option<&OriginPolicy>,
+	io: RpcHandler<M>,
+) -> io::Result<WsServer> {
+	start(addr, max_connections, cors, auth, policy, io, DeflateHandler::new)
+}
+
+fn start<M, H>(
+	addr: &SocketAddr,
+	max_connections: Option<usize>,
+	cors: Option<&Vec<String>>,
+	auth: Option<&Auth>,
+	policy: Option<&OriginPolicy>,
+	io: RpcHandler<M>,
+	wrap: fn(Session<M>) -> H,
+) -> io::Result<WsServer> where
+	M: PubSubMetadata + ClientMetadata + From<mpsc::Sender<String>>,
+	H: ws_rs::Handler,
+{
+	let runtime = tokio::runtime::Builder::new()
+		.core_threads(4)
+		.name_prefix("rpc-ws-")
+		.build()?;
+	let io = policy.cloned().unwrap_or_default().wrap(&io);
+	let handle: Handle<M> = match auth {
+		Some(auth) => {
+			let io = auth.wrap(&io);
+			Arc::new(move |request: &str, meta: &AuthMetadata<OriginMetadata<M>>| {
+				Box::new(io.handle_request(request, meta.clone())) as ResponseFuture
+			})
+		},
+		None => Arc::new(move |request: &str, meta: &AuthMetadata<OriginMetadata<M>>| {
+			Box::new(io.handle_request(request, meta.inner.clone())) as ResponseFuture
+		}),
+	};
+	let cors = cors.cloned();
+	let executor = runtime.executor();
+	// Known once bound, before any connection is accepted.
+	let listening = Arc::new(Mutex::new(*addr));
+	let session_listening = listening.clone();
+
+	let socket = ws_rs::Builder::new()
+		.with_settings(ws_rs::Settings {
+			max_connections: max_connections.unwrap_or(WS_MAX_CONNECTIONS),
+			max_fragment_size: MAX_PAYLOAD,
+			..Default::default()
+		})
+		.build(move |out: ws_rs::Sender| {
+			// Subscriptions send their notifications through the metadata of the session.
+			let (sender, notifications) = mpsc::channel(NOTIFICATIONS_BUFFER);
+			let forward = out.clone();
+			executor.spawn(notifications.for_each(move |notification| {
+				let _ = forward.send(notification);
+				Ok(())
+			}));
+			wrap(Session {
+				out,
+				handle: handle.clone(),
+				meta: AuthMetadata {
+					inner: OriginMetadata { inner: sender.into(), origin: None },
+					api_key: None,
+					client: None,
+				},
+				executor: executor.clone(),
+				cors: cors.clone(),
+				listening: session_listening.clone(),
+			})
+		})
+		.map_err(ws_error)?
+		.bind(addr)
+		.map_err(ws_error)?;
+	let local_addr = socket.local_addr()?;
+	*listening.lock().expect("Listening address is never poisoned; qed") = local_addr;
+	let broadcaster = socket.broadcaster();
+
+	thread::Builder::new()
+		.name("rpc-ws".into())
+		.spawn(move || if let Err(e) = socket.run() {
+			error!("WS RPC server failed: {}", e);
+		})?;
+
+	Ok(WsServer { local_addr, broadcaster, _runtime: Mutex::new(runtime) })
+}
+
+fn ws_error(error: ws_rs::Error) -> io::Error {
+	match error.kind {
+		ws_rs::ErrorKind::Io(e) => e,
+		_ => io::Error::new(io::ErrorKind::Other, error.to_string()),
+	}
+}
+
+/// Answers a request with the metadata of its session.
+type Handle<M> = Arc<dyn Fn(&str, &AuthMetadata<OriginMetadata<M>>) -> ResponseFuture + Send + Sync>;
+
+/// A WebSocket connection.
+struct Session<M: PubSubMetadata> {
+	out: ws_rs::Sender,
+	handle: Handle<M>,
+	meta: AuthMetadata<OriginMetadata<M>>,
+	executor: tokio::runtime::TaskExecutor,
+	cors: Option<Vec<String>>,
+	listening: Arc<Mutex<SocketAddr>>,
+}
+
+impl<M: PubSubMetadata + ClientMetadata> ws_rs::Handler for Session<M> {
+	fn on_request(&mut self, request: &Request) -> ws_rs::Result<Response> {
+		if let Some(origin) = request.origin()? {
+			if !is_origin_allowed(self.cors.as_ref(), origin) {
+				warn!("Rejected WS RPC connection from origin {}", origin);
+				return Ok(Response::new(403, "Forbidden", b"Origin not allowed".to_vec()));
+			}
+		}
+		if self.cors.is_some() && !self.is_host_allowed(request) {
+			return Ok(Response::new(403, "Forbidden", b"Host not allowed".to_vec()));
+		}
+		self.meta.inner.origin = request.origin()?.map(String::from);
+		self.meta.api_key = request.header("authorization")
+			.and_then(|header| std::str::from_utf8(header).ok())
+			.and_then(crate::auth::bearer);
+		Response::from_request(request)
+	}
+
+	fn on_open(&mut self, handshake: Handshake) -> ws_rs::Result<()> {
+		if let Some(peer) = handshake.peer_addr {
+			self.meta.inner.inner.set_client(peer.ip());
+			self.meta.client = Some(peer.ip());
+		}
+		Ok(())
+	}
+
+	fn on_message(&mut self, message: Message) -> ws_rs::Result<()> {
+		let request = message.into_text()?;
+		let out = self.out.clone();
+		self.executor.spawn((self.handle)(&request, &self.meta).map(move |response| {
+			if let Some(response) = response {
+				let _ = out.send(response);
+			}
+		}));
+		Ok(())
+	}
+}
+
+impl<M: PubSubMetadata> Session<M> {
+	/// Like `jsonrpc-ws-server` when CORS are restricted, only allow the listening address as
+	/// `Host`, to prevent DNS rebinding.
+	fn is_host_allowed(&self, request: &Request) -> bool {
+		let host = match request.header("host").and_then(|host| std::str::from_utf8(host).ok()) {
+			Some(host) => host,
+			None => return false,
+		};
+		is_host_allowed(&self.listening.lock().expect("Listening address is never poisoned; qed"), host)
+	}
+}
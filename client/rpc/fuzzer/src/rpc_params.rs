@@ -0,0 +1,61 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fuzzes the parameters of the author, chain and state RPC methods.
+//!
+//! Each input is handled as a JSON-RPC request by a full node backed by the test runtime.
+//!
+//! # Running
+//! Running this fuzzer can be done with `cargo hfuzz run rpc_params`. `honggfuzz` CLI options
+//! can be used by setting `HFUZZ_RUN_ARGS`, such as `-n 4` to use 4 threads.
+//!
+//! # Debugging a panic
+//! Once a panic is found, it can be debugged with
+//! `cargo hfuzz run-debug rpc_params hfuzz_workspace/rpc_params/*.fuzz`.
+
+use std::sync::Arc;
+use honggfuzz::fuzz;
+use jsonrpc_core::MetaIoHandler;
+use sc_rpc::{
+	Metadata, Subscriptions, author::{Author, AuthorApi}, chain::{self, ChainApi}, state::{self, StateApi},
+};
+use sc_transaction_pool::{BasicPool, FullChainApi};
+use sp_core::testing::KeyStore;
+use substrate_test_runtime_client::{DefaultTestClientBuilderExt, TestClientBuilderExt};
+
+fn main() {
+	let runtime = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+	let subscriptions = || Subscriptions::new(Arc::new(runtime.executor()));
+
+	let keystore = KeyStore::new();
+	let client = Arc::new(
+		substrate_test_runtime_client::TestClientBuilder::new().set_keystore(keystore.clone()).build()
+	);
+	let pool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+
+	let mut io = MetaIoHandler::<Metadata>::default();
+	io.extend_with(AuthorApi::to_delegate(Author::new(client.clone(), pool, subscriptions(), keystore)));
+	io.extend_with(ChainApi::to_delegate(chain::new_full(client.clone(), subscriptions())));
+	io.extend_with(StateApi::to_delegate(state::new_full(client, subscriptions())));
+
+	loop {
+		fuzz!(|data: &[u8]| {
+			if let Ok(request) = std::str::from_utf8(data) {
+				let _ = io.handle_request_sync(request, Default::default());
+			}
+		});
+	}
+}
@@ -18,9 +18,11 @@
 
 #[cfg(test)]
 mod tests;
+mod queue;
 
 use std::{sync::Arc, convert::TryInto};
 use log::warn;
+use parking_lot::Mutex;
 
 use sc_client::Client;
 use sp_blockchain::Error as ClientError;
@@ -36,16 +38,21 @@ use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use codec::{Encode, Decode};
 use sp_core::{Bytes, traits::BareCryptoStorePtr};
 use sp_api::ProvideRuntimeApi;
-use sp_runtime::{generic, traits};
+use sp_runtime::{generic, traits::{self, Header as _, SaturatedConversion}};
+use sp_runtime::transaction_validity::InvalidTransaction;
+use sp_state_machine::ExecutionStrategy;
 use sp_transaction_pool::{
 	TransactionPool, InPoolTransaction, TransactionStatus,
-	BlockHash, TxHash, TransactionFor, error::IntoPoolError,
+	BlockHash, TxHash, TransactionFor, error::{Error as PoolError, IntoPoolError},
 };
 use sp_session::SessionKeys;
+use sp_version::RuntimeVersion;
+use substrate_frame_decode::{Decoder, TypeRegistry};
 
 /// Re-export the API for backward compatibility.
 pub use sc_rpc_api::author::*;
-use self::error::{Error, FutureResult, Result};
+pub use self::queue::SubmissionQueue;
+use self::error::{EraInfo, Error, FutureResult, Result};
 
 /// Authoring API
 pub struct Author<B, E, P, Block: traits::Block, RA> {
@@ -57,8 +64,16 @@ pub struct Author<B, E, P, Block: traits::Block, RA> {
 	subscriptions: Subscriptions,
 	/// The key store.
 	keystore: BareCryptoStorePtr,
+	/// The extrinsic decoder of the last runtime a rejected extrinsic was described for.
+	decoder: DecoderCache,
+	/// Submissions waiting for validation.
+	submissions: SubmissionQueue,
 }
 
+/// The runtime version an extrinsic decoder was built for, and the decoder, `None` if its metadata
+/// can't be decoded.
+type DecoderCache = Arc<Mutex<Option<(RuntimeVersion, Option<Arc<Decoder>>)>>>;
+
 impl<B, E, P, Block: traits::Block, RA> Author<B, E, P, Block, RA> {
 	/// Create new instance of Authoring API.
 	pub fn new(
@@ -72,8 +87,16 @@ impl<B, E, P, Block: traits::Block, RA> Author<B, E, P, Block, RA> {
 			pool,
 			subscriptions,
 			keystore,
+			decoder: Default::default(),
+			submissions: Default::default(),
 		}
 	}
+
+	/// Admit the submitted transactions to validation through `submissions`, e.g. to share it
+	/// with the other RPCs submitting transactions.
+	pub fn with_submission_queue(self, submissions: SubmissionQueue) -> Self {
+		Author { submissions, ..self }
+	}
 }
 
 impl<B, E, P, RA> AuthorApi<TxHash<P>, BlockHash<P>>
@@ -112,19 +135,36 @@ where
 		).map(Into::into).map_err(|e| Error::Client(Box::new(e)))
 	}
 
-	fn submit_extrinsic(&self, ext: Bytes) -> FutureResult<TxHash<P>> {
+	fn submit_extrinsic(&self, metadata: Self::Metadata, ext: Bytes) -> FutureResult<TxHash<P>> {
 		let xt = match Decode::decode(&mut &ext[..]) {
 			Ok(xt) => xt,
 			Err(err) => return Box::new(result(Err(err.into()))),
 		};
-		let best_block_hash = self.client.chain_info().best_hash;
-		Box::new(self.pool
-			.submit_one(&generic::BlockId::hash(best_block_hash), xt)
-			.compat()
-			.map_err(|e| e.into_pool_error()
-				.map(Into::into)
-				.unwrap_or_else(|e| error::Error::Verification(Box::new(e)).into()))
-		)
+		let admission = match self.submissions.admit(metadata.client()) {
+			Ok(admission) => admission,
+			Err(_) => return Box::new(result(Err(Error::Overloaded))),
+		};
+		let client = self.client.clone();
+		let pool = self.pool.clone();
+		let decoder = self.decoder.clone();
+		let future = admission.then(move |permit| {
+			let best_block_hash = client.chain_info().best_hash;
+			pool.submit_one(&generic::BlockId::hash(best_block_hash), xt)
+				.map(move |result| {
+					drop(permit);
+					result.map_err(|e| match e.into_pool_error() {
+						Ok(e @ PoolError::InvalidTransaction(InvalidTransaction::AncientBirthBlock)) |
+						Ok(e @ PoolError::InvalidTransaction(InvalidTransaction::BadProof)) =>
+							match era_info(&client, &decoder, best_block_hash, &ext) {
+								Some(era) => Error::InvalidEra(e, era),
+								None => e.into(),
+							},
+						Ok(e) => e.into(),
+						Err(e) => error::Error::Verification(Box::new(e)),
+					})
+				})
+		});
+		Box::new(future.boxed().compat())
 	}
 
 	fn pending_extrinsics(&self) -> Result<Vec<Bytes>> {
@@ -155,16 +195,25 @@ where
 	}
 
 	fn watch_extrinsic(&self,
-		_metadata: Self::Metadata,
+		metadata: Self::Metadata,
 		subscriber: Subscriber<TransactionStatus<TxHash<P>, BlockHash<P>>>,
 		xt: Bytes,
 	) {
-		let submit = || -> Result<_> {
-			let best_block_hash = self.client.chain_info().best_hash;
+		let admission = match self.submissions.admit(metadata.client()) {
+			Ok(admission) => admission,
+			Err(_) => {
+				let _ = subscriber.reject(Error::Overloaded.into());
+				return;
+			},
+		};
+		let client = self.client.clone();
+		let pool = self.pool.clone();
+		let submit = move || -> Result<_> {
+			let best_block_hash = client.chain_info().best_hash;
 			let dxt = TransactionFor::<P>::decode(&mut &xt[..])
 				.map_err(error::Error::from)?;
 			Ok(
-				self.pool
+				pool
 					.submit_and_watch(&generic::BlockId::hash(best_block_hash), dxt)
 					.map_err(|e| e.into_pool_error()
 						.map(error::Error::from)
@@ -174,8 +223,14 @@ where
 		};
 
 		let subscriptions = self.subscriptions.clone();
-		let future = ready(submit())
-			.and_then(|res| res)
+		let future = admission
+			.then(move |permit| ready(submit())
+				.and_then(|res| res)
+				.map(move |res| {
+					drop(permit);
+					res
+				})
+			)
 			// convert the watcher into a `Stream`
 			.map(|res| res.map(|stream| stream.map(|v| Ok::<_, ()>(Ok(v)))))
 			// now handle the import result,
@@ -207,3 +262,51 @@ where
 		Ok(self.subscriptions.cancel(id))
 	}
 }
+
+/// Describe the era of a signed extrinsic relative to the given block, to explain its rejection.
+///
+/// The extrinsic is decoded using the runtime metadata, `None` is returned if that fails
+/// or the extrinsic is immortal. The decoder is kept in `cache` until the runtime changes.
+fn era_info<B, E, Block, RA>(
+	client: &Client<B, E, Block, RA>,
+	cache: &DecoderCache,
+	at: Block::Hash,
+	extrinsic: &[u8],
+) -> Option<EraInfo> where
+	B: sc_client_api::backend::Backend<Block>,
+	E: sc_client::CallExecutor<Block>,
+	Block: traits::Block,
+{
+	let at = generic::BlockId::hash(at);
+	let version = client.runtime_version_at(&at).ok()?;
+	let cached = match &*cache.lock() {
+		Some((cached, decoder)) if *cached == version => Some(decoder.clone()),
+		_ => None,
+	};
+	let decoder = match cached {
+		Some(decoder) => decoder?,
+		None => {
+			let decoder = client.executor()
+				.call(&at, "Metadata_metadata", &[], ExecutionStrategy::NativeElseWasm, None)
+				.ok()
+				.and_then(|metadata| Vec::<u8>::decode(&mut &metadata[..]).ok())
+				.and_then(|metadata| Decoder::from_bytes(&metadata, TypeRegistry::with_defaults()).ok())
+				.map(Arc::new);
+			*cache.lock() = Some((version, decoder.clone()));
+			decoder?
+		},
+	};
+	let extrinsic = decoder.decode_extrinsic(extrinsic).ok()?;
+
+	let era = &extrinsic["signature"]["extra"]["era"]["Mortal"];
+	let period = era["period"].as_u64()?;
+	let phase = era["phase"].as_u64()?;
+	let current_block = (*client.header(&at).ok()??.number()).saturated_into::<u64>();
+
+	Some(EraInfo {
+		birth_block: generic::Era::Mortal(period, phase).birth(current_block),
+		current_block,
+		period,
+		phase,
+	})
+}
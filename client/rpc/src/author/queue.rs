@@ -0,0 +1,232 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Admission of submitted transactions to validation.
+//!
+//! Validating a transaction takes a runtime call, so that under heavy submission load the
+//! validations pile up and the latency of every submission collapses. Only a bounded number of
+//! submissions are validated at a time. The others wait in a queue per client address, up to a
+//! limit beyond which they are rejected, telling the client to retry later. Clients are served in
+//! turn, so that a client submitting a lot, even over many connections, doesn't delay the others.
+
+use std::{collections::{HashMap, VecDeque}, net::IpAddr, pin::Pin, sync::Arc};
+use futures::{channel::oneshot, task::{Context, Poll}, Future};
+use parking_lot::Mutex;
+
+/// Maximum number of submissions validated at a time.
+const MAX_VALIDATING: usize = 64;
+/// Maximum number of submissions waiting for validation, per client.
+const MAX_WAITING_PER_CLIENT: usize = 256;
+/// Maximum number of submissions waiting for validation, over all clients.
+const MAX_WAITING: usize = 4096;
+
+/// Identifies the client a submission comes from by its address, `None` for the submissions
+/// which don't come from the network.
+pub type ClientId = Option<IpAddr>;
+
+/// Too many submissions are waiting for validation.
+#[derive(Debug, PartialEq)]
+pub struct Overloaded;
+
+/// Queue of submissions waiting for validation.
+#[derive(Clone)]
+pub struct SubmissionQueue {
+	inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+	max_validating: usize,
+	max_waiting_per_client: usize,
+	max_waiting: usize,
+	/// Number of submissions being validated.
+	validating: usize,
+	/// Number of submissions waiting.
+	waiting: usize,
+	/// Waiting submissions of each client.
+	queues: HashMap<ClientId, VecDeque<oneshot::Sender<()>>>,
+	/// Clients with a queue, in the order they are served.
+	turns: VecDeque<ClientId>,
+}
+
+impl Default for SubmissionQueue {
+	fn default() -> Self {
+		SubmissionQueue::new(MAX_VALIDATING, MAX_WAITING_PER_CLIENT, MAX_WAITING)
+	}
+}
+
+impl SubmissionQueue {
+	/// Create a queue validating at most `max_validating` submissions at a time, and keeping at
+	/// most `max_waiting_per_client` waiting submissions per client and `max_waiting` overall.
+	pub fn new(max_validating: usize, max_waiting_per_client: usize, max_waiting: usize) -> Self {
+		SubmissionQueue {
+			inner: Arc::new(Mutex::new(Inner {
+				max_validating,
+				max_waiting_per_client,
+				max_waiting,
+				validating: 0,
+				waiting: 0,
+				queues: HashMap::new(),
+				turns: VecDeque::new(),
+			})),
+		}
+	}
+
+	/// Queue a submission coming from `client`.
+	///
+	/// The returned future resolves once the submission may be validated.
+	pub fn admit(&self, client: ClientId) -> Result<Admission, Overloaded> {
+		let mut inner = self.inner.lock();
+		if inner.validating < inner.max_validating {
+			inner.validating += 1;
+			return Ok(Admission { inner: self.inner.clone(), receiver: None, admitted: false });
+		}
+
+		let inner = &mut *inner;
+		if !inner.queues.contains_key(&client) {
+			if inner.waiting >= inner.max_waiting {
+				return Err(Overloaded);
+			}
+			inner.queues.insert(client, VecDeque::new());
+			inner.turns.push_back(client);
+		}
+
+		// Forget the submissions given up on while waiting.
+		let queue = inner.queues.get_mut(&client).expect("Inserted above if missing; qed");
+		let waiting = queue.len();
+		queue.retain(|sender| !sender.is_canceled());
+		inner.waiting -= waiting - queue.len();
+		if queue.len() >= inner.max_waiting_per_client || inner.waiting >= inner.max_waiting {
+			return Err(Overloaded);
+		}
+
+		let (sender, receiver) = oneshot::channel();
+		queue.push_back(sender);
+		inner.waiting += 1;
+		Ok(Admission { inner: self.inner.clone(), receiver: Some(receiver), admitted: false })
+	}
+}
+
+impl Inner {
+	/// Hand over the slot of a validated submission to the next waiting one.
+	fn release(&mut self) {
+		while let Some(client) = self.turns.pop_front() {
+			let queue = self.queues.get_mut(&client)
+				.expect("Clients with a turn have a queue; qed");
+			let next = queue.pop_front();
+			if queue.is_empty() {
+				self.queues.remove(&client);
+			} else {
+				self.turns.push_back(client);
+			}
+
+			if let Some(sender) = next {
+				self.waiting -= 1;
+				if sender.send(()).is_ok() {
+					return;
+				}
+			}
+		}
+		self.validating -= 1;
+	}
+}
+
+/// A submission waiting for validation. Resolves to a `Permit` once it may be validated.
+pub struct Admission {
+	inner: Arc<Mutex<Inner>>,
+	receiver: Option<oneshot::Receiver<()>>,
+	admitted: bool,
+}
+
+impl Future for Admission {
+	type Output = Permit;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		if let Some(receiver) = self.receiver.as_mut() {
+			if Pin::new(receiver).poll(cx).is_pending() {
+				return Poll::Pending;
+			}
+			self.receiver = None;
+		}
+		self.admitted = true;
+		Poll::Ready(Permit { inner: self.inner.clone() })
+	}
+}
+
+impl Drop for Admission {
+	fn drop(&mut self) {
+		if self.admitted {
+			return;
+		}
+		let handed_over = match self.receiver.as_mut() {
+			Some(receiver) => {
+				receiver.close();
+				receiver.try_recv().ok().flatten().is_some()
+			},
+			None => true,
+		};
+		if handed_over {
+			self.inner.lock().release();
+		}
+	}
+}
+
+/// Allows a submission to be validated, until dropped.
+pub struct Permit {
+	inner: Arc<Mutex<Inner>>,
+}
+
+impl Drop for Permit {
+	fn drop(&mut self) {
+		self.inner.lock().release();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on, FutureExt};
+
+	#[test]
+	fn clients_are_served_in_turn() {
+		let (alice, bob) = (Some([10, 0, 0, 1].into()), Some([10, 0, 0, 2].into()));
+		let queue = SubmissionQueue::new(1, 2, 3);
+		let validating = block_on(queue.admit(alice).unwrap());
+
+		let mut first = queue.admit(alice).unwrap();
+		let mut second = queue.admit(alice).unwrap();
+		assert_eq!(queue.admit(alice).err(), Some(Overloaded));
+		let mut other = queue.admit(bob).unwrap();
+		assert_eq!(queue.admit(None).err(), Some(Overloaded));
+
+		assert!((&mut first).now_or_never().is_none());
+		drop(validating);
+		let validating = (&mut first).now_or_never().unwrap();
+		assert!((&mut second).now_or_never().is_none());
+
+		// the other client comes before the second submission of the first one
+		drop(validating);
+		assert!((&mut second).now_or_never().is_none());
+		let validating = (&mut other).now_or_never().unwrap();
+
+		// dropping a waiting submission frees its place
+		drop(second);
+		let mut third = queue.admit(alice).unwrap();
+		let _fourth = queue.admit(alice).unwrap();
+		drop(validating);
+		assert!((&mut third).now_or_never().is_some());
+		assert_eq!(queue.inner.lock().validating, 1);
+	}
+}
@@ -74,6 +74,8 @@ impl TestSetup {
 			pool: self.pool.clone(),
 			subscriptions: Subscriptions::new(Arc::new(self.runtime.executor())),
 			keystore: self.keystore.clone(),
+			decoder: Default::default(),
+			submissions: Default::default(),
 		}
 	}
 }
@@ -85,14 +87,51 @@ fn submit_transaction_should_not_cause_error() {
 	let h: H256 = blake2_256(&xt).into();
 
 	assert_matches!(
-		AuthorApi::submit_extrinsic(&p, xt.clone().into()).wait(),
+		AuthorApi::submit_extrinsic(&p, Default::default(), xt.clone().into()).wait(),
 		Ok(h2) if h == h2
 	);
 	assert!(
-		AuthorApi::submit_extrinsic(&p, xt.into()).wait().is_err()
+		AuthorApi::submit_extrinsic(&p, Default::default(), xt.into()).wait().is_err()
 	);
 }
 
+#[test]
+fn submit_transaction_with_bad_proof_should_fall_back_to_pool_error() {
+	let p = TestSetup::default().author();
+	let transfer = Transfer {
+		amount: Default::default(),
+		nonce: 0,
+		from: AccountKeyring::Alice.into(),
+		to: Default::default(),
+	};
+	let signature = AccountKeyring::Bob.sign(&transfer.encode()).into();
+	let xt = Extrinsic::Transfer(transfer, signature).encode();
+
+	// The extrinsics of the test runtime can't be decoded using the metadata.
+	assert_matches!(
+		AuthorApi::submit_extrinsic(&p, Default::default(), xt.into()).wait(),
+		Err(Error::Pool(PoolError::InvalidTransaction(InvalidTransaction::BadProof)))
+	);
+	// The failure to decode the metadata is kept until the runtime changes.
+	assert_matches!(&*p.decoder.lock(), Some((_, None)));
+}
+
+#[test]
+fn invalid_era_should_be_described_in_error_data() {
+	let error = Error::InvalidEra(
+		PoolError::InvalidTransaction(InvalidTransaction::AncientBirthBlock),
+		EraInfo { birth_block: 266, current_block: 300, period: 64, phase: 10 },
+	);
+
+	let error = rpc::Error::from(error);
+
+	assert_eq!(error.message, "Invalid Transaction");
+	assert_eq!(error.data, Some(serde_json::json!({
+		"error": "AncientBirthBlock",
+		"era": { "birthBlock": 266, "currentBlock": 300, "period": 64, "phase": 10 },
+	})));
+}
+
 #[test]
 fn submit_rich_transaction_should_not_cause_error() {
 	let p = TestSetup::default().author();
@@ -100,11 +139,11 @@ fn submit_rich_transaction_should_not_cause_error() {
 	let h: H256 = blake2_256(&xt).into();
 
 	assert_matches!(
-		AuthorApi::submit_extrinsic(&p, xt.clone().into()).wait(),
+		AuthorApi::submit_extrinsic(&p, Default::default(), xt.clone().into()).wait(),
 		Ok(h2) if h == h2
 	);
 	assert!(
-		AuthorApi::submit_extrinsic(&p, xt.into()).wait().is_err()
+		AuthorApi::submit_extrinsic(&p, Default::default(), xt.into()).wait().is_err()
 	);
 }
 
@@ -131,7 +170,7 @@ fn should_watch_extrinsic() {
 		};
 		tx.into_signed_tx()
 	};
-	AuthorApi::submit_extrinsic(&p, replacement.encode().into()).wait().unwrap();
+	AuthorApi::submit_extrinsic(&p, Default::default(), replacement.encode().into()).wait().unwrap();
 	let (res, data) = setup.runtime.block_on(data.into_future()).unwrap();
 	assert_eq!(
 		res,
@@ -165,7 +204,7 @@ fn should_return_pending_extrinsics() {
 	let p = TestSetup::default().author();
 
 	let ex = uxt(AccountKeyring::Alice, 0);
-	AuthorApi::submit_extrinsic(&p, ex.encode().into()).wait().unwrap();
+	AuthorApi::submit_extrinsic(&p, Default::default(), ex.encode().into()).wait().unwrap();
  	assert_matches!(
 		p.pending_extrinsics(),
 		Ok(ref expected) if *expected == vec![Bytes(ex.encode())]
@@ -178,11 +217,11 @@ fn should_remove_extrinsics() {
 	let p = setup.author();
 
 	let ex1 = uxt(AccountKeyring::Alice, 0);
-	p.submit_extrinsic(ex1.encode().into()).wait().unwrap();
+	p.submit_extrinsic(Default::default(), ex1.encode().into()).wait().unwrap();
 	let ex2 = uxt(AccountKeyring::Alice, 1);
-	p.submit_extrinsic(ex2.encode().into()).wait().unwrap();
+	p.submit_extrinsic(Default::default(), ex2.encode().into()).wait().unwrap();
 	let ex3 = uxt(AccountKeyring::Bob, 0);
-	let hash3 = p.submit_extrinsic(ex3.encode().into()).wait().unwrap();
+	let hash3 = p.submit_extrinsic(Default::default(), ex3.encode().into()).wait().unwrap();
 	assert_eq!(setup.pool.status().ready, 3);
 
 	// now remove all 3
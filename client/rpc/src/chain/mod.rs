@@ -18,6 +18,7 @@
 
 mod chain_full;
 mod chain_light;
+mod pinning;
 
 #[cfg(test)]
 mod tests;
@@ -35,7 +36,7 @@ use sc_client::{
 	self, Client, BlockchainEvents,
 	light::{fetcher::Fetcher, blockchain::RemoteBlockchain},
 };
-use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use jsonrpc_pubsub::{typed::Subscriber, PubSubMetadata, Session, SubscriptionId};
 use sp_rpc::{number::NumberOrHex, list::ListOrValue};
 use sp_runtime::{
 	generic::{BlockId, SignedBlock},
@@ -43,8 +44,10 @@ use sp_runtime::{
 };
 
 use self::error::{Result, Error, FutureResult};
+use self::pinning::PinGuard;
 
 pub use sc_rpc_api::chain::*;
+pub use self::pinning::{PinnedBlocks, PinSet, DEFAULT_PIN_BUDGET, DEFAULT_SUBSCRIPTION_PIN_LIMIT};
 
 /// Blockchain backend API
 trait ChainBackend<B, E, Block: BlockT, RA>: Send + Sync + 'static
@@ -161,6 +164,7 @@ pub fn new_full<B, E, Block: BlockT, RA>(
 {
 	Chain {
 		backend: Box::new(self::chain_full::FullChain::new(client, subscriptions)),
+		pins: Arc::new(PinnedBlocks::new(DEFAULT_PIN_BUDGET, DEFAULT_SUBSCRIPTION_PIN_LIMIT)),
 	}
 }
 
@@ -185,12 +189,23 @@ pub fn new_light<B, E, Block: BlockT, RA, F: Fetcher<Block>>(
 			remote_blockchain,
 			fetcher,
 		)),
+		pins: Arc::new(PinnedBlocks::new(DEFAULT_PIN_BUDGET, DEFAULT_SUBSCRIPTION_PIN_LIMIT)),
 	}
 }
 
 /// Chain API with subscriptions support.
 pub struct Chain<B, E, Block: BlockT, RA> {
 	backend: Box<dyn ChainBackend<B, E, Block, RA>>,
+	pins: Arc<PinnedBlocks<Block>>,
+}
+
+impl<B, E, Block: BlockT, RA> Chain<B, E, Block, RA> {
+	/// Limit the number of blocks all pinned head subscriptions together may pin, and the number
+	/// of blocks a single one may pin.
+	pub fn with_pin_budget(mut self, budget: usize, subscription_limit: usize) -> Self {
+		self.pins = Arc::new(PinnedBlocks::new(budget, subscription_limit));
+		self
+	}
 }
 
 impl<B, E, Block, RA> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, SignedBlock<Block>> for Chain<B, E, Block, RA> where
@@ -244,6 +259,29 @@ impl<B, E, Block, RA> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, Sig
 	fn unsubscribe_finalized_heads(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
 		self.backend.unsubscribe_finalized_heads(metadata, id)
 	}
+
+	fn subscribe_pinned_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Block::Header>) {
+		subscribe_pinned_headers(
+			self.backend.client(),
+			self.backend.subscriptions(),
+			&self.pins,
+			metadata.session(),
+			subscriber,
+		)
+	}
+
+	fn unsubscribe_pinned_heads(&self, _metadata: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
+		Ok(self.backend.subscriptions().cancel(id))
+	}
+
+	fn unpin_block(&self, metadata: Self::Metadata, subscription: u64, hash: Block::Hash) -> Result<bool> {
+		Ok(self.pins.unpin(
+			self.backend.client(),
+			metadata.session().as_ref(),
+			&SubscriptionId::Number(subscription),
+			&hash,
+		))
+	}
 }
 
 /// Subscribe to new headers.
@@ -287,6 +325,64 @@ fn subscribe_headers<B, E, Block, RA, F, G, S, ERR>(
 	});
 }
 
+/// Subscribe to new best headers, pinning the state of each of them.
+fn subscribe_pinned_headers<B, E, Block, RA>(
+	client: &Arc<Client<B, E, Block, RA>>,
+	subscriptions: &Subscriptions,
+	pins: &Arc<PinnedBlocks<Block>>,
+	session: Option<Arc<Session>>,
+	subscriber: Subscriber<Block::Header>,
+) where
+	Block: BlockT + 'static,
+	B: sc_client_api::backend::Backend<Block> + Send + Sync + 'static,
+	E: sc_client::CallExecutor<Block> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+{
+	let set: PinSet<Block> = Default::default();
+	// releases the pins once the subscription is over, whether it ended or was cancelled.
+	let guard = PinGuard { client: client.clone(), pins: pins.clone(), set: set.clone() };
+
+	let id = subscriptions.add(subscriber, |sink| {
+		// send current head right at the start.
+		let best_hash = client.chain_info().best_hash;
+		let header = pins.pin(client, &set, best_hash)
+			.and_then(|_| client.header(&BlockId::Hash(best_hash)).map_err(client_err))
+			.and_then(|header| {
+				header.ok_or_else(|| "Best header missing.".to_owned().into())
+			})
+			.map_err(Into::into);
+
+		// send further subscriptions as long as their blocks can be pinned
+		let (stream_client, stream_pins, stream_set) = (client.clone(), pins.clone(), set.clone());
+		let stream = client.import_notification_stream()
+			.filter(|notification| future::ready(notification.is_new_best))
+			.map(|notification| Ok::<_, ()>(notification.header))
+			.compat()
+			.take_while(move |header| {
+				let pinned = stream_pins.pin(&stream_client, &stream_set, header.hash());
+				if let Err(ref e) = pinned {
+					warn!("Ending pinned head subscription: {}", e);
+				}
+				Ok(pinned.is_ok())
+			})
+			.map(|res| Ok(res))
+			.map_err(|e| warn!("Block notification stream error: {:?}", e));
+
+		sink
+			.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+			.send_all(
+				stream::iter_result(vec![Ok(header)])
+					.chain(stream)
+			)
+			.then(move |_| {
+				drop(guard);
+				Ok(())
+			})
+	});
+
+	pins.register(id, session, set);
+}
+
 fn client_err(err: sp_blockchain::Error) -> Error {
 	Error::Client(Box::new(err))
 }
@@ -0,0 +1,178 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Block states pinned by subscriptions.
+
+use std::{collections::HashMap, sync::{Arc, Weak}};
+use parking_lot::Mutex;
+use jsonrpc_pubsub::{Session, SubscriptionId};
+use sc_client::Client;
+use sp_runtime::traits::Block as BlockT;
+
+use super::{client_err, error::{Error, Result}};
+
+/// The default number of blocks all subscriptions together may keep pinned.
+pub const DEFAULT_PIN_BUDGET: usize = 256;
+
+/// The default number of blocks a single subscription may keep pinned.
+pub const DEFAULT_SUBSCRIPTION_PIN_LIMIT: usize = 32;
+
+/// The blocks pinned by a single subscription.
+pub type PinSet<Block> = Arc<Mutex<Vec<<Block as BlockT>::Hash>>>;
+
+/// Blocks pinned against pruning on behalf of subscriptions.
+///
+/// All subscriptions share a global budget and each one has a limit within it, so that they
+/// can not keep an unbounded amount of state around, nor a single one exhaust the budget.
+pub struct PinnedBlocks<Block: BlockT> {
+	budget: usize,
+	subscription_limit: usize,
+	inner: Mutex<Inner<Block>>,
+}
+
+struct Inner<Block: BlockT> {
+	used: usize,
+	subscriptions: HashMap<SubscriptionId, Subscription<Block>>,
+}
+
+/// A subscription pinning blocks.
+struct Subscription<Block: BlockT> {
+	/// The session the subscription was made on, the only one allowed to unpin its blocks.
+	///
+	/// Holding a weak reference keeps the address of the session from being reused by another one.
+	session: Weak<Session>,
+	set: PinSet<Block>,
+}
+
+impl<Block: BlockT> PinnedBlocks<Block> {
+	/// Create a new instance that pins at most `budget` blocks, at most `subscription_limit`
+	/// of them for a single subscription.
+	pub fn new(budget: usize, subscription_limit: usize) -> Self {
+		PinnedBlocks {
+			budget,
+			subscription_limit,
+			inner: Mutex::new(Inner { used: 0, subscriptions: HashMap::new() }),
+		}
+	}
+
+	/// The number of pins currently held by all subscriptions.
+	pub fn used(&self) -> usize {
+		self.inner.lock().used
+	}
+
+	/// Make the pins in `set` releasable by `unpin` for the subscription `id` made on `session`.
+	pub fn register(&self, id: SubscriptionId, session: Option<Arc<Session>>, set: PinSet<Block>) {
+		let session = session.as_ref().map(Arc::downgrade).unwrap_or_default();
+		self.inner.lock().subscriptions.insert(id, Subscription { session, set });
+	}
+
+	/// Pin `hash` on behalf of the subscription owning `set`.
+	pub fn pin<B, E, RA>(
+		&self,
+		client: &Client<B, E, Block, RA>,
+		set: &PinSet<Block>,
+		hash: Block::Hash,
+	) -> Result<()> where
+		B: sc_client_api::backend::Backend<Block>,
+		E: sc_client::CallExecutor<Block>,
+	{
+		let mut inner = self.inner.lock();
+		if inner.used >= self.budget {
+			return Err(Error::Other("Pin budget exhausted".into()));
+		}
+		let mut set = set.lock();
+		if set.len() >= self.subscription_limit {
+			return Err(Error::Other("Pin limit of the subscription reached".into()));
+		}
+
+		client.pin_block(&hash).map_err(client_err)?;
+		inner.used += 1;
+		set.push(hash);
+		Ok(())
+	}
+
+	/// Release one pin the subscription `id` holds on `hash`, on behalf of `session`.
+	///
+	/// Returns `false` if the subscription does not hold a pin on the block, or was not made
+	/// on `session`.
+	pub fn unpin<B, E, RA>(
+		&self,
+		client: &Client<B, E, Block, RA>,
+		session: Option<&Arc<Session>>,
+		id: &SubscriptionId,
+		hash: &Block::Hash,
+	) -> bool where
+		B: sc_client_api::backend::Backend<Block>,
+		E: sc_client::CallExecutor<Block>,
+	{
+		let mut inner = self.inner.lock();
+		let removed = match (inner.subscriptions.get(id), session) {
+			(Some(subscription), Some(session))
+				if subscription.session.upgrade().map_or(false, |owner| Arc::ptr_eq(&owner, session)) =>
+			{
+				let mut set = subscription.set.lock();
+				match set.iter().position(|h| h == hash) {
+					Some(index) => {
+						set.remove(index);
+						true
+					},
+					None => false,
+				}
+			},
+			_ => false,
+		};
+
+		if removed {
+			client.unpin_block(hash);
+			inner.used -= 1;
+		}
+		removed
+	}
+
+	/// Release all pins in `set` and forget about the subscription owning it.
+	pub fn release<B, E, RA>(&self, client: &Client<B, E, Block, RA>, set: &PinSet<Block>) where
+		B: sc_client_api::backend::Backend<Block>,
+		E: sc_client::CallExecutor<Block>,
+	{
+		let mut inner = self.inner.lock();
+		inner.subscriptions.retain(|_, subscription| !Arc::ptr_eq(&subscription.set, set));
+
+		let hashes = std::mem::replace(&mut *set.lock(), Vec::new());
+		for hash in &hashes {
+			client.unpin_block(hash);
+		}
+		inner.used -= hashes.len();
+	}
+}
+
+/// Releases the pins of a subscription when the subscription ends.
+pub(super) struct PinGuard<B, E, Block: BlockT, RA> where
+	B: sc_client_api::backend::Backend<Block>,
+	E: sc_client::CallExecutor<Block>,
+{
+	pub(super) client: Arc<Client<B, E, Block, RA>>,
+	pub(super) pins: Arc<PinnedBlocks<Block>>,
+	pub(super) set: PinSet<Block>,
+}
+
+impl<B, E, Block: BlockT, RA> Drop for PinGuard<B, E, Block, RA> where
+	B: sc_client_api::backend::Backend<Block>,
+	E: sc_client::CallExecutor<Block>,
+{
+	fn drop(&mut self) {
+		self.pins.release(&self.client, &self.set);
+	}
+}
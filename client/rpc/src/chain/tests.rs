@@ -247,3 +247,92 @@ fn should_notify_about_finalized_block() {
 	// no more notifications on this channel
 	assert_eq!(core.block_on(next.into_future()).unwrap().0, None);
 }
+
+#[test]
+fn should_pin_notified_blocks_within_budget() {
+	let mut core = ::tokio::runtime::Runtime::new().unwrap();
+	let remote = core.executor();
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let (_, metadata) = crate::metadata::Metadata::new_test();
+
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote))).with_pin_budget(2, 2);
+	let pins = api.pins.clone();
+
+	api.subscribe_pinned_heads(metadata.clone(), subscriber);
+
+	// assert id assigned
+	assert_eq!(core.block_on(id), Ok(Ok(SubscriptionId::Number(1))));
+
+	// assert initial head sent and pinned.
+	let (notification, next) = core.block_on(transport.into_future()).unwrap();
+	assert!(notification.is_some());
+	assert_eq!(pins.used(), 1);
+
+	assert_eq!(api.unpin_block(metadata.clone(), 1, client.genesis_hash()).unwrap(), true);
+	assert_eq!(api.unpin_block(metadata, 1, client.genesis_hash()).unwrap(), false);
+	assert_eq!(pins.used(), 0);
+
+	for _ in 0..3 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+	}
+
+	// the first two blocks fit into the budget.
+	let (notification, next) = core.block_on(next.into_future()).unwrap();
+	assert!(notification.is_some());
+	let (notification, next) = core.block_on(next.into_future()).unwrap();
+	assert!(notification.is_some());
+	// the third one ends the subscription, which releases its pins.
+	assert_eq!(core.block_on(next.into_future()).unwrap().0, None);
+	assert_eq!(pins.used(), 0);
+}
+
+#[test]
+fn should_limit_the_pins_of_a_subscription() {
+	let mut core = ::tokio::runtime::Runtime::new().unwrap();
+	let remote = core.executor();
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let (_, metadata) = crate::metadata::Metadata::new_test();
+
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote))).with_pin_budget(10, 1);
+	let pins = api.pins.clone();
+
+	api.subscribe_pinned_heads(metadata, subscriber);
+	assert_eq!(core.block_on(id), Ok(Ok(SubscriptionId::Number(1))));
+	let (notification, next) = core.block_on(transport.into_future()).unwrap();
+	assert!(notification.is_some());
+	assert_eq!(pins.used(), 1);
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	// the second block exceeds the limit of the subscription, not the budget.
+	assert_eq!(core.block_on(next.into_future()).unwrap().0, None);
+	assert_eq!(pins.used(), 0);
+}
+
+#[test]
+fn should_only_unpin_blocks_on_the_session_of_the_subscription() {
+	let mut core = ::tokio::runtime::Runtime::new().unwrap();
+	let remote = core.executor();
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let (_, metadata) = crate::metadata::Metadata::new_test();
+	let (_, other_metadata) = crate::metadata::Metadata::new_test();
+
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+	let pins = api.pins.clone();
+
+	api.subscribe_pinned_heads(metadata.clone(), subscriber);
+	assert_eq!(core.block_on(id), Ok(Ok(SubscriptionId::Number(1))));
+	let (notification, _next) = core.block_on(transport.into_future()).unwrap();
+	assert!(notification.is_some());
+
+	assert_eq!(api.unpin_block(other_metadata, 1, client.genesis_hash()).unwrap(), false);
+	assert_eq!(api.unpin_block(Default::default(), 1, client.genesis_hash()).unwrap(), false);
+	assert_eq!(pins.used(), 1);
+	assert_eq!(api.unpin_block(metadata, 1, client.genesis_hash()).unwrap(), true);
+	assert_eq!(pins.used(), 0);
+}
@@ -15,7 +15,7 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! RPC Metadata
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
 use jsonrpc_pubsub::{Session, PubSubMetadata};
 use rpc::futures::sync::mpsc;
@@ -28,6 +28,7 @@ use rpc::futures::sync::mpsc;
 #[derive(Default, Clone)]
 pub struct Metadata {
 	session: Option<Arc<Session>>,
+	client: Option<IpAddr>,
 }
 
 impl rpc::Metadata for Metadata {}
@@ -42,9 +43,15 @@ impl Metadata {
 	pub fn new(transport: mpsc::Sender<String>) -> Self {
 		Metadata {
 			session: Some(Arc::new(Session::new(transport))),
+			client: None,
 		}
 	}
 
+	/// Address of the client, if the request comes from the network.
+	pub fn client(&self) -> Option<IpAddr> {
+		self.client
+	}
+
 	/// Create new `Metadata` for tests.
 	#[cfg(test)]
 	pub fn new_test() -> (mpsc::Receiver<String>, Self) {
@@ -53,6 +60,12 @@ impl Metadata {
 	}
 }
 
+impl sc_rpc_server::ClientMetadata for Metadata {
+	fn set_client(&mut self, client: IpAddr) {
+		self.client = Some(client);
+	}
+}
+
 impl From<mpsc::Sender<String>> for Metadata {
 	fn from(sender: mpsc::Sender<String>) -> Self {
 		Self::new(sender)
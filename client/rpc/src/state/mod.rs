@@ -54,6 +54,21 @@ pub trait StateBackend<B, E, Block: BlockT, RA>: Send + Sync + 'static
 		call_data: Bytes,
 	) -> FutureResult<Bytes>;
 
+	/// Call runtime method at given block and return the result with a proof of execution.
+	fn execution_proof(
+		&self,
+		block: Option<Block::Hash>,
+		method: String,
+		call_data: Bytes,
+	) -> FutureResult<ExecutionProof<Block::Hash>>;
+
+	/// Returns proof of storage entries at a specific block's state.
+	fn read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<ReadProof<Block::Hash>>;
+
 	/// Returns the keys with prefix, leave empty to get all the keys.
 	fn storage_keys(
 		&self,
@@ -61,6 +76,15 @@ pub trait StateBackend<B, E, Block: BlockT, RA>: Send + Sync + 'static
 		prefix: StorageKey,
 	) -> FutureResult<Vec<StorageKey>>;
 
+	/// Returns at most `count` keys with prefix, ordered and only after `start_key` if given.
+	fn storage_keys_paged(
+		&self,
+		block: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>>;
+
 	/// Returns a storage entry at a specific block's state.
 	fn storage(
 		&self,
@@ -134,6 +158,20 @@ pub trait StateBackend<B, E, Block: BlockT, RA>: Send + Sync + 'static
 	/// Get the runtime version.
 	fn runtime_version(&self, block: Option<Block::Hash>) -> FutureResult<RuntimeVersion>;
 
+	/// Get the ranges of blocks of the best chain executed by the same runtime.
+	fn runtime_version_history(&self) -> FutureResult<Vec<RuntimeVersionRange<Block::Hash>>>;
+
+	/// Get a page of the storage items under `prefix` that differ between the states of two
+	/// blocks, after `start_key`.
+	fn state_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<StorageDiffPage>;
+
 	/// Query historical storage entries (by key) starting from a block given as the second parameter.
 	///
 	/// NOTE This first returned result contains the initial state of storage for all keys.
@@ -236,6 +274,19 @@ impl<B, E, Block, RA> StateApi<Block::Hash> for State<B, E, Block, RA>
 		self.backend.call(block, method, data)
 	}
 
+	fn execution_proof(
+		&self,
+		method: String,
+		data: Bytes,
+		block: Option<Block::Hash>,
+	) -> FutureResult<ExecutionProof<Block::Hash>> {
+		self.backend.execution_proof(block, method, data)
+	}
+
+	fn read_proof(&self, keys: Vec<StorageKey>, block: Option<Block::Hash>) -> FutureResult<ReadProof<Block::Hash>> {
+		self.backend.read_proof(block, keys)
+	}
+
 	fn storage_keys(
 		&self,
 		key_prefix: StorageKey,
@@ -244,6 +295,16 @@ impl<B, E, Block, RA> StateApi<Block::Hash> for State<B, E, Block, RA>
 		self.backend.storage_keys(block, key_prefix)
 	}
 
+	fn storage_keys_paged(
+		&self,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		block: Option<Block::Hash>,
+	) -> FutureResult<Vec<StorageKey>> {
+		self.backend.storage_keys_paged(block, prefix, count, start_key)
+	}
+
 	fn storage(&self, key: StorageKey, block: Option<Block::Hash>) -> FutureResult<Option<StorageData>> {
 		self.backend.storage(block, key)
 	}
@@ -330,6 +391,21 @@ impl<B, E, Block, RA> StateApi<Block::Hash> for State<B, E, Block, RA>
 		self.backend.runtime_version(at)
 	}
 
+	fn runtime_version_history(&self) -> FutureResult<Vec<RuntimeVersionRange<Block::Hash>>> {
+		self.backend.runtime_version_history()
+	}
+
+	fn state_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<StorageDiffPage> {
+		self.backend.state_diff(from, to, prefix, count, start_key)
+	}
+
 	fn subscribe_runtime_version(&self, meta: Self::Metadata, subscriber: Subscriber<RuntimeVersion>) {
 		self.backend.subscribe_runtime_version(meta, subscriber);
 	}
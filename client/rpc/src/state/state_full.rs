@@ -36,14 +36,28 @@ use sp_core::{
 	Bytes, storage::{well_known_keys, StorageKey, StorageData, StorageChangeSet, ChildInfo},
 };
 use sp_version::RuntimeVersion;
-use sp_state_machine::ExecutionStrategy;
+use sp_state_machine::{Backend as _, ExecutionStrategy, StorageProof};
 use sp_runtime::{
-	generic::BlockId, traits::{Block as BlockT, NumberFor, SaturatedConversion},
+	generic::BlockId, traits::{Block as BlockT, Hash as HashT, Header as HeaderT, NumberFor, SaturatedConversion},
 };
 
 use sp_api::{Metadata, ProvideRuntimeApi};
 
-use super::{StateBackend, error::{FutureResult, Error, Result}, client_err, child_resolution_error};
+use super::{
+	StateBackend, RuntimeVersionRange, StorageDiff, StorageDiffPage, ReadProof, ExecutionProof,
+	error::{FutureResult, Error, Result}, client_err,
+	child_resolution_error,
+};
+
+/// Maximum number of keys returned by a page of `state_getKeysPaged`.
+const MAX_KEYS_COUNT: usize = 1000;
+
+/// Maximum number of storage items returned by a page of `state_diff`.
+const MAX_DIFF_COUNT: usize = 1000;
+/// Maximum number of keys compared by a page of `state_diff`.
+const MAX_DIFF_KEYS: usize = 100_000;
+/// Maximum number of keys proven by `state_getReadProof`.
+pub(crate) const MAX_PROOF_KEYS: usize = 1000;
 
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
@@ -81,6 +95,29 @@ impl<B, E, Block: BlockT, RA> FullState<B, E, Block, RA>
 		Ok(hash.unwrap_or_else(|| self.client.chain_info().best_hash))
 	}
 
+	/// Returns the hash of the best chain block with the given number.
+	fn canonical_hash(&self, number: u64) -> ClientResult<Block::Hash> {
+		self.client.block_hash(number.saturated_into())?
+			.ok_or_else(|| ClientError::UnknownBlock(format!("Best chain block #{}", number)))
+	}
+
+	/// Describes the runtime executing the genesis block, for chains without recorded upgrades.
+	fn genesis_runtime_version_range(&self) -> ClientResult<RuntimeVersionRange<Block::Hash>>
+		where
+			RA: Send + Sync + 'static,
+			Client<B, E, Block, RA>: ProvideRuntimeApi<Block>,
+			<Client<B, E, Block, RA> as ProvideRuntimeApi<Block>>::Api: Metadata<Block, Error = ClientError>,
+	{
+		let from_block = self.canonical_hash(0)?;
+		let at = BlockId::Hash(from_block);
+		let version = self.client.runtime_version_at(&at)?;
+		// Runtimes are not required to provide metadata.
+		let metadata_hash = self.client.runtime_api().metadata(&at).ok()
+			.map(|metadata| <<Block::Header as HeaderT>::Hashing as HashT>::hash(&metadata));
+
+		Ok(RuntimeVersionRange { from_block, from_number: 0, version, metadata_hash })
+	}
+
 	/// Splits the `query_storage` block range into 'filtered' and 'unfiltered' subranges.
 	/// Blocks that contain changes within filtered subrange could be filtered using changes tries.
 	/// Blocks that contain changes within unfiltered subrange must be filtered manually.
@@ -211,6 +248,84 @@ impl<B, E, Block: BlockT, RA> FullState<B, E, Block, RA>
 		changes.extend(changes_map.into_iter().map(|(_, cs)| cs));
 		Ok(())
 	}
+
+	/// Returns the first key of `state` starting with `prefix`, after `key` if given.
+	fn next_key(state: &B::State, prefix: &[u8], key: Option<&[u8]>) -> ClientResult<Option<Vec<u8>>> {
+		let next = match key {
+			Some(key) => state.next_storage_key(key),
+			None => match state.exists_storage(prefix) {
+				Ok(true) => Ok(Some(prefix.to_vec())),
+				_ => state.next_storage_key(prefix),
+			},
+		}.map_err(|e| ClientError::Backend(format!("{}", e)))?;
+		Ok(next.filter(|next| next.starts_with(prefix)))
+	}
+
+	/// Returns at most `max_diffs` storage items starting with `prefix` and after `start_key` that
+	/// differ between the states of two blocks, ordered by key. Stops after comparing `max_keys`
+	/// keys.
+	pub(crate) fn diff_states(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: &[u8],
+		start_key: Option<&[u8]>,
+		max_diffs: usize,
+		max_keys: usize,
+	) -> ClientResult<StorageDiffPage> {
+		let state_root = |hash| self.client.header(&BlockId::Hash(hash))?
+			.map(|header| *header.state_root())
+			.ok_or_else(|| ClientError::UnknownBlock(format!("{}", hash)));
+		if state_root(from)? == state_root(to)? {
+			return Ok(StorageDiffPage { diffs: Vec::new(), next_key: None });
+		}
+
+		let from_state = self.client.state_at(&BlockId::Hash(from))?;
+		let to_state = self.client.state_at(&BlockId::Hash(to))?;
+		let value = |state: &B::State, key: &[u8]| state.storage(key)
+			.map(|value| value.map(StorageData))
+			.map_err(|e| ClientError::Backend(format!("{}", e)));
+
+		// Walk the keys of both states in order, from the first one after `start_key`.
+		let start_key = start_key.filter(|key| *key >= prefix);
+		let mut diffs = Vec::new();
+		let mut from_key = Self::next_key(&from_state, prefix, start_key)?;
+		let mut to_key = Self::next_key(&to_state, prefix, start_key)?;
+		let mut compared = 0;
+		let mut last_key = start_key.map(|key| key.to_vec());
+		loop {
+			let key = match (&from_key, &to_key) {
+				(None, None) => break,
+				(Some(from_key), Some(to_key)) => std::cmp::min(from_key, to_key).clone(),
+				(Some(key), None) | (None, Some(key)) => key.clone(),
+			};
+			if diffs.len() >= max_diffs || compared >= max_keys {
+				let next_key = Some(StorageKey(last_key.unwrap_or_else(|| prefix.to_vec())));
+				return Ok(StorageDiffPage { diffs, next_key });
+			}
+			compared += 1;
+			last_key = Some(key.clone());
+
+			let old = if from_key.as_ref() == Some(&key) {
+				from_key = Self::next_key(&from_state, prefix, Some(&key))?;
+				value(&from_state, &key)?
+			} else {
+				None
+			};
+			let new = if to_key.as_ref() == Some(&key) {
+				to_key = Self::next_key(&to_state, prefix, Some(&key))?;
+				value(&to_state, &key)?
+			} else {
+				None
+			};
+
+			if old != new {
+				diffs.push(StorageDiff { key: StorageKey(key), old, new });
+			}
+		}
+
+		Ok(StorageDiffPage { diffs, next_key: None })
+	}
 }
 
 impl<B, E, Block, RA> StateBackend<B, E, Block, RA> for FullState<B, E, Block, RA>
@@ -246,6 +361,46 @@ impl<B, E, Block, RA> StateBackend<B, E, Block, RA> for FullState<B, E, Block, R
 				.map_err(client_err)))
 	}
 
+	fn execution_proof(
+		&self,
+		block: Option<Block::Hash>,
+		method: String,
+		call_data: Bytes,
+	) -> FutureResult<ExecutionProof<Block::Hash>> {
+		Box::new(result(
+			self.block_or_best(block)
+				.and_then(|block| {
+					let id = BlockId::Hash(block);
+					let (result, proof) = self.client.execution_proof(&id, &method, &*call_data)?;
+					let code = self.client.storage(&id, &StorageKey(well_known_keys::CODE.to_vec()))?;
+					Ok(ExecutionProof {
+						at: block,
+						result: result.into(),
+						proof: without_code(proof, code),
+					})
+				})
+				.map_err(client_err)))
+	}
+
+	fn read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		if keys.len() > MAX_PROOF_KEYS {
+			return Box::new(result(Err(Error::TooManyKeys { count: keys.len(), max: MAX_PROOF_KEYS })))
+		}
+		Box::new(result(
+			self.block_or_best(block)
+				.and_then(|block|
+					self.client.read_proof(&BlockId::Hash(block), keys.iter().map(|key| &key.0))
+						.map(|proof| ReadProof {
+							at: block,
+							proof: proof.iter_nodes().map(Into::into).collect(),
+						}))
+				.map_err(client_err)))
+	}
+
 	fn storage_keys(
 		&self,
 		block: Option<Block::Hash>,
@@ -257,6 +412,34 @@ impl<B, E, Block, RA> StateBackend<B, E, Block, RA> for FullState<B, E, Block, R
 				.map_err(client_err)))
 	}
 
+	fn storage_keys_paged(
+		&self,
+		block: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>> {
+		let prefix = prefix.map(|prefix| prefix.0).unwrap_or_default();
+		let count = (count as usize).min(MAX_KEYS_COUNT);
+		let call_fn = move || {
+			let state = self.client.state_at(&BlockId::Hash(self.block_or_best(block)?))?;
+			let mut keys = Vec::new();
+			let mut key = start_key.map(|key| key.0).filter(|key| *key >= prefix);
+			while keys.len() < count {
+				match Self::next_key(&state, &prefix, key.as_ref().map(|key| &key[..]))? {
+					Some(next) => {
+						keys.push(StorageKey(next.clone()));
+						key = Some(next);
+					},
+					None => break,
+				}
+			}
+			Ok(keys)
+		};
+
+		Box::new(result(call_fn().map_err(client_err)))
+	}
+
 	fn storage(
 		&self,
 		block: Option<Block::Hash>,
@@ -355,6 +538,51 @@ impl<B, E, Block, RA> StateBackend<B, E, Block, RA> for FullState<B, E, Block, R
 				.map_err(client_err)))
 	}
 
+	fn state_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<StorageDiffPage> {
+		let prefix = prefix.map(|prefix| prefix.0).unwrap_or_default();
+		let start_key = start_key.as_ref().map(|key| &key.0[..]);
+		let max_diffs = (count as usize).max(1).min(MAX_DIFF_COUNT);
+		Box::new(result(
+			self.diff_states(from, to, &prefix, start_key, max_diffs, MAX_DIFF_KEYS).map_err(client_err)
+		))
+	}
+
+	fn runtime_version_history(&self) -> FutureResult<Vec<RuntimeVersionRange<Block::Hash>>> {
+		let call_fn = move || {
+			let best_number = self.client.chain_info().best_number.saturated_into::<u64>();
+			// Upgrades are recorded on all forks, keep the ones of the best chain.
+			let mut ranges = Vec::new();
+			for upgrade in self.client.runtime_upgrades()? {
+				let canonical = upgrade.number <= best_number &&
+					self.canonical_hash(upgrade.number)? == upgrade.block;
+				if canonical {
+					ranges.push(RuntimeVersionRange {
+						from_block: upgrade.block,
+						from_number: upgrade.number,
+						version: upgrade.version,
+						metadata_hash: upgrade.metadata_hash,
+					});
+				}
+			}
+			ranges.sort_by_key(|range| range.from_number);
+
+			if ranges.first().map_or(true, |range| range.from_number != 0) {
+				ranges.insert(0, self.genesis_runtime_version_range()?);
+			}
+
+			Ok(ranges)
+		};
+
+		Box::new(result(call_fn().map_err(client_err)))
+	}
+
 	fn query_storage(
 		&self,
 		from: Block::Hash,
@@ -492,6 +720,21 @@ impl<B, E, Block, RA> StateBackend<B, E, Block, RA> for FullState<B, E, Block, R
 	}
 }
 
+/// The nodes of an execution `proof`, leaving out the trie leaf holding the runtime `code`.
+///
+/// Every call reads the code, which would make up most of the proof. The verifier re-executing
+/// the call needs the code anyway, and can get it with a read proof of `:code` once per runtime.
+fn without_code(proof: StorageProof, code: Option<StorageData>) -> Vec<Bytes> {
+	proof.iter_nodes()
+		.filter(|node| match &code {
+			// leaves end with their value.
+			Some(code) if !code.0.is_empty() => !node.ends_with(&code.0),
+			_ => true,
+		})
+		.map(Into::into)
+		.collect()
+}
+
 /// Splits passed range into two subranges where:
 /// - first range has at least one element in it;
 /// - second range (optionally) starts at given `middle` element.
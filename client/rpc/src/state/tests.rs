@@ -15,7 +15,7 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::*;
-use super::state_full::split_range;
+use super::state_full::{split_range, MAX_PROOF_KEYS};
 use self::error::Error;
 
 use std::sync::Arc;
@@ -75,6 +75,36 @@ fn should_return_storage() {
 
 }
 
+#[test]
+fn should_return_storage_keys_paged() {
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let client = TestClientBuilder::new()
+		.add_extra_storage(b"paged:a".to_vec(), vec![1])
+		.add_extra_storage(b"paged:b".to_vec(), vec![2])
+		.add_extra_storage(b"paged:c".to_vec(), vec![3])
+		.build();
+	let api = new_full(Arc::new(client), Subscriptions::new(Arc::new(core.executor())));
+
+	let keys = |keys: &[&str]| keys.iter().map(|key| StorageKey(key.as_bytes().to_vec())).collect::<Vec<_>>();
+	let prefix = Some(StorageKey(b"paged:".to_vec()));
+	assert_eq!(
+		api.storage_keys_paged(prefix.clone(), 10, None, None).wait().unwrap(),
+		keys(&["paged:a", "paged:b", "paged:c"]),
+	);
+	assert_eq!(
+		api.storage_keys_paged(prefix.clone(), 2, None, None).wait().unwrap(),
+		keys(&["paged:a", "paged:b"]),
+	);
+	assert_eq!(
+		api.storage_keys_paged(prefix.clone(), 2, Some(StorageKey(b"paged:b".to_vec())), None).wait().unwrap(),
+		keys(&["paged:c"]),
+	);
+	assert_eq!(
+		api.storage_keys_paged(prefix, 2, Some(StorageKey(b"paged:c".to_vec())), None).wait().unwrap(),
+		keys(&[]),
+	);
+}
+
 #[test]
 fn should_return_child_storage() {
 	let (child_info, child_type) = CHILD_INFO.info();
@@ -136,6 +166,71 @@ fn should_call_contract() {
 	)
 }
 
+#[test]
+fn should_return_read_proof() {
+	const KEY: &[u8] = b":mock";
+	const VALUE: &[u8] = b"hello world";
+
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let client = Arc::new(TestClientBuilder::new()
+		.add_extra_storage(KEY.to_vec(), VALUE.to_vec())
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let state_root = client.header(&sp_runtime::generic::BlockId::Hash(genesis_hash))
+		.unwrap().unwrap().state_root;
+	let api = new_full(client, Subscriptions::new(Arc::new(core.executor())));
+
+	let keys = vec![StorageKey(KEY.to_vec()), StorageKey(b":missing".to_vec())];
+	let proof = api.read_proof(keys.clone(), None).wait().unwrap();
+	assert_eq!(proof.at, genesis_hash);
+
+	let proof = sp_state_machine::StorageProof::new(proof.proof.into_iter().map(|node| node.0).collect());
+	let values = sp_state_machine::read_proof_check::<sp_core::Blake2Hasher, _>(
+		state_root,
+		proof,
+		keys.iter().map(|key| &key.0),
+	).unwrap();
+	assert_eq!(values.get(KEY), Some(&Some(VALUE.to_vec())));
+	assert_eq!(values.get(&b":missing"[..]), Some(&None));
+
+	let keys = (0..=MAX_PROOF_KEYS as u32).map(|i| StorageKey(i.to_le_bytes().to_vec())).collect::<Vec<_>>();
+	assert_matches!(
+		api.read_proof(keys, None).wait(),
+		Err(Error::TooManyKeys { count, max: MAX_PROOF_KEYS }) if count == MAX_PROOF_KEYS + 1
+	);
+}
+
+#[test]
+fn should_return_execution_proof() {
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let state_root = client.header(&sp_runtime::generic::BlockId::Hash(genesis_hash))
+		.unwrap().unwrap().state_root;
+	let api = new_full(client, Subscriptions::new(Arc::new(core.executor())));
+
+	let proof = api.execution_proof("Core_version".into(), Bytes(vec![]), None).wait().unwrap();
+	assert_eq!(proof.at, genesis_hash);
+	let version: RuntimeVersion = codec::Decode::decode(&mut &proof.result[..]).unwrap();
+	assert_eq!(&*version.spec_name, "test");
+	assert!(!proof.proof.is_empty());
+
+	// the code is left out of the proof, the verifier reads it separately.
+	let code = runtime::WASM_BINARY;
+	assert!(proof.proof.iter().all(|node| !node.0.ends_with(code)));
+	let code_proof = api.read_proof(vec![StorageKey(well_known_keys::CODE.to_vec())], None).wait().unwrap();
+	let nodes = proof.proof.into_iter().chain(code_proof.proof).map(|node| node.0).collect();
+	let result = sp_state_machine::execution_proof_check::<sp_core::Blake2Hasher, _>(
+		state_root,
+		sp_state_machine::StorageProof::new(nodes),
+		&mut Default::default(),
+		&NativeExecutor::<LocalExecutor>::new(WasmExecutionMethod::Interpreted, None),
+		"Core_version",
+		&[],
+	).unwrap();
+	assert_eq!(Bytes(result), proof.result);
+}
+
 #[test]
 fn should_notify_about_storage_changes() {
 	let mut core = tokio::runtime::Runtime::new().unwrap();
@@ -400,9 +495,10 @@ fn should_return_runtime_version() {
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
 		\"specVersion\":1,\"implVersion\":1,\"apis\":[[\"0xdf6acb689907609b\",2],\
-		[\"0x37e397fc7c91f5e4\",1],[\"0xd2bc9897eed08f15\",1],[\"0x40fe3ad401f8959a\",4],\
-		[\"0xc6e9a76309f39b09\",1],[\"0xdd718d5cc53262d4\",1],[\"0xcbca25e39f142387\",1],\
-		[\"0xf78b278be53f454c\",2],[\"0xab3c0572291feb8b\",1],[\"0xbc9d89904f5b923f\",1]]}";
+		[\"0x37e397fc7c91f5e4\",1],[\"0xd2bc9897eed08f15\",1],[\"0x40fe3ad401f8959a\",5],\
+		[\"0xc6e9a76309f39b09\",1],[\"0xdd718d5cc53262d4\",1],[\"0xcbca25e39f142387\",2],\
+		[\"0xf78b278be53f454c\",2],[\"0xab3c0572291feb8b\",1],[\"0xbc9d89904f5b923f\",1],\
+		[\"0xc615eabe4ebc3d45\",1]]}";
 
 	let runtime_version = api.runtime_version(None.into()).wait().unwrap();
 	let serialized = serde_json::to_string(&runtime_version).unwrap();
@@ -412,6 +508,132 @@ fn should_return_runtime_version() {
 	assert_eq!(deserialized, runtime_version);
 }
 
+#[test]
+fn should_return_runtime_version_history() {
+	let core = tokio::runtime::Runtime::new().unwrap();
+	// Executing a block that carries the whole runtime needs more than the default heap.
+	let mut client = Arc::new(TestClientBuilder::new()
+		.add_extra_storage(well_known_keys::HEAP_PAGES.to_vec(), 256u64.to_le_bytes().to_vec())
+		.build());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(core.executor())));
+
+	fn import_block(client: &mut Arc<TestClient>, new_code: Option<Vec<u8>>) -> H256 {
+		let mut builder = client.new_block(Default::default()).unwrap();
+		if let Some(code) = new_code {
+			builder.push_storage_change(well_known_keys::CODE.to_vec(), Some(code)).unwrap();
+		}
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		client.import(BlockOrigin::Own, block).unwrap();
+		hash
+	}
+
+	let genesis_hash = client.genesis_hash();
+	let version = api.runtime_version(None).wait().unwrap();
+	let range = |from_block, from_number| RuntimeVersionRange {
+		from_block,
+		from_number,
+		version: version.clone(),
+		metadata_hash: None,
+	};
+
+	import_block(&mut client, None);
+	assert_eq!(api.runtime_version_history().wait().unwrap(), vec![range(genesis_hash, 0)]);
+
+	// Same runtime with an empty custom section appended.
+	let mut code = runtime::WASM_BINARY.to_vec();
+	code.extend_from_slice(&[0, 1, 0]);
+	import_block(&mut client, Some(code));
+	// The new code is only executed by the next block.
+	assert_eq!(api.runtime_version_history().wait().unwrap(), vec![range(genesis_hash, 0)]);
+	let upgraded_hash = import_block(&mut client, None);
+	for _ in 0..3 {
+		import_block(&mut client, None);
+	}
+
+	let expected = vec![range(genesis_hash, 0), range(upgraded_hash, 3)];
+	assert_eq!(api.runtime_version_history().wait().unwrap(), expected);
+
+	// Upgrading back to the original code is a new range too.
+	import_block(&mut client, Some(runtime::WASM_BINARY.to_vec()));
+	let downgraded_hash = import_block(&mut client, None);
+	let expected = vec![range(genesis_hash, 0), range(upgraded_hash, 3), range(downgraded_hash, 8)];
+	assert_eq!(api.runtime_version_history().wait().unwrap(), expected);
+	// The ranges are recorded at import and don't need the states of the blocks.
+	assert_eq!(client.runtime_upgrades().unwrap().len(), 3);
+}
+
+#[test]
+fn should_return_state_diff() {
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(core.executor())));
+
+	let mut add_block = |changes: Vec<(&[u8], Option<&[u8]>)>| {
+		let mut builder = client.new_block(Default::default()).unwrap();
+		for (key, value) in changes {
+			builder.push_storage_change(key.to_vec(), value.map(|v| v.to_vec())).unwrap();
+		}
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		client.import(BlockOrigin::Own, block).unwrap();
+		hash
+	};
+	let block1_hash = add_block(vec![(b"diff:a", Some(b"1")), (b"diff:b", Some(b"2"))]);
+	let block2_hash = add_block(vec![(b"diff:a", Some(b"3")), (b"diff:b", None), (b"diff:c", Some(b"4"))]);
+
+	let diff = |key: &[u8], old: Option<&[u8]>, new: Option<&[u8]>| StorageDiff {
+		key: StorageKey(key.to_vec()),
+		old: old.map(|v| StorageData(v.to_vec())),
+		new: new.map(|v| StorageData(v.to_vec())),
+	};
+	let expected = vec![
+		diff(b"diff:a", Some(b"1"), Some(b"3")),
+		diff(b"diff:b", Some(b"2"), None),
+		diff(b"diff:c", None, Some(b"4")),
+	];
+
+	let page = |diffs: &[StorageDiff], next_key: Option<&[u8]>| StorageDiffPage {
+		diffs: diffs.to_vec(),
+		next_key: next_key.map(|key| StorageKey(key.to_vec())),
+	};
+
+	let prefix = Some(StorageKey(b"diff:".to_vec()));
+	assert_eq!(
+		api.state_diff(block1_hash, block2_hash, prefix.clone(), 10, None).wait().unwrap(),
+		page(&expected, None),
+	);
+	assert_eq!(
+		api.state_diff(block2_hash, block2_hash, prefix.clone(), 10, None).wait().unwrap(),
+		page(&[], None),
+	);
+
+	// The diff is paged.
+	assert_eq!(
+		api.state_diff(block1_hash, block2_hash, prefix.clone(), 2, None).wait().unwrap(),
+		page(&expected[..2], Some(b"diff:b")),
+	);
+	let start_key = Some(StorageKey(b"diff:b".to_vec()));
+	assert_eq!(
+		api.state_diff(block1_hash, block2_hash, prefix, 2, start_key).wait().unwrap(),
+		page(&expected[2..], None),
+	);
+
+	// The test runtime doesn't change any other keys.
+	assert_eq!(
+		api.state_diff(block1_hash, block2_hash, None, 10, None).wait().unwrap(),
+		page(&expected, None),
+	);
+
+	// A page ends once enough keys are compared, even if they don't differ.
+	let subscriptions = Subscriptions::new(Arc::new(core.executor()));
+	let state = super::state_full::FullState::new(client.clone(), subscriptions);
+	assert_eq!(
+		state.diff_states(block1_hash, block2_hash, b"diff:", None, 10, 1).unwrap(),
+		page(&expected[..1], Some(b"diff:a")),
+	);
+}
+
 #[test]
 fn should_notify_on_runtime_version_initially() {
 	let mut core = tokio::runtime::Runtime::new().unwrap();
@@ -27,7 +27,7 @@ use sp_runtime::traits::{self, Header as HeaderT};
 use self::error::Result;
 
 pub use sc_rpc_api::system::*;
-pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole, NodeIdentity};
 pub use self::gen_client::Client as SystemClient;
 
 /// System API implementation
@@ -44,12 +44,21 @@ pub enum Request<B: traits::Block> {
 	Peers(oneshot::Sender<Vec<PeerInfo<B::Hash, <B::Header as HeaderT>::Number>>>),
 	/// Must return the state of the network.
 	NetworkState(oneshot::Sender<rpc::Value>),
+	/// Must return the addresses the node is listening on.
+	LocalListenAddresses(oneshot::Sender<Vec<String>>),
+	/// Must return the NAT status of the node.
+	NatStatus(oneshot::Sender<rpc::Value>),
 	/// Must return any potential parse error.
 	NetworkAddReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return any potential parse error.
 	NetworkRemoveReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return the node role.
-	NodeRoles(oneshot::Sender<Vec<NodeRole>>)
+	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
+	/// Must return the identity of the node, with the given challenge signed with its key.
+	///
+	/// The challenge must be prefixed with a fixed context before being signed, so that the
+	/// signature can't be reused in other protocols.
+	NodeIdentity(Vec<u8>, oneshot::Sender<NodeIdentity>),
 }
 
 impl<B: traits::Block> System<B> {
@@ -103,6 +112,18 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		Receiver(Compat::new(rx))
 	}
 
+	fn system_local_listen_addresses(&self) -> Receiver<Vec<String>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::LocalListenAddresses(tx));
+		Receiver(Compat::new(rx))
+	}
+
+	fn system_nat_status(&self) -> Receiver<rpc::Value> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NatStatus(tx));
+		Receiver(Compat::new(rx))
+	}
+
 	fn system_add_reserved_peer(&self, peer: String)
 		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
 	{
@@ -136,4 +157,10 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
 		Receiver(Compat::new(rx))
 	}
+
+	fn system_node_identity(&self, challenge: Option<sp_core::Bytes>) -> Receiver<NodeIdentity> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NodeIdentity(challenge.unwrap_or_default().0, tx));
+		Receiver(Compat::new(rx))
+	}
 }
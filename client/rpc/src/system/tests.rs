@@ -18,6 +18,7 @@ use super::*;
 
 use sc_network::{self, PeerId};
 use sc_network::config::Roles;
+use sc_network::config::identity::{Keypair, PublicKey};
 use substrate_test_runtime_client::runtime::Block;
 use assert_matches::assert_matches;
 use futures::{prelude::*, channel::mpsc};
@@ -44,6 +45,7 @@ impl Default for Status {
 fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 	let status = sync.into().unwrap_or_default();
 	let should_have_peers = !status.is_dev;
+	let node_key = Keypair::generate_ed25519();
 	let (tx, rx) = mpsc::unbounded();
 	thread::spawn(move || {
 		futures::executor::block_on(rx.for_each(move |request| {
@@ -78,6 +80,23 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 						average_download_per_sec: 0,
 						average_upload_per_sec: 0,
 						peerset: serde_json::Value::Null,
+						bootnodes: Default::default(),
+					}).unwrap());
+				},
+				Request::LocalListenAddresses(sender) => {
+					let _ = sender.send(vec![
+						format!("/ip4/198.51.100.19/tcp/30333/p2p/{}", status.peer_id.to_base58()),
+					]);
+				},
+				Request::NatStatus(sender) => {
+					let _ = sender.send(serde_json::to_value(&sc_network::NatStatus {
+						listened_addresses: Default::default(),
+						external_addresses: Default::default(),
+						observed_addresses: vec![sc_network::NatStatusObservedAddress {
+							address: "/ip4/203.0.113.7/tcp/41234".parse().unwrap(),
+							reporters: status.peers,
+						}],
+						port_mapping: sc_network::PortMappingStatus::NotSupported,
 					}).unwrap());
 				},
 				Request::NetworkAddReservedPeer(peer, sender) => {
@@ -95,6 +114,14 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				}
+				Request::NodeIdentity(challenge, sender) => {
+					let message = [sc_network::NODE_IDENTITY_CONTEXT, &challenge].concat();
+					let _ = sender.send(NodeIdentity {
+						peer_id: node_key.public().into_peer_id().to_base58(),
+						public_key: node_key.public().into_protobuf_encoding().into(),
+						signature: node_key.sign(&message).unwrap().into(),
+					});
+				}
 			};
 
 			future::ready(())
@@ -233,10 +260,34 @@ fn system_network_state() {
 			average_download_per_sec: 0,
 			average_upload_per_sec: 0,
 			peerset: serde_json::Value::Null,
+			bootnodes: Default::default(),
 		}
 	);
 }
 
+#[test]
+fn system_local_listen_addresses() {
+	let peer_id = PeerId::random();
+	assert_eq!(
+		wait_receiver(api(Status { peer_id: peer_id.clone(), ..Default::default() })
+			.system_local_listen_addresses()),
+		vec![format!("/ip4/198.51.100.19/tcp/30333/p2p/{}", peer_id.to_base58())]
+	);
+}
+
+#[test]
+fn system_nat_status() {
+	let res = wait_receiver(api(Status { peers: 3, ..Default::default() }).system_nat_status());
+	assert_eq!(res["portMapping"], "notSupported");
+	assert_eq!(
+		serde_json::from_value::<sc_network::NatStatus>(res).unwrap().observed_addresses,
+		vec![sc_network::NatStatusObservedAddress {
+			address: "/ip4/203.0.113.7/tcp/41234".parse().unwrap(),
+			reporters: 3,
+		}]
+	);
+}
+
 #[test]
 fn system_node_roles() {
 	assert_eq!(
@@ -245,6 +296,16 @@ fn system_node_roles() {
 	);
 }
 
+#[test]
+fn system_node_identity() {
+	let identity = wait_receiver(api(None).system_node_identity(Some(b"challenge".to_vec().into())));
+
+	let public_key = PublicKey::from_protobuf_encoding(&identity.public_key).unwrap();
+	assert_eq!(identity.peer_id, public_key.clone().into_peer_id().to_base58());
+	assert!(public_key.verify(b"substrate-node-identity:challenge", &identity.signature));
+	assert!(!public_key.verify(b"challenge", &identity.signature));
+}
+
 #[test]
 fn system_network_add_reserved() {
 	let good_peer_id = "/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
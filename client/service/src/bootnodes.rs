@@ -0,0 +1,133 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Periodically fetches the signed list of bootnodes advertised in the chain spec and adds them
+//! to the network.
+
+use std::{sync::Weak, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use futures01::{Future, Stream};
+use log::{info, warn};
+use sc_chain_spec::BootNodesSource;
+use sc_network::{NetworkService, ExHashT, specialization::NetworkSpecialization};
+use sp_runtime::traits::Block as BlockT;
+use tokio01::util::FutureExt as _;
+
+/// Interval at which the list is fetched again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Interval at which the thread checks whether the network is still running.
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Time after which a fetch is abandoned.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum size of the list, in bytes.
+const MAX_RESPONSE_SIZE: usize = 64 * 1024;
+
+/// Starts a background thread that fetches the bootnodes of the chain with the given genesis
+/// hash from `source` at startup and then every hour. The thread stops once `network` is dropped.
+pub fn start_fetcher<B, S, H>(
+	source: BootNodesSource,
+	genesis_hash: B::Hash,
+	network: Weak<NetworkService<B, S, H>>,
+) where
+		B: BlockT + 'static,
+		S: NetworkSpecialization<B>,
+		H: ExHashT,
+{
+	let spawned = thread::Builder::new()
+		.name("bootnodes-fetcher".into())
+		.spawn(move || loop {
+			match fetch(&source, genesis_hash.as_ref()) {
+				Ok(boot_nodes) => {
+					let network = match network.upgrade() {
+						Some(network) => network,
+						None => return,
+					};
+					info!("Fetched {} bootnodes from {}", boot_nodes.len(), source.url);
+					for boot_node in boot_nodes {
+						if let Err(err) = network.add_bootnode(boot_node.clone()) {
+							warn!("Not a valid bootnode address: {} ({})", boot_node, err);
+						}
+					}
+				},
+				Err(err) => warn!("Failed to fetch bootnodes from {}: {}", source.url, err),
+			}
+
+			let refresh_at = Instant::now() + REFRESH_INTERVAL;
+			while Instant::now() < refresh_at {
+				thread::sleep(SHUTDOWN_CHECK_INTERVAL);
+				if network.upgrade().is_none() {
+					return
+				}
+			}
+		});
+
+	if let Err(err) = spawned {
+		warn!("Failed to start fetching bootnodes: {}", err);
+	}
+}
+
+/// Downloads and verifies the list of bootnodes.
+fn fetch(source: &BootNodesSource, genesis_hash: &[u8]) -> Result<Vec<String>, String> {
+	let uri: hyper::Uri = source.url.parse().map_err(|e| format!("Invalid URL: {}", e))?;
+	if uri.scheme_str() != Some("https") {
+		return Err("The bootnodes list must be served over HTTPS".into())
+	}
+
+	let client = hyper::Client::builder()
+		.build::<_, hyper::Body>(hyper_rustls::HttpsConnector::new(1));
+	let body = client.get(uri)
+		.map_err(|e| e.to_string())
+		.and_then(|response| {
+			let status = response.status();
+			futures01::future::result(if status.is_success() {
+				Ok(response.into_body())
+			} else {
+				Err(format!("Unexpected HTTP status {}", status))
+			})
+		})
+		.and_then(|body| body
+			.map_err(|e| e.to_string())
+			.fold(Vec::new(), |mut body, chunk| {
+				body.extend_from_slice(&chunk);
+				if body.len() > MAX_RESPONSE_SIZE {
+					Err(format!("Response is larger than {} bytes", MAX_RESPONSE_SIZE))
+				} else {
+					Ok(body)
+				}
+			})
+		)
+		.timeout(FETCH_TIMEOUT)
+		.map_err(|e| e.into_inner().unwrap_or_else(|| "Timeout".into()));
+
+	let mut runtime = tokio01::runtime::current_thread::Runtime::new().map_err(|e| e.to_string())?;
+	let body = runtime.block_on(body)?;
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?;
+	source.verify(&body, genesis_hash, now.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rejects_plain_http() {
+		let source = BootNodesSource {
+			url: "http://127.0.0.1:1/bootnodes.json".into(),
+			public_key: Default::default(),
+		};
+		assert_eq!(fetch(&source, &[0; 32]), Err("The bootnodes list must be served over HTTPS".into()));
+	}
+}
@@ -27,12 +27,14 @@ use sc_client_api::{
 use sc_client::Client;
 use sc_chain_spec::{RuntimeGenesis, Extension};
 use sp_consensus::import_queue::ImportQueue;
+use sp_consensus::block_validation::{BlockAnnounceValidator, DefaultBlockAnnounceValidator};
 use futures::{
 	Future, FutureExt, StreamExt,
 	channel::mpsc,
 	future::{select, ready}
 };
 use sc_keystore::{Store as Keystore};
+use sc_rpc::author::SubmissionQueue;
 use log::{info, warn, error};
 use sc_network::{FinalityProofProvider, OnDemand, NetworkService, NetworkStateInfo};
 use sc_network::{config::BoxFinalityProofRequestBuilder, specialization::NetworkSpecialization};
@@ -78,6 +80,7 @@ pub struct ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImp
 	pub (crate) client: Arc<TCl>,
 	backend: Arc<Backend>,
 	keystore: Arc<RwLock<Keystore>>,
+	submissions: SubmissionQueue,
 	fetcher: Option<TFchr>,
 	select_chain: Option<TSc>,
 	pub (crate) import_queue: TImpQu,
@@ -87,6 +90,9 @@ pub struct ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImp
 	transaction_pool: Arc<TExPool>,
 	rpc_extensions: TRpc,
 	remote_backend: Option<Arc<dyn RemoteBlockchain<TBl>>>,
+	block_announce_validator_builder: Option<Box<
+		dyn FnOnce(Arc<TCl>) -> Box<dyn BlockAnnounceValidator<TBl> + Send> + Send
+	>>,
 	marker: PhantomData<(TBl, TRtApi)>,
 }
 
@@ -154,6 +160,59 @@ pub fn new_full_client<TBl, TRtApi, TExecDisp, TCfg, TGen, TCSExt>(
 	new_full_parts(config).map(|parts| parts.0)
 }
 
+/// Returns the fork blocks of the chain spec, together with its light checkpoint if `light`.
+fn fork_blocks<TBl, TCfg, TGen, TCSExt>(
+	config: &Configuration<TCfg, TGen, TCSExt>,
+	light: bool,
+) -> sc_client::ForkBlocks<TBl> where
+	TBl: BlockT,
+	TCSExt: Extension,
+{
+	let extensions = config.chain_spec.extensions();
+	let mut fork_blocks = extensions
+		.get::<sc_client::ForkBlocks<TBl>>()
+		.cloned()
+		.unwrap_or_default();
+
+	let checkpoint = extensions
+		.get::<sc_client::LightCheckpoint<TBl>>()
+		.cloned()
+		.unwrap_or_default();
+
+	if light {
+		if let Some(checkpoint) = checkpoint {
+			fork_blocks.get_or_insert_with(Default::default).push(checkpoint);
+		}
+	}
+
+	fork_blocks
+}
+
+/// Returns the known bad blocks of the chain spec, together with the ones of the configuration.
+fn bad_blocks<TBl, TCfg, TGen, TCSExt>(
+	config: &Configuration<TCfg, TGen, TCSExt>,
+) -> Result<sc_client::BadBlocks<TBl>, Error> where
+	TBl: BlockT,
+	TCSExt: Extension,
+{
+	let mut bad_blocks = config.chain_spec
+		.extensions()
+		.get::<sc_client::BadBlocks<TBl>>()
+		.cloned()
+		.unwrap_or_default();
+
+	if !config.bad_blocks.is_empty() {
+		let bad_blocks = bad_blocks.get_or_insert_with(Default::default);
+		for hash in &config.bad_blocks {
+			let parsed = serde_json::from_value(serde_json::Value::String(hash.clone()))
+				.map_err(|_| Error::Other(format!("Invalid bad block hash: {}", hash)))?;
+			bad_blocks.insert(parsed);
+		}
+	}
+
+	Ok(bad_blocks)
+}
+
 fn new_full_parts<TBl, TRtApi, TExecDisp, TCfg, TGen, TCSExt>(
 	config: &Configuration<TCfg, TGen, TCSExt>,
 ) -> Result<TFullParts<TBl, TRtApi, TExecDisp>,	Error> where
@@ -176,17 +235,8 @@ fn new_full_parts<TBl, TRtApi, TExecDisp, TCfg, TGen, TCSExt>(
 		config.default_heap_pages,
 	);
 
-	let fork_blocks = config.chain_spec
-		.extensions()
-		.get::<sc_client::ForkBlocks<TBl>>()
-		.cloned()
-		.unwrap_or_default();
-
-	let bad_blocks = config.chain_spec
-		.extensions()
-		.get::<sc_client::BadBlocks<TBl>>()
-		.cloned()
-		.unwrap_or_default();
+	let fork_blocks = fork_blocks::<TBl, _, _, _>(config, false);
+	let bad_blocks = bad_blocks::<TBl, _, _, _>(config)?;
 
 	let (client, backend) = {
 		let db_config = sc_client_db::DatabaseSettings {
@@ -203,6 +253,8 @@ fn new_full_parts<TBl, TRtApi, TExecDisp, TCfg, TGen, TCSExt>(
 				DatabaseConfig::Custom(db) =>
 					sc_client_db::DatabaseSettingsSrc::Custom(db.clone()),
 			},
+			memory_budget: config.memory_budget,
+			compress_blocks: config.compress_blocks,
 		};
 
 		let extensions = sc_client_api::execution_extensions::ExecutionExtensions::new(
@@ -249,11 +301,14 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 
 		let client = Arc::new(client);
 
+		crate::tasks::setup_import_thread(&config.consensus_threads);
+
 		Ok(ServiceBuilder {
 			config,
 			client,
 			backend,
 			keystore,
+			submissions: Default::default(),
 			fetcher: None,
 			select_chain: None,
 			import_queue: (),
@@ -263,6 +318,7 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 			transaction_pool: Arc::new(()),
 			rpc_extensions: Default::default(),
 			remote_backend: None,
+			block_announce_validator_builder: None,
 			marker: PhantomData,
 		})
 	}
@@ -316,6 +372,8 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 					DatabaseConfig::Custom(db) =>
 						sc_client_db::DatabaseSettingsSrc::Custom(db.clone()),
 				},
+				memory_budget: config.memory_budget,
+				compress_blocks: config.compress_blocks,
 			};
 			sc_client_db::light::LightStorage::new(db_settings)?
 		};
@@ -329,10 +387,15 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 		let fetcher = Arc::new(sc_network::OnDemand::new(fetch_checker));
 		let backend = sc_client::light::new_light_backend(light_blockchain);
 		let remote_blockchain = backend.remote_blockchain();
+		let fork_blocks = fork_blocks::<TBl, _, _, _>(&config, true);
+		let bad_blocks = bad_blocks::<TBl, _, _, _>(&config)?;
+
 		let client = Arc::new(sc_client::light::new_light(
 			backend.clone(),
 			&config.chain_spec,
 			executor,
+			fork_blocks,
+			bad_blocks,
 		)?);
 
 		Ok(ServiceBuilder {
@@ -340,6 +403,7 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 			client,
 			backend,
 			keystore,
+			submissions: Default::default(),
 			fetcher: Some(fetcher.clone()),
 			select_chain: None,
 			import_queue: (),
@@ -349,6 +413,7 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 			transaction_pool: Arc::new(()),
 			rpc_extensions: Default::default(),
 			remote_backend: Some(remote_blockchain),
+			block_announce_validator_builder: None,
 			marker: PhantomData,
 		})
 	}
@@ -373,6 +438,17 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 		self.select_chain.as_ref()
 	}
 
+	/// Returns the keystore that was opened for this builder.
+	pub fn keystore(&self) -> Arc<RwLock<Keystore>> {
+		self.keystore.clone()
+	}
+
+	/// Returns the queue of the transactions submitted over RPC waiting for validation, for the
+	/// RPC extensions submitting transactions to share it with the author RPC.
+	pub fn submission_queue(&self) -> SubmissionQueue {
+		self.submissions.clone()
+	}
+
 	/// Defines which head-of-chain strategy to use.
 	pub fn with_opt_select_chain<USc>(
 		self,
@@ -388,6 +464,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			client: self.client,
 			backend: self.backend,
 			keystore: self.keystore,
+			submissions: self.submissions,
 			fetcher: self.fetcher,
 			select_chain,
 			import_queue: self.import_queue,
@@ -397,6 +474,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			transaction_pool: self.transaction_pool,
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -430,6 +508,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			client: self.client,
 			backend: self.backend,
 			keystore: self.keystore,
+			submissions: self.submissions,
 			fetcher: self.fetcher,
 			select_chain: self.select_chain,
 			import_queue,
@@ -439,6 +518,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			transaction_pool: self.transaction_pool,
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -456,6 +536,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			client: self.client,
 			backend: self.backend,
 			keystore: self.keystore,
+			submissions: self.submissions,
 			fetcher: self.fetcher,
 			select_chain: self.select_chain,
 			import_queue: self.import_queue,
@@ -465,6 +546,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			transaction_pool: self.transaction_pool,
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -497,6 +579,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			client: self.client,
 			backend: self.backend,
 			keystore: self.keystore,
+			submissions: self.submissions,
 			fetcher: self.fetcher,
 			select_chain: self.select_chain,
 			import_queue: self.import_queue,
@@ -506,6 +589,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			transaction_pool: self.transaction_pool,
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -562,6 +646,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			client: self.client,
 			backend: self.backend,
 			keystore: self.keystore,
+			submissions: self.submissions,
 			fetcher: self.fetcher,
 			select_chain: self.select_chain,
 			import_queue,
@@ -571,6 +656,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			transaction_pool: self.transaction_pool,
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -617,6 +703,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			client: self.client,
 			backend: self.backend,
 			keystore: self.keystore,
+			submissions: self.submissions,
 			fetcher: self.fetcher,
 			select_chain: self.select_chain,
 			import_queue: self.import_queue,
@@ -626,6 +713,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			transaction_pool: Arc::new(transaction_pool),
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -656,6 +744,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			client: self.client,
 			backend: self.backend,
 			keystore: self.keystore,
+			submissions: self.submissions,
 			fetcher: self.fetcher,
 			select_chain: self.select_chain,
 			import_queue: self.import_queue,
@@ -665,9 +754,24 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			transaction_pool: self.transaction_pool,
 			rpc_extensions,
 			remote_backend: self.remote_backend,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
+
+	/// Defines the `BlockAnnounceValidator` used to check the data attached to incoming block
+	/// announcements. `DefaultBlockAnnounceValidator`, which accepts any data, is used if this
+	/// isn't called.
+	pub fn with_block_announce_validator(
+		self,
+		block_announce_validator_builder: impl FnOnce(Arc<TCl>) -> Box<dyn BlockAnnounceValidator<TBl> + Send>
+			+ Send + 'static,
+	) -> Result<Self, Error> {
+		Ok(ServiceBuilder {
+			block_announce_validator_builder: Some(Box::new(block_announce_validator_builder)),
+			..self
+		})
+	}
 }
 
 /// Implemented on `ServiceBuilder`. Allows running block commands, such as import/export/validate
@@ -773,6 +877,7 @@ ServiceBuilder<
 			fetcher: on_demand,
 			backend,
 			keystore,
+			submissions,
 			select_chain,
 			import_queue,
 			finality_proof_request_builder,
@@ -781,6 +886,7 @@ ServiceBuilder<
 			transaction_pool,
 			rpc_extensions,
 			remote_backend,
+			block_announce_validator_builder,
 		} = self;
 
 		sp_session::generate_initial_session_keys(
@@ -798,7 +904,16 @@ ServiceBuilder<
 		// A side-channel for essential tasks to communicate shutdown.
 		let (essential_failed_tx, essential_failed_rx) = mpsc::unbounded();
 
-		let import_queue = Box::new(import_queue);
+		let mut import_queue = Box::new(import_queue);
+		if let Some(dir) = config.in_chain_config_dir("import-queue") {
+			import_queue.set_overflow_dir(dir);
+		}
+		let consensus_pool = if config.roles.is_authority() {
+			crate::tasks::start_pool(&config.consensus_threads)
+		} else {
+			None
+		};
+
 		let chain_info = client.chain_info();
 
 		let version = config.full_version();
@@ -834,8 +949,10 @@ ServiceBuilder<
 			sc_network::config::ProtocolId::from(protocol_id_full)
 		};
 
-		let block_announce_validator =
-			Box::new(sp_consensus::block_validation::DefaultBlockAnnounceValidator::new(client.clone()));
+		let block_announce_validator = match block_announce_validator_builder {
+			Some(builder) => builder(client.clone()),
+			None => Box::new(DefaultBlockAnnounceValidator::new(client.clone())),
+		};
 
 		let network_params = sc_network::config::Params {
 			roles: config.roles,
@@ -847,6 +964,7 @@ ServiceBuilder<
 			transaction_pool: transaction_pool_adapter.clone() as _,
 			import_queue,
 			protocol_id,
+			fork_id: config.chain_spec.fork_id().map(str::to_owned),
 			specialization: network_protocol,
 			block_announce_validator,
 		};
@@ -854,6 +972,25 @@ ServiceBuilder<
 		let has_bootnodes = !network_params.network_config.boot_nodes.is_empty();
 		let network_mut = sc_network::NetworkWorker::new(network_params)?;
 		let network = network_mut.service().clone();
+		if !config.roles.is_light() {
+			// serve the state of our blocks to nodes downloading it
+			let (tx, rx) = mpsc::channel(sc_network::state_sync::INBOUND_QUEUE_SIZE);
+			network.register_request_response_protocol(
+				sc_network::state_sync::request_response_config(Some(tx)),
+			);
+			let future = sc_network::state_sync::answer_requests::<TBl>(client.clone(), rx);
+			let _ = to_spawn_tx.unbounded_send(Box::pin(select(Box::pin(future), exit.clone()).map(drop)));
+		}
+		#[cfg(not(target_os = "unknown"))]
+		{
+			if let Some(source) = config.chain_spec.boot_nodes_source() {
+				crate::bootnodes::start_fetcher(
+					source.clone(),
+					client.chain_info().genesis_hash,
+					Arc::downgrade(&network),
+				);
+			}
+		}
 		let network_status_sinks = Arc::new(Mutex::new(status_sinks::StatusSinks::new()));
 
 		let offchain_storage = backend.offchain_storage();
@@ -928,6 +1065,7 @@ ServiceBuilder<
 		let client_ = client.clone();
 		let mut sys = System::new();
 		let self_pid = get_current_pid().ok();
+		let metrics_prefix = config.metrics_prefix.clone();
 		let (state_tx, state_rx) = mpsc::unbounded::<(NetworkStatus<_>, NetworkState)>();
 		network_status_sinks.lock().push(std::time::Duration::from_millis(5000), state_tx);
 		let tel_task = state_rx.for_each(move |(net_status, _)| {
@@ -968,6 +1106,7 @@ ServiceBuilder<
 				"disk_write_per_sec" => info.usage.as_ref().map(|usage| usage.io.bytes_written).unwrap_or(0),
 			);
 			let _ = record_metrics!(
+				prefix = metrics_prefix.as_ref().map(String::as_str);
 				"peers" => num_peers,
 				"height" => best_number,
 				"txcount" => txpool_status.ready,
@@ -1001,7 +1140,7 @@ ServiceBuilder<
 
 		// RPC
 		let (system_rpc_tx, system_rpc_rx) = mpsc::unbounded();
-		let gen_handler = || {
+		let gen_handler = |exposed: sc_rpc_server::RpcMethods| {
 			use sc_rpc::{chain, state, author, system};
 
 			let system_info = sc_rpc::system::SystemInfo {
@@ -1045,7 +1184,7 @@ ServiceBuilder<
 				transaction_pool.clone(),
 				subscriptions,
 				keystore.clone(),
-			);
+			).with_submission_queue(submissions.clone());
 			let system = system::System::new(system_info, system_rpc_tx.clone());
 
 			sc_rpc_server::rpc_handler((
@@ -1054,9 +1193,9 @@ ServiceBuilder<
 				author::AuthorApi::to_delegate(author),
 				system::SystemApi::to_delegate(system),
 				rpc_extensions.clone(),
-			))
+			), &exposed)
 		};
-		let rpc_handlers = gen_handler();
+		let rpc_handlers = gen_handler(sc_rpc_server::RpcMethods::all());
 		let rpc = start_rpc_servers(&config, gen_handler)?;
 
 
@@ -1124,6 +1263,74 @@ ServiceBuilder<
 			let _ = to_spawn_tx.unbounded_send(Box::pin(future));
     	}
 
+		// gRPC server
+		if let Some(addr) = config.grpc {
+			#[cfg(feature = "grpc")]
+			{
+				if config.roles.is_light() {
+					warn!("The gRPC server is not supported by light clients");
+				} else {
+					let future = select(
+						sc_grpc::run_server(addr, client.clone())
+							.map(|result| if let Err(e) = result {
+								error!(target: "grpc", "gRPC server failed: {}", e);
+							})
+							.boxed(),
+						exit.clone()
+					).map(drop);
+
+					let _ = to_spawn_tx.unbounded_send(Box::pin(future));
+				}
+			}
+			#[cfg(not(feature = "grpc"))]
+			warn!("Not serving gRPC on {}, the node was built without the `grpc` feature", addr);
+		}
+
+		// GraphQL endpoint
+		if let Some(addr) = config.graphql {
+			#[cfg(feature = "graphql")]
+			{
+				if config.roles.is_light() {
+					warn!("The GraphQL endpoint is not supported by light clients");
+				} else {
+					let future = select(
+						sc_graphql::run_server(addr, client.clone())
+							.map(|result| if let Err(e) = result {
+								error!(target: "graphql", "GraphQL server failed: {}", e);
+							})
+							.boxed(),
+						exit.clone()
+					).map(drop);
+
+					let _ = to_spawn_tx.unbounded_send(Box::pin(future));
+				}
+			}
+			#[cfg(not(feature = "graphql"))]
+			warn!("Not serving GraphQL on {}, the node was built without the `graphql` feature", addr);
+		}
+
+		// Block publisher
+		if let Some(publisher) = config.publisher.as_ref() {
+			#[cfg(any(feature = "kafka", feature = "nats"))]
+			{
+				if config.roles.is_light() {
+					warn!("Publishing blocks is not supported by light clients");
+				} else {
+					sc_publisher::start(
+						&publisher.url,
+						publisher.topic_prefix.clone(),
+						client.clone(),
+						exit.clone(),
+					).map_err(|e| Error::Other(format!("Failed to start the block publisher: {}", e)))?;
+				}
+			}
+			#[cfg(not(any(feature = "kafka", feature = "nats")))]
+			warn!(
+				"Not publishing blocks to {}, the node was built without the `kafka` and `nats` features",
+				publisher.url,
+			);
+		}
+
 		// Instrumentation
 		if let Some(tracing_targets) = config.tracing_targets.as_ref() {
 			let subscriber = sc_tracing::ProfilingSubscriber::new(
@@ -1148,6 +1355,7 @@ ServiceBuilder<
 			to_spawn_tx,
 			to_spawn_rx,
 			to_poll: Vec::new(),
+			consensus_pool,
 			rpc_handlers,
 			_rpc: rpc,
 			_telemetry: telemetry,
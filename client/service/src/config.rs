@@ -20,6 +20,7 @@ pub use sc_client::ExecutionStrategies;
 pub use sc_client_db::{kvdb::KeyValueDB, PruningMode};
 pub use sc_network::config::{ExtTransport, NetworkConfiguration, Roles};
 pub use sc_executor::WasmExecutionMethod;
+pub use sc_rpc_server::TlsConfig as RpcTlsConfig;
 
 use std::{path::{PathBuf, Path}, net::SocketAddr, sync::Arc};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
@@ -53,8 +54,15 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub state_cache_size: usize,
 	/// Size in percent of cache size dedicated to child tries
 	pub state_cache_child_ratio: Option<usize>,
+	/// Total memory budget of the node in Bytes, if any.
+	pub memory_budget: Option<usize>,
+	/// Compress block bodies and justifications in the database.
+	pub compress_blocks: bool,
 	/// Pruning settings.
 	pub pruning: PruningMode,
+	/// Hashes of blocks that must not be imported nor built upon, in addition to the ones of the
+	/// chain spec. Hex encoded with `0x` prefix.
+	pub bad_blocks: Vec<String>,
 	/// Chain configuration.
 	pub chain_spec: ChainSpec<G, E>,
 	/// Custom configuration.
@@ -71,10 +79,43 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub rpc_ws: Option<SocketAddr>,
 	/// Maximum number of connections for WebSockets RPC server. `None` if default.
 	pub rpc_ws_max_connections: Option<usize>,
+	/// Compress the WebSockets RPC messages with `permessage-deflate` for the clients supporting it.
+	pub rpc_ws_compression: bool,
 	/// CORS settings for HTTP & WS servers. `None` if all origins are allowed.
 	pub rpc_cors: Option<Vec<String>>,
+	/// Expose unsafe methods over HTTP even if not listening on a loopback interface.
+	pub rpc_http_unsafe: bool,
+	/// Expose unsafe methods over WebSockets even if not listening on a loopback interface.
+	pub rpc_ws_unsafe: bool,
+	/// RPC methods exposed even if they are unsafe.
+	pub rpc_allowed_methods: Vec<String>,
+	/// RPC methods never exposed over HTTP or WebSockets.
+	pub rpc_denied_methods: Vec<String>,
+	/// File with the API keys required to call the HTTP and WS RPC methods. `None` if not required.
+	pub rpc_auth: Option<PathBuf>,
+	/// File with the RPC methods callable from given browser origins. `None` if not restricted.
+	pub rpc_origin_policy: Option<PathBuf>,
+	/// Certificates serving the HTTP RPC over TLS, with HTTP/2. `None` if served over plain
+	/// HTTP/1.1.
+	pub rpc_tls: Option<RpcTlsConfig>,
 	/// Grafana data source http port. `None` if disabled.
 	pub grafana_port: Option<SocketAddr>,
+	/// Prefix of the keys of the metrics recorded for the Grafana data source, telling apart the
+	/// nodes run by the same process. `None` if not prefixed.
+	pub metrics_prefix: Option<String>,
+	/// gRPC server binding address, streaming the finalized blocks. `None` if disabled.
+	///
+	/// Requires the `grpc` feature.
+	pub grpc: Option<SocketAddr>,
+	/// GraphQL endpoint binding address, querying the blocks, extrinsics, events and accounts.
+	/// `None` if disabled.
+	///
+	/// Requires the `graphql` feature.
+	pub graphql: Option<SocketAddr>,
+	/// Broker the finalized blocks are published to. `None` if disabled.
+	///
+	/// Requires the `kafka` or `nats` feature.
+	pub publisher: Option<PublisherConfig>,
 	/// Telemetry service URL. `None` if disabled.
 	pub telemetry_endpoints: Option<TelemetryEndpoints>,
 	/// External WASM transport for the telemetry. If `Some`, when connection to a telemetry
@@ -92,6 +133,15 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub force_authoring: bool,
 	/// Disable GRANDPA when running in validator mode
 	pub disable_grandpa: bool,
+	/// How many times slower than their weight allows extrinsics may execute while authoring
+	/// before being reported, `0` to not report them.
+	pub slow_extrinsic_factor: u64,
+	/// Only follow the GRANDPA commits when observing finality.
+	pub grandpa_lightweight_observer: bool,
+	/// Peers holding shares of the GRANDPA keys split between several hosts.
+	pub grandpa_threshold_peers: Vec<sc_network::PeerId>,
+	/// Threads running consensus-critical tasks.
+	pub consensus_threads: ConsensusThreads,
 	/// Development key seed.
 	///
 	/// When running in development mode, the seed will be used to generate authority keys by the keystore.
@@ -104,6 +154,54 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub tracing_receiver: sc_tracing::TracingReceiver,
 }
 
+/// Threads dedicated to consensus-critical tasks: block authoring, GRANDPA voting and block
+/// import.
+#[derive(Debug, Clone)]
+pub struct ConsensusThreads {
+	/// Number of threads running authoring and voting. With `0`, these tasks run on the same
+	/// threads as the other tasks.
+	pub count: usize,
+	/// CPUs the threads are pinned to, including the import thread. The threads are spread over
+	/// them in turn. Empty if they aren't pinned.
+	pub cpus: Vec<usize>,
+	/// Scheduling priority of the threads.
+	pub priority: ThreadPriority,
+	/// Threads shared with the other nodes run by the process, used instead of starting threads
+	/// of its own.
+	pub pool: Option<futures::executor::ThreadPool>,
+}
+
+impl Default for ConsensusThreads {
+	fn default() -> Self {
+		ConsensusThreads {
+			count: 2,
+			cpus: Vec::new(),
+			priority: ThreadPriority::Normal,
+			pool: None,
+		}
+	}
+}
+
+/// Scheduling priority of a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadPriority {
+	/// The priority of the other threads of the process.
+	Normal,
+	/// Scheduled before threads with a normal priority. Requires the `CAP_SYS_NICE` capability
+	/// on Linux.
+	High,
+}
+
+/// Broker the finalized blocks are published to.
+#[derive(Debug, Clone)]
+pub struct PublisherConfig {
+	/// URL of the broker: `kafka://` followed by the comma-separated list of the brokers of the
+	/// cluster, or the `nats://` URL of the server.
+	pub url: String,
+	/// Prefix of the topics, followed by `.blocks`, `.extrinsics` or `.events`.
+	pub topic_prefix: String,
+}
+
 /// Configuration of the client keystore.
 #[derive(Clone)]
 pub enum KeystoreConfig {
@@ -169,15 +267,30 @@ impl<C, G, E> Configuration<C, G, E> where
 			},
 			state_cache_size: Default::default(),
 			state_cache_child_ratio: Default::default(),
+			memory_budget: None,
+			compress_blocks: false,
 			custom: Default::default(),
 			pruning: PruningMode::default(),
+			bad_blocks: Vec::new(),
 			wasm_method: WasmExecutionMethod::Interpreted,
 			execution_strategies: Default::default(),
 			rpc_http: None,
 			rpc_ws: None,
 			rpc_ws_max_connections: None,
+			rpc_ws_compression: false,
 			rpc_cors: Some(vec![]),
+			rpc_http_unsafe: false,
+			rpc_ws_unsafe: false,
+			rpc_allowed_methods: Vec::new(),
+			rpc_denied_methods: Vec::new(),
+			rpc_auth: None,
+			rpc_origin_policy: None,
+			rpc_tls: None,
 			grafana_port: None,
+			metrics_prefix: None,
+			grpc: None,
+			graphql: None,
+			publisher: None,
 			telemetry_endpoints: None,
 			telemetry_external_transport: None,
 			default_heap_pages: None,
@@ -185,6 +298,10 @@ impl<C, G, E> Configuration<C, G, E> where
 			sentry_mode: false,
 			force_authoring: false,
 			disable_grandpa: false,
+			slow_extrinsic_factor: 2,
+			grandpa_lightweight_observer: false,
+			grandpa_threshold_peers: Vec::new(),
+			consensus_threads: Default::default(),
 			dev_key_seed: None,
 			tracing_targets: Default::default(),
 			tracing_receiver: Default::default(),
@@ -23,9 +23,13 @@ pub mod config;
 #[macro_use]
 pub mod chain_ops;
 pub mod error;
+pub mod multi;
 
 mod builder;
+#[cfg(not(target_os = "unknown"))]
+mod bootnodes;
 mod status_sinks;
+mod tasks;
 
 use std::{io, pin::Pin};
 use std::marker::PhantomData;
@@ -48,7 +52,7 @@ use sc_network::{
 	NetworkService, NetworkState, specialization::NetworkSpecialization,
 	PeerId, ReportHandle,
 };
-use log::{log, warn, debug, error, Level};
+use log::{log, info, warn, debug, error, Level};
 use codec::{Encode, Decode};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{NumberFor, Block as BlockT};
@@ -99,6 +103,8 @@ pub struct Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
 	/// If spawning a background task is not possible, we instead push the task into this `Vec`.
 	/// The elements must then be polled manually.
 	to_poll: Vec<Pin<Box<dyn Future<Output = ()> + Send>>>,
+	/// Threads dedicated to consensus-critical tasks, if any.
+	consensus_pool: Option<futures::executor::ThreadPool>,
 	rpc_handlers: sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
 	_rpc: Box<dyn std::any::Any + Send + Sync>,
 	_telemetry: Option<sc_telemetry::Telemetry>,
@@ -169,6 +175,11 @@ pub trait AbstractService: 'static + Future<Output = Result<(), Error>> +
 	/// trigger a service exit.
 	fn spawn_essential_task(&self, task: impl Future<Output = ()> + Send + Unpin + 'static);
 
+	/// Spawns an essential task that is critical to consensus, e.g. block authoring or GRANDPA
+	/// voting. It runs on the threads dedicated to consensus if there are any, so that it isn't
+	/// delayed by the load of the other tasks.
+	fn spawn_consensus_task(&self, task: impl Future<Output = ()> + Send + Unpin + 'static);
+
 	/// Returns a handle for spawning tasks.
 	fn spawn_task_handle(&self) -> SpawnTaskHandle;
 
@@ -248,16 +259,15 @@ where
 	}
 
 	fn spawn_essential_task(&self, task: impl Future<Output = ()> + Send + Unpin + 'static) {
-		let mut essential_failed = self.essential_failed_tx.clone();
-		let essential_task = std::panic::AssertUnwindSafe(task)
-			.catch_unwind()
-			.map(move |_| {
-				error!("Essential task failed. Shutting down service.");
-				let _ = essential_failed.send(());
-			});
-		let task = select(self.on_exit(), essential_task).map(drop);
+		let _ = self.to_spawn_tx.unbounded_send(Box::pin(self.essential_task(task)));
+	}
 
-		let _ = self.to_spawn_tx.unbounded_send(Box::pin(task));
+	fn spawn_consensus_task(&self, task: impl Future<Output = ()> + Send + Unpin + 'static) {
+		let task = self.essential_task(task);
+		match &self.consensus_pool {
+			Some(pool) => pool.spawn_ok(task),
+			None => { let _ = self.to_spawn_tx.unbounded_send(Box::pin(task)); },
+		}
 	}
 
 	fn spawn_task_handle(&self) -> SpawnTaskHandle {
@@ -345,6 +355,24 @@ impl<TBl: Unpin, TCl, TSc: Unpin, TNetStatus, TNet, TTxPool, TOc> Future for
 	}
 }
 
+impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
+	/// Wrap `task` so that the service exits when it ends or panics, and that it is dropped when
+	/// the service exits.
+	fn essential_task(
+		&self,
+		task: impl Future<Output = ()> + Send + Unpin + 'static,
+	) -> impl Future<Output = ()> + Send + 'static {
+		let mut essential_failed = self.essential_failed_tx.clone();
+		let essential_task = std::panic::AssertUnwindSafe(task)
+			.catch_unwind()
+			.map(move |_| {
+				error!("Essential task failed. Shutting down service.");
+				let _ = essential_failed.send(());
+			});
+		select(self.exit.clone(), essential_task).map(drop)
+	}
+}
+
 impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Spawn for
 	Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc>
 {
@@ -419,6 +447,18 @@ fn build_network_future<
 						let _ = sender.send(network_state);
 					}
 				}
+				sc_rpc::system::Request::LocalListenAddresses(sender) => {
+					let addresses = network.local_listen_addresses()
+						.into_iter()
+						.map(|addr| addr.to_string())
+						.collect();
+					let _ = sender.send(addresses);
+				}
+				sc_rpc::system::Request::NatStatus(sender) => {
+					if let Some(nat_status) = serde_json::to_value(&network.nat_status()).ok() {
+						let _ = sender.send(nat_status);
+					}
+				}
 				sc_rpc::system::Request::NetworkAddReservedPeer(peer_addr, sender) => {
 					let x = network.add_reserved_peer(peer_addr)
 						.map_err(sc_rpc::system::error::Error::MalformattedPeerArg);
@@ -450,6 +490,18 @@ fn build_network_future<
 
 					let _ = sender.send(node_roles);
 				}
+				sc_rpc::system::Request::NodeIdentity(challenge, sender) => {
+					match network.service().sign_with_local_identity(&challenge) {
+						Ok((public_key, signature)) => {
+							let _ = sender.send(sc_rpc::system::NodeIdentity {
+								peer_id: public_key.clone().into_peer_id().to_base58(),
+								public_key: public_key.into_protobuf_encoding().into(),
+								signature: signature.into(),
+							});
+						},
+						Err(err) => warn!("Failed to sign with the node key: {:?}", err),
+					}
+				}
 			};
 		}
 
@@ -461,6 +513,7 @@ fn build_network_future<
 				num_sync_peers: network.num_sync_peers(),
 				num_connected_peers: network.num_connected_peers(),
 				num_active_peers: network.num_active_peers(),
+				num_queued_blocks: network.num_queued_blocks(),
 				average_download_per_sec: network.average_download_per_sec(),
 				average_upload_per_sec: network.average_upload_per_sec(),
 			};
@@ -501,6 +554,8 @@ pub struct NetworkStatus<B: BlockT> {
 	pub num_connected_peers: usize,
 	/// Total number of active peers.
 	pub num_active_peers: usize,
+	/// Number of blocks waiting to be imported.
+	pub num_queued_blocks: u32,
 	/// Downloaded bytes per second averaged over the past few seconds.
 	pub average_download_per_sec: u64,
 	/// Uploaded bytes per second averaged over the past few seconds.
@@ -519,11 +574,15 @@ impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Drop for
 }
 
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
+///
+/// Unsafe methods are only exposed on loopback interfaces, unless allowed by the configuration.
 #[cfg(not(target_os = "unknown"))]
-fn start_rpc_servers<C, G, E, H: FnMut() -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
+fn start_rpc_servers<C, G, E, H>(
 	config: &Configuration<C, G, E>,
 	mut gen_handler: H
-) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
+) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> where
+	H: FnMut(sc_rpc_server::RpcMethods) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+{
 	fn maybe_start_server<T, F>(address: Option<SocketAddr>, mut start: F) -> Result<Option<T>, io::Error>
 		where F: FnMut(&SocketAddr) -> Result<T, io::Error>,
 	{
@@ -542,26 +601,78 @@ fn start_rpc_servers<C, G, E, H: FnMut() -> sc_rpc_server::RpcHandler<sc_rpc::Me
 		})
 	}
 
+	let exposed = |address: &SocketAddr, unsafe_external: bool| {
+		let unsafe_methods = unsafe_external || address.ip().is_loopback();
+		if !unsafe_methods {
+			info!("Unsafe RPC methods are not exposed on {}", address);
+		}
+		sc_rpc_server::RpcMethods {
+			unsafe_methods,
+			allowed: config.rpc_allowed_methods.clone(),
+			denied: config.rpc_denied_methods.clone(),
+		}
+	};
+
+	let auth = match &config.rpc_auth {
+		Some(path) => Some(sc_rpc_server::Auth::from_file(path)?),
+		None => None,
+	};
+	let policy = match &config.rpc_origin_policy {
+		Some(path) => Some(sc_rpc_server::OriginPolicy::from_file(path)?),
+		None => None,
+	};
+
+	type Server = Box<dyn std::any::Any + Send + Sync>;
+
 	Ok(Box::new((
 		maybe_start_server(
 			config.rpc_http,
-			|address| sc_rpc_server::start_http(address, config.rpc_cors.as_ref(), gen_handler()),
+			|address| match &config.rpc_tls {
+				Some(tls) => sc_rpc_server::start_https(
+					address,
+					config.rpc_cors.as_ref(),
+					auth.as_ref(),
+					policy.as_ref(),
+					gen_handler(exposed(address, config.rpc_http_unsafe)),
+					tls,
+				).map(|server| Box::new(server) as Server),
+				None => sc_rpc_server::start_http(
+					address,
+					config.rpc_cors.as_ref(),
+					auth.as_ref(),
+					policy.as_ref(),
+					gen_handler(exposed(address, config.rpc_http_unsafe)),
+				).map(|server| Box::new(server) as Server),
+			},
 		)?,
 		maybe_start_server(
 			config.rpc_ws,
-			|address| sc_rpc_server::start_ws(
-				address,
-				config.rpc_ws_max_connections,
-				config.rpc_cors.as_ref(),
-				gen_handler(),
-			),
-		)?.map(Mutex::new),
+			|address| if config.rpc_ws_compression {
+				sc_rpc_server::start_compressed_ws(
+					address,
+					config.rpc_ws_max_connections,
+					config.rpc_cors.as_ref(),
+					auth.as_ref(),
+					policy.as_ref(),
+					gen_handler(exposed(address, config.rpc_ws_unsafe)),
+				).map(|server| Box::new(server) as Server)
+			} else {
+				sc_rpc_server::start_ws(
+					address,
+					config.rpc_ws_max_connections,
+					config.rpc_cors.as_ref(),
+					auth.as_ref(),
+					policy.as_ref(),
+					gen_handler(exposed(address, config.rpc_ws_unsafe)),
+				).map(|server| Box::new(server) as Server)
+			},
+		)?,
 	)))
 }
 
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
 #[cfg(target_os = "unknown")]
-fn start_rpc_servers<C, G, E, H: FnMut() -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
+fn start_rpc_servers<C, G, E, H: FnMut(sc_rpc_server::RpcMethods) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
 	_: &Configuration<C, G, E>,
 	_: H
 ) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
@@ -624,7 +735,7 @@ where
 	C: sc_network::ClientHandle<B> + Send + Sync,
 	Pool: 'static + TransactionPool<Block=B, Hash=H, Error=E>,
 	B: BlockT,
-	H: std::hash::Hash + Eq + sp_runtime::traits::Member + sp_runtime::traits::MaybeSerialize,
+	H: std::hash::Hash + Eq + sp_runtime::traits::Member + sp_runtime::traits::MaybeSerialize + Encode + Decode,
 	E: 'static + IntoPoolError + From<sp_transaction_pool::error::Error>,
 {
 	fn transactions(&self) -> Vec<(H, <B as BlockT>::Extrinsic)> {
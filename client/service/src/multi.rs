@@ -0,0 +1,140 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Nodes of several chains run by the same process.
+//!
+//! Operators of many small networks can run a node of each of them with one process. Each node
+//! keeps its own chain spec, database and ports, but the services are driven by the same tokio
+//! runtime, their authoring and voting tasks run on the same consensus threads, and their metrics
+//! are served by a single Grafana data source, prefixed with the name given to each chain.
+
+use std::{net::SocketAddr, pin::Pin, task::{Context, Poll}};
+use futures::{Future, FutureExt};
+use log::warn;
+
+use crate::{Configuration, Error};
+
+/// Prepare the configurations of the nodes run by the same process, given with the names their
+/// metrics are recorded under.
+///
+/// The Grafana data sources of the nodes are disabled in favour of [`metrics_server`]. The
+/// consensus threads are started once, as configured for the first authority, and shared by
+/// all nodes.
+pub fn share_resources<C, G, E>(configs: &mut [(String, Configuration<C, G, E>)]) {
+	let pool = configs.iter()
+		.find(|(_, config)| config.roles.is_authority())
+		.and_then(|(_, config)| crate::tasks::start_pool(&config.consensus_threads));
+
+	for (name, config) in configs.iter_mut() {
+		config.grafana_port = None;
+		config.metrics_prefix = Some(name.clone());
+		config.consensus_threads.pool = pool.clone();
+		if pool.is_none() {
+			// Consensus tasks run with the other tasks of the runtime rather than on threads of
+			// each node.
+			config.consensus_threads.count = 0;
+		}
+	}
+}
+
+/// Serve the metrics of all the nodes of the process as a Grafana data source.
+pub fn metrics_server(address: SocketAddr) -> impl Future<Output = ()> + Send {
+	grafana_data_source::run_server(address).map(|result| if let Err(e) = result {
+		warn!(target: "service", "Grafana data source failed: {}", e);
+	})
+}
+
+/// The services of the nodes run by the same process, together.
+///
+/// Resolves with the error of the first service failing, the other ones being dropped with it.
+#[derive(Default)]
+pub struct Chains {
+	services: Vec<(String, Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>)>,
+}
+
+impl Chains {
+	/// Create an empty set of services.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add the service of the chain `name`.
+	pub fn push(&mut self, name: String, service: impl Future<Output = Result<(), Error>> + Send + 'static) {
+		self.services.push((name, Box::pin(service)));
+	}
+
+	/// Number of services.
+	pub fn len(&self) -> usize {
+		self.services.len()
+	}
+
+	/// Whether there is no service.
+	pub fn is_empty(&self) -> bool {
+		self.services.is_empty()
+	}
+}
+
+impl Future for Chains {
+	type Output = Result<(), Error>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		let this = Pin::into_inner(self);
+		let mut index = 0;
+		while index < this.services.len() {
+			match this.services[index].1.as_mut().poll(cx) {
+				Poll::Pending => index += 1,
+				Poll::Ready(Ok(())) => { this.services.remove(index); },
+				Poll::Ready(Err(e)) => {
+					let name = &this.services[index].0;
+					return Poll::Ready(Err(Error::Other(format!("Chain {} failed: {}", name, e))));
+				},
+			}
+		}
+
+		if this.services.is_empty() {
+			Poll::Ready(Ok(()))
+		} else {
+			Poll::Pending
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::future;
+
+	#[test]
+	fn chains_fail_with_the_first_service() {
+		let mut chains = Chains::new();
+		chains.push("alpha".into(), future::pending());
+		chains.push("beta".into(), future::ready(Err(Error::Other("Essential task failed.".into()))));
+
+		match futures::executor::block_on(chains) {
+			Err(Error::Other(e)) => assert_eq!(e, "Chain beta failed: Essential task failed."),
+			_ => panic!("beta failed"),
+		}
+	}
+
+	#[test]
+	fn chains_run_until_all_services_end() {
+		let mut chains = Chains::new();
+		chains.push("alpha".into(), future::ready(Ok(())));
+		chains.push("beta".into(), future::ready(Ok(())));
+
+		assert!(futures::executor::block_on(chains).is_ok());
+	}
+}
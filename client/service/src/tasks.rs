@@ -0,0 +1,150 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Threads dedicated to consensus-critical tasks.
+//!
+//! Block authoring and GRANDPA voting have to happen in time, but would otherwise share the
+//! threads of the runtime with the RPC and network tasks, so that a storm of RPC requests could
+//! make the node miss its slots. They are instead run on a small pool of their own. The threads
+//! of this pool and the one of the import queue can be pinned to CPUs and be given a higher
+//! scheduling priority than the other threads.
+
+use futures::executor::ThreadPool;
+use log::warn;
+
+use crate::config::{ConsensusThreads, ThreadPriority};
+
+/// Set up the worker thread of the import queues created afterwards.
+pub(crate) fn setup_import_thread(config: &ConsensusThreads) {
+	let config = config.clone();
+	// The import thread comes after the ones of the pool when spreading them over the CPUs.
+	sp_consensus::import_queue::set_worker_thread_setup(move || setup_thread(&config, config.count));
+}
+
+/// Start the threads running authoring and voting.
+///
+/// Returns the shared pool of `config` if any. Returns `None` if no thread is dedicated to
+/// consensus or if they can't be started, in which case consensus tasks run with the other tasks.
+pub(crate) fn start_pool(config: &ConsensusThreads) -> Option<ThreadPool> {
+	if let Some(pool) = &config.pool {
+		return Some(pool.clone());
+	}
+	if config.count == 0 {
+		return None;
+	}
+
+	let setup_config = config.clone();
+	ThreadPool::builder()
+		.name_prefix("consensus-")
+		.pool_size(config.count)
+		.after_start(move |index| setup_thread(&setup_config, index))
+		.create()
+		.map_err(|err| warn!(
+			target: "service",
+			"Failed to start the consensus threads, running consensus tasks with the others: {}",
+			err,
+		))
+		.ok()
+}
+
+/// Apply the CPU affinity and priority of `config` to the current thread, the `index`-th
+/// consensus thread.
+fn setup_thread(config: &ConsensusThreads, index: usize) {
+	if !config.cpus.is_empty() {
+		let cpu = config.cpus[index % config.cpus.len()];
+		if let Err(err) = sys::pin_to_cpu(cpu) {
+			warn!(target: "service", "Failed to pin consensus thread to CPU {}: {}", cpu, err);
+		}
+	}
+
+	if config.priority == ThreadPriority::High {
+		if let Err(err) = sys::raise_priority() {
+			warn!(target: "service", "Failed to raise the priority of consensus thread: {}", err);
+		}
+	}
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+	use std::{io, mem};
+
+	/// Niceness of the threads with a high priority, the default being `0`.
+	const HIGH_PRIORITY_NICENESS: libc::c_int = -10;
+
+	pub fn pin_to_cpu(cpu: usize) -> io::Result<()> {
+		unsafe {
+			let mut set: libc::cpu_set_t = mem::zeroed();
+			libc::CPU_SET(cpu, &mut set);
+			if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+				return Err(io::Error::last_os_error());
+			}
+		}
+		Ok(())
+	}
+
+	pub fn raise_priority() -> io::Result<()> {
+		// The niceness is an attribute of each thread on Linux, rather than of the process.
+		unsafe {
+			let thread_id = libc::syscall(libc::SYS_gettid) as libc::id_t;
+			if libc::setpriority(libc::PRIO_PROCESS as _, thread_id, HIGH_PRIORITY_NICENESS) != 0 {
+				return Err(io::Error::last_os_error());
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+	use std::io;
+
+	fn unsupported() -> io::Error {
+		io::Error::new(io::ErrorKind::Other, "not supported on this platform")
+	}
+
+	pub fn pin_to_cpu(_cpu: usize) -> io::Result<()> {
+		Err(unsupported())
+	}
+
+	pub fn raise_priority() -> io::Result<()> {
+		Err(unsupported())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::channel::oneshot;
+
+	#[test]
+	fn consensus_tasks_run_on_dedicated_threads() {
+		assert!(start_pool(&ConsensusThreads { count: 0, ..Default::default() }).is_none());
+
+		let pool = start_pool(&ConsensusThreads {
+			count: 1,
+			cpus: vec![0],
+			priority: ThreadPriority::Normal,
+			pool: None,
+		}).unwrap();
+		let (tx, rx) = oneshot::channel();
+		pool.spawn_ok(async move {
+			let _ = tx.send(std::thread::current().name().map(String::from));
+		});
+
+		let name = futures::executor::block_on(rx).unwrap().unwrap();
+		assert!(name.starts_with("consensus-"));
+	}
+}
@@ -165,6 +165,8 @@ fn node_config<G, E: Clone> (
 			wasm_external_transport: None,
 		},
 		max_parallel_downloads: NetworkConfiguration::default().max_parallel_downloads,
+		max_pending_light_requests: NetworkConfiguration::default().max_pending_light_requests,
+		max_light_requests_per_second: NetworkConfiguration::default().max_light_requests_per_second,
 	};
 
 	Configuration {
@@ -185,7 +187,10 @@ fn node_config<G, E: Clone> (
 		},
 		state_cache_size: 16777216,
 		state_cache_child_ratio: None,
+		memory_budget: None,
+		compress_blocks: false,
 		pruning: Default::default(),
+		bad_blocks: Vec::new(),
 		chain_spec: (*spec).clone(),
 		custom: Default::default(),
 		name: format!("Node {}", index),
@@ -194,8 +199,20 @@ fn node_config<G, E: Clone> (
 		rpc_http: None,
 		rpc_ws: None,
 		rpc_ws_max_connections: None,
+		rpc_ws_compression: false,
 		rpc_cors: None,
+		rpc_http_unsafe: false,
+		rpc_ws_unsafe: false,
+		rpc_allowed_methods: Vec::new(),
+		rpc_denied_methods: Vec::new(),
+		rpc_auth: None,
+		rpc_origin_policy: None,
+		rpc_tls: None,
 		grafana_port: None,
+		metrics_prefix: None,
+		grpc: None,
+		graphql: None,
+		publisher: None,
 		telemetry_endpoints: None,
 		telemetry_external_transport: None,
 		default_heap_pages: None,
@@ -203,6 +220,10 @@ fn node_config<G, E: Clone> (
 		sentry_mode: false,
 		force_authoring: false,
 		disable_grandpa: false,
+		slow_extrinsic_factor: 0,
+		grandpa_lightweight_observer: false,
+		grandpa_threshold_peers: Vec::new(),
+		consensus_threads: Default::default(),
 		dev_key_seed: key_seed,
 		tracing_targets: None,
 		tracing_receiver: Default::default(),
@@ -27,22 +27,22 @@ use codec::{Encode, Decode};
 use hash_db::Prefix;
 use sp_core::{
 	ChangesTrieConfiguration, convert_hash, traits::CodeExecutor,
-	NativeOrEncoded, storage::{StorageKey, StorageData, well_known_keys, ChildInfo},
+	NativeOrEncoded, storage::{StorageKey, StorageData, well_known_keys, ChildInfo, Storage},
 };
 use sc_telemetry::{telemetry, SUBSTRATE_INFO};
 use sp_runtime::{
 	Justification, BuildStorage,
 	generic::{BlockId, SignedBlock, DigestItem},
 	traits::{
-		Block as BlockT, Header as HeaderT, Zero, NumberFor, HasherFor, SaturatedConversion, One,
-		DigestFor,
+		Block as BlockT, Header as HeaderT, Zero, NumberFor, HasherFor, HashFor, Hash as HashT,
+		SaturatedConversion, One, DigestFor,
 	},
 };
 use sp_state_machine::{
 	DBValue, Backend as StateBackend, ChangesTrieAnchorBlockId,
-	prove_read, prove_child_read, ChangesTrieRootsStorage, ChangesTrieStorage,
+	prove_read, prove_child_read, prove_range_read, prove_child_range_read, ChangesTrieRootsStorage, ChangesTrieStorage,
 	ChangesTrieConfigurationRange, key_changes, key_changes_proof, StorageProof,
-	merge_storage_proofs,
+	merge_storage_proofs, ExecutionStrategy,
 };
 use sc_executor::{RuntimeVersion, RuntimeInfo};
 use sp_consensus::{
@@ -70,7 +70,7 @@ pub use sc_client_api::{
 	client::{
 		ImportNotifications, FinalityNotification, FinalityNotifications, BlockImportNotification,
 		ClientInfo, BlockchainEvents, BlockBody, ProvideUncles, BadBlocks, ForkBlocks,
-		BlockOf,
+		LightCheckpoint, BlockOf,
 	},
 	execution_extensions::{ExecutionExtensions, ExecutionStrategies},
 	notifications::{StorageNotifications, StorageEventStream},
@@ -82,6 +82,7 @@ use crate::{
 	call_executor::LocalCallExecutor,
 	light::{call_executor::prove_execution, fetcher::ChangesProof},
 	in_mem, genesis, cht,
+	runtime_upgrades::{self, RuntimeUpgrade},
 };
 
 /// Substrate Client
@@ -211,6 +212,17 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			backend.commit_operation(op)?;
 		}
 
+		for hash in bad_blocks.iter().flatten() {
+			if let Some(header) = backend.blockchain().header(BlockId::Hash(*hash))? {
+				warn!(
+					"Known bad block #{} ({}) has already been imported. \
+					Revert the chain below it to leave the bad fork.",
+					header.number(),
+					hash,
+				);
+			}
+		}
+
 		Ok(Client {
 			backend,
 			executor,
@@ -225,6 +237,15 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		})
 	}
 
+	/// Returns an error if the given block is in the list of known bad blocks.
+	fn ensure_not_bad_block(&self, hash: &Block::Hash) -> sp_blockchain::Result<()> {
+		match self.bad_blocks {
+			Some(ref bad_blocks) if bad_blocks.contains(hash) =>
+				Err(Error::KnownBadBlock(format!("{:?}", hash))),
+			_ => Ok(()),
+		}
+	}
+
 	/// Get a reference to the execution extensions.
 	pub fn execution_extensions(&self) -> &ExecutionExtensions<Block> {
 		&self.execution_extensions
@@ -235,6 +256,16 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		self.backend.state_at(*block)
 	}
 
+	/// Prevent the state of the given block from being pruned until `unpin_block` is called.
+	pub fn pin_block(&self, hash: &Block::Hash) -> sp_blockchain::Result<()> {
+		self.backend.pin_block(hash)
+	}
+
+	/// Release a pin taken with `pin_block`.
+	pub fn unpin_block(&self, hash: &Block::Hash) {
+		self.backend.unpin_block(hash)
+	}
+
 	/// Given a `BlockId` and a key prefix, return the matching child storage keys in that block.
 	pub fn storage_keys(&self, id: &BlockId<Block>, key_prefix: &StorageKey) -> sp_blockchain::Result<Vec<StorageKey>> {
 		let keys = self.state_at(id)?.keys(&key_prefix.0).into_iter().map(StorageKey).collect();
@@ -313,6 +344,14 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		self.executor.runtime_version(id)
 	}
 
+	/// Get the runtime upgrades recorded at block import, on all forks, in import order.
+	///
+	/// The runtime executing the genesis block is recorded when importing its first child. Blocks
+	/// imported without executing them, e.g. on light clients, are not recorded.
+	pub fn runtime_upgrades(&self) -> sp_blockchain::Result<Vec<RuntimeUpgrade<Block::Hash>>> {
+		runtime_upgrades::load_runtime_upgrades(&*self.backend)
+	}
+
 	/// Get call executor reference.
 	pub fn executor(&self) -> &E {
 		&self.executor
@@ -328,6 +367,37 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 				.map_err(Into::into))
 	}
 
+	/// Reads the storage entries of a key range at a given block, returning range proof.
+	///
+	/// The range starts at `start` and ends before `end`, or after `max_keys` entries.
+	pub fn read_range_proof(
+		&self,
+		id: &BlockId<Block>,
+		start: &[u8],
+		end: Option<&[u8]>,
+		max_keys: usize,
+	) -> sp_blockchain::Result<StorageProof> {
+		self.state_at(id)
+			.and_then(|state| prove_range_read(state, start, end, max_keys)
+				.map_err(Into::into))
+	}
+
+	/// Reads the storage entries of a key range of a child trie at a given block, returning range
+	/// proof.
+	pub fn read_child_range_proof(
+		&self,
+		id: &BlockId<Block>,
+		storage_key: &[u8],
+		child_info: ChildInfo,
+		start: &[u8],
+		end: Option<&[u8]>,
+		max_keys: usize,
+	) -> sp_blockchain::Result<StorageProof> {
+		self.state_at(id)
+			.and_then(|state| prove_child_range_read(state, storage_key, child_info, start, end, max_keys)
+				.map_err(Into::into))
+	}
+
 	/// Reads child storage value at a given block + storage_key + key, returning
 	/// read proof.
 	pub fn read_child_proof<I>(
@@ -672,6 +742,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			ApiExt<Block, StateBackend = backend::StateBackendFor<B, Block>>
 	{
 		let info = self.chain_info();
+		self.ensure_not_bad_block(&info.best_hash)?;
 		sc_block_builder::BlockBuilder::new(
 			self,
 			info.best_hash,
@@ -699,9 +770,11 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		<Self as ProvideRuntimeApi<Block>>::Api: BlockBuilderApi<Block, Error = Error> +
 			ApiExt<Block, StateBackend = backend::StateBackendFor<B, Block>>
 	{
+		let parent_hash = self.expect_block_hash_from_id(parent)?;
+		self.ensure_not_bad_block(&parent_hash)?;
 		sc_block_builder::BlockBuilder::new(
 			self,
-			self.expect_block_hash_from_id(parent)?,
+			parent_hash,
 			self.expect_block_number_from_id(parent)?,
 			record_proof.into(),
 			inherent_digests,
@@ -742,6 +815,34 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		result
 	}
 
+	/// Import the block with the given header as the new best block, with its whole state
+	/// downloaded from other nodes instead of built by executing the block.
+	///
+	/// The parent of the block must be known, but its state doesn't need to be. `state` must
+	/// match the state root of the header.
+	pub fn import_state(&self, header: Block::Header, state: Storage) -> sp_blockchain::Result<()> {
+		let hash = header.hash();
+		let chain = self.backend.blockchain();
+		if chain.status(BlockId::Hash(hash))? == blockchain::BlockStatus::InChain {
+			return Err(Error::Msg(format!("Block {:?} is already in chain", hash)));
+		}
+		if chain.status(BlockId::Hash(*header.parent_hash()))? == blockchain::BlockStatus::Unknown {
+			return Err(Error::UnknownBlock(format!("{:?}", header.parent_hash())));
+		}
+		if *header.number() <= chain.info().finalized_number {
+			return Err(Error::NotInFinalizedChain);
+		}
+
+		self.lock_import_and_run(|operation| {
+			let root = operation.op.reset_storage(state)?;
+			if &root != header.state_root() {
+				return Err(Error::InvalidStateRoot);
+			}
+			operation.op.set_block_data(header, None, None, NewBlockState::Best)?;
+			Ok(())
+		})
+	}
+
 	/// Apply a checked and validated block to an operation. If a justification is provided
 	/// then `finalized` *must* be true.
 	fn apply_block(
@@ -825,7 +926,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		storage_changes: Option<sp_api::StorageChanges<backend::StateBackendFor<B, Block>, Block>>,
 		new_cache: HashMap<CacheKeyId, Vec<u8>>,
 		finalized: bool,
-		aux: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+		mut aux: Vec<(Vec<u8>, Option<Vec<u8>>)>,
 		fork_choice: ForkChoiceStrategy,
 		import_existing: bool,
 	) -> sp_blockchain::Result<ImportResult> where
@@ -878,10 +979,23 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 
 				operation.op.update_cache(new_cache);
 
-				let (main_sc, child_sc, tx, _, changes_trie_tx) = storage_changes.into_inner();
+				let (main_sc, child_sc, tx, _, changes_trie_tx, offchain_sc) =
+					storage_changes.into_inner();
+
+				if main_sc.iter().any(|(key, _)| &key[..] == well_known_keys::CODE) {
+					aux.push(runtime_upgrades::write_code_change(hash));
+				}
+				if let Some(upgrade) = self.runtime_upgrade(
+					parent_hash,
+					hash,
+					*import_headers.post().number(),
+				)? {
+					aux.push(upgrade);
+				}
 
 				operation.op.update_db_storage(tx)?;
 				operation.op.update_storage(main_sc.clone(), child_sc.clone())?;
+				operation.op.update_offchain_storage(offchain_sc)?;
 
 				if let Some(changes_trie_transaction) = changes_trie_tx {
 					operation.op.update_changes_trie(changes_trie_transaction)?;
@@ -951,6 +1065,42 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		Ok(ImportResult::imported(is_new_best))
 	}
 
+	/// Aux data recording the runtime first executing the block `hash`, if its parent stores new
+	/// runtime code.
+	///
+	/// The state of the parent is read, so its version survives pruning once recorded.
+	fn runtime_upgrade(
+		&self,
+		parent_hash: Block::Hash,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+	) -> sp_blockchain::Result<Option<(Vec<u8>, Option<Vec<u8>>)>> {
+		let (block, number) = if number == One::one() {
+			// The genesis runtime executes the genesis block itself.
+			(parent_hash, 0)
+		} else if runtime_upgrades::is_code_change(&*self.backend, parent_hash)? {
+			(hash, number.saturated_into())
+		} else {
+			return Ok(None)
+		};
+		let mut upgrades = runtime_upgrades::load_runtime_upgrades(&*self.backend)?;
+		if upgrades.iter().any(|upgrade| upgrade.block == block) {
+			return Ok(None)
+		}
+
+		let at = BlockId::Hash(parent_hash);
+		let version = self.runtime_version_at(&at)?;
+		// Runtimes are not required to provide metadata.
+		let metadata_hash = self.executor
+			.call(&at, "Metadata_metadata", &[], ExecutionStrategy::NativeElseWasm, None)
+			.ok()
+			.and_then(|metadata| Vec::<u8>::decode(&mut &metadata[..]).ok())
+			.map(|metadata| HashFor::<Block>::hash(&metadata));
+
+		upgrades.push(RuntimeUpgrade { block, number, version, metadata_hash });
+		Ok(Some(runtime_upgrades::write_runtime_upgrades(&upgrades)))
+	}
+
 	/// Prepares the storage changes for a block.
 	///
 	/// It checks if the state should be enacted and if the `import_block` maybe already provides
@@ -1523,6 +1673,21 @@ impl<B, E, Block, RA> sp_consensus::BlockImport<Block> for &Client<B, E, Block,
 			return Ok(ImportResult::KnownBad);
 		}
 
+		// The bad block may have been imported before it was known to be bad.
+		let bad_parent = self.bad_blocks.as_ref()
+			.filter(|bs| bs.contains(&parent_hash))
+			.is_some();
+
+		if bad_parent {
+			trace!(
+				"Rejecting child of known bad block: #{} {:?}, parent {:?}",
+				number,
+				hash,
+				parent_hash,
+			);
+			return Ok(ImportResult::KnownBad);
+		}
+
 		// Own status must be checked first. If the block and ancestry is pruned
 		// this function must return `AlreadyInChain` rather than `MissingState`
 		match self.block_status(&BlockId::Hash(hash))
@@ -2866,7 +3031,9 @@ pub(crate) mod tests {
 				source: DatabaseSettingsSrc::Path {
 					path: tmp.path().into(),
 					cache_size: None,
-				}
+				},
+				memory_budget: None,
+				compress_blocks: false,
 			},
 			u64::max_value(),
 		).unwrap());
@@ -2965,7 +3132,9 @@ pub(crate) mod tests {
 					source: DatabaseSettingsSrc::Path {
 						path: tmp.path().into(),
 						cache_size: None,
-					}
+					},
+					memory_budget: None,
+					compress_blocks: false,
 				},
 				u64::max_value(),
 		).unwrap());
@@ -3052,4 +3221,40 @@ pub(crate) mod tests {
 		check_block_b1.parent_hash = H256::random();
 		assert_eq!(client.check_block(check_block_b1.clone()).unwrap(), ImportResult::UnknownParent);
 	}
+
+	#[test]
+	fn known_bad_blocks_are_not_imported_nor_built_upon() {
+		let backend = Arc::new(Backend::new_test(u32::max_value(), u64::max_value()));
+		let mut client = TestClientBuilder::with_backend(backend.clone()).build();
+
+		let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, a1.clone()).unwrap();
+		let a2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+
+		// `a1` turns out to be bad after it has been imported.
+		let executor = LocalCallExecutor::new(
+			backend.clone(),
+			NativeExecutor::<LocalExecutor>::new(WasmExecutionMethod::Interpreted, None),
+		);
+		let mut client = Client::<_, _, Block, RuntimeApi>::new(
+			backend,
+			executor,
+			&sp_runtime::Storage::default(),
+			None,
+			Some(vec![a1.hash()].into_iter().collect()),
+			Default::default(),
+		).unwrap();
+
+		let check_block = |block: &Block| BlockCheckParams {
+			hash: block.hash(),
+			number: *block.header().number(),
+			parent_hash: *block.header().parent_hash(),
+			allow_missing_state: false,
+			import_existing: false,
+		};
+		assert_eq!(client.check_block(check_block(&a1)).unwrap(), ImportResult::KnownBad);
+		assert_eq!(client.check_block(check_block(&a2)).unwrap(), ImportResult::KnownBad);
+		assert!(client.new_block(Default::default()).is_err());
+		assert!(client.new_block_at(&BlockId::Number(0), Default::default(), false).is_ok());
+	}
 }
@@ -35,7 +35,7 @@ use sp_trie::MemoryDB;
 use sp_blockchain::{CachedHeaderMetadata, HeaderMetadata};
 
 use sc_client_api::{
-	backend::{self, NewBlockState, StorageCollection, ChildStorageCollection},
+	backend::{self, NewBlockState, StorageCollection, ChildStorageCollection, OffchainStorageCollection},
 	blockchain::{
 		self, BlockStatus, HeaderBackend, well_known_cache_keys::Id as CacheKeyId
 	},
@@ -547,6 +547,13 @@ impl<Block: BlockT> backend::BlockImportOperation<Block> for BlockImportOperatio
 		Ok(())
 	}
 
+	fn update_offchain_storage(
+		&mut self,
+		_offchain_update: OffchainStorageCollection,
+	) -> sp_blockchain::Result<()> {
+		Ok(())
+	}
+
 	fn mark_finalized(
 		&mut self,
 		block: BlockId<Block>,
@@ -83,6 +83,7 @@ pub mod light;
 pub mod leaves;
 mod call_executor;
 mod client;
+mod runtime_upgrades;
 
 pub use sc_client_api::{
 	blockchain,
@@ -99,8 +100,9 @@ pub use crate::{
 		new_in_mem,
 		BlockBody, ImportNotifications, FinalityNotifications, BlockchainEvents,
 		BlockImportNotification, Client, ClientInfo, ExecutionStrategies, FinalityNotification,
-		LongestChain, BlockOf, ProvideUncles, BadBlocks, ForkBlocks, apply_aux,
+		LongestChain, BlockOf, ProvideUncles, BadBlocks, ForkBlocks, LightCheckpoint, apply_aux,
 	},
 	leaves::LeafSet,
+	runtime_upgrades::RuntimeUpgrade,
 };
 pub use sp_state_machine::{ExecutionStrategy, StorageProof};
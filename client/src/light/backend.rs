@@ -33,7 +33,7 @@ use sp_blockchain::{Error as ClientError, Result as ClientResult};
 use sc_client_api::{
 	backend::{
 		AuxStore, Backend as ClientBackend, BlockImportOperation, RemoteBackend, NewBlockState,
-		StorageCollection, ChildStorageCollection,
+		StorageCollection, ChildStorageCollection, OffchainStorageCollection,
 	},
 	blockchain::{
 		HeaderBackend as BlockchainHeaderBackend, well_known_cache_keys,
@@ -285,6 +285,14 @@ impl<S, Block> BlockImportOperation<Block> for ImportOperation<Block, S>
 		Ok(())
 	}
 
+	fn update_offchain_storage(
+		&mut self,
+		_offchain_update: OffchainStorageCollection,
+	) -> ClientResult<()> {
+		// light clients do not execute blocks => there is nothing to index
+		Ok(())
+	}
+
 	fn update_changes_trie(
 		&mut self,
 		_update: ChangesTrieTransaction<HasherFor<Block>, NumberFor<Block>>,
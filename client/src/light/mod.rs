@@ -32,7 +32,7 @@ use sp_blockchain::Result as ClientResult;
 use crate::call_executor::LocalCallExecutor;
 use crate::client::Client;
 use sc_client_api::{
-	light::Storage as BlockchainStorage,
+	light::Storage as BlockchainStorage, BadBlocks, ForkBlocks,
 };
 use crate::light::backend::Backend;
 use crate::light::blockchain::Blockchain;
@@ -54,10 +54,15 @@ pub fn new_light_backend<B, S>(blockchain: Arc<Blockchain<S>>) -> Arc<Backend<S,
 }
 
 /// Create an instance of light client.
+///
+/// Headers that don't match the `fork_blocks` or that are in `bad_blocks` are rejected on import,
+/// which lets the chain spec pin the light client to the right chain.
 pub fn new_light<B, S, GS, RA, E>(
 	backend: Arc<Backend<S, HasherFor<B>>>,
 	genesis_storage: &GS,
 	code_executor: E,
+	fork_blocks: ForkBlocks<B>,
+	bad_blocks: BadBlocks<B>,
 ) -> ClientResult<
 		Client<
 			Backend<S, HasherFor<B>>,
@@ -81,8 +86,8 @@ pub fn new_light<B, S, GS, RA, E>(
 		backend,
 		executor,
 		genesis_storage,
-		Default::default(),
-		Default::default(),
+		fork_blocks,
+		bad_blocks,
 		Default::default(),
 	)
 }
@@ -0,0 +1,73 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Schema for the runtime upgrades recorded in the aux-db at block import.
+
+use codec::{Decode, Encode};
+use sc_client_api::backend::AuxStore;
+use sp_blockchain::{Result as ClientResult, Error as ClientError};
+use sp_version::RuntimeVersion;
+
+const RUNTIME_UPGRADES: &[u8] = b"runtime_upgrades";
+
+fn code_change_key<H: Encode>(block_hash: H) -> Vec<u8> {
+	(b"runtime_code_change", block_hash).encode()
+}
+
+/// The first block executed by a runtime, on any fork.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct RuntimeUpgrade<Hash> {
+	/// Hash of the first block executed by the runtime.
+	pub block: Hash,
+	/// Number of the first block executed by the runtime.
+	pub number: u64,
+	/// Version of the runtime.
+	pub version: RuntimeVersion,
+	/// Hash of the runtime metadata, if the runtime provides it.
+	pub metadata_hash: Option<Hash>,
+}
+
+/// Load the runtime upgrades recorded so far, in import order.
+pub(crate) fn load_runtime_upgrades<B: AuxStore, Hash: Decode>(
+	backend: &B,
+) -> ClientResult<Vec<RuntimeUpgrade<Hash>>> {
+	match backend.get_aux(RUNTIME_UPGRADES)? {
+		None => Ok(Vec::new()),
+		Some(upgrades) => Decode::decode(&mut &upgrades[..]).map_err(|e| ClientError::Backend(
+			format!("Runtime upgrades DB is corrupted. Decode error: {}", e.what())
+		)),
+	}
+}
+
+/// Aux data storing `upgrades` as the recorded runtime upgrades.
+pub(crate) fn write_runtime_upgrades<Hash: Encode>(
+	upgrades: &[RuntimeUpgrade<Hash>],
+) -> (Vec<u8>, Option<Vec<u8>>) {
+	(RUNTIME_UPGRADES.to_vec(), Some(upgrades.encode()))
+}
+
+/// Whether the block `block_hash` was recorded as storing new runtime code.
+pub(crate) fn is_code_change<B: AuxStore, Hash: Encode>(
+	backend: &B,
+	block_hash: Hash,
+) -> ClientResult<bool> {
+	Ok(backend.get_aux(&code_change_key(block_hash))?.is_some())
+}
+
+/// Aux data recording that the block `block_hash` stores new runtime code.
+pub(crate) fn write_code_change<Hash: Encode>(block_hash: Hash) -> (Vec<u8>, Option<Vec<u8>>) {
+	(code_change_key(block_hash), Some(Vec::new()))
+}
@@ -360,11 +360,21 @@ impl<B: ChainApi> Pool<B> {
 		self.validated_pool.remove_invalid(hashes)
 	}
 
+	/// Remove transactions from the pool without banning them.
+	pub fn remove(&self, hashes: &[ExHash<B>]) -> Vec<TransactionFor<B>> {
+		self.validated_pool.remove(hashes)
+	}
+
 	/// Get an iterator for ready transactions ordered by priority
 	pub fn ready(&self) -> impl Iterator<Item=TransactionFor<B>> {
 		self.validated_pool.ready()
 	}
 
+	/// Get the future transactions, waiting for the tags they require.
+	pub fn futures(&self) -> Vec<TransactionFor<B>> {
+		self.validated_pool.futures()
+	}
+
 	/// Returns pool status.
 	pub fn status(&self) -> PoolStatus {
 		self.validated_pool.status()
@@ -684,6 +694,40 @@ mod tests {
 		assert!(pool.validated_pool.rotator().is_banned(&hash3));
 	}
 
+	#[test]
+	fn should_remove_transactions_without_banning_them() {
+		// given
+		let pool = pool();
+		let ready = uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 0,
+		});
+		let future = uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 3,
+		});
+		let hash1 = block_on(pool.submit_one(&BlockId::Number(0), ready.clone())).unwrap();
+		let hash2 = block_on(pool.submit_one(&BlockId::Number(0), future.clone())).unwrap();
+		assert_eq!(pool.futures().iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![hash2]);
+
+		// when
+		let removed = pool.remove(&[hash1, hash2]);
+
+		// then
+		assert_eq!(removed.len(), 2);
+		assert_eq!(pool.status().ready, 0);
+		assert_eq!(pool.status().future, 0);
+		assert!(!pool.validated_pool.rotator().is_banned(&hash1));
+		assert!(!pool.validated_pool.rotator().is_banned(&hash2));
+		block_on(pool.submit_at(&BlockId::Number(0), vec![ready, future], false)).unwrap();
+		assert_eq!(pool.status().ready, 1);
+		assert_eq!(pool.status().future, 1);
+	}
+
 	#[test]
 	fn should_ban_mined_transactions() {
 		// given
@@ -484,11 +484,29 @@ impl<B: ChainApi> ValidatedPool<B> {
 		invalid
 	}
 
+	/// Remove transactions identified by given hashes (and dependent transactions) from the pool,
+	/// without banning them, so that they can be submitted again.
+	pub fn remove(&self, hashes: &[ExHash<B>]) -> Vec<TransactionFor<B>> {
+		let removed = self.pool.write().remove_subtree(hashes);
+
+		let mut listener = self.listener.write();
+		for tx in &removed {
+			listener.dropped(&tx.hash, None);
+		}
+
+		removed
+	}
+
 	/// Get an iterator for ready transactions ordered by priority
 	pub fn ready(&self) -> impl Iterator<Item=TransactionFor<B>> {
 		self.pool.read().ready()
 	}
 
+	/// Get the future transactions, waiting for the tags they require.
+	pub fn futures(&self) -> Vec<TransactionFor<B>> {
+		self.pool.read().futures().cloned().collect()
+	}
+
 	/// Returns pool status.
 	pub fn status(&self) -> PoolStatus {
 		self.pool.read().status()
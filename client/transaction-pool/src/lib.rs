@@ -110,6 +110,10 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		self.pool.remove_invalid(hashes)
 	}
 
+	fn remove(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.remove(hashes)
+	}
+
 	fn status(&self) -> PoolStatus {
 		self.pool.status()
 	}
@@ -118,6 +122,10 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		Box::new(self.pool.ready())
 	}
 
+	fn futures(&self) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.futures()
+	}
+
 	fn import_notification_stream(&self) -> ImportNotificationStream {
 		self.pool.import_notification_stream()
 	}
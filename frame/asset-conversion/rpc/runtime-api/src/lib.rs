@@ -0,0 +1,62 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime API definition for the asset conversion module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use sp_runtime::RuntimeDebug;
+
+/// Something that can be swapped: the native currency or an asset of the assets module.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum AssetKind<AssetId> {
+	/// The native currency.
+	Native,
+	/// The asset with the given identifier.
+	Asset(AssetId),
+}
+
+sp_api::decl_runtime_apis! {
+	/// Quotes of swaps through the pools of the asset conversion module.
+	pub trait AssetConversionApi<Balance, AssetId> where
+		Balance: Codec,
+		AssetId: Codec,
+	{
+		/// The amount of `to` received for selling `amount_in` of `from`, if the pools on the
+		/// route exist and have enough liquidity.
+		fn quote_exact_in(
+			from: AssetKind<AssetId>,
+			to: AssetKind<AssetId>,
+			amount_in: Balance,
+		) -> Option<Balance>;
+
+		/// The amount of `from` to sell for receiving `amount_out` of `to`, if the pools on the
+		/// route exist and have enough liquidity.
+		fn quote_exact_out(
+			from: AssetKind<AssetId>,
+			to: AssetKind<AssetId>,
+			amount_out: Balance,
+		) -> Option<Balance>;
+
+		/// The reserves of the native currency and of `asset` in the pool of `asset`.
+		fn reserves(asset: AssetId) -> Option<(Balance, Balance)>;
+	}
+}
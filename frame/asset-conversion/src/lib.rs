@@ -0,0 +1,934 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Asset Conversion Module
+//!
+//! - [`asset_conversion::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Asset Conversion module is a constant product market maker between the native currency
+//! and the assets of the [Assets module](../pallet_assets/index.html).
+//!
+//! Every asset can have one pool, holding reserves of the native currency and of the asset in
+//! an account derived from the asset identifier. Liquidity providers deposit both in the ratio
+//! of the reserves and receive liquidity tokens, an asset minted by this module, which are
+//! redeemed for their share of the reserves. Swaps keep the product of the reserves constant,
+//! apart from the `SwapFee` which is taken from the input and stays in the pool. Swaps between
+//! two assets go through the native currency.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `create_pool` - Create the pool of an asset.
+//! * `add_liquidity` - Deposit native currency and an asset into its pool for liquidity tokens.
+//! * `remove_liquidity` - Redeem liquidity tokens for a share of the reserves of a pool.
+//! * `swap_exact_in` - Sell an exact amount, receiving at least a minimum amount.
+//! * `swap_exact_out` - Buy an exact amount, selling at most a maximum amount.
+//!
+//! ### Public Functions
+//!
+//! * `reserves` - The reserves of the pool of an asset.
+//! * `quote_exact_in` - The amount received for selling an amount.
+//! * `quote_exact_out` - The amount to sell for receiving an amount.
+//! * `buy_native` - Sell at most a maximum amount of an asset for an exact amount of the native
+//!   currency, which is returned as an imbalance rather than credited to an account.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::{prelude::*, convert::TryFrom};
+use codec::{Encode, Decode};
+use sp_core::U256;
+use sp_runtime::{
+	ModuleId, Permill, RuntimeDebug, DispatchResult, DispatchError,
+	traits::{AccountIdConversion, CheckedAdd, CheckedSub, Saturating, UniqueSaturatedInto, Zero},
+};
+use frame_support::{
+	decl_module, decl_event, decl_storage, decl_error, ensure,
+	traits::{Currency, Get, ExistenceRequirement::KeepAlive, WithdrawReason},
+	weights::SimpleDispatchInfo,
+};
+use frame_system::{self as system, ensure_signed};
+
+pub use pallet_asset_conversion_rpc_runtime_api::AssetKind;
+
+type AssetIdOf<T> = <T as pallet_assets::Trait>::AssetId;
+type BalanceOf<T> = <T as pallet_assets::Trait>::Balance;
+type AssetKindOf<T> = AssetKind<AssetIdOf<T>>;
+pub type NegativeImbalanceOf<T> =
+	<<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
+type Assets<T> = pallet_assets::Module<T>;
+
+/// The prefix of the accounts holding the reserves of the pools.
+const MODULE_ID: ModuleId = ModuleId(*b"py/ascnv");
+
+pub trait Trait: pallet_assets::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The native currency, measured in the same unit as assets.
+	type Currency: Currency<Self::AccountId, Balance = BalanceOf<Self>>;
+
+	/// The share of the input of every swap which is kept by the pool.
+	type SwapFee: Get<Permill>;
+
+	/// The amount of liquidity tokens of the first deposit into a pool which are never
+	/// redeemed, so that the reserves of a pool can't be drained.
+	type MinimumLiquidity: Get<BalanceOf<Self>>;
+}
+
+/// The pool of an asset.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PoolInfo<AssetId> {
+	/// The asset counting the shares of the liquidity providers of the pool.
+	pub lp_token: AssetId,
+}
+
+/// A swap through a single pool.
+#[derive(Clone, Copy)]
+struct Hop<AssetId> {
+	/// The asset of the pool.
+	asset: AssetId,
+	/// Whether the native currency is sold for the asset, or the other way around.
+	sells_native: bool,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as AssetConversion {
+		/// The pools, by the asset they pair with the native currency.
+		Pools get(fn pools): map AssetIdOf<T> => Option<PoolInfo<AssetIdOf<T>>>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as frame_system::Trait>::AccountId,
+		Balance = BalanceOf<T>,
+		AssetId = AssetIdOf<T>,
+		AssetKind = AssetKindOf<T>,
+	{
+		/// The pool of an asset was created, with the given liquidity token.
+		PoolCreated(AccountId, AssetId, AssetId),
+		/// Native currency and an asset were deposited into its pool, for liquidity tokens.
+		LiquidityAdded(AccountId, AssetId, Balance, Balance, Balance),
+		/// Liquidity tokens were redeemed for native currency and an asset of its pool.
+		LiquidityRemoved(AccountId, AssetId, Balance, Balance, Balance),
+		/// An amount of the first kind was swapped for an amount of the second kind.
+		SwapExecuted(AccountId, AssetKind, AssetKind, Balance, Balance),
+	}
+);
+
+decl_error! {
+	/// Error for the asset conversion module.
+	pub enum Error for Module<T: Trait> {
+		/// The asset was never issued.
+		UnknownAsset,
+		/// The pool of the asset already exists.
+		PoolExists,
+		/// There is no pool for the asset.
+		PoolNotFound,
+		/// The amount is zero.
+		ZeroAmount,
+		/// The assets sold and bought are the same.
+		SameAsset,
+		/// The reserves of the pool are too small.
+		InsufficientLiquidity,
+		/// Too few liquidity tokens would be minted.
+		InsufficientLiquidityMinted,
+		/// An amount is below the given minimum.
+		AmountBelowMinimum,
+		/// An amount is above the given maximum.
+		AmountAboveMaximum,
+		/// The balance of the account is too low.
+		BalanceLow,
+		/// The amount received would leave the account below the existential deposit.
+		BelowMinimumBalance,
+		/// An amount doesn't fit into the balance type.
+		Overflow,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		/// The share of the input of every swap which is kept by the pool.
+		const SwapFee: Permill = T::SwapFee::get();
+
+		/// The liquidity tokens of the first deposit into a pool which are never redeemed.
+		const MinimumLiquidity: BalanceOf<T> = T::MinimumLiquidity::get();
+
+		fn deposit_event() = default;
+
+		/// Create the pool of `asset` and its liquidity token.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - Two storage writes.
+		/// - One event.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(100_000)]
+		fn create_pool(origin, asset: AssetIdOf<T>) {
+			let who = ensure_signed(origin)?;
+			ensure!(asset < Assets::<T>::next_asset_id(), Error::<T>::UnknownAsset);
+			ensure!(!<Pools<T>>::exists(asset), Error::<T>::PoolExists);
+
+			let lp_token = Assets::<T>::create();
+			<Pools<T>>::insert(asset, PoolInfo { lp_token });
+
+			Self::deposit_event(RawEvent::PoolCreated(who, asset, lp_token));
+		}
+
+		/// Deposit native currency and `asset` into the pool of `asset` for liquidity tokens.
+		///
+		/// The amounts deposited are in the ratio of the reserves, up to the desired amounts.
+		/// The call fails if this requires depositing less than the given minimum amounts.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - Four storage reads, seven storage writes.
+		/// - Three events.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(200_000)]
+		fn add_liquidity(origin,
+			asset: AssetIdOf<T>,
+			native_desired: BalanceOf<T>,
+			asset_desired: BalanceOf<T>,
+			native_min: BalanceOf<T>,
+			asset_min: BalanceOf<T>,
+		) {
+			let who = ensure_signed(origin)?;
+			let pool = Self::pools(asset).ok_or(Error::<T>::PoolNotFound)?;
+			let pool_account = Self::pool_account(asset);
+			let (native_reserve, asset_reserve) = Self::pool_reserves(asset);
+			let total_liquidity = Assets::<T>::total_supply(pool.lp_token);
+
+			let (native_amount, asset_amount) = if total_liquidity.is_zero() {
+				(native_desired, asset_desired)
+			} else {
+				let asset_optimal = Self::mul_div(native_desired, asset_reserve, native_reserve)?;
+				if asset_optimal <= asset_desired {
+					ensure!(asset_optimal >= asset_min, Error::<T>::AmountBelowMinimum);
+					(native_desired, asset_optimal)
+				} else {
+					let native_optimal = Self::mul_div(asset_desired, native_reserve, asset_reserve)?;
+					ensure!(native_optimal >= native_min, Error::<T>::AmountBelowMinimum);
+					(native_optimal, asset_desired)
+				}
+			};
+			ensure!(!native_amount.is_zero() && !asset_amount.is_zero(), Error::<T>::ZeroAmount);
+
+			let (liquidity, locked) = if total_liquidity.is_zero() {
+				let product = Self::to_u256(native_amount) * Self::to_u256(asset_amount);
+				let liquidity = Self::from_u256(product.integer_sqrt())?;
+				let locked = T::MinimumLiquidity::get();
+				ensure!(liquidity > locked, Error::<T>::InsufficientLiquidityMinted);
+				(liquidity - locked, locked)
+			} else {
+				let liquidity = Self::mul_div(native_amount, total_liquidity, native_reserve)?
+					.min(Self::mul_div(asset_amount, total_liquidity, asset_reserve)?);
+				ensure!(!liquidity.is_zero(), Error::<T>::InsufficientLiquidityMinted);
+				(liquidity, Zero::zero())
+			};
+			total_liquidity.checked_add(&liquidity)
+				.and_then(|total| total.checked_add(&locked))
+				.ok_or(Error::<T>::Overflow)?;
+
+			Self::ensure_can_transfer(AssetKind::Asset(asset), &who, &pool_account, asset_amount)?;
+			Self::ensure_can_transfer(AssetKind::Native, &who, &pool_account, native_amount)?;
+			Self::transfer(AssetKind::Native, &who, &pool_account, native_amount)?;
+			Self::transfer(AssetKind::Asset(asset), &who, &pool_account, asset_amount)?;
+			if !locked.is_zero() {
+				Assets::<T>::mint(pool.lp_token, &pool_account, locked)?;
+			}
+			Assets::<T>::mint(pool.lp_token, &who, liquidity)?;
+
+			Self::deposit_event(RawEvent::LiquidityAdded(who, asset, native_amount, asset_amount, liquidity));
+		}
+
+		/// Redeem `liquidity` tokens of the pool of `asset` for their share of its reserves.
+		///
+		/// The call fails if the share is less than the given minimum amounts.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - Four storage reads, six storage writes.
+		/// - Three events.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(200_000)]
+		fn remove_liquidity(origin,
+			asset: AssetIdOf<T>,
+			liquidity: BalanceOf<T>,
+			native_min: BalanceOf<T>,
+			asset_min: BalanceOf<T>,
+		) {
+			let who = ensure_signed(origin)?;
+			let pool = Self::pools(asset).ok_or(Error::<T>::PoolNotFound)?;
+			let pool_account = Self::pool_account(asset);
+			let (native_reserve, asset_reserve) = Self::pool_reserves(asset);
+			let total_liquidity = Assets::<T>::total_supply(pool.lp_token);
+
+			ensure!(!liquidity.is_zero(), Error::<T>::ZeroAmount);
+			ensure!(Assets::<T>::balance(pool.lp_token, who.clone()) >= liquidity, Error::<T>::BalanceLow);
+
+			let native_amount = Self::mul_div(liquidity, native_reserve, total_liquidity)?;
+			let asset_amount = Self::mul_div(liquidity, asset_reserve, total_liquidity)?;
+			ensure!(
+				native_amount >= native_min && asset_amount >= asset_min,
+				Error::<T>::AmountBelowMinimum,
+			);
+
+			Self::ensure_can_transfer(AssetKind::Native, &pool_account, &who, native_amount)?;
+			Self::transfer(AssetKind::Native, &pool_account, &who, native_amount)?;
+			Self::transfer(AssetKind::Asset(asset), &pool_account, &who, asset_amount)?;
+			Assets::<T>::burn(pool.lp_token, &who, liquidity)?;
+
+			Self::deposit_event(RawEvent::LiquidityRemoved(who, asset, native_amount, asset_amount, liquidity));
+		}
+
+		/// Sell `amount_in` of `from` for at least `amount_out_min` of `to`.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1), at most two pools are on the route.
+		/// - Up to six storage reads, up to six storage writes.
+		/// - Up to four events.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(200_000)]
+		fn swap_exact_in(origin,
+			from: AssetKindOf<T>,
+			to: AssetKindOf<T>,
+			amount_in: BalanceOf<T>,
+			amount_out_min: BalanceOf<T>,
+		) {
+			let who = ensure_signed(origin)?;
+			ensure!(!amount_in.is_zero(), Error::<T>::ZeroAmount);
+
+			let route = Self::route(from, to)?;
+			let amounts = Self::amounts_out(&route, amount_in)?;
+			let amount_out = *amounts.last().expect("route has at least one hop; qed");
+			ensure!(amount_out >= amount_out_min, Error::<T>::AmountBelowMinimum);
+
+			Self::swap(&who, &route, &amounts)?;
+
+			Self::deposit_event(RawEvent::SwapExecuted(who, from, to, amount_in, amount_out));
+		}
+
+		/// Buy `amount_out` of `to` for at most `amount_in_max` of `from`.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1), at most two pools are on the route.
+		/// - Up to six storage reads, up to six storage writes.
+		/// - Up to four events.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(200_000)]
+		fn swap_exact_out(origin,
+			from: AssetKindOf<T>,
+			to: AssetKindOf<T>,
+			amount_out: BalanceOf<T>,
+			amount_in_max: BalanceOf<T>,
+		) {
+			let who = ensure_signed(origin)?;
+			ensure!(!amount_out.is_zero(), Error::<T>::ZeroAmount);
+
+			let route = Self::route(from, to)?;
+			let amounts = Self::amounts_in(&route, amount_out)?;
+			let amount_in = amounts[0];
+			ensure!(amount_in <= amount_in_max, Error::<T>::AmountAboveMaximum);
+
+			Self::swap(&who, &route, &amounts)?;
+
+			Self::deposit_event(RawEvent::SwapExecuted(who, from, to, amount_in, amount_out));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The account holding the reserves of the pool of `asset`.
+	pub fn pool_account(asset: AssetIdOf<T>) -> T::AccountId {
+		MODULE_ID.into_sub_account(asset)
+	}
+
+	/// The reserves of the native currency and of `asset` in the pool of `asset`, if it exists.
+	pub fn reserves(asset: AssetIdOf<T>) -> Option<(BalanceOf<T>, BalanceOf<T>)> {
+		Self::pools(asset).map(|_| Self::pool_reserves(asset))
+	}
+
+	/// The amount of `to` received for selling `amount_in` of `from`.
+	pub fn quote_exact_in(
+		from: AssetKindOf<T>,
+		to: AssetKindOf<T>,
+		amount_in: BalanceOf<T>,
+	) -> Option<BalanceOf<T>> {
+		let route = Self::route(from, to).ok()?;
+		Self::amounts_out(&route, amount_in).ok()?.last().cloned()
+	}
+
+	/// The amount of `from` to sell for receiving `amount_out` of `to`.
+	pub fn quote_exact_out(
+		from: AssetKindOf<T>,
+		to: AssetKindOf<T>,
+		amount_out: BalanceOf<T>,
+	) -> Option<BalanceOf<T>> {
+		let route = Self::route(from, to).ok()?;
+		Self::amounts_in(&route, amount_out).ok()?.first().cloned()
+	}
+
+	/// Sell at most `amount_in_max` of `asset` of `who` for exactly `amount_out` of the native
+	/// currency, which is withdrawn from the pool of `asset` and returned as an imbalance instead
+	/// of being credited to `who`.
+	///
+	/// Returns the amount of `asset` sold. Nothing is changed if the swap fails.
+	pub fn buy_native(
+		who: &T::AccountId,
+		asset: AssetIdOf<T>,
+		amount_out: BalanceOf<T>,
+		amount_in_max: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, NegativeImbalanceOf<T>), DispatchError> {
+		ensure!(!amount_out.is_zero(), Error::<T>::ZeroAmount);
+		let route = Self::route(AssetKind::Asset(asset), AssetKind::Native)?;
+		let amount_in = Self::amounts_in(&route, amount_out)?[0];
+		ensure!(amount_in <= amount_in_max, Error::<T>::AmountAboveMaximum);
+		let pool_account = Self::pool_account(asset);
+
+		Self::ensure_can_transfer(AssetKind::Asset(asset), who, &pool_account, amount_in)?;
+		let imbalance = T::Currency::withdraw(
+			&pool_account,
+			amount_out,
+			WithdrawReason::Transfer.into(),
+			KeepAlive,
+		)?;
+		Self::transfer(AssetKind::Asset(asset), who, &pool_account, amount_in)?;
+
+		Self::deposit_event(RawEvent::SwapExecuted(
+			who.clone(),
+			AssetKind::Asset(asset),
+			AssetKind::Native,
+			amount_in,
+			amount_out,
+		));
+		Ok((amount_in, imbalance))
+	}
+
+	fn pool_reserves(asset: AssetIdOf<T>) -> (BalanceOf<T>, BalanceOf<T>) {
+		let pool_account = Self::pool_account(asset);
+		(T::Currency::free_balance(&pool_account), Assets::<T>::balance(asset, pool_account))
+	}
+
+	/// The pools to swap `from` for `to` through.
+	fn route(
+		from: AssetKindOf<T>,
+		to: AssetKindOf<T>,
+	) -> Result<Vec<Hop<AssetIdOf<T>>>, Error<T>> {
+		let route = match (from, to) {
+			(AssetKind::Native, AssetKind::Native) => return Err(Error::<T>::SameAsset),
+			(AssetKind::Asset(from), AssetKind::Asset(to)) if from == to =>
+				return Err(Error::<T>::SameAsset),
+			(AssetKind::Native, AssetKind::Asset(asset)) => vec![Hop { asset, sells_native: true }],
+			(AssetKind::Asset(asset), AssetKind::Native) => vec![Hop { asset, sells_native: false }],
+			(AssetKind::Asset(from), AssetKind::Asset(to)) => vec![
+				Hop { asset: from, sells_native: false },
+				Hop { asset: to, sells_native: true },
+			],
+		};
+		for hop in &route {
+			ensure!(<Pools<T>>::exists(hop.asset), Error::<T>::PoolNotFound);
+		}
+		Ok(route)
+	}
+
+	/// The reserves of the kind sold and of the kind bought in the pool of `hop`.
+	fn hop_reserves(hop: &Hop<AssetIdOf<T>>) -> (BalanceOf<T>, BalanceOf<T>) {
+		let (native_reserve, asset_reserve) = Self::pool_reserves(hop.asset);
+		if hop.sells_native {
+			(native_reserve, asset_reserve)
+		} else {
+			(asset_reserve, native_reserve)
+		}
+	}
+
+	/// The kinds sold and bought in the pool of `hop`.
+	fn hop_kinds(hop: &Hop<AssetIdOf<T>>) -> (AssetKindOf<T>, AssetKindOf<T>) {
+		if hop.sells_native {
+			(AssetKind::Native, AssetKind::Asset(hop.asset))
+		} else {
+			(AssetKind::Asset(hop.asset), AssetKind::Native)
+		}
+	}
+
+	/// The amounts going into each pool of `route` and the amount coming out of the last one
+	/// when selling `amount_in`.
+	fn amounts_out(
+		route: &[Hop<AssetIdOf<T>>],
+		amount_in: BalanceOf<T>,
+	) -> Result<Vec<BalanceOf<T>>, Error<T>> {
+		let mut amounts = vec![amount_in];
+		for hop in route {
+			let (reserve_in, reserve_out) = Self::hop_reserves(hop);
+			let amount_out = Self::amount_out(amounts[amounts.len() - 1], reserve_in, reserve_out)?;
+			amounts.push(amount_out);
+		}
+		Ok(amounts)
+	}
+
+	/// The amounts going into each pool of `route` and the amount coming out of the last one
+	/// when buying `amount_out`.
+	fn amounts_in(
+		route: &[Hop<AssetIdOf<T>>],
+		amount_out: BalanceOf<T>,
+	) -> Result<Vec<BalanceOf<T>>, Error<T>> {
+		let mut amounts = vec![amount_out];
+		for hop in route.iter().rev() {
+			let (reserve_in, reserve_out) = Self::hop_reserves(hop);
+			let amount_in = Self::amount_in(amounts[amounts.len() - 1], reserve_in, reserve_out)?;
+			amounts.push(amount_in);
+		}
+		amounts.reverse();
+		Ok(amounts)
+	}
+
+	/// The amount bought for selling `amount_in` to a pool with the given reserves.
+	///
+	/// `amount_out = amount_in' * reserve_out / (reserve_in + amount_in')`, where `amount_in'`
+	/// is `amount_in` minus the swap fee.
+	fn amount_out(
+		amount_in: BalanceOf<T>,
+		reserve_in: BalanceOf<T>,
+		reserve_out: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, Error<T>> {
+		ensure!(!reserve_in.is_zero() && !reserve_out.is_zero(), Error::<T>::InsufficientLiquidity);
+
+		let (accuracy, fee) = Self::fee_parts();
+		let amount_in = Self::to_u256(amount_in) * (accuracy - fee);
+		let numerator = amount_in.checked_mul(Self::to_u256(reserve_out))
+			.ok_or(Error::<T>::Overflow)?;
+		let denominator = (Self::to_u256(reserve_in) * accuracy).checked_add(amount_in)
+			.ok_or(Error::<T>::Overflow)?;
+		Self::from_u256(numerator / denominator)
+	}
+
+	/// The amount to sell to a pool with the given reserves for buying `amount_out`, rounded up.
+	fn amount_in(
+		amount_out: BalanceOf<T>,
+		reserve_in: BalanceOf<T>,
+		reserve_out: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, Error<T>> {
+		ensure!(
+			!reserve_in.is_zero() && amount_out < reserve_out,
+			Error::<T>::InsufficientLiquidity,
+		);
+
+		let (accuracy, fee) = Self::fee_parts();
+		let numerator = (Self::to_u256(reserve_in) * Self::to_u256(amount_out)).checked_mul(accuracy)
+			.ok_or(Error::<T>::Overflow)?;
+		let denominator = Self::to_u256(reserve_out - amount_out) * (accuracy - fee);
+		Self::from_u256(numerator / denominator + 1)
+	}
+
+	/// Move the `amounts` along `route`, from `who` through the pools back to `who`.
+	///
+	/// All transfers are checked before any is made, so that a failing swap changes nothing.
+	fn swap(
+		who: &T::AccountId,
+		route: &[Hop<AssetIdOf<T>>],
+		amounts: &[BalanceOf<T>],
+	) -> DispatchResult {
+		let mut transfers = Vec::with_capacity(route.len() + 1);
+		let (first_in, _) = Self::hop_kinds(&route[0]);
+		transfers.push((first_in, who.clone(), Self::pool_account(route[0].asset), amounts[0]));
+		for (i, hop) in route.iter().enumerate() {
+			let (_, kind_out) = Self::hop_kinds(hop);
+			let dest = match route.get(i + 1) {
+				Some(next) => Self::pool_account(next.asset),
+				None => who.clone(),
+			};
+			let (_, reserve_out) = Self::hop_reserves(hop);
+			if kind_out == AssetKind::Native {
+				ensure!(
+					reserve_out.saturating_sub(amounts[i + 1]) >= T::Currency::minimum_balance(),
+					Error::<T>::InsufficientLiquidity,
+				);
+			}
+			transfers.push((kind_out, Self::pool_account(hop.asset), dest, amounts[i + 1]));
+		}
+
+		for (kind, source, dest, amount) in &transfers {
+			Self::ensure_can_transfer(*kind, source, dest, *amount)?;
+		}
+		for (kind, source, dest, amount) in &transfers {
+			Self::transfer(*kind, source, dest, *amount)?;
+		}
+		Ok(())
+	}
+
+	/// Ensure that `transfer` succeeds for the given arguments.
+	fn ensure_can_transfer(
+		kind: AssetKindOf<T>,
+		source: &T::AccountId,
+		dest: &T::AccountId,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		match kind {
+			AssetKind::Native => {
+				let new_balance = T::Currency::free_balance(source)
+					.checked_sub(&amount)
+					.ok_or(Error::<T>::BalanceLow)?;
+				ensure!(new_balance >= T::Currency::minimum_balance(), Error::<T>::BalanceLow);
+				T::Currency::ensure_can_withdraw(
+					source,
+					amount,
+					WithdrawReason::Transfer.into(),
+					new_balance,
+				)?;
+				ensure!(
+					T::Currency::free_balance(dest).saturating_add(amount) >= T::Currency::minimum_balance(),
+					Error::<T>::BelowMinimumBalance,
+				);
+			}
+			AssetKind::Asset(asset) => ensure!(
+				Assets::<T>::balance(asset, source.clone()) >= amount,
+				Error::<T>::BalanceLow,
+			),
+		}
+		Ok(())
+	}
+
+	/// Move `amount` of `kind` from `source` to `dest`, without transfer fees.
+	fn transfer(
+		kind: AssetKindOf<T>,
+		source: &T::AccountId,
+		dest: &T::AccountId,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		match kind {
+			AssetKind::Native => {
+				let imbalance = T::Currency::withdraw(
+					source,
+					amount,
+					WithdrawReason::Transfer.into(),
+					KeepAlive,
+				)?;
+				T::Currency::resolve_creating(dest, imbalance);
+				Ok(())
+			}
+			AssetKind::Asset(asset) => Assets::<T>::transfer_from(asset, source, dest, amount),
+		}
+	}
+
+	/// The accuracy of `Permill` and the parts of it taken as swap fee.
+	fn fee_parts() -> (U256, U256) {
+		(U256::from(Permill::one().deconstruct()), U256::from(T::SwapFee::get().deconstruct()))
+	}
+
+	/// `a * b / c`, rounded down.
+	fn mul_div(a: BalanceOf<T>, b: BalanceOf<T>, c: BalanceOf<T>) -> Result<BalanceOf<T>, Error<T>> {
+		ensure!(!c.is_zero(), Error::<T>::InsufficientLiquidity);
+		Self::from_u256(Self::to_u256(a) * Self::to_u256(b) / Self::to_u256(c))
+	}
+
+	fn to_u256(balance: BalanceOf<T>) -> U256 {
+		U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(balance))
+	}
+
+	fn from_u256(value: U256) -> Result<BalanceOf<T>, Error<T>> {
+		if value > U256::from(u128::max_value()) {
+			return Err(Error::<T>::Overflow)
+		}
+		BalanceOf::<T>::try_from(value.low_u128()).map_err(|_| Error::<T>::Overflow)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use frame_support::{
+		assert_ok, assert_noop, impl_outer_origin, parameter_types, traits::Imbalance,
+		weights::Weight,
+	};
+	use sp_core::H256;
+	use sp_runtime::{Perbill, testing::Header, traits::{BlakeTwo256, IdentityLookup}};
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = ();
+		type Hashing = BlakeTwo256;
+		// Long enough for the pool accounts of different assets to differ.
+		type AccountId = u128;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u128 = 1;
+		pub const TransferFee: u128 = 0;
+		pub const CreationFee: u128 = 0;
+	}
+	impl pallet_balances::Trait for Test {
+		type Balance = u128;
+		type OnFreeBalanceZero = ();
+		type OnReapAccount = System;
+		type OnNewAccount = ();
+		type Event = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+	}
+	impl pallet_assets::Trait for Test {
+		type Event = ();
+		type Balance = u128;
+		type AssetId = u32;
+	}
+	parameter_types! {
+		pub const SwapFee: Permill = Permill::from_percent(1);
+		pub const MinimumLiquidity: u128 = 1_000;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Currency = Balances;
+		type SwapFee = SwapFee;
+		type MinimumLiquidity = MinimumLiquidity;
+	}
+	type System = frame_system::Module<Test>;
+	type Balances = pallet_balances::Module<Test>;
+	type TestAssets = pallet_assets::Module<Test>;
+	type AssetConversion = Module<Test>;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(1, 1_000_000_000), (2, 1_000_000_000)],
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		let mut ext: sp_io::TestExternalities = t.into();
+		ext.execute_with(|| {
+			// Assets 0 and 1, owned by account 1.
+			assert_ok!(TestAssets::issue(Origin::signed(1), 1_000_000_000));
+			assert_ok!(TestAssets::issue(Origin::signed(1), 1_000_000_000));
+		});
+		ext
+	}
+
+	fn create_pool_with(asset: u32, native: u128, amount: u128) -> u32 {
+		assert_ok!(AssetConversion::create_pool(Origin::signed(1), asset));
+		assert_ok!(AssetConversion::add_liquidity(Origin::signed(1), asset, native, amount, 0, 0));
+		AssetConversion::pools(asset).unwrap().lp_token
+	}
+
+	#[test]
+	fn liquidity_can_be_added_and_removed() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(AssetConversion::create_pool(Origin::signed(1), 0));
+			assert_noop!(AssetConversion::create_pool(Origin::signed(2), 0), Error::<Test>::PoolExists);
+			assert_noop!(AssetConversion::create_pool(Origin::signed(2), 5), Error::<Test>::UnknownAsset);
+			let lp_token = AssetConversion::pools(0).unwrap().lp_token;
+			assert_eq!(lp_token, 2);
+
+			assert_ok!(AssetConversion::add_liquidity(Origin::signed(1), 0, 10_000, 40_000, 0, 0));
+			assert_eq!(AssetConversion::reserves(0), Some((10_000, 40_000)));
+			// sqrt(10_000 * 40_000) minus the locked liquidity.
+			assert_eq!(TestAssets::balance(lp_token, 1), 19_000);
+			assert_eq!(TestAssets::total_supply(lp_token), 20_000);
+
+			// Deposits are capped to the ratio of the reserves.
+			assert_noop!(
+				AssetConversion::add_liquidity(Origin::signed(1), 0, 1_000, 10_000, 0, 5_000),
+				Error::<Test>::AmountBelowMinimum,
+			);
+			assert_ok!(AssetConversion::add_liquidity(Origin::signed(1), 0, 1_000, 10_000, 0, 0));
+			assert_eq!(AssetConversion::reserves(0), Some((11_000, 44_000)));
+			assert_eq!(TestAssets::balance(lp_token, 1), 21_000);
+
+			assert_ok!(AssetConversion::remove_liquidity(Origin::signed(1), 0, 11_000, 0, 0));
+			assert_eq!(AssetConversion::reserves(0), Some((5_500, 22_000)));
+			assert_eq!(TestAssets::balance(lp_token, 1), 10_000);
+			assert_noop!(
+				AssetConversion::remove_liquidity(Origin::signed(1), 0, 10_001, 0, 0),
+				Error::<Test>::BalanceLow,
+			);
+		});
+	}
+
+	#[test]
+	fn first_deposit_must_exceed_minimum_liquidity() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(AssetConversion::create_pool(Origin::signed(1), 0));
+			assert_noop!(
+				AssetConversion::add_liquidity(Origin::signed(1), 0, 1_000, 1_000, 0, 0),
+				Error::<Test>::InsufficientLiquidityMinted,
+			);
+		});
+	}
+
+	#[test]
+	fn swaps_keep_the_product_of_reserves() {
+		new_test_ext().execute_with(|| {
+			create_pool_with(0, 1_000_000, 2_000_000);
+
+			let quote = AssetConversion::quote_exact_in(AssetKind::Native, AssetKind::Asset(0), 10_000);
+			// 9_900 * 2_000_000 / (1_000_000 + 9_900)
+			assert_eq!(quote, Some(19_605));
+			assert_noop!(
+				AssetConversion::swap_exact_in(Origin::signed(2), AssetKind::Native, AssetKind::Asset(0), 10_000, 19_606),
+				Error::<Test>::AmountBelowMinimum,
+			);
+			assert_ok!(
+				AssetConversion::swap_exact_in(Origin::signed(2), AssetKind::Native, AssetKind::Asset(0), 10_000, 19_605),
+			);
+			assert_eq!(TestAssets::balance(0, 2), 19_605);
+			assert_eq!(Balances::free_balance(&2), 1_000_000_000 - 10_000);
+			assert_eq!(AssetConversion::reserves(0), Some((1_010_000, 2_000_000 - 19_605)));
+
+			let quote = AssetConversion::quote_exact_out(AssetKind::Asset(0), AssetKind::Native, 5_000).unwrap();
+			assert_ok!(
+				AssetConversion::swap_exact_out(Origin::signed(2), AssetKind::Asset(0), AssetKind::Native, 5_000, quote),
+			);
+			assert_eq!(TestAssets::balance(0, 2), 19_605 - quote);
+			assert_eq!(Balances::free_balance(&2), 1_000_000_000 - 5_000);
+			// Selling one unit less wouldn't have been enough.
+			assert!(AssetConversion::amount_out(quote - 1, 2_000_000 - 19_605, 1_010_000).unwrap() < 5_000);
+		});
+	}
+
+	#[test]
+	fn assets_are_swapped_through_native_currency() {
+		new_test_ext().execute_with(|| {
+			create_pool_with(0, 1_000_000, 1_000_000);
+			create_pool_with(1, 1_000_000, 1_000_000);
+			assert_ok!(TestAssets::transfer(Origin::signed(1), 0, 2, 10_000));
+
+			let quote = AssetConversion::quote_exact_in(AssetKind::Asset(0), AssetKind::Asset(1), 10_000).unwrap();
+			assert_ok!(
+				AssetConversion::swap_exact_in(Origin::signed(2), AssetKind::Asset(0), AssetKind::Asset(1), 10_000, quote),
+			);
+			assert_eq!(TestAssets::balance(0, 2), 0);
+			assert_eq!(TestAssets::balance(1, 2), quote);
+			let (native_0, _) = AssetConversion::reserves(0).unwrap();
+			let (native_1, _) = AssetConversion::reserves(1).unwrap();
+			assert_eq!(native_0 + native_1, 2_000_000);
+
+			assert_noop!(
+				AssetConversion::swap_exact_in(Origin::signed(2), AssetKind::Asset(1), AssetKind::Asset(1), 1, 0),
+				Error::<Test>::SameAsset,
+			);
+			assert_noop!(
+				AssetConversion::swap_exact_in(Origin::signed(2), AssetKind::Native, AssetKind::Asset(3), 1, 0),
+				Error::<Test>::PoolNotFound,
+			);
+		});
+	}
+
+	#[test]
+	fn failing_swaps_change_nothing() {
+		new_test_ext().execute_with(|| {
+			create_pool_with(0, 1_000_000, 1_000_000);
+
+			// Account 2 holds no asset 0.
+			assert_noop!(
+				AssetConversion::swap_exact_in(Origin::signed(2), AssetKind::Asset(0), AssetKind::Native, 1_000, 0),
+				Error::<Test>::BalanceLow,
+			);
+			assert_noop!(
+				AssetConversion::swap_exact_out(Origin::signed(2), AssetKind::Native, AssetKind::Asset(0), 1_000_000, u128::max_value()),
+				Error::<Test>::InsufficientLiquidity,
+			);
+		});
+	}
+
+	#[test]
+	fn large_reserves_overflow_instead_of_panicking() {
+		new_test_ext().execute_with(|| {
+			let max = u128::max_value();
+			assert_eq!(
+				AssetConversion::amount_out(max / 2, max, max).map_err(DispatchError::from),
+				Err(Error::<Test>::Overflow.into()),
+			);
+			assert_eq!(
+				AssetConversion::amount_in(max / 2, max, max).map_err(DispatchError::from),
+				Err(Error::<Test>::Overflow.into()),
+			);
+			// Small amounts still fit.
+			assert_eq!(AssetConversion::amount_out(1_000, max, max).map_err(DispatchError::from), Ok(989));
+		});
+	}
+
+	#[test]
+	fn native_can_be_bought_as_imbalance() {
+		new_test_ext().execute_with(|| {
+			create_pool_with(0, 1_000_000, 1_000_000);
+			assert_ok!(TestAssets::transfer(Origin::signed(1), 0, 2, 20_000));
+			let issuance = Balances::total_issuance();
+
+			assert_noop!(
+				AssetConversion::buy_native(&2, 0, 10_000, 10_203).map(|(sold, _)| sold),
+				Error::<Test>::AmountAboveMaximum,
+			);
+			let (sold, imbalance) = AssetConversion::buy_native(&2, 0, 10_000, 10_204).unwrap();
+			assert_eq!(sold, 10_204);
+			assert_eq!(imbalance.peek(), 10_000);
+			assert_eq!(TestAssets::balance(0, 2), 9_796);
+			assert_eq!(AssetConversion::reserves(0), Some((990_000, 1_010_204)));
+			drop(imbalance);
+			assert_eq!(Balances::total_issuance(), issuance - 10_000);
+
+			assert_noop!(
+				AssetConversion::buy_native(&2, 0, 10_000, 20_000).map(|(sold, _)| sold),
+				Error::<Test>::BalanceLow,
+			);
+			assert_noop!(
+				AssetConversion::buy_native(&2, 1, 10, 20_000).map(|(sold, _)| sold),
+				Error::<Test>::PoolNotFound,
+			);
+		});
+	}
+}
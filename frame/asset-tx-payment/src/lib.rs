@@ -0,0 +1,443 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Asset Transaction Payment Module
+//!
+//! - [`asset_tx_payment::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Asset Transaction Payment module lets transactions pay their fees in an asset of the
+//! [Assets module](../pallet_assets/index.html) instead of the native currency.
+//!
+//! The [`ChargeAssetTxPayment`](./struct.ChargeAssetTxPayment.html) signed extension replaces
+//! `ChargeTransactionPayment` of the Transaction Payment module. Transactions which name no asset
+//! pay as before. Otherwise the fee, computed as for the native currency, is bought with the
+//! named asset from its pool in the [Asset Conversion module](../pallet_asset_conversion/index.html)
+//! when the transaction is included. The transaction also names the most of the asset it sells
+//! for the fee, and is invalid if the pool asks for more. Only assets whitelisted by
+//! `FeeAssetOrigin` are accepted.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `add_fee_asset` - Accept an asset for paying fees.
+//! * `remove_fee_asset` - Stop accepting an asset for paying fees.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Encode, Decode};
+use sp_runtime::{
+	transaction_validity::{
+		TransactionPriority, ValidTransaction, InvalidTransaction, TransactionValidityError,
+		TransactionValidity,
+	},
+	traits::{EnsureOrigin, SaturatedConversion, SignedExtension, UniqueSaturatedFrom, Zero},
+};
+use frame_support::{
+	decl_module, decl_event, decl_storage, decl_error, ensure,
+	traits::{Currency, OnUnbalanced},
+	weights::{DispatchInfo, SimpleDispatchInfo},
+};
+use frame_system::{self as system, ensure_root};
+use pallet_asset_conversion::NegativeImbalanceOf;
+use pallet_transaction_payment::ChargeTransactionPayment;
+
+type AssetIdOf<T> = <T as pallet_assets::Trait>::AssetId;
+type AssetBalanceOf<T> = <T as pallet_assets::Trait>::Balance;
+type PaymentBalanceOf<T> = <<T as pallet_transaction_payment::Trait>::Currency as Currency<
+	<T as frame_system::Trait>::AccountId,
+>>::Balance;
+type AssetConversion<T> = pallet_asset_conversion::Module<T>;
+
+pub trait Trait: pallet_transaction_payment::Trait + pallet_asset_conversion::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The origin which may change the assets fees can be paid in. Root can always do this.
+	type FeeAssetOrigin: EnsureOrigin<Self::Origin>;
+
+	/// Handler for the native currency bought with assets to pay fees.
+	type OnAssetFeePayment: OnUnbalanced<NegativeImbalanceOf<Self>>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as AssetTxPayment {
+		/// Whether fees can be paid in an asset.
+		FeeAssets get(fn is_fee_asset): map AssetIdOf<T> => bool;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as frame_system::Trait>::AccountId,
+		AssetId = AssetIdOf<T>,
+		Balance = AssetBalanceOf<T>,
+	{
+		/// Fees can be paid in the asset.
+		FeeAssetAdded(AssetId),
+		/// Fees can no longer be paid in the asset.
+		FeeAssetRemoved(AssetId),
+		/// A fee was paid with an amount of the asset, bought for the given native amount.
+		AssetTxFeePaid(AccountId, AssetId, Balance, Balance),
+	}
+);
+
+decl_error! {
+	/// Error for the asset transaction payment module.
+	pub enum Error for Module<T: Trait> {
+		/// There is no pool for the asset.
+		PoolNotFound,
+		/// Fees can already be paid in the asset.
+		AlreadyFeeAsset,
+		/// Fees can't be paid in the asset.
+		NotFeeAsset,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Accept `asset` for paying fees. The asset must have a pool to buy the fees from.
+		///
+		/// The dispatch origin for this call must be _Root_ or match `T::FeeAssetOrigin`.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - Two storage reads, one storage write.
+		/// - One event.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		fn add_fee_asset(origin, asset: AssetIdOf<T>) {
+			T::FeeAssetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(AssetConversion::<T>::pools(asset).is_some(), Error::<T>::PoolNotFound);
+			ensure!(!Self::is_fee_asset(asset), Error::<T>::AlreadyFeeAsset);
+			<FeeAssets<T>>::insert(asset, true);
+
+			Self::deposit_event(RawEvent::FeeAssetAdded(asset));
+		}
+
+		/// Stop accepting `asset` for paying fees.
+		///
+		/// The dispatch origin for this call must be _Root_ or match `T::FeeAssetOrigin`.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One storage read, one storage write.
+		/// - One event.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		fn remove_fee_asset(origin, asset: AssetIdOf<T>) {
+			T::FeeAssetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(Self::is_fee_asset(asset), Error::<T>::NotFeeAsset);
+			<FeeAssets<T>>::remove(asset);
+
+			Self::deposit_event(RawEvent::FeeAssetRemoved(asset));
+		}
+	}
+}
+
+/// Require the transactor pay for themselves, in the native currency or in a whitelisted asset,
+/// and maybe include a tip to gain additional priority in the queue.
+///
+/// The tip is measured in the native currency, also when paying in an asset. The asset comes
+/// with the most of it which may be sold for the fee and the tip.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct ChargeAssetTxPayment<T: Trait + Send + Sync> {
+	#[codec(compact)]
+	tip: PaymentBalanceOf<T>,
+	asset: Option<(AssetIdOf<T>, AssetBalanceOf<T>)>,
+}
+
+impl<T: Trait + Send + Sync> ChargeAssetTxPayment<T> {
+	/// utility constructor. Used only in client/factory code.
+	pub fn from(tip: PaymentBalanceOf<T>, asset: Option<(AssetIdOf<T>, AssetBalanceOf<T>)>) -> Self {
+		Self { tip, asset }
+	}
+}
+
+impl<T: Trait + Send + Sync> sp_std::fmt::Debug for ChargeAssetTxPayment<T> {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		write!(f, "ChargeAssetTxPayment<{:?}, {:?}>", self.tip, self.asset)
+	}
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		Ok(())
+	}
+}
+
+impl<T: Trait + Send + Sync> SignedExtension for ChargeAssetTxPayment<T> where
+	PaymentBalanceOf<T>: Send + Sync,
+	AssetIdOf<T>: Send + Sync,
+	AssetBalanceOf<T>: Send + Sync,
+{
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Trait>::Call;
+	type AdditionalSigned = ();
+	type DispatchInfo = DispatchInfo;
+	type Pre = ();
+	fn additional_signed(&self) -> sp_std::result::Result<(), TransactionValidityError> { Ok(()) }
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: Self::DispatchInfo,
+		len: usize,
+	) -> TransactionValidity {
+		let (asset, max_asset_fee) = match self.asset {
+			Some(asset) => asset,
+			None => return ChargeTransactionPayment::<T>::from(self.tip).validate(who, call, info, len),
+		};
+		if !<Module<T>>::is_fee_asset(asset) {
+			return InvalidTransaction::Payment.into()
+		}
+
+		let fee = ChargeTransactionPayment::<T>::compute_fee(len as u32, info, self.tip);
+		// Only mess with balances if fee is not zero.
+		if !fee.is_zero() {
+			let native_fee = AssetBalanceOf::<T>::unique_saturated_from(fee.saturated_into::<u128>());
+			let bought = AssetConversion::<T>::buy_native(who, asset, native_fee, max_asset_fee);
+			let (asset_fee, imbalance) = match bought {
+				Ok(bought) => bought,
+				Err(_) => return InvalidTransaction::Payment.into(),
+			};
+			T::OnAssetFeePayment::on_unbalanced(imbalance);
+			<Module<T>>::deposit_event(RawEvent::AssetTxFeePaid(who.clone(), asset, asset_fee, native_fee));
+		}
+
+		let mut r = ValidTransaction::default();
+		r.priority = fee.saturated_into::<TransactionPriority>();
+		Ok(r)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use frame_support::{
+		assert_ok, assert_noop, impl_outer_origin, parameter_types, ord_parameter_types,
+		weights::Weight,
+	};
+	use frame_system::EnsureSignedBy;
+	use sp_core::H256;
+	use sp_runtime::{
+		Perbill, Permill, testing::Header, traits::{BlakeTwo256, ConvertInto, IdentityLookup, BadOrigin},
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = ();
+		type Hashing = BlakeTwo256;
+		// Long enough for the pool accounts of different assets to differ.
+		type AccountId = u128;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u128 = 1;
+		pub const TransferFee: u128 = 0;
+		pub const CreationFee: u128 = 0;
+	}
+	impl pallet_balances::Trait for Test {
+		type Balance = u128;
+		type OnFreeBalanceZero = ();
+		type OnReapAccount = System;
+		type OnNewAccount = ();
+		type Event = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+	}
+	parameter_types! {
+		pub const TransactionBaseFee: u128 = 10;
+		pub const TransactionByteFee: u128 = 1;
+	}
+	impl pallet_transaction_payment::Trait for Test {
+		type Currency = Balances;
+		type OnTransactionPayment = ();
+		type TransactionBaseFee = TransactionBaseFee;
+		type TransactionByteFee = TransactionByteFee;
+		type WeightToFee = ConvertInto;
+		type OperationalWeightToFee = ConvertInto;
+		type FeeMultiplierUpdate = ();
+	}
+	impl pallet_assets::Trait for Test {
+		type Event = ();
+		type Balance = u128;
+		type AssetId = u32;
+	}
+	parameter_types! {
+		pub const SwapFee: Permill = Permill::from_percent(1);
+		pub const MinimumLiquidity: u128 = 1_000;
+	}
+	impl pallet_asset_conversion::Trait for Test {
+		type Event = ();
+		type Currency = Balances;
+		type SwapFee = SwapFee;
+		type MinimumLiquidity = MinimumLiquidity;
+	}
+	ord_parameter_types! {
+		pub const One: u128 = 1;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type FeeAssetOrigin = EnsureSignedBy<One, u128>;
+		type OnAssetFeePayment = ();
+	}
+	type System = frame_system::Module<Test>;
+	type Balances = pallet_balances::Module<Test>;
+	type TestAssets = pallet_assets::Module<Test>;
+	type TestAssetConversion = pallet_asset_conversion::Module<Test>;
+	type AssetTxPayment = Module<Test>;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(1, 1_000_000_000), (2, 1_000)],
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		let mut ext: sp_io::TestExternalities = t.into();
+		ext.execute_with(|| {
+			// Assets 0 and 1 owned by account 1, asset 0 paired with 1_000_000 native.
+			assert_ok!(TestAssets::issue(Origin::signed(1), 1_000_000_000));
+			assert_ok!(TestAssets::issue(Origin::signed(1), 1_000_000_000));
+			assert_ok!(TestAssetConversion::create_pool(Origin::signed(1), 0));
+			assert_ok!(TestAssetConversion::add_liquidity(
+				Origin::signed(1), 0, 1_000_000, 1_000_000, 0, 0,
+			));
+			assert_ok!(TestAssets::transfer(Origin::signed(1), 0, 3, 100));
+		});
+		ext
+	}
+
+	/// Weight 5, which costs 5 with `ConvertInto`.
+	fn info() -> DispatchInfo {
+		DispatchInfo { weight: 5, pays_fee: true, ..Default::default() }
+	}
+
+	#[test]
+	fn fee_assets_can_be_added_and_removed() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(AssetTxPayment::add_fee_asset(Origin::signed(2), 0), BadOrigin);
+			assert_noop!(AssetTxPayment::add_fee_asset(Origin::ROOT, 1), Error::<Test>::PoolNotFound);
+			assert_ok!(AssetTxPayment::add_fee_asset(Origin::signed(1), 0));
+			assert_noop!(AssetTxPayment::add_fee_asset(Origin::ROOT, 0), Error::<Test>::AlreadyFeeAsset);
+			assert!(AssetTxPayment::is_fee_asset(0));
+
+			assert_ok!(AssetTxPayment::remove_fee_asset(Origin::ROOT, 0));
+			assert_noop!(AssetTxPayment::remove_fee_asset(Origin::ROOT, 0), Error::<Test>::NotFeeAsset);
+			assert!(!AssetTxPayment::is_fee_asset(0));
+		});
+	}
+
+	#[test]
+	fn fee_is_bought_with_whitelisted_asset() {
+		new_test_ext().execute_with(|| {
+			let issuance = Balances::total_issuance();
+			// Base fee 10, length 10 and weight 5.
+			assert_eq!(
+				ChargeAssetTxPayment::<Test>::from(0, Some((0, 100))).validate(&3, &(), info(), 10),
+				InvalidTransaction::Payment.into(),
+			);
+
+			assert_ok!(AssetTxPayment::add_fee_asset(Origin::ROOT, 0));
+			let valid = ChargeAssetTxPayment::<Test>::from(0, Some((0, 26)))
+				.validate(&3, &(), info(), 10)
+				.unwrap();
+			assert_eq!(valid.priority, 25);
+			assert_eq!(TestAssets::balance(0, 3), 74);
+			assert_eq!(TestAssetConversion::reserves(0), Some((999_975, 1_000_026)));
+			assert_eq!(Balances::total_issuance(), issuance - 25);
+
+			// The tip is bought as well.
+			assert!(ChargeAssetTxPayment::<Test>::from(5, Some((0, 100))).validate(&3, &(), info(), 10).is_ok());
+			assert_eq!(TestAssets::balance(0, 3), 43);
+
+			// Account 2 holds no asset 0.
+			assert_eq!(
+				ChargeAssetTxPayment::<Test>::from(0, Some((0, 100))).validate(&2, &(), info(), 10),
+				InvalidTransaction::Payment.into(),
+			);
+		});
+	}
+
+	#[test]
+	fn fee_above_the_maximum_is_rejected() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(AssetTxPayment::add_fee_asset(Origin::ROOT, 0));
+			// The fee of 25 costs 26 of asset 0.
+			assert_eq!(
+				ChargeAssetTxPayment::<Test>::from(0, Some((0, 25))).validate(&3, &(), info(), 10),
+				InvalidTransaction::Payment.into(),
+			);
+			assert_eq!(TestAssets::balance(0, 3), 100);
+			assert_eq!(TestAssetConversion::reserves(0), Some((1_000_000, 1_000_000)));
+
+			assert!(ChargeAssetTxPayment::<Test>::from(0, Some((0, 26))).validate(&3, &(), info(), 10).is_ok());
+			assert_eq!(TestAssets::balance(0, 3), 74);
+		});
+	}
+
+	#[test]
+	fn fee_without_asset_is_paid_natively() {
+		new_test_ext().execute_with(|| {
+			assert!(ChargeAssetTxPayment::<Test>::from(0, None).validate(&2, &(), info(), 10).is_ok());
+			assert_eq!(Balances::free_balance(&2), 975);
+			assert_eq!(TestAssetConversion::reserves(0), Some((1_000_000, 1_000_000)));
+		});
+	}
+}
@@ -66,6 +66,10 @@
 //!
 //! * `balance` - Get the asset `id` balance of `who`.
 //! * `total_supply` - Get the total supply of an asset `id`.
+//! * `create` - Create a new asset without supply, for other modules to `mint` units of.
+//! * `transfer_from` - Move units of an asset from one account to another.
+//! * `mint` - Create new units of an asset in an account.
+//! * `burn` - Remove units of an asset from an account.
 //!
 //! Please refer to the [`Module`](./struct.Module.html) struct for details on publicly available functions.
 //!
@@ -132,8 +136,10 @@
 // Ensure we're `no_std` when compiling for Wasm.
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{Parameter, decl_module, decl_event, decl_storage, decl_error, ensure};
-use sp_runtime::traits::{Member, SimpleArithmetic, Zero, StaticLookup};
+use frame_support::{
+	Parameter, decl_module, decl_event, decl_storage, decl_error, ensure, dispatch::DispatchResult,
+};
+use sp_runtime::traits::{Member, SimpleArithmetic, Zero, StaticLookup, CheckedAdd};
 use frame_system::{self as system, ensure_signed};
 use sp_runtime::traits::One;
 
@@ -176,15 +182,10 @@ decl_module! {
 			#[compact] amount: T::Balance
 		) {
 			let origin = ensure_signed(origin)?;
-			let origin_account = (id, origin.clone());
-			let origin_balance = <Balances<T>>::get(&origin_account);
 			let target = T::Lookup::lookup(target)?;
 			ensure!(!amount.is_zero(), Error::<T>::AmountZero);
-			ensure!(origin_balance >= amount, Error::<T>::BalanceLow);
 
-			Self::deposit_event(RawEvent::Transferred(id, origin, target.clone(), amount));
-			<Balances<T>>::insert(origin_account, origin_balance - amount);
-			<Balances<T>>::mutate((id, target), |balance| *balance += amount);
+			Self::transfer_from(id, &origin, &target, amount)?;
 		}
 
 		/// Destroy any assets of `id` owned by `origin`.
@@ -211,6 +212,10 @@ decl_event! {
 		Transferred(AssetId, AccountId, AccountId, Balance),
 		/// Some assets were destroyed.
 		Destroyed(AssetId, AccountId, Balance),
+		/// Some units of an asset were created by another module.
+		Minted(AssetId, AccountId, Balance),
+		/// Some units of an asset were removed by another module.
+		Burned(AssetId, AccountId, Balance),
 	}
 }
 
@@ -222,6 +227,8 @@ decl_error! {
 		BalanceLow,
 		/// Balance should be non-zero
 		BalanceZero,
+		/// Minting would overflow the total supply
+		Overflow,
 	}
 }
 
@@ -249,6 +256,54 @@ impl<T: Trait> Module<T> {
 	pub fn total_supply(id: T::AssetId) -> T::Balance {
 		<TotalSupply<T>>::get(id)
 	}
+
+	// Public mutables
+
+	/// Create a new asset with no supply and return its identifier. Units of it only come into
+	/// existence through `mint`.
+	pub fn create() -> T::AssetId {
+		let id = Self::next_asset_id();
+		<NextAssetId<T>>::mutate(|id| *id += One::one());
+		id
+	}
+
+	/// Move `amount` units of asset `id` from `source` to `dest`.
+	pub fn transfer_from(
+		id: T::AssetId,
+		source: &T::AccountId,
+		dest: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let source_balance = <Balances<T>>::get((id, source));
+		ensure!(source_balance >= amount, Error::<T>::BalanceLow);
+
+		Self::deposit_event(RawEvent::Transferred(id, source.clone(), dest.clone(), amount));
+		<Balances<T>>::insert((id, source), source_balance - amount);
+		<Balances<T>>::mutate((id, dest), |balance| *balance += amount);
+		Ok(())
+	}
+
+	/// Create `amount` new units of asset `id` in the account `who`.
+	pub fn mint(id: T::AssetId, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let total_supply = <TotalSupply<T>>::get(id).checked_add(&amount)
+			.ok_or(Error::<T>::Overflow)?;
+
+		<TotalSupply<T>>::insert(id, total_supply);
+		<Balances<T>>::mutate((id, who), |balance| *balance += amount);
+		Self::deposit_event(RawEvent::Minted(id, who.clone(), amount));
+		Ok(())
+	}
+
+	/// Remove `amount` units of asset `id` from the account `who`.
+	pub fn burn(id: T::AssetId, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let balance = <Balances<T>>::get((id, who));
+		ensure!(balance >= amount, Error::<T>::BalanceLow);
+
+		<Balances<T>>::insert((id, who), balance - amount);
+		<TotalSupply<T>>::mutate(id, |total_supply| *total_supply -= amount);
+		Self::deposit_event(RawEvent::Burned(id, who.clone(), amount));
+		Ok(())
+	}
 }
 
 #[cfg(test)]
@@ -384,6 +439,27 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn minting_and_burning_created_asset_should_work() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(Assets::issue(Origin::signed(1), 100));
+			let id = Assets::create();
+			assert_eq!(id, 1);
+			assert_eq!(Assets::total_supply(id), 0);
+
+			assert_ok!(Assets::mint(id, &2, 50));
+			assert_ok!(Assets::transfer_from(id, &2, &3, 20));
+			assert_eq!(Assets::balance(id, 2), 30);
+			assert_eq!(Assets::balance(id, 3), 20);
+			assert_eq!(Assets::total_supply(id), 50);
+
+			assert_noop!(Assets::burn(id, &3, 21), Error::<Test>::BalanceLow);
+			assert_ok!(Assets::burn(id, &3, 20));
+			assert_eq!(Assets::total_supply(id), 30);
+			assert_noop!(Assets::mint(id, &2, u64::max_value()), Error::<Test>::Overflow);
+		});
+	}
+
 	#[test]
 	fn destroying_asset_balance_with_zero_balance_should_not_work() {
 		new_test_ext().execute_with(|| {
@@ -42,6 +42,7 @@ parameter_types! {
 	pub const MaximumBlockLength: u32 = 2 * 1024;
 	pub const AvailableBlockRatio: Perbill = Perbill::one();
 	pub const MinimumPeriod: u64 = 1;
+	pub const MaxDrift: u64 = 30 * 1000;
 }
 
 impl frame_system::Trait for Test {
@@ -67,6 +68,7 @@ impl pallet_timestamp::Trait for Test {
 	type Moment = u64;
 	type OnTimestampSet = Aura;
 	type MinimumPeriod = MinimumPeriod;
+	type MaxDrift = MaxDrift;
 }
 
 impl Trait for Test {
@@ -239,6 +239,15 @@ impl<T: Trait> pallet_session::ShouldEndSession<T::BlockNumber> for Module<T> {
 	}
 }
 
+impl<T: Trait> frame_support::traits::EstimateNextNewSession<T::BlockNumber> for Module<T> {
+	fn estimate_next_new_session(now: T::BlockNumber) -> Option<T::BlockNumber> {
+		// Assumes a block per slot until the end of the epoch.
+		let next_epoch_start = Self::current_epoch_start().saturating_add(T::EpochDuration::get());
+		next_epoch_start.checked_sub(CurrentSlot::get())
+			.map(|slots| now.saturating_add(slots.saturated_into()))
+	}
+}
+
 // TODO [slashing]: @marcio use this, remove the dead_code annotation.
 /// A BABE equivocation offence report.
 ///
@@ -540,13 +549,22 @@ impl<T: Trait> ProvideInherent for Module<T> {
 			_ => return Ok(()),
 		};
 
-		let timestamp_based_slot = (timestamp / Self::slot_duration()).saturated_into::<u64>();
+		// the timestamp may drift from the window of the seal's slot by at most `MaxDrift`, so
+		// authors with slightly skewed clocks can still produce blocks.
+		let timestamp = timestamp.saturated_into::<u64>();
+		let slot_duration = Self::slot_duration().saturated_into::<u64>();
+		let max_drift = <T as pallet_timestamp::Trait>::MaxDrift::get().saturated_into::<u64>();
 		let seal_slot = data.babe_inherent_data()?;
 
-		if timestamp_based_slot == seal_slot {
+		let slot_start = seal_slot.saturating_mul(slot_duration);
+		let slot_end = slot_start.saturating_add(slot_duration);
+
+		if timestamp.saturating_add(max_drift) >= slot_start &&
+			timestamp < slot_end.saturating_add(max_drift)
+		{
 			Ok(())
 		} else {
-			Err(sp_inherents::Error::from("timestamp set in block doesn't match slot in seal").into())
+			Err(sp_inherents::Error::from("timestamp set in block is outside of the slot in seal").into())
 		}
 	}
 }
@@ -130,3 +130,20 @@ fn authority_index() {
 			"Trivially invalid authorities are ignored")
 	})
 }
+
+#[test]
+fn timestamp_may_drift_from_slot_window() {
+	new_test_ext(vec![0, 1, 2, 3]).execute_with(|| {
+		// slot 5 covers [10, 12) and the mock tolerates a drift of 1.
+		let mut data = InherentData::new();
+		data.put_data(INHERENT_IDENTIFIER, &5u64).unwrap();
+
+		let check = |t| Babe::check_inherent(&pallet_timestamp::Call::<Test>::set(t), &data);
+
+		assert!(check(9).is_ok());
+		assert!(check(10).is_ok());
+		assert!(check(12).is_ok());
+		assert!(check(8).is_err());
+		assert!(check(13).is_err());
+	})
+}
@@ -34,6 +34,7 @@
 //! - Account creation and removal.
 //! - Managing total issuance.
 //! - Setting and managing locks.
+//! - Holding and freezing funds for named reasons.
 //!
 //! ### Terminology
 //!
@@ -67,6 +68,11 @@
 //! simply dropped, it should automatically maintain any book-keeping such as total issuance.)
 //! - **Lock:** A freeze on a specified amount of an account's free balance until a specified block number. Multiple
 //! locks always operate over the same funds, so they "overlay" rather than "stack".
+//! - **Hold:** A named part of the reserved balance. Each hold can only be released or slashed by its own reason,
+//! and the anonymous reserve functions never touch held funds.
+//! - **Freeze:** A named restriction on all withdrawals from the free balance, which never expires. Like locks,
+//! freezes overlay. Locks which apply to all withdrawals forever can be moved into freezes with `upgrade_accounts`;
+//! from then on, setting or removing the lock maintains the freeze instead.
 //! - **Vesting:** Similar to a lock, this is another, but independent, liquidity restriction that reduces linearly
 //! over time.
 //!
@@ -81,6 +87,10 @@
 //! Functions for dealing with assets that can be reserved from an account.
 //! - [`LockableCurrency`](../frame_support/traits/trait.LockableCurrency.html): Functions for
 //! dealing with accounts that allow liquidity restrictions.
+//! - [`NamedHoldCurrency`](../frame_support/traits/trait.NamedHoldCurrency.html): Functions for
+//! holding reserved funds for named reasons.
+//! - [`FreezableCurrency`](../frame_support/traits/trait.FreezableCurrency.html): Functions for
+//! freezing funds for named reasons.
 //! - [`Imbalance`](../frame_support/traits/trait.Imbalance.html): Functions for handling
 //! imbalances between total issuance in the system and account balances. Must be used when a function
 //! creates new funds (e.g. a reward) or destroys some funds (e.g. a system fee).
@@ -93,6 +103,7 @@
 //!
 //! - `transfer` - Transfer some liquid free balance to another account.
 //! - `set_balance` - Set the balances of a given account. The origin of this call must be root.
+//! - `upgrade_accounts` - Move the legacy locks of some accounts into freezes.
 //!
 //! ### Public Functions
 //!
@@ -163,13 +174,14 @@ use sp_std::prelude::*;
 use sp_std::{cmp, result, mem, fmt::Debug};
 use codec::{Codec, Encode, Decode};
 use frame_support::{
-	StorageValue, Parameter, decl_event, decl_storage, decl_module, decl_error,
+	StorageValue, Parameter, decl_event, decl_storage, decl_module, decl_error, ensure,
 	traits::{
 		UpdateBalanceOutcome, Currency, OnFreeBalanceZero, OnReapAccount, OnUnbalanced, TryDrop,
 		WithdrawReason, WithdrawReasons, LockIdentifier, LockableCurrency, ExistenceRequirement,
 		Imbalance, SignedImbalance, ReservableCurrency, Get, VestingCurrency,
+		HoldIdentifier, NamedHoldCurrency, FreezeIdentifier, FreezableCurrency,
 	},
-	weights::SimpleDispatchInfo,
+	weights::{Weight, DispatchClass, SimpleDispatchInfo, FunctionOf},
 };
 use sp_runtime::{
 	RuntimeDebug, DispatchResult, DispatchError,
@@ -282,6 +294,11 @@ decl_event!(
 		BalanceSet(AccountId, Balance, Balance),
 		/// Some amount was deposited (e.g. for transaction fees).
 		Deposit(AccountId, Balance),
+		/// The legacy locks of an account were moved into freezes.
+		AccountUpgraded(AccountId),
+		/// Some balance of an account fell below the existential deposit and was handed to
+		/// `DustRemoval` (who, dust).
+		DustLost(AccountId, Balance),
 	}
 );
 
@@ -342,6 +359,15 @@ pub struct BalanceLock<Balance, BlockNumber> {
 	pub reasons: WithdrawReasons,
 }
 
+/// An amount held or frozen for the reason `id`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct IdAmount<Id, Balance> {
+	/// The reason of the hold or freeze.
+	pub id: Id,
+	/// The amount held or frozen.
+	pub amount: Balance,
+}
+
 decl_storage! {
 	trait Store for Module<T: Trait<I>, I: Instance=DefaultInstance> as Balances {
 		/// The total units issued in the system.
@@ -406,6 +432,16 @@ decl_storage! {
 
 		/// Any liquidity locks on some account balances.
 		pub Locks get(fn locks): map T::AccountId => Vec<BalanceLock<T::Balance, T::BlockNumber>>;
+
+		/// The named holds on the reserved balance of some account. Their total is part of
+		/// `ReservedBalance` and never exceeds it.
+		pub Holds get(fn holds): map T::AccountId => Vec<IdAmount<HoldIdentifier, T::Balance>>;
+
+		/// The named freezes on the balance of some account.
+		///
+		/// Locks which apply to all withdrawals and never expire are moved here by
+		/// `upgrade_accounts`.
+		pub Freezes get(fn freezes): map T::AccountId => Vec<IdAmount<FreezeIdentifier, T::Balance>>;
 	}
 	add_extra_genesis {
 		config(balances): Vec<(T::AccountId, T::Balance)>;
@@ -551,10 +587,89 @@ decl_module! {
 			<Self as Currency<_>>::transfer(&transactor, &dest, value, ExistenceRequirement::KeepAlive)?;
 		}
 
+		/// Move the legacy locks of the given accounts which apply to all withdrawals and never
+		/// expire into freezes.
+		///
+		/// Locks with the same identifier are subsequently maintained as freezes, so this can be
+		/// called for any account at any time without changing what it is able to withdraw.
+		///
+		/// The dispatch origin for this call must be `Signed`.
+		///
+		/// # <weight>
+		/// - O(N) in the number of accounts, each of which has a bounded number of locks.
+		/// # </weight>
+		#[weight = FunctionOf(
+			|args: (&Vec<T::AccountId>,)| (args.0.len() as Weight).saturating_mul(50_000),
+			DispatchClass::Normal,
+			true,
+		)]
+		fn upgrade_accounts(origin, who: Vec<T::AccountId>) {
+			ensure_signed(origin)?;
+			for who in who.iter() {
+				Self::upgrade_account(who);
+			}
+		}
 	}
 }
 
 impl<T: Trait<I>, I: Instance> Module<T, I> {
+	// PUBLIC IMMUTABLES
+
+	/// The total amount held from `who` for any reason.
+	pub fn total_held(who: &T::AccountId) -> T::Balance {
+		Self::holds(who).into_iter().fold(Zero::zero(), |acc: T::Balance, h| acc.saturating_add(h.amount))
+	}
+
+	/// The amount of the balance of `who` which is frozen, i.e. the largest of its freezes.
+	pub fn frozen_balance(who: &T::AccountId) -> T::Balance {
+		Self::freezes(who).into_iter().fold(Zero::zero(), |acc: T::Balance, f| acc.max(f.amount))
+	}
+
+	/// The part of the free balance of `who` which can currently be transferred, i.e. what is
+	/// left after vesting, freezes and locks restricting transfers.
+	///
+	/// Transferring all of it may reap the account, and the transfer fee is not deducted.
+	pub fn transferable_balance(who: &T::AccountId) -> T::Balance {
+		let now = <frame_system::Module<T>>::block_number();
+		let transfer: WithdrawReasons = WithdrawReason::Transfer.into();
+		let locked = Self::locks(who).into_iter()
+			.filter(|l| now < l.until && l.reasons.intersects(transfer))
+			.fold(Zero::zero(), |acc: T::Balance, l| acc.max(l.amount));
+		let restricted = locked
+			.max(Self::frozen_balance(who))
+			.max(<Self as VestingCurrency<_>>::vesting_balance(who));
+		Self::free_balance(who).saturating_sub(restricted)
+	}
+
+	// PUBLIC MUTABLES (DANGEROUS)
+
+	/// Move the legacy locks of `who` which apply to all withdrawals and never expire into
+	/// freezes with the same identifiers. Returns whether any lock was moved.
+	pub fn upgrade_account(who: &T::AccountId) -> bool {
+		let (moved, kept): (Vec<_>, Vec<_>) = Self::locks(who).into_iter()
+			.partition(|l| Self::is_freeze_like(l.until, l.reasons));
+		if moved.is_empty() {
+			return false
+		}
+
+		let mut freezes = Self::freezes(who);
+		for lock in moved {
+			match freezes.iter_mut().find(|f| f.id == lock.id) {
+				Some(f) => f.amount = f.amount.max(lock.amount),
+				None => freezes.push(IdAmount { id: lock.id, amount: lock.amount }),
+			}
+		}
+		Self::update_freezes(who, freezes);
+		if kept.is_empty() {
+			<Locks<T, I>>::remove(who);
+		} else {
+			<Locks<T, I>>::insert(who, kept);
+		}
+
+		Self::deposit_event(RawEvent::AccountUpgraded(who.clone()));
+		true
+	}
+
 	// PRIVATE MUTABLES
 
 	/// Set the reserved balance of an account to some new value. Will enforce `ExistentialDeposit`
@@ -572,10 +687,76 @@ impl<T: Trait<I>, I: Instance> Module<T, I> {
 			UpdateBalanceOutcome::AccountKilled
 		} else {
 			<ReservedBalance<T, I>>::insert(who, balance);
+			Self::trim_holds(who, balance);
 			UpdateBalanceOutcome::Updated
 		}
 	}
 
+	/// Reduce the holds of `who`, earliest first, until they fit in their reserved balance.
+	///
+	/// This only has an effect when held funds were taken by something other than the hold
+	/// itself, e.g. a `slash` which went beyond the free balance.
+	fn trim_holds(who: &T::AccountId, reserved: T::Balance) {
+		let holds = Self::holds(who);
+		if holds.is_empty() {
+			return
+		}
+		let mut remaining = reserved;
+		let trimmed = holds.iter().cloned().filter_map(|mut h| {
+			h.amount = h.amount.min(remaining);
+			remaining -= h.amount;
+			if h.amount.is_zero() { None } else { Some(h) }
+		}).collect::<Vec<_>>();
+		if trimmed != holds {
+			Self::update_holds(who, trimmed);
+		}
+	}
+
+	/// Take up to `value` from the hold `id` of `who`, returning the amount actually taken.
+	///
+	/// NOTE: LOW-LEVEL: This only updates the hold; the caller must move the funds out of the
+	/// reserved balance.
+	fn take_from_hold(id: HoldIdentifier, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		let mut holds = Self::holds(who);
+		let taken = match holds.iter_mut().find(|h| h.id == id) {
+			Some(h) => {
+				let taken = h.amount.min(value);
+				h.amount -= taken;
+				taken
+			}
+			None => return Zero::zero(),
+		};
+		holds.retain(|h| !h.amount.is_zero());
+		Self::update_holds(who, holds);
+		taken
+	}
+
+	fn update_holds(who: &T::AccountId, holds: Vec<IdAmount<HoldIdentifier, T::Balance>>) {
+		if holds.is_empty() {
+			<Holds<T, I>>::remove(who);
+		} else {
+			<Holds<T, I>>::insert(who, holds);
+		}
+	}
+
+	fn update_freezes(who: &T::AccountId, freezes: Vec<IdAmount<FreezeIdentifier, T::Balance>>) {
+		if freezes.is_empty() {
+			<Freezes<T, I>>::remove(who);
+		} else {
+			<Freezes<T, I>>::insert(who, freezes);
+		}
+	}
+
+	/// Whether a lock with the given parameters is equivalent to a freeze.
+	fn is_freeze_like(until: T::BlockNumber, reasons: WithdrawReasons) -> bool {
+		until == T::BlockNumber::max_value() && reasons == WithdrawReasons::all()
+	}
+
+	/// The anonymous part of the reserved balance of `who`, i.e. the part which is not held.
+	fn unheld_reserve(who: &T::AccountId) -> T::Balance {
+		Self::reserved_balance(who).saturating_sub(Self::total_held(who))
+	}
+
 	/// Set the free balance of an account to some new value. Will enforce `ExistentialDeposit`
 	/// law, annulling the account as needed.
 	///
@@ -621,6 +802,7 @@ impl<T: Trait<I>, I: Instance> Module<T, I> {
 	fn on_free_too_low(who: &T::AccountId) {
 		let dust = <FreeBalance<T, I>>::take(who);
 		<Locks<T, I>>::remove(who);
+		<Freezes<T, I>>::remove(who);
 
 		T::OnFreeBalanceZero::on_free_balance_zero(who);
 
@@ -634,6 +816,7 @@ impl<T: Trait<I>, I: Instance> Module<T, I> {
 			} else {
 				// underflow should never happen, but if it does, there's not much we can do.
 				T::DustRemoval::on_unbalanced(NegativeImbalance::new(dust));
+				Self::deposit_event(RawEvent::DustLost(who.clone(), dust));
 			}
 		}
 
@@ -648,6 +831,7 @@ impl<T: Trait<I>, I: Instance> Module<T, I> {
 	/// Will maintain total issuance.
 	fn on_reserved_too_low(who: &T::AccountId) {
 		let dust = <ReservedBalance<T, I>>::take(who);
+		<Holds<T, I>>::remove(who);
 
 		let mut free_balance = Self::free_balance(who);
 
@@ -659,6 +843,7 @@ impl<T: Trait<I>, I: Instance> Module<T, I> {
 			} else {
 				// underflow should never happen, but it if does, there's nothing to be done here.
 				T::DustRemoval::on_unbalanced(NegativeImbalance::new(dust));
+				Self::deposit_event(RawEvent::DustLost(who.clone(), dust));
 			}
 		}
 
@@ -951,6 +1136,9 @@ where
 		{
 			Err(Error::<T, I>::VestingBalance)?
 		}
+		if new_balance < Self::frozen_balance(who) {
+			Err(Error::<T, I>::LiquidityRestrictions)?
+		}
 		let locks = Self::locks(who);
 		if locks.is_empty() {
 			return Ok(())
@@ -1188,11 +1376,12 @@ where
 	}
 
 	// Unreserve some funds, returning any amount that was unable to be unreserved.
+	// Held funds are never unreserved.
 	// Is a no-op if the value to be unreserved is zero.
 	fn unreserve(who: &T::AccountId, value: Self::Balance) -> Self::Balance {
 		if value.is_zero() { return Zero::zero() }
 		let b = Self::reserved_balance(who);
-		let actual = cmp::min(b, value);
+		let actual = cmp::min(Self::unheld_reserve(who), value);
 		Self::set_free_balance(who, Self::free_balance(who) + actual);
 		Self::set_reserved_balance(who, b - actual);
 		value - actual
@@ -1200,6 +1389,7 @@ where
 
 	// Slash from reserved balance, returning the negative imbalance created,
 	// and any amount that was unable to be slashed.
+	// Held funds are never slashed.
 	// Is a no-op if the value to be slashed is zero.
 	fn slash_reserved(
 		who: &T::AccountId,
@@ -1207,13 +1397,14 @@ where
 	) -> (Self::NegativeImbalance, Self::Balance) {
 		if value.is_zero() { return (NegativeImbalance::zero(), Zero::zero()) }
 		let b = Self::reserved_balance(who);
-		let slash = cmp::min(b, value);
+		let slash = cmp::min(Self::unheld_reserve(who), value);
 		// underflow should never happen, but it if does, there's nothing to be done here.
 		Self::set_reserved_balance(who, b - slash);
 		(NegativeImbalance::new(slash), value - slash)
 	}
 
 	// Move the reserved balance of one account into the free balance of another.
+	// Held funds are never moved.
 	// Is a no-op if the value to be moved is zero.
 	fn repatriate_reserved(
 		slashed: &T::AccountId,
@@ -1225,13 +1416,119 @@ where
 			Err(Error::<T, I>::DeadAccount)?
 		}
 		let b = Self::reserved_balance(slashed);
-		let slash = cmp::min(b, value);
+		let slash = cmp::min(Self::unheld_reserve(slashed), value);
 		Self::set_free_balance(beneficiary, Self::free_balance(beneficiary) + slash);
 		Self::set_reserved_balance(slashed, b - slash);
 		Ok(value - slash)
 	}
 }
 
+impl<T: Trait<I>, I: Instance> NamedHoldCurrency<T::AccountId> for Module<T, I>
+where
+	T::Balance: MaybeSerializeDeserialize + Debug
+{
+	fn balance_on_hold(id: HoldIdentifier, who: &T::AccountId) -> Self::Balance {
+		Self::holds(who).into_iter().find(|h| h.id == id).map_or_else(Zero::zero, |h| h.amount)
+	}
+
+	// Move `value` from the free balance of `who` into the hold `id`.
+	// Is a no-op if the value to be held is zero.
+	fn hold(id: HoldIdentifier, who: &T::AccountId, value: Self::Balance) -> DispatchResult {
+		if value.is_zero() { return Ok(()) }
+		// a reserved balance below the existential deposit would be dusted along with the hold.
+		ensure!(
+			Self::reserved_balance(who).saturating_add(value) >= T::ExistentialDeposit::get(),
+			Error::<T, I>::ExistentialDeposit,
+		);
+		<Self as ReservableCurrency<_>>::reserve(who, value)?;
+
+		let mut holds = Self::holds(who);
+		match holds.iter_mut().find(|h| h.id == id) {
+			Some(h) => h.amount += value,
+			None => holds.push(IdAmount { id, amount: value }),
+		}
+		Self::update_holds(who, holds);
+		Ok(())
+	}
+
+	// Release some of the hold `id`, returning any amount that was unable to be released.
+	// Is a no-op if the value to be released is zero.
+	fn release(id: HoldIdentifier, who: &T::AccountId, value: Self::Balance) -> Self::Balance {
+		if value.is_zero() { return Zero::zero() }
+		let actual = Self::take_from_hold(id, who, value);
+		let b = Self::reserved_balance(who);
+		Self::set_free_balance(who, Self::free_balance(who) + actual);
+		Self::set_reserved_balance(who, b - actual);
+		value - actual
+	}
+
+	// Slash from the hold `id`, returning the negative imbalance created,
+	// and any amount that was unable to be slashed.
+	// Is a no-op if the value to be slashed is zero.
+	fn slash_held(
+		id: HoldIdentifier,
+		who: &T::AccountId,
+		value: Self::Balance,
+	) -> (Self::NegativeImbalance, Self::Balance) {
+		if value.is_zero() { return (NegativeImbalance::zero(), Zero::zero()) }
+		let slash = Self::take_from_hold(id, who, value);
+		Self::set_reserved_balance(who, Self::reserved_balance(who) - slash);
+		(NegativeImbalance::new(slash), value - slash)
+	}
+
+	// Move some of the hold `id` of one account into the free balance of another.
+	// Is a no-op if the value to be moved is zero.
+	fn repatriate_held(
+		id: HoldIdentifier,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		value: Self::Balance,
+	) -> result::Result<Self::Balance, DispatchError> {
+		if value.is_zero() { return Ok(Zero::zero()) }
+		if Self::total_balance(beneficiary).is_zero() {
+			Err(Error::<T, I>::DeadAccount)?
+		}
+		let slash = Self::take_from_hold(id, slashed, value);
+		let b = Self::reserved_balance(slashed);
+		Self::set_free_balance(beneficiary, Self::free_balance(beneficiary) + slash);
+		Self::set_reserved_balance(slashed, b - slash);
+		Ok(value - slash)
+	}
+}
+
+impl<T: Trait<I>, I: Instance> FreezableCurrency<T::AccountId> for Module<T, I>
+where
+	T::Balance: MaybeSerializeDeserialize + Debug
+{
+	fn balance_frozen(id: FreezeIdentifier, who: &T::AccountId) -> Self::Balance {
+		Self::freezes(who).into_iter().find(|f| f.id == id).map_or_else(Zero::zero, |f| f.amount)
+	}
+
+	fn set_freeze(id: FreezeIdentifier, who: &T::AccountId, amount: Self::Balance) {
+		let mut freezes = Self::freezes(who);
+		freezes.retain(|f| f.id != id);
+		if !amount.is_zero() {
+			freezes.push(IdAmount { id, amount });
+		}
+		Self::update_freezes(who, freezes);
+	}
+
+	// Is a no-op if the amount to be frozen is zero.
+	fn extend_freeze(id: FreezeIdentifier, who: &T::AccountId, amount: Self::Balance) {
+		if amount.is_zero() { return }
+		let mut freezes = Self::freezes(who);
+		match freezes.iter_mut().find(|f| f.id == id) {
+			Some(f) => f.amount = f.amount.max(amount),
+			None => freezes.push(IdAmount { id, amount }),
+		}
+		Self::update_freezes(who, freezes);
+	}
+
+	fn thaw(id: FreezeIdentifier, who: &T::AccountId) {
+		Self::set_freeze(id, who, Zero::zero());
+	}
+}
+
 impl<T: Trait<I>, I: Instance> LockableCurrency<T::AccountId> for Module<T, I>
 where
 	T::Balance: MaybeSerializeDeserialize + Debug
@@ -1239,6 +1536,8 @@ where
 	type Moment = T::BlockNumber;
 
 	// Set a lock on the balance of `who`.
+	// A lock which has been moved into a freeze by `upgrade_account` is kept as one, unless it
+	// no longer applies to all withdrawals forever.
 	// Is a no-op if lock amount is zero.
 	fn set_lock(
 		id: LockIdentifier,
@@ -1248,6 +1547,12 @@ where
 		reasons: WithdrawReasons,
 	) {
 		if amount.is_zero() { return }
+		if Self::freezes(who).iter().any(|f| f.id == id) {
+			if Self::is_freeze_like(until, reasons) {
+				return <Self as FreezableCurrency<_>>::set_freeze(id, who, amount)
+			}
+			<Self as FreezableCurrency<_>>::thaw(id, who);
+		}
 		let now = <frame_system::Module<T>>::block_number();
 		let mut new_lock = Some(BalanceLock { id, amount, until, reasons });
 		let mut locks = Self::locks(who).into_iter().filter_map(|l|
@@ -1271,6 +1576,10 @@ where
 		until: T::BlockNumber,
 		reasons: WithdrawReasons,
 	) {
+		// extending a freeze always results in a lock which is equivalent to a freeze.
+		if Self::freezes(who).iter().any(|f| f.id == id) {
+			return <Self as FreezableCurrency<_>>::extend_freeze(id, who, amount)
+		}
 		let now = <frame_system::Module<T>>::block_number();
 		let mut new_lock = Some(BalanceLock { id, amount, until, reasons });
 		let mut locks = Self::locks(who).into_iter().filter_map(|l|
@@ -1298,6 +1607,7 @@ where
 		id: LockIdentifier,
 		who: &T::AccountId,
 	) {
+		<Self as FreezableCurrency<_>>::thaw(id, who);
 		let now = <frame_system::Module<T>>::block_number();
 		let locks = Self::locks(who).into_iter().filter_map(|l|
 			if l.until > now && l.id != id {
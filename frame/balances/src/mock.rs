@@ -19,7 +19,7 @@
 use sp_runtime::{Perbill, traits::{ConvertInto, IdentityLookup}, testing::Header};
 use sp_core::H256;
 use sp_io;
-use frame_support::{impl_outer_origin, parameter_types};
+use frame_support::{impl_outer_origin, impl_outer_event, parameter_types};
 use frame_support::traits::Get;
 use frame_support::weights::{Weight, DispatchInfo};
 use std::cell::RefCell;
@@ -30,6 +30,16 @@ impl_outer_origin!{
 	pub enum Origin for Test {}
 }
 
+mod balances {
+	pub use crate::Event;
+}
+
+impl_outer_event! {
+	pub enum MetaEvent for Test {
+		balances<T>,
+	}
+}
+
 thread_local! {
 	pub(crate) static EXISTENTIAL_DEPOSIT: RefCell<u64> = RefCell::new(0);
 	static TRANSFER_FEE: RefCell<u64> = RefCell::new(0);
@@ -70,7 +80,7 @@ impl frame_system::Trait for Test {
 	type AccountId = u64;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = MetaEvent;
 	type BlockHashCount = BlockHashCount;
 	type MaximumBlockWeight = MaximumBlockWeight;
 	type MaximumBlockLength = MaximumBlockLength;
@@ -88,6 +98,7 @@ impl pallet_transaction_payment::Trait for Test {
 	type TransactionBaseFee = TransactionBaseFee;
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = ConvertInto;
+	type OperationalWeightToFee = ConvertInto;
 	type FeeMultiplierUpdate = ();
 }
 impl Trait for Test {
@@ -95,7 +106,7 @@ impl Trait for Test {
 	type OnFreeBalanceZero = ();
 	type OnReapAccount = System;
 	type OnNewAccount = ();
-	type Event = ();
+	type Event = MetaEvent;
 	type DustRemoval = ();
 	type TransferPayment = ();
 	type ExistentialDeposit = ExistentialDeposit;
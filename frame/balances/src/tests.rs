@@ -17,12 +17,13 @@
 //! Tests for the module.
 
 use super::*;
-use mock::{Balances, ExtBuilder, Test, System, info_from_weight, CALL};
+use mock::{Balances, ExtBuilder, Test, System, MetaEvent, info_from_weight, CALL};
 use sp_runtime::traits::{SignedExtension, BadOrigin};
 use frame_support::{
 	assert_noop, assert_ok, assert_err,
 	traits::{LockableCurrency, LockIdentifier, WithdrawReason, WithdrawReasons,
-	Currency, ReservableCurrency, ExistenceRequirement::AllowDeath}
+	Currency, ReservableCurrency, ExistenceRequirement::AllowDeath,
+	NamedHoldCurrency, HoldIdentifier, FreezableCurrency}
 };
 use pallet_transaction_payment::ChargeTransactionPayment;
 use frame_system::RawOrigin;
@@ -30,6 +31,8 @@ use frame_system::RawOrigin;
 const ID_1: LockIdentifier = *b"1       ";
 const ID_2: LockIdentifier = *b"2       ";
 const ID_3: LockIdentifier = *b"3       ";
+const HOLD_1: HoldIdentifier = *b"hold1   ";
+const HOLD_2: HoldIdentifier = *b"hold2   ";
 
 #[test]
 fn basic_locking_should_work() {
@@ -808,3 +811,163 @@ fn dust_moves_between_free_and_reserved() {
 		assert_eq!(Balances::reserved_balance(1), 0);
 	});
 }
+
+#[test]
+fn holds_are_accounted_per_reason() {
+	ExtBuilder::default().existential_deposit(1).monied(true).build().execute_with(|| {
+		assert_ok!(Balances::hold(HOLD_1, &2, 3));
+		assert_ok!(Balances::hold(HOLD_2, &2, 4));
+		assert_ok!(Balances::reserve(&2, 1));
+		assert_eq!(Balances::reserved_balance(2), 8);
+		assert_eq!(Balances::total_held(&2), 7);
+
+		// only the anonymous reserve can be unreserved.
+		assert_eq!(Balances::unreserve(&2, 8), 7);
+		assert_eq!(Balances::reserved_balance(2), 7);
+		assert_eq!(Balances::slash_reserved(&2, 1).1, 1);
+
+		assert_eq!(Balances::release(HOLD_1, &2, 5), 2);
+		assert_eq!(Balances::balance_on_hold(HOLD_1, &2), 0);
+		assert_eq!(Balances::balance_on_hold(HOLD_2, &2), 4);
+
+		let (imbalance, remaining) = Balances::slash_held(HOLD_2, &2, 10);
+		assert_eq!(imbalance.peek(), 4);
+		assert_eq!(remaining, 6);
+		drop(imbalance);
+
+		assert_eq!(Balances::reserved_balance(2), 0);
+		assert_eq!(Balances::free_balance(2), 16);
+		assert!(Balances::holds(2).is_empty());
+	});
+}
+
+#[test]
+fn held_funds_can_be_repatriated() {
+	ExtBuilder::default().existential_deposit(1).monied(true).build().execute_with(|| {
+		assert_ok!(Balances::hold(HOLD_1, &1, 5));
+		assert_noop!(Balances::repatriate_held(HOLD_1, &1, &5, 3), Error::<Test, _>::DeadAccount);
+		assert_eq!(Balances::repatriate_held(HOLD_1, &1, &2, 3), Ok(0));
+		assert_eq!(Balances::balance_on_hold(HOLD_1, &1), 2);
+		assert_eq!(Balances::reserved_balance(1), 2);
+		assert_eq!(Balances::free_balance(2), 23);
+	});
+}
+
+#[test]
+fn hold_below_existential_deposit_fails() {
+	ExtBuilder::default().existential_deposit(10).monied(true).build().execute_with(|| {
+		assert_noop!(Balances::hold(HOLD_1, &1, 5), Error::<Test, _>::ExistentialDeposit);
+		assert_ok!(Balances::hold(HOLD_1, &1, 10));
+		assert_ok!(Balances::hold(HOLD_1, &1, 5));
+		assert_eq!(Balances::balance_on_hold(HOLD_1, &1), 15);
+	});
+}
+
+#[test]
+fn slashing_beyond_free_balance_trims_holds() {
+	ExtBuilder::default().existential_deposit(1).monied(true).build().execute_with(|| {
+		assert_ok!(Balances::hold(HOLD_1, &1, 4));
+		assert_ok!(Balances::hold(HOLD_2, &1, 4));
+		let (imbalance, _) = Balances::slash(&1, 5);
+		drop(imbalance);
+		assert_eq!(Balances::reserved_balance(1), 5);
+		assert_eq!(Balances::balance_on_hold(HOLD_1, &1), 4);
+		assert_eq!(Balances::balance_on_hold(HOLD_2, &1), 1);
+	});
+}
+
+#[test]
+fn freezes_restrict_all_withdrawals() {
+	ExtBuilder::default().existential_deposit(1).monied(true).build().execute_with(|| {
+		Balances::set_freeze(ID_1, &1, 5);
+		Balances::set_freeze(ID_2, &1, 7);
+		assert_eq!(Balances::frozen_balance(&1), 7);
+		assert_noop!(
+			<Balances as Currency<_>>::transfer(&1, &2, 4, AllowDeath),
+			Error::<Test, _>::LiquidityRestrictions
+		);
+		assert_ok!(<Balances as Currency<_>>::transfer(&1, &2, 3, AllowDeath));
+		assert_noop!(Balances::reserve(&1, 1), Error::<Test, _>::LiquidityRestrictions);
+
+		Balances::extend_freeze(ID_2, &1, 6);
+		assert_eq!(Balances::balance_frozen(ID_2, &1), 7);
+		Balances::thaw(ID_2, &1);
+		assert_ok!(<Balances as Currency<_>>::transfer(&1, &2, 2, AllowDeath));
+		assert_noop!(
+			<Balances as Currency<_>>::transfer(&1, &2, 1, AllowDeath),
+			Error::<Test, _>::LiquidityRestrictions
+		);
+	});
+}
+
+#[test]
+fn upgrade_accounts_moves_locks_into_freezes() {
+	ExtBuilder::default().existential_deposit(1).monied(true).build().execute_with(|| {
+		Balances::set_lock(ID_1, &1, 5, u64::max_value(), WithdrawReasons::all());
+		Balances::set_lock(ID_2, &1, 3, 10, WithdrawReasons::all());
+		assert_ok!(Balances::upgrade_accounts(Some(3).into(), vec![1, 2]));
+
+		assert_eq!(Balances::locks(1).len(), 1);
+		assert_eq!(Balances::locks(1)[0].id, ID_2);
+		assert_eq!(Balances::balance_frozen(ID_1, &1), 5);
+		assert!(!Balances::upgrade_account(&1));
+
+		// the lock is now maintained as a freeze.
+		Balances::set_lock(ID_1, &1, 8, u64::max_value(), WithdrawReasons::all());
+		assert_eq!(Balances::balance_frozen(ID_1, &1), 8);
+		Balances::extend_lock(ID_1, &1, 9, 5, WithdrawReason::Transfer.into());
+		assert_eq!(Balances::balance_frozen(ID_1, &1), 9);
+		assert_eq!(Balances::locks(1).len(), 1);
+		assert_noop!(
+			<Balances as Currency<_>>::transfer(&1, &2, 2, AllowDeath),
+			Error::<Test, _>::LiquidityRestrictions
+		);
+
+		// unless it stops applying to all withdrawals forever.
+		Balances::set_lock(ID_1, &1, 9, 5, WithdrawReason::Transfer.into());
+		assert_eq!(Balances::balance_frozen(ID_1, &1), 0);
+		assert_eq!(Balances::locks(1).len(), 2);
+
+		Balances::remove_lock(ID_1, &1);
+		Balances::remove_lock(ID_2, &1);
+		assert_ok!(<Balances as Currency<_>>::transfer(&1, &2, 2, AllowDeath));
+	});
+}
+
+#[test]
+fn transferable_balance_accounts_for_restrictions() {
+	ExtBuilder::default().existential_deposit(1).monied(true).build().execute_with(|| {
+		assert_eq!(Balances::transferable_balance(&1), 10);
+		Balances::set_lock(ID_1, &1, 4, u64::max_value(), WithdrawReason::Transfer.into());
+		Balances::set_lock(ID_2, &1, 9, u64::max_value(), WithdrawReason::Reserve.into());
+		Balances::set_lock(ID_3, &1, 9, 5, WithdrawReasons::all());
+		assert_eq!(Balances::transferable_balance(&1), 1);
+
+		System::set_block_number(5);
+		assert_eq!(Balances::transferable_balance(&1), 6);
+		Balances::set_freeze(ID_1, &1, 7);
+		assert_eq!(Balances::transferable_balance(&1), 3);
+		assert_ok!(<Balances as Currency<_>>::transfer(&1, &2, 3, AllowDeath));
+	});
+}
+
+#[test]
+fn dust_lost_is_reported() {
+	ExtBuilder::default().existential_deposit(10).monied(true).build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(<Balances as Currency<_>>::transfer(&1, &2, 95, AllowDeath));
+		assert_eq!(Balances::free_balance(&1), 0);
+		assert!(System::events().iter().any(|record|
+			record.event == MetaEvent::balances(RawEvent::DustLost(1, 5))
+		));
+
+		// no dust is lost when the remaining balance is kept in the reserved balance.
+		assert_ok!(Balances::reserve(&2, 100));
+		assert_ok!(<Balances as Currency<_>>::transfer(&2, &3, 190, AllowDeath));
+		assert_eq!(Balances::free_balance(&2), 0);
+		assert_eq!(Balances::reserved_balance(&2), 105);
+		assert!(!System::events().iter().any(|record|
+			record.event == MetaEvent::balances(RawEvent::DustLost(2, 5))
+		));
+	});
+}
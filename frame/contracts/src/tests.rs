@@ -133,11 +133,13 @@ impl pallet_balances::Trait for Test {
 }
 parameter_types! {
 	pub const MinimumPeriod: u64 = 1;
+	pub const MaxDrift: u64 = 30 * 1000;
 }
 impl pallet_timestamp::Trait for Test {
 	type Moment = u64;
 	type OnTimestampSet = ();
 	type MinimumPeriod = MinimumPeriod;
+	type MaxDrift = MaxDrift;
 }
 parameter_types! {
 	pub const SignedClaimHandicap: u64 = 2;
@@ -73,11 +73,15 @@
 //! /// Executive: handles dispatch to the various modules.
 //! pub type Executive = executive::Executive<Runtime, Block, Context, Runtime, AllModules>;
 //! ```
+//!
+//! The last, optional, type parameter lists the modules implementing
+//! [`OnIdle`](../frame_support/weights/trait.OnIdle.html). They are given the weight left unused
+//! by the extrinsics of each block, right before `on_finalize`.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use sp_std::{prelude::*, marker::PhantomData};
-use frame_support::weights::{GetDispatchInfo, WeighBlock, DispatchInfo};
+use frame_support::weights::{GetDispatchInfo, WeighBlock, DispatchInfo, DispatchClass, OnIdle};
 use sp_runtime::{
 	generic::Digest, ApplyExtrinsicResult,
 	traits::{
@@ -101,8 +105,8 @@ pub type CheckedOf<E, C> = <E as Checkable<C>>::Checked;
 pub type CallOf<E, C> = <CheckedOf<E, C> as Applyable>::Call;
 pub type OriginOf<E, C> = <CallOf<E, C> as Dispatchable>::Origin;
 
-pub struct Executive<System, Block, Context, UnsignedValidator, AllModules>(
-	PhantomData<(System, Block, Context, UnsignedValidator, AllModules)>
+pub struct Executive<System, Block, Context, UnsignedValidator, AllModules, IdleModules = ()>(
+	PhantomData<(System, Block, Context, UnsignedValidator, AllModules, IdleModules)>
 );
 
 #[allow(deprecated)] // Allow ValidateUnsigned, remove the attribute when the trait is removed.
@@ -116,7 +120,8 @@ impl<
 		OnFinalize<System::BlockNumber> +
 		OffchainWorker<System::BlockNumber> +
 		WeighBlock<System::BlockNumber>,
-> ExecuteBlock<Block> for Executive<System, Block, Context, UnsignedValidator, AllModules>
+	IdleModules: OnIdle<System::BlockNumber>,
+> ExecuteBlock<Block> for Executive<System, Block, Context, UnsignedValidator, AllModules, IdleModules>
 where
 	Block::Extrinsic: Checkable<Context> + Codec,
 	CheckedOf<Block::Extrinsic, Context>:
@@ -127,7 +132,7 @@ where
 	UnsignedValidator: ValidateUnsigned<Call=CallOf<Block::Extrinsic, Context>>,
 {
 	fn execute_block(block: Block) {
-		Executive::<System, Block, Context, UnsignedValidator, AllModules>::execute_block(block);
+		Executive::<System, Block, Context, UnsignedValidator, AllModules, IdleModules>::execute_block(block);
 	}
 }
 
@@ -142,7 +147,8 @@ impl<
 		OnFinalize<System::BlockNumber> +
 		OffchainWorker<System::BlockNumber> +
 		WeighBlock<System::BlockNumber>,
-> Executive<System, Block, Context, UnsignedValidator, AllModules>
+	IdleModules: OnIdle<System::BlockNumber>,
+> Executive<System, Block, Context, UnsignedValidator, AllModules, IdleModules>
 where
 	Block::Extrinsic: Checkable<Context> + Codec,
 	CheckedOf<Block::Extrinsic, Context>:
@@ -187,14 +193,26 @@ where
 			frame_system::InitKind::Full,
 		);
 		<AllModules as OnInitialize<System::BlockNumber>>::on_initialize(*block_number);
-		<frame_system::Module<System>>::register_extra_weight_unchecked(
+		<frame_system::Module<System>>::register_hooks_weight(
 			<AllModules as WeighBlock<System::BlockNumber>>::on_initialize(*block_number)
-		);
-		<frame_system::Module<System>>::register_extra_weight_unchecked(
-			<AllModules as WeighBlock<System::BlockNumber>>::on_finalize(*block_number)
+				.saturating_add(<AllModules as WeighBlock<System::BlockNumber>>::on_finalize(*block_number))
 		);
 	}
 
+	/// Give the weight left unused by the block to the `IdleModules`, and account for the weight
+	/// they consume. The weight reserved for operational extrinsics is not given.
+	fn idle(block_number: System::BlockNumber) {
+		let remaining_weight = <frame_system::Module<System>>::weight_limit(DispatchClass::Normal)
+			.saturating_sub(<frame_system::Module<System>>::all_extrinsics_weight());
+		if remaining_weight > 0 {
+			let consumed_weight = <IdleModules as OnIdle<System::BlockNumber>>::on_idle(
+				block_number,
+				remaining_weight,
+			);
+			<frame_system::Module<System>>::register_extra_weight_unchecked(consumed_weight);
+		}
+	}
+
 	fn initial_checks(block: &Block) {
 		let header = block.header();
 
@@ -234,6 +252,7 @@ where
 
 		// post-extrinsics book-keeping
 		<frame_system::Module<System>>::note_finished_extrinsics();
+		Self::idle(block_number);
 		<AllModules as OnFinalize<System::BlockNumber>>::on_finalize(block_number);
 	}
 
@@ -241,7 +260,9 @@ where
 	/// except state-root.
 	pub fn finalize_block() -> System::Header {
 		<frame_system::Module<System>>::note_finished_extrinsics();
-		<AllModules as OnFinalize<System::BlockNumber>>::on_finalize(<frame_system::Module<System>>::block_number());
+		let block_number = <frame_system::Module<System>>::block_number();
+		Self::idle(block_number);
+		<AllModules as OnFinalize<System::BlockNumber>>::on_finalize(block_number);
 
 		// set up extrinsics
 		<frame_system::Module<System>>::derive_extrinsics();
@@ -481,6 +502,7 @@ mod tests {
 		type TransactionBaseFee = TransactionBaseFee;
 		type TransactionByteFee = TransactionByteFee;
 		type WeightToFee = ConvertInto;
+		type OperationalWeightToFee = ConvertInto;
 		type FeeMultiplierUpdate = ();
 	}
 	impl custom::Trait for Runtime {}
@@ -509,7 +531,20 @@ mod tests {
 	);
 	type AllModules = (System, Balances, Custom);
 	type TestXt = sp_runtime::testing::TestXt<Call, SignedExtra>;
-	type Executive = super::Executive<Runtime, Block<TestXt>, ChainContext<Runtime>, Runtime, AllModules>;
+	type Executive = super::Executive<Runtime, Block<TestXt>, ChainContext<Runtime>, Runtime, AllModules, IdleHook>;
+
+	thread_local! {
+		static IDLE_REMAINING_WEIGHT: std::cell::RefCell<Weight> = std::cell::RefCell::new(0);
+	}
+
+	/// Records the weight it is given and consumes half of it.
+	pub struct IdleHook;
+	impl OnIdle<u64> for IdleHook {
+		fn on_idle(_n: u64, remaining_weight: Weight) -> Weight {
+			IDLE_REMAINING_WEIGHT.with(|w| *w.borrow_mut() = remaining_weight);
+			remaining_weight / 2
+		}
+	}
 
 	fn extra(nonce: u64, fee: u64) -> SignedExtra {
 		(
@@ -691,6 +726,21 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn idle_modules_get_the_unused_weight() {
+		let xt = sp_runtime::testing::TestXt(sign_extra(1, 0, 0), Call::Balances(BalancesCall::transfer(33, 0)));
+		let len = xt.clone().encode().len() as Weight;
+		let mut t = new_test_ext(1);
+		t.execute_with(|| {
+			assert!(Executive::apply_extrinsic(xt).unwrap().is_ok());
+			Executive::idle(1);
+
+			let remaining = AvailableBlockRatio::get() * MaximumBlockWeight::get() - len;
+			assert_eq!(IDLE_REMAINING_WEIGHT.with(|w| *w.borrow()), remaining);
+			assert_eq!(<frame_system::Module<Runtime>>::all_extrinsics_weight(), len + remaining / 2);
+		});
+	}
+
 	#[test]
 	fn validate_unsigned() {
 		let xt = sp_runtime::testing::TestXt(None, Call::Balances(BalancesCall::set_balance(33, 69, 69)));
@@ -754,6 +804,149 @@ mod tests {
 			// NOTE: might need updates over time if system and balance introduce new weights. For
 			// now only accounts for the custom module.
 			assert_eq!(<frame_system::Module<Runtime>>::all_extrinsics_weight(), 150 + 25);
+			assert_eq!(<frame_system::Module<Runtime>>::hooks_weight(), 150 + 25);
 		})
 	}
+
+	/// A runtime whose accounts are Ethereum addresses, signing with `EthereumSignature`.
+	mod ethereum {
+		use super::*;
+		use codec::Decode;
+		use sp_core::{ecdsa, Pair};
+		use sp_runtime::{
+			AccountId20, EthereumSignature, generic, traits::{IdentifyAccount, SignPayload},
+		};
+
+		impl_outer_origin! {
+			pub enum Origin for Runtime { }
+		}
+
+		impl_outer_event!{
+			pub enum MetaEvent for Runtime {
+				balances<T>,
+			}
+		}
+
+		impl_outer_dispatch! {
+			pub enum Call for Runtime where origin: Origin {
+				frame_system::System,
+				pallet_balances::Balances,
+			}
+		}
+
+		#[derive(Clone, Eq, PartialEq)]
+		pub struct Runtime;
+
+		impl frame_system::Trait for Runtime {
+			type Origin = Origin;
+			type Index = u64;
+			type Call = Call;
+			type BlockNumber = u64;
+			type Hash = sp_core::H256;
+			type Hashing = BlakeTwo256;
+			type AccountId = AccountId20;
+			type Lookup = IdentityLookup<AccountId20>;
+			type Header = Header;
+			type Event = MetaEvent;
+			type BlockHashCount = BlockHashCount;
+			type MaximumBlockWeight = MaximumBlockWeight;
+			type AvailableBlockRatio = AvailableBlockRatio;
+			type MaximumBlockLength = MaximumBlockLength;
+			type Version = ();
+			type ModuleToIndex = ();
+		}
+
+		impl pallet_balances::Trait for Runtime {
+			type Balance = u64;
+			type OnFreeBalanceZero = ();
+			type OnReapAccount = System;
+			type OnNewAccount = ();
+			type Event = MetaEvent;
+			type DustRemoval = ();
+			type TransferPayment = ();
+			type ExistentialDeposit = ExistentialDeposit;
+			type TransferFee = TransferFee;
+			type CreationFee = CreationFee;
+		}
+
+		#[allow(deprecated)]
+		impl ValidateUnsigned for Runtime {
+			type Call = Call;
+
+			fn validate_unsigned(_call: &Self::Call) -> TransactionValidity {
+				UnknownTransaction::NoUnsignedValidator.into()
+			}
+		}
+
+		type System = frame_system::Module<Runtime>;
+		type Balances = pallet_balances::Module<Runtime>;
+		type SignedExtra = (
+			frame_system::CheckNonce<Runtime>,
+			frame_system::CheckWeight<Runtime>,
+		);
+		type UncheckedExtrinsic = generic::UncheckedExtrinsic<
+			AccountId20,
+			Call,
+			EthereumSignature,
+			SignedExtra,
+		>;
+		type Block = generic::Block<Header, UncheckedExtrinsic>;
+		type Executive = super::super::Executive<
+			Runtime,
+			Block,
+			ChainContext<Runtime>,
+			Runtime,
+			(System, Balances),
+		>;
+
+		fn account(pair: &ecdsa::Pair) -> AccountId20 {
+			SignPayload::<EthereumSignature>::signer(pair).into_account()
+		}
+
+		/// Sign `call` from the account of `pair` with `key`, as a wallet would.
+		fn sign(pair: &ecdsa::Pair, key: &ecdsa::Pair, nonce: u64, call: Call) -> Vec<u8> {
+			let extra = (frame_system::CheckNonce::from(nonce), frame_system::CheckWeight::new());
+			let payload = generic::SignedPayload::new(call, extra).unwrap();
+			let signature = payload.using_encoded(|payload| {
+				SignPayload::<EthereumSignature>::sign_payload(key, payload)
+			});
+			let (call, extra, _) = payload.deconstruct();
+			UncheckedExtrinsic::new_signed(call, account(pair), signature, extra).encode()
+		}
+
+		fn new_test_ext(alice: &ecdsa::Pair) -> sp_io::TestExternalities {
+			let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+			pallet_balances::GenesisConfig::<Runtime> {
+				balances: vec![(account(alice), 111)],
+				vesting: vec![],
+			}.assimilate_storage(&mut t).unwrap();
+			t.into()
+		}
+
+		#[test]
+		fn extrinsics_signed_with_ethereum_keys_are_applied() {
+			let alice = ecdsa::Pair::from_string("//Alice", None).unwrap();
+			let bob = ecdsa::Pair::from_string("//Bob", None).unwrap();
+			let transfer = || Call::Balances(BalancesCall::transfer(account(&bob), 69));
+
+			new_test_ext(&alice).execute_with(|| {
+				Executive::initialize_block(&Header::new_from_number(1));
+
+				let xt = sign(&alice, &alice, 0, transfer());
+				let xt = UncheckedExtrinsic::decode(&mut &xt[..]).unwrap();
+				assert_eq!(Executive::apply_extrinsic(xt), Ok(Ok(())));
+				assert_eq!(Balances::total_balance(&account(&alice)), 111 - 69);
+				assert_eq!(Balances::total_balance(&account(&bob)), 69);
+
+				// Signed by another key than the one of the address.
+				let xt = sign(&alice, &bob, 1, transfer());
+				let xt = UncheckedExtrinsic::decode(&mut &xt[..]).unwrap();
+				assert_eq!(
+					Executive::apply_extrinsic(xt),
+					Err(InvalidTransaction::BadProof.into()),
+				);
+				assert_eq!(Balances::total_balance(&account(&bob)), 69);
+			});
+		}
+	}
 }
@@ -0,0 +1,524 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Fast Unstake Module
+//!
+//! - [`fast_unstake::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Fast Unstake module lets a stash whose bond was never exposed, as a validator or a
+//! nominator, in any era of the bonding duration unbond all of its funds immediately instead of
+//! waiting for the bonding duration. Such a bond was never rewarded and cannot be slashed.
+//!
+//! Registered stashes are chilled and queued. The stash at the head of the queue is checked
+//! against up to `ErasToCheckPerBlock` eras with the weight left unused by each block, see
+//! [`OnIdle`](../frame_support/weights/trait.OnIdle.html), until all the eras of the bonding
+//! duration are checked. If the stash was never exposed it is unbonded and the deposit is
+//! returned, otherwise the deposit is slashed.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `register_fast_unstake` - Chill the stash of the controller and queue it to be unbonded;
+//!   a deposit is reserved.
+//! * `deregister` - Remove the stash of the controller from the queue; the deposit is returned.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Encode, Decode};
+use sp_runtime::RuntimeDebug;
+use frame_support::{
+	decl_module, decl_event, decl_storage, ensure, decl_error, StorageLinkedMap,
+	traits::{Currency, ReservableCurrency, OnUnbalanced, Get},
+	weights::{SimpleDispatchInfo, Weight, OnIdle},
+};
+use frame_system::{self as system, ensure_signed};
+use pallet_staking::EraIndex;
+
+/// Weight of reading the request to check and of unbonding or slashing it.
+const PROCESS_WEIGHT: Weight = 600_000;
+/// Weight of checking the exposure of a stash in an era.
+const ERA_CHECK_WEIGHT: Weight = 20_000;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
+
+/// The staking system that stashes are unbonded from.
+pub trait StakingInterface<AccountId> {
+	/// The stash controlled by `controller`, if any.
+	fn stash_of(controller: &AccountId) -> Option<AccountId>;
+
+	/// Whether `stash` is bonded.
+	fn is_bonded(stash: &AccountId) -> bool;
+
+	/// Stop `stash` from validating or nominating from the next era.
+	fn chill(stash: &AccountId);
+
+	/// The eras in which an exposure can still be slashed, or `None` if they are not all known.
+	fn exposure_eras() -> Option<Vec<EraIndex>>;
+
+	/// Whether `stash` was exposed in `era`, or `None` while the exposures of `era` are being
+	/// recorded.
+	fn is_exposed_in_era(stash: &AccountId, era: EraIndex) -> Option<bool>;
+
+	/// Unbond all the funds of `stash` immediately.
+	fn force_unbond(stash: &AccountId);
+}
+
+impl<T: pallet_staking::Trait> StakingInterface<T::AccountId> for pallet_staking::Module<T> {
+	fn stash_of(controller: &T::AccountId) -> Option<T::AccountId> {
+		Self::ledger(controller).map(|ledger| ledger.stash)
+	}
+
+	fn is_bonded(stash: &T::AccountId) -> bool {
+		Self::bonded(stash).is_some()
+	}
+
+	fn chill(stash: &T::AccountId) {
+		Self::chill_stash(stash)
+	}
+
+	fn exposure_eras() -> Option<Vec<EraIndex>> {
+		Self::exposure_eras()
+	}
+
+	fn is_exposed_in_era(stash: &T::AccountId, era: EraIndex) -> Option<bool> {
+		match Self::exposures_recorded_in_era(era) {
+			true => Some(Self::is_exposed_in_era(stash, era)),
+			false => None,
+		}
+	}
+
+	fn force_unbond(stash: &T::AccountId) {
+		Self::force_unbond(stash)
+	}
+}
+
+pub trait Trait: frame_system::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The currency of the deposit.
+	type Currency: ReservableCurrency<Self::AccountId>;
+
+	/// The staking system.
+	type Staking: StakingInterface<Self::AccountId>;
+
+	/// The deposit reserved from the controller while its stash is queued.
+	type Deposit: Get<BalanceOf<Self>>;
+
+	/// What to do with slashed deposits.
+	type Slashed: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+	/// The maximum number of eras checked in a block, within the weight it leaves unused.
+	type ErasToCheckPerBlock: Get<u32>;
+}
+
+/// A stash being checked before it is unbonded.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct UnstakeRequest<AccountId, Balance> {
+	/// The stash to unbond.
+	pub stash: AccountId,
+	/// The account the deposit is reserved from.
+	pub depositor: AccountId,
+	/// The reserved deposit.
+	pub deposit: Balance,
+	/// The eras in which the stash is known not to be exposed.
+	pub checked: Vec<EraIndex>,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as FastUnstake {
+		/// The queued stashes, with the account their deposit is reserved from and the deposit.
+		pub Queue get(fn queue): linked_map T::AccountId => Option<(T::AccountId, BalanceOf<T>)>;
+
+		/// The request being checked, if any.
+		pub Head get(fn head): Option<UnstakeRequest<T::AccountId, BalanceOf<T>>>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where AccountId = <T as frame_system::Trait>::AccountId, Balance = BalanceOf<T> {
+		/// A stash was queued to be unbonded.
+		Queued(AccountId),
+		/// A stash was unbonded.
+		Unstaked(AccountId),
+		/// A queued stash was exposed in an era of the bonding duration; the deposit was slashed.
+		Slashed(AccountId, Balance),
+	}
+);
+
+decl_error! {
+	/// Error for the fast unstake module.
+	pub enum Error for Module<T: Trait> {
+		/// Not a controller account.
+		NotController,
+		/// The stash is already queued.
+		AlreadyQueued,
+		/// The stash is not queued.
+		NotQueued,
+		/// The stash is already being checked.
+		AlreadyHead,
+		/// The exposures of some eras of the bonding duration are unknown.
+		ExposuresUnknown,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// The deposit reserved from the controller while its stash is queued.
+		const Deposit: BalanceOf<T> = T::Deposit::get();
+
+		/// The maximum number of eras checked in a block, within the weight it leaves unused.
+		const ErasToCheckPerBlock: u32 = T::ErasToCheckPerBlock::get();
+
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Chill the stash of the controller and queue it to be unbonded without waiting for the
+		/// bonding duration. `Deposit` is reserved from the controller and slashed if the stash
+		/// turns out to be exposed in an era of the bonding duration.
+		///
+		/// The dispatch origin for this call must be _Signed_ by the controller.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One balance reserve operation.
+		/// - Three storage writes.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(500_000)]
+		fn register_fast_unstake(origin) {
+			let controller = ensure_signed(origin)?;
+			let stash = T::Staking::stash_of(&controller).ok_or(Error::<T>::NotController)?;
+			ensure!(!<Queue<T>>::exists(&stash), Error::<T>::AlreadyQueued);
+			ensure!(
+				Self::head().map_or(true, |head| head.stash != stash),
+				Error::<T>::AlreadyQueued,
+			);
+			ensure!(T::Staking::exposure_eras().is_some(), Error::<T>::ExposuresUnknown);
+
+			let deposit = T::Deposit::get();
+			T::Currency::reserve(&controller, deposit)?;
+			T::Staking::chill(&stash);
+			<Queue<T>>::insert(&stash, (controller, deposit));
+			Self::deposit_event(RawEvent::Queued(stash));
+		}
+
+		/// Remove the stash of the controller from the queue, returning the deposit. A stash that
+		/// is already being checked cannot be removed.
+		///
+		/// The dispatch origin for this call must be _Signed_ by the controller.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One balance operation.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(500_000)]
+		fn deregister(origin) {
+			let controller = ensure_signed(origin)?;
+			let stash = T::Staking::stash_of(&controller).ok_or(Error::<T>::NotController)?;
+			ensure!(
+				Self::head().map_or(true, |head| head.stash != stash),
+				Error::<T>::AlreadyHead,
+			);
+			let (depositor, deposit) = <Queue<T>>::take(&stash).ok_or(Error::<T>::NotQueued)?;
+			T::Currency::unreserve(&depositor, deposit);
+		}
+	}
+}
+
+/// The requests are checked with the weight left unused by blocks.
+impl<T: Trait> OnIdle<T::BlockNumber> for Module<T> {
+	fn on_idle(_n: T::BlockNumber, remaining_weight: Weight) -> Weight {
+		let budget = remaining_weight.saturating_sub(PROCESS_WEIGHT) / ERA_CHECK_WEIGHT;
+		let budget = budget.min(T::ErasToCheckPerBlock::get() as Weight) as u32;
+		if budget == 0 {
+			return 0
+		}
+		let checked = Self::process(budget);
+		PROCESS_WEIGHT + checked as Weight * ERA_CHECK_WEIGHT
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Check up to `budget` eras for the request at the head of the queue, and unbond or slash it
+	/// once all the eras of the bonding duration are checked. Returns the number of eras checked.
+	fn process(budget: u32) -> u32 {
+		let eras = match T::Staking::exposure_eras() {
+			Some(eras) => eras,
+			None => return 0,
+		};
+		let mut request = match <Head<T>>::take().or_else(Self::next_request) {
+			Some(request) => request,
+			None => return 0,
+		};
+
+		// eras out of the bonding duration need not be checked anymore.
+		request.checked.retain(|era| eras.contains(era));
+		let unchecked = eras.iter()
+			.filter(|era| !request.checked.contains(era))
+			.take(budget as usize)
+			.cloned()
+			.collect::<Vec<_>>();
+		let mut checked = 0;
+		for era in unchecked {
+			checked += 1;
+			match T::Staking::is_exposed_in_era(&request.stash, era) {
+				Some(true) => {
+					let (imbalance, _) = T::Currency::slash_reserved(&request.depositor, request.deposit);
+					T::Slashed::on_unbalanced(imbalance);
+					Self::deposit_event(RawEvent::Slashed(request.stash, request.deposit));
+					return checked
+				},
+				Some(false) => request.checked.push(era),
+				// checked in a later block, once recorded.
+				None => break,
+			}
+		}
+
+		if request.checked.len() < eras.len() {
+			<Head<T>>::put(request);
+			return checked
+		}
+
+		if T::Staking::is_bonded(&request.stash) {
+			T::Staking::force_unbond(&request.stash);
+			Self::deposit_event(RawEvent::Unstaked(request.stash));
+		}
+		T::Currency::unreserve(&request.depositor, request.deposit);
+		checked
+	}
+
+	/// Take the next request out of the queue.
+	fn next_request() -> Option<UnstakeRequest<T::AccountId, BalanceOf<T>>> {
+		let stash = <Queue<T>>::head()?;
+		let (depositor, deposit) = <Queue<T>>::take(&stash)?;
+		Some(UnstakeRequest { stash, depositor, deposit, checked: Vec::new() })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::cell::RefCell;
+	use std::collections::BTreeSet;
+	use frame_support::{
+		assert_ok, assert_noop, impl_outer_origin, parameter_types, weights::Weight,
+	};
+	use sp_core::H256;
+	use sp_runtime::{
+		Perbill, testing::Header, traits::{BlakeTwo256, IdentityLookup},
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = ();
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u64 = 0;
+		pub const TransferFee: u64 = 0;
+		pub const CreationFee: u64 = 0;
+	}
+	impl pallet_balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnReapAccount = System;
+		type OnNewAccount = ();
+		type Event = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+	}
+
+	thread_local! {
+		static BONDED: RefCell<BTreeSet<u64>> = RefCell::new(vec![11, 21, 31].into_iter().collect());
+		static CHILLED: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+		static ERAS: RefCell<Option<Vec<EraIndex>>> = RefCell::new(Some(vec![0, 1, 2, 3]));
+		static EXPOSED: RefCell<BTreeSet<(u64, EraIndex)>> = RefCell::new(Default::default());
+	}
+
+	/// Stashes `x1` controlled by `x0`.
+	pub struct TestStaking;
+	impl StakingInterface<u64> for TestStaking {
+		fn stash_of(controller: &u64) -> Option<u64> {
+			Some(controller + 1).filter(|stash| Self::is_bonded(stash))
+		}
+		fn is_bonded(stash: &u64) -> bool {
+			BONDED.with(|b| b.borrow().contains(stash))
+		}
+		fn chill(stash: &u64) {
+			CHILLED.with(|c| c.borrow_mut().push(*stash));
+		}
+		fn exposure_eras() -> Option<Vec<EraIndex>> {
+			ERAS.with(|e| e.borrow().clone())
+		}
+		fn is_exposed_in_era(stash: &u64, era: EraIndex) -> Option<bool> {
+			Some(EXPOSED.with(|e| e.borrow().contains(&(*stash, era))))
+		}
+		fn force_unbond(stash: &u64) {
+			BONDED.with(|b| b.borrow_mut().remove(stash));
+		}
+	}
+
+	parameter_types! {
+		pub const Deposit: u64 = 10;
+		pub const ErasToCheckPerBlock: u32 = 3;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Currency = Balances;
+		type Staking = TestStaking;
+		type Deposit = Deposit;
+		type Slashed = ();
+		type ErasToCheckPerBlock = ErasToCheckPerBlock;
+	}
+	type System = frame_system::Module<Test>;
+	type Balances = pallet_balances::Module<Test>;
+	type FastUnstake = Module<Test>;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(10, 100), (20, 100), (30, 5)],
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	#[test]
+	fn register_and_deregister_work() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(FastUnstake::register_fast_unstake(Origin::signed(1)), Error::<Test>::NotController);
+			assert_noop!(
+				FastUnstake::register_fast_unstake(Origin::signed(30)),
+				pallet_balances::Error::<Test, _>::InsufficientBalance,
+			);
+
+			assert_ok!(FastUnstake::register_fast_unstake(Origin::signed(10)));
+			assert_eq!(Balances::reserved_balance(&10), 10);
+			assert_eq!(FastUnstake::queue(&11), Some((10, 10)));
+			CHILLED.with(|c| assert_eq!(*c.borrow(), vec![11]));
+			assert_noop!(FastUnstake::register_fast_unstake(Origin::signed(10)), Error::<Test>::AlreadyQueued);
+
+			assert_ok!(FastUnstake::deregister(Origin::signed(10)));
+			assert_eq!(Balances::reserved_balance(&10), 0);
+			assert_eq!(FastUnstake::queue(&11), None);
+			assert_noop!(FastUnstake::deregister(Origin::signed(10)), Error::<Test>::NotQueued);
+
+			ERAS.with(|e| *e.borrow_mut() = None);
+			assert_noop!(FastUnstake::register_fast_unstake(Origin::signed(20)), Error::<Test>::ExposuresUnknown);
+		});
+	}
+
+	#[test]
+	fn unexposed_stash_is_unbonded_gradually() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(FastUnstake::register_fast_unstake(Origin::signed(10)));
+
+			FastUnstake::on_idle(1, Weight::max_value());
+			assert_eq!(FastUnstake::head().unwrap().checked, vec![0, 1, 2]);
+			assert_ok!(FastUnstake::register_fast_unstake(Origin::signed(20)));
+			assert_noop!(FastUnstake::deregister(Origin::signed(10)), Error::<Test>::AlreadyHead);
+			assert_noop!(FastUnstake::register_fast_unstake(Origin::signed(10)), Error::<Test>::AlreadyQueued);
+
+			// a new era starts while checking.
+			ERAS.with(|e| *e.borrow_mut() = Some(vec![1, 2, 3, 4]));
+			FastUnstake::on_idle(2, Weight::max_value());
+			assert_eq!(FastUnstake::head(), None);
+			assert!(!TestStaking::is_bonded(&11));
+			assert_eq!(Balances::reserved_balance(&10), 0);
+			assert_eq!(Balances::free_balance(&10), 100);
+
+			// the next stash is checked afterwards.
+			FastUnstake::on_idle(3, Weight::max_value());
+			assert_eq!(FastUnstake::head().unwrap().stash, 21);
+			assert_eq!(FastUnstake::queue(&21), None);
+		});
+	}
+
+	#[test]
+	fn eras_are_checked_within_the_unused_weight() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(FastUnstake::register_fast_unstake(Origin::signed(10)));
+
+			// not enough weight to check an era.
+			assert_eq!(FastUnstake::on_idle(1, PROCESS_WEIGHT + ERA_CHECK_WEIGHT - 1), 0);
+			assert_eq!(FastUnstake::head(), None);
+
+			assert_eq!(FastUnstake::on_idle(1, PROCESS_WEIGHT + ERA_CHECK_WEIGHT), PROCESS_WEIGHT + ERA_CHECK_WEIGHT);
+			assert_eq!(FastUnstake::head().unwrap().checked, vec![0]);
+
+			// at most `ErasToCheckPerBlock` eras are checked.
+			assert_eq!(FastUnstake::on_idle(2, Weight::max_value()), PROCESS_WEIGHT + 3 * ERA_CHECK_WEIGHT);
+			assert_eq!(FastUnstake::head(), None);
+			assert!(!TestStaking::is_bonded(&11));
+		});
+	}
+
+	#[test]
+	fn exposed_stash_is_slashed() {
+		new_test_ext().execute_with(|| {
+			EXPOSED.with(|e| e.borrow_mut().insert((11, 3)));
+			assert_ok!(FastUnstake::register_fast_unstake(Origin::signed(10)));
+
+			FastUnstake::on_idle(1, Weight::max_value());
+			assert!(FastUnstake::head().is_some());
+			FastUnstake::on_idle(2, Weight::max_value());
+			assert_eq!(FastUnstake::head(), None);
+			assert!(TestStaking::is_bonded(&11));
+			assert_eq!(Balances::reserved_balance(&10), 0);
+			assert_eq!(Balances::free_balance(&10), 90);
+		});
+	}
+}
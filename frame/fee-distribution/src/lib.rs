@@ -0,0 +1,498 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Fee Distribution Module
+//!
+//! - [`fee_distribution::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Fee Distribution module distributes the fees and tips paid by transactions between the
+//! author of the block, the treasury and burning. Fees and tips each have their own
+//! [`Distribution`](./struct.Distribution.html), which can be changed by governance. Until a
+//! distribution is set, at genesis or by governance, the defaults of the runtime apply, so that
+//! chains upgraded to this module keep distributing their fees.
+//!
+//! The amounts distributed in a block are added up and reported by a single `Distributed` event
+//! at the end of the block, so that the flow of fees can be audited.
+//!
+//! ## Usage
+//!
+//! Use the module as the `OnTransactionPayment` handler of the Transaction Payment module, which
+//! hands over the fee and then the tip of each transaction. Any other imbalance it is given is
+//! distributed as a fee.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! - `set_fee_distribution` - Change the distribution of fees.
+//! - `set_tip_distribution` - Change the distribution of tips.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Encode, Decode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use sp_runtime::{Perbill, RuntimeDebug, traits::{EnsureOrigin, Saturating, SimpleArithmetic, Zero}};
+use frame_support::{
+	decl_module, decl_storage, decl_event, decl_error, ensure,
+	traits::{Currency, Get, Imbalance, OnUnbalanced},
+	weights::SimpleDispatchInfo,
+};
+use frame_system::ensure_root;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> =
+	<<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
+
+pub trait Trait: pallet_authorship::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The currency in which fees are paid.
+	type Currency: Currency<Self::AccountId>;
+
+	/// Handler for the share of the treasury.
+	type Treasury: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+	/// The origin which may change the distributions. Root can always do this.
+	type DistributionOrigin: EnsureOrigin<Self::Origin>;
+
+	/// The distribution of fees until one is set.
+	type DefaultFeeDistribution: Get<Distribution>;
+
+	/// The distribution of tips until one is set.
+	type DefaultTipDistribution: Get<Distribution>;
+}
+
+/// The shares of an amount going to the block author and to the treasury. The rest is burned.
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct Distribution {
+	/// The share of the block author.
+	pub author: Perbill,
+	/// The share of the treasury.
+	pub treasury: Perbill,
+}
+
+impl Distribution {
+	/// Whether the shares add up to no more than the whole.
+	pub fn is_valid(&self) -> bool {
+		self.author.deconstruct() as u64 + self.treasury.deconstruct() as u64
+			<= Perbill::one().deconstruct() as u64
+	}
+
+	/// The shares of `amount` going to the author, to the treasury and burned.
+	pub fn split<Balance: SimpleArithmetic + Copy>(
+		&self,
+		amount: Balance,
+	) -> (Balance, Balance, Balance) {
+		let author = self.author * amount;
+		let treasury = self.treasury * amount;
+		(author, treasury, amount.saturating_sub(author).saturating_sub(treasury))
+	}
+}
+
+/// The amounts distributed in a block.
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct Totals<Balance> {
+	/// The total given to the block author.
+	pub author: Balance,
+	/// The total given to the treasury.
+	pub treasury: Balance,
+	/// The total burned.
+	pub burned: Balance,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as FeeDistribution {
+		/// The distribution of transaction fees, `T::DefaultFeeDistribution` if not set.
+		pub FeeDistribution config(fee_distribution): Option<Distribution>;
+
+		/// The distribution of transaction tips, `T::DefaultTipDistribution` if not set.
+		pub TipDistribution config(tip_distribution): Option<Distribution>;
+
+		/// The amounts distributed in the current block.
+		BlockTotals get(fn block_totals): Totals<BalanceOf<T>>;
+	}
+	add_extra_genesis {
+		build(|config: &GenesisConfig| {
+			assert!(config.fee_distribution.is_valid(), "fee distribution shares exceed the whole");
+			assert!(config.tip_distribution.is_valid(), "tip distribution shares exceed the whole");
+		});
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as frame_system::Trait>::AccountId,
+		Balance = BalanceOf<T>,
+	{
+		/// The fees and tips of a block were distributed
+		/// (author, to the author, to the treasury, burned).
+		Distributed(AccountId, Balance, Balance, Balance),
+		/// The distribution of fees was changed.
+		FeeDistributionChanged(Distribution),
+		/// The distribution of tips was changed.
+		TipDistributionChanged(Distribution),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Trait> {
+		/// The shares of the distribution exceed the whole.
+		InvalidDistribution,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Change the distribution of transaction fees.
+		///
+		/// The dispatch origin for this call must be _Root_ or match `T::DistributionOrigin`.
+		///
+		/// # <weight>
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		fn set_fee_distribution(origin, distribution: Distribution) {
+			T::DistributionOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			ensure!(distribution.is_valid(), Error::<T>::InvalidDistribution);
+
+			FeeDistribution::put(distribution);
+			Self::deposit_event(RawEvent::FeeDistributionChanged(distribution));
+		}
+
+		/// Change the distribution of transaction tips.
+		///
+		/// The dispatch origin for this call must be _Root_ or match `T::DistributionOrigin`.
+		///
+		/// # <weight>
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		fn set_tip_distribution(origin, distribution: Distribution) {
+			T::DistributionOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			ensure!(distribution.is_valid(), Error::<T>::InvalidDistribution);
+
+			TipDistribution::put(distribution);
+			Self::deposit_event(RawEvent::TipDistributionChanged(distribution));
+		}
+
+		fn on_finalize() {
+			if <BlockTotals<T>>::exists() {
+				let totals = <BlockTotals<T>>::take();
+				Self::deposit_event(RawEvent::Distributed(
+					<pallet_authorship::Module<T>>::author(),
+					totals.author,
+					totals.treasury,
+					totals.burned,
+				));
+			}
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The distribution of transaction fees.
+	pub fn fee_distribution() -> Distribution {
+		FeeDistribution::get().unwrap_or_else(T::DefaultFeeDistribution::get)
+	}
+
+	/// The distribution of transaction tips.
+	pub fn tip_distribution() -> Distribution {
+		TipDistribution::get().unwrap_or_else(T::DefaultTipDistribution::get)
+	}
+
+	/// Distribute `amount` according to `distribution`.
+	fn distribute(amount: NegativeImbalanceOf<T>, distribution: Distribution) {
+		let (to_author, to_treasury, burned) = distribution.split(amount.peek());
+		let (author_share, rest) = amount.split(to_author);
+		let (treasury_share, burned_share) = rest.split(to_treasury);
+
+		let author = <pallet_authorship::Module<T>>::author();
+		T::Currency::resolve_creating(&author, author_share);
+		T::Treasury::on_unbalanced(treasury_share);
+		drop(burned_share);
+
+		<BlockTotals<T>>::mutate(|totals| {
+			totals.author = totals.author.saturating_add(to_author);
+			totals.treasury = totals.treasury.saturating_add(to_treasury);
+			totals.burned = totals.burned.saturating_add(burned);
+		});
+	}
+}
+
+impl<T: Trait> OnUnbalanced<NegativeImbalanceOf<T>> for Module<T> {
+	fn on_nonzero_unbalanced(amount: NegativeImbalanceOf<T>) {
+		Self::distribute(amount, Self::fee_distribution());
+	}
+
+	fn on_unbalanceds<B>(mut amounts: impl Iterator<Item=NegativeImbalanceOf<T>>) where
+		NegativeImbalanceOf<T>: Imbalance<B>,
+	{
+		// the first imbalance is the fee, any other one is a tip.
+		if let Some(fee) = amounts.next() {
+			Self::on_unbalanced(fee);
+		}
+		for tip in amounts {
+			if !tip.peek().is_zero() {
+				Self::distribute(tip, Self::tip_distribution());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use frame_support::{
+		assert_noop, assert_ok, impl_outer_origin, parameter_types, ord_parameter_types,
+		weights::Weight,
+	};
+	use sp_core::H256;
+	use sp_runtime::{
+		testing::Header,
+		traits::{BlakeTwo256, IdentityLookup, OnFinalize, BadOrigin},
+		ConsensusEngineId,
+	};
+	use frame_system::EnsureSignedBy;
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = ();
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u64 = 1;
+		pub const TransferFee: u64 = 0;
+		pub const CreationFee: u64 = 0;
+	}
+	impl pallet_balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnReapAccount = ();
+		type OnNewAccount = ();
+		type Event = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+	}
+
+	pub struct AuthorGiven;
+	impl frame_support::traits::FindAuthor<u64> for AuthorGiven {
+		fn find_author<'a, I>(_: I) -> Option<u64>
+			where I: 'a + IntoIterator<Item=(ConsensusEngineId, &'a [u8])>
+		{
+			Some(7)
+		}
+	}
+	parameter_types! {
+		pub const UncleGenerations: u64 = 0;
+	}
+	impl pallet_authorship::Trait for Test {
+		type FindAuthor = AuthorGiven;
+		type UncleGenerations = UncleGenerations;
+		type FilterUncle = ();
+		type EventHandler = ();
+	}
+
+	pub struct ToTreasury;
+	impl OnUnbalanced<pallet_balances::NegativeImbalance<Test>> for ToTreasury {
+		fn on_nonzero_unbalanced(amount: pallet_balances::NegativeImbalance<Test>) {
+			Balances::resolve_creating(&TREASURY, amount);
+		}
+	}
+	ord_parameter_types! {
+		pub const Governance: u64 = 10;
+	}
+	parameter_types! {
+		pub const DefaultFeeDistribution: Distribution = Distribution {
+			author: Perbill::from_percent(20),
+			treasury: Perbill::from_percent(80),
+		};
+		pub const DefaultTipDistribution: Distribution = Distribution {
+			author: Perbill::one(),
+			treasury: Perbill::zero(),
+		};
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Currency = Balances;
+		type Treasury = ToTreasury;
+		type DistributionOrigin = EnsureSignedBy<Governance, u64>;
+		type DefaultFeeDistribution = DefaultFeeDistribution;
+		type DefaultTipDistribution = DefaultTipDistribution;
+	}
+
+	type Balances = pallet_balances::Module<Test>;
+	type FeeDistribution = Module<Test>;
+
+	const TREASURY: u64 = 100;
+
+	fn balances_ext() -> sp_runtime::Storage {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(1, 1_000), (7, 10), (TREASURY, 10)],
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		t
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = balances_ext();
+		GenesisConfig {
+			fee_distribution: Distribution {
+				author: Perbill::from_percent(20),
+				treasury: Perbill::from_percent(70),
+			},
+			tip_distribution: Distribution {
+				author: Perbill::one(),
+				treasury: Perbill::zero(),
+			},
+		}.assimilate_storage::<Test>(&mut t).unwrap();
+		t.into()
+	}
+
+	fn pay(fee: u64, tip: u64) {
+		let imbalance = Balances::withdraw(
+			&1,
+			fee + tip,
+			frame_support::traits::WithdrawReason::TransactionPayment.into(),
+			frame_support::traits::ExistenceRequirement::KeepAlive,
+		).unwrap();
+		let (tip, fee) = imbalance.split(tip);
+		FeeDistribution::on_unbalanceds(Some(fee).into_iter().chain(Some(tip)));
+	}
+
+	#[test]
+	fn fees_and_tips_are_distributed_separately() {
+		new_test_ext().execute_with(|| {
+			let issuance = Balances::total_issuance();
+			pay(100, 30);
+			pay(50, 0);
+
+			assert_eq!(Balances::free_balance(&1), 820);
+			assert_eq!(Balances::free_balance(&7), 10 + 20 + 30 + 10);
+			assert_eq!(Balances::free_balance(&TREASURY), 10 + 70 + 35);
+			assert_eq!(Balances::total_issuance(), issuance - 15);
+			assert_eq!(
+				FeeDistribution::block_totals(),
+				Totals { author: 60, treasury: 105, burned: 15 },
+			);
+
+			FeeDistribution::on_finalize(1);
+			assert!(!<BlockTotals<Test>>::exists());
+		});
+	}
+
+	#[test]
+	fn other_imbalances_are_distributed_as_fees() {
+		new_test_ext().execute_with(|| {
+			let (imbalance, _) = Balances::slash(&1, 10);
+			FeeDistribution::on_unbalanced(imbalance);
+			assert_eq!(Balances::free_balance(&7), 12);
+			assert_eq!(Balances::free_balance(&TREASURY), 17);
+		});
+	}
+
+	#[test]
+	fn governance_can_change_the_distributions() {
+		new_test_ext().execute_with(|| {
+			let distribution = Distribution {
+				author: Perbill::from_percent(50),
+				treasury: Perbill::from_percent(50),
+			};
+			assert_noop!(
+				FeeDistribution::set_fee_distribution(Origin::signed(1), distribution),
+				BadOrigin,
+			);
+			assert_noop!(
+				FeeDistribution::set_fee_distribution(Origin::ROOT, Distribution {
+					author: Perbill::from_percent(50),
+					treasury: Perbill::from_percent(51),
+				}),
+				Error::<Test>::InvalidDistribution,
+			);
+			assert_ok!(FeeDistribution::set_fee_distribution(Origin::signed(10), distribution));
+			assert_ok!(FeeDistribution::set_tip_distribution(Origin::ROOT, Distribution::default()));
+			assert_eq!(FeeDistribution::fee_distribution(), distribution);
+
+			pay(10, 10);
+			assert_eq!(Balances::free_balance(&7), 15);
+			assert_eq!(Balances::free_balance(&TREASURY), 15);
+			assert_eq!(FeeDistribution::block_totals().burned, 10);
+		});
+	}
+
+	#[test]
+	fn upgraded_chains_use_the_default_distributions() {
+		// a chain upgraded to the module has no distribution in storage.
+		sp_io::TestExternalities::new(balances_ext()).execute_with(|| {
+			assert_eq!(FeeDistribution::fee_distribution(), DefaultFeeDistribution::get());
+			assert_eq!(FeeDistribution::tip_distribution(), DefaultTipDistribution::get());
+
+			let issuance = Balances::total_issuance();
+			pay(100, 30);
+			assert_eq!(Balances::free_balance(&7), 10 + 20 + 30);
+			assert_eq!(Balances::free_balance(&TREASURY), 10 + 80);
+			assert_eq!(Balances::total_issuance(), issuance);
+
+			// governance overrides the defaults.
+			assert_ok!(FeeDistribution::set_fee_distribution(Origin::ROOT, Distribution::default()));
+			assert_eq!(FeeDistribution::fee_distribution(), Distribution::default());
+		});
+	}
+}
@@ -15,16 +15,18 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! SRML module that tracks the last finalized block, as perceived by block authors.
+//!
+//! Authors hint at the best finalized block through a GRANDPA pre-runtime digest, see
+//! `sp_finality_tracker`. A hint above the parent block or not above the last hint is ignored.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use sp_inherents::{InherentIdentifier, ProvideInherent, InherentData, MakeFatalError};
 use sp_runtime::traits::{One, Zero, SaturatedConversion};
-use sp_std::{prelude::*, result, cmp, vec};
-use frame_support::{decl_module, decl_storage, decl_error, ensure};
+use sp_std::{prelude::*, cmp, vec};
+use frame_support::{decl_module, decl_storage};
 use frame_support::traits::Get;
-use frame_system::{ensure_none, Trait as SystemTrait};
-use sp_finality_tracker::{INHERENT_IDENTIFIER, FinalizedInherentData};
+use frame_system::Trait as SystemTrait;
+use sp_finality_tracker::find_finality_hint;
 
 pub const DEFAULT_WINDOW_SIZE: u32 = 101;
 pub const DEFAULT_REPORT_LATENCY: u32 = 1000;
@@ -33,6 +35,9 @@ pub trait Trait: SystemTrait {
 	/// Something which can be notified when the timestamp is set. Set this to `()`
 	/// if not needed.
 	type OnFinalizationStalled: OnFinalizationStalled<Self::BlockNumber>;
+	/// Something which can be notified of the hint of the block author. Set this to `()`
+	/// if not needed.
+	type OnFinalHint: OnFinalHint<Self::BlockNumber>;
 	/// The number of recent samples to keep from this chain. Default is 101.
 	type WindowSize: Get<Self::BlockNumber>;
 	/// The delay after which point things become suspicious. Default is 1000.
@@ -56,34 +61,19 @@ decl_storage! {
 	}
 }
 
-decl_error! {
-	pub enum Error for Module<T: Trait> {
-		/// Final hint must be updated only once in the block
-		AlreadyUpdated,
-		/// Finalized height above block number
-		BadHint,
-	}
-}
-
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-		type Error = Error<T>;
 		/// The number of recent samples to keep from this chain. Default is 101.
 		const WindowSize: T::BlockNumber = T::WindowSize::get();
 
 		/// The delay after which point things become suspicious. Default is 1000.
 		const ReportLatency: T::BlockNumber = T::ReportLatency::get();
 
-		/// Hint that the author of this block thinks the best finalized
-		/// block is the given number.
-		fn final_hint(origin, #[compact] hint: T::BlockNumber) {
-			ensure_none(origin)?;
-			ensure!(!<Self as Store>::Update::exists(), Error::<T>::AlreadyUpdated);
-			ensure!(
-				frame_system::Module::<T>::block_number() >= hint,
-				Error::<T>::BadHint,
-			);
-			<Self as Store>::Update::put(hint);
+		fn on_initialize(now: T::BlockNumber) {
+			if let Some(hint) = Self::find_hint(now) {
+				<Self as Store>::Update::put(hint);
+				T::OnFinalHint::on_final_hint(hint);
+			}
 		}
 
 		fn on_finalize() {
@@ -93,6 +83,20 @@ decl_module! {
 }
 
 impl<T: Trait> Module<T> {
+	/// The hint of the author of the block `now`, if it is valid and differs from the last one.
+	fn find_hint(now: T::BlockNumber) -> Option<T::BlockNumber> {
+		let digest = frame_system::Module::<T>::digest();
+		let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+		let hint: T::BlockNumber = find_finality_hint(pre_runtime_digests)?;
+
+		let last = Self::recent_hints().last().cloned().unwrap_or_else(Zero::zero);
+		if hint < now && hint > last {
+			Some(hint)
+		} else {
+			None
+		}
+	}
+
 	fn update_hint(hint: Option<T::BlockNumber>) {
 		if !Self::initialized() {
 			<Self as Store>::RecentHints::put(vec![T::BlockNumber::zero()]);
@@ -176,27 +180,11 @@ pub trait OnFinalizationStalled<N> {
 	fn on_stalled(further_wait: N, median: N);
 }
 
-impl<T: Trait> ProvideInherent for Module<T> {
-	type Call = Call<T>;
-	type Error = MakeFatalError<()>;
-	const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
-
-	fn create_inherent(data: &InherentData) -> Option<Self::Call> {
-		if let Ok(final_num) = data.finalized_number() {
-			// make hint only when not same as last to avoid bloat.
-			Self::recent_hints().last().and_then(|last| if last == &final_num {
-				None
-			} else {
-				Some(Call::final_hint(final_num))
-			})
-		} else {
-			None
-		}
-	}
-
-	fn check_inherent(_call: &Self::Call, _data: &InherentData) -> result::Result<(), Self::Error> {
-		Ok(())
-	}
+/// Called when the author of the block provides its hint of the best finalized block.
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+pub trait OnFinalHint<N> {
+	/// The parameter is the best finalized block as perceived by the block author.
+	fn on_final_hint(hint: N);
 }
 
 #[cfg(test)]
@@ -206,10 +194,11 @@ mod tests {
 	use sp_io::TestExternalities;
 	use sp_core::H256;
 	use sp_runtime::{
-		testing::Header, Perbill,
-		traits::{BlakeTwo256, IdentityLookup, OnFinalize, Header as HeaderT},
+		testing::{Header, Digest}, Perbill,
+		traits::{BlakeTwo256, IdentityLookup, OnInitialize, OnFinalize, Header as HeaderT},
 	};
-	use frame_support::{assert_ok, impl_outer_origin, parameter_types, weights::Weight};
+	use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+	use sp_finality_tracker::finality_hint_digest;
 	use frame_system as system;
 	use std::cell::RefCell;
 
@@ -268,6 +257,7 @@ mod tests {
 	}
 	impl Trait for Test {
 		type OnFinalizationStalled = StallTracker;
+		type OnFinalHint = ();
 		type WindowSize = WindowSize;
 		type ReportLatency = ReportLatency;
 	}
@@ -316,17 +306,15 @@ mod tests {
 		TestExternalities::new(t).execute_with(|| {
 			let mut parent_hash = System::parent_hash();
 			for i in 2..106 {
+				let digest = Digest { logs: vec![finality_hint_digest(i - 1)] };
 				System::initialize(
 					&i,
 					&parent_hash,
 					&Default::default(),
-					&Default::default(),
+					&digest,
 					Default::default(),
 				);
-				assert_ok!(FinalityTracker::dispatch(
-					Call::final_hint(i-1),
-					Origin::NONE,
-				));
+				FinalityTracker::on_initialize(i);
 				FinalityTracker::on_finalize(i);
 				let hdr = System::finalize();
 				parent_hash = hdr.hash();
@@ -335,4 +323,22 @@ mod tests {
 			assert!(NOTIFICATIONS.with(|n| n.borrow().is_empty()));
 		});
 	}
+
+	#[test]
+	fn invalid_hints_are_ignored() {
+		let t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		TestExternalities::new(t).execute_with(|| {
+			let mut parent_hash = System::parent_hash();
+			for (i, hint) in vec![(2, 1), (3, 3), (4, 0), (5, 4)] {
+				let digest = Digest { logs: vec![finality_hint_digest(hint)] };
+				System::initialize(&i, &parent_hash, &Default::default(), &digest, Default::default());
+				FinalityTracker::on_initialize(i);
+				FinalityTracker::on_finalize(i);
+				parent_hash = System::finalize().hash();
+			}
+
+			// the hint at or above its own block and the regressing hint repeat the last one.
+			assert_eq!(FinalityTracker::recent_hints(), vec![0, 1, 1, 1, 4]);
+		});
+	}
 }
@@ -32,7 +32,9 @@ pub use sp_finality_grandpa as fg_primitives;
 
 use sp_std::prelude::*;
 use codec::{self as codec, Encode, Decode};
-use frame_support::{decl_event, decl_storage, decl_module, decl_error, storage};
+use frame_support::{
+	debug, decl_event, decl_storage, decl_module, decl_error, storage, weights::SimpleDispatchInfo,
+};
 use sp_runtime::{
 	DispatchResult, generic::{DigestItem, OpaqueDigestItemId}, traits::Zero, Perbill,
 };
@@ -44,7 +46,7 @@ use fg_primitives::{
 	GRANDPA_AUTHORITIES_KEY, GRANDPA_ENGINE_ID, ScheduledChange, ConsensusLog, SetId, RoundNumber,
 };
 pub use fg_primitives::{AuthorityId, AuthorityList, AuthorityWeight, VersionedAuthorityList};
-use frame_system::{self as system, ensure_signed, DigestOf};
+use frame_system::{self as system, ensure_signed, ensure_root, DigestOf};
 
 mod mock;
 mod tests;
@@ -195,6 +197,22 @@ decl_module! {
 			// FIXME: https://github.com/paritytech/substrate/issues/1112
 		}
 
+		/// Note that the current authority set has stalled, i.e. it can't finalize
+		/// blocks anymore (e.g. because more than 1/3 of the authorities are gone).
+		///
+		/// This schedules a forced authority set change at the beginning of the next
+		/// session, enacted `delay` blocks after it. The delay should be high enough
+		/// to make sure the block signaling the change isn't reorged. The new voters
+		/// start from `best_finalized_block_number`, which should be the best block
+		/// finalized by the stalled set.
+		///
+		/// Only callable by root.
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		fn note_stalled(origin, delay: T::BlockNumber, best_finalized_block_number: T::BlockNumber) {
+			ensure_root(origin)?;
+			<Stalled<T>>::put((delay, best_finalized_block_number));
+		}
+
 		fn on_initialize() {
 			#[cfg(feature = "migrate-authorities")]
 			Self::migrate_authorities();
@@ -355,6 +373,30 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
+	/// Schedule a change to `next_authorities`, forced if the current set stalled, and return the
+	/// id of the set in effect afterwards.
+	///
+	/// If the change can't be scheduled, the current set stays in effect and a stall stays noted
+	/// so that the change is forced at the next session.
+	fn schedule_next_set(next_authorities: AuthorityList) -> SetId {
+		let result = match Self::stalled() {
+			Some((further_wait, median)) =>
+				Self::schedule_change(next_authorities, further_wait, Some(median)),
+			None => Self::schedule_change(next_authorities, Zero::zero(), None),
+		};
+
+		match result {
+			Ok(()) => {
+				<Stalled<T>>::kill();
+				CurrentSetId::mutate(|s| { *s += 1; *s })
+			},
+			Err(err) => {
+				debug::warn!("Failed to schedule a change of the GRANDPA authority set: {:?}", err);
+				Self::current_set_id()
+			},
+		}
+	}
+
 	/// Deposit one of this module's logs.
 	fn deposit_log(log: ConsensusLog<T::BlockNumber>) {
 		let log: DigestItem<T::Hash> = DigestItem::Consensus(GRANDPA_ENGINE_ID, log.encode());
@@ -436,15 +478,10 @@ impl<T: Trait> pallet_session::OneSessionHandler<T::AccountId> for Module<T>
 	{
 		// Always issue a change if `session` says that the validators have changed.
 		// Even if their session keys are the same as before, the underyling economic
-		// identities have changed.
-		let current_set_id = if changed {
+		// identities have changed. A stalled set must be replaced in any case.
+		let current_set_id = if changed || <Stalled<T>>::exists() {
 			let next_authorities = validators.map(|(_, k)| (k, 1)).collect::<Vec<_>>();
-			if let Some((further_wait, median)) = <Stalled<T>>::take() {
-				let _ = Self::schedule_change(next_authorities, further_wait, Some(median));
-			} else {
-				let _ = Self::schedule_change(next_authorities, Zero::zero(), None);
-			}
-			CurrentSetId::mutate(|s| { *s += 1; *s })
+			Self::schedule_next_set(next_authorities)
 		} else {
 			// nothing's changed, neither economic conditions nor session keys. update the pointer
 			// of the current set.
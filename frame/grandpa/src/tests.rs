@@ -291,6 +291,49 @@ fn schedule_resume_only_when_paused() {
 	});
 }
 
+#[test]
+fn note_stalled_is_root_only() {
+	new_test_ext(vec![(1, 1), (2, 1), (3, 1)]).execute_with(|| {
+		initialize_block(1, Default::default());
+
+		assert_eq!(
+			Grandpa::note_stalled(Origin::signed(1), 1000, 0),
+			Err(sp_runtime::traits::BadOrigin.into()),
+		);
+		assert_eq!(Grandpa::stalled(), None);
+
+		assert_eq!(Grandpa::note_stalled(Origin::ROOT, 1000, 0), Ok(()));
+		assert_eq!(Grandpa::stalled(), Some((1000, 0)));
+	});
+}
+
+#[test]
+fn stall_stays_noted_until_a_forced_change_is_scheduled() {
+	new_test_ext(vec![(1, 1), (2, 1), (3, 1)]).execute_with(|| {
+		initialize_block(1, Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, None).unwrap();
+		assert_eq!(Grandpa::note_stalled(Origin::ROOT, 10, 0), Ok(()));
+
+		// a change is already pending, so the set stays the same and the stall is kept.
+		assert_eq!(Grandpa::schedule_next_set(to_authorities(vec![(5, 1)])), 0);
+		assert_eq!(Grandpa::current_set_id(), 0);
+		assert_eq!(Grandpa::stalled(), Some((10, 0)));
+
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+		initialize_block(2, header.hash());
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+
+		// once the pending change was applied, the forced change is scheduled.
+		initialize_block(3, header.hash());
+		assert_eq!(Grandpa::schedule_next_set(to_authorities(vec![(5, 1)])), 1);
+		assert_eq!(Grandpa::current_set_id(), 1);
+		assert_eq!(Grandpa::stalled(), None);
+		assert_eq!(<PendingChange<Test>>::get().unwrap().forced, Some(0));
+	});
+}
+
 #[test]
 fn time_slot_have_sane_ord() {
 	// Ensure that `Ord` implementation is sane.
@@ -181,6 +181,13 @@ pub struct Heartbeat<BlockNumber>
 	pub authority_index: AuthIndex,
 }
 
+/// Called when a heartbeat of a validator of the current session is received.
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+pub trait OnHeartbeat<ValidatorId> {
+	/// The validator that sent the heartbeat.
+	fn on_heartbeat(validator: &ValidatorId);
+}
+
 pub trait Trait: frame_system::Trait + pallet_session::historical::Trait {
 	/// The identifier type for an authority.
 	type AuthorityId: Member + Parameter + RuntimeAppPublic + Default + Ord;
@@ -207,6 +214,9 @@ pub trait Trait: frame_system::Trait + pallet_session::historical::Trait {
 			IdentificationTuple<Self>,
 			UnresponsivenessOffence<IdentificationTuple<Self>>,
 		>;
+
+	/// Something which can be notified of received heartbeats. Set this to `()` if not needed.
+	type OnHeartbeat: OnHeartbeat<Self::ValidatorId>;
 }
 
 decl_event!(
@@ -287,6 +297,11 @@ decl_module! {
 					&heartbeat.authority_index,
 					&network_state
 				);
+
+				let validators = <pallet_session::Module<T>>::validators();
+				if let Some(validator) = validators.get(heartbeat.authority_index as usize) {
+					T::OnHeartbeat::on_heartbeat(validator);
+				}
 			} else if exists {
 				Err(Error::<T>::DuplicatedHeartbeat)?
 			} else {
@@ -164,6 +164,7 @@ impl Trait for Runtime {
 	type SubmitTransaction = SubmitTransaction;
 	type ReportUnresponsiveness = OffenceHandler;
 	type SessionDuration = Period;
+	type OnHeartbeat = ();
 }
 
 /// Im Online module.
@@ -0,0 +1,496 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Meta Transaction Module
+//!
+//! - [`meta_tx::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Meta Transaction module lets a sponsor submit calls signed by other accounts, dispatching
+//! them from the signer's origin while the sponsor pays the transaction fee. Accounts holding no
+//! native currency can thus use the chain through a relayer.
+//!
+//! The signer signs the [`payload`](./struct.Module.html#method.payload) of the call, which
+//! contains the genesis hash of the chain, a nonce kept by this module and a deadline block, so a
+//! signed call is dispatched at most once, on one chain only, and not after its deadline.
+//!
+//! Every sponsor sets a budget for the calls it relays, which is reserved from its balance. The
+//! fee the Transaction Payment module computes for the weight and length of each relayed call is
+//! deducted from it and unreserved, and calls exceeding the remaining budget are rejected. This
+//! bounds the funds a relaying service spends, even if its users flood it with calls.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `set_budget` - Set the budget of the sender for sponsoring calls.
+//! * `sponsor_call` - Dispatch a call signed by another account, paying its fee.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::Encode;
+use sp_runtime::{
+	DispatchResult,
+	traits::{CheckedSub, Dispatchable, IdentifyAccount, One, Verify, Zero},
+};
+use frame_support::{
+	decl_module, decl_event, decl_storage, decl_error, ensure, Parameter,
+	traits::{Currency, ReservableCurrency},
+	weights::{
+		ClassifyDispatch, DispatchClass, GetDispatchInfo, PaysFee, SimpleDispatchInfo, WeighData,
+		Weight,
+	},
+};
+use frame_system::{self as system, ensure_signed};
+
+type BalanceOf<T> = <<T as pallet_transaction_payment::Trait>::Currency as Currency<
+	<T as frame_system::Trait>::AccountId,
+>>::Balance;
+
+pub trait Trait: pallet_transaction_payment::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The overarching call type.
+	type Call: Parameter + Dispatchable<Origin=Self::Origin> + GetDispatchInfo;
+
+	/// The signature of sponsored calls.
+	type Signature: Parameter + Verify<Signer = Self::Public>;
+
+	/// The public key of the signers of sponsored calls.
+	type Public: IdentifyAccount<AccountId = Self::AccountId>;
+
+	/// The currency the budgets of the sponsors are reserved in.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = BalanceOf<Self>>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as MetaTx {
+		/// The nonce of the next sponsored call of an account.
+		Nonces get(fn nonce): map T::AccountId => T::Index;
+
+		/// The fees a sponsor is still willing to pay for the calls it relays, reserved from its
+		/// balance.
+		Budgets get(fn budget): map T::AccountId => BalanceOf<T>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as frame_system::Trait>::AccountId,
+		Balance = BalanceOf<T>,
+	{
+		/// The budget of a sponsor was set.
+		BudgetSet(AccountId, Balance),
+		/// A sponsor dispatched a call of a signer, paying the given fee from its budget.
+		CallSponsored(AccountId, AccountId, Balance, DispatchResult),
+	}
+);
+
+decl_error! {
+	/// Error for the meta transaction module.
+	pub enum Error for Module<T: Trait> {
+		/// The signature doesn't match the signer and the payload.
+		BadSignature,
+		/// The nonce isn't the next nonce of the signer.
+		BadNonce,
+		/// The fee of the call exceeds the remaining budget of the sponsor.
+		BudgetExceeded,
+		/// The deadline of the signed call has passed.
+		Expired,
+	}
+}
+
+/// Pass through the weight, class and fee flag of the sponsored call.
+struct SponsoredPassthrough<AccountId, Index, BlockNumber, Call, Signature>(
+	sp_std::marker::PhantomData<(AccountId, Index, BlockNumber, Call, Signature)>
+);
+
+impl<AccountId, Index, BlockNumber, Call, Signature>
+	SponsoredPassthrough<AccountId, Index, BlockNumber, Call, Signature>
+{
+	fn new() -> Self { Self(Default::default()) }
+}
+impl<AccountId, Index, BlockNumber, Call: GetDispatchInfo, Signature>
+	WeighData<(&AccountId, &Index, &BlockNumber, &Box<Call>, &Signature)>
+	for SponsoredPassthrough<AccountId, Index, BlockNumber, Call, Signature>
+{
+	fn weigh_data(
+		&self,
+		(_, _, _, call, _): (&AccountId, &Index, &BlockNumber, &Box<Call>, &Signature),
+	) -> Weight {
+		// Verifying the signature comes on top of the call.
+		call.get_dispatch_info().weight.saturating_add(50_000)
+	}
+}
+impl<AccountId, Index, BlockNumber, Call: GetDispatchInfo, Signature>
+	ClassifyDispatch<(&AccountId, &Index, &BlockNumber, &Box<Call>, &Signature)>
+	for SponsoredPassthrough<AccountId, Index, BlockNumber, Call, Signature>
+{
+	fn classify_dispatch(
+		&self,
+		(_, _, _, call, _): (&AccountId, &Index, &BlockNumber, &Box<Call>, &Signature),
+	) -> DispatchClass {
+		call.get_dispatch_info().class
+	}
+}
+impl<AccountId, Index, BlockNumber, Call, Signature>
+	PaysFee<(&AccountId, &Index, &BlockNumber, &Box<Call>, &Signature)>
+	for SponsoredPassthrough<AccountId, Index, BlockNumber, Call, Signature>
+{
+	fn pays_fee(&self, _: (&AccountId, &Index, &BlockNumber, &Box<Call>, &Signature)) -> bool {
+		// The sponsor always pays.
+		true
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Set the budget of the sender for the fees of the calls it sponsors, reserving the
+		/// increase from its balance or unreserving the decrease.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One storage read, one storage write.
+		/// - One balance reserve or unreserve.
+		/// - One event.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+		fn set_budget(origin, budget: BalanceOf<T>) {
+			let who = ensure_signed(origin)?;
+
+			let old_budget = Self::budget(&who);
+			if budget > old_budget {
+				<T as Trait>::Currency::reserve(&who, budget - old_budget)?;
+			} else {
+				<T as Trait>::Currency::unreserve(&who, old_budget - budget);
+			}
+
+			if budget.is_zero() {
+				<Budgets<T>>::remove(&who);
+			} else {
+				<Budgets<T>>::insert(&who, budget);
+			}
+
+			Self::deposit_event(RawEvent::BudgetSet(who, budget));
+		}
+
+		/// Dispatch `call` from the origin of `signer`, who signed the payload of `call`, `nonce`
+		/// and `deadline` with `signature`. The call is rejected after the `deadline` block. The
+		/// fee of the call is deducted from the budget of the sender and unreserved, and the
+		/// sender pays the fee of this transaction.
+		///
+		/// The nonce of the signer is used up even if `call` fails.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - The weight of the `call`, plus verifying the signature.
+		/// - Two storage reads, two storage writes.
+		/// - One balance unreserve.
+		/// - One event.
+		/// # </weight>
+		#[weight = <SponsoredPassthrough<
+			T::AccountId, T::Index, T::BlockNumber, <T as Trait>::Call, T::Signature,
+		>>::new()]
+		fn sponsor_call(origin,
+			signer: T::AccountId,
+			nonce: T::Index,
+			deadline: T::BlockNumber,
+			call: Box<<T as Trait>::Call>,
+			signature: T::Signature,
+		) {
+			let sponsor = ensure_signed(origin)?;
+
+			ensure!(
+				<frame_system::Module<T>>::block_number() <= deadline,
+				Error::<T>::Expired,
+			);
+			ensure!(nonce == Self::nonce(&signer), Error::<T>::BadNonce);
+			ensure!(
+				signature.verify(&Self::payload(nonce, deadline, &call)[..], &signer),
+				Error::<T>::BadSignature,
+			);
+			let fee = <pallet_transaction_payment::Module<T>>::compute_fee(
+				call.encoded_size() as u32,
+				call.get_dispatch_info(),
+				Zero::zero(),
+			);
+			let budget = Self::budget(&sponsor).checked_sub(&fee).ok_or(Error::<T>::BudgetExceeded)?;
+
+			<Nonces<T>>::insert(&signer, nonce + One::one());
+			<Budgets<T>>::insert(&sponsor, budget);
+			<T as Trait>::Currency::unreserve(&sponsor, fee);
+
+			let result = call.dispatch(frame_system::RawOrigin::Signed(signer.clone()).into());
+
+			Self::deposit_event(RawEvent::CallSponsored(sponsor, signer, fee, result));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The payload a signer signs for `call` to be sponsored with `nonce` until the `deadline`
+	/// block.
+	pub fn payload(nonce: T::Index, deadline: T::BlockNumber, call: &<T as Trait>::Call) -> Vec<u8> {
+		let genesis_hash = <frame_system::Module<T>>::block_hash(T::BlockNumber::zero());
+		(genesis_hash, nonce, deadline, call).encode()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use frame_support::{
+		assert_ok, assert_noop, impl_outer_origin, impl_outer_dispatch, parameter_types,
+	};
+	use sp_core::{H256, sr25519};
+	use sp_keyring::Sr25519Keyring;
+	use sp_runtime::{Perbill, testing::Header, traits::{BlakeTwo256, ConvertInto, IdentityLookup}};
+	use crate as meta_tx;
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+	impl_outer_dispatch! {
+		pub enum Call for Test where origin: Origin {
+			pallet_balances::Balances,
+			meta_tx::MetaTx,
+		}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = Call;
+		type Hashing = BlakeTwo256;
+		type AccountId = sr25519::Public;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u64 = 1;
+		pub const TransferFee: u64 = 0;
+		pub const CreationFee: u64 = 0;
+	}
+	impl pallet_balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnReapAccount = System;
+		type OnNewAccount = ();
+		type Event = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+	}
+	parameter_types! {
+		pub const TransactionBaseFee: u64 = 0;
+		pub const TransactionByteFee: u64 = 0;
+	}
+	impl pallet_transaction_payment::Trait for Test {
+		type Currency = Balances;
+		type OnTransactionPayment = ();
+		type TransactionBaseFee = TransactionBaseFee;
+		type TransactionByteFee = TransactionByteFee;
+		type WeightToFee = ConvertInto;
+		type OperationalWeightToFee = ConvertInto;
+		type FeeMultiplierUpdate = ();
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Call = Call;
+		type Signature = sr25519::Signature;
+		type Public = sr25519::Public;
+		type Currency = Balances;
+	}
+	type System = frame_system::Module<Test>;
+	type Balances = pallet_balances::Module<Test>;
+	type MetaTx = Module<Test>;
+
+	const ALICE: Sr25519Keyring = Sr25519Keyring::Alice;
+	const BOB: Sr25519Keyring = Sr25519Keyring::Bob;
+	const RELAYER: Sr25519Keyring = Sr25519Keyring::Charlie;
+	const DEADLINE: u64 = 10;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(ALICE.public(), 100), (RELAYER.public(), 100_000)],
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	/// A transfer from Alice to Bob, signed by Alice with the given nonce until `DEADLINE`.
+	fn signed_transfer(nonce: u64, value: u64) -> (Box<Call>, sr25519::Signature) {
+		let call = Call::Balances(pallet_balances::Call::transfer(BOB.public(), value));
+		let signature = ALICE.sign(&MetaTx::payload(nonce, DEADLINE, &call));
+		(Box::new(call), signature)
+	}
+
+	#[test]
+	fn sponsored_call_is_dispatched_from_signer() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(MetaTx::set_budget(Origin::signed(RELAYER.public()), 2_000));
+
+			let (call, signature) = signed_transfer(0, 10);
+			assert_ok!(MetaTx::sponsor_call(
+				Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE, call, signature,
+			));
+			assert_eq!(Balances::free_balance(&ALICE.public()), 90);
+			assert_eq!(Balances::free_balance(&BOB.public()), 10);
+			assert_eq!(MetaTx::nonce(&ALICE.public()), 1);
+			// The weight of a transfer, capped to the maximum block weight.
+			assert_eq!(MetaTx::budget(&RELAYER.public()), 2_000 - 1_024);
+			assert_eq!(Balances::reserved_balance(&RELAYER.public()), 2_000 - 1_024);
+		});
+	}
+
+	#[test]
+	fn budgets_are_reserved() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(MetaTx::set_budget(Origin::signed(RELAYER.public()), 2_000));
+			assert_eq!(Balances::reserved_balance(&RELAYER.public()), 2_000);
+			assert_eq!(Balances::free_balance(&RELAYER.public()), 98_000);
+
+			assert_ok!(MetaTx::set_budget(Origin::signed(RELAYER.public()), 500));
+			assert_eq!(Balances::reserved_balance(&RELAYER.public()), 500);
+			assert_eq!(Balances::free_balance(&RELAYER.public()), 99_500);
+
+			assert_noop!(
+				MetaTx::set_budget(Origin::signed(RELAYER.public()), 200_000),
+				pallet_balances::Error::<Test, _>::InsufficientBalance,
+			);
+			assert_noop!(
+				MetaTx::set_budget(Origin::signed(BOB.public()), 1),
+				pallet_balances::Error::<Test, _>::InsufficientBalance,
+			);
+
+			assert_ok!(MetaTx::set_budget(Origin::signed(RELAYER.public()), 0));
+			assert_eq!(Balances::reserved_balance(&RELAYER.public()), 0);
+			assert_eq!(Balances::free_balance(&RELAYER.public()), 100_000);
+			assert!(!<Budgets<Test>>::exists(&RELAYER.public()));
+		});
+	}
+
+	#[test]
+	fn sponsored_calls_expire_after_their_deadline() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(MetaTx::set_budget(Origin::signed(RELAYER.public()), 10_000));
+
+			let (call, signature) = signed_transfer(0, 10);
+			System::set_block_number(DEADLINE + 1);
+			assert_noop!(
+				MetaTx::sponsor_call(
+					Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE, call.clone(), signature.clone(),
+				),
+				Error::<Test>::Expired,
+			);
+			// The deadline is part of the signed payload.
+			assert_noop!(
+				MetaTx::sponsor_call(
+					Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE + 1, call.clone(), signature.clone(),
+				),
+				Error::<Test>::BadSignature,
+			);
+
+			System::set_block_number(DEADLINE);
+			assert_ok!(MetaTx::sponsor_call(
+				Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE, call, signature,
+			));
+		});
+	}
+
+	#[test]
+	fn sponsored_calls_cannot_be_replayed_or_forged() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(MetaTx::set_budget(Origin::signed(RELAYER.public()), 10_000));
+
+			let (call, signature) = signed_transfer(0, 10);
+			assert_ok!(MetaTx::sponsor_call(
+				Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE, call.clone(), signature.clone(),
+			));
+			assert_noop!(
+				MetaTx::sponsor_call(Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE, call, signature),
+				Error::<Test>::BadNonce,
+			);
+
+			let (call, signature) = signed_transfer(1, 10);
+			assert_noop!(
+				MetaTx::sponsor_call(Origin::signed(RELAYER.public()), BOB.public(), 0, DEADLINE, call.clone(), signature.clone()),
+				Error::<Test>::BadSignature,
+			);
+			let (other_call, _) = signed_transfer(1, 20);
+			assert_noop!(
+				MetaTx::sponsor_call(Origin::signed(RELAYER.public()), ALICE.public(), 1, DEADLINE, other_call, signature),
+				Error::<Test>::BadSignature,
+			);
+		});
+	}
+
+	#[test]
+	fn sponsors_are_limited_by_their_budget() {
+		new_test_ext().execute_with(|| {
+			let (call, signature) = signed_transfer(0, 10);
+			assert_noop!(
+				MetaTx::sponsor_call(Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE, call.clone(), signature.clone()),
+				Error::<Test>::BudgetExceeded,
+			);
+
+			assert_ok!(MetaTx::set_budget(Origin::signed(RELAYER.public()), 1_500));
+			assert_ok!(MetaTx::sponsor_call(
+				Origin::signed(RELAYER.public()), ALICE.public(), 0, DEADLINE, call, signature,
+			));
+			let (call, signature) = signed_transfer(1, 10);
+			assert_noop!(
+				MetaTx::sponsor_call(Origin::signed(RELAYER.public()), ALICE.public(), 1, DEADLINE, call, signature),
+				Error::<Test>::BudgetExceeded,
+			);
+		});
+	}
+}
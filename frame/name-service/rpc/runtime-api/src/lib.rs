@@ -0,0 +1,39 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime API definition for the name service module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::Codec;
+
+sp_api::decl_runtime_apis! {
+	/// Resolution of the names registered with the name service module.
+	pub trait NameServiceApi<AccountId, BlockNumber> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// The account `name` resolves to, if it is registered and not expired.
+		fn resolve(name: Vec<u8>) -> Option<AccountId>;
+
+		/// The name `who` chose as its reverse record, if it still resolves to `who`.
+		fn reverse(who: AccountId) -> Option<Vec<u8>>;
+
+		/// The block the registration of `name` expires at, if it is registered.
+		fn expiry(name: Vec<u8>) -> Option<BlockNumber>;
+	}
+}
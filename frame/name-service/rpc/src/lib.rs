@@ -0,0 +1,131 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface for the name service module.
+
+use std::sync::Arc;
+use codec::Codec;
+use sp_blockchain::HeaderBackend;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+pub use pallet_name_service_rpc_runtime_api::NameServiceApi as NameServiceRuntimeApi;
+pub use self::gen_client::Client as NameServiceClient;
+
+#[rpc]
+pub trait NameServiceApi<BlockHash, AccountId, BlockNumber> {
+	/// The account `name` resolves to at the given block, or the best block.
+	#[rpc(name = "nameService_resolve")]
+	fn resolve(&self, name: String, at: Option<BlockHash>) -> Result<Option<AccountId>>;
+
+	/// The name `who` chose as its reverse record, if it still resolves to `who`.
+	#[rpc(name = "nameService_reverse")]
+	fn reverse(&self, who: AccountId, at: Option<BlockHash>) -> Result<Option<String>>;
+
+	/// The block the registration of `name` expires at, if it is registered.
+	#[rpc(name = "nameService_expiry")]
+	fn expiry(&self, name: String, at: Option<BlockHash>) -> Result<Option<BlockNumber>>;
+}
+
+/// A struct that implements the [`NameServiceApi`].
+pub struct NameService<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> NameService<C, B> {
+	/// Create new `NameService` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		NameService { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// The runtime at the given block does not implement the api.
+	UnsupportedApi,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::UnsupportedApi => 2,
+		}
+	}
+}
+
+fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query the name service.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, AccountId, BlockNumber> NameService<C, (Block, AccountId, BlockNumber)> where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: NameServiceRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec,
+	BlockNumber: Codec,
+{
+	/// The block to query at, checking that its runtime implements the api.
+	fn at(&self, at: Option<<Block as BlockT>::Hash>) -> Result<BlockId<Block>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		let has_api = self.client.runtime_api().has_api::<
+			dyn NameServiceRuntimeApi<Block, AccountId, BlockNumber, Error = ()>
+		>(&at).map_err(runtime_error)?;
+		if !has_api {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::UnsupportedApi.into()),
+				message: "Name service api is not available at the given block.".into(),
+				data: None,
+			})
+		}
+		Ok(at)
+	}
+}
+
+impl<C, Block, AccountId, BlockNumber> NameServiceApi<<Block as BlockT>::Hash, AccountId, BlockNumber>
+	for NameService<C, (Block, AccountId, BlockNumber)>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: NameServiceRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec + Send + Sync + 'static,
+	BlockNumber: Codec + Send + Sync + 'static,
+{
+	fn resolve(&self, name: String, at: Option<<Block as BlockT>::Hash>) -> Result<Option<AccountId>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().resolve(&at, name.into_bytes()).map_err(runtime_error)
+	}
+
+	fn reverse(&self, who: AccountId, at: Option<<Block as BlockT>::Hash>) -> Result<Option<String>> {
+		let at = self.at(at)?;
+		let name = self.client.runtime_api().reverse(&at, who).map_err(runtime_error)?;
+		// registered names are ASCII.
+		Ok(name.map(|name| String::from_utf8_lossy(&name).into_owned()))
+	}
+
+	fn expiry(&self, name: String, at: Option<<Block as BlockT>::Hash>) -> Result<Option<BlockNumber>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().expiry(&at, name.into_bytes()).map_err(runtime_error)
+	}
+}
@@ -0,0 +1,706 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Name Service Module
+//!
+//! - [`name_service::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Name Service module registers human-readable names, resolving to an account, for a
+//! number of registration periods. Names are made of lowercase ASCII letters, digits and
+//! hyphens, so that they can be used as payment addresses.
+//!
+//! Names are registered in two steps so that a registration cannot be front-run: a hash of the
+//! account, the name and a secret is committed first, with `CommitmentDeposit` reserved, and the
+//! name is revealed between `MinCommitmentAge` and `MaxCommitmentAge` blocks later. The
+//! registration fee is paid for each period on reveal, and registrations can be renewed by
+//! anyone. An expired name stops resolving, and can be registered by anyone else once
+//! `GracePeriod` more blocks have passed without renewal.
+//!
+//! An account may also choose a name as its reverse record, which is only returned while the
+//! name resolves to the account.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `commit` - Commit to the registration of a name.
+//! * `cancel_commitment` - Remove a commitment, returning its deposit.
+//! * `reveal` - Register a committed name.
+//! * `renew` - Extend the registration of a name.
+//! * `set_target` - Change the account a name resolves to.
+//! * `transfer` - Transfer the ownership of a name.
+//! * `set_reverse` - Choose the name an account is known by.
+//! * `clear_reverse` - Remove the reverse record of an account.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Encode, Decode};
+use sp_runtime::{RuntimeDebug, traits::{Hash, Saturating}};
+use frame_support::{
+	decl_module, decl_event, decl_storage, ensure, decl_error,
+	traits::{
+		Currency, ReservableCurrency, OnUnbalanced, Get, WithdrawReason, ExistenceRequirement,
+	},
+	weights::SimpleDispatchInfo,
+};
+use frame_system::{self as system, ensure_signed};
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
+
+pub trait Trait: frame_system::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The currency trait.
+	type Currency: ReservableCurrency<Self::AccountId>;
+
+	/// What to do with registration fees.
+	type FeeDestination: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+	/// The deposit reserved while a commitment is pending.
+	type CommitmentDeposit: Get<BalanceOf<Self>>;
+
+	/// The fee paid for each registration period.
+	type RegistrationFee: Get<BalanceOf<Self>>;
+
+	/// The length of a registration period.
+	type RegistrationPeriod: Get<Self::BlockNumber>;
+
+	/// The maximum number of periods a name may be registered or renewed for at once.
+	type MaxPeriods: Get<u32>;
+
+	/// The number of blocks after its expiry during which a name can only be renewed.
+	type GracePeriod: Get<Self::BlockNumber>;
+
+	/// The number of blocks a commitment must wait before being revealed.
+	type MinCommitmentAge: Get<Self::BlockNumber>;
+
+	/// The number of blocks after which a commitment can no longer be revealed.
+	type MaxCommitmentAge: Get<Self::BlockNumber>;
+
+	/// The minimum length a name may be.
+	type MinLength: Get<usize>;
+
+	/// The maximum length a name may be.
+	type MaxLength: Get<usize>;
+}
+
+/// A pending commitment to register a name.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Commitment<AccountId, BlockNumber, Balance> {
+	/// The account which committed.
+	pub who: AccountId,
+	/// The block the commitment was made at.
+	pub when: BlockNumber,
+	/// The reserved deposit.
+	pub deposit: Balance,
+}
+
+/// The registration of a name.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Registration<AccountId, BlockNumber> {
+	/// The account which may manage the name.
+	pub owner: AccountId,
+	/// The account the name resolves to.
+	pub target: AccountId,
+	/// The block the registration expires at.
+	pub expiry: BlockNumber,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as NameService {
+		/// The pending commitments, by hash of the account, the name and the secret.
+		pub Commitments get(fn commitment): map T::Hash => Option<Commitment<T::AccountId, T::BlockNumber, BalanceOf<T>>>;
+
+		/// The registrations, by hash of the name.
+		pub Registrations get(fn registration): map T::Hash => Option<Registration<T::AccountId, T::BlockNumber>>;
+
+		/// The name each account chose to be known by.
+		pub ReverseRecords get(fn reverse_record): map T::AccountId => Option<Vec<u8>>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		AccountId = <T as frame_system::Trait>::AccountId,
+		Hash = <T as frame_system::Trait>::Hash,
+		BlockNumber = <T as frame_system::Trait>::BlockNumber,
+	{
+		/// A commitment was made.
+		Committed(AccountId, Hash),
+		/// A name was registered by an account, until the given block.
+		Registered(Hash, AccountId, BlockNumber),
+		/// A registration was renewed until the given block.
+		Renewed(Hash, BlockNumber),
+		/// A name now resolves to an account.
+		TargetSet(Hash, AccountId),
+		/// A name was transferred to a new owner.
+		Transferred(Hash, AccountId),
+		/// An account chose the name it is known by.
+		ReverseSet(AccountId),
+		/// An account removed its reverse record.
+		ReverseCleared(AccountId),
+	}
+);
+
+decl_error! {
+	/// Error for the name service module.
+	pub enum Error for Module<T: Trait> {
+		/// A name is too short.
+		TooShort,
+		/// A name is too long.
+		TooLong,
+		/// A name contains other characters than lowercase letters, digits and hyphens.
+		InvalidCharacter,
+		/// The commitment already exists.
+		AlreadyCommitted,
+		/// There is no such commitment.
+		NoCommitment,
+		/// The commitment cannot be revealed yet.
+		CommitmentTooNew,
+		/// The commitment can no longer be revealed.
+		CommitmentTooOld,
+		/// The name is registered.
+		Unavailable,
+		/// The name is not registered.
+		NotRegistered,
+		/// The registration expired.
+		Expired,
+		/// The sender does not own the name.
+		NotOwner,
+		/// The name does not resolve to the sender.
+		NotTarget,
+		/// The number of periods is zero or exceeds `MaxPeriods`.
+		InvalidPeriods,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// The deposit reserved while a commitment is pending.
+		const CommitmentDeposit: BalanceOf<T> = T::CommitmentDeposit::get();
+
+		/// The fee paid for each registration period.
+		const RegistrationFee: BalanceOf<T> = T::RegistrationFee::get();
+
+		/// The length of a registration period.
+		const RegistrationPeriod: T::BlockNumber = T::RegistrationPeriod::get();
+
+		/// The maximum number of periods a name may be registered or renewed for at once.
+		const MaxPeriods: u32 = T::MaxPeriods::get();
+
+		/// The number of blocks after its expiry during which a name can only be renewed.
+		const GracePeriod: T::BlockNumber = T::GracePeriod::get();
+
+		/// The minimum length a name may be.
+		const MinLength: u32 = T::MinLength::get() as u32;
+
+		/// The maximum length a name may be.
+		const MaxLength: u32 = T::MaxLength::get() as u32;
+
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Commit to the registration of a name. `commitment` is the hash of the sender, the
+		/// name and a secret, as given by `commitment_of`. `CommitmentDeposit` is reserved until
+		/// the commitment is revealed or cancelled.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One balance reserve operation.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(50_000)]
+		fn commit(origin, commitment: T::Hash) {
+			let who = ensure_signed(origin)?;
+			ensure!(!<Commitments<T>>::exists(&commitment), Error::<T>::AlreadyCommitted);
+
+			let deposit = T::CommitmentDeposit::get();
+			T::Currency::reserve(&who, deposit)?;
+			let when = <frame_system::Module<T>>::block_number();
+			<Commitments<T>>::insert(&commitment, Commitment { who: who.clone(), when, deposit });
+			Self::deposit_event(RawEvent::Committed(who, commitment));
+		}
+
+		/// Remove a commitment of the sender, returning its deposit.
+		///
+		/// The dispatch origin for this call must be _Signed_ by the account which committed.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One balance operation.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(50_000)]
+		fn cancel_commitment(origin, commitment: T::Hash) {
+			let who = ensure_signed(origin)?;
+			let pending = Self::commitment(&commitment)
+				.filter(|pending| pending.who == who)
+				.ok_or(Error::<T>::NoCommitment)?;
+			T::Currency::unreserve(&who, pending.deposit);
+			<Commitments<T>>::remove(&commitment);
+		}
+
+		/// Register `name` for `periods` registration periods, revealing the commitment made
+		/// with `secret`. The registration fee is paid for each period and the commitment
+		/// deposit is returned. The name resolves to the sender.
+		///
+		/// The dispatch origin for this call must be _Signed_ by the account which committed.
+		///
+		/// # <weight>
+		/// - O(N) where N is the length of `name`.
+		/// - Two balance operations.
+		/// - Two storage writes.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(100_000)]
+		fn reveal(origin, name: Vec<u8>, secret: T::Hash, periods: u32) {
+			let who = ensure_signed(origin)?;
+			Self::validate_name(&name)?;
+			ensure!(periods > 0 && periods <= T::MaxPeriods::get(), Error::<T>::InvalidPeriods);
+
+			let commitment = Self::commitment_of(&who, &name, &secret);
+			let pending = Self::commitment(&commitment).ok_or(Error::<T>::NoCommitment)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(
+				now >= pending.when.saturating_add(T::MinCommitmentAge::get()),
+				Error::<T>::CommitmentTooNew,
+			);
+			ensure!(
+				now <= pending.when.saturating_add(T::MaxCommitmentAge::get()),
+				Error::<T>::CommitmentTooOld,
+			);
+
+			let name_hash = T::Hashing::hash(&name);
+			ensure!(
+				Self::registration(&name_hash).map_or(true, |registration| {
+					now > registration.expiry.saturating_add(T::GracePeriod::get())
+				}),
+				Error::<T>::Unavailable,
+			);
+
+			Self::charge(&who, periods)?;
+			T::Currency::unreserve(&who, pending.deposit);
+			<Commitments<T>>::remove(&commitment);
+
+			let expiry = now.saturating_add(Self::duration(periods));
+			<Registrations<T>>::insert(&name_hash, Registration {
+				owner: who.clone(),
+				target: who.clone(),
+				expiry,
+			});
+			Self::deposit_event(RawEvent::Registered(name_hash, who, expiry));
+		}
+
+		/// Extend the registration of `name` by `periods` registration periods, paying the
+		/// registration fee for each. Expired names can be renewed during the grace period.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(N) where N is the length of `name`.
+		/// - One balance operation.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(100_000)]
+		fn renew(origin, name: Vec<u8>, periods: u32) {
+			let who = ensure_signed(origin)?;
+			ensure!(periods > 0 && periods <= T::MaxPeriods::get(), Error::<T>::InvalidPeriods);
+
+			let name_hash = T::Hashing::hash(&name);
+			let mut registration = Self::registration(&name_hash).ok_or(Error::<T>::NotRegistered)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(
+				now <= registration.expiry.saturating_add(T::GracePeriod::get()),
+				Error::<T>::Expired,
+			);
+
+			Self::charge(&who, periods)?;
+			registration.expiry = registration.expiry.saturating_add(Self::duration(periods));
+			let expiry = registration.expiry;
+			<Registrations<T>>::insert(&name_hash, registration);
+			Self::deposit_event(RawEvent::Renewed(name_hash, expiry));
+		}
+
+		/// Make `name` resolve to `target`.
+		///
+		/// The dispatch origin for this call must be _Signed_ by the owner of the name.
+		///
+		/// # <weight>
+		/// - O(N) where N is the length of `name`.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(50_000)]
+		fn set_target(origin, name: Vec<u8>, target: T::AccountId) {
+			let who = ensure_signed(origin)?;
+			let name_hash = T::Hashing::hash(&name);
+			let mut registration = Self::owned_registration(&who, &name_hash)?;
+			registration.target = target.clone();
+			<Registrations<T>>::insert(&name_hash, registration);
+			Self::deposit_event(RawEvent::TargetSet(name_hash, target));
+		}
+
+		/// Transfer the ownership of `name` to `new_owner`. The account it resolves to is
+		/// unchanged.
+		///
+		/// The dispatch origin for this call must be _Signed_ by the owner of the name.
+		///
+		/// # <weight>
+		/// - O(N) where N is the length of `name`.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(50_000)]
+		fn transfer(origin, name: Vec<u8>, new_owner: T::AccountId) {
+			let who = ensure_signed(origin)?;
+			let name_hash = T::Hashing::hash(&name);
+			let mut registration = Self::owned_registration(&who, &name_hash)?;
+			registration.owner = new_owner.clone();
+			<Registrations<T>>::insert(&name_hash, registration);
+			Self::deposit_event(RawEvent::Transferred(name_hash, new_owner));
+		}
+
+		/// Choose `name` as the name the sender is known by. The name must resolve to the
+		/// sender.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(N) where N is the length of `name`.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(50_000)]
+		fn set_reverse(origin, name: Vec<u8>) {
+			let who = ensure_signed(origin)?;
+			ensure!(Self::resolve(&name).as_ref() == Some(&who), Error::<T>::NotTarget);
+			<ReverseRecords<T>>::insert(&who, name);
+			Self::deposit_event(RawEvent::ReverseSet(who));
+		}
+
+		/// Remove the reverse record of the sender.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(50_000)]
+		fn clear_reverse(origin) {
+			let who = ensure_signed(origin)?;
+			<ReverseRecords<T>>::remove(&who);
+			Self::deposit_event(RawEvent::ReverseCleared(who));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The commitment `who` makes to register `name` with `secret`.
+	pub fn commitment_of(who: &T::AccountId, name: &[u8], secret: &T::Hash) -> T::Hash {
+		T::Hashing::hash_of(&(who, name, secret))
+	}
+
+	/// The account `name` resolves to, if it is registered and not expired.
+	pub fn resolve(name: &[u8]) -> Option<T::AccountId> {
+		let now = <frame_system::Module<T>>::block_number();
+		Self::registration(T::Hashing::hash(name))
+			.filter(|registration| now <= registration.expiry)
+			.map(|registration| registration.target)
+	}
+
+	/// The name `who` chose as its reverse record, if it still resolves to `who`.
+	pub fn reverse(who: &T::AccountId) -> Option<Vec<u8>> {
+		Self::reverse_record(who).filter(|name| Self::resolve(name).as_ref() == Some(who))
+	}
+
+	/// The block the registration of `name` expires at, if it is registered.
+	pub fn expiry(name: &[u8]) -> Option<T::BlockNumber> {
+		Self::registration(T::Hashing::hash(name)).map(|registration| registration.expiry)
+	}
+
+	/// Check the length and the characters of `name`.
+	fn validate_name(name: &[u8]) -> Result<(), Error<T>> {
+		ensure!(name.len() >= T::MinLength::get(), Error::<T>::TooShort);
+		ensure!(name.len() <= T::MaxLength::get(), Error::<T>::TooLong);
+		ensure!(
+			name.iter().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == b'-'),
+			Error::<T>::InvalidCharacter,
+		);
+		Ok(())
+	}
+
+	/// The registration of `name_hash`, if owned by `who` and not expired.
+	fn owned_registration(
+		who: &T::AccountId,
+		name_hash: &T::Hash,
+	) -> Result<Registration<T::AccountId, T::BlockNumber>, Error<T>> {
+		let registration = Self::registration(name_hash).ok_or(Error::<T>::NotRegistered)?;
+		ensure!(&registration.owner == who, Error::<T>::NotOwner);
+		ensure!(
+			<frame_system::Module<T>>::block_number() <= registration.expiry,
+			Error::<T>::Expired,
+		);
+		Ok(registration)
+	}
+
+	/// Make `who` pay the registration fee for `periods` periods.
+	fn charge(who: &T::AccountId, periods: u32) -> frame_support::dispatch::DispatchResult {
+		let fee = T::RegistrationFee::get().saturating_mul(periods.into());
+		let imbalance = T::Currency::withdraw(
+			who,
+			fee,
+			WithdrawReason::Fee.into(),
+			ExistenceRequirement::KeepAlive,
+		)?;
+		T::FeeDestination::on_unbalanced(imbalance);
+		Ok(())
+	}
+
+	/// The number of blocks of `periods` registration periods.
+	fn duration(periods: u32) -> T::BlockNumber {
+		T::RegistrationPeriod::get().saturating_mul(periods.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use frame_support::{
+		assert_ok, assert_noop, impl_outer_origin, parameter_types, weights::Weight,
+	};
+	use sp_core::H256;
+	use sp_runtime::{
+		Perbill, testing::Header, traits::{BlakeTwo256, IdentityLookup},
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = ();
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u64 = 1;
+		pub const TransferFee: u64 = 0;
+		pub const CreationFee: u64 = 0;
+	}
+	impl pallet_balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnReapAccount = System;
+		type OnNewAccount = ();
+		type Event = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+	}
+	parameter_types! {
+		pub const CommitmentDeposit: u64 = 5;
+		pub const RegistrationFee: u64 = 10;
+		pub const RegistrationPeriod: u64 = 100;
+		pub const MaxPeriods: u32 = 3;
+		pub const GracePeriod: u64 = 20;
+		pub const MinCommitmentAge: u64 = 2;
+		pub const MaxCommitmentAge: u64 = 10;
+		pub const MinLength: usize = 3;
+		pub const MaxLength: usize = 16;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Currency = Balances;
+		type FeeDestination = ();
+		type CommitmentDeposit = CommitmentDeposit;
+		type RegistrationFee = RegistrationFee;
+		type RegistrationPeriod = RegistrationPeriod;
+		type MaxPeriods = MaxPeriods;
+		type GracePeriod = GracePeriod;
+		type MinCommitmentAge = MinCommitmentAge;
+		type MaxCommitmentAge = MaxCommitmentAge;
+		type MinLength = MinLength;
+		type MaxLength = MaxLength;
+	}
+	type System = frame_system::Module<Test>;
+	type Balances = pallet_balances::Module<Test>;
+	type NameService = Module<Test>;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(1, 100), (2, 100)],
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	/// Register `name` for `who` from the current block, for one period.
+	fn register(who: u64, name: &[u8]) {
+		let secret = H256::repeat_byte(who as u8);
+		assert_ok!(NameService::commit(Origin::signed(who), NameService::commitment_of(&who, name, &secret)));
+		System::set_block_number(System::block_number() + 2);
+		assert_ok!(NameService::reveal(Origin::signed(who), name.to_vec(), secret, 1));
+	}
+
+	#[test]
+	fn commit_reveal_works() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			let secret = H256::repeat_byte(7);
+			let commitment = NameService::commitment_of(&1, b"alice", &secret);
+			assert_ok!(NameService::commit(Origin::signed(1), commitment));
+			assert_eq!(Balances::reserved_balance(&1), 5);
+			assert_noop!(NameService::commit(Origin::signed(2), commitment), Error::<Test>::AlreadyCommitted);
+
+			assert_noop!(
+				NameService::reveal(Origin::signed(1), b"alice".to_vec(), secret, 1),
+				Error::<Test>::CommitmentTooNew,
+			);
+			System::set_block_number(3);
+			// another account cannot reveal the commitment.
+			assert_noop!(
+				NameService::reveal(Origin::signed(2), b"alice".to_vec(), secret, 1),
+				Error::<Test>::NoCommitment,
+			);
+			assert_noop!(
+				NameService::reveal(Origin::signed(1), b"alice".to_vec(), secret, 4),
+				Error::<Test>::InvalidPeriods,
+			);
+			assert_ok!(NameService::reveal(Origin::signed(1), b"alice".to_vec(), secret, 2));
+
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(Balances::free_balance(&1), 80);
+			assert_eq!(NameService::resolve(b"alice"), Some(1));
+			assert_eq!(NameService::expiry(b"alice"), Some(203));
+			assert!(NameService::commitment(&commitment).is_none());
+		});
+	}
+
+	#[test]
+	fn invalid_names_and_old_commitments_are_rejected() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			let secret = H256::repeat_byte(7);
+			for (name, error) in vec![
+				(&b"al"[..], Error::<Test>::TooShort),
+				(&b"a-very-long-name-indeed"[..], Error::<Test>::TooLong),
+				(&b"Alice"[..], Error::<Test>::InvalidCharacter),
+			] {
+				assert_noop!(NameService::reveal(Origin::signed(1), name.to_vec(), secret, 1), error);
+			}
+
+			let commitment = NameService::commitment_of(&1, b"alice", &secret);
+			assert_ok!(NameService::commit(Origin::signed(1), commitment));
+			System::set_block_number(12);
+			assert_noop!(
+				NameService::reveal(Origin::signed(1), b"alice".to_vec(), secret, 1),
+				Error::<Test>::CommitmentTooOld,
+			);
+			assert_noop!(NameService::cancel_commitment(Origin::signed(2), commitment), Error::<Test>::NoCommitment);
+			assert_ok!(NameService::cancel_commitment(Origin::signed(1), commitment));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+		});
+	}
+
+	#[test]
+	fn expiry_and_renewal_work() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			register(1, b"alice");
+			assert_eq!(NameService::expiry(b"alice"), Some(103));
+
+			// taken until the end of the grace period.
+			System::set_block_number(104);
+			assert_eq!(NameService::resolve(b"alice"), None);
+			let secret = H256::repeat_byte(9);
+			assert_ok!(NameService::commit(Origin::signed(2), NameService::commitment_of(&2, b"alice", &secret)));
+			System::set_block_number(106);
+			assert_noop!(
+				NameService::reveal(Origin::signed(2), b"alice".to_vec(), secret, 1),
+				Error::<Test>::Unavailable,
+			);
+
+			// anyone may renew during the grace period.
+			assert_ok!(NameService::renew(Origin::signed(2), b"alice".to_vec(), 1));
+			assert_eq!(NameService::expiry(b"alice"), Some(203));
+			assert_eq!(NameService::resolve(b"alice"), Some(1));
+
+			System::set_block_number(224);
+			assert_noop!(NameService::renew(Origin::signed(1), b"alice".to_vec(), 1), Error::<Test>::Expired);
+			register(2, b"alice");
+			assert_eq!(NameService::resolve(b"alice"), Some(2));
+		});
+	}
+
+	#[test]
+	fn targets_owners_and_reverse_records_work() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			register(1, b"alice");
+
+			assert_noop!(NameService::set_reverse(Origin::signed(2), b"alice".to_vec()), Error::<Test>::NotTarget);
+			assert_ok!(NameService::set_reverse(Origin::signed(1), b"alice".to_vec()));
+			assert_eq!(NameService::reverse(&1), Some(b"alice".to_vec()));
+
+			assert_noop!(NameService::set_target(Origin::signed(2), b"alice".to_vec(), 2), Error::<Test>::NotOwner);
+			assert_ok!(NameService::set_target(Origin::signed(1), b"alice".to_vec(), 2));
+			assert_eq!(NameService::resolve(b"alice"), Some(2));
+			// the reverse record no longer matches.
+			assert_eq!(NameService::reverse(&1), None);
+
+			assert_ok!(NameService::transfer(Origin::signed(1), b"alice".to_vec(), 2));
+			assert_noop!(NameService::set_target(Origin::signed(1), b"alice".to_vec(), 1), Error::<Test>::NotOwner);
+			assert_ok!(NameService::set_reverse(Origin::signed(2), b"alice".to_vec()));
+			assert_eq!(NameService::reverse(&2), Some(b"alice".to_vec()));
+			assert_ok!(NameService::clear_reverse(Origin::signed(2)));
+			assert_eq!(NameService::reverse(&2), None);
+		});
+	}
+}
@@ -0,0 +1,475 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Oracle Module
+//!
+//! - [`oracle::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Oracle module brings off-chain data, such as prices, on-chain. A set of feeder keys
+//! managed by governance feed values for arbitrary data keys, and other modules read the median
+//! of the values fed for a key through the [`DataProvider`](./trait.DataProvider.html) trait.
+//!
+//! Values are fed by the offchain workers of the nodes holding a feeder key in their keystore,
+//! every `FeedInterval` blocks. The values are read from a [`FeedSource`](./trait.FeedSource.html),
+//! by default the persistent offchain storage, and submitted as unsigned transactions carrying
+//! the signature of the feeder.
+//!
+//! Values older than `StalenessThreshold` blocks are ignored, and no value is provided for a
+//! key with fewer than `MinimumFeeds` fresh values.
+//!
+//! The feed of a round requires the feed of the same feeder in the previous round, while it is
+//! not included yet, so that the transaction pool includes the rounds of a feeder in order
+//! rather than making the earlier feed stale.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `add_feeder` - Allow a key to feed values.
+//! * `remove_feeder` - Disallow a key to feed values.
+//! * `feed_values` - Feed values, signed by a feeder key.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod mock;
+mod tests;
+
+use sp_application_crypto::RuntimeAppPublic;
+use codec::{Encode, Decode};
+use sp_core::offchain::StorageKind;
+use sp_std::prelude::*;
+use sp_std::convert::TryInto;
+use sp_runtime::{
+	RuntimeDebug,
+	traits::{CheckedSub, EnsureOrigin, Member, One, Saturating, Zero},
+	transaction_validity::{
+		TransactionValidity, ValidTransaction, InvalidTransaction, TransactionPriority,
+	},
+};
+use frame_support::{
+	decl_module, decl_event, decl_storage, ensure, decl_error, debug, Parameter,
+	traits::Get,
+	weights::{DispatchClass, FunctionOf, SimpleDispatchInfo, Weight},
+};
+use frame_system::{self as system, ensure_none, ensure_root};
+use frame_system::offchain::SubmitUnsignedTransaction;
+
+pub mod sr25519 {
+	mod app_sr25519 {
+		use sp_application_crypto::{app_crypto, key_types::ORACLE, sr25519};
+		app_crypto!(sr25519, ORACLE);
+	}
+
+	/// An oracle feeder keypair using sr25519 as its crypto.
+	#[cfg(feature = "std")]
+	pub type AuthorityPair = app_sr25519::Pair;
+
+	/// An oracle feeder signature using sr25519 as its crypto.
+	pub type AuthoritySignature = app_sr25519::Signature;
+
+	/// An oracle feeder identifier using sr25519 as its crypto.
+	pub type AuthorityId = app_sr25519::Public;
+}
+
+/// The key identifying the data a value is fed for, such as `b"DOT/USD"`.
+pub type OracleKey = Vec<u8>;
+
+/// The offchain storage key the default [`FeedSource`](./trait.FeedSource.html) reads the
+/// values to feed from, as an encoded `Vec<(OracleKey, Value)>`.
+pub const FEED_VALUES_KEY: &[u8] = b"oracle::feed_values";
+
+/// The data provided by the oracle, for other modules.
+pub trait DataProvider<Key, Value> {
+	/// The value for `key`, if enough fresh values were fed.
+	fn get(key: &Key) -> Option<Value>;
+}
+
+impl<Key, Value> DataProvider<Key, Value> for () {
+	fn get(_key: &Key) -> Option<Value> {
+		None
+	}
+}
+
+/// The source of the values fed by the offchain worker.
+pub trait FeedSource<Value> {
+	/// The values to feed.
+	fn values() -> Vec<(OracleKey, Value)>;
+}
+
+/// A feed source reading the values from the persistent offchain storage, under
+/// `FEED_VALUES_KEY`. The values are to be written by a process of the node.
+pub struct LocalStorageSource;
+impl<Value: Decode> FeedSource<Value> for LocalStorageSource {
+	fn values() -> Vec<(OracleKey, Value)> {
+		sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, FEED_VALUES_KEY)
+			.and_then(|values| Decode::decode(&mut &values[..]).ok())
+			.unwrap_or_default()
+	}
+}
+
+/// A value along with the block it was fed at.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct TimestampedValue<Value, BlockNumber> {
+	/// The value.
+	pub value: Value,
+	/// The block the value was fed at.
+	pub timestamp: BlockNumber,
+}
+
+/// The values fed by a feeder.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Feed<BlockNumber, Value> {
+	/// The block the values were read at.
+	pub block_number: BlockNumber,
+	/// The index of the feeder in the feeders.
+	pub feeder_index: u32,
+	/// The values, by key.
+	pub values: Vec<(OracleKey, Value)>,
+}
+
+pub trait Trait: frame_system::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The identifier type for a feeder.
+	type AuthorityId: Member + Parameter + RuntimeAppPublic + Default + Ord;
+
+	/// A dispatchable call type.
+	type Call: From<Call<Self>>;
+
+	/// A transaction submitter.
+	type SubmitTransaction: SubmitUnsignedTransaction<Self, <Self as Trait>::Call>;
+
+	/// The type of the values fed.
+	type Value: Member + Parameter + Ord + Copy;
+
+	/// The source of the values fed by the offchain worker.
+	type FeedSource: FeedSource<Self::Value>;
+
+	/// The origin which may add or remove feeders. Root can always do this.
+	type FeederOrigin: EnsureOrigin<Self::Origin>;
+
+	/// The maximum number of feeders.
+	type MaxFeeders: Get<u32>;
+
+	/// The maximum number of values in a feed.
+	type MaxFeedValues: Get<u32>;
+
+	/// The minimum number of fresh values for a key to have a value.
+	type MinimumFeeds: Get<u32>;
+
+	/// The number of blocks after which a fed value is stale.
+	type StalenessThreshold: Get<Self::BlockNumber>;
+
+	/// The number of blocks between two feeds of the offchain worker.
+	type FeedInterval: Get<Self::BlockNumber>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Oracle {
+		/// The keys allowed to feed values.
+		pub Feeders get(fn feeders): Vec<T::AuthorityId>;
+
+		/// The last value fed by each feeder for each key.
+		pub RawValues get(fn raw_value):
+			double_map OracleKey, hasher(twox_128) T::AuthorityId
+			=> Option<TimestampedValue<T::Value, T::BlockNumber>>;
+
+		/// The block of the last feed of each feeder.
+		LastFeed: map T::AuthorityId => T::BlockNumber;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		AuthorityId = <T as Trait>::AuthorityId,
+		Value = <T as Trait>::Value,
+	{
+		/// A feeder was added.
+		FeederAdded(AuthorityId),
+		/// A feeder was removed.
+		FeederRemoved(AuthorityId),
+		/// A feeder fed values.
+		NewFeedData(AuthorityId, Vec<(OracleKey, Value)>),
+	}
+);
+
+decl_error! {
+	/// Error for the oracle module.
+	pub enum Error for Module<T: Trait> {
+		/// The key already is a feeder.
+		AlreadyFeeder,
+		/// The key is not a feeder.
+		NotFeeder,
+		/// There are already `MaxFeeders` feeders.
+		TooManyFeeders,
+		/// The feeder already fed values at a later block.
+		StaleFeed,
+		/// The feed has more than `MaxFeedValues` values.
+		TooManyValues,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// The maximum number of values in a feed.
+		const MaxFeedValues: u32 = T::MaxFeedValues::get();
+
+		/// The minimum number of fresh values for a key to have a value.
+		const MinimumFeeds: u32 = T::MinimumFeeds::get();
+
+		/// The number of blocks after which a fed value is stale.
+		const StalenessThreshold: T::BlockNumber = T::StalenessThreshold::get();
+
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Allow `feeder` to feed values.
+		///
+		/// The dispatch origin for this call must be _Root_ or match `T::FeederOrigin`.
+		///
+		/// # <weight>
+		/// - O(F) where F is the number of feeders.
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(50_000)]
+		fn add_feeder(origin, feeder: T::AuthorityId) {
+			T::FeederOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			let mut feeders = Self::feeders();
+			ensure!(!feeders.contains(&feeder), Error::<T>::AlreadyFeeder);
+			ensure!((feeders.len() as u32) < T::MaxFeeders::get(), Error::<T>::TooManyFeeders);
+			feeders.push(feeder.clone());
+			<Feeders<T>>::put(feeders);
+			Self::deposit_event(RawEvent::FeederAdded(feeder));
+		}
+
+		/// Disallow `feeder` to feed values. The values it fed are no longer taken into account.
+		///
+		/// The dispatch origin for this call must be _Root_ or match `T::FeederOrigin`.
+		///
+		/// # <weight>
+		/// - O(F) where F is the number of feeders.
+		/// - Two storage writes.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(50_000)]
+		fn remove_feeder(origin, feeder: T::AuthorityId) {
+			T::FeederOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			let mut feeders = Self::feeders();
+			let index = feeders.iter().position(|f| f == &feeder).ok_or(Error::<T>::NotFeeder)?;
+			feeders.remove(index);
+			<Feeders<T>>::put(feeders);
+			<LastFeed<T>>::remove(&feeder);
+			Self::deposit_event(RawEvent::FeederRemoved(feeder));
+		}
+
+		/// Feed values, signed by the feeder at `feed.feeder_index`. Each value replaces the
+		/// previous value of the feeder for its key.
+		///
+		/// The dispatch origin for this call must be _None_; the signature is checked when the
+		/// transaction is validated.
+		///
+		/// # <weight>
+		/// - O(V + F) where V is the number of values and F the number of feeders, V being at
+		///   most `MaxFeedValues`.
+		/// - V + 1 storage writes.
+		/// # </weight>
+		#[weight = FunctionOf(
+			|args: (&Feed<T::BlockNumber, T::Value>, &<T::AuthorityId as RuntimeAppPublic>::Signature)| {
+				(args.0.values.len() as Weight).saturating_mul(10_000).saturating_add(100_000)
+			},
+			DispatchClass::Operational,
+			true,
+		)]
+		fn feed_values(
+			origin,
+			feed: Feed<T::BlockNumber, T::Value>,
+			// since signature verification is done in `validate_unsigned`
+			// we can skip doing it here again.
+			_signature: <T::AuthorityId as RuntimeAppPublic>::Signature
+		) {
+			ensure_none(origin)?;
+			ensure!(
+				feed.values.len() as u32 <= T::MaxFeedValues::get(),
+				Error::<T>::TooManyValues,
+			);
+
+			let feeder = Self::feeders()
+				.get(feed.feeder_index as usize)
+				.cloned()
+				.ok_or(Error::<T>::NotFeeder)?;
+			ensure!(<LastFeed<T>>::get(&feeder) < feed.block_number, Error::<T>::StaleFeed);
+
+			let timestamp = <frame_system::Module<T>>::block_number();
+			for (key, value) in &feed.values {
+				<RawValues<T>>::insert(key, &feeder, TimestampedValue { value: *value, timestamp });
+			}
+			<LastFeed<T>>::insert(&feeder, feed.block_number);
+			Self::deposit_event(RawEvent::NewFeedData(feeder, feed.values));
+		}
+
+		fn offchain_worker(now: T::BlockNumber) {
+			debug::RuntimeLogger::init();
+
+			let interval = T::FeedInterval::get();
+			if interval.is_zero() || (now % interval).is_zero() {
+				Self::offchain(now);
+			}
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The fresh values fed for `key` by the current feeders.
+	pub fn fresh_values(key: &OracleKey) -> Vec<TimestampedValue<T::Value, T::BlockNumber>> {
+		let now = <frame_system::Module<T>>::block_number();
+		let threshold = T::StalenessThreshold::get();
+		Self::feeders()
+			.iter()
+			.filter_map(|feeder| Self::raw_value(key, feeder))
+			.filter(|value| value.timestamp.saturating_add(threshold) >= now)
+			.collect()
+	}
+
+	/// The median of the fresh values fed for `key`, if there are at least `MinimumFeeds`.
+	pub fn median(key: &OracleKey) -> Option<T::Value> {
+		let mut values = Self::fresh_values(key)
+			.into_iter()
+			.map(|value| value.value)
+			.collect::<Vec<_>>();
+		if values.is_empty() || (values.len() as u32) < T::MinimumFeeds::get() {
+			return None
+		}
+		values.sort();
+		Some(values[(values.len() - 1) / 2])
+	}
+
+	/// The previous round of the feed of `feeder` at `block_number`, if `feeder` fed before but
+	/// its feed of that round is not included yet and still fresh.
+	fn pending_previous_round(feeder: &T::AuthorityId, block_number: T::BlockNumber) -> Option<T::BlockNumber> {
+		if !<LastFeed<T>>::exists(feeder) {
+			return None
+		}
+		let interval = T::FeedInterval::get().max(One::one());
+		let previous = block_number.checked_sub(&interval)?;
+		let now = <frame_system::Module<T>>::block_number();
+		if <LastFeed<T>>::get(feeder) >= previous
+			|| previous.saturating_add(T::StalenessThreshold::get()) < now
+		{
+			return None
+		}
+		Some(previous)
+	}
+
+	/// Feed the values of the feed source with each local feeder key.
+	pub(crate) fn offchain(now: T::BlockNumber) {
+		let values = T::FeedSource::values();
+		if values.is_empty() {
+			return
+		}
+		let values = values.into_iter()
+			.take(T::MaxFeedValues::get() as usize)
+			.collect::<Vec<_>>();
+
+		let mut local_keys = T::AuthorityId::all();
+		local_keys.sort();
+		for (feeder_index, key) in Self::feeders().into_iter()
+			.enumerate()
+			.filter_map(|(index, feeder)| {
+				local_keys.binary_search(&feeder)
+					.ok()
+					.map(|location| (index as u32, &local_keys[location]))
+			})
+		{
+			let feed = Feed { block_number: now, feeder_index, values: values.clone() };
+			let signature = match key.sign(&feed.encode()) {
+				Some(signature) => signature,
+				None => {
+					debug::native::warn!(target: "oracle", "Failed to sign the feed of {:?}", feeder_index);
+					continue
+				}
+			};
+			debug::info!(target: "oracle", "[index: {:?}] Feeding values at block: {:?}", feeder_index, now);
+			if T::SubmitTransaction::submit_unsigned(Call::feed_values(feed, signature)).is_err() {
+				debug::native::warn!(target: "oracle", "Failed to submit the feed of {:?}", feeder_index);
+			}
+		}
+	}
+}
+
+impl<T: Trait> DataProvider<OracleKey, T::Value> for Module<T> {
+	fn get(key: &OracleKey) -> Option<T::Value> {
+		Self::median(key)
+	}
+}
+
+#[allow(deprecated)]
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		if let Call::feed_values(feed, signature) = call {
+			let feeder = match Self::feeders().get(feed.feeder_index as usize) {
+				Some(feeder) => feeder.clone(),
+				None => return InvalidTransaction::BadProof.into(),
+			};
+			if feed.values.len() as u32 > T::MaxFeedValues::get() {
+				return InvalidTransaction::ExhaustsResources.into();
+			}
+
+			// reject feeds superseded by a later feed, or already stale.
+			let now = <frame_system::Module<T>>::block_number();
+			let threshold = T::StalenessThreshold::get();
+			if <LastFeed<T>>::get(&feeder) >= feed.block_number
+				|| feed.block_number.saturating_add(threshold) < now
+			{
+				return InvalidTransaction::Stale.into();
+			}
+
+			// check signature (this is expensive so we do it last).
+			let signature_valid = feed.using_encoded(|encoded_feed| {
+				feeder.verify(&encoded_feed, &signature)
+			});
+			if !signature_valid {
+				return InvalidTransaction::BadProof.into();
+			}
+
+			let validity = ValidTransaction::with_tag_prefix("Oracle")
+				.priority(TransactionPriority::max_value())
+				.and_provides((feed.block_number, &feeder))
+				.longevity(TryInto::<u64>::try_into(threshold).unwrap_or(64_u64))
+				.propagate(true);
+			match Self::pending_previous_round(&feeder, feed.block_number) {
+				Some(previous) => validity.and_requires((previous, feeder)).build(),
+				None => validity.build(),
+			}
+		} else {
+			InvalidTransaction::Call.into()
+		}
+	}
+}
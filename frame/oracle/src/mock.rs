@@ -0,0 +1,109 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Test utilities
+
+#![cfg(test)]
+
+use crate::{Module, Trait, LocalStorageSource};
+use sp_runtime::Perbill;
+use sp_runtime::testing::{Header, UintAuthorityId, TestXt};
+use sp_runtime::traits::{IdentityLookup, BlakeTwo256};
+use sp_core::H256;
+use frame_support::{
+	impl_outer_origin, impl_outer_dispatch, parameter_types, ord_parameter_types, weights::Weight,
+};
+use frame_system::EnsureSignedBy;
+
+use frame_system as system;
+impl_outer_origin!{
+	pub enum Origin for Runtime {}
+}
+
+impl_outer_dispatch! {
+	pub enum Call for Runtime where origin: Origin {
+		oracle::Oracle,
+	}
+}
+
+/// An extrinsic type used for tests.
+pub type Extrinsic = TestXt<Call, ()>;
+type SubmitTransaction = frame_system::offchain::TransactionSubmitter<(), Call, Extrinsic>;
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+	t.into()
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Runtime;
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: Weight = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+
+impl frame_system::Trait for Runtime {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Call = Call;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+	type ModuleToIndex = ();
+}
+
+ord_parameter_types! {
+	pub const One: u64 = 1;
+}
+
+parameter_types! {
+	pub const MaxFeeders: u32 = 4;
+	pub const MaxFeedValues: u32 = 2;
+	pub const MinimumFeeds: u32 = 2;
+	pub const StalenessThreshold: u64 = 10;
+	pub const FeedInterval: u64 = 5;
+}
+
+impl Trait for Runtime {
+	type Event = ();
+	type AuthorityId = UintAuthorityId;
+	type Call = Call;
+	type SubmitTransaction = SubmitTransaction;
+	type Value = u64;
+	type FeedSource = LocalStorageSource;
+	type FeederOrigin = EnsureSignedBy<One, u64>;
+	type MaxFeeders = MaxFeeders;
+	type MaxFeedValues = MaxFeedValues;
+	type MinimumFeeds = MinimumFeeds;
+	type StalenessThreshold = StalenessThreshold;
+	type FeedInterval = FeedInterval;
+}
+
+/// Oracle module.
+pub type Oracle = Module<Runtime>;
+pub type System = frame_system::Module<Runtime>;
@@ -0,0 +1,230 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tests for the oracle module.
+
+#![cfg(test)]
+
+use super::*;
+use crate::mock::*;
+use sp_core::offchain::{
+	OffchainExt,
+	TransactionPoolExt,
+	testing::{TestOffchainExt, TestTransactionPoolExt},
+};
+use frame_support::{assert_ok, assert_noop, unsigned::ValidateUnsigned, weights::GetDispatchInfo};
+use sp_runtime::{testing::UintAuthorityId, traits::BadOrigin};
+
+fn add_feeders(feeders: Vec<u64>) {
+	for feeder in feeders {
+		assert_ok!(Oracle::add_feeder(Origin::ROOT, UintAuthorityId(feeder)));
+	}
+}
+
+fn feed(feeder_index: u32, block_number: u64, values: Vec<(OracleKey, u64)>) -> Call {
+	let feed = Feed { block_number, feeder_index, values };
+	let signature = UintAuthorityId(0).sign(&feed.encode()).unwrap();
+	crate::Call::feed_values(feed, signature)
+}
+
+fn feed_values(feeder_index: u32, block_number: u64, values: Vec<(OracleKey, u64)>) {
+	let call = feed(feeder_index, block_number, values);
+	assert!(Oracle::validate_unsigned(&call).is_ok());
+	match call {
+		crate::Call::feed_values(feed, signature) => {
+			assert_ok!(Oracle::feed_values(Origin::NONE, feed, signature));
+		}
+		_ => unreachable!(),
+	}
+}
+
+#[test]
+fn feeders_are_managed_by_governance() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(Oracle::add_feeder(Origin::signed(2), UintAuthorityId(1)), BadOrigin);
+		assert_ok!(Oracle::add_feeder(Origin::signed(1), UintAuthorityId(1)));
+		assert_noop!(
+			Oracle::add_feeder(Origin::ROOT, UintAuthorityId(1)),
+			Error::<Runtime>::AlreadyFeeder,
+		);
+		add_feeders(vec![2, 3, 4]);
+		assert_noop!(
+			Oracle::add_feeder(Origin::ROOT, UintAuthorityId(5)),
+			Error::<Runtime>::TooManyFeeders,
+		);
+
+		assert_ok!(Oracle::remove_feeder(Origin::signed(1), UintAuthorityId(2)));
+		assert_noop!(
+			Oracle::remove_feeder(Origin::ROOT, UintAuthorityId(2)),
+			Error::<Runtime>::NotFeeder,
+		);
+		assert_eq!(Oracle::feeders(), vec![UintAuthorityId(1), UintAuthorityId(3), UintAuthorityId(4)]);
+	});
+}
+
+#[test]
+fn values_are_aggregated_by_median() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		add_feeders(vec![1, 2, 3]);
+		let key = b"DOT/USD".to_vec();
+
+		feed_values(0, 1, vec![(key.clone(), 100)]);
+		// not enough values yet.
+		assert_eq!(<Oracle as DataProvider<_, _>>::get(&key), None);
+
+		feed_values(1, 1, vec![(key.clone(), 300)]);
+		assert_eq!(<Oracle as DataProvider<_, _>>::get(&key), Some(100));
+		feed_values(2, 1, vec![(key.clone(), 120)]);
+		assert_eq!(<Oracle as DataProvider<_, _>>::get(&key), Some(120));
+
+		// values of removed feeders are ignored.
+		assert_ok!(Oracle::remove_feeder(Origin::ROOT, UintAuthorityId(3)));
+		assert_eq!(Oracle::median(&key), Some(100));
+	});
+}
+
+#[test]
+fn stale_values_are_ignored() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		add_feeders(vec![1, 2]);
+		let key = b"DOT/USD".to_vec();
+		feed_values(0, 1, vec![(key.clone(), 100)]);
+		System::set_block_number(5);
+		feed_values(1, 5, vec![(key.clone(), 200)]);
+		assert_eq!(Oracle::median(&key), Some(100));
+
+		System::set_block_number(12);
+		assert_eq!(Oracle::fresh_values(&key).len(), 1);
+		assert_eq!(Oracle::median(&key), None);
+	});
+}
+
+#[test]
+fn invalid_feeds_are_rejected() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(20);
+		add_feeders(vec![1]);
+		let key = b"DOT/USD".to_vec();
+
+		// unknown feeder.
+		assert!(Oracle::validate_unsigned(&feed(1, 20, vec![(key.clone(), 1)])).is_err());
+		// too many values.
+		let values = vec![(b"A".to_vec(), 1), (b"B".to_vec(), 1), (b"C".to_vec(), 1)];
+		assert!(Oracle::validate_unsigned(&feed(0, 20, values)).is_err());
+		// stale feed.
+		assert!(Oracle::validate_unsigned(&feed(0, 9, vec![(key.clone(), 1)])).is_err());
+		// bad signature.
+		let call = crate::Call::feed_values(Feed { block_number: 20, feeder_index: 0, values: vec![] }, 0);
+		assert!(Oracle::validate_unsigned(&call).is_err());
+
+		feed_values(0, 20, vec![(key.clone(), 1)]);
+		// superseded feed.
+		assert!(Oracle::validate_unsigned(&feed(0, 20, vec![(key.clone(), 2)])).is_err());
+		assert_noop!(
+			Oracle::feed_values(Origin::NONE, Feed { block_number: 19, feeder_index: 0, values: vec![] }, 0),
+			Error::<Runtime>::StaleFeed,
+		);
+	});
+}
+
+#[test]
+fn feeds_are_weighed_and_bounded_by_their_values() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(20);
+		add_feeders(vec![1]);
+		let values = |count: u8| (0..count).map(|key| (vec![key], 1)).collect::<Vec<_>>();
+
+		let weight = |count| feed(0, 20, values(count)).get_dispatch_info().weight;
+		assert!(weight(1) > weight(0));
+		assert!(weight(2) > weight(1));
+
+		// too many values, even if it wasn't validated.
+		assert_noop!(
+			Oracle::feed_values(Origin::NONE, Feed { block_number: 20, feeder_index: 0, values: values(3) }, 0),
+			Error::<Runtime>::TooManyValues,
+		);
+	});
+}
+
+#[test]
+fn feeds_require_the_previous_round_until_it_is_included() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(5);
+		add_feeders(vec![1]);
+		let key = b"DOT/USD".to_vec();
+		let tag = |round: u64| ("Oracle", (round, UintAuthorityId(1))).encode();
+
+		// the first feed of a feeder doesn't wait for any other.
+		let first = Oracle::validate_unsigned(&feed(0, 5, vec![(key.clone(), 1)])).unwrap();
+		assert_eq!(first.provides, vec![tag(5)]);
+		assert!(first.requires.is_empty());
+		feed_values(0, 5, vec![(key.clone(), 1)]);
+
+		// the next round follows an included round.
+		System::set_block_number(10);
+		let second = Oracle::validate_unsigned(&feed(0, 10, vec![(key.clone(), 2)])).unwrap();
+		assert_eq!(second.provides, vec![tag(10)]);
+		assert!(second.requires.is_empty());
+
+		// the round after waits for the previous round, not included yet.
+		System::set_block_number(15);
+		let third = Oracle::validate_unsigned(&feed(0, 15, vec![(key.clone(), 3)])).unwrap();
+		assert_eq!(third.provides, vec![tag(15)]);
+		assert_eq!(third.requires, vec![tag(10)]);
+
+		// until the previous round is stale.
+		System::set_block_number(21);
+		let third = Oracle::validate_unsigned(&feed(0, 15, vec![(key.clone(), 3)])).unwrap();
+		assert!(third.requires.is_empty());
+	});
+}
+
+#[test]
+fn offchain_worker_feeds_values_with_local_keys() {
+	let mut ext = new_test_ext();
+	let (offchain, _state) = TestOffchainExt::new();
+	let (pool, state) = TestTransactionPoolExt::new();
+	ext.register_extension(OffchainExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+
+	ext.execute_with(|| {
+		System::set_block_number(5);
+		add_feeders(vec![1, 2, 3]);
+		UintAuthorityId::set_all_keys(vec![2, 3, 7]);
+
+		// nothing to feed.
+		Oracle::offchain(5);
+		assert!(state.read().transactions.is_empty());
+
+		let values: Vec<(OracleKey, u64)> = vec![(b"DOT/USD".to_vec(), 42)];
+		sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, FEED_VALUES_KEY, &values.encode());
+		Oracle::offchain(5);
+
+		let transactions = state.read().transactions.clone();
+		assert_eq!(transactions.len(), 2);
+		for (transaction, feeder_index) in transactions.iter().zip(vec![1, 2]) {
+			let ex: Extrinsic = Decode::decode(&mut &transaction[..]).unwrap();
+			let call = match ex.1 {
+				crate::mock::Call::Oracle(call) => call,
+				e => panic!("Unexpected call: {:?}", e),
+			};
+			assert!(Oracle::validate_unsigned(&call).is_ok());
+			assert_eq!(call, feed(feeder_index, 5, values.clone()));
+		}
+	});
+}
@@ -123,10 +123,13 @@ use sp_std::{prelude::*, marker::PhantomData, ops::{Sub, Rem}};
 use codec::Decode;
 use sp_runtime::{KeyTypeId, Perbill, RuntimeAppPublic, BoundToRuntimeAppPublic};
 use frame_support::weights::SimpleDispatchInfo;
-use sp_runtime::traits::{Convert, Zero, Member, OpaqueKeys};
+use sp_runtime::traits::{Convert, Zero, Member, OpaqueKeys, Saturating};
 use sp_staking::SessionIndex;
 use frame_support::{dispatch, ConsensusEngineId, decl_module, decl_event, decl_storage, decl_error};
-use frame_support::{ensure, traits::{OnFreeBalanceZero, Get, FindAuthor, ValidatorRegistration}, Parameter};
+use frame_support::{
+	ensure, Parameter,
+	traits::{OnFreeBalanceZero, Get, FindAuthor, ValidatorRegistration, EstimateNextNewSession},
+};
 use frame_system::{self as system, ensure_signed};
 
 #[cfg(test)]
@@ -162,6 +165,23 @@ impl<
 	}
 }
 
+impl<
+	BlockNumber: Rem<Output=BlockNumber> + Sub<Output=BlockNumber> + Zero + PartialOrd + Saturating + Clone,
+	Period: Get<BlockNumber>,
+	Offset: Get<BlockNumber>,
+> EstimateNextNewSession<BlockNumber> for PeriodicSessions<Period, Offset> {
+	fn estimate_next_new_session(now: BlockNumber) -> Option<BlockNumber> {
+		let offset = Offset::get();
+		let period = Period::get();
+		if now < offset {
+			return Some(offset)
+		}
+		// A session starting at `now` has already started.
+		let since_last = (now.clone() - offset) % period.clone();
+		Some(now.saturating_add(period.saturating_sub(since_last)))
+	}
+}
+
 /// An event handler for when the session is ending.
 /// TODO [slashing] consider renaming to OnSessionStarting
 pub trait OnSessionEnding<ValidatorId> {
@@ -492,7 +512,9 @@ decl_module! {
 		/// Allows an account to set its session key prior to becoming a validator.
 		/// This doesn't take effect until the next session.
 		///
-		/// The dispatch origin of this function must be signed.
+		/// The dispatch origin of this function must be signed, by an account that
+		/// `T::ValidatorIdOf` converts to a validator id. With staking this is the controller,
+		/// which is the stash itself once the stash is its own controller.
 		///
 		/// # <weight>
 		/// - O(log n) in number of accounts.
@@ -998,6 +1020,12 @@ mod tests {
 		}
 
 		assert!(P::should_end_session(13));
+
+		assert_eq!(P::estimate_next_new_session(0), Some(3));
+		assert_eq!(P::estimate_next_new_session(3), Some(13));
+		assert_eq!(P::estimate_next_new_session(4), Some(13));
+		assert_eq!(P::estimate_next_new_session(12), Some(13));
+		assert_eq!(P::estimate_next_new_session(13), Some(23));
 	}
 
 	#[test]
@@ -155,6 +155,7 @@ parameter_types! {
 	pub const MaximumBlockWeight: Weight = 1024;
 	pub const MaximumBlockLength: u32 = 2 * 1024;
 	pub const MinimumPeriod: u64 = 5;
+	pub const MaxDrift: u64 = 30 * 1000;
 	pub const AvailableBlockRatio: Perbill = Perbill::one();
 }
 
@@ -181,6 +182,7 @@ impl pallet_timestamp::Trait for Test {
 	type Moment = u64;
 	type OnTimestampSet = ();
 	type MinimumPeriod = MinimumPeriod;
+	type MaxDrift = MaxDrift;
 }
 
 parameter_types! {
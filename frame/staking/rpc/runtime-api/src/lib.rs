@@ -0,0 +1,89 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime API definition for the slashing queries of the staking module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Codec, Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use sp_runtime::RuntimeDebug;
+
+/// Counter for the number of eras that have passed.
+pub type EraIndex = u32;
+
+/// A slash computed for an offence but not applied yet.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PendingSlash<AccountId, Balance> {
+	/// The era the offence was reported in.
+	pub era: EraIndex,
+	/// The index of the slash among those of `era`. Together with `era`, it identifies the slash
+	/// to cancel.
+	pub index: u32,
+	/// The era at the start of which the slash is applied, unless cancelled.
+	pub apply_at: EraIndex,
+	/// The stash of the offending validator.
+	pub validator: AccountId,
+	/// The validator's own slash.
+	pub own: Balance,
+	/// All other slashed stakers and amounts.
+	pub others: Vec<(AccountId, Balance)>,
+	/// Reporters of the offence.
+	pub reporters: Vec<AccountId>,
+	/// The amount paid out to the reporters.
+	pub payout: Balance,
+}
+
+/// A slashing span of a stash.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct SlashingSpanInfo<Balance> {
+	/// The index of the span.
+	pub index: u32,
+	/// The first era of the span.
+	pub start: EraIndex,
+	/// The number of eras in the span, `None` for the ongoing span.
+	pub length: Option<EraIndex>,
+	/// The amount of the stash slashed in the span.
+	pub slashed: Balance,
+	/// The amount paid out to reporters for slashes in the span.
+	pub paid_out: Balance,
+}
+
+sp_api::decl_runtime_apis! {
+	/// Slashing records of the staking module.
+	pub trait StakingSlashingApi<AccountId, Balance> where
+		AccountId: Codec,
+		Balance: Codec,
+	{
+		/// All the pending slashes, oldest first.
+		fn pending_slashes() -> Vec<PendingSlash<AccountId, Balance>>;
+
+		/// The pending slashes affecting `who`, as a validator or a nominator, oldest first.
+		fn pending_slashes_of(who: AccountId) -> Vec<PendingSlash<AccountId, Balance>>;
+
+		/// The total amount of the pending slashes of `who`.
+		fn unapplied_slash_of(who: AccountId) -> Balance;
+
+		/// The slashing spans of `stash`, most recent first.
+		fn slashing_spans(stash: AccountId) -> Vec<SlashingSpanInfo<Balance>>;
+	}
+}
@@ -0,0 +1,155 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface for the slashing queries of the staking module.
+
+use std::sync::Arc;
+use codec::Codec;
+use sp_blockchain::HeaderBackend;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+pub use pallet_staking_rpc_runtime_api::{
+	PendingSlash, SlashingSpanInfo, StakingSlashingApi as StakingSlashingRuntimeApi,
+};
+pub use self::gen_client::Client as StakingSlashingClient;
+
+#[rpc]
+pub trait StakingSlashingApi<BlockHash, AccountId, Balance> {
+	/// All the pending slashes at the given block, or the best block, oldest first.
+	#[rpc(name = "staking_pendingSlashes")]
+	fn pending_slashes(&self, at: Option<BlockHash>) -> Result<Vec<PendingSlash<AccountId, Balance>>>;
+
+	/// The pending slashes affecting `who`, as a validator or a nominator, oldest first.
+	#[rpc(name = "staking_pendingSlashesOf")]
+	fn pending_slashes_of(
+		&self,
+		who: AccountId,
+		at: Option<BlockHash>,
+	) -> Result<Vec<PendingSlash<AccountId, Balance>>>;
+
+	/// The total amount of the pending slashes of `who`.
+	#[rpc(name = "staking_unappliedSlashOf")]
+	fn unapplied_slash_of(&self, who: AccountId, at: Option<BlockHash>) -> Result<Balance>;
+
+	/// The slashing spans of `stash`, most recent first.
+	#[rpc(name = "staking_slashingSpans")]
+	fn slashing_spans(&self, stash: AccountId, at: Option<BlockHash>) -> Result<Vec<SlashingSpanInfo<Balance>>>;
+}
+
+/// A struct that implements the [`StakingSlashingApi`].
+pub struct StakingSlashing<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> StakingSlashing<C, B> {
+	/// Create new `StakingSlashing` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		StakingSlashing { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// The runtime at the given block does not implement the api.
+	UnsupportedApi,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::UnsupportedApi => 2,
+		}
+	}
+}
+
+fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query slashes.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, AccountId, Balance> StakingSlashing<C, (Block, AccountId, Balance)> where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: StakingSlashingRuntimeApi<Block, AccountId, Balance>,
+	AccountId: Codec,
+	Balance: Codec,
+{
+	/// The block to query at, checking that its runtime implements the api.
+	fn at(&self, at: Option<<Block as BlockT>::Hash>) -> Result<BlockId<Block>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		let has_api = self.client.runtime_api().has_api::<
+			dyn StakingSlashingRuntimeApi<Block, AccountId, Balance, Error = ()>
+		>(&at).map_err(runtime_error)?;
+		if !has_api {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::UnsupportedApi.into()),
+				message: "Staking slashing api is not available at the given block.".into(),
+				data: None,
+			})
+		}
+		Ok(at)
+	}
+}
+
+impl<C, Block, AccountId, Balance> StakingSlashingApi<<Block as BlockT>::Hash, AccountId, Balance>
+	for StakingSlashing<C, (Block, AccountId, Balance)>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: StakingSlashingRuntimeApi<Block, AccountId, Balance>,
+	AccountId: Codec + Send + Sync + 'static,
+	Balance: Codec + Send + Sync + 'static,
+{
+	fn pending_slashes(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<PendingSlash<AccountId, Balance>>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().pending_slashes(&at).map_err(runtime_error)
+	}
+
+	fn pending_slashes_of(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<PendingSlash<AccountId, Balance>>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().pending_slashes_of(&at, who).map_err(runtime_error)
+	}
+
+	fn unapplied_slash_of(&self, who: AccountId, at: Option<<Block as BlockT>::Hash>) -> Result<Balance> {
+		let at = self.at(at)?;
+		self.client.runtime_api().unapplied_slash_of(&at, who).map_err(runtime_error)
+	}
+
+	fn slashing_spans(
+		&self,
+		stash: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<SlashingSpanInfo<Balance>>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().slashing_spans(&at, stash).map_err(runtime_error)
+	}
+}
@@ -18,8 +18,95 @@
 //! the total payout for the era given the era duration and the staking rate in NPoS.
 //! The staking rate in NPoS is the total amount of tokens staked by nominators and validators,
 //! divided by the total token supply.
+//!
+//! The curve is either built at compile time with the `pallet-staking-reward-curve` macro, or
+//! approximated on chain from [`InflationParams`](./struct.InflationParams.html) set by
+//! governance.
 
-use sp_runtime::{Perbill, traits::SimpleArithmetic, curve::PiecewiseLinear};
+use sp_std::prelude::*;
+use codec::{Encode, Decode};
+use sp_runtime::{Perbill, RuntimeDebug, traits::{SimpleArithmetic, Zero}, curve::PiecewiseLinear};
+#[cfg(feature = "std")]
+use sp_runtime::{Serialize, Deserialize};
+
+/// Number of segments approximating the curve past the ideal stake.
+const FALLOFF_PIECE_COUNT: u64 = 40;
+
+const BILLION: u64 = 1_000_000_000;
+
+/// The parameters of the NPoS inflation curve, as taken by the `pallet-staking-reward-curve`
+/// macro.
+///
+/// The yearly inflation grows linearly from `min_inflation` when nothing is staked to
+/// `max_inflation` at `ideal_stake`. Past it, the inflation above `min_inflation` halves every
+/// `falloff` of additional stake.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct InflationParams {
+	/// The yearly inflation when nothing is staked.
+	pub min_inflation: Perbill,
+	/// The yearly inflation at the ideal stake.
+	pub max_inflation: Perbill,
+	/// The ideal share of the total issuance staked.
+	pub ideal_stake: Perbill,
+	/// The share of the total issuance past the ideal stake over which the inflation above
+	/// `min_inflation` halves.
+	pub falloff: Perbill,
+}
+
+impl InflationParams {
+	/// Whether the parameters describe a curve.
+	pub fn is_valid(&self) -> bool {
+		self.min_inflation <= self.max_inflation
+			&& !self.ideal_stake.is_zero()
+			&& self.ideal_stake < Perbill::one()
+			&& !self.falloff.is_zero()
+	}
+
+	/// Approximate the curve with linear segments, as taken by `PiecewiseLinear`. The parameters
+	/// must be valid.
+	pub fn points(&self) -> Vec<(Perbill, Perbill)> {
+		let min = self.min_inflation.deconstruct() as u64;
+		let max = self.max_inflation.deconstruct() as u64;
+		let ideal = self.ideal_stake.deconstruct() as u64;
+		let falloff = self.falloff.deconstruct() as u64;
+
+		let mut points = vec![
+			(Perbill::zero(), self.min_inflation),
+			(self.ideal_stake, self.max_inflation),
+		];
+		for piece in 1..=FALLOFF_PIECE_COUNT {
+			let x = ideal + (BILLION - ideal) * piece / FALLOFF_PIECE_COUNT;
+			let halvings = (x - ideal) * BILLION / falloff;
+			let y = min + ((max - min) as u128 * pow2_neg(halvings) as u128 / BILLION as u128) as u64;
+			points.push((Perbill::from_parts(x as u32), Perbill::from_parts(y as u32)));
+		}
+		points
+	}
+}
+
+/// `2^(-t)` in billionths, for `t` in billionths.
+fn pow2_neg(t: u64) -> u64 {
+	const LN_2: i128 = 693_147_181;
+
+	let (whole, fraction) = (t / BILLION, t % BILLION);
+	if whole >= 32 {
+		return 0
+	}
+
+	// `e^(-z)` with `z = fraction * ln(2) < 1`, by its Taylor series.
+	let z = fraction as i128 * LN_2 / BILLION as i128;
+	let mut term = BILLION as i128;
+	let mut result = term;
+	for k in 1.. {
+		term = -term * z / (k * BILLION as i128);
+		if term == 0 {
+			break
+		}
+		result += term;
+	}
+	(result as u64) >> whole
+}
 
 /// The total payout to all validators (and their nominators) per era.
 ///
@@ -28,7 +115,7 @@ use sp_runtime::{Perbill, traits::SimpleArithmetic, curve::PiecewiseLinear};
 ///
 /// `era_duration` is expressed in millisecond.
 pub fn compute_total_payout<N>(
-	yearly_inflation: &PiecewiseLinear<'static>,
+	yearly_inflation: &PiecewiseLinear<'_>,
 	npos_token_staked: N,
 	total_tokens: N,
 	era_duration: u64
@@ -100,4 +187,34 @@ mod test {
 			57_038_500_000_000_000_000_000
 		);
 	}
+
+	#[test]
+	fn governed_curve_approximates_built_curve() {
+		use sp_runtime::Perbill;
+		use super::InflationParams;
+
+		const YEAR: u64 = 365 * 24 * 60 * 60 * 1000;
+
+		let params = InflationParams {
+			min_inflation: Perbill::from_parts(25_000_000),
+			max_inflation: Perbill::from_parts(100_000_000),
+			ideal_stake: Perbill::from_parts(500_000_000),
+			falloff: Perbill::from_parts(50_000_000),
+		};
+		assert!(params.is_valid());
+		let points = params.points();
+		let governed = PiecewiseLinear { points: &points, maximum: params.max_inflation };
+
+		for staked in (0..=100_000u64).step_by(2_500) {
+			let built = super::compute_total_payout(&I_NPOS, staked, 100_000u64, YEAR);
+			let approximated = super::compute_total_payout(&governed, staked, 100_000u64, YEAR);
+			assert_eq!(built.1, approximated.1);
+			let error = built.0.max(approximated.0) - built.0.min(approximated.0);
+			assert!(error * 200 <= built.0, "{} staked: {:?} != {:?}", staked, built, approximated);
+		}
+
+		assert!(!InflationParams { falloff: Perbill::zero(), ..params }.is_valid());
+		assert!(!InflationParams { ideal_stake: Perbill::one(), ..params }.is_valid());
+		assert!(!InflationParams { min_inflation: Perbill::from_percent(20), ..params }.is_valid());
+	}
 }
@@ -0,0 +1,181 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Randomized tests checking that global invariants of the staking and balances modules hold
+//! across arbitrary sequences of calls.
+//!
+//! Each run starts from the mock genesis, dispatches random calls from a fixed set of accounts and
+//! checks after every block that:
+//!
+//! - the total issuance matches the sum of all balances,
+//! - each stash has a staking lock matching its bonded amount,
+//! - no stash has more bonded than its free balance,
+//! - each ledger's total matches its active and unlocking funds.
+
+use super::*;
+use mock::*;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use sp_runtime::traits::OnInitialize;
+
+/// Accounts funded at genesis by the mock, plus some that start empty.
+const ACCOUNTS: [AccountId; 20] = [
+	1, 2, 3, 4, 10, 11, 20, 21, 30, 31, 40, 41, 100, 101, 999, 5, 6, 7, 8, 9,
+];
+
+/// Number of independent runs, each using its own seed.
+const RUNS: u64 = 32;
+
+/// Number of blocks per run.
+const BLOCKS: u64 = 60;
+
+/// Maximum number of calls dispatched in a block.
+const CALLS_PER_BLOCK: usize = 4;
+
+/// A call dispatched during a run.
+#[derive(Debug)]
+enum Action {
+	Transfer { from: AccountId, to: AccountId, value: Balance },
+	Bond { stash: AccountId, controller: AccountId, value: Balance },
+	BondExtra { stash: AccountId, value: Balance },
+	Unbond { controller: AccountId, value: Balance },
+	Rebond { controller: AccountId, value: Balance },
+	WithdrawUnbonded { controller: AccountId },
+	Validate { controller: AccountId },
+	Nominate { controller: AccountId, targets: Vec<AccountId> },
+	Chill { controller: AccountId },
+	SetController { stash: AccountId, controller: AccountId },
+	ForceUnstake { stash: AccountId },
+}
+
+impl Action {
+	fn random(rng: &mut StdRng) -> Self {
+		let account = |rng: &mut StdRng| *ACCOUNTS.choose(rng).expect("ACCOUNTS is not empty; qed");
+		let (a, b, c) = (account(rng), account(rng), account(rng));
+		let value = match rng.gen_range(0, 4) {
+			0 => 0,
+			1 => rng.gen_range(0, 10),
+			2 => rng.gen_range(0, 10_000),
+			_ => rng.gen_range(0, 1_000_000),
+		};
+
+		match rng.gen_range(0, 11) {
+			0 => Action::Transfer { from: a, to: b, value },
+			1 => Action::Bond { stash: a, controller: b, value },
+			2 => Action::BondExtra { stash: a, value },
+			3 => Action::Unbond { controller: a, value },
+			4 => Action::Rebond { controller: a, value },
+			5 => Action::WithdrawUnbonded { controller: a },
+			6 => Action::Validate { controller: a },
+			7 => Action::Nominate { controller: a, targets: vec![b, c] },
+			8 => Action::Chill { controller: a },
+			9 => Action::SetController { stash: a, controller: b },
+			_ => Action::ForceUnstake { stash: a },
+		}
+	}
+
+	/// Dispatch the call. Failing calls are expected and their result is ignored: invariants
+	/// must hold whatever the outcome.
+	fn dispatch(self) {
+		let _ = match self {
+			Action::Transfer { from, to, value } =>
+				Balances::transfer(Origin::signed(from), to, value),
+			Action::Bond { stash, controller, value } =>
+				Staking::bond(Origin::signed(stash), controller, value, RewardDestination::Stash),
+			Action::BondExtra { stash, value } =>
+				Staking::bond_extra(Origin::signed(stash), value),
+			Action::Unbond { controller, value } =>
+				Staking::unbond(Origin::signed(controller), value),
+			Action::Rebond { controller, value } =>
+				Staking::rebond(Origin::signed(controller), value),
+			Action::WithdrawUnbonded { controller } =>
+				Staking::withdraw_unbonded(Origin::signed(controller)),
+			Action::Validate { controller } =>
+				Staking::validate(Origin::signed(controller), ValidatorPrefs::default()),
+			Action::Nominate { controller, targets } =>
+				Staking::nominate(Origin::signed(controller), targets),
+			Action::Chill { controller } =>
+				Staking::chill(Origin::signed(controller)),
+			Action::SetController { stash, controller } =>
+				Staking::set_controller(Origin::signed(stash), controller),
+			Action::ForceUnstake { stash } =>
+				Staking::force_unstake(Origin::ROOT, stash),
+		};
+	}
+}
+
+fn check_invariants(seed: u64, block: u64, history: &[String]) {
+	let context = || format!("in run {} at block {} after {:?}", seed, block, history);
+
+	let total: Balance = ACCOUNTS.iter().map(|who| Balances::total_balance(who)).sum();
+	assert_eq!(Balances::total_issuance(), total, "total issuance mismatch {}", context());
+
+	for who in ACCOUNTS.iter() {
+		let staking_lock = Balances::locks(who).into_iter().find(|lock| lock.id == STAKING_ID);
+
+		match Staking::bonded(who).and_then(|controller| Staking::ledger(controller)) {
+			Some(ledger) => {
+				assert_eq!(ledger.stash, *who, "ledger of wrong stash {}", context());
+				assert_eq!(
+					ledger.total,
+					ledger.unlocking.iter().fold(ledger.active, |total, chunk| total + chunk.value),
+					"inconsistent ledger of {} {}", who, context(),
+				);
+				assert!(
+					ledger.total <= Balances::free_balance(who),
+					"bonded more than free for {} {}", who, context(),
+				);
+				// Balances does not store zero locks.
+				assert_eq!(
+					staking_lock.map_or(0, |lock| lock.amount),
+					ledger.total,
+					"lock does not match bonded amount of {} {}", who, context(),
+				);
+			}
+			None => assert!(staking_lock.is_none(), "lock without a ledger for {} {}", who, context()),
+		}
+	}
+}
+
+fn run(seed: u64) {
+	let mut rng = StdRng::seed_from_u64(seed);
+	// Half of the runs use an existential deposit, so that accounts can be reaped.
+	let existential_deposit = if seed % 2 == 0 { 0 } else { 10 };
+	ExtBuilder::default().existential_deposit(existential_deposit).build().execute_with(|| {
+		let mut history = Vec::new();
+		check_invariants(seed, 0, &history);
+
+		for block in 1..=BLOCKS {
+			System::set_block_number(block);
+			Timestamp::set_timestamp(block * 1000);
+			Session::on_initialize(block);
+
+			for _ in 0..rng.gen_range(0, CALLS_PER_BLOCK + 1) {
+				let action = Action::random(&mut rng);
+				history.push(format!("{:?}", action));
+				action.dispatch();
+			}
+
+			check_invariants(seed, block, &history);
+		}
+	});
+}
+
+#[test]
+fn staking_and_balances_invariants_hold() {
+	for seed in 0..RUNS {
+		run(seed);
+	}
+}
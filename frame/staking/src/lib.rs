@@ -69,6 +69,12 @@
 //! Stash accounts can change their associated controller using the
 //! [`set_controller`](./enum.Call.html#variant.set_controller) call.
 //!
+//! Separate controller accounts are deprecated. A stash can bond with itself as controller, and an
+//! existing pair is migrated to a single key with the
+//! [`set_stash_as_controller`](./enum.Call.html#variant.set_stash_as_controller) call, or in bulk
+//! by root with
+//! [`force_set_stashes_as_controllers`](./enum.Call.html#variant.force_set_stashes_as_controllers).
+//!
 //! There are three possible roles that any staked account pair can be in: `Validator`, `Nominator`
 //! and `Idle` (defined in [`StakerStatus`](./enum.StakerStatus.html)). There are three
 //! corresponding instructions to change between roles, namely:
@@ -205,6 +211,21 @@
 //! - Stash account, not increasing the staked value.
 //! - Stash account, also increasing the staked value.
 //!
+//! Paying out the rewards of many validators and nominators is heavy. Unless
+//! [`EraValidatorsPerBlock`](./trait.Trait.html#associatedtype.EraValidatorsPerBlock) is 0, only the
+//! rewards are computed at the end of the era, and they are paid out in the `on_finalize` of the
+//! following blocks, a few validators per block. The exposures of the new era are recorded the
+//! same way once all the rewards are paid out.
+//!
+//! The election is heavy too. Unless
+//! [`ElectionVotersPerBlock`](./trait.Trait.html#associatedtype.ElectionVotersPerBlock) is 0, it
+//! is run in the [`ElectionLookahead`](./trait.Trait.html#associatedtype.ElectionLookahead) blocks
+//! before the end of the era: its candidates and voters are read into a snapshot, a few voters per
+//! block, then its rounds are run, a few per block. What is left is done when the era changes.
+//! Bonding extra funds, unbonding, rebonding, withdrawing, validating, nominating and chilling are
+//! not allowed in these blocks. The voters removed meanwhile, e.g. chilled for an offence, are
+//! left out of the election.
+//!
 //! ### Additional Fund Management Operations
 //!
 //! Any funds already placed into stash can be the target of the following operations:
@@ -250,19 +271,24 @@
 mod mock;
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+mod invariants;
 mod migration;
 mod slashing;
 
 pub mod inflation;
 
-use sp_std::{prelude::*, result};
+pub use inflation::InflationParams;
+pub use pallet_staking_rpc_runtime_api::{EraIndex, PendingSlash, SlashingSpanInfo};
+
+use sp_std::{prelude::*, result, collections::{btree_map::BTreeMap, btree_set::BTreeSet}};
 use codec::{HasCompact, Encode, Decode};
 use frame_support::{
-	decl_module, decl_event, decl_storage, ensure, decl_error,
-	weights::SimpleDispatchInfo,
+	decl_module, decl_event, decl_storage, ensure, decl_error, dispatch::DispatchResult,
+	weights::{SimpleDispatchInfo, Weight, WeighData, OnIdle},
 	traits::{
 		Currency, OnFreeBalanceZero, LockIdentifier, LockableCurrency,
-		WithdrawReasons, OnUnbalanced, Imbalance, Get, Time
+		WithdrawReasons, OnUnbalanced, Imbalance, Get, Time, EstimateNextNewSession,
 	}
 };
 use pallet_session::{historical::OnSessionEnding, SelectInitialValidators};
@@ -283,15 +309,22 @@ use sp_staking::{
 use sp_runtime::{Serialize, Deserialize};
 use frame_system::{self as system, ensure_signed, ensure_root};
 
-use sp_phragmen::ExtendedBalance;
+use sp_phragmen::{Election, ExtendedBalance};
 
 const DEFAULT_MINIMUM_VALIDATOR_COUNT: u32 = 4;
 const MAX_NOMINATIONS: usize = 16;
 const MAX_UNLOCKING_CHUNKS: usize = 32;
+const MAX_CONTROLLER_MIGRATIONS: usize = 64;
+const IDLE_READ_WEIGHT: Weight = 10_000;
+const IDLE_CLEAR_EXPOSURE_WEIGHT: Weight = 20_000;
+const IDLE_WITHDRAW_WEIGHT: Weight = 400_000;
+const PAYOUT_WEIGHT: Weight = 500_000;
+const NOTE_EXPOSURE_WEIGHT: Weight = 100_000;
+const ERA_WORK_READ_WEIGHT: Weight = 10_000;
+const ELECTION_VOTER_WEIGHT: Weight = 200_000;
+const ELECTION_EDGE_WEIGHT: Weight = 1_000;
 const STAKING_ID: LockIdentifier = *b"staking ";
 
-/// Counter for the number of eras that have passed.
-pub type EraIndex = u32;
 
 /// Counter for the number of "reward" points earned by a given validator.
 pub type Points = u32;
@@ -528,6 +561,69 @@ pub struct Exposure<AccountId, Balance: HasCompact> {
 	pub others: Vec<IndividualExposure<AccountId, Balance>>,
 }
 
+/// The reward of a validator and its nominators for an ended era, not paid out yet.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ValidatorReward<AccountId, Balance: HasCompact> {
+	/// The stash of the validator.
+	pub stash: AccountId,
+	/// The reward to share between the validator and its nominators.
+	#[codec(compact)]
+	pub reward: Balance,
+	/// The commission of the validator at the end of the era.
+	pub commission: Perbill,
+	/// The exposure of the validator in the era.
+	pub exposure: Exposure<AccountId, Balance>,
+}
+
+/// The payout of an ended era, in progress.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct EraPayout<Balance: HasCompact> {
+	/// The maximum payout of the era, what the validators aren't paid goes to `RewardRemainder`.
+	#[codec(compact)]
+	pub max_payout: Balance,
+	/// The total paid out so far.
+	#[codec(compact)]
+	pub paid: Balance,
+	/// The position in `PendingRewards` of the next reward to pay out.
+	pub next: u32,
+	/// The number of rewards in `PendingRewards`.
+	pub count: u32,
+}
+
+/// A voter of the election, with the stake backing its votes.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ElectionVoter<AccountId, Balance: HasCompact> {
+	/// The stash of the voter.
+	pub who: AccountId,
+	/// The active stake of the voter.
+	#[codec(compact)]
+	pub stake: Balance,
+	/// The candidates voted for: the validator itself for a candidate, the targets of a
+	/// nominator not nominated before their last slash.
+	pub targets: Vec<AccountId>,
+}
+
+/// The progress of the election of the next era, run before the era changes.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ElectionSnapshotProgress<AccountId> {
+	/// The next candidate to read, `None` once all are read.
+	pub next_validator: Option<AccountId>,
+	/// The next nominator to read, `None` once all are read.
+	pub next_nominator: Option<AccountId>,
+	/// The number of chunks of `SnapshotVoters` holding candidates, the first ones.
+	pub candidate_chunks: u32,
+	/// The number of chunks of `SnapshotVoters`.
+	pub chunks: u32,
+	/// The number of voters read.
+	pub voters: u32,
+	/// The number of votes read, including the one of each candidate for itself.
+	pub edges: u32,
+	/// The voters removed since the snapshot started, left out of the election.
+	pub removed: Vec<AccountId>,
+	/// Whether all the rounds of `RunningElection` are run.
+	pub elected: bool,
+}
+
 /// A pending slash record. The value of the slash has been computed but not applied yet,
 /// rather deferred for several eras.
 #[derive(Encode, Decode, Default, RuntimeDebug)]
@@ -637,6 +733,28 @@ pub trait Trait: frame_system::Trait {
 
 	/// The NPoS reward curve to use.
 	type RewardCurve: Get<&'static PiecewiseLinear<'static>>;
+
+	/// Number of validators whose era boundary work is done per block: paying out their rewards
+	/// for the ended era, then recording their exposures in the new one. The work is done in the
+	/// `on_finalize` of the blocks following the era change. Set to 0 to do all of it when the
+	/// era changes.
+	type EraValidatorsPerBlock: Get<u32>;
+
+	/// Number of voters read per block into the snapshot of the election inputs, taken in the
+	/// `on_finalize` of the `ElectionLookahead` blocks before the end of the era. Set to 0 to run
+	/// the whole election when the era changes.
+	type ElectionVotersPerBlock: Get<u32>;
+
+	/// Number of rounds of the election run per block once its inputs are read, each electing a
+	/// validator and going through all the votes.
+	type ElectionRoundsPerBlock: Get<u32>;
+
+	/// Number of blocks before the end of the era in which the election is run. The calls
+	/// changing the stakers are not allowed in these blocks.
+	type ElectionLookahead: Get<Self::BlockNumber>;
+
+	/// Estimate of the start of the next session, which the end of the era is one of.
+	type NextNewSession: EstimateNextNewSession<Self::BlockNumber>;
 }
 
 /// Mode of era-forcing.
@@ -755,6 +873,59 @@ decl_storage! {
 		/// The earliest era for which we have a pending, unapplied slash.
 		EarliestUnappliedSlash: Option<EraIndex>;
 
+		/// The parameters of the reward curve set by governance, used instead of `T::RewardCurve`.
+		pub InflationParameters get(fn inflation_params): Option<InflationParams>;
+
+		/// The parameters of the reward curve taking effect at the start of the next era.
+		pub PendingInflationParameters get(fn pending_inflation_params): Option<InflationParams>;
+
+		/// The approximation of the reward curve of `InflationParameters`.
+		InflationCurve: Vec<(Perbill, Perbill)>;
+
+		/// The stashes exposed, as a validator or a nominator, in each era of the bonding duration.
+		/// The stash is kept as the value, for the expired eras to be cleared a few at a time.
+		ErasExposed:
+			double_map EraIndex, hasher(blake2_128_concat) T::AccountId => Option<T::AccountId>;
+
+		/// The first era whose exposures are recorded in `ErasExposed`.
+		ExposuresRecordedFrom get(fn exposures_recorded_from): EraIndex;
+
+		/// Eras out of the bonding duration whose exposures are still to be cleared.
+		ExpiredExposureEras: Vec<EraIndex>;
+
+		/// The stashes with funds unlocking at the start of each era, to be withdrawn
+		/// automatically. The stash is kept as the value, for them to be withdrawn a few at a time.
+		UnlockingStashes:
+			double_map EraIndex, hasher(blake2_128_concat) T::AccountId => Option<T::AccountId>;
+
+		/// The first era of `UnlockingStashes` not withdrawn yet.
+		NextUnlockingEra: Option<EraIndex>;
+
+		/// The payout of the last ended era, while it is in progress.
+		pub PendingEraPayout get(fn pending_era_payout): Option<EraPayout<BalanceOf<T>>>;
+
+		/// The rewards of `PendingEraPayout` left to pay out, by position in the payout.
+		PendingRewards get(fn pending_reward):
+			map u32 => Option<ValidatorReward<T::AccountId, BalanceOf<T>>>;
+
+		/// The validators of the current era whose exposures are not recorded in `ErasExposed`
+		/// yet, in reverse order of recording.
+		PendingExposures get(fn pending_exposures): Vec<T::AccountId>;
+
+		/// Whether the era ends at the next session change, the election is then run in the last
+		/// blocks of the session.
+		EraEndsNextSession get(fn era_ends_next_session): bool;
+
+		/// The progress of the election of the next era, while it is run.
+		pub ElectionSnapshot get(fn election_snapshot):
+			Option<ElectionSnapshotProgress<T::AccountId>>;
+
+		/// The chunks of voters read into the snapshot of the election inputs, by position.
+		SnapshotVoters: map u32 => Vec<ElectionVoter<T::AccountId, BalanceOf<T>>>;
+
+		/// The election of the next era set up from the snapshot, while its rounds are run.
+		RunningElection: Option<Election<T::AccountId>>;
+
 		/// The version of storage for upgrade.
 		StorageVersion: u32;
 	}
@@ -804,6 +975,8 @@ decl_event!(
 		/// An old slashing report from a prior era was discarded because it could
 		/// not be processed.
 		OldSlashingReportDiscarded(SessionIndex),
+		/// The reward curve set by governance took effect with the new era.
+		InflationParamsChanged(InflationParams),
 	}
 );
 
@@ -830,6 +1003,12 @@ decl_error! {
 		NoMoreChunks,
 		/// Can not rebond without unlocking chunks.
 		NoUnlockChunk,
+		/// The inflation parameters do not describe a reward curve.
+		InvalidInflationParams,
+		/// Too many stashes to migrate in a single call.
+		TooManyMigrations,
+		/// The election of the next era is run, the stakers can't change until the era changes.
+		ElectionSnapshotInProgress,
 	}
 }
 
@@ -849,16 +1028,31 @@ decl_module! {
 			Self::ensure_storage_upgraded();
 		}
 
-		fn on_finalize() {
+		#[weight = EraWorkWeight::<T>(sp_std::marker::PhantomData)]
+		fn on_finalize(now: T::BlockNumber) {
 			// Set the start of the first era.
 			if !<CurrentEraStart<T>>::exists() {
 				<CurrentEraStart<T>>::put(T::Time::now());
 			}
+
+			let validators_per_block = T::EraValidatorsPerBlock::get();
+			if validators_per_block > 0 {
+				Self::do_era_work(validators_per_block as usize);
+			}
+
+			let voters_per_block = T::ElectionVotersPerBlock::get();
+			if voters_per_block > 0 {
+				Self::open_election(now);
+				Self::do_election_work(voters_per_block as usize);
+			}
 		}
 
 		/// Take the origin account as a stash and lock up `value` of its balance. `controller` will
 		/// be the account that controls it.
 		///
+		/// Separate controller accounts are deprecated: new stakers should pass the stash itself
+		/// as `controller`.
+		///
 		/// `value` must be more than the `minimum_balance` specified by `T::Currency`.
 		///
 		/// The dispatch origin for this call must be _Signed_ by the stash account.
@@ -922,6 +1116,7 @@ decl_module! {
 		#[weight = SimpleDispatchInfo::FixedNormal(500_000)]
 		fn bond_extra(origin, #[compact] max_additional: BalanceOf<T>) {
 			let stash = ensure_signed(origin)?;
+			ensure!(!Self::is_election_open(), Error::<T>::ElectionSnapshotInProgress);
 
 			let controller = Self::bonded(&stash).ok_or(Error::<T>::NotStash)?;
 			let mut ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
@@ -962,6 +1157,7 @@ decl_module! {
 		#[weight = SimpleDispatchInfo::FixedNormal(400_000)]
 		fn unbond(origin, #[compact] value: BalanceOf<T>) {
 			let controller = ensure_signed(origin)?;
+			ensure!(!Self::is_election_open(), Error::<T>::ElectionSnapshotInProgress);
 			let mut ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
 			ensure!(
 				ledger.unlocking.len() < MAX_UNLOCKING_CHUNKS,
@@ -982,6 +1178,7 @@ decl_module! {
 				let era = Self::current_era() + T::BondingDuration::get();
 				ledger.unlocking.push(UnlockChunk { value, era });
 				Self::update_ledger(&controller, &ledger);
+				Self::note_unlocking(era, &ledger.stash);
 			}
 		}
 
@@ -1004,22 +1201,9 @@ decl_module! {
 		#[weight = SimpleDispatchInfo::FixedNormal(400_000)]
 		fn withdraw_unbonded(origin) {
 			let controller = ensure_signed(origin)?;
+			ensure!(!Self::is_election_open(), Error::<T>::ElectionSnapshotInProgress);
 			let ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
-			let ledger = ledger.consolidate_unlocked(Self::current_era());
-
-			if ledger.unlocking.is_empty() && ledger.active.is_zero() {
-				// This account must have called `unbond()` with some value that caused the active
-				// portion to fall below existential deposit + will have no more unlocking chunks
-				// left. We can now safely remove this.
-				let stash = ledger.stash;
-				// remove the lock.
-				T::Currency::remove_lock(STAKING_ID, &stash);
-				// remove all staking-related information.
-				Self::kill_stash(&stash);
-			} else {
-				// This was the consequence of a partial unbond. just update the ledger and move on.
-				Self::update_ledger(&controller, &ledger);
-			}
+			Self::do_withdraw_unbonded(&controller, ledger);
 		}
 
 		/// Declare the desire to validate for the origin controller.
@@ -1038,6 +1222,7 @@ decl_module! {
 			Self::ensure_storage_upgraded();
 
 			let controller = ensure_signed(origin)?;
+			ensure!(!Self::is_election_open(), Error::<T>::ElectionSnapshotInProgress);
 			let ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
 			let stash = &ledger.stash;
 			<Nominators<T>>::remove(stash);
@@ -1060,6 +1245,7 @@ decl_module! {
 			Self::ensure_storage_upgraded();
 
 			let controller = ensure_signed(origin)?;
+			ensure!(!Self::is_election_open(), Error::<T>::ElectionSnapshotInProgress);
 			let ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
 			let stash = &ledger.stash;
 			ensure!(!targets.is_empty(), Error::<T>::EmptyTargets);
@@ -1092,6 +1278,7 @@ decl_module! {
 		#[weight = SimpleDispatchInfo::FixedNormal(500_000)]
 		fn chill(origin) {
 			let controller = ensure_signed(origin)?;
+			ensure!(!Self::is_election_open(), Error::<T>::ElectionSnapshotInProgress);
 			let ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
 			Self::chill_stash(&ledger.stash);
 		}
@@ -1117,6 +1304,8 @@ decl_module! {
 
 		/// (Re-)set the controller of a stash.
 		///
+		/// Deprecated: use [`set_stash_as_controller`] to make the stash its own controller.
+		///
 		/// Effects will be felt at the beginning of the next era.
 		///
 		/// The dispatch origin for this call must be _Signed_ by the stash, not the controller.
@@ -1142,6 +1331,24 @@ decl_module! {
 			}
 		}
 
+		/// Make the stash its own controller, freeing the account previously controlling it.
+		///
+		/// Fails if the stash is the controller of another stash. Does nothing if the stash
+		/// already controls itself.
+		///
+		/// The dispatch origin for this call must be _Signed_ by the stash.
+		///
+		/// # <weight>
+		/// - Independent of the arguments. Insignificant complexity.
+		/// - Contains a limited number of reads.
+		/// - Writes are limited to the `origin` account key and the previous controller key.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedNormal(750_000)]
+		fn set_stash_as_controller(origin) {
+			let stash = ensure_signed(origin)?;
+			Self::do_set_stash_as_controller(&stash)?;
+		}
+
 		/// The ideal number of validators.
 		#[weight = SimpleDispatchInfo::FreeOperational]
 		fn set_validator_count(origin, #[compact] new: u32) {
@@ -1174,6 +1381,38 @@ decl_module! {
 			ForceEra::put(Forcing::ForceNew);
 		}
 
+		/// Set the parameters of the reward curve, replacing `T::RewardCurve`. The curve takes
+		/// effect at the start of the next era, the rewards of the current era are still paid
+		/// according to the previous curve.
+		///
+		/// # <weight>
+		/// - One storage write.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FreeOperational]
+		fn set_inflation_params(origin, params: InflationParams) {
+			ensure_root(origin)?;
+			ensure!(params.is_valid(), Error::<T>::InvalidInflationParams);
+			PendingInflationParameters::put(params);
+		}
+
+		/// Make each of `stashes` its own controller, as [`set_stash_as_controller`] would. Stashes
+		/// that are not bonded, or that control another stash, are skipped.
+		///
+		/// At most 64 stashes can be migrated per call.
+		///
+		/// # <weight>
+		/// - O(S) where S is the number of stashes.
+		/// - At most two storage writes per stash.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FreeOperational]
+		fn force_set_stashes_as_controllers(origin, stashes: Vec<T::AccountId>) {
+			ensure_root(origin)?;
+			ensure!(stashes.len() <= MAX_CONTROLLER_MIGRATIONS, Error::<T>::TooManyMigrations);
+			for stash in stashes {
+				let _ = Self::do_set_stash_as_controller(&stash);
+			}
+		}
+
 		/// Set the validators who cannot be slashed (if any).
 		#[weight = SimpleDispatchInfo::FreeOperational]
 		fn set_invulnerables(origin, validators: Vec<T::AccountId>) {
@@ -1186,10 +1425,7 @@ decl_module! {
 		fn force_unstake(origin, stash: T::AccountId) {
 			ensure_root(origin)?;
 
-			// remove the lock.
-			T::Currency::remove_lock(STAKING_ID, &stash);
-			// remove all staking-related information.
-			Self::kill_stash(&stash);
+			Self::force_unbond(&stash);
 		}
 
 		/// Force there to be a new era at the end of sessions indefinitely.
@@ -1246,6 +1482,7 @@ decl_module! {
 		#[weight = SimpleDispatchInfo::FixedNormal(500_000)]
 		fn rebond(origin, #[compact] value: BalanceOf<T>) {
 			let controller = ensure_signed(origin)?;
+			ensure!(!Self::is_election_open(), Error::<T>::ElectionSnapshotInProgress);
 			let ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
 			ensure!(
 				ledger.unlocking.len() > 0,
@@ -1285,8 +1522,25 @@ impl<T: Trait> Module<T> {
 		<Ledger<T>>::insert(controller, ledger);
 	}
 
+	/// Move the ledger of `stash` from its controller to the stash itself.
+	fn do_set_stash_as_controller(stash: &T::AccountId) -> DispatchResult {
+		let old_controller = Self::bonded(stash).ok_or(Error::<T>::NotStash)?;
+		if old_controller == *stash {
+			return Ok(())
+		}
+		if <Ledger<T>>::exists(stash) {
+			Err(Error::<T>::AlreadyPaired)?
+		}
+		<Bonded<T>>::insert(stash, stash);
+		if let Some(l) = <Ledger<T>>::take(&old_controller) {
+			<Ledger<T>>::insert(stash, l);
+		}
+		Ok(())
+	}
+
 	/// Chill a stash account.
-	fn chill_stash(stash: &T::AccountId) {
+	pub fn chill_stash(stash: &T::AccountId) {
+		Self::note_voter_removal(stash);
 		<Validators<T>>::remove(stash);
 		<Nominators<T>>::remove(stash);
 	}
@@ -1322,14 +1576,15 @@ impl<T: Trait> Module<T> {
 	/// Reward a given validator by a specific amount. Add the reward to the validator's, and its
 	/// nominators' balance, pro-rata based on their exposure, after having removed the validator's
 	/// pre-payout cut.
-	fn reward_validator(stash: &T::AccountId, reward: BalanceOf<T>) -> PositiveImbalanceOf<T> {
-		let off_the_table = Self::validators(stash).commission * reward;
+	fn reward_validator(validator_reward: ValidatorReward<T::AccountId, BalanceOf<T>>) -> PositiveImbalanceOf<T> {
+		let ValidatorReward { stash, reward, commission, exposure } = validator_reward;
+		let stash = &stash;
+		let off_the_table = commission * reward;
 		let reward = reward.saturating_sub(off_the_table);
 		let mut imbalance = <PositiveImbalanceOf<T>>::zero();
 		let validator_cut = if reward.is_zero() {
 			Zero::zero()
 		} else {
-			let exposure = Self::stakers(stash);
 			let total = exposure.total.max(One::one());
 
 			for i in &exposure.others {
@@ -1352,11 +1607,22 @@ impl<T: Trait> Module<T> {
 		-> Option<(Vec<T::AccountId>, Vec<(T::AccountId, Exposure<T::AccountId, BalanceOf<T>>)>)>
 	{
 		let era_length = session_index.checked_sub(Self::current_era_start_session_index()).unwrap_or(0);
+		EraEndsNextSession::kill();
 		match ForceEra::get() {
 			Forcing::ForceNew => ForceEra::kill(),
 			Forcing::ForceAlways => (),
 			Forcing::NotForcing if era_length >= T::SessionsPerEra::get() => (),
-			_ => return None,
+			Forcing::NotForcing => {
+				// The era changes at the next session change, run the election before it.
+				if era_length + 1 >= T::SessionsPerEra::get() {
+					EraEndsNextSession::put(true);
+				}
+				return None
+			},
+			Forcing::ForceNone => {
+				Self::discard_election_snapshot();
+				return None
+			},
 		}
 		let validators = T::SessionInterface::validators();
 		let prior = validators.into_iter()
@@ -1371,6 +1637,9 @@ impl<T: Trait> Module<T> {
 	/// NOTE: This always happens immediately before a session change to ensure that new validators
 	/// get a chance to set their session keys.
 	fn new_era(start_session_index: SessionIndex) -> Option<Vec<T::AccountId>> {
+		// Finish the work left from the previous era change, before the exposures change.
+		Self::do_era_work(usize::max_value());
+
 		// Payout
 		let points = CurrentEraPointsEarned::take();
 		let now = T::Time::now();
@@ -1384,31 +1653,49 @@ impl<T: Trait> Module<T> {
 			let validator_len: BalanceOf<T> = (validators.len() as u32).into();
 			let total_rewarded_stake = Self::slot_stake() * validator_len;
 
+			let points;
+			let governed_curve;
+			let reward_curve = match Self::inflation_params() {
+				Some(params) => {
+					points = InflationCurve::get();
+					governed_curve = PiecewiseLinear { points: &points, maximum: params.max_inflation };
+					&governed_curve
+				},
+				None => T::RewardCurve::get(),
+			};
+
 			let (total_payout, max_payout) = inflation::compute_total_payout(
-				&T::RewardCurve::get(),
+				reward_curve,
 				total_rewarded_stake.clone(),
 				T::Currency::total_issuance(),
 				// Duration of era; more than u64::MAX is rewarded as u64::MAX.
 				era_duration.saturated_into::<u64>(),
 			);
 
-			let mut total_imbalance = <PositiveImbalanceOf<T>>::zero();
-
+			// The exposures are copied, as they are replaced by the election.
+			let mut count = 0;
 			for (v, p) in validators.iter().zip(points.individual.into_iter()) {
 				if p != 0 {
-					let reward = Perbill::from_rational_approximation(p, points.total) * total_payout;
-					total_imbalance.subsume(Self::reward_validator(v, reward));
+					<PendingRewards<T>>::insert(count, ValidatorReward {
+						stash: v.clone(),
+						reward: Perbill::from_rational_approximation(p, points.total) * total_payout,
+						commission: Self::validators(v).commission,
+						exposure: Self::stakers(v),
+					});
+					count += 1;
 				}
 			}
 
-			// assert!(total_imbalance.peek() == total_payout)
-			let total_payout = total_imbalance.peek();
-
-			let rest = max_payout.saturating_sub(total_payout);
-			Self::deposit_event(RawEvent::Reward(total_payout, rest));
+			<PendingEraPayout<T>>::put(EraPayout { max_payout, paid: Zero::zero(), next: 0, count });
+			if T::EraValidatorsPerBlock::get() == 0 {
+				Self::do_era_work(usize::max_value());
+			}
+		}
 
-			T::Reward::on_unbalanced(total_imbalance);
-			T::RewardRemainder::on_unbalanced(T::Currency::issue(rest));
+		if let Some(params) = PendingInflationParameters::take() {
+			InflationCurve::put(params.points());
+			InflationParameters::put(params);
+			Self::deposit_event(RawEvent::InflationParamsChanged(params));
 		}
 
 		// Increment current era.
@@ -1441,8 +1728,13 @@ impl<T: Trait> Module<T> {
 			}
 		});
 
+		if current_era > bonding_duration {
+			ExpiredExposureEras::mutate(|eras| eras.push(current_era - bonding_duration - 1));
+		}
+
 		// Reassign all Stakers.
 		let (_slot_stake, maybe_new_validators) = Self::select_validators();
+		Self::queue_exposures();
 		Self::apply_unapplied_slashes(current_era);
 
 		maybe_new_validators
@@ -1470,51 +1762,29 @@ impl<T: Trait> Module<T> {
 	///
 	/// Assumes storage is coherent with the declaration.
 	fn select_validators() -> (BalanceOf<T>, Option<Vec<T::AccountId>>) {
-		let mut all_nominators: Vec<(T::AccountId, Vec<T::AccountId>)> = Vec::new();
-		let all_validator_candidates_iter = <Validators<T>>::enumerate();
-		let all_validators = all_validator_candidates_iter.map(|(who, _pref)| {
-			let self_vote = (who.clone(), vec![who.clone()]);
-			all_nominators.push(self_vote);
-			who
-		}).collect::<Vec<T::AccountId>>();
-
-		let nominator_votes = <Nominators<T>>::enumerate().map(|(nominator, nominations)| {
-			let Nominations { submitted_in, mut targets, suppressed: _ } = nominations;
-
-			// Filter out nomination targets which were nominated before the most recent
-			// slashing span.
-			targets.retain(|stash| {
-				<Self as Store>::SlashingSpans::get(&stash).map_or(
-					true,
-					|spans| submitted_in >= spans.last_start(),
-				)
-			});
-
-			(nominator, targets)
+		let election = Self::take_election().unwrap_or_else(|| {
+			let (candidates, voters) = Self::election_inputs();
+			Self::new_election(candidates, voters)
 		});
-		all_nominators.extend(nominator_votes);
 
-		let maybe_phragmen_result = sp_phragmen::elect::<_, _, _, T::CurrencyToVote>(
-			Self::validator_count() as usize,
-			Self::minimum_validator_count().max(1) as usize,
-			all_validators,
-			all_nominators,
-			Self::slashable_balance_of,
-		);
+		let to_balance = |e: ExtendedBalance|
+			<T::CurrencyToVote as Convert<ExtendedBalance, BalanceOf<T>>>::convert(e);
+		// The stakes as votes, converted back to balances to be converted to the same votes.
+		let stakes = election.as_ref().map(Election::budgets).unwrap_or_default();
+		let stake_of = |who: &T::AccountId| -> BalanceOf<T> {
+			stakes.get(who).cloned().map_or_else(Zero::zero, to_balance)
+		};
 
-		if let Some(phragmen_result) = maybe_phragmen_result {
+		if let Some(phragmen_result) = election.map(Election::into_result) {
 			let elected_stashes = phragmen_result.winners.iter()
 				.map(|(s, _)| s.clone())
 				.collect::<Vec<T::AccountId>>();
 			let assignments = phragmen_result.assignments;
 
-			let to_balance = |e: ExtendedBalance|
-				<T::CurrencyToVote as Convert<ExtendedBalance, BalanceOf<T>>>::convert(e);
-
 			let supports = sp_phragmen::build_support_map::<_, _, _, T::CurrencyToVote>(
 				&elected_stashes,
 				&assignments,
-				Self::slashable_balance_of,
+				stake_of,
 			);
 
 			// Clear Stakers.
@@ -1578,6 +1848,463 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
+	/// Remove the unlocked chunks of `ledger` from the staking system, freeing them.
+	fn do_withdraw_unbonded(
+		controller: &T::AccountId,
+		ledger: StakingLedger<T::AccountId, BalanceOf<T>>,
+	) {
+		let ledger = ledger.consolidate_unlocked(Self::current_era());
+
+		if ledger.unlocking.is_empty() && ledger.active.is_zero() {
+			// This account must have called `unbond()` with some value that caused the active
+			// portion to fall below existential deposit + will have no more unlocking chunks
+			// left. We can now safely remove this.
+			Self::force_unbond(&ledger.stash);
+		} else {
+			// This was the consequence of a partial unbond. just update the ledger and move on.
+			Self::update_ledger(controller, &ledger);
+		}
+	}
+
+	/// Queue `stash` to have its funds withdrawn automatically at the start of `era`.
+	fn note_unlocking(era: EraIndex, stash: &T::AccountId) {
+		if NextUnlockingEra::get().is_none() {
+			NextUnlockingEra::put(Self::current_era());
+		}
+		<UnlockingStashes<T>>::insert(era, stash, stash);
+	}
+
+	/// Do the era boundary work of at most `limit` validators: pay out the rewards of the ended
+	/// era, then record the exposures of the current one.
+	fn do_era_work(limit: usize) {
+		let mut done = 0;
+
+		if let Some(mut payout) = <PendingEraPayout<T>>::take() {
+			let mut imbalance = <PositiveImbalanceOf<T>>::zero();
+			while done < limit && payout.next < payout.count {
+				if let Some(reward) = <PendingRewards<T>>::take(payout.next) {
+					imbalance.subsume(Self::reward_validator(reward));
+				}
+				payout.next += 1;
+				done += 1;
+			}
+			payout.paid = payout.paid.saturating_add(imbalance.peek());
+			T::Reward::on_unbalanced(imbalance);
+
+			if payout.next == payout.count {
+				let rest = payout.max_payout.saturating_sub(payout.paid);
+				Self::deposit_event(RawEvent::Reward(payout.paid, rest));
+				T::RewardRemainder::on_unbalanced(T::Currency::issue(rest));
+			} else {
+				<PendingEraPayout<T>>::put(payout);
+			}
+		}
+
+		if done < limit && PendingExposures::<T>::exists() {
+			let mut pending = PendingExposures::<T>::get();
+			let era = Self::current_era();
+			while done < limit {
+				match pending.pop() {
+					Some(validator) => Self::note_exposure(era, &validator),
+					None => break,
+				}
+				done += 1;
+			}
+			if pending.is_empty() {
+				PendingExposures::<T>::kill();
+			} else {
+				PendingExposures::<T>::put(pending);
+			}
+		}
+	}
+
+	/// The weight of the era boundary work and of the reading of the election inputs done in the
+	/// `on_finalize` of the current block.
+	///
+	/// The rewards and exposures weighed are read here, within the weight of the work.
+	fn era_work_weight() -> Weight {
+		let mut weight = ERA_WORK_READ_WEIGHT.saturating_add(Self::election_work_weight());
+
+		let mut left = T::EraValidatorsPerBlock::get();
+		if left == 0 {
+			return weight
+		}
+
+		if let Some(payout) = Self::pending_era_payout() {
+			for position in payout.next..payout.count {
+				if left == 0 {
+					return weight
+				}
+				let others = Self::pending_reward(position)
+					.map_or(0, |reward| reward.exposure.others.len() as Weight);
+				weight = weight
+					.saturating_add(ERA_WORK_READ_WEIGHT)
+					.saturating_add((others + 1).saturating_mul(PAYOUT_WEIGHT));
+				left -= 1;
+			}
+		}
+
+		weight = weight.saturating_add(ERA_WORK_READ_WEIGHT);
+		for validator in Self::pending_exposures().iter().rev().take(left as usize) {
+			let others = Self::stakers(validator).others.len() as Weight;
+			weight = weight
+				.saturating_add(ERA_WORK_READ_WEIGHT)
+				.saturating_add((others + 1).saturating_mul(NOTE_EXPOSURE_WEIGHT));
+		}
+
+		weight
+	}
+
+	/// The weight of the work on the election done in the `on_finalize` of the current block.
+	fn election_work_weight() -> Weight {
+		let voters_per_block = T::ElectionVotersPerBlock::get() as Weight;
+		if voters_per_block == 0 {
+			return 0
+		}
+
+		let read_voters = voters_per_block.saturating_mul(ELECTION_VOTER_WEIGHT);
+		let weight = match Self::election_snapshot() {
+			// The election may open.
+			None if Self::era_ends_next_session() => read_voters,
+			None => 0,
+			Some(progress) => if progress.next_validator.is_some() || progress.next_nominator.is_some() {
+				read_voters
+			} else if progress.elected {
+				0
+			} else if <RunningElection<T>>::exists() {
+				// The election is decoded and encoded again around its rounds.
+				let rounds = T::ElectionRoundsPerBlock::get() as Weight;
+				(progress.edges as Weight)
+					.saturating_mul(rounds.saturating_add(2))
+					.saturating_mul(ELECTION_EDGE_WEIGHT)
+			} else {
+				// The election is set up from the snapshot.
+				(progress.voters as Weight).saturating_mul(ELECTION_VOTER_WEIGHT)
+			},
+		};
+		weight.saturating_add(ERA_WORK_READ_WEIGHT)
+	}
+
+	/// Whether the election of the next era is run, the stakers can't change meanwhile.
+	pub fn is_election_open() -> bool {
+		<ElectionSnapshot<T>>::exists()
+	}
+
+	/// Start the election of the next era if the era ends within `ElectionLookahead` blocks.
+	fn open_election(now: T::BlockNumber) {
+		if !Self::era_ends_next_session() || Self::is_election_open() {
+			return
+		}
+		let ends_soon = T::NextNewSession::estimate_next_new_session(now)
+			.map_or(false, |end| end.saturating_sub(now) <= T::ElectionLookahead::get());
+		if ends_soon {
+			<ElectionSnapshot<T>>::put(ElectionSnapshotProgress {
+				next_validator: <Validators<T>>::head(),
+				next_nominator: <Nominators<T>>::head(),
+				candidate_chunks: 0,
+				chunks: 0,
+				voters: 0,
+				edges: 0,
+				removed: Vec::new(),
+				elected: false,
+			});
+		}
+	}
+
+	/// Do the next step of the election being run: read at most `limit` voters into its snapshot,
+	/// set it up once they are all read, then run `ElectionRoundsPerBlock` of its rounds.
+	fn do_election_work(limit: usize) {
+		let mut progress = match Self::election_snapshot() {
+			Some(progress) => progress,
+			None => return,
+		};
+
+		if progress.next_validator.is_some() || progress.next_nominator.is_some() {
+			Self::read_election_voters(&mut progress, limit);
+		} else if progress.elected {
+			return
+		} else if let Some(mut election) = <RunningElection<T>>::get() {
+			election.run_rounds(T::ElectionRoundsPerBlock::get() as usize);
+			progress.elected = election.is_done();
+			<RunningElection<T>>::put(election);
+		} else {
+			match Self::snapshot_election(&progress) {
+				Some(election) => <RunningElection<T>>::put(election),
+				// Not enough candidates, there is nothing to run.
+				None => progress.elected = true,
+			}
+		}
+		<ElectionSnapshot<T>>::put(progress);
+	}
+
+	/// Read at most `limit` voters, candidates first, into the snapshot of the election inputs.
+	fn read_election_voters(progress: &mut ElectionSnapshotProgress<T::AccountId>, limit: usize) {
+		let voters = if let Some(next) = progress.next_validator.take() {
+			let mut candidates = <Validators<T>>::enumerate_from(next).map(|(who, _)| who);
+			let voters = candidates.by_ref().take(limit).map(Self::candidate_voter).collect::<Vec<_>>();
+			progress.next_validator = candidates.next();
+			progress.candidate_chunks = progress.chunks + 1;
+			voters
+		} else if let Some(next) = progress.next_nominator.take() {
+			let mut nominators = <Nominators<T>>::enumerate_from(next);
+			let voters = nominators.by_ref()
+				.take(limit)
+				.map(|(who, nominations)| Self::nominator_voter(who, nominations))
+				.collect::<Vec<_>>();
+			progress.next_nominator = nominators.next().map(|(who, _)| who);
+			voters
+		} else {
+			return
+		};
+
+		// Empty if the next voter to read was removed, which `note_voter_removal` prevents.
+		if !voters.is_empty() {
+			progress.voters += voters.len() as u32;
+			progress.edges += voters.iter().map(|voter| voter.targets.len() as u32).sum::<u32>();
+			<SnapshotVoters<T>>::insert(progress.chunks, voters);
+			progress.chunks += 1;
+		}
+	}
+
+	/// Leave `stash` out of the election being run if it is one of its voters.
+	///
+	/// The snapshot goes on with the voter following it, the election is set up again without it
+	/// if it already was.
+	fn note_voter_removal(stash: &T::AccountId) {
+		let mut progress = match Self::election_snapshot() {
+			Some(progress) => progress,
+			None => return,
+		};
+		let is_validator = <Validators<T>>::exists(stash);
+		let is_nominator = <Nominators<T>>::exists(stash);
+		if !is_validator && !is_nominator {
+			return
+		}
+
+		if is_validator && progress.next_validator.as_ref() == Some(stash) {
+			progress.next_validator = <Validators<T>>::enumerate_from(stash.clone())
+				.nth(1)
+				.map(|(who, _)| who);
+		}
+		if is_nominator && progress.next_nominator.as_ref() == Some(stash) {
+			progress.next_nominator = <Nominators<T>>::enumerate_from(stash.clone())
+				.nth(1)
+				.map(|(who, _)| who);
+		}
+		progress.removed.push(stash.clone());
+		progress.elected = false;
+		<ElectionSnapshot<T>>::put(progress);
+		<RunningElection<T>>::kill();
+	}
+
+	/// Set up the election of the voters of the snapshot, leaving the removed ones out. `None`
+	/// if there are not enough candidates.
+	fn snapshot_election(
+		progress: &ElectionSnapshotProgress<T::AccountId>,
+	) -> Option<Election<T::AccountId>> {
+		let removed = progress.removed.iter().collect::<BTreeSet<_>>();
+		let mut candidates = Vec::new();
+		let mut voters = Vec::new();
+		for chunk in 0..progress.chunks {
+			let chunk_voters = <SnapshotVoters<T>>::get(chunk).into_iter()
+				.filter(|voter| !removed.contains(&voter.who))
+				.collect::<Vec<_>>();
+			if chunk < progress.candidate_chunks {
+				candidates.extend(chunk_voters.iter().map(|voter| voter.who.clone()));
+			}
+			voters.extend(chunk_voters);
+		}
+		Self::new_election(candidates, voters)
+	}
+
+	/// Finish the election being run and remove it, `None` if none is.
+	fn take_election() -> Option<Option<Election<T::AccountId>>> {
+		let mut progress = Self::election_snapshot()?;
+		Self::read_election_voters(&mut progress, usize::max_value());
+		let election = <RunningElection<T>>::take()
+			.or_else(|| Self::snapshot_election(&progress));
+		<ElectionSnapshot<T>>::put(progress);
+		Self::discard_election_snapshot();
+		Some(election)
+	}
+
+	/// Remove the election being run, if any.
+	fn discard_election_snapshot() {
+		if let Some(progress) = <ElectionSnapshot<T>>::take() {
+			for chunk in 0..progress.chunks {
+				<SnapshotVoters<T>>::remove(chunk);
+			}
+		}
+		<RunningElection<T>>::kill();
+	}
+
+	/// Set up the election of the given candidates by the given voters, `None` if there are not
+	/// enough candidates.
+	fn new_election(
+		candidates: Vec<T::AccountId>,
+		voters: Vec<ElectionVoter<T::AccountId, BalanceOf<T>>>,
+	) -> Option<Election<T::AccountId>> {
+		let mut stakes = BTreeMap::new();
+		let voters = voters.into_iter()
+			.map(|ElectionVoter { who, stake, targets }| {
+				stakes.insert(who.clone(), stake);
+				(who, targets)
+			})
+			.collect::<Vec<_>>();
+		Election::new::<_, _, T::CurrencyToVote>(
+			Self::validator_count() as usize,
+			Self::minimum_validator_count().max(1) as usize,
+			candidates,
+			voters,
+			|who: &T::AccountId| -> BalanceOf<T> { stakes.get(who).cloned().unwrap_or_default() },
+		)
+	}
+
+	/// Read the candidates and voters of the election.
+	fn election_inputs() -> (Vec<T::AccountId>, Vec<ElectionVoter<T::AccountId, BalanceOf<T>>>) {
+		let candidates = <Validators<T>>::enumerate().map(|(who, _)| who).collect::<Vec<_>>();
+		let voters = candidates.iter()
+			.cloned()
+			.map(Self::candidate_voter)
+			.chain(<Nominators<T>>::enumerate().map(|(who, nominations)| {
+				Self::nominator_voter(who, nominations)
+			}))
+			.collect();
+		(candidates, voters)
+	}
+
+	/// The candidate `who`, voting for itself.
+	fn candidate_voter(who: T::AccountId) -> ElectionVoter<T::AccountId, BalanceOf<T>> {
+		ElectionVoter { stake: Self::slashable_balance_of(&who), targets: vec![who.clone()], who }
+	}
+
+	/// The nominator `who`, voting for the targets of its `nominations`.
+	fn nominator_voter(
+		who: T::AccountId,
+		nominations: Nominations<T::AccountId>,
+	) -> ElectionVoter<T::AccountId, BalanceOf<T>> {
+		let Nominations { submitted_in, mut targets, suppressed: _ } = nominations;
+
+		// Filter out nomination targets which were nominated before the most recent
+		// slashing span.
+		targets.retain(|stash| {
+			<Self as Store>::SlashingSpans::get(&stash).map_or(
+				true,
+				|spans| submitted_in >= spans.last_start(),
+			)
+		});
+
+		ElectionVoter { stake: Self::slashable_balance_of(&who), targets, who }
+	}
+
+	/// Queue the recording of the stashes exposed by the current `Stakers` in the current era.
+	/// They are recorded over the next blocks with the rest of the era boundary work, unless it
+	/// is done at once.
+	pub(crate) fn queue_exposures() {
+		let mut exposed = Self::current_elected();
+		exposed.reverse();
+		PendingExposures::<T>::put(exposed);
+		if T::EraValidatorsPerBlock::get() == 0 {
+			Self::do_era_work(usize::max_value());
+		}
+	}
+
+	/// Record the stashes exposed by the current `Stakers` in `era` at once. Only meant for the
+	/// genesis, [`queue_exposures`] spreads the recording over blocks otherwise.
+	fn note_exposures(era: EraIndex) {
+		for validator in Self::current_elected() {
+			Self::note_exposure(era, &validator);
+		}
+	}
+
+	/// Record the stashes exposed by the current `Stakers` of `validator` in `era`.
+	fn note_exposure(era: EraIndex, validator: &T::AccountId) {
+		for nominator in Self::stakers(validator).others {
+			<ErasExposed<T>>::insert(&era, &nominator.who, &nominator.who);
+		}
+		<ErasExposed<T>>::insert(&era, validator, validator);
+	}
+
+	/// Whether `stash` was exposed, as a validator or a nominator, in `era`.
+	///
+	/// Only the eras of the bonding duration are kept, see [`exposure_eras`].
+	/// The exposures of the current era are recorded over several blocks after the era change,
+	/// see [`exposures_recorded_in_era`].
+	pub fn is_exposed_in_era(stash: &T::AccountId, era: EraIndex) -> bool {
+		<ErasExposed<T>>::exists(&era, stash)
+	}
+
+	/// Whether all the exposures of `era` are recorded.
+	pub fn exposures_recorded_in_era(era: EraIndex) -> bool {
+		era != Self::current_era() || !PendingExposures::<T>::exists()
+	}
+
+	/// The eras in which an exposure can still be slashed, oldest first, or `None` if some of
+	/// them predate the recording of exposures.
+	pub fn exposure_eras() -> Option<Vec<EraIndex>> {
+		let current_era = Self::current_era();
+		let first = current_era.saturating_sub(T::BondingDuration::get());
+		if first < Self::exposures_recorded_from() {
+			return None
+		}
+		Some((first..=current_era).collect())
+	}
+
+	/// All the pending slashes, oldest first.
+	pub fn pending_slashes() -> Vec<PendingSlash<T::AccountId, BalanceOf<T>>> {
+		let earliest = match <Self as Store>::EarliestUnappliedSlash::get() {
+			Some(earliest) => earliest,
+			None => return Vec::new(),
+		};
+		let apply_after = T::SlashDeferDuration::get() + 1;
+		(earliest..=Self::current_era()).flat_map(|era| {
+			<Self as Store>::UnappliedSlashes::get(&era).into_iter()
+				.enumerate()
+				.map(move |(index, slash)| PendingSlash {
+					era,
+					index: index as u32,
+					apply_at: era + apply_after,
+					validator: slash.validator,
+					own: slash.own,
+					others: slash.others,
+					reporters: slash.reporters,
+					payout: slash.payout,
+				})
+		}).collect()
+	}
+
+	/// The pending slashes affecting `who`, as a validator or a nominator, oldest first.
+	pub fn pending_slashes_of(who: &T::AccountId) -> Vec<PendingSlash<T::AccountId, BalanceOf<T>>> {
+		Self::pending_slashes().into_iter()
+			.filter(|slash| slash.validator == *who || slash.others.iter().any(|(n, _)| n == who))
+			.collect()
+	}
+
+	/// The total amount of the pending slashes of `who`.
+	pub fn unapplied_slash_of(who: &T::AccountId) -> BalanceOf<T> {
+		Self::pending_slashes_of(who).into_iter()
+			.map(|slash| {
+				let own = if slash.validator == *who { slash.own } else { Zero::zero() };
+				slash.others.into_iter()
+					.filter(|(n, _)| n == who)
+					.fold(own, |total, (_, value)| total.saturating_add(value))
+			})
+			.fold(Zero::zero(), |total: BalanceOf<T>, value| total.saturating_add(value))
+	}
+
+	/// The slashing spans of `stash`, most recent first.
+	pub fn slashing_spans(stash: &T::AccountId) -> Vec<SlashingSpanInfo<BalanceOf<T>>> {
+		slashing::spans_of::<T>(stash)
+	}
+
+	/// Remove the lock of `stash` and all of its staking information, immediately.
+	pub fn force_unbond(stash: &T::AccountId) {
+		// remove the lock.
+		T::Currency::remove_lock(STAKING_ID, stash);
+		// remove all staking-related information.
+		Self::kill_stash(stash);
+	}
+
 	/// Remove all associated data of a stash account from the staking system.
 	///
 	/// Assumes storage is upgraded before calling.
@@ -1590,6 +2317,7 @@ impl<T: Trait> Module<T> {
 			<Ledger<T>>::remove(&controller);
 		}
 		<Payee<T>>::remove(stash);
+		Self::note_voter_removal(stash);
 		<Validators<T>>::remove(stash);
 		<Nominators<T>>::remove(stash);
 
@@ -1707,9 +2435,84 @@ impl<T: Trait> Convert<T::AccountId, Option<Exposure<T::AccountId, BalanceOf<T>>
 	}
 }
 
+/// The weight of the era boundary work done in `on_finalize`. It is known when the block is
+/// initialized, as the work only depends on the state left by the era change.
+struct EraWorkWeight<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Trait> WeighData<T::BlockNumber> for EraWorkWeight<T> {
+	fn weigh_data(&self, _: T::BlockNumber) -> Weight {
+		<Module<T>>::era_work_weight()
+	}
+}
+
+/// Housekeeping done with the weight left unused by blocks: the exposures of the eras out of the
+/// bonding duration are cleared, and the unlocked funds of the stashes are withdrawn. Both are
+/// done a few stashes at a time, within the given weight.
+impl<T: Trait> OnIdle<T::BlockNumber> for Module<T> {
+	fn on_idle(_n: T::BlockNumber, remaining_weight: Weight) -> Weight {
+		let mut consumed_weight = IDLE_READ_WEIGHT;
+		if remaining_weight < consumed_weight {
+			return 0
+		}
+
+		let mut expired = ExpiredExposureEras::get();
+		let expired_count = expired.len();
+		while let Some(&era) = expired.last() {
+			// one more read finds out that the era is cleared.
+			let steps = (remaining_weight - consumed_weight) / IDLE_CLEAR_EXPOSURE_WEIGHT;
+			if steps < 2 {
+				break
+			}
+			let limit = steps as usize - 1;
+			let exposed = <ErasExposed<T>>::iter_prefix(&era).take(limit).collect::<Vec<_>>();
+			consumed_weight += (exposed.len() as Weight + 1) * IDLE_CLEAR_EXPOSURE_WEIGHT;
+			for stash in &exposed {
+				<ErasExposed<T>>::remove(&era, stash);
+			}
+			if exposed.len() == limit {
+				break
+			}
+			expired.pop();
+		}
+		if expired.len() != expired_count {
+			ExpiredExposureEras::put(expired);
+		}
+
+		let current_era = Self::current_era();
+		while let Some(era) = NextUnlockingEra::get().filter(|era| *era <= current_era) {
+			// one more read finds out that all the stashes of the era are withdrawn.
+			if consumed_weight.saturating_add(IDLE_READ_WEIGHT) > remaining_weight {
+				break
+			}
+			consumed_weight += IDLE_READ_WEIGHT;
+
+			let limit = ((remaining_weight - consumed_weight) / IDLE_WITHDRAW_WEIGHT) as usize;
+			let stashes = <UnlockingStashes<T>>::iter_prefix(&era).take(limit).collect::<Vec<_>>();
+			consumed_weight += stashes.len() as Weight * IDLE_WITHDRAW_WEIGHT;
+			for stash in &stashes {
+				<UnlockingStashes<T>>::remove(&era, stash);
+				if let Some(controller) = Self::bonded(stash) {
+					if let Some(ledger) = Self::ledger(&controller) {
+						Self::do_withdraw_unbonded(&controller, ledger);
+					}
+				}
+			}
+
+			if stashes.len() == limit {
+				break
+			}
+			NextUnlockingEra::put(era + 1);
+		}
+
+		consumed_weight
+	}
+}
+
 impl<T: Trait> SelectInitialValidators<T::AccountId> for Module<T> {
 	fn select_initial_validators() -> Option<Vec<T::AccountId>> {
-		<Module<T>>::select_validators().1
+		let maybe_validators = <Module<T>>::select_validators().1;
+		<Module<T>>::note_exposures(<Module<T>>::current_era());
+		maybe_validators
 	}
 }
 
@@ -52,6 +52,10 @@ thread_local! {
 	static SESSION: RefCell<(Vec<AccountId>, HashSet<AccountId>)> = RefCell::new(Default::default());
 	static EXISTENTIAL_DEPOSIT: RefCell<u64> = RefCell::new(0);
 	static SLASH_DEFER_DURATION: RefCell<EraIndex> = RefCell::new(0);
+	static ERA_VALIDATORS_PER_BLOCK: RefCell<u32> = RefCell::new(0);
+	static ELECTION_VOTERS_PER_BLOCK: RefCell<u32> = RefCell::new(0);
+	static ELECTION_ROUNDS_PER_BLOCK: RefCell<u32> = RefCell::new(0);
+	static ELECTION_LOOKAHEAD: RefCell<BlockNumber> = RefCell::new(0);
 }
 
 pub struct TestSessionHandler;
@@ -98,6 +102,34 @@ impl Get<EraIndex> for SlashDeferDuration {
 	}
 }
 
+pub struct EraValidatorsPerBlock;
+impl Get<u32> for EraValidatorsPerBlock {
+	fn get() -> u32 {
+		ERA_VALIDATORS_PER_BLOCK.with(|v| *v.borrow())
+	}
+}
+
+pub struct ElectionVotersPerBlock;
+impl Get<u32> for ElectionVotersPerBlock {
+	fn get() -> u32 {
+		ELECTION_VOTERS_PER_BLOCK.with(|v| *v.borrow())
+	}
+}
+
+pub struct ElectionRoundsPerBlock;
+impl Get<u32> for ElectionRoundsPerBlock {
+	fn get() -> u32 {
+		ELECTION_ROUNDS_PER_BLOCK.with(|v| *v.borrow())
+	}
+}
+
+pub struct ElectionLookahead;
+impl Get<BlockNumber> for ElectionLookahead {
+	fn get() -> BlockNumber {
+		ELECTION_LOOKAHEAD.with(|v| *v.borrow())
+	}
+}
+
 impl_outer_origin!{
 	pub enum Origin for Test  where system = frame_system {}
 }
@@ -185,11 +217,13 @@ impl pallet_authorship::Trait for Test {
 }
 parameter_types! {
 	pub const MinimumPeriod: u64 = 5;
+	pub const MaxDrift: u64 = 30 * 1000;
 }
 impl pallet_timestamp::Trait for Test {
 	type Moment = u64;
 	type OnTimestampSet = ();
 	type MinimumPeriod = MinimumPeriod;
+	type MaxDrift = MaxDrift;
 }
 pallet_staking_reward_curve::build! {
 	const I_NPOS: PiecewiseLinear<'static> = curve!(
@@ -220,6 +254,11 @@ impl Trait for Test {
 	type BondingDuration = BondingDuration;
 	type SessionInterface = Self;
 	type RewardCurve = RewardCurve;
+	type EraValidatorsPerBlock = EraValidatorsPerBlock;
+	type ElectionVotersPerBlock = ElectionVotersPerBlock;
+	type ElectionRoundsPerBlock = ElectionRoundsPerBlock;
+	type ElectionLookahead = ElectionLookahead;
+	type NextNewSession = pallet_session::PeriodicSessions<Period, Offset>;
 }
 
 pub struct ExtBuilder {
@@ -229,6 +268,10 @@ pub struct ExtBuilder {
 	validator_count: u32,
 	minimum_validator_count: u32,
 	slash_defer_duration: EraIndex,
+	era_validators_per_block: u32,
+	election_voters_per_block: u32,
+	election_rounds_per_block: u32,
+	election_lookahead: BlockNumber,
 	fair: bool,
 	num_validators: Option<u32>,
 	invulnerables: Vec<u64>,
@@ -243,6 +286,10 @@ impl Default for ExtBuilder {
 			validator_count: 2,
 			minimum_validator_count: 0,
 			slash_defer_duration: 0,
+			era_validators_per_block: 0,
+			election_voters_per_block: 0,
+			election_rounds_per_block: 1,
+			election_lookahead: 1,
 			fair: true,
 			num_validators: None,
 			invulnerables: vec![],
@@ -275,6 +322,22 @@ impl ExtBuilder {
 		self.slash_defer_duration = eras;
 		self
 	}
+	pub fn era_validators_per_block(mut self, validators: u32) -> Self {
+		self.era_validators_per_block = validators;
+		self
+	}
+	pub fn election_voters_per_block(mut self, voters: u32) -> Self {
+		self.election_voters_per_block = voters;
+		self
+	}
+	pub fn election_rounds_per_block(mut self, rounds: u32) -> Self {
+		self.election_rounds_per_block = rounds;
+		self
+	}
+	pub fn election_lookahead(mut self, blocks: BlockNumber) -> Self {
+		self.election_lookahead = blocks;
+		self
+	}
 	pub fn fair(mut self, is_fair: bool) -> Self {
 		self.fair = is_fair;
 		self
@@ -290,6 +353,10 @@ impl ExtBuilder {
 	pub fn set_associated_consts(&self) {
 		EXISTENTIAL_DEPOSIT.with(|v| *v.borrow_mut() = self.existential_deposit);
 		SLASH_DEFER_DURATION.with(|v| *v.borrow_mut() = self.slash_defer_duration);
+		ERA_VALIDATORS_PER_BLOCK.with(|v| *v.borrow_mut() = self.era_validators_per_block);
+		ELECTION_VOTERS_PER_BLOCK.with(|v| *v.borrow_mut() = self.election_voters_per_block);
+		ELECTION_ROUNDS_PER_BLOCK.with(|v| *v.borrow_mut() = self.election_rounds_per_block);
+		ELECTION_LOOKAHEAD.with(|v| *v.borrow_mut() = self.election_lookahead);
 	}
 	pub fn build(self) -> sp_io::TestExternalities {
 		self.set_associated_consts();
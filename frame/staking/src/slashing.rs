@@ -50,7 +50,7 @@
 
 use super::{
 	EraIndex, Trait, Module, Store, BalanceOf, Exposure, Perbill, SessionInterface,
-	NegativeImbalanceOf, UnappliedSlash,
+	NegativeImbalanceOf, UnappliedSlash, SlashingSpanInfo,
 };
 use sp_runtime::traits::{Zero, Saturating};
 use frame_support::{
@@ -536,6 +536,24 @@ pub(crate) fn clear_era_metadata<T: Trait>(obsolete_era: EraIndex) {
 	<Module<T> as Store>::NominatorSlashInEra::remove_prefix(&obsolete_era);
 }
 
+/// The slashing spans of a stash with their records, most recent first.
+pub(crate) fn spans_of<T: Trait>(stash: &T::AccountId) -> Vec<SlashingSpanInfo<BalanceOf<T>>> {
+	let spans = match <Module<T> as Store>::SlashingSpans::get(stash) {
+		None => return Vec::new(),
+		Some(spans) => spans,
+	};
+	spans.iter().map(|span| {
+		let record = <Module<T> as Store>::SpanSlash::get(&(stash.clone(), span.index));
+		SlashingSpanInfo {
+			index: span.index,
+			start: span.start,
+			length: span.length,
+			slashed: record.slashed,
+			paid_out: record.paid_out,
+		}
+	}).collect()
+}
+
 /// Clear slashing metadata for a dead account.
 pub(crate) fn clear_stash_metadata<T: Trait>(stash: &T::AccountId) {
 	let spans = match <Module<T> as Store>::SlashingSpans::take(stash) {
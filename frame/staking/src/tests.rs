@@ -18,7 +18,7 @@
 
 use super::*;
 use mock::*;
-use sp_runtime::{assert_eq_error_rate, traits::{OnInitialize, BadOrigin}};
+use sp_runtime::{assert_eq_error_rate, traits::{OnInitialize, OnFinalize, BadOrigin}};
 use sp_staking::offence::OffenceDetails;
 use frame_support::{
 	assert_ok, assert_noop,
@@ -156,6 +156,60 @@ fn change_controller_works() {
 	})
 }
 
+#[test]
+fn set_stash_as_controller_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(Staking::bonded(&11), Some(10));
+		let ledger = Staking::ledger(&10).unwrap();
+
+		// Only a stash can be its own controller.
+		assert_noop!(Staking::set_stash_as_controller(Origin::signed(10)), Error::<Test>::NotStash);
+
+		assert_ok!(Staking::set_stash_as_controller(Origin::signed(11)));
+		assert_eq!(Staking::bonded(&11), Some(11));
+		assert_eq!(Staking::ledger(&11), Some(ledger));
+		assert!(Staking::ledger(&10).is_none());
+
+		// Doing it again is a no-op.
+		assert_ok!(Staking::set_stash_as_controller(Origin::signed(11)));
+		assert_eq!(Staking::bonded(&11), Some(11));
+
+		// The stash now acts as the controller.
+		assert_noop!(
+			Staking::validate(Origin::signed(10), ValidatorPrefs::default()),
+			Error::<Test>::NotController,
+		);
+		assert_ok!(Staking::validate(Origin::signed(11), ValidatorPrefs::default()));
+
+		// A stash controlling another stash cannot become its own controller.
+		assert_ok!(Staking::set_controller(Origin::signed(21), 31));
+		assert_noop!(Staking::set_stash_as_controller(Origin::signed(31)), Error::<Test>::AlreadyPaired);
+	})
+}
+
+#[test]
+fn force_set_stashes_as_controllers_works() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			Staking::force_set_stashes_as_controllers(Origin::signed(11), vec![11]),
+			BadOrigin,
+		);
+		assert_noop!(
+			Staking::force_set_stashes_as_controllers(Origin::ROOT, vec![11; 65]),
+			Error::<Test>::TooManyMigrations,
+		);
+
+		// Unbonded accounts are skipped.
+		assert_ok!(Staking::force_set_stashes_as_controllers(Origin::ROOT, vec![11, 1, 21]));
+		assert_eq!(Staking::bonded(&11), Some(11));
+		assert_eq!(Staking::bonded(&21), Some(21));
+		assert!(Staking::ledger(&10).is_none());
+		assert!(Staking::ledger(&20).is_none());
+		assert_eq!(Staking::ledger(&21).unwrap().stash, 21);
+		assert_eq!(Staking::bonded(&1), None);
+	})
+}
+
 #[test]
 fn rewards_should_work() {
 	// should check that:
@@ -2669,6 +2723,45 @@ fn deferred_slashes_are_deferred() {
 	})
 }
 
+#[test]
+fn pending_slashes_can_be_queried() {
+	ExtBuilder::default().slash_defer_duration(2).build().execute_with(|| {
+		start_era(1);
+		assert!(Staking::pending_slashes().is_empty());
+		assert!(Staking::slashing_spans(&11).is_empty());
+
+		let nominated_value = Staking::stakers(&11).others.iter().find(|o| o.who == 101).unwrap().value;
+		on_offence_now(
+			&[
+				OffenceDetails {
+					offender: (11, Staking::stakers(&11)),
+					reporters: vec![],
+				},
+			],
+			&[Perbill::from_percent(10)],
+		);
+
+		let slashes = Staking::pending_slashes();
+		assert_eq!(slashes.len(), 1);
+		assert_eq!((slashes[0].era, slashes[0].index, slashes[0].apply_at), (1, 0, 4));
+		assert_eq!(slashes[0].validator, 11);
+		assert_eq!(slashes[0].own, 100);
+		assert_eq!(Staking::pending_slashes_of(&101), slashes);
+		assert!(Staking::pending_slashes_of(&21).is_empty());
+		assert_eq!(Staking::unapplied_slash_of(&11), 100);
+		assert_eq!(Staking::unapplied_slash_of(&101), nominated_value / 10);
+
+		let spans = Staking::slashing_spans(&11);
+		assert_eq!(spans.len(), 2);
+		assert_eq!((spans[0].index, spans[0].start, spans[0].length), (1, 2, None));
+		assert_eq!(spans[1].slashed, 100);
+
+		start_era(4);
+		assert!(Staking::pending_slashes().is_empty());
+		assert_eq!(Staking::unapplied_slash_of(&11), 0);
+	})
+}
+
 #[test]
 fn remove_deferred() {
 	ExtBuilder::default().slash_defer_duration(2).build().execute_with(|| {
@@ -2795,6 +2888,90 @@ fn version_initialized() {
 	});
 }
 
+#[test]
+fn exposures_are_recorded_for_the_bonding_duration() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(Staking::is_exposed_in_era(&11, 0));
+		assert!(Staking::is_exposed_in_era(&21, 0));
+		assert!(Staking::is_exposed_in_era(&101, 0));
+		assert!(!Staking::is_exposed_in_era(&41, 0));
+		assert_eq!(Staking::exposure_eras(), Some(vec![0]));
+
+		assert_ok!(Staking::chill(Origin::signed(100)));
+		start_era(1);
+		assert!(Staking::is_exposed_in_era(&11, 1));
+		assert!(!Staking::is_exposed_in_era(&101, 1));
+
+		start_era(4);
+		assert_eq!(Staking::exposure_eras(), Some(vec![1, 2, 3, 4]));
+		// expired exposures are cleared with the unused weight of blocks.
+		assert!(Staking::is_exposed_in_era(&11, 0));
+		Staking::on_idle(1, Weight::max_value());
+		assert!(!Staking::is_exposed_in_era(&11, 0));
+		assert!(Staking::is_exposed_in_era(&11, 1));
+
+		// eras before the recording of exposures are unknown.
+		<Staking as Store>::ExposuresRecordedFrom::put(2);
+		assert_eq!(Staking::exposure_eras(), None);
+	});
+}
+
+#[test]
+fn on_idle_clears_expired_exposures_within_the_weight() {
+	ExtBuilder::default().build().execute_with(|| {
+		let exposed = <Staking as Store>::ErasExposed::iter_prefix(0).count();
+		assert_eq!(exposed, 3);
+
+		start_era(4);
+		assert_eq!(<Staking as Store>::ExpiredExposureEras::get(), vec![0]);
+
+		// a single exposure is cleared, another read would be needed to know the era is cleared.
+		let weight = IDLE_READ_WEIGHT + 2 * IDLE_CLEAR_EXPOSURE_WEIGHT;
+		assert_eq!(Staking::on_idle(1, weight), weight);
+		assert_eq!(<Staking as Store>::ErasExposed::iter_prefix(0).count(), exposed - 1);
+		assert_eq!(<Staking as Store>::ExpiredExposureEras::get(), vec![0]);
+
+		// not enough weight for any exposure.
+		assert_eq!(Staking::on_idle(1, weight - 1), IDLE_READ_WEIGHT);
+		assert_eq!(<Staking as Store>::ErasExposed::iter_prefix(0).count(), exposed - 1);
+
+		assert_eq!(
+			Staking::on_idle(1, Weight::max_value()),
+			IDLE_READ_WEIGHT + exposed as Weight * IDLE_CLEAR_EXPOSURE_WEIGHT,
+		);
+		assert_eq!(<Staking as Store>::ErasExposed::iter_prefix(0).count(), 0);
+		assert!(<Staking as Store>::ExpiredExposureEras::get().is_empty());
+		assert!(Staking::is_exposed_in_era(&11, 1));
+	});
+}
+
+#[test]
+fn on_idle_withdraws_unlocked_funds() {
+	ExtBuilder::default().nominate(false).build().execute_with(|| {
+		assert_ok!(Staking::unbond(Origin::signed(10), 500));
+		assert_eq!(Staking::ledger(&10).unwrap().unlocking, vec![UnlockChunk { value: 500, era: 3 }]);
+
+		start_era(2);
+		Staking::on_idle(1, Weight::max_value());
+		assert_eq!(Staking::ledger(&10).unwrap().unlocking.len(), 1);
+
+		start_era(3);
+		// not enough weight to withdraw.
+		assert_eq!(Staking::on_idle(1, 2 * IDLE_READ_WEIGHT), 2 * IDLE_READ_WEIGHT);
+		assert_eq!(Staking::ledger(&10).unwrap().unlocking.len(), 1);
+
+		assert_eq!(
+			Staking::on_idle(1, Weight::max_value()),
+			2 * IDLE_READ_WEIGHT + IDLE_WITHDRAW_WEIGHT,
+		);
+		let ledger = Staking::ledger(&10).unwrap();
+		assert!(ledger.unlocking.is_empty());
+		assert_eq!(ledger.total, ledger.active);
+		assert_eq!(<Staking as Store>::UnlockingStashes::iter_prefix(3).count(), 0);
+		assert_eq!(<Staking as Store>::NextUnlockingEra::get(), Some(4));
+	});
+}
+
 #[test]
 fn slash_kicks_validators_not_nominators() {
 	ExtBuilder::default().build().execute_with(|| {
@@ -2833,3 +3010,250 @@ fn slash_kicks_validators_not_nominators() {
 		assert!(nominations.submitted_in < last_slash);
 	});
 }
+
+#[test]
+fn inflation_params_take_effect_at_the_next_era() {
+	ExtBuilder::default().build().execute_with(|| {
+		let params = InflationParams {
+			min_inflation: Perbill::zero(),
+			max_inflation: Perbill::zero(),
+			ideal_stake: Perbill::from_percent(50),
+			falloff: Perbill::from_percent(5),
+		};
+		assert_noop!(Staking::set_inflation_params(Origin::signed(10), params), BadOrigin);
+		assert_noop!(
+			Staking::set_inflation_params(
+				Origin::ROOT,
+				InflationParams { falloff: Perbill::zero(), ..params },
+			),
+			Error::<Test>::InvalidInflationParams,
+		);
+		assert_ok!(Staking::set_inflation_params(Origin::ROOT, params));
+		assert_eq!(Staking::inflation_params(), None);
+
+		// The current era is rewarded according to the previous curve.
+		reward_all_elected();
+		let issuance = Balances::total_issuance();
+		start_era(1);
+		assert!(Balances::total_issuance() > issuance);
+		assert_eq!(Staking::inflation_params(), Some(params));
+		assert_eq!(Staking::pending_inflation_params(), None);
+
+		reward_all_elected();
+		let issuance = Balances::total_issuance();
+		start_era(2);
+		assert_eq!(Balances::total_issuance(), issuance);
+	});
+}
+
+#[test]
+fn era_boundary_work_is_spread_over_blocks() {
+	ExtBuilder::default().era_validators_per_block(1).build().execute_with(|| {
+		reward_all_elected();
+		let issuance = Balances::total_issuance();
+		start_era(1);
+
+		// nothing is paid out nor recorded at the era change.
+		assert_eq!(Balances::total_issuance(), issuance);
+		assert_eq!(Staking::pending_era_payout().unwrap().count, 2);
+		assert_eq!(Staking::pending_exposures().len(), 2);
+		assert!(!Staking::is_exposed_in_era(&11, 1));
+		assert!(!Staking::exposures_recorded_in_era(1));
+		assert!(Staking::exposures_recorded_in_era(0));
+
+		// one validator and its nominator are paid out per block.
+		assert_eq!(Staking::era_work_weight(), 2 * ERA_WORK_READ_WEIGHT + 2 * PAYOUT_WEIGHT);
+		Staking::on_finalize(System::block_number());
+		let partial_issuance = Balances::total_issuance();
+		assert!(partial_issuance > issuance);
+		assert!(Staking::pending_era_payout().is_some());
+		assert!(Staking::pending_reward(0).is_none());
+
+		Staking::on_finalize(System::block_number());
+		assert!(Balances::total_issuance() > partial_issuance);
+		assert!(Staking::pending_era_payout().is_none());
+		assert!(Staking::pending_reward(1).is_none());
+
+		// then the exposures of the new era are recorded.
+		assert_eq!(Staking::era_work_weight(), 3 * ERA_WORK_READ_WEIGHT + 2 * NOTE_EXPOSURE_WEIGHT);
+		Staking::on_finalize(System::block_number());
+		Staking::on_finalize(System::block_number());
+		assert!(Staking::pending_exposures().is_empty());
+		assert!(Staking::exposures_recorded_in_era(1));
+		assert!(Staking::is_exposed_in_era(&11, 1));
+		assert!(Staking::is_exposed_in_era(&101, 1));
+		assert!(!Staking::is_exposed_in_era(&41, 1));
+		assert_eq!(Staking::era_work_weight(), 2 * ERA_WORK_READ_WEIGHT);
+	});
+}
+
+/// Advance the sessions of era 1 until the election of era 2 opens.
+fn open_election() {
+	while !Staking::is_election_open() {
+		assert_eq!(Staking::current_era(), 1);
+		advance_session();
+		Staking::on_finalize(System::block_number());
+	}
+}
+
+/// Run the election being run to its end.
+fn run_election() {
+	while !Staking::election_snapshot().unwrap().elected {
+		Staking::on_finalize(System::block_number());
+	}
+}
+
+#[test]
+fn election_is_run_over_blocks() {
+	let elect = |voters_per_block| {
+		ExtBuilder::default().election_voters_per_block(voters_per_block).build().execute_with(|| {
+			start_era(1);
+			if voters_per_block > 0 {
+				open_election();
+				let voters = <Staking as Store>::Validators::enumerate().count() +
+					<Staking as Store>::Nominators::enumerate().count();
+
+				// one voter is read per block, the first when the election opens.
+				let mut blocks = 1;
+				while Staking::election_snapshot().unwrap().next_nominator.is_some() {
+					assert_eq!(
+						Staking::era_work_weight(),
+						2 * ERA_WORK_READ_WEIGHT + ELECTION_VOTER_WEIGHT,
+					);
+					Staking::on_finalize(System::block_number());
+					blocks += 1;
+				}
+				assert_eq!(blocks, voters);
+				let progress = Staking::election_snapshot().unwrap();
+				assert_eq!(progress.chunks as usize, voters);
+				assert_eq!(progress.voters as usize, voters);
+
+				// then the election is set up.
+				assert_eq!(
+					Staking::era_work_weight(),
+					2 * ERA_WORK_READ_WEIGHT + voters as Weight * ELECTION_VOTER_WEIGHT,
+				);
+				Staking::on_finalize(System::block_number());
+
+				// and a validator is elected per block.
+				let mut rounds = 0;
+				while !Staking::election_snapshot().unwrap().elected {
+					assert_eq!(
+						Staking::era_work_weight(),
+						2 * ERA_WORK_READ_WEIGHT + 3 * progress.edges as Weight * ELECTION_EDGE_WEIGHT,
+					);
+					Staking::on_finalize(System::block_number());
+					rounds += 1;
+				}
+				assert_eq!(rounds, Staking::validator_count());
+				assert_eq!(Staking::era_work_weight(), 2 * ERA_WORK_READ_WEIGHT);
+
+				// the stakers can't change until the era changes.
+				assert_noop!(
+					Staking::nominate(Origin::signed(100), vec![21]),
+					Error::<Test>::ElectionSnapshotInProgress,
+				);
+				assert_noop!(Staking::chill(Origin::signed(10)), Error::<Test>::ElectionSnapshotInProgress);
+				assert_noop!(Staking::unbond(Origin::signed(10), 1), Error::<Test>::ElectionSnapshotInProgress);
+			}
+
+			start_era(2);
+			assert!(!Staking::is_election_open());
+			assert_eq!(<Staking as Store>::SnapshotVoters::get(0), vec![]);
+			assert!(<Staking as Store>::RunningElection::get().is_none());
+			assert_ok!(Staking::nominate(Origin::signed(100), vec![21]));
+
+			let elected = Staking::current_elected();
+			let exposures = elected.iter().map(Staking::stakers).collect::<Vec<_>>();
+			(elected, exposures)
+		})
+	};
+
+	// the election run over blocks elects the same stakers.
+	assert_eq!(elect(1), elect(0));
+}
+
+#[test]
+fn election_opens_in_the_last_blocks_of_the_era() {
+	ExtBuilder::default()
+		.election_voters_per_block(1)
+		.election_lookahead(0)
+		.build()
+		.execute_with(|| {
+			start_era(1);
+
+			// sessions last a block, the election doesn't open with no lookahead.
+			while Staking::current_era() == 1 {
+				assert!(!Staking::is_election_open());
+				assert_ok!(Staking::nominate(Origin::signed(100), vec![11, 21]));
+				advance_session();
+				Staking::on_finalize(System::block_number());
+			}
+
+			// it is run when the era changes instead.
+			assert!(!Staking::is_election_open());
+			assert_eq_uvec!(Staking::current_elected(), vec![11, 21]);
+		});
+}
+
+#[test]
+fn voters_removed_while_the_snapshot_is_taken_are_left_out() {
+	ExtBuilder::default().election_voters_per_block(1).build().execute_with(|| {
+		start_era(1);
+		open_election();
+		// validators are read from the latest one.
+		assert_eq!(Staking::election_snapshot().unwrap().chunks, 1);
+		assert_eq!(Staking::election_snapshot().unwrap().next_validator, Some(21));
+
+		// the next voter to read is chilled for an offence.
+		on_offence_now(
+			&[OffenceDetails { offender: (21, Staking::stakers(&21)), reporters: vec![] }],
+			&[Perbill::from_percent(10)],
+		);
+		assert!(!<Staking as Store>::Validators::exists(&21));
+		let progress = Staking::election_snapshot().unwrap();
+		assert_eq!(progress.next_validator, Some(11));
+		assert_eq!(progress.removed, vec![21]);
+		assert!(Staking::is_election_open());
+
+		// the snapshot goes on with the voter following it.
+		run_election();
+		assert_eq!(Staking::election_snapshot().unwrap().voters, 3);
+
+		start_era(2);
+		assert_eq_uvec!(Staking::current_elected(), vec![11, 31]);
+	});
+}
+
+#[test]
+fn voters_removed_while_the_election_is_run_are_left_out() {
+	ExtBuilder::default().election_voters_per_block(1).build().execute_with(|| {
+		start_era(1);
+		open_election();
+		while Staking::election_snapshot().unwrap().next_nominator.is_some() {
+			Staking::on_finalize(System::block_number());
+		}
+		// the election is set up, and a validator elected.
+		Staking::on_finalize(System::block_number());
+		Staking::on_finalize(System::block_number());
+		assert!(<Staking as Store>::RunningElection::get().is_some());
+
+		// a voter of the snapshot is chilled for an offence.
+		on_offence_now(
+			&[OffenceDetails { offender: (11, Staking::stakers(&11)), reporters: vec![] }],
+			&[Perbill::from_percent(10)],
+		);
+		assert!(!<Staking as Store>::Validators::exists(&11));
+		assert_eq!(Staking::election_snapshot().unwrap().removed, vec![11]);
+		assert!(<Staking as Store>::RunningElection::get().is_none());
+
+		// the election is set up again without it, and run over blocks.
+		Staking::on_finalize(System::block_number());
+		assert!(<Staking as Store>::RunningElection::get().is_some());
+		assert!(!Staking::election_snapshot().unwrap().elected);
+		run_election();
+
+		start_era(2);
+		assert_eq_uvec!(Staking::current_elected(), vec![21, 31]);
+	});
+}
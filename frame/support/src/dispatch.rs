@@ -48,6 +48,37 @@ pub type CallableCallFor<A, T> = <A as Callable<T>>::Call;
 pub trait Parameter: Codec + EncodeLike + Clone + Eq + fmt::Debug {}
 impl<T> Parameter for T where T: Codec + EncodeLike + Clone + Eq + fmt::Debug {}
 
+/// Maximum nesting depth of calls, e.g. of a `sudo` call dispatching a `batch` of calls.
+///
+/// Decoding calls nested deeper fails, so that a malformed transaction can't exhaust the stack
+/// of the node decoding it.
+pub const MAX_CALL_DEPTH: u32 = 256;
+
+environmental::environmental!(call_depth: u32);
+
+/// Decode a call with `decode`, failing if it is nested in `MAX_CALL_DEPTH` other calls.
+#[doc(hidden)]
+pub fn decode_call_with_depth_limit<R>(
+	decode: impl FnOnce() -> result::Result<R, crate::codec::Error>,
+) -> result::Result<R, crate::codec::Error> {
+	let nested = call_depth::with(|depth| if *depth >= MAX_CALL_DEPTH {
+		false
+	} else {
+		*depth += 1;
+		true
+	});
+
+	match nested {
+		None => call_depth::using(&mut 1, decode),
+		Some(true) => {
+			let result = decode();
+			call_depth::with(|depth| *depth -= 1);
+			result
+		},
+		Some(false) => Err("Calls are nested too deep".into()),
+	}
+}
+
 /// Declares a `Module` struct and a `Call` enum, which implements the dispatch logic.
 ///
 /// ## Declaration
@@ -1485,7 +1516,6 @@ macro_rules! impl_outer_dispatch {
 		#[derive(
 			Clone, PartialEq, Eq,
 			$crate::codec::Encode,
-			$crate::codec::Decode,
 			$crate::RuntimeDebug,
 		)]
 		pub enum $call_type {
@@ -1493,6 +1523,25 @@ macro_rules! impl_outer_dispatch {
 				$camelcase ( $crate::dispatch::CallableCallFor<$camelcase, $runtime> )
 			,)*
 		}
+		impl $crate::dispatch::Decode for $call_type {
+			fn decode<I: $crate::dispatch::Input>(
+				input: &mut I,
+			) -> $crate::dispatch::result::Result<Self, $crate::codec::Error> {
+				// Same encoding as the call, decoded without limiting the depth of nested calls.
+				#[derive($crate::codec::Decode)]
+				enum Unlimited {
+					$(
+						$camelcase ( $crate::dispatch::CallableCallFor<$camelcase, $runtime> )
+					,)*
+				}
+
+				$crate::dispatch::decode_call_with_depth_limit(|| {
+					Ok(match <Unlimited as $crate::dispatch::Decode>::decode(input)? {
+						$( Unlimited::$camelcase(call) => $call_type::$camelcase(call), )*
+					})
+				})
+			}
+		}
 		impl $crate::dispatch::GetDispatchInfo for $call_type {
 			fn get_dispatch_info(&self) -> $crate::dispatch::DispatchInfo {
 				match self {
@@ -2084,4 +2133,29 @@ mod tests {
 		assert_eq!(<Test as WeighBlock<u32>>::on_finalize(2), 10);
 		assert_eq!(<Test as WeighBlock<u32>>::on_finalize(3), 0);
 	}
+
+	/// A call nesting another one, like `sudo`.
+	#[derive(Debug, PartialEq, Encode)]
+	struct NestedCall(Option<Box<NestedCall>>);
+
+	impl Decode for NestedCall {
+		fn decode<I: Input>(input: &mut I) -> result::Result<Self, crate::codec::Error> {
+			decode_call_with_depth_limit(|| Ok(NestedCall(Decode::decode(input)?)))
+		}
+	}
+
+	fn nested_call(depth: u32) -> NestedCall {
+		(1..depth).fold(NestedCall(None), |call, _| NestedCall(Some(Box::new(call))))
+	}
+
+	#[test]
+	fn calls_nested_too_deep_fail_to_decode() {
+		let call = nested_call(MAX_CALL_DEPTH);
+		assert_eq!(NestedCall::decode(&mut &call.encode()[..]).ok(), Some(call));
+		assert!(NestedCall::decode(&mut &nested_call(MAX_CALL_DEPTH + 1).encode()[..]).is_err());
+
+		// The depth is counted again from the start for the next call.
+		let call = nested_call(2);
+		assert_eq!(NestedCall::decode(&mut &call.encode()[..]).ok(), Some(call));
+	}
 }
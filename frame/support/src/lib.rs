@@ -429,6 +429,21 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn linked_map_enumeration_from_key_should_work() {
+		new_test_ext().execute_with(|| {
+			Map::insert(16u32, 43u64);
+			Map::insert(17u32, 44u64);
+			assert_eq!(Map::enumerate_from(17).collect::<Vec<_>>(), vec![(17, 44), (16, 43), (15, 42)]);
+			assert_eq!(Map::enumerate_from(16).collect::<Vec<_>>(), vec![(16, 43), (15, 42)]);
+			assert_eq!(Map::enumerate_from(15).collect::<Vec<_>>(), vec![(15, 42)]);
+
+			Map::remove(&16);
+			assert_eq!(Map::enumerate_from(16).collect::<Vec<_>>(), vec![]);
+			assert_eq!(Map::enumerate_from(17).collect::<Vec<_>>(), vec![(17, 44), (15, 42)]);
+		});
+	}
+
 	#[test]
 	fn double_map_basic_insert_remove_remove_prefix_should_work() {
 		new_test_ext().execute_with(|| {
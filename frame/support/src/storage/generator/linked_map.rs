@@ -414,6 +414,13 @@ where
 		}
 	}
 
+	fn enumerate_from(key: K) -> Self::Enumerator {
+		Enumerator::<_, _, G::KeyFormat> {
+			next: Some(key).filter(|key| Self::exists(key)),
+			_phantom: Default::default(),
+		}
+	}
+
 	fn head() -> Option<K> {
 		read_head::<_, G::KeyFormat>()
 	}
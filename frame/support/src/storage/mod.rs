@@ -26,6 +26,19 @@ pub mod child;
 #[doc(hidden)]
 pub mod generator;
 
+/// Execute `f` in a storage transaction: its changes are kept if it returns `Ok`, and rolled back
+/// if it returns `Err`.
+pub fn transactional<R, E>(f: impl FnOnce() -> Result<R, E>) -> Result<R, E> {
+	sp_io::storage::start_transaction();
+	let result = f();
+	if result.is_ok() {
+		sp_io::storage::commit_transaction();
+	} else {
+		sp_io::storage::rollback_transaction();
+	}
+	result
+}
+
 /// A trait for working with macro-generated storage values under the substrate storage API.
 ///
 /// Details on implementation can be found at
@@ -222,6 +235,11 @@ pub trait StorageLinkedMap<K: FullCodec, V: FullCodec> {
 	/// Enumerate all elements in the map.
 	fn enumerate() -> Self::Enumerator;
 
+	/// Enumerate the elements in the map from the one under `key`, in the order of `enumerate`.
+	///
+	/// Nothing is enumerated if `key` is not in the map.
+	fn enumerate_from(key: K) -> Self::Enumerator;
+
 	/// Read the length of the value in a fast way, without decoding the entire value.
 	///
 	/// `T` is required to implement `Codec::DecodeLength`.
@@ -477,7 +495,30 @@ pub trait StoragePrefixedMap<Value: FullCodec> {
 mod test {
 	use sp_core::hashing::twox_128;
 	use sp_io::TestExternalities;
-	use crate::storage::{unhashed, StoragePrefixedMap};
+	use crate::storage::{unhashed, transactional, StoragePrefixedMap};
+
+	#[test]
+	fn transactional_keeps_changes_only_on_success() {
+		TestExternalities::default().execute_with(|| {
+			unhashed::put(b"a", &1u32);
+
+			assert_eq!(transactional(|| { unhashed::put(b"a", &2u32); Err::<(), _>(()) }), Err(()));
+			assert_eq!(unhashed::get(b"a"), Some(1u32));
+
+			assert_eq!(transactional(|| { unhashed::put(b"a", &3u32); Ok::<_, ()>(()) }), Ok(()));
+			assert_eq!(unhashed::get(b"a"), Some(3u32));
+
+			// a failed nested transaction only rolls back its own changes.
+			let result = transactional(|| {
+				unhashed::put(b"b", &4u32);
+				let _ = transactional(|| { unhashed::kill(b"a"); Err::<(), _>(()) });
+				Ok::<_, ()>(())
+			});
+			assert_eq!(result, Ok(()));
+			assert_eq!(unhashed::get(b"a"), Some(3u32));
+			assert_eq!(unhashed::get(b"b"), Some(4u32));
+		});
+	}
 
 	#[test]
 	fn prefixed_map_works() {
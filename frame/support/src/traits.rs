@@ -102,6 +102,19 @@ impl<A> FindAuthor<A> for () {
 	}
 }
 
+/// Something that can estimate at which block the next session starts, e.g. the same logic as
+/// the one deciding when the session module ends sessions.
+pub trait EstimateNextNewSession<BlockNumber> {
+	/// The block at which the next session is estimated to start, `None` if unknown.
+	fn estimate_next_new_session(now: BlockNumber) -> Option<BlockNumber>;
+}
+
+impl<BlockNumber> EstimateNextNewSession<BlockNumber> for () {
+	fn estimate_next_new_session(_: BlockNumber) -> Option<BlockNumber> {
+		None
+	}
+}
+
 /// A trait for verifying the seal of a header and returning the author.
 pub trait VerifySeal<Header, Author> {
 	/// Verify a header and return the author, if any.
@@ -147,6 +160,14 @@ pub trait OnUnbalanced<Imbalance: TryDrop> {
 		amount.try_drop().unwrap_or_else(Self::on_nonzero_unbalanced)
 	}
 
+	/// Handler for several imbalances, whose meaning depends on the context (e.g. the fee and then
+	/// the tip of a transaction). By default they are merged and handled as one. Infallible.
+	fn on_unbalanceds<B>(amounts: impl Iterator<Item=Imbalance>) where
+		Imbalance: crate::traits::Imbalance<B>,
+	{
+		Self::on_unbalanced(amounts.fold(Imbalance::zero(), |acc, x| acc.merge(x)))
+	}
+
 	/// Actually handle a non-zero imbalance. You probably want to implement this rather than
 	/// `on_unbalanced`.
 	fn on_nonzero_unbalanced(amount: Imbalance);
@@ -609,6 +630,76 @@ pub trait LockableCurrency<AccountId>: Currency<AccountId> {
 	);
 }
 
+/// An identifier for a hold. Used for disambiguating different holds so that
+/// they can be individually released or slashed.
+pub type HoldIdentifier = [u8; 8];
+
+/// A currency whose reserved balance can be held for named reasons.
+///
+/// Each hold is accounted for separately, so that releasing or slashing the funds held for one
+/// reason can never touch the funds held for another. Held funds are part of the reserved balance
+/// of an account, but the anonymous operations of `ReservableCurrency` only ever act on the part
+/// of it that is not held.
+pub trait NamedHoldCurrency<AccountId>: ReservableCurrency<AccountId> {
+	/// The amount held from `who` for the reason `id`.
+	fn balance_on_hold(id: HoldIdentifier, who: &AccountId) -> Self::Balance;
+
+	/// Move `value` from the free balance of `who` into the hold `id`, adding to any amount that
+	/// is already held for it.
+	///
+	/// As with `reserve`, the free balance must be able to be withdrawn for the `Reserve` reason.
+	fn hold(id: HoldIdentifier, who: &AccountId, value: Self::Balance) -> DispatchResult;
+
+	/// Move up to `value` from the hold `id` of `who` back into their free balance.
+	///
+	/// Returns the amount that could not be released.
+	fn release(id: HoldIdentifier, who: &AccountId, value: Self::Balance) -> Self::Balance;
+
+	/// Slash up to `value` from the hold `id` of `who`.
+	///
+	/// Returns the negative imbalance created and the amount that could not be slashed.
+	fn slash_held(
+		id: HoldIdentifier,
+		who: &AccountId,
+		value: Self::Balance,
+	) -> (Self::NegativeImbalance, Self::Balance);
+
+	/// Move up to `value` from the hold `id` of `slashed` into the free balance of `beneficiary`.
+	///
+	/// Returns the amount that could not be moved, or an error if `beneficiary` does not exist.
+	fn repatriate_held(
+		id: HoldIdentifier,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		value: Self::Balance,
+	) -> result::Result<Self::Balance, DispatchError>;
+}
+
+/// An identifier for a freeze.
+pub type FreezeIdentifier = [u8; 8];
+
+/// A currency whose free balance can be frozen for named reasons.
+///
+/// Unlike a lock, a freeze has no expiry and applies to every kind of withdrawal. Freezes overlap:
+/// the amount frozen in an account is the largest of its freezes, not their sum.
+pub trait FreezableCurrency<AccountId>: Currency<AccountId> {
+	/// The amount of the balance of `who` frozen for the reason `id`.
+	fn balance_frozen(id: FreezeIdentifier, who: &AccountId) -> Self::Balance;
+
+	/// Freeze `amount` of the balance of `who` for the reason `id`, replacing any previous freeze
+	/// with the same `id`. A zero `amount` thaws it.
+	///
+	/// Note that you can freeze more funds than a user has.
+	fn set_freeze(id: FreezeIdentifier, who: &AccountId, amount: Self::Balance);
+
+	/// Freeze at least `amount` of the balance of `who` for the reason `id`, keeping any larger
+	/// freeze with the same `id`.
+	fn extend_freeze(id: FreezeIdentifier, who: &AccountId, amount: Self::Balance);
+
+	/// Remove the freeze `id` of `who`.
+	fn thaw(id: FreezeIdentifier, who: &AccountId);
+}
+
 /// A currency whose accounts can have balances which vest over time.
 pub trait VestingCurrency<AccountId>: Currency<AccountId> {
 	/// The quantity used to denote time; usually just a `BlockNumber`.
@@ -104,6 +104,27 @@ impl<BlockNumber: Copy> WeighBlock<BlockNumber> for SingleModule {
 	}
 }
 
+/// A hook run once the extrinsics of a block are applied, right before `on_finalize`, with the
+/// weight the block left unused. It lets modules do deferrable housekeeping without taking block
+/// space from transactions.
+pub trait OnIdle<BlockNumber> {
+	/// Do some work using at most `remaining_weight`, and return the weight actually consumed.
+	fn on_idle(_n: BlockNumber, _remaining_weight: Weight) -> Weight { Zero::zero() }
+}
+
+#[impl_for_tuples(30)]
+impl<BlockNumber: Copy> OnIdle<BlockNumber> for SingleModule {
+	fn on_idle(n: BlockNumber, remaining_weight: Weight) -> Weight {
+		let mut consumed_weight: Weight = Zero::zero();
+		for_tuples!(
+			#( consumed_weight = consumed_weight.saturating_add(
+				SingleModule::on_idle(n, remaining_weight.saturating_sub(consumed_weight))
+			); )*
+		);
+		consumed_weight
+	}
+}
+
 /// A generalized group of dispatch types. This is only distinguishing normal, user-triggered transactions
 /// (`Normal`) and anything beyond which serves a higher purpose to the system (`Operational`).
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -22,6 +22,9 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use sp_runtime::{DispatchResult, transaction_validity::TransactionValidityError};
+use sp_std::vec::Vec;
+
 sp_api::decl_runtime_apis! {
 	/// The API to query account nonce (aka transaction index).
 	pub trait AccountNonceApi<AccountId, Index> where
@@ -31,4 +34,27 @@ sp_api::decl_runtime_apis! {
 		/// Get current account nonce of given `AccountId`.
 		fn account_nonce(account: AccountId) -> Index;
 	}
+
+	/// The API to query the parameters of mortal transactions.
+	pub trait MortalityApi {
+		/// Get the longest era period that transactions can be created with.
+		///
+		/// A mortal transaction is only valid as long as the hash of the block it was
+		/// created at is kept by the runtime, so the period depends on the block hash count.
+		fn era_period() -> u64;
+	}
+
+	/// The API to preview the effect of dispatching calls.
+	pub trait CallSimulationApi<AccountId, Call> where
+		AccountId: codec::Codec,
+		Call: codec::Codec,
+	{
+		/// Dispatch the calls one after the other, signed by the given account, and return the
+		/// result of each dispatch.
+		///
+		/// No fees are charged. The calls must fit together in the weight of a block, otherwise
+		/// none is dispatched and `ExhaustsResources` is returned. The events deposited before the
+		/// first call are cleared, so that only the events deposited by the calls are left in storage.
+		fn simulate_calls(who: AccountId, calls: Vec<Call>) -> Result<Vec<DispatchResult>, TransactionValidityError>;
+	}
 }
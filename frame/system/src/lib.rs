@@ -114,7 +114,7 @@ use sp_core::storage::well_known_keys;
 use frame_support::{
 	decl_module, decl_event, decl_storage, decl_error, storage, Parameter,
 	traits::{Contains, Get, ModuleToIndex, OnReapAccount},
-	weights::{Weight, DispatchInfo, DispatchClass, SimpleDispatchInfo},
+	weights::{Weight, DispatchInfo, DispatchClass, SimpleDispatchInfo, FunctionOf},
 };
 use codec::{Encode, Decode};
 
@@ -267,7 +267,18 @@ decl_module! {
 		}
 
 		/// Set some items of storage.
-		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		///
+		/// The dispatch origin for this call must be _Root_.
+		///
+		/// # <weight>
+		/// - O(I) where I is the number of items.
+		/// - One storage write per item.
+		/// # </weight>
+		#[weight = FunctionOf(
+			|args: (&Vec<KeyValue>,)| (args.0.len() as Weight).saturating_mul(10_000).max(10_000),
+			DispatchClass::Operational,
+			true,
+		)]
 		fn set_storage(origin, items: Vec<KeyValue>) {
 			ensure_root(origin)?;
 			for i in &items {
@@ -284,11 +295,25 @@ decl_module! {
 			}
 		}
 
-		/// Kill all storage items with a key that starts with the given prefix.
-		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
-		fn kill_prefix(origin, prefix: Key) {
+		/// Kill at most `subkeys` storage items with a key that starts with the given prefix.
+		///
+		/// The limit keeps the weight of the call, and the number of storage changes notified to
+		/// the subscribers of the block, bounded. The call can be repeated until no item is left.
+		///
+		/// The dispatch origin for this call must be _Root_.
+		///
+		/// # <weight>
+		/// - O(S) where S is `subkeys`.
+		/// - At most `subkeys` storage reads and removals.
+		/// # </weight>
+		#[weight = FunctionOf(
+			|args: (&Key, &u32)| (*args.1).saturating_mul(10_000).max(10_000),
+			DispatchClass::Operational,
+			true,
+		)]
+		fn kill_prefix(origin, prefix: Key, subkeys: u32) {
 			ensure_root(origin)?;
-			storage::unhashed::kill_prefix(&prefix);
+			Self::kill_prefix_limited(&prefix, subkeys);
 		}
 	}
 }
@@ -378,6 +403,9 @@ decl_storage! {
 		ExtrinsicCount: Option<u32>;
 		/// Total weight for all extrinsics put together, for the current block.
 		AllExtrinsicsWeight: Option<Weight>;
+		/// Weight reserved by the `on_initialize` and `on_finalize` hooks of the modules, for the
+		/// current block. Included in `AllExtrinsicsWeight`.
+		HooksWeight: Option<Weight>;
 		/// Total length (in bytes) for all extrinsics put together, for the current block.
 		AllExtrinsicsLen: Option<u32>;
 		/// Map of block numbers to block hashes.
@@ -621,6 +649,19 @@ impl<T: Trait> Module<T> {
 		AllExtrinsicsWeight::get().unwrap_or_default()
 	}
 
+	/// Gets the weight reserved by the hooks of the modules in the current block.
+	pub fn hooks_weight() -> Weight {
+		HooksWeight::get().unwrap_or_default()
+	}
+
+	/// The weight the extrinsics of `class` may bring the block to.
+	pub fn weight_limit(class: DispatchClass) -> Weight {
+		match class {
+			DispatchClass::Operational => T::MaximumBlockWeight::get(),
+			DispatchClass::Normal => T::AvailableBlockRatio::get() * T::MaximumBlockWeight::get(),
+		}
+	}
+
 	pub fn all_extrinsics_len() -> u32 {
 		AllExtrinsicsLen::get().unwrap_or_default()
 	}
@@ -639,7 +680,8 @@ impl<T: Trait> Module<T> {
 	/// Even more dangerous is to note that this function does NOT take any action, if the new sum
 	/// of block weight is more than the block weight limit. This is what the _unchecked_.
 	///
-	/// Another potential use-case could be for the `on_initialise` and `on_finalize` hooks.
+	/// The weight of the `on_initialize` and `on_finalize` hooks is registered with
+	/// [`register_hooks_weight`](#method.register_hooks_weight) instead.
 	///
 	/// If no previous weight exists, the function initializes the weight to zero.
 	pub fn register_extra_weight_unchecked(weight: Weight) {
@@ -648,6 +690,18 @@ impl<T: Trait> Module<T> {
 		AllExtrinsicsWeight::put(next_weight);
 	}
 
+	/// Reserve the weight of the `on_initialize` and `on_finalize` hooks of the modules in the
+	/// current block, before any extrinsic is applied.
+	///
+	/// Like [`register_extra_weight_unchecked`](#method.register_extra_weight_unchecked), the
+	/// weight is accounted even if it exceeds the block weight limit. It is also noted
+	/// separately, so that block authors know how much of the block the hooks take.
+	pub fn register_hooks_weight(weight: Weight) {
+		Self::register_extra_weight_unchecked(weight);
+		let hooks_weight = HooksWeight::get().unwrap_or_default().saturating_add(weight);
+		HooksWeight::put(hooks_weight.min(T::MaximumBlockWeight::get()));
+	}
+
 	/// Start the execution of a particular block.
 	pub fn initialize(
 		number: &T::BlockNumber,
@@ -665,16 +719,22 @@ impl<T: Trait> Module<T> {
 		<ExtrinsicsRoot<T>>::put(txs_root);
 
 		if let InitKind::Full = kind {
-			<Events<T>>::kill();
-			EventCount::kill();
-			<EventTopics<T>>::remove_all();
+			Self::reset_events();
 		}
 	}
 
+	/// Remove all the events deposited so far in the block.
+	pub fn reset_events() {
+		<Events<T>>::kill();
+		EventCount::kill();
+		<EventTopics<T>>::remove_all();
+	}
+
 	/// Remove temporary "environment" entries in storage.
 	pub fn finalize() -> T::Header {
 		ExtrinsicCount::kill();
 		AllExtrinsicsWeight::kill();
+		HooksWeight::kill();
 		AllExtrinsicsLen::kill();
 
 		let number = <Number<T>>::take();
@@ -812,6 +872,24 @@ impl<T: Trait> Module<T> {
 		let xts_root = extrinsics_data_root::<T::Hashing>(extrinsics);
 		<ExtrinsicsRoot<T>>::put(xts_root);
 	}
+
+	/// Remove at most `limit` storage items with a key starting with `prefix`, in key order.
+	/// Returns the number of items removed.
+	pub fn kill_prefix_limited(prefix: &[u8], limit: u32) -> u32 {
+		let mut removed = 0;
+		let mut next = Some(prefix.to_vec())
+			.filter(|key| storage::unhashed::exists(key))
+			.or_else(|| sp_io::storage::next_key(prefix));
+		while let Some(key) = next.filter(|key| key.starts_with(prefix)) {
+			if removed == limit {
+				break
+			}
+			next = sp_io::storage::next_key(&key);
+			storage::unhashed::kill(&key);
+			removed += 1;
+		}
+		removed
+	}
 }
 
 impl<T: Trait> OnReapAccount<T::AccountId> for Module<T> {
@@ -843,8 +921,7 @@ impl<T: Trait + Send + Sync> CheckWeight<T> {
 		info: <Self as SignedExtension>::DispatchInfo,
 	) -> Result<Weight, TransactionValidityError> {
 		let current_weight = Module::<T>::all_extrinsics_weight();
-		let maximum_weight = T::MaximumBlockWeight::get();
-		let limit = Self::get_dispatch_limit_ratio(info.class) * maximum_weight;
+		let limit = Module::<T>::weight_limit(info.class);
 		let added_weight = info.weight.min(limit);
 		let next_weight = current_weight.saturating_add(added_weight);
 		if next_weight > limit {
@@ -1175,7 +1252,7 @@ mod tests {
 	use super::*;
 	use sp_core::H256;
 	use sp_runtime::{traits::{BlakeTwo256, IdentityLookup}, testing::Header, DispatchError};
-	use frame_support::{impl_outer_origin, parameter_types};
+	use frame_support::{assert_ok, assert_noop, impl_outer_origin, parameter_types};
 
 	impl_outer_origin! {
 		pub enum Origin for Test where system = super {}
@@ -1562,4 +1639,23 @@ mod tests {
 			assert_eq!(ext.validate(&1, CALL, normal, len).unwrap().longevity, 15);
 		})
 	}
+
+	#[test]
+	fn kill_prefix_is_limited() {
+		new_test_ext().execute_with(|| {
+			for key in &[&b"ab"[..], b"ab1", b"ab2", b"ab3", b"ac"] {
+				storage::unhashed::put_raw(key, b"value");
+			}
+			assert_noop!(System::kill_prefix(Origin::signed(1), b"ab".to_vec(), 1), BadOrigin);
+
+			assert_ok!(System::kill_prefix(Origin::ROOT, b"ab".to_vec(), 2));
+			assert!(!storage::unhashed::exists(b"ab"));
+			assert!(!storage::unhashed::exists(b"ab1"));
+			assert!(storage::unhashed::exists(b"ab2"));
+
+			assert_eq!(System::kill_prefix_limited(b"ab", 10), 2);
+			assert!(!storage::unhashed::exists(b"ab3"));
+			assert!(storage::unhashed::exists(b"ac"));
+		})
+	}
 }
@@ -48,6 +48,7 @@
 //! ### Trait Getters
 //!
 //! * `MinimumPeriod` - Gets the minimum (and advised) period between blocks for the chain.
+//! * `MaxDrift` - Gets the maximum drift a block's timestamp may have from the expected time.
 //!
 //! ## Usage
 //!
@@ -121,6 +122,11 @@ pub trait Trait: frame_system::Trait {
 	/// work with this to determine a sensible block time. e.g. For Aura, it will be double this
 	/// period on default settings.
 	type MinimumPeriod: Get<Self::Moment>;
+
+	/// The maximum amount a block's timestamp may drift from the expected time. A timestamp
+	/// further ahead of the local clock of the checking node is rejected. Consensus modules that
+	/// check timestamps against slots use it as the tolerance around the slot window.
+	type MaxDrift: Get<Self::Moment>;
 }
 
 decl_module! {
@@ -131,6 +137,9 @@ decl_module! {
 		/// period on default settings.
 		const MinimumPeriod: T::Moment = T::MinimumPeriod::get();
 
+		/// The maximum amount a block's timestamp may drift from the expected time.
+		const MaxDrift: T::Moment = T::MaxDrift::get();
+
 		/// Set the current time.
 		///
 		/// This call should be invoked exactly once per block. It will panic at the finalization
@@ -207,7 +216,7 @@ impl<T: Trait> ProvideInherent for Module<T> {
 	}
 
 	fn check_inherent(call: &Self::Call, data: &InherentData) -> result::Result<(), Self::Error> {
-		const MAX_TIMESTAMP_DRIFT_MILLIS: u64 = 30 * 1000;
+		let max_drift = T::MaxDrift::get().saturated_into::<u64>();
 
 		let t: u64 = match call {
 			Call::set(ref t) => t.clone().saturated_into::<u64>(),
@@ -217,7 +226,7 @@ impl<T: Trait> ProvideInherent for Module<T> {
 		let data = extract_inherent_data(data).map_err(|e| InherentError::Other(e))?;
 
 		let minimum = (Self::now() + T::MinimumPeriod::get()).saturated_into::<u64>();
-		if t > data + MAX_TIMESTAMP_DRIFT_MILLIS {
+		if t > data.saturating_add(max_drift) {
 			Err(InherentError::Other("Timestamp too far in future to accept".into()))
 		} else if t < minimum {
 			Err(InherentError::ValidAtTimestamp(minimum))
@@ -277,11 +286,13 @@ mod tests {
 	}
 	parameter_types! {
 		pub const MinimumPeriod: u64 = 5;
+		pub const MaxDrift: u64 = 30;
 	}
 	impl Trait for Test {
 		type Moment = u64;
 		type OnTimestampSet = ();
 		type MinimumPeriod = MinimumPeriod;
+		type MaxDrift = MaxDrift;
 	}
 	type Timestamp = Module<Test>;
 
@@ -315,4 +326,25 @@ mod tests {
 			let _ = Timestamp::dispatch(Call::set(46), Origin::NONE);
 		});
 	}
+
+	#[test]
+	fn check_inherent_respects_max_drift() {
+		let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		TestExternalities::new(t).execute_with(|| {
+			Timestamp::set_timestamp(42);
+
+			let mut data = InherentData::new();
+			data.put_data(INHERENT_IDENTIFIER, &100u64).unwrap();
+
+			assert!(Timestamp::check_inherent(&Call::set(130), &data).is_ok());
+			match Timestamp::check_inherent(&Call::set(131), &data) {
+				Err(InherentError::Other(_)) => {},
+				e => panic!("unexpected result: {:?}", e),
+			}
+			match Timestamp::check_inherent(&Call::set(46), &data) {
+				Err(InherentError::ValidAtTimestamp(47)) => {},
+				e => panic!("unexpected result: {:?}", e),
+			}
+		});
+	}
 }
@@ -23,7 +23,7 @@ use frame_support::weights::{Weight, DispatchClass};
 use codec::{Encode, Codec, Decode};
 #[cfg(feature = "std")]
 use serde::{Serialize, Deserialize};
-use sp_runtime::traits::{UniqueSaturatedInto, SaturatedConversion};
+use sp_runtime::{Fixed64, traits::{UniqueSaturatedInto, SaturatedConversion}};
 
 /// Some information related to a dispatchable that can be queried from the runtime.
 #[derive(Eq, PartialEq, Encode, Decode, Default)]
@@ -73,12 +73,87 @@ impl CappedDispatchInfo {
 	}
 }
 
+/// The parameters the fee of a transaction is computed from, at some block.
+///
+/// The fee of a transaction paying fees is
+/// `base_fee + adjusted(byte_fee * len + weight_fee * min(weight, max_weight)) + tip`,
+/// where `adjusted(fee) = fee + fee * fee_multiplier` and `weight_fee` is the weight fee of the
+/// dispatch class of the transaction.
+#[derive(Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct FeeParameters<Balance> {
+	/// The fee paid by every transaction.
+	pub base_fee: Balance,
+	/// The fee per byte of the encoded transaction.
+	pub byte_fee: Balance,
+	/// The fee of one unit of weight of a normal dispatch. Exact as long as weight is converted
+	/// to fee linearly.
+	pub normal_weight_fee: Balance,
+	/// The fee of one unit of weight of an operational dispatch. Exact as long as weight is
+	/// converted to fee linearly.
+	pub operational_weight_fee: Balance,
+	/// The weight above which transactions don't pay any more weight fee.
+	pub max_weight: Weight,
+	/// The multiplier applied to the length and weight fee of the next block.
+	pub fee_multiplier: Fixed64,
+}
+
+/// A capped version of `FeeParameters`.
+///
+/// The `Balance` is capped (or expanded) to `u64` to avoid serde issues with `u128`, and the
+/// multiplier is given in parts of `Fixed64::accuracy()`, i.e. in billionths.
+#[derive(Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct CappedFeeParameters {
+	/// The fee paid by every transaction.
+	pub base_fee: u64,
+	/// The fee per byte of the encoded transaction.
+	pub byte_fee: u64,
+	/// The fee of one unit of weight of a normal dispatch.
+	pub normal_weight_fee: u64,
+	/// The fee of one unit of weight of an operational dispatch.
+	pub operational_weight_fee: u64,
+	/// The weight above which transactions don't pay any more weight fee.
+	pub max_weight: Weight,
+	/// The multiplier applied to the length and weight fee.
+	pub fee_multiplier: i64,
+}
+
+#[cfg(feature = "std")]
+impl CappedFeeParameters {
+	/// Create a new `CappedFeeParameters` from `FeeParameters`.
+	pub fn new<Balance: UniqueSaturatedInto<u64>>(parameters: FeeParameters<Balance>) -> Self {
+		let FeeParameters {
+			base_fee,
+			byte_fee,
+			normal_weight_fee,
+			operational_weight_fee,
+			max_weight,
+			fee_multiplier,
+		} = parameters;
+
+		Self {
+			base_fee: base_fee.saturated_into(),
+			byte_fee: byte_fee.saturated_into(),
+			normal_weight_fee: normal_weight_fee.saturated_into(),
+			operational_weight_fee: operational_weight_fee.saturated_into(),
+			max_weight,
+			fee_multiplier: fee_multiplier.into_inner(),
+		}
+	}
+}
+
 sp_api::decl_runtime_apis! {
+	#[api_version(2)]
 	pub trait TransactionPaymentApi<Balance, Extrinsic> where
 		Balance: Codec,
 		Extrinsic: Codec,
 	{
 		fn query_info(uxt: Extrinsic, len: u32) -> RuntimeDispatchInfo<Balance>;
+
+		/// Return the parameters the fee of transactions in the next block is computed from.
+		fn fee_parameters() -> FeeParameters<Balance>;
 	}
 }
 
@@ -103,4 +178,21 @@ mod tests {
 		// should not panic
 		serde_json::to_value(&info).unwrap();
 	}
+
+	#[test]
+	fn fee_parameters_should_serialize_properly() {
+		let parameters = FeeParameters {
+			base_fee: 1_000_u128,
+			byte_fee: 10,
+			normal_weight_fee: 1,
+			operational_weight_fee: 2,
+			max_weight: 1_000_000,
+			fee_multiplier: Fixed64::from_rational(1, 2),
+		};
+
+		assert_eq!(
+			serde_json::to_string(&CappedFeeParameters::new(parameters)).unwrap(),
+			r#"{"baseFee":1000,"byteFee":10,"normalWeightFee":1,"operationalWeightFee":2,"maxWeight":1000000,"feeMultiplier":500000000}"#,
+		);
+	}
 }
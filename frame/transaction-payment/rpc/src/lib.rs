@@ -22,9 +22,9 @@ use sp_blockchain::HeaderBackend;
 use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
 use jsonrpc_derive::rpc;
 use sp_runtime::{generic::BlockId, traits::{Block as BlockT, UniqueSaturatedInto}};
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_core::Bytes;
-use pallet_transaction_payment_rpc_runtime_api::CappedDispatchInfo;
+use pallet_transaction_payment_rpc_runtime_api::{CappedDispatchInfo, CappedFeeParameters};
 pub use pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi as TransactionPaymentRuntimeApi;
 pub use self::gen_client::Client as TransactionPaymentClient;
 
@@ -36,6 +36,11 @@ pub trait TransactionPaymentApi<BlockHash, Balance> {
 		encoded_xt: Bytes,
 		at: Option<BlockHash>
 	) -> Result<CappedDispatchInfo>;
+
+	/// Returns the parameters the fee of transactions is computed from at the given block, or
+	/// the best block, so fees can be estimated offline.
+	#[rpc(name = "payment_feeParameters")]
+	fn fee_parameters(&self, at: Option<BlockHash>) -> Result<CappedFeeParameters>;
 }
 
 /// A struct that implements the [`TransactionPaymentApi`].
@@ -57,6 +62,8 @@ pub enum Error {
 	DecodeError,
 	/// The call to runtime failed.
 	RuntimeError,
+	/// The runtime at the given block does not implement the api.
+	UnsupportedApi,
 }
 
 impl From<Error> for i64 {
@@ -64,6 +71,7 @@ impl From<Error> for i64 {
 		match e {
 			Error::RuntimeError => 1,
 			Error::DecodeError => 2,
+			Error::UnsupportedApi => 3,
 		}
 	}
 }
@@ -88,6 +96,22 @@ where
 			self.client.info().best_hash
 		));
 
+		// Blocks from before the transaction payment module was introduced can't be queried.
+		let has_api = api.has_api_with::<
+			dyn TransactionPaymentRuntimeApi<Block, Balance, Extrinsic, Error = ()>, _
+		>(&at, |_| true).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(Error::RuntimeError.into()),
+			message: "Unable to query dispatch info.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})?;
+		if !has_api {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::UnsupportedApi.into()),
+				message: "Transaction payment api is not available at the given block.".into(),
+				data: None,
+			})
+		}
+
 		let encoded_len = encoded_xt.len() as u32;
 
 		let uxt: Extrinsic = Decode::decode(&mut &*encoded_xt).map_err(|e| RpcError {
@@ -101,4 +125,30 @@ where
 			data: Some(format!("{:?}", e).into()),
 		}).map(CappedDispatchInfo::new)
 	}
+
+	fn fee_parameters(&self, at: Option<<Block as BlockT>::Hash>) -> Result<CappedFeeParameters> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let has_api = api.has_api_with::<
+			dyn TransactionPaymentRuntimeApi<Block, Balance, Extrinsic, Error = ()>, _
+		>(&at, |version| version >= 2).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(Error::RuntimeError.into()),
+			message: "Unable to query fee parameters.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})?;
+		if !has_api {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::UnsupportedApi.into()),
+				message: "Fee parameters are not available at the given block.".into(),
+				data: None,
+			})
+		}
+
+		api.fee_parameters(&at).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(Error::RuntimeError.into()),
+			message: "Unable to query fee parameters.".into(),
+			data: Some(format!("{:?}", e).into()),
+		}).map(CappedFeeParameters::new)
+	}
 }
@@ -24,7 +24,8 @@
 //!     chance to be included by the transaction queue.
 //!
 //! Additionally, this module allows one to configure:
-//!   - The mapping between one unit of weight to one unit of fee via [`WeightToFee`].
+//!   - The mapping between one unit of weight to one unit of fee via [`WeightToFee`], or
+//!     [`OperationalWeightToFee`] for operational dispatches.
 //!   - A means of updating the fee for the next block, via defining a multiplier, based on the
 //!     final state of the chain at the end of the previous block. This can be configured via
 //!     [`FeeMultiplierUpdate`]
@@ -35,8 +36,8 @@ use sp_std::prelude::*;
 use codec::{Encode, Decode};
 use frame_support::{
 	decl_storage, decl_module,
-	traits::{Currency, Get, OnUnbalanced, ExistenceRequirement, WithdrawReason},
-	weights::{Weight, DispatchInfo, GetDispatchInfo},
+	traits::{Currency, Get, OnUnbalanced, ExistenceRequirement, WithdrawReason, Imbalance},
+	weights::{Weight, DispatchClass, DispatchInfo, GetDispatchInfo},
 };
 use sp_runtime::{
 	Fixed64,
@@ -46,7 +47,7 @@ use sp_runtime::{
 	},
 	traits::{Zero, Saturating, SignedExtension, SaturatedConversion, Convert},
 };
-use pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
+use pallet_transaction_payment_rpc_runtime_api::{FeeParameters, RuntimeDispatchInfo};
 
 type Multiplier = Fixed64;
 type BalanceOf<T> =
@@ -58,7 +59,8 @@ pub trait Trait: frame_system::Trait {
 	/// The currency type in which fees will be paid.
 	type Currency: Currency<Self::AccountId> + Send + Sync;
 
-	/// Handler for the unbalanced reduction when taking transaction fees.
+	/// Handler for the unbalanced reduction when taking transaction fees. It is given the fee
+	/// and then the tip through `on_unbalanceds`.
 	type OnTransactionPayment: OnUnbalanced<NegativeImbalanceOf<Self>>;
 
 	/// The fee to be paid for making a transaction; the base.
@@ -70,6 +72,10 @@ pub trait Trait: frame_system::Trait {
 	/// Convert a weight value into a deductible fee based on the currency type.
 	type WeightToFee: Convert<Weight, BalanceOf<Self>>;
 
+	/// Convert the weight of an operational dispatch into a deductible fee. `WeightToFee` is used
+	/// for normal dispatches.
+	type OperationalWeightToFee: Convert<Weight, BalanceOf<Self>>;
+
 	/// Update the multiplier of the next block, based on the previous block's weight.
 	type FeeMultiplierUpdate: Convert<Multiplier, Multiplier>;
 }
@@ -124,17 +130,18 @@ impl<T: Trait> Module<T> {
 
 		RuntimeDispatchInfo { weight, class, partial_fee }
 	}
-}
 
-/// Require the transactor pay for themselves and maybe include a tip to gain additional priority
-/// in the queue.
-#[derive(Encode, Decode, Clone, Eq, PartialEq)]
-pub struct ChargeTransactionPayment<T: Trait + Send + Sync>(#[codec(compact)] BalanceOf<T>);
-
-impl<T: Trait + Send + Sync> ChargeTransactionPayment<T> {
-	/// utility constructor. Used only in client/factory code.
-	pub fn from(fee: BalanceOf<T>) -> Self {
-		Self(fee)
+	/// The parameters `compute_fee` uses for transactions in the next block, so fees can be
+	/// computed off-chain without a call per transaction.
+	pub fn fee_parameters() -> FeeParameters<BalanceOf<T>> {
+		FeeParameters {
+			base_fee: T::TransactionBaseFee::get(),
+			byte_fee: T::TransactionByteFee::get(),
+			normal_weight_fee: T::WeightToFee::convert(1),
+			operational_weight_fee: T::OperationalWeightToFee::convert(1),
+			max_weight: <T as frame_system::Trait>::MaximumBlockWeight::get(),
+			fee_multiplier: NextFeeMultiplier::get(),
+		}
 	}
 
 	/// Compute the final fee value for a particular transaction.
@@ -144,21 +151,18 @@ impl<T: Trait + Send + Sync> ChargeTransactionPayment<T> {
 	///   - _len_fee_: This is the amount paid merely to pay for size of the transaction.
 	///   - _weight_fee_: This amount is computed based on the weight of the transaction. Unlike
 	///      size-fee, this is not input dependent and reflects the _complexity_ of the execution
-	///      and the time it consumes.
+	///      and the time it consumes. Each dispatch class has its own weight to fee conversion.
 	///   - _targeted_fee_adjustment_: This is a multiplier that can tune the final fee based on
 	///     the congestion of the network.
 	///   - (optional) _tip_: if included in the transaction, it will be added on top. Only signed
 	///      transactions can have a tip.
 	///
 	/// final_fee = base_fee + targeted_fee_adjustment(len_fee + weight_fee) + tip;
-	fn compute_fee(
+	pub fn compute_fee(
 		len: u32,
-		info: <Self as SignedExtension>::DispatchInfo,
+		info: DispatchInfo,
 		tip: BalanceOf<T>,
-	) -> BalanceOf<T>
-	where
-		BalanceOf<T>: Sync + Send,
-	{
+	) -> BalanceOf<T> {
 		if info.pays_fee {
 			let len = <BalanceOf<T>>::from(len);
 			let per_byte = T::TransactionByteFee::get();
@@ -168,7 +172,10 @@ impl<T: Trait + Send + Sync> ChargeTransactionPayment<T> {
 				// cap the weight to the maximum defined in runtime, otherwise it will be the `Bounded`
 				// maximum of its data type, which is not desired.
 				let capped_weight = info.weight.min(<T as frame_system::Trait>::MaximumBlockWeight::get());
-				T::WeightToFee::convert(capped_weight)
+				match info.class {
+					DispatchClass::Normal => T::WeightToFee::convert(capped_weight),
+					DispatchClass::Operational => T::OperationalWeightToFee::convert(capped_weight),
+				}
 			};
 
 			// the adjustable part of the fee
@@ -187,6 +194,32 @@ impl<T: Trait + Send + Sync> ChargeTransactionPayment<T> {
 	}
 }
 
+/// Require the transactor pay for themselves and maybe include a tip to gain additional priority
+/// in the queue.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct ChargeTransactionPayment<T: Trait + Send + Sync>(#[codec(compact)] BalanceOf<T>);
+
+impl<T: Trait + Send + Sync> ChargeTransactionPayment<T> {
+	/// utility constructor. Used only in client/factory code.
+	pub fn from(fee: BalanceOf<T>) -> Self {
+		Self(fee)
+	}
+
+	/// Compute the final fee value for a particular transaction.
+	///
+	/// See [`Module::compute_fee`](./struct.Module.html#method.compute_fee).
+	pub fn compute_fee(
+		len: u32,
+		info: <Self as SignedExtension>::DispatchInfo,
+		tip: BalanceOf<T>,
+	) -> BalanceOf<T>
+	where
+		BalanceOf<T>: Sync + Send,
+	{
+		Module::<T>::compute_fee(len, info, tip)
+	}
+}
+
 impl<T: Trait + Send + Sync> sp_std::fmt::Debug for ChargeTransactionPayment<T> {
 	#[cfg(feature = "std")]
 	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
@@ -233,7 +266,9 @@ impl<T: Trait + Send + Sync> SignedExtension for ChargeTransactionPayment<T>
 				Ok(imbalance) => imbalance,
 				Err(_) => return InvalidTransaction::Payment.into(),
 			};
-			T::OnTransactionPayment::on_unbalanced(imbalance);
+			// the fee is handed over before the tip, so that they can be told apart.
+			let (tip, fee) = imbalance.split(tip);
+			T::OnTransactionPayment::on_unbalanceds(Some(fee).into_iter().chain(Some(tip)));
 		}
 
 		let mut r = ValidTransaction::default();
@@ -260,7 +295,7 @@ mod tests {
 	};
 	use pallet_balances::Call as BalancesCall;
 	use sp_std::cell::RefCell;
-	use pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
+	use pallet_transaction_payment_rpc_runtime_api::{FeeParameters, RuntimeDispatchInfo};
 
 	const CALL: &<Runtime as frame_system::Trait>::Call = &Call::Balances(BalancesCall::transfer(2, 69));
 
@@ -328,6 +363,7 @@ mod tests {
 		static TRANSACTION_BASE_FEE: RefCell<u64> = RefCell::new(0);
 		static TRANSACTION_BYTE_FEE: RefCell<u64> = RefCell::new(1);
 		static WEIGHT_TO_FEE: RefCell<u64> = RefCell::new(1);
+		static OPERATIONAL_WEIGHT_TO_FEE: RefCell<u64> = RefCell::new(1);
 	}
 
 	pub struct TransactionBaseFee;
@@ -347,12 +383,20 @@ mod tests {
 		}
 	}
 
+	pub struct OperationalWeightToFee(u64);
+	impl Convert<Weight, u64> for OperationalWeightToFee {
+		fn convert(t: Weight) -> u64 {
+			OPERATIONAL_WEIGHT_TO_FEE.with(|v| *v.borrow() * (t as u64))
+		}
+	}
+
 	impl Trait for Runtime {
 		type Currency = pallet_balances::Module<Runtime>;
 		type OnTransactionPayment = ();
 		type TransactionBaseFee = TransactionBaseFee;
 		type TransactionByteFee = TransactionByteFee;
 		type WeightToFee = WeightToFee;
+		type OperationalWeightToFee = OperationalWeightToFee;
 		type FeeMultiplierUpdate = ();
 	}
 
@@ -364,7 +408,8 @@ mod tests {
 		balance_factor: u64,
 		base_fee: u64,
 		byte_fee: u64,
-		weight_to_fee: u64
+		weight_to_fee: u64,
+		operational_weight_to_fee: u64,
 	}
 
 	impl Default for ExtBuilder {
@@ -374,6 +419,7 @@ mod tests {
 				base_fee: 0,
 				byte_fee: 1,
 				weight_to_fee: 1,
+				operational_weight_to_fee: 1,
 			}
 		}
 	}
@@ -383,6 +429,11 @@ mod tests {
 			self.base_fee = base;
 			self.byte_fee = byte;
 			self.weight_to_fee = weight;
+			self.operational_weight_to_fee = weight;
+			self
+		}
+		pub fn operational_weight_fee(mut self, weight: u64) -> Self {
+			self.operational_weight_to_fee = weight;
 			self
 		}
 		pub fn balance_factor(mut self, factor: u64) -> Self {
@@ -393,6 +444,7 @@ mod tests {
 			TRANSACTION_BASE_FEE.with(|v| *v.borrow_mut() = self.base_fee);
 			TRANSACTION_BYTE_FEE.with(|v| *v.borrow_mut() = self.byte_fee);
 			WEIGHT_TO_FEE.with(|v| *v.borrow_mut() = self.weight_to_fee);
+			OPERATIONAL_WEIGHT_TO_FEE.with(|v| *v.borrow_mut() = self.operational_weight_to_fee);
 		}
 		pub fn build(self) -> sp_io::TestExternalities {
 			self.set_constants();
@@ -558,6 +610,50 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn fee_parameters_reproduce_query_info() {
+		let call = Call::Balances(BalancesCall::transfer(2, 69));
+		let xt = TestXt::new(call, Some((111111, ()))).unwrap();
+		let info = xt.get_dispatch_info();
+		let len = xt.encode().len() as u32;
+		ExtBuilder::default()
+			.fees(5, 1, 2)
+			.build()
+			.execute_with(||
+		{
+			NextFeeMultiplier::put(Fixed64::from_rational(1, 2));
+
+			let parameters = TransactionPayment::fee_parameters();
+			assert_eq!(parameters.fee_multiplier, Fixed64::from_rational(1, 2));
+			let adjustable_fee = parameters.byte_fee * len as u64
+				+ parameters.normal_weight_fee * info.weight.min(parameters.max_weight) as u64;
+			assert_eq!(
+				parameters.base_fee + parameters.fee_multiplier.saturated_multiply_accumulate(adjustable_fee),
+				TransactionPayment::query_info(xt, len).partial_fee,
+			);
+		});
+	}
+
+	#[test]
+	fn fee_parameters_report_the_weight_fee_of_each_class() {
+		ExtBuilder::default()
+			.fees(5, 1, 2)
+			.operational_weight_fee(3)
+			.build()
+			.execute_with(||
+		{
+			let parameters = TransactionPayment::fee_parameters();
+			assert_eq!(parameters.normal_weight_fee, 2);
+			assert_eq!(parameters.operational_weight_fee, 3);
+
+			let normal = DispatchInfo { weight: 100, class: DispatchClass::Normal, pays_fee: true };
+			let operational = DispatchInfo { class: DispatchClass::Operational, ..normal };
+			// 5 base fee, 10 length fee and 100 weight times the coefficient of the class.
+			assert_eq!(ChargeTransactionPayment::<Runtime>::compute_fee(10, normal, 0), 215);
+			assert_eq!(ChargeTransactionPayment::<Runtime>::compute_fee(10, operational, 0), 315);
+		});
+	}
+
 	#[test]
 	fn compute_fee_works_without_multiplier() {
 		ExtBuilder::default()
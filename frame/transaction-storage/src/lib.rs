@@ -0,0 +1,797 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Transaction Storage Module
+//!
+//! - [`transaction_storage::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Transaction Storage module lets the chain serve as a short-term data availability layer.
+//! Accounts pay a fee to store data blobs, which never enter the state: they are indexed into the
+//! offchain storage of the nodes importing the block, and only their content hash, size and the
+//! root of their proof chunks are stored on-chain.
+//!
+//! The content hash of a blob is its BLAKE2b-256 hash. Blobs are at most `MaxTransactionSize`
+//! bytes, which should not exceed the 256 KiB chunks IPFS splits files into: each blob is then
+//! a raw IPFS block, addressed by [`ipfs_cid`](./fn.ipfs_cid.html), and a file chunked by IPFS
+//! is stored with one transaction per chunk.
+//!
+//! Nodes must keep the blobs for `StoragePeriod` blocks. One block before this retention period
+//! ends, a chunk of the blobs of the block is drawn from the randomness of the chain and the
+//! parent block hash, and its storage must be proven in the next block: the offchain worker of
+//! each node keeping the blobs submits an unsigned `check_proof` transaction, and a block
+//! missing the proof due in it is invalid. The blobs are then removed from the offchain storage
+//! and their on-chain information is dropped.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `store` - Store a data blob, paying `EntryFee` plus `ByteFee` for each byte.
+//! * `check_proof` - Prove the storage of the challenged chunk of the blobs of a block.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Encode, Decode};
+use sp_core::offchain::StorageKind;
+use sp_io::hashing::blake2_256;
+use sp_runtime::{
+	RuntimeDebug,
+	traits::{Saturating, CheckedSub, One},
+	transaction_validity::{
+		TransactionValidity, ValidTransaction, InvalidTransaction, TransactionPriority,
+	},
+};
+use frame_support::{
+	decl_module, decl_event, decl_storage, ensure, decl_error, debug,
+	traits::{
+		Currency, OnUnbalanced, Get, Randomness, WithdrawReason, ExistenceRequirement,
+	},
+	weights::{Weight, DispatchClass, SimpleDispatchInfo, FunctionOf},
+};
+use frame_system::{self as system, ensure_signed, ensure_none, offchain::SubmitUnsignedTransaction};
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
+
+/// The size of the chunks the storage of a blob is proven by.
+pub const CHUNK_SIZE: usize = 256;
+
+/// The prefix of the keys the blobs are indexed under in the offchain storage.
+pub const OFFCHAIN_PREFIX: &[u8] = b"transaction_storage";
+
+pub trait Trait: frame_system::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// A dispatchable call type.
+	type Call: From<Call<Self>>;
+
+	/// A transaction submitter for the storage proofs.
+	type SubmitTransaction: SubmitUnsignedTransaction<Self, <Self as Trait>::Call>;
+
+	/// The currency the storage fees are paid in.
+	type Currency: Currency<Self::AccountId>;
+
+	/// What to do with the storage fees.
+	type FeeDestination: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+	/// The fee paid for each blob.
+	type EntryFee: Get<BalanceOf<Self>>;
+
+	/// The fee paid for each byte of a blob.
+	type ByteFee: Get<BalanceOf<Self>>;
+
+	/// The maximum size of a blob.
+	type MaxTransactionSize: Get<u32>;
+
+	/// The maximum number of blobs stored in a block.
+	type MaxBlockTransactions: Get<u32>;
+
+	/// The number of blocks the blobs must be kept for.
+	type StoragePeriod: Get<Self::BlockNumber>;
+
+	/// The source of the chunks to prove.
+	type Randomness: Randomness<Self::Hash>;
+}
+
+/// The on-chain information of a stored blob.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct TransactionInfo {
+	/// The root of the proof chunks of the blob.
+	pub chunk_root: [u8; 32],
+	/// The BLAKE2b-256 hash of the blob.
+	pub content_hash: [u8; 32],
+	/// The size of the blob, in bytes.
+	pub size: u32,
+	/// The number of proof chunks of the blobs of the block, up to this one included.
+	pub block_chunks: u32,
+}
+
+/// The proof of storage of a chunk.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct TransactionStorageProof {
+	/// The chunk, at most `CHUNK_SIZE` bytes.
+	pub chunk: Vec<u8>,
+	/// The hashes of its siblings in the chunk tree of its blob, from the bottom up.
+	pub proof: Vec<[u8; 32]>,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as TransactionStorage {
+		/// The blobs stored in each block, until the end of the storage period.
+		pub Transactions get(fn transactions): map T::BlockNumber => Vec<TransactionInfo>;
+
+		/// The number of proof chunks of the blobs stored in each block.
+		pub ChunkCount get(fn chunk_count): map T::BlockNumber => u32;
+
+		/// The chunk to prove for the blobs of each block, drawn one block before the end of
+		/// the storage period and removed once proven.
+		pub Challenges get(fn challenge): map T::BlockNumber => Option<u32>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where AccountId = <T as frame_system::Trait>::AccountId, BlockNumber = <T as frame_system::Trait>::BlockNumber {
+		/// A blob was stored, at the given index in the block.
+		Stored(AccountId, u32),
+		/// The storage of the blobs of a block must be proven for the given chunk.
+		ProofRequired(BlockNumber, u32),
+		/// The storage of the blobs of a block was proven.
+		ProofChecked(BlockNumber),
+	}
+);
+
+decl_error! {
+	/// Error for the transaction storage module.
+	pub enum Error for Module<T: Trait> {
+		/// The blob is empty.
+		EmptyTransaction,
+		/// The blob exceeds `MaxTransactionSize`.
+		TransactionTooLarge,
+		/// The block already stores `MaxBlockTransactions` blobs.
+		TooManyTransactions,
+		/// The storage of the blobs of the block is not to be proven.
+		NoChallenge,
+		/// The proof does not match the challenged chunk.
+		InvalidProof,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// The fee paid for each blob.
+		const EntryFee: BalanceOf<T> = T::EntryFee::get();
+
+		/// The fee paid for each byte of a blob.
+		const ByteFee: BalanceOf<T> = T::ByteFee::get();
+
+		/// The maximum size of a blob.
+		const MaxTransactionSize: u32 = T::MaxTransactionSize::get();
+
+		/// The number of blocks the blobs must be kept for.
+		const StoragePeriod: T::BlockNumber = T::StoragePeriod::get();
+
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		fn on_initialize(n: T::BlockNumber) {
+			if let Some(target) = Self::proof_target(n.saturating_add(One::one())) {
+				Self::challenge_block(target);
+			}
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			if let Some(target) = Self::proof_target(n) {
+				assert!(
+					!<Challenges<T>>::exists(target),
+					"Storage proof must be checked in the block it is due in"
+				);
+				Self::remove_block(target);
+			}
+		}
+
+		fn offchain_worker(now: T::BlockNumber) {
+			debug::RuntimeLogger::init();
+
+			if let Some(target) = Self::proof_target(now.saturating_add(One::one())) {
+				Self::offchain(target);
+			}
+		}
+
+		/// Store `data` for `StoragePeriod` blocks, paying `EntryFee` plus `ByteFee` for each
+		/// byte. The data is indexed into the offchain storage; only its information enters the
+		/// state.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(D) where D is the length of `data`.
+		/// - One balance operation.
+		/// - Two storage writes.
+		/// - One offchain storage write.
+		/// # </weight>
+		#[weight = FunctionOf(
+			|args: (&Vec<u8>,)| (args.0.len() as Weight).saturating_add(200_000),
+			DispatchClass::Normal,
+			true,
+		)]
+		fn store(origin, data: Vec<u8>) {
+			let who = ensure_signed(origin)?;
+			ensure!(!data.is_empty(), Error::<T>::EmptyTransaction);
+			ensure!(data.len() <= T::MaxTransactionSize::get() as usize, Error::<T>::TransactionTooLarge);
+
+			let block_number = <frame_system::Module<T>>::block_number();
+			let mut transactions = <Transactions<T>>::get(block_number);
+			ensure!(
+				transactions.len() < T::MaxBlockTransactions::get() as usize,
+				Error::<T>::TooManyTransactions,
+			);
+
+			let fee = T::ByteFee::get()
+				.saturating_mul((data.len() as u32).into())
+				.saturating_add(T::EntryFee::get());
+			let imbalance = T::Currency::withdraw(
+				&who,
+				fee,
+				WithdrawReason::Fee.into(),
+				ExistenceRequirement::KeepAlive,
+			)?;
+			T::FeeDestination::on_unbalanced(imbalance);
+
+			let block_chunks = <ChunkCount<T>>::get(block_number) + num_chunks(data.len());
+			transactions.push(TransactionInfo {
+				chunk_root: chunk_root(&data),
+				content_hash: blake2_256(&data),
+				size: data.len() as u32,
+				block_chunks,
+			});
+			let index = transactions.len() as u32 - 1;
+			<Transactions<T>>::insert(block_number, transactions);
+			<ChunkCount<T>>::insert(block_number, block_chunks);
+			sp_io::offchain_index::set(&offchain_key(block_number, index), &data);
+			Self::deposit_event(RawEvent::Stored(who, index));
+		}
+
+		/// Prove the storage of the challenged chunk of the blobs stored at block `target`.
+		///
+		/// The dispatch origin for this call must be _None_.
+		///
+		/// # <weight>
+		/// - O(log C) where C is the number of chunks of the blob.
+		/// - One storage removal.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(100_000)]
+		fn check_proof(origin, target: T::BlockNumber, proof: TransactionStorageProof) {
+			ensure_none(origin)?;
+			ensure!(Self::verify(target, &proof)?, Error::<T>::InvalidProof);
+
+			<Challenges<T>>::remove(target);
+			Self::deposit_event(RawEvent::ProofChecked(target));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The block whose blobs must be proven in block `n`, if any.
+	fn proof_target(n: T::BlockNumber) -> Option<T::BlockNumber> {
+		n.checked_sub(&T::StoragePeriod::get())
+	}
+
+	/// Draw the chunk to prove for the blobs stored at block `target`, if any, from the
+	/// randomness of the chain and the parent block hash.
+	fn challenge_block(target: T::BlockNumber) {
+		let chunks = <ChunkCount<T>>::get(target);
+		if chunks == 0 {
+			return
+		}
+		let random = T::Randomness::random(&(OFFCHAIN_PREFIX, target).encode());
+		let seed = (random, <frame_system::Module<T>>::parent_hash()).encode();
+		let chunk = random_chunk(&seed, chunks);
+		<Challenges<T>>::insert(target, chunk);
+		Self::deposit_event(RawEvent::ProofRequired(target, chunk));
+	}
+
+	/// Remove the blobs stored at block `target` from the offchain storage, and drop their
+	/// information.
+	fn remove_block(target: T::BlockNumber) {
+		for index in 0..<Transactions<T>>::take(target).len() {
+			sp_io::offchain_index::clear(&offchain_key(target, index as u32));
+		}
+		<ChunkCount<T>>::remove(target);
+	}
+
+	/// The blob holding `chunk` among the blobs stored at block `target`, with its index and the
+	/// number of chunks of the blobs before it.
+	fn locate_chunk(target: T::BlockNumber, chunk: u32) -> Option<(TransactionInfo, u32, u32)> {
+		let transactions = Self::transactions(target);
+		let index = transactions.iter().position(|info| info.block_chunks > chunk)?;
+		let first_chunk = index.checked_sub(1).map_or(0, |previous| transactions[previous].block_chunks);
+		Some((transactions[index].clone(), index as u32, first_chunk))
+	}
+
+	/// Whether `proof` proves the challenged chunk of the blobs stored at block `target`.
+	fn verify(target: T::BlockNumber, proof: &TransactionStorageProof) -> Result<bool, Error<T>> {
+		let chunk = Self::challenge(target).ok_or(Error::<T>::NoChallenge)?;
+		let (info, _, first_chunk) = match Self::locate_chunk(target, chunk) {
+			Some(location) => location,
+			None => return Ok(false),
+		};
+		Ok(verify_chunk_proof(
+			&info.chunk_root,
+			info.block_chunks - first_chunk,
+			chunk - first_chunk,
+			proof,
+		))
+	}
+
+	/// Submit the proof of the challenged chunk of the blobs stored at block `target`, if they
+	/// are kept in the offchain storage of this node.
+	pub(crate) fn offchain(target: T::BlockNumber) {
+		let chunk = match Self::challenge(target) {
+			Some(chunk) => chunk,
+			None => return,
+		};
+		let (_, index, first_chunk) = match Self::locate_chunk(target, chunk) {
+			Some(location) => location,
+			None => return,
+		};
+		let data = match sp_io::offchain::local_storage_get(
+			StorageKind::PERSISTENT,
+			&offchain_key(target, index),
+		) {
+			Some(data) => data,
+			None => {
+				debug::native::warn!(
+					target: "transaction-storage",
+					"Blob {:?} of block {:?} is not kept locally",
+					index,
+					target,
+				);
+				return
+			}
+		};
+		let proof = match chunk_proof(&data, chunk - first_chunk) {
+			Some(proof) => proof,
+			None => return,
+		};
+		debug::info!(target: "transaction-storage", "Proving the storage of block {:?}", target);
+		if T::SubmitTransaction::submit_unsigned(Call::check_proof(target, proof)).is_err() {
+			debug::native::warn!(
+				target: "transaction-storage",
+				"Failed to submit the storage proof of block {:?}",
+				target,
+			);
+		}
+	}
+}
+
+#[allow(deprecated)] // Allow ValidateUnsigned, remove the attribute when the trait is removed.
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		if let Call::check_proof(target, proof) = call {
+			match Self::verify(*target, proof) {
+				Ok(true) => {},
+				Ok(false) => return InvalidTransaction::BadProof.into(),
+				Err(_) => return InvalidTransaction::Stale.into(),
+			}
+
+			Ok(ValidTransaction {
+				priority: TransactionPriority::max_value(),
+				requires: vec![],
+				provides: vec![(b"transaction_storage_proof", target).encode()],
+				// the proof is due in the next block.
+				longevity: 1,
+				propagate: true,
+			})
+		} else {
+			InvalidTransaction::Call.into()
+		}
+	}
+}
+
+/// The key the blob at `index` of block `block_number` is indexed under in the offchain storage.
+pub fn offchain_key<BlockNumber: Encode>(block_number: BlockNumber, index: u32) -> Vec<u8> {
+	(OFFCHAIN_PREFIX, block_number, index).encode()
+}
+
+/// The number of proof chunks of a blob of `size` bytes.
+pub fn num_chunks(size: usize) -> u32 {
+	((size + CHUNK_SIZE - 1) / CHUNK_SIZE) as u32
+}
+
+/// The index of the chunk drawn by `seed` out of `chunks`.
+pub fn random_chunk(seed: &[u8], chunks: u32) -> u32 {
+	let hash = blake2_256(seed);
+	u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]) % chunks
+}
+
+/// The CIDv1 of a blob stored as a raw IPFS block, given its content hash.
+pub fn ipfs_cid(content_hash: &[u8; 32]) -> Vec<u8> {
+	// version 1, raw codec, blake2b-256 multihash of 32 bytes.
+	let mut cid = vec![0x01, 0x55, 0xa0, 0xe4, 0x02, 0x20];
+	cid.extend_from_slice(content_hash);
+	cid
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+	let mut pair = [0u8; 64];
+	pair[..32].copy_from_slice(left);
+	pair[32..].copy_from_slice(right);
+	blake2_256(&pair)
+}
+
+/// The layers of the chunk tree of `data`, from the chunk hashes up to the root. A node without
+/// a sibling is moved up as is.
+fn chunk_tree(data: &[u8]) -> Vec<Vec<[u8; 32]>> {
+	let mut layers = vec![data.chunks(CHUNK_SIZE).map(blake2_256).collect::<Vec<_>>()];
+	loop {
+		let layer = &layers[layers.len() - 1];
+		if layer.len() <= 1 {
+			return layers
+		}
+		let next = layer.chunks(2)
+			.map(|pair| if pair.len() == 2 { hash_pair(&pair[0], &pair[1]) } else { pair[0] })
+			.collect();
+		layers.push(next);
+	}
+}
+
+/// The root of the chunk tree of `data`.
+pub fn chunk_root(data: &[u8]) -> [u8; 32] {
+	chunk_tree(data).last().and_then(|root| root.first().cloned()).unwrap_or_default()
+}
+
+/// The proof of storage of the chunk at `index` of `data`, if it exists.
+pub fn chunk_proof(data: &[u8], index: u32) -> Option<TransactionStorageProof> {
+	let chunk = data.chunks(CHUNK_SIZE).nth(index as usize)?.to_vec();
+	let mut index = index as usize;
+	let mut proof = Vec::new();
+	let layers = chunk_tree(data);
+	for layer in &layers[..layers.len() - 1] {
+		if let Some(sibling) = layer.get(index ^ 1) {
+			proof.push(*sibling);
+		}
+		index /= 2;
+	}
+	Some(TransactionStorageProof { chunk, proof })
+}
+
+/// Whether `proof` proves the chunk at `index` of a blob of `chunks` chunks with the given root.
+pub fn verify_chunk_proof(
+	chunk_root: &[u8; 32],
+	chunks: u32,
+	index: u32,
+	proof: &TransactionStorageProof,
+) -> bool {
+	if index >= chunks || proof.chunk.len() > CHUNK_SIZE {
+		return false
+	}
+	let mut hash = blake2_256(&proof.chunk);
+	let mut siblings = proof.proof.iter();
+	let (mut index, mut width) = (index, chunks);
+	while width > 1 {
+		if index % 2 == 1 {
+			match siblings.next() {
+				Some(sibling) => hash = hash_pair(sibling, &hash),
+				None => return false,
+			}
+		} else if index + 1 < width {
+			match siblings.next() {
+				Some(sibling) => hash = hash_pair(&hash, sibling),
+				None => return false,
+			}
+		}
+		index /= 2;
+		width = (width + 1) / 2;
+	}
+	siblings.next().is_none() && &hash == chunk_root
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use frame_support::{
+		assert_ok, assert_noop, impl_outer_origin, impl_outer_dispatch, parameter_types,
+		unsigned::ValidateUnsigned,
+	};
+	use sp_core::{
+		H256,
+		offchain::{
+			OffchainExt, OffchainStorage, TransactionPoolExt,
+			testing::{TestOffchainExt, TestTransactionPoolExt},
+		},
+	};
+	use sp_runtime::{
+		Perbill, testing::{Header, TestXt}, traits::{BlakeTwo256, IdentityLookup, OnInitialize, OnFinalize},
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	impl_outer_dispatch! {
+		pub enum Call for Test where origin: Origin {
+			transaction_storage::TransactionStorage,
+		}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = Call;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u64 = 1;
+		pub const TransferFee: u64 = 0;
+		pub const CreationFee: u64 = 0;
+	}
+	impl pallet_balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnReapAccount = System;
+		type OnNewAccount = ();
+		type Event = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+	}
+	parameter_types! {
+		pub const EntryFee: u64 = 10;
+		pub const ByteFee: u64 = 1;
+		pub const MaxTransactionSize: u32 = 1024;
+		pub const MaxBlockTransactions: u32 = 2;
+		pub const StoragePeriod: u64 = 10;
+	}
+	type Extrinsic = TestXt<Call, ()>;
+	type SubmitTransaction = frame_system::offchain::TransactionSubmitter<(), Call, Extrinsic>;
+	impl Trait for Test {
+		type Event = ();
+		type Call = Call;
+		type SubmitTransaction = SubmitTransaction;
+		type Currency = Balances;
+		type FeeDestination = ();
+		type EntryFee = EntryFee;
+		type ByteFee = ByteFee;
+		type MaxTransactionSize = MaxTransactionSize;
+		type MaxBlockTransactions = MaxBlockTransactions;
+		type StoragePeriod = StoragePeriod;
+		type Randomness = ();
+	}
+	type System = frame_system::Module<Test>;
+	type Balances = pallet_balances::Module<Test>;
+	type TransactionStorage = Module<Test>;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(1, 5000), (2, 100)],
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	fn run_to_block(n: u64) {
+		while System::block_number() < n {
+			TransactionStorage::on_finalize(System::block_number());
+			System::set_block_number(System::block_number() + 1);
+			System::set_parent_hash(H256::repeat_byte(System::block_number() as u8));
+			TransactionStorage::on_initialize(System::block_number());
+		}
+	}
+
+	/// A blob whose chunks all differ.
+	fn blob(size: usize) -> Vec<u8> {
+		(0..size).map(|i| (i * 31 + i / CHUNK_SIZE) as u8).collect()
+	}
+
+	#[test]
+	fn chunk_proofs_work() {
+		for size in &[1, 256, 257, 256 * 5, 256 * 6 + 1] {
+			let data = blob(*size);
+			let root = chunk_root(&data);
+			let chunks = num_chunks(data.len());
+			for index in 0..chunks {
+				let proof = chunk_proof(&data, index).unwrap();
+				assert!(verify_chunk_proof(&root, chunks, index, &proof));
+				assert!(!verify_chunk_proof(&root, chunks, (index + 1) % chunks, &proof) || chunks == 1);
+			}
+			assert!(chunk_proof(&data, chunks).is_none());
+		}
+	}
+
+	#[test]
+	fn store_works() {
+		let mut ext = new_test_ext();
+		let data = blob(600);
+		ext.execute_with(|| {
+			System::set_block_number(1);
+			assert_noop!(TransactionStorage::store(Origin::signed(1), vec![]), Error::<Test>::EmptyTransaction);
+			assert_noop!(
+				TransactionStorage::store(Origin::signed(1), blob(1025)),
+				Error::<Test>::TransactionTooLarge,
+			);
+			assert!(TransactionStorage::store(Origin::signed(2), blob(100)).is_err());
+
+			assert_ok!(TransactionStorage::store(Origin::signed(1), data.clone()));
+			assert_eq!(Balances::free_balance(&1), 5000 - 610);
+			assert_ok!(TransactionStorage::store(Origin::signed(1), blob(10)));
+			assert_noop!(
+				TransactionStorage::store(Origin::signed(1), blob(10)),
+				Error::<Test>::TooManyTransactions,
+			);
+
+			assert_eq!(TransactionStorage::chunk_count(1), 4);
+			assert_eq!(TransactionStorage::transactions(1)[0], TransactionInfo {
+				chunk_root: chunk_root(&data),
+				content_hash: blake2_256(&data),
+				size: 600,
+				block_chunks: 3,
+			});
+			assert_eq!(TransactionStorage::transactions(1)[1].block_chunks, 4);
+		});
+
+		// the blobs are indexed into the offchain storage, not the state.
+		let index = ext.offchain_index();
+		assert_eq!(index.len(), 2);
+		assert_eq!(index.get(&offchain_key(1u64, 0)), Some(&Some(data)));
+		assert_eq!(index.get(&offchain_key(1u64, 1)), Some(&Some(blob(10))));
+	}
+
+	#[test]
+	fn storage_must_be_proven_at_the_end_of_the_storage_period() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			let data = vec![blob(600), blob(300).into_iter().rev().collect::<Vec<_>>()];
+			for d in &data {
+				assert_ok!(TransactionStorage::store(Origin::signed(1), d.clone()));
+			}
+			run_to_block(2);
+			assert_ok!(TransactionStorage::store(Origin::signed(1), blob(10)));
+
+			// the chunk to prove in block 11 is only drawn in block 10.
+			run_to_block(9);
+			assert_eq!(TransactionStorage::challenge(1), None);
+			run_to_block(10);
+			let chunk = TransactionStorage::challenge(1).unwrap();
+			assert!(chunk < 5);
+
+			let (blob_index, blob_chunk) = if chunk < 3 { (0, chunk) } else { (1, chunk - 3) };
+			let proof = chunk_proof(&data[blob_index], blob_chunk).unwrap();
+			let call = crate::Call::check_proof(1, proof.clone());
+			assert!(TransactionStorage::validate_unsigned(&call).is_ok());
+
+			let wrong = chunk_proof(&data[1 - blob_index], 0).unwrap();
+			assert!(TransactionStorage::validate_unsigned(&crate::Call::check_proof(1, wrong.clone())).is_err());
+			assert_noop!(TransactionStorage::check_proof(Origin::NONE, 1, wrong), Error::<Test>::InvalidProof);
+			assert_noop!(
+				TransactionStorage::check_proof(Origin::NONE, 2, proof.clone()),
+				Error::<Test>::NoChallenge,
+			);
+
+			run_to_block(11);
+			assert_ok!(TransactionStorage::check_proof(Origin::NONE, 1, proof));
+			assert_eq!(TransactionStorage::challenge(1), None);
+			assert_eq!(TransactionStorage::transactions(1).len(), 2);
+
+			// the information of the blobs is dropped at the end of the block the proof is due in.
+			run_to_block(12);
+			assert!(TransactionStorage::transactions(1).is_empty());
+			assert_eq!(TransactionStorage::chunk_count(1), 0);
+			assert!(TransactionStorage::challenge(2).is_some());
+		});
+	}
+
+	#[test]
+	#[should_panic(expected = "Storage proof must be checked in the block it is due in")]
+	fn blocks_missing_a_due_proof_are_invalid() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(TransactionStorage::store(Origin::signed(1), blob(10)));
+
+			run_to_block(11);
+			TransactionStorage::on_finalize(11);
+		});
+	}
+
+	#[test]
+	fn offchain_worker_proves_the_indexed_blobs() {
+		let mut ext = new_test_ext();
+		let (offchain, offchain_state) = TestOffchainExt::new();
+		let (pool, pool_state) = TestTransactionPoolExt::new();
+		ext.register_extension(OffchainExt::new(offchain));
+		ext.register_extension(TransactionPoolExt::new(pool));
+
+		ext.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(TransactionStorage::store(Origin::signed(1), blob(600)));
+			assert_ok!(TransactionStorage::store(Origin::signed(1), blob(300)));
+			run_to_block(10);
+
+			// the blobs are not kept locally.
+			TransactionStorage::offchain(1);
+			assert!(pool_state.read().transactions.is_empty());
+		});
+
+		// keep the blobs indexed when importing block 1.
+		for (key, value) in ext.offchain_index() {
+			offchain_state.write().persistent_storage.set(b"", &key, &value.unwrap());
+		}
+
+		ext.execute_with(|| {
+			TransactionStorage::offchain(1);
+			let transactions = pool_state.read().transactions.clone();
+			assert_eq!(transactions.len(), 1);
+			let ex: Extrinsic = Decode::decode(&mut &transactions[0][..]).unwrap();
+			let call = match ex.1 {
+				Call::TransactionStorage(call) => call,
+				e => panic!("Unexpected call: {:?}", e),
+			};
+			assert!(TransactionStorage::validate_unsigned(&call).is_ok());
+
+			run_to_block(11);
+			if let crate::Call::check_proof(target, proof) = call {
+				assert_ok!(TransactionStorage::check_proof(Origin::NONE, target, proof));
+			}
+			run_to_block(12);
+		});
+
+		// the blobs are removed from the offchain storage at the end of the storage period.
+		let index = ext.offchain_index();
+		assert_eq!(index.get(&offchain_key(1u64, 0)), Some(&None));
+		assert_eq!(index.get(&offchain_key(1u64, 1)), Some(&None));
+	}
+
+	#[test]
+	fn ipfs_cid_works() {
+		let cid = ipfs_cid(&[1u8; 32]);
+		assert_eq!(&cid[..6], &[0x01, 0x55, 0xa0, 0xe4, 0x02, 0x20]);
+		assert_eq!(cid.len(), 38);
+	}
+}
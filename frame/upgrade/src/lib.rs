@@ -0,0 +1,327 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Upgrade Module
+//!
+//! - [`upgrade::Trait`](./trait.Trait.html)
+//! - [`Call`](./enum.Call.html)
+//!
+//! ## Overview
+//!
+//! The Upgrade module splits a runtime upgrade into two steps. Governance only authorizes the
+//! hash of the new code, which keeps multi-megabyte blobs out of proposals, preimages and votes.
+//! Anyone may then submit the code matching the authorized hash, which is set as the new runtime
+//! if it has the spec name of the current runtime and a greater spec version.
+//!
+//! ## Interface
+//!
+//! ### Dispatchable Functions
+//!
+//! * `authorize_upgrade` - Authorize the hash of the code of the next runtime.
+//! * `apply_authorized_upgrade` - Set the code matching the authorized hash as the new runtime.
+//!
+//! [`Call`]: ./enum.Call.html
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::Decode;
+use sp_core::storage::well_known_keys;
+use sp_runtime::traits::{EnsureOrigin, Hash as HashT};
+use frame_support::{
+	decl_module, decl_event, decl_storage, decl_error, ensure, storage,
+	weights::{Weight, DispatchClass, SimpleDispatchInfo, FunctionOf},
+	traits::Get, dispatch::DispatchResult,
+};
+use frame_system::{self as system, ensure_signed, ensure_root};
+
+pub trait Trait: frame_system::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+	/// The origin which may authorize an upgrade. Root can always do this.
+	type AuthorizeOrigin: EnsureOrigin<Self::Origin>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Upgrade {
+		/// The hash of the code which may be set by `apply_authorized_upgrade`, if any.
+		AuthorizedUpgrade get(fn authorized_upgrade): Option<T::Hash>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where Hash = <T as frame_system::Trait>::Hash {
+		/// An upgrade to the code with the given hash was authorized.
+		UpgradeAuthorized(Hash),
+		/// The code with the given hash was set as the new runtime.
+		UpgradeApplied(Hash),
+	}
+);
+
+decl_error! {
+	/// Error for the upgrade module.
+	pub enum Error for Module<T: Trait> {
+		/// No upgrade is authorized.
+		NothingAuthorized,
+		/// The code does not match the authorized hash.
+		Unauthorized,
+		/// The runtime version of the code could not be extracted.
+		FailedToExtractRuntimeVersion,
+		/// The spec name of the code differs from the one of the current runtime.
+		InvalidSpecName,
+		/// The spec version of the code is not greater than the one of the current runtime.
+		SpecVersionNeedsToIncrease,
+	}
+}
+
+/// The leading fields of an encoded `RuntimeVersion`, which can't be decoded in the runtime.
+#[derive(Decode)]
+struct SpecVersion {
+	spec_name: Vec<u8>,
+	_impl_name: Vec<u8>,
+	_authoring_version: u32,
+	spec_version: u32,
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Authorize an upgrade to the code with the given hash, replacing any previously
+		/// authorized upgrade.
+		///
+		/// The dispatch origin for this call must be _Root_ or match `T::AuthorizeOrigin`.
+		///
+		/// # <weight>
+		/// - O(1).
+		/// - One storage write.
+		/// - One event.
+		/// # </weight>
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		fn authorize_upgrade(origin, code_hash: T::Hash) {
+			T::AuthorizeOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			<AuthorizedUpgrade<T>>::put(code_hash);
+
+			Self::deposit_event(RawEvent::UpgradeAuthorized(code_hash));
+		}
+
+		/// Set the code matching the authorized hash as the new runtime and clear the
+		/// authorization.
+		///
+		/// The dispatch origin for this call must be _Signed_. The transaction fee, which is
+		/// dominated by the length of the code, is paid by the sender. The code must have the spec
+		/// name of the current runtime and a greater spec version.
+		///
+		/// # <weight>
+		/// - O(C) where C is the length of the code, for hashing, instantiating and writing it.
+		/// - One storage read, two storage writes.
+		/// - One event.
+		/// # </weight>
+		#[weight = FunctionOf(
+			|args: (&Vec<u8>,)| (args.0.len() as Weight).saturating_mul(10).saturating_add(200_000),
+			DispatchClass::Normal,
+			true,
+		)]
+		fn apply_authorized_upgrade(origin, code: Vec<u8>) {
+			ensure_signed(origin)?;
+
+			let authorized = <AuthorizedUpgrade<T>>::get().ok_or(Error::<T>::NothingAuthorized)?;
+			let code_hash = T::Hashing::hash(&code);
+			ensure!(code_hash == authorized, Error::<T>::Unauthorized);
+			Self::can_set_code(&code)?;
+
+			storage::unhashed::put_raw(well_known_keys::CODE, &code);
+			<AuthorizedUpgrade<T>>::kill();
+
+			Self::deposit_event(RawEvent::UpgradeApplied(code_hash));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Check that `code` has the spec name of the current runtime and a greater spec version.
+	fn can_set_code(code: &[u8]) -> DispatchResult {
+		let current = <T as frame_system::Trait>::Version::get();
+		let new = sp_io::misc::runtime_version(code)
+			.and_then(|version| SpecVersion::decode(&mut &version[..]).ok())
+			.ok_or(Error::<T>::FailedToExtractRuntimeVersion)?;
+
+		ensure!(new.spec_name == current.spec_name.as_bytes(), Error::<T>::InvalidSpecName);
+		ensure!(new.spec_version > current.spec_version, Error::<T>::SpecVersionNeedsToIncrease);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use codec::Encode;
+	use frame_support::{
+		assert_ok, assert_noop, impl_outer_origin, parameter_types,
+		weights::{Weight, GetDispatchInfo}, ord_parameter_types,
+	};
+	use sp_core::{H256, traits::{CallInWasm, CallInWasmExt, Externalities}};
+	use sp_version::RuntimeVersion;
+	use frame_system::EnsureSignedBy;
+	use sp_runtime::{
+		Perbill, testing::Header, traits::{BlakeTwo256, IdentityLookup, BadOrigin},
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = ();
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = Version;
+		type ModuleToIndex = ();
+	}
+	pub struct Version;
+	impl Get<RuntimeVersion> for Version {
+		fn get() -> RuntimeVersion {
+			version("test", 1)
+		}
+	}
+	ord_parameter_types! {
+		pub const One: u64 = 1;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type AuthorizeOrigin = EnsureSignedBy<One, u64>;
+	}
+	type Upgrade = Module<Test>;
+
+	fn version(spec_name: &'static str, spec_version: u32) -> RuntimeVersion {
+		RuntimeVersion { spec_name: spec_name.into(), spec_version, ..Default::default() }
+	}
+
+	/// Reads the runtime version of codes made of an encoded `RuntimeVersion`.
+	struct ReadRuntimeVersion;
+
+	impl CallInWasm for ReadRuntimeVersion {
+		fn call_in_wasm(
+			&self,
+			wasm_blob: &[u8],
+			method: &str,
+			_: &[u8],
+			_: &mut dyn Externalities,
+		) -> Result<Vec<u8>, String> {
+			assert_eq!(method, "Core_version");
+			RuntimeVersion::decode(&mut &wasm_blob[..])
+				.map(|_| wasm_blob.to_vec())
+				.map_err(|_| "Not a runtime".into())
+		}
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut ext: sp_io::TestExternalities = frame_system::GenesisConfig::default()
+			.build_storage::<Test>()
+			.unwrap()
+			.into();
+		ext.register_extension(CallInWasmExt::new(ReadRuntimeVersion));
+		ext
+	}
+
+	#[test]
+	fn authorized_code_can_be_applied_by_anyone() {
+		new_test_ext().execute_with(|| {
+			let code = version("test", 2).encode();
+			assert_ok!(Upgrade::authorize_upgrade(Origin::signed(1), BlakeTwo256::hash(&code)));
+			assert_eq!(Upgrade::authorized_upgrade(), Some(BlakeTwo256::hash(&code)));
+
+			assert_ok!(Upgrade::apply_authorized_upgrade(Origin::signed(2), code.clone()));
+			assert_eq!(storage::unhashed::get_raw(well_known_keys::CODE), Some(code));
+			assert_eq!(Upgrade::authorized_upgrade(), None);
+		});
+	}
+
+	#[test]
+	fn applying_weighs_the_length_of_the_code() {
+		let info = |len: usize| Call::<Test>::apply_authorized_upgrade(vec![0; len]).get_dispatch_info();
+		assert_eq!(info(0).weight, 200_000);
+		assert_eq!(info(1_000_000).weight, 10_200_000);
+		assert_eq!(info(0).class, DispatchClass::Normal);
+	}
+
+	#[test]
+	fn only_code_upgrading_the_current_runtime_can_be_applied() {
+		new_test_ext().execute_with(|| {
+			let authorize = |code: &Vec<u8>| {
+				assert_ok!(Upgrade::authorize_upgrade(Origin::ROOT, BlakeTwo256::hash(code)));
+			};
+			let rejected = vec![
+				(b"not a runtime".to_vec(), Error::<Test>::FailedToExtractRuntimeVersion),
+				(version("other", 2).encode(), Error::<Test>::InvalidSpecName),
+				(version("test", 1).encode(), Error::<Test>::SpecVersionNeedsToIncrease),
+			];
+			for (code, error) in rejected {
+				authorize(&code);
+				assert_noop!(Upgrade::apply_authorized_upgrade(Origin::signed(2), code), error);
+			}
+
+			let code = version("test", 2).encode();
+			authorize(&code);
+			assert_ok!(Upgrade::apply_authorized_upgrade(Origin::signed(2), code));
+		});
+	}
+
+	#[test]
+	fn only_authorized_code_can_be_applied() {
+		new_test_ext().execute_with(|| {
+			let code = b"new runtime".to_vec();
+			assert_noop!(
+				Upgrade::apply_authorized_upgrade(Origin::signed(2), code.clone()),
+				Error::<Test>::NothingAuthorized,
+			);
+
+			assert_noop!(Upgrade::authorize_upgrade(Origin::signed(2), BlakeTwo256::hash(&code)), BadOrigin);
+			assert_ok!(Upgrade::authorize_upgrade(Origin::ROOT, BlakeTwo256::hash(&code)));
+			assert_noop!(
+				Upgrade::apply_authorized_upgrade(Origin::signed(2), version("test", 3).encode()),
+				Error::<Test>::Unauthorized,
+			);
+		});
+	}
+}
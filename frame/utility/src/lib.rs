@@ -44,7 +44,13 @@
 //! ### Dispatchable Functions
 //!
 //! #### For batch dispatch
-//! * `batch` - Dispatch multiple calls from the sender's origin.
+//! * `batch` - Dispatch multiple calls from the sender's origin, stopping at the first failure.
+//! * `batch_all` - Dispatch multiple calls from the sender's origin, failing with the error of
+//!   the first failed call.
+//! * `force_batch` - Dispatch multiple calls from the sender's origin, carrying on after failures.
+//!
+//! #### For remarks
+//! * `remark_with_event` - Make an on-chain remark and deposit an event with its hash.
 //!
 //! #### For pseudonymal dispatch
 //! * `as_sub` - Dispatch a call from a secondary ("sub") signed origin.
@@ -65,12 +71,14 @@ use sp_std::prelude::*;
 use codec::{Encode, Decode};
 use sp_core::TypeId;
 use sp_io::hashing::blake2_256;
-use frame_support::{decl_module, decl_event, decl_error, decl_storage, Parameter, ensure, RuntimeDebug};
+use frame_support::{
+	decl_module, decl_event, decl_error, decl_storage, Parameter, ensure, RuntimeDebug, storage,
+};
 use frame_support::{traits::{Get, ReservableCurrency, Currency}, weights::{
-	GetDispatchInfo, ClassifyDispatch, WeighData, Weight, DispatchClass, PaysFee
+	GetDispatchInfo, ClassifyDispatch, WeighData, Weight, DispatchClass, PaysFee, FunctionOf,
 }};
 use frame_system::{self as system, ensure_signed};
-use sp_runtime::{DispatchError, DispatchResult, traits::Dispatchable};
+use sp_runtime::{DispatchError, DispatchResult, traits::{Dispatchable, Hash}};
 
 type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
 
@@ -166,13 +174,21 @@ decl_event! {
 	/// Events type.
 	pub enum Event<T> where
 		AccountId = <T as system::Trait>::AccountId,
-		BlockNumber = <T as system::Trait>::BlockNumber
+		BlockNumber = <T as system::Trait>::BlockNumber,
+		Hash = <T as system::Trait>::Hash
 	{
 		/// Batch of dispatches did not complete fully. Index of first failing dispatch given, as
 		/// well as the error.
 		BatchInterrupted(u32, DispatchError),
 		/// Batch of dispatches completed fully with no error.
 		BatchCompleted,
+		/// A dispatch of a forced batch failed. Index of the failing dispatch given, as well as
+		/// the error.
+		BatchItemFailed(u32, DispatchError),
+		/// A forced batch of dispatches completed, with some dispatches failing.
+		BatchCompletedWithErrors,
+		/// An on-chain remark was made by the account. The hash of the remark is given.
+		Remarked(AccountId, Hash),
 		/// A new multisig operation has begun. First param is the account that is approving,
 		/// second is the multisig account.
 		NewMultisig(AccountId, AccountId),
@@ -351,6 +367,90 @@ decl_module! {
 			Self::deposit_event(Event::<T>::BatchCompleted);
 		}
 
+		/// Send a batch of dispatch calls, all of which must succeed.
+		///
+		/// This will execute until the first one fails, then revert the changes of all the calls
+		/// of the batch, deposit the `BatchInterrupted` event with the index of the failing call
+		/// and fail with its error.
+		///
+		/// May be called from any origin.
+		///
+		/// - `calls`: The calls to be dispatched from the same origin.
+		///
+		/// # <weight>
+		/// - The sum of the weights of the `calls`.
+		/// - One event.
+		/// # </weight>
+		#[weight = <BatchPassthrough<<T as Trait>::Call>>::new()]
+		fn batch_all(origin, calls: Vec<<T as Trait>::Call>) -> DispatchResult {
+			let result = storage::transactional(|| {
+				for (index, call) in calls.into_iter().enumerate() {
+					call.dispatch(origin.clone()).map_err(|e| (index as u32, e))?;
+				}
+				Ok(())
+			});
+			match result {
+				Ok(()) => {
+					Self::deposit_event(Event::<T>::BatchCompleted);
+					Ok(())
+				},
+				Err((index, e)) => {
+					Self::deposit_event(Event::<T>::BatchInterrupted(index, e));
+					Err(e)
+				},
+			}
+		}
+
+		/// Send a batch of dispatch calls, carrying on after failed ones.
+		///
+		/// May be called from any origin.
+		///
+		/// - `calls`: The calls to be dispatched from the same origin.
+		///
+		/// # <weight>
+		/// - The sum of the weights of the `calls`.
+		/// - One event, plus one for each failed call.
+		/// # </weight>
+		///
+		/// This will return `Ok` in all circumstances. A `BatchItemFailed` event is deposited for
+		/// each failed call, with its index and error. If some calls failed, the
+		/// `BatchCompletedWithErrors` event is deposited at the end, otherwise `BatchCompleted`.
+		#[weight = <BatchPassthrough<<T as Trait>::Call>>::new()]
+		fn force_batch(origin, calls: Vec<<T as Trait>::Call>) {
+			let mut failed = false;
+			for (index, call) in calls.into_iter().enumerate() {
+				if let Err(e) = call.dispatch(origin.clone()) {
+					Self::deposit_event(Event::<T>::BatchItemFailed(index as u32, e));
+					failed = true;
+				}
+			}
+			if failed {
+				Self::deposit_event(Event::<T>::BatchCompletedWithErrors);
+			} else {
+				Self::deposit_event(Event::<T>::BatchCompleted);
+			}
+		}
+
+		/// Make an on-chain remark and deposit the `Remarked` event with its hash, so that the
+		/// remark can be found from the events.
+		///
+		/// The dispatch origin for this call must be _Signed_.
+		///
+		/// # <weight>
+		/// - O(R) where R is the length of the remark, to hash it.
+		/// - One event.
+		/// # </weight>
+		#[weight = FunctionOf(
+			|args: (&Vec<u8>,)| (args.0.len() as Weight).saturating_add(10_000),
+			DispatchClass::Normal,
+			true,
+		)]
+		fn remark_with_event(origin, remark: Vec<u8>) {
+			let who = ensure_signed(origin)?;
+			let hash = T::Hashing::hash(&remark[..]);
+			Self::deposit_event(RawEvent::Remarked(who, hash));
+		}
+
 		/// Send a call through an indexed pseudonym of the sender.
 		///
 		/// The dispatch origin for this call must be _Signed_.
@@ -650,7 +750,7 @@ mod tests {
 		weights::Weight, impl_outer_event
 	};
 	use sp_core::H256;
-	use sp_runtime::{Perbill, traits::{BlakeTwo256, IdentityLookup}, testing::Header};
+	use sp_runtime::{Perbill, traits::{BlakeTwo256, IdentityLookup, BadOrigin}, testing::Header};
 	use crate as utility;
 
 	impl_outer_origin! {
@@ -1066,4 +1166,65 @@ mod tests {
 			assert_eq!(Balances::free_balance(2), 15);
 		});
 	}
+
+	#[test]
+	fn batch_all_fails_with_the_failing_call() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(
+				Utility::batch_all(Origin::signed(1), vec![
+					Call::Balances(BalancesCall::transfer(2, 5)),
+					Call::Balances(BalancesCall::transfer(2, 10)),
+					Call::Balances(BalancesCall::transfer(2, 5)),
+				]),
+				Err(pallet_balances::Error::<Test, _>::InsufficientBalance.into()),
+			);
+			expect_event(RawEvent::BatchInterrupted(
+				1,
+				pallet_balances::Error::<Test, _>::InsufficientBalance.into(),
+			));
+			// the transfer before the failing one is reverted.
+			assert_eq!(Balances::free_balance(1), 10);
+			assert_eq!(Balances::free_balance(2), 10);
+		});
+	}
+
+	#[test]
+	fn force_batch_carries_on_after_failures() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(
+				Utility::force_batch(Origin::signed(1), vec![
+					Call::Balances(BalancesCall::transfer(2, 5)),
+					Call::Balances(BalancesCall::transfer(2, 10)),
+					Call::Balances(BalancesCall::transfer(2, 5)),
+				]),
+			);
+			assert_eq!(Balances::free_balance(1), 0);
+			assert_eq!(Balances::free_balance(2), 20);
+			let events = system::Module::<Test>::events().into_iter()
+				.map(|e| e.event)
+				.filter(|e| match e { TestEvent::utility(_) => true, _ => false })
+				.collect::<Vec<_>>();
+			assert_eq!(events, vec![
+				RawEvent::BatchItemFailed(1, pallet_balances::Error::<Test, _>::InsufficientBalance.into()).into(),
+				RawEvent::BatchCompletedWithErrors.into(),
+			]);
+		});
+	}
+
+	#[test]
+	fn remark_with_event_works() {
+		new_test_ext().execute_with(|| {
+			let remark = b"hello".to_vec();
+			assert_noop!(Utility::remark_with_event(Origin::NONE, remark.clone()), BadOrigin);
+			assert_ok!(Utility::remark_with_event(Origin::signed(1), remark.clone()));
+			expect_event(RawEvent::Remarked(1, BlakeTwo256::hash(&remark[..])));
+		});
+	}
+
+	#[test]
+	fn remark_with_event_weight_grows_with_its_length() {
+		let short = Call::Utility(utility::Call::remark_with_event(vec![0; 10])).get_dispatch_info();
+		let long = Call::Utility(utility::Call::remark_with_event(vec![0; 1000])).get_dispatch_info();
+		assert_eq!(long.weight - short.weight, 990);
+	}
 }
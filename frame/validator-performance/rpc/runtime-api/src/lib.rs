@@ -0,0 +1,77 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime API definition for the validator performance module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Codec, Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use sp_runtime::{Perbill, RuntimeDebug};
+use sp_staking::SessionIndex;
+
+/// The performance of a validator in a session.
+#[derive(Default, Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ValidatorPerformance {
+	/// Number of blocks authored, uncles excluded.
+	pub blocks_authored: u32,
+	/// Number of authored blocks including a hint of the best finalized block.
+	pub finality_hints: u32,
+	/// Number of those hints within the maximum finality lag of their block. A validator whose
+	/// node keeps up with finality takes part in the GRANDPA votes.
+	pub finality_in_sync: u32,
+	/// Whether an im-online heartbeat was received.
+	pub heartbeat: bool,
+}
+
+impl ValidatorPerformance {
+	/// Whether the validator proved to be online, by a heartbeat or an authored block.
+	pub fn is_online(&self) -> bool {
+		self.heartbeat || self.blocks_authored > 0
+	}
+
+	/// The score of the session: nothing if the validator was offline, otherwise the share of
+	/// its finality hints in sync with the chain, or everything if it provided no hint.
+	pub fn score(&self) -> Perbill {
+		if !self.is_online() {
+			Perbill::zero()
+		} else if self.finality_hints == 0 {
+			Perbill::one()
+		} else {
+			Perbill::from_rational_approximation(self.finality_in_sync, self.finality_hints)
+		}
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	/// Performance records of the validators kept by the validator performance module.
+	pub trait ValidatorPerformanceApi<ValidatorId> where ValidatorId: Codec {
+		/// The performance of `validator` in each recorded session it was part of, oldest first.
+		/// The current session is included, even though it is still in progress.
+		fn history(validator: ValidatorId) -> Vec<(SessionIndex, ValidatorPerformance)>;
+
+		/// The average score of `validator` over the recorded completed sessions it was part of,
+		/// if any.
+		fn score(validator: ValidatorId) -> Option<Perbill>;
+
+		/// The scores of the validators of the current session.
+		fn scores() -> Vec<(ValidatorId, Option<Perbill>)>;
+	}
+}
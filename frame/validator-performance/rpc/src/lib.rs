@@ -0,0 +1,143 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface for the validator performance module.
+
+use std::sync::Arc;
+use codec::Codec;
+use sp_blockchain::HeaderBackend;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_runtime::{Perbill, generic::BlockId, traits::Block as BlockT};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_staking::SessionIndex;
+pub use pallet_validator_performance_rpc_runtime_api::{
+	ValidatorPerformance, ValidatorPerformanceApi as ValidatorPerformanceRuntimeApi,
+};
+pub use self::gen_client::Client as ValidatorPerformanceClient;
+
+#[rpc]
+pub trait ValidatorPerformanceApi<BlockHash, ValidatorId> {
+	/// The performance of `validator` in each recorded session it was part of at the given
+	/// block, or the best block, oldest first.
+	#[rpc(name = "validatorPerformance_history")]
+	fn history(
+		&self,
+		validator: ValidatorId,
+		at: Option<BlockHash>,
+	) -> Result<Vec<(SessionIndex, ValidatorPerformance)>>;
+
+	/// The average score of `validator` over the recorded completed sessions.
+	#[rpc(name = "validatorPerformance_score")]
+	fn score(&self, validator: ValidatorId, at: Option<BlockHash>) -> Result<Option<Perbill>>;
+
+	/// The scores of the validators of the current session.
+	#[rpc(name = "validatorPerformance_scores")]
+	fn scores(&self, at: Option<BlockHash>) -> Result<Vec<(ValidatorId, Option<Perbill>)>>;
+}
+
+/// A struct that implements the [`ValidatorPerformanceApi`].
+pub struct ValidatorPerformanceRpc<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> ValidatorPerformanceRpc<C, B> {
+	/// Create new `ValidatorPerformanceRpc` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		ValidatorPerformanceRpc { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// The runtime at the given block does not implement the api.
+	UnsupportedApi,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::UnsupportedApi => 2,
+		}
+	}
+}
+
+fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query validator performance.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, ValidatorId> ValidatorPerformanceRpc<C, Block> where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: ValidatorPerformanceRuntimeApi<Block, ValidatorId>,
+	ValidatorId: Codec,
+{
+	/// The block to query at, checking that its runtime implements the api.
+	fn at(&self, at: Option<<Block as BlockT>::Hash>) -> Result<BlockId<Block>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		let has_api = self.client.runtime_api().has_api::<
+			dyn ValidatorPerformanceRuntimeApi<Block, ValidatorId, Error = ()>
+		>(&at).map_err(runtime_error)?;
+		if !has_api {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::UnsupportedApi.into()),
+				message: "Validator performance api is not available at the given block.".into(),
+				data: None,
+			})
+		}
+		Ok(at)
+	}
+}
+
+impl<C, Block, ValidatorId> ValidatorPerformanceApi<<Block as BlockT>::Hash, ValidatorId>
+	for ValidatorPerformanceRpc<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: ValidatorPerformanceRuntimeApi<Block, ValidatorId>,
+	ValidatorId: Codec + Send + Sync + 'static,
+{
+	fn history(
+		&self,
+		validator: ValidatorId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(SessionIndex, ValidatorPerformance)>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().history(&at, validator).map_err(runtime_error)
+	}
+
+	fn score(
+		&self,
+		validator: ValidatorId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<Perbill>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().score(&at, validator).map_err(runtime_error)
+	}
+
+	fn scores(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<(ValidatorId, Option<Perbill>)>> {
+		let at = self.at(at)?;
+		self.client.runtime_api().scores(&at).map_err(runtime_error)
+	}
+}
@@ -0,0 +1,430 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Validator Performance Module
+//!
+//! - [`validator_performance::Trait`](./trait.Trait.html)
+//!
+//! ## Overview
+//!
+//! The Validator Performance module records how reliably each validator did its job in the
+//! recent sessions, so nominators and dashboards can compare operators using on-chain data only.
+//!
+//! For every validator of a session it records:
+//!
+//! * the blocks authored, found with `FindAuthor`;
+//! * whether an im-online heartbeat was received;
+//! * the hints of the best finalized block included by the validator in its blocks, and how many
+//!   of them were within `MaxFinalityLag` of the block. GRANDPA votes are not recorded on chain,
+//!   but a validator whose node keeps up with finality takes part in them.
+//!
+//! The records of the last `HistoryDepth` sessions are kept, older ones are pruned at the start
+//! of a session. The records and the scores derived from them are exposed through the
+//! `ValidatorPerformanceApi` runtime api.
+//!
+//! ## Usage
+//!
+//! Add the module to the `OnHeartbeat` handler of the im-online module and the `OnFinalHint`
+//! handler of the finality tracker module.
+//!
+//! The module must come after the Session module in `construct_runtime!`. The first block of a
+//! session is authored by a validator of that session, so the author is only found once the
+//! session has been rotated, not in the `EventHandler` of the Authorship module, which is called
+//! before that.
+//!
+//! [`Trait`]: ./trait.Trait.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use sp_runtime::{Perbill, traits::Saturating};
+use sp_staking::SessionIndex;
+use frame_support::{decl_module, decl_storage, traits::{FindAuthor, Get}};
+
+pub use pallet_validator_performance_rpc_runtime_api::ValidatorPerformance;
+
+pub trait Trait: pallet_session::Trait {
+	/// The maximum number of blocks between a block and the best finalized block hinted by its
+	/// author for the author to be in sync with finality.
+	type MaxFinalityLag: Get<Self::BlockNumber>;
+
+	/// The number of sessions to keep the records of, the current one included.
+	type HistoryDepth: Get<SessionIndex>;
+
+	/// Find the author of a block among the validators of the current session.
+	type FindAuthor: FindAuthor<Self::ValidatorId>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as ValidatorPerformance {
+		/// The validators of each recorded session.
+		SessionValidators get(fn session_validators): map SessionIndex => Vec<T::ValidatorId>;
+
+		/// The performance of each validator in each recorded session.
+		Performance get(fn performance): double_map SessionIndex, T::ValidatorId => ValidatorPerformance;
+
+		/// The oldest recorded session, `None` until the first session is recorded.
+		EarliestSession get(fn earliest_session): Option<SessionIndex>;
+
+		/// The author of the current block.
+		Author: Option<T::ValidatorId>;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// The maximum number of blocks between a block and the best finalized block hinted by
+		/// its author for the author to be in sync with finality.
+		const MaxFinalityLag: T::BlockNumber = T::MaxFinalityLag::get();
+
+		/// The number of sessions to keep the records of, the current one included.
+		const HistoryDepth: SessionIndex = T::HistoryDepth::get();
+
+		fn on_initialize() {
+			Self::note_session(<pallet_session::Module<T>>::current_index());
+			Self::note_author();
+		}
+
+		fn on_finalize() {
+			// ensure we never go to trie with this value.
+			<Self as Store>::Author::kill();
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The performance of `validator` in each recorded session it was part of, oldest first.
+	pub fn history(validator: &T::ValidatorId) -> Vec<(SessionIndex, ValidatorPerformance)> {
+		let earliest = match Self::earliest_session() {
+			Some(earliest) => earliest,
+			None => return Vec::new(),
+		};
+		let current = <pallet_session::Module<T>>::current_index();
+		(earliest..=current)
+			.filter(|session| Self::session_validators(session).contains(validator))
+			.map(|session| (session, Self::performance(session, validator)))
+			.collect()
+	}
+
+	/// The average score of `validator` over the recorded completed sessions it was part of, if
+	/// any.
+	pub fn score(validator: &T::ValidatorId) -> Option<Perbill> {
+		let current = <pallet_session::Module<T>>::current_index();
+		let scores = Self::history(validator).into_iter()
+			.filter(|(session, _)| *session < current)
+			.map(|(_, performance)| performance.score().deconstruct() as u64)
+			.collect::<Vec<_>>();
+		if scores.is_empty() {
+			return None
+		}
+		let average = scores.iter().sum::<u64>() / scores.len() as u64;
+		Some(Perbill::from_parts(average as u32))
+	}
+
+	/// The scores of the validators of the current session.
+	pub fn scores() -> Vec<(T::ValidatorId, Option<Perbill>)> {
+		<pallet_session::Module<T>>::validators().into_iter()
+			.map(|validator| {
+				let score = Self::score(&validator);
+				(validator, score)
+			})
+			.collect()
+	}
+
+	/// Record the validators of `session` when it starts and prune the records of the sessions
+	/// out of the history.
+	fn note_session(session: SessionIndex) {
+		if <SessionValidators<T>>::exists(session) {
+			return
+		}
+		<SessionValidators<T>>::insert(session, <pallet_session::Module<T>>::validators());
+
+		let keep_from = (session + 1).saturating_sub(T::HistoryDepth::get().max(1));
+		let mut earliest = Self::earliest_session().unwrap_or(session);
+		while earliest < keep_from {
+			<SessionValidators<T>>::remove(earliest);
+			<Performance<T>>::remove_prefix(&earliest);
+			earliest += 1;
+		}
+		EarliestSession::put(earliest);
+	}
+
+	/// Record the author of the current block in the current session.
+	fn note_author() {
+		let digest = <frame_system::Module<T>>::digest();
+		let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+		let author = match T::FindAuthor::find_author(pre_runtime_digests) {
+			Some(author) => author,
+			None => return,
+		};
+		Self::mutate_current(&author, |performance| {
+			performance.blocks_authored = performance.blocks_authored.saturating_add(1);
+		});
+		<Self as Store>::Author::put(author);
+	}
+
+	/// Update the performance of `validator` in the current session.
+	fn mutate_current(validator: &T::ValidatorId, f: impl FnOnce(&mut ValidatorPerformance)) {
+		let session = <pallet_session::Module<T>>::current_index();
+		<Performance<T>>::mutate(&session, validator, f);
+	}
+}
+
+impl<T: Trait> pallet_im_online::OnHeartbeat<T::ValidatorId> for Module<T> {
+	fn on_heartbeat(validator: &T::ValidatorId) {
+		Self::mutate_current(validator, |performance| performance.heartbeat = true);
+	}
+}
+
+impl<T: Trait> pallet_finality_tracker::OnFinalHint<T::BlockNumber> for Module<T> {
+	fn on_final_hint(hint: T::BlockNumber) {
+		let author = match <Self as Store>::Author::get() {
+			Some(author) => author,
+			None => return,
+		};
+		let lag = <frame_system::Module<T>>::block_number().saturating_sub(hint);
+		let in_sync = lag <= T::MaxFinalityLag::get();
+		Self::mutate_current(&author, |performance| {
+			performance.finality_hints = performance.finality_hints.saturating_add(1);
+			if in_sync {
+				performance.finality_in_sync = performance.finality_in_sync.saturating_add(1);
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::cell::RefCell;
+	use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+	use sp_core::{H256, crypto::key_types};
+	use sp_runtime::{
+		testing::{Header, UintAuthorityId},
+		traits::{BlakeTwo256, ConvertInto, IdentityLookup, OpaqueKeys, OnFinalize, OnInitialize},
+		ConsensusEngineId, KeyTypeId,
+	};
+	use pallet_finality_tracker::OnFinalHint;
+	use pallet_im_online::OnHeartbeat;
+
+	impl_outer_origin! {
+		pub enum Origin for Test where system = frame_system {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+	impl frame_system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Call = ();
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type ModuleToIndex = ();
+	}
+
+	pub struct TestSessionHandler;
+	impl pallet_session::SessionHandler<u64> for TestSessionHandler {
+		const KEY_TYPE_IDS: &'static [KeyTypeId] = &[key_types::DUMMY];
+		fn on_genesis_session<Ks: OpaqueKeys>(_validators: &[(u64, Ks)]) {}
+		fn on_new_session<Ks: OpaqueKeys>(_: bool, _: &[(u64, Ks)], _: &[(u64, Ks)]) {}
+		fn on_disabled(_validator_index: usize) {}
+	}
+
+	thread_local! {
+		static AUTHOR_INDEX: RefCell<Option<u32>> = RefCell::new(None);
+		static NEXT_VALIDATORS: RefCell<Option<Vec<u64>>> = RefCell::new(None);
+	}
+
+	/// The validators of the session after the next one, if changed.
+	pub struct TestOnSessionEnding;
+	impl pallet_session::OnSessionEnding<u64> for TestOnSessionEnding {
+		fn on_session_ending(_: SessionIndex, _: SessionIndex) -> Option<Vec<u64>> {
+			NEXT_VALIDATORS.with(|v| v.borrow_mut().take())
+		}
+	}
+
+	/// The index of the author among the validators, as given by the digest of a block.
+	pub struct TestAuthorIndex;
+	impl FindAuthor<u32> for TestAuthorIndex {
+		fn find_author<'a, I>(_digests: I) -> Option<u32>
+			where I: 'a + IntoIterator<Item=(ConsensusEngineId, &'a [u8])>
+		{
+			AUTHOR_INDEX.with(|i| *i.borrow())
+		}
+	}
+
+	parameter_types! {
+		pub const Period: u64 = 10;
+		pub const Offset: u64 = 0;
+		pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(33);
+	}
+	impl pallet_session::Trait for Test {
+		type Event = ();
+		type ValidatorId = u64;
+		type ValidatorIdOf = ConvertInto;
+		type ShouldEndSession = pallet_session::PeriodicSessions<Period, Offset>;
+		type OnSessionEnding = TestOnSessionEnding;
+		type SessionHandler = TestSessionHandler;
+		type Keys = UintAuthorityId;
+		type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
+		type SelectInitialValidators = ();
+	}
+	parameter_types! {
+		pub const MaxFinalityLag: u64 = 5;
+		pub const HistoryDepth: SessionIndex = 3;
+	}
+	impl Trait for Test {
+		type MaxFinalityLag = MaxFinalityLag;
+		type HistoryDepth = HistoryDepth;
+		type FindAuthor = pallet_session::FindAccountFromAuthorIndex<Test, TestAuthorIndex>;
+	}
+	type System = frame_system::Module<Test>;
+	type Session = pallet_session::Module<Test>;
+	type ValidatorPerformance = Module<Test>;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_session::GenesisConfig::<Test> {
+			keys: vec![1, 2, 3].into_iter().map(|v| (v, UintAuthorityId(v))).collect(),
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	/// Execute block `n` authored by the validator at `author_index`, with the given finality hint.
+	fn author_block(n: u64, author_index: u32, hint: Option<u64>) {
+		AUTHOR_INDEX.with(|i| *i.borrow_mut() = Some(author_index));
+		System::set_block_number(n);
+		Session::on_initialize(n);
+		ValidatorPerformance::on_initialize(n);
+		if let Some(hint) = hint {
+			ValidatorPerformance::on_final_hint(hint);
+		}
+		ValidatorPerformance::on_finalize(n);
+	}
+
+	#[test]
+	fn records_authorship_heartbeats_and_finality_hints() {
+		new_test_ext().execute_with(|| {
+			author_block(1, 0, Some(1));
+			author_block(2, 0, Some(1));
+			author_block(9, 1, Some(2));
+			author_block(9, 2, None);
+			ValidatorPerformance::on_heartbeat(&3);
+
+			assert_eq!(Session::current_index(), 0);
+			assert_eq!(ValidatorPerformance::performance(0, 1), super::ValidatorPerformance {
+				blocks_authored: 2,
+				finality_hints: 2,
+				finality_in_sync: 2,
+				heartbeat: false,
+			});
+			assert_eq!(ValidatorPerformance::performance(0, 2), super::ValidatorPerformance {
+				blocks_authored: 1,
+				finality_hints: 1,
+				finality_in_sync: 0,
+				heartbeat: false,
+			});
+			assert_eq!(ValidatorPerformance::performance(0, 3), super::ValidatorPerformance {
+				blocks_authored: 1,
+				finality_hints: 0,
+				finality_in_sync: 0,
+				heartbeat: true,
+			});
+			// the author is only known within its block.
+			ValidatorPerformance::on_final_hint(9);
+			assert_eq!(ValidatorPerformance::performance(0, 3).finality_hints, 0);
+		});
+	}
+
+	#[test]
+	fn scores_completed_sessions() {
+		new_test_ext().execute_with(|| {
+			author_block(1, 0, Some(1));
+			author_block(2, 1, Some(1));
+			author_block(8, 1, Some(1));
+			// nothing is scored before the first session is completed.
+			assert_eq!(ValidatorPerformance::score(&1), None);
+
+			author_block(10, 0, Some(10));
+			assert_eq!(Session::current_index(), 1);
+			assert_eq!(ValidatorPerformance::scores(), vec![
+				(1, Some(Perbill::one())),
+				(2, Some(Perbill::from_percent(50))),
+				(3, Some(Perbill::zero())),
+			]);
+
+			ValidatorPerformance::on_heartbeat(&3);
+			author_block(20, 0, Some(20));
+			assert_eq!(ValidatorPerformance::score(&2), Some(Perbill::from_percent(25)));
+			assert_eq!(ValidatorPerformance::score(&3), Some(Perbill::from_percent(50)));
+			let sessions = ValidatorPerformance::history(&1).into_iter().map(|(s, _)| s).collect::<Vec<_>>();
+			assert_eq!(sessions, vec![0, 1, 2]);
+		});
+	}
+
+	#[test]
+	fn authors_are_found_among_the_validators_of_their_session() {
+		new_test_ext().execute_with(|| {
+			NEXT_VALIDATORS.with(|v| *v.borrow_mut() = Some(vec![4, 5, 6]));
+			author_block(10, 0, None);
+			assert_eq!(Session::validators(), vec![1, 2, 3]);
+
+			// The first block of session 2 is authored by the validators of session 2.
+			author_block(20, 0, None);
+			assert_eq!(Session::current_index(), 2);
+			assert_eq!(Session::validators(), vec![4, 5, 6]);
+			assert_eq!(ValidatorPerformance::performance(2, 4).blocks_authored, 1);
+			assert_eq!(ValidatorPerformance::performance(1, 1).blocks_authored, 1);
+			assert_eq!(ValidatorPerformance::performance(2, 1).blocks_authored, 0);
+			assert_eq!(ValidatorPerformance::session_validators(2), vec![4, 5, 6]);
+		});
+	}
+
+	#[test]
+	fn prunes_sessions_out_of_the_history() {
+		new_test_ext().execute_with(|| {
+			author_block(1, 0, None);
+			assert_eq!(ValidatorPerformance::earliest_session(), Some(0));
+
+			for session in 1..5 {
+				author_block(session * 10, 0, None);
+			}
+			assert_eq!(Session::current_index(), 4);
+			assert_eq!(ValidatorPerformance::earliest_session(), Some(2));
+			assert!(!<SessionValidators<Test>>::exists(1));
+			assert_eq!(ValidatorPerformance::performance(1, 1), Default::default());
+			assert_eq!(ValidatorPerformance::history(&1).len(), 3);
+			assert_eq!(ValidatorPerformance::session_validators(4), vec![1, 2, 3]);
+		});
+	}
+}
@@ -15,12 +15,13 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use sp_std::{cmp::Ordering, prelude::*};
+use codec::{Encode, Decode};
 use crate::helpers_128bit;
 use num_traits::Zero;
 use sp_debug_derive::RuntimeDebug;
 
 /// A wrapper for any rational number with a 128 bit numerator and denominator.
-#[derive(Clone, Copy, Default, Eq, RuntimeDebug)]
+#[derive(Clone, Copy, Default, Eq, Encode, Decode, RuntimeDebug)]
 pub struct Rational128(u128, u128);
 
 impl Rational128 {
@@ -18,7 +18,8 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use sp_runtime::{traits::Block as BlockT, ApplyExtrinsicResult};
+use codec::{Encode, Decode};
+use sp_runtime::{traits::Block as BlockT, ApplyExtrinsicResult, RuntimeDebug};
 
 use sp_inherents::{InherentData, CheckInherentsResult};
 
@@ -41,9 +42,34 @@ pub mod compatability_v3 {
 	pub type ApplyResult = Result<DispatchOutcome, ApplyError>;
 }
 
+/// The weight of the block being built.
+#[derive(Eq, PartialEq, Clone, Copy, Default, Encode, Decode, RuntimeDebug)]
+pub struct BlockWeight {
+	/// Weight reserved by the `on_initialize` and `on_finalize` hooks of the runtime.
+	pub hooks: u64,
+	/// Weight consumed so far, the weight of the hooks included.
+	pub consumed: u64,
+	/// Weight up to which the block accepts normal transactions.
+	pub normal_limit: u64,
+	/// Weight up to which the block accepts operational transactions, the maximum block weight.
+	pub maximum: u64,
+}
+
+impl BlockWeight {
+	/// The weight left for normal transactions.
+	pub fn remaining(&self) -> u64 {
+		self.normal_limit.saturating_sub(self.consumed)
+	}
+
+	/// The weight left for operational transactions, which no transaction can exceed.
+	pub fn remaining_operational(&self) -> u64 {
+		self.maximum.saturating_sub(self.consumed)
+	}
+}
+
 sp_api::decl_runtime_apis! {
 	/// The `BlockBuilder` api trait that provides the required functionality for building a block.
-	#[api_version(4)]
+	#[api_version(5)]
 	pub trait BlockBuilder {
 		/// Compatibility version of `apply_extrinsic` for v3.
 		///
@@ -69,5 +95,9 @@ sp_api::decl_runtime_apis! {
 		fn check_inherents(block: Block, data: InherentData) -> CheckInherentsResult;
 		/// Generate a random seed.
 		fn random_seed() -> <Block as BlockT>::Hash;
+		/// The weight of the current block, taking the weight of the hooks into account from the
+		/// block initialization on. A runtime without weight limits reports limits of
+		/// `u64::max_value()`.
+		fn block_weight() -> BlockWeight;
 	}
 }
@@ -123,6 +123,10 @@ pub enum Error {
 	/// Invalid calculated state root on block import.
 	#[display(fmt = "Calculated state root does not match.")]
 	InvalidStateRoot,
+	/// The block is in the list of known bad blocks.
+	#[display(fmt = "Block {} is known to be bad", _0)]
+	#[from(ignore)]
+	KnownBadBlock(String),
 	/// A convenience variant for String
 	#[display(fmt = "{}", _0)]
 	Msg(String),
@@ -156,6 +156,40 @@ pub struct BabeConfiguration {
 	pub secondary_slots: bool,
 }
 
+/// Configuration data used by the BABE consensus engine, as returned by version 1 of `BabeApi`.
+///
+/// Runtimes implementing this version predate secondary slots.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct BabeConfigurationV1 {
+	/// The slot duration in milliseconds for BABE.
+	pub slot_duration: u64,
+
+	/// The duration of epochs in slots.
+	pub epoch_length: SlotNumber,
+
+	/// A constant value that is used in the threshold calculation formula.
+	pub c: (u64, u64),
+
+	/// The authorities for the genesis epoch.
+	pub genesis_authorities: Vec<(AuthorityId, BabeAuthorityWeight)>,
+
+	/// The randomness for the genesis epoch.
+	pub randomness: [u8; VRF_OUTPUT_LENGTH],
+}
+
+impl From<BabeConfigurationV1> for BabeConfiguration {
+	fn from(config: BabeConfigurationV1) -> Self {
+		BabeConfiguration {
+			slot_duration: config.slot_duration,
+			epoch_length: config.epoch_length,
+			c: config.c,
+			genesis_authorities: config.genesis_authorities,
+			randomness: config.randomness,
+			secondary_slots: false,
+		}
+	}
+}
+
 #[cfg(feature = "std")]
 impl sp_consensus::SlotData for BabeConfiguration {
 	fn slot_duration(&self) -> u64 {
@@ -167,11 +201,18 @@ impl sp_consensus::SlotData for BabeConfiguration {
 
 sp_api::decl_runtime_apis! {
 	/// API necessary for block authorship with BABE.
+	#[api_version(2)]
 	pub trait BabeApi {
 		/// Return the configuration for BABE. Currently,
 		/// only the value provided by this type at genesis will be used.
 		///
 		/// Dynamic configuration may be supported in the future.
 		fn configuration() -> BabeConfiguration;
+
+		/// Return the configuration for BABE, without secondary slots.
+		///
+		/// Is callable by `configuration_before_version_2`.
+		#[changed_in(2)]
+		fn configuration() -> BabeConfigurationV1;
 	}
 }
@@ -20,6 +20,7 @@ use sp_runtime::{
 	Justification,
 	traits::{Block as BlockT, DigestItemFor, Header as HeaderT, NumberFor, HasherFor},
 };
+use codec::{Encode, Decode};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -71,7 +72,7 @@ impl ImportResult {
 }
 
 /// Block data origin.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
 pub enum BlockOrigin {
 	/// Genesis block built into the client.
 	Genesis,
@@ -33,10 +33,11 @@ use crate::block_import::{
 	BlockCheckParams, FinalityProofImport,
 };
 
-pub use basic_queue::BasicQueue;
+pub use basic_queue::{BasicQueue, set_worker_thread_setup};
 
 mod basic_queue;
 pub mod buffered_link;
+mod overflow;
 
 /// Shared block import struct used by the queue.
 pub type BoxBlockImport<B, Transaction> = Box<
@@ -120,6 +121,16 @@ pub trait ImportQueue<B: BlockT>: Send {
 	/// task and notify later when more actions are ready to be polled. To continue the comparison,
 	/// it is as if this method always returned `Poll::Pending`.
 	fn poll_actions(&mut self, cx: &mut futures::task::Context, link: &mut dyn Link<B>);
+
+	/// Write the blocks that don't fit in memory to a temporary file in `dir`.
+	fn set_overflow_dir(&mut self, _dir: std::path::PathBuf) {}
+
+	/// Returns `Some(true)` if the queue holds so many blocks that no more should be downloaded
+	/// for now, or `None` if the queue doesn't keep track of its size, in which case sync bounds
+	/// the number of blocks it hands over to the queue itself.
+	fn is_saturated(&self) -> Option<bool> {
+		None
+	}
 }
 
 /// Hooks that the verification queue can use to influence the synchronization
@@ -14,9 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{mem, pin::Pin, time::Duration, marker::PhantomData};
+use std::{mem, pin::Pin, path::PathBuf, time::Duration, marker::PhantomData};
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 use futures::{prelude::*, channel::mpsc, task::Context, task::Poll};
 use futures_timer::Delay;
+use parking_lot::RwLock;
 use sp_runtime::{Justification, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
 
 use crate::block_import::BlockOrigin;
@@ -24,9 +26,29 @@ use crate::import_queue::{
 	BlockImportResult, BlockImportError, Verifier, BoxBlockImport, BoxFinalityProofImport,
 	BoxJustificationImport, ImportQueue, Link, Origin,
 	IncomingBlock, import_single_block,
-	buffered_link::{self, BufferedLinkSender, BufferedLinkReceiver}
+	buffered_link::{self, BufferedLinkSender, BufferedLinkReceiver},
+	overflow::OverflowQueue,
 };
 
+/// Maximum number of blocks handed over to the background task at once.
+const MAX_BLOCKS_IN_WORKER: usize = 256;
+
+/// Default maximum number of blocks waiting in memory for the background task.
+const DEFAULT_MEMORY_LIMIT: usize = 1024;
+
+/// Number of waiting blocks above which the queue reports being saturated.
+const MAX_QUEUED_BLOCKS: usize = 16 * 1024;
+
+lazy_static::lazy_static! {
+	static ref WORKER_THREAD_SETUP: RwLock<Option<Arc<dyn Fn() + Send + Sync>>> = RwLock::new(None);
+}
+
+/// Set a function run on the worker thread of the queues created afterwards when it starts,
+/// e.g. to pin it to a CPU.
+pub fn set_worker_thread_setup(setup: impl Fn() + Send + Sync + 'static) {
+	*WORKER_THREAD_SETUP.write() = Some(Arc::new(setup));
+}
+
 /// Interface to a basic block import queue that is importing blocks sequentially in a separate
 /// task, with pluggable verification.
 pub struct BasicQueue<B: BlockT, Transaction> {
@@ -34,6 +56,10 @@ pub struct BasicQueue<B: BlockT, Transaction> {
 	sender: mpsc::UnboundedSender<ToWorkerMsg<B>>,
 	/// Results coming from the worker task.
 	result_port: BufferedLinkReceiver<B>,
+	/// Blocks waiting for the worker task to have room for them.
+	queued: OverflowQueue<B>,
+	/// Number of blocks handed over to the worker task and not imported yet.
+	in_worker: Arc<AtomicUsize>,
 	/// If it isn't possible to spawn the future in `future_to_spawn` (which is notably the case in
 	/// "no std" environment), we instead put it in `manual_poll`. It is then polled manually from
 	/// `poll_actions`.
@@ -55,17 +81,23 @@ impl<B: BlockT, Transaction: Send + 'static> BasicQueue<B, Transaction> {
 		finality_proof_import: Option<BoxFinalityProofImport<B>>,
 	) -> Self {
 		let (result_sender, result_port) = buffered_link::buffered_link();
+		let in_worker = Arc::new(AtomicUsize::new(0));
 		let (future, worker_sender) = BlockImportWorker::new(
 			result_sender,
+			in_worker.clone(),
 			verifier,
 			block_import,
 			justification_import,
 			finality_proof_import,
 		);
 
+		let setup = WORKER_THREAD_SETUP.read().clone();
 		let mut pool = futures::executor::ThreadPool::builder()
 			.name_prefix("import-queue-worker-")
 			.pool_size(1)
+			.after_start(move |_| if let Some(setup) = &setup {
+				setup();
+			})
 			.create()
 			.ok();
 
@@ -80,11 +112,26 @@ impl<B: BlockT, Transaction: Send + 'static> BasicQueue<B, Transaction> {
 		Self {
 			sender: worker_sender,
 			result_port,
+			queued: OverflowQueue::new(DEFAULT_MEMORY_LIMIT, std::env::temp_dir()),
+			in_worker,
 			manual_poll,
 			pool,
 			_phantom: PhantomData,
 		}
 	}
+
+	/// Hand the waiting blocks over to the worker task, as long as it has room for them.
+	fn dispatch_queued(&mut self) {
+		while self.in_worker.load(Ordering::SeqCst) < MAX_BLOCKS_IN_WORKER {
+			let (origin, blocks) = match self.queued.pop() {
+				Some(batch) => batch,
+				None => break,
+			};
+
+			self.in_worker.fetch_add(blocks.len(), Ordering::SeqCst);
+			let _ = self.sender.unbounded_send(ToWorkerMsg::ImportBlocks(origin, blocks));
+		}
+	}
 }
 
 impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction> {
@@ -94,7 +141,8 @@ impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction>
 		}
 
 		trace!(target: "sync", "Scheduling {} blocks for import", blocks.len());
-		let _ = self.sender.unbounded_send(ToWorkerMsg::ImportBlocks(origin, blocks));
+		self.queued.push((origin, blocks));
+		self.dispatch_queued();
 	}
 
 	fn import_justification(
@@ -135,6 +183,15 @@ impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction>
 		}
 
 		self.result_port.poll_actions(cx, link);
+		self.dispatch_queued();
+	}
+
+	fn set_overflow_dir(&mut self, dir: PathBuf) {
+		self.queued.set_dir(dir)
+	}
+
+	fn is_saturated(&self) -> Option<bool> {
+		Some(self.queued.len() > MAX_QUEUED_BLOCKS)
 	}
 }
 
@@ -148,6 +205,7 @@ enum ToWorkerMsg<B: BlockT> {
 
 struct BlockImportWorker<B: BlockT, Transaction> {
 	result_sender: BufferedLinkSender<B>,
+	in_worker: Arc<AtomicUsize>,
 	justification_import: Option<BoxJustificationImport<B>>,
 	finality_proof_import: Option<BoxFinalityProofImport<B>>,
 	delay_between_blocks: Duration,
@@ -157,6 +215,7 @@ struct BlockImportWorker<B: BlockT, Transaction> {
 impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 	fn new<V: 'static + Verifier<B>>(
 		result_sender: BufferedLinkSender<B>,
+		in_worker: Arc<AtomicUsize>,
 		verifier: V,
 		block_import: BoxBlockImport<B, Transaction>,
 		justification_import: Option<BoxJustificationImport<B>>,
@@ -166,6 +225,7 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 
 		let mut worker = BlockImportWorker {
 			result_sender,
+			in_worker,
 			justification_import,
 			finality_proof_import,
 			delay_between_blocks: Duration::new(0, 0),
@@ -259,9 +319,13 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 		blocks: Vec<IncomingBlock<B>>
 	) -> impl Future<Output = (BoxBlockImport<B, Transaction>, V)> {
 		let mut result_sender = self.result_sender.clone();
+		let in_worker = self.in_worker.clone();
 
 		import_many_blocks(block_import, origin, blocks, verifier, self.delay_between_blocks)
 			.then(move |(imported, count, results, block_import, verifier)| {
+				// Make room before reporting, so that the next batch is sent when the results
+				// are processed.
+				in_worker.fetch_sub(count, Ordering::SeqCst);
 				result_sender.blocks_processed(imported, count, results);
 				future::ready((block_import, verifier))
 			})
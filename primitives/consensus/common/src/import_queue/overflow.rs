@@ -0,0 +1,334 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Queue of blocks waiting for import, spilling to a temporary file.
+//!
+//! Up to a fixed number of blocks are kept in memory. Once that number is reached, further
+//! batches are appended to a temporary file and read back in order once the in-memory part
+//! has been drained. If the file can't be written, the batches are kept in memory behind the
+//! ones on disk.
+
+use std::{collections::VecDeque, fs, io::{self, Read, Seek, SeekFrom, Write}, path::PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use codec::{Encode, Decode};
+use log::warn;
+use sp_runtime::{Justification, traits::Block as BlockT};
+
+use crate::block_import::BlockOrigin;
+use crate::import_queue::{IncomingBlock, Origin};
+
+/// A batch of blocks, as passed to `ImportQueue::import_blocks`.
+pub type Batch<B> = (BlockOrigin, Vec<IncomingBlock<B>>);
+
+/// On-disk representation of an `IncomingBlock`.
+#[derive(Decode)]
+struct EncodedBlock<B: BlockT> {
+	hash: B::Hash,
+	header: Option<B::Header>,
+	body: Option<Vec<B::Extrinsic>>,
+	justification: Option<Justification>,
+	origin: Option<Vec<u8>>,
+	allow_missing_state: bool,
+	import_existing: bool,
+}
+
+impl<B: BlockT> From<EncodedBlock<B>> for IncomingBlock<B> {
+	fn from(block: EncodedBlock<B>) -> Self {
+		IncomingBlock {
+			hash: block.hash,
+			header: block.header,
+			body: block.body,
+			justification: block.justification,
+			origin: block.origin.and_then(|origin| Origin::from_bytes(origin).ok()),
+			allow_missing_state: block.allow_missing_state,
+			import_existing: block.import_existing,
+		}
+	}
+}
+
+/// First-in first-out queue of batches of blocks with a bounded in-memory part.
+pub struct OverflowQueue<B: BlockT> {
+	memory: VecDeque<Batch<B>>,
+	memory_blocks: usize,
+	memory_limit: usize,
+	disk: Option<DiskQueue>,
+	disk_blocks: usize,
+	/// Batches that failed to be written to disk, coming after the ones on disk.
+	tail: VecDeque<Batch<B>>,
+	tail_blocks: usize,
+	dir: PathBuf,
+}
+
+impl<B: BlockT> OverflowQueue<B> {
+	/// Create a queue keeping at most `memory_limit` blocks in memory, and writing the overflow
+	/// to a temporary file in `dir`.
+	pub fn new(memory_limit: usize, dir: PathBuf) -> Self {
+		OverflowQueue {
+			memory: VecDeque::new(),
+			memory_blocks: 0,
+			memory_limit,
+			disk: None,
+			disk_blocks: 0,
+			tail: VecDeque::new(),
+			tail_blocks: 0,
+			dir,
+		}
+	}
+
+	/// Write the overflow to a temporary file in `dir` from now on.
+	pub fn set_dir(&mut self, dir: PathBuf) {
+		self.dir = dir;
+		if self.disk_blocks == 0 {
+			self.disk = None;
+		}
+	}
+
+	/// Total number of queued blocks.
+	pub fn len(&self) -> usize {
+		self.memory_blocks + self.disk_blocks + self.tail_blocks
+	}
+
+	/// Number of queued blocks that have been written to disk.
+	pub fn len_on_disk(&self) -> usize {
+		self.disk_blocks
+	}
+
+	/// Append a batch at the end of the queue.
+	pub fn push(&mut self, batch: Batch<B>) {
+		// Once anything has been written to disk, later batches have to follow it there in
+		// order to preserve the ordering.
+		if self.disk_blocks == 0 && self.tail_blocks == 0
+			&& self.memory_blocks + batch.1.len() <= self.memory_limit
+		{
+			self.memory_blocks += batch.1.len();
+			self.memory.push_back(batch);
+			return;
+		}
+
+		if self.tail_blocks == 0 {
+			match self.disk_queue().and_then(|disk| disk.push(&encode_batch(&batch))) {
+				Ok(()) => {
+					self.disk_blocks += batch.1.len();
+					return;
+				},
+				Err(err) => warn!(
+					target: "sync",
+					"Failed to write blocks to the import queue file: {}",
+					err,
+				),
+			}
+		}
+
+		// The blocks are kept in memory after the ones on disk.
+		self.tail_blocks += batch.1.len();
+		self.tail.push_back(batch);
+	}
+
+	/// Remove the batch at the front of the queue.
+	pub fn pop(&mut self) -> Option<Batch<B>> {
+		if let Some(batch) = self.memory.pop_front() {
+			self.memory_blocks -= batch.1.len();
+			return Some(batch);
+		}
+
+		if self.disk_blocks == 0 {
+			let batch = self.tail.pop_front()?;
+			self.tail_blocks -= batch.1.len();
+			return Some(batch);
+		}
+
+		let disk = self.disk.as_mut()?;
+		let read = disk.pop().and_then(|encoded| {
+			Decode::decode(&mut &encoded[..])
+				.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.what()))
+		});
+		match read {
+			Ok(batch) => {
+				let batch = decode_batch(batch);
+				self.disk_blocks -= batch.1.len();
+				Some(batch)
+			},
+			Err(err) => {
+				warn!(target: "sync", "Failed to read blocks from the import queue file: {}", err);
+				// Whatever is left in the file is lost. Sync requests the missing blocks again.
+				self.disk = None;
+				self.disk_blocks = 0;
+				self.pop()
+			},
+		}
+	}
+
+	fn disk_queue(&mut self) -> io::Result<&mut DiskQueue> {
+		if self.disk.is_none() {
+			self.disk = Some(DiskQueue::create(&self.dir)?);
+		}
+		Ok(self.disk.as_mut().expect("Set to `Some` just above; qed"))
+	}
+}
+
+/// Encode a batch the way `EncodedBlock` decodes it.
+fn encode_batch<B: BlockT>((origin, blocks): &Batch<B>) -> Vec<u8> {
+	let mut encoded = origin.encode();
+	codec::Compact(blocks.len() as u32).encode_to(&mut encoded);
+	for block in blocks {
+		block.hash.encode_to(&mut encoded);
+		block.header.encode_to(&mut encoded);
+		block.body.encode_to(&mut encoded);
+		block.justification.encode_to(&mut encoded);
+		block.origin.as_ref().map(|origin| origin.as_bytes()).encode_to(&mut encoded);
+		block.allow_missing_state.encode_to(&mut encoded);
+		block.import_existing.encode_to(&mut encoded);
+	}
+	encoded
+}
+
+fn decode_batch<B: BlockT>((origin, blocks): (BlockOrigin, Vec<EncodedBlock<B>>)) -> Batch<B> {
+	(origin, blocks.into_iter().map(Into::into).collect())
+}
+
+/// Length-prefixed records in a temporary file, removed when dropped.
+struct DiskQueue {
+	path: PathBuf,
+	file: fs::File,
+	read_pos: u64,
+	write_pos: u64,
+}
+
+impl DiskQueue {
+	fn create(dir: &PathBuf) -> io::Result<Self> {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+		fs::create_dir_all(dir)?;
+		let path = dir.join(format!(
+			"import-queue-{}-{}",
+			std::process::id(),
+			COUNTER.fetch_add(1, Ordering::Relaxed),
+		));
+		let file = fs::OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+		Ok(DiskQueue { path, file, read_pos: 0, write_pos: 0 })
+	}
+
+	fn push(&mut self, record: &[u8]) -> io::Result<()> {
+		self.file.seek(SeekFrom::Start(self.write_pos))?;
+		self.file.write_all(&(record.len() as u64).to_le_bytes())?;
+		self.file.write_all(record)?;
+		self.write_pos += 8 + record.len() as u64;
+		Ok(())
+	}
+
+	fn pop(&mut self) -> io::Result<Vec<u8>> {
+		self.file.seek(SeekFrom::Start(self.read_pos))?;
+		let mut len = [0u8; 8];
+		self.file.read_exact(&mut len)?;
+		let mut record = vec![0; u64::from_le_bytes(len) as usize];
+		self.file.read_exact(&mut record)?;
+		self.read_pos += 8 + record.len() as u64;
+
+		// Reclaim the space once everything has been read.
+		if self.read_pos == self.write_pos && self.file.set_len(0).is_ok() {
+			self.read_pos = 0;
+			self.write_pos = 0;
+		}
+		Ok(record)
+	}
+}
+
+impl Drop for DiskQueue {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::traits::Header as _;
+	use sp_test_primitives::{Block, Header};
+
+	fn batch(numbers: std::ops::Range<u64>) -> Batch<Block> {
+		(BlockOrigin::NetworkInitialSync, numbers.map(|number| {
+			let header = Header {
+				parent_hash: Default::default(),
+				number,
+				state_root: Default::default(),
+				extrinsics_root: Default::default(),
+				digest: Default::default(),
+			};
+			IncomingBlock {
+				hash: header.hash(),
+				header: Some(header),
+				body: Some(Vec::new()),
+				justification: None,
+				origin: Some(Origin::random()),
+				allow_missing_state: false,
+				import_existing: false,
+			}
+		}).collect())
+	}
+
+	#[test]
+	fn overflow_goes_to_disk_and_preserves_order() {
+		let mut queue = OverflowQueue::<Block>::new(4, std::env::temp_dir());
+		let batches = vec![batch(0..3), batch(3..6), batch(6..7), batch(7..9)];
+		for batch in batches.clone() {
+			queue.push(batch);
+		}
+
+		// only the first batch fits in memory, and later batches line up behind the ones on disk
+		assert_eq!(queue.len(), 9);
+		assert_eq!(queue.len_on_disk(), 6);
+
+		let mut popped = Vec::new();
+		while let Some(batch) = queue.pop() {
+			popped.push(batch);
+		}
+		assert_eq!(popped, batches);
+		assert_eq!(queue.len(), 0);
+
+		// the file is reused once drained
+		let batch = batch(9..15);
+		queue.push(batch.clone());
+		assert_eq!(queue.len_on_disk(), 6);
+		assert_eq!(queue.pop(), Some(batch));
+	}
+
+	#[test]
+	fn failed_writes_preserve_order() {
+		let dir = std::env::temp_dir().join(format!("import-queue-test-{}", std::process::id()));
+		let mut queue = OverflowQueue::<Block>::new(4, dir.clone());
+		let batches = vec![batch(0..3), batch(3..6), batch(6..7), batch(7..9)];
+		queue.push(batches[0].clone());
+		queue.push(batches[1].clone());
+		assert_eq!(queue.len_on_disk(), 3);
+
+		// the file can no longer be written to, so later batches wait in memory behind the ones
+		// on disk
+		queue.disk.as_mut().unwrap().file = fs::OpenOptions::new()
+			.read(true)
+			.open(&queue.disk.as_ref().unwrap().path)
+			.unwrap();
+		queue.push(batches[2].clone());
+		queue.push(batches[3].clone());
+		assert_eq!(queue.len(), 9);
+		assert_eq!(queue.len_on_disk(), 3);
+
+		let mut popped = Vec::new();
+		while let Some(batch) = queue.pop() {
+			popped.push(batch);
+		}
+		assert_eq!(popped, batches);
+		let _ = fs::remove_dir_all(&dir);
+	}
+}
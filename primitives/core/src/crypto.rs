@@ -417,6 +417,16 @@ macro_rules! ss58_address_format {
 			}
 		}
 
+		/// Deserialized from its number, e.g. the `ss58Format` property of chain specs, as a
+		/// known network or a custom format.
+		#[cfg(feature = "std")]
+		impl<'de> serde::Deserialize<'de> for Ss58AddressFormat {
+			fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				let format = u8::deserialize(deserializer)?;
+				Ok(Ss58AddressFormat::try_from(format).unwrap_or(Ss58AddressFormat::Custom(format)))
+			}
+		}
+
 		#[cfg(feature = "std")]
 		impl From<Ss58AddressFormat> for String {
 			fn from(x: Ss58AddressFormat) -> String {
@@ -624,6 +634,146 @@ impl<'de> serde::Deserialize<'de> for AccountId32 {
 	}
 }
 
+/// An opaque 20-byte account identifier, derived from ECDSA public keys the way Ethereum
+/// addresses are.
+///
+/// It is displayed and serialized as `0x`-prefixed hex checksummed as in EIP-55, rather than
+/// as SS58, so the same address can be used in Ethereum wallets.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Default, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Hash))]
+pub struct AccountId20([u8; 20]);
+
+#[cfg(feature = "std")]
+impl AccountId20 {
+	/// The `0x`-prefixed hex of the address, with the case of the letters encoding the
+	/// checksum of EIP-55.
+	pub fn to_checksummed_hex(&self) -> String {
+		let hex = hex::encode(self.0);
+		let hash = crate::hashing::keccak_256(hex.as_bytes());
+		let checksummed = hex.char_indices()
+			.map(|(i, c)| {
+				let nibble = if i % 2 == 0 { hash[i / 2] >> 4 } else { hash[i / 2] & 0x0f };
+				if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+			})
+			.collect::<String>();
+		format!("0x{}", checksummed)
+	}
+
+	/// Parse the `0x`-prefixed hex of an address. The checksum is only verified if the letters
+	/// are of mixed case, as addresses in all lower or upper case carry none.
+	pub fn from_checksummed_hex(s: &str) -> Result<Self, PublicError> {
+		if !s.starts_with("0x") {
+			return Err(PublicError::InvalidFormat)
+		}
+		let hex = &s[2..];
+		if hex.len() != 40 {
+			return Err(PublicError::BadLength)
+		}
+		let mut address = [0u8; 20];
+		hex::decode_to_slice(hex, &mut address).map_err(|_| PublicError::InvalidFormat)?;
+		let address = AccountId20(address);
+
+		let is_mixed_case = hex.chars().any(|c| c.is_ascii_lowercase())
+			&& hex.chars().any(|c| c.is_ascii_uppercase());
+		if is_mixed_case && address.to_checksummed_hex()[2..] != *hex {
+			return Err(PublicError::InvalidChecksum)
+		}
+		Ok(address)
+	}
+}
+
+impl AsRef<[u8]> for AccountId20 {
+	fn as_ref(&self) -> &[u8] {
+		&self.0[..]
+	}
+}
+
+impl AsMut<[u8]> for AccountId20 {
+	fn as_mut(&mut self) -> &mut [u8] {
+		&mut self.0[..]
+	}
+}
+
+impl AsRef<[u8; 20]> for AccountId20 {
+	fn as_ref(&self) -> &[u8; 20] {
+		&self.0
+	}
+}
+
+impl AsMut<[u8; 20]> for AccountId20 {
+	fn as_mut(&mut self) -> &mut [u8; 20] {
+		&mut self.0
+	}
+}
+
+impl From<[u8; 20]> for AccountId20 {
+	fn from(x: [u8; 20]) -> AccountId20 {
+		AccountId20(x)
+	}
+}
+
+impl<'a> sp_std::convert::TryFrom<&'a [u8]> for AccountId20 {
+	type Error = ();
+	fn try_from(x: &'a [u8]) -> Result<AccountId20, ()> {
+		if x.len() == 20 {
+			let mut r = AccountId20::default();
+			r.0.copy_from_slice(x);
+			Ok(r)
+		} else {
+			Err(())
+		}
+	}
+}
+
+impl From<AccountId20> for [u8; 20] {
+	fn from(x: AccountId20) -> [u8; 20] {
+		x.0
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for AccountId20 {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}", self.to_checksummed_hex())
+	}
+}
+
+impl sp_std::fmt::Debug for AccountId20 {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		write!(f, "{}", self.to_checksummed_hex())
+	}
+
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		Ok(())
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::str::FromStr for AccountId20 {
+	type Err = PublicError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_checksummed_hex(s)
+	}
+}
+
+#[cfg(feature = "std")]
+impl serde::Serialize for AccountId20 {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+		serializer.serialize_str(&self.to_checksummed_hex())
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'de> serde::Deserialize<'de> for AccountId20 {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+		Self::from_checksummed_hex(&String::deserialize(deserializer)?)
+			.map_err(|e| serde::de::Error::custom(format!("{:?}", e)))
+	}
+}
+
 #[cfg(feature = "std")]
 pub use self::dummy::*;
 
@@ -936,6 +1086,8 @@ pub mod key_types {
 	pub const IM_ONLINE: KeyTypeId = KeyTypeId(*b"imon");
 	/// Key type for AuthorityDiscovery module, built-in.
 	pub const AUTHORITY_DISCOVERY: KeyTypeId = KeyTypeId(*b"audi");
+	/// Key type for Oracle module, built-in.
+	pub const ORACLE: KeyTypeId = KeyTypeId(*b"orcl");
 	/// A key type ID useful for tests.
 	pub const DUMMY: KeyTypeId = KeyTypeId(*b"dumy");
 }
@@ -1101,4 +1253,41 @@ mod tests {
 			Ok(TestPair::Standard{phrase: "hello world".to_owned(), password: Some("password".to_owned()), path: vec![DeriveJunction::soft(1), DeriveJunction::hard("DOT")]})
 		);
 	}
+
+	#[test]
+	fn account_id_20_checksum_works() {
+		// Test vectors of EIP-55.
+		for address in &[
+			"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+			"0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+			"0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+			"0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+		] {
+			let account = AccountId20::from_checksummed_hex(address).unwrap();
+			assert_eq!(&account.to_string(), address);
+			assert_eq!(AccountId20::from_checksummed_hex(&address.to_lowercase()), Ok(account));
+		}
+
+		assert_eq!(
+			AccountId20::from_checksummed_hex("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+			Err(PublicError::InvalidChecksum),
+		);
+		assert_eq!(
+			AccountId20::from_checksummed_hex("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+			Err(PublicError::InvalidFormat),
+		);
+		assert_eq!(AccountId20::from_checksummed_hex("0x5aAeb605"), Err(PublicError::BadLength));
+	}
+
+	#[test]
+	fn ss58_address_format_is_deserialized_from_its_number() {
+		let format = |format| serde_json::from_value::<Ss58AddressFormat>(format).map(u8::from);
+
+		assert_eq!(format(serde_json::json!(2)).unwrap(), 2);
+		assert!(!serde_json::from_value::<Ss58AddressFormat>(serde_json::json!(2)).unwrap().is_custom());
+		assert_eq!(format(serde_json::json!(200)).unwrap(), 200);
+		assert!(serde_json::from_value::<Ss58AddressFormat>(serde_json::json!(200)).unwrap().is_custom());
+		assert!(format(serde_json::json!(256)).is_err());
+		assert!(format(serde_json::json!("kusama")).is_err());
+	}
 }
@@ -127,6 +127,26 @@ impl Public {
 	pub fn into_compressed(self) -> Result<Self, ()> {
 		self.as_compressed().map(Self::Compressed)
 	}
+
+	/// The Ethereum address of the key, the last 20 bytes of the Keccak-256 hash of the full
+	/// public key.
+	///
+	/// Returns an error if `self` is an invalid compressed public key.
+	#[cfg(feature = "full_crypto")]
+	pub fn to_eth_address(&self) -> Result<[u8; 20], ()> {
+		let full = match self {
+			Self::Full(d) => *d,
+			Self::Compressed(d) => {
+				let key = secp256k1::PublicKey::parse_compressed(d).map_err(|_| ())?;
+				let mut full = [0u8; 64];
+				full.copy_from_slice(&key.serialize()[1..]);
+				full
+			}
+		};
+		let mut address = [0u8; 20];
+		address.copy_from_slice(&crate::hashing::keccak_256(&full)[12..]);
+		Ok(address)
+	}
 }
 
 impl TraitPublic for Public {
@@ -525,6 +545,13 @@ impl Pair {
 		self.secret.serialize()
 	}
 
+	/// Sign a 32-byte message hash, such as the Keccak-256 hash signed by Ethereum accounts,
+	/// instead of the Blake2 hash of a message `sign` signs.
+	pub fn sign_prehashed(&self, message: &[u8; 32]) -> Signature {
+		let message = secp256k1::Message::parse(message);
+		secp256k1::sign(&message, &self.secret).into()
+	}
+
 	/// Exactly as `from_string` except that if no matches are found then, the the first 32
 	/// characters are taken (padded with spaces as necessary) and used as the MiniSecretKey.
 	#[cfg(feature = "std")]
@@ -202,6 +202,28 @@ pub trait Externalities: ExtensionStore {
 	///
 	/// Returns the SCALE encoded hash.
 	fn storage_changes_root(&mut self, parent: &[u8]) -> Result<Option<Vec<u8>>, ()>;
+
+	/// Set or clear, with `None`, the value of `key` in the offchain storage of the node.
+	///
+	/// The change is written to the persistent offchain storage when the block is imported, and
+	/// is not part of the state.
+	fn set_offchain_storage(&mut self, key: &[u8], value: Option<&[u8]>);
+
+	/// Start a storage transaction, nested in the ones already open.
+	///
+	/// The changes made from now on are rolled back by `storage_rollback_transaction`, or kept by
+	/// `storage_commit_transaction`, which both close the transaction.
+	fn storage_start_transaction(&mut self);
+
+	/// Roll back the changes made since the last open transaction started, and close it.
+	///
+	/// Returns `Err` if no transaction is open.
+	fn storage_rollback_transaction(&mut self) -> Result<(), ()>;
+
+	/// Keep the changes made since the last open transaction started, and close it.
+	///
+	/// Returns `Err` if no transaction is open.
+	fn storage_commit_transaction(&mut self) -> Result<(), ()>;
 }
 
 /// Extension for the [`Externalities`] trait.
@@ -14,63 +14,55 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-//! SRML module that tracks the last finalized block, as perceived by block authors.
+//! Primitives for tracking the last finalized block, as perceived by block authors.
+//!
+//! Block authors put the number of the best block finalized by their client into a pre-runtime
+//! digest with the GRANDPA engine id. The client only considers blocks final after importing a
+//! GRANDPA justification for them, so the hint follows finality proofs instead of being provided
+//! as inherent data. The GRANDPA block import of the other nodes rejects blocks hinting at blocks
+//! they don't know to be final.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use sp_inherents::{InherentIdentifier, InherentData, Error};
-use codec::Decode;
+use codec::{Encode, Decode};
+use sp_runtime::{ConsensusEngineId, DigestItem};
+use sp_finality_grandpa::GRANDPA_ENGINE_ID;
 
-#[cfg(feature = "std")]
-use codec::Encode;
+/// The engine id of the pre-runtime digest carrying the finality hint.
+pub const FINALITY_HINT_ENGINE_ID: ConsensusEngineId = GRANDPA_ENGINE_ID;
 
-/// The identifier for the `finalnum` inherent.
-pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"finalnum";
-
-/// Auxiliary trait to extract finalized inherent data.
-pub trait FinalizedInherentData<N: Decode> {
-	/// Get finalized inherent data.
-	fn finalized_number(&self) -> Result<N, Error>;
+/// Create the pre-runtime digest item hinting that the given block number is finalized.
+pub fn finality_hint_digest<Hash, N: Encode>(finalized_number: N) -> DigestItem<Hash> {
+	DigestItem::PreRuntime(FINALITY_HINT_ENGINE_ID, finalized_number.encode())
 }
 
-impl<N: Decode> FinalizedInherentData<N> for InherentData {
-	fn finalized_number(&self) -> Result<N, Error> {
-		self.get_data(&INHERENT_IDENTIFIER)
-			.and_then(|r| r.ok_or_else(|| "Finalized number inherent data not found".into()))
-	}
-}
-
-/// Provider for inherent data.
-#[cfg(feature = "std")]
-pub struct InherentDataProvider<F, N> {
-	inner: F,
-	_marker: std::marker::PhantomData<N>,
+/// Find the finality hint among the given pre-runtime digests.
+///
+/// Returns `None` if there is no hint or it could not be decoded.
+pub fn find_finality_hint<'a, N, I>(pre_runtime_digests: I) -> Option<N> where
+	N: Decode,
+	I: IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+{
+	pre_runtime_digests.into_iter()
+		.find(|(id, _)| *id == FINALITY_HINT_ENGINE_ID)
+		.and_then(|(_, mut data)| N::decode(&mut data).ok())
 }
 
-#[cfg(feature = "std")]
-impl<F, N> InherentDataProvider<F, N> {
-	pub fn new(final_oracle: F) -> Self {
-		InherentDataProvider { inner: final_oracle, _marker: Default::default() }
-	}
-}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::testing::H256;
 
-#[cfg(feature = "std")]
-impl<F, N: Encode> sp_inherents::ProvideInherentData for InherentDataProvider<F, N>
-	where F: Fn() -> Result<N, Error>
-{
-	fn inherent_identifier(&self) -> &'static InherentIdentifier {
-		&INHERENT_IDENTIFIER
-	}
+	#[test]
+	fn finality_hint_roundtrips() {
+		let digest = finality_hint_digest::<H256, u64>(42);
+		let other = DigestItem::<H256>::PreRuntime(*b"BABE", vec![1, 2, 3]);
 
-	fn provide_inherent_data(
-		&self,
-		inherent_data: &mut InherentData,
-	) -> Result<(), Error> {
-		(self.inner)()
-			.and_then(|n| inherent_data.put_data(INHERENT_IDENTIFIER, &n))
-	}
+		let logs = vec![other, digest];
+		let hint = find_finality_hint::<u64, _>(logs.iter().filter_map(|d| d.as_pre_runtime()));
+		assert_eq!(hint, Some(42));
 
-	fn error_to_string(&self, _error: &[u8]) -> Option<String> {
-		Some(format!("no further information"))
+		let logs: Vec<DigestItem<H256>> = Vec::new();
+		assert_eq!(find_finality_hint::<u64, _>(logs.iter().filter_map(|d| d.as_pre_runtime())), None);
 	}
 }
@@ -0,0 +1,102 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The genesis builder runtime api.
+//!
+//! Lets the runtime build its own genesis state from a JSON config, so clients only provide
+//! the values that differ from the runtime defaults instead of assembling the config types.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::vec::Vec;
+
+/// The result of building the genesis state, the error being a UTF-8 encoded message.
+pub type Result = sp_std::result::Result<(), Vec<u8>>;
+
+sp_api::decl_runtime_apis! {
+	/// API to build the genesis state using the genesis logic of the runtime.
+	pub trait GenesisBuilder {
+		/// The JSON encoded default genesis config of the runtime.
+		fn create_default_config() -> Vec<u8>;
+
+		/// Build the genesis state from the given JSON encoded config, writing it to storage.
+		///
+		/// The config is usually the default config with a patch applied by `merge_patch`.
+		fn build_config(json: Vec<u8>) -> Result;
+	}
+}
+
+/// Apply a JSON merge patch (RFC 7386) to `base`.
+///
+/// Objects are merged recursively and `null` removes a key, also within the objects added by
+/// the patch. Any other value replaces the existing one.
+#[cfg(feature = "std")]
+pub fn merge_patch(base: &mut serde_json::Value, patch: serde_json::Value) {
+	use serde_json::{Map, Value};
+
+	match patch {
+		Value::Object(patch) => {
+			if !base.is_object() {
+				*base = Value::Object(Map::new());
+			}
+			let base = base.as_object_mut().expect("Replaced by an object above; qed");
+			for (key, value) in patch {
+				if value.is_null() {
+					base.remove(&key);
+				} else {
+					merge_patch(base.entry(key).or_insert(Value::Null), value);
+				}
+			}
+		},
+		patch => *base = patch,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn merge_patch_works() {
+		let mut base = json!({
+			"balances": { "balances": [["alice", 1]], "vesting": [] },
+			"sudo": { "key": "alice" },
+		});
+		merge_patch(&mut base, json!({
+			"balances": { "balances": [["bob", 2]] },
+			"sudo": null,
+		}));
+		assert_eq!(base, json!({ "balances": { "balances": [["bob", 2]], "vesting": [] } }));
+	}
+
+	#[test]
+	fn merge_patch_removes_nested_nulls() {
+		let mut base = json!({ "sudo": "alice", "staking": [1] });
+		merge_patch(&mut base, json!({
+			"sudo": { "key": "bob", "removed": null },
+			"staking": { "validatorCount": 2, "invulnerables": { "stash": null } },
+			"treasury": { "pot": null, "spend": { "period": 1, "burn": null } },
+			"balances": [null],
+		}));
+		assert_eq!(base, json!({
+			"sudo": { "key": "bob" },
+			"staking": { "validatorCount": 2, "invulnerables": {} },
+			"treasury": { "spend": { "period": 1 } },
+			"balances": [null],
+		}));
+	}
+}
@@ -295,6 +295,11 @@ impl InherentDataProviders {
 		self.providers.read().iter().any(|p| p.inherent_identifier() == identifier)
 	}
 
+	/// Returns the identifiers of all registered providers, in registration order.
+	pub fn identifiers(&self) -> Vec<InherentIdentifier> {
+		self.providers.read().iter().map(|p| *p.inherent_identifier()).collect()
+	}
+
 	/// Create inherent data.
 	pub fn create_inherent_data(&self) -> Result<InherentData, Error> {
 		let mut data = InherentData::new();
@@ -328,6 +333,33 @@ impl InherentDataProviders {
 			)
 		}
 	}
+
+	/// Converts all given encoded errors into one `String`.
+	///
+	/// Every error is decoded by the provider registered for its identifier and prefixed with
+	/// that identifier, so that all reasons why the runtime rejected the inherents are reported.
+	pub fn errors_to_string<I>(&self, errors: I) -> String
+		where I: IntoIterator<Item = (InherentIdentifier, Vec<u8>)>
+	{
+		errors.into_iter()
+			.map(|(i, e)| format!("\"{}\": {}", String::from_utf8_lossy(&i), self.error_to_string(&i, &e)))
+			.collect::<Vec<_>>()
+			.join("; ")
+	}
+
+	/// Converts the errors of a failed `CheckInherentsResult` into one `String`.
+	///
+	/// See `errors_to_string`. A fatal error is marked as such.
+	pub fn check_result_to_string(&self, result: CheckInherentsResult) -> String {
+		let fatal = result.fatal_error();
+		let errors = self.errors_to_string(result.into_errors());
+
+		if fatal {
+			format!("fatal: {}", errors)
+		} else {
+			errors
+		}
+	}
 }
 
 /// Something that provides inherent data.
@@ -541,6 +573,27 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn all_check_errors_are_decoded() {
+		let provider = TestInherentDataProvider::new();
+		let providers = InherentDataProviders::new();
+
+		providers.register_provider(provider.clone()).unwrap();
+		assert_eq!(providers.identifiers(), vec![TEST_INHERENT_0]);
+
+		let mut result = CheckInherentsResult::new();
+		result.put_error(TEST_INHERENT_0, &NoFatalError(2u32)).unwrap();
+		result.put_error(TEST_INHERENT_1, &NoFatalError(3u32)).unwrap();
+
+		let message = providers.check_result_to_string(result);
+		assert!(message.starts_with("\"testinh0\": Found error!; \"testinh1\": "));
+		assert!(message.contains("inherent type is unknown"));
+
+		let mut result = CheckInherentsResult::new();
+		result.put_error(TEST_INHERENT_0, &MakeFatalError(2u32)).unwrap();
+		assert_eq!(providers.check_result_to_string(result), "fatal: \"testinh0\": Found error!");
+	}
+
 	#[test]
 	fn check_inherents_result_encodes_and_decodes() {
 		let mut result = CheckInherentsResult::new();
@@ -35,7 +35,7 @@ use sp_std::ops::Deref;
 #[cfg(feature = "std")]
 use sp_core::{
 	crypto::Pair,
-	traits::KeystoreExt,
+	traits::{KeystoreExt, CallInWasmExt},
 	offchain::{OffchainExt, TransactionPoolExt},
 	hexdisplay::HexDisplay,
 	storage::{ChildStorageKey, ChildInfo},
@@ -311,6 +311,30 @@ pub trait Storage {
 			.expect("Invalid child definition");
 		self.next_child_storage_key(storage_key, child_info, key)
 	}
+
+	/// Start a storage transaction, nested in the ones already open.
+	///
+	/// For every transaction started, there must be a matching call to either
+	/// `rollback_transaction` or `commit_transaction`.
+	fn start_transaction(&mut self) {
+		self.storage_start_transaction();
+	}
+
+	/// Roll back the changes made since the last open transaction started, and close it.
+	///
+	/// Panics if no transaction is open.
+	fn rollback_transaction(&mut self) {
+		self.storage_rollback_transaction()
+			.expect("No open storage transaction to roll back.");
+	}
+
+	/// Keep the changes made since the last open transaction started, and close it.
+	///
+	/// Panics if no transaction is open.
+	fn commit_transaction(&mut self) {
+		self.storage_commit_transaction()
+			.expect("No open storage transaction to commit.");
+	}
 }
 
 /// Interface that provides trie related functionality.
@@ -351,6 +375,24 @@ pub trait Misc {
 	fn print_hex(data: &[u8]) {
 		log::debug!(target: "runtime", "{}", HexDisplay::from(&data));
 	}
+
+	/// Extract the runtime version of the given wasm blob by calling `Core_version`.
+	///
+	/// Returns the SCALE encoded runtime version, or `None` if the call failed.
+	///
+	/// # Performance
+	///
+	/// The wasm blob is instantiated to call `Core_version`, which is very expensive. This should
+	/// only be done very occasionally, e.g. when upgrading the runtime.
+	fn runtime_version(&mut self, wasm: &[u8]) -> Option<Vec<u8>> {
+		// `Core_version` doesn't access the storage, run it on empty externalities.
+		let mut ext = sp_state_machine::BasicExternalities::default();
+
+		self.extension::<CallInWasmExt>()
+			.expect("No `CallInWasmExt` associated for the current context!")
+			.call_in_wasm(wasm, "Core_version", &[], &mut ext)
+			.ok()
+	}
 }
 
 /// Interfaces for working with crypto related types from within the runtime.
@@ -530,6 +572,23 @@ pub trait Hashing {
 	}
 }
 
+/// Interface that provides functions for indexing data into the offchain storage while the
+/// block is executed.
+#[runtime_interface]
+pub trait OffchainIndex {
+	/// Write `value` under `key` in the persistent offchain storage of the node.
+	///
+	/// The value is written when the block is imported, and is not part of the state.
+	fn set(&mut self, key: &[u8], value: &[u8]) {
+		self.set_offchain_storage(key, Some(value));
+	}
+
+	/// Remove `key` from the persistent offchain storage of the node.
+	fn clear(&mut self, key: &[u8]) {
+		self.set_offchain_storage(key, None);
+	}
+}
+
 /// Interface that provides functions to access the offchain functionality.
 #[runtime_interface]
 pub trait Offchain {
@@ -891,6 +950,7 @@ pub type SubstrateHostFunctions = (
 	storage::HostFunctions,
 	misc::HostFunctions,
 	offchain::HostFunctions,
+	offchain_index::HostFunctions,
 	crypto::HostFunctions,
 	hashing::HostFunctions,
 	allocator::HostFunctions,
@@ -34,6 +34,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use sp_std::{prelude::*, collections::btree_map::BTreeMap};
+use codec::{Encode, Decode};
 use sp_runtime::RuntimeDebug;
 use sp_runtime::{helpers_128bit::multiply_by_rational, Perbill, Rational128};
 use sp_runtime::traits::{Zero, Convert, Member, SimpleArithmetic, Saturating, Bounded};
@@ -57,7 +58,7 @@ pub type ExtendedBalance = u128;
 const DEN: u128 = u128::max_value();
 
 /// A candidate entity for phragmen election.
-#[derive(Clone, Default, RuntimeDebug)]
+#[derive(Clone, Default, Encode, Decode, RuntimeDebug)]
 pub struct Candidate<AccountId> {
 	/// Identifier.
 	pub who: AccountId,
@@ -70,7 +71,7 @@ pub struct Candidate<AccountId> {
 }
 
 /// A voter entity.
-#[derive(Clone, Default, RuntimeDebug)]
+#[derive(Clone, Default, Encode, Decode, RuntimeDebug)]
 pub struct Voter<AccountId> {
 	/// Identifier.
 	who: AccountId,
@@ -83,14 +84,14 @@ pub struct Voter<AccountId> {
 }
 
 /// A candidate being backed by a voter.
-#[derive(Clone, Default, RuntimeDebug)]
+#[derive(Clone, Default, Encode, Decode, RuntimeDebug)]
 pub struct Edge<AccountId> {
 	/// Identifier.
 	who: AccountId,
 	/// Load of this vote.
 	load: Rational128,
 	/// Index of the candidate stored in the 'candidates' vector.
-	candidate_index: usize,
+	candidate_index: u32,
 }
 
 /// Means a particular `AccountId` was backed by `Perbill`th of a nominator's stake.
@@ -145,6 +146,8 @@ pub type SupportMap<A> = BTreeMap<A, Support<A>>;
 /// responsibility of the caller to make sure only those candidates who have a sensible economic
 /// value are passed in. From the perspective of this function, a candidate can easily be among the
 /// winner with no backing stake.
+///
+/// All the rounds are run at once, see [`Election`] to run them a few at a time.
 pub fn elect<AccountId, Balance, FS, C>(
 	candidate_count: usize,
 	minimum_candidate_count: usize,
@@ -157,187 +160,251 @@ pub fn elect<AccountId, Balance, FS, C>(
 	for<'r> FS: Fn(&'r AccountId) -> Balance,
 	C: Convert<Balance, u64> + Convert<u128, Balance>,
 {
-	let to_votes = |b: Balance| <C as Convert<Balance, u64>>::convert(b) as ExtendedBalance;
+	Election::new::<Balance, FS, C>(
+		candidate_count,
+		minimum_candidate_count,
+		initial_candidates,
+		initial_voters,
+		stake_of,
+	).map(Election::into_result)
+}
+
+/// A Phragmén election whose rounds, each electing a candidate, are run a few at a time.
+///
+/// The election can be encoded between its rounds, in order to spread them over several blocks.
+/// Each round goes through all the votes.
+#[derive(Clone, Encode, Decode, RuntimeDebug)]
+pub struct Election<AccountId> {
+	/// The candidates, elected or not.
+	candidates: Vec<Candidate<AccountId>>,
+	/// The voters, with their votes for the candidates.
+	voters: Vec<Voter<AccountId>>,
+	/// Number of candidates left to elect.
+	to_elect: u32,
+	/// The candidates elected so far, with their approval stake.
+	winners: Vec<(AccountId, ExtendedBalance)>,
+}
 
-	// return structures
-	let mut elected_candidates: Vec<(AccountId, ExtendedBalance)>;
-	let mut assigned: Vec<(AccountId, Vec<PhragmenAssignment<AccountId>>)>;
-
-	// used to cache and access candidates index.
-	let mut c_idx_cache = BTreeMap::<AccountId, usize>::new();
-
-	// voters list.
-	let num_voters = initial_candidates.len() + initial_voters.len();
-	let mut voters: Vec<Voter<AccountId>> = Vec::with_capacity(num_voters);
-
-	// Iterate once to create a cache of candidates indexes. This could be optimized by being
-	// provided by the call site.
-	let mut candidates = initial_candidates
-		.into_iter()
-		.enumerate()
-		.map(|(idx, who)| {
-			c_idx_cache.insert(who.clone(), idx);
-			Candidate { who, ..Default::default() }
+impl<AccountId: Default + Ord + Member> Election<AccountId> {
+	/// Set up an election, `None` if there are less than `minimum_candidate_count` candidates.
+	///
+	/// The parameters are the ones of [`elect`].
+	pub fn new<Balance, FS, C>(
+		candidate_count: usize,
+		minimum_candidate_count: usize,
+		initial_candidates: Vec<AccountId>,
+		initial_voters: Vec<(AccountId, Vec<AccountId>)>,
+		stake_of: FS,
+	) -> Option<Self> where
+		Balance: Default + Copy + SimpleArithmetic,
+		for<'r> FS: Fn(&'r AccountId) -> Balance,
+		C: Convert<Balance, u64> + Convert<u128, Balance>,
+	{
+		let to_votes = |b: Balance| <C as Convert<Balance, u64>>::convert(b) as ExtendedBalance;
+
+		// used to cache and access candidates index.
+		let mut c_idx_cache = BTreeMap::<AccountId, u32>::new();
+
+		// Iterate once to create a cache of candidates indexes. This could be optimized by being
+		// provided by the call site.
+		let mut candidates = initial_candidates
+			.into_iter()
+			.enumerate()
+			.map(|(idx, who)| {
+				c_idx_cache.insert(who.clone(), idx as u32);
+				Candidate { who, ..Default::default() }
+			})
+			.collect::<Vec<Candidate<AccountId>>>();
+
+		// early return if we don't have enough candidates
+		if candidates.len() < minimum_candidate_count { return None; }
+
+		// collect voters. use `c_idx_cache` for fast access and aggregate `approval_stake` of
+		// candidates.
+		let voters = initial_voters.into_iter().map(|(who, votes)| {
+			let voter_stake = stake_of(&who);
+			let mut edges: Vec<Edge<AccountId>> = Vec::with_capacity(votes.len());
+			for v in votes {
+				if let Some(idx) = c_idx_cache.get(&v) {
+					// This candidate is valid + already cached.
+					let candidate = &mut candidates[*idx as usize];
+					candidate.approval_stake = candidate.approval_stake
+						.saturating_add(to_votes(voter_stake));
+					edges.push(Edge { who: v.clone(), candidate_index: *idx, ..Default::default() });
+				} // else {} would be wrong votes. We don't really care about it.
+			}
+			Voter {
+				who,
+				edges: edges,
+				budget: to_votes(voter_stake),
+				load: Rational128::zero(),
+			}
+		}).collect::<Vec<_>>();
+
+		// we have already checked that we have more candidates than minimum_candidate_count.
+		let to_elect = candidate_count.min(candidates.len());
+		Some(Election {
+			candidates,
+			voters,
+			to_elect: to_elect as u32,
+			winners: Vec::with_capacity(to_elect),
 		})
-		.collect::<Vec<Candidate<AccountId>>>();
-
-	// early return if we don't have enough candidates
-	if candidates.len() < minimum_candidate_count { return None; }
-
-	// collect voters. use `c_idx_cache` for fast access and aggregate `approval_stake` of
-	// candidates.
-	voters.extend(initial_voters.into_iter().map(|(who, votes)| {
-		let voter_stake = stake_of(&who);
-		let mut edges: Vec<Edge<AccountId>> = Vec::with_capacity(votes.len());
-		for v in votes {
-			if let Some(idx) = c_idx_cache.get(&v) {
-				// This candidate is valid + already cached.
-				candidates[*idx].approval_stake = candidates[*idx].approval_stake
-					.saturating_add(to_votes(voter_stake));
-				edges.push(Edge { who: v.clone(), candidate_index: *idx, ..Default::default() });
-			} // else {} would be wrong votes. We don't really care about it.
-		}
-		Voter {
-			who,
-			edges: edges,
-			budget: to_votes(voter_stake),
-			load: Rational128::zero(),
-		}
-	}));
-
-
-	// we have already checked that we have more candidates than minimum_candidate_count.
-	// run phragmen.
-	let to_elect = candidate_count.min(candidates.len());
-	elected_candidates = Vec::with_capacity(candidate_count);
-	assigned = Vec::with_capacity(candidate_count);
-
-	// main election loop
-	for _round in 0..to_elect {
-		// loop 1: initialize score
-		for c in &mut candidates {
-			if !c.elected {
-				// 1 / approval_stake == (DEN / approval_stake) / DEN. If approval_stake is zero,
-				// then the ratio should be as large as possible, essentially `infinity`.
-				if c.approval_stake.is_zero() {
-					c.score = Rational128::from_unchecked(DEN, 0);
-				} else {
-					c.score = Rational128::from(DEN / c.approval_stake, DEN);
+	}
+
+	/// Whether all the candidates to elect are elected.
+	pub fn is_done(&self) -> bool {
+		self.to_elect == 0
+	}
+
+	/// The number of votes, which each round goes through.
+	pub fn num_edges(&self) -> usize {
+		self.voters.iter().map(|n| n.edges.len()).sum()
+	}
+
+	/// The stake of each voter, as votes.
+	pub fn budgets(&self) -> BTreeMap<AccountId, ExtendedBalance> {
+		self.voters.iter().map(|n| (n.who.clone(), n.budget)).collect()
+	}
+
+	/// Run at most `rounds` rounds of the election.
+	pub fn run_rounds(&mut self, rounds: usize) {
+		let Election { candidates, voters, to_elect, winners } = self;
+		for _round in 0..rounds.min(*to_elect as usize) {
+			// loop 1: initialize score
+			for c in candidates.iter_mut() {
+				if !c.elected {
+					// 1 / approval_stake == (DEN / approval_stake) / DEN. If approval_stake is
+					// zero, then the ratio should be as large as possible, essentially `infinity`.
+					if c.approval_stake.is_zero() {
+						c.score = Rational128::from_unchecked(DEN, 0);
+					} else {
+						c.score = Rational128::from(DEN / c.approval_stake, DEN);
+					}
 				}
 			}
-		}
 
-		// loop 2: increment score
-		for n in &voters {
-			for e in &n.edges {
-				let c = &mut candidates[e.candidate_index];
-				if !c.elected && !c.approval_stake.is_zero() {
-					let temp_n = multiply_by_rational(
-						n.load.n(),
-						n.budget,
-						c.approval_stake,
-					).unwrap_or(Bounded::max_value());
-					let temp_d = n.load.d();
-					let temp = Rational128::from(temp_n, temp_d);
-					c.score = c.score.lazy_saturating_add(temp);
+			// loop 2: increment score
+			for n in voters.iter() {
+				for e in &n.edges {
+					let c = &mut candidates[e.candidate_index as usize];
+					if !c.elected && !c.approval_stake.is_zero() {
+						let temp_n = multiply_by_rational(
+							n.load.n(),
+							n.budget,
+							c.approval_stake,
+						).unwrap_or(Bounded::max_value());
+						let temp_d = n.load.d();
+						let temp = Rational128::from(temp_n, temp_d);
+						c.score = c.score.lazy_saturating_add(temp);
+					}
 				}
 			}
-		}
 
-		// loop 3: find the best
-		if let Some(winner) = candidates
-			.iter_mut()
-			.filter(|c| !c.elected)
-			.min_by_key(|c| c.score)
-		{
-			// loop 3: update voter and edge load
-			winner.elected = true;
-			for n in &mut voters {
-				for e in &mut n.edges {
-					if e.who == winner.who {
-						e.load = winner.score.lazy_saturating_sub(n.load);
-						n.load = winner.score;
+			// loop 3: find the best
+			if let Some(winner) = candidates
+				.iter_mut()
+				.filter(|c| !c.elected)
+				.min_by_key(|c| c.score)
+			{
+				// loop 3: update voter and edge load
+				winner.elected = true;
+				for n in voters.iter_mut() {
+					for e in &mut n.edges {
+						if e.who == winner.who {
+							e.load = winner.score.lazy_saturating_sub(n.load);
+							n.load = winner.score;
+						}
 					}
 				}
+
+				winners.push((winner.who.clone(), winner.approval_stake));
+				*to_elect -= 1;
+			} else {
+				*to_elect = 0;
+				break
 			}
+		} // end of all rounds
+	}
 
-			elected_candidates.push((winner.who.clone(), winner.approval_stake));
-		} else {
-			break
-		}
-	} // end of all rounds
-
-	// update backing stake of candidates and voters
-	for n in &mut voters {
-		let mut assignment = (n.who.clone(), vec![]);
-		for e in &mut n.edges {
-			if elected_candidates.iter().position(|(ref c, _)| *c == e.who).is_some() {
-				let per_bill_parts =
-				{
-					if n.load == e.load {
-						// Full support. No need to calculate.
-						Perbill::accuracy().into()
-					} else {
-						if e.load.d() == n.load.d() {
-							// return e.load / n.load.
-							let desired_scale: u128 = Perbill::accuracy().into();
-							multiply_by_rational(
-								desired_scale,
-								e.load.n(),
-								n.load.n(),
-							).unwrap_or(Bounded::max_value())
+	/// Run the rounds left and return the result of the election.
+	pub fn into_result(mut self) -> PhragmenResult<AccountId> {
+		self.run_rounds(usize::max_value());
+		let Election { voters, winners: elected_candidates, .. } = self;
+		let mut assigned = Vec::with_capacity(elected_candidates.len());
+
+		// update backing stake of candidates and voters
+		for mut n in voters {
+			let mut assignment = (n.who.clone(), vec![]);
+			for e in &mut n.edges {
+				if elected_candidates.iter().position(|(ref c, _)| *c == e.who).is_some() {
+					let per_bill_parts =
+					{
+						if n.load == e.load {
+							// Full support. No need to calculate.
+							Perbill::accuracy().into()
 						} else {
-							// defensive only. Both edge and nominator loads are built from
-							// scores, hence MUST have the same denominator.
-							Zero::zero()
+							if e.load.d() == n.load.d() {
+								// return e.load / n.load.
+								let desired_scale: u128 = Perbill::accuracy().into();
+								multiply_by_rational(
+									desired_scale,
+									e.load.n(),
+									n.load.n(),
+								).unwrap_or(Bounded::max_value())
+							} else {
+								// defensive only. Both edge and nominator loads are built from
+								// scores, hence MUST have the same denominator.
+								Zero::zero()
+							}
 						}
-					}
-				};
-				// safer to .min() inside as well to argue as u32 is safe.
-				let per_thing = Perbill::from_parts(
-					per_bill_parts.min(Perbill::accuracy().into()) as u32
-				);
-				assignment.1.push((e.who.clone(), per_thing));
+					};
+					// safer to .min() inside as well to argue as u32 is safe.
+					let per_thing = Perbill::from_parts(
+						per_bill_parts.min(Perbill::accuracy().into()) as u32
+					);
+					assignment.1.push((e.who.clone(), per_thing));
+				}
 			}
-		}
 
-		if assignment.1.len() > 0 {
-			// To ensure an assertion indicating: no stake from the nominator going to waste,
-			// we add a minimal post-processing to equally assign all of the leftover stake ratios.
-			let vote_count = assignment.1.len() as u32;
-			let len = assignment.1.len();
-			let sum = assignment.1.iter()
-				.map(|a| a.1.deconstruct())
-				.sum::<u32>();
-			let accuracy = Perbill::accuracy();
-			let diff = accuracy.checked_sub(sum).unwrap_or(0);
-			let diff_per_vote = (diff / vote_count).min(accuracy);
-
-			if diff_per_vote > 0 {
-				for i in 0..len {
+			if assignment.1.len() > 0 {
+				// To ensure an assertion indicating: no stake from the nominator going to waste,
+				// we add a minimal post-processing to equally assign all of the leftover stake
+				// ratios.
+				let vote_count = assignment.1.len() as u32;
+				let len = assignment.1.len();
+				let sum = assignment.1.iter()
+					.map(|a| a.1.deconstruct())
+					.sum::<u32>();
+				let accuracy = Perbill::accuracy();
+				let diff = accuracy.checked_sub(sum).unwrap_or(0);
+				let diff_per_vote = (diff / vote_count).min(accuracy);
+
+				if diff_per_vote > 0 {
+					for i in 0..len {
+						let current_ratio = assignment.1[i % len].1;
+						let next_ratio = current_ratio
+							.saturating_add(Perbill::from_parts(diff_per_vote));
+						assignment.1[i % len].1 = next_ratio;
+					}
+				}
+
+				// `remainder` is set to be less than maximum votes of a nominator (currently 16).
+				// safe to cast it to usize.
+				let remainder = diff - diff_per_vote * vote_count;
+				for i in 0..remainder as usize {
 					let current_ratio = assignment.1[i % len].1;
-					let next_ratio = current_ratio
-						.saturating_add(Perbill::from_parts(diff_per_vote));
+					let next_ratio = current_ratio.saturating_add(Perbill::from_parts(1));
 					assignment.1[i % len].1 = next_ratio;
 				}
+				assigned.push(assignment);
 			}
+		}
 
-			// `remainder` is set to be less than maximum votes of a nominator (currently 16).
-			// safe to cast it to usize.
-			let remainder = diff - diff_per_vote * vote_count;
-			for i in 0..remainder as usize {
-				let current_ratio = assignment.1[i % len].1;
-				let next_ratio = current_ratio.saturating_add(Perbill::from_parts(1));
-				assignment.1[i % len].1 = next_ratio;
-			}
-			assigned.push(assignment);
+		PhragmenResult {
+			winners: elected_candidates,
+			assignments: assigned,
 		}
 	}
-
-	Some(PhragmenResult {
-		winners: elected_candidates,
-		assignments: assigned,
-	})
 }
 
 /// Build the support map from the given phragmen result.
@@ -19,7 +19,10 @@
 #![cfg(test)]
 
 use crate::mock::*;
-use crate::{elect, PhragmenResult, PhragmenStakedAssignment, build_support_map, Support, equalize};
+use crate::{
+	elect, Election, PhragmenResult, PhragmenStakedAssignment, build_support_map, Support, equalize,
+};
+use codec::{Encode, Decode};
 use substrate_test_utils::assert_eq_uvec;
 use sp_runtime::Perbill;
 
@@ -97,6 +100,46 @@ fn phragmen_poc_works() {
 	);
 }
 
+#[test]
+fn election_run_over_several_steps_elects_the_same() {
+	let candidates = vec![1, 2, 3, 4];
+	let voters = vec![
+		(10, vec![1, 2]),
+		(20, vec![1, 3]),
+		(30, vec![2, 3, 4]),
+		(40, vec![4]),
+	];
+	let stake = [(10, 10), (20, 20), (30, 30), (40, 15)];
+
+	let mut election = Election::new::<_, _, TestCurrencyToVote>(
+		3,
+		2,
+		candidates.clone(),
+		voters.clone(),
+		create_stake_of(&stake),
+	).unwrap();
+	assert_eq!(election.num_edges(), 8);
+	let mut rounds = 0;
+	while !election.is_done() {
+		// the election is stored between its rounds.
+		election.run_rounds(1);
+		election = Election::decode(&mut &election.encode()[..]).unwrap();
+		rounds += 1;
+	}
+	assert_eq!(rounds, 3);
+
+	let stepped = election.into_result();
+	let at_once = elect::<_, _, _, TestCurrencyToVote>(
+		3,
+		2,
+		candidates,
+		voters,
+		create_stake_of(&stake),
+	).unwrap();
+	assert_eq!(stepped.winners, at_once.winners);
+	assert_eq!(stepped.assignments, at_once.assignments);
+}
+
 #[test]
 fn phragmen_poc_2_works() {
 	let candidates = vec![10, 20, 30];
@@ -58,7 +58,7 @@ pub mod random_number_generator;
 pub use generic::{DigestItem, Digest};
 
 /// Re-export this since it's part of the API of this crate.
-pub use sp_core::{TypeId, crypto::{key_types, KeyTypeId, CryptoType, AccountId32}};
+pub use sp_core::{TypeId, crypto::{key_types, KeyTypeId, CryptoType, AccountId32, AccountId20}};
 pub use sp_application_crypto::{RuntimeAppPublic, BoundToRuntimeAppPublic};
 
 /// Re-export `RuntimeDebug`, to avoid dependency clutter.
@@ -313,6 +313,39 @@ impl std::fmt::Display for MultiSigner {
 	}
 }
 
+#[cfg(feature = "std")]
+impl traits::SignPayload<MultiSignature> for ed25519::Pair {
+	fn signer(&self) -> MultiSigner {
+		sp_core::Pair::public(self).into()
+	}
+
+	fn sign_payload(&self, payload: &[u8]) -> MultiSignature {
+		sp_core::Pair::sign(self, payload).into()
+	}
+}
+
+#[cfg(feature = "std")]
+impl traits::SignPayload<MultiSignature> for sr25519::Pair {
+	fn signer(&self) -> MultiSigner {
+		sp_core::Pair::public(self).into()
+	}
+
+	fn sign_payload(&self, payload: &[u8]) -> MultiSignature {
+		sp_core::Pair::sign(self, payload).into()
+	}
+}
+
+#[cfg(feature = "std")]
+impl traits::SignPayload<MultiSignature> for ecdsa::Pair {
+	fn signer(&self) -> MultiSigner {
+		sp_core::Pair::public(self).into()
+	}
+
+	fn sign_payload(&self, payload: &[u8]) -> MultiSignature {
+		sp_core::Pair::sign(self, payload).into()
+	}
+}
+
 impl Verify for MultiSignature {
 	type Signer = MultiSigner;
 	fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId32) -> bool {
@@ -352,6 +385,76 @@ impl Verify for AnySignature {
 	}
 }
 
+/// An ECDSA signature of the Keccak-256 hash of a message, verified against the Ethereum
+/// address of the signer.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Eq, PartialEq, Clone, Default, Encode, Decode, RuntimeDebug)]
+pub struct EthereumSignature(ecdsa::Signature);
+
+impl From<ecdsa::Signature> for EthereumSignature {
+	fn from(x: ecdsa::Signature) -> Self {
+		EthereumSignature(x)
+	}
+}
+
+impl Verify for EthereumSignature {
+	type Signer = EthereumSigner;
+	fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId20) -> bool {
+		let m = sp_io::hashing::keccak_256(msg.get());
+		match sp_io::crypto::secp256k1_ecdsa_recover(self.0.as_ref(), &m) {
+			Ok(pubkey) =>
+				&sp_io::hashing::keccak_256(&pubkey)[12..] == <dyn AsRef<[u8]>>::as_ref(signer),
+			_ => false,
+		}
+	}
+}
+
+/// The signer of an `EthereumSignature`, identified by its Ethereum address.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Default, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct EthereumSigner(AccountId20);
+
+impl traits::IdentifyAccount for EthereumSigner {
+	type AccountId = AccountId20;
+	fn into_account(self) -> AccountId20 {
+		self.0
+	}
+}
+
+impl From<AccountId20> for EthereumSigner {
+	fn from(x: AccountId20) -> Self {
+		EthereumSigner(x)
+	}
+}
+
+/// Fails if the key is an invalid compressed public key.
+#[cfg(feature = "std")]
+impl TryFrom<ecdsa::Public> for EthereumSigner {
+	type Error = ();
+	fn try_from(x: ecdsa::Public) -> Result<Self, ()> {
+		x.to_eth_address().map(|address| EthereumSigner(address.into()))
+	}
+}
+
+#[cfg(feature = "std")]
+impl traits::SignPayload<EthereumSignature> for ecdsa::Pair {
+	fn signer(&self) -> EthereumSigner {
+		EthereumSigner::try_from(sp_core::Pair::public(self))
+			.expect("The public key of a pair is a valid key; qed")
+	}
+
+	fn sign_payload(&self, payload: &[u8]) -> EthereumSignature {
+		self.sign_prehashed(&sp_io::hashing::keccak_256(payload)).into()
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for EthereumSigner {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(fmt, "{}", self.0)
+	}
+}
+
 impl From<sr25519::Signature> for AnySignature {
 	fn from(s: sr25519::Signature) -> Self {
 		AnySignature(s.into())
@@ -745,4 +848,47 @@ mod tests {
 		let multi_signer = MultiSigner::from(pair.public().into_compressed().unwrap());
 		assert!(multi_sig.verify(msg, &multi_signer.into_account()));
 	}
+
+	#[test]
+	fn ethereum_signature_verify_works() {
+		let msg = &b"test-message"[..];
+		let (pair, _) = ecdsa::Pair::generate();
+
+		let signature = EthereumSignature::from(pair.sign_prehashed(&sp_io::hashing::keccak_256(msg)));
+		let signer = EthereumSigner::try_from(pair.public()).unwrap();
+		assert!(signature.verify(msg, &signer.clone().into_account()));
+		assert!(!signature.verify(&b"other-message"[..], &signer.into_account()));
+
+		// The address is the same for compressed keys.
+		let signer = EthereumSigner::try_from(pair.public().into_compressed().unwrap()).unwrap();
+		assert!(signature.verify(msg, &signer.into_account()));
+
+		// Signatures of the Blake2 hash are not accepted.
+		let signature = EthereumSignature::from(pair.sign(msg));
+		assert!(!signature.verify(msg, &EthereumSigner::try_from(pair.public()).unwrap().into_account()));
+	}
+
+	#[test]
+	fn ecdsa_pairs_sign_payloads_of_both_accounts() {
+		use traits::SignPayload;
+
+		let msg = &b"test-message"[..];
+		let (pair, _) = ecdsa::Pair::generate();
+
+		let signature: MultiSignature = pair.sign_payload(msg);
+		let signer: MultiSigner = pair.signer();
+		assert!(signature.verify(msg, &signer.into_account()));
+
+		let signature: EthereumSignature = pair.sign_payload(msg);
+		let signer: EthereumSigner = pair.signer();
+		assert_eq!(signer, EthereumSigner::try_from(pair.public()).unwrap());
+		assert!(signature.verify(msg, &signer.into_account()));
+	}
+
+	#[test]
+	fn ethereum_signer_of_invalid_key_is_an_error() {
+		use sp_core::crypto::Public;
+
+		assert_eq!(EthereumSigner::try_from(ecdsa::Public::from_slice(&[0u8; 33])), Err(()));
+	}
 }
@@ -108,6 +108,15 @@ impl Verify for sp_core::ecdsa::Signature {
 	}
 }
 
+/// A key pair signing the payloads of its account with signatures of type `Signature`.
+#[cfg(feature = "std")]
+pub trait SignPayload<Signature: Verify> {
+	/// The signer of the signatures, identifying the account of the key.
+	fn signer(&self) -> Signature::Signer;
+	/// Sign `payload`.
+	fn sign_payload(&self, payload: &[u8]) -> Signature;
+}
+
 /// Means of signature verification of an application key.
 pub trait AppVerify {
 	/// Type of the signer.
@@ -207,6 +207,17 @@ impl Default for ValidTransaction {
 }
 
 impl ValidTransaction {
+	/// Start building a `ValidTransaction` whose tags are prefixed by `prefix`.
+	///
+	/// Tags of different modules could otherwise collide, making unrelated transactions depend
+	/// on or replace each other. Each module should use its own prefix, e.g. its name.
+	pub fn with_tag_prefix(prefix: &'static str) -> ValidTransactionBuilder {
+		ValidTransactionBuilder {
+			prefix: Some(prefix),
+			validity: Default::default(),
+		}
+	}
+
 	/// Combine two instances into one, as a best effort. This will take the superset of each of the
 	/// `provides` and `requires` tags, it will sum the priorities, take the minimum longevity and
 	/// the logic *And* of the propagate flags.
@@ -221,6 +232,87 @@ impl ValidTransaction {
 	}
 }
 
+/// Builder of a `ValidTransaction`, see [`ValidTransaction::with_tag_prefix`].
+///
+/// Lets modules describe the validity of their transactions without depending on the fields of
+/// `ValidTransaction`, which start from their defaults.
+#[derive(Default, Clone, RuntimeDebug)]
+pub struct ValidTransactionBuilder {
+	prefix: Option<&'static str>,
+	validity: ValidTransaction,
+}
+
+impl ValidTransactionBuilder {
+	/// Set the priority of the transaction, see [`ValidTransaction::priority`].
+	pub fn priority(mut self, priority: TransactionPriority) -> Self {
+		self.validity.priority = priority;
+		self
+	}
+
+	/// Set the longevity of the transaction, see [`ValidTransaction::longevity`].
+	pub fn longevity(mut self, longevity: TransactionLongevity) -> Self {
+		self.validity.longevity = longevity;
+		self
+	}
+
+	/// Set whether the transaction is propagated, see [`ValidTransaction::propagate`].
+	pub fn propagate(mut self, propagate: bool) -> Self {
+		self.validity.propagate = propagate;
+		self
+	}
+
+	/// Add a tag required by the transaction, encoded after the prefix of the builder.
+	///
+	/// The transaction stays in the future queue of the pool until a transaction providing the
+	/// tag is in the pool, or was just included.
+	pub fn and_requires(mut self, tag: impl Encode) -> Self {
+		let tag = self.tag(tag);
+		self.validity.requires.push(tag);
+		self
+	}
+
+	/// Add a tag provided by the transaction, encoded after the prefix of the builder.
+	///
+	/// Transactions of the pool providing the same tag replace each other, the one with the
+	/// highest priority being kept.
+	pub fn and_provides(mut self, tag: impl Encode) -> Self {
+		let tag = self.tag(tag);
+		self.validity.provides.push(tag);
+		self
+	}
+
+	/// Combine with another validity, see [`ValidTransaction::combine_with`]. Its tags are kept
+	/// as they are.
+	pub fn combine_with(mut self, validity: ValidTransaction) -> Self {
+		self.validity = self.validity.combine_with(validity);
+		self
+	}
+
+	/// The validity built.
+	pub fn build(self) -> TransactionValidity {
+		self.into()
+	}
+
+	fn tag(&self, tag: impl Encode) -> TransactionTag {
+		match self.prefix {
+			Some(prefix) => (prefix, tag).encode(),
+			None => tag.encode(),
+		}
+	}
+}
+
+impl From<ValidTransactionBuilder> for TransactionValidity {
+	fn from(builder: ValidTransactionBuilder) -> Self {
+		Ok(builder.into())
+	}
+}
+
+impl From<ValidTransactionBuilder> for ValidTransaction {
+	fn from(builder: ValidTransactionBuilder) -> Self {
+		builder.validity
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -244,4 +336,27 @@ mod tests {
 		// decode back
 		assert_eq!(TransactionValidity::decode(&mut &*encoded), Ok(v));
 	}
+
+	#[test]
+	fn builder_should_prefix_the_tags() {
+		let a: ValidTransaction = ValidTransaction::with_tag_prefix("a")
+			.priority(3)
+			.priority(6)
+			.and_provides(3)
+			.and_requires(vec![1u8])
+			.longevity(5)
+			.propagate(false)
+			.combine_with(ValidTransaction { provides: vec![vec![7]], ..Default::default() })
+			.into();
+		assert_eq!(a, ValidTransaction {
+			priority: 6,
+			requires: vec![("a", vec![1u8]).encode()],
+			provides: vec![("a", 3).encode(), vec![7]],
+			longevity: 5,
+			propagate: false,
+		});
+
+		let b = ValidTransactionBuilder::default().and_provides(3).build();
+		assert_eq!(b.unwrap().provides, vec![3.encode()]);
+	}
 }
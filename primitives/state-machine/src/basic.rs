@@ -37,12 +37,14 @@ use codec::Encode;
 #[derive(Debug)]
 pub struct BasicExternalities {
 	inner: Storage,
+	/// The storage as it was when each open transaction started.
+	transactions: Vec<Storage>,
 }
 
 impl BasicExternalities {
 	/// Create a new instance of `BasicExternalities`
 	pub fn new(inner: Storage) -> Self {
-		BasicExternalities { inner }
+		BasicExternalities { inner, transactions: Vec::new() }
 	}
 
 	/// Insert key/value
@@ -62,10 +64,10 @@ impl BasicExternalities {
 		storage: &mut sp_core::storage::Storage,
 		f: impl FnOnce() -> R,
 	) -> R {
-		let mut ext = Self { inner: Storage {
+		let mut ext = Self::new(Storage {
 			top: std::mem::replace(&mut storage.top, Default::default()),
 			children: std::mem::replace(&mut storage.children, Default::default()),
-		}};
+		});
 
 		let r = ext.execute_with(f);
 
@@ -103,10 +105,10 @@ impl Default for BasicExternalities {
 
 impl From<BTreeMap<Vec<u8>, Vec<u8>>> for BasicExternalities {
 	fn from(hashmap: BTreeMap<Vec<u8>, Vec<u8>>) -> Self {
-		BasicExternalities { inner: Storage {
+		Self::new(Storage {
 			top: hashmap,
 			children: Default::default(),
-		}}
+		})
 	}
 }
 
@@ -298,6 +300,23 @@ impl Externalities for BasicExternalities {
 	fn storage_changes_root(&mut self, _parent: &[u8]) -> Result<Option<Vec<u8>>, ()> {
 		Ok(None)
 	}
+
+	fn set_offchain_storage(&mut self, _key: &[u8], _value: Option<&[u8]>) {
+		// There is no offchain storage to index into, e.g. when building the genesis state.
+	}
+
+	fn storage_start_transaction(&mut self) {
+		self.transactions.push(self.inner.clone());
+	}
+
+	fn storage_rollback_transaction(&mut self) -> Result<(), ()> {
+		self.inner = self.transactions.pop().ok_or(())?;
+		Ok(())
+	}
+
+	fn storage_commit_transaction(&mut self) -> Result<(), ()> {
+		self.transactions.pop().map(drop).ok_or(())
+	}
 }
 
 impl sp_externalities::ExtensionStore for BasicExternalities {
@@ -440,7 +440,8 @@ mod test {
 							extrinsics: Some(vec![0, 2].into_iter().collect())
 						})
 					].into_iter().collect(), CHILD_INFO_2.to_owned())),
-				].into_iter().collect()
+				].into_iter().collect(),
+				offchain: Default::default(),
 			},
 			committed: OverlayedChangeSet { top: vec![
 				(EXTRINSIC_INDEX.to_vec(), OverlayedValue {
@@ -464,8 +465,10 @@ mod test {
 						})
 					].into_iter().collect(), CHILD_INFO_1.to_owned())),
 				].into_iter().collect(),
+				offchain: Default::default(),
 			},
 			changes_trie_config: Some(config.clone()),
+			transactions: Default::default(),
 		};
 
 		(backend, storage, changes, config)
@@ -40,8 +40,16 @@ pub enum ExecutionError {
 	UnableToGenerateProof,
 	/// Invalid execution proof.
 	InvalidProof,
+	/// Native and wasm execution produced different outcomes under the canary strategy.
+	Divergence(String),
 }
 
 impl fmt::Display for ExecutionError {
-	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "Externalities Error") }
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ExecutionError::Divergence(divergence) =>
+				write!(f, "Native and wasm execution diverged: {}", divergence),
+			_ => write!(f, "Externalities Error"),
+		}
+	}
 }
@@ -584,6 +584,31 @@ where
 
 		root.map(|r| r.map(|o| o.encode()))
 	}
+
+	fn set_offchain_storage(&mut self, key: &[u8], value: Option<&[u8]>) {
+		trace!(target: "state-trace", "{:04x}: SetOffchain {}={:?}",
+			self.id,
+			HexDisplay::from(&key),
+			value.as_ref().map(HexDisplay::from),
+		);
+		self.overlay.set_offchain_storage(key.to_vec(), value.map(|v| v.to_vec()));
+	}
+
+	fn storage_start_transaction(&mut self) {
+		trace!(target: "state-trace", "{:04x}: StartTransaction", self.id);
+		self.overlay.start_transaction();
+	}
+
+	fn storage_rollback_transaction(&mut self) -> Result<(), ()> {
+		trace!(target: "state-trace", "{:04x}: RollbackTransaction", self.id);
+		self.mark_dirty();
+		self.overlay.rollback_transaction()
+	}
+
+	fn storage_commit_transaction(&mut self) -> Result<(), ()> {
+		trace!(target: "state-trace", "{:04x}: CommitTransaction", self.id);
+		self.overlay.commit_transaction()
+	}
 }
 
 impl<'a, H, B, T, N> sp_externalities::ExtensionStore for Ext<'a, H, N, B, T>
@@ -633,6 +658,7 @@ mod tests {
 				digest_interval: 0,
 				digest_levels: 0,
 			}),
+			transactions: Default::default(),
 		}
 	}
 
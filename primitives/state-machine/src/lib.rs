@@ -24,7 +24,7 @@ use hash_db::Hasher;
 use codec::{Decode, Encode, Codec};
 use sp_core::{
 	storage::{well_known_keys, ChildInfo}, NativeOrEncoded, NeverNativeValue,
-	traits::CodeExecutor, hexdisplay::HexDisplay
+	traits::{CodeExecutor, CallInWasmExt}, hexdisplay::HexDisplay
 };
 use overlayed_changes::OverlayedChangeSet;
 use sp_externalities::Extensions;
@@ -93,6 +93,13 @@ pub enum ExecutionStrategy {
 	Both,
 	/// First native, then if that fails or is not possible, wasm.
 	NativeElseWasm,
+	/// Run with both the wasm and the native variant (if compatible) and compare the results
+	/// together with the storage changes each produced. The wasm outcome is kept; divergence
+	/// is logged and only reported as an error if `fail_on_divergence` is set.
+	Canary {
+		/// Whether a divergence should fail the call instead of only being logged.
+		fail_on_divergence: bool,
+	},
 }
 
 /// Storage backend trust level.
@@ -119,6 +126,9 @@ pub enum ExecutionManager<F> {
 	Both(F),
 	/// First native, then if that fails or is not possible, wasm.
 	NativeElseWasm,
+	/// Run with both the wasm and the native variant (if compatible) and diff results and storage
+	/// changes. The flag tells whether a divergence fails the call.
+	Canary(bool),
 }
 
 impl<'a, F> From<&'a ExecutionManager<F>> for ExecutionStrategy {
@@ -128,6 +138,8 @@ impl<'a, F> From<&'a ExecutionManager<F>> for ExecutionStrategy {
 			ExecutionManager::AlwaysWasm(_) => ExecutionStrategy::AlwaysWasm,
 			ExecutionManager::NativeElseWasm => ExecutionStrategy::NativeElseWasm,
 			ExecutionManager::Both(_) => ExecutionStrategy::Both,
+			ExecutionManager::Canary(fail_on_divergence) =>
+				ExecutionStrategy::Canary { fail_on_divergence },
 		}
 	}
 }
@@ -151,6 +163,8 @@ impl ExecutionStrategy {
 				warn!("   Wasm result {:?}", wasm_result);
 				wasm_result
 			}),
+			ExecutionStrategy::Canary { fail_on_divergence } =>
+				ExecutionManager::Canary(fail_on_divergence),
 		}
 	}
 }
@@ -204,8 +218,10 @@ impl<'a, B, H, N, T, Exec> StateMachine<'a, B, H, N, T, Exec> where
 		exec: &'a Exec,
 		method: &'a str,
 		call_data: &'a [u8],
-		extensions: Extensions,
+		mut extensions: Extensions,
 	) -> Self {
+		extensions.register(CallInWasmExt::new(exec.clone()));
+
 		Self {
 			backend,
 			exec,
@@ -232,6 +248,53 @@ impl<'a, B, H, N, T, Exec> StateMachine<'a, B, H, N, T, Exec> where
 		self
 	}
 
+	/// Execute with native and wasm, keeping the wasm outcome.
+	///
+	/// Returns the wasm result along with a description of where the native execution diverged
+	/// from it, if it did.
+	fn execute_call_with_canary_strategy<R, NC>(
+		&mut self,
+		mut native_call: Option<NC>,
+		orig_prospective: OverlayedChangeSet,
+	) -> (CallResult<R, Exec::Error>, Option<String>)
+		where
+			R: Decode + Encode + PartialEq,
+			NC: FnOnce() -> result::Result<R, String> + UnwindSafe,
+	{
+		let (native_result, was_native) = self.execute_aux(true, native_call.take());
+
+		if !was_native {
+			return (native_result, None);
+		}
+
+		let native_prospective = std::mem::replace(&mut self.overlay.prospective, orig_prospective);
+		let (wasm_result, _) = self.execute_aux(false, native_call);
+
+		let mut divergence = Vec::new();
+		let results_match = match (&native_result, &wasm_result) {
+			(Ok(native), Ok(wasm)) => native == wasm,
+			(Err(_), Err(_)) => true,
+			_ => false,
+		};
+		if !results_match {
+			divergence.push(format!("result native {:?} wasm {:?}", native_result, wasm_result));
+		}
+
+		let keys = native_prospective.diverging_keys(&self.overlay.prospective);
+		if !keys.is_empty() {
+			divergence.push(format!(
+				"storage changes at {}",
+				keys.iter().map(|k| HexDisplay::from(k).to_string()).collect::<Vec<_>>().join(", "),
+			));
+		}
+
+		if divergence.is_empty() {
+			(wasm_result, None)
+		} else {
+			(wasm_result, Some(divergence.join("; ")))
+		}
+	}
+
 	/// Execute a call using the given state backend, overlayed changes, and call executor.
 	///
 	/// On an error, no prospective changes are written to the overlay.
@@ -412,6 +475,26 @@ impl<'a, B, H, N, T, Exec> StateMachine<'a, B, H, N, T, Exec> where
 						on_consensus_failure,
 					)
 				},
+				ExecutionManager::Canary(fail_on_divergence) => {
+					let (result, divergence) = self.execute_call_with_canary_strategy(
+						native_call.take(),
+						orig_prospective,
+					);
+					if let Some(divergence) = divergence {
+						warn!(
+							target: "canary",
+							"Native and wasm execution of {} diverged: {}",
+							self.method,
+							divergence,
+						);
+						if fail_on_divergence {
+							return Err(
+								Box::new(ExecutionError::Divergence(divergence)) as Box<dyn Error>
+							);
+						}
+					}
+					result
+				},
 				ExecutionManager::NativeElseWasm => {
 					self.execute_call_with_native_else_wasm_strategy(
 						native_call.take(),
@@ -690,6 +773,173 @@ where
 		.map_err(|e| Box::new(e) as Box<dyn Error>)
 }
 
+/// Generate a proof of the storage entries of the key range starting at `start`.
+///
+/// The range ends before `end`, or after `max_keys` entries, whichever comes first.
+pub fn prove_range_read<B, H>(
+	mut backend: B,
+	start: &[u8],
+	end: Option<&[u8]>,
+	max_keys: usize,
+) -> Result<StorageProof, Box<dyn Error>>
+where
+	B: Backend<H>,
+	H: Hasher,
+	H::Out: Ord + Codec,
+{
+	let trie_backend = backend.as_trie_backend()
+		.ok_or_else(
+			|| Box::new(ExecutionError::UnableToGenerateProof) as Box<dyn Error>
+		)?;
+	prove_range_read_on_trie_backend(trie_backend, start, end, max_keys)
+}
+
+/// Generate a proof of the storage entries of a key range of a child trie.
+///
+/// The range starts at `start` and ends before `end`, or after `max_keys` entries, whichever
+/// comes first.
+pub fn prove_child_range_read<B, H>(
+	mut backend: B,
+	storage_key: &[u8],
+	child_info: ChildInfo,
+	start: &[u8],
+	end: Option<&[u8]>,
+	max_keys: usize,
+) -> Result<StorageProof, Box<dyn Error>>
+where
+	B: Backend<H>,
+	H: Hasher,
+	H::Out: Ord + Codec,
+{
+	let trie_backend = backend.as_trie_backend()
+		.ok_or_else(
+			|| Box::new(ExecutionError::UnableToGenerateProof) as Box<dyn Error>
+		)?;
+	prove_child_range_read_on_trie_backend(trie_backend, storage_key, child_info, start, end, max_keys)
+}
+
+/// Generate a key range proof on pre-created trie backend.
+pub fn prove_range_read_on_trie_backend<S, H>(
+	trie_backend: &TrieBackend<S, H>,
+	start: &[u8],
+	end: Option<&[u8]>,
+	max_keys: usize,
+) -> Result<StorageProof, Box<dyn Error>>
+where
+	S: trie_backend_essence::TrieBackendStorage<H>,
+	H: Hasher,
+	H::Out: Ord + Codec,
+{
+	let proving_backend = proving_backend::ProvingBackend::<_, H>::new(trie_backend);
+	read_range(&proving_backend, None, start, end, max_keys)?;
+	Ok(proving_backend.extract_proof())
+}
+
+/// Generate a child trie key range proof on pre-created trie backend.
+pub fn prove_child_range_read_on_trie_backend<S, H>(
+	trie_backend: &TrieBackend<S, H>,
+	storage_key: &[u8],
+	child_info: ChildInfo,
+	start: &[u8],
+	end: Option<&[u8]>,
+	max_keys: usize,
+) -> Result<StorageProof, Box<dyn Error>>
+where
+	S: trie_backend_essence::TrieBackendStorage<H>,
+	H: Hasher,
+	H::Out: Ord + Codec,
+{
+	let proving_backend = proving_backend::ProvingBackend::<_, H>::new(trie_backend);
+	read_range(&proving_backend, Some((storage_key, child_info)), start, end, max_keys)?;
+	Ok(proving_backend.extract_proof())
+}
+
+/// Check a key range proof, generated by `prove_range_read` call.
+///
+/// Returns the entries of the range, and whether the range has been exhausted. As the proof
+/// has to contain every trie node on the way from one key to the next, a proof that omits any
+/// entry of the range fails the check.
+pub fn range_proof_check<H>(
+	root: H::Out,
+	proof: StorageProof,
+	start: &[u8],
+	end: Option<&[u8]>,
+	max_keys: usize,
+) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, bool), Box<dyn Error>>
+where
+	H: Hasher,
+	H::Out: Ord + Codec,
+{
+	let proving_backend = create_proof_check_backend::<H>(root, proof)?;
+	read_range(&proving_backend, None, start, end, max_keys)
+}
+
+/// Check a child trie key range proof, generated by `prove_child_range_read` call.
+///
+/// `root` is the root of the top trie, the proof covers the path to the root of the child trie.
+pub fn child_range_proof_check<H>(
+	root: H::Out,
+	proof: StorageProof,
+	storage_key: &[u8],
+	child_info: ChildInfo,
+	start: &[u8],
+	end: Option<&[u8]>,
+	max_keys: usize,
+) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, bool), Box<dyn Error>>
+where
+	H: Hasher,
+	H::Out: Ord + Codec,
+{
+	let proving_backend = create_proof_check_backend::<H>(root, proof)?;
+	read_range(&proving_backend, Some((storage_key, child_info)), start, end, max_keys)
+}
+
+/// Walk the keys from `start` on, collecting at most `max_keys` entries lower than `end`, of the
+/// top trie or of the given child trie.
+///
+/// Proof generation and proof checking must perform exactly the same reads.
+fn read_range<B, H>(
+	backend: &B,
+	child: Option<(&[u8], ChildInfo)>,
+	start: &[u8],
+	end: Option<&[u8]>,
+	max_keys: usize,
+) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, bool), Box<dyn Error>>
+where
+	B: Backend<H>,
+	H: Hasher,
+{
+	let storage = |key: &[u8]| match child {
+		Some((storage_key, child_info)) => backend.child_storage(storage_key, child_info, key),
+		None => backend.storage(key),
+	}.map_err(|e| Box::new(e) as Box<dyn Error>);
+	let next_key = |key: &[u8]| match child {
+		Some((storage_key, child_info)) => backend.next_child_storage_key(storage_key, child_info, key),
+		None => backend.next_storage_key(key),
+	}.map_err(|e| Box::new(e) as Box<dyn Error>);
+
+	let mut entries = Vec::new();
+	let mut key = start.to_vec();
+	let mut value = storage(&key)?;
+	loop {
+		if end.map_or(false, |end| key.as_slice() >= end) {
+			return Ok((entries, true));
+		}
+		if let Some(value) = value {
+			if entries.len() >= max_keys {
+				return Ok((entries, false));
+			}
+			entries.push((key.clone(), value));
+		}
+
+		key = match next_key(&key)? {
+			Some(next) => next,
+			None => return Ok((entries, true)),
+		};
+		value = storage(&key)?;
+	}
+}
+
 /// Sets overlayed changes' changes trie configuration. Returns error if configuration
 /// differs from previous OR config decode has failed.
 fn set_changes_trie_config(
@@ -739,8 +989,9 @@ mod tests {
 		InMemoryStorage as InMemoryChangesTrieStorage,
 		Configuration as ChangesTrieConfig,
 	};
-	use sp_core::{Blake2Hasher, map, traits::Externalities, storage::ChildStorageKey};
+	use sp_core::{Blake2Hasher, map, traits::{Externalities, CallInWasm}, storage::ChildStorageKey};
 
+	#[derive(Clone)]
 	struct DummyCodeExecutor {
 		change_changes_trie_config: bool,
 		native_available: bool,
@@ -750,6 +1001,18 @@ mod tests {
 
 	const CHILD_INFO_1: ChildInfo<'static> = ChildInfo::new_default(b"unique_id_1");
 
+	impl CallInWasm for DummyCodeExecutor {
+		fn call_in_wasm(
+			&self,
+			_: &[u8],
+			_: &str,
+			_: &[u8],
+			_: &mut dyn Externalities,
+		) -> std::result::Result<Vec<u8>, String> {
+			unimplemented!("Not required in tests.")
+		}
+	}
+
 	impl CodeExecutor for DummyCodeExecutor {
 		type Error = u8;
 
@@ -849,6 +1112,34 @@ mod tests {
 		assert_eq!(state_machine.execute(ExecutionStrategy::NativeElseWasm).unwrap(), vec![66]);
 	}
 
+	#[test]
+	fn canary_execution_strategy_reports_divergence() {
+		let backend = trie_backend::tests::test_trie();
+		let changes_trie_storage = InMemoryChangesTrieStorage::<Blake2Hasher, u64>::new();
+		let execute = |fallback_succeeds| {
+			let mut overlayed_changes = Default::default();
+			StateMachine::new(
+				&backend,
+				Some(&changes_trie_storage),
+				&mut overlayed_changes,
+				&DummyCodeExecutor {
+					change_changes_trie_config: false,
+					native_available: true,
+					native_succeeds: true,
+					fallback_succeeds,
+				},
+				"test",
+				&[],
+				Default::default(),
+			).execute(ExecutionStrategy::Canary { fail_on_divergence: true })
+		};
+
+		assert_eq!(execute(true).unwrap(), vec![66]);
+		let error = execute(false).unwrap_err();
+		assert!(format!("{:?}", error).starts_with("Divergence("));
+		assert!(error.to_string().starts_with("Native and wasm execution diverged: result native"));
+	}
+
 	#[test]
 	fn dual_execution_strategy_detects_consensus_failure() {
 		let mut consensus_failed = false;
@@ -1065,6 +1356,110 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn prove_range_read_and_range_proof_check_works() {
+		let remote_backend = trie_backend::tests::test_trie();
+		let remote_root = remote_backend.storage_root(::std::iter::empty()).0;
+
+		// a range bounded by its end
+		let remote_proof = prove_range_read(remote_backend, b"k", Some(&[0x80]), 10).unwrap();
+		let local_result = range_proof_check::<Blake2Hasher>(
+			remote_root,
+			remote_proof,
+			b"k",
+			Some(&[0x80]),
+			10,
+		).unwrap();
+		assert_eq!(local_result, (vec![
+			(b"key".to_vec(), b"value".to_vec()),
+			(b"value1".to_vec(), vec![42]),
+			(b"value2".to_vec(), vec![24]),
+		], true));
+
+		// a range bounded by the number of keys
+		let remote_backend = trie_backend::tests::test_trie();
+		let remote_proof = prove_range_read(remote_backend, &[], None, 2).unwrap();
+		let local_result = range_proof_check::<Blake2Hasher>(
+			remote_root,
+			remote_proof.clone(),
+			&[],
+			None,
+			2,
+		).unwrap();
+		assert_eq!(local_result, (vec![
+			(b":child_storage:default:sub1".to_vec(), local_result.0[0].1.clone()),
+			(b":code".to_vec(), b"return 42".to_vec()),
+		], false));
+
+		// the proof doesn't cover a larger range
+		assert!(range_proof_check::<Blake2Hasher>(
+			remote_root,
+			remote_proof.clone(),
+			&[],
+			None,
+			3,
+		).is_err());
+
+		// nor can any node be omitted
+		let mut nodes = remote_proof.iter_nodes().collect::<Vec<_>>();
+		nodes.pop();
+		assert!(range_proof_check::<Blake2Hasher>(
+			remote_root,
+			StorageProof::new(nodes),
+			&[],
+			None,
+			2,
+		).is_err());
+	}
+
+	#[test]
+	fn prove_child_range_read_and_child_range_proof_check_works() {
+		let child_storage_key = b":child_storage:default:sub1";
+		let remote_backend = trie_backend::tests::test_trie();
+		let remote_root = remote_backend.storage_root(::std::iter::empty()).0;
+		let remote_proof = prove_child_range_read(
+			remote_backend,
+			child_storage_key,
+			CHILD_INFO_1,
+			&[],
+			None,
+			1,
+		).unwrap();
+
+		// checked against the root of the top trie
+		let local_result = child_range_proof_check::<Blake2Hasher>(
+			remote_root,
+			remote_proof.clone(),
+			child_storage_key,
+			CHILD_INFO_1,
+			&[],
+			None,
+			1,
+		).unwrap();
+		assert_eq!(local_result, (vec![(b"value3".to_vec(), vec![142])], false));
+
+		// a range exhausting the child trie
+		let remote_backend = trie_backend::tests::test_trie();
+		let remote_proof = prove_child_range_read(
+			remote_backend,
+			child_storage_key,
+			CHILD_INFO_1,
+			b"value4",
+			None,
+			10,
+		).unwrap();
+		let local_result = child_range_proof_check::<Blake2Hasher>(
+			remote_root,
+			remote_proof,
+			child_storage_key,
+			CHILD_INFO_1,
+			b"value4",
+			None,
+			10,
+		).unwrap();
+		assert_eq!(local_result, (vec![(b"value4".to_vec(), vec![124])], true));
+	}
+
 	#[test]
 	fn cannot_change_changes_trie_config() {
 		let backend = trie_backend::tests::test_trie();
@@ -46,6 +46,8 @@ pub struct OverlayedChanges {
 	/// Changes trie configuration. None by default, but could be installed by the
 	/// runtime if it supports change tries.
 	pub(crate) changes_trie_config: Option<ChangesTrieConfig>,
+	/// Prospective changes as they were when each open storage transaction started.
+	pub(crate) transactions: Vec<OverlayedChangeSet>,
 }
 
 /// The storage value, used inside OverlayedChanges.
@@ -67,6 +69,8 @@ pub struct OverlayedChangeSet {
 	pub top: BTreeMap<Vec<u8>, OverlayedValue>,
 	/// Child storage changes.
 	pub children: HashMap<Vec<u8>, (BTreeMap<Vec<u8>, OverlayedValue>, OwnedChildInfo)>,
+	/// Changes to the offchain storage indexed by the runtime. They are not part of the state.
+	pub offchain: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
 }
 
 /// A storage changes structure that can be generated by the data collected in [`OverlayedChanges`].
@@ -90,6 +94,10 @@ pub struct StorageChanges<Transaction, H: Hasher, N: BlockNumber> {
 	///
 	/// If changes trie is disabled the value is set to `None`.
 	pub changes_trie_transaction: Option<ChangesTrieTransaction<H, N>>,
+	/// All changes to the offchain storage, indexed by the runtime.
+	///
+	/// A value of `None` means that it was deleted.
+	pub offchain_storage_changes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
 }
 
 impl<Transaction, H: Hasher, N: BlockNumber> StorageChanges<Transaction, H, N> {
@@ -100,6 +108,7 @@ impl<Transaction, H: Hasher, N: BlockNumber> StorageChanges<Transaction, H, N> {
 		Transaction,
 		H::Out,
 		Option<ChangesTrieTransaction<H, N>>,
+		Vec<(Vec<u8>, Option<Vec<u8>>)>,
 	) {
 		(
 			self.main_storage_changes,
@@ -107,6 +116,7 @@ impl<Transaction, H: Hasher, N: BlockNumber> StorageChanges<Transaction, H, N> {
 			self.transaction,
 			self.transaction_storage_root,
 			self.changes_trie_transaction,
+			self.offchain_storage_changes,
 		)
 	}
 }
@@ -151,6 +161,7 @@ impl<Transaction: Default, H: Hasher, N: BlockNumber> Default for StorageChanges
 			transaction: Default::default(),
 			transaction_storage_root: Default::default(),
 			changes_trie_transaction: None,
+			offchain_storage_changes: Default::default(),
 		}
 	}
 }
@@ -161,6 +172,7 @@ impl FromIterator<(Vec<u8>, OverlayedValue)> for OverlayedChangeSet {
 		Self {
 			top: iter.into_iter().collect(),
 			children: Default::default(),
+			offchain: Default::default(),
 		}
 	}
 }
@@ -168,13 +180,50 @@ impl FromIterator<(Vec<u8>, OverlayedValue)> for OverlayedChangeSet {
 impl OverlayedChangeSet {
 	/// Whether the change set is empty.
 	pub fn is_empty(&self) -> bool {
-		self.top.is_empty() && self.children.is_empty()
+		self.top.is_empty() && self.children.is_empty() && self.offchain.is_empty()
 	}
 
 	/// Clear the change set.
 	pub fn clear(&mut self) {
 		self.top.clear();
 		self.children.clear();
+		self.offchain.clear();
+	}
+
+	/// Collect the keys whose values differ between this change set and `other`.
+	///
+	/// Child keys are reported prefixed with their child storage key.
+	pub fn diverging_keys(&self, other: &OverlayedChangeSet) -> Vec<Vec<u8>> {
+		fn diff(
+			prefix: &[u8],
+			a: &BTreeMap<Vec<u8>, OverlayedValue>,
+			b: &BTreeMap<Vec<u8>, OverlayedValue>,
+			out: &mut Vec<Vec<u8>>,
+		) {
+			let keys: BTreeSet<&Vec<u8>> = a.keys().chain(b.keys()).collect();
+			for key in keys {
+				let left = a.get(key).map(|v| &v.value);
+				let right = b.get(key).map(|v| &v.value);
+				if left != right {
+					out.push(prefix.iter().chain(key.iter()).cloned().collect());
+				}
+			}
+		}
+
+		let empty = BTreeMap::new();
+		let mut out = Vec::new();
+		diff(&[], &self.top, &other.top, &mut out);
+
+		let storage_keys: BTreeSet<&Vec<u8>> = self.children.keys()
+			.chain(other.children.keys())
+			.collect();
+		for storage_key in storage_keys {
+			let left = self.children.get(storage_key).map(|c| &c.0).unwrap_or(&empty);
+			let right = other.children.get(storage_key).map(|c| &c.0).unwrap_or(&empty);
+			diff(storage_key, left, right, &mut out);
+		}
+
+		out
 	}
 }
 
@@ -267,6 +316,14 @@ impl OverlayedChanges {
 		}
 	}
 
+	/// Inserts the given key-value pair into the prospective offchain change set.
+	///
+	/// `None` can be used to delete a value specified by the given key. The offchain changes
+	/// are written to the offchain storage of the node when the block is imported.
+	pub(crate) fn set_offchain_storage(&mut self, key: Vec<u8>, val: Option<Vec<u8>>) {
+		self.prospective.offchain.insert(key, val);
+	}
+
 	/// Clear child storage of given storage key.
 	///
 	/// NOTE that this doesn't take place immediately but written into the prospective
@@ -390,10 +447,33 @@ impl OverlayedChanges {
 	/// Discard prospective changes to state.
 	pub fn discard_prospective(&mut self) {
 		self.prospective.clear();
+		self.transactions.clear();
+	}
+
+	/// Start a storage transaction, nested in the ones already open.
+	///
+	/// The prospective changes made from now on can be rolled back with `rollback_transaction`,
+	/// or kept with `commit_transaction`.
+	pub fn start_transaction(&mut self) {
+		self.transactions.push(self.prospective.clone());
+	}
+
+	/// Discard the prospective changes made since the last open transaction started, and close
+	/// it. Returns `Err` if no transaction is open.
+	pub fn rollback_transaction(&mut self) -> Result<(), ()> {
+		self.prospective = self.transactions.pop().ok_or(())?;
+		Ok(())
+	}
+
+	/// Keep the prospective changes made since the last open transaction started, and close it.
+	/// Returns `Err` if no transaction is open.
+	pub fn commit_transaction(&mut self) -> Result<(), ()> {
+		self.transactions.pop().map(drop).ok_or(())
 	}
 
 	/// Commit prospective changes to state.
 	pub fn commit_prospective(&mut self) {
+		self.transactions.clear();
 		if self.committed.is_empty() {
 			mem::swap(&mut self.prospective, &mut self.committed);
 		} else {
@@ -421,6 +501,8 @@ impl OverlayedChanges {
 					}
 				}
 			}
+			let offchain_to_commit = mem::replace(&mut self.prospective.offchain, BTreeMap::new());
+			self.committed.offchain.extend(offchain_to_commit);
 		}
 	}
 
@@ -444,7 +526,7 @@ impl OverlayedChanges {
 	pub fn into_storage_changes<
 		B: Backend<H>, H: Hasher, N: BlockNumber, T: ChangesTrieStorage<H, N>
 	>(
-		self,
+		mut self,
 		backend: &B,
 		changes_trie_storage: Option<&T>,
 		parent_hash: H::Out,
@@ -474,6 +556,7 @@ impl OverlayedChanges {
 			.take()
 			.expect("Changes trie transaction was generated by `changes_trie_root`; qed");
 
+		let offchain_storage_changes = mem::replace(&mut self.committed.offchain, BTreeMap::new());
 		let (main_storage_changes, child_storage_changes) = self.into_committed();
 
 		Ok(StorageChanges {
@@ -482,6 +565,7 @@ impl OverlayedChanges {
 			transaction,
 			transaction_storage_root,
 			changes_trie_transaction,
+			offchain_storage_changes: offchain_storage_changes.into_iter().collect(),
 		})
 	}
 
@@ -696,6 +780,80 @@ mod tests {
 		assert!(overlayed.storage(&key).unwrap().is_none());
 	}
 
+	#[test]
+	fn transactions_roll_back_or_keep_their_changes() {
+		let mut overlayed = OverlayedChanges::default();
+		let key = vec![42, 69, 169, 142];
+
+		overlayed.set_storage(key.clone(), Some(vec![1]));
+		overlayed.start_transaction();
+		overlayed.set_storage(key.clone(), Some(vec![2]));
+		overlayed.start_transaction();
+		overlayed.set_storage(key.clone(), None);
+		assert!(overlayed.storage(&key).unwrap().is_none());
+
+		assert_eq!(overlayed.rollback_transaction(), Ok(()));
+		assert_eq!(overlayed.storage(&key).unwrap(), Some(&[2][..]));
+
+		assert_eq!(overlayed.commit_transaction(), Ok(()));
+		assert_eq!(overlayed.storage(&key).unwrap(), Some(&[2][..]));
+
+		assert_eq!(overlayed.rollback_transaction(), Err(()));
+		assert_eq!(overlayed.commit_transaction(), Err(()));
+
+		// transactions left open are closed with the prospective changes.
+		overlayed.start_transaction();
+		overlayed.commit_prospective();
+		assert_eq!(overlayed.rollback_transaction(), Err(()));
+	}
+
+	#[test]
+	fn offchain_changes_follow_the_prospective_changes() {
+		let mut overlayed = OverlayedChanges::default();
+
+		overlayed.set_offchain_storage(vec![1], Some(vec![1]));
+		overlayed.commit_prospective();
+		overlayed.set_offchain_storage(vec![2], Some(vec![2]));
+		overlayed.start_transaction();
+		overlayed.set_offchain_storage(vec![3], Some(vec![3]));
+		assert_eq!(overlayed.rollback_transaction(), Ok(()));
+		overlayed.commit_prospective();
+		overlayed.set_offchain_storage(vec![1], None);
+		overlayed.discard_prospective();
+
+		let backend = InMemoryBackend::<Blake2Hasher>::default();
+		let changes = overlayed.into_storage_changes::<_, Blake2Hasher, u64, InMemoryChangesTrieStorage<_, u64>>(
+			&backend,
+			None,
+			Default::default(),
+			Default::default(),
+		).unwrap();
+		assert_eq!(
+			changes.offchain_storage_changes,
+			vec![(vec![1], Some(vec![1])), (vec![2], Some(vec![2]))],
+		);
+	}
+
+	#[test]
+	fn diverging_keys_reports_differing_values() {
+		let a: OverlayedChangeSet = vec![
+			(b"dog".to_vec(), Some(b"puppy".to_vec()).into()),
+			(b"doe".to_vec(), Some(b"reindeer".to_vec()).into()),
+			(b"doug".to_vec(), None.into()),
+		].into_iter().collect();
+		let b: OverlayedChangeSet = vec![
+			(b"dog".to_vec(), Some(b"puppy".to_vec()).into()),
+			(b"doe".to_vec(), Some(b"deer".to_vec()).into()),
+			(b"dogglesworth".to_vec(), Some(b"cat".to_vec()).into()),
+		].into_iter().collect();
+
+		assert!(a.diverging_keys(&a.clone()).is_empty());
+		assert_eq!(
+			a.diverging_keys(&b),
+			vec![b"doe".to_vec(), b"dogglesworth".to_vec(), b"doug".to_vec()],
+		);
+	}
+
 	#[test]
 	fn overlayed_storage_root_works() {
 		let initial: BTreeMap<_, _> = vec![
@@ -17,6 +17,7 @@
 //! Test implementation for Externalities.
 
 use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
 use hash_db::Hasher;
 use crate::{
 	backend::Backend, OverlayedChanges, StorageTransactionCache, ext::Ext, InMemoryBackend,
@@ -130,6 +131,13 @@ impl<H: Hasher, N: ChangesTrieBlockNumber> TestExternalities<H, N>
 		self.backend.update(transaction)
 	}
 
+	/// Return the pending changes to the offchain storage, indexed by the runtime.
+	pub fn offchain_index(&self) -> BTreeMap<Vec<u8>, Option<Vec<u8>>> {
+		let mut changes = self.overlay.committed.offchain.clone();
+		changes.extend(self.overlay.prospective.offchain.clone());
+		changes
+	}
+
 	/// Execute the given closure while `self` is set as externalities.
 	///
 	/// Returns the result of the given closure.
@@ -200,12 +200,19 @@ pub trait TransactionPool: Send + Sync {
 	/// Get an iterator for ready transactions ordered by priority
 	fn ready(&self) -> Box<dyn Iterator<Item=Arc<Self::InPoolTransaction>>>;
 
+	/// Get the future transactions, waiting for the tags they require.
+	fn futures(&self) -> Vec<Arc<Self::InPoolTransaction>>;
+
 
 	// Block production
 
 	/// Remove transactions identified by given hashes (and dependent transactions) from the pool.
 	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>>;
 
+	/// Remove transactions identified by given hashes (and dependent transactions) from the pool,
+	/// without banning them, so that they can be submitted again.
+	fn remove(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>>;
+
 	// logging
 
 	/// Returns pool status.
@@ -333,6 +340,10 @@ impl<Pool, Maintainer> TransactionPool for MaintainableTransactionPool<Pool, Mai
 		self.pool.remove_invalid(hashes)
 	}
 
+	fn remove(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.remove(hashes)
+	}
+
 	fn status(&self) -> PoolStatus {
 		self.pool.status()
 	}
@@ -341,6 +352,10 @@ impl<Pool, Maintainer> TransactionPool for MaintainableTransactionPool<Pool, Mai
 		self.pool.ready()
 	}
 
+	fn futures(&self) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.futures()
+	}
+
 	fn import_notification_stream(&self) -> ImportNotificationStream {
 		self.pool.import_notification_stream()
 	}
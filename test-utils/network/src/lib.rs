@@ -0,0 +1,294 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! In-process multi-node networks for integration tests.
+//!
+//! [`TestNetwork`] drives any [`TestNetFactory`] of `sc-network-test`, whose peers are full
+//! clients connected through in-memory transports. On top of it, it offers the operations
+//! integration tests usually need: authoring blocks on a peer, splitting the network into
+//! partitions and healing it again, and waiting for peers to reach a given best or finalized
+//! block.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use substrate_test_network::{TestNet, TestNetwork};
+//!
+//! let mut network = TestNetwork::<TestNet>::new(4);
+//! network.partition(&[&[0, 1], &[2, 3]]);
+//! network.author_blocks(0, 5);
+//! network.author_blocks(2, 3);
+//! network.heal();
+//! network.assert_best_number(0..4, 5, Duration::from_secs(10));
+//! ```
+
+#![warn(missing_docs)]
+
+use std::{collections::HashSet, fmt, thread, time::{Duration, Instant}};
+
+use futures::{future, Async};
+use sp_runtime::{generic::BlockId, traits::NumberFor};
+use tokio::runtime::current_thread::Runtime;
+
+pub use sc_network_test::{Block, Hash, Peer, PeersClient, TestNet, TestNetFactory, JustificationTestNet};
+
+/// How long to sleep between two polls of the network that made no progress.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The block number type of the test runtime.
+pub type BlockNumber = NumberFor<Block>;
+
+/// Error returned when a condition was not met in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeout(pub Duration);
+
+impl fmt::Display for Timeout {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Condition not met within {:?}", self.0)
+	}
+}
+
+impl std::error::Error for Timeout {}
+
+/// A network of in-process peers.
+pub struct TestNetwork<N> {
+	net: N,
+	runtime: Runtime,
+	/// The partition each peer belongs to, if the network is partitioned.
+	partitions: Option<Vec<usize>>,
+}
+
+impl<N: TestNetFactory> TestNetwork<N> {
+	/// Create a network of `peers` full peers, all connected to each other.
+	pub fn new(peers: usize) -> Self {
+		Self::from_net(N::new(peers))
+	}
+
+	/// Wrap an existing network.
+	pub fn from_net(net: N) -> Self {
+		TestNetwork {
+			net,
+			runtime: Runtime::new().expect("Creating a current thread runtime never fails; qed"),
+			partitions: None,
+		}
+	}
+
+	/// The underlying network.
+	pub fn net(&mut self) -> &mut N {
+		&mut self.net
+	}
+
+	/// The peer with the given index.
+	pub fn peer(&mut self, index: usize) -> &mut Peer<N::PeerData, N::Specialization> {
+		self.net.peer(index)
+	}
+
+	/// Number of peers in the network.
+	pub fn len(&self) -> usize {
+		self.net.peers().len()
+	}
+
+	/// Author `count` blocks on top of the best block of the given peer, returning the hash of
+	/// the last one. The blocks are announced to the connected peers.
+	pub fn author_blocks(&mut self, peer: usize, count: usize) -> Hash {
+		self.net.peer(peer).push_blocks(count, false)
+	}
+
+	/// Finalize the block with the given hash on the given peer.
+	pub fn finalize(&mut self, peer: usize, hash: Hash) {
+		self.net.peer(peer).client().finalize_block(BlockId::Hash(hash), None, true)
+			.expect("Finalizing a known block succeeds");
+	}
+
+	/// Split the network into the given partitions.
+	///
+	/// Peers only stay connected to peers of their own partition. Peers not listed in
+	/// any partition are isolated.
+	pub fn partition(&mut self, partitions: &[&[usize]]) {
+		let mut assignment = vec![usize::max_value(); self.len()];
+		for (index, members) in partitions.iter().enumerate() {
+			for &peer in members.iter() {
+				assignment[peer] = index;
+			}
+		}
+
+		let addresses = self.net.peers().iter()
+			.map(|peer| format!("{}/p2p/{}", peer.listen_addr(), peer.id()))
+			.collect::<Vec<_>>();
+		for (index, peer) in self.net.peers().iter().enumerate() {
+			let service = peer.network_service();
+			for (other, address) in addresses.iter().enumerate() {
+				if other != index && assignment[other] == assignment[index] && assignment[index] != usize::max_value() {
+					service.add_reserved_peer(address.clone())
+						.expect("The address was created from a valid peer id and multiaddr; qed");
+				}
+			}
+			service.deny_unreserved_peers();
+		}
+
+		self.partitions = Some(assignment);
+	}
+
+	/// Undo a partition, allowing all peers to connect to each other again.
+	pub fn heal(&mut self) {
+		let ids = self.net.peers().iter().map(|peer| peer.id()).collect::<Vec<_>>();
+		for peer in self.net.peers().iter() {
+			let service = peer.network_service();
+			for id in ids.iter().filter(|id| **id != peer.id()) {
+				service.remove_reserved_peer(id.clone());
+			}
+			service.accept_unreserved_peers();
+		}
+		self.partitions = None;
+	}
+
+	/// Whether the two peers may be connected under the current partition.
+	pub fn can_connect(&self, a: usize, b: usize) -> bool {
+		match &self.partitions {
+			Some(assignment) => assignment[a] == assignment[b] && assignment[a] != usize::max_value(),
+			None => true,
+		}
+	}
+
+	/// Poll the network once, processing all pending events.
+	pub fn poll(&mut self) {
+		let net = &mut self.net;
+		let _ = self.runtime.block_on(future::poll_fn::<(), (), _>(|| {
+			net.poll();
+			Ok(Async::Ready(()))
+		}));
+	}
+
+	/// Poll the network until `condition` holds, or fail after `timeout`.
+	pub fn run_until<F>(&mut self, timeout: Duration, mut condition: F) -> Result<(), Timeout> where
+		F: FnMut(&N) -> bool,
+	{
+		let deadline = Instant::now() + timeout;
+		loop {
+			self.poll();
+			if condition(&self.net) {
+				return Ok(())
+			}
+			if Instant::now() >= deadline {
+				return Err(Timeout(timeout))
+			}
+			thread::sleep(POLL_INTERVAL);
+		}
+	}
+
+	/// Poll the network for the given duration.
+	pub fn run_for(&mut self, duration: Duration) {
+		let _ = self.run_until(duration, |_| false);
+	}
+
+	/// Wait until all given peers have the given best block number, panicking after `timeout`.
+	pub fn assert_best_number(
+		&mut self,
+		peers: impl IntoIterator<Item = usize>,
+		number: BlockNumber,
+		timeout: Duration,
+	) {
+		self.assert_all(peers, timeout, "best", |peer| peer.client().info().best_number == number)
+	}
+
+	/// Wait until all given peers have finalized at least the given block number, panicking
+	/// after `timeout`.
+	pub fn assert_finalized(
+		&mut self,
+		peers: impl IntoIterator<Item = usize>,
+		number: BlockNumber,
+		timeout: Duration,
+	) {
+		self.assert_all(peers, timeout, "finalized", |peer| peer.client().info().finalized_number >= number)
+	}
+
+	/// Wait until all given peers are connected to exactly the given number of peers,
+	/// panicking after `timeout`.
+	pub fn assert_connected(
+		&mut self,
+		peers: impl IntoIterator<Item = usize>,
+		connected: usize,
+		timeout: Duration,
+	) {
+		self.assert_all(peers, timeout, "connected", |peer| peer.num_peers() == connected)
+	}
+
+	fn assert_all<F>(
+		&mut self,
+		peers: impl IntoIterator<Item = usize>,
+		timeout: Duration,
+		what: &str,
+		check: F,
+	) where F: Fn(&Peer<N::PeerData, N::Specialization>) -> bool {
+		let peers = peers.into_iter().collect::<HashSet<_>>();
+		let result = self.run_until(timeout, |net| {
+			peers.iter().all(|index| check(&net.peers()[*index]))
+		});
+
+		if let Err(e) = result {
+			let state = self.net.peers().iter().enumerate()
+				.filter(|(index, _)| peers.contains(index))
+				.map(|(index, peer)| {
+					let info = peer.client().info();
+					format!(
+						"peer {}: best #{} ({}), finalized #{}, {} connected",
+						index, info.best_number, info.best_hash, info.finalized_number, peer.num_peers(),
+					)
+				})
+				.collect::<Vec<_>>();
+			panic!("Peers not {}: {}\n{}", what, e, state.join("\n"));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TIMEOUT: Duration = Duration::from_secs(30);
+
+	#[test]
+	fn syncs_and_finalizes() {
+		let mut network = TestNetwork::<TestNet>::new(3);
+		network.assert_connected(0..3, 2, TIMEOUT);
+
+		let hash = network.author_blocks(0, 10);
+		network.assert_best_number(0..3, 10, TIMEOUT);
+
+		for peer in 0..3 {
+			network.finalize(peer, hash);
+		}
+		network.assert_finalized(0..3, 10, TIMEOUT);
+	}
+
+	#[test]
+	fn partitions_and_heals() {
+		let mut network = TestNetwork::<TestNet>::new(4);
+		network.assert_connected(0..4, 3, TIMEOUT);
+
+		network.partition(&[&[0, 1], &[2, 3]]);
+		network.assert_connected(0..4, 1, TIMEOUT);
+		assert!(network.can_connect(0, 1));
+		assert!(!network.can_connect(1, 2));
+
+		network.author_blocks(0, 5);
+		network.author_blocks(2, 3);
+		network.assert_best_number(0..2, 5, TIMEOUT);
+		network.assert_best_number(2..4, 3, TIMEOUT);
+
+		network.heal();
+		network.assert_best_number(0..4, 5, TIMEOUT);
+	}
+}
@@ -353,11 +353,26 @@ impl From<frame_system::Event> for Event {
 parameter_types! {
 	pub const BlockHashCount: BlockNumber = 250;
 	pub const MinimumPeriod: u64 = 5;
+	pub const MaxDrift: u64 = 30 * 1000;
 	pub const MaximumBlockWeight: Weight = 4 * 1024 * 1024;
 	pub const MaximumBlockLength: u32 = 4 * 1024 * 1024;
 	pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
 }
 
+/// The weight of the block reported to block authors. The test runtime doesn't account the weight
+/// of the extrinsics, so the block looks empty.
+fn empty_block_weight() -> sp_block_builder::BlockWeight {
+	use frame_support::traits::Get;
+
+	let maximum = MaximumBlockWeight::get();
+	sp_block_builder::BlockWeight {
+		hooks: 0,
+		consumed: 0,
+		normal_limit: (AvailableBlockRatio::get() * maximum) as u64,
+		maximum: maximum as u64,
+	}
+}
+
 impl frame_system::Trait for Runtime {
 	type Origin = Origin;
 	type Call = Extrinsic;
@@ -382,6 +397,7 @@ impl pallet_timestamp::Trait for Runtime {
 	type Moment = u64;
 	type OnTimestampSet = ();
 	type MinimumPeriod = MinimumPeriod;
+	type MaxDrift = MaxDrift;
 }
 
 parameter_types! {
@@ -514,6 +530,10 @@ cfg_if! {
 				fn random_seed() -> <Block as BlockT>::Hash {
 					unimplemented!()
 				}
+
+				fn block_weight() -> sp_block_builder::BlockWeight {
+					empty_block_weight()
+				}
 			}
 
 			impl self::TestAPI<Block> for Runtime {
@@ -640,6 +660,12 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::MortalityApi<Block> for Runtime {
+				fn era_period() -> u64 {
+					BlockHashCount::get().next_power_of_two() / 2
+				}
+			}
 		}
 	} else {
 		impl_runtime_apis! {
@@ -699,6 +725,10 @@ cfg_if! {
 				fn random_seed() -> <Block as BlockT>::Hash {
 					unimplemented!()
 				}
+
+				fn block_weight() -> sp_block_builder::BlockWeight {
+					empty_block_weight()
+				}
 			}
 
 			impl self::TestAPI<Block> for Runtime {
@@ -856,6 +886,12 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::MortalityApi<Block> for Runtime {
+				fn era_period() -> u64 {
+					BlockHashCount::get().next_power_of_two() / 2
+				}
+			}
 		}
 	}
 }
@@ -269,7 +269,8 @@ fn execute_transfer_backend(tx: &Transfer) -> ApplyExtrinsicResult {
 	let to_balance_key = tx.to.to_keyed_vec(BALANCE_OF);
 	let to_balance: u64 = storage::hashed::get_or(&blake2_256, &to_balance_key, 0);
 	storage::hashed::put(&blake2_256, &from_balance_key, &(from_balance - tx.amount));
-	storage::hashed::put(&blake2_256, &to_balance_key, &(to_balance + tx.amount));
+	let to_balance = to_balance.checked_add(tx.amount).expect("The balance of the recipient overflows");
+	storage::hashed::put(&blake2_256, &to_balance_key, &to_balance);
 	Ok(Ok(()))
 }
 
@@ -0,0 +1,360 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Detection of changes between two runtime metadata versions that break encoded data.
+//!
+//! Calls, events and errors are identified on the wire by the index of their module and their
+//! index within the module. Moving, renaming or removing any of them, or changing the encoding
+//! of their arguments, breaks transactions and tooling built against the older runtime.
+//! Appending new items is compatible.
+
+use std::fmt;
+
+use frame_metadata::ModuleMetadata;
+
+use crate::decoder::{decoded, MAX_DEPTH};
+use crate::{Decoder, Result, TypeDef, TypeName, TypeRegistry};
+
+/// A change that breaks data encoded for the older runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakingChange {
+	/// The module and kind of item affected, e.g. `Balances calls`.
+	pub location: String,
+	/// What changed.
+	pub description: String,
+}
+
+impl fmt::Display for BreakingChange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: {}", self.location, self.description)
+	}
+}
+
+/// Compare the metadata of two runtimes, reporting all changes that break calls, events or
+/// errors encoded for the `old` runtime.
+///
+/// Argument types are compared by their layout, resolved with the registry of the respective
+/// decoder, so renaming a type or adding an alias is not reported.
+pub fn check_compat(old: &Decoder, new: &Decoder) -> Result<Vec<BreakingChange>> {
+	let layouts = Layouts { old: old.registry(), new: new.registry() };
+	let mut changes = Vec::new();
+
+	for kind in &[Kind::Call, Kind::Event, Kind::Error] {
+		let old_modules = kind.modules(old)?;
+		let new_modules = kind.modules(new)?;
+
+		for (index, (module, old_items)) in old_modules.iter().enumerate() {
+			let mut report = |description: String| changes.push(BreakingChange {
+				location: format!("{} {}", module, kind.plural()),
+				description,
+			});
+
+			let (new_index, new_items) = match new_modules.iter()
+				.enumerate()
+				.find(|(_, (name, _))| name == module)
+			{
+				Some((new_index, (_, new_items))) => (new_index, new_items),
+				None => {
+					report("removed".into());
+					continue
+				},
+			};
+
+			if new_index != index {
+				report(format!("module index changed from {} to {}", index, new_index));
+			}
+
+			for (i, item) in old_items.iter().enumerate() {
+				match new_items.get(i) {
+					None => report(format!("{} `{}` at index {} removed", kind.singular(), item.name, i)),
+					Some(new_item) if new_item.name != item.name => report(format!(
+						"{} at index {} changed from `{}` to `{}`",
+						kind.singular(),
+						i,
+						item.name,
+						new_item.name,
+					)),
+					Some(new_item) if !layouts.same_all(&item.args, &new_item.args, 0) => report(format!(
+						"arguments of {} `{}` changed from ({}) to ({})",
+						kind.singular(),
+						item.name,
+						join(&item.args),
+						join(&new_item.args),
+					)),
+					Some(_) => {},
+				}
+			}
+		}
+	}
+
+	Ok(changes)
+}
+
+/// A call, event or error with the types of its arguments.
+struct Item {
+	name: String,
+	args: Vec<TypeName>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+	Call,
+	Event,
+	Error,
+}
+
+impl Kind {
+	fn singular(self) -> &'static str {
+		match self {
+			Kind::Call => "call",
+			Kind::Event => "event",
+			Kind::Error => "error",
+		}
+	}
+
+	fn plural(self) -> &'static str {
+		match self {
+			Kind::Call => "calls",
+			Kind::Event => "events",
+			Kind::Error => "errors",
+		}
+	}
+
+	/// The items of this kind of a module, `None` if the module has no such item type.
+	///
+	/// Modules without calls or events are skipped when indexing the outer enums, while
+	/// errors are indexed by the position of the module in the runtime.
+	fn items(self, module: &ModuleMetadata) -> Result<Option<Vec<Item>>> {
+		let items = match self {
+			Kind::Call => match &module.calls {
+				Some(calls) => decoded(calls)?.iter()
+					.map(|call| Ok(Item {
+						name: decoded(&call.name)?.clone(),
+						args: decoded(&call.arguments)?.iter()
+							.map(|arg| TypeName::parse(decoded(&arg.ty)?))
+							.collect::<Result<_>>()?,
+					}))
+					.collect::<Result<_>>()?,
+				None => return Ok(None),
+			},
+			Kind::Event => match &module.event {
+				Some(events) => decoded(events)?.iter()
+					.map(|event| Ok(Item {
+						name: decoded(&event.name)?.clone(),
+						args: decoded(&event.arguments)?.iter()
+							.map(|ty| TypeName::parse(ty))
+							.collect::<Result<_>>()?,
+					}))
+					.collect::<Result<_>>()?,
+				None => return Ok(None),
+			},
+			Kind::Error => decoded(&module.errors)?.iter()
+				.map(|error| Ok(Item { name: decoded(&error.name)?.clone(), args: Vec::new() }))
+				.collect::<Result<_>>()?,
+		};
+		Ok(Some(items))
+	}
+
+	/// The modules taking part in the indexing of this kind, in index order.
+	fn modules(self, decoder: &Decoder) -> Result<Vec<(String, Vec<Item>)>> {
+		let mut modules = Vec::new();
+		for module in decoder.modules() {
+			if let Some(items) = self.items(module)? {
+				modules.push((decoded(&module.name)?.clone(), items));
+			}
+		}
+		Ok(modules)
+	}
+}
+
+/// Compares the encoding of types of the old and the new runtime.
+struct Layouts<'a> {
+	old: &'a TypeRegistry,
+	new: &'a TypeRegistry,
+}
+
+impl<'a> Layouts<'a> {
+	fn same_all(&self, old: &[TypeName], new: &[TypeName], depth: usize) -> bool {
+		old.len() == new.len() && old.iter().zip(new).all(|(old, new)| self.same(old, new, depth))
+	}
+
+	fn same(&self, old: &TypeName, new: &TypeName, depth: usize) -> bool {
+		if depth > MAX_DEPTH {
+			return old == new
+		}
+		let depth = depth + 1;
+
+		// Aliases don't change the encoding.
+		if let Some(TypeDef::Alias(aliased)) = old.name().and_then(|name| self.old.get(name)) {
+			return self.same(aliased, new, depth)
+		}
+		if let Some(TypeDef::Alias(aliased)) = new.name().and_then(|name| self.new.get(name)) {
+			return self.same(old, aliased, depth)
+		}
+
+		match (old, new) {
+			(TypeName::Tuple(old), TypeName::Tuple(new)) => self.same_all(old, new, depth),
+			(TypeName::Array(old, old_len), TypeName::Array(new, new_len)) =>
+				old_len == new_len && self.same(old, new, depth),
+			(TypeName::Slice(old), TypeName::Slice(new)) => self.same(old, new, depth),
+			(
+				TypeName::Path { name: old_name, params: old_params },
+				TypeName::Path { name: new_name, params: new_params },
+			) => self.same_all(old_params, new_params, depth) &&
+				match (self.old.get(old_name), self.new.get(new_name)) {
+					(Some(TypeDef::Struct(old)), Some(TypeDef::Struct(new))) =>
+						old.len() == new.len() &&
+							old.iter().zip(new).all(|((_, old), (_, new))| self.same(old, new, depth)),
+					(Some(TypeDef::Enum(old)), Some(TypeDef::Enum(new))) =>
+						old.len() == new.len() &&
+							old.iter().zip(new).all(|((_, old), (_, new))| match (old, new) {
+								(None, None) => true,
+								(Some(old), Some(new)) => self.same(old, new, depth),
+								_ => false,
+							}),
+					(None, None) => old_name == new_name,
+					_ => false,
+				},
+			_ => false,
+		}
+	}
+}
+
+fn join(types: &[TypeName]) -> String {
+	types.iter().map(|ty| ty.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use frame_metadata::{
+		DecodeDifferent, ErrorMetadata, EventMetadata, FunctionArgumentMetadata, FunctionMetadata,
+		RuntimeMetadata, RuntimeMetadataPrefixed, RuntimeMetadataV10, META_RESERVED,
+	};
+
+	fn d<T>(value: T) -> DecodeDifferent<&'static str, T> {
+		DecodeDifferent::Decoded(value)
+	}
+
+	fn module(name: &str, calls: &[(&str, &[&str])], events: &[&str], errors: &[&str]) -> ModuleMetadata {
+		ModuleMetadata {
+			name: d(name.into()),
+			storage: None,
+			calls: if calls.is_empty() { None } else {
+				Some(d(calls.iter().map(|(name, args)| FunctionMetadata {
+					name: d((*name).into()),
+					arguments: d(args.iter().enumerate().map(|(i, ty)| FunctionArgumentMetadata {
+						name: d(format!("arg{}", i)),
+						ty: d((*ty).into()),
+					}).collect()),
+					documentation: d(vec![]),
+				}).collect()))
+			},
+			event: if events.is_empty() { None } else {
+				Some(d(events.iter().map(|name| EventMetadata {
+					name: d((*name).into()),
+					arguments: d(vec![]),
+					documentation: d(vec![]),
+				}).collect()))
+			},
+			constants: d(vec![]),
+			errors: d(errors.iter().map(|name| ErrorMetadata {
+				name: d((*name).into()),
+				documentation: d(vec![]),
+			}).collect()),
+		}
+	}
+
+	fn decoder(modules: Vec<ModuleMetadata>, registry: TypeRegistry) -> Decoder {
+		let metadata = RuntimeMetadataPrefixed(
+			META_RESERVED,
+			RuntimeMetadata::V10(RuntimeMetadataV10 { modules: d(modules) }),
+		);
+		Decoder::from_bytes(&metadata.encode(), registry).unwrap()
+	}
+
+	fn balances(transfer_value: &str) -> ModuleMetadata {
+		module(
+			"Balances",
+			&[("transfer", &["Address", transfer_value][..])],
+			&["Transfer"],
+			&["InsufficientBalance"],
+		)
+	}
+
+	#[test]
+	fn appending_items_is_compatible() {
+		let old = decoder(
+			vec![module("System", &[], &["ExtrinsicSuccess"], &[]), balances("Balance")],
+			TypeRegistry::new(),
+		);
+		let mut new_balances = balances("Balance");
+		new_balances.errors = d(vec![
+			ErrorMetadata { name: d("InsufficientBalance".into()), documentation: d(vec![]) },
+			ErrorMetadata { name: d("ExistentialDeposit".into()), documentation: d(vec![]) },
+		]);
+		let new = decoder(vec![
+			module("System", &[], &["ExtrinsicSuccess"], &[]),
+			new_balances,
+			module("Sudo", &[("sudo", &["Call"][..])], &[], &[]),
+		], TypeRegistry::new());
+
+		assert_eq!(check_compat(&old, &new).unwrap(), vec![]);
+	}
+
+	#[test]
+	fn reports_moved_and_changed_items() {
+		let old = decoder(
+			vec![module("System", &[], &["ExtrinsicSuccess"], &[]), balances("Balance")],
+			TypeRegistry::new(),
+		);
+		let new = decoder(vec![
+			module("Timestamp", &[("set", &["Moment"][..])], &[], &[]),
+			module("System", &[], &["ExtrinsicFailed"], &[]),
+			balances("u64"),
+		], TypeRegistry::new());
+
+		let changes = check_compat(&old, &new).unwrap()
+			.into_iter()
+			.map(|change| change.to_string())
+			.collect::<Vec<_>>();
+		assert_eq!(changes, vec![
+			"Balances calls: module index changed from 0 to 1",
+			"Balances calls: arguments of call `transfer` changed from (Address, Balance) to (Address, u64)",
+			"System events: event at index 0 changed from `ExtrinsicSuccess` to `ExtrinsicFailed`",
+			"System errors: module index changed from 0 to 1",
+			"Balances errors: module index changed from 1 to 2",
+		]);
+	}
+
+	#[test]
+	fn compares_argument_layouts() {
+		let mut old_registry = TypeRegistry::new();
+		old_registry.register("Balance", TypeDef::Alias(TypeName::named("u128")));
+		let mut new_registry = TypeRegistry::new();
+		new_registry.register("BalanceOf", TypeDef::Alias(TypeName::named("u128")));
+
+		let old = decoder(vec![balances("Balance")], old_registry.clone());
+		let new = decoder(vec![balances("BalanceOf")], new_registry);
+		assert_eq!(check_compat(&old, &new).unwrap(), vec![]);
+
+		let mut new_registry = TypeRegistry::new();
+		new_registry.register("Balance", TypeDef::Alias(TypeName::named("u64")));
+		let new = decoder(vec![balances("Balance")], new_registry);
+		assert_eq!(check_compat(&old, &new).unwrap().len(), 1);
+	}
+}
@@ -0,0 +1,781 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of SCALE encoded data into JSON, driven by the runtime metadata.
+
+use codec::{Compact, Decode};
+use frame_metadata::{
+	DecodeDifferent, ModuleMetadata, RuntimeMetadata, RuntimeMetadataPrefixed, RuntimeMetadataV10,
+	StorageEntryMetadata, StorageEntryModifier, StorageEntryType, StorageHasher, META_RESERVED,
+};
+use serde_json::{json, Map, Value};
+use sp_core::hashing::twox_128;
+
+use crate::{Error, Result, TypeDef, TypeName, TypeRegistry};
+
+/// Maximum nesting of types the decoder follows, guarding against cyclic definitions.
+pub(crate) const MAX_DEPTH: usize = 64;
+
+/// The extrinsic format version the decoder understands.
+pub(crate) const EXTRINSIC_VERSION: u8 = 4;
+
+/// Human readable information about an error of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleError {
+	/// Name of the module.
+	pub module: String,
+	/// Name of the error.
+	pub error: String,
+	/// Documentation of the error.
+	pub documentation: Vec<String>,
+}
+
+/// Decodes SCALE encoded runtime data using the metadata of that runtime.
+pub struct Decoder {
+	modules: Vec<ModuleMetadata>,
+	registry: TypeRegistry,
+}
+
+/// Access the value of a decoded metadata field.
+pub(crate) fn decoded<B, O>(value: &DecodeDifferent<B, O>) -> Result<&O> {
+	match value {
+		DecodeDifferent::Decoded(value) => Ok(value),
+		DecodeDifferent::Encode(_) => Err(Error::InvalidMetadata("Metadata was not decoded".into())),
+	}
+}
+
+impl Decoder {
+	/// Create a decoder from the metadata as returned by `state_getMetadata`.
+	pub fn from_bytes(metadata: &[u8], registry: TypeRegistry) -> Result<Self> {
+		let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+			.map_err(|e| Error::InvalidMetadata(e.what().into()))?;
+		Self::new(metadata, registry)
+	}
+
+	/// Create a decoder from the decoded metadata.
+	pub fn new(metadata: RuntimeMetadataPrefixed, registry: TypeRegistry) -> Result<Self> {
+		if metadata.0 != META_RESERVED {
+			return Err(Error::InvalidMetadata("Invalid magic number".into()))
+		}
+		match metadata.1 {
+			RuntimeMetadata::V10(RuntimeMetadataV10 { modules: DecodeDifferent::Decoded(modules) }) =>
+				Ok(Decoder { modules, registry }),
+			_ => Err(Error::InvalidMetadata("Unsupported metadata version".into())),
+		}
+	}
+
+	/// The modules described by the metadata, in the order of the runtime.
+	pub fn modules(&self) -> &[ModuleMetadata] {
+		&self.modules
+	}
+
+	/// Find a module by name.
+	pub fn module(&self, name: &str) -> Option<&ModuleMetadata> {
+		self.modules.iter().find(|m| decoded(&m.name).map(|n| n == name).unwrap_or(false))
+	}
+
+	/// The type registry used to resolve type names.
+	pub fn registry(&self) -> &TypeRegistry {
+		&self.registry
+	}
+
+	/// Decode `data` as the given type, requiring all of the input to be consumed.
+	pub fn decode(&self, ty: &str, data: &[u8]) -> Result<Value> {
+		let ty = TypeName::parse(ty)?;
+		let mut input = data;
+		let value = self.decode_type(&ty, &mut input, 0)?;
+		ensure_consumed(input)?;
+		Ok(value)
+	}
+
+	/// Decode the next value of the given type from `input`.
+	pub fn decode_type(&self, ty: &TypeName, input: &mut &[u8], depth: usize) -> Result<Value> {
+		if depth > MAX_DEPTH {
+			return Err(Error::TooDeep(ty.to_string()))
+		}
+		let depth = depth + 1;
+
+		let (name, params) = match ty {
+			TypeName::Tuple(items) if items.is_empty() => return Ok(Value::Null),
+			TypeName::Tuple(items) => return items.iter()
+				.map(|item| self.decode_type(item, input, depth))
+				.collect::<Result<_>>()
+				.map(Value::Array),
+			TypeName::Array(item, len) => {
+				if item.name() == Some("u8") {
+					return take(input, *len).map(|b| hex_value(b))
+				}
+				return (0..*len)
+					.map(|_| self.decode_type(item, input, depth))
+					.collect::<Result<_>>()
+					.map(Value::Array)
+			},
+			TypeName::Slice(item) => return self.decode_vec(item, input, depth),
+			TypeName::Path { name, params } => (name.as_str(), params.as_slice()),
+		};
+
+		match (name, params) {
+			("Vec", [item]) => return self.decode_vec(item, input, depth),
+			("Option", [item]) => return self.decode_option(item, input, depth),
+			("Box", [item]) => return self.decode_type(item, input, depth),
+			("Compact", [item]) => return self.decode_compact(item, input, depth),
+			("Result", [ok, err]) => return match read_u8(input)? {
+				0 => Ok(json!({ "Ok": self.decode_type(ok, input, depth)? })),
+				1 => Ok(json!({ "Err": self.decode_type(err, input, depth)? })),
+				_ => Err(Error::InvalidData(format!("Invalid `Result` variant for {}", ty))),
+			},
+			("BTreeMap", [key, value]) => {
+				let len = read_len(input)?;
+				return (0..len)
+					.map(|_| Ok(Value::Array(vec![
+						self.decode_type(key, input, depth)?,
+						self.decode_type(value, input, depth)?,
+					])))
+					.collect::<Result<_>>()
+					.map(Value::Array)
+			},
+			_ => {},
+		}
+
+		if let Some(def) = self.registry.get(name) {
+			return self.decode_def(def, input, depth)
+		}
+
+		match name {
+			"bool" => match read_u8(input)? {
+				0 => Ok(Value::Bool(false)),
+				1 => Ok(Value::Bool(true)),
+				_ => Err(Error::InvalidData("Invalid boolean".into())),
+			},
+			"u8" => decode_number::<u8>(input).map(|v| json!(v)),
+			"u16" => decode_number::<u16>(input).map(|v| json!(v)),
+			"u32" => decode_number::<u32>(input).map(|v| json!(v)),
+			"u64" => decode_number::<u64>(input).map(|v| json!(v)),
+			"u128" => decode_number::<u128>(input).map(u128_value),
+			"i8" => decode_number::<i8>(input).map(|v| json!(v)),
+			"i16" => decode_number::<i16>(input).map(|v| json!(v)),
+			"i32" => decode_number::<i32>(input).map(|v| json!(v)),
+			"i64" => decode_number::<i64>(input).map(|v| json!(v)),
+			"i128" => decode_number::<i128>(input).map(|v| match v {
+				v if v >= i64::min_value() as i128 && v <= i64::max_value() as i128 => json!(v as i64),
+				v => Value::String(v.to_string()),
+			}),
+			"H160" => take(input, 20).map(hex_value),
+			"H256" => take(input, 32).map(hex_value),
+			"H512" => take(input, 64).map(hex_value),
+			"Bytes" => decode_bytes(input).map(|b| hex_value(&b)),
+			"str" | "String" | "Text" => decode_bytes(input).map(|b| match String::from_utf8(b) {
+				Ok(s) => Value::String(s),
+				Err(e) => hex_value(e.as_bytes()),
+			}),
+			// `codec::OptionBool` packs the option into a single byte.
+			"OptionBool" => match read_u8(input)? {
+				0 => Ok(Value::Null),
+				1 => Ok(Value::Bool(true)),
+				2 => Ok(Value::Bool(false)),
+				_ => Err(Error::InvalidData("Invalid `OptionBool`".into())),
+			},
+			"Null" | "PhantomData" => Ok(Value::Null),
+			"Call" => self.decode_call(input, depth),
+			"Event" => self.decode_event(input, depth),
+			"Address" => decode_address(input),
+			"ExtrinsicEra" => decode_era(input),
+			_ => Err(Error::UnknownType(ty.to_string())),
+		}
+	}
+
+	fn decode_def(&self, def: &TypeDef, input: &mut &[u8], depth: usize) -> Result<Value> {
+		match def {
+			TypeDef::Alias(ty) => self.decode_type(ty, input, depth),
+			TypeDef::Struct(fields) => {
+				let mut object = Map::new();
+				for (name, ty) in fields {
+					object.insert(name.clone(), self.decode_type(ty, input, depth)?);
+				}
+				Ok(Value::Object(object))
+			},
+			TypeDef::Enum(variants) => {
+				let index = read_u8(input)? as usize;
+				match variants.get(index) {
+					Some((name, None)) => Ok(Value::String(name.clone())),
+					Some((name, Some(ty))) => {
+						let mut object = Map::new();
+						object.insert(name.clone(), self.decode_type(ty, input, depth)?);
+						Ok(Value::Object(object))
+					},
+					None => Err(Error::InvalidData(format!("Invalid enum variant {}", index))),
+				}
+			},
+		}
+	}
+
+	fn decode_vec(&self, item: &TypeName, input: &mut &[u8], depth: usize) -> Result<Value> {
+		if item.name() == Some("u8") {
+			return decode_bytes(input).map(|b| hex_value(&b))
+		}
+		let len = read_len(input)?;
+		(0..len)
+			.map(|_| self.decode_type(item, input, depth))
+			.collect::<Result<_>>()
+			.map(Value::Array)
+	}
+
+	fn decode_option(&self, item: &TypeName, input: &mut &[u8], depth: usize) -> Result<Value> {
+		match read_u8(input)? {
+			0 => Ok(Value::Null),
+			1 => self.decode_type(item, input, depth),
+			_ => Err(Error::InvalidData(format!("Invalid `Option` variant for {}", item))),
+		}
+	}
+
+	fn decode_compact(&self, item: &TypeName, input: &mut &[u8], depth: usize) -> Result<Value> {
+		// Unit and single field structs are transparent to the compact encoding, everything else
+		// must resolve to an unsigned integer.
+		match self.resolve(item, depth)? {
+			TypeName::Tuple(items) if items.is_empty() => Ok(Value::Null),
+			TypeName::Path { ref name, .. } if ["u8", "u16", "u32", "u64", "u128"].contains(&name.as_str()) =>
+				<Compact<u128>>::decode(input)
+					.map(|c| u128_value(c.0))
+					.map_err(|e| Error::InvalidData(e.what().into())),
+			_ => Err(Error::UnknownType(format!("Compact<{}>", item))),
+		}
+	}
+
+	/// Follow aliases until reaching a type that is not an alias.
+	pub(crate) fn resolve(&self, ty: &TypeName, depth: usize) -> Result<TypeName> {
+		if depth > MAX_DEPTH {
+			return Err(Error::TooDeep(ty.to_string()))
+		}
+		match ty.name().and_then(|name| self.registry.get(name)) {
+			Some(TypeDef::Alias(aliased)) => self.resolve(aliased, depth + 1),
+			Some(TypeDef::Struct(fields)) if fields.len() == 1 => self.resolve(&fields[0].1, depth + 1),
+			_ => Ok(ty.clone()),
+		}
+	}
+
+	/// Decode a call of the outer `Call` enum.
+	pub fn decode_call(&self, input: &mut &[u8], depth: usize) -> Result<Value> {
+		let module_index = read_u8(input)? as usize;
+		let module = self.modules.iter()
+			.filter(|m| m.calls.is_some())
+			.nth(module_index)
+			.ok_or_else(|| Error::InvalidData(format!("Unknown module with call index {}", module_index)))?;
+		let calls = decoded(module.calls.as_ref().expect("Filtered on modules with calls; qed"))?;
+
+		let call_index = read_u8(input)? as usize;
+		let call = calls.get(call_index)
+			.ok_or_else(|| Error::InvalidData(format!("Unknown call index {}", call_index)))?;
+
+		let mut args = Map::new();
+		for arg in decoded(&call.arguments)? {
+			let ty = TypeName::parse(decoded(&arg.ty)?)?;
+			args.insert(decoded(&arg.name)?.clone(), self.decode_type(&ty, input, depth + 1)?);
+		}
+
+		Ok(json!({
+			"module": decoded(&module.name)?,
+			"call": decoded(&call.name)?,
+			"args": args,
+		}))
+	}
+
+	/// Find the indices identifying the call `module.call` in the outer `Call` enum: the index of
+	/// the module among the ones with calls and the index of the call within the module.
+	pub fn call_index(&self, module: &str, call: &str) -> Result<Option<(u8, u8)>> {
+		for (module_index, metadata) in self.modules.iter().filter(|m| m.calls.is_some()).enumerate() {
+			if decoded(&metadata.name)? != module {
+				continue
+			}
+			let calls = decoded(metadata.calls.as_ref().expect("Filtered on modules with calls; qed"))?;
+			for (call_index, metadata) in calls.iter().enumerate() {
+				if decoded(&metadata.name)? == call {
+					return Ok(Some((module_index as u8, call_index as u8)))
+				}
+			}
+			return Ok(None)
+		}
+		Ok(None)
+	}
+
+	/// The SCALE encoded value of the constant `module.name`.
+	pub fn constant(&self, module: &str, name: &str) -> Result<Option<&[u8]>> {
+		let module = match self.module(module) {
+			Some(module) => module,
+			None => return Ok(None),
+		};
+		for constant in decoded(&module.constants)? {
+			if decoded(&constant.name)? == name {
+				return Ok(Some(&decoded(&constant.value)?[..]))
+			}
+		}
+		Ok(None)
+	}
+
+	/// Decode an event of the outer `Event` enum.
+	pub fn decode_event(&self, input: &mut &[u8], depth: usize) -> Result<Value> {
+		let module_index = read_u8(input)? as usize;
+		let module = self.modules.iter()
+			.filter(|m| m.event.is_some())
+			.nth(module_index)
+			.ok_or_else(|| Error::InvalidData(format!("Unknown module with event index {}", module_index)))?;
+		let events = decoded(module.event.as_ref().expect("Filtered on modules with events; qed"))?;
+
+		let event_index = read_u8(input)? as usize;
+		let event = events.get(event_index)
+			.ok_or_else(|| Error::InvalidData(format!("Unknown event index {}", event_index)))?;
+
+		let args = decoded(&event.arguments)?.iter()
+			.map(|ty| self.decode_type(&TypeName::parse(ty)?, input, depth + 1))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(json!({
+			"module": decoded(&module.name)?,
+			"event": decoded(&event.name)?,
+			"args": args,
+		}))
+	}
+
+	/// Decode the content of the `System::Events` storage item.
+	pub fn decode_events(&self, data: &[u8]) -> Result<Value> {
+		self.decode("Vec<EventRecord>", data)
+	}
+
+	/// Decode a length prefixed extrinsic, as found in block bodies.
+	pub fn decode_extrinsic(&self, data: &[u8]) -> Result<Value> {
+		let mut input = data;
+		let len = read_len(&mut input)?;
+		if len != input.len() {
+			return Err(Error::InvalidData("Extrinsic length prefix does not match".into()))
+		}
+
+		let version = read_u8(&mut input)?;
+		if version & 0b0111_1111 != EXTRINSIC_VERSION {
+			return Err(Error::InvalidData(format!("Unsupported extrinsic version {}", version & 0b0111_1111)))
+		}
+
+		let mut extrinsic = Map::new();
+		if version & 0b1000_0000 != 0 {
+			let signature = json!({
+				"address": self.decode_type(&TypeName::named("Address"), &mut input, 0)?,
+				"signature": self.decode_type(&TypeName::named("Signature"), &mut input, 0)?,
+				"extra": self.decode_type(&TypeName::named("ExtrinsicExtra"), &mut input, 0)?,
+			});
+			extrinsic.insert("signature".into(), signature);
+		}
+		extrinsic.insert("call".into(), self.decode_call(&mut input, 0)?);
+		ensure_consumed(input)?;
+
+		Ok(Value::Object(extrinsic))
+	}
+
+	/// Look up a module error, as reported by `DispatchError::Module`.
+	pub fn module_error(&self, module_index: u8, error_index: u8) -> Result<ModuleError> {
+		let module = self.modules.get(module_index as usize)
+			.ok_or_else(|| Error::InvalidData(format!("Unknown module index {}", module_index)))?;
+		let error = decoded(&module.errors)?.get(error_index as usize)
+			.ok_or_else(|| Error::InvalidData(format!("Unknown error index {}", error_index)))?;
+
+		Ok(ModuleError {
+			module: decoded(&module.name)?.clone(),
+			error: decoded(&error.name)?.clone(),
+			documentation: decoded(&error.documentation)?.clone(),
+		})
+	}
+
+	/// Find the storage entry a key belongs to.
+	pub fn storage_entry(&self, key: &[u8]) -> Result<Option<(&str, &StorageEntryMetadata)>> {
+		if key.len() < 32 {
+			return Ok(None)
+		}
+
+		for module in &self.modules {
+			let storage = match &module.storage {
+				Some(storage) => decoded(storage)?,
+				None => continue,
+			};
+			let prefix = decoded(&storage.prefix)?;
+			if key[..16] != twox_128(prefix.as_bytes()) {
+				continue
+			}
+			for entry in decoded(&storage.entries)? {
+				let name = decoded(&entry.name)?;
+				let head_key = match entry.ty {
+					StorageEntryType::Map { is_linked: true, .. } => Some(format!("HeadOf{}", name)),
+					_ => None,
+				};
+				if key[16..32] == twox_128(name.as_bytes())
+					|| head_key.map_or(false, |head| key[16..32] == twox_128(head.as_bytes()))
+				{
+					return Ok(Some((prefix, entry)))
+				}
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Find the storage prefix of the module and the name of the storage item a key belongs to.
+	pub fn storage_item(&self, key: &[u8]) -> Result<Option<(&str, &str)>> {
+		match self.storage_entry(key)? {
+			Some((prefix, entry)) => Ok(Some((prefix, decoded(&entry.name)?))),
+			None => Ok(None),
+		}
+	}
+
+	/// Decode a storage key and its value.
+	///
+	/// The value falls back to the default of the storage entry if `None`.
+	pub fn decode_storage(&self, key: &[u8], value: Option<&[u8]>) -> Result<Value> {
+		let (prefix, entry) = self.storage_entry(key)?
+			.ok_or_else(|| Error::UnknownStorageKey(hex(key)))?;
+		let name = decoded(&entry.name)?;
+
+		let value = match value {
+			Some(value) => Some(value.to_vec()),
+			None => match entry.modifier {
+				StorageEntryModifier::Default => Some(decoded(&entry.default)?.clone()),
+				StorageEntryModifier::Optional => None,
+			},
+		};
+
+		let mut result = Map::new();
+		result.insert("module".into(), Value::String(prefix.into()));
+		result.insert("storage".into(), Value::String(name.clone()));
+
+		let mut rest = &key[32..];
+		let value_ty = match &entry.ty {
+			StorageEntryType::Plain(ty) => {
+				ensure_consumed(rest)?;
+				TypeName::parse(decoded(ty)?)?
+			},
+			StorageEntryType::Map { hasher, key: key_ty, value: value_ty, is_linked } => {
+				let value_ty = TypeName::parse(decoded(value_ty)?)?;
+				if rest.is_empty() && *is_linked {
+					// The head of a linked map stores the first key.
+					result.insert("head".into(), Value::Bool(true));
+					TypeName::Path { name: "Option".into(), params: vec![TypeName::parse(decoded(key_ty)?)?] }
+				} else {
+					let key_ty = TypeName::parse(decoded(key_ty)?)?;
+					result.insert("key".into(), self.decode_hashed_key(hasher, &key_ty, &mut rest)?);
+					ensure_consumed(rest)?;
+					if *is_linked {
+						let linkage = TypeName::Path { name: "Option".into(), params: vec![key_ty] };
+						TypeName::Tuple(vec![value_ty, TypeName::Tuple(vec![linkage.clone(), linkage])])
+					} else {
+						value_ty
+					}
+				}
+			},
+			StorageEntryType::DoubleMap { hasher, key1, key2, value, key2_hasher } => {
+				let key1 = TypeName::parse(decoded(key1)?)?;
+				let key2 = TypeName::parse(decoded(key2)?)?;
+				result.insert("key1".into(), self.decode_hashed_key(hasher, &key1, &mut rest)?);
+				result.insert("key2".into(), self.decode_hashed_key(key2_hasher, &key2, &mut rest)?);
+				ensure_consumed(rest)?;
+				TypeName::parse(decoded(value)?)?
+			},
+		};
+
+		let value = match value {
+			Some(value) => {
+				let mut input = &value[..];
+				let decoded = self.decode_type(&value_ty, &mut input, 0)?;
+				ensure_consumed(input)?;
+				decoded
+			},
+			None => Value::Null,
+		};
+		result.insert("value".into(), value);
+
+		Ok(Value::Object(result))
+	}
+
+	fn decode_hashed_key(&self, hasher: &StorageHasher, ty: &TypeName, input: &mut &[u8]) -> Result<Value> {
+		let (hash_len, concat) = match hasher {
+			StorageHasher::Blake2_128 | StorageHasher::Twox128 => (16, false),
+			StorageHasher::Blake2_256 | StorageHasher::Twox256 => (32, false),
+			StorageHasher::Blake2_128Concat => (16, true),
+			StorageHasher::Twox64Concat => (8, true),
+		};
+		let hash = take(input, hash_len)?;
+
+		if concat {
+			self.decode_type(ty, input, 0)
+		} else if hash_len == input.len() || input.is_empty() {
+			// The original key can't be recovered from a non-concat hash.
+			Ok(json!({ "hash": hex(hash) }))
+		} else {
+			Err(Error::InvalidData("Trailing data after hashed key".into()))
+		}
+	}
+}
+
+pub(crate) fn hex(data: &[u8]) -> String {
+	data.iter().fold(String::from("0x"), |mut s, b| {
+		s.push_str(&format!("{:02x}", b));
+		s
+	})
+}
+
+fn hex_value(data: &[u8]) -> Value {
+	Value::String(hex(data))
+}
+
+fn u128_value(value: u128) -> Value {
+	if value <= u64::max_value() as u128 {
+		json!(value as u64)
+	} else {
+		// JSON numbers can't represent the full range faithfully.
+		Value::String(value.to_string())
+	}
+}
+
+fn ensure_consumed(input: &[u8]) -> Result<()> {
+	if input.is_empty() {
+		Ok(())
+	} else {
+		Err(Error::InvalidData(format!("{} bytes left after decoding", input.len())))
+	}
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+	if input.len() < len {
+		return Err(Error::InvalidData("Unexpected end of input".into()))
+	}
+	let (head, tail) = input.split_at(len);
+	*input = tail;
+	Ok(head)
+}
+
+fn read_u8(input: &mut &[u8]) -> Result<u8> {
+	take(input, 1).map(|b| b[0])
+}
+
+/// Read a compact length prefix, rejecting lengths that could not possibly fit the input.
+fn read_len(input: &mut &[u8]) -> Result<usize> {
+	let len = <Compact<u32>>::decode(input)
+		.map_err(|e| Error::InvalidData(e.what().into()))?.0 as usize;
+	if len > input.len() {
+		return Err(Error::InvalidData(format!("Length {} exceeds the remaining input", len)))
+	}
+	Ok(len)
+}
+
+fn decode_bytes(input: &mut &[u8]) -> Result<Vec<u8>> {
+	let len = read_len(input)?;
+	take(input, len).map(|b| b.to_vec())
+}
+
+fn decode_number<T: Decode>(input: &mut &[u8]) -> Result<T> {
+	T::decode(input).map_err(|e| Error::InvalidData(e.what().into()))
+}
+
+/// Decode an address in the format of `pallet_indices::address::Address`.
+fn decode_address(input: &mut &[u8]) -> Result<Value> {
+	match read_u8(input)? {
+		0xff => take(input, 32).map(|id| json!({ "Id": hex(id) })),
+		0xfc => decode_number::<u16>(input).map(|i| json!({ "Index": i })),
+		0xfd => decode_number::<u32>(input).map(|i| json!({ "Index": i })),
+		0xfe => decode_number::<u64>(input).map(|i| json!({ "Index": i })),
+		i if i < 0xef => Ok(json!({ "Index": i })),
+		_ => Err(Error::InvalidData("Invalid address".into())),
+	}
+}
+
+/// Decode a `sp_runtime::generic::Era`.
+fn decode_era(input: &mut &[u8]) -> Result<Value> {
+	let first = read_u8(input)?;
+	if first == 0 {
+		return Ok(Value::String("Immortal".into()))
+	}
+	let encoded = first as u64 + ((read_u8(input)? as u64) << 8);
+	let period = 2 << (encoded % (1 << 4));
+	let quantize_factor = (period >> 12).max(1);
+	let phase = (encoded >> 4) * quantize_factor;
+	if period >= 4 && phase < period {
+		Ok(json!({ "Mortal": { "period": period, "phase": phase } }))
+	} else {
+		Err(Error::InvalidData("Invalid era".into()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use frame_metadata::{
+		ErrorMetadata, EventMetadata, FunctionArgumentMetadata, FunctionMetadata,
+		ModuleConstantMetadata, StorageMetadata,
+	};
+	use sp_core::hashing::blake2_128;
+
+	fn d<T>(value: T) -> DecodeDifferent<&'static str, T> {
+		DecodeDifferent::Decoded(value)
+	}
+
+	fn module(name: &str) -> ModuleMetadata {
+		ModuleMetadata {
+			name: d(name.into()),
+			storage: None,
+			calls: None,
+			event: None,
+			constants: DecodeDifferent::Decoded(vec![]),
+			errors: DecodeDifferent::Decoded(vec![]),
+		}
+	}
+
+	fn decoder() -> Decoder {
+		let mut system = module("System");
+		system.event = Some(DecodeDifferent::Decoded(vec![EventMetadata {
+			name: d("ExtrinsicSuccess".into()),
+			arguments: DecodeDifferent::Decoded(vec!["DispatchInfo".into()]),
+			documentation: DecodeDifferent::Decoded(vec![]),
+		}]));
+
+		let mut balances = module("Balances");
+		balances.storage = Some(DecodeDifferent::Decoded(StorageMetadata {
+			prefix: d("Balances".into()),
+			entries: DecodeDifferent::Decoded(vec![StorageEntryMetadata {
+				name: d("FreeBalance".into()),
+				modifier: StorageEntryModifier::Default,
+				ty: StorageEntryType::Map {
+					hasher: StorageHasher::Blake2_128Concat,
+					key: d("T::AccountId".into()),
+					value: d("T::Balance".into()),
+					is_linked: false,
+				},
+				default: DecodeDifferent::Decoded(0u128.encode()),
+				documentation: DecodeDifferent::Decoded(vec![]),
+			}]),
+		}));
+		balances.calls = Some(DecodeDifferent::Decoded(vec![FunctionMetadata {
+			name: d("transfer".into()),
+			arguments: DecodeDifferent::Decoded(vec![
+				FunctionArgumentMetadata { name: d("dest".into()), ty: d("<T::Lookup as StaticLookup>::Source".into()) },
+				FunctionArgumentMetadata { name: d("value".into()), ty: d("Compact<T::Balance>".into()) },
+			]),
+			documentation: DecodeDifferent::Decoded(vec![]),
+		}]));
+		balances.constants = DecodeDifferent::Decoded(vec![ModuleConstantMetadata {
+			name: d("ExistentialDeposit".into()),
+			ty: d("T::Balance".into()),
+			value: DecodeDifferent::Decoded(500u128.encode()),
+			documentation: DecodeDifferent::Decoded(vec![]),
+		}]);
+		balances.errors = DecodeDifferent::Decoded(vec![ErrorMetadata {
+			name: d("InsufficientBalance".into()),
+			documentation: DecodeDifferent::Decoded(vec![" Balance too low".into()]),
+		}]);
+
+		let metadata = RuntimeMetadataPrefixed(
+			META_RESERVED,
+			RuntimeMetadata::V10(RuntimeMetadataV10 { modules: DecodeDifferent::Decoded(vec![system, balances]) }),
+		);
+		Decoder::from_bytes(&metadata.encode(), TypeRegistry::with_defaults()).unwrap()
+	}
+
+	#[test]
+	fn decodes_values() {
+		let decoder = decoder();
+		assert_eq!(decoder.decode("u32", &5u32.encode()).unwrap(), json!(5));
+		assert_eq!(decoder.decode("u128", &u128::max_value().encode()).unwrap(), json!(u128::max_value().to_string()));
+		assert_eq!(decoder.decode("Option<bool>", &Some(false).encode()).unwrap(), json!(false));
+		assert_eq!(decoder.decode("OptionBool", &codec::OptionBool(Some(false)).encode()).unwrap(), json!(false));
+		assert_eq!(decoder.decode("Vec<u8>", &vec![1u8, 2].encode()).unwrap(), json!("0x0102"));
+		assert_eq!(decoder.decode("(u8, Vec<u16>)", &(1u8, vec![2u16]).encode()).unwrap(), json!([1, [2]]));
+		assert_eq!(
+			decoder.decode("DispatchInfo", &(10u32, 1u8, true).encode()).unwrap(),
+			json!({ "weight": 10, "class": "Operational", "pays_fee": true }),
+		);
+		assert!(decoder.decode("u32", &5u64.encode()).is_err());
+		assert!(decoder.decode("Unknown", &[]).is_err());
+	}
+
+	#[test]
+	fn decodes_extrinsics_and_events() {
+		let decoder = decoder();
+
+		let call = (0u8, 0u8, 0xffu8, [1u8; 32], Compact(100u128));
+		let extrinsic = (4u8, call).encode().encode();
+		assert_eq!(decoder.decode_extrinsic(&extrinsic).unwrap(), json!({
+			"call": {
+				"module": "Balances",
+				"call": "transfer",
+				"args": { "dest": { "Id": hex(&[1u8; 32]) }, "value": 100 },
+			},
+		}));
+
+		let events = vec![(0u8, 0u32, 0u8, 0u8, (10u32, 0u8, true), Vec::<[u8; 32]>::new())].encode();
+		assert_eq!(decoder.decode_events(&events).unwrap(), json!([{
+			"phase": { "ApplyExtrinsic": 0 },
+			"event": {
+				"module": "System",
+				"event": "ExtrinsicSuccess",
+				"args": [{ "weight": 10, "class": "Normal", "pays_fee": true }],
+			},
+			"topics": [],
+		}]));
+	}
+
+	#[test]
+	fn decodes_storage() {
+		let decoder = decoder();
+		let who = [7u8; 32];
+		let key = [
+			&twox_128(b"Balances")[..],
+			&twox_128(b"FreeBalance")[..],
+			&blake2_128(&who)[..],
+			&who[..],
+		].concat();
+
+		let expected = |value| json!({
+			"module": "Balances",
+			"storage": "FreeBalance",
+			"key": hex(&who),
+			"value": value,
+		});
+		assert_eq!(decoder.decode_storage(&key, Some(&42u128.encode())).unwrap(), expected(42));
+		assert_eq!(decoder.decode_storage(&key, None).unwrap(), expected(0));
+		assert!(decoder.decode_storage(&twox_128(b"Unknown"), None).is_err());
+	}
+
+	#[test]
+	fn looks_up_call_indices() {
+		let decoder = decoder();
+		assert_eq!(decoder.call_index("Balances", "transfer").unwrap(), Some((0, 0)));
+		assert_eq!(decoder.call_index("Balances", "unknown").unwrap(), None);
+		assert_eq!(decoder.call_index("System", "remark").unwrap(), None);
+	}
+
+	#[test]
+	fn looks_up_constants() {
+		let decoder = decoder();
+		assert_eq!(decoder.constant("Balances", "ExistentialDeposit").unwrap(), Some(&500u128.encode()[..]));
+		assert_eq!(decoder.constant("Balances", "Unknown").unwrap(), None);
+		assert_eq!(decoder.constant("Unknown", "ExistentialDeposit").unwrap(), None);
+	}
+
+	#[test]
+	fn looks_up_module_errors() {
+		let decoder = decoder();
+		assert_eq!(decoder.module_error(1, 0).unwrap(), ModuleError {
+			module: "Balances".into(),
+			error: "InsufficientBalance".into(),
+			documentation: vec![" Balance too low".into()],
+		});
+		assert!(decoder.module_error(0, 0).is_err());
+	}
+}
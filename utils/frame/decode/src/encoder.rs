@@ -0,0 +1,482 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Encoding of JSON values into SCALE, driven by the runtime metadata.
+//!
+//! Values are expected in the form the decoder produces them, so that decoded data can be
+//! edited and encoded again.
+
+use std::convert::TryFrom;
+
+use codec::{Compact, Encode};
+use frame_metadata::FunctionMetadata;
+use serde_json::Value;
+
+use crate::decoder::{decoded, MAX_DEPTH};
+use crate::{Decoder, Error, Result, TypeDef, TypeName};
+
+impl Decoder {
+	/// Encode `value` as the given type.
+	pub fn encode(&self, ty: &str, value: &Value) -> Result<Vec<u8>> {
+		let mut output = Vec::new();
+		self.encode_type(&TypeName::parse(ty)?, value, &mut output, 0)?;
+		Ok(output)
+	}
+
+	/// Encode `value` as the given type, appending it to `output`.
+	pub fn encode_type(&self, ty: &TypeName, value: &Value, output: &mut Vec<u8>, depth: usize) -> Result<()> {
+		if depth > MAX_DEPTH {
+			return Err(Error::TooDeep(ty.to_string()))
+		}
+		let depth = depth + 1;
+
+		let (name, params) = match ty {
+			TypeName::Tuple(items) if items.is_empty() => return expect_null(ty, value),
+			TypeName::Tuple(items) => return array(ty, value, Some(items.len()))?.iter()
+				.zip(items)
+				.try_for_each(|(value, item)| self.encode_type(item, value, output, depth)),
+			TypeName::Array(item, len) => {
+				if item.name() == Some("u8") {
+					return encode_fixed(ty, value, *len, output)
+				}
+				return array(ty, value, Some(*len))?.iter()
+					.try_for_each(|value| self.encode_type(item, value, output, depth))
+			},
+			TypeName::Slice(item) => return self.encode_vec(item, value, output, depth),
+			TypeName::Path { name, params } => (name.as_str(), params.as_slice()),
+		};
+
+		match (name, params) {
+			("Vec", [item]) => return self.encode_vec(item, value, output, depth),
+			("Option", [item]) => return match value {
+				Value::Null => {
+					output.push(0);
+					Ok(())
+				},
+				value => {
+					output.push(1);
+					self.encode_type(item, value, output, depth)
+				},
+			},
+			("Box", [item]) => return self.encode_type(item, value, output, depth),
+			("Compact", [item]) => return self.encode_compact(item, value, output, depth),
+			("Result", [ok, err]) => return match variant(value) {
+				Some(("Ok", value)) => {
+					output.push(0);
+					self.encode_type(ok, value, output, depth)
+				},
+				Some(("Err", value)) => {
+					output.push(1);
+					self.encode_type(err, value, output, depth)
+				},
+				_ => Err(mismatch(ty, value)),
+			},
+			("BTreeMap", [key, item]) => {
+				let entries = array(ty, value, None)?;
+				Compact(entries.len() as u32).encode_to(output);
+				return entries.iter().try_for_each(|entry| {
+					let entry = array(ty, entry, Some(2))?;
+					self.encode_type(key, &entry[0], output, depth)?;
+					self.encode_type(item, &entry[1], output, depth)
+				})
+			},
+			_ => {},
+		}
+
+		if let Some(def) = self.registry().get(name) {
+			return self.encode_def(ty, def, value, output, depth)
+		}
+
+		match name {
+			"bool" => match value {
+				Value::Bool(b) => {
+					output.push(*b as u8);
+					Ok(())
+				},
+				_ => Err(mismatch(ty, value)),
+			},
+			"u8" => encode_number::<u8, _>(ty, value, unsigned(value), output),
+			"u16" => encode_number::<u16, _>(ty, value, unsigned(value), output),
+			"u32" => encode_number::<u32, _>(ty, value, unsigned(value), output),
+			"u64" => encode_number::<u64, _>(ty, value, unsigned(value), output),
+			"u128" => encode_number::<u128, _>(ty, value, unsigned(value), output),
+			"i8" => encode_number::<i8, _>(ty, value, signed(value), output),
+			"i16" => encode_number::<i16, _>(ty, value, signed(value), output),
+			"i32" => encode_number::<i32, _>(ty, value, signed(value), output),
+			"i64" => encode_number::<i64, _>(ty, value, signed(value), output),
+			"i128" => encode_number::<i128, _>(ty, value, signed(value), output),
+			"H160" => encode_fixed(ty, value, 20, output),
+			"H256" => encode_fixed(ty, value, 32, output),
+			"H512" => encode_fixed(ty, value, 64, output),
+			"Bytes" => {
+				bytes(ty, value, None)?.encode_to(output);
+				Ok(())
+			},
+			"str" | "String" | "Text" => match value {
+				Value::String(s) => {
+					s.encode_to(output);
+					Ok(())
+				},
+				_ => Err(mismatch(ty, value)),
+			},
+			// `codec::OptionBool` packs the option into a single byte.
+			"OptionBool" => {
+				output.push(match value {
+					Value::Null => 0,
+					Value::Bool(true) => 1,
+					Value::Bool(false) => 2,
+					_ => return Err(mismatch(ty, value)),
+				});
+				Ok(())
+			},
+			"Null" | "PhantomData" => expect_null(ty, value),
+			"Call" => match (value.get("module"), value.get("call")) {
+				(Some(Value::String(module)), Some(Value::String(call))) => {
+					let args = value.get("args").unwrap_or(&Value::Null);
+					output.extend_from_slice(&self.encode_call(module, call, args)?);
+					Ok(())
+				},
+				_ => Err(mismatch(ty, value)),
+			},
+			"Address" => encode_address(ty, value, output),
+			"ExtrinsicEra" => encode_era(ty, value, output),
+			_ => Err(Error::UnknownType(ty.to_string())),
+		}
+	}
+
+	fn encode_def(&self, ty: &TypeName, def: &TypeDef, value: &Value, output: &mut Vec<u8>, depth: usize) -> Result<()> {
+		match def {
+			TypeDef::Alias(aliased) => self.encode_type(aliased, value, output, depth),
+			TypeDef::Struct(fields) => fields.iter().try_for_each(|(name, field)| match value.get(name) {
+				Some(value) => self.encode_type(field, value, output, depth),
+				None => Err(Error::InvalidData(format!("Missing field `{}` of {}", name, ty))),
+			}),
+			TypeDef::Enum(variants) => {
+				let (name, payload) = match value {
+					Value::String(name) => (name.as_str(), None),
+					value => match variant(value) {
+						Some((name, payload)) => (name, Some(payload)),
+						None => return Err(mismatch(ty, value)),
+					},
+				};
+				let index = variants.iter().position(|(variant, _)| variant == name)
+					.ok_or_else(|| Error::InvalidData(format!("Unknown variant `{}` of {}", name, ty)))?;
+				output.push(index as u8);
+				match (&variants[index].1, payload) {
+					(None, None) => Ok(()),
+					(None, Some(payload)) => expect_null(ty, payload),
+					(Some(item), Some(payload)) => self.encode_type(item, payload, output, depth),
+					(Some(_), None) => Err(mismatch(ty, value)),
+				}
+			},
+		}
+	}
+
+	fn encode_vec(&self, item: &TypeName, value: &Value, output: &mut Vec<u8>, depth: usize) -> Result<()> {
+		if item.name() == Some("u8") {
+			bytes(item, value, None)?.encode_to(output);
+			return Ok(())
+		}
+		let items = array(item, value, None)?;
+		Compact(items.len() as u32).encode_to(output);
+		items.iter().try_for_each(|value| self.encode_type(item, value, output, depth))
+	}
+
+	fn encode_compact(&self, item: &TypeName, value: &Value, output: &mut Vec<u8>, depth: usize) -> Result<()> {
+		let max = match self.resolve(item, depth)? {
+			TypeName::Tuple(ref items) if items.is_empty() => return expect_null(item, value),
+			TypeName::Path { ref name, .. } => match name.as_str() {
+				"u8" => u8::max_value() as u128,
+				"u16" => u16::max_value() as u128,
+				"u32" => u32::max_value() as u128,
+				"u64" => u64::max_value() as u128,
+				"u128" => u128::max_value(),
+				_ => return Err(Error::UnknownType(format!("Compact<{}>", item))),
+			},
+			_ => return Err(Error::UnknownType(format!("Compact<{}>", item))),
+		};
+		match unsigned(value) {
+			Some(n) if n <= max => {
+				Compact(n).encode_to(output);
+				Ok(())
+			},
+			_ => Err(mismatch(item, value)),
+		}
+	}
+
+	/// Encode a call of the outer `Call` enum.
+	///
+	/// `args` is either an object mapping the names of the arguments to their values, as
+	/// produced by the decoder, or an array of the values in order.
+	pub fn encode_call(&self, module: &str, call: &str, args: &Value) -> Result<Vec<u8>> {
+		let (module_index, call_index, metadata) = self.find_call(module, call)?
+			.ok_or_else(|| Error::InvalidData(format!("Unknown call {}.{}", module, call)))?;
+		let arguments = decoded(&metadata.arguments)?;
+
+		let mut output = vec![module_index, call_index];
+		for (position, argument) in arguments.iter().enumerate() {
+			let name = decoded(&argument.name)?;
+			let value = match args {
+				Value::Object(args) => args.get(name.as_str()),
+				Value::Array(args) if args.len() == arguments.len() => args.get(position),
+				_ => return Err(Error::InvalidData(format!("Invalid arguments for {}.{}", module, call))),
+			};
+			let value = value.ok_or_else(|| Error::InvalidData(format!("Missing argument `{}` of {}.{}", name, module, call)))?;
+			self.encode_type(&TypeName::parse(decoded(&argument.ty)?)?, value, &mut output, 0)?;
+		}
+
+		Ok(output)
+	}
+
+	fn find_call(&self, module: &str, call: &str) -> Result<Option<(u8, u8, &FunctionMetadata)>> {
+		let (module_index, call_index) = match self.call_index(module, call)? {
+			Some(indices) => indices,
+			None => return Ok(None),
+		};
+		let module = self.modules().iter()
+			.filter(|m| m.calls.is_some())
+			.nth(module_index as usize)
+			.expect("Index was found among the modules with calls; qed");
+		let calls = decoded(module.calls.as_ref().expect("Filtered on modules with calls; qed"))?;
+		Ok(Some((module_index, call_index, &calls[call_index as usize])))
+	}
+}
+
+fn mismatch(ty: &TypeName, value: &Value) -> Error {
+	Error::InvalidData(format!("Expected {}, found {}", ty, value))
+}
+
+fn expect_null(ty: &TypeName, value: &Value) -> Result<()> {
+	match value {
+		Value::Null => Ok(()),
+		_ => Err(mismatch(ty, value)),
+	}
+}
+
+/// The name and the payload of an enum variant given as a single entry object.
+fn variant(value: &Value) -> Option<(&str, &Value)> {
+	match value {
+		Value::Object(object) if object.len() == 1 => object.iter().next().map(|(k, v)| (k.as_str(), v)),
+		_ => None,
+	}
+}
+
+fn array<'a>(ty: &TypeName, value: &'a Value, len: Option<usize>) -> Result<&'a Vec<Value>> {
+	match value {
+		Value::Array(items) if len.map_or(true, |len| len == items.len()) => Ok(items),
+		_ => Err(mismatch(ty, value)),
+	}
+}
+
+/// Parse a `0x` prefixed hex string.
+fn bytes(ty: &TypeName, value: &Value, len: Option<usize>) -> Result<Vec<u8>> {
+	let hex = match value {
+		Value::String(s) if s.starts_with("0x") && s.len() % 2 == 0 => &s[2..],
+		_ => return Err(mismatch(ty, value)),
+	};
+	let bytes = (0..hex.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+		.collect::<std::result::Result<Vec<_>, _>>()
+		.map_err(|_| mismatch(ty, value))?;
+	match len {
+		Some(len) if len != bytes.len() => Err(mismatch(ty, value)),
+		_ => Ok(bytes),
+	}
+}
+
+/// Numbers beyond the range of JSON numbers are given as decimal strings.
+fn unsigned(value: &Value) -> Option<u128> {
+	match value {
+		Value::Number(n) => n.as_u64().map(u128::from),
+		Value::String(s) => s.parse().ok(),
+		_ => None,
+	}
+}
+
+fn signed(value: &Value) -> Option<i128> {
+	match value {
+		Value::Number(n) => n.as_i64().map(i128::from),
+		Value::String(s) => s.parse().ok(),
+		_ => None,
+	}
+}
+
+fn encode_fixed(ty: &TypeName, value: &Value, len: usize, output: &mut Vec<u8>) -> Result<()> {
+	output.extend_from_slice(&bytes(ty, value, Some(len))?);
+	Ok(())
+}
+
+fn encode_number<T: Encode + TryFrom<N>, N>(
+	ty: &TypeName,
+	value: &Value,
+	number: Option<N>,
+	output: &mut Vec<u8>,
+) -> Result<()> {
+	match number.and_then(|n| T::try_from(n).ok()) {
+		Some(n) => {
+			n.encode_to(output);
+			Ok(())
+		},
+		None => Err(mismatch(ty, value)),
+	}
+}
+
+/// Encode an address in the format of `pallet_indices::address::Address`.
+fn encode_address(ty: &TypeName, value: &Value, output: &mut Vec<u8>) -> Result<()> {
+	match variant(value) {
+		Some(("Id", id)) => {
+			output.push(0xff);
+			output.extend_from_slice(&bytes(ty, id, Some(32))?);
+		},
+		Some(("Index", index)) => match unsigned(index) {
+			Some(i) if i < 0xf0 => output.push(i as u8),
+			Some(i) if i <= u16::max_value() as u128 => {
+				output.push(0xfc);
+				(i as u16).encode_to(output);
+			},
+			Some(i) if i <= u32::max_value() as u128 => {
+				output.push(0xfd);
+				(i as u32).encode_to(output);
+			},
+			Some(i) if i <= u64::max_value() as u128 => {
+				output.push(0xfe);
+				(i as u64).encode_to(output);
+			},
+			_ => return Err(mismatch(ty, value)),
+		},
+		_ => return Err(mismatch(ty, value)),
+	}
+	Ok(())
+}
+
+/// Encode a `sp_runtime::generic::Era`.
+fn encode_era(ty: &TypeName, value: &Value, output: &mut Vec<u8>) -> Result<()> {
+	if value.as_str() == Some("Immortal") {
+		output.push(0);
+		return Ok(())
+	}
+	let (period, phase) = match variant(value) {
+		Some(("Mortal", mortal)) => match (mortal["period"].as_u64(), mortal["phase"].as_u64()) {
+			(Some(period), Some(phase)) => (period, phase),
+			_ => return Err(mismatch(ty, value)),
+		},
+		_ => return Err(mismatch(ty, value)),
+	};
+	if !period.is_power_of_two() || period < 4 || period > 1 << 16 || phase >= period {
+		return Err(Error::InvalidData(format!("Invalid era: period {}, phase {}", period, phase)))
+	}
+
+	let quantize_factor = (period >> 12).max(1);
+	let encoded = (period.trailing_zeros() - 1).max(1).min(15) as u16 | ((phase / quantize_factor) << 4) as u16;
+	encoded.encode_to(output);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_metadata::{
+		DecodeDifferent, FunctionArgumentMetadata, ModuleMetadata, RuntimeMetadata,
+		RuntimeMetadataPrefixed, RuntimeMetadataV10, META_RESERVED,
+	};
+	use serde_json::json;
+
+	use crate::TypeRegistry;
+
+	fn d<B, O>(value: O) -> DecodeDifferent<B, O> {
+		DecodeDifferent::Decoded(value)
+	}
+
+	fn decoder() -> Decoder {
+		let balances = ModuleMetadata {
+			name: d("Balances".into()),
+			storage: None,
+			calls: Some(d(vec![FunctionMetadata {
+				name: d("transfer".into()),
+				arguments: d(vec![
+					FunctionArgumentMetadata { name: d("dest".into()), ty: d("<T::Lookup as StaticLookup>::Source".into()) },
+					FunctionArgumentMetadata { name: d("value".into()), ty: d("Compact<T::Balance>".into()) },
+				]),
+				documentation: d(vec![]),
+			}])),
+			event: None,
+			constants: d(vec![]),
+			errors: d(vec![]),
+		};
+		let metadata = RuntimeMetadataPrefixed(
+			META_RESERVED,
+			RuntimeMetadata::V10(RuntimeMetadataV10 { modules: d(vec![balances]) }),
+		);
+		Decoder::from_bytes(&metadata.encode(), TypeRegistry::with_defaults()).unwrap()
+	}
+
+	#[test]
+	fn encodes_what_the_decoder_decodes() {
+		let decoder = decoder();
+		let values = vec![
+			("u32", 5u32.encode()),
+			("u128", u128::max_value().encode()),
+			("i16", (-3i16).encode()),
+			("Option<bool>", Some(false).encode()),
+			("OptionBool", codec::OptionBool(Some(true)).encode()),
+			("Vec<u8>", vec![1u8, 2].encode()),
+			("(u8, Vec<u16>)", (1u8, vec![2u16]).encode()),
+			("[u8; 4]", [1u8, 2, 3, 4].encode()),
+			("Text", "storm".encode()),
+			("BTreeMap<u8, bool>", vec![(1u8, true)].encode()),
+			("Compact<Balance>", Compact(1_000_000u128).encode()),
+			("DispatchInfo", (10u32, 1u8, true).encode()),
+			("DispatchResult", Err::<(), _>((3u8, 1u8, 2u8)).encode()),
+			("ExtrinsicEra", vec![0u8]),
+			("ExtrinsicEra", (7u16 | (42 << 4)).encode()),
+			("Address", (0xffu8, [9u8; 32]).encode()),
+			("Address", vec![5u8]),
+			("Address", (0xfdu8, 70_000u32).encode()),
+			("Call", (0u8, 0u8, 0xffu8, [1u8; 32], Compact(100u128)).encode()),
+		];
+
+		for (ty, encoded) in values {
+			let value = decoder.decode(ty, &encoded).unwrap();
+			assert_eq!(decoder.encode(ty, &value).unwrap(), encoded, "{} {}", ty, value);
+		}
+	}
+
+	#[test]
+	fn encodes_calls_from_named_or_positional_arguments() {
+		let decoder = decoder();
+		let expected = (0u8, 0u8, 0xffu8, [1u8; 32], Compact(100u128)).encode();
+		let dest = json!({ "Id": format!("0x{}", "01".repeat(32)) });
+
+		assert_eq!(decoder.encode_call("Balances", "transfer", &json!({ "dest": dest, "value": 100 })).unwrap(), expected);
+		assert_eq!(decoder.encode_call("Balances", "transfer", &json!([dest, "100"])).unwrap(), expected);
+		assert!(decoder.encode_call("Balances", "transfer", &json!({ "dest": dest })).is_err());
+		assert!(decoder.encode_call("Balances", "unknown", &json!([])).is_err());
+	}
+
+	#[test]
+	fn rejects_values_not_matching_the_type() {
+		let decoder = decoder();
+		assert!(decoder.encode("u8", &json!(256)).is_err());
+		assert!(decoder.encode("u32", &json!(-1)).is_err());
+		assert!(decoder.encode("[u8; 2]", &json!("0x010203")).is_err());
+		assert!(decoder.encode("Vec<u8>", &json!("0x0")).is_err());
+		assert!(decoder.encode("DispatchClass", &json!("Unknown")).is_err());
+		assert!(decoder.encode("DispatchInfo", &json!({ "weight": 1 })).is_err());
+		assert!(decoder.encode("ExtrinsicEra", &json!({ "Mortal": { "period": 3, "phase": 0 } })).is_err());
+		assert!(decoder.encode("Unknown", &json!(null)).is_err());
+	}
+}
@@ -0,0 +1,226 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Extrinsics built and signed from the runtime metadata.
+//!
+//! Tools which don't link the runtime, like the transaction factory or an offline signer, build
+//! calls from the names of the module and the function and JSON arguments, and sign them. The
+//! signed extensions are encoded as the `ExtrinsicExtra` and `ExtrinsicAdditionalSigned` types of
+//! the registry, which chains with other extensions than the node runtime define themselves.
+
+use codec::Encode;
+use serde_json::{json, Value};
+use sp_core::hashing::blake2_256;
+
+use crate::decoder::EXTRINSIC_VERSION;
+use crate::{Decoder, Result, TypeName};
+
+/// Payloads longer than this are hashed before being signed.
+const MAX_UNHASHED_PAYLOAD: usize = 256;
+
+/// An extrinsic being built from the runtime metadata.
+pub struct ExtrinsicBuilder<'a> {
+	decoder: &'a Decoder,
+	call: Vec<u8>,
+}
+
+impl Decoder {
+	/// Start building an extrinsic calling `module.call`.
+	///
+	/// `args` is either an object mapping the names of the arguments to their values, or an array
+	/// of the values in order.
+	pub fn extrinsic(&self, module: &str, call: &str, args: &Value) -> Result<ExtrinsicBuilder> {
+		Ok(ExtrinsicBuilder::from_call(self, self.encode_call(module, call, args)?))
+	}
+}
+
+impl<'a> ExtrinsicBuilder<'a> {
+	/// Start building an extrinsic from an encoded call.
+	pub fn from_call(decoder: &'a Decoder, call: Vec<u8>) -> Self {
+		ExtrinsicBuilder { decoder, call }
+	}
+
+	/// The encoded call.
+	pub fn call(&self) -> &[u8] {
+		&self.call
+	}
+
+	/// The unsigned extrinsic, length prefixed as expected by `author_submitExtrinsic`.
+	pub fn unsigned(&self) -> Vec<u8> {
+		let mut extrinsic = vec![EXTRINSIC_VERSION];
+		extrinsic.extend_from_slice(&self.call);
+		extrinsic.encode()
+	}
+
+	/// The payload to sign: the call followed by the signed extensions and the data they add to
+	/// the signature, hashed if longer than 256 bytes.
+	pub fn signing_payload(&self, extra: &Value, additional_signed: &Value) -> Result<Vec<u8>> {
+		let mut payload = self.call.clone();
+		self.decoder.encode_type(&TypeName::named("ExtrinsicExtra"), extra, &mut payload, 0)?;
+		self.decoder.encode_type(&TypeName::named("ExtrinsicAdditionalSigned"), additional_signed, &mut payload, 0)?;
+
+		if payload.len() > MAX_UNHASHED_PAYLOAD {
+			Ok(blake2_256(&payload).to_vec())
+		} else {
+			Ok(payload)
+		}
+	}
+
+	/// The extrinsic signed by `address`, `signature` being the `Signature` of the signing payload.
+	pub fn signed(&self, address: &Value, signature: &Value, extra: &Value) -> Result<Vec<u8>> {
+		let mut extrinsic = vec![EXTRINSIC_VERSION | 0b1000_0000];
+		self.decoder.encode_type(&TypeName::named("Address"), address, &mut extrinsic, 0)?;
+		self.decoder.encode_type(&TypeName::named("Signature"), signature, &mut extrinsic, 0)?;
+		self.decoder.encode_type(&TypeName::named("ExtrinsicExtra"), extra, &mut extrinsic, 0)?;
+		extrinsic.extend_from_slice(&self.call);
+		Ok(extrinsic.encode())
+	}
+
+	/// Sign the extrinsic by `address`.
+	///
+	/// `sign` signs the payload it is given and returns the signature as a value of the
+	/// `Signature` type, e.g. `{ "Sr25519": "0x…" }` for a `MultiSignature`.
+	pub fn sign(
+		&self,
+		address: &Value,
+		extra: &Value,
+		additional_signed: &Value,
+		sign: impl FnOnce(&[u8]) -> Value,
+	) -> Result<Vec<u8>> {
+		let payload = self.signing_payload(extra, additional_signed)?;
+		self.signed(address, &sign(&payload), extra)
+	}
+}
+
+/// The `ExtrinsicEra` of a transaction valid for about `period` blocks from the block `current`,
+/// as created by `Era::mortal`.
+pub fn mortal_era(period: u64, current: u64) -> Value {
+	let period = period.checked_next_power_of_two()
+		.unwrap_or(1 << 16)
+		.max(4)
+		.min(1 << 16);
+	let phase = current % period;
+	let quantize_factor = (period >> 12).max(1);
+	let quantized_phase = phase / quantize_factor * quantize_factor;
+
+	json!({ "Mortal": { "period": period, "phase": quantized_phase } })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Compact;
+	use frame_metadata::{
+		DecodeDifferent, FunctionArgumentMetadata, FunctionMetadata, ModuleMetadata, RuntimeMetadata,
+		RuntimeMetadataPrefixed, RuntimeMetadataV10, META_RESERVED,
+	};
+	use sp_core::{sr25519, Pair};
+
+	use crate::{decoder::hex, TypeRegistry};
+
+	fn d<B, O>(value: O) -> DecodeDifferent<B, O> {
+		DecodeDifferent::Decoded(value)
+	}
+
+	fn decoder() -> Decoder {
+		let system = ModuleMetadata {
+			name: d("System".into()),
+			storage: None,
+			calls: Some(d(vec![FunctionMetadata {
+				name: d("remark".into()),
+				arguments: d(vec![
+					FunctionArgumentMetadata { name: d("_remark".into()), ty: d("Vec<u8>".into()) },
+				]),
+				documentation: d(vec![]),
+			}])),
+			event: None,
+			constants: d(vec![]),
+			errors: d(vec![]),
+		};
+		let metadata = RuntimeMetadataPrefixed(
+			META_RESERVED,
+			RuntimeMetadata::V10(RuntimeMetadataV10 { modules: d(vec![system]) }),
+		);
+		Decoder::from_bytes(&metadata.encode(), TypeRegistry::with_defaults()).unwrap()
+	}
+
+	fn extra() -> Value {
+		json!({ "era": mortal_era(64, 100), "nonce": 3, "tip": 0, "asset": null })
+	}
+
+	fn additional_signed() -> Value {
+		json!({ "spec_version": 229, "genesis_hash": hex(&[1; 32]), "block_hash": hex(&[2; 32]) })
+	}
+
+	#[test]
+	fn builds_unsigned_extrinsics() {
+		let decoder = decoder();
+		let builder = decoder.extrinsic("System", "remark", &json!(["0x0102"])).unwrap();
+
+		assert_eq!(builder.call(), &[0, 0, 8, 1, 2][..]);
+		assert_eq!(builder.unsigned(), (4u8, 0u8, 0u8, vec![1u8, 2]).encode().encode());
+	}
+
+	#[test]
+	fn signs_extrinsics() {
+		let decoder = decoder();
+		let pair = sr25519::Pair::from_seed(&[7; 32]);
+		let builder = decoder.extrinsic("System", "remark", &json!({ "_remark": "0x0102" })).unwrap();
+		let address = json!({ "Id": hex(pair.public().as_ref()) });
+
+		let mut signed = None;
+		let extrinsic = builder.sign(&address, &extra(), &additional_signed(), |message| {
+			let signature = pair.sign(message);
+			signed = Some((message.to_vec(), signature.clone()));
+			json!({ "Sr25519": hex(signature.as_ref()) })
+		}).unwrap();
+		let (payload, signature) = signed.unwrap();
+
+		let era = 5u16 | (36 << 4);
+		let expected_payload = (
+			(0u8, 0u8, vec![1u8, 2]),
+			(era, Compact(3u32), Compact(0u128), None::<u32>),
+			(229u32, [1u8; 32], [2u8; 32]),
+		).encode();
+		assert_eq!(payload, expected_payload);
+		assert!(sr25519::Pair::verify(&signature, &payload, &pair.public()));
+
+		let decoded = decoder.decode_extrinsic(&extrinsic).unwrap();
+		assert_eq!(decoded["signature"], json!({
+			"address": address,
+			"signature": { "Sr25519": hex(signature.as_ref()) },
+			"extra": extra(),
+		}));
+		assert_eq!(decoded["call"], json!({ "module": "System", "call": "remark", "args": { "_remark": "0x0102" } }));
+	}
+
+	#[test]
+	fn hashes_long_payloads() {
+		let decoder = decoder();
+		let remark = hex(&[0; 300]);
+		let builder = decoder.extrinsic("System", "remark", &json!([remark])).unwrap();
+
+		let payload = builder.signing_payload(&extra(), &additional_signed()).unwrap();
+		assert_eq!(payload.len(), 32);
+	}
+
+	#[test]
+	fn builds_mortal_eras_like_the_runtime() {
+		assert_eq!(mortal_era(64, 100), json!({ "Mortal": { "period": 64, "phase": 36 } }));
+		assert_eq!(mortal_era(1, 5), json!({ "Mortal": { "period": 4, "phase": 1 } }));
+		assert_eq!(mortal_era(100_000, 70_000), json!({ "Mortal": { "period": 65536, "phase": 4464 } }));
+	}
+}
@@ -0,0 +1,71 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of SCALE encoded runtime data into JSON.
+//!
+//! The metadata of a runtime only describes types by their name. The [`TypeRegistry`] maps these
+//! names to their definitions, which allows the [`Decoder`] to turn storage values, events,
+//! extrinsics and arbitrary values into a human readable form without having access to the
+//! runtime itself. The other way around, it encodes JSON values and builds extrinsics with the
+//! [`ExtrinsicBuilder`].
+
+#![warn(missing_docs)]
+
+mod compat;
+mod decoder;
+mod encoder;
+mod extrinsic;
+mod registry;
+mod schema;
+mod type_name;
+
+pub use compat::{check_compat, BreakingChange};
+pub use decoder::{Decoder, ModuleError};
+pub use extrinsic::{mortal_era, ExtrinsicBuilder};
+pub use registry::{TypeDef, TypeRegistry};
+pub use schema::{export_schema, SCHEMA_VERSION};
+pub use type_name::TypeName;
+
+/// Decoding error.
+#[derive(Debug, derive_more::Display)]
+pub enum Error {
+	/// A type name could not be parsed.
+	#[display(fmt="Invalid type name: {}", _0)]
+	InvalidTypeName(String),
+	/// A type definition could not be parsed.
+	#[display(fmt="Invalid type definition: {}", _0)]
+	InvalidTypeDefinition(String),
+	/// The metadata could not be decoded or is of an unsupported version.
+	#[display(fmt="Invalid metadata: {}", _0)]
+	InvalidMetadata(String),
+	/// A type is neither built in nor registered.
+	#[display(fmt="Unknown type: {}", _0)]
+	UnknownType(String),
+	/// A storage key does not belong to any storage entry of the metadata.
+	#[display(fmt="Unknown storage key: {}", _0)]
+	UnknownStorageKey(String),
+	/// The data does not match the expected type.
+	#[display(fmt="Invalid data: {}", _0)]
+	InvalidData(String),
+	/// The type nesting exceeds the supported depth.
+	#[display(fmt="Type nesting too deep: {}", _0)]
+	TooDeep(String),
+}
+
+impl std::error::Error for Error {}
+
+/// Decoding result.
+pub type Result<T> = std::result::Result<T, Error>;
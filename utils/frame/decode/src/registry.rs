@@ -0,0 +1,221 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Registry of type definitions the metadata only refers to by name.
+//!
+//! Definitions use the JSON format established by the JavaScript tooling:
+//!
+//! ```json
+//! {
+//!   "Balance": "u128",
+//!   "Exposure": { "total": "Compact<Balance>", "own": "Compact<Balance>" },
+//!   "StakerStatus": { "_enum": { "Idle": "Null", "Validator": "Null", "Nominator": "Vec<AccountId>" } },
+//!   "Reasons": { "_enum": ["Fee", "Misc", "All"] }
+//! }
+//! ```
+
+use std::{collections::HashMap, fmt};
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::{Error, Result, TypeName};
+
+/// Definitions of the types used by the node runtime.
+const DEFAULT_TYPES: &str = include_str!("../res/default_types.json");
+
+/// The definition of a named type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDef {
+	/// Same encoding as another type.
+	Alias(TypeName),
+	/// A struct with named fields, encoded in order.
+	Struct(Vec<(String, TypeName)>),
+	/// An enum, encoded as a variant index followed by the optional payload.
+	Enum(Vec<(String, Option<TypeName>)>),
+}
+
+/// A set of named type definitions.
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+	types: HashMap<String, TypeDef>,
+}
+
+impl TypeRegistry {
+	/// Create an empty registry.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a registry with the definitions required by the node runtime.
+	pub fn with_defaults() -> Self {
+		let mut registry = Self::new();
+		registry.extend_from_json(DEFAULT_TYPES)
+			.expect("The default type definitions are valid; qed");
+		registry
+	}
+
+	/// Register a single definition, replacing any previous one with the same name.
+	pub fn register(&mut self, name: impl Into<String>, def: TypeDef) {
+		self.types.insert(name.into(), def);
+	}
+
+	/// Add all definitions from a JSON object, replacing existing ones.
+	pub fn extend_from_json(&mut self, json: &str) -> Result<()> {
+		let raw: RawDef = serde_json::from_str(json)
+			.map_err(|e| Error::InvalidTypeDefinition(format!("{}", e)))?;
+		let definitions = match raw {
+			RawDef::Object(definitions) => definitions,
+			_ => return Err(Error::InvalidTypeDefinition("Expected a JSON object".into())),
+		};
+
+		for (name, def) in definitions {
+			let def = parse_def(def)
+				.map_err(|e| Error::InvalidTypeDefinition(format!("{}: {}", name, e)))?;
+			self.register(name, def);
+		}
+
+		Ok(())
+	}
+
+	/// Look up the definition of a type.
+	pub fn get(&self, name: &str) -> Option<&TypeDef> {
+		self.types.get(name)
+	}
+}
+
+/// JSON definition as written in the file.
+///
+/// `serde_json::Value` sorts object keys, which would lose the field order of structs.
+enum RawDef {
+	Name(String),
+	List(Vec<String>),
+	Object(Vec<(String, RawDef)>),
+}
+
+impl<'de> Deserialize<'de> for RawDef {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+		struct RawDefVisitor;
+
+		impl<'de> Visitor<'de> for RawDefVisitor {
+			type Value = RawDef;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("a type name, a list of variant names or an object")
+			}
+
+			fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<RawDef, E> {
+				Ok(RawDef::Name(v.into()))
+			}
+
+			fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<RawDef, A::Error> {
+				let mut items = Vec::new();
+				while let Some(item) = seq.next_element()? {
+					items.push(item);
+				}
+				Ok(RawDef::List(items))
+			}
+
+			fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<RawDef, A::Error> {
+				let mut entries = Vec::new();
+				while let Some(entry) = map.next_entry()? {
+					entries.push(entry);
+				}
+				Ok(RawDef::Object(entries))
+			}
+		}
+
+		deserializer.deserialize_any(RawDefVisitor)
+	}
+}
+
+fn parse_def(def: RawDef) -> Result<TypeDef> {
+	match def {
+		RawDef::Name(ty) => Ok(TypeDef::Alias(TypeName::parse(&ty)?)),
+		RawDef::List(_) => Err(Error::InvalidTypeDefinition("Expected a string or an object".into())),
+		RawDef::Object(mut fields) => {
+			if fields.len() == 1 && fields[0].0 == "_enum" {
+				return match fields.remove(0).1 {
+					RawDef::List(variants) => Ok(TypeDef::Enum(
+						variants.into_iter().map(|name| (name, None)).collect()
+					)),
+					RawDef::Object(variants) => variants.into_iter()
+						.map(|(name, ty)| Ok((name, parse_payload(ty)?)))
+						.collect::<Result<_>>()
+						.map(TypeDef::Enum),
+					RawDef::Name(_) => Err(Error::InvalidTypeDefinition("Invalid `_enum` definition".into())),
+				}
+			}
+
+			fields.into_iter()
+				.map(|(name, ty)| Ok((name, parse_field(ty)?)))
+				.collect::<Result<_>>()
+				.map(TypeDef::Struct)
+		},
+	}
+}
+
+fn parse_field(ty: RawDef) -> Result<TypeName> {
+	match ty {
+		RawDef::Name(ty) => TypeName::parse(&ty),
+		_ => Err(Error::InvalidTypeDefinition("Expected a type name".into())),
+	}
+}
+
+fn parse_payload(ty: RawDef) -> Result<Option<TypeName>> {
+	let ty = parse_field(ty)?;
+	if ty.name() == Some("Null") {
+		Ok(None)
+	} else {
+		Ok(Some(ty))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn default_types_are_valid() {
+		let registry = TypeRegistry::with_defaults();
+		assert_eq!(registry.get("Balance"), Some(&TypeDef::Alias(TypeName::named("u128"))));
+	}
+
+	#[test]
+	fn parses_structs_in_field_order_and_enums() {
+		let mut registry = TypeRegistry::new();
+		registry.extend_from_json(r#"{
+			"Foo": { "b": "u32", "a": "Vec<u8>" },
+			"Bar": { "_enum": ["A", "B"] },
+			"Baz": { "_enum": { "A": "Null", "B": "Foo" } }
+		}"#).unwrap();
+
+		assert_eq!(registry.get("Foo"), Some(&TypeDef::Struct(vec![
+			("b".into(), TypeName::named("u32")),
+			("a".into(), TypeName::parse("Vec<u8>").unwrap()),
+		])));
+		assert_eq!(registry.get("Bar"), Some(&TypeDef::Enum(vec![("A".into(), None), ("B".into(), None)])));
+		assert_eq!(
+			registry.get("Baz"),
+			Some(&TypeDef::Enum(vec![("A".into(), None), ("B".into(), Some(TypeName::named("Foo")))])),
+		);
+	}
+
+	#[test]
+	fn rejects_invalid_definitions() {
+		assert!(TypeRegistry::new().extend_from_json(r#"{ "Foo": 1 }"#).is_err());
+		assert!(TypeRegistry::new().extend_from_json(r#"[]"#).is_err());
+	}
+}
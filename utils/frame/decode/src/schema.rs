@@ -0,0 +1,391 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export of the runtime interface as a JSON schema, for client code generators.
+//!
+//! The schema lists the modules in runtime order with their calls, events, errors, constants and
+//! storage items, each with its documentation and the indices identifying it on the wire, and
+//! the definitions of all registered types they refer to:
+//!
+//! ```json
+//! {
+//!   "schemaVersion": 1,
+//!   "modules": [{
+//!     "name": "Balances",
+//!     "index": 5,
+//!     "calls": { "index": 4, "items": [{ "index": 0, "name": "transfer", "args": [...], "documentation": [...] }] },
+//!     "events": { "index": 4, "items": [...] },
+//!     "errors": { "index": 5, "items": [...] },
+//!     "constants": [{ "name": "ExistentialDeposit", "type": "Balance", "value": "0x...", "documentation": [...] }],
+//!     "storage": { "prefix": "Balances", "items": [...] }
+//!   }],
+//!   "types": {
+//!     "Balance": { "kind": "alias", "type": "u128" },
+//!     "Reasons": { "kind": "enum", "variants": [{ "name": "Fee", "type": null }, ...] }
+//!   }
+//! }
+//! ```
+//!
+//! Type names are normalized, e.g. `T::AccountId` becomes `AccountId`. Names without definition
+//! in `types` are built in types of the codec, like `u32`, `Vec<T>` or `Compact<T>`. Fields are
+//! only ever added to the schema within a `schemaVersion`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use frame_metadata::{ModuleMetadata, StorageEntryModifier, StorageEntryType, StorageHasher};
+use serde_json::{json, Value};
+
+use crate::decoder::{decoded, hex};
+use crate::{Decoder, Result, TypeDef, TypeName, TypeRegistry};
+
+/// Version of the layout of the schema, increased on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Export the modules of the runtime and the types they use as a JSON schema.
+pub fn export_schema(decoder: &Decoder) -> Result<Value> {
+	let mut types = Types { registry: decoder.registry(), seen: HashSet::new(), defs: BTreeMap::new() };
+	let (mut call_index, mut event_index) = (0, 0);
+	let mut modules = Vec::new();
+
+	for (index, module) in decoder.modules().iter().enumerate() {
+		// Modules without calls or events don't take part in the indexing of the outer enums.
+		let calls = match calls(module, &mut types)? {
+			Some(items) => {
+				call_index += 1;
+				json!({ "index": call_index - 1, "items": items })
+			},
+			None => Value::Null,
+		};
+		let events = match events(module, &mut types)? {
+			Some(items) => {
+				event_index += 1;
+				json!({ "index": event_index - 1, "items": items })
+			},
+			None => Value::Null,
+		};
+
+		modules.push(json!({
+			"name": decoded(&module.name)?,
+			"index": index,
+			"calls": calls,
+			"events": events,
+			"errors": {
+				"index": index,
+				"items": errors(module)?,
+			},
+			"constants": constants(module, &mut types)?,
+			"storage": storage(module, &mut types)?,
+		}));
+	}
+
+	Ok(json!({
+		"schemaVersion": SCHEMA_VERSION,
+		"modules": modules,
+		"types": types.defs,
+	}))
+}
+
+fn calls(module: &ModuleMetadata, types: &mut Types) -> Result<Option<Vec<Value>>> {
+	let calls = match &module.calls {
+		Some(calls) => decoded(calls)?,
+		None => return Ok(None),
+	};
+	calls.iter().enumerate()
+		.map(|(index, call)| Ok(json!({
+			"index": index,
+			"name": decoded(&call.name)?,
+			"args": decoded(&call.arguments)?.iter()
+				.map(|arg| Ok(json!({
+					"name": decoded(&arg.name)?,
+					"type": types.use_type(decoded(&arg.ty)?)?,
+				})))
+				.collect::<Result<Vec<_>>>()?,
+			"documentation": decoded(&call.documentation)?,
+		})))
+		.collect::<Result<_>>()
+		.map(Some)
+}
+
+fn events(module: &ModuleMetadata, types: &mut Types) -> Result<Option<Vec<Value>>> {
+	let events = match &module.event {
+		Some(events) => decoded(events)?,
+		None => return Ok(None),
+	};
+	events.iter().enumerate()
+		.map(|(index, event)| Ok(json!({
+			"index": index,
+			"name": decoded(&event.name)?,
+			"args": decoded(&event.arguments)?.iter()
+				.map(|ty| types.use_type(ty))
+				.collect::<Result<Vec<_>>>()?,
+			"documentation": decoded(&event.documentation)?,
+		})))
+		.collect::<Result<_>>()
+		.map(Some)
+}
+
+fn errors(module: &ModuleMetadata) -> Result<Vec<Value>> {
+	decoded(&module.errors)?.iter().enumerate()
+		.map(|(index, error)| Ok(json!({
+			"index": index,
+			"name": decoded(&error.name)?,
+			"documentation": decoded(&error.documentation)?,
+		})))
+		.collect()
+}
+
+fn constants(module: &ModuleMetadata, types: &mut Types) -> Result<Vec<Value>> {
+	decoded(&module.constants)?.iter()
+		.map(|constant| Ok(json!({
+			"name": decoded(&constant.name)?,
+			"type": types.use_type(decoded(&constant.ty)?)?,
+			"value": hex(decoded(&constant.value)?),
+			"documentation": decoded(&constant.documentation)?,
+		})))
+		.collect()
+}
+
+fn storage(module: &ModuleMetadata, types: &mut Types) -> Result<Value> {
+	let storage = match &module.storage {
+		Some(storage) => decoded(storage)?,
+		None => return Ok(Value::Null),
+	};
+	let items = decoded(&storage.entries)?.iter()
+		.map(|entry| {
+			let ty = match &entry.ty {
+				StorageEntryType::Plain(value) => json!({
+					"kind": "plain",
+					"value": types.use_type(decoded(value)?)?,
+				}),
+				StorageEntryType::Map { hasher, key, value, is_linked } => json!({
+					"kind": "map",
+					"hasher": hasher_name(hasher),
+					"key": types.use_type(decoded(key)?)?,
+					"value": types.use_type(decoded(value)?)?,
+					"isLinked": is_linked,
+				}),
+				StorageEntryType::DoubleMap { hasher, key1, key2, value, key2_hasher } => json!({
+					"kind": "doubleMap",
+					"hasher": hasher_name(hasher),
+					"key1": types.use_type(decoded(key1)?)?,
+					"key2": types.use_type(decoded(key2)?)?,
+					"value": types.use_type(decoded(value)?)?,
+					"key2Hasher": hasher_name(key2_hasher),
+				}),
+			};
+			Ok(json!({
+				"name": decoded(&entry.name)?,
+				"modifier": match entry.modifier {
+					StorageEntryModifier::Optional => "optional",
+					StorageEntryModifier::Default => "default",
+				},
+				"type": ty,
+				"default": hex(decoded(&entry.default)?),
+				"documentation": decoded(&entry.documentation)?,
+			}))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(json!({ "prefix": decoded(&storage.prefix)?, "items": items }))
+}
+
+fn hasher_name(hasher: &StorageHasher) -> &'static str {
+	match hasher {
+		StorageHasher::Blake2_128 => "blake2_128",
+		StorageHasher::Blake2_256 => "blake2_256",
+		StorageHasher::Blake2_128Concat => "blake2_128Concat",
+		StorageHasher::Twox128 => "twox128",
+		StorageHasher::Twox256 => "twox256",
+		StorageHasher::Twox64Concat => "twox64Concat",
+	}
+}
+
+/// Collects the definitions of the registered types the schema refers to.
+struct Types<'a> {
+	registry: &'a TypeRegistry,
+	seen: HashSet<String>,
+	defs: BTreeMap<String, Value>,
+}
+
+impl<'a> Types<'a> {
+	/// Parse a type name of the metadata, returning its normalized form.
+	fn use_type(&mut self, ty: &str) -> Result<String> {
+		let ty = TypeName::parse(ty)?;
+		self.visit(&ty);
+		Ok(ty.to_string())
+	}
+
+	fn visit(&mut self, ty: &TypeName) {
+		match ty {
+			TypeName::Path { name, params } => {
+				params.iter().for_each(|param| self.visit(param));
+				if !self.seen.insert(name.clone()) {
+					return
+				}
+				let def = match self.registry.get(name) {
+					Some(def) => def,
+					None => return,
+				};
+				let exported = match def {
+					TypeDef::Alias(aliased) => {
+						self.visit(aliased);
+						json!({ "kind": "alias", "type": aliased.to_string() })
+					},
+					TypeDef::Struct(fields) => {
+						fields.iter().for_each(|(_, ty)| self.visit(ty));
+						json!({
+							"kind": "struct",
+							"fields": fields.iter()
+								.map(|(name, ty)| json!({ "name": name, "type": ty.to_string() }))
+								.collect::<Vec<_>>(),
+						})
+					},
+					TypeDef::Enum(variants) => {
+						variants.iter().filter_map(|(_, ty)| ty.as_ref()).for_each(|ty| self.visit(ty));
+						json!({
+							"kind": "enum",
+							"variants": variants.iter()
+								.map(|(name, ty)| json!({
+									"name": name,
+									"type": ty.as_ref().map(|ty| ty.to_string()),
+								}))
+								.collect::<Vec<_>>(),
+						})
+					},
+				};
+				self.defs.insert(name.clone(), exported);
+			},
+			TypeName::Tuple(items) => items.iter().for_each(|item| self.visit(item)),
+			TypeName::Array(item, _) | TypeName::Slice(item) => self.visit(item),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use frame_metadata::{
+		DecodeDifferent, EventMetadata, FunctionArgumentMetadata, FunctionMetadata,
+		ModuleConstantMetadata, RuntimeMetadata, RuntimeMetadataPrefixed, RuntimeMetadataV10,
+		StorageEntryMetadata, StorageMetadata, META_RESERVED,
+	};
+
+	fn d<B, O>(value: O) -> DecodeDifferent<B, O> {
+		DecodeDifferent::Decoded(value)
+	}
+
+	fn decoder(modules: Vec<ModuleMetadata>, registry: TypeRegistry) -> Decoder {
+		let metadata = RuntimeMetadataPrefixed(
+			META_RESERVED,
+			RuntimeMetadata::V10(RuntimeMetadataV10 { modules: d(modules) }),
+		);
+		Decoder::from_bytes(&metadata.encode(), registry).unwrap()
+	}
+
+	fn balances() -> ModuleMetadata {
+		ModuleMetadata {
+			name: d("Balances".into()),
+			storage: Some(d(StorageMetadata {
+				prefix: d("Balances".into()),
+				entries: d(vec![StorageEntryMetadata {
+					name: d("Account".into()),
+					modifier: StorageEntryModifier::Default,
+					ty: StorageEntryType::Map {
+						hasher: StorageHasher::Blake2_128Concat,
+						key: d("T::AccountId".into()),
+						value: d("AccountData<T::Balance>".into()),
+						is_linked: false,
+					},
+					default: d(vec![0; 4]),
+					documentation: d(vec!["The balances of an account.".into()]),
+				}]),
+			})),
+			calls: Some(d(vec![FunctionMetadata {
+				name: d("transfer".into()),
+				arguments: d(vec![
+					FunctionArgumentMetadata { name: d("dest".into()), ty: d("<T::Lookup as StaticLookup>::Source".into()) },
+					FunctionArgumentMetadata { name: d("value".into()), ty: d("Compact<T::Balance>".into()) },
+				]),
+				documentation: d(vec!["Transfer some balance.".into()]),
+			}])),
+			event: Some(d(vec![EventMetadata {
+				name: d("Transfer".into()),
+				arguments: d(vec!["AccountId".into(), "AccountId".into(), "Balance".into()]),
+				documentation: d(vec![]),
+			}])),
+			constants: d(vec![ModuleConstantMetadata {
+				name: d("ExistentialDeposit".into()),
+				ty: d("T::Balance".into()),
+				value: d(vec![1, 0]),
+				documentation: d(vec![]),
+			}]),
+			errors: d(vec![]),
+		}
+	}
+
+	fn system() -> ModuleMetadata {
+		ModuleMetadata {
+			name: d("System".into()),
+			storage: None,
+			calls: None,
+			event: Some(d(vec![])),
+			constants: d(vec![]),
+			errors: d(vec![]),
+		}
+	}
+
+	#[test]
+	fn exports_modules_with_their_indices() {
+		let schema = export_schema(&decoder(vec![system(), balances()], TypeRegistry::new())).unwrap();
+		let balances = &schema["modules"][1];
+
+		assert_eq!(schema["schemaVersion"], json!(1));
+		assert_eq!(schema["modules"][0]["calls"], Value::Null);
+		assert_eq!(balances["index"], json!(1));
+		assert_eq!(balances["calls"]["index"], json!(0));
+		assert_eq!(balances["events"]["index"], json!(1));
+		assert_eq!(balances["errors"]["index"], json!(1));
+		assert_eq!(balances["calls"]["items"][0]["args"][1], json!({ "name": "value", "type": "Compact<Balance>" }));
+		assert_eq!(balances["constants"][0]["value"], json!("0x0100"));
+		assert_eq!(balances["storage"]["items"][0]["type"], json!({
+			"kind": "map",
+			"hasher": "blake2_128Concat",
+			"key": "AccountId",
+			"value": "AccountData<Balance>",
+			"isLinked": false,
+		}));
+	}
+
+	#[test]
+	fn exports_the_types_used() {
+		let mut registry = TypeRegistry::new();
+		registry.extend_from_json(r#"{
+			"Balance": "u128",
+			"AccountData": { "free": "Balance", "reserved": "Balance" },
+			"Reasons": { "_enum": ["Fee", "Misc", "All"] }
+		}"#).unwrap();
+
+		let schema = export_schema(&decoder(vec![balances()], registry)).unwrap();
+		assert_eq!(schema["types"], json!({
+			"Balance": { "kind": "alias", "type": "u128" },
+			"AccountData": {
+				"kind": "struct",
+				"fields": [{ "name": "free", "type": "Balance" }, { "name": "reserved", "type": "Balance" }],
+			},
+		}));
+	}
+}
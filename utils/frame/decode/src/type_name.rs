@@ -0,0 +1,281 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parsing of the type names found in the runtime metadata.
+//!
+//! Metadata v10 describes types by the string they were written as in the
+//! runtime source, e.g. `Vec<(T::AccountId, BalanceOf<T>)>`. This module
+//! turns such strings into a small syntax tree with the trait qualifications
+//! (`T::`, `<T as Trait>::`) stripped, which the decoder can then resolve.
+
+use std::fmt;
+
+use crate::{Error, Result};
+
+/// A parsed type name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeName {
+	/// A named type with optional generic parameters, e.g. `Vec<u8>`.
+	Path {
+		/// Last segment of the path, e.g. `AccountId` for `T::AccountId`.
+		name: String,
+		/// Generic parameters.
+		params: Vec<TypeName>,
+	},
+	/// A tuple, `()` being the empty tuple.
+	Tuple(Vec<TypeName>),
+	/// A fixed size array, e.g. `[u8; 32]`.
+	Array(Box<TypeName>, usize),
+	/// A slice, e.g. `&[u8]`. Encoded like a `Vec`.
+	Slice(Box<TypeName>),
+}
+
+impl TypeName {
+	/// Parse a type name as written in the metadata.
+	pub fn parse(s: &str) -> Result<Self> {
+		let mut parser = Parser { input: s, pos: 0 };
+		let ty = parser.parse_type()?;
+		parser.skip_whitespace();
+		if parser.pos != s.len() {
+			return Err(Error::InvalidTypeName(s.into()))
+		}
+		Ok(ty)
+	}
+
+	/// Create a path type without generic parameters.
+	pub fn named(name: &str) -> Self {
+		TypeName::Path { name: name.into(), params: Vec::new() }
+	}
+
+	/// Returns the name of a path type.
+	pub fn name(&self) -> Option<&str> {
+		match self {
+			TypeName::Path { name, .. } => Some(name),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for TypeName {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fn join(f: &mut fmt::Formatter, items: &[TypeName]) -> fmt::Result {
+			for (i, item) in items.iter().enumerate() {
+				if i > 0 {
+					write!(f, ", ")?;
+				}
+				write!(f, "{}", item)?;
+			}
+			Ok(())
+		}
+
+		match self {
+			TypeName::Path { name, params } if params.is_empty() => write!(f, "{}", name),
+			TypeName::Path { name, params } => {
+				write!(f, "{}<", name)?;
+				join(f, params)?;
+				write!(f, ">")
+			},
+			TypeName::Tuple(items) => {
+				write!(f, "(")?;
+				join(f, items)?;
+				write!(f, ")")
+			},
+			TypeName::Array(item, len) => write!(f, "[{}; {}]", item, len),
+			TypeName::Slice(item) => write!(f, "[{}]", item),
+		}
+	}
+}
+
+struct Parser<'a> {
+	input: &'a str,
+	pos: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn error(&self) -> Error {
+		Error::InvalidTypeName(self.input.into())
+	}
+
+	fn rest(&self) -> &'a str {
+		&self.input[self.pos..]
+	}
+
+	fn skip_whitespace(&mut self) {
+		let trimmed = self.rest().trim_start();
+		self.pos = self.input.len() - trimmed.len();
+	}
+
+	fn eat(&mut self, token: &str) -> bool {
+		self.skip_whitespace();
+		if self.rest().starts_with(token) {
+			self.pos += token.len();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn expect(&mut self, token: &str) -> Result<()> {
+		if self.eat(token) {
+			Ok(())
+		} else {
+			Err(self.error())
+		}
+	}
+
+	fn ident(&mut self) -> Result<&'a str> {
+		self.skip_whitespace();
+		let rest = self.rest();
+		let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+		if len == 0 {
+			return Err(self.error())
+		}
+		self.pos += len;
+		Ok(&rest[..len])
+	}
+
+	fn number(&mut self) -> Result<usize> {
+		let ident = self.ident()?;
+		ident.replace('_', "").parse().map_err(|_| self.error())
+	}
+
+	fn parse_type(&mut self) -> Result<TypeName> {
+		if self.eat("&") {
+			// Lifetimes carry no information for decoding.
+			if self.eat("'") {
+				self.ident()?;
+			}
+			return self.parse_type()
+		}
+
+		if self.eat("(") {
+			let mut items = Vec::new();
+			while !self.eat(")") {
+				items.push(self.parse_type()?);
+				if !self.eat(",") {
+					self.expect(")")?;
+					break
+				}
+			}
+			return Ok(TypeName::Tuple(items))
+		}
+
+		if self.eat("[") {
+			let item = self.parse_type()?;
+			if self.eat(";") {
+				let len = self.number()?;
+				self.expect("]")?;
+				return Ok(TypeName::Array(Box::new(item), len))
+			}
+			self.expect("]")?;
+			return Ok(TypeName::Slice(Box::new(item)))
+		}
+
+		if self.eat("<") {
+			// Qualified path, `<T as Trait<I>>::Balance`. Only the segments after the
+			// qualification are relevant.
+			self.parse_type()?;
+			if self.eat("as") {
+				self.parse_type()?;
+			}
+			self.expect(">")?;
+			self.expect("::")?;
+		}
+
+		let mut name = self.ident()?;
+		let mut params = self.parse_params()?;
+		while self.eat("::") {
+			name = self.ident()?;
+			params = self.parse_params()?;
+		}
+
+		Ok(TypeName::Path { name: name.into(), params })
+	}
+
+	fn parse_params(&mut self) -> Result<Vec<TypeName>> {
+		let mut params = Vec::new();
+		// `::` following a path segment must not be mistaken for generics.
+		self.skip_whitespace();
+		if !self.rest().starts_with('<') {
+			return Ok(params)
+		}
+		self.pos += 1;
+		while !self.eat(">") {
+			params.push(self.parse_type()?);
+			if !self.eat(",") {
+				self.expect(">")?;
+				break
+			}
+		}
+		Ok(params)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn path(name: &str, params: Vec<TypeName>) -> TypeName {
+		TypeName::Path { name: name.into(), params }
+	}
+
+	#[test]
+	fn strips_trait_qualifications() {
+		assert_eq!(TypeName::parse("T::AccountId").unwrap(), TypeName::named("AccountId"));
+		assert_eq!(
+			TypeName::parse("<T as Trait<I>>::Balance").unwrap(),
+			TypeName::named("Balance"),
+		);
+		assert_eq!(
+			TypeName::parse("Box<<T as Trait>::Proposal>").unwrap(),
+			path("Box", vec![TypeName::named("Proposal")]),
+		);
+	}
+
+	#[test]
+	fn parses_compound_types() {
+		assert_eq!(
+			TypeName::parse("Vec<(T::AccountId, BalanceOf<T>)>").unwrap(),
+			path("Vec", vec![TypeName::Tuple(vec![
+				TypeName::named("AccountId"),
+				path("BalanceOf", vec![TypeName::named("T")]),
+			])]),
+		);
+		assert_eq!(
+			TypeName::parse("[u8; 32]").unwrap(),
+			TypeName::Array(Box::new(TypeName::named("u8")), 32),
+		);
+		assert_eq!(
+			TypeName::parse("&'static [u8]").unwrap(),
+			TypeName::Slice(Box::new(TypeName::named("u8"))),
+		);
+		assert_eq!(TypeName::parse("()").unwrap(), TypeName::Tuple(Vec::new()));
+	}
+
+	#[test]
+	fn display_roundtrips_normalized_form() {
+		let ty = TypeName::parse("Option<Vec<(T::BlockNumber, [u8; 4])>>").unwrap();
+		assert_eq!(ty.to_string(), "Option<Vec<(BlockNumber, [u8; 4])>>");
+		assert_eq!(TypeName::parse(&ty.to_string()).unwrap(), ty);
+	}
+
+	#[test]
+	fn rejects_garbage() {
+		assert!(TypeName::parse("Vec<u8").is_err());
+		assert!(TypeName::parse("u8 u16").is_err());
+		assert!(TypeName::parse("").is_err());
+	}
+}
@@ -34,21 +34,67 @@ use sp_blockchain::{
 	Error as ClientError
 };
 use sp_runtime::{
+	DispatchError,
 	generic::BlockId,
-	traits,
+	traits::{self, UniqueSaturatedInto},
 };
-use sp_core::hexdisplay::HexDisplay;
+use sp_core::{Bytes, hexdisplay::HexDisplay};
+use sp_api::Metadata;
 use sp_transaction_pool::{TransactionPool, InPoolTransaction};
+use serde::{Serialize, Deserialize};
+use substrate_frame_decode::{Decoder, TypeRegistry};
 
-pub use frame_system_rpc_runtime_api::AccountNonceApi;
+pub use frame_system_rpc_runtime_api::{AccountNonceApi, MortalityApi};
+pub use sp_block_builder::BlockBuilder;
 pub use self::gen_client::Client as SystemClient;
+pub use simulation::{
+	CallEffect, CallSimulationApi, FullSimulation, SimulationApi, StorageChange, MAX_SIMULATED_CALLS,
+};
+
+mod simulation;
 
 /// Future that resolves to account nonce.
 pub type FutureResult<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
 
+/// Outcome of dry running an extrinsic.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DryRunResult {
+	/// The extrinsic would be included and dispatched successfully.
+	Success,
+	/// The extrinsic would be included, but its dispatch fails.
+	DispatchError(DispatchErrorInfo),
+	/// The extrinsic would not be included in a block.
+	Invalid(String),
+}
+
+/// Description of a dispatch error.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DispatchErrorInfo {
+	/// Name of the module raising the error, for module errors.
+	pub module: Option<String>,
+	/// Name of the error.
+	pub error: String,
+	/// Documentation of the error, from the runtime metadata.
+	pub documentation: Vec<String>,
+}
+
+/// Parameters for creating a mortal transaction at the best block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mortality<BlockHash> {
+	/// Hash of the best block, the checkpoint of the era.
+	pub block_hash: BlockHash,
+	/// Number of the best block, the current block of the era.
+	pub block_number: u64,
+	/// The recommended era period.
+	pub period: u64,
+}
+
 /// System RPC methods.
 #[rpc]
-pub trait SystemApi<AccountId, Index> {
+pub trait SystemApi<BlockHash, AccountId, Index> {
 	/// Returns the next valid index (aka nonce) for given account.
 	///
 	/// This method takes into consideration all pending transactions
@@ -56,9 +102,24 @@ pub trait SystemApi<AccountId, Index> {
 	/// it fallbacks to query the index from the runtime (aka. state nonce).
 	#[rpc(name = "system_accountNextIndex", alias("account_nextIndex"))]
 	fn nonce(&self, account: AccountId) -> FutureResult<Index>;
+
+	/// Dry run an extrinsic on top of the given block, or the best block.
+	///
+	/// Module errors are resolved to their module and error names using the runtime metadata.
+	#[rpc(name = "system_dryRun")]
+	fn dry_run(&self, extrinsic: Bytes, at: Option<BlockHash>) -> FutureResult<DryRunResult>;
+
+	/// Returns the best block and the era period to create mortal transactions with.
+	///
+	/// The period is the longest one accepted by the runtime, so signers don't need
+	/// to know the number of block hashes the runtime keeps.
+	#[rpc(name = "system_mortality")]
+	fn mortality(&self) -> FutureResult<Mortality<BlockHash>>;
 }
 
 const RUNTIME_ERROR: i64 = 1;
+const BAD_EXTRINSIC: i64 = 2;
+const NOT_SUPPORTED: i64 = 3;
 
 /// An implementation of System-specific RPC methods on full client.
 pub struct FullSystem<P: TransactionPool, C, B> {
@@ -78,12 +139,16 @@ impl<P: TransactionPool, C, B> FullSystem<P, C, B> {
 	}
 }
 
-impl<P, C, Block, AccountId, Index> SystemApi<AccountId, Index> for FullSystem<P, C, Block>
+impl<P, C, Block, AccountId, Index> SystemApi<<Block as traits::Block>::Hash, AccountId, Index>
+	for FullSystem<P, C, Block>
 where
 	C: sp_api::ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block>,
 	C: Send + Sync + 'static,
 	C::Api: AccountNonceApi<Block, AccountId, Index>,
+	C::Api: MortalityApi<Block>,
+	C::Api: BlockBuilder<Block>,
+	C::Api: Metadata<Block>,
 	P: TransactionPool + 'static,
 	Block: traits::Block,
 	AccountId: Clone + std::fmt::Display + Codec,
@@ -106,6 +171,57 @@ where
 
 		Box::new(result(get_nonce()))
 	}
+
+	fn dry_run(
+		&self,
+		extrinsic: Bytes,
+		at: Option<<Block as traits::Block>::Hash>,
+	) -> FutureResult<DryRunResult> {
+		let dry_run = || {
+			let api = self.client.runtime_api();
+			let at = BlockId::<Block>::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+			let uxt: <Block as traits::Block>::Extrinsic = Decode::decode(&mut &*extrinsic)
+				.map_err(|e| Error {
+					code: ErrorCode::ServerError(BAD_EXTRINSIC),
+					message: "Unable to decode extrinsic.".into(),
+					data: Some(format!("{:?}", e).into()),
+				})?;
+
+			let outcome = api.apply_extrinsic(&at, uxt).map_err(|e| Error {
+				code: ErrorCode::ServerError(RUNTIME_ERROR),
+				message: "Unable to dry run extrinsic.".into(),
+				data: Some(format!("{:?}", e).into()),
+			})?;
+
+			Ok(match outcome {
+				Ok(Ok(())) => DryRunResult::Success,
+				Ok(Err(error)) => DryRunResult::DispatchError(dispatch_error_info(error, || {
+					api.metadata(&at).ok().map(|metadata| metadata.to_vec())
+				})),
+				Err(invalid) => DryRunResult::Invalid(format!("{:?}", invalid)),
+			})
+		};
+
+		Box::new(result(dry_run()))
+	}
+
+	fn mortality(&self) -> FutureResult<Mortality<<Block as traits::Block>::Hash>> {
+		let info = self.client.info();
+		let period = self.client.runtime_api()
+			.era_period(&BlockId::hash(info.best_hash))
+			.map_err(|e| Error {
+				code: ErrorCode::ServerError(RUNTIME_ERROR),
+				message: "Unable to query era period.".into(),
+				data: Some(format!("{:?}", e).into()),
+			});
+
+		Box::new(result(period.map(|period| Mortality {
+			block_hash: info.best_hash,
+			block_number: UniqueSaturatedInto::<u64>::unique_saturated_into(info.best_number),
+			period,
+		})))
+	}
 }
 
 /// An implementation of System-specific RPC methods on light client.
@@ -133,7 +249,8 @@ impl<P: TransactionPool, C, F, Block> LightSystem<P, C, F, Block> {
 	}
 }
 
-impl<P, C, F, Block, AccountId, Index> SystemApi<AccountId, Index> for LightSystem<P, C, F, Block>
+impl<P, C, F, Block, AccountId, Index> SystemApi<<Block as traits::Block>::Hash, AccountId, Index>
+	for LightSystem<P, C, F, Block>
 where
 	P: TransactionPool + 'static,
 	C: HeaderBackend<Block>,
@@ -178,11 +295,91 @@ where
 
 		Box::new(future_nonce)
 	}
+
+	fn dry_run(
+		&self,
+		_extrinsic: Bytes,
+		_at: Option<<Block as traits::Block>::Hash>,
+	) -> FutureResult<DryRunResult> {
+		Box::new(result(Err(Error {
+			code: ErrorCode::ServerError(NOT_SUPPORTED),
+			message: "Dry running extrinsics is not supported by light clients.".into(),
+			data: None,
+		})))
+	}
+
+	fn mortality(&self) -> FutureResult<Mortality<<Block as traits::Block>::Hash>> {
+		let info = self.client.info();
+		let best_hash = info.best_hash;
+		let best_number = UniqueSaturatedInto::<u64>::unique_saturated_into(info.best_number);
+		let future_best_header = future_header(&*self.remote_blockchain, &*self.fetcher, BlockId::hash(best_hash));
+		let fetcher = self.fetcher.clone();
+		let future_best_header = future_best_header
+			.and_then(move |maybe_best_header| ready(
+				match maybe_best_header {
+					Some(best_header) => Ok(best_header),
+					None => Err(ClientError::UnknownBlock(format!("{}", best_hash))),
+				}
+			));
+		let future_period = future_best_header.and_then(move |best_header|
+			fetcher.remote_call(RemoteCallRequest {
+				block: best_hash,
+				header: best_header,
+				method: "MortalityApi_era_period".into(),
+				call_data: Vec::new(),
+				retry_count: None,
+			})
+		).compat();
+		let future_period = future_period.and_then(|period| Decode::decode(&mut &period[..])
+			.map_err(|e| ClientError::CallResultDecode("Cannot decode era period", e)));
+		let future_period = future_period.map_err(|e| Error {
+			code: ErrorCode::ServerError(RUNTIME_ERROR),
+			message: "Unable to query era period.".into(),
+			data: Some(format!("{:?}", e).into()),
+		});
+
+		Box::new(future_period.map(move |period| Mortality {
+			block_hash: best_hash,
+			block_number: best_number,
+			period,
+		}))
+	}
+}
+
+/// Describe a dispatch error, looking up module errors in the metadata returned by `metadata`.
+fn dispatch_error_info(
+	error: DispatchError,
+	metadata: impl FnOnce() -> Option<Vec<u8>>,
+) -> DispatchErrorInfo {
+	let error = match error {
+		DispatchError::Module { index, error, .. } => {
+			let module_error = metadata()
+				.and_then(|metadata| Decoder::from_bytes(&metadata, TypeRegistry::new()).ok())
+				.and_then(|decoder| decoder.module_error(index, error).ok());
+			return match module_error {
+				Some(module_error) => DispatchErrorInfo {
+					module: Some(module_error.module),
+					error: module_error.error,
+					documentation: module_error.documentation,
+				},
+				None => DispatchErrorInfo {
+					module: None,
+					error: format!("Module {{ index: {}, error: {} }}", index, error),
+					documentation: Vec::new(),
+				},
+			}
+		},
+		DispatchError::Other(_) => "Other",
+		DispatchError::CannotLookup => "CannotLookup",
+		DispatchError::BadOrigin => "BadOrigin",
+	};
+
+	DispatchErrorInfo { module: None, error: error.into(), documentation: Vec::new() }
 }
 
 /// Adjust account nonce from state, so that tx with the nonce will be
 /// placed after all ready txpool transactions.
-fn adjust_nonce<P, AccountId, Index>(
+pub fn adjust_nonce<P, AccountId, Index>(
 	pool: &P,
 	account: AccountId,
 	nonce: Index,
@@ -225,7 +422,7 @@ mod tests {
 
 	use futures::executor::block_on;
 	use substrate_test_runtime_client::{
-		runtime::Transfer,
+		runtime::{AccountId, Transfer},
 		AccountKeyring,
 	};
 	use sc_transaction_pool::{BasicPool, FullChainApi};
@@ -260,4 +457,48 @@ mod tests {
 		// then
 		assert_eq!(nonce.wait().unwrap(), 2);
 	}
+
+	#[test]
+	fn should_return_mortality_of_best_block() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let pool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		let genesis_hash = client.info().genesis_hash;
+		let system = FullSystem::new(client, pool);
+
+		let mortality = SystemApi::<_, AccountId, u64>::mortality(&system).wait().unwrap();
+
+		assert_eq!(mortality, Mortality { block_hash: genesis_hash, block_number: 0, period: 128 });
+	}
+
+	#[test]
+	fn dry_run_should_reject_undecodable_extrinsic() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let pool = Arc::new(BasicPool::new(Default::default(), FullChainApi::new(client.clone())));
+		let system = FullSystem::new(client, pool);
+
+		let error = SystemApi::<_, AccountId, u64>::dry_run(&system, vec![0xff].into(), None)
+			.wait()
+			.unwrap_err();
+
+		assert_eq!(error.code, ErrorCode::ServerError(BAD_EXTRINSIC));
+	}
+
+	#[test]
+	fn should_describe_dispatch_errors() {
+		let no_metadata = || None;
+
+		assert_eq!(dispatch_error_info(DispatchError::BadOrigin, no_metadata), DispatchErrorInfo {
+			module: None,
+			error: "BadOrigin".into(),
+			documentation: vec![],
+		});
+		assert_eq!(
+			dispatch_error_info(DispatchError::Module { index: 3, error: 1, message: None }, no_metadata),
+			DispatchErrorInfo {
+				module: None,
+				error: "Module { index: 3, error: 1 }".into(),
+				documentation: vec![],
+			},
+		);
+	}
 }
@@ -0,0 +1,285 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulation of calls on top of the state of a block.
+//!
+//! The calls are dispatched in memory by the runtime, nothing is ever written to the database.
+//! Running the runtime api initializes a new block on top of the given one, so the storage changes
+//! made by each call are separated from those made before it by also dispatching every shorter
+//! prefix of the calls, starting with none, and comparing the state after each prefix.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error, ErrorCode, futures::future::result};
+use jsonrpc_derive::rpc;
+use sc_client_api::backend::{Backend, StateBackendFor};
+use sp_api::{ApiExt, Metadata, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, hashing::twox_128};
+use sp_runtime::{generic::BlockId, traits};
+use sp_state_machine::Backend as _;
+use serde::{Serialize, Deserialize};
+use substrate_frame_decode::{Decoder, TypeRegistry};
+
+pub use frame_system_rpc_runtime_api::CallSimulationApi;
+
+use crate::{DryRunResult, FutureResult, dispatch_error_info, BAD_EXTRINSIC, RUNTIME_ERROR};
+
+/// Maximum number of calls simulated by a single request.
+///
+/// Every prefix of the calls is dispatched, so the work grows with the square of the number of calls.
+pub const MAX_SIMULATED_CALLS: usize = 8;
+
+/// A storage item changed by a simulated call.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageChange {
+	/// The storage key.
+	pub key: Bytes,
+	/// The value before the call, `None` if the item didn't exist.
+	pub old: Option<Bytes>,
+	/// The value after the call, `None` if the item was deleted.
+	pub new: Option<Bytes>,
+	/// The item decoded using the runtime metadata, if possible.
+	pub decoded: Option<serde_json::Value>,
+}
+
+/// Effect of a simulated call.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallEffect {
+	/// Outcome of the call.
+	pub result: DryRunResult,
+	/// The events deposited by the call, decoded using the runtime metadata if possible.
+	pub events: Option<serde_json::Value>,
+	/// The storage items changed by the call, ordered by key.
+	pub storage_changes: Vec<StorageChange>,
+}
+
+/// Call simulation RPC methods.
+#[rpc]
+pub trait SimulationApi<BlockHash, AccountId> {
+	/// Dispatch the given encoded calls signed by `who` on top of the state of a block,
+	/// or the best block, and return the events and storage changes of each call.
+	///
+	/// Nothing is persisted. This can be used to preview the effect of a transaction.
+	/// At most `MAX_SIMULATED_CALLS` calls are accepted and they must fit in the weight of a block.
+	#[rpc(name = "system_simulateCalls")]
+	fn simulate_calls(
+		&self,
+		who: AccountId,
+		calls: Vec<Bytes>,
+		at: Option<BlockHash>,
+	) -> FutureResult<Vec<CallEffect>>;
+}
+
+/// An implementation of the call simulation RPC methods on full client.
+pub struct FullSimulation<C, Be, Block, Call> {
+	client: Arc<C>,
+	backend: Arc<Be>,
+	_marker: std::marker::PhantomData<(Block, Call)>,
+}
+
+impl<C, Be, Block, Call> FullSimulation<C, Be, Block, Call> {
+	/// Create new `FullSimulation` given client and backend.
+	pub fn new(client: Arc<C>, backend: Arc<Be>) -> Self {
+		FullSimulation {
+			client,
+			backend,
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<C, Be, Block, Call, AccountId> SimulationApi<<Block as traits::Block>::Hash, AccountId>
+	for FullSimulation<C, Be, Block, Call>
+where
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block>,
+	C: Send + Sync + 'static,
+	C::Api: CallSimulationApi<Block, AccountId, Call>,
+	C::Api: Metadata<Block>,
+	C::Api: ApiExt<Block, StateBackend = StateBackendFor<Be, Block>>,
+	Be: Backend<Block> + 'static,
+	Block: traits::Block,
+	Call: Clone + Codec + Send + Sync + 'static,
+	AccountId: Clone + Codec + Send + Sync + 'static,
+{
+	fn simulate_calls(
+		&self,
+		who: AccountId,
+		calls: Vec<Bytes>,
+		at: Option<<Block as traits::Block>::Hash>,
+	) -> FutureResult<Vec<CallEffect>> {
+		let simulate = || {
+			if calls.len() > MAX_SIMULATED_CALLS {
+				return Err(Error {
+					code: ErrorCode::ServerError(BAD_EXTRINSIC),
+					message: format!("At most {} calls can be simulated at once.", MAX_SIMULATED_CALLS),
+					data: Some(calls.len().into()),
+				});
+			}
+
+			let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+			let at = BlockId::<Block>::hash(at_hash);
+			let runtime_error = |e: String| Error {
+				code: ErrorCode::ServerError(RUNTIME_ERROR),
+				message: "Unable to simulate calls.".into(),
+				data: Some(e.into()),
+			};
+
+			let calls = calls.iter()
+				.map(|call| Call::decode(&mut &call[..]))
+				.collect::<Result<Vec<_>, _>>()
+				.map_err(|e| Error {
+					code: ErrorCode::ServerError(BAD_EXTRINSIC),
+					message: "Unable to decode call.".into(),
+					data: Some(format!("{:?}", e).into()),
+				})?;
+
+			let state = self.backend.state_at(at).map_err(|e| runtime_error(format!("{:?}", e)))?;
+			let dispatch = |calls: &[Call]| {
+				let api = self.client.runtime_api();
+				let results = api.simulate_calls(&at, who.clone(), calls.to_vec())
+					.map_err(|e| runtime_error(format!("{:?}", e)))?
+					.map_err(|e| Error {
+						code: ErrorCode::ServerError(BAD_EXTRINSIC),
+						message: "The calls don't fit in a block.".into(),
+						data: Some(format!("{:?}", e).into()),
+					})?;
+				let changes = api.into_storage_changes(&state, self.backend.changes_trie_storage(), at_hash)
+					.map_err(runtime_error)?
+					.main_storage_changes;
+				Ok((results, changes))
+			};
+
+			let api = self.client.runtime_api();
+			let decoder = api.metadata(&at).ok()
+				.and_then(|metadata| Decoder::from_bytes(&metadata, TypeRegistry::with_defaults()).ok());
+			let events_key = [twox_128(b"System"), twox_128(b"Events")].concat();
+
+			let (_, initialization) = dispatch(&[])?;
+			let mut before = initialization.into_iter().collect::<HashMap<_, _>>();
+			let mut events_before = 0;
+			let mut effects = Vec::with_capacity(calls.len());
+			for index in 0..calls.len() {
+				let (mut results, changes) = dispatch(&calls[..=index])?;
+				let result = match results.pop() {
+					Some(Ok(())) => DryRunResult::Success,
+					Some(Err(error)) => DryRunResult::DispatchError(dispatch_error_info(error, || {
+						api.metadata(&at).ok().map(|metadata| metadata.to_vec())
+					})),
+					None => return Err(runtime_error("No result for the call.".into())),
+				};
+
+				let events = changes.iter()
+					.find(|(key, _)| *key == events_key)
+					.and_then(|(_, value)| value.as_ref())
+					.and_then(|value| decoder.as_ref()?.decode_events(value).ok())
+					.map(|events| match events {
+						serde_json::Value::Array(events) => {
+							let deposited = events.len();
+							let events = events.into_iter().skip(events_before).collect();
+							events_before = deposited;
+							serde_json::Value::Array(events)
+						},
+						events => events,
+					});
+
+				let storage_changes = changed_by_call(&before, &changes, |key| state.storage(key).ok().flatten())
+					.into_iter()
+					.map(|(key, old, new)| {
+						let decoded = decoder.as_ref()
+							.and_then(|decoder| decoder.decode_storage(&key, new.as_ref().map(|v| &v[..])).ok());
+						StorageChange {
+							key: key.into(),
+							old: old.map(Into::into),
+							new: new.map(Into::into),
+							decoded,
+						}
+					})
+					.collect();
+
+				before = changes.into_iter().collect();
+				effects.push(CallEffect { result, events, storage_changes });
+			}
+
+			Ok(effects)
+		};
+
+		Box::new(result(simulate()))
+	}
+}
+
+/// Returns the storage items, ordered by key, whose value after a call differs from the value
+/// before it, with both values.
+///
+/// `before` holds the changes made on top of the block state before the call and `after` those
+/// made once the call is dispatched. Items not changed before the call are read with `parent`.
+fn changed_by_call(
+	before: &HashMap<Vec<u8>, Option<Vec<u8>>>,
+	after: &[(Vec<u8>, Option<Vec<u8>>)],
+	parent: impl Fn(&[u8]) -> Option<Vec<u8>>,
+) -> Vec<(Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>)> {
+	let mut changes = after.iter()
+		.filter_map(|(key, new)| {
+			let old = match before.get(key) {
+				Some(old) => old.clone(),
+				None => parent(key),
+			};
+			if old == *new {
+				None
+			} else {
+				Some((key.clone(), old, new.clone()))
+			}
+		})
+		.collect::<Vec<_>>();
+	changes.sort_by(|a, b| a.0.cmp(&b.0));
+	changes
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_compare_with_the_state_before_the_call() {
+		let value = |value: &[u8]| Some(value.to_vec());
+		let change = |key: &[u8], value: Option<&[u8]>| (key.to_vec(), value.map(|v| v.to_vec()));
+		let before = vec![change(b"number", Some(b"2")), change(b"balance", Some(b"10"))]
+			.into_iter()
+			.collect::<HashMap<_, _>>();
+		let after = vec![
+			change(b"number", Some(b"2")),
+			change(b"balance", Some(b"5")),
+			change(b"account", None),
+			change(b"nonce", Some(b"1")),
+		];
+		let parent = |key: &[u8]| match key {
+			b"account" => value(b"alice"),
+			b"balance" => value(b"20"),
+			b"nonce" => value(b"1"),
+			_ => None,
+		};
+
+		assert_eq!(changed_by_call(&before, &after, parent), vec![
+			(b"account".to_vec(), value(b"alice"), None),
+			(b"balance".to_vec(), value(b"10"), value(b"5")),
+		]);
+	}
+}
@@ -44,8 +44,20 @@ lazy_static! {
 }
 
 /// Write metrics to `METRICS`.
+///
+/// With `prefix = Some(prefix);` before the metrics, their keys are recorded as
+/// `<prefix>.<key>`, to tell apart the metrics of several nodes run by the same process.
 #[macro_export]
 macro_rules! record_metrics(
+	(prefix = $prefix:expr; $($key:expr => $value:expr,)*) => {
+		if cfg!(not(target_os = "unknown")) {
+			$crate::record_prefixed_metrics_slice($prefix, &[
+				$( ($key, $value as f32), )*
+			])
+		} else {
+			Ok(())
+		}
+	};
 	($($key:expr => $value:expr,)*) => {
 		if cfg!(not(target_os = "unknown")) {
 			$crate::record_metrics_slice(&[
@@ -68,6 +80,22 @@ pub fn record_metrics_slice(metrics: &[(&str, f32)]) -> Result<(), Error> {
 	Ok(())
 }
 
+/// Write metrics to `METRICS` as a slice, prefixing their keys with `<prefix>.` if given.
+/// Intended to be only used via `record_metrics!`.
+pub fn record_prefixed_metrics_slice(prefix: Option<&str>, metrics: &[(&str, f32)]) -> Result<(), Error> {
+	let prefix = match prefix {
+		Some(prefix) => prefix,
+		None => return record_metrics_slice(metrics),
+	};
+	let mut database = crate::DATABASE.write();
+
+	for &(key, value) in metrics.iter() {
+		database.push(&format!("{}.{}", prefix, key), value)?;
+	}
+
+	Ok(())
+}
+
 /// Error type that can be returned by either `record_metrics` or `run_server`.
 #[derive(Debug, derive_more::Display, derive_more::From)]
 pub enum Error {